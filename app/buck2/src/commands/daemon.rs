@@ -26,6 +26,7 @@ use buck2_bxl::profile_command::bxl_profile_command;
 use buck2_cli_proto::ConfiguredTargetsRequest;
 use buck2_cli_proto::ConfiguredTargetsResponse;
 use buck2_cli_proto::DaemonProcessInfo;
+use buck2_client_ctx::daemon::client::BuckdLifecycleLock;
 use buck2_client_ctx::daemon_constraints::gen_daemon_constraints;
 use buck2_client_ctx::version::BuckVersion;
 use buck2_common::buckd_connection::ConnectionType;
@@ -72,6 +73,10 @@ use crate::commands::schedule_termination::maybe_schedule_termination;
 enum DaemonError {
     #[error("The buckd pid file at `{0}` had a mismatched pid, expected `{1}`, got `{2}`")]
     PidFileMismatch(PathBuf, u32, u32),
+    #[error(
+        "A buck2 daemon (pid `{0}`) is already running for this project, stop it with `buck2 kill` before running one in the foreground"
+    )]
+    DaemonAlreadyRunning(u32),
 }
 
 /// Start or run buck daemon.
@@ -86,8 +91,15 @@ pub(crate) struct DaemonCommand {
     #[clap(long, default_value("60"))]
     checker_interval_seconds: u64,
     /// Run buck daemon but do not daemonize the process.
-    #[clap(long)]
+    #[clap(long, alias = "no-detach")]
     dont_daemonize: bool,
+    /// Run buck daemon attached to the terminal, for debugging.
+    ///
+    /// The daemon is not daemonized, its logs are written to stderr
+    /// (at `info` level unless `BUCK_LOG` is set),
+    /// and Ctrl-C shuts it down.
+    #[clap(long)]
+    foreground: bool,
     /// This flag is set to prevent infinite recursion when the process is restarted
     /// with lower priority.
     #[clap(long)]
@@ -100,6 +112,7 @@ impl DaemonCommand {
         DaemonCommand {
             checker_interval_seconds: 60,
             dont_daemonize: true,
+            foreground: false,
             skip_macos_qos: true,
         }
     }
@@ -261,6 +274,42 @@ fn verify_current_daemon(daemon_dir: &DaemonDir) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Fail if the pid file points to a live process other than this one.
+fn check_no_running_daemon(daemon_dir: &DaemonDir) -> anyhow::Result<()> {
+    let recorded_pid: u32 = match fs_util::read_to_string_opt(daemon_dir.buckd_pid())? {
+        Some(pid) => match pid.trim().parse() {
+            Ok(pid) => pid,
+            // Garbage in the pid file, nobody can be using it.
+            Err(_) => return Ok(()),
+        },
+        None => return Ok(()),
+    };
+    if recorded_pid != process::id() && is_process_alive(recorded_pid)? {
+        return Err(DaemonError::DaemonAlreadyRunning(recorded_pid).into());
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn is_process_alive(pid: u32) -> anyhow::Result<bool> {
+    match nix::sys::signal::kill(nix::unistd::Pid::from_raw(pid as i32), None) {
+        // EPERM means the process exists but belongs to someone else.
+        Ok(()) | Err(nix::errno::Errno::EPERM) => Ok(true),
+        Err(nix::errno::Errno::ESRCH) => Ok(false),
+        Err(e) => Err(anyhow::anyhow!(
+            "Unexpected error checking whether process `{}` is alive (`{}`)",
+            pid,
+            e
+        )),
+    }
+}
+
+#[cfg(not(unix))]
+fn is_process_alive(_pid: u32) -> anyhow::Result<bool> {
+    // No cheap liveness check here; the lifecycle lock is the only protection.
+    Ok(false)
+}
+
 fn gen_auth_token() -> String {
     (0..20)
         .map(|_| rand::thread_rng().gen_range('a'..='z'))
@@ -287,6 +336,18 @@ impl DaemonCommand {
         let pid_path = daemon_dir.buckd_pid();
         let stdout_path = daemon_dir.buckd_stdout();
         let stderr_path = daemon_dir.buckd_stderr();
+
+        // There is no client starting us, so take the lifecycle lock ourselves
+        // while the daemon dir is being written.
+        let lifecycle_lock = if self.foreground {
+            let lifecycle_lock = BuckdLifecycleLock::try_lock(daemon_dir.clone())?;
+            // Do not take over the daemon dir from a daemon which is still serving clients.
+            check_no_running_daemon(&daemon_dir)?;
+            Some(lifecycle_lock)
+        } else {
+            None
+        };
+
         // Even if we don't redirect output, we still need to create stdout/stderr files,
         // because tailer opens them. This is untidy.
         let stdout = File::create(stdout_path)?;
@@ -294,7 +355,7 @@ impl DaemonCommand {
 
         let auth_token = gen_auth_token();
 
-        let (listener, process_info) = if !self.dont_daemonize {
+        let (listener, process_info) = if !self.dont_daemonize && !self.foreground {
             // We must create stdout/stderr before creating a listener,
             // otherwise it is race:
             // * daemon parent process exits
@@ -324,7 +385,7 @@ impl DaemonCommand {
         } else {
             fs_util::write(&pid_path, format!("{}", process::id()))?;
 
-            if !in_process {
+            if !in_process && !self.foreground {
                 self.redirect_output(stdout, stderr)?;
             }
 
@@ -342,6 +403,12 @@ impl DaemonCommand {
             (listener, process_info)
         };
 
+        drop(lifecycle_lock);
+
+        if self.foreground && env::var_os("BUCK_LOG").is_none() {
+            log_reload_handle.update_log_filter("info")?;
+        }
+
         listener_created();
 
         gazebo::terminate_on_panic();
@@ -429,19 +496,32 @@ impl DaemonCommand {
             )
             .fuse();
             let shutdown_future = async move { hard_shutdown_receiver.next().await }.fuse();
+            let foreground = self.foreground;
+            let interrupt_future = async move {
+                if foreground {
+                    tokio::signal::ctrl_c().await
+                } else {
+                    futures::future::pending().await
+                }
+            }
+            .fuse();
             pin_mut!(buckd_server);
             pin_mut!(shutdown_future);
+            pin_mut!(interrupt_future);
 
             let checker_interval_seconds = self.checker_interval_seconds;
 
             thread::Builder::new()
                 .name("check-daemon-dir".to_owned())
-                .spawn(move || {
-                    Self::check_daemon_dir_thread(
-                        checker_interval_seconds,
-                        daemon_dir,
-                        hard_shutdown_sender,
-                    )
+                .spawn({
+                    let daemon_dir = daemon_dir.clone();
+                    move || {
+                        Self::check_daemon_dir_thread(
+                            checker_interval_seconds,
+                            daemon_dir,
+                            hard_shutdown_sender,
+                        )
+                    }
                 })?;
 
             tracing::info!("Initialization complete, running the server.");
@@ -457,6 +537,11 @@ impl DaemonCommand {
                         let reason = reason.as_deref().unwrap_or("no reason available");
                         tracing::info!("server forced shutdown: {}", reason);
                     },
+                    res = interrupt_future => {
+                        res.context("Error waiting for Ctrl-C")?;
+                        tracing::info!("server interrupted, shutting down");
+                        Self::release_daemon_dir(daemon_dir)?;
+                    },
                 };
             }
 
@@ -465,6 +550,18 @@ impl DaemonCommand {
         Ok(())
     }
 
+    /// Remove the files pointing clients to this daemon, so they start a new one
+    /// instead of trying to connect to a daemon which is gone.
+    fn release_daemon_dir(daemon_dir: DaemonDir) -> anyhow::Result<()> {
+        let _lifecycle_lock = BuckdLifecycleLock::lock(daemon_dir.clone())?;
+        // Another daemon might have been started meanwhile, leave its files alone.
+        if verify_current_daemon(&daemon_dir).is_ok() {
+            fs_util::remove_file(daemon_dir.buckd_info())?;
+            fs_util::remove_file(daemon_dir.buckd_pid())?;
+        }
+        Ok(())
+    }
+
     /// We start a dedicated thread to periodically check that the files in the daemon
     /// dir still reflect that we are the current buckd and verify that when you connect
     /// to the server it is our server.
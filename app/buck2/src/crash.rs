@@ -0,0 +1,750 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! The crash handler of the buck2 CLI.
+//!
+//! Panics are reported by the panic hook (see `panic.rs`), but when the client crashes on a fatal
+//! signal (e.g. a segfault in native code) or an unhandled structured exception on Windows, no
+//! Rust code gets to run. This module installs a handler for these which writes a crash report to
+//! `buck-out/<isolation>/crashes/<trace id>.crash`: a partial invocation record (version, command
+//! line, working directory...) and the registers of the crashing thread. Next to it, it writes a
+//! dump to be analyzed offline against the buck2 binary: on Unix, the raw memory of the stack of
+//! the crashing thread (`<trace id>.stack`), on Windows, a minidump (`<trace id>.dmp`). The
+//! previous handler (or the default action) then terminates the process as it would have
+//! without us.
+//!
+//! A signal handler can only do async-signal-safe work, so everything which allocates is
+//! prepared when it is installed, and the handler only makes `open` and `write` syscalls. In
+//! particular it does not unwind the stack: the stack may be corrupted, and unwinding takes locks.
+//!
+//! Crash reports left by previous invocations are reported to Scribe by the next invocation
+//! (unless `BUCK2_UPLOAD_CRASH_REPORTS=false`), and uploaded along with their dumps to the upload
+//! backend configured in `[log_upload]` if any. They are deleted after a week along with the
+//! dumps.
+//! Aborts following a panic are not reported, since the panic hook reports those.
+//!
+//! Set `BUCK2_DISABLE_CRASH_HANDLER=true` to not install the handler. Note that nothing can be
+//! done when the client is killed with `SIGKILL`.
+
+use std::ffi::OsStr;
+use std::fs;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::process::Stdio;
+use std::ptr;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicPtr;
+use std::sync::atomic::Ordering;
+use std::sync::Once;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use anyhow::Context;
+use buck2_client_ctx::manifold::Bucket;
+use buck2_client_ctx::upload_backend::UploadConfig;
+use buck2_client_ctx::version::BuckVersion;
+use buck2_core::env_helper::EnvHelper;
+use buck2_core::fs::working_dir::WorkingDir;
+use buck2_wrapper_common::invocation_id::TraceId;
+use fbinit::FacebookInit;
+
+static DISABLE_CRASH_HANDLER: EnvHelper<bool> = EnvHelper::new("BUCK2_DISABLE_CRASH_HANDLER");
+static UPLOAD_CRASH_REPORTS: EnvHelper<bool> = EnvHelper::new("BUCK2_UPLOAD_CRASH_REPORTS");
+
+const CRASH_REPORT_EXTENSION: &str = "crash";
+/// Extension of the crash reports which were reported by a later invocation.
+const UPLOADED_CRASH_REPORT_EXTENSION: &str = "uploaded";
+const MAX_CRASH_REPORT_AGE: Duration = Duration::from_secs(60 * 60 * 24 * 7);
+
+/// Everything the crash handler needs, prepared when the handler is installed.
+struct CrashState {
+    report_path: imp::ReportPath,
+    dump_path: imp::ReportPath,
+    /// The partial invocation record, written at the top of the crash report.
+    header: Vec<u8>,
+    /// Message printed to stderr on crash.
+    message: Vec<u8>,
+}
+
+/// The state for the current invocation. It is replaced when the invocation is restarted, and the
+/// previous state is leaked since a crash handler may be using it.
+static CRASH_STATE: AtomicPtr<CrashState> = AtomicPtr::new(ptr::null_mut());
+
+/// Set by the first crash, so that a crash in the handler or in another thread does not write
+/// another report.
+static CRASHING: AtomicBool = AtomicBool::new(false);
+
+/// Set by the panic hook, which reports the panic itself, so that the abort which follows it is
+/// not reported again.
+static PANICKED: AtomicBool = AtomicBool::new(false);
+
+static INSTALL_HANDLERS: Once = Once::new();
+
+/// What crashed the process.
+enum Cause {
+    #[cfg(unix)]
+    Signal {
+        signal: libc::c_int,
+        name: &'static str,
+        /// The instruction and stack pointers of the crashing thread, when we know how to get
+        /// them on this platform.
+        registers: Option<(usize, usize)>,
+    },
+    #[cfg(windows)]
+    Exception {
+        code: u32,
+        address: usize,
+        info: *mut winapi::um::winnt::EXCEPTION_POINTERS,
+    },
+}
+
+/// Called by the panic hook.
+pub(crate) fn panicked() {
+    PANICKED.store(true, Ordering::SeqCst);
+}
+
+/// Installs the crash handler for this invocation, writing reports to `crashes_dir`, and uploads
+/// the reports left by previous invocations. Errors are not fatal: the command can run without
+/// the crash handler.
+pub(crate) fn initialize(
+    fb: FacebookInit,
+    crashes_dir: &Path,
+    trace_id: &TraceId,
+    args: &[String],
+    working_dir: &WorkingDir,
+) {
+    if let Err(e) = initialize_impl(fb, crashes_dir, trace_id, args, working_dir) {
+        tracing::debug!("Error initializing the crash handler: {:#}", e);
+    }
+}
+
+fn initialize_impl(
+    fb: FacebookInit,
+    crashes_dir: &Path,
+    trace_id: &TraceId,
+    args: &[String],
+    working_dir: &WorkingDir,
+) -> anyhow::Result<()> {
+    if DISABLE_CRASH_HANDLER.get_copied()?.unwrap_or_default() {
+        return Ok(());
+    }
+
+    fs::create_dir_all(crashes_dir)?;
+    report_previous_crashes(fb, crashes_dir)?;
+
+    let report_path = crashes_dir.join(format!("{}.{}", trace_id, CRASH_REPORT_EXTENSION));
+    let dump_path = crashes_dir.join(format!("{}.{}", trace_id, imp::DUMP_EXTENSION));
+    let started = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    let header = format!(
+        "buck2 crash report\n\
+        version: {}\n\
+        trace_id: {}\n\
+        pid: {}\n\
+        started: {} (seconds since epoch)\n\
+        working_dir: {}\n\
+        command: {}\n\
+        dump: {}\n\
+        anchor: 0x{:x} (address of `write_crash_report`, to symbolize the dump)\n",
+        BuckVersion::get_version(),
+        trace_id,
+        std::process::id(),
+        started.as_secs(),
+        working_dir.path(),
+        args.join(" "),
+        dump_path.display(),
+        write_crash_report as usize,
+    );
+    let message = format!(
+        "buck2 crashed, crash report written to `{}`\n",
+        report_path.display()
+    );
+    let state = CrashState {
+        report_path: imp::ReportPath::new(&report_path)?,
+        dump_path: imp::ReportPath::new(&dump_path)?,
+        header: header.into_bytes(),
+        message: message.into_bytes(),
+    };
+    CRASH_STATE.store(Box::into_raw(Box::new(state)), Ordering::Release);
+
+    INSTALL_HANDLERS.call_once(|| {
+        if let Err(e) = imp::install_handlers() {
+            tracing::debug!("Error installing the crash handler: {:#}", e);
+        }
+    });
+    Ok(())
+}
+
+/// Reports the crash reports of previous invocations, and deletes the old ones.
+fn report_previous_crashes(fb: FacebookInit, crashes_dir: &Path) -> anyhow::Result<()> {
+    let upload = UPLOAD_CRASH_REPORTS.get_copied()?.unwrap_or(true);
+    let now = SystemTime::now();
+    for entry in fs::read_dir(crashes_dir)? {
+        // Another client may be looking at the same reports, so errors on a report are ignored.
+        if let Err(e) = report_previous_crash(fb, &entry?.path(), now, upload) {
+            tracing::debug!("Error reporting previous crash: {:#}", e);
+        }
+    }
+    Ok(())
+}
+
+fn report_previous_crash(
+    fb: FacebookInit,
+    path: &Path,
+    now: SystemTime,
+    upload: bool,
+) -> anyhow::Result<()> {
+    let modified = fs::metadata(path)?.modified()?;
+    if now.duration_since(modified).unwrap_or_default() > MAX_CRASH_REPORT_AGE {
+        fs::remove_file(path)?;
+    } else if upload && path.extension() == Some(OsStr::new(CRASH_REPORT_EXTENSION)) {
+        // Claim the report before reporting it: renaming fails for all but one of the clients
+        // started concurrently after a crash, so that the crash is only reported once.
+        let claimed = path.with_extension(UPLOADED_CRASH_REPORT_EXTENSION);
+        fs::rename(path, &claimed)?;
+        crate::panic::report_crash(fb, fs::read_to_string(&claimed)?);
+        // Uploading is best effort: the crash was reported already.
+        let dump = path.with_extension(imp::DUMP_EXTENSION);
+        for (file, name) in [(claimed.as_path(), path), (&dump, &dump)] {
+            if let Err(e) = upload_crash_file(file, name) {
+                tracing::debug!("Error uploading `{}`: {:#}", file.display(), e);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Starts uploading a file of a crash report to the upload backend, if one is configured, under
+/// the file name of `name`. The upload is not waited for: the command should not be slowed down
+/// by a previous crash.
+fn upload_crash_file(path: &Path, name: &Path) -> anyhow::Result<()> {
+    let backend = match UploadConfig::get()?.backend() {
+        Some(backend) => backend,
+        None => return Ok(()),
+    };
+    if !path.exists() {
+        return Ok(());
+    }
+    let name = name
+        .file_name()
+        .context("Crash report path has no file name")?
+        .to_string_lossy();
+    backend
+        .upload_command(Bucket::RageDumps, &name, None)?
+        .stdin(File::open(path)?)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    Ok(())
+}
+
+/// Writes the crash report and the dump, and tells the user about them. Called from the crash
+/// handler: must be async-signal-safe.
+fn write_crash_report(cause: Cause) {
+    #[cfg(unix)]
+    if let Cause::Signal { signal, .. } = cause {
+        if signal == libc::SIGABRT && PANICKED.load(Ordering::SeqCst) {
+            return;
+        }
+    }
+    if CRASHING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let state = CRASH_STATE.load(Ordering::Acquire);
+    if state.is_null() {
+        return;
+    }
+    // SAFETY: states are never freed.
+    let state = unsafe { &*state };
+
+    // Writing to a `File` is a plain `write` syscall, and does not allocate.
+    if let Some(mut report) = state.report_path.create() {
+        let _ignored = report.write_all(&state.header);
+        write_cause(&mut report, &cause);
+    }
+    if let Some(dump) = state.dump_path.create() {
+        imp::write_dump(dump, &cause);
+    }
+    if let Some(mut stderr) = imp::stderr() {
+        let _ignored = stderr.write_all(&state.message);
+    }
+}
+
+fn write_cause(out: &mut File, cause: &Cause) {
+    match cause {
+        #[cfg(unix)]
+        Cause::Signal {
+            signal,
+            name,
+            registers,
+        } => {
+            let mut buf = [0; 20];
+            let _ignored = out.write_all(b"cause: signal ");
+            let _ignored = out.write_all(format_decimal(*signal as u64, &mut buf));
+            let _ignored = out.write_all(b" (");
+            let _ignored = out.write_all(name.as_bytes());
+            let _ignored = out.write_all(b")\n");
+            if let Some((ip, sp)) = registers {
+                let mut buf = [0; 18];
+                let _ignored = out.write_all(b"ip: ");
+                let _ignored = out.write_all(format_hex(*ip, &mut buf));
+                let _ignored = out.write_all(b"\nsp: ");
+                let _ignored = out.write_all(format_hex(*sp, &mut buf));
+                let _ignored = out.write_all(b" (start of the dumped stack)\n");
+            }
+        }
+        #[cfg(windows)]
+        Cause::Exception { code, address, .. } => {
+            let mut buf = [0; 18];
+            let _ignored = out.write_all(b"cause: exception ");
+            let _ignored = out.write_all(format_hex(*code as usize, &mut buf));
+            let _ignored = out.write_all(b"\nip: ");
+            let _ignored = out.write_all(format_hex(*address, &mut buf));
+            let _ignored = out.write_all(b"\n");
+        }
+    }
+}
+
+/// Formats `n` in hexadecimal without allocating.
+fn format_hex(mut n: usize, buf: &mut [u8; 18]) -> &[u8] {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut i = buf.len();
+    loop {
+        i -= 1;
+        buf[i] = DIGITS[n & 0xf];
+        n >>= 4;
+        if n == 0 {
+            break;
+        }
+    }
+    i -= 2;
+    buf[i] = b'0';
+    buf[i + 1] = b'x';
+    &buf[i..]
+}
+
+/// Formats `n` in decimal without allocating.
+#[cfg_attr(windows, allow(dead_code))]
+fn format_decimal(mut n: u64, buf: &mut [u8; 20]) -> &[u8] {
+    let mut i = buf.len();
+    loop {
+        i -= 1;
+        buf[i] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 {
+            break;
+        }
+    }
+    &buf[i..]
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::ffi::CString;
+    use std::fs::File;
+    use std::io;
+    use std::mem;
+    use std::mem::ManuallyDrop;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::io::AsRawFd;
+    use std::os::unix::io::FromRawFd;
+    use std::path::Path;
+    use std::ptr;
+
+    use once_cell::sync::OnceCell;
+
+    use crate::crash::write_crash_report;
+    use crate::crash::Cause;
+
+    pub(super) const DUMP_EXTENSION: &str = "stack";
+
+    /// How much of the stack of the crashing thread to dump at most.
+    const MAX_STACK_DUMP: usize = 1024 * 1024;
+
+    /// The smallest page size of the platforms we support.
+    const PAGE_SIZE: usize = 4096;
+
+    /// The signals we write a crash report for.
+    const SIGNALS: &[(libc::c_int, &str)] = &[
+        (libc::SIGSEGV, "SIGSEGV"),
+        (libc::SIGBUS, "SIGBUS"),
+        (libc::SIGILL, "SIGILL"),
+        (libc::SIGFPE, "SIGFPE"),
+        (libc::SIGABRT, "SIGABRT"),
+    ];
+
+    /// The handlers which were installed before ours, e.g. the stack overflow handler of the Rust
+    /// runtime. They are restored when we crash.
+    static PREVIOUS_ACTIONS: OnceCell<Vec<(libc::c_int, libc::sigaction)>> = OnceCell::new();
+
+    pub(super) struct ReportPath(CString);
+
+    impl ReportPath {
+        pub(super) fn new(path: &Path) -> anyhow::Result<ReportPath> {
+            Ok(ReportPath(CString::new(path.as_os_str().as_bytes())?))
+        }
+
+        /// Creates the report, unless it already exists. Async-signal-safe.
+        pub(super) fn create(&self) -> Option<File> {
+            let fd = unsafe {
+                libc::open(
+                    self.0.as_ptr(),
+                    libc::O_WRONLY | libc::O_CREAT | libc::O_EXCL | libc::O_CLOEXEC,
+                    0o644,
+                )
+            };
+            if fd < 0 {
+                None
+            } else {
+                Some(unsafe { File::from_raw_fd(fd) })
+            }
+        }
+    }
+
+    /// Stderr as a `File`, which must not be closed. Async-signal-safe.
+    pub(super) fn stderr() -> Option<ManuallyDrop<File>> {
+        Some(ManuallyDrop::new(unsafe {
+            File::from_raw_fd(libc::STDERR_FILENO)
+        }))
+    }
+
+    /// Writes the raw memory of the stack of the crashing thread, from the stack pointer up, a
+    /// page at most at a time. `write` fails with `EFAULT` instead of crashing on memory which is
+    /// not mapped, so we stop at the end of the stack. Async-signal-safe.
+    pub(super) fn write_dump(dump: File, cause: &Cause) {
+        let sp = match cause {
+            Cause::Signal {
+                registers: Some((_, sp)),
+                ..
+            } => *sp,
+            _ => return,
+        };
+        let end = sp.saturating_add(MAX_STACK_DUMP);
+        let mut start = sp;
+        while start < end {
+            let page_end = (start / PAGE_SIZE + 1) * PAGE_SIZE;
+            let len = page_end - start;
+            let written =
+                unsafe { libc::write(dump.as_raw_fd(), start as *const libc::c_void, len) };
+            if written != len as isize {
+                break;
+            }
+            start = page_end;
+        }
+    }
+
+    /// The instruction and stack pointers of the crashing thread, read from the context of the
+    /// signal.
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    unsafe fn registers(context: *const libc::ucontext_t) -> Option<(usize, usize)> {
+        let gregs = &(*context).uc_mcontext.gregs;
+        Some((
+            gregs[libc::REG_RIP as usize] as usize,
+            gregs[libc::REG_RSP as usize] as usize,
+        ))
+    }
+
+    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+    unsafe fn registers(context: *const libc::ucontext_t) -> Option<(usize, usize)> {
+        let mcontext = &(*context).uc_mcontext;
+        Some((mcontext.pc as usize, mcontext.sp as usize))
+    }
+
+    #[cfg(all(target_os = "macos", target_arch = "x86_64"))]
+    unsafe fn registers(context: *const libc::ucontext_t) -> Option<(usize, usize)> {
+        let state = &(*(*context).uc_mcontext).__ss;
+        Some((state.__rip as usize, state.__rsp as usize))
+    }
+
+    #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+    unsafe fn registers(context: *const libc::ucontext_t) -> Option<(usize, usize)> {
+        let state = &(*(*context).uc_mcontext).__ss;
+        Some((state.__pc as usize, state.__sp as usize))
+    }
+
+    #[cfg(not(all(
+        any(target_os = "linux", target_os = "macos"),
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    )))]
+    unsafe fn registers(_context: *const libc::ucontext_t) -> Option<(usize, usize)> {
+        None
+    }
+
+    pub(super) fn install_handlers() -> anyhow::Result<()> {
+        let mut previous_actions = Vec::with_capacity(SIGNALS.len());
+        for (signal, _) in SIGNALS {
+            unsafe {
+                let mut action: libc::sigaction = mem::zeroed();
+                action.sa_sigaction = handle_signal as usize;
+                // Run on the alternate stack set up by the Rust runtime, so that we can report
+                // stack overflows too.
+                action.sa_flags = libc::SA_SIGINFO | libc::SA_ONSTACK;
+                libc::sigemptyset(&mut action.sa_mask);
+                let mut previous: libc::sigaction = mem::zeroed();
+                if libc::sigaction(*signal, &action, &mut previous) != 0 {
+                    return Err(io::Error::last_os_error().into());
+                }
+                previous_actions.push((*signal, previous));
+            }
+        }
+        let _ignored = PREVIOUS_ACTIONS.set(previous_actions);
+        Ok(())
+    }
+
+    extern "C" fn handle_signal(
+        signal: libc::c_int,
+        info: *mut libc::siginfo_t,
+        context: *mut libc::c_void,
+    ) {
+        let name = SIGNALS
+            .iter()
+            .find(|(s, _)| *s == signal)
+            .map_or("unknown", |(_, name)| *name);
+        let registers = if context.is_null() {
+            None
+        } else {
+            unsafe { registers(context as *const libc::ucontext_t) }
+        };
+        write_crash_report(Cause::Signal {
+            signal,
+            name,
+            registers,
+        });
+
+        unsafe {
+            match PREVIOUS_ACTIONS
+                .get()
+                .and_then(|actions| actions.iter().find(|(s, _)| *s == signal))
+            {
+                Some((_, previous)) => {
+                    libc::sigaction(signal, previous, ptr::null_mut());
+                }
+                None => {
+                    libc::signal(signal, libc::SIG_DFL);
+                }
+            }
+            // Faults happen again when the handler returns, and go to the restored handler.
+            // Signals sent with `kill` or `abort` must be raised again.
+            if info.is_null() || (*info).si_code <= 0 || signal == libc::SIGABRT {
+                libc::raise(signal);
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::ffi::c_void;
+    use std::fs::File;
+    use std::mem::ManuallyDrop;
+    use std::os::windows::ffi::OsStrExt;
+    use std::os::windows::io::AsRawHandle;
+    use std::os::windows::io::FromRawHandle;
+    use std::path::Path;
+    use std::ptr;
+
+    use once_cell::sync::OnceCell;
+    use winapi::shared::minwindef::FALSE;
+    use winapi::um::errhandlingapi::SetUnhandledExceptionFilter;
+    use winapi::um::errhandlingapi::LPTOP_LEVEL_EXCEPTION_FILTER;
+    use winapi::um::fileapi::CreateFileW;
+    use winapi::um::fileapi::CREATE_NEW;
+    use winapi::um::handleapi::INVALID_HANDLE_VALUE;
+    use winapi::um::minidumpapiset::MiniDumpNormal;
+    use winapi::um::minidumpapiset::MiniDumpWriteDump;
+    use winapi::um::minidumpapiset::MINIDUMP_EXCEPTION_INFORMATION;
+    use winapi::um::processenv::GetStdHandle;
+    use winapi::um::processthreadsapi::GetCurrentProcess;
+    use winapi::um::processthreadsapi::GetCurrentProcessId;
+    use winapi::um::processthreadsapi::GetCurrentThreadId;
+    use winapi::um::winbase::STD_ERROR_HANDLE;
+    use winapi::um::winnt::EXCEPTION_POINTERS;
+    use winapi::um::winnt::FILE_ATTRIBUTE_NORMAL;
+    use winapi::um::winnt::GENERIC_WRITE;
+    use winapi::um::winnt::LONG;
+    use winapi::vc::excpt::EXCEPTION_CONTINUE_SEARCH;
+
+    use crate::crash::write_crash_report;
+    use crate::crash::Cause;
+
+    pub(super) const DUMP_EXTENSION: &str = "dmp";
+
+    /// The filter which was installed before ours, called after writing the report.
+    static PREVIOUS_FILTER: OnceCell<LPTOP_LEVEL_EXCEPTION_FILTER> = OnceCell::new();
+
+    pub(super) struct ReportPath(Vec<u16>);
+
+    impl ReportPath {
+        pub(super) fn new(path: &Path) -> anyhow::Result<ReportPath> {
+            Ok(ReportPath(
+                path.as_os_str().encode_wide().chain(Some(0)).collect(),
+            ))
+        }
+
+        /// Creates the report, unless it already exists. Does not allocate.
+        pub(super) fn create(&self) -> Option<File> {
+            let handle = unsafe {
+                CreateFileW(
+                    self.0.as_ptr(),
+                    GENERIC_WRITE,
+                    0,
+                    ptr::null_mut(),
+                    CREATE_NEW,
+                    FILE_ATTRIBUTE_NORMAL,
+                    ptr::null_mut(),
+                )
+            };
+            if handle == INVALID_HANDLE_VALUE {
+                None
+            } else {
+                Some(unsafe { File::from_raw_handle(handle as *mut c_void) })
+            }
+        }
+    }
+
+    /// Stderr as a `File`, which must not be closed. Does not allocate.
+    pub(super) fn stderr() -> Option<ManuallyDrop<File>> {
+        let handle = unsafe { GetStdHandle(STD_ERROR_HANDLE) };
+        if handle.is_null() || handle == INVALID_HANDLE_VALUE {
+            None
+        } else {
+            Some(ManuallyDrop::new(unsafe {
+                File::from_raw_handle(handle as *mut c_void)
+            }))
+        }
+    }
+
+    /// Writes a minidump of the process. Unlike signal handlers, the unhandled exception filter
+    /// is not restricted to async-signal-safe work, and this is what it is meant for.
+    pub(super) fn write_dump(dump: File, cause: &Cause) {
+        let info = match cause {
+            Cause::Exception { info, .. } if !info.is_null() => *info,
+            _ => return,
+        };
+        let mut exception = MINIDUMP_EXCEPTION_INFORMATION {
+            ThreadId: unsafe { GetCurrentThreadId() },
+            ExceptionPointers: info,
+            ClientPointers: FALSE,
+        };
+        unsafe {
+            MiniDumpWriteDump(
+                GetCurrentProcess(),
+                GetCurrentProcessId(),
+                dump.as_raw_handle() as _,
+                MiniDumpNormal,
+                &mut exception,
+                ptr::null_mut(),
+                ptr::null_mut(),
+            );
+        }
+    }
+
+    /// Structured exceptions which are not handled (access violations, illegal instructions,
+    /// stack overflows...) end up in the unhandled exception filter.
+    pub(super) fn install_handlers() -> anyhow::Result<()> {
+        let previous = unsafe { SetUnhandledExceptionFilter(Some(handle_exception)) };
+        let _ignored = PREVIOUS_FILTER.set(previous);
+        Ok(())
+    }
+
+    unsafe extern "system" fn handle_exception(info: *mut EXCEPTION_POINTERS) -> LONG {
+        let (code, address) = if info.is_null() || (*info).ExceptionRecord.is_null() {
+            (0, 0)
+        } else {
+            let record = &*(*info).ExceptionRecord;
+            (record.ExceptionCode, record.ExceptionAddress as usize)
+        };
+        write_crash_report(Cause::Exception {
+            code,
+            address,
+            info,
+        });
+
+        match PREVIOUS_FILTER.get().copied().flatten() {
+            Some(previous) => previous(info),
+            None => EXCEPTION_CONTINUE_SEARCH as LONG,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crash::format_decimal;
+    use crate::crash::format_hex;
+
+    #[test]
+    fn test_format_hex() {
+        let mut buf = [0; 18];
+        assert_eq!(b"0x0", format_hex(0, &mut buf));
+        assert_eq!(b"0x7f3a", format_hex(0x7f3a, &mut buf));
+        assert_eq!(
+            b"0xffffffffffffffff",
+            format_hex(u64::MAX as usize, &mut buf)
+        );
+    }
+
+    #[test]
+    fn test_format_decimal() {
+        let mut buf = [0; 20];
+        assert_eq!(b"0", format_decimal(0, &mut buf));
+        assert_eq!(b"11", format_decimal(11, &mut buf));
+        assert_eq!(b"18446744073709551615", format_decimal(u64::MAX, &mut buf));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_dump_stops_at_unmapped_memory() -> anyhow::Result<()> {
+        use std::fs;
+        use std::fs::File;
+        use std::ptr;
+
+        use crate::crash::imp::write_dump;
+        use crate::crash::Cause;
+
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+        // Map two pages and unmap the second, so that the "stack" ends with the first.
+        let pages = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                2 * page_size,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        assert_ne!(pages, libc::MAP_FAILED);
+        let pages = pages as *mut u8;
+        unsafe {
+            ptr::write_bytes(pages, 0xab, page_size);
+            assert_eq!(
+                0,
+                libc::munmap(pages.add(page_size) as *mut libc::c_void, page_size)
+            );
+        }
+
+        let path = std::env::temp_dir().join(format!("buck2-crash-test-{}", std::process::id()));
+        write_dump(
+            File::create(&path)?,
+            &Cause::Signal {
+                signal: libc::SIGSEGV,
+                name: "SIGSEGV",
+                registers: Some((0, pages as usize + 100)),
+            },
+        );
+        let dump = fs::read(&path)?;
+        fs::remove_file(&path)?;
+        unsafe { libc::munmap(pages as *mut libc::c_void, page_size) };
+
+        assert_eq!(vec![0xab; page_size - 100], dump);
+        Ok(())
+    }
+}
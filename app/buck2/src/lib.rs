@@ -14,6 +14,7 @@
 #![cfg_attr(feature = "gazebo_lint", allow(deprecated))] // :(
 #![cfg_attr(feature = "gazebo_lint", plugin(gazebo_lint))]
 
+use std::env;
 use std::thread;
 
 use anyhow::Context as _;
@@ -22,11 +23,15 @@ use buck2_client::args::expand_argfiles_with_context;
 use buck2_client::args::ArgExpansionContext;
 use buck2_client::commands::build::BuildCommand;
 use buck2_client::commands::bxl::BxlCommand;
+use buck2_client::commands::cdb::CdbCommand;
 use buck2_client::commands::clean::CleanCommand;
 use buck2_client::commands::ctargets::ConfiguredTargetsCommand;
 use buck2_client::commands::debug::DebugCommand;
+use buck2_client::commands::expand::ExpandCommand;
+use buck2_client::commands::fmt::FmtCommand;
 use buck2_client::commands::init::InitCommand;
 use buck2_client::commands::install::InstallCommand;
+use buck2_client::commands::isolation::IsolationCommand;
 use buck2_client::commands::kill::KillCommand;
 use buck2_client::commands::killall::KillallCommand;
 use buck2_client::commands::log::LogCommand;
@@ -45,6 +50,7 @@ use buck2_client::commands::targets::TargetsCommand;
 use buck2_client::commands::test::TestCommand;
 use buck2_client_ctx::cleanup_ctx::AsyncCleanupContextGuard;
 use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::daemon::client::restart_policy::DaemonRestartPolicy;
 use buck2_client_ctx::exit_result::ExitResult;
 use buck2_client_ctx::streaming::BuckSubcommand;
 use buck2_client_ctx::version::BuckVersion;
@@ -75,6 +81,7 @@ use crate::process_context::ProcessContext;
 #[macro_use]
 pub mod panic;
 mod check_user_allowed;
+mod crash;
 
 pub mod commands;
 pub mod process_context;
@@ -114,6 +121,12 @@ struct BeforeSubcommandOptions {
     #[clap(long, hidden(true))]
     reject_materializer_state: Option<String>,
 
+    /// What to do when the running daemon has to be restarted to run this command, e.g. because
+    /// it runs a different version of buck2. `prompt` asks for confirmation when the daemon is
+    /// running other commands, and fails like `if-idle` when not running in a terminal.
+    #[clap(arg_enum, env("BUCK2_DAEMON_RESTART"), long, default_value = "always")]
+    daemon_restart: DaemonRestartPolicy,
+
     /// How verbose buck should be while logging.
     /// Values:
     /// 0 = Quiet, errors only;
@@ -247,10 +260,16 @@ pub(crate) enum CommandKind {
     Aquery(AqueryCommand),
     Build(BuildCommand),
     Bxl(BxlCommand),
+    #[clap(subcommand)]
+    Cdb(CdbCommand),
     Test(TestCommand),
     Cquery(CqueryCommand),
+    Expand(ExpandCommand),
+    Fmt(FmtCommand),
     Init(InitCommand),
     Install(InstallCommand),
+    #[clap(subcommand)]
+    Isolation(IsolationCommand),
     Kill(KillCommand),
     Killall(KillallCommand),
     Root(RootCommand),
@@ -314,6 +333,24 @@ impl CommandKind {
                 .into();
         }
 
+        // The forkserver and the test runner are started by the daemon, not by users.
+        if !matches!(
+            self,
+            CommandKind::Forkserver(..) | CommandKind::InternalTestRunner(..)
+        ) {
+            let crashes_dir = match &paths {
+                Ok(paths) => paths.crashes_dir().into_path_buf(),
+                Err(_) => env::temp_dir().join("buck2-crashes"),
+            };
+            crash::initialize(
+                process.init,
+                &crashes_dir,
+                &process.trace_id,
+                process.args,
+                process.working_dir,
+            );
+        }
+
         let async_cleanup = AsyncCleanupContextGuard::new();
 
         let start_in_process_daemon: Option<Box<dyn FnOnce() -> anyhow::Result<()> + Send + Sync>> =
@@ -365,6 +402,7 @@ impl CommandKind {
             init: process.init,
             paths,
             verbosity: common_opts.verbosity,
+            daemon_restart_policy: common_opts.daemon_restart,
             start_in_process_daemon,
             command_name: self.command_name(),
             working_dir: process.working_dir.clone(),
@@ -386,8 +424,12 @@ impl CommandKind {
             CommandKind::Aquery(cmd) => cmd.exec(matches, command_ctx),
             CommandKind::Build(cmd) => cmd.exec(matches, command_ctx),
             CommandKind::Bxl(cmd) => cmd.exec(matches, command_ctx),
+            CommandKind::Cdb(cmd) => cmd.exec(matches, command_ctx),
             CommandKind::Test(cmd) => cmd.exec(matches, command_ctx),
             CommandKind::Cquery(cmd) => cmd.exec(matches, command_ctx),
+            CommandKind::Expand(cmd) => cmd.exec(matches, command_ctx),
+            CommandKind::Fmt(cmd) => cmd.exec(matches, command_ctx),
+            CommandKind::Isolation(cmd) => cmd.exec(matches, command_ctx),
             CommandKind::Kill(cmd) => cmd.exec(matches, command_ctx).into(),
             CommandKind::Killall(cmd) => cmd.exec(matches, command_ctx),
             CommandKind::Clean(cmd) => cmd.exec(matches, command_ctx),
@@ -427,6 +469,9 @@ mod tests {
 
     #[test]
     fn test_command_name() {
-        assert_eq!("kill", CommandKind::Kill(KillCommand {}).command_name());
+        assert_eq!(
+            "kill",
+            CommandKind::Kill(KillCommand { force: false }).command_name()
+        );
     }
 }
@@ -48,9 +48,15 @@ pub fn initialize(fb: FacebookInit) -> anyhow::Result<()> {
 /// The panic hook is called on the same thread that the panic occurred. It is possible to perform a backtrace here
 /// to collect additional information.
 fn the_panic_hook(fb: FacebookInit, info: &PanicInfo) {
+    crate::crash::panicked();
     imp::write_panic_to_scribe(fb, info);
 }
 
+/// Reports a crash report written by the crash handler of a previous invocation (see `crash.rs`).
+pub(crate) fn report_crash(fb: FacebookInit, report: String) {
+    imp::write_crash_report_to_scribe(fb, report);
+}
+
 mod imp {
     use std::collections::HashMap;
     use std::panic::PanicInfo;
@@ -145,6 +151,21 @@ mod imp {
         );
     }
 
+    /// Writes a crash report of a previous invocation to Scribe. The report already contains the
+    /// stack of the crash, so none is collected here.
+    pub(crate) fn write_crash_report_to_scribe(fb: FacebookInit, report: String) {
+        write_to_scribe(
+            fb,
+            panic_payload(
+                None,
+                format!("Client crash: {}", report),
+                Vec::new(),
+                &Default::default(),
+                None,
+            ),
+        );
+    }
+
     pub(crate) fn write_soft_error(
         fb: FacebookInit,
         category: &str,
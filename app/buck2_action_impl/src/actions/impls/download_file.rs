@@ -60,6 +60,7 @@ enum DownloadFileActionError {
 pub(crate) struct UnregisteredDownloadFileAction {
     checksum: Checksum,
     url: Arc<str>,
+    mirrors: Arc<[Arc<str>]>,
     is_executable: bool,
     is_deferrable: bool,
 }
@@ -68,12 +69,14 @@ impl UnregisteredDownloadFileAction {
     pub(crate) fn new(
         checksum: Checksum,
         url: Arc<str>,
+        mirrors: Arc<[Arc<str>]>,
         is_executable: bool,
         is_deferrable: bool,
     ) -> Self {
         Self {
             checksum,
             url,
+            mirrors,
             is_executable,
             is_deferrable,
         }
@@ -152,7 +155,16 @@ impl DownloadFileAction {
             Err(_) => return Ok(None),
         };
 
-        let head = http_head(client, &self.inner.url).await?;
+        // Mirrors are expected to serve the same file, so the first one that responds tells us
+        // its size.
+        let mut head = http_head(client, &self.inner.url).await;
+        for mirror in self.inner.mirrors.iter() {
+            if head.is_ok() {
+                break;
+            }
+            head = http_head(client, mirror).await;
+        }
+        let head = head?;
 
         // NOTE: Don't use reqwest's content_length() method here, that always returns zero!
         // https://github.com/seanmonstar/reqwest/issues/843
@@ -271,6 +283,7 @@ impl IncrementalActionExecutable for DownloadFileAction {
                             rel_path,
                             HttpDownloadInfo {
                                 url: self.inner.url.dupe(),
+                                mirrors: self.inner.mirrors.dupe(),
                                 checksum: self.inner.checksum.dupe(),
                                 metadata: metadata.dupe(),
                                 owner: ctx.target().owner().dupe().into_dyn(),
@@ -295,6 +308,7 @@ impl IncrementalActionExecutable for DownloadFileAction {
                         ctx.digest_config(),
                         &rel_path,
                         &self.inner.url,
+                        &self.inner.mirrors,
                         &self.inner.checksum,
                         self.inner.is_executable,
                     )
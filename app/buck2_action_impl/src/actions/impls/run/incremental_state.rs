@@ -0,0 +1,72 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Incremental state directories for run actions.
+//!
+//! An action can ask for a directory which is preserved between runs on the same host, so that
+//! incremental compilers can reuse their caches. The directory is not an input of the action, so
+//! it does not affect its cache key, and it is only trusted if the last run which used it
+//! succeeded: a marker file is removed before running the command and written back once it
+//! succeeds, and the state is wiped if the marker is missing.
+
+use buck2_build_api::actions::ActionExecutionCtx;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+
+pub(crate) struct IncrementalStateDir {
+    /// The directory passed to the command.
+    state: ProjectRelativePathBuf,
+    /// Marker present if the last run using the state succeeded.
+    marker: ProjectRelativePathBuf,
+}
+
+impl IncrementalStateDir {
+    pub(crate) fn new(ctx: &dyn ActionExecutionCtx) -> Self {
+        let root = ctx
+            .fs()
+            .buck_out_path_resolver()
+            .resolve_incremental_state(&ctx.target().custom_tmpdir());
+        Self {
+            state: root.join(ForwardRelativePath::unchecked_new("state")),
+            marker: root.join(ForwardRelativePath::unchecked_new("complete")),
+        }
+    }
+
+    pub(crate) fn path(&self) -> &ProjectRelativePathBuf {
+        &self.state
+    }
+
+    /// Make the state directory ready for the command: wipe it if the last run using it did not
+    /// complete, and mark it as in use until `mark_complete` is called.
+    pub(crate) async fn prepare(&self, ctx: &dyn ActionExecutionCtx) -> anyhow::Result<()> {
+        let fs = ctx.fs().fs();
+        let state = fs.resolve(&self.state);
+        let marker = fs.resolve(&self.marker);
+        ctx.blocking_executor()
+            .execute_io_inline(|| {
+                if fs_util::try_exists(&marker)? {
+                    fs_util::remove_file(&marker)?;
+                } else {
+                    tracing::debug!("Discarding incremental state at `{}`", state);
+                    fs_util::remove_all(&state)?;
+                }
+                fs_util::create_dir_all(&state)
+            })
+            .await
+    }
+
+    /// Record that the command using the state succeeded, so the state can be reused.
+    pub(crate) async fn mark_complete(&self, ctx: &dyn ActionExecutionCtx) -> anyhow::Result<()> {
+        let marker = ctx.fs().fs().resolve(&self.marker);
+        ctx.blocking_executor()
+            .execute_io_inline(|| fs_util::write(marker, ""))
+            .await
+    }
+}
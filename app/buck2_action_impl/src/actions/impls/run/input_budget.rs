@@ -0,0 +1,194 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Warnings for run actions whose inputs exceed the `[action_input_budget]` buckconfig.
+//!
+//! A dependency accidentally pulling a large part of the graph into an action usually shows up as
+//! a huge number of inputs or a huge command line, most of the time through a transitive set. We
+//! report which limits were exceeded along with the transitive sets contributing the most inputs,
+//! so that the offending dependency can be found.
+
+use std::cmp::Reverse;
+
+use buck2_build_api::actions::impls::run_action_knobs::ActionInputBudget;
+use buck2_build_api::actions::ActionExecutionCtx;
+use buck2_build_api::artifact_groups::ArtifactGroup;
+use buck2_build_api::artifact_groups::ArtifactGroupValues;
+use buck2_common::file_ops::TrackedFileDigest;
+use buck2_core::directory::DirectoryEntry;
+use buck2_core::directory::DirectoryIterator;
+use buck2_core::directory::FingerprintedDirectory;
+use buck2_events::dispatch::instant_event;
+use buck2_execute::directory::ActionDirectoryMember;
+use buck2_execute::execute::target::CommandExecutionTarget;
+
+/// How many transitive sets to report.
+const MAX_REPORTED_CONTRIBUTORS: usize = 5;
+
+/// The number of input files (and symlinks), and the total size of the files.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct InputSize {
+    count: u64,
+    bytes: u64,
+}
+
+impl InputSize {
+    fn add_leaf(&mut self, leaf: &ActionDirectoryMember) {
+        self.count += 1;
+        if let ActionDirectoryMember::File(file) = leaf {
+            self.bytes += file.digest.size();
+        }
+    }
+
+    fn add_directory(
+        &mut self,
+        dir: &impl FingerprintedDirectory<ActionDirectoryMember, TrackedFileDigest>,
+    ) {
+        let mut walk = dir.fingerprinted_unordered_walk();
+        while let Some((_path, entry)) = walk.next() {
+            if let DirectoryEntry::Leaf(leaf) = entry {
+                self.add_leaf(leaf);
+            }
+        }
+    }
+
+    fn of_values(values: &ArtifactGroupValues) -> Self {
+        let mut size = Self::default();
+        for (_artifact, value) in values.iter() {
+            match value.entry() {
+                DirectoryEntry::Dir(dir) => size.add_directory(dir),
+                DirectoryEntry::Leaf(leaf) => size.add_leaf(leaf),
+            }
+        }
+        size
+    }
+}
+
+/// Emit a warning if the action exceeds the `budget`. The totals are computed from the input
+/// directory of the action, where inputs reachable through several groups are only counted once.
+pub(crate) fn check_input_budget<'a>(
+    budget: ActionInputBudget,
+    ctx: &dyn ActionExecutionCtx,
+    cli: &[String],
+    inputs: impl Iterator<Item = (&'a ArtifactGroup, &'a ArtifactGroupValues)>,
+    input_directory: &impl FingerprintedDirectory<ActionDirectoryMember, TrackedFileDigest>,
+) {
+    let mut total = InputSize::default();
+    total.add_directory(input_directory);
+
+    let exceeded = exceeded_limits(budget, total, command_line_length(cli));
+    if exceeded.is_empty() {
+        return;
+    }
+
+    // Rank by size if that is the only problem, by number of inputs otherwise.
+    let by_bytes = exceeded.iter().all(|l| l.name == "max_input_bytes");
+    let mut contributors: Vec<_> = inputs
+        .filter_map(|(group, values)| match group {
+            ArtifactGroup::TransitiveSetProjection(key) => {
+                Some((key, InputSize::of_values(values)))
+            }
+            ArtifactGroup::Artifact(_) => None,
+        })
+        .collect();
+    contributors.sort_by_key(|(_, size)| Reverse(if by_bytes { size.bytes } else { size.count }));
+
+    let target = ctx.target();
+    instant_event(buck2_data::ActionInputBudgetExceeded {
+        key: Some(target.as_proto_action_key()),
+        name: Some(target.as_proto_action_name()),
+        exceeded,
+        largest_contributors: contributors
+            .into_iter()
+            .take(MAX_REPORTED_CONTRIBUTORS)
+            .map(|(key, size)| buck2_data::ActionInputContributor {
+                name: key.to_string(),
+                input_count: size.count,
+                input_bytes: size.bytes,
+            })
+            .collect(),
+    });
+}
+
+fn exceeded_limits(
+    budget: ActionInputBudget,
+    total: InputSize,
+    command_line_length: u64,
+) -> Vec<buck2_data::ActionInputBudgetLimit> {
+    [
+        ("max_inputs", total.count, budget.max_inputs),
+        ("max_input_bytes", total.bytes, budget.max_input_bytes),
+        (
+            "max_command_line_length",
+            command_line_length,
+            budget.max_command_line_length,
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(name, value, limit)| match limit {
+        Some(limit) if value > limit => Some(buck2_data::ActionInputBudgetLimit {
+            name: name.to_owned(),
+            value,
+            limit,
+        }),
+        _ => None,
+    })
+    .collect()
+}
+
+/// The length of the command line, with its arguments separated by spaces.
+fn command_line_length(cli: &[String]) -> u64 {
+    let separators = cli.len().saturating_sub(1);
+    (cli.iter().map(|arg| arg.len()).sum::<usize>() + separators) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_build_api::actions::impls::run_action_knobs::ActionInputBudget;
+
+    use crate::actions::impls::run::input_budget::command_line_length;
+    use crate::actions::impls::run::input_budget::exceeded_limits;
+    use crate::actions::impls::run::input_budget::InputSize;
+
+    #[test]
+    fn test_command_line_length() {
+        assert_eq!(command_line_length(&[]), 0);
+        assert_eq!(
+            command_line_length(&["cc".to_owned(), "-c".to_owned(), "foo.c".to_owned()]),
+            11
+        );
+    }
+
+    #[test]
+    fn test_exceeded_limits() {
+        let budget = ActionInputBudget {
+            max_inputs: Some(100),
+            max_input_bytes: None,
+            max_command_line_length: Some(1000),
+        };
+        let size = |count| InputSize {
+            count,
+            bytes: 1 << 40,
+        };
+
+        assert!(exceeded_limits(budget, size(100), 1000).is_empty());
+
+        let exceeded = exceeded_limits(budget, size(101), 2000);
+        assert_eq!(
+            exceeded
+                .iter()
+                .map(|l| (l.name.as_str(), l.value, l.limit))
+                .collect::<Vec<_>>(),
+            vec![
+                ("max_inputs", 101, 100),
+                ("max_command_line_length", 2000, 1000)
+            ]
+        );
+    }
+}
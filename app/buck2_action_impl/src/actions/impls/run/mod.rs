@@ -34,6 +34,7 @@ use buck2_core::category::Category;
 use buck2_core::directory::FingerprintedDirectory;
 use buck2_core::fs::buck_out_path::BuckOutPath;
 use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+use buck2_events::dispatch::get_dispatcher;
 use buck2_events::dispatch::span_async;
 use buck2_execute::artifact::fs::ExecutorFs;
 use buck2_execute::execute::environment_inheritance::EnvironmentInheritance;
@@ -43,9 +44,11 @@ use buck2_execute::execute::request::CommandExecutionOutput;
 use buck2_execute::execute::request::CommandExecutionPaths;
 use buck2_execute::execute::request::CommandExecutionRequest;
 use buck2_execute::execute::request::ExecutorPreference;
+use buck2_execute::execute::request::Nondeterminism;
 use dupe::Dupe;
 use gazebo::prelude::*;
 use host_sharing::HostSharingRequirements;
+use host_sharing::MemoryRequirement;
 use host_sharing::WeightClass;
 use indexmap::indexmap;
 use indexmap::IndexSet;
@@ -63,10 +66,14 @@ use crate::actions::impls::run::dep_files::CommandDigests;
 use crate::actions::impls::run::dep_files::DepFilesCommandLineVisitor;
 use crate::actions::impls::run::dep_files::DepFilesKey;
 use crate::actions::impls::run::dep_files::RunActionDepFiles;
+use crate::actions::impls::run::incremental_state::IncrementalStateDir;
+use crate::actions::impls::run::input_budget::check_input_budget;
 use crate::actions::impls::run::metadata::metadata_content;
 
 mod audit_dep_files;
 pub mod dep_files;
+mod incremental_state;
+mod input_budget;
 mod metadata;
 
 #[derive(Debug, Error)]
@@ -139,12 +146,26 @@ pub(crate) struct UnregisteredRunAction {
     pub(crate) identifier: Option<String>,
     pub(crate) executor_preference: ExecutorPreference,
     pub(crate) always_print_stderr: bool,
-    pub(crate) weight: WeightClass,
+    /// Unset if the action should use the defaults for its category.
+    pub(crate) weight: Option<WeightClass>,
+    pub(crate) memory: Option<MemoryRequirement>,
     pub(crate) dep_files: RunActionDepFiles,
     pub(crate) metadata_param: Option<MetadataParameter>,
     pub(crate) no_outputs_cleanup: bool,
+    /// Name of the environment variable set to the incremental state directory, if the action
+    /// uses one.
+    pub(crate) incremental_state_env_var: Option<String>,
     pub(crate) allow_cache_upload: bool,
+    pub(crate) allow_forced_cache_upload: bool,
     pub(crate) force_full_hybrid_if_capable: bool,
+    pub(crate) nondeterminism: Nondeterminism,
+    /// Whether to run unsandboxed when local actions are sandboxed.
+    pub(crate) no_sandbox: bool,
+    /// Whether to run in a pseudo-terminal.
+    pub(crate) tty: bool,
+    /// Name of the environment variable set to the build ID, if the action wants it. It is not
+    /// part of the cache key.
+    pub(crate) build_id_env_var: Option<String>,
 }
 
 impl UnregisteredAction for UnregisteredRunAction {
@@ -267,7 +288,8 @@ impl RunAction {
         // Generate content and output path for the file. It will be either passed
         // to RE as a blob or written to disk in local executor.
         // Path to this file is passed to user in environment variable which is selected by user.
-        let extra_env = if let Some(metadata_param) = &self.inner.metadata_param {
+        let mut extra_env = Vec::new();
+        if let Some(metadata_param) = &self.inner.metadata_param {
             let path = BuckOutPath::new(
                 ctx.target().owner().dupe().into_dyn(),
                 metadata_param.path.clone(),
@@ -280,10 +302,15 @@ impl RunAction {
                 digest,
                 path,
             }));
-            Some(extra)
-        } else {
-            None
-        };
+            extra_env.push(extra);
+        }
+
+        // The incremental state directory is not an input: its path is stable, so it doesn't
+        // affect the cache key, and its contents are not part of the action digest.
+        if let Some(env_var) = &self.inner.incremental_state_env_var {
+            let state = IncrementalStateDir::new(ctx);
+            extra_env.push((env_var.to_owned(), state.path().to_string()));
+        }
 
         let paths = CommandExecutionPaths::new(
             inputs,
@@ -298,6 +325,17 @@ impl RunAction {
             ctx.digest_config(),
         )?;
 
+        let input_budget = ctx.run_action_knobs().action_input_budget;
+        if input_budget.is_enabled() {
+            check_input_budget(
+                input_budget,
+                ctx,
+                &expanded.cli,
+                visitor.inputs().zip(artifact_inputs.iter().copied()),
+                paths.input_directory(),
+            );
+        }
+
         Ok(PreparedRunAction {
             expanded,
             extra_env,
@@ -308,7 +346,7 @@ impl RunAction {
 
 struct PreparedRunAction {
     expanded: ExpandedCommandLine,
-    extra_env: Option<(String, String)>,
+    extra_env: Vec<(String, String)>,
     paths: CommandExecutionPaths,
 }
 
@@ -388,6 +426,10 @@ impl Action for RunAction {
         self.inner.always_print_stderr
     }
 
+    fn nondeterminism(&self) -> Nondeterminism {
+        self.inner.nondeterminism
+    }
+
     fn aquery_attributes(&self, fs: &ExecutorFs) -> indexmap::IndexMap<String, String> {
         let mut cli_rendered = Vec::<String>::new();
         let mut ctx = DefaultCommandLineContext::new(fs);
@@ -399,13 +441,31 @@ impl Action for RunAction {
             "cmd".to_owned() => cmd,
             "executor_preference".to_owned() => self.inner.executor_preference.to_string(),
             "always_print_stderr".to_owned() => self.inner.always_print_stderr.to_string(),
-            "weight".to_owned() => self.inner.weight.to_string(),
+            "weight".to_owned() => match &self.inner.weight {
+                None => "None".to_owned(),
+                Some(x) => x.to_string(),
+            },
+            "memory".to_owned() => match &self.inner.memory {
+                None => "None".to_owned(),
+                Some(x) => x.to_string(),
+            },
             "dep_files".to_owned() => self.inner.dep_files.to_string(),
             "metadata_param".to_owned() => match &self.inner.metadata_param {
                 None => "None".to_owned(),
                 Some(x) => x.to_string(),
             },
             "no_outputs_cleanup".to_owned() => self.inner.no_outputs_cleanup.to_string(),
+            "incremental_state_env_var".to_owned() => match &self.inner.incremental_state_env_var {
+                None => "None".to_owned(),
+                Some(x) => x.to_owned(),
+            },
+            "nondeterminism".to_owned() => self.inner.nondeterminism.to_string(),
+            "no_sandbox".to_owned() => self.inner.no_sandbox.to_string(),
+            "tty".to_owned() => self.inner.tty.to_string(),
+            "build_id_env_var".to_owned() => match &self.inner.build_id_env_var {
+                None => "None".to_owned(),
+                Some(x) => x.to_owned(),
+            },
         }
     }
 }
@@ -478,22 +538,61 @@ impl IncrementalActionExecutable for RunAction {
             (prepared, Some(dep_files))
         };
 
-        // Run actions are assumed to be shared
-        let host_sharing_requirements = HostSharingRequirements::Shared(self.inner.weight);
+        let default_resources = knobs
+            .action_resource_defaults
+            .get(self.inner.category.as_str());
 
-        let req = prepared
+        // Run actions are assumed to be shared
+        let host_sharing_requirements = HostSharingRequirements::Shared(
+            self.inner
+                .weight
+                .or(default_resources.weight)
+                .unwrap_or(WeightClass::Permits(1)),
+        );
+
+        let mut req = prepared
             .into_command_execution_request()
             .with_prefetch_lossy_stderr(true)
             .with_executor_preference(self.inner.executor_preference)
             .with_host_sharing_requirements(host_sharing_requirements)
             .with_outputs_cleanup(!self.inner.no_outputs_cleanup)
             .with_allow_cache_upload(self.inner.allow_cache_upload)
+            .with_allow_forced_cache_upload(self.inner.allow_forced_cache_upload)
             .with_local_environment_inheritance(EnvironmentInheritance::local_command_exclusions())
             .with_force_full_hybrid_if_capable(self.inner.force_full_hybrid_if_capable)
+            .with_nondeterminism(self.inner.nondeterminism)
+            .with_disable_sandbox(self.inner.no_sandbox)
+            .with_tty(self.inner.tty)
             .with_custom_tmpdir(ctx.target().custom_tmpdir());
 
+        if let Some(memory) = self.inner.memory.or(default_resources.memory) {
+            req = req.with_memory_requirement(memory);
+        }
+
+        // The build ID changes on every invocation, so it must not be part of the action digest.
+        // The local executor checks that it doesn't end up in cacheable outputs.
+        if let Some(env_var) = &self.inner.build_id_env_var {
+            let mut env = SortedVectorMap::new();
+            env.insert(env_var.to_owned(), get_dispatcher().trace_id().to_string());
+            req = req.with_cache_exempt_env(env);
+        }
+
+        let incremental_state = match &self.inner.incremental_state_env_var {
+            Some(_) => {
+                let state = IncrementalStateDir::new(ctx);
+                state.prepare(ctx).await?;
+                req = req.with_incremental_state_dir(state.path().clone());
+                Some(state)
+            }
+            None => None,
+        };
+
         let (outputs, meta) = ctx.exec_cmd(&req).await?;
 
+        if let Some(state) = incremental_state {
+            state.mark_complete(ctx).await?;
+        }
+
         let outputs = ActionOutputs::new(outputs);
 
         if let Some(dep_files) = dep_files {
@@ -10,8 +10,10 @@
 use std::borrow::Cow;
 use std::convert::TryFrom;
 use std::fmt;
+use std::io::BufWriter;
 use std::io::Write;
 use std::slice;
+use std::time::Duration;
 use std::time::Instant;
 
 use allocative::Allocative;
@@ -35,8 +37,17 @@ use buck2_build_api::interpreter::rule_defs::cmd_args::CommandLineArtifactVisito
 use buck2_build_api::interpreter::rule_defs::cmd_args::CommandLineBuilder;
 use buck2_build_api::interpreter::rule_defs::cmd_args::CommandLineContext;
 use buck2_build_api::interpreter::rule_defs::cmd_args::WriteToFileMacroVisitor;
+use buck2_common::cas_digest::CasDigestConfig;
+use buck2_common::cas_digest::Digester;
+use buck2_common::file_ops::FileDigest;
+use buck2_common::file_ops::FileDigestKind;
+use buck2_common::file_ops::FileMetadata;
+use buck2_common::file_ops::TrackedFileDigest;
 use buck2_core::category::Category;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
 use buck2_execute::artifact::fs::ExecutorFs;
+use buck2_execute::artifact_value::ArtifactValue;
 use buck2_execute::execute::command_executor::ActionExecutionTimingData;
 use buck2_execute::materialize::materializer::WriteRequest;
 use dupe::Dupe;
@@ -69,11 +80,27 @@ enum WriteJsonActionValidationError {
 }
 
 #[derive(Allocative)]
-pub(crate) struct UnregisteredWriteJsonAction;
+pub(crate) struct UnregisteredWriteJsonAction {
+    is_executable: bool,
+    streamed: bool,
+}
 
 impl UnregisteredWriteJsonAction {
     pub(crate) fn new() -> Self {
-        Self
+        Self {
+            is_executable: false,
+            streamed: false,
+        }
+    }
+
+    /// An action which serializes the JSON straight to the output file while it executes, for
+    /// contents too large to hold in memory (i.e. transitive set projections). This gives up
+    /// deferring the write to the materializer.
+    pub(crate) fn streamed(is_executable: bool) -> Self {
+        Self {
+            is_executable,
+            streamed: true,
+        }
     }
 
     pub(crate) fn cli<'v>(
@@ -92,7 +119,8 @@ impl UnregisteredAction for UnregisteredWriteJsonAction {
         starlark_data: Option<OwnedFrozenValue>,
     ) -> anyhow::Result<Box<dyn Action>> {
         let contents = starlark_data.expect("module data to be present");
-        let action = WriteJsonAction::new(contents, inputs, outputs)?;
+        let action =
+            WriteJsonAction::new(contents, inputs, outputs, self.is_executable, self.streamed)?;
         Ok(Box::new(action))
     }
 }
@@ -101,6 +129,8 @@ impl UnregisteredAction for UnregisteredWriteJsonAction {
 struct WriteJsonAction {
     contents: OwnedFrozenValue, // JSON value
     output: BuildArtifact,
+    is_executable: bool,
+    streamed: bool,
 }
 
 impl WriteJsonAction {
@@ -108,6 +138,8 @@ impl WriteJsonAction {
         contents: OwnedFrozenValue,
         inputs: IndexSet<ArtifactGroup>,
         outputs: IndexSet<BuildArtifact>,
+        is_executable: bool,
+        streamed: bool,
     ) -> anyhow::Result<Self> {
         validate_json(contents.value())?;
 
@@ -125,7 +157,12 @@ impl WriteJsonAction {
             return Err(WriteJsonActionValidationError::TooManyInputs.into());
         }
 
-        Ok(WriteJsonAction { contents, output })
+        Ok(WriteJsonAction {
+            contents,
+            output,
+            is_executable,
+            streamed,
+        })
     }
 
     fn write(&self, fs: &ExecutorFs, writer: impl Write) -> anyhow::Result<()> {
@@ -137,6 +174,85 @@ impl WriteJsonAction {
         self.write(fs, &mut contents)?;
         Ok(contents)
     }
+
+    /// Serialize the contents to the output file, and declare the file to the materializer
+    /// once it is written.
+    async fn execute_streamed(
+        &self,
+        ctx: &mut dyn ActionExecutionCtx,
+    ) -> anyhow::Result<(ArtifactValue, Duration)> {
+        ctx.cleanup_outputs().await?;
+
+        let execution_start = Instant::now();
+        let path = ctx.fs().resolve_build(self.output.get_path());
+        let abs_path = ctx.fs().fs().resolve(&path);
+        let executor_fs = ctx.executor_fs();
+        let digest_config = ctx.digest_config().cas_digest_config();
+        let digest = ctx
+            .blocking_executor()
+            .execute_io_inline(|| {
+                write_streamed(&abs_path, self.is_executable, digest_config, |w| {
+                    self.write(&executor_fs, w)
+                })
+            })
+            .await?;
+
+        let value = ArtifactValue::file(FileMetadata {
+            digest,
+            is_executable: self.is_executable,
+        });
+        ctx.materializer()
+            .declare_existing(vec![(path, value.dupe())])
+            .await?;
+        Ok((value, execution_start.elapsed()))
+    }
+}
+
+/// Passes what is written on to `inner`, computing its digest on the way.
+struct DigestingWriter<W> {
+    inner: W,
+    digester: Digester<FileDigestKind>,
+}
+
+impl<W: Write> Write for DigestingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.digester.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Write the output of `write` to the file at `path` through a buffer, so it never needs to be
+/// held in memory as a whole, and return the digest of the file.
+fn write_streamed(
+    path: &AbsNormPath,
+    is_executable: bool,
+    digest_config: CasDigestConfig,
+    write: impl FnOnce(&mut dyn Write) -> anyhow::Result<()>,
+) -> anyhow::Result<TrackedFileDigest> {
+    if let Some(dir) = path.parent() {
+        fs_util::create_dir_all(dir)?;
+    }
+    let mut writer = DigestingWriter {
+        inner: BufWriter::new(fs_util::create_file(path)?),
+        digester: FileDigest::digester(digest_config),
+    };
+    write(&mut writer)?;
+    writer
+        .inner
+        .flush()
+        .with_context(|| format!("Error writing `{}`", path))?;
+    if is_executable {
+        fs_util::set_executable(path)?;
+    }
+    Ok(TrackedFileDigest::new(
+        writer.digester.finalize(),
+        digest_config,
+    ))
 }
 
 #[async_trait]
@@ -186,29 +302,34 @@ impl IncrementalActionExecutable for WriteJsonAction {
         &self,
         ctx: &mut dyn ActionExecutionCtx,
     ) -> anyhow::Result<(ActionOutputs, ActionExecutionMetadata)> {
-        let fs = ctx.fs();
-
-        let mut execution_start = None;
-
-        let value = ctx
-            .materializer()
-            .declare_write(Box::new(|| {
-                execution_start = Some(Instant::now());
-                let content = self.get_contents(&ctx.executor_fs())?;
-                Ok(vec![WriteRequest {
-                    path: fs.resolve_build(self.output.get_path()),
-                    content,
-                    is_executable: false,
-                }])
-            }))
-            .await?
-            .into_iter()
-            .next()
-            .context("Write did not execute")?;
-
-        let wall_time = execution_start
-            .context("Action did not set execution_start")?
-            .elapsed();
+        let (value, wall_time) = if self.streamed {
+            self.execute_streamed(ctx).await?
+        } else {
+            let fs = ctx.fs();
+
+            let mut execution_start = None;
+
+            let value = ctx
+                .materializer()
+                .declare_write(Box::new(|| {
+                    execution_start = Some(Instant::now());
+                    let content = self.get_contents(&ctx.executor_fs())?;
+                    Ok(vec![WriteRequest {
+                        path: fs.resolve_build(self.output.get_path()),
+                        content,
+                        is_executable: self.is_executable,
+                    }])
+                }))
+                .await?
+                .into_iter()
+                .next()
+                .context("Write did not execute")?;
+
+            let wall_time = execution_start
+                .context("Action did not set execution_start")?
+                .elapsed();
+            (value, wall_time)
+        };
 
         Ok((
             ActionOutputs::new(indexmap![self.output.get_path().dupe() => value]),
@@ -285,3 +406,41 @@ impl<'v, V: ValueLike<'v>> CommandLineArgLike for WriteJsonCommandLineArgGen<V>
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use buck2_common::cas_digest::CasDigestConfig;
+    use buck2_common::file_ops::TrackedFileDigest;
+    use buck2_core::fs::fs_util;
+    use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+    use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+
+    use crate::actions::impls::write_json::write_streamed;
+
+    #[test]
+    fn test_write_streamed() -> anyhow::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let path = AbsNormPathBuf::try_from(tempdir.path().to_owned())?
+            .join(ForwardRelativePath::new("out/file.json")?);
+        let config = CasDigestConfig::testing_default();
+
+        // Larger than the buffer, so that it is written in several chunks.
+        let line = "[\"some\", \"json\"]\n";
+        let digest = write_streamed(&path, false, config, |w| {
+            for _ in 0..10000 {
+                w.write_all(line.as_bytes())?;
+            }
+            Ok(())
+        })?;
+
+        let expected = line.repeat(10000);
+        assert_eq!(expected, fs_util::read_to_string(&path)?);
+        assert_eq!(
+            TrackedFileDigest::from_content(expected.as_bytes(), config),
+            digest
+        );
+        Ok(())
+    }
+}
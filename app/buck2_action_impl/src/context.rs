@@ -13,7 +13,9 @@ use std::sync::Arc;
 
 use anyhow::Context;
 use buck2_build_api::actions::artifact::artifact_type::OutputArtifact;
+use buck2_build_api::actions::artifact::output_options::OutputOptions;
 use buck2_build_api::actions::impls::json::validate_json;
+use buck2_build_api::actions::impls::json::visit_json_artifacts;
 use buck2_build_api::artifact_groups::ArtifactGroup;
 use buck2_build_api::attrs::resolve::attr_type::arg::value::ResolvedMacro;
 use buck2_build_api::interpreter::rule_defs::artifact::associated::AssociatedArtifacts;
@@ -31,10 +33,12 @@ use buck2_build_api::interpreter::rule_defs::cmd_args::StarlarkCommandLine;
 use buck2_build_api::interpreter::rule_defs::cmd_args::WriteToFileMacroVisitor;
 use buck2_build_api::interpreter::rule_defs::context::AnalysisActions;
 use buck2_build_api::interpreter::rule_defs::context::REGISTER_CONTEXT_ACTIONS;
+use buck2_build_api::interpreter::rule_defs::transitive_set::TransitiveSetJsonProjection;
 use buck2_common::cas_digest::CasDigest;
 use buck2_common::executor_config::RemoteExecutorUseCase;
 use buck2_core::category::Category;
 use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+use buck2_execute::execute::request::Nondeterminism;
 use buck2_execute::execute::request::OutputType;
 use buck2_execute::materialize::http::Checksum;
 use buck2_interpreter::starlark_promise::StarlarkPromise;
@@ -44,6 +48,7 @@ use chrono::Utc;
 use ctor::ctor;
 use dupe::Dupe;
 use dupe::OptionDupedExt;
+use host_sharing::MemoryRequirement;
 use host_sharing::WeightClass;
 use host_sharing::WeightPercentage;
 use indexmap::indexset;
@@ -128,6 +133,20 @@ enum RunActionError {
         "missing `metadata_env_var` parameter which is required when `metadata_path` parameter is present"
     )]
     MetadataEnvVarMissing,
+    #[error(
+        "`incremental_state_env_var` requires `local_only = True`, as incremental state is only kept on the host which ran the action"
+    )]
+    IncrementalStateRequiresLocalOnly,
+    #[error(
+        "`incremental_state_env_var` is incompatible with `allow_cache_upload = True`, as outputs built from incremental state may not be reproducible"
+    )]
+    IncrementalStateWithCacheUpload,
+    #[error("`tty = True` requires `local_only = True`")]
+    TtyRequiresLocalOnly,
+    #[error(
+        "`build_id_env_var` requires `local_only = True`, as remote execution makes the whole environment part of the cache key"
+    )]
+    BuildIdRequiresLocalOnly,
     #[error(
         "Recursion limit exceeded when visiting artifacts: do you have a cycle in your inputs or outputs?"
     )]
@@ -219,11 +238,26 @@ fn register_context_actions(builder: &mut MethodsBuilder) {
     /// `directory/foo`.
     ///
     /// The `dir` argument should be set to `True` if the binding will be a directory.
+    ///
+    /// If `executable` is `True`, the output must be a file, and it will have its executable bit set once built,
+    /// whichever way the action producing it was executed. If `symlink_target` is given, the output will be a
+    /// symlink to that path (relative to the directory containing the output) once built, even if the action
+    /// produced something else. On Windows, if symlinks can't be created and the target is another output of the
+    /// same action, the output is a copy of the target instead.
+    ///
+    /// If `short_path_prefix` is given, the artifact's `short_path` is `short_path_prefix` followed by
+    /// `filename`, e.g. `ctx.actions.declare_output("__objs__", "foo.o", short_path_prefix = "lib")` has the
+    /// short path `lib/foo.o`. Rules use this to control the paths their outputs take in runfiles-like
+    /// layouts, such as transitive set JSON projections or install file maps built from `short_path`.
+    /// Short paths declared this way must not conflict with the short path of any other output of the target.
     fn declare_output<'v>(
         this: &AnalysisActions<'v>,
         #[starlark(require = pos)] prefix: &str,
         #[starlark(require = pos)] filename: Option<&str>,
         #[starlark(require = named, default = false)] dir: bool,
+        #[starlark(require = named, default = false)] executable: bool,
+        #[starlark(require = named)] symlink_target: Option<&str>,
+        #[starlark(require = named)] short_path_prefix: Option<&str>,
         eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<StarlarkDeclaredArtifact> {
         // We take either one or two positional arguments, namely (filename) or (prefix, filename).
@@ -239,12 +273,15 @@ fn register_context_actions(builder: &mut MethodsBuilder) {
         } else {
             OutputType::FileOrDirectory
         };
+        let output_options = OutputOptions::new(executable, symlink_target, dir)?;
         let artifact = this.state().declare_output(
             prefix,
+            short_path_prefix,
             filename,
             output_type,
             eval.call_stack_top_location(),
         )?;
+        artifact.set_output_options(output_options)?;
 
         Ok(StarlarkDeclaredArtifact::new(
             eval.call_stack_top_location(),
@@ -295,6 +332,8 @@ fn register_context_actions(builder: &mut MethodsBuilder) {
 
     /// Returns an `artifact` whose contents are content
     ///
+    /// * `content`: a command line, or a transitive set JSON projection (from `project_as_json()`), which is written as JSON
+    ///   when the action runs, without being turned into a string during analysis
     /// * `is_executable` (optional): indicates whether the resulting file should be marked with executable permissions
     /// * `allow_args` (optional): must be set to `True` if you want to write parameter arguments to the file (in particular, macros that write to file)
     ///     * If it is true, the result will be a pair of the `artifact` containing content and a list of artifact values that were written by macros, which should be used in hidden fields or similar
@@ -344,6 +383,18 @@ fn register_context_actions(builder: &mut MethodsBuilder) {
             Ok(counter.count)
         }
 
+        #[derive(Default)]
+        struct CommandLineInputVisitor {
+            inputs: SmallSet<ArtifactGroup>,
+        }
+        impl CommandLineArtifactVisitor for CommandLineInputVisitor {
+            fn visit_input(&mut self, input: ArtifactGroup, _tag: Option<&ArtifactTag>) {
+                self.inputs.insert(input);
+            }
+
+            fn visit_output(&mut self, _artifact: OutputArtifact, _tag: Option<&ArtifactTag>) {}
+        }
+
         fn get_cli_inputs(
             with_inputs: bool,
             cli: &dyn CommandLineArgLike,
@@ -352,18 +403,6 @@ fn register_context_actions(builder: &mut MethodsBuilder) {
                 return Ok(Default::default());
             }
 
-            #[derive(Default)]
-            struct CommandLineInputVisitor {
-                inputs: SmallSet<ArtifactGroup>,
-            }
-            impl CommandLineArtifactVisitor for CommandLineInputVisitor {
-                fn visit_input(&mut self, input: ArtifactGroup, _tag: Option<&ArtifactTag>) {
-                    self.inputs.insert(input);
-                }
-
-                fn visit_output(&mut self, _artifact: OutputArtifact, _tag: Option<&ArtifactTag>) {}
-            }
-
             let mut visitor = CommandLineInputVisitor::default();
             cli.visit_artifacts(&mut visitor)?;
             Ok(visitor.inputs)
@@ -373,6 +412,32 @@ fn register_context_actions(builder: &mut MethodsBuilder) {
         let (declaration, output_artifact) =
             this.get_or_declare_output(eval, output, "output", OutputType::File)?;
 
+        if TransitiveSetJsonProjection::from_value(content).is_some() {
+            // Large projections would use a lot of memory if they were rendered as a string here,
+            // or by the action before writing it, so the action streams them to the output file.
+            // A projection can't contain write-to-file macros, so there are never any macro files.
+            let mut visitor = CommandLineInputVisitor::default();
+            if with_inputs {
+                visit_json_artifacts(content, &mut visitor)?;
+            }
+            this.register_action(
+                IndexSet::new(),
+                indexset![output_artifact],
+                UnregisteredWriteJsonAction::streamed(is_executable),
+                Some(content),
+            )?;
+            let value = declaration
+                .into_declared_artifact(AssociatedArtifacts::from(visitor.inputs))
+                .to_value();
+            return if allow_args {
+                Ok(eval
+                    .heap()
+                    .alloc((value, Vec::<StarlarkDeclaredArtifact>::new())))
+            } else {
+                Ok(value)
+            };
+        }
+
         let (content_cli, written_macro_count, mut associated_artifacts) =
             if let Some(content_arg) = content.as_command_line() {
                 let count = count_write_to_file_macros(allow_args, content_arg)?;
@@ -398,6 +463,7 @@ fn register_context_actions(builder: &mut MethodsBuilder) {
             let mut written_macro_files = indexset![];
             for i in 0..written_macro_count {
                 let macro_file = this.declare_output(
+                    None,
                     None,
                     &format!("{}/{}.macro", &macro_directory_path, i),
                     OutputType::File,
@@ -564,11 +630,29 @@ fn register_context_actions(builder: &mut MethodsBuilder) {
     /// * `arguments`: must be of type `cmd_args`, or a type convertible to such (such as a list of strings and artifacts) and must contain at least one `.as_output()` artifact
     /// * `category`: category and identifier - when used together, identify the action in Buck2's event stream, and must be unique for a given target
     /// * `weight`: used to note how heavy the command is and will typically be set to a higher value to indicate that less such commands should be run in parallel (if running locally)
+    /// * `memory`: how much memory the command needs when running locally (e.g. `"512M"` or `"8G"`); local commands only start once that much of the `build.local_memory` budget is available
+    ///     * When `weight` or `memory` are unset, the defaults configured for the action's `category` in the `[action_resources]` buckconfig section are used, if any
+    /// * `allow_forced_cache_upload`: if this flag is unset then the results of this action are never uploaded to the remote cache by `--upload-all-outputs` (useful for actions whose outputs are host specific or contain secrets); `allow_cache_upload` is unaffected
     /// * `no_outputs_cleanup`: if this flag is set then Buck2 won't clean the outputs of a previous build that might be present on a disk; in which case, command from arguments should be responsible for the cleanup (that is useful, for example, when an action is supporting incremental mode and its outputs are based on result from a previous build)
     /// * `metadata_env_var` and `meadata_path` should be used together: both set or both unset
     ///     * `metadata_path`: defines a path relative to the result directory for a file with action metadata, which will be created right before the command will be run.
     ///     * Metadata contains the path relative to the Buck2 project root and hash digest for every action input (this excludes symlinks as they could be resolved by a user script if needed). The resolved path relative to the Buck2 project for the metadata file will be passed to command from arguments, via the environment variable, with its name set by `metadata_env_var`
     ///     * Both `metadata_env_var` and `metadata_path` are useful when making actions behave in an incremental manner (for details, see [Incremental Actions](https://buck2.build/docs/rule_authors/incremental_actions/))
+    /// * `incremental_state_env_var`: if set, the command gets the path (relative to the Buck2 project root) of a directory preserved between runs of this action on the same host in this environment variable, e.g. for the caches of incremental compilers
+    ///     * The contents of the directory are not inputs of the action, so they must not change its outputs
+    ///     * The directory is wiped if the last run which used it did not succeed
+    ///     * Requires `local_only = True`, and is incompatible with `allow_cache_upload = True`
+    /// * `nondeterministic`: marks an action whose outputs may differ between runs (e.g. archives embedding timestamps), and how the cache treats it:
+    ///     * `"no_verify"`: the outputs are never checked against a rebuild; results of a rebuild which skips the cache (`--no-remote-cache`) are not written to the cache, so they never replace outputs which other cached actions were built against
+    ///     * `"prefer_cached"`: a cached result is always preferred to running the action, even with `--no-remote-cache`
+    /// * `no_sandbox`: run this action unsandboxed when local actions are sandboxed (`build.sandbox_local_actions`), e.g. for tools which need to access the whole repository
+    /// * `tty`: run the command in a pseudo-terminal, for tools which behave differently when not writing to a terminal
+    ///     * Requires `local_only = True`, and is not supported on Windows
+    ///     * The stdout and stderr of the command are both captured as its stdout, with terminal escape sequences (colors, progress bars, ...) stripped
+    /// * `build_id_env_var`: if set, the command gets the ID of the current invocation in this environment variable, e.g. to tag its logs
+    ///     * The variable is not part of the cache key of the action, so results are reused across invocations
+    ///     * Requires `local_only = True`
+    ///     * The action fails if its outputs contain the build ID and they may be uploaded to the cache (unless both `allow_cache_upload` and `allow_forced_cache_upload` are unset)
     fn run<'v>(
         this: &AnalysisActions<'v>,
         #[starlark(require = pos, type = TYPE_CMD_ARG_LIKE)] arguments: Value<'v>,
@@ -581,6 +665,7 @@ fn register_context_actions(builder: &mut MethodsBuilder) {
         #[starlark(require = named, default = false)] always_print_stderr: bool,
         #[starlark(require = named)] weight: Option<i32>,
         #[starlark(require = named)] weight_percentage: Option<i32>,
+        #[starlark(require = named)] memory: Option<&str>,
         #[starlark(require = named, type = "{str.type, \"artifact_tag\"}")] dep_files: Option<
             ValueOf<'v, SmallMap<&'v str, Value<'v>>>,
         >,
@@ -588,8 +673,14 @@ fn register_context_actions(builder: &mut MethodsBuilder) {
         #[starlark(require = named)] metadata_path: Option<String>,
         // TODO(scottcao): Refactor `no_outputs_cleanup` to `outputs_cleanup`
         #[starlark(require = named, default = false)] no_outputs_cleanup: bool,
+        #[starlark(require = named)] incremental_state_env_var: Option<String>,
         #[starlark(require = named, default = false)] allow_cache_upload: bool,
+        #[starlark(require = named, default = true)] allow_forced_cache_upload: bool,
         #[starlark(require = named, default = false)] force_full_hybrid_if_capable: bool,
+        #[starlark(require = named)] nondeterministic: Option<&str>,
+        #[starlark(require = named, default = false)] no_sandbox: bool,
+        #[starlark(require = named, default = false)] tty: bool,
+        #[starlark(require = named)] build_id_env_var: Option<String>,
         eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<NoneType> {
         struct RunCommandArtifactVisitor {
@@ -642,28 +733,52 @@ fn register_context_actions(builder: &mut MethodsBuilder) {
 
         let executor_preference = new_executor_preference(local_only, prefer_local, prefer_remote)?;
 
+        if incremental_state_env_var.is_some() && !local_only {
+            return Err(RunActionError::IncrementalStateRequiresLocalOnly.into());
+        }
+        if incremental_state_env_var.is_some() && allow_cache_upload {
+            return Err(RunActionError::IncrementalStateWithCacheUpload.into());
+        }
+
+        if tty && !local_only {
+            return Err(RunActionError::TtyRequiresLocalOnly.into());
+        }
+
+        if build_id_env_var.is_some() && !local_only {
+            return Err(RunActionError::BuildIdRequiresLocalOnly.into());
+        }
+
+        let nondeterminism = match nondeterministic {
+            None => Nondeterminism::Deterministic,
+            Some(nondeterministic) => nondeterministic.parse()?,
+        };
+
         let mut artifact_visitor = RunCommandArtifactVisitor::new();
 
         let starlark_cli = StarlarkCommandLine::try_from_value(arguments)?;
         starlark_cli.visit_artifacts(&mut artifact_visitor)?;
 
         let weight = match (weight, weight_percentage) {
-            (None, None) => WeightClass::Permits(1),
+            (None, None) => None,
             (Some(v), None) => {
                 if v < 1 {
                     return Err(RunActionError::InvalidWeight(v).into());
                 } else {
-                    WeightClass::Permits(v as usize)
+                    Some(WeightClass::Permits(v as usize))
                 }
             }
-            (None, Some(v)) => WeightClass::Percentage(
+            (None, Some(v)) => Some(WeightClass::Percentage(
                 WeightPercentage::try_new(v).context("Invalid `weight_percentage`")?,
-            ),
+            )),
             (Some(..), Some(..)) => {
                 return Err(RunActionError::DuplicateWeightsSpecified.into());
             }
         };
 
+        let memory = memory
+            .map(|m| m.parse::<MemoryRequirement>().context("Invalid `memory`"))
+            .transpose()?;
+
         let starlark_env = match env {
             None => Value::new_none(),
             Some(env) => {
@@ -743,11 +858,18 @@ fn register_context_actions(builder: &mut MethodsBuilder) {
             executor_preference,
             always_print_stderr,
             weight,
+            memory,
             dep_files: dep_files_configuration,
             metadata_param,
             no_outputs_cleanup,
+            incremental_state_env_var,
             allow_cache_upload,
+            allow_forced_cache_upload,
             force_full_hybrid_if_capable,
+            nondeterminism,
+            no_sandbox,
+            tty,
+            build_id_env_var,
         };
         this.state().register_action(
             artifacts.inputs,
@@ -759,7 +881,9 @@ fn register_context_actions(builder: &mut MethodsBuilder) {
     }
 
     /// Downloads a URL to an output (filename as string or output artifact).
-    /// The file at the URL must have the given sha1 or the command will fail.
+    /// The file at the URL must have the given sha1, sha256 or blake3 checksum (or all of those
+    /// given) or the command will fail.
+    /// The optional parameter `mirrors` lists URLs to try in order if downloading from `url` fails.
     /// The optional parameter is_executable indicates whether the resulting file should be marked with executable permissions.
     #[starlark(return_type = TYPE_ARTIFACT)]
     fn download_file<'v>(
@@ -768,6 +892,8 @@ fn register_context_actions(builder: &mut MethodsBuilder) {
         #[starlark(require = pos)] url: &str,
         #[starlark(require = named, default = NoneOr::None)] sha1: NoneOr<&str>,
         #[starlark(require = named, default = NoneOr::None)] sha256: NoneOr<&str>,
+        #[starlark(require = named, default = NoneOr::None)] blake3: NoneOr<&str>,
+        #[starlark(require = named, default = Vec::new())] mirrors: Vec<String>,
         #[starlark(require = named, default = false)] is_executable: bool,
         #[starlark(require = named, default = false)] is_deferrable: bool,
         eval: &mut Evaluator<'v, '_>,
@@ -776,15 +902,12 @@ fn register_context_actions(builder: &mut MethodsBuilder) {
         let (declaration, output_artifact) =
             this.get_or_declare_output(eval, output, "output", OutputType::File)?;
 
-        let checksum = match (
+        let checksum = Checksum::new(
             sha1.into_option().map(Arc::from),
             sha256.into_option().map(Arc::from),
-        ) {
-            (Some(sha1), None) => Checksum::Sha1(sha1),
-            (None, Some(sha256)) => Checksum::Sha256(sha256),
-            (Some(sha1), Some(sha256)) => Checksum::Both { sha1, sha256 },
-            (None, None) => return Err(DownloadFileError::MissingChecksum.into()),
-        };
+            blake3.into_option().map(Arc::from),
+        )
+        .ok_or(DownloadFileError::MissingChecksum)?;
 
         this.register_action(
             IndexSet::new(),
@@ -792,6 +915,7 @@ fn register_context_actions(builder: &mut MethodsBuilder) {
             UnregisteredDownloadFileAction::new(
                 checksum,
                 Arc::from(url),
+                mirrors.into_iter().map(Arc::from).collect(),
                 is_executable,
                 is_deferrable,
             ),
@@ -888,6 +1012,10 @@ fn register_context_actions(builder: &mut MethodsBuilder) {
     ///   * The inputs are used for `buck2 aquery` functionality, but do not cause speculative building. In fact, these inputs may form a cycle with other `dynamic_output` actions if they were all required.
     ///   * In the future, it may be possible to not pass all the inputs if the repo is set to permissive mode, allowing a more powerful form of dynamic dependencies.
     /// * `outputs` - a list of unbound artifacts (created with `declare_artifact`) which will be bound by the function.
+    /// * `output_groups` - if `True`, the function must return a dict from output group name to a list of artifacts.
+    ///   Each group can then be built as a sub-target of the rule (for example `//foo:bar[group]`), even though the
+    ///   group names are only known once the `dynamic` artifacts have been built. Sub-targets defined in `DefaultInfo`
+    ///   take precedence over output groups of the same name.
     /// * The function argument is given 3 arguments:
     ///   * `ctx` (context) - which is the same as that passed to the initial rule analysis.
     ///   * `outputs` - using one of the artifacts from the `dynamic_output`'s `outputs` (example usage: `outputs[artifact_from_dynamic_output_outputs]`) gives an unbounded artifact. The function argument must use its `outputs` argument to bind output artifacts, rather than reusing artifacts from the outputs passed into `dynamic_output` directly.
@@ -902,6 +1030,7 @@ fn register_context_actions(builder: &mut MethodsBuilder) {
         #[starlark(require = named)] inputs: Vec<StarlarkArtifact>,
         #[starlark(require = named)] outputs: Vec<StarlarkOutputArtifact>,
         #[starlark(require = named)] f: Value<'v>,
+        #[starlark(require = named, default = false)] output_groups: bool,
         heap: &'v Heap,
     ) -> anyhow::Result<NoneType> {
         // Parameter validation
@@ -922,9 +1051,11 @@ fn register_context_actions(builder: &mut MethodsBuilder) {
         let outputs = outputs.iter().map(|x| x.artifact()).collect();
 
         // Registration
+        // The function may read any of the attributes.
+        this.attrs_read.record_all();
         let attributes_lambda = heap.alloc((this.attributes, f));
         let mut this = this.state();
-        this.register_dynamic_output(dynamic, inputs, outputs, attributes_lambda)?;
+        this.register_dynamic_output(dynamic, inputs, outputs, output_groups, attributes_lambda)?;
         Ok(NoneType)
     }
 
@@ -1108,6 +1239,92 @@ mod tests {
         })
     }
 
+    #[test]
+    fn declare_output_with_short_path_prefix() -> anyhow::Result<()> {
+        let content = indoc!(
+            r#"
+             def test(c):
+                 out = c.actions.declare_output("out/test", "foo/bar.cpp", short_path_prefix = "lib")
+                 return (out.short_path, out.project("baz").short_path)
+             "#
+        );
+
+        run_ctx_test(content, |ret| {
+            let a = <(&str, &str)>::unpack_value(ret.unwrap()).unwrap();
+            assert_eq!("lib/foo/bar.cpp", a.0);
+            assert_eq!("lib/foo/bar.cpp/baz", a.1);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn declare_output_short_path_prefix_conflict() -> anyhow::Result<()> {
+        let content = indoc!(
+            r#"
+             def test(c):
+                 c.actions.declare_output("a", "foo.o", short_path_prefix = "lib")
+                 return c.actions.declare_output("b", "foo.o", short_path_prefix = "lib")
+             "#
+        );
+
+        let expect = "Error declaring short path `lib/foo.o`";
+        run_ctx_test(content, |ret| match ret {
+            Err(e) if e.to_string().contains(expect) => Ok(()),
+            _ => panic!(
+                "Expected a specific failure containing `{}`, got {:?}",
+                expect, ret
+            ),
+        })
+    }
+
+    #[test]
+    fn declare_output_short_path_prefix_conflicts_with_other_outputs() -> anyhow::Result<()> {
+        let declared_first = indoc!(
+            r#"
+             def test(c):
+                 c.actions.declare_output("a", "lib/foo.o")
+                 return c.actions.declare_output("b", "foo.o", short_path_prefix = "lib")
+             "#
+        );
+        let declared_last = indoc!(
+            r#"
+             def test(c):
+                 c.actions.declare_output("b", "foo.o", short_path_prefix = "lib")
+                 return c.actions.declare_output("lib")
+             "#
+        );
+
+        let expect = "Error declaring short path `lib";
+        for content in [declared_first, declared_last] {
+            run_ctx_test(content, |ret| match ret {
+                Err(e) if e.to_string().contains(expect) => Ok(()),
+                _ => panic!(
+                    "Expected a specific failure containing `{}`, got {:?}",
+                    expect, ret
+                ),
+            })?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn declare_output_same_short_path_without_prefix() -> anyhow::Result<()> {
+        let content = indoc!(
+            r#"
+             def test(c):
+                 a = c.actions.declare_output("a", "foo.o")
+                 b = c.actions.declare_output("b", "foo.o")
+                 return (a.short_path, b.short_path)
+             "#
+        );
+
+        run_ctx_test(content, |ret| {
+            let a = <(&str, &str)>::unpack_value(ret.unwrap()).unwrap();
+            assert_eq!(("foo.o", "foo.o"), a);
+            Ok(())
+        })
+    }
+
     #[test]
     fn declare_output_dot() -> anyhow::Result<()> {
         let content = indoc!(
@@ -1145,6 +1362,26 @@ mod tests {
             ),
         })
     }
+
+    #[test]
+    fn declare_output_executable_dir() -> anyhow::Result<()> {
+        let content = indoc!(
+            r#"
+             def test(c):
+                 return c.actions.declare_output("foo", dir = True, executable = True)
+             "#
+        );
+
+        let expect = "cannot be `executable` or a symlink";
+        run_ctx_test(content, |ret| match ret {
+            Err(e) if e.to_string().contains(expect) => Ok(()),
+            _ => panic!(
+                "Expected a specific failure containing `{}`, got {:?}",
+                expect, ret
+            ),
+        })
+    }
+
     #[test]
     fn declare_output_dotdot() -> anyhow::Result<()> {
         let content = indoc!(
@@ -1184,4 +1421,64 @@ mod tests {
             ),
         })
     }
+
+    #[test]
+    fn run_incremental_state_requires_local_only() -> anyhow::Result<()> {
+        let content = indoc!(
+            r#"
+             def test(c):
+                 a = c.actions.declare_output("a")
+                 c.actions.run([a.as_output()], category = "test_category", incremental_state_env_var = "STATE")
+             "#
+        );
+
+        let expect = "requires `local_only = True`";
+        run_ctx_test(content, |ret| match ret {
+            Err(e) if e.to_string().contains(expect) => Ok(()),
+            _ => panic!(
+                "Expected a specific failure containing `{}`, got {:?}",
+                expect, ret
+            ),
+        })
+    }
+
+    #[test]
+    fn run_incremental_state_with_cache_upload() -> anyhow::Result<()> {
+        let content = indoc!(
+            r#"
+             def test(c):
+                 a = c.actions.declare_output("a")
+                 c.actions.run([a.as_output()], category = "test_category", incremental_state_env_var = "STATE", local_only = True, allow_cache_upload = True)
+             "#
+        );
+
+        let expect = "incompatible with `allow_cache_upload = True`";
+        run_ctx_test(content, |ret| match ret {
+            Err(e) if e.to_string().contains(expect) => Ok(()),
+            _ => panic!(
+                "Expected a specific failure containing `{}`, got {:?}",
+                expect, ret
+            ),
+        })
+    }
+
+    #[test]
+    fn run_invalid_nondeterministic() -> anyhow::Result<()> {
+        let content = indoc!(
+            r#"
+             def test(c):
+                 a = c.actions.declare_output("a")
+                 c.actions.run([a.as_output()], category = "test_category", nondeterministic = "sometimes")
+             "#
+        );
+
+        let expect = "Invalid nondeterminism `sometimes`";
+        run_ctx_test(content, |ret| match ret {
+            Err(e) if e.to_string().contains(expect) => Ok(()),
+            _ => panic!(
+                "Expected a specific failure containing `{}`, got {:?}",
+                expect, ret
+            ),
+        })
+    }
 }
@@ -0,0 +1,310 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Compilation databases (`compile_commands.json`) for C/C++/ObjC targets, used by
+//! `buck2 cdb gen`.
+//!
+//! The prelude exposes the compilation database of a target as its `compilation-database`
+//! sub-target. This builds the sub-target of every matching target which has one, and merges
+//! the databases. Headers which are not compiled on their own get an entry copied from a source
+//! of the same target, so that tools like clangd know how to parse them.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+
+use async_trait::async_trait;
+use buck2_build_api::analysis::calculation::RuleAnalysisCalculation;
+use buck2_build_api::artifact_groups::ArtifactGroup;
+use buck2_build_api::build::materialize_artifact_group;
+use buck2_build_api::build::MaterializationContext;
+use buck2_build_api::calculation::load_patterns;
+use buck2_build_api::calculation::Calculation;
+use buck2_build_api::calculation::MissingTargetBehavior;
+use buck2_cli_proto::ClientContext;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_common::dice::cells::HasCellResolver;
+use buck2_common::result::SharedResult;
+use buck2_common::result::ToUnsharedResultExt;
+use buck2_core::fs::fs_util;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_core::provider::label::ProvidersLabel;
+use buck2_execute::artifact::artifact_dyn::ArtifactDyn;
+use buck2_query::query::compatibility::MaybeCompatible;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::pattern::parse_patterns_from_cli_args;
+use buck2_server_ctx::pattern::target_platform_from_client_context;
+use dupe::Dupe;
+use gazebo::prelude::*;
+
+use crate::AuditSubcommand;
+
+/// Name of the sub-target exposing the compilation database of a target.
+const COMPILATION_DATABASE_SUB_TARGET: &str = "compilation-database";
+
+/// Extensions of the files considered to be headers.
+const HEADER_EXTENSIONS: &[&str] = &["h", "hh", "hpp", "hxx", "h++", "inl", "ipp", "tcc"];
+
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(
+    name = "audit-compilation-database",
+    about = "Build the compilation databases of C/C++/ObjC targets and print them merged, as JSON"
+)]
+pub struct AuditCompilationDatabaseCommand {
+    #[clap(flatten)]
+    pub common_opts: CommonCommandOptions,
+
+    #[clap(name = "TARGET_PATTERNS", help = "Patterns of the targets to include")]
+    pub patterns: Vec<String>,
+}
+
+/// An entry of a `compile_commands.json`, in the format documented by clang.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CompilationDatabaseEntry {
+    pub directory: String,
+    pub file: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub arguments: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+}
+
+/// Merge compilation databases, sorted by file. Entries replace the earlier entries for the same
+/// file.
+pub fn merge_entries(
+    entries: impl IntoIterator<Item = CompilationDatabaseEntry>,
+) -> Vec<CompilationDatabaseEntry> {
+    let mut merged = BTreeMap::new();
+    for entry in entries {
+        merged.insert(entry.file.clone(), entry);
+    }
+    merged.into_values().collect()
+}
+
+/// Number of leading directories `a` and `b` have in common.
+fn common_dirs(a: &str, b: &str) -> usize {
+    let dirs = |p: &str| p.rsplit_once('/').map_or("", |(dir, _)| dir).to_owned();
+    let (a, b) = (dirs(a), dirs(b));
+    a.split('/')
+        .zip(b.split('/'))
+        .take_while(|(a, b)| !a.is_empty() && a == b)
+        .count()
+}
+
+/// Add entries for the `headers` of a target which have none, copied from the entry of the
+/// source of the target closest to each header.
+fn add_header_entries(entries: &mut Vec<CompilationDatabaseEntry>, headers: &[String]) {
+    let sources = entries.len();
+    for header in headers {
+        if entries.iter().any(|e| &e.file == header) {
+            continue;
+        }
+        // `max_by_key` returns the last of equal elements, we want the first.
+        let closest = match entries[..sources]
+            .iter()
+            .rev()
+            .max_by_key(|e| common_dirs(&e.file, header))
+        {
+            Some(closest) => closest,
+            None => return,
+        };
+        let entry = CompilationDatabaseEntry {
+            directory: closest.directory.clone(),
+            file: header.clone(),
+            arguments: closest.arguments.map(|a| {
+                if *a == closest.file {
+                    header.clone()
+                } else {
+                    a.clone()
+                }
+            }),
+            command: closest.command.clone(),
+        };
+        entries.push(entry);
+    }
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditCompilationDatabaseCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(async move |server_ctx, ctx| {
+                let cells = ctx.get_cell_resolver().await?;
+                let artifact_fs = ctx.get_artifact_fs().await?;
+                let target_platform =
+                    target_platform_from_client_context(&client_ctx, server_ctx, &ctx).await?;
+                let parsed_patterns = parse_patterns_from_cli_args::<TargetPatternExtra>(
+                    &ctx,
+                    &self
+                        .patterns
+                        .map(|pat| buck2_data::TargetPattern { value: pat.clone() }),
+                    server_ctx.working_dir(),
+                )
+                .await?;
+                let loaded_patterns =
+                    load_patterns(&ctx, parsed_patterns, MissingTargetBehavior::Fail).await?;
+                let materialization = MaterializationContext::force_materializations();
+                let project_root = artifact_fs.fs().root().to_string();
+
+                let mut nodes = Vec::new();
+                for (_package, result) in loaded_patterns.iter() {
+                    match result {
+                        Ok(res) => nodes.extend(res.values()),
+                        Err(e) => return SharedResult::unshared_error(Err(e.dupe())),
+                    }
+                }
+
+                let databases = futures::future::try_join_all(nodes.into_iter().map(|node| {
+                    let (ctx, cells, artifact_fs, materialization, target_platform) = (
+                        &ctx,
+                        &cells,
+                        &artifact_fs,
+                        &materialization,
+                        &target_platform,
+                    );
+                    async move {
+                        let label = ctx
+                            .get_configured_target(
+                                &ProvidersLabel::default_for(node.label().dupe()),
+                                target_platform.as_ref(),
+                            )
+                            .await?;
+                        let providers = match ctx.get_providers(&label).await? {
+                            MaybeCompatible::Compatible(providers) => providers,
+                            MaybeCompatible::Incompatible(_) => return anyhow::Ok(Vec::new()),
+                        };
+                        let mut outputs = Vec::new();
+                        if let Some(database) = providers
+                            .provider_collection()
+                            .default_info()
+                            .sub_targets()
+                            .get(COMPILATION_DATABASE_SUB_TARGET)
+                        {
+                            database
+                                .default_info()
+                                .for_each_default_output_artifact_only(&mut |o| {
+                                    outputs.push(o);
+                                    Ok(())
+                                })?;
+                        }
+                        if outputs.is_empty() {
+                            return Ok(Vec::new());
+                        }
+
+                        let mut entries = Vec::new();
+                        for output in outputs {
+                            materialize_artifact_group(
+                                ctx,
+                                &ArtifactGroup::Artifact(output.dupe()),
+                                materialization,
+                            )
+                            .await?;
+                            let path = artifact_fs.fs().resolve(&output.resolve_path(artifact_fs)?);
+                            let database: Vec<CompilationDatabaseEntry> =
+                                serde_json::from_str(&fs_util::read_to_string(&path)?)?;
+                            entries.extend(database);
+                        }
+                        let headers = node
+                            .inputs()
+                            .filter(|p| {
+                                p.path()
+                                    .extension()
+                                    .map_or(false, |e| HEADER_EXTENSIONS.contains(&e))
+                            })
+                            .map(|p| Ok(cells.resolve_path(p.as_ref())?.to_string()))
+                            .collect::<anyhow::Result<Vec<_>>>()?;
+                        add_header_entries(&mut entries, &headers);
+                        Ok(entries)
+                    }
+                }))
+                .await?;
+
+                // Commands run from the project root, make the database usable from anywhere.
+                let entries = merge_entries(databases.into_iter().flatten().map(|mut entry| {
+                    if entry.directory == "." {
+                        entry.directory = project_root.clone();
+                    } else if Path::new(&entry.directory).is_relative() {
+                        entry.directory = format!("{}/{}", project_root, entry.directory);
+                    }
+                    entry
+                }));
+
+                let mut stdout = stdout.as_writer();
+                writeln!(stdout, "{}", serde_json::to_string_pretty(&entries)?)?;
+                Ok(())
+            })
+            .await
+    }
+
+    fn common_opts(&self) -> &CommonCommandOptions {
+        &self.common_opts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compilation_database::add_header_entries;
+    use crate::compilation_database::merge_entries;
+    use crate::compilation_database::CompilationDatabaseEntry;
+
+    fn entry(file: &str, arguments: &[&str]) -> CompilationDatabaseEntry {
+        CompilationDatabaseEntry {
+            directory: ".".to_owned(),
+            file: file.to_owned(),
+            arguments: arguments.iter().map(|a| (*a).to_owned()).collect(),
+            command: None,
+        }
+    }
+
+    #[test]
+    fn test_add_header_entries() {
+        let mut entries = vec![
+            entry("foo/a.cpp", &["clang++", "-DA"]),
+            entry("foo/bar/b.cpp", &["clang++", "-DB", "foo/bar/b.cpp"]),
+        ];
+        let headers = vec![
+            "foo/bar/b.h".to_owned(),
+            "foo/a.h".to_owned(),
+            "other/c.h".to_owned(),
+        ];
+        add_header_entries(&mut entries, &headers);
+        assert_eq!(
+            entries[2..],
+            [
+                entry("foo/bar/b.h", &["clang++", "-DB", "foo/bar/b.h"]),
+                entry("foo/a.h", &["clang++", "-DA"]),
+                entry("other/c.h", &["clang++", "-DA"]),
+            ]
+        );
+
+        // Headers are only added once, and not without any source.
+        add_header_entries(&mut entries, &headers);
+        assert_eq!(entries.len(), 5);
+        let mut empty = Vec::new();
+        add_header_entries(&mut empty, &headers);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_merge_entries() {
+        let merged = merge_entries(vec![
+            entry("b.cpp", &["old"]),
+            entry("a.cpp", &[]),
+            entry("b.cpp", &["new"]),
+        ]);
+        assert_eq!(merged, vec![entry("a.cpp", &[]), entry("b.cpp", &["new"])]);
+    }
+}
@@ -36,6 +36,15 @@ pub struct DeferredMaterializerCommand {
 pub enum DeferredMaterializerSubcommand {
     List,
     Fsck,
+    /// Check that materialized artifacts match what is on disk (including file contents), and
+    /// report the ones that don't.
+    Verify {
+        /// Drop mismatching artifacts from the materializer state and delete them from disk, so
+        /// they are materialized again the next time they are needed. Files and symlinks in use
+        /// by the running daemon are materialized again right away.
+        #[clap(long)]
+        repair: bool,
+    },
     Refresh {
         /// Minimum TTL to require for actions.
         #[clap()]
@@ -89,6 +98,62 @@ impl AuditSubcommand for DeferredMaterializerCommand {
                 let mut stderr = server_ctx.stderr()?;
                 writeln!(&mut stderr, "total errors: {}", n)?;
             }
+            DeferredMaterializerSubcommand::Verify { repair } => {
+                let mut stream = deferred_materializer
+                    .verify(repair)
+                    .context("Failed to start verifying")?;
+
+                let mut n = 0;
+                let mut unrepaired = 0;
+                let mut active_repaired = 0;
+
+                while let Some(inconsistency) = stream.next().await {
+                    n += 1;
+                    let status = if inconsistency.rematerialized {
+                        "materialized again"
+                    } else if inconsistency.repaired {
+                        if inconsistency.active {
+                            active_repaired += 1;
+                        }
+                        "repaired"
+                    } else {
+                        unrepaired += 1;
+                        "not repaired"
+                    };
+                    writeln!(
+                        stdout,
+                        "{}\t{}\t{:#}",
+                        inconsistency.path, status, inconsistency.error
+                    )?;
+                }
+
+                let mut stderr = server_ctx.stderr()?;
+                writeln!(
+                    &mut stderr,
+                    "total inconsistencies: {}, repaired: {}",
+                    n,
+                    n - unrepaired
+                )?;
+                if active_repaired > 0 {
+                    writeln!(
+                        &mut stderr,
+                        "{} repaired artifacts are in use by the running daemon, \
+                        run `buck2 kill` so they are rebuilt or fetched again",
+                        active_repaired
+                    )?;
+                }
+                if unrepaired > 0 {
+                    return Err(anyhow::anyhow!(
+                        "Materializer state does not match disk for {} artifacts{}",
+                        unrepaired,
+                        if repair {
+                            ""
+                        } else {
+                            ", use `--repair` to fix them"
+                        }
+                    ));
+                }
+            }
             DeferredMaterializerSubcommand::Refresh { min_ttl } => {
                 deferred_materializer
                     .refresh_ttls(min_ttl)
@@ -0,0 +1,649 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Find the dependency cycles of the graph of some targets, all at once, rather than one error at
+//! a time when building.
+//!
+//! A cycle is reported for each target on a cycle: the shortest one through it, starting with its
+//! smallest label. Cycles found for several of their targets are reported once.
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::future::Future;
+use std::hash::Hash;
+use std::io::Write;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use buck2_build_api::calculation::load_patterns;
+use buck2_build_api::calculation::Calculation;
+use buck2_build_api::calculation::MissingTargetBehavior;
+use buck2_build_api::nodes::calculation::get_configured_target_attr_deps;
+use buck2_build_api::nodes::calculation::NodeCalculation;
+use buck2_cli_proto::ClientContext;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_common::result::SharedResult;
+use buck2_common::result::ToUnsharedResultExt;
+use buck2_core::buck_path::path::BuckPathRef;
+use buck2_core::configuration::transition::id::TransitionId;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_core::target::label::ConfiguredTargetLabel;
+use buck2_core::target::label::TargetLabel;
+use buck2_node::attrs::inspect_options::AttrInspectOptions;
+use buck2_node::attrs::traversal::CoercedAttrTraversal;
+use buck2_query::query::compatibility::MaybeCompatible;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::pattern::parse_patterns_from_cli_args;
+use buck2_server_ctx::pattern::target_platform_from_client_context;
+use dice::DiceComputations;
+use dupe::Dupe;
+use gazebo::prelude::*;
+
+use crate::AuditSubcommand;
+
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(
+    name = "audit-dep-cycles",
+    about = "Find the dependency cycles in the graph of the given targets, with the attributes \
+    creating each dependency"
+)]
+pub struct AuditDepCyclesCommand {
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    #[clap(
+        name = "TARGET_PATTERNS",
+        help = "Patterns of the targets to inspect the deps of"
+    )]
+    patterns: Vec<String>,
+
+    #[clap(
+        long,
+        help = "Inspect the configured graph (for the target platform), in which only the deps \
+        selected for the configuration are followed, instead of the deps of all the branches of \
+        `select()`s"
+    )]
+    configured: bool,
+
+    #[clap(long, help = "Output in JSON format")]
+    json: bool,
+}
+
+/// The deps of each target of a graph, with the attributes referencing each dep.
+type DepGraph = BTreeMap<String, BTreeMap<String, Vec<String>>>;
+
+/// A link of a cycle: `target` depends on the next target of the cycle (the first one for the
+/// last link) through `attrs`.
+#[derive(Debug, PartialEq, Eq, serde::Serialize)]
+struct DepCycleLink {
+    target: String,
+    attrs: Vec<String>,
+}
+
+/// The strongly connected components of a graph, given by the successors of each node.
+/// Iterative, since dependency chains can be deeper than the stack.
+fn strongly_connected_components(succs: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    const UNVISITED: usize = usize::MAX;
+    let mut index = vec![UNVISITED; succs.len()];
+    let mut lowlink = vec![0; succs.len()];
+    let mut on_stack = vec![false; succs.len()];
+    let mut stack = Vec::new();
+    let mut next_index = 0;
+    let mut components = Vec::new();
+
+    for root in 0..succs.len() {
+        if index[root] != UNVISITED {
+            continue;
+        }
+        // Nodes being visited, with the position of their next successor to visit.
+        let mut work = vec![(root, 0)];
+        index[root] = next_index;
+        lowlink[root] = next_index;
+        next_index += 1;
+        stack.push(root);
+        on_stack[root] = true;
+
+        while let Some(&(node, i)) = work.last() {
+            if let Some(&succ) = succs[node].get(i) {
+                work.last_mut().expect("not empty").1 += 1;
+                if index[succ] == UNVISITED {
+                    index[succ] = next_index;
+                    lowlink[succ] = next_index;
+                    next_index += 1;
+                    stack.push(succ);
+                    on_stack[succ] = true;
+                    work.push((succ, 0));
+                } else if on_stack[succ] {
+                    lowlink[node] = lowlink[node].min(index[succ]);
+                }
+                continue;
+            }
+
+            work.pop();
+            if let Some(&(parent, _)) = work.last() {
+                lowlink[parent] = lowlink[parent].min(lowlink[node]);
+            }
+            if lowlink[node] == index[node] {
+                let mut component = Vec::new();
+                loop {
+                    let member = stack.pop().expect("node is on the stack");
+                    on_stack[member] = false;
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                components.push(component);
+            }
+        }
+    }
+    components
+}
+
+/// The shortest cycle through `start`, which is in the component `component`, as the nodes of the
+/// cycle starting with `start`.
+fn shortest_cycle(
+    succs: &[Vec<usize>],
+    component_of: &[usize],
+    component: usize,
+    start: usize,
+) -> Option<Vec<usize>> {
+    let mut parents = HashMap::new();
+    let mut queue = VecDeque::from([start]);
+    while let Some(node) = queue.pop_front() {
+        for &succ in &succs[node] {
+            if succ == start {
+                let mut cycle = vec![node];
+                let mut n = node;
+                while n != start {
+                    n = parents[&n];
+                    cycle.push(n);
+                }
+                cycle.reverse();
+                return Some(cycle);
+            }
+            if component_of[succ] == component && !parents.contains_key(&succ) {
+                parents.insert(succ, node);
+                queue.push_back(succ);
+            }
+        }
+    }
+    None
+}
+
+/// The minimal cycles of `graph`: the shortest cycle through each target on a cycle, rotated to
+/// start with its smallest target, without duplicates, shortest first.
+fn minimal_cycles(graph: &DepGraph) -> Vec<Vec<DepCycleLink>> {
+    // Indices are in the order of the labels, so the smallest label is the smallest index.
+    let labels: Vec<&String> = graph.keys().collect();
+    let indices: HashMap<&String, usize> =
+        labels.iter().enumerate().map(|(i, l)| (*l, i)).collect();
+    let succs: Vec<Vec<usize>> = graph
+        .values()
+        .map(|deps| {
+            deps.keys()
+                .filter_map(|dep| indices.get(dep).copied())
+                .collect()
+        })
+        .collect();
+
+    let mut component_of = vec![0; labels.len()];
+    let components = strongly_connected_components(&succs);
+    for (i, component) in components.iter().enumerate() {
+        for &node in component {
+            component_of[node] = i;
+        }
+    }
+
+    let mut cycles = HashSet::new();
+    for (i, component) in components.iter().enumerate() {
+        if component.len() == 1 && !succs[component[0]].contains(&component[0]) {
+            continue;
+        }
+        for &node in component {
+            if let Some(mut cycle) = shortest_cycle(&succs, &component_of, i, node) {
+                let smallest = cycle.iter().enumerate().min_by_key(|(_, n)| **n);
+                cycle.rotate_left(smallest.map_or(0, |(i, _)| i));
+                cycles.insert(cycle);
+            }
+        }
+    }
+
+    let mut cycles: Vec<Vec<usize>> = cycles.into_iter().collect();
+    cycles.sort_by(|a, b| (a.len(), a).cmp(&(b.len(), b)));
+    cycles.into_map(|cycle| {
+        cycle
+            .iter()
+            .enumerate()
+            .map(|(i, &node)| {
+                let next = labels[cycle[(i + 1) % cycle.len()]];
+                DepCycleLink {
+                    target: labels[node].clone(),
+                    attrs: graph[labels[node]][next].clone(),
+                }
+            })
+            .collect()
+    })
+}
+
+/// One line per cycle, like `a -(deps)-> b -(exported_deps)-> a`.
+fn display_cycle(cycle: &[DepCycleLink]) -> String {
+    let mut line = String::new();
+    for link in cycle {
+        line.push_str(&format!("{} -({})-> ", link.target, link.attrs.join(", ")));
+    }
+    if let Some(first) = cycle.first() {
+        line.push_str(&first.target);
+    }
+    line
+}
+
+/// Follow the deps of `roots` given by `deps`, returning the graph of all the targets reached.
+async fn collect_graph<L, F, Fut>(roots: Vec<L>, deps: F) -> anyhow::Result<DepGraph>
+where
+    L: Display + Clone + Eq + Hash,
+    F: Fn(L) -> Fut,
+    Fut: Future<Output = anyhow::Result<Vec<(String, L)>>>,
+{
+    let mut graph = DepGraph::new();
+    let mut seen = HashSet::new();
+    let mut frontier: Vec<L> = roots
+        .into_iter()
+        .filter(|l| seen.insert(l.clone()))
+        .collect();
+    while !frontier.is_empty() {
+        let results =
+            futures::future::try_join_all(frontier.iter().map(|l| deps(l.clone()))).await?;
+        let mut next = Vec::new();
+        for (label, label_deps) in frontier.iter().zip(results) {
+            let node = graph.entry(label.to_string()).or_default();
+            for (attr, dep) in label_deps {
+                let attrs = node.entry(dep.to_string()).or_default();
+                if !attrs.contains(&attr) {
+                    attrs.push(attr);
+                }
+                if seen.insert(dep.clone()) {
+                    next.push(dep);
+                }
+            }
+        }
+        frontier = next;
+    }
+    Ok(graph)
+}
+
+/// Collects the deps of an unconfigured attribute, whatever their kind.
+struct CoercedDeps<'a> {
+    attr: &'a str,
+    deps: &'a mut Vec<(String, TargetLabel)>,
+}
+
+impl<'a> CoercedAttrTraversal<'a> for CoercedDeps<'_> {
+    fn dep(&mut self, dep: &'a TargetLabel) -> anyhow::Result<()> {
+        self.deps.push((self.attr.to_owned(), dep.dupe()));
+        Ok(())
+    }
+
+    fn exec_dep(&mut self, dep: &'a TargetLabel) -> anyhow::Result<()> {
+        self.dep(dep)
+    }
+
+    fn toolchain_dep(&mut self, dep: &'a TargetLabel) -> anyhow::Result<()> {
+        self.dep(dep)
+    }
+
+    fn transition_dep(
+        &mut self,
+        dep: &'a TargetLabel,
+        _tr: &Arc<TransitionId>,
+    ) -> anyhow::Result<()> {
+        self.dep(dep)
+    }
+
+    fn split_transition_dep(
+        &mut self,
+        dep: &'a TargetLabel,
+        _tr: &Arc<TransitionId>,
+    ) -> anyhow::Result<()> {
+        self.dep(dep)
+    }
+
+    fn configuration_dep(&mut self, _dep: &'a TargetLabel) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn platform_dep(&mut self, _dep: &'a TargetLabel) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn input(&mut self, _input: BuckPathRef) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+async fn unconfigured_deps(
+    ctx: &DiceComputations,
+    label: TargetLabel,
+) -> anyhow::Result<Vec<(String, TargetLabel)>> {
+    let node = ctx.get_target_node(&label).await?;
+    let mut deps = Vec::new();
+    for a in node.attrs(AttrInspectOptions::All) {
+        a.traverse(
+            node.label().pkg(),
+            &mut CoercedDeps {
+                attr: a.name,
+                deps: &mut deps,
+            },
+        )?;
+    }
+    Ok(deps)
+}
+
+async fn configured_deps(
+    ctx: &DiceComputations,
+    label: ConfiguredTargetLabel,
+) -> anyhow::Result<Vec<(String, ConfiguredTargetLabel)>> {
+    Ok(match get_configured_target_attr_deps(ctx, &label).await? {
+        MaybeCompatible::Compatible(deps) => deps,
+        MaybeCompatible::Incompatible(_) => Vec::new(),
+    })
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditDepCyclesCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(async move |server_ctx, ctx| {
+                let parsed_patterns = parse_patterns_from_cli_args::<TargetPatternExtra>(
+                    &ctx,
+                    &self
+                        .patterns
+                        .map(|pat| buck2_data::TargetPattern { value: pat.clone() }),
+                    server_ctx.working_dir(),
+                )
+                .await?;
+                let loaded_patterns =
+                    load_patterns(&ctx, parsed_patterns, MissingTargetBehavior::Fail).await?;
+                let mut roots = Vec::new();
+                for (_package, result) in loaded_patterns.iter() {
+                    match result {
+                        Ok(res) => roots.extend(res.values().map(|node| node.label().dupe())),
+                        Err(e) => return SharedResult::unshared_error(Err(e.dupe())),
+                    }
+                }
+
+                // Configured nodes can't be computed when their deps form a cycle, so the graph
+                // is followed through the configured attributes instead.
+                let graph = if self.configured {
+                    let target_platform =
+                        target_platform_from_client_context(&client_ctx, server_ctx, &ctx).await?;
+                    let roots =
+                        futures::future::try_join_all(roots.iter().map(|label| {
+                            ctx.get_configured_target(label, target_platform.as_ref())
+                        }))
+                        .await?;
+                    collect_graph(roots, |label| configured_deps(&ctx, label)).await?
+                } else {
+                    collect_graph(roots, |label| unconfigured_deps(&ctx, label)).await?
+                };
+
+                let cycles = minimal_cycles(&graph);
+                let mut stdout = stdout.as_writer();
+                if self.json {
+                    writeln!(stdout, "{}", serde_json::to_string_pretty(&cycles)?)?;
+                } else {
+                    for cycle in &cycles {
+                        writeln!(stdout, "{}", display_cycle(cycle))?;
+                    }
+                }
+                Ok(())
+            })
+            .await
+    }
+
+    fn common_opts(&self) -> &CommonCommandOptions {
+        &self.common_opts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+
+    use buck2_build_api::configuration::calculation::ConfigurationNodeKey;
+    use buck2_build_api::configuration::calculation::ExecutionPlatformsKey;
+    use buck2_common::executor_config::CommandExecutorConfig;
+    use buck2_common::result::SharedResult;
+    use buck2_core::build_file_path::BuildFilePath;
+    use buck2_core::bzl::ImportPath;
+    use buck2_core::configuration::config_setting::ConfigSettingData;
+    use buck2_core::configuration::data::ConfigurationData;
+    use buck2_core::configuration::data::ConfigurationDataData;
+    use buck2_core::fs::paths::file_name::FileNameBuf;
+    use buck2_core::provider::label::ProvidersLabel;
+    use buck2_core::provider::label::ProvidersName;
+    use buck2_core::target::label::ConfiguredTargetLabel;
+    use buck2_core::target::label::TargetLabel;
+    use buck2_execute::execute::dice_data::set_fallback_executor_config;
+    use buck2_interpreter_for_build::interpreter::calculation::testing::InterpreterResultsKey;
+    use buck2_node::attrs::attr::Attribute;
+    use buck2_node::attrs::attr_type::list::ListLiteral;
+    use buck2_node::attrs::attr_type::AttrType;
+    use buck2_node::attrs::coerced_attr::CoercedAttr;
+    use buck2_node::attrs::coerced_attr::CoercedSelector;
+    use buck2_node::configuration::resolved::ConfigurationNode;
+    use buck2_node::nodes::eval_result::EvaluationResult;
+    use buck2_node::nodes::targets_map::TargetsMap;
+    use buck2_node::nodes::unconfigured::testing::TargetNodeExt;
+    use buck2_node::nodes::unconfigured::TargetNode;
+    use buck2_node::provider_id_set::ProviderIdSet;
+    use buck2_node::rule_type::RuleType;
+    use buck2_node::rule_type::StarlarkRuleType;
+    use buck2_util::arc_str::ArcSlice;
+    use dice::testing::DiceBuilder;
+    use dice::DiceComputations;
+    use dice::UserComputationData;
+    use dupe::Dupe;
+
+    use crate::dep_cycles::collect_graph;
+    use crate::dep_cycles::configured_deps;
+    use crate::dep_cycles::display_cycle;
+    use crate::dep_cycles::minimal_cycles;
+    use crate::dep_cycles::DepGraph;
+
+    fn graph(edges: &[(&str, &str, &str)]) -> DepGraph {
+        let mut graph = DepGraph::new();
+        for (target, attr, dep) in edges {
+            graph.entry((*dep).to_owned()).or_default();
+            graph
+                .entry((*target).to_owned())
+                .or_default()
+                .entry((*dep).to_owned())
+                .or_default()
+                .push((*attr).to_owned());
+        }
+        graph
+    }
+
+    fn cycles(graph: &DepGraph) -> Vec<String> {
+        minimal_cycles(graph)
+            .iter()
+            .map(|c| display_cycle(c))
+            .collect()
+    }
+
+    #[test]
+    fn test_no_cycles() {
+        let graph = graph(&[("a", "deps", "b"), ("a", "deps", "c"), ("b", "deps", "c")]);
+        assert!(cycles(&graph).is_empty());
+    }
+
+    #[test]
+    fn test_minimal_cycles() {
+        let graph = graph(&[
+            // A cycle with a shortcut: `f` and `g` are only on the long one.
+            ("d", "deps", "e"),
+            ("e", "deps", "f"),
+            ("f", "deps", "g"),
+            ("g", "deps", "d"),
+            ("e", "exported_deps", "d"),
+            ("e", "deps", "d"),
+            // A self-dep.
+            ("x", "exec_deps", "x"),
+            // Reported from its smallest target, whichever target it is found from.
+            ("c", "deps", "b"),
+            ("b", "deps", "c"),
+        ]);
+        assert_eq!(
+            cycles(&graph),
+            vec![
+                "x -(exec_deps)-> x",
+                "b -(deps)-> c -(deps)-> b",
+                "d -(deps)-> e -(exported_deps, deps)-> d",
+                "d -(deps)-> e -(deps)-> f -(deps)-> g -(deps)-> d",
+            ]
+        );
+    }
+
+    fn deps(deps: &[&TargetLabel]) -> CoercedAttr {
+        CoercedAttr::List(ListLiteral(ArcSlice::from_iter(deps.iter().map(|dep| {
+            CoercedAttr::Dep(ProvidersLabel::new((*dep).dupe(), ProvidersName::Default))
+        }))))
+    }
+
+    /// The node of the config setting `setting` in the configuration `cfg`, for the targets of the
+    /// same cell.
+    fn config_setting(
+        cfg: &ConfigurationData,
+        setting: &TargetLabel,
+        matches: bool,
+    ) -> (ConfigurationNodeKey, SharedResult<ConfigurationNode>) {
+        (
+            ConfigurationNodeKey {
+                target_cfg: cfg.dupe(),
+                target_cell: setting.pkg().cell_name(),
+                cfg_target: setting.dupe(),
+            },
+            Ok(ConfigurationNode::new(
+                cfg.dupe(),
+                setting.dupe(),
+                ConfigSettingData {
+                    constraints: BTreeMap::new(),
+                    buckconfigs: BTreeMap::new(),
+                },
+                matches,
+            )),
+        )
+    }
+
+    async fn configured_cycles(
+        ctx: &DiceComputations,
+        root: ConfiguredTargetLabel,
+    ) -> anyhow::Result<Vec<String>> {
+        let graph = collect_graph(vec![root], |label| configured_deps(ctx, label)).await?;
+        Ok(cycles(&graph))
+    }
+
+    #[tokio::test]
+    async fn test_configured_cycle_in_select_branch() -> anyhow::Result<()> {
+        let a = TargetLabel::testing_parse("root//pkg:a");
+        let b = TargetLabel::testing_parse("root//pkg:b");
+        let linux = TargetLabel::testing_parse("root//:linux");
+        let pkg = a.pkg();
+
+        let rule_type = RuleType::Starlark(Arc::new(StarlarkRuleType {
+            import_path: ImportPath::testing_new("root//:defs.bzl"),
+            name: "some_rule".to_owned(),
+        }));
+        let deps_attr = || {
+            Attribute::new(
+                None,
+                "",
+                AttrType::list(AttrType::dep(ProviderIdSet::EMPTY)),
+            )
+        };
+        // `a` only depends on `b` on linux, and `b` always depends on `a`.
+        let node_a = TargetNode::testing_new(
+            a.dupe(),
+            rule_type.dupe(),
+            vec![(
+                "deps",
+                deps_attr(),
+                CoercedAttr::Selector(Box::new(CoercedSelector::new(
+                    ArcSlice::from_iter([(linux.dupe(), deps(&[&b]))]),
+                    Some(deps(&[])),
+                )?)),
+            )],
+        );
+        let node_b = TargetNode::testing_new(
+            b.dupe(),
+            rule_type.dupe(),
+            vec![("deps", deps_attr(), deps(&[&a]))],
+        );
+        let eval_result = EvaluationResult::new(
+            Arc::new(BuildFilePath::new(
+                pkg.dupe(),
+                FileNameBuf::unchecked_new("BUCK"),
+            )),
+            Vec::new(),
+            TargetsMap::from_iter([node_a, node_b]),
+        );
+
+        let platform = |name: &str| {
+            ConfigurationData::from_platform(
+                name.to_owned(),
+                ConfigurationDataData {
+                    constraints: BTreeMap::new(),
+                },
+            )
+        };
+        let linux_cfg = platform("linux")?;
+        let mac_cfg = platform("mac")?;
+        let (linux_key, linux_node) = config_setting(&linux_cfg, &linux, true);
+        let (mac_key, mac_node) = config_setting(&mac_cfg, &linux, false);
+
+        let mut data = UserComputationData::new();
+        set_fallback_executor_config(&mut data.data, CommandExecutorConfig::testing_local());
+        let ctx = DiceBuilder::new()
+            .mock_and_return(InterpreterResultsKey(pkg.dupe()), Ok(Arc::new(eval_result)))
+            .mock_and_return(ExecutionPlatformsKey, Ok(None))
+            .mock_and_return(linux_key, linux_node)
+            .mock_and_return(mac_key, mac_node)
+            .build(data)?
+            .commit()
+            .await;
+
+        let a_linux = a.configure(linux_cfg.dupe());
+        let b_linux = b.configure(linux_cfg.dupe());
+        assert_eq!(
+            configured_cycles(&ctx, a_linux.dupe()).await?,
+            vec![format!(
+                "{} -(deps)-> {} -(deps)-> {}",
+                a_linux, b_linux, a_linux
+            )]
+        );
+        assert!(
+            configured_cycles(&ctx, a.configure(mac_cfg.dupe()))
+                .await?
+                .is_empty()
+        );
+        Ok(())
+    }
+}
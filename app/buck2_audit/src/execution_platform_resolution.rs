@@ -13,6 +13,9 @@ use async_trait::async_trait;
 use buck2_build_api::calculation::load_patterns;
 use buck2_build_api::calculation::Calculation;
 use buck2_build_api::calculation::MissingTargetBehavior;
+use buck2_build_api::configuration::calculation::ConfigurationCalculation;
+use buck2_build_api::configuration::ExecutionPlatformFallback;
+use buck2_build_api::configuration::ExecutionPlatforms;
 use buck2_build_api::nodes::calculation::NodeCalculation;
 use buck2_cli_proto::ClientContext;
 use buck2_client_ctx::common::CommonCommandOptions;
@@ -22,6 +25,8 @@ use buck2_core::pattern::pattern_type::ConfigurationPredicate;
 use buck2_core::pattern::pattern_type::ConfiguredTargetPatternExtra;
 use buck2_core::pattern::ParsedPattern;
 use buck2_core::target::label::TargetLabel;
+use buck2_node::configuration::execution::ExecutionPlatform;
+use buck2_node::configuration::execution::ExecutionPlatformResolution;
 use buck2_server_ctx::ctx::ServerCommandContextTrait;
 use buck2_server_ctx::ctx::ServerCommandDiceContext;
 use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
@@ -52,6 +57,84 @@ pub struct AuditExecutionPlatformResolutionCommand {
 
     #[clap(name = "TARGET_PATTERNS", help = "Patterns to analyze")]
     patterns: Vec<String>,
+
+    #[clap(
+        long,
+        help = "Also list the registered execution platforms in resolution order, and for each target, which of them were skipped, selected or not considered"
+    )]
+    platforms: bool,
+}
+
+fn write_platform(mut stdout: impl Write, platform: &ExecutionPlatform) -> anyhow::Result<()> {
+    writeln!(stdout, "{}", platform.id())?;
+    writeln!(stdout, "  Configuration: {}", platform.cfg())?;
+    writeln!(
+        stdout,
+        "  Executor: {}",
+        platform.executor_config().executor
+    )?;
+    Ok(())
+}
+
+/// Print the registered execution platforms in resolution order, and the fallback.
+fn write_platforms(
+    mut stdout: impl Write,
+    platforms: Option<&ExecutionPlatforms>,
+) -> anyhow::Result<()> {
+    let platforms = match platforms {
+        Some(platforms) => platforms,
+        None => {
+            writeln!(
+                stdout,
+                "No execution platforms registered (`build.execution_platforms` is not set), \
+                using the legacy execution platform"
+            )?;
+            return Ok(());
+        }
+    };
+    writeln!(stdout, "Execution platforms (in resolution order):")?;
+    for platform in platforms.candidates() {
+        write_platform(IndentWriter::new("  ", &mut stdout), platform)?;
+    }
+    match platforms.fallback() {
+        ExecutionPlatformFallback::Error => writeln!(stdout, "Fallback: error")?,
+        ExecutionPlatformFallback::UseUnspecifiedExec => {
+            writeln!(stdout, "Fallback: unspecified execution platform")?
+        }
+        ExecutionPlatformFallback::Platform(platform) => {
+            write!(stdout, "Fallback: ")?;
+            write_platform(&mut stdout, platform)?;
+        }
+    }
+    Ok(())
+}
+
+/// Print each registered platform in resolution order, with whether it was skipped, selected,
+/// or not considered because a higher priority platform was selected.
+fn write_resolution_order(
+    mut stdout: impl Write,
+    resolution: &ExecutionPlatformResolution,
+    candidates: &[ExecutionPlatform],
+) -> anyhow::Result<()> {
+    let selected = resolution.platform().ok().map(|p| p.id());
+    writeln!(stdout, "Resolution order:")?;
+    for (i, candidate) in candidates.iter().enumerate() {
+        let id = candidate.id();
+        let status = if resolution.skipped().iter().any(|(s, _)| *s == id) {
+            "skipped"
+        } else if selected.as_ref() == Some(&id) {
+            "selected"
+        } else {
+            "not considered"
+        };
+        writeln!(stdout, "  {}. {}: {}", i + 1, id, status)?;
+    }
+    if let Some(selected) = selected {
+        if !candidates.iter().any(|c| c.id() == selected) {
+            writeln!(stdout, "  Fallback: {}", selected)?;
+        }
+    }
+    Ok(())
 }
 
 #[async_trait]
@@ -69,6 +152,19 @@ impl AuditSubcommand for AuditExecutionPlatformResolutionCommand {
                     server_ctx.working_dir(),
                 ).await?;
 
+                let mut stdout = stdout.as_writer();
+
+                let candidates: Vec<ExecutionPlatform> = if self.platforms {
+                    let platforms = ctx.get_execution_platforms().await?;
+                    write_platforms(&mut stdout, platforms.as_ref())?;
+                    match &platforms {
+                        Some(platforms) => platforms.candidates().cloned().collect(),
+                        None => Vec::new(),
+                    }
+                } else {
+                    Vec::new()
+                };
+
                 let mut configured_patterns = Vec::new();
                 let mut target_patterns = Vec::new();
                 for pat in self.patterns.iter() {
@@ -107,8 +203,6 @@ impl AuditSubcommand for AuditExecutionPlatformResolutionCommand {
                     }
                 }
 
-                let mut stdout = stdout.as_writer();
-
                 for configured_target in configured_patterns {
                     let configured_node = ctx.get_configured_target_node(&configured_target).await?;
                     let configured_node = configured_node.require_compatible()?;
@@ -133,6 +227,9 @@ impl AuditSubcommand for AuditExecutionPlatformResolutionCommand {
                         }
                         Err(e) => writeln!(stdout, "{}", e)?,
                     }
+                    if self.platforms {
+                        write_resolution_order(IndentWriter::new("  ", &mut stdout), resolution, &candidates)?;
+                    }
                 }
 
                 Ok(())
@@ -0,0 +1,402 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::io::Write;
+
+use async_trait::async_trait;
+use buck2_build_api::calculation::load_patterns;
+use buck2_build_api::calculation::Calculation;
+use buck2_build_api::calculation::MissingTargetBehavior;
+use buck2_build_api::nodes::calculation::NodeCalculation;
+use buck2_cli_proto::ClientContext;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_common::executor_config::Executor;
+use buck2_common::executor_config::RemoteEnabledExecutor;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_node::configuration::execution::ExecutionPlatform;
+use buck2_query::query::compatibility::MaybeCompatible;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::pattern::parse_patterns_from_cli_args;
+use buck2_server_ctx::pattern::target_platform_from_client_context;
+use gazebo::prelude::*;
+use indent_write::io::IndentWriter;
+
+use crate::AuditSubcommand;
+
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(
+    name = "audit-host-compatibility",
+    about = "Explain whether the execution platforms selected for targets can run their actions on this host, or require remote execution"
+)]
+pub struct AuditHostCompatibilityCommand {
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    #[clap(name = "TARGET_PATTERNS", help = "Patterns of targets to check")]
+    patterns: Vec<String>,
+}
+
+/// Constraint value names which identify an operating system, and the names the host OS goes by.
+const OS_NAMES: &[(&str, &[&str])] = &[
+    ("linux", &["linux"]),
+    ("macos", &["macos", "darwin", "osx"]),
+    ("windows", &["windows"]),
+    ("freebsd", &["freebsd"]),
+];
+
+/// Constraint value names which identify a CPU architecture, and the names the host arch goes by.
+const ARCH_NAMES: &[(&str, &[&str])] = &[
+    ("x86_64", &["x86_64", "x86-64", "amd64"]),
+    ("aarch64", &["aarch64", "arm64"]),
+    ("arm", &["arm", "arm32", "armv7"]),
+    ("x86", &["x86", "x86_32", "i386", "i686"]),
+];
+
+/// Which part of the host a constraint value of an execution platform disagrees with.
+#[derive(Debug, PartialEq)]
+enum HostMismatch {
+    Os,
+    Arch,
+}
+
+/// Guess whether a constraint value (e.g. `config//os:macos`) describes a different operating
+/// system or architecture than the host's. This is a heuristic based on the name of the constraint
+/// value: constraint values with names we don't recognize never mismatch.
+fn host_mismatch(value_name: &str, host_os: &str, host_arch: &str) -> Option<HostMismatch> {
+    let value_name = value_name.to_lowercase();
+    let mismatches = |known: &[(&str, &[&str])], host: &str| {
+        let is_known = known
+            .iter()
+            .any(|(_, names)| names.contains(&value_name.as_str()));
+        let is_host = known
+            .iter()
+            .find(|(name, _)| *name == host)
+            .map_or(false, |(_, names)| names.contains(&value_name.as_str()));
+        is_known && !is_host
+    };
+    if mismatches(OS_NAMES, host_os) {
+        Some(HostMismatch::Os)
+    } else if mismatches(ARCH_NAMES, host_arch) {
+        Some(HostMismatch::Arch)
+    } else {
+        None
+    }
+}
+
+/// Describe where actions using this execution platform's executor can run.
+fn executor_summary(platform: &ExecutionPlatform) -> &'static str {
+    match &platform.executor_config().executor {
+        Executor::Local => "local only",
+        Executor::RemoteEnabled { executor, .. } => match executor {
+            RemoteEnabledExecutor::Local => "local only (with remote cache)",
+            RemoteEnabledExecutor::Remote(_) => "remote only (requires remote execution)",
+            RemoteEnabledExecutor::Hybrid { .. } => "local or remote (hybrid)",
+        },
+    }
+}
+
+fn runs_locally(platform: &ExecutionPlatform) -> bool {
+    match &platform.executor_config().executor {
+        Executor::Local => true,
+        Executor::RemoteEnabled { executor, .. } => {
+            !matches!(executor, RemoteEnabledExecutor::Remote(_))
+        }
+    }
+}
+
+const GIB: u64 = 1024 * 1024 * 1024;
+
+/// Names of remote execution properties giving the number of CPUs the actions of a platform need.
+const CPU_PROPERTIES: &[&str] = &["cpus", "cpu_count", "num_cpus"];
+
+/// Names of remote execution properties giving the memory the actions of a platform need.
+const MEMORY_PROPERTIES: &[&str] = &["memory", "mem", "memory_gb"];
+
+/// The CPUs and memory of a machine, or the ones needed by the actions of an execution platform.
+#[derive(Debug, Default, PartialEq)]
+struct Resources {
+    cpus: Option<u64>,
+    memory_bytes: Option<u64>,
+}
+
+impl Resources {
+    fn host() -> Resources {
+        use sysinfo::RefreshKind;
+        use sysinfo::System;
+        use sysinfo::SystemExt;
+
+        let system = System::new_with_specifics(RefreshKind::new().with_memory());
+        Resources {
+            cpus: Some(num_cpus::get() as u64),
+            memory_bytes: Some(system.total_memory()),
+        }
+    }
+
+    /// The resources required by an execution platform, from the properties it sends to remote
+    /// execution. Like constraint values, these are recognized by name, and properties with
+    /// values we can't parse are ignored.
+    fn required(platform: &ExecutionPlatform) -> Resources {
+        let properties = match &platform.executor_config().executor {
+            Executor::Local => return Resources::default(),
+            Executor::RemoteEnabled { re_properties, .. } => re_properties,
+        };
+        let find = |names: &[&str], parse: fn(&str) -> Option<u64>| {
+            properties
+                .iter()
+                .find(|(k, _)| names.contains(&k.to_lowercase().as_str()))
+                .and_then(|(_, v)| parse(v))
+        };
+        Resources {
+            cpus: find(CPU_PROPERTIES, |v| v.trim().parse().ok()),
+            memory_bytes: find(MEMORY_PROPERTIES, parse_memory),
+        }
+    }
+
+    /// What `required` asks for that `self` doesn't have.
+    fn missing(&self, required: &Resources) -> Vec<String> {
+        let mut missing = Vec::new();
+        if let (Some(required), Some(host)) = (required.cpus, self.cpus) {
+            if required > host {
+                missing.push(format!("{} CPUs (host has {})", required, host));
+            }
+        }
+        if let (Some(required), Some(host)) = (required.memory_bytes, self.memory_bytes) {
+            if required > host {
+                missing.push(format!(
+                    "{} GiB memory (host has {} GiB)",
+                    required / GIB,
+                    host / GIB
+                ));
+            }
+        }
+        missing
+    }
+}
+
+/// Parse an amount of memory such as `16`, `16GB` or `512MiB`. Numbers without a unit are GiB.
+fn parse_memory(value: &str) -> Option<u64> {
+    let value = value.trim().to_lowercase();
+    let digits = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(digits);
+    let number: u64 = number.parse().ok()?;
+    let unit = match unit.trim() {
+        "" | "g" | "gb" | "gib" => GIB,
+        "m" | "mb" | "mib" => 1024 * 1024,
+        "t" | "tb" | "tib" => 1024 * GIB,
+        _ => return None,
+    };
+    number.checked_mul(unit)
+}
+
+fn write_host(mut stdout: impl Write, host: &Resources) -> anyhow::Result<()> {
+    writeln!(
+        stdout,
+        "Host: {} {}, {} CPUs, {} GiB memory",
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        host.cpus.unwrap_or_default(),
+        host.memory_bytes.unwrap_or_default() / GIB,
+    )?;
+    Ok(())
+}
+
+fn write_platform_compatibility(
+    mut stdout: impl Write,
+    platform: &ExecutionPlatform,
+    host: &Resources,
+) -> anyhow::Result<()> {
+    writeln!(stdout, "Execution platform: {}", platform.id())?;
+    writeln!(stdout, "  Executor: {}", executor_summary(platform))?;
+
+    let mut mismatches = Vec::new();
+    if let Ok(data) = platform.cfg().data() {
+        for value in data.constraints.values() {
+            if let Some(mismatch) = host_mismatch(
+                value.0.name().as_str(),
+                std::env::consts::OS,
+                std::env::consts::ARCH,
+            ) {
+                mismatches.push((value, mismatch));
+            }
+        }
+    }
+
+    let missing = host.missing(&Resources::required(platform));
+
+    if !runs_locally(platform) {
+        writeln!(
+            stdout,
+            "  Host compatible: no, all actions on this platform require remote execution"
+        )?;
+    } else if mismatches.is_empty() && missing.is_empty() {
+        writeln!(stdout, "  Host compatible: yes")?;
+    } else if mismatches.is_empty() {
+        writeln!(
+            stdout,
+            "  Host compatible: no, the platform requires more resources than the host has"
+        )?;
+    } else {
+        writeln!(
+            stdout,
+            "  Host compatible: no, the platform's constraints describe a different machine"
+        )?;
+    }
+    for (value, mismatch) in mismatches {
+        let host = match mismatch {
+            HostMismatch::Os => std::env::consts::OS,
+            HostMismatch::Arch => std::env::consts::ARCH,
+        };
+        writeln!(stdout, "    {} (host is {})", value, host)?;
+    }
+    for missing in missing {
+        writeln!(stdout, "    requires {}", missing)?;
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditHostCompatibilityCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(async move |server_ctx, ctx| {
+                let mut stdout = stdout.as_writer();
+                let host = Resources::host();
+                write_host(&mut stdout, &host)?;
+
+                let parsed_patterns = parse_patterns_from_cli_args::<TargetPatternExtra>(
+                    &ctx,
+                    &self
+                        .patterns
+                        .map(|pat| buck2_data::TargetPattern { value: pat.clone() }),
+                    server_ctx.working_dir(),
+                )
+                .await?;
+                let loaded_patterns =
+                    load_patterns(&ctx, parsed_patterns, MissingTargetBehavior::Fail).await?;
+                let target_platform =
+                    target_platform_from_client_context(&client_ctx, server_ctx, &ctx).await?;
+
+                for (_, targets) in loaded_patterns.into_iter() {
+                    for (_, node) in targets? {
+                        let label = ctx
+                            .get_configured_target(node.label(), target_platform.as_ref())
+                            .await?;
+                        writeln!(stdout)?;
+                        writeln!(stdout, "{}:", label)?;
+                        let node = match ctx.get_configured_target_node(&label).await? {
+                            MaybeCompatible::Incompatible(reason) => {
+                                writeln!(stdout, "  Incompatible: {}", reason)?;
+                                continue;
+                            }
+                            MaybeCompatible::Compatible(node) => node,
+                        };
+                        let resolution = node.execution_platform_resolution();
+                        match resolution.platform() {
+                            Ok(platform) => write_platform_compatibility(
+                                IndentWriter::new("  ", &mut stdout),
+                                platform,
+                                &host,
+                            )?,
+                            Err(e) => writeln!(IndentWriter::new("  ", &mut stdout), "{:#}", e)?,
+                        }
+                        for (id, reason) in resolution.skipped() {
+                            writeln!(stdout, "  Skipped execution platform {}:", id)?;
+                            writeln!(IndentWriter::new("    ", &mut stdout), "{:#}", reason)?;
+                        }
+                    }
+                }
+
+                Ok(())
+            })
+            .await
+    }
+
+    fn common_opts(&self) -> &CommonCommandOptions {
+        &self.common_opts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::host_compatibility::host_mismatch;
+    use crate::host_compatibility::parse_memory;
+    use crate::host_compatibility::HostMismatch;
+    use crate::host_compatibility::Resources;
+    use crate::host_compatibility::GIB;
+
+    #[test]
+    fn test_host_mismatch() {
+        assert_eq!(host_mismatch("linux", "linux", "x86_64"), None);
+        assert_eq!(host_mismatch("macos", "macos", "aarch64"), None);
+        assert_eq!(host_mismatch("darwin", "macos", "aarch64"), None);
+        assert_eq!(host_mismatch("arm64", "macos", "aarch64"), None);
+        assert_eq!(
+            host_mismatch("macos", "linux", "x86_64"),
+            Some(HostMismatch::Os)
+        );
+        assert_eq!(
+            host_mismatch("aarch64", "linux", "x86_64"),
+            Some(HostMismatch::Arch)
+        );
+        assert_eq!(host_mismatch("some_value", "linux", "x86_64"), None);
+    }
+
+    #[test]
+    fn test_parse_memory() {
+        assert_eq!(parse_memory("16"), Some(16 * GIB));
+        assert_eq!(parse_memory("16GB"), Some(16 * GIB));
+        assert_eq!(parse_memory(" 2 GiB "), Some(2 * GIB));
+        assert_eq!(parse_memory("512mb"), Some(512 * 1024 * 1024));
+        assert_eq!(parse_memory("1T"), Some(1024 * GIB));
+        assert_eq!(parse_memory("lots"), None);
+        assert_eq!(parse_memory("16 bytes"), None);
+    }
+
+    #[test]
+    fn test_missing_resources() {
+        let host = Resources {
+            cpus: Some(8),
+            memory_bytes: Some(16 * GIB),
+        };
+        assert!(host.missing(&Resources::default()).is_empty());
+        assert!(
+            host.missing(&Resources {
+                cpus: Some(8),
+                memory_bytes: Some(16 * GIB),
+            })
+            .is_empty()
+        );
+        assert_eq!(
+            host.missing(&Resources {
+                cpus: Some(32),
+                memory_bytes: Some(64 * GIB),
+            }),
+            vec![
+                "32 CPUs (host has 8)".to_owned(),
+                "64 GiB memory (host has 16 GiB)".to_owned()
+            ]
+        );
+        assert_eq!(
+            host.missing(&Resources {
+                cpus: None,
+                memory_bytes: Some(64 * GIB),
+            }),
+            vec!["64 GiB memory (host has 16 GiB)".to_owned()]
+        );
+    }
+}
@@ -0,0 +1,164 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use async_trait::async_trait;
+use buck2_cli_proto::ClientContext;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_common::dice::cells::HasCellResolver;
+use buck2_core::bzl::ImportPath;
+use buck2_core::cells::build_file_cell::BuildFileCell;
+use buck2_interpreter::import_paths::HasImportPaths;
+use buck2_interpreter::path::StarlarkModulePath;
+use buck2_interpreter_for_build::interpreter::dice_calculation_delegate::HasCalculationDelegate;
+use buck2_interpreter_for_build::interpreter::global_interpreter_state::HasGlobalInterpreterState;
+use buck2_interpreter_for_build::interpreter::interpreter_for_cell::check_prelude_extension;
+use buck2_interpreter_for_build::interpreter::interpreter_for_cell::public_symbols;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use dice::DiceComputations;
+use dupe::Dupe;
+use indexmap::IndexMap;
+use starlark::environment::FrozenModule;
+
+use crate::AuditSubcommand;
+
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(
+    name = "audit-implicit-symbols",
+    about = "List the symbols implicitly available in the build files of cells: the symbols of \
+    the prelude, of the `buildfile.prelude_extensions` and of the `buildfile.includes` of the \
+    cell, and where each symbol comes from. Package includes are not listed."
+)]
+pub struct AuditImplicitSymbolsCommand {
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    #[clap(long = "json", help = "Output in JSON format")]
+    json: bool,
+
+    #[clap(
+        name = "CELL_ALIASES",
+        help = "Cells to list the symbols of, resolved in the working directory cell. Defaults to the working directory cell."
+    )]
+    aliases: Vec<String>,
+}
+
+async fn eval_import(ctx: &DiceComputations, import: &ImportPath) -> anyhow::Result<FrozenModule> {
+    Ok(ctx
+        .get_interpreter_calculator(import.cell(), import.build_file_cell())
+        .await?
+        .eval_module(StarlarkModulePath::LoadFile(import))
+        .await?
+        .env()
+        .dupe())
+}
+
+/// The symbols available in the build files of a cell, and where they come from, in the order in
+/// which they are added to the build files: later sources shadow earlier ones.
+async fn implicit_symbols(
+    ctx: &DiceComputations,
+    cell: BuildFileCell,
+) -> anyhow::Result<BTreeMap<String, String>> {
+    let mut symbols = BTreeMap::new();
+    let global_interpreter_state = ctx.get_global_interpreter_state().await?;
+    let import_paths = ctx.import_paths_for_cell(cell).await?;
+
+    let prelude = match global_interpreter_state.configuror.prelude_import() {
+        Some(prelude_import) => {
+            let prelude = eval_import(ctx, prelude_import).await?;
+            for symbol in public_symbols(&prelude) {
+                symbols.insert(symbol.to_owned(), prelude_import.to_string());
+            }
+            if let Some(native) = prelude.get_option("native")? {
+                for symbol in native.value().dir_attr() {
+                    symbols.insert(symbol, format!("{} (native)", prelude_import));
+                }
+            }
+            Some((prelude_import, prelude))
+        }
+        None => None,
+    };
+
+    for extension_import in &import_paths.prelude_extensions {
+        let extension = eval_import(ctx, extension_import).await?;
+        if let Some((prelude_import, prelude)) = &prelude {
+            check_prelude_extension(prelude_import, prelude, extension_import, &extension)?;
+        }
+        for symbol in public_symbols(&extension) {
+            symbols.insert(symbol.to_owned(), extension_import.to_string());
+        }
+    }
+
+    if let Some(root_import) = import_paths.root_import() {
+        let root = eval_import(ctx, root_import).await?;
+        for symbol in public_symbols(&root) {
+            symbols.insert(symbol.to_owned(), root_import.to_string());
+        }
+    }
+
+    Ok(symbols)
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditImplicitSymbolsCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        _client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(async move |server_ctx, ctx| {
+                let cells = ctx.get_cell_resolver().await?;
+                let this_cell = cells.find(server_ctx.working_dir())?;
+                let cell_names = if self.aliases.is_empty() {
+                    vec![this_cell]
+                } else {
+                    let cell_alias_resolver = cells.get(this_cell)?.cell_alias_resolver();
+                    self.aliases
+                        .iter()
+                        .map(|alias| cell_alias_resolver.resolve(alias))
+                        .collect::<anyhow::Result<_>>()?
+                };
+
+                let mut symbols_by_cell = IndexMap::new();
+                for cell_name in cell_names {
+                    let symbols = implicit_symbols(&ctx, BuildFileCell::new(cell_name)).await?;
+                    symbols_by_cell.insert(cell_name.as_str().to_owned(), symbols);
+                }
+
+                let mut stdout = stdout.as_writer();
+                if self.json {
+                    writeln!(
+                        stdout,
+                        "{}",
+                        serde_json::to_string_pretty(&symbols_by_cell)?
+                    )?;
+                } else {
+                    for (cell_name, symbols) in symbols_by_cell {
+                        writeln!(stdout, "{}:", cell_name)?;
+                        for (symbol, source) in symbols {
+                            writeln!(stdout, "  {}: {}", symbol, source)?;
+                        }
+                    }
+                }
+
+                Ok(())
+            })
+            .await
+    }
+
+    fn common_opts(&self) -> &CommonCommandOptions {
+        &self.common_opts
+    }
+}
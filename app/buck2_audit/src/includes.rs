@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use std::fmt;
 use std::io::Write;
 
 use async_trait::async_trait;
@@ -27,6 +28,8 @@ use buck2_core::package::PackageLabel;
 use buck2_interpreter::file_loader::LoadedModule;
 use buck2_interpreter::path::StarlarkModulePath;
 use buck2_interpreter_for_build::interpreter::calculation::InterpreterCalculation;
+use buck2_interpreter_for_build::interpreter::load_digests::HasLoadDigests;
+use buck2_interpreter_for_build::interpreter::load_digests::LoadDigest;
 use buck2_node::nodes::eval_result::EvaluationResult;
 use buck2_query::query::environment::LabeledNode;
 use buck2_query::query::environment::NodeLabel;
@@ -74,6 +77,11 @@ pub struct AuditIncludesCommand {
     #[clap(long)]
     json: bool,
 
+    /// Also print the digest of the content of each included file, and whether it changed
+    /// between the last two times this daemon loaded it (`new`, `changed` or `unchanged`)
+    #[clap(long)]
+    digests: bool,
+
     #[clap(
         name = "BUILD_FILES",
         help = "Build files to audit. These are expected to be relative paths from the working dir cell."
@@ -189,6 +197,51 @@ async fn load_and_collect_includes(
     Ok(get_transitive_includes(ctx, &load_result).await?)
 }
 
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Include {
+    Path(AbsNormPathBuf),
+    WithDigest {
+        path: AbsNormPathBuf,
+        /// Unset if the file was not loaded by this daemon.
+        digest: Option<String>,
+        change: Option<String>,
+    },
+}
+
+impl Include {
+    /// `digest` is only reported with `--digests`.
+    fn new(path: AbsNormPathBuf, with_digest: bool, digest: Option<LoadDigest>) -> Self {
+        if !with_digest {
+            return Include::Path(path);
+        }
+        Include::WithDigest {
+            path,
+            digest: digest.map(|d| d.digest.to_string()),
+            change: digest.map(|d| d.change.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Include {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Include::Path(path) => write!(f, "{}", path),
+            Include::WithDigest {
+                path,
+                digest,
+                change,
+            } => write!(
+                f,
+                "{}\t{}\t{}",
+                path,
+                digest.as_deref().unwrap_or("-"),
+                change.as_deref().unwrap_or("-"),
+            ),
+        }
+    }
+}
+
 fn resolve_path(
     cells: &CellResolver,
     fs: &ProjectRoot,
@@ -245,19 +298,20 @@ impl AuditSubcommand for AuditIncludesCommand {
                     .collect();
 
                 let results: Vec<(_, SharedResult<Vec<_>>)> = futures.collect().await;
+                let load_digests = ctx.per_transaction_data().get_load_digests();
                 // This is expected to not return any errors, and so we're not careful about not propagating it.
-                let to_absolute_path = move |include: ImportPath| -> anyhow::Result<_> {
-                    let include = include.path();
-                    let cell = cells.get(include.cell())?;
-                    let path = cell.path().join(include.path());
-                    Ok(fs.resolve(&path))
+                let to_include = move |include: ImportPath| -> anyhow::Result<_> {
+                    let cell_path = include.path();
+                    let cell = cells.get(cell_path.cell())?;
+                    let path = fs.resolve(&cell.path().join(cell_path.path()));
+                    let digest = load_digests.and_then(|digests| digests.get(&include));
+                    Ok(Include::new(path, self.digests, digest))
                 };
-                let absolutize_paths =
-                    |paths: Vec<ImportPath>| -> SharedResult<Vec<AbsNormPathBuf>> {
-                        Ok(paths.into_try_map(&to_absolute_path)?)
-                    };
-                let results: Vec<(String, SharedResult<Vec<AbsNormPathBuf>>)> = results
-                    .into_map(|(path, includes)| (path, includes.and_then(absolutize_paths)));
+                let to_includes = |paths: Vec<ImportPath>| -> SharedResult<Vec<Include>> {
+                    Ok(paths.into_try_map(&to_include)?)
+                };
+                let results: Vec<(String, SharedResult<Vec<Include>>)> =
+                    results.into_map(|(path, includes)| (path, includes.and_then(to_includes)));
 
                 let mut stdout = stdout.as_writer();
 
@@ -297,6 +351,8 @@ impl AuditSubcommand for AuditIncludesCommand {
                                 writeln!(stdout, "# {}\n", path)?;
                                 for include in includes {
                                     // To match buck1, we print absolute paths.
+                                    // With `--digests`, they are followed by the digest and
+                                    // whether the file changed, separated by tabs.
                                     writeln!(stdout, "{}", include)?;
                                 }
                             }
@@ -323,3 +379,62 @@ impl AuditSubcommand for AuditIncludesCommand {
         &self.common_opts
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+    use buck2_interpreter::file_loader::ModuleDigestBuilder;
+    use buck2_interpreter_for_build::interpreter::load_digests::LoadChange;
+    use buck2_interpreter_for_build::interpreter::load_digests::LoadDigest;
+
+    use crate::includes::Include;
+
+    #[test]
+    fn test_include_output() -> anyhow::Result<()> {
+        let path = if cfg!(windows) {
+            "C:\\repo\\foo\\defs.bzl"
+        } else {
+            "/repo/foo/defs.bzl"
+        };
+        let path = || AbsNormPathBuf::from(path.to_owned()).unwrap();
+        let digest = ModuleDigestBuilder::new("a = 1").finish();
+        let loaded = LoadDigest {
+            digest,
+            change: LoadChange::Changed,
+        };
+
+        let include = Include::new(path(), false, Some(loaded));
+        assert_eq!(include.to_string(), path().to_string());
+        assert_eq!(
+            serde_json::to_value(&include)?,
+            serde_json::json!(path().to_string())
+        );
+
+        let include = Include::new(path(), true, Some(loaded));
+        assert_eq!(
+            include.to_string(),
+            format!("{}\t{}\tchanged", path(), digest)
+        );
+        assert_eq!(
+            serde_json::to_value(&include)?,
+            serde_json::json!({
+                "path": path().to_string(),
+                "digest": digest.to_string(),
+                "change": "changed",
+            })
+        );
+
+        // Files this daemon did not load have no digest.
+        let include = Include::new(path(), true, None);
+        assert_eq!(include.to_string(), format!("{}\t-\t-", path()));
+        assert_eq!(
+            serde_json::to_value(&include)?,
+            serde_json::json!({
+                "path": path().to_string(),
+                "digest": null,
+                "change": null,
+            })
+        );
+        Ok(())
+    }
+}
@@ -29,32 +29,52 @@ use classpath::AuditClasspathCommand;
 
 use crate::analysis_queries::AuditAnalysisQueriesCommand;
 use crate::cell::AuditCellCommand;
+use crate::compilation_database::AuditCompilationDatabaseCommand;
 use crate::config::AuditConfigCommand;
 use crate::configurations::AuditConfigurationsCommand;
 use crate::deferred_materializer::DeferredMaterializerCommand;
+use crate::dep_cycles::AuditDepCyclesCommand;
 use crate::dep_files::AuditDepFilesCommand;
 use crate::execution_platform_resolution::AuditExecutionPlatformResolutionCommand;
+use crate::host_compatibility::AuditHostCompatibilityCommand;
+use crate::implicit_symbols::AuditImplicitSymbolsCommand;
 use crate::includes::AuditIncludesCommand;
+use crate::nondeterministic_actions::AuditNondeterministicActionsCommand;
 use crate::output::command::AuditOutputCommand;
+use crate::output_ttls::AuditOutputTtlsCommand;
+use crate::package_boundary_violations::AuditPackageBoundaryViolationsCommand;
+use crate::package_value_schemas::AuditPackageValueSchemasCommand;
 use crate::prelude::AuditPreludeCommand;
 use crate::providers::AuditProvidersCommand;
+use crate::rule_usage::AuditRuleUsageCommand;
 use crate::starlark::StarlarkCommand;
+use crate::transitive_sets::AuditTransitiveSetsCommand;
 use crate::visibility::AuditVisibilityCommand;
 
 mod analysis_queries;
 mod cell;
 mod classpath;
+pub mod compilation_database;
 mod config;
 mod configurations;
 pub mod deferred_materializer;
+mod dep_cycles;
 mod dep_files;
 mod execution_platform_resolution;
+mod host_compatibility;
+mod implicit_symbols;
 mod includes;
+mod nondeterministic_actions;
 pub mod output;
+mod output_ttls;
+mod package_boundary_violations;
+mod package_value_schemas;
 mod prelude;
 mod providers;
+mod rule_usage;
 pub mod server;
 mod starlark;
+mod transitive_sets;
 mod visibility;
 
 #[derive(Debug, clap::Subcommand, serde::Serialize, serde::Deserialize)]
@@ -75,6 +95,16 @@ pub enum AuditCommand {
     DepFiles(AuditDepFilesCommand),
     DeferredMaterializer(DeferredMaterializerCommand),
     Output(AuditOutputCommand),
+    PackageValueSchemas(AuditPackageValueSchemasCommand),
+    NondeterministicActions(AuditNondeterministicActionsCommand),
+    ImplicitSymbols(AuditImplicitSymbolsCommand),
+    RuleUsage(AuditRuleUsageCommand),
+    HostCompatibility(AuditHostCompatibilityCommand),
+    OutputTtls(AuditOutputTtlsCommand),
+    PackageBoundaryViolations(AuditPackageBoundaryViolationsCommand),
+    CompilationDatabase(AuditCompilationDatabaseCommand),
+    DepCycles(AuditDepCyclesCommand),
+    TransitiveSets(AuditTransitiveSetsCommand),
 }
 
 /// `buck2 audit` subcommands have a somewhat unique approach to make it really easy to
@@ -124,6 +154,16 @@ impl AuditCommand {
             AuditCommand::DeferredMaterializer(cmd) => cmd,
             AuditCommand::Visibility(cmd) => cmd,
             AuditCommand::Output(cmd) => cmd,
+            AuditCommand::PackageValueSchemas(cmd) => cmd,
+            AuditCommand::NondeterministicActions(cmd) => cmd,
+            AuditCommand::ImplicitSymbols(cmd) => cmd,
+            AuditCommand::RuleUsage(cmd) => cmd,
+            AuditCommand::HostCompatibility(cmd) => cmd,
+            AuditCommand::OutputTtls(cmd) => cmd,
+            AuditCommand::PackageBoundaryViolations(cmd) => cmd,
+            AuditCommand::CompilationDatabase(cmd) => cmd,
+            AuditCommand::DepCycles(cmd) => cmd,
+            AuditCommand::TransitiveSets(cmd) => cmd,
         }
     }
 }
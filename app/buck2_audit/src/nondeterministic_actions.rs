@@ -0,0 +1,151 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::any;
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::io::Write;
+
+use async_trait::async_trait;
+use buck2_build_api::actions::artifact::provide_outputs::ProvideOutputs;
+use buck2_build_api::actions::calculation::ActionCalculation;
+use buck2_build_api::analysis::calculation::RuleAnalysisCalculation;
+use buck2_build_api::calculation::load_patterns;
+use buck2_build_api::calculation::Calculation;
+use buck2_build_api::calculation::MissingTargetBehavior;
+use buck2_build_api::nodes::calculation::NodeCalculation;
+use buck2_cli_proto::ClientContext;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_execute::execute::request::Nondeterminism;
+use buck2_query::query::compatibility::MaybeCompatible;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::pattern::parse_patterns_from_cli_args;
+use buck2_server_ctx::pattern::target_platform_from_client_context;
+use dupe::Dupe;
+use gazebo::prelude::*;
+
+use crate::AuditSubcommand;
+
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(
+    name = "audit-nondeterministic-actions",
+    about = "Count the actions which rules mark as nondeterministic, per rule type"
+)]
+pub struct AuditNondeterministicActionsCommand {
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    #[clap(
+        name = "TARGET_PATTERNS",
+        help = "Patterns of targets whose actions to count",
+        required = true
+    )]
+    patterns: Vec<String>,
+}
+
+#[derive(Default)]
+struct ActionCounts {
+    total: u64,
+    no_verify: u64,
+    prefer_cached: u64,
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditNondeterministicActionsCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(async move |server_ctx, ctx| {
+                let parsed_patterns = parse_patterns_from_cli_args::<TargetPatternExtra>(
+                    &ctx,
+                    &self
+                        .patterns
+                        .map(|pat| buck2_data::TargetPattern { value: pat.clone() }),
+                    server_ctx.working_dir(),
+                )
+                .await?;
+                let loaded_patterns =
+                    load_patterns(&ctx, parsed_patterns, MissingTargetBehavior::Fail).await?;
+                let target_platform =
+                    target_platform_from_client_context(&client_ctx, server_ctx, &ctx).await?;
+
+                let mut counts: BTreeMap<String, ActionCounts> = BTreeMap::new();
+
+                for (_, targets) in loaded_patterns.into_iter() {
+                    for (_, node) in targets? {
+                        let label = ctx
+                            .get_configured_target(node.label(), target_platform.as_ref())
+                            .await?;
+                        let node = match ctx.get_configured_target_node(&label).await? {
+                            MaybeCompatible::Incompatible(_) => continue,
+                            MaybeCompatible::Compatible(node) => node,
+                        };
+                        let analysis = ctx
+                            .get_analysis_result(&label)
+                            .await?
+                            .require_compatible()?;
+
+                        // Actions with several outputs appear once per output.
+                        let mut action_keys = HashSet::new();
+                        for entry in analysis.iter_deferreds() {
+                            if let Some(outputs) =
+                                any::request_value::<ProvideOutputs>(entry.as_complex())
+                            {
+                                for output in outputs.0? {
+                                    action_keys.insert(output.key().dupe());
+                                }
+                            }
+                        }
+
+                        let rule_counts = counts.entry(node.rule_type().to_string()).or_default();
+                        for key in action_keys {
+                            let action = ActionCalculation::get_action(&ctx, &key).await?;
+                            rule_counts.total += 1;
+                            match action.nondeterminism() {
+                                Nondeterminism::Deterministic => {}
+                                Nondeterminism::NoVerify => rule_counts.no_verify += 1,
+                                Nondeterminism::PreferCached => rule_counts.prefer_cached += 1,
+                            }
+                        }
+                    }
+                }
+
+                let mut stdout = stdout.as_writer();
+                writeln!(
+                    stdout,
+                    "rule type\tnondeterministic\tno_verify\tprefer_cached\ttotal actions"
+                )?;
+                for (rule_type, counts) in counts {
+                    writeln!(
+                        stdout,
+                        "{}\t{}\t{}\t{}\t{}",
+                        rule_type,
+                        counts.no_verify + counts.prefer_cached,
+                        counts.no_verify,
+                        counts.prefer_cached,
+                        counts.total
+                    )?;
+                }
+
+                Ok(())
+            })
+            .await
+    }
+
+    fn common_opts(&self) -> &CommonCommandOptions {
+        &self.common_opts
+    }
+}
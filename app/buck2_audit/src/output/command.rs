@@ -20,6 +20,7 @@ use buck2_build_api::analysis::AnalysisResult;
 use buck2_build_api::audit_output::AuditOutputResult;
 use buck2_build_api::audit_output::AUDIT_OUTPUT;
 use buck2_build_api::calculation::Calculation;
+use buck2_build_api::context::HasBuildContextData;
 use buck2_build_api::query::aquery::environment::ActionQueryNode;
 use buck2_build_api::query::aquery::evaluator::get_dice_aquery_delegate;
 use buck2_build_api::query::dice::aquery::DiceAqueryDelegate;
@@ -28,7 +29,12 @@ use buck2_cli_proto::QueryOutputFormat;
 use buck2_client_ctx::common::CommonCommandOptions;
 use buck2_client_ctx::query_args::CommonAttributeArgs;
 use buck2_common::dice::cells::HasCellResolver;
+use buck2_common::pattern::package_roots::find_package_roots_stream;
+use buck2_core::cells::cell_path::CellPath;
+use buck2_core::cells::name::CellName;
+use buck2_core::cells::paths::CellRelativePath;
 use buck2_core::cells::CellResolver;
+use buck2_core::fs::buck_out_path::BuckOutPathScheme;
 use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
 use buck2_core::target::label::TargetLabel;
@@ -42,6 +48,7 @@ use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
 use buck2_server_ctx::pattern::target_platform_from_client_context;
 use ctor::ctor;
 use dice::DiceComputations;
+use futures::StreamExt;
 use thiserror::Error;
 use tracing::debug;
 
@@ -55,6 +62,8 @@ pub(crate) enum AuditOutputError {
         "BXL, anonymous target, test, and tmp artifacts are not supported for audit output. Only rule output artifacts are supported. Path: `{0}`"
     )]
     UnsupportedPathType(String),
+    #[error("No package in cell `{0}` has the shortened output path `{1}`")]
+    UnknownShortPackagePath(CellName, String),
 }
 
 #[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
@@ -98,7 +107,7 @@ fn check_output_path<'v>(
 }
 
 async fn write_output(
-    stdout: &mut impl Write,
+    stdout: &mut (impl Write + Send),
     action: ActionQueryNode,
     json: bool,
     output_attributes: &[String],
@@ -156,6 +165,34 @@ async fn find_matching_action<'v>(
     Ok(None)
 }
 
+/// Replace the directory standing for a long package path (see `BuckOutPathScheme`) with the
+/// package path, found by listing the packages of the cell.
+async fn expand_short_package_path(
+    output_path: &str,
+    scheme: &BuckOutPathScheme,
+    dice_ctx: &DiceComputations,
+) -> anyhow::Result<String> {
+    // `buck-out/<isolation_prefix>/<prefix>/<cell_name>/<cfg_hash>/<package>/...`
+    let mut parts: Vec<&str> = output_path.split('/').collect();
+    let short = match parts.get(5) {
+        Some(part) if scheme.is_short_package_path(part) => *part,
+        _ => return Ok(output_path.to_owned()),
+    };
+    let cell = CellName::unchecked_new(parts[3])?;
+
+    let cell_root = CellPath::new(cell, CellRelativePath::empty().to_owned());
+    let mut packages = find_package_roots_stream(dice_ctx, vec![cell_root]);
+    while let Some(package) = packages.next().await {
+        let package = package?;
+        let package_path = package.cell_relative_path().as_str();
+        if scheme.short_package_path(package_path).as_deref() == Some(short) {
+            parts[5] = package_path;
+            return Ok(parts.join("/"));
+        }
+    }
+    Err(AuditOutputError::UnknownShortPackagePath(cell, short.to_owned()).into())
+}
+
 async fn audit_output<'v>(
     output_path: &'v str,
     working_dir: &'v ProjectRelativePath,
@@ -163,8 +200,11 @@ async fn audit_output<'v>(
     dice_ctx: &'v DiceComputations,
     global_target_platform: Option<TargetLabel>,
 ) -> anyhow::Result<Option<AuditOutputResult>> {
+    let scheme = dice_ctx.get_buck_out_path_scheme().await?;
+    let output_path = expand_short_package_path(output_path, &scheme, dice_ctx).await?;
+
     let buck_out_parser = BuckOutPathParser::new(cell_resolver);
-    let parsed = buck_out_parser.parse(output_path)?;
+    let parsed = buck_out_parser.parse(&output_path)?;
 
     let (target_label, config_hash, path_after_target_name) = match parsed {
         BuckOutPathType::RuleOutput {
@@ -175,7 +215,7 @@ async fn audit_output<'v>(
         } => (target_label, config_hash, path_after_target_name),
         _ => {
             return Err(anyhow::anyhow!(AuditOutputError::UnsupportedPathType(
-                output_path.to_owned()
+                output_path
             )));
         }
     };
@@ -184,9 +224,21 @@ async fn audit_output<'v>(
         .get_configured_target(&target_label, global_target_platform.as_ref())
         .await?;
 
-    let command_config = configured_target_label.cfg();
-    let command_config_hash = command_config.output_hash();
-    if command_config_hash.as_str() != config_hash {
+    // The configuration directory is computed as in `BaseDeferredKeyDyn::make_hashed_path`.
+    let command_config_hash = configured_target_label.cfg().output_hash().as_str();
+    let command_exec_config_hash = configured_target_label
+        .exec_cfg()
+        .as_ref()
+        .map(|x| x.output_hash().as_str());
+    let command_config_dir =
+        match scheme.short_config_hash(command_config_hash, command_exec_config_hash) {
+            Some(short_config_hash) => short_config_hash,
+            None => match command_exec_config_hash {
+                Some(exec_config_hash) => format!("{}-{}", command_config_hash, exec_config_hash),
+                None => command_config_hash.to_owned(),
+            },
+        };
+    if command_config_dir != config_hash {
         return Ok(Some(AuditOutputResult::MaybeRelevant(target_label)));
     }
 
@@ -0,0 +1,339 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::HashMap;
+use std::io::Write;
+
+use async_trait::async_trait;
+use buck2_build_api::analysis::calculation::RuleAnalysisCalculation;
+use buck2_build_api::artifact_groups::ArtifactGroup;
+use buck2_build_api::calculation::load_patterns;
+use buck2_build_api::calculation::Calculation;
+use buck2_build_api::calculation::MissingTargetBehavior;
+use buck2_build_api::nodes::calculation::NodeCalculation;
+use buck2_cli_proto::ClientContext;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_common::executor_config::Executor;
+use buck2_common::executor_config::RemoteExecutorUseCase;
+use buck2_core::directory::unordered_entry_walk;
+use buck2_core::directory::DirectoryEntry;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_execute::digest::CasDigestToReExt;
+use buck2_execute::directory::ActionDirectoryEntry;
+use buck2_execute::directory::ActionDirectoryMember;
+use buck2_execute::directory::ActionSharedDirectory;
+use buck2_execute::execute::dice_data::GetReClient;
+use buck2_execute::materialize::materializer::HasMaterializer;
+use buck2_execute::re::client::reports_digest_ttls;
+use buck2_execute::re::manager::ManagedRemoteExecutionClient;
+use buck2_query::query::compatibility::MaybeCompatible;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::pattern::parse_patterns_from_cli_args;
+use buck2_server_ctx::pattern::target_platform_from_client_context;
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::Utc;
+use gazebo::prelude::*;
+use remote_execution::NamedDigest;
+use remote_execution::TDigest;
+
+use crate::AuditSubcommand;
+
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(
+    name = "audit-output-ttls",
+    about = "Show when the outputs of targets expire from the remote cache, and optionally extend their TTLs"
+)]
+pub struct AuditOutputTtlsCommand {
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    #[clap(
+        name = "TARGET_PATTERNS",
+        help = "Patterns of targets whose default outputs to check. The outputs are those of the last build of the targets in this daemon; nothing is built.",
+        required = true
+    )]
+    patterns: Vec<String>,
+
+    #[clap(
+        long,
+        value_name = "SECONDS",
+        help = "Upload outputs which expire sooner than this many seconds from now again, even if the remote cache has them. This extends their TTL on remote caches which refresh it when a blob is uploaded. Outputs which are not available locally are materialized first. If the remote cache doesn't report TTLs, all the outputs are uploaded."
+    )]
+    extend: Option<i64>,
+}
+
+/// We need to pick *a number* to not send an unbounded amount of digests at once.
+const EXPIRATIONS_CHUNK_SIZE: usize = 500;
+
+#[derive(Debug, thiserror::Error)]
+enum AuditOutputTtlsError {
+    #[error("The outputs of the last build are only known to the deferred materializer")]
+    NoDeferredMaterializer,
+}
+
+/// An output artifact, and the files it consists of.
+struct Output {
+    path: ProjectRelativePathBuf,
+    /// The path and digest of each file.
+    files: Vec<(String, TDigest)>,
+}
+
+impl Output {
+    fn new(
+        path: ProjectRelativePathBuf,
+        entry: &ActionDirectoryEntry<ActionSharedDirectory>,
+    ) -> anyhow::Result<Self> {
+        let mut files = Vec::new();
+        let mut walk = unordered_entry_walk(entry.as_ref());
+        while let Some((entry_path, entry)) = walk.next() {
+            if let DirectoryEntry::Leaf(ActionDirectoryMember::File(m)) = entry {
+                let name = path.join_normalized(entry_path.get())?.to_string();
+                files.push((name, m.digest.to_re()));
+            }
+        }
+        Ok(Output { path, files })
+    }
+
+    /// When the first of the files of this output expires. `None` if the output has no files
+    /// (e.g. it is a symlink).
+    fn expires(
+        &self,
+        expirations: &HashMap<TDigest, DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        // Digests the remote cache doesn't report on are treated as already expired.
+        self.files
+            .iter()
+            .map(|(_, digest)| expirations.get(digest).copied().unwrap_or(now))
+            .min()
+    }
+}
+
+/// `ttls_known` is false when the remote cache only reports whether the files exist.
+fn describe_expiration(
+    expires: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+    ttls_known: bool,
+) -> String {
+    match expires {
+        None => "no files".to_owned(),
+        Some(expires) if expires <= now => "not in the remote cache".to_owned(),
+        Some(_) if !ttls_known => "in the remote cache, expires unknown".to_owned(),
+        Some(expires) => format!(
+            "expires {} (in {}s)",
+            expires.to_rfc3339(),
+            (expires - now).num_seconds()
+        ),
+    }
+}
+
+async fn get_expirations(
+    re_client: &ManagedRemoteExecutionClient,
+    outputs: &[Output],
+    use_case: RemoteExecutorUseCase,
+) -> anyhow::Result<HashMap<TDigest, DateTime<Utc>>> {
+    let digests: Vec<TDigest> = outputs
+        .iter()
+        .flat_map(|o| o.files.iter().map(|(_, digest)| digest.clone()))
+        .collect();
+    let mut expirations = HashMap::with_capacity(digests.len());
+    for chunk in digests.chunks(EXPIRATIONS_CHUNK_SIZE) {
+        expirations.extend(
+            re_client
+                .get_digest_expirations(chunk.to_vec(), use_case)
+                .await?,
+        );
+    }
+    Ok(expirations)
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditOutputTtlsCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(async move |server_ctx, ctx| {
+                let mut stdout = stdout.as_writer();
+
+                let parsed_patterns = parse_patterns_from_cli_args::<TargetPatternExtra>(
+                    &ctx,
+                    &self
+                        .patterns
+                        .map(|pat| buck2_data::TargetPattern { value: pat.clone() }),
+                    server_ctx.working_dir(),
+                )
+                .await?;
+                let loaded_patterns =
+                    load_patterns(&ctx, parsed_patterns, MissingTargetBehavior::Fail).await?;
+                let target_platform =
+                    target_platform_from_client_context(&client_ctx, server_ctx, &ctx).await?;
+                let artifact_fs = ctx.get_artifact_fs().await?;
+                let re_client = ctx.per_transaction_data().get_re_client();
+                let materializer = ctx.per_transaction_data().get_materializer();
+                let deferred_materializer = materializer
+                    .as_deferred_materializer_extension()
+                    .ok_or(AuditOutputTtlsError::NoDeferredMaterializer)?;
+                let ttls_known = reports_digest_ttls();
+
+                for (_, targets) in loaded_patterns.into_iter() {
+                    for (_, node) in targets? {
+                        let label = ctx
+                            .get_configured_target(node.label(), target_platform.as_ref())
+                            .await?;
+                        let node = match ctx.get_configured_target_node(&label).await? {
+                            MaybeCompatible::Incompatible(_) => continue,
+                            MaybeCompatible::Compatible(node) => node,
+                        };
+                        let platform = node.execution_platform_resolution().platform()?;
+                        let use_case = match &platform.executor_config().executor {
+                            Executor::RemoteEnabled { re_use_case, .. } => *re_use_case,
+                            Executor::Local => {
+                                writeln!(
+                                    stdout,
+                                    "{}\t\tskipped: the execution platform does not use remote execution",
+                                    label
+                                )?;
+                                continue;
+                            }
+                        };
+
+                        let analysis = ctx.get_analysis_result(&label).await?.require_compatible()?;
+                        let mut groups = Vec::new();
+                        analysis
+                            .providers()
+                            .provider_collection()
+                            .default_info()
+                            .for_each_output(&mut |group| {
+                                groups.push(group);
+                                Ok(())
+                            })?;
+
+                        // Outputs are looked up in the materializer rather than built, so that
+                        // this reports on what the last build produced.
+                        let mut paths = Vec::new();
+                        for group in groups {
+                            if let ArtifactGroup::Artifact(artifact) = group {
+                                if !artifact.is_source() {
+                                    paths.push(artifact.get_path().resolve(&artifact_fs)?);
+                                }
+                            }
+                        }
+                        let entries = deferred_materializer
+                            .get_artifact_entries(paths.clone())
+                            .await?;
+                        let mut outputs = Vec::new();
+                        for (path, entry) in paths.into_iter().zip(entries) {
+                            match entry {
+                                Some(entry) => outputs.push(Output::new(path, &entry)?),
+                                None => writeln!(stdout, "{}\t{}\tnot built", label, path)?,
+                            }
+                        }
+
+                        let mut expirations =
+                            get_expirations(&re_client, &outputs, use_case).await?;
+                        let mut now = Utc::now();
+                        let mut extend_errors = HashMap::new();
+
+                        if let Some(extend) = self.extend {
+                            let deadline = now + Duration::seconds(extend);
+                            for output in &outputs {
+                                let expires = match output.expires(&expirations, now) {
+                                    Some(expires) => expires,
+                                    None => continue,
+                                };
+                                if ttls_known && expires >= deadline {
+                                    continue;
+                                }
+                                let res: anyhow::Result<()> = try {
+                                    materializer
+                                        .ensure_materialized(vec![output.path.clone()])
+                                        .await?;
+                                    let files = output.files.map(|(name, digest)| NamedDigest {
+                                        name: name.clone(),
+                                        digest: digest.clone(),
+                                        ..Default::default()
+                                    });
+                                    re_client.reupload_files(files, use_case).await?;
+                                };
+                                if let Err(e) = res {
+                                    extend_errors.insert(output.path.clone(), e);
+                                }
+                            }
+                            expirations = get_expirations(&re_client, &outputs, use_case).await?;
+                            now = Utc::now();
+                        }
+
+                        for output in &outputs {
+                            let status = describe_expiration(
+                                output.expires(&expirations, now),
+                                now,
+                                ttls_known,
+                            );
+                            match extend_errors.get(&output.path) {
+                                Some(e) => writeln!(
+                                    stdout,
+                                    "{}\t{}\t{}, failed to extend: {:#}",
+                                    label, output.path, status, e
+                                )?,
+                                None => writeln!(stdout, "{}\t{}\t{}", label, output.path, status)?,
+                            }
+                        }
+                    }
+                }
+
+                Ok(())
+            })
+            .await
+    }
+
+    fn common_opts(&self) -> &CommonCommandOptions {
+        &self.common_opts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use chrono::TimeZone;
+    use chrono::Utc;
+
+    use crate::output_ttls::describe_expiration;
+
+    #[test]
+    fn test_describe_expiration() {
+        let now = Utc.timestamp_opt(1_000_000, 0).unwrap();
+        assert_eq!(describe_expiration(None, now, true), "no files");
+        assert_eq!(
+            describe_expiration(Some(now), now, true),
+            "not in the remote cache"
+        );
+        assert_eq!(
+            describe_expiration(Some(now + Duration::seconds(60)), now, true),
+            "expires 1970-01-12T13:47:40+00:00 (in 60s)"
+        );
+
+        // A backend which doesn't report TTLs still tells which files are missing.
+        assert_eq!(
+            describe_expiration(Some(now), now, false),
+            "not in the remote cache"
+        );
+        assert_eq!(
+            describe_expiration(Some(now + Duration::seconds(60)), now, false),
+            "in the remote cache, expires unknown"
+        );
+    }
+}
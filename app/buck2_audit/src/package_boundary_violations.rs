@@ -0,0 +1,314 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::io::Write;
+
+use async_trait::async_trait;
+use buck2_build_api::calculation::load_patterns;
+use buck2_build_api::calculation::MissingTargetBehavior;
+use buck2_cli_proto::ClientContext;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_common::dice::cells::HasCellResolver;
+use buck2_common::package_boundary::HasPackageBoundaryExceptions;
+use buck2_common::package_listing::dice::HasPackageListingResolver;
+use buck2_common::package_listing::resolver::PackageListingResolver;
+use buck2_common::result::SharedResult;
+use buck2_common::result::ToUnsharedResultExt;
+use buck2_core::cells::cell_path::CellPath;
+use buck2_core::cells::paths::CellRelativePath;
+use buck2_core::package::package_relative_path::PackageRelativePath;
+use buck2_core::package::PackageLabel;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_core::pattern::ParsedPattern;
+use buck2_core::target::label::TargetLabel;
+use buck2_interpreter_for_build::interpreter::calculation::InterpreterCalculation;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::pattern::parse_patterns_from_cli_args;
+use dupe::Dupe;
+use gazebo::prelude::*;
+
+use crate::AuditSubcommand;
+
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(
+    name = "audit-package-boundary-violations",
+    about = "List the sources and `glob` patterns of targets which belong to another package, along with a suggested fix"
+)]
+pub struct AuditPackageBoundaryViolationsCommand {
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    #[clap(
+        name = "TARGET_PATTERNS",
+        help = "Patterns of targets to inspect. Every target in every cell is inspected if none are given."
+    )]
+    patterns: Vec<String>,
+
+    #[clap(long, help = "Output in JSON format")]
+    json: bool,
+}
+
+/// A source of a target which is a file of another package. Such sources are only accepted in
+/// packages covered by `project.package_boundary_exceptions`.
+#[derive(Debug, PartialEq, Eq, serde::Serialize)]
+struct PackageBoundaryViolation {
+    target: String,
+    /// The source, as written in the target (relative to its package).
+    src: String,
+    /// The package owning the source.
+    owner: String,
+    /// Whether the package of the target is covered by `project.package_boundary_exceptions`.
+    exception: bool,
+    /// Call to add to the build file of `owner` to make the source available to other packages.
+    export_file: String,
+    /// Label to use instead of `src` in the target once the file is exported.
+    replacement: String,
+}
+
+impl PackageBoundaryViolation {
+    fn new(
+        target: &TargetLabel,
+        src: &PackageRelativePath,
+        owner: &PackageLabel,
+        exception: bool,
+    ) -> anyhow::Result<Self> {
+        let path = target.pkg().as_cell_path().join(src);
+        let name = path.as_ref().strip_prefix(owner.as_cell_path())?;
+        Ok(Self {
+            target: target.to_string(),
+            src: src.to_string(),
+            owner: owner.to_string(),
+            exception,
+            export_file: format!("export_file(name = \"{}\")", name),
+            replacement: format!("{}:{}", owner, name),
+        })
+    }
+}
+
+/// A `glob` pattern of a build file which can only match files of another package. Such patterns
+/// never match anything.
+#[derive(Debug, PartialEq, Eq, serde::Serialize)]
+struct GlobPackageBoundaryViolation {
+    package: String,
+    /// The pattern, as written in the build file (relative to its package).
+    pattern: String,
+    /// The package owning the files the pattern is meant to match.
+    owner: String,
+    /// Whether the package is covered by `project.package_boundary_exceptions`.
+    exception: bool,
+    /// The same pattern relative to `owner`, to be globbed (and exported) there instead.
+    owner_pattern: String,
+}
+
+impl GlobPackageBoundaryViolation {
+    fn new(
+        package: &PackageLabel,
+        pattern: &str,
+        owner: &PackageLabel,
+        exception: bool,
+    ) -> anyhow::Result<Self> {
+        let subpackage = owner
+            .as_cell_path()
+            .strip_prefix(package.as_cell_path())?
+            .to_string();
+        let owner_pattern = pattern
+            .strip_prefix(&subpackage)
+            .and_then(|p| p.strip_prefix('/'))
+            .unwrap_or(pattern);
+        Ok(Self {
+            package: package.to_string(),
+            pattern: pattern.to_owned(),
+            owner: owner.to_string(),
+            exception,
+            owner_pattern: owner_pattern.to_owned(),
+        })
+    }
+}
+
+#[derive(Debug, Default, serde::Serialize)]
+struct PackageBoundaryViolations {
+    sources: Vec<PackageBoundaryViolation>,
+    globs: Vec<GlobPackageBoundaryViolation>,
+}
+
+const EXCEPTION_NOTE: &str = " (allowed by project.package_boundary_exceptions)";
+
+#[async_trait]
+impl AuditSubcommand for AuditPackageBoundaryViolationsCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        _client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(async move |server_ctx, ctx| {
+                let mut parsed_patterns = parse_patterns_from_cli_args::<TargetPatternExtra>(
+                    &ctx,
+                    &self
+                        .patterns
+                        .map(|pat| buck2_data::TargetPattern { value: pat.clone() }),
+                    server_ctx.working_dir(),
+                )
+                .await?;
+                if parsed_patterns.is_empty() {
+                    let cells = ctx.get_cell_resolver().await?;
+                    parsed_patterns.extend(cells.cells().map(|(name, _)| {
+                        ParsedPattern::Recursive(CellPath::new(
+                            name,
+                            CellRelativePath::empty().to_owned(),
+                        ))
+                    }));
+                }
+
+                let loaded_patterns =
+                    load_patterns(&ctx, parsed_patterns, MissingTargetBehavior::Fail).await?;
+                let exceptions = ctx.get_package_boundary_exceptions().await?;
+                let resolver = ctx.get_package_listing_resolver();
+
+                let mut violations = PackageBoundaryViolations::default();
+                for (package, result) in loaded_patterns.iter() {
+                    let nodes = match result {
+                        Ok(res) => res,
+                        Err(e) => return SharedResult::unshared_error(Err(e.dupe())),
+                    };
+                    let listing = ctx.resolve_package_listing(package.dupe()).await?;
+                    let exception = exceptions.contains(&package.as_cell_path().to_owned());
+                    let eval_result = ctx.get_interpreter_results(package.dupe()).await?;
+                    for glob in eval_result.glob_package_boundary_violations() {
+                        violations.globs.push(GlobPackageBoundaryViolation::new(
+                            package,
+                            &glob.pattern,
+                            &glob.subpackage,
+                            exception,
+                        )?);
+                    }
+                    for node in nodes.values() {
+                        for input in node.inputs() {
+                            let src = PackageRelativePath::unchecked_new(
+                                input
+                                    .as_ref()
+                                    .strip_prefix(package.as_cell_path())?
+                                    .as_str(),
+                            );
+                            if listing.get_file(src).is_some() {
+                                continue;
+                            }
+                            let owner = resolver.get_enclosing_package(input.as_ref()).await?;
+                            if owner != *package {
+                                violations.sources.push(PackageBoundaryViolation::new(
+                                    node.label(),
+                                    src,
+                                    &owner,
+                                    exception,
+                                )?);
+                            }
+                        }
+                    }
+                }
+
+                let mut stdout = stdout.as_writer();
+                if self.json {
+                    writeln!(stdout, "{}", serde_json::to_string_pretty(&violations)?)?;
+                } else {
+                    for v in &violations.sources {
+                        writeln!(
+                            stdout,
+                            "{}: `{}` belongs to {}{}",
+                            v.target,
+                            v.src,
+                            v.owner,
+                            if v.exception { EXCEPTION_NOTE } else { "" }
+                        )?;
+                        writeln!(
+                            stdout,
+                            "  add `{}` to {} and use `{}` instead",
+                            v.export_file, v.owner, v.replacement
+                        )?;
+                    }
+                    for v in &violations.globs {
+                        writeln!(
+                            stdout,
+                            "{}: glob pattern `{}` only matches files of {}{}",
+                            v.package,
+                            v.pattern,
+                            v.owner,
+                            if v.exception { EXCEPTION_NOTE } else { "" }
+                        )?;
+                        writeln!(
+                            stdout,
+                            "  glob `{}` in {} and export the files from there instead",
+                            v.owner_pattern, v.owner
+                        )?;
+                    }
+                }
+                Ok(())
+            })
+            .await
+    }
+
+    fn common_opts(&self) -> &CommonCommandOptions {
+        &self.common_opts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::package::package_relative_path::PackageRelativePath;
+    use buck2_core::package::PackageLabel;
+    use buck2_core::target::label::TargetLabel;
+
+    use crate::package_boundary_violations::GlobPackageBoundaryViolation;
+    use crate::package_boundary_violations::PackageBoundaryViolation;
+
+    #[test]
+    fn test_violation_suggestion() -> anyhow::Result<()> {
+        let violation = PackageBoundaryViolation::new(
+            &TargetLabel::testing_parse("root//foo:lib"),
+            PackageRelativePath::new("bar/include/x.h")?,
+            &PackageLabel::testing_parse("root//foo/bar"),
+            false,
+        )?;
+        assert_eq!(
+            violation,
+            PackageBoundaryViolation {
+                target: "root//foo:lib".to_owned(),
+                src: "bar/include/x.h".to_owned(),
+                owner: "root//foo/bar".to_owned(),
+                exception: false,
+                export_file: "export_file(name = \"include/x.h\")".to_owned(),
+                replacement: "root//foo/bar:include/x.h".to_owned(),
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_glob_violation_suggestion() -> anyhow::Result<()> {
+        let violation = GlobPackageBoundaryViolation::new(
+            &PackageLabel::testing_parse("root//foo"),
+            "bar/include/**/*.h",
+            &PackageLabel::testing_parse("root//foo/bar"),
+            true,
+        )?;
+        assert_eq!(
+            violation,
+            GlobPackageBoundaryViolation {
+                package: "root//foo".to_owned(),
+                pattern: "bar/include/**/*.h".to_owned(),
+                owner: "root//foo/bar".to_owned(),
+                exception: true,
+                owner_pattern: "include/**/*.h".to_owned(),
+            }
+        );
+        Ok(())
+    }
+}
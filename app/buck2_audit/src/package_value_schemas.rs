@@ -0,0 +1,142 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::BTreeSet;
+use std::io::Write;
+
+use async_trait::async_trait;
+use buck2_build_api::calculation::load_patterns;
+use buck2_build_api::calculation::MissingTargetBehavior;
+use buck2_cli_proto::ClientContext;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_common::dice::cells::HasCellResolver;
+use buck2_common::result::recursive_shared_downcast_ref;
+use buck2_core::cells::cell_path::CellPath;
+use buck2_core::cells::paths::CellRelativePath;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_core::pattern::ParsedPattern;
+use buck2_interpreter_for_build::super_package::package_value::PackageValueSchemaError;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::pattern::parse_patterns_from_cli_args;
+use gazebo::prelude::*;
+use starlark::errors::Diagnostic;
+
+use crate::AuditSubcommand;
+
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(
+    name = "audit-package-value-schemas",
+    about = "List package values which do not conform to the schemas declared with `package_value_schema()`"
+)]
+pub struct AuditPackageValueSchemasCommand {
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    #[clap(
+        name = "TARGET_PATTERNS",
+        help = "Patterns of packages to check. Every package in every cell is checked if none are given."
+    )]
+    patterns: Vec<String>,
+
+    #[clap(long, help = "Output violations in JSON format")]
+    json: bool,
+}
+
+#[derive(serde::Serialize, PartialEq, Eq, PartialOrd, Ord)]
+struct SchemaViolation {
+    file: String,
+    error: String,
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditPackageValueSchemasCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        _client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(async move |server_ctx, ctx| {
+                let mut parsed_patterns = parse_patterns_from_cli_args::<TargetPatternExtra>(
+                    &ctx,
+                    &self
+                        .patterns
+                        .map(|pat| buck2_data::TargetPattern { value: pat.clone() }),
+                    server_ctx.working_dir(),
+                )
+                .await?;
+                if parsed_patterns.is_empty() {
+                    let cells = ctx.get_cell_resolver().await?;
+                    parsed_patterns.extend(cells.cells().map(|(name, _)| {
+                        ParsedPattern::Recursive(CellPath::new(
+                            name,
+                            CellRelativePath::empty().to_owned(),
+                        ))
+                    }));
+                }
+
+                let loaded_patterns =
+                    load_patterns(&ctx, parsed_patterns, MissingTargetBehavior::Fail).await?;
+
+                // A violation in a `PACKAGE` file fails every package below it, so deduplicate
+                // by the file which declared the non-conforming value.
+                let mut violations = BTreeSet::new();
+                for (_package, result) in loaded_patterns.iter() {
+                    let err = match result {
+                        Ok(_) => continue,
+                        Err(e) => e,
+                    };
+                    let diagnostic = match recursive_shared_downcast_ref::<Diagnostic>(err.inner())
+                    {
+                        Some(diagnostic) => diagnostic,
+                        None => continue,
+                    };
+                    if let Some(schema_err) =
+                        diagnostic.message.downcast_ref::<PackageValueSchemaError>()
+                    {
+                        violations.insert(SchemaViolation {
+                            file: diagnostic
+                                .span
+                                .as_ref()
+                                .map_or_else(String::new, |span| span.to_string()),
+                            error: schema_err.to_string(),
+                        });
+                    }
+                }
+
+                let mut stdout = stdout.as_writer();
+                if self.json {
+                    let violations: Vec<_> = violations.iter().collect();
+                    writeln!(stdout, "{}", serde_json::to_string_pretty(&violations)?)?;
+                } else {
+                    for violation in &violations {
+                        writeln!(stdout, "{}: {}", violation.file, violation.error)?;
+                    }
+                }
+
+                if !violations.is_empty() {
+                    return Err(anyhow::anyhow!(
+                        "Found {} package value schema violation(s)",
+                        violations.len()
+                    ));
+                }
+
+                buck2_client_ctx::eprintln!("audit package-value-schemas succeeded")?;
+                Ok(())
+            })
+            .await
+    }
+
+    fn common_opts(&self) -> &CommonCommandOptions {
+        &self.common_opts
+    }
+}
@@ -0,0 +1,250 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use async_trait::async_trait;
+use buck2_build_api::calculation::load_patterns;
+use buck2_build_api::calculation::MissingTargetBehavior;
+use buck2_cli_proto::ClientContext;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_common::dice::cells::HasCellResolver;
+use buck2_common::result::SharedResult;
+use buck2_common::result::ToUnsharedResultExt;
+use buck2_core::cells::cell_path::CellPath;
+use buck2_core::cells::paths::CellRelativePath;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_core::pattern::ParsedPattern;
+use buck2_node::attrs::inspect_options::AttrInspectOptions;
+use buck2_node::nodes::unconfigured::TargetNode;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::pattern::parse_patterns_from_cli_args;
+use dupe::Dupe;
+use gazebo::prelude::*;
+
+use crate::AuditSubcommand;
+
+#[derive(
+    Debug,
+    Clone,
+    Dupe,
+    clap::ArgEnum,
+    serde::Serialize,
+    serde::Deserialize
+)]
+#[clap(rename_all = "snake_case")]
+enum RuleUsageOutputFormat {
+    Json,
+    Csv,
+}
+
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(
+    name = "audit-rule-usage",
+    about = "Count the rules and explicitly set attributes used by the targets matching the given patterns"
+)]
+pub struct AuditRuleUsageCommand {
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    #[clap(
+        name = "TARGET_PATTERNS",
+        help = "Patterns of targets to inspect. Every target in every cell is inspected if none are given."
+    )]
+    patterns: Vec<String>,
+
+    #[clap(
+        long,
+        value_name = "[RULE.]ATTR",
+        help = "Report every target which sets this attribute. Matches the attribute on every rule, or only on rules with the given name (e.g. `cxx_library.link_style`). May be repeated."
+    )]
+    deprecated_attr: Vec<String>,
+
+    #[clap(long, arg_enum, default_value = "json")]
+    output_format: RuleUsageOutputFormat,
+}
+
+/// A `--deprecated-attr` argument.
+struct DeprecatedAttr<'a> {
+    rule: Option<&'a str>,
+    attr: &'a str,
+}
+
+impl<'a> DeprecatedAttr<'a> {
+    fn parse(s: &'a str) -> Self {
+        match s.rsplit_once('.') {
+            Some((rule, attr)) => DeprecatedAttr {
+                rule: Some(rule),
+                attr,
+            },
+            None => DeprecatedAttr {
+                rule: None,
+                attr: s,
+            },
+        }
+    }
+
+    fn matches(&self, rule: &str, attr: &str) -> bool {
+        self.attr == attr && self.rule.map_or(true, |r| r == rule)
+    }
+}
+
+#[derive(Default, serde::Serialize)]
+struct RuleUsage {
+    /// Number of targets of this rule.
+    targets: u64,
+    /// Number of targets of this rule explicitly setting each attribute.
+    attrs: BTreeMap<String, u64>,
+}
+
+#[derive(serde::Serialize)]
+struct DeprecatedAttrUsage {
+    target: String,
+    rule: String,
+    attr: String,
+}
+
+#[derive(Default, serde::Serialize)]
+struct RuleUsageReport {
+    /// Keyed by the rule type, e.g. `prelude//rules.bzl:cxx_library`.
+    rules: BTreeMap<String, RuleUsage>,
+    deprecated_attrs: Vec<DeprecatedAttrUsage>,
+}
+
+impl RuleUsageReport {
+    fn add(&mut self, node: &TargetNode, deprecated_attrs: &[DeprecatedAttr]) {
+        let rule_name = node.rule_type().name();
+        let usage = self.rules.entry(node.rule_type().to_string()).or_default();
+        usage.targets += 1;
+        for attr in node.attrs(AttrInspectOptions::DefinedOnly) {
+            *usage.attrs.entry(attr.name.to_owned()).or_default() += 1;
+            if deprecated_attrs
+                .iter()
+                .any(|d| d.matches(rule_name, attr.name))
+            {
+                self.deprecated_attrs.push(DeprecatedAttrUsage {
+                    target: node.label().to_string(),
+                    rule: rule_name.to_owned(),
+                    attr: attr.name.to_owned(),
+                });
+            }
+        }
+    }
+
+    /// One row per rule (with an empty `attr`) followed by one row per attribute of that rule.
+    fn write_csv(&self, deprecated_attrs: &[DeprecatedAttr], w: impl Write) -> anyhow::Result<()> {
+        let mut writer = csv::Writer::from_writer(w);
+        writer.write_record(["rule", "attr", "count", "deprecated"])?;
+        for (rule_type, usage) in &self.rules {
+            let targets = usage.targets.to_string();
+            writer.write_record([rule_type.as_str(), "", targets.as_str(), ""])?;
+            let rule_name = rule_type
+                .rsplit_once(':')
+                .map_or(rule_type.as_str(), |x| x.1);
+            for (attr, count) in &usage.attrs {
+                let count = count.to_string();
+                let deprecated = deprecated_attrs.iter().any(|d| d.matches(rule_name, attr));
+                writer.write_record([
+                    rule_type.as_str(),
+                    attr.as_str(),
+                    count.as_str(),
+                    if deprecated { "true" } else { "false" },
+                ])?;
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditRuleUsageCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        _client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(async move |server_ctx, ctx| {
+                let mut parsed_patterns = parse_patterns_from_cli_args::<TargetPatternExtra>(
+                    &ctx,
+                    &self
+                        .patterns
+                        .map(|pat| buck2_data::TargetPattern { value: pat.clone() }),
+                    server_ctx.working_dir(),
+                )
+                .await?;
+                if parsed_patterns.is_empty() {
+                    let cells = ctx.get_cell_resolver().await?;
+                    parsed_patterns.extend(cells.cells().map(|(name, _)| {
+                        ParsedPattern::Recursive(CellPath::new(
+                            name,
+                            CellRelativePath::empty().to_owned(),
+                        ))
+                    }));
+                }
+
+                let loaded_patterns =
+                    load_patterns(&ctx, parsed_patterns, MissingTargetBehavior::Fail).await?;
+
+                let deprecated_attrs = self.deprecated_attr.map(|s| DeprecatedAttr::parse(s));
+
+                let mut report = RuleUsageReport::default();
+                for (_package, result) in loaded_patterns.iter() {
+                    match result {
+                        Ok(res) => {
+                            for node in res.values() {
+                                report.add(node, &deprecated_attrs);
+                            }
+                        }
+                        Err(e) => {
+                            return SharedResult::unshared_error(Err(e.dupe()));
+                        }
+                    }
+                }
+
+                let mut stdout = stdout.as_writer();
+                match self.output_format {
+                    RuleUsageOutputFormat::Json => {
+                        writeln!(stdout, "{}", serde_json::to_string_pretty(&report)?)?;
+                    }
+                    RuleUsageOutputFormat::Csv => {
+                        report.write_csv(&deprecated_attrs, stdout)?;
+                    }
+                }
+                Ok(())
+            })
+            .await
+    }
+
+    fn common_opts(&self) -> &CommonCommandOptions {
+        &self.common_opts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rule_usage::DeprecatedAttr;
+
+    #[test]
+    fn test_deprecated_attr_matches() {
+        let any_rule = DeprecatedAttr::parse("link_style");
+        assert!(any_rule.matches("cxx_library", "link_style"));
+        assert!(any_rule.matches("cxx_binary", "link_style"));
+        assert!(!any_rule.matches("cxx_library", "srcs"));
+
+        let one_rule = DeprecatedAttr::parse("cxx_library.link_style");
+        assert!(one_rule.matches("cxx_library", "link_style"));
+        assert!(!one_rule.matches("cxx_binary", "link_style"));
+    }
+}
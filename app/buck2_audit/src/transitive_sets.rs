@@ -0,0 +1,389 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Size accounting of the transitive sets created by the analysis of targets, to find out which
+//! ones are responsible for the memory used by analysis.
+//!
+//! Every set created by a rule implementation is a deferred of its analysis result. Each node of
+//! a set is itself a set, so nodes created by the analysis of dependencies are shared with them,
+//! and only the nodes owned by the target take memory of its own.
+
+use std::any;
+use std::collections::HashSet;
+use std::io::Write;
+
+use async_trait::async_trait;
+use buck2_build_api::analysis::calculation::RuleAnalysisCalculation;
+use buck2_build_api::artifact_groups::deferred::DeferredTransitiveSetData;
+use buck2_build_api::artifact_groups::deferred::TransitiveSetKey;
+use buck2_build_api::calculation::load_patterns;
+use buck2_build_api::calculation::Calculation;
+use buck2_build_api::calculation::MissingTargetBehavior;
+use buck2_build_api::interpreter::rule_defs::transitive_set::TransitiveSet;
+use buck2_build_api::interpreter::rule_defs::transitive_set::TransitiveSetOrdering;
+use buck2_cli_proto::ClientContext;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_query::query::compatibility::MaybeCompatible;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::pattern::parse_patterns_from_cli_args;
+use buck2_server_ctx::pattern::target_platform_from_client_context;
+use dupe::Dupe;
+use gazebo::prelude::*;
+
+use crate::AuditSubcommand;
+
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(
+    name = "audit-transitive-sets",
+    about = "Show the node counts, projection sizes and sharing with dependencies of the \
+    transitive sets created by the analysis of targets"
+)]
+pub struct AuditTransitiveSetsCommand {
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    /// Print the statistics as JSON.
+    #[clap(long)]
+    json: bool,
+
+    #[clap(
+        name = "TARGET_PATTERNS",
+        help = "Patterns of the targets whose transitive sets to show",
+        required = true
+    )]
+    patterns: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, serde::Serialize)]
+struct ProjectionStats {
+    name: String,
+    /// Number of nodes with a value for the projection.
+    values: u64,
+    /// Memory used by the projected values, as measured by allocative. Values shared with the
+    /// value of the node are not included.
+    allocated_bytes: u64,
+}
+
+#[derive(Debug, PartialEq, Eq, serde::Serialize)]
+struct TransitiveSetStats {
+    /// The id of the set within the analysis of the target.
+    id: usize,
+    definition: String,
+    /// Number of distinct nodes reachable from the set, including itself.
+    nodes: u64,
+    /// Nodes which have a value, which are the ones iteration and projections yield.
+    nodes_with_values: u64,
+    /// Nodes created by the analysis of other targets.
+    shared_nodes: u64,
+    projections: Vec<ProjectionStats>,
+}
+
+#[derive(Debug, PartialEq, Eq, serde::Serialize)]
+struct TargetStats {
+    target: String,
+    /// Number of distinct nodes reachable from any set of the target.
+    nodes: u64,
+    /// Of those, the nodes created by the analysis of other targets.
+    shared_nodes: u64,
+    /// Sets created by the analysis of the target, largest first.
+    transitive_sets: Vec<TransitiveSetStats>,
+}
+
+/// Count the nodes of `tset`, adding them to `seen`. Nodes are shared if they were not created by
+/// the analysis which created `tset`.
+fn transitive_set_stats<'v>(
+    tset: &'v TransitiveSet<'v>,
+    seen: &mut HashSet<TransitiveSetKey>,
+) -> anyhow::Result<TransitiveSetStats> {
+    let owner = tset.key().deferred_key().owner();
+    let mut stats = TransitiveSetStats {
+        id: tset.key().deferred_key().id().as_usize(),
+        definition: tset.definition().to_string(),
+        nodes: 0,
+        nodes_with_values: 0,
+        shared_nodes: 0,
+        projections: Vec::new(),
+    };
+    for node in tset.iter(TransitiveSetOrdering::Preorder) {
+        stats.nodes += 1;
+        if node.key().deferred_key().owner() != owner {
+            stats.shared_nodes += 1;
+        }
+        seen.insert(node.key().dupe());
+
+        let projections = match node.node_projections() {
+            Some(projections) => projections,
+            None => continue,
+        };
+        stats.nodes_with_values += 1;
+        for (i, projection) in projections.iter().enumerate() {
+            if stats.projections.len() <= i {
+                stats.projections.push(ProjectionStats {
+                    name: tset.projection_name(i)?.to_owned(),
+                    values: 0,
+                    allocated_bytes: 0,
+                });
+            }
+            stats.projections[i].values += 1;
+            stats.projections[i].allocated_bytes += projection.total_memory() as u64;
+        }
+    }
+    Ok(stats)
+}
+
+fn render_target_stats(stats: &TargetStats, out: &mut dyn Write) -> anyhow::Result<()> {
+    writeln!(
+        out,
+        "{}: {} transitive sets, {} distinct nodes, {} shared with deps",
+        stats.target,
+        stats.transitive_sets.len(),
+        stats.nodes,
+        stats.shared_nodes
+    )?;
+    for tset in &stats.transitive_sets {
+        writeln!(
+            out,
+            "  {} (id {}): {} nodes, {} with values, {} shared with deps",
+            tset.definition, tset.id, tset.nodes, tset.nodes_with_values, tset.shared_nodes
+        )?;
+        for projection in &tset.projections {
+            writeln!(
+                out,
+                "    projection {}: {} values, {} bytes allocated",
+                projection.name, projection.values, projection.allocated_bytes
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditTransitiveSetsCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(async move |server_ctx, ctx| {
+                let parsed_patterns = parse_patterns_from_cli_args::<TargetPatternExtra>(
+                    &ctx,
+                    &self
+                        .patterns
+                        .map(|pat| buck2_data::TargetPattern { value: pat.clone() }),
+                    server_ctx.working_dir(),
+                )
+                .await?;
+                let loaded_patterns =
+                    load_patterns(&ctx, parsed_patterns, MissingTargetBehavior::Fail).await?;
+                let target_platform =
+                    target_platform_from_client_context(&client_ctx, server_ctx, &ctx).await?;
+
+                let mut all_stats = Vec::new();
+                for (_, targets) in loaded_patterns.into_iter() {
+                    for (_, node) in targets? {
+                        let label = ctx
+                            .get_configured_target(node.label(), target_platform.as_ref())
+                            .await?;
+                        let analysis = match ctx.get_analysis_result(&label).await? {
+                            MaybeCompatible::Incompatible(_) => continue,
+                            MaybeCompatible::Compatible(analysis) => analysis,
+                        };
+
+                        // The sets are owned by the analysis, which is keyed by the label after
+                        // any incoming transition, so this is not necessarily `label`.
+                        let mut owners = HashSet::new();
+                        let mut seen = HashSet::new();
+                        let mut transitive_sets = Vec::new();
+                        for entry in analysis.iter_deferreds() {
+                            if let Some(data) =
+                                any::request_ref::<DeferredTransitiveSetData>(entry.as_complex())
+                            {
+                                let tset = data.as_transitive_set()?;
+                                owners.insert(tset.key().deferred_key().owner().dupe());
+                                transitive_sets.push(transitive_set_stats(tset, &mut seen)?);
+                            }
+                        }
+                        transitive_sets.sort_by_key(|s| (std::cmp::Reverse(s.nodes), s.id));
+
+                        all_stats.push(TargetStats {
+                            target: label.to_string(),
+                            nodes: seen.len() as u64,
+                            shared_nodes: seen
+                                .iter()
+                                .filter(|k| !owners.contains(k.deferred_key().owner()))
+                                .count() as u64,
+                            transitive_sets,
+                        });
+                    }
+                }
+
+                let mut stdout = stdout.as_writer();
+                if self.json {
+                    writeln!(stdout, "{}", serde_json::to_string_pretty(&all_stats)?)?;
+                } else {
+                    for stats in &all_stats {
+                        render_target_stats(stats, &mut stdout)?;
+                    }
+                }
+                Ok(())
+            })
+            .await
+    }
+
+    fn common_opts(&self) -> &CommonCommandOptions {
+        &self.common_opts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::sync::atomic::AtomicU32;
+    use std::sync::atomic::Ordering;
+
+    use buck2_build_api::deferred::base_deferred_key::BaseDeferredKey;
+    use buck2_build_api::deferred::types::testing::DeferredDataExt;
+    use buck2_build_api::deferred::types::testing::DeferredIdExt;
+    use buck2_build_api::deferred::types::DeferredData;
+    use buck2_build_api::deferred::types::DeferredId;
+    use buck2_build_api::deferred::types::DeferredKey;
+    use buck2_build_api::interpreter::build_defs::register_transitive_set;
+    use buck2_build_api::interpreter::rule_defs::transitive_set::TransitiveSet;
+    use buck2_core::configuration::data::ConfigurationData;
+    use buck2_core::target::label::ConfiguredTargetLabel;
+    use buck2_interpreter_for_build::attrs::coerce::testing::to_value;
+    use starlark::environment::GlobalsBuilder;
+    use starlark::environment::Module;
+    use starlark::eval::Evaluator;
+    use starlark::starlark_module;
+    use starlark::values::Value;
+    use starlark::values::ValueLike;
+
+    use crate::transitive_sets::render_target_stats;
+    use crate::transitive_sets::transitive_set_stats;
+    use crate::transitive_sets::ProjectionStats;
+    use crate::transitive_sets::TargetStats;
+    use crate::transitive_sets::TransitiveSetStats;
+
+    #[starlark_module]
+    fn tset_factory(builder: &mut GlobalsBuilder) {
+        /// Make a set as if it was created by the analysis of `owner`.
+        fn make_tset<'v>(
+            owner: &str,
+            definition: Value<'v>,
+            value: Option<Value<'v>>,
+            children: Option<Value<'v>>,
+            eval: &mut Evaluator<'v, '_>,
+        ) -> anyhow::Result<TransitiveSet<'v>> {
+            static LAST_ID: AtomicU32 = AtomicU32::new(0);
+
+            let owner =
+                ConfiguredTargetLabel::testing_parse(owner, ConfigurationData::testing_new());
+            let deferred_id = DeferredId::testing_new(LAST_ID.fetch_add(1, Ordering::Relaxed));
+            let deferred_key = DeferredKey::Base(BaseDeferredKey::TargetLabel(owner), deferred_id);
+
+            TransitiveSet::new_from_values(
+                DeferredData::testing_new(deferred_key),
+                definition,
+                value,
+                children,
+                eval,
+            )
+        }
+    }
+
+    #[test]
+    fn test_transitive_set_stats_with_set_shared_with_dep() -> anyhow::Result<()> {
+        let env = Module::new();
+        let globals = GlobalsBuilder::extended()
+            .with(register_transitive_set)
+            .with(tset_factory)
+            .build();
+        let tset = to_value(
+            &env,
+            &globals,
+            r#"
+def _flags(value):
+    return value
+
+FlagsSet = transitive_set(args_projections = {"flags": _flags})
+
+dep = make_tset(
+    "root//:dep",
+    FlagsSet,
+    value = "dep",
+    children = [make_tset("root//:dep", FlagsSet, value = "dep_child")],
+)
+make_tset("root//:target", FlagsSet, value = "target", children = [dep, make_tset("root//:target", FlagsSet)])
+"#,
+        );
+        let tset = tset
+            .downcast_ref::<TransitiveSet>()
+            .ok_or_else(|| anyhow::anyhow!("not a transitive set: {}", tset))?;
+
+        let mut seen = HashSet::new();
+        let stats = transitive_set_stats(tset, &mut seen)?;
+        assert_eq!(stats.definition, "FlagsSet");
+        assert_eq!(stats.nodes, 4);
+        assert_eq!(stats.nodes_with_values, 3);
+        // `dep` and its child were created by the analysis of the dependency.
+        assert_eq!(stats.shared_nodes, 2);
+        assert_eq!(stats.projections.len(), 1);
+        assert_eq!(stats.projections[0].name, "flags");
+        assert_eq!(stats.projections[0].values, 3);
+        assert!(stats.projections[0].allocated_bytes > 0);
+        assert_eq!(seen.len(), 4);
+
+        // The set of the dependency owns all of its nodes.
+        let dep = env
+            .get("dep")
+            .and_then(|dep| dep.downcast_ref::<TransitiveSet>())
+            .ok_or_else(|| anyhow::anyhow!("`dep` is not a transitive set"))?;
+        let stats = transitive_set_stats(dep, &mut seen)?;
+        assert_eq!(stats.nodes, 2);
+        assert_eq!(stats.shared_nodes, 0);
+        assert_eq!(seen.len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_target_stats() -> anyhow::Result<()> {
+        let stats = TargetStats {
+            target: "root//:foo".to_owned(),
+            nodes: 5,
+            shared_nodes: 3,
+            transitive_sets: vec![TransitiveSetStats {
+                id: 2,
+                definition: "LinkInfos".to_owned(),
+                nodes: 5,
+                nodes_with_values: 4,
+                shared_nodes: 3,
+                projections: vec![ProjectionStats {
+                    name: "flags".to_owned(),
+                    values: 4,
+                    allocated_bytes: 120,
+                }],
+            }],
+        };
+        let mut out = Vec::new();
+        render_target_stats(&stats, &mut out)?;
+        assert_eq!(
+            String::from_utf8(out)?,
+            "root//:foo: 1 transitive sets, 5 distinct nodes, 3 shared with deps\n  \
+            LinkInfos (id 2): 5 nodes, 4 with values, 3 shared with deps\n    \
+            projection flags: 4 values, 120 bytes allocated\n"
+        );
+        Ok(())
+    }
+}
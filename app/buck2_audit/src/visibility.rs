@@ -7,15 +7,22 @@
  * of this source tree.
  */
 
+use std::collections::BTreeMap;
+use std::io::Write;
+
 use async_trait::async_trait;
 use buck2_build_api::calculation::load_patterns;
 use buck2_build_api::calculation::MissingTargetBehavior;
 use buck2_build_api::nodes::lookup::TargetNodeLookup;
 use buck2_cli_proto::ClientContext;
 use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_common::dice::cells::HasCellResolver;
 use buck2_common::result::SharedResult;
 use buck2_common::result::ToUnsharedResultExt;
+use buck2_core::cells::cell_path::CellPath;
+use buck2_core::cells::paths::CellRelativePath;
 use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_core::pattern::ParsedPattern;
 use buck2_node::nodes::unconfigured::TargetNode;
 use buck2_node::visibility::VisibilityError;
 use buck2_query::query::syntax::simple::eval::set::TargetSet;
@@ -43,7 +50,7 @@ enum VisibilityCommandError {
 #[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
 #[clap(
     name = "audit-visibility",
-    about = "Verify the visibility for transitive deps of the specified target(s) on the unconfigured target graph"
+    about = "Verify the visibility and `within_view` for transitive deps of the specified target(s) on the unconfigured target graph"
 )]
 pub struct AuditVisibilityCommand {
     #[clap(flatten)]
@@ -51,12 +58,70 @@ pub struct AuditVisibilityCommand {
 
     #[clap(name = "TARGET_PATTERNS", help = "Target pattern(s) to analyze.")]
     patterns: Vec<String>,
+
+    #[clap(
+        long,
+        help = "Also check every target in every cell (as if `cell//...` was passed for each cell)"
+    )]
+    all: bool,
+
+    #[clap(
+        long,
+        help = "Output violations in JSON format, grouped by the package of the target depending on a target it can't see"
+    )]
+    json: bool,
+}
+
+#[derive(serde::Serialize)]
+struct VisibilityViolation {
+    target: String,
+    dep: String,
+    /// `visibility` if `dep` is not visible to `target`, `within_view` if it is outside the
+    /// `within_view` of the package of `target`.
+    kind: &'static str,
+}
+
+/// All the visibility and `within_view` violations between `targets`, which must be closed under
+/// deps.
+fn visibility_errors(targets: &TargetSet<TargetNode>) -> anyhow::Result<Vec<VisibilityError>> {
+    let mut visibility_errors = Vec::new();
+
+    for target in targets.iter() {
+        for dep in target.deps() {
+            match targets.get(dep) {
+                Some(val) => {
+                    if !val.is_visible_to(target.label())? {
+                        visibility_errors.push(VisibilityError::NotVisibleTo(
+                            dep.dupe(),
+                            target.label().dupe(),
+                        ));
+                    }
+                    if !target.is_within_view(dep) {
+                        visibility_errors.push(VisibilityError::NotWithinView(
+                            dep.dupe(),
+                            target.label().dupe(),
+                        ));
+                    }
+                }
+                None => {
+                    return Err(anyhow::Error::new(VisibilityCommandError::DepNodeNotFound(
+                        dep.to_string(),
+                        target.label().name().to_string(),
+                    )));
+                }
+            }
+        }
+    }
+
+    Ok(visibility_errors)
 }
 
 impl AuditVisibilityCommand {
     async fn verify_visibility(
+        &self,
         ctx: DiceTransaction,
         targets: TargetSet<TargetNode>,
+        mut stdout: impl Write,
     ) -> anyhow::Result<()> {
         struct Delegate {
             targets: TargetSet<TargetNode>,
@@ -88,31 +153,29 @@ impl AuditVisibilityCommand {
 
         async_depth_first_postorder_traversal(&lookup, targets.iter_names(), &mut delegate).await?;
 
-        let mut visibility_errors = Vec::new();
-
-        for target in delegate.targets.iter() {
-            for dep in target.deps() {
-                match delegate.targets.get(dep) {
-                    Some(val) => {
-                        if !val.is_visible_to(target.label())? {
-                            visibility_errors.push(VisibilityError::NotVisibleTo(
-                                dep.dupe(),
-                                target.label().dupe(),
-                            ));
-                        }
-                    }
-                    None => {
-                        return Err(anyhow::Error::new(VisibilityCommandError::DepNodeNotFound(
-                            dep.to_string(),
-                            target.label().name().to_string(),
-                        )));
-                    }
-                }
-            }
-        }
+        let visibility_errors = visibility_errors(&delegate.targets)?;
 
-        for err in &visibility_errors {
-            buck2_client_ctx::eprintln!("{}", err)?;
+        if self.json {
+            let mut by_package: BTreeMap<String, Vec<VisibilityViolation>> = BTreeMap::new();
+            for err in &visibility_errors {
+                let (dep, target, kind) = match err {
+                    VisibilityError::NotVisibleTo(dep, target) => (dep, target, "visibility"),
+                    VisibilityError::NotWithinView(dep, target) => (dep, target, "within_view"),
+                };
+                by_package
+                    .entry(target.pkg().to_string())
+                    .or_default()
+                    .push(VisibilityViolation {
+                        target: target.to_string(),
+                        dep: dep.to_string(),
+                        kind,
+                    });
+            }
+            writeln!(stdout, "{}", serde_json::to_string_pretty(&by_package)?)?;
+        } else {
+            for err in &visibility_errors {
+                buck2_client_ctx::eprintln!("{}", err)?;
+            }
         }
 
         if !visibility_errors.is_empty() {
@@ -129,12 +192,12 @@ impl AuditSubcommand for AuditVisibilityCommand {
     async fn server_execute(
         &self,
         server_ctx: &dyn ServerCommandContextTrait,
-        _stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
         _client_ctx: ClientContext,
     ) -> anyhow::Result<()> {
         server_ctx
             .with_dice_ctx(async move |server_ctx, ctx| {
-                let parsed_patterns = parse_patterns_from_cli_args::<TargetPatternExtra>(
+                let mut parsed_patterns = parse_patterns_from_cli_args::<TargetPatternExtra>(
                     &ctx,
                     &self
                         .patterns
@@ -142,6 +205,15 @@ impl AuditSubcommand for AuditVisibilityCommand {
                     server_ctx.working_dir(),
                 )
                 .await?;
+                if self.all {
+                    let cells = ctx.get_cell_resolver().await?;
+                    parsed_patterns.extend(cells.cells().map(|(name, _)| {
+                        ParsedPattern::Recursive(CellPath::new(
+                            name,
+                            CellRelativePath::empty().to_owned(),
+                        ))
+                    }));
+                }
 
                 let parsed_target_patterns =
                     load_patterns(&ctx, parsed_patterns, MissingTargetBehavior::Fail).await?;
@@ -158,7 +230,8 @@ impl AuditSubcommand for AuditVisibilityCommand {
                     }
                 }
 
-                AuditVisibilityCommand::verify_visibility(ctx, nodes).await?;
+                self.verify_visibility(ctx, nodes, stdout.as_writer())
+                    .await?;
                 Ok(())
             })
             .await
@@ -168,3 +241,124 @@ impl AuditSubcommand for AuditVisibilityCommand {
         &self.common_opts
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use buck2_core::bzl::ImportPath;
+    use buck2_core::provider::label::ProvidersLabel;
+    use buck2_core::provider::label::ProvidersName;
+    use buck2_core::target::label::TargetLabel;
+    use buck2_node::attrs::attr::Attribute;
+    use buck2_node::attrs::attr_type::list::ListLiteral;
+    use buck2_node::attrs::attr_type::AttrType;
+    use buck2_node::attrs::coerced_attr::CoercedAttr;
+    use buck2_node::nodes::unconfigured::testing::TargetNodeExt;
+    use buck2_node::nodes::unconfigured::TargetNode;
+    use buck2_node::provider_id_set::ProviderIdSet;
+    use buck2_node::rule_type::RuleType;
+    use buck2_node::rule_type::StarlarkRuleType;
+    use buck2_node::visibility::VisibilityError;
+    use buck2_node::visibility::VisibilityPattern;
+    use buck2_node::visibility::VisibilitySpecification;
+    use buck2_node::visibility::WithinViewSpecification;
+    use buck2_query::query::syntax::simple::eval::set::TargetSet;
+    use buck2_util::arc_str::ArcSlice;
+    use dupe::Dupe;
+
+    use crate::visibility::visibility_errors;
+
+    fn node(
+        label: &str,
+        visibility: VisibilitySpecification,
+        deps: &[&str],
+        within_view: &[&str],
+    ) -> TargetNode {
+        let rule_type = RuleType::Starlark(Arc::new(StarlarkRuleType {
+            import_path: ImportPath::testing_new("root//:defs.bzl"),
+            name: "some_rule".to_owned(),
+        }));
+        let deps = deps.iter().map(|dep| {
+            CoercedAttr::Dep(ProvidersLabel::new(
+                TargetLabel::testing_parse(dep),
+                ProvidersName::Default,
+            ))
+        });
+        let within_view = if within_view.is_empty() {
+            WithinViewSpecification::Public
+        } else {
+            WithinViewSpecification::VisibleTo(
+                within_view
+                    .iter()
+                    .map(|p| VisibilityPattern::testing_new(p))
+                    .collect(),
+            )
+        };
+        TargetNode::testing_new_with_within_view(
+            TargetLabel::testing_parse(label),
+            rule_type,
+            vec![
+                (
+                    "visibility",
+                    Attribute::new(None, "", AttrType::visibility()),
+                    CoercedAttr::Visibility(visibility),
+                ),
+                (
+                    "deps",
+                    Attribute::new(
+                        None,
+                        "",
+                        AttrType::list(AttrType::dep(ProviderIdSet::EMPTY)),
+                    ),
+                    CoercedAttr::List(ListLiteral(ArcSlice::from_iter(deps))),
+                ),
+            ],
+            within_view,
+        )
+    }
+
+    #[test]
+    fn test_visibility_errors() -> anyhow::Result<()> {
+        let public = VisibilitySpecification::Public;
+        let mut targets = TargetSet::new();
+        targets.extend([
+            // `b` is outside of the `within_view` of `a`, and `a` is not visible to `d`.
+            node(
+                "root//a:a",
+                VisibilitySpecification::DEFAULT,
+                &["root//b:b", "root//a:a2"],
+                &["root//a/...", "root//c/..."],
+            ),
+            node("root//a:a2", VisibilitySpecification::DEFAULT, &[], &[]),
+            node("root//b:b", public.dupe(), &["root//c:c"], &["root//c/..."]),
+            node("root//c:c", public.dupe(), &[], &[]),
+            node("root//d:d", public.dupe(), &["root//a:a"], &[]),
+        ]);
+
+        let mut errors = visibility_errors(&targets)?
+            .iter()
+            .map(|err| match err {
+                VisibilityError::NotVisibleTo(dep, target) => {
+                    ("visibility", dep.to_string(), target.to_string())
+                }
+                VisibilityError::NotWithinView(dep, target) => {
+                    ("within_view", dep.to_string(), target.to_string())
+                }
+            })
+            .collect::<Vec<_>>();
+        errors.sort();
+        assert_eq!(
+            errors,
+            vec![
+                ("visibility", "root//a:a".to_owned(), "root//d:d".to_owned()),
+                (
+                    "within_view",
+                    "root//b:b".to_owned(),
+                    "root//a:a".to_owned()
+                ),
+            ]
+        );
+        Ok(())
+    }
+}
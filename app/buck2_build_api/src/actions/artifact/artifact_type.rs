@@ -35,6 +35,7 @@ use starlark_map::Hashed;
 use thiserror::Error;
 
 use crate::actions::artifact::build_artifact::BuildArtifact;
+use crate::actions::artifact::output_options::OutputOptions;
 use crate::actions::artifact::projected_artifact::ProjectedArtifact;
 use crate::actions::artifact::source_artifact::SourceArtifact;
 use crate::actions::key::ActionKey;
@@ -56,6 +57,11 @@ struct ArtifactData {
     /// not returned by `.short_path`. Omitted from Eq and Hash comparisons.
     #[derivative(Hash = "ignore", PartialEq = "ignore")]
     hidden_components_count: usize,
+
+    /// A logical prefix of `.short_path`, declared with the output. Omitted from Eq and Hash
+    /// comparisons.
+    #[derivative(Hash = "ignore", PartialEq = "ignore")]
+    short_path_prefix: Option<Arc<ForwardRelativePathBuf>>,
 }
 
 impl Artifact {
@@ -63,6 +69,7 @@ impl Artifact {
         artifact: impl Into<BaseArtifactKind>,
         projected_path: Option<Arc<ForwardRelativePathBuf>>,
         hidden_components_count: usize,
+        short_path_prefix: Option<Arc<ForwardRelativePathBuf>>,
     ) -> Self {
         let artifact = match projected_path {
             Some(path) => ArtifactKind::Projected(ProjectedArtifact::new(artifact.into(), path)),
@@ -72,6 +79,7 @@ impl Artifact {
         Self(Arc::new(ArtifactData {
             data: Hashed::new(artifact),
             hidden_components_count,
+            short_path_prefix,
         }))
     }
 
@@ -87,6 +95,7 @@ impl Artifact {
                     artifact: artifact.dupe(),
                     projected_path: projected_path.cloned(),
                     hidden_components_count: self.0.hidden_components_count,
+                    short_path_prefix: self.0.short_path_prefix.dupe(),
                 };
                 Some(bound.into_declared_artifact().into())
             }
@@ -146,6 +155,7 @@ impl Artifact {
             base_path,
             projected_path,
             hidden_components_count: self.0.hidden_components_count,
+            short_path_prefix: self.0.short_path_prefix.as_deref().map(|p| &**p),
         }
     }
 }
@@ -174,13 +184,13 @@ pub enum ArtifactKind {
 
 impl From<SourceArtifact> for Artifact {
     fn from(a: SourceArtifact) -> Self {
-        Self::new(a, None, 0)
+        Self::new(a, None, 0, None)
     }
 }
 
 impl From<BuildArtifact> for Artifact {
     fn from(a: BuildArtifact) -> Self {
-        Self::new(a, None, 0)
+        Self::new(a, None, 0, None)
     }
 }
 
@@ -193,6 +203,8 @@ pub struct BoundBuildArtifact {
     projected_path: Option<Arc<ForwardRelativePathBuf>>,
     #[derivative(Hash = "ignore", PartialEq = "ignore")]
     hidden_components_count: usize,
+    #[derivative(Hash = "ignore", PartialEq = "ignore")]
+    short_path_prefix: Option<Arc<ForwardRelativePathBuf>>,
 }
 
 impl BoundBuildArtifact {
@@ -201,6 +213,7 @@ impl BoundBuildArtifact {
             self.artifact,
             self.projected_path,
             self.hidden_components_count,
+            self.short_path_prefix,
         )
     }
 
@@ -209,6 +222,7 @@ impl BoundBuildArtifact {
             artifact: Rc::new(RefCell::new(DeclaredArtifactKind::Bound(self.artifact))),
             projected_path: self.projected_path,
             hidden_components_count: self.hidden_components_count,
+            short_path_prefix: self.short_path_prefix,
         }
     }
 
@@ -228,6 +242,7 @@ impl BoundBuildArtifact {
                 .as_ref()
                 .map(|p| AsRef::<ForwardRelativePath>::as_ref(&**p)),
             hidden_components_count: self.hidden_components_count,
+            short_path_prefix: self.short_path_prefix.as_deref().map(|p| &**p),
         }
     }
 }
@@ -248,6 +263,7 @@ pub struct DeclaredArtifact {
     artifact: Rc<RefCell<DeclaredArtifactKind>>,
     projected_path: Option<Arc<ForwardRelativePathBuf>>,
     hidden_components_count: usize,
+    short_path_prefix: Option<Arc<ForwardRelativePathBuf>>,
 }
 
 impl DeclaredArtifact {
@@ -255,13 +271,15 @@ impl DeclaredArtifact {
         path: BuckOutPath,
         output_type: OutputType,
         hidden_components_count: usize,
+        short_path_prefix: Option<Arc<ForwardRelativePathBuf>>,
     ) -> DeclaredArtifact {
         DeclaredArtifact {
             artifact: Rc::new(RefCell::new(DeclaredArtifactKind::Unbound(
-                UnboundArtifact(path, output_type),
+                UnboundArtifact(path, output_type, OutputOptions::default()),
             ))),
             projected_path: None,
             hidden_components_count,
+            short_path_prefix,
         }
     }
 
@@ -270,12 +288,12 @@ impl DeclaredArtifact {
             return self.dupe();
         }
 
-        let hidden_components_count = self.hidden_components_count
-            + if hide_prefix {
-                self.get_path().with_short_path(|p| p.iter().count())
-            } else {
-                0
-            };
+        // Hiding the prefix hides the logical short path prefix as well.
+        let (hidden_components_count, short_path_prefix) = if hide_prefix {
+            (self.get_path().components_count(), None)
+        } else {
+            (self.hidden_components_count, self.short_path_prefix.dupe())
+        };
 
         Self {
             artifact: self.artifact.dupe(),
@@ -284,6 +302,7 @@ impl DeclaredArtifact {
                 None => path.to_owned(),
             })),
             hidden_components_count,
+            short_path_prefix,
         }
     }
 
@@ -308,6 +327,7 @@ impl DeclaredArtifact {
             base_path: Either::Left(ARef::new_ref(base_path)),
             projected_path,
             hidden_components_count: self.hidden_components_count,
+            short_path_prefix: self.short_path_prefix.as_deref().map(|p| &**p),
         }
     }
 
@@ -318,6 +338,20 @@ impl DeclaredArtifact {
         }
     }
 
+    /// Set the options applied to the output once it is built. The artifact must not be bound yet.
+    pub fn set_output_options(&self, options: OutputOptions) -> anyhow::Result<()> {
+        match &mut *self.artifact.borrow_mut() {
+            DeclaredArtifactKind::Bound(x) => Err(anyhow::anyhow!(
+                "Internal error: cannot set output options of bound artifact `{}`",
+                x
+            )),
+            DeclaredArtifactKind::Unbound(x) => {
+                x.2 = options;
+                Ok(())
+            }
+        }
+    }
+
     /// Ensure that the artifact is bound.
     ///
     /// This is called before we freeze the artifacts by the artifact registry.
@@ -340,6 +374,7 @@ impl DeclaredArtifact {
             artifact,
             projected_path: self.projected_path,
             hidden_components_count: self.hidden_components_count,
+            short_path_prefix: self.short_path_prefix,
         })
     }
 
@@ -439,6 +474,7 @@ impl OutputArtifact {
             artifact,
             projected_path: self.0.projected_path.dupe(),
             hidden_components_count: self.0.hidden_components_count,
+            short_path_prefix: self.0.short_path_prefix.dupe(),
         })
     }
 
@@ -457,11 +493,11 @@ impl Deref for OutputArtifact {
 
 #[derive(Clone, Dupe, Debug, Display, Allocative)]
 #[display(fmt = "{}", "self.0")]
-pub struct UnboundArtifact(BuckOutPath, OutputType);
+pub struct UnboundArtifact(BuckOutPath, OutputType, OutputOptions);
 
 impl UnboundArtifact {
     fn bind(self, key: ActionKey) -> BuildArtifact {
-        BuildArtifact::new(self.0, key, self.1)
+        BuildArtifact::new(self.0, key, self.1, self.2)
     }
 }
 
@@ -476,6 +512,7 @@ pub mod testing {
     use crate::actions::artifact::artifact_type::DeclaredArtifact;
     use crate::actions::artifact::artifact_type::DeclaredArtifactKind;
     use crate::actions::artifact::build_artifact::BuildArtifact;
+    use crate::actions::artifact::output_options::OutputOptions;
     use crate::actions::key::ActionKey;
     use crate::deferred::base_deferred_key::BaseDeferredKey;
     use crate::deferred::types::testing::DeferredDataExt;
@@ -536,6 +573,7 @@ pub mod testing {
                     id,
                 ))),
                 OutputType::File,
+                OutputOptions::default(),
             )
         }
     }
@@ -546,6 +584,7 @@ mod tests {
     use std::collections::hash_map::DefaultHasher;
     use std::hash::Hash;
     use std::hash::Hasher;
+    use std::sync::Arc;
 
     use assert_matches::assert_matches;
     use buck2_core::base_deferred_key_dyn::BaseDeferredKeyDyn;
@@ -594,6 +633,7 @@ mod tests {
             ),
             OutputType::File,
             0,
+            None,
         );
         let key = ActionKey::testing_new(DeferredKey::Base(
             BaseDeferredKey::TargetLabel(target.dupe()),
@@ -732,8 +772,8 @@ mod tests {
             DeferredId::testing_new(0),
         );
 
-        let full = Artifact::new(artifact.clone(), None, 0);
-        let hidden = Artifact::new(artifact, None, 1);
+        let full = Artifact::new(artifact.clone(), None, 0, None);
+        let hidden = Artifact::new(artifact, None, 1, None);
 
         assert_eq!(full, hidden);
 
@@ -765,8 +805,8 @@ mod tests {
             DeferredId::testing_new(0),
         );
 
-        let full = Artifact::new(artifact.clone(), None, 0);
-        let hidden = Artifact::new(artifact, None, 1);
+        let full = Artifact::new(artifact.clone(), None, 0, None);
+        let hidden = Artifact::new(artifact, None, 1, None);
 
         full.get_path()
             .with_full_path(|p| assert_eq!(p, "foo/bar.cpp"));
@@ -784,4 +824,34 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_short_path_prefix() -> anyhow::Result<()> {
+        let target =
+            ConfiguredTargetLabel::testing_parse("cell//pkg:foo", ConfigurationData::testing_new());
+
+        let artifact = BuildArtifact::testing_new(
+            target.dupe(),
+            ForwardRelativePathBuf::unchecked_new("foo/bar.cpp".to_owned()),
+            DeferredId::testing_new(0),
+        );
+
+        let prefix = Arc::new(ForwardRelativePathBuf::unchecked_new(
+            "runfiles/x".to_owned(),
+        ));
+        let full = Artifact::new(artifact.clone(), None, 0, None);
+        let prefixed = Artifact::new(artifact, None, 1, Some(prefix));
+
+        assert_eq!(full, prefixed);
+
+        prefixed
+            .get_path()
+            .with_full_path(|p| assert_eq!(p, "foo/bar.cpp"));
+
+        prefixed
+            .get_path()
+            .with_short_path(|p| assert_eq!(p, "runfiles/x/bar.cpp"));
+
+        Ok(())
+    }
 }
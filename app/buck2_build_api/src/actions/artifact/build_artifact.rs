@@ -15,6 +15,7 @@ use derivative::Derivative;
 use derive_more::Display;
 use dupe::Dupe;
 
+use crate::actions::artifact::output_options::OutputOptions;
 use crate::actions::key::ActionKey;
 
 /// An artifact that is built by the build system
@@ -31,14 +32,22 @@ pub struct BuildArtifact {
     pub(super) key: ActionKey,
     #[derivative(PartialEq = "ignore", Hash = "ignore")]
     pub(super) output_type: OutputType,
+    #[derivative(PartialEq = "ignore", Hash = "ignore")]
+    pub(super) output_options: OutputOptions,
 }
 
 impl BuildArtifact {
-    pub(super) fn new(path: BuckOutPath, key: ActionKey, output_type: OutputType) -> Self {
+    pub(super) fn new(
+        path: BuckOutPath,
+        key: ActionKey,
+        output_type: OutputType,
+        output_options: OutputOptions,
+    ) -> Self {
         BuildArtifact {
             path,
             key,
             output_type,
+            output_options,
         }
     }
 
@@ -53,6 +62,10 @@ impl BuildArtifact {
     pub fn output_type(&self) -> OutputType {
         self.output_type
     }
+
+    pub fn output_options(&self) -> &OutputOptions {
+        &self.output_options
+    }
 }
 
 impl ToProtoMessage for BuildArtifact {
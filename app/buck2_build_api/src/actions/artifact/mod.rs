@@ -22,6 +22,7 @@ pub mod build_artifact;
 
 pub mod artifact_type;
 pub mod materializer;
+pub mod output_options;
 pub(crate) mod projected_artifact;
 pub mod provide_outputs;
 pub mod source_artifact;
@@ -0,0 +1,79 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::path::Path;
+use std::sync::Arc;
+
+use allocative::Allocative;
+use dupe::Dupe;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+enum OutputOptionsError {
+    #[error("An output cannot be both `executable` and a symlink")]
+    ExecutableSymlink,
+    #[error("A directory output cannot be `executable` or a symlink")]
+    Directory,
+    #[error("Symlink target must be a non-empty relative path, got `{0}`")]
+    InvalidSymlinkTarget(String),
+}
+
+/// Properties of a declared output which buck2 applies once the action producing it has run,
+/// so that they are the same regardless of which executor ran the action.
+#[derive(Clone, Dupe, Debug, Default, PartialEq, Eq, Hash, Allocative)]
+pub struct OutputOptions {
+    /// The output is a file with the executable bit set.
+    pub executable: bool,
+    /// The output is a symlink to this path, relative to the directory containing the output.
+    pub symlink_target: Option<Arc<str>>,
+}
+
+impl OutputOptions {
+    pub fn new(
+        executable: bool,
+        symlink_target: Option<&str>,
+        dir: bool,
+    ) -> anyhow::Result<OutputOptions> {
+        if executable && symlink_target.is_some() {
+            return Err(OutputOptionsError::ExecutableSymlink.into());
+        }
+        if dir && (executable || symlink_target.is_some()) {
+            return Err(OutputOptionsError::Directory.into());
+        }
+        if let Some(target) = symlink_target {
+            if target.is_empty() || target.starts_with('/') || Path::new(target).is_absolute() {
+                return Err(OutputOptionsError::InvalidSymlinkTarget(target.to_owned()).into());
+            }
+        }
+        Ok(OutputOptions {
+            executable,
+            symlink_target: symlink_target.map(Arc::from),
+        })
+    }
+
+    pub fn is_default(&self) -> bool {
+        !self.executable && self.symlink_target.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::actions::artifact::output_options::OutputOptions;
+
+    #[test]
+    fn test_output_options() {
+        assert!(OutputOptions::new(false, None, false).unwrap().is_default());
+        assert!(OutputOptions::new(true, None, false).unwrap().executable);
+        assert!(OutputOptions::new(false, Some("../foo"), false).is_ok());
+        assert!(OutputOptions::new(true, Some("foo"), false).is_err());
+        assert!(OutputOptions::new(true, None, true).is_err());
+        assert!(OutputOptions::new(false, Some(""), false).is_err());
+        assert!(OutputOptions::new(false, Some("/foo"), false).is_err());
+    }
+}
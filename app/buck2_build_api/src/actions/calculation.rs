@@ -47,6 +47,7 @@ use crate::actions::execute::action_executor::HasActionExecutor;
 use crate::actions::key::ActionKey;
 use crate::actions::RegisteredAction;
 use crate::artifact_groups::calculation::ensure_artifact_group_staged;
+use crate::calculation::Calculation;
 use crate::deferred::calculation::DeferredCalculation;
 use crate::keep_going;
 
@@ -121,6 +122,7 @@ async fn build_action_no_redirect(
         .get_action_executor(action.execution_config())
         .await
         .context(format!("for action `{}`", action))?;
+    let artifact_fs = ctx.get_artifact_fs().await?;
 
     let now = Instant::now();
 
@@ -209,9 +211,12 @@ async fn build_action_no_redirect(
             .map(|outputs| {
                 outputs
                     .iter()
-                    .filter_map(|(_artifact, value)| {
+                    .filter_map(|(artifact, value)| {
+                        let digest = value.digest()?;
                         Some(buck2_data::ActionOutput {
-                            tiny_digest: value.digest()?.tiny_digest().to_string(),
+                            tiny_digest: digest.tiny_digest().to_string(),
+                            digest: digest.to_string(),
+                            path: artifact_fs.resolve_build(artifact).to_string(),
                         })
                     })
                     .collect()
@@ -398,18 +403,22 @@ pub async fn command_details(
                 .into()
             }
         }
-        CommandExecutionKind::Remote { digest } => buck2_data::RemoteCommand {
+        CommandExecutionKind::Remote { digest, .. } => buck2_data::RemoteCommand {
             action_digest: digest.to_string(),
             cache_hit: false,
             queue_time: command.timing.re_queue_time.and_then(|d| d.try_into().ok()),
         }
         .into(),
-        CommandExecutionKind::ActionCache { digest } => buck2_data::RemoteCommand {
+        CommandExecutionKind::ActionCache { digest, .. } => buck2_data::RemoteCommand {
             action_digest: digest.to_string(),
             cache_hit: true,
             queue_time: command.timing.re_queue_time.and_then(|d| d.try_into().ok()),
         }
         .into(),
+        CommandExecutionKind::LocalActionCache { digest } => buck2_data::LocalActionCacheCommand {
+            action_digest: digest.to_string(),
+        }
+        .into(),
     });
 
     buck2_data::CommandExecutionDetails {
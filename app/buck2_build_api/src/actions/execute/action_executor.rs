@@ -16,15 +16,25 @@ use async_trait::async_trait;
 use buck2_common::dice::data::HasIoProvider;
 use buck2_common::events::HasEvents;
 use buck2_common::executor_config::CommandExecutorConfig;
+use buck2_common::executor_config::Executor;
+use buck2_common::executor_config::RemoteExecutorUseCase;
+use buck2_common::file_ops::FileMetadata;
 use buck2_common::io::IoProvider;
 use buck2_common::liveliness_observer::NoopLivelinessObserver;
+use buck2_core::directory::DirectoryEntry;
 use buck2_core::fs::artifact_path_resolver::ArtifactFs;
 use buck2_core::fs::buck_out_path::BuckOutPath;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use buck2_events::dispatch::EventDispatcher;
 use buck2_execute::artifact::fs::ExecutorFs;
+use buck2_execute::artifact_utils::ArtifactValueBuilder;
 use buck2_execute::artifact_value::ArtifactValue;
 use buck2_execute::digest_config::DigestConfig;
 use buck2_execute::digest_config::HasDigestConfig;
+use buck2_execute::directory::new_symlink;
+use buck2_execute::directory::ActionDirectoryMember;
+use buck2_execute::execute::action_digest::ActionDigest;
 use buck2_execute::execute::blocking::BlockingExecutor;
 use buck2_execute::execute::blocking::HasBlockingExecutor;
 use buck2_execute::execute::claim::MutexClaimManager;
@@ -41,6 +51,8 @@ use buck2_execute::execute::request::OutputType;
 use buck2_execute::execute::result::CommandExecutionReport;
 use buck2_execute::execute::result::CommandExecutionResult;
 use buck2_execute::execute::result::CommandExecutionStatus;
+use buck2_execute::materialize::materializer::CasDownloadInfo;
+use buck2_execute::materialize::materializer::CopiedArtifact;
 use buck2_execute::materialize::materializer::HasMaterializer;
 use buck2_execute::materialize::materializer::Materializer;
 use buck2_execute::output_size::OutputCountAndBytes;
@@ -68,6 +80,16 @@ use crate::artifact_groups::ArtifactGroup;
 use crate::artifact_groups::ArtifactGroupValues;
 use crate::calculation::Calculation;
 
+#[derive(Debug, thiserror::Error)]
+enum OutputOptionsApplyError {
+    #[error("Output `{0}` is declared `executable`, but the action did not produce a file")]
+    ExecutableNotFile(ProjectRelativePathBuf),
+    #[error(
+        "Output `{0}` is declared `executable` and was produced remotely, but the executor has no RE use case"
+    )]
+    NoReUseCase(ProjectRelativePathBuf),
+}
+
 /// This is the result of the action as exposed to other things in the dice computation.
 #[derive(Clone, Dupe, Debug, PartialEq, Eq, Allocative)]
 pub struct ActionOutputs(Arc<ActionOutputsData>);
@@ -92,6 +114,37 @@ impl OutputSize for ActionOutputs {
 #[derivative(PartialEq, Eq)]
 struct ActionOutputsData {
     outputs: IndexMap<BuckOutPath, ArtifactValue>,
+    /// How the outputs were produced.
+    provenance: Option<ActionOutputsProvenance>,
+}
+
+/// How the outputs of an action which ran a command were produced, used to write provenance
+/// files for build outputs.
+///
+/// Only the action digest is compared: when an action is executed again with the same digest
+/// and produces the same outputs, the previous `ActionOutputs` are kept (and dependents are not
+/// invalidated), and the execution they describe is still one which produced these outputs from
+/// these inputs. A different digest (e.g. after inputs changed without changing the outputs)
+/// replaces them, so the provenance never describes an execution of another action.
+#[derive(Clone, Debug, Derivative, Allocative)]
+#[derivative(PartialEq, Eq)]
+pub struct ActionOutputsProvenance {
+    pub action_digest: ActionDigest,
+    #[derivative(PartialEq = "ignore")]
+    pub execution_kind: buck2_data::ActionExecutionKind,
+    /// The RE worker which executed the command, if it ran remotely.
+    #[derivative(PartialEq = "ignore")]
+    pub worker: Option<String>,
+}
+
+impl ActionOutputsProvenance {
+    fn new(kind: &CommandExecutionKind) -> Self {
+        Self {
+            action_digest: kind.digest().dupe(),
+            execution_kind: kind.as_enum(),
+            worker: kind.worker().map(|w| w.to_owned()),
+        }
+    }
 }
 
 /// Metadata associated with the execution of this action.
@@ -167,7 +220,23 @@ impl ActionExecutionKind {
 
 impl ActionOutputs {
     pub fn new(outputs: IndexMap<BuckOutPath, ArtifactValue>) -> Self {
-        Self(Arc::new(ActionOutputsData { outputs }))
+        Self(Arc::new(ActionOutputsData {
+            outputs,
+            provenance: None,
+        }))
+    }
+
+    fn with_provenance(self, provenance: Option<ActionOutputsProvenance>) -> Self {
+        match Arc::try_unwrap(self.0) {
+            Ok(mut data) => {
+                data.provenance = provenance;
+                Self(Arc::new(data))
+            }
+            Err(data) => Self(Arc::new(ActionOutputsData {
+                outputs: data.outputs.clone(),
+                provenance,
+            })),
+        }
     }
 
     pub fn from_single(artifact: BuckOutPath, value: ArtifactValue) -> Self {
@@ -185,6 +254,11 @@ impl ActionOutputs {
     pub fn values(&self) -> impl Iterator<Item = &ArtifactValue> {
         self.0.outputs.values()
     }
+
+    /// How the outputs were produced, if the action ran a command.
+    pub fn provenance(&self) -> Option<&ActionOutputsProvenance> {
+        self.0.provenance.as_ref()
+    }
 }
 
 /// Executes 'Actions'
@@ -218,17 +292,30 @@ impl HasActionExecutor for DiceComputations {
         let artifact_fs = self.get_artifact_fs().await?;
         let digest_config = self.global_data().get_digest_config();
 
-        let CommandExecutorResponse { executor, platform } =
-            self.get_command_executor(&artifact_fs, executor_config)?;
+        let CommandExecutorResponse {
+            executor,
+            platform,
+            platform_by_category,
+        } = self.get_command_executor(&artifact_fs, executor_config)?;
         let blocking_executor = self.get_blocking_executor();
         let materializer = self.per_transaction_data().get_materializer();
         let events = self.per_transaction_data().get_dispatcher().dupe();
         let re_client = self.per_transaction_data().get_re_client();
         let run_action_knobs = self.per_transaction_data().get_run_action_knobs();
         let io_provider = self.global_data().get_io_provider();
+        let re_use_case = match &executor_config.executor {
+            Executor::Local => None,
+            Executor::RemoteEnabled { re_use_case, .. } => Some(*re_use_case),
+        };
 
         Ok(Arc::new(BuckActionExecutor::new(
-            CommandExecutor::new(executor, artifact_fs, executor_config.options, platform),
+            CommandExecutor::new(
+                executor,
+                artifact_fs,
+                executor_config.options,
+                platform,
+                platform_by_category,
+            ),
             blocking_executor,
             materializer,
             events,
@@ -236,6 +323,7 @@ impl HasActionExecutor for DiceComputations {
             digest_config,
             run_action_knobs,
             io_provider,
+            re_use_case,
         )))
     }
 }
@@ -249,6 +337,8 @@ pub struct BuckActionExecutor {
     digest_config: DigestConfig,
     run_action_knobs: RunActionKnobs,
     io_provider: Arc<dyn IoProvider>,
+    /// The use case of the RE backend of the executor, if it has one.
+    re_use_case: Option<RemoteExecutorUseCase>,
 }
 
 impl BuckActionExecutor {
@@ -261,6 +351,7 @@ impl BuckActionExecutor {
         digest_config: DigestConfig,
         run_action_knobs: RunActionKnobs,
         io_provider: Arc<dyn IoProvider>,
+        re_use_case: Option<RemoteExecutorUseCase>,
     ) -> Self {
         Self {
             command_executor,
@@ -271,8 +362,134 @@ impl BuckActionExecutor {
             digest_config,
             run_action_knobs,
             io_provider,
+            re_use_case,
         }
     }
+
+    /// Apply the `OutputOptions` of the outputs once the action has run, so that they hold
+    /// regardless of the executor which ran it.
+    async fn apply_output_options(
+        &self,
+        outputs: &[BuildArtifact],
+        result: ActionOutputs,
+        execution_kind: &ActionExecutionKind,
+        cancellations: &CancellationContext,
+    ) -> anyhow::Result<ActionOutputs> {
+        if outputs.iter().all(|x| x.output_options().is_default()) {
+            return Ok(result);
+        }
+
+        let fs = self.command_executor.fs();
+        let mut values = result.0.outputs.clone();
+
+        // Executable outputs first, so that symlinks or copies of them see the updated value.
+        for output in outputs.iter().filter(|x| x.output_options().executable) {
+            let path = fs.resolve_build(output.get_path());
+            let value = values
+                .get_mut(output.get_path())
+                .context("Missing output")?;
+            let digest = match value.entry() {
+                DirectoryEntry::Leaf(ActionDirectoryMember::File(meta)) => {
+                    if meta.is_executable {
+                        continue;
+                    }
+                    meta.digest.dupe()
+                }
+                _ => return Err(OutputOptionsApplyError::ExecutableNotFile(path).into()),
+            };
+            let executable = ArtifactValue::file(FileMetadata {
+                digest,
+                is_executable: true,
+            });
+
+            match execution_kind.command().map(|c| c.kind) {
+                // The output is in the CAS and may not be materialized: declare it again, so that
+                // it gets the executable bit whenever it is materialized.
+                Some(CommandExecutionKind::Remote { .. })
+                | Some(CommandExecutionKind::ActionCache { .. }) => {
+                    let re_use_case = self
+                        .re_use_case
+                        .ok_or_else(|| OutputOptionsApplyError::NoReUseCase(path.clone()))?;
+                    self.materializer
+                        .declare_cas_many(
+                            Arc::new(CasDownloadInfo::new_declared(re_use_case)),
+                            vec![(path, executable.dupe())],
+                            cancellations,
+                        )
+                        .await?;
+                }
+                // The action wrote the output to disk.
+                Some(CommandExecutionKind::Local { .. })
+                | Some(CommandExecutionKind::LocalActionCache { .. }) => {
+                    fs_util::set_executable(fs.fs().resolve(&path))?;
+                    self.materializer
+                        .declare_existing(vec![(path, executable.dupe())])
+                        .await?;
+                }
+                // We don't know where the contents of the output come from, so we have to
+                // materialize it to change its permissions.
+                None => {
+                    self.materializer
+                        .ensure_materialized(vec![path.clone()])
+                        .await?;
+                    fs_util::set_executable(fs.fs().resolve(&path))?;
+                    self.materializer
+                        .declare_existing(vec![(path, executable.dupe())])
+                        .await?;
+                }
+            }
+            *value = executable;
+        }
+
+        for output in outputs.iter() {
+            let target = match &output.output_options().symlink_target {
+                Some(target) => target,
+                None => continue,
+            };
+            let path = fs.resolve_build(output.get_path());
+            let target_path = path
+                .parent()
+                .context("Output has no parent directory")?
+                .join_normalized(&**target)?;
+            // If the target is another output of this action, record it as a dependency of the
+            // symlink, so that it is materialized along with it.
+            let target_value = outputs
+                .iter()
+                .filter(|x| x.get_path() != output.get_path())
+                .find(|x| fs.resolve_build(x.get_path()) == target_path)
+                .and_then(|x| values.get(x.get_path()))
+                .map(|v| v.dupe());
+
+            let mut builder = ArtifactValueBuilder::new(fs.fs(), self.digest_config);
+            let (value, srcs) = match target_value {
+                // Creating symlinks on Windows requires privileges users often don't have, so
+                // fall back to copying the target.
+                Some(target_value) if cfg!(windows) => {
+                    let entry = builder.add_copied(&target_value, &target_path, &path)?;
+                    let srcs = vec![CopiedArtifact::new(
+                        target_path,
+                        path.clone(),
+                        entry.map_dir(|d| d.as_immutable()),
+                    )];
+                    (builder.build(&path)?, srcs)
+                }
+                Some(target_value) => {
+                    builder.add_symlinked(&target_value, &target_path, &path)?;
+                    (builder.build(&path)?, Vec::new())
+                }
+                None => (
+                    ArtifactValue::from(DirectoryEntry::Leaf(new_symlink(&**target)?)),
+                    Vec::new(),
+                ),
+            };
+            self.materializer
+                .declare_copy(path, value.dupe(), srcs, cancellations)
+                .await?;
+            values.insert(output.get_path().clone(), value);
+        }
+
+        Ok(ActionOutputs::new(values))
+    }
 }
 
 struct BuckActionExecutionContext<'a> {
@@ -323,7 +540,7 @@ impl ActionExecutionCtx for BuckActionExecutionContext<'_> {
     }
 
     fn run_action_knobs(&self) -> RunActionKnobs {
-        self.executor.run_action_knobs
+        self.executor.run_action_knobs.dupe()
     }
 
     fn cancellation_context(&self) -> &CancellationContext {
@@ -522,7 +739,14 @@ impl ActionExecutor for BuckActionExecutor {
                     Err(ExecuteError::MismatchedOutputs { declared, real })
                 }
             } else {
-                Ok((result, metadata))
+                let result = self
+                    .apply_output_options(&outputs, result, &metadata.execution_kind, cancellations)
+                    .await?;
+                let provenance = metadata
+                    .execution_kind
+                    .command()
+                    .map(|command| ActionOutputsProvenance::new(command.kind));
+                Ok((result.with_provenance(provenance), metadata))
             }
         }
         .await;
@@ -569,10 +793,12 @@ mod tests {
     use buck2_events::dispatch::EventDispatcher;
     use buck2_execute::artifact_value::ArtifactValue;
     use buck2_execute::digest_config::DigestConfig;
+    use buck2_execute::execute::action_digest::ActionDigest;
     use buck2_execute::execute::blocking::testing::DummyBlockingExecutor;
     use buck2_execute::execute::clean_output_paths::cleanup_path;
     use buck2_execute::execute::command_executor::ActionExecutionTimingData;
     use buck2_execute::execute::command_executor::CommandExecutor;
+    use buck2_execute::execute::kind::CommandExecutionKind;
     use buck2_execute::execute::request::CommandExecutionInput;
     use buck2_execute::execute::request::CommandExecutionOutput;
     use buck2_execute::execute::request::CommandExecutionPaths;
@@ -582,6 +808,7 @@ mod tests {
     use buck2_execute::materialize::nodisk::NoDiskMaterializer;
     use buck2_execute::re::manager::ManagedRemoteExecutionClient;
     use dupe::Dupe;
+    use indexmap::indexmap;
     use indexmap::indexset;
     use more_futures::cancellation::CancellationContext;
     use once_cell::sync::Lazy;
@@ -596,6 +823,7 @@ mod tests {
     use crate::actions::execute::action_executor::ActionExecutionMetadata;
     use crate::actions::execute::action_executor::ActionExecutor;
     use crate::actions::execute::action_executor::ActionOutputs;
+    use crate::actions::execute::action_executor::ActionOutputsProvenance;
     use crate::actions::execute::action_executor::BuckActionExecutor;
     use crate::actions::key::ActionKey;
     use crate::actions::Action;
@@ -641,6 +869,7 @@ mod tests {
                     output_paths_behavior: Default::default(),
                 },
                 Default::default(),
+                Default::default(),
             ),
             Arc::new(DummyBlockingExecutor {
                 fs: project_fs.dupe(),
@@ -654,6 +883,7 @@ mod tests {
                 project_fs,
                 CasDigestConfig::testing_default(),
             )),
+            None,
         );
 
         #[derive(Debug, Allocative)]
@@ -809,6 +1039,56 @@ mod tests {
         assert_eq!(res.0, ActionOutputs::new(outputs));
     }
 
+    #[test]
+    fn test_action_outputs_provenance() {
+        let digest_config = DigestConfig::testing_default();
+        let digest = ActionDigest::new_sha1([1; 20], 10);
+        let other_digest = ActionDigest::new_sha1([2; 20], 10);
+        let output = BuildArtifact::testing_new(
+            TargetLabel::testing_parse("cell//pkg:foo").configure(ConfigurationData::testing_new()),
+            ForwardRelativePathBuf::unchecked_new("output".into()),
+            DeferredId::testing_new(0),
+        );
+        let outputs = || {
+            indexmap! {
+                output.get_path().dupe() => ArtifactValue::file(digest_config.empty_file()),
+            }
+        };
+        let with_kind = |kind: &CommandExecutionKind| {
+            ActionOutputs::new(outputs()).with_provenance(Some(ActionOutputsProvenance::new(kind)))
+        };
+
+        let remote = with_kind(&CommandExecutionKind::Remote {
+            digest: digest.dupe(),
+            worker: Some("worker-1".to_owned()),
+        });
+        let provenance = remote.provenance().unwrap();
+        assert_eq!(provenance.action_digest, digest);
+        assert_eq!(
+            provenance.execution_kind,
+            buck2_data::ActionExecutionKind::Remote
+        );
+        assert_eq!(provenance.worker.as_deref(), Some("worker-1"));
+
+        // Executing the same action again is the same provenance, however it ran.
+        assert_eq!(
+            remote,
+            with_kind(&CommandExecutionKind::ActionCache {
+                digest: digest.dupe(),
+                worker: Some("worker-2".to_owned()),
+            })
+        );
+        // Another action producing the same outputs is not.
+        assert_ne!(
+            remote,
+            with_kind(&CommandExecutionKind::Remote {
+                digest: other_digest,
+                worker: Some("worker-1".to_owned()),
+            })
+        );
+        assert_ne!(remote, ActionOutputs::new(outputs()));
+    }
+
     #[test]
     fn test_cleanup_path_missing() -> anyhow::Result<()> {
         let fs = ProjectRootTemp::new()?;
@@ -7,11 +7,146 @@
  * of this source tree.
  */
 
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::Context;
+use buck2_common::legacy_configs::LegacyBuckConfig;
 use dice::UserComputationData;
 use dupe::Dupe;
+use host_sharing::MemoryRequirement;
+use host_sharing::WeightClass;
+use host_sharing::WeightPercentage;
+
+#[derive(Debug, thiserror::Error)]
+enum ActionResourcesError {
+    #[error("Expected `key=value`, got `{0}`")]
+    NotKeyValue(String),
+    #[error("Unknown action resource `{0}`, expected `weight` or `memory`")]
+    UnknownResource(String),
+    #[error("`weight` must be a positive integer or a percentage, got `{0}`")]
+    InvalidWeight(String),
+}
+
+/// Local resources a `run` action reserves while it executes. Parsed from strings like
+/// `weight=4, memory=8G`, where `weight` may also be a percentage of the host (e.g. `weight=50%`).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ActionResources {
+    pub weight: Option<WeightClass>,
+    pub memory: Option<MemoryRequirement>,
+}
+
+impl FromStr for ActionResources {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut res = ActionResources::default();
+        for item in s.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+            let (key, value) = item
+                .split_once('=')
+                .ok_or_else(|| ActionResourcesError::NotKeyValue(item.to_owned()))?;
+            let value = value.trim();
+            match key.trim() {
+                "weight" => res.weight = Some(parse_weight(value)?),
+                "memory" => {
+                    res.memory = Some(value.parse().context("Invalid `memory`")?);
+                }
+                key => return Err(ActionResourcesError::UnknownResource(key.to_owned()).into()),
+            }
+        }
+        Ok(res)
+    }
+}
+
+fn parse_weight(value: &str) -> anyhow::Result<WeightClass> {
+    let invalid = || ActionResourcesError::InvalidWeight(value.to_owned());
+    match value.strip_suffix('%') {
+        Some(percentage) => {
+            let percentage: u32 = percentage.trim().parse().map_err(|_| invalid())?;
+            Ok(WeightClass::Percentage(WeightPercentage::try_new(
+                percentage,
+            )?))
+        }
+        None => match value.parse::<usize>() {
+            Ok(permits) if permits > 0 => Ok(WeightClass::Permits(permits)),
+            _ => Err(invalid().into()),
+        },
+    }
+}
+
+/// Default `ActionResources` per action category, used for `run` actions that don't specify
+/// them. Configured via the `[action_resources]` buckconfig section, with one key per category:
+///
+/// ```ini
+/// [action_resources]
+/// cxx_link = weight=4, memory=8G
+/// ```
+#[derive(Debug, Default)]
+pub struct ActionResourceDefaults {
+    categories: HashMap<String, ActionResources>,
+}
+
+impl ActionResourceDefaults {
+    pub const SECTION: &'static str = "action_resources";
+
+    pub fn from_config(config: &LegacyBuckConfig) -> anyhow::Result<Self> {
+        let mut categories = HashMap::new();
+        if let Some(section) = config.get_section(Self::SECTION) {
+            for (category, value) in section.iter() {
+                let resources =
+                    LegacyBuckConfig::parse_impl(Self::SECTION, category, value.as_str())?;
+                categories.insert(category.to_owned(), resources);
+            }
+        }
+        Ok(Self { categories })
+    }
+
+    pub fn get(&self, category: &str) -> ActionResources {
+        self.categories.get(category).copied().unwrap_or_default()
+    }
+}
+
+/// Limits on the inputs of `run` actions, beyond which a warning is emitted, to catch accidental
+/// dependency explosions early. Configured via the `[action_input_budget]` buckconfig section,
+/// where all keys are optional:
+///
+/// ```ini
+/// [action_input_budget]
+/// max_inputs = 100000
+/// max_input_bytes = 10000000000
+/// max_command_line_length = 1000000
+/// ```
+#[derive(Debug, Default, Clone, Copy, Dupe, PartialEq, Eq)]
+pub struct ActionInputBudget {
+    /// Maximum number of input files (and symlinks).
+    pub max_inputs: Option<u64>,
+    /// Maximum total size of the input files.
+    pub max_input_bytes: Option<u64>,
+    /// Maximum length of the command line, with its arguments separated by spaces.
+    pub max_command_line_length: Option<u64>,
+}
+
+impl ActionInputBudget {
+    pub const SECTION: &'static str = "action_input_budget";
+
+    pub fn from_config(config: &LegacyBuckConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            max_inputs: config.parse(Self::SECTION, "max_inputs")?,
+            max_input_bytes: config.parse(Self::SECTION, "max_input_bytes")?,
+            max_command_line_length: config.parse(Self::SECTION, "max_command_line_length")?,
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_inputs.is_some()
+            || self.max_input_bytes.is_some()
+            || self.max_command_line_length.is_some()
+    }
+}
 
 /// Knobs controlling how RunAction works.
-#[derive(Copy, Clone, Dupe, Default)]
+#[derive(Clone, Dupe, Default)]
 pub struct RunActionKnobs {
     /// Process dep files as they are generated.
     pub eager_dep_files: bool,
@@ -24,6 +159,12 @@ pub struct RunActionKnobs {
     /// for network actions (download_file, cas_artifact). Used to support offline
     /// builds.
     pub use_network_action_output_cache: bool,
+
+    /// Resources reserved by run actions that don't declare their own, keyed by category.
+    pub action_resource_defaults: Arc<ActionResourceDefaults>,
+
+    /// Limits on the inputs of run actions, beyond which we warn.
+    pub action_input_budget: ActionInputBudget,
 }
 
 pub trait HasRunActionKnobs {
@@ -38,9 +179,91 @@ impl HasRunActionKnobs for UserComputationData {
     }
 
     fn get_run_action_knobs(&self) -> RunActionKnobs {
-        *self
-            .data
+        self.data
             .get::<RunActionKnobs>()
             .expect("RunActionKnobs should be set")
+            .dupe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_common::legacy_configs::testing::legacy_buck_config_from_entries;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_action_resources() {
+        assert_eq!(
+            "weight=4, memory=8G".parse::<ActionResources>().unwrap(),
+            ActionResources {
+                weight: Some(WeightClass::Permits(4)),
+                memory: Some(MemoryRequirement::from_megabytes(8 * 1024)),
+            }
+        );
+        assert_eq!(
+            "weight=50%".parse::<ActionResources>().unwrap(),
+            ActionResources {
+                weight: Some(WeightClass::Percentage(
+                    WeightPercentage::try_new(50).unwrap()
+                )),
+                memory: None,
+            }
+        );
+        assert_eq!(
+            "".parse::<ActionResources>().unwrap(),
+            ActionResources::default()
+        );
+
+        assert!("weight=0".parse::<ActionResources>().is_err());
+        assert!("weight=101%".parse::<ActionResources>().is_err());
+        assert!("cpu=4".parse::<ActionResources>().is_err());
+        assert!("memory".parse::<ActionResources>().is_err());
+    }
+
+    #[test]
+    fn test_action_resource_defaults_from_config() -> anyhow::Result<()> {
+        let config = legacy_buck_config_from_entries([
+            ("action_resources", "cxx_link", "weight=4, memory=8G"),
+            ("action_resources", "cxx_compile", "memory=512M"),
+        ])?;
+        let defaults = ActionResourceDefaults::from_config(&config)?;
+
+        assert_eq!(
+            defaults.get("cxx_link").weight,
+            Some(WeightClass::Permits(4))
+        );
+        assert_eq!(
+            defaults.get("cxx_compile").memory,
+            Some(MemoryRequirement::from_megabytes(512))
+        );
+        assert_eq!(defaults.get("other"), ActionResources::default());
+        Ok(())
+    }
+
+    #[test]
+    fn test_action_input_budget_from_config() -> anyhow::Result<()> {
+        let config = legacy_buck_config_from_entries([
+            ("action_input_budget", "max_inputs", "1000"),
+            ("action_input_budget", "max_command_line_length", "50000"),
+        ])?;
+        let budget = ActionInputBudget::from_config(&config)?;
+        assert_eq!(
+            budget,
+            ActionInputBudget {
+                max_inputs: Some(1000),
+                max_input_bytes: None,
+                max_command_line_length: Some(50000),
+            }
+        );
+        assert!(budget.is_enabled());
+
+        let config = legacy_buck_config_from_entries([])?;
+        assert!(!ActionInputBudget::from_config(&config)?.is_enabled());
+
+        let config =
+            legacy_buck_config_from_entries([("action_input_budget", "max_inputs", "many")])?;
+        assert!(ActionInputBudget::from_config(&config).is_err());
+        Ok(())
     }
 }
@@ -51,6 +51,7 @@ use buck2_execute::artifact_value::ArtifactValue;
 use buck2_execute::digest_config::DigestConfig;
 use buck2_execute::execute::blocking::BlockingExecutor;
 use buck2_execute::execute::request::CommandExecutionRequest;
+use buck2_execute::execute::request::Nondeterminism;
 use buck2_execute::materialize::materializer::Materializer;
 use buck2_execute::re::manager::ManagedRemoteExecutionClient;
 use derivative::Derivative;
@@ -132,6 +133,11 @@ pub trait Action: Allocative + Debug + Send + Sync + 'static {
         false
     }
 
+    /// Whether the outputs of this action may differ between runs, as declared by the rule.
+    fn nondeterminism(&self) -> Nondeterminism {
+        Nondeterminism::Deterministic
+    }
+
     /// Provides a string name for this action, obtained by combining the provided category and identifier.
     fn name(&self) -> String {
         if let Some(identifier) = self.identifier() {
@@ -12,11 +12,13 @@ use std::collections::HashSet;
 use std::sync::Arc;
 
 use allocative::Allocative;
+use anyhow::Context;
 use buck2_core::category::Category;
 use buck2_core::directory;
 use buck2_core::directory::Directory;
 use buck2_core::directory::DirectoryBuilder;
 use buck2_core::directory::DirectoryEntry;
+use buck2_core::directory::DirectoryFindError;
 use buck2_core::directory::DirectoryInsertError;
 use buck2_core::directory::DirectoryIterator;
 use buck2_core::directory::NoDigest;
@@ -55,6 +57,10 @@ pub struct ActionsRegistry {
     )>,
     execution_platform: ExecutionPlatformResolution,
     claimed_output_paths: DirectoryBuilder<Option<FileSpan>, NoDigest>,
+    /// Short paths of outputs declared with a `short_path_prefix`.
+    claimed_short_paths: DirectoryBuilder<Option<FileSpan>, NoDigest>,
+    /// Short paths of the other outputs, which are allowed to conflict with each other.
+    other_short_paths: DirectoryBuilder<Option<FileSpan>, NoDigest>,
 }
 
 impl ActionsRegistry {
@@ -66,6 +72,8 @@ impl ActionsRegistry {
             pending: Default::default(),
             execution_platform,
             claimed_output_paths: DirectoryBuilder::empty(),
+            claimed_short_paths: DirectoryBuilder::empty(),
+            other_short_paths: DirectoryBuilder::empty(),
         }
     }
 
@@ -81,7 +89,7 @@ impl ActionsRegistry {
         // We don't want to claim path, because the output belongs to different (outer) context. We
         // also don't care to keep track of the hidden components count since this output will
         // never escape the dynamic lambda.
-        DeclaredArtifact::new(path, output_type, 0)
+        DeclaredArtifact::new(path, output_type, 0, None)
     }
 
     pub fn claim_output_path(
@@ -89,69 +97,44 @@ impl ActionsRegistry {
         path: &ForwardRelativePath,
         declaration_location: Option<FileSpan>,
     ) -> anyhow::Result<()> {
-        match self
-            .claimed_output_paths
-            .insert(path, DirectoryEntry::Leaf(declaration_location))
-        {
-            Ok(None) => Ok(()),
-            Ok(Some(conflict)) => match conflict {
-                DirectoryEntry::Leaf(_payload) => Err(anyhow::anyhow!(
-                    ActionErrors::ConflictingOutputPath(path.to_owned())
-                )),
-                DirectoryEntry::Dir(conflict_dir) => {
-                    let conflicting_paths = conflict_dir
-                        .ordered_walk()
-                        .with_paths()
-                        .filter_map(|(p, entry)| match entry {
-                            DirectoryEntry::Leaf(location) => Some(format!(
-                                "{} declared at {}",
-                                path.join(p),
-                                location
-                                    .as_ref()
-                                    .map_or(&"<unknown>" as _, |l| l as &dyn std::fmt::Display)
-                            )),
-                            _ => None,
-                        })
-                        .collect::<Vec<_>>();
-                    Err(anyhow::anyhow!(ActionErrors::ConflictingOutputPaths(
-                        path.to_owned(),
-                        conflicting_paths,
-                    )))
-                }
-            },
-            Err(DirectoryInsertError::EmptyPath) => {
-                Err(anyhow::anyhow!(ActionErrors::EmptyOutputPath))
-            }
-            Err(DirectoryInsertError::CannotTraverseLeaf { path: conflict }) => {
-                let location = match directory::find(&self.claimed_output_paths, &conflict) {
-                    Ok(Some(DirectoryEntry::Leaf(l))) => l.as_ref(),
-                    _ => None,
-                };
-
-                let conflict = format!(
-                    "{} declared at {}",
-                    conflict,
-                    location
-                        .as_ref()
-                        .map_or(&"<unknown>" as _, |l| l as &dyn std::fmt::Display)
-                );
-
-                Err(anyhow::anyhow!(ActionErrors::ConflictingOutputPaths(
-                    path.to_owned(),
-                    vec![conflict],
-                )))
-            }
-        }
+        claim_path(&mut self.claimed_output_paths, path, declaration_location)
     }
 
     /// Declares a new output file that will be generated by some action.
     pub fn declare_artifact(
         &mut self,
         prefix: Option<ForwardRelativePathBuf>,
+        short_path_prefix: Option<ForwardRelativePathBuf>,
         path: ForwardRelativePathBuf,
         output_type: OutputType,
         declaration_location: Option<FileSpan>,
     ) -> anyhow::Result<DeclaredArtifact> {
+        // Artifacts declaring their short path must not collide with the short path of any other
+        // output, as they are meant to be laid out together (e.g. in runfiles).
+        match &short_path_prefix {
+            None => {
+                check_unclaimed(&self.claimed_short_paths, &path)
+                    .with_context(|| format!("Error declaring short path `{}`", path))?;
+                // Other outputs may share a short path, so conflicts are ignored: the entry that
+                // remains covers the conflicting path as well.
+                let _ignored = self
+                    .other_short_paths
+                    .insert(&path, DirectoryEntry::Leaf(declaration_location.dupe()));
+            }
+            Some(short_path_prefix) => {
+                let short_path = short_path_prefix.join(&path);
+                check_unclaimed(&self.other_short_paths, &short_path)
+                    .and_then(|()| {
+                        claim_path(
+                            &mut self.claimed_short_paths,
+                            &short_path,
+                            declaration_location.dupe(),
+                        )
+                    })
+                    .with_context(|| format!("Error declaring short path `{}`", short_path))?;
+            }
+        }
+        let short_path_prefix = short_path_prefix.map(Arc::new);
         let (path, hidden) = match prefix {
             None => (path, 0),
             Some(prefix) => (prefix.join(path), prefix.iter().count()),
@@ -162,7 +145,7 @@ impl ActionsRegistry {
             path,
             self.action_key.dupe(),
         );
-        let declared = DeclaredArtifact::new(out_path, output_type, hidden);
+        let declared = DeclaredArtifact::new(out_path, output_type, hidden, short_path_prefix);
         if !self.artifacts.insert(declared.dupe()) {
             panic!("not expected duplicate artifact after output path was successfully claimed");
         }
@@ -264,3 +247,89 @@ impl ActionsRegistry {
         self.pending.iter().map(|(reserved, _)| reserved)
     }
 }
+
+/// Fails if `path` conflicts with a path in `claimed`, without claiming it.
+fn check_unclaimed(
+    claimed: &DirectoryBuilder<Option<FileSpan>, NoDigest>,
+    path: &ForwardRelativePath,
+) -> anyhow::Result<()> {
+    let conflicts = match directory::find(claimed, path) {
+        Ok(None) | Err(DirectoryFindError::EmptyPath) => return Ok(()),
+        Ok(Some(DirectoryEntry::Leaf(location))) => {
+            vec![describe_claimed(path, location.as_ref())]
+        }
+        Ok(Some(DirectoryEntry::Dir(conflict_dir))) => conflict_dir
+            .ordered_walk()
+            .with_paths()
+            .filter_map(|(p, entry)| match entry {
+                DirectoryEntry::Leaf(location) => {
+                    Some(describe_claimed(&path.join(p), location.as_ref()))
+                }
+                _ => None,
+            })
+            .collect(),
+        Err(DirectoryFindError::CannotTraverseLeaf { path: conflict }) => {
+            let location = match directory::find(claimed, &conflict) {
+                Ok(Some(DirectoryEntry::Leaf(l))) => l.as_ref(),
+                _ => None,
+            };
+            vec![describe_claimed(&conflict, location)]
+        }
+    };
+    Err(anyhow::anyhow!(ActionErrors::ConflictingOutputPaths(
+        path.to_owned(),
+        conflicts,
+    )))
+}
+
+fn describe_claimed(path: &dyn std::fmt::Display, location: Option<&FileSpan>) -> String {
+    format!(
+        "{} declared at {}",
+        path,
+        location.map_or(&"<unknown>" as _, |l| l as &dyn std::fmt::Display)
+    )
+}
+
+/// Reserves `path` in `claimed`, failing if it conflicts with an already claimed path.
+fn claim_path(
+    claimed: &mut DirectoryBuilder<Option<FileSpan>, NoDigest>,
+    path: &ForwardRelativePath,
+    declaration_location: Option<FileSpan>,
+) -> anyhow::Result<()> {
+    match claimed.insert(path, DirectoryEntry::Leaf(declaration_location)) {
+        Ok(None) => Ok(()),
+        Ok(Some(conflict)) => match conflict {
+            DirectoryEntry::Leaf(_payload) => Err(anyhow::anyhow!(
+                ActionErrors::ConflictingOutputPath(path.to_owned())
+            )),
+            DirectoryEntry::Dir(conflict_dir) => {
+                let conflicting_paths = conflict_dir
+                    .ordered_walk()
+                    .with_paths()
+                    .filter_map(|(p, entry)| match entry {
+                        DirectoryEntry::Leaf(location) => {
+                            Some(describe_claimed(&path.join(p), location.as_ref()))
+                        }
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                Err(anyhow::anyhow!(ActionErrors::ConflictingOutputPaths(
+                    path.to_owned(),
+                    conflicting_paths,
+                )))
+            }
+        },
+        Err(DirectoryInsertError::EmptyPath) => Err(anyhow::anyhow!(ActionErrors::EmptyOutputPath)),
+        Err(DirectoryInsertError::CannotTraverseLeaf { path: conflict }) => {
+            let location = match directory::find(claimed, &conflict) {
+                Ok(Some(DirectoryEntry::Leaf(l))) => l.as_ref(),
+                _ => None,
+            };
+
+            Err(anyhow::anyhow!(ActionErrors::ConflictingOutputPaths(
+                path.to_owned(),
+                vec![describe_claimed(&conflict, location)],
+            )))
+        }
+    }
+}
@@ -18,6 +18,7 @@ use buck2_core::base_deferred_key_dyn::BaseDeferredKeyDynImpl;
 use buck2_core::collections::sorted_map::SortedMap;
 use buck2_core::configuration::data::ConfigurationData;
 use buck2_core::configuration::pair::ConfigurationNoExec;
+use buck2_core::fs::buck_out_path::BuckOutPathScheme;
 use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
 use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
@@ -128,11 +129,17 @@ impl BaseDeferredKeyDynImpl for AnonTarget {
     fn make_hashed_path(
         &self,
         base: &ProjectRelativePath,
+        scheme: &BuckOutPathScheme,
         prefix: &ForwardRelativePath,
         action_key: Option<&str>,
         path: &ForwardRelativePath,
     ) -> ProjectRelativePathBuf {
         let cell_relative_path = self.name().pkg().cell_relative_path().as_str();
+        let output_hash = self.exec_cfg().cfg().output_hash().as_str();
+        let short_output_hash = scheme.short_config_hash(output_hash, None);
+        let output_hash = short_output_hash.as_deref().unwrap_or(output_hash);
+        let short_package_path = scheme.short_package_path(cell_relative_path);
+        let cell_relative_path = short_package_path.as_deref().unwrap_or(cell_relative_path);
 
         // It is performance critical that we use slices and allocate via `join` instead of
         // repeated calls to `join` on the path object because `join` allocates on each call,
@@ -144,7 +151,7 @@ impl BaseDeferredKeyDynImpl for AnonTarget {
             "-anon/",
             self.name().pkg().cell_name().as_str(),
             "/",
-            self.exec_cfg().cfg().output_hash().as_str(),
+            output_hash,
             cell_relative_path,
             if cell_relative_path.is_empty() {
                 ""
@@ -88,8 +88,10 @@ use thiserror::Error;
 
 use crate::analysis::anon_target_node::AnonTarget;
 use crate::analysis::calculation::get_rule_impl;
+use crate::analysis::calculation::starlark_fail_to_proto;
 use crate::analysis::calculation::RuleAnalysisCalculation;
 use crate::analysis::registry::AnalysisRegistry;
+use crate::analysis::warnings::filter_suppressed_warnings;
 use crate::analysis::AnalysisResult;
 use crate::analysis::RuleAnalysisAttrResolutionContext;
 use crate::analysis::RuleImplFunction;
@@ -369,7 +371,7 @@ impl AnonTargetKey {
                 rule: self.0.rule_type().to_string(),
             },
             async move {
-                let analysis_registry = {
+                let (analysis_registry, warnings) = {
                     let mut eval = Evaluator::new(&env);
                     eval.set_print_handler(&print);
 
@@ -415,7 +417,7 @@ impl AnonTargetKey {
                     env.set("", res);
 
                     // Pull the ctx object back out, and steal ctx.action's state back
-                    ctx.take_state()
+                    (ctx.take_state(), ctx.take_warnings())
                 };
                 let (frozen_env, deferreds) = analysis_registry.finalize(&env)(env)?;
 
@@ -425,15 +427,19 @@ impl AnonTargetKey {
 
                 // this could look nicer if we had the entire analysis be a deferred
                 let deferred = DeferredTable::new(deferreds.take_result()?);
-                Ok(AnalysisResult::new(provider_collection, deferred, None))
+                let warnings = filter_suppressed_warnings(dice, warnings).await?;
+                let result = AnalysisResult::new(provider_collection, deferred, None);
+                Ok(result.with_warnings(warnings))
             }
             .map(|res| {
+                let fail = res.as_ref().err().and_then(starlark_fail_to_proto);
                 (
                     res,
                     buck2_data::AnalysisEnd {
                         target: Some(self.0.as_proto().into()),
                         rule: self.0.rule_type().to_string(),
                         profile: None, // Not implemented for anon targets
+                        fail,
                     },
                 )
             }),
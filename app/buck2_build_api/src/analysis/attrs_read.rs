@@ -0,0 +1,181 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Recording of the attributes read by analysis.
+//!
+//! A change to a target which only touches attributes that its analysis did not read (e.g.
+//! `labels` or `contacts`) does not need the target to be analysed again. The attributes read by
+//! the analysis of a node are recorded with the node in DICE, and the next version of the node is
+//! only compared to it on these attributes (see `AnalysisNodeProjectionKey`).
+
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use allocative::Allocative;
+use buck2_node::attrs::inspect_options::AttrInspectOptions;
+use buck2_node::nodes::configured::ConfiguredTargetNode;
+use dupe::Dupe;
+
+/// The attributes of a target read by its analysis.
+#[derive(Debug, Clone, Eq, PartialEq, Allocative)]
+pub enum AttrsRead {
+    /// All the attributes may have been read, e.g. because the attributes struct was listed
+    /// with `dir()` or escaped the analysis.
+    All,
+    Some(BTreeSet<String>),
+}
+
+impl Default for AttrsRead {
+    fn default() -> Self {
+        AttrsRead::Some(BTreeSet::new())
+    }
+}
+
+impl AttrsRead {
+    /// Whether all the attributes in `other` are also in `self`.
+    pub fn contains(&self, other: &AttrsRead) -> bool {
+        match (self, other) {
+            (AttrsRead::All, _) => true,
+            (AttrsRead::Some(_), AttrsRead::All) => false,
+            (AttrsRead::Some(this), AttrsRead::Some(other)) => other.is_subset(this),
+        }
+    }
+
+    /// Add the attributes in `other`.
+    pub fn union(&mut self, other: &AttrsRead) {
+        match (&mut *self, other) {
+            (AttrsRead::All, _) => {}
+            (AttrsRead::Some(_), AttrsRead::All) => *self = AttrsRead::All,
+            (AttrsRead::Some(this), AttrsRead::Some(other)) => this.extend(other.iter().cloned()),
+        }
+    }
+
+    /// Whether the two nodes of the same target are equal as far as an analysis which reads
+    /// these attributes can tell.
+    pub(crate) fn nodes_equal(&self, x: &ConfiguredTargetNode, y: &ConfiguredTargetNode) -> bool {
+        let attrs = match self {
+            AttrsRead::All => return x == y,
+            AttrsRead::Some(attrs) => attrs,
+        };
+        // Besides the attributes, analysis depends on the analysis of the deps of the target
+        // and the results of its queries, whichever attributes they come from.
+        x.label() == y.label()
+            && x.rule_type() == y.rule_type()
+            && x.execution_platform_resolution() == y.execution_platform_resolution()
+            && x.deps().map(|d| d.label()).eq(y.deps().map(|d| d.label()))
+            && x.queries().eq(y.queries())
+            && attrs.iter().all(|attr| {
+                let x = x.get(attr, AttrInspectOptions::All).map(|a| a.value);
+                let y = y.get(attr, AttrInspectOptions::All).map(|a| a.value);
+                x == y
+            })
+    }
+}
+
+/// Records the attributes read during an analysis.
+#[derive(Debug, Default)]
+pub struct AttrsReadRecorder(RefCell<AttrsRead>);
+
+impl AttrsReadRecorder {
+    pub fn record(&self, attr: &str) {
+        if let AttrsRead::Some(attrs) = &mut *self.0.borrow_mut() {
+            if !attrs.contains(attr) {
+                attrs.insert(attr.to_owned());
+            }
+        }
+    }
+
+    pub fn record_all(&self) {
+        *self.0.borrow_mut() = AttrsRead::All;
+    }
+
+    pub fn take(&self) -> AttrsRead {
+        self.0.take()
+    }
+}
+
+/// The union of the attributes read by the analyses of a node, empty until the node was
+/// analysed. It is part of the DICE value of the node, so it is dropped with it and never shared
+/// between two versions of the node.
+///
+/// DICE keeps the previous value of the node when a new version is equal to it, so the node may be
+/// analysed again (e.g. because a dep changed) while it is kept, and that analysis may read other
+/// attributes: these are added to the ones the next version is compared on.
+#[derive(Debug, Default, Clone, Dupe, Allocative)]
+pub(crate) struct AttrsReadCell(Arc<Mutex<Option<AttrsRead>>>);
+
+impl AttrsReadCell {
+    pub(crate) fn get(&self) -> Option<AttrsRead> {
+        self.0.lock().unwrap().clone()
+    }
+
+    pub(crate) fn record(&self, attrs_read: &AttrsRead) {
+        let mut recorded = self.0.lock().unwrap();
+        match &mut *recorded {
+            Some(recorded) => recorded.union(attrs_read),
+            None => *recorded = Some(attrs_read.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use crate::analysis::attrs_read::AttrsRead;
+    use crate::analysis::attrs_read::AttrsReadCell;
+    use crate::analysis::attrs_read::AttrsReadRecorder;
+
+    fn attrs_read(attrs: &[&str]) -> AttrsRead {
+        AttrsRead::Some(
+            attrs
+                .iter()
+                .map(|a| (*a).to_owned())
+                .collect::<BTreeSet<_>>(),
+        )
+    }
+
+    #[test]
+    fn test_contains() {
+        assert!(AttrsRead::All.contains(&AttrsRead::All));
+        assert!(AttrsRead::All.contains(&attrs_read(&["srcs"])));
+        assert!(!attrs_read(&["srcs"]).contains(&AttrsRead::All));
+        assert!(attrs_read(&["deps", "srcs"]).contains(&attrs_read(&["srcs"])));
+        assert!(!attrs_read(&["srcs"]).contains(&attrs_read(&["deps", "srcs"])));
+        assert!(attrs_read(&[]).contains(&attrs_read(&[])));
+    }
+
+    #[test]
+    fn test_cell_records_union() {
+        let cell = AttrsReadCell::default();
+        assert_eq!(None, cell.get());
+        cell.record(&attrs_read(&["srcs"]));
+        cell.record(&attrs_read(&["deps"]));
+        assert_eq!(Some(attrs_read(&["deps", "srcs"])), cell.get());
+        cell.record(&AttrsRead::All);
+        cell.record(&attrs_read(&["labels"]));
+        assert_eq!(Some(AttrsRead::All), cell.get());
+    }
+
+    #[test]
+    fn test_recorder() {
+        let recorder = AttrsReadRecorder::default();
+        recorder.record("srcs");
+        recorder.record("deps");
+        recorder.record("srcs");
+        assert_eq!(attrs_read(&["deps", "srcs"]), recorder.take());
+
+        recorder.record("srcs");
+        recorder.record_all();
+        recorder.record("deps");
+        assert_eq!(AttrsRead::All, recorder.take());
+    }
+}
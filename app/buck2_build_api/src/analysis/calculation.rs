@@ -12,6 +12,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Instant;
 
+use allocative::Allocative;
 use anyhow::Context;
 use async_trait::async_trait;
 use buck2_common::result::SharedResult;
@@ -23,7 +24,9 @@ use buck2_core::target::label::TargetLabel;
 use buck2_data::ToProtoMessage;
 use buck2_events::dispatch::current_span;
 use buck2_events::dispatch::span_async;
+use buck2_events::span::SpanId;
 use buck2_interpreter::dice::starlark_profiler::GetStarlarkProfilerInstrumentation;
+use buck2_interpreter::functions::fail::find_starlark_fail;
 use buck2_interpreter::path::StarlarkModulePath;
 use buck2_interpreter::starlark_profiler::StarlarkProfileDataAndStats;
 use buck2_interpreter::starlark_profiler::StarlarkProfileModeOrInstrumentation;
@@ -36,8 +39,11 @@ use buck2_query::query::compatibility::MaybeCompatible;
 use buck2_query::query::syntax::simple::eval::evaluator::QueryEvaluator;
 use buck2_query::query::syntax::simple::eval::label_indexed::LabelIndexedSet;
 use buck2_query::query::syntax::simple::eval::set::TargetSet;
+use derive_more::Display;
 use dice::DiceComputations;
+use dice::DiceProjectionComputations;
 use dice::Key;
+use dice::ProjectionKey;
 use dupe::Dupe;
 use dupe::IterDupedExt;
 use futures::stream::FuturesOrdered;
@@ -49,6 +55,8 @@ use starlark::eval::ProfileMode;
 use crate::actions::build_listener::AnalysisSignal;
 use crate::actions::build_listener::HasBuildSignals;
 use crate::actions::build_listener::NodeDuration;
+use crate::analysis::attrs_read::AttrsRead;
+use crate::analysis::attrs_read::AttrsReadCell;
 use crate::analysis::calculation::keys::AnalysisKey;
 use crate::analysis::configured_graph::AnalysisConfiguredGraphQueryDelegate;
 use crate::analysis::configured_graph::AnalysisDiceQueryDelegate;
@@ -59,6 +67,7 @@ use crate::analysis::RuleImplFunction;
 use crate::attrs::resolve::ctx::AnalysisQueryResult;
 use crate::interpreter::rule_defs::provider::collection::FrozenProviderCollectionValue;
 use crate::keep_going;
+use crate::nodes::calculation::ConfiguredTargetNodeKey;
 use crate::nodes::calculation::NodeCalculation;
 use crate::query::analysis::environment::AnalysisQueryError;
 use crate::query::analysis::environment::ConfiguredGraphQueryEnvironment;
@@ -243,6 +252,20 @@ pub async fn get_dep_analysis<'v>(
     .await
 }
 
+/// Extract the code and metadata of a `fail()` call which caused analysis to fail.
+pub(crate) fn starlark_fail_to_proto(err: &anyhow::Error) -> Option<buck2_data::StarlarkFail> {
+    let fail = find_starlark_fail(err)?;
+    Some(buck2_data::StarlarkFail {
+        message: fail.message().to_owned(),
+        code: fail.code().map(str::to_owned),
+        metadata: fail
+            .metadata()
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect(),
+    })
+}
+
 pub(crate) async fn get_rule_impl(
     ctx: &DiceComputations,
     func: &StarlarkRuleType,
@@ -261,21 +284,114 @@ async fn get_analysis_result(
     target: &ConfiguredTargetLabel,
     profile_mode: &StarlarkProfileModeOrInstrumentation,
 ) -> anyhow::Result<MaybeCompatible<AnalysisResult>> {
-    let configured_node: MaybeCompatible<ConfiguredTargetNode> =
-        ctx.get_configured_target_node(target).await?;
-    let configured_node: ConfiguredTargetNode = match configured_node {
-        MaybeCompatible::Incompatible(reason) => return Ok(MaybeCompatible::Incompatible(reason)),
-        MaybeCompatible::Compatible(configured_node) => configured_node,
+    // Only depend on the attributes read by the analysis of the previous version of the node, so
+    // that changes to the other attributes do not cause it to be analysed again.
+    let analysis_node = ctx
+        .compute_opaque(&ConfiguredTargetNodeKey(target.dupe()))
+        .await?
+        .projection(&AnalysisNodeProjectionKey)?;
+    let mut configured_node: ConfiguredTargetNode =
+        match analysis_node.node.dupe().unshared_error()? {
+            MaybeCompatible::Incompatible(reason) => {
+                return Ok(MaybeCompatible::Incompatible(reason));
+            }
+            MaybeCompatible::Compatible(configured_node) => configured_node,
+        };
+    let recorded = analysis_node.attrs_read.get();
+
+    let mut now = Instant::now();
+
+    let mut span_id = None;
+
+    let mut res = analyze_node(
+        ctx,
+        target,
+        &configured_node,
+        profile_mode,
+        &mut now,
+        &mut span_id,
+    )
+    .await;
+
+    // DICE keeps the node it has if the current version only differs from it on attributes which
+    // were not read before. If this analysis read any other attribute (e.g. because a dep
+    // changed), it may have seen a stale value, so analyse the current version of the node.
+    let may_be_stale = match (&recorded, &res) {
+        (Some(recorded), Ok((_, attrs_read))) => !recorded.contains(attrs_read),
+        _ => false,
     };
+    if may_be_stale {
+        if let Ok((_, attrs_read)) = &res {
+            analysis_node.attrs_read.record(attrs_read);
+        }
+        match ctx.get_configured_target_node(target).await? {
+            MaybeCompatible::Incompatible(reason) => {
+                return Ok(MaybeCompatible::Incompatible(reason));
+            }
+            MaybeCompatible::Compatible(current) => {
+                if current != configured_node {
+                    configured_node = current;
+                    res = analyze_node(
+                        ctx,
+                        target,
+                        &configured_node,
+                        profile_mode,
+                        &mut now,
+                        &mut span_id,
+                    )
+                    .await;
+                }
+            }
+        }
+    }
 
-    let mut dep_analysis = get_dep_analysis(&configured_node, ctx).await?;
+    let res = match res {
+        Ok((result, attrs_read)) => {
+            analysis_node.attrs_read.record(&attrs_read);
+            Ok(MaybeCompatible::Compatible(
+                result.with_attrs_read(attrs_read),
+            ))
+        }
+        Err(e) => {
+            // Failures may come from any attribute.
+            analysis_node.attrs_read.record(&AttrsRead::All);
+            Err(e)
+        }
+    };
 
-    let now = Instant::now();
+    if let Some(signals) = ctx.per_transaction_data().get_build_signals() {
+        let duration = now.elapsed();
 
-    let mut span_id = None;
+        signals.signal(AnalysisSignal {
+            label: target.dupe(),
+            node: configured_node,
+            duration: NodeDuration {
+                user: duration,
+                total: duration,
+            },
+            span_id,
+        });
+    }
+
+    res
+}
+
+/// Run the analysis of a node, returning the attributes the analysis read. `start` is set once the
+/// deps have been analysed.
+async fn analyze_node(
+    ctx: &DiceComputations,
+    target: &ConfiguredTargetLabel,
+    configured_node: &ConfiguredTargetNode,
+    profile_mode: &StarlarkProfileModeOrInstrumentation,
+    start: &mut Instant,
+    span_id: &mut Option<SpanId>,
+) -> anyhow::Result<(AnalysisResult, AttrsRead)> {
+    let mut dep_analysis = get_dep_analysis(configured_node, ctx).await?;
+
+    *start = Instant::now();
 
     let func = configured_node.rule_type();
-    let res = match func {
+    match func {
         RuleType::Starlark(func) => {
             let rule_impl = get_rule_impl(ctx, func).await?;
             let start_event = buck2_data::AnalysisStart {
@@ -285,10 +401,10 @@ async fn get_analysis_result(
 
             span_async(start_event, async {
                 let mut profile = None;
-                span_id = current_span();
+                *span_id = current_span();
 
                 let result: anyhow::Result<_> = try {
-                    let query_results = resolve_queries(ctx, &configured_node).await?;
+                    let query_results = resolve_queries(ctx, configured_node).await?;
 
                     let result = span_async(
                         buck2_data::AnalysisStageStart {
@@ -303,7 +419,7 @@ async fn get_analysis_result(
                                     query_results,
                                     configured_node.execution_platform_resolution(),
                                     &rule_impl,
-                                    &configured_node,
+                                    configured_node,
                                     profile_mode,
                                 )
                                 .await,
@@ -313,17 +429,19 @@ async fn get_analysis_result(
                     )
                     .await?;
 
-                    profile = Some(make_analysis_profile(&result));
+                    profile = Some(make_analysis_profile(&result.0));
 
-                    MaybeCompatible::Compatible(result)
+                    result
                 };
 
+                let fail = result.as_ref().err().and_then(starlark_fail_to_proto);
                 (
                     result,
                     buck2_data::AnalysisEnd {
                         target: Some(target.as_proto().into()),
                         rule: func.to_string(),
                         profile,
+                        fail,
                     },
                 )
             })
@@ -331,25 +449,53 @@ async fn get_analysis_result(
         }
         RuleType::Forward => {
             assert!(dep_analysis.len() == 1);
-            Ok(MaybeCompatible::Compatible(dep_analysis.pop().unwrap().1))
+            Ok((dep_analysis.pop().unwrap().1, AttrsRead::All))
         }
-    };
+    }
+}
 
-    if let Some(signals) = ctx.per_transaction_data().get_build_signals() {
-        let duration = now.elapsed();
+/// The configured target node, as seen by its analysis: changes to the attributes which the
+/// analysis of the previous version of the node did not read are ignored.
+#[derive(Debug, Display, Hash, PartialEq, Eq, Clone, Dupe, Allocative)]
+#[display(fmt = "{:?}", self)]
+struct AnalysisNodeProjectionKey;
+
+#[derive(Clone, Dupe, Allocative)]
+struct AnalysisNode {
+    node: SharedResult<MaybeCompatible<ConfiguredTargetNode>>,
+    /// Recorded by the analyses of the node.
+    attrs_read: AttrsReadCell,
+}
 
-        signals.signal(AnalysisSignal {
-            label: target.dupe(),
-            node: configured_node,
-            duration: NodeDuration {
-                user: duration,
-                total: duration,
-            },
-            span_id,
-        });
+impl ProjectionKey for AnalysisNodeProjectionKey {
+    type DeriveFromKey = ConfiguredTargetNodeKey;
+    type Value = AnalysisNode;
+
+    fn compute(
+        &self,
+        node: &SharedResult<MaybeCompatible<ConfiguredTargetNode>>,
+        _ctx: &DiceProjectionComputations,
+    ) -> AnalysisNode {
+        AnalysisNode {
+            node: node.dupe(),
+            attrs_read: AttrsReadCell::default(),
+        }
     }
 
-    res
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        match (&x.node, &y.node) {
+            (Ok(MaybeCompatible::Compatible(x_node)), Ok(MaybeCompatible::Compatible(y_node))) => {
+                // DICE keeps `x` if the two are equal, so only the attributes read by its analyses
+                // matter: the analysis of `x` would read the same ones from `y`.
+                match x.attrs_read.get() {
+                    Some(attrs_read) => attrs_read.nodes_equal(x_node, y_node),
+                    None => x_node == y_node,
+                }
+            }
+            (Ok(x), Ok(y)) => x == y,
+            _ => false,
+        }
+    }
 }
 
 fn make_analysis_profile(res: &AnalysisResult) -> buck2_data::AnalysisProfile {
@@ -457,6 +603,7 @@ pub mod testing {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeSet;
     use std::collections::HashMap;
     use std::sync::Arc;
 
@@ -472,9 +619,12 @@ mod tests {
     use buck2_core::cells::cell_root_path::CellRootPathBuf;
     use buck2_core::cells::name::CellName;
     use buck2_core::cells::CellAliasResolver;
+    use buck2_core::cells::CellResolver;
     use buck2_core::cells::CellsAggregator;
     use buck2_core::collections::ordered_map::OrderedMap;
+    use buck2_core::collections::unordered_map::UnorderedMap;
     use buck2_core::configuration::data::ConfigurationData;
+    use buck2_core::configuration::pair::ConfigurationNoExec;
     use buck2_core::fs::project::ProjectRootTemp;
     use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
     use buck2_core::package::PackageLabel;
@@ -487,6 +637,7 @@ mod tests {
     use buck2_execute::execute::dice_data::set_fallback_executor_config;
     use buck2_interpreter::extra::InterpreterHostArchitecture;
     use buck2_interpreter::extra::InterpreterHostPlatform;
+    use buck2_interpreter::file_loader::LoadedModule;
     use buck2_interpreter::file_loader::LoadedModules;
     use buck2_interpreter::path::OwnedStarlarkModulePath;
     use buck2_interpreter_for_build::interpreter::calculation::testing::InterpreterResultsKey;
@@ -494,14 +645,34 @@ mod tests {
     use buck2_interpreter_for_build::interpreter::dice_calculation_delegate::testing::EvalImportKey;
     use buck2_interpreter_for_build::interpreter::interpreter_setup::setup_interpreter_basic;
     use buck2_interpreter_for_build::interpreter::testing::Tester;
+    use buck2_node::attrs::attr::Attribute;
+    use buck2_node::attrs::attr_type::string::StringLiteral;
+    use buck2_node::attrs::attr_type::AttrType;
+    use buck2_node::attrs::coerced_attr::CoercedAttr;
+    use buck2_node::configuration::execution::ExecutionPlatformResolution;
+    use buck2_node::configuration::resolved::ResolvedConfiguration;
+    use buck2_node::nodes::configured::ConfiguredTargetNode;
+    use buck2_node::nodes::eval_result::EvaluationResult;
+    use buck2_node::nodes::unconfigured::testing::TargetNodeExt;
+    use buck2_node::nodes::unconfigured::TargetNode;
+    use buck2_node::rule_type::RuleType;
+    use buck2_node::rule_type::StarlarkRuleType;
+    use buck2_query::query::compatibility::MaybeCompatible;
     use dice::testing::DiceBuilder;
+    use dice::DiceTransaction;
+    use dice::ProjectionKey;
     use dice::UserComputationData;
     use dupe::Dupe;
     use indoc::indoc;
     use itertools::Itertools;
     use maplit::hashmap;
 
+    use crate::analysis::attrs_read::AttrsRead;
+    use crate::analysis::attrs_read::AttrsReadCell;
+    use crate::analysis::calculation::AnalysisNode;
+    use crate::analysis::calculation::AnalysisNodeProjectionKey;
     use crate::analysis::calculation::RuleAnalysisCalculation;
+    use crate::analysis::AnalysisResult;
     use crate::configuration::calculation::ExecutionPlatformsKey;
     use crate::deferred::types::testing::DeferredAnalysisResultExt;
     use crate::interpreter::build_defs::register_provider;
@@ -510,9 +681,7 @@ mod tests {
     use crate::keep_going::HasKeepGoing;
     use crate::spawner::BuckSpawner;
 
-    #[tokio::test]
-    async fn test_analysis_calculation() -> anyhow::Result<()> {
-        let bzlfile = ImportPath::testing_new("cell//pkg:foo.bzl");
+    fn testing_tester() -> anyhow::Result<(Tester, CellResolver, LegacyBuckConfigs)> {
         let resolver = {
             let mut cells = CellsAggregator::new();
             cells.add_cell_entry(
@@ -540,46 +709,18 @@ mod tests {
         ))?;
         interpreter.additional_globals(register_rule_defs);
         interpreter.additional_globals(register_provider);
-        let module = interpreter
-            .eval_import(
-                &bzlfile,
-                indoc!(r#"
-                            FooInfo = provider(fields=["str"])
-
-                            def impl(ctx):
-                                str = ""
-                                if ctx.attrs.dep:
-                                    str = ctx.attrs.dep[FooInfo].str
-                                return [FooInfo(str=(str + ctx.attrs.str)), DefaultInfo()]
-                            foo_binary = rule(impl=impl, attrs={"dep": attrs.option(attrs.dep(providers=[FooInfo]), default = None), "str": attrs.string()})
-                        "#),
-                LoadedModules::default(),
-            )?;
-
-        let buildfile = BuildFilePath::testing_new("cell//pkg:BUCK");
-        let eval_res = interpreter.eval_build_file_with_loaded_modules(
-            &buildfile,
-            indoc!(
-                r#"
-                    load(":foo.bzl", "FooInfo", "foo_binary")
+        Ok((interpreter, resolver, configs))
+    }
 
-                    foo_binary(
-                        name = "rule1",
-                        str = "a",
-                        dep = ":rule2",
-                    )
-                    foo_binary(
-                        name = "rule2",
-                        str = "b",
-                        dep = ":rule3",
-                    )
-                    foo_binary(
-                        name = "rule3",
-                        str = "c",
-                        dep = None,
-                    )
-                "#
-            ),
+    fn eval_build_file(
+        interpreter: &Tester,
+        bzlfile: &ImportPath,
+        module: &LoadedModule,
+        content: &str,
+    ) -> anyhow::Result<Arc<EvaluationResult>> {
+        Ok(Arc::new(interpreter.eval_build_file_with_loaded_modules(
+            &BuildFilePath::testing_new("cell//pkg:BUCK"),
+            content,
             LoadedModules {
                 map: OrderedMap::from_iter([(
                     OwnedStarlarkModulePath::LoadFile(bzlfile.clone()),
@@ -587,9 +728,17 @@ mod tests {
                 )]),
             },
             PackageListing::testing_new(&[], "BUCK"),
-        )?;
+        )?))
+    }
 
-        let fs = ProjectRootTemp::new()?;
+    async fn testing_dice(
+        resolver: CellResolver,
+        configs: LegacyBuckConfigs,
+        fs: &ProjectRootTemp,
+        bzlfile: &ImportPath,
+        module: LoadedModule,
+        eval_res: Arc<EvaluationResult>,
+    ) -> anyhow::Result<DiceTransaction> {
         let mut dice = DiceBuilder::new()
             .mock_and_return(
                 EvalImportKey(OwnedStarlarkModulePath::LoadFile(bzlfile.clone())),
@@ -597,11 +746,11 @@ mod tests {
             )
             .mock_and_return(
                 InterpreterResultsKey(PackageLabel::testing_parse("cell//pkg")),
-                Ok(Arc::new(eval_res)),
+                Ok(eval_res),
             )
             .mock_and_return(ExecutionPlatformsKey, Ok(None))
             .set_data(|data| {
-                data.set_testing_io_provider(&fs);
+                data.set_testing_io_provider(fs);
                 data.set_digest_config(DigestConfig::testing_default());
             })
             .build({
@@ -624,6 +773,7 @@ mod tests {
                 InterpreterHostArchitecture::X86_64,
                 None,
                 false,
+                false,
                 |_| {},
                 |_| {},
                 register_rule_defs,
@@ -632,7 +782,58 @@ mod tests {
             )?,
             configs,
         )?;
-        let dice = dice.commit().await;
+        Ok(dice.commit().await)
+    }
+
+    #[tokio::test]
+    async fn test_analysis_calculation() -> anyhow::Result<()> {
+        let bzlfile = ImportPath::testing_new("cell//pkg:foo.bzl");
+        let (interpreter, resolver, configs) = testing_tester()?;
+        let module = interpreter
+            .eval_import(
+                &bzlfile,
+                indoc!(r#"
+                            FooInfo = provider(fields=["str"])
+
+                            def impl(ctx):
+                                str = ""
+                                if ctx.attrs.dep:
+                                    str = ctx.attrs.dep[FooInfo].str
+                                return [FooInfo(str=(str + ctx.attrs.str)), DefaultInfo()]
+                            foo_binary = rule(impl=impl, attrs={"dep": attrs.option(attrs.dep(providers=[FooInfo]), default = None), "str": attrs.string()})
+                        "#),
+                LoadedModules::default(),
+            )?;
+
+        let eval_res = eval_build_file(
+            &interpreter,
+            &bzlfile,
+            &module,
+            indoc!(
+                r#"
+                    load(":foo.bzl", "FooInfo", "foo_binary")
+
+                    foo_binary(
+                        name = "rule1",
+                        str = "a",
+                        dep = ":rule2",
+                    )
+                    foo_binary(
+                        name = "rule2",
+                        str = "b",
+                        dep = ":rule3",
+                    )
+                    foo_binary(
+                        name = "rule3",
+                        str = "c",
+                        dep = None,
+                    )
+                "#
+            ),
+        )?;
+
+        let fs = ProjectRootTemp::new()?;
+        let dice = testing_dice(resolver, configs, &fs, &bzlfile, module, eval_res).await?;
 
         let analysis = dice
             .get_analysis_result(
@@ -674,4 +875,209 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_analysis_sees_attrs_not_read_before() -> anyhow::Result<()> {
+        let bzlfile = ImportPath::testing_new("cell//pkg:foo.bzl");
+        let (interpreter, resolver, configs) = testing_tester()?;
+        let module = interpreter
+            .eval_import(
+                &bzlfile,
+                indoc!(r#"
+                            FooInfo = provider(fields=["str"])
+
+                            def impl(ctx):
+                                str = ctx.attrs.str
+                                if ctx.attrs.dep:
+                                    str = ctx.attrs.dep[FooInfo].str + str
+                                    if ctx.attrs.dep[FooInfo].str == "read_extra":
+                                        str += ctx.attrs.extra
+                                return [FooInfo(str=str), DefaultInfo()]
+                            foo_binary = rule(impl=impl, attrs={"dep": attrs.option(attrs.dep(providers=[FooInfo]), default = None), "str": attrs.string(), "extra": attrs.string(default = "")})
+                        "#),
+                LoadedModules::default(),
+            )?;
+        let build_file = |dep_str: &str, extra: &str| {
+            eval_build_file(
+                &interpreter,
+                &bzlfile,
+                &module,
+                &format!(
+                    indoc!(
+                        r#"
+                            load(":foo.bzl", "FooInfo", "foo_binary")
+
+                            foo_binary(
+                                name = "rule1",
+                                str = "a",
+                                extra = "{extra}",
+                                dep = ":rule2",
+                            )
+                            foo_binary(
+                                name = "rule2",
+                                str = "{dep_str}",
+                            )
+                        "#
+                    ),
+                    dep_str = dep_str,
+                    extra = extra,
+                ),
+            )
+        };
+        let foo_info = |analysis: &AnalysisResult| {
+            analysis
+                .provider_collection
+                .provider_collection()
+                .get_provider_raw(&ProviderId::testing_new(bzlfile.path().clone(), "FooInfo"))
+                .unwrap()
+                .to_value()
+                .to_repr()
+        };
+        let target = TargetLabel::testing_parse("cell//pkg:rule1")
+            .configure(ConfigurationData::testing_new());
+        let package = PackageLabel::testing_parse("cell//pkg");
+
+        let fs = ProjectRootTemp::new()?;
+        let dice = testing_dice(
+            resolver,
+            configs,
+            &fs,
+            &bzlfile,
+            module.dupe(),
+            build_file("b", "x")?,
+        )
+        .await?;
+        let analysis = dice
+            .get_analysis_result(&target)
+            .await?
+            .require_compatible()?;
+        assert_eq!(r#"FooInfo(str="ba")"#, foo_info(&analysis));
+
+        // `extra` was not read, so the node DICE keeps is the previous one.
+        let mut updater = dice.into_updater();
+        updater.changed_to(vec![(
+            InterpreterResultsKey(package.dupe()),
+            Ok(build_file("b", "y")?),
+        )])?;
+        let dice = updater.commit().await;
+        let analysis = dice
+            .get_analysis_result(&target)
+            .await?
+            .require_compatible()?;
+        assert_eq!(r#"FooInfo(str="ba")"#, foo_info(&analysis));
+
+        // The dep changes, so `extra` is read: its value must be the current one.
+        let mut updater = dice.into_updater();
+        updater.changed_to(vec![(
+            InterpreterResultsKey(package.dupe()),
+            Ok(build_file("read_extra", "y")?),
+        )])?;
+        let dice = updater.commit().await;
+        let analysis = dice
+            .get_analysis_result(&target)
+            .await?
+            .require_compatible()?;
+        assert_eq!(r#"FooInfo(str="read_extraay")"#, foo_info(&analysis));
+        assert!(
+            analysis
+                .attrs_read()
+                .contains(&AttrsRead::Some(BTreeSet::from([
+                    "dep".to_owned(),
+                    "extra".to_owned(),
+                    "str".to_owned(),
+                ])))
+        );
+
+        Ok(())
+    }
+
+    fn testing_node(srcs: &str, description: &str) -> ConfiguredTargetNode {
+        let label =
+            TargetLabel::testing_parse("cell//pkg:foo").configure(ConfigurationData::testing_new());
+        let rule_type = RuleType::Starlark(Arc::new(StarlarkRuleType {
+            import_path: ImportPath::testing_new("cell//pkg:foo.bzl"),
+            name: "foo_library".to_owned(),
+        }));
+        let string = |s: &str| CoercedAttr::String(StringLiteral(s.into()));
+        let target_node = TargetNode::testing_new(
+            label.unconfigured().dupe(),
+            rule_type,
+            vec![
+                (
+                    "description",
+                    Attribute::new(None, "", AttrType::string()),
+                    string(description),
+                ),
+                (
+                    "srcs",
+                    Attribute::new(None, "", AttrType::string()),
+                    string(srcs),
+                ),
+            ],
+        );
+        ConfiguredTargetNode::new(
+            label.dupe(),
+            target_node,
+            ResolvedConfiguration::new(
+                ConfigurationNoExec::new(label.cfg().dupe()),
+                UnorderedMap::new(),
+            ),
+            OrderedMap::new(),
+            ExecutionPlatformResolution::new(None, Vec::new()),
+            None,
+            Vec::new(),
+            Vec::new(),
+            OrderedMap::new(),
+        )
+    }
+
+    #[test]
+    fn test_analysis_node_skips_unread_attrs() {
+        let analysis_node = |node| AnalysisNode {
+            node: Ok(MaybeCompatible::Compatible(node)),
+            attrs_read: AttrsReadCell::default(),
+        };
+        let analysed = analysis_node(testing_node("a.c", "old"));
+        let changed_description = analysis_node(testing_node("a.c", "new"));
+        let changed_srcs = analysis_node(testing_node("b.c", "old"));
+
+        // Until the node is analysed, any change to it is a change.
+        assert!(!AnalysisNodeProjectionKey::equality(
+            &analysed,
+            &changed_description
+        ));
+
+        analysed
+            .attrs_read
+            .record(&AttrsRead::Some(BTreeSet::from(["srcs".to_owned()])));
+        assert!(!AnalysisNodeProjectionKey::equality(
+            &analysed,
+            &changed_srcs
+        ));
+        assert!(AnalysisNodeProjectionKey::equality(
+            &analysed,
+            &changed_description
+        ));
+
+        // Comparing does not record anything.
+        assert!(changed_srcs.attrs_read.get().is_none());
+        assert!(changed_description.attrs_read.get().is_none());
+
+        // Attributes read by a later analysis of the kept version are compared too.
+        analysed
+            .attrs_read
+            .record(&AttrsRead::Some(BTreeSet::from(["description".to_owned()])));
+        assert!(!AnalysisNodeProjectionKey::equality(
+            &analysed,
+            &changed_description
+        ));
+
+        // Analyses which read all the attributes see every change.
+        let read_all = analysis_node(testing_node("a.c", "old"));
+        read_all.attrs_read.record(&AttrsRead::All);
+        assert!(!AnalysisNodeProjectionKey::equality(
+            &read_all,
+            &analysis_node(testing_node("a.c", "new"))
+        ));
+    }
 }
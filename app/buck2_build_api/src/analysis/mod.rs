@@ -37,7 +37,10 @@ use starlark::values::ValueLike;
 use starlark::values::ValueTyped;
 use thiserror::Error;
 
+use crate::analysis::attrs_read::AttrsRead;
 use crate::analysis::registry::AnalysisRegistry;
+use crate::analysis::warnings::filter_suppressed_warnings;
+use crate::analysis::warnings::AnalysisWarning;
 use crate::attrs::resolve::ctx::AnalysisQueryResult;
 use crate::attrs::resolve::ctx::AttrResolutionContext;
 use crate::deferred::types::DeferredId;
@@ -45,14 +48,17 @@ use crate::deferred::types::DeferredLookup;
 use crate::deferred::types::DeferredTable;
 use crate::interpreter::rule_defs::cmd_args::FrozenCommandLineArgLike;
 use crate::interpreter::rule_defs::context::AnalysisContext;
+use crate::interpreter::rule_defs::package_values::PackageValuesLookup;
 use crate::interpreter::rule_defs::provider::builtin::template_placeholder_info::FrozenTemplatePlaceholderInfo;
 use crate::interpreter::rule_defs::provider::collection::ProviderCollection;
 
 pub mod anon_target_node;
 pub(crate) mod anon_targets;
+pub mod attrs_read;
 pub mod calculation;
 pub(crate) mod configured_graph;
 pub mod registry;
+pub mod warnings;
 
 use allocative::Allocative;
 use buck2_interpreter::types::label::Label;
@@ -82,6 +88,10 @@ pub struct AnalysisResult {
     provider_collection: FrozenProviderCollectionValue,
     deferred: DeferredTable,
     profile_data: Option<Arc<StarlarkProfileDataAndStats>>,
+    /// Warnings emitted with `ctx.emit_warning` which were not suppressed.
+    warnings: Arc<Vec<AnalysisWarning>>,
+    /// The attributes of the target read by the analysis.
+    attrs_read: Arc<AttrsRead>,
 }
 
 impl AnalysisResult {
@@ -95,9 +105,33 @@ impl AnalysisResult {
             provider_collection,
             deferred,
             profile_data,
+            warnings: Arc::new(Vec::new()),
+            attrs_read: Arc::new(AttrsRead::All),
         }
     }
 
+    pub fn with_warnings(self, warnings: Vec<AnalysisWarning>) -> Self {
+        Self {
+            warnings: Arc::new(warnings),
+            ..self
+        }
+    }
+
+    pub fn warnings(&self) -> &[AnalysisWarning] {
+        &self.warnings
+    }
+
+    pub fn with_attrs_read(self, attrs_read: AttrsRead) -> Self {
+        Self {
+            attrs_read: Arc::new(attrs_read),
+            ..self
+        }
+    }
+
+    pub fn attrs_read(&self) -> &AttrsRead {
+        &self.attrs_read
+    }
+
     pub fn providers(&self) -> &FrozenProviderCollectionValue {
         &self.provider_collection
     }
@@ -139,6 +173,10 @@ impl<'v> AttrResolutionContext<'v> for RuleAnalysisAttrResolutionContext<'v> {
         get_dep(&self.dep_analysis_results, target, self.module)
     }
 
+    fn all_deps(&self) -> Vec<(ConfiguredProvidersLabel, FrozenProviderCollectionValue)> {
+        all_deps(&self.dep_analysis_results)
+    }
+
     fn resolve_unkeyed_placeholder(
         &self,
         name: &str,
@@ -171,6 +209,20 @@ pub fn get_dep<'v>(
     }
 }
 
+pub fn all_deps<'v>(
+    dep_analysis_results: &HashMap<&'v ConfiguredTargetLabel, FrozenProviderCollectionValue>,
+) -> Vec<(ConfiguredProvidersLabel, FrozenProviderCollectionValue)> {
+    dep_analysis_results
+        .iter()
+        .map(|(label, providers)| {
+            (
+                ConfiguredProvidersLabel::new((*label).dupe(), ProvidersName::Default),
+                providers.dupe(),
+            )
+        })
+        .collect()
+}
+
 pub fn resolve_unkeyed_placeholder<'v>(
     dep_analysis_results: &HashMap<&'v ConfiguredTargetLabel, FrozenProviderCollectionValue>,
     name: &str,
@@ -236,7 +288,7 @@ async fn run_analysis<'a>(
     impl_function: &'a dyn RuleImplFunction,
     node: &ConfiguredTargetNode,
     profile_mode: &StarlarkProfileModeOrInstrumentation,
-) -> anyhow::Result<AnalysisResult> {
+) -> anyhow::Result<(AnalysisResult, AttrsRead)> {
     let analysis_env = AnalysisEnv::new(
         label,
         results,
@@ -280,7 +332,7 @@ fn run_analysis_with_env<'a>(
     analysis_env: AnalysisEnv<'a>,
     node: &'a ConfiguredTargetNode,
     profile_mode: &'a StarlarkProfileModeOrInstrumentation,
-) -> impl Future<Output = anyhow::Result<AnalysisResult>> + Send + 'a {
+) -> impl Future<Output = anyhow::Result<(AnalysisResult, AttrsRead)>> + Send + 'a {
     let fut = async move {
         run_analysis_with_env_underlying(dice, analysis_env, node, profile_mode).await
     };
@@ -292,7 +344,7 @@ async fn run_analysis_with_env_underlying(
     analysis_env: AnalysisEnv<'_>,
     node: &ConfiguredTargetNode,
     profile_mode: &StarlarkProfileModeOrInstrumentation,
-) -> anyhow::Result<AnalysisResult> {
+) -> anyhow::Result<(AnalysisResult, AttrsRead)> {
     let env = Module::new();
     let print = EventDispatcherPrintHandler(get_dispatcher());
 
@@ -329,23 +381,26 @@ async fn run_analysis_with_env_underlying(
         Some(profiler) => StarlarkProfilerOrInstrumentation::for_profiler(profiler),
     };
 
-    let analysis_registry = {
+    let (analysis_registry, warnings, attrs_read) = {
         let mut eval = Evaluator::new(&env);
         eval.set_print_handler(&print);
 
-        let ctx = env.heap().alloc_typed(AnalysisContext::new(
-            eval.heap(),
-            attributes,
-            Some(
-                eval.heap()
-                    .alloc_typed(Label::new(ConfiguredProvidersLabel::new(
-                        analysis_env.label,
-                        ProvidersName::Default,
-                    ))),
-            ),
-            registry,
-            dice.global_data().get_digest_config(),
-        ));
+        let ctx = env.heap().alloc_typed(
+            AnalysisContext::new(
+                eval.heap(),
+                attributes,
+                Some(
+                    eval.heap()
+                        .alloc_typed(Label::new(ConfiguredProvidersLabel::new(
+                            analysis_env.label,
+                            ProvidersName::Default,
+                        ))),
+                ),
+                registry,
+                dice.global_data().get_digest_config(),
+            )
+            .with_package_values(PackageValuesLookup::new(dice.dupe(), node.label().pkg())),
+        );
 
         profiler.initialize(&mut eval)?;
 
@@ -363,10 +418,12 @@ async fn run_analysis_with_env_underlying(
         env.set_extra_value(res);
 
         // Pull the ctx object back out, and steal ctx.action's state back
-        ctx.take_state()
+        (ctx.take_state(), ctx.take_warnings(), ctx.attrs_read())
     };
 
     let (frozen_env, deferreds) = analysis_registry.finalize(&env)(env)?;
+    // Only taken after freezing, which records the attributes escaping the analysis.
+    let attrs_read = attrs_read.take();
 
     profiler
         .visit_frozen_module(Some(&frozen_env))
@@ -382,11 +439,9 @@ async fn run_analysis_with_env_underlying(
 
     // this could look nicer if we had the entire analysis be a deferred
     let deferred = DeferredTable::new(deferreds.take_result()?);
-    Ok(AnalysisResult::new(
-        provider_collection,
-        deferred,
-        profile_data,
-    ))
+    let warnings = filter_suppressed_warnings(dice, warnings).await?;
+    let result = AnalysisResult::new(provider_collection, deferred, profile_data);
+    Ok((result.with_warnings(warnings), attrs_read))
 }
 
 pub fn get_user_defined_rule_impl(
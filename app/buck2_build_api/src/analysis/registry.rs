@@ -128,6 +128,7 @@ impl<'v> AnalysisRegistry<'v> {
     pub fn declare_output(
         &mut self,
         prefix: Option<&str>,
+        short_path_prefix: Option<&str>,
         filename: &str,
         output_type: OutputType,
         declaration_location: Option<FileSpan>,
@@ -145,8 +146,17 @@ impl<'v> AnalysisRegistry<'v> {
             None => None,
             Some(x) => Some(ForwardRelativePath::new(x)?.to_owned()),
         };
-        self.actions
-            .declare_artifact(prefix, path, output_type, declaration_location)
+        let short_path_prefix = match short_path_prefix {
+            None => None,
+            Some(x) => Some(ForwardRelativePath::new(x)?.to_owned()),
+        };
+        self.actions.declare_artifact(
+            prefix,
+            short_path_prefix,
+            path,
+            output_type,
+            declaration_location,
+        )
     }
 
     /// Takes a string or artifact/output artifact and converts it into an output artifact
@@ -172,7 +182,7 @@ impl<'v> AnalysisRegistry<'v> {
         let heap = eval.heap();
         let declared_artifact = if let Some(path) = value.unpack_str() {
             let artifact =
-                self.declare_output(None, path, output_type, declaration_location.dupe())?;
+                self.declare_output(None, None, path, output_type, declaration_location.dupe())?;
             heap.alloc_typed(StarlarkDeclaredArtifact::new(
                 declaration_location,
                 artifact,
@@ -245,11 +255,12 @@ impl<'v> AnalysisRegistry<'v> {
         dynamic: IndexSet<Artifact>,
         inputs: IndexSet<Artifact>,
         outputs: IndexSet<OutputArtifact>,
+        output_groups: bool,
         attributes_lambda: Value<'v>,
     ) -> anyhow::Result<()> {
-        let id = self
-            .dynamic
-            .register(dynamic, inputs, outputs, &mut self.deferred)?;
+        let id =
+            self.dynamic
+                .register(dynamic, inputs, outputs, output_groups, &mut self.deferred)?;
         self.analysis_value_storage.set_value(id, attributes_lambda);
         Ok(())
     }
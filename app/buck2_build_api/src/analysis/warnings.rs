@@ -0,0 +1,100 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Warnings emitted by rule implementations with `ctx.emit_warning`.
+//!
+//! Warnings are part of the analysis result, and are reported by the commands which use it rather
+//! than when analysis runs, so that they are reported again when analysis is cached.
+
+use allocative::Allocative;
+use buck2_common::dice::cells::HasCellResolver;
+use buck2_common::legacy_configs::dice::HasLegacyConfigs;
+use buck2_core::target::label::ConfiguredTargetLabel;
+use buck2_data::ToProtoMessage;
+use buck2_events::dispatch::instant_event;
+use dice::DiceComputations;
+
+#[derive(Debug, Clone, Allocative)]
+pub struct AnalysisWarning {
+    pub message: String,
+    pub tags: Vec<String>,
+}
+
+impl AnalysisWarning {
+    fn is_suppressed(&self, suppressed_tags: &[String]) -> bool {
+        self.tags.iter().any(|tag| suppressed_tags.contains(tag))
+    }
+}
+
+/// Tags of the warnings which should not be reported, from the comma separated
+/// `analysis.suppress_warnings` buckconfig of the root cell.
+async fn suppressed_warning_tags(ctx: &DiceComputations) -> anyhow::Result<Vec<String>> {
+    let cells = ctx.get_cell_resolver().await?;
+    let tags = ctx
+        .get_legacy_config_property(cells.root_cell(), "analysis", "suppress_warnings")
+        .await?;
+    Ok(match tags {
+        Some(tags) => tags
+            .split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_owned)
+            .collect(),
+        None => Vec::new(),
+    })
+}
+
+/// Drop the suppressed warnings emitted by an analysis.
+pub(crate) async fn filter_suppressed_warnings(
+    ctx: &DiceComputations,
+    warnings: Vec<AnalysisWarning>,
+) -> anyhow::Result<Vec<AnalysisWarning>> {
+    if warnings.is_empty() {
+        return Ok(warnings);
+    }
+
+    let suppressed_tags = suppressed_warning_tags(ctx).await?;
+    Ok(warnings
+        .into_iter()
+        .filter(|warning| !warning.is_suppressed(&suppressed_tags))
+        .collect())
+}
+
+/// Report the warnings emitted by the analysis of `target` to the console.
+pub fn report_analysis_warnings(target: &ConfiguredTargetLabel, warnings: &[AnalysisWarning]) {
+    for warning in warnings {
+        instant_event(buck2_data::AnalysisWarning {
+            target: Some(target.as_proto().into()),
+            message: warning.message.clone(),
+            tags: warning.tags.clone(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::warnings::AnalysisWarning;
+
+    #[test]
+    fn test_is_suppressed() {
+        let warning = AnalysisWarning {
+            message: "`foo` is deprecated".to_owned(),
+            tags: vec!["deprecated".to_owned(), "foo".to_owned()],
+        };
+        assert!(warning.is_suppressed(&["foo".to_owned()]));
+        assert!(!warning.is_suppressed(&["bar".to_owned()]));
+        assert!(!warning.is_suppressed(&[]));
+
+        let untagged = AnalysisWarning {
+            message: "untagged".to_owned(),
+            tags: Vec::new(),
+        };
+        assert!(!untagged.is_suppressed(&["foo".to_owned()]));
+    }
+}
@@ -30,7 +30,9 @@ impl TrivialDeferred for DeferredTransitiveSetData {
         self
     }
 
-    fn provide<'a>(&'a self, _demand: &mut Demand<'a>) {}
+    fn provide<'a>(&'a self, demand: &mut Demand<'a>) {
+        demand.provide_ref::<DeferredTransitiveSetData>(self);
+    }
 }
 
 impl DeferredTransitiveSetData {
@@ -7,12 +7,17 @@
  * of this source tree.
  */
 
+use std::fmt::Display;
+
 use buck2_core::provider::label::ConfiguredProvidersLabel;
+use buck2_core::provider::label::ProviderName;
 use buck2_node::attrs::attr_type::configured_dep::ConfiguredExplicitConfiguredDep;
 use buck2_node::attrs::attr_type::configured_dep::ExplicitConfiguredDepAttrType;
 use buck2_node::attrs::attr_type::dep::DepAttr;
 use buck2_node::attrs::attr_type::dep::DepAttrType;
 use buck2_node::provider_id_set::ProviderIdSet;
+use dupe::Dupe;
+use itertools::Itertools;
 use starlark::environment::Module;
 use starlark::values::Value;
 use thiserror::Error;
@@ -24,12 +29,100 @@ use crate::interpreter::rule_defs::provider::dependency::Dependency;
 
 #[derive(Error, Debug)]
 enum ResolutionError {
-    #[error("required provider `{0}` was not found on `{1}`. Found these providers: {}", .2.join(", "))]
-    MissingRequiredProvider(String, ConfiguredProvidersLabel, Vec<String>),
+    #[error(
+        "required provider {} was not found on `{target}`. Found these providers: {}{}",
+        .missing.iter().map(|p| format!("`{}`", p)).join(", "),
+        .found.join(", "),
+        SuggestedDeps(.suggestions)
+    )]
+    MissingRequiredProvider {
+        missing: Vec<String>,
+        target: ConfiguredProvidersLabel,
+        found: Vec<String>,
+        suggestions: Vec<ConfiguredProvidersLabel>,
+    },
+}
+
+struct SuggestedDeps<'a>(&'a [ConfiguredProvidersLabel]);
+
+impl Display for SuggestedDeps<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.0.is_empty() {
+            write!(
+                f,
+                "\nDid you mean one of these, which provide all the required providers?"
+            )?;
+            for label in self.0 {
+                write!(f, "\n  {}", label)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Labels which have all of `required_providers`, out of the sub-targets of `target` (whose
+/// providers are `providers`) and the other `deps` in the same package as `target`. Other deps
+/// are only suggested if their name is similar to the name of `target`, closest first.
+fn suggest_deps(
+    required_providers: &ProviderIdSet,
+    target: &ConfiguredProvidersLabel,
+    providers: &FrozenProviderCollection,
+    deps: &[(ConfiguredProvidersLabel, FrozenProviderCollectionValue)],
+) -> Vec<ConfiguredProvidersLabel> {
+    const MAX_RESULTS: usize = 5;
+    const MAX_LEVENSHTEIN_DISTANCE: usize = 5;
+
+    let has_required = |providers: &FrozenProviderCollection| {
+        required_providers
+            .providers()
+            .iter()
+            .all(|id| providers.contains_provider(id))
+    };
+
+    let sub_targets = providers
+        .default_info()
+        .sub_targets()
+        .into_iter()
+        .filter(|(_, providers)| has_required(providers))
+        .map(|(name, _)| {
+            ConfiguredProvidersLabel::new(
+                target.target().dupe(),
+                target
+                    .name()
+                    .push(ProviderName::new_unchecked(name.to_owned())),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let name = target.target().name().as_str();
+    let similar_deps = deps
+        .iter()
+        .filter(|(label, _)| {
+            label.target() != target.target() && label.target().pkg() == target.target().pkg()
+        })
+        .filter(|(_, providers)| has_required(providers.provider_collection()))
+        .map(|(label, _)| {
+            let dep_name = label.target().name().as_str();
+            (label, strsim::levenshtein(name, dep_name), dep_name)
+        })
+        .filter(|(_, lev, dep_name)| {
+            *lev <= MAX_LEVENSHTEIN_DISTANCE
+                || name.starts_with(dep_name)
+                || dep_name.starts_with(name)
+        })
+        .sorted_by(|(a, a_lev, _), (b, b_lev, _)| a_lev.cmp(b_lev).then_with(|| a.cmp(b)))
+        .map(|(label, _, _)| label.clone());
+
+    sub_targets
+        .into_iter()
+        .chain(similar_deps)
+        .take(MAX_RESULTS)
+        .collect()
 }
 
 pub(crate) trait DepAttrTypeExt {
-    fn check_providers(
+    fn check_providers<'v>(
+        ctx: &dyn AttrResolutionContext<'v>,
         required_providers: &ProviderIdSet,
         providers: &FrozenProviderCollection,
         target: &ConfiguredProvidersLabel,
@@ -54,22 +147,29 @@ pub(crate) trait DepAttrTypeExt {
 }
 
 impl DepAttrTypeExt for DepAttrType {
-    fn check_providers(
+    fn check_providers<'v>(
+        ctx: &dyn AttrResolutionContext<'v>,
         required_providers: &ProviderIdSet,
         providers: &FrozenProviderCollection,
         target: &ConfiguredProvidersLabel,
     ) -> anyhow::Result<()> {
-        for provider_id in required_providers {
-            if !providers.contains_provider(provider_id) {
-                return Err(ResolutionError::MissingRequiredProvider(
-                    provider_id.name().to_owned(),
-                    target.clone(),
-                    providers.provider_names(),
-                )
-                .into());
-            }
+        let missing: Vec<String> = required_providers
+            .providers()
+            .iter()
+            .filter(|provider_id| !providers.contains_provider(provider_id))
+            .map(|provider_id| provider_id.name().to_owned())
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
         }
-        Ok(())
+
+        Err(ResolutionError::MissingRequiredProvider {
+            missing,
+            target: target.clone(),
+            found: providers.provider_names(),
+            suggestions: suggest_deps(required_providers, target, providers, &ctx.all_deps()),
+        }
+        .into())
     }
 
     fn alloc_dependency<'v>(
@@ -91,7 +191,7 @@ impl DepAttrTypeExt for DepAttrType {
     ) -> anyhow::Result<Value<'v>> {
         let v = ctx.get_dep(target)?;
         let provider_collection = v.provider_collection();
-        Self::check_providers(required_providers, provider_collection, target)?;
+        Self::check_providers(ctx, required_providers, provider_collection, target)?;
 
         Ok(Self::alloc_dependency(ctx.starlark_module(), target, &v))
     }
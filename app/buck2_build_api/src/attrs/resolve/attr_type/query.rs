@@ -48,6 +48,7 @@ impl ConfiguredQueryAttrExt for QueryAttr<ConfiguredProvidersLabel> {
                 let provider_collection = providers.provider_collection();
 
                 DepAttrType::check_providers(
+                    ctx,
                     &self.providers,
                     provider_collection,
                     &providers_label,
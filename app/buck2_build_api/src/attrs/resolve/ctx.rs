@@ -37,6 +37,12 @@ pub trait AttrResolutionContext<'v> {
         target: &ConfiguredProvidersLabel,
     ) -> anyhow::Result<FrozenProviderCollectionValue>;
 
+    /// All the deps of the target whose attributes are being resolved, used to suggest
+    /// alternatives when a dep doesn't have the providers an attribute requires.
+    fn all_deps(&self) -> Vec<(ConfiguredProvidersLabel, FrozenProviderCollectionValue)> {
+        Vec::new()
+    }
+
     fn resolve_unkeyed_placeholder(
         &self,
         name: &str,
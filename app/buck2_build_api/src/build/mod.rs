@@ -16,12 +16,17 @@ use std::sync::Arc;
 use allocative::Allocative;
 use anyhow::Context;
 use buck2_cli_proto::build_request::Materializations;
+use buck2_common::dice::cells::HasCellResolver;
 use buck2_common::executor_config::PathSeparatorKind;
 use buck2_common::result::SharedResult;
 use buck2_common::result::ToSharedResultExt;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::provider::label::ConfiguredProvidersLabel;
+use buck2_core::provider::label::NonDefaultProvidersName;
+use buck2_core::provider::label::ProvidersName;
 use buck2_events::dispatch::console_message;
 use buck2_execute::artifact::fs::ExecutorFs;
+use buck2_query::query::compatibility::IncompatiblePlatformReason;
 use buck2_query::query::compatibility::MaybeCompatible;
 use dashmap::mapref::entry::Entry;
 use dashmap::DashMap;
@@ -37,20 +42,25 @@ use futures::stream::TryStreamExt;
 use itertools::Itertools;
 use tokio::sync::Mutex;
 
+use crate::actions::artifact::artifact_type::Artifact;
 use crate::actions::artifact::artifact_type::BaseArtifactKind;
 use crate::actions::artifact::build_artifact::BuildArtifact;
 use crate::actions::artifact::materializer::ArtifactMaterializer;
 use crate::actions::build_listener::HasBuildSignals;
 use crate::actions::build_listener::TopLevelTargetSignal;
 use crate::analysis::calculation::RuleAnalysisCalculation;
+use crate::analysis::warnings::AnalysisWarning;
 use crate::artifact_groups::calculation::ArtifactGroupCalculation;
 use crate::artifact_groups::ArtifactGroup;
 use crate::artifact_groups::ArtifactGroupValues;
 use crate::calculation::Calculation;
+use crate::dynamic::lookup_dynamic_output_group;
 use crate::interpreter::rule_defs::cmd_args::AbsCommandLineContext;
 use crate::interpreter::rule_defs::cmd_args::CommandLineArgLike;
 use crate::interpreter::rule_defs::cmd_args::SimpleCommandLineArtifactVisitor;
+use crate::interpreter::rule_defs::provider::builtin::external_runner_test_info::ExternalRunnerTestInfo;
 use crate::interpreter::rule_defs::provider::builtin::run_info::RunInfo;
+use crate::interpreter::rule_defs::provider::collection::is_missing_sub_target_error;
 use crate::interpreter::rule_defs::provider::collection::FrozenProviderCollectionValue;
 use crate::interpreter::rule_defs::provider::test_provider::TestProvider;
 
@@ -63,11 +73,24 @@ pub enum BuildProviderType {
     Test,
 }
 
+/// How to run a target on the local machine.
+#[derive(Clone, Debug, Allocative)]
+pub struct RunArgs {
+    pub args: Vec<String>,
+    /// Environment to set. Only non-empty for test targets, which are run like `buck2 test`
+    /// would run them.
+    pub env: Vec<(String, String)>,
+    /// Working directory to run in. Only set for test targets.
+    pub cwd: Option<AbsNormPathBuf>,
+}
+
 #[derive(Clone, Debug, Allocative)]
 pub struct BuildTargetResultGen<T> {
     pub outputs: Vec<T>,
     pub providers: FrozenProviderCollectionValue,
-    pub run_args: Option<Vec<String>>,
+    pub run_args: Option<RunArgs>,
+    /// Warnings emitted by the analysis of the target.
+    pub warnings: Vec<AnalysisWarning>,
 }
 
 pub type BuildTargetResult = BuildTargetResultGen<SharedResult<ProviderArtifacts>>;
@@ -76,27 +99,30 @@ impl BuildTargetResult {
     pub async fn collect_stream(
         mut stream: impl Stream<Item = anyhow::Result<BuildEvent>> + Unpin,
         fail_fast: bool,
-    ) -> anyhow::Result<BTreeMap<ConfiguredProvidersLabel, Option<Self>>> {
+    ) -> anyhow::Result<BTreeMap<ConfiguredProvidersLabel, MaybeCompatible<Self>>> {
         // Create a map of labels to outputs, but retain the expected index of each output.
         let mut res = HashMap::<
             ConfiguredProvidersLabel,
-            Option<BuildTargetResultGen<(usize, SharedResult<ProviderArtifacts>)>>,
+            MaybeCompatible<BuildTargetResultGen<(usize, SharedResult<ProviderArtifacts>)>>,
         >::new();
 
         while let Some(BuildEvent { label, variant }) = stream.try_next().await? {
             match variant {
-                BuildEventVariant::SkippedIncompatible => {
-                    res.entry((*label).clone()).or_insert(None);
+                BuildEventVariant::SkippedIncompatible(reason) => {
+                    res.entry((*label).clone())
+                        .or_insert(MaybeCompatible::Incompatible(reason));
                 }
                 BuildEventVariant::Prepared {
                     providers,
                     run_args,
+                    warnings,
                 } => {
                     res.entry((*label).clone())
-                        .or_insert(Some(BuildTargetResultGen {
+                        .or_insert(MaybeCompatible::Compatible(BuildTargetResultGen {
                             outputs: Vec::new(),
                             providers,
                             run_args,
+                            warnings,
                         }));
                 }
                 BuildEventVariant::Output { index, output } => {
@@ -104,7 +130,7 @@ impl BuildTargetResult {
 
                     res.get_mut(label.as_ref())
                         .with_context(|| format!("BuildEventVariant::Output before BuildEventVariant::Prepared for {} (internal error)", label))?
-                        .as_mut()
+                        .as_compatible_mut()
                         .with_context(|| format!("BuildEventVariant::Output for a skipped target: `{}` (internal error)", label))?
                         .outputs
                         .push((index, output));
@@ -126,6 +152,7 @@ impl BuildTargetResult {
                         mut outputs,
                         providers,
                         run_args,
+                        warnings,
                     } = result;
 
                     // No need for a stable sort: the indices are unique (see below).
@@ -143,6 +170,7 @@ impl BuildTargetResult {
                             .collect(),
                         providers,
                         run_args,
+                        warnings,
                     }
                 });
 
@@ -155,10 +183,11 @@ impl BuildTargetResult {
 }
 
 enum BuildEventVariant {
-    SkippedIncompatible,
+    SkippedIncompatible(Arc<IncompatiblePlatformReason>),
     Prepared {
         providers: FrozenProviderCollectionValue,
-        run_args: Option<Vec<String>>,
+        run_args: Option<RunArgs>,
+        warnings: Vec<AnalysisWarning>,
     },
     Output {
         output: SharedResult<ProviderArtifacts>,
@@ -183,16 +212,19 @@ pub async fn build_configured_label(
     let providers_label = Arc::new(providers_label);
 
     let artifact_fs = ctx.get_artifact_fs().await?;
+    let cell_resolver = ctx.get_cell_resolver().await?;
 
     let (providers, outputs, run_args) = {
         // A couple of these objects aren't Send and so scope them here so async transform doesn't get concerned.
-        let providers = match ctx.get_providers(providers_label.as_ref()).await? {
+        let (providers, dynamic_output_group) =
+            get_providers_or_dynamic_output_group(ctx, providers_label.as_ref()).await?;
+        let providers = match providers {
             MaybeCompatible::Incompatible(reason) => {
                 if skippable {
                     console_message(reason.skipping_message(providers_label.target()));
                     return Ok(futures::stream::once(futures::future::ready(BuildEvent {
                         label: providers_label.dupe(),
-                        variant: BuildEventVariant::SkippedIncompatible,
+                        variant: BuildEventVariant::SkippedIncompatible(reason),
                     }))
                     .boxed());
                 } else {
@@ -209,36 +241,39 @@ pub async fn build_configured_label(
         // otherwise we'd build the same output twice when it's both in DefaultInfo and RunInfo
         let collection = providers.provider_collection();
 
-        let mut run_args: Option<Vec<String>> = None;
+        let mut run_args: Option<RunArgs> = None;
 
-        if providers_to_build.default {
-            collection
-                .default_info()
-                .for_each_default_output_artifact_only(&mut |o| {
-                    outputs.push((ArtifactGroup::Artifact(o), BuildProviderType::Default));
-                    Ok(())
-                })?;
-        }
-        if providers_to_build.default_other {
-            collection
-                .default_info()
-                .for_each_default_output_other_artifacts_only(&mut |o| {
+        if let Some(artifacts) = dynamic_output_group {
+            // A dynamic output group only has default outputs.
+            for artifact in artifacts {
+                outputs.push((
+                    ArtifactGroup::Artifact(artifact),
+                    BuildProviderType::Default,
+                ));
+            }
+        } else {
+            if providers_to_build.default {
+                collection
+                    .default_info()
+                    .for_each_default_output_artifact_only(&mut |o| {
+                        outputs.push((ArtifactGroup::Artifact(o), BuildProviderType::Default));
+                        Ok(())
+                    })?;
+            }
+            if providers_to_build.default_other {
+                collection
+                    .default_info()
+                    .for_each_default_output_other_artifacts_only(&mut |o| {
+                        outputs.push((o, BuildProviderType::DefaultOther));
+                        Ok(())
+                    })?;
+                // TODO(marwhal): We can remove this once we migrate all other outputs to be handled with Artifacts directly
+                collection.default_info().for_each_other_output(&mut |o| {
                     outputs.push((o, BuildProviderType::DefaultOther));
                     Ok(())
                 })?;
-            // TODO(marwhal): We can remove this once we migrate all other outputs to be handled with Artifacts directly
-            collection.default_info().for_each_other_output(&mut |o| {
-                outputs.push((o, BuildProviderType::DefaultOther));
-                Ok(())
-            })?;
-        }
-        if providers_to_build.run {
-            if let Some(runinfo) = RunInfo::from_providers(providers.provider_collection()) {
-                let mut artifact_visitor = SimpleCommandLineArtifactVisitor::new();
-                runinfo.visit_artifacts(&mut artifact_visitor)?;
-                for input in artifact_visitor.inputs {
-                    outputs.push((input, BuildProviderType::Run));
-                }
+            }
+            if providers_to_build.run {
                 // Produce arguments to run on a local machine.
                 let path_separator = if cfg!(windows) {
                     PathSeparatorKind::Windows
@@ -246,18 +281,65 @@ pub async fn build_configured_label(
                     PathSeparatorKind::Unix
                 };
                 let executor_fs = ExecutorFs::new(&artifact_fs, path_separator);
-                let mut cli = Vec::<String>::new();
-                let mut ctx = AbsCommandLineContext::new(&executor_fs);
-                runinfo.add_to_command_line(&mut cli, &mut ctx)?;
-                run_args = Some(cli);
+                if let Some(runinfo) = RunInfo::from_providers(providers.provider_collection()) {
+                    let mut artifact_visitor = SimpleCommandLineArtifactVisitor::new();
+                    runinfo.visit_artifacts(&mut artifact_visitor)?;
+                    for input in artifact_visitor.inputs {
+                        outputs.push((input, BuildProviderType::Run));
+                    }
+                    let mut cli = Vec::<String>::new();
+                    let mut ctx = AbsCommandLineContext::new(&executor_fs);
+                    runinfo.add_to_command_line(&mut cli, &mut ctx)?;
+                    run_args = Some(RunArgs {
+                        args: cli,
+                        env: Vec::new(),
+                        cwd: None,
+                    });
+                } else if let Some(test_info) =
+                    ExternalRunnerTestInfo::from_providers(providers.provider_collection())
+                {
+                    // Test targets are run the way `buck2 test` runs them locally: with the test
+                    // environment, from the project root or the cell root.
+                    let mut artifact_visitor = SimpleCommandLineArtifactVisitor::new();
+                    test_info.visit_artifacts(&mut artifact_visitor)?;
+                    for input in artifact_visitor.inputs {
+                        outputs.push((input, BuildProviderType::Run));
+                    }
+                    let mut cli = Vec::<String>::new();
+                    let mut ctx = AbsCommandLineContext::new(&executor_fs);
+                    for member in test_info.command() {
+                        member.add_to_command_line(&mut cli, &mut ctx)?;
+                    }
+                    let env: Vec<(String, String)> = test_info
+                        .env()
+                        .map(|(k, v)| {
+                            let mut value = Vec::<String>::new();
+                            v.add_to_command_line(&mut value, &mut ctx)?;
+                            anyhow::Ok((k.to_owned(), value.join(" ")))
+                        })
+                        .collect::<anyhow::Result<_>>()?;
+                    let cwd = if test_info.run_from_project_root() {
+                        artifact_fs.fs().root().to_owned()
+                    } else {
+                        let cell = cell_resolver.get(providers_label.target().pkg().cell_name())?;
+                        artifact_fs
+                            .fs()
+                            .resolve(cell.path().as_project_relative_path())
+                    };
+                    run_args = Some(RunArgs {
+                        args: cli,
+                        env,
+                        cwd: Some(cwd),
+                    });
+                }
             }
-        }
-        if providers_to_build.tests {
-            if let Some(test_provider) = <dyn TestProvider>::from_collection(collection) {
-                let mut artifact_visitor = SimpleCommandLineArtifactVisitor::new();
-                test_provider.visit_artifacts(&mut artifact_visitor)?;
-                for input in artifact_visitor.inputs {
-                    outputs.push((input, BuildProviderType::Test));
+            if providers_to_build.tests {
+                if let Some(test_provider) = <dyn TestProvider>::from_collection(collection) {
+                    let mut artifact_visitor = SimpleCommandLineArtifactVisitor::new();
+                    test_provider.visit_artifacts(&mut artifact_visitor)?;
+                    for input in artifact_visitor.inputs {
+                        outputs.push((input, BuildProviderType::Test));
+                    }
                 }
             }
         }
@@ -265,6 +347,12 @@ pub async fn build_configured_label(
         (providers, outputs, run_args)
     };
 
+    // Analysis already ran to compute the providers, this only looks up the warnings it emitted.
+    let warnings = match ctx.get_analysis_result(providers_label.target()).await? {
+        MaybeCompatible::Compatible(analysis) => analysis.warnings().to_vec(),
+        MaybeCompatible::Incompatible(_) => Vec::new(),
+    };
+
     if let Some(signals) = ctx.per_transaction_data().get_build_signals() {
         signals.signal(TopLevelTargetSignal {
             label: providers_label.target().dupe(),
@@ -294,6 +382,8 @@ pub async fn build_configured_label(
                 let ctx = ctx.dupe();
                 let materialization_context = materialization_context.dupe();
                 async move {
+                    let materialization_context =
+                        materialization_context.for_provider_type(&provider_type);
                     let res = materialize_artifact_group(&ctx, &output, &materialization_context)
                         .await
                         .shared_error()
@@ -320,6 +410,7 @@ pub async fn build_configured_label(
         variant: BuildEventVariant::Prepared {
             providers,
             run_args,
+            warnings,
         },
     }))
     .chain(outputs)
@@ -328,6 +419,36 @@ pub async fn build_configured_label(
     Ok(stream)
 }
 
+/// Get the providers for `label`. If the only error is that `label` refers to a sub-target which
+/// the rule doesn't define, fall back to the output groups returned by the rule's `dynamic_output` lambdas. In that case
+/// the providers of the target itself are returned along with the artifacts in the group.
+async fn get_providers_or_dynamic_output_group(
+    ctx: &DiceComputations,
+    label: &ConfiguredProvidersLabel,
+) -> anyhow::Result<(
+    MaybeCompatible<FrozenProviderCollectionValue>,
+    Option<Vec<Artifact>>,
+)> {
+    let err = match ctx.get_providers(label).await {
+        Ok(providers) => return Ok((providers, None)),
+        Err(e) => e,
+    };
+    let name = match label.name() {
+        ProvidersName::NonDefault(box NonDefaultProvidersName::Named(names))
+            if names.len() == 1 && is_missing_sub_target_error(&err, &names[0]) =>
+        {
+            &names[0]
+        }
+        _ => return Err(err),
+    };
+    let target_label = ConfiguredProvidersLabel::new(label.target().dupe(), ProvidersName::Default);
+    let providers = ctx.get_providers(&target_label).await?;
+    match lookup_dynamic_output_group(ctx, label.target(), name.as_str()).await? {
+        Some(artifacts) => Ok((providers, Some(artifacts))),
+        None => Err(err),
+    }
+}
+
 #[derive(Clone, Allocative)]
 pub struct ProviderArtifacts {
     pub values: ArtifactGroupValues,
@@ -359,7 +480,7 @@ pub async fn materialize_artifact_group(
 ) -> anyhow::Result<ArtifactGroupValues> {
     let values = ctx.ensure_artifact_group(artifact_group).await?;
 
-    if let MaterializationContext::Materialize { map, force } = materialization_context {
+    if let MaterializationContext::Materialize { map, force, .. } = materialization_context {
         future::try_join_all(values.iter().filter_map(|(artifact, _value)| {
             match artifact.as_parts().0 {
                 BaseArtifactKind::Build(artifact) => {
@@ -397,6 +518,8 @@ pub enum MaterializationContext {
         /// Whether we should force the materialization of requested artifacts, or defer to the
         /// config.
         force: bool,
+        /// Whether only the default outputs of requested targets should be materialized.
+        default_outputs_only: bool,
     },
 }
 
@@ -406,6 +529,21 @@ impl MaterializationContext {
         Self::Materialize {
             map: Arc::new(DashMap::new()),
             force: true,
+            default_outputs_only: false,
+        }
+    }
+
+    /// The context to use for an output of a requested target, given the provider it came from.
+    fn for_provider_type(&self, provider_type: &BuildProviderType) -> Self {
+        match (self, provider_type) {
+            (
+                Self::Materialize {
+                    default_outputs_only: true,
+                    ..
+                },
+                BuildProviderType::DefaultOther | BuildProviderType::Run | BuildProviderType::Test,
+            ) => Self::Skip,
+            _ => self.dupe(),
         }
     }
 }
@@ -418,17 +556,7 @@ pub trait ConvertMaterializationContext {
 
 impl ConvertMaterializationContext for Materializations {
     fn from(self) -> MaterializationContext {
-        match self {
-            Materializations::Skip => MaterializationContext::Skip,
-            Materializations::Default => MaterializationContext::Materialize {
-                map: Arc::new(DashMap::new()),
-                force: false,
-            },
-            Materializations::Materialize => MaterializationContext::Materialize {
-                map: Arc::new(DashMap::new()),
-                force: true,
-            },
-        }
+        self.with_existing_map(&Arc::new(DashMap::new()))
     }
 
     fn with_existing_map(self, map: &Arc<DashMap<BuildArtifact, ()>>) -> MaterializationContext {
@@ -437,10 +565,17 @@ impl ConvertMaterializationContext for Materializations {
             Materializations::Default => MaterializationContext::Materialize {
                 map: map.dupe(),
                 force: false,
+                default_outputs_only: false,
             },
             Materializations::Materialize => MaterializationContext::Materialize {
                 map: map.dupe(),
                 force: true,
+                default_outputs_only: false,
+            },
+            Materializations::MaterializeDefaultOutputs => MaterializationContext::Materialize {
+                map: map.dupe(),
+                force: true,
+                default_outputs_only: true,
             },
         }
     }
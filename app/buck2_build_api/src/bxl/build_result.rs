@@ -8,6 +8,7 @@
  */
 
 use allocative::Allocative;
+use buck2_query::query::compatibility::MaybeCompatible;
 use gazebo::variants::UnpackVariants;
 
 use crate::build::BuildTargetResult;
@@ -20,10 +21,10 @@ pub enum BxlBuildResult {
 }
 
 impl BxlBuildResult {
-    pub fn new(result: Option<BuildTargetResult>) -> Self {
+    pub fn new(result: MaybeCompatible<BuildTargetResult>) -> Self {
         match result {
-            Some(result) => Self::Built(result),
-            None => Self::None,
+            MaybeCompatible::Compatible(result) => Self::Built(result),
+            MaybeCompatible::Incompatible(_) => Self::None,
         }
     }
 }
@@ -15,6 +15,7 @@ use std::sync::Arc;
 use allocative::Allocative;
 use buck2_core::base_deferred_key_dyn::BaseDeferredKeyDynImpl;
 use buck2_core::collections::ordered_map::OrderedMap;
+use buck2_core::fs::buck_out_path::BuckOutPathScheme;
 use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
 use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
@@ -45,6 +46,12 @@ pub enum CliArgValue {
     // so pass the type info together with values to be used later.
     #[display(fmt = "{}", "_0.iter().map(|v| v.to_string()).join(\",\")")]
     List(Vec<CliArgValue>),
+    /// Pairs given as `key=value`, sorted by key.
+    #[display(
+        fmt = "{}",
+        "_0.iter().map(|(k, v)| format!(\"{}={}\", k, v)).join(\",\")"
+    )]
+    KeyValue(Vec<(String, String)>),
     None,
     TargetLabel(TargetLabel),
     ProvidersLabel(ProvidersLabel),
@@ -115,6 +122,7 @@ impl BaseDeferredKeyDynImpl for BxlKeyData {
     fn make_hashed_path(
         &self,
         base: &ProjectRelativePath,
+        scheme: &BuckOutPathScheme,
         prefix: &ForwardRelativePath,
         action_key: Option<&str>,
         path: &ForwardRelativePath,
@@ -128,6 +136,10 @@ impl BaseDeferredKeyDynImpl for BxlKeyData {
             let output_hash = hasher.finish();
             format!("{:x}", output_hash)
         };
+        let short_output_hash = scheme.short_config_hash(&output_hash, None);
+        let output_hash = short_output_hash.as_deref().unwrap_or(&output_hash);
+        let short_package_path = scheme.short_package_path(cell_relative_path);
+        let cell_relative_path = short_package_path.as_deref().unwrap_or(cell_relative_path);
 
         // It is performance critical that we use slices and allocate via `join` instead of
         // repeated calls to `join` on the path object because `join` allocates on each call,
@@ -139,7 +151,7 @@ impl BaseDeferredKeyDynImpl for BxlKeyData {
             "-bxl/",
             label.bxl_path.cell().as_str(),
             "/",
-            output_hash.as_str(),
+            output_hash,
             "/",
             cell_relative_path,
             if cell_relative_path.is_empty() {
@@ -161,7 +161,10 @@ pub trait Calculation<'c> {
 #[async_trait]
 impl<'c> Calculation<'c> for DiceComputations {
     async fn get_artifact_fs(&self) -> anyhow::Result<ArtifactFs> {
-        let buck_out_path_resolver = BuckOutPathResolver::new(self.get_buck_out_path().await?);
+        let buck_out_path_resolver = BuckOutPathResolver::with_scheme(
+            self.get_buck_out_path().await?,
+            self.get_buck_out_path_scheme().await?,
+        );
         let project_filesystem = self.global_data().get_io_provider().project_root().dupe();
         let buck_path_resolver = BuckPathResolver::new(self.get_cell_resolver().await?);
         Ok(ArtifactFs::new(
@@ -318,17 +318,18 @@ async fn configuration_matches(
         }
     }
 
+    if constraints_and_configs.buckconfigs.is_empty() {
+        return Ok(true);
+    }
+
     // Cell used for buckconfigs is set to cell of target that applies select to match Buck v1 behavior.
     // Eventually, we want this to be the cell of the platform instead.
+    // Only depend on the buckconfig keys of the `config_setting`, so that changes to other keys do
+    // not invalidate the selects.
+    let config = ctx.get_legacy_config_on_dice(target_node_cell).await?;
     for (raw_section_and_key, config_value) in &constraints_and_configs.buckconfigs {
         let config_section_and_key = parse_config_section_and_key(raw_section_and_key, None)?;
-        let v = ctx
-            .get_legacy_config_property(
-                target_node_cell,
-                &config_section_and_key.section,
-                &config_section_and_key.key,
-            )
-            .await?;
+        let v = config.get(&config_section_and_key.section, &config_section_and_key.key)?;
         match v {
             Some(v) if &*v == config_value => {}
             _ => return Ok(false),
@@ -344,10 +345,10 @@ pub struct ExecutionPlatformsKey;
 
 #[derive(Clone, Display, Debug, Eq, Hash, PartialEq, Allocative)]
 #[display(fmt = "ConfigurationNode({}, {})", cfg_target, target_cfg)]
-struct ConfigurationNodeKey {
-    target_cfg: ConfigurationData,
-    target_cell: CellName,
-    cfg_target: TargetLabel,
+pub struct ConfigurationNodeKey {
+    pub target_cfg: ConfigurationData,
+    pub target_cell: CellName,
+    pub cfg_target: TargetLabel,
 }
 
 #[derive(Clone, Display, Debug, Eq, Hash, PartialEq, Allocative)]
@@ -18,6 +18,7 @@ use buck2_execute::digest_config::DigestConfig;
 use buck2_execute::digest_config::SetDigestConfig;
 use dice::DetectCycles;
 use dice::Dice;
+use dice::DiceTaskSpans;
 use dice::WhichDice;
 use dice::WhichSpawner;
 
@@ -55,12 +56,28 @@ pub async fn configure_dice_for_buck(
         .and_then(|c| c.parse::<WhichSpawner>("buck2", "dice_spawner").transpose())
         .unwrap_or(Ok(WhichSpawner::DropCancel))?;
 
+    let compress_deps_threshold = root_config
+        .map(|c| c.parse::<usize>("buck2", "dice_compress_deps_threshold"))
+        .transpose()?
+        .flatten();
+
+    let task_spans = root_config
+        .and_then(|c| {
+            c.parse::<DiceTaskSpans>("buck2", "dice_task_spans")
+                .transpose()
+        })
+        .unwrap_or(Ok(DiceTaskSpans::Disabled))?;
+
     let mut dice = match which_dice {
         WhichDice::Legacy => Dice::builder(),
         WhichDice::Modern => Dice::modern(),
     };
     dice.set_io_provider(io);
     dice.set_digest_config(digest_config);
+    if let Some(threshold) = compress_deps_threshold {
+        dice.compress_deps_at_least(threshold);
+    }
+    dice.task_spans(task_spans);
 
     let dice = dice.build_with_which_spawner(detect_cycles, which_spawner);
     let mut dice_ctx = dice.updater();
@@ -14,6 +14,7 @@ use std::sync::Arc;
 use allocative::Allocative;
 use async_trait::async_trait;
 use buck2_common::result::SharedResult;
+use buck2_core::fs::buck_out_path::BuckOutPathScheme;
 use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use derive_more::Display;
 use dice::DiceComputations;
@@ -24,15 +25,24 @@ use dupe::Dupe;
 #[async_trait]
 pub trait HasBuildContextData {
     async fn get_buck_out_path(&self) -> SharedResult<ProjectRelativePathBuf>;
+
+    async fn get_buck_out_path_scheme(&self) -> SharedResult<BuckOutPathScheme>;
 }
 
 pub trait SetBuildContextData {
     fn set_buck_out_path(&mut self, path: Option<ProjectRelativePathBuf>) -> anyhow::Result<()>;
+
+    fn set_buck_out_path_with_scheme(
+        &mut self,
+        path: Option<ProjectRelativePathBuf>,
+        scheme: BuckOutPathScheme,
+    ) -> anyhow::Result<()>;
 }
 
 #[derive(PartialEq, Eq, Allocative)]
 pub struct BuildData {
     buck_out_path: ProjectRelativePathBuf,
+    buck_out_path_scheme: BuckOutPathScheme,
 }
 
 #[derive(Clone, Dupe, Display, Debug, Eq, Hash, PartialEq, Allocative)]
@@ -53,16 +63,30 @@ impl HasBuildContextData for DiceComputations {
         let data = self.compute(&BuildDataKey).await?;
         Ok(data.buck_out_path.to_buf())
     }
+
+    async fn get_buck_out_path_scheme(&self) -> SharedResult<BuckOutPathScheme> {
+        let data = self.compute(&BuildDataKey).await?;
+        Ok(data.buck_out_path_scheme)
+    }
 }
 
 impl SetBuildContextData for DiceTransactionUpdater {
     fn set_buck_out_path(&mut self, path: Option<ProjectRelativePathBuf>) -> anyhow::Result<()> {
+        self.set_buck_out_path_with_scheme(path, BuckOutPathScheme::default())
+    }
+
+    fn set_buck_out_path_with_scheme(
+        &mut self,
+        path: Option<ProjectRelativePathBuf>,
+        scheme: BuckOutPathScheme,
+    ) -> anyhow::Result<()> {
         Ok(self.changed_to(vec![(
             BuildDataKey,
             Arc::new(BuildData {
                 buck_out_path: path.unwrap_or_else(|| {
                     ProjectRelativePathBuf::unchecked_new("buck-out/v2".to_owned())
                 }),
+                buck_out_path_scheme: scheme,
             }),
         )])?)
     }
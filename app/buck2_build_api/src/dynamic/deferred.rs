@@ -20,6 +20,7 @@ use buck2_events::dispatch::get_dispatcher;
 use buck2_interpreter::types::label::Label;
 use buck2_interpreter_for_build::interpreter::print_handler::EventDispatcherPrintHandler;
 use dupe::Dupe;
+use either::Either;
 use gazebo::prelude::*;
 use indexmap::indexset;
 use indexmap::IndexSet;
@@ -27,11 +28,15 @@ use starlark::collections::SmallMap;
 use starlark::environment::Module;
 use starlark::eval::Evaluator;
 use starlark::values::dict::Dict;
+use starlark::values::dict::DictRef;
+use starlark::values::list::ListRef;
 use starlark::values::tuple::TupleRef;
 use starlark::values::OwnedFrozenValue;
+use starlark::values::Value;
 use thiserror::Error;
 
 use crate::actions::artifact::artifact_type::Artifact;
+use crate::actions::artifact::artifact_type::DeclaredArtifact;
 use crate::actions::artifact::build_artifact::BuildArtifact;
 use crate::actions::artifact::provide_outputs::ProvideOutputs;
 use crate::actions::key::ActionKey;
@@ -50,6 +55,7 @@ use crate::interpreter::rule_defs::artifact::associated::AssociatedArtifacts;
 use crate::interpreter::rule_defs::artifact::StarlarkArtifact;
 use crate::interpreter::rule_defs::artifact::StarlarkArtifactValue;
 use crate::interpreter::rule_defs::artifact::StarlarkDeclaredArtifact;
+use crate::interpreter::rule_defs::artifact::ValueAsArtifactLike;
 use crate::interpreter::rule_defs::context::AnalysisContext;
 
 /// The artifacts that are returned are dynamic actions, which depend on the `DynamicLambda`
@@ -83,6 +89,8 @@ pub(crate) struct DynamicLambda {
     outputs: Vec<BuildArtifact>,
     /// A Starlark pair of the attributes and a lambda function that binds the outputs given a context
     attributes_lambda: OwnedFrozenValue,
+    /// Set if the lambda returns output groups, pointing at this lambda's own output.
+    output_groups: Option<DeferredData<DynamicLambdaOutput>>,
 }
 
 impl DynamicLambda {
@@ -91,6 +99,7 @@ impl DynamicLambda {
         dynamic: IndexSet<Artifact>,
         inputs: IndexSet<Artifact>,
         outputs: Vec<BuildArtifact>,
+        output_groups: Option<DeferredData<DynamicLambdaOutput>>,
     ) -> Self {
         let mut depends = IndexSet::with_capacity(dynamic.len() + 1);
         match &owner {
@@ -112,6 +121,7 @@ impl DynamicLambda {
             inputs,
             outputs,
             attributes_lambda: Default::default(),
+            output_groups,
         }
     }
 
@@ -126,8 +136,20 @@ pub(crate) struct DynamicLambdaOutput {
     /// The actions the DynamicLambda produces, in the right order.
     /// `DynamicAction.index` is an index into this Vec.
     output: Vec<ActionKey>,
+    /// The named output groups returned by the lambda, if it was declared with `output_groups`.
+    output_groups: SmallMap<String, Vec<Artifact>>,
 }
 
+impl DynamicLambdaOutput {
+    pub(crate) fn output_group(&self, name: &str) -> Option<&[Artifact]> {
+        self.output_groups.get(name).map(|x| x.as_slice())
+    }
+}
+
+/// Provided by a `DynamicLambda` which returns output groups, so they can be found from the
+/// analysis result of the owning target.
+pub(crate) struct ProvideDynamicOutputGroups(pub(crate) DeferredData<DynamicLambdaOutput>);
+
 impl any::Provider for DynamicAction {
     fn provide<'a>(&'a self, _demand: &mut Demand<'a>) {}
 }
@@ -165,11 +187,62 @@ impl Deferred for DynamicAction {
 enum DynamicLambdaError {
     #[error("dynamic_output and anon_target cannot be used together (yet)")]
     AnonTargetIncompatible,
+    #[error(
+        "dynamic_output with `output_groups = True` must return a dict from output group name to a list of artifacts, got `{0}`"
+    )]
+    OutputGroupsNotADict(String),
+    #[error("Output group `{0}` must be a list of artifacts, got `{1}`")]
+    OutputGroupNotAList(String, String),
+    #[error("Output group `{0}` contains `{1}`, which is not an artifact")]
+    OutputGroupNotAnArtifact(String, String),
+}
+
+/// An artifact in an output group, which may not be bound until the lambda's actions are registered.
+type OutputGroupArtifact = Either<Artifact, DeclaredArtifact>;
+
+fn unpack_output_groups<'v>(
+    value: Value<'v>,
+) -> anyhow::Result<Vec<(String, Vec<OutputGroupArtifact>)>> {
+    let dict = DictRef::from_value(value)
+        .ok_or_else(|| DynamicLambdaError::OutputGroupsNotADict(value.to_repr()))?;
+    dict.iter()
+        .map(|(name, artifacts)| {
+            let name = match name.unpack_str() {
+                Some(name) => name.to_owned(),
+                None => {
+                    return Err(DynamicLambdaError::OutputGroupsNotADict(value.to_repr()).into());
+                }
+            };
+            let artifacts = ListRef::from_value(artifacts).ok_or_else(|| {
+                DynamicLambdaError::OutputGroupNotAList(name.clone(), artifacts.to_repr())
+            })?;
+            let artifacts = artifacts
+                .iter()
+                .map(|artifact| {
+                    if let Some(declared) = artifact.downcast_ref::<StarlarkDeclaredArtifact>() {
+                        Ok(Either::Right((*declared.output_artifact()).dupe()))
+                    } else if let Some(artifact) = artifact.as_artifact() {
+                        Ok(Either::Left(artifact.get_bound_artifact()?))
+                    } else {
+                        Err(DynamicLambdaError::OutputGroupNotAnArtifact(
+                            name.clone(),
+                            artifact.to_repr(),
+                        )
+                        .into())
+                    }
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            Ok((name, artifacts))
+        })
+        .collect()
 }
 
 impl any::Provider for DynamicLambda {
     fn provide<'a>(&'a self, demand: &mut Demand<'a>) {
         demand.provide_value_with(|| ProvideOutputs(Ok(self.outputs.clone())));
+        if let Some(output_groups) = &self.output_groups {
+            demand.provide_value_with(|| ProvideDynamicOutputGroups(output_groups.dupe()));
+        }
     }
 }
 
@@ -190,7 +263,7 @@ impl Deferred for DynamicLambda {
         let mut outputs = SmallMap::with_capacity(self.outputs.len());
         let mut declared_outputs = IndexSet::with_capacity(self.outputs.len());
 
-        let analysis_registry = {
+        let (analysis_registry, output_groups) = {
             let mut eval = Evaluator::new(&env);
             eval.set_print_handler(&print);
 
@@ -252,6 +325,7 @@ impl Deferred for DynamicLambda {
                 let k = heap.alloc(StarlarkArtifact::new(Artifact::from(x.dupe())));
                 let declared =
                     registry.declare_dynamic_output(x.get_path().dupe(), x.output_type());
+                declared.set_output_options(x.output_options().dupe())?;
                 declared_outputs.insert(declared.dupe());
                 let v = heap.alloc(StarlarkDeclaredArtifact::new(
                     None,
@@ -283,14 +357,20 @@ impl Deferred for DynamicLambda {
                 deferred_ctx.digest_config(),
             ));
 
-            eval.eval_function(
+            let returned = eval.eval_function(
                 lambda,
                 &[ctx.to_value(), heap.alloc(artifacts), heap.alloc(outputs)],
                 &[],
             )?;
             ctx.assert_no_promises()?;
 
-            ctx.take_state()
+            let output_groups = if self.output_groups.is_some() {
+                unpack_output_groups(returned)?
+            } else {
+                Vec::new()
+            };
+
+            (ctx.take_state(), output_groups)
         };
 
         let (_frozen_env, deferred) = analysis_registry.finalize(&env)(env)?;
@@ -302,8 +382,20 @@ impl Deferred for DynamicLambda {
             .into_iter()
             .map(|x| anyhow::Ok(x.ensure_bound()?.action_key().dupe()))
             .collect();
+        // Artifacts declared by the lambda are only bound once the registry is finalized.
+        let output_groups = output_groups
+            .into_iter()
+            .map(|(name, artifacts)| {
+                let artifacts = artifacts.into_try_map(|artifact| match artifact {
+                    Either::Left(artifact) => anyhow::Ok(artifact),
+                    Either::Right(declared) => Ok(declared.ensure_bound()?.into_artifact()),
+                })?;
+                Ok((name, artifacts))
+            })
+            .collect::<anyhow::Result<_>>()?;
         Ok(DeferredValue::Ready(DynamicLambdaOutput {
             output: output?,
+            output_groups,
         }))
     }
 
@@ -317,3 +409,58 @@ impl Deferred for DynamicLambda {
         Some(buck2_data::DynamicLambdaStart { owner: Some(owner) }.into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use starlark::values::dict::AllocDict;
+    use starlark::values::list::AllocList;
+    use starlark::values::Heap;
+
+    use crate::dynamic::deferred::unpack_output_groups;
+    use crate::dynamic::deferred::DynamicLambdaError;
+
+    fn unpack_error(value: starlark::values::Value) -> DynamicLambdaError {
+        unpack_output_groups(value)
+            .err()
+            .unwrap()
+            .downcast::<DynamicLambdaError>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_unpack_output_groups() {
+        let heap = Heap::new();
+
+        assert!(
+            unpack_output_groups(heap.alloc(AllocDict::EMPTY))
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            vec![("empty".to_owned(), 0)],
+            unpack_output_groups(heap.alloc(AllocDict([("empty", AllocList::EMPTY)])))
+                .unwrap()
+                .into_iter()
+                .map(|(name, artifacts)| (name, artifacts.len()))
+                .collect::<Vec<_>>()
+        );
+
+        assert_matches!(
+            unpack_error(heap.alloc(AllocList::EMPTY)),
+            DynamicLambdaError::OutputGroupsNotADict(..)
+        );
+        assert_matches!(
+            unpack_error(heap.alloc(AllocDict([(1, AllocList::EMPTY)]))),
+            DynamicLambdaError::OutputGroupsNotADict(..)
+        );
+        assert_matches!(
+            unpack_error(heap.alloc(AllocDict([("group", "a")]))),
+            DynamicLambdaError::OutputGroupNotAList(name, _) if name == "group"
+        );
+        assert_matches!(
+            unpack_error(heap.alloc(AllocDict([("group", AllocList(["a"]))]))),
+            DynamicLambdaError::OutputGroupNotAnArtifact(name, _) if name == "group"
+        );
+    }
+}
@@ -7,5 +7,42 @@
  * of this source tree.
  */
 
+use std::any;
+
+use buck2_core::target::label::ConfiguredTargetLabel;
+use dice::DiceComputations;
+
+use crate::actions::artifact::artifact_type::Artifact;
+use crate::analysis::calculation::RuleAnalysisCalculation;
+use crate::deferred::calculation::DeferredCalculation;
+use crate::dynamic::deferred::ProvideDynamicOutputGroups;
+
 pub(crate) mod deferred;
 pub(crate) mod registry;
+
+/// Find the output group `name` returned by one of the `dynamic_output` lambdas that `target`
+/// registered with `output_groups = True`.
+///
+/// This runs those lambdas, so their `dynamic` artifacts are built first.
+/// Returns `None` if no lambda returned a group with that name.
+pub async fn lookup_dynamic_output_group(
+    ctx: &DiceComputations,
+    target: &ConfiguredTargetLabel,
+    name: &str,
+) -> anyhow::Result<Option<Vec<Artifact>>> {
+    let analysis = ctx
+        .get_analysis_result(target)
+        .await?
+        .require_compatible()?;
+    let lambdas: Vec<_> = analysis
+        .iter_deferreds()
+        .filter_map(|entry| any::request_value::<ProvideDynamicOutputGroups>(entry.as_complex()))
+        .collect();
+    for ProvideDynamicOutputGroups(lambda) in lambdas {
+        let output = ctx.compute_deferred_data(&lambda).await?;
+        if let Some(artifacts) = output.output_group(name) {
+            return Ok(Some(artifacts.to_vec()));
+        }
+    }
+    Ok(None)
+}
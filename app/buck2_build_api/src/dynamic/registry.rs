@@ -43,6 +43,7 @@ impl DynamicRegistry {
         dynamic: IndexSet<Artifact>,
         inputs: IndexSet<Artifact>,
         outputs: IndexSet<OutputArtifact>,
+        output_groups: bool,
         registry: &mut DeferredRegistry,
     ) -> anyhow::Result<DeferredId> {
         let reserved = registry.reserve::<DynamicLambdaOutput>();
@@ -58,7 +59,12 @@ impl DynamicRegistry {
                 Ok(bound)
             })
             .collect::<anyhow::Result<_>>()?;
-        let lambda = DynamicLambda::new(self.owner.dupe(), dynamic, inputs, outputs);
+        let output_groups = if output_groups {
+            Some(reserved.data().dupe())
+        } else {
+            None
+        };
+        let lambda = DynamicLambda::new(self.owner.dupe(), dynamic, inputs, outputs, output_groups);
         let lambda_id = reserved.data().deferred_key().id();
         self.pending.push((reserved, lambda));
         Ok(lambda_id)
@@ -23,6 +23,7 @@ use starlark::eval::Evaluator;
 use starlark::values::Value;
 use starlark_map::small_set::SmallSet;
 
+use crate::interpreter::rule_defs::provider::callable::ProviderFieldAliases;
 use crate::interpreter::rule_defs::provider::callable::UserProviderCallable;
 use crate::interpreter::rule_defs::transitive_set::TransitiveSetDefinition;
 use crate::interpreter::rule_defs::transitive_set::TransitiveSetError;
@@ -36,6 +37,64 @@ enum NativesError {
     NonUniqueFields(Vec<String>),
     #[error("`transitive_set()` can only be used in `bzl` files")]
     TransitiveSetOnlyInBzl,
+    #[error("alias `{0}` must not be a field of the provider")]
+    AliasIsField(String),
+    #[error("alias `{alias}` refers to `{field}`, which is not a field of the provider")]
+    AliasNotToField { alias: String, field: String },
+    #[error(
+        "`{function}()` cannot be called in `{path}`, it is only allowed in: {allowlist} (set by `buck2.provider_allowlist` in the root `.buckconfig`)"
+    )]
+    NotInProviderAllowlist {
+        function: &'static str,
+        path: String,
+        allowlist: String,
+    },
+}
+
+const PROVIDER_ALLOWLIST_SECTION: &str = "buck2";
+const PROVIDER_ALLOWLIST_KEY: &str = "provider_allowlist";
+
+/// Whether `path` is one of the comma-separated cells, directories or files of `allowlist`,
+/// e.g. `prelude//, root//build_defs`.
+fn is_in_provider_allowlist(path: &str, allowlist: &str) -> bool {
+    allowlist
+        .split(',')
+        .map(|entry| entry.trim())
+        .filter(|entry| !entry.is_empty())
+        .any(|entry| {
+            if entry.ends_with("//") {
+                path.starts_with(entry)
+            } else {
+                let entry = entry.trim_end_matches('/');
+                path.strip_prefix(entry)
+                    .map_or(false, |rest| rest.is_empty() || rest.starts_with('/'))
+            }
+        })
+}
+
+/// Fail if the root buckconfig restricts where providers and transitive sets can be created,
+/// and the current file is not one of those places.
+fn check_provider_allowlist(
+    function: &'static str,
+    build_context: &BuildContext,
+) -> anyhow::Result<()> {
+    let allowlist = match build_context
+        .root_buckconfig
+        .get_str(PROVIDER_ALLOWLIST_SECTION, PROVIDER_ALLOWLIST_KEY)?
+    {
+        Some(allowlist) => allowlist,
+        None => return Ok(()),
+    };
+    let path = build_context.starlark_path().path().to_string();
+    if is_in_provider_allowlist(&path, &allowlist) {
+        return Ok(());
+    }
+    Err(NativesError::NotInProviderAllowlist {
+        function,
+        path,
+        allowlist: allowlist.trim().to_owned(),
+    }
+    .into())
 }
 
 #[starlark_module]
@@ -55,13 +114,32 @@ pub fn register_provider(builder: &mut GlobalsBuilder) {
     /// which returns either `None` or a value of type `GroovyLibraryInfo`.
     ///
     /// For providers that accumulate upwards a transitive set is often a good choice.
+    ///
+    /// To rename a field without updating all its users at once, the old name can be kept as an
+    /// alias of the new one: it can then be used both to construct the provider and to read the
+    /// field. With `warn_on_aliases = True`, each use of an alias prints a warning to the console.
+    ///
+    /// ```python
+    /// GroovyLibraryInfo = provider(
+    ///     fields = ["objects", "compiler_options"],
+    ///     aliases = {"options": "compiler_options"},
+    /// )
+    /// ```
+    ///
+    /// If `buck2.provider_allowlist` is set in the root `.buckconfig` (e.g. to `prelude//`),
+    /// providers and transitive sets can only be created in the comma-separated cells and
+    /// directories it lists.
     fn provider(
         #[starlark(require=named, default = "")] doc: &str,
         #[starlark(require=named)] fields: Either<Vec<String>, SmallMap<&str, &str>>,
+        #[starlark(require=named)] aliases: Option<SmallMap<&str, &str>>,
+        #[starlark(require=named, default = false)] warn_on_aliases: bool,
         eval: &mut Evaluator,
     ) -> anyhow::Result<UserProviderCallable> {
         let docstring = DocString::from_docstring(DocStringKind::Starlark, doc);
-        let path = BuildContext::from_context(eval)?.starlark_path().path();
+        let build_context = BuildContext::from_context(eval)?;
+        check_provider_allowlist("provider", build_context)?;
+        let path = build_context.starlark_path().path();
 
         let (field_names, field_docs) = match fields {
             Either::Left(f) => {
@@ -83,11 +161,28 @@ pub fn register_provider(builder: &mut GlobalsBuilder) {
                 (field_names, field_docs)
             }
         };
+
+        let mut alias_map = SmallMap::new();
+        for (alias, field) in aliases.unwrap_or_default() {
+            if field_names.contains(alias) {
+                return Err(NativesError::AliasIsField(alias.to_owned()).into());
+            }
+            if !field_names.contains(field) {
+                return Err(NativesError::AliasNotToField {
+                    alias: alias.to_owned(),
+                    field: field.to_owned(),
+                }
+                .into());
+            }
+            alias_map.insert(alias.to_owned(), field.to_owned());
+        }
+
         Ok(UserProviderCallable::new(
             path.into_owned(),
             docstring,
             field_docs,
             field_names,
+            ProviderFieldAliases::new(alias_map, warn_on_aliases),
         ))
     }
 }
@@ -101,6 +196,7 @@ pub fn register_transitive_set(builder: &mut GlobalsBuilder) {
         eval: &mut Evaluator,
     ) -> anyhow::Result<TransitiveSetDefinition<'v>> {
         let build_context = BuildContext::from_context(eval)?;
+        check_provider_allowlist("transitive_set", build_context)?;
         // TODO(cjhopman): Reductions could do similar signature checking.
         let projections: SmallMap<_, _> = args_projections
             .into_iter()
@@ -186,6 +282,7 @@ mod tests {
     use serde_json::json;
 
     use crate::interpreter::build_defs::register_provider;
+    use crate::interpreter::build_defs::register_transitive_set;
     use crate::interpreter::rule_defs::register_rule_defs;
 
     #[test]
@@ -327,6 +424,64 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_provider_allowlist() -> anyhow::Result<()> {
+        let mut tester = Tester::with_cells(cells(Some(indoc!(
+            r#"
+            [buck2]
+                provider_allowlist = root//allowed, root//other/defs.bzl
+        "#
+        )))?)?;
+        tester.additional_globals(register_provider);
+        tester.additional_globals(register_transitive_set);
+
+        let content = indoc!(
+            r#"
+            SomeInfo = provider(fields=["x"])
+            SomeTSet = transitive_set()
+            "#
+        );
+        for allowed in [
+            "root//allowed:defs.bzl",
+            "root//allowed/sub:defs.bzl",
+            "root//other:defs.bzl",
+        ] {
+            tester.eval_import(
+                &ImportPath::testing_new(allowed),
+                content,
+                LoadedModules::default(),
+            )?;
+        }
+
+        let err = tester
+            .eval_import(
+                &ImportPath::testing_new("root//allowed_not:defs.bzl"),
+                "SomeInfo = provider(fields=[\"x\"])",
+                LoadedModules::default(),
+            )
+            .unwrap_err();
+        assert!(
+            format!("{:#}", err)
+                .contains("`provider()` cannot be called in `root//allowed_not/defs.bzl`"),
+            "{:#}",
+            err
+        );
+
+        let err = tester
+            .eval_import(
+                &ImportPath::testing_new("root//other:more_defs.bzl"),
+                "SomeTSet = transitive_set()",
+                LoadedModules::default(),
+            )
+            .unwrap_err();
+        assert!(
+            format!("{:#}", err).contains("buck2.provider_allowlist"),
+            "{:#}",
+            err
+        );
+        Ok(())
+    }
+
     #[test]
     fn eval() -> anyhow::Result<()> {
         let mut tester = Tester::new()?;
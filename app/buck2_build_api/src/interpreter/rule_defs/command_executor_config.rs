@@ -17,9 +17,11 @@ use buck2_common::executor_config::CommandGenerationOptions;
 use buck2_common::executor_config::Executor;
 use buck2_common::executor_config::HybridExecutionLevel;
 use buck2_common::executor_config::PathSeparatorKind;
+use buck2_common::executor_config::RePropertiesByCategory;
 use buck2_common::executor_config::RemoteEnabledExecutor;
 use buck2_common::executor_config::RemoteExecutorOptions;
 use buck2_common::executor_config::RemoteExecutorUseCase;
+use buck2_core::collections::sorted_map::SortedMap;
 use derive_more::Display;
 use starlark::any::ProvidesStaticType;
 use starlark::environment::GlobalsBuilder;
@@ -56,6 +58,23 @@ impl<'v> StarlarkValue<'v> for StarlarkCommandExecutorConfig {
     starlark_type!("command_executor_config");
 }
 
+fn unpack_dict<'v>(value: Value<'v>) -> anyhow::Result<DictRef<'v>> {
+    DictRef::from_value(value).ok_or_else(|| {
+        CommandExecutorConfigErrors::RePropertiesNotADict(
+            value.to_repr(),
+            value.get_type().to_owned(),
+        )
+        .into()
+    })
+}
+
+fn unpack_re_properties(value: Value) -> anyhow::Result<SortedMap<String, String>> {
+    Ok(unpack_dict(value)?
+        .iter()
+        .map(|(k, v)| (k.to_str(), v.to_str()))
+        .collect())
+}
+
 #[starlark_module]
 pub fn register_command_executor_config(builder: &mut GlobalsBuilder) {
     /// Contains configurations for how actions should be executed
@@ -65,6 +84,10 @@ pub fn register_command_executor_config(builder: &mut GlobalsBuilder) {
     /// * `remote_enabled`: Whether to use remote execution for this execution platform
     /// * `remote_cache_enabled`: Whether to query RE caches
     /// * `remote_execution_properties`: Properties for remote execution for this platform
+    /// * `remote_execution_properties_by_category`: Properties for remote execution of actions of
+    /// a given category (e.g. `{"cxx_link": {"pool": "big-memory"}}`), merged on top of
+    /// `remote_execution_properties`. They can be overridden in the `[re_properties_by_category]`
+    /// buckconfig section
     /// * `remote_execution_action_key`: A component to inject into the action key
     /// This should typically used to inject variability into the action key so that
     /// it's different across e.g. build modes (RE uses the action key for things like expected memory utilization)
@@ -86,6 +109,8 @@ pub fn register_command_executor_config(builder: &mut GlobalsBuilder) {
         #[starlark(require = named)] remote_enabled: bool,
         #[starlark(default = NoneOr::None, require = named)] remote_cache_enabled: NoneOr<bool>,
         #[starlark(default = NoneType, require = named)] remote_execution_properties: Value<'v>,
+        #[starlark(default = NoneType, require = named)]
+        remote_execution_properties_by_category: Value<'v>,
         #[starlark(default = NoneType, require = named)] remote_execution_action_key: Value<'v>,
         #[starlark(default = NoneOr::None, require = named)]
         remote_execution_max_input_files_mebibytes: NoneOr<i32>,
@@ -115,19 +140,17 @@ pub fn register_command_executor_config(builder: &mut GlobalsBuilder) {
             let re_properties = if remote_execution_properties.is_none() {
                 None
             } else {
-                let re_properties = DictRef::from_value(remote_execution_properties.to_value())
-                    .ok_or_else(|| {
-                        CommandExecutorConfigErrors::RePropertiesNotADict(
-                            remote_execution_properties.to_value().to_repr(),
-                            remote_execution_properties.to_value().get_type().to_owned(),
-                        )
-                    })?;
+                Some(unpack_re_properties(remote_execution_properties)?)
+            };
 
-                Some(
-                    re_properties
+            let re_properties_by_category = if remote_execution_properties_by_category.is_none() {
+                RePropertiesByCategory::default()
+            } else {
+                RePropertiesByCategory(
+                    unpack_dict(remote_execution_properties_by_category)?
                         .iter()
-                        .map(|(k, v)| (k.to_str(), v.to_str()))
-                        .collect(),
+                        .map(|(k, v)| anyhow::Ok((k.to_str(), unpack_re_properties(v)?)))
+                        .collect::<anyhow::Result<_>>()?,
                 )
             };
 
@@ -226,6 +249,7 @@ pub fn register_command_executor_config(builder: &mut GlobalsBuilder) {
                                 "remote_execution_properties",
                             ),
                         )?,
+                        re_properties_by_category,
                         re_use_case: re_use_case
                             .context(CommandExecutorConfigErrors::MissingField("re_use_case"))?,
                         cache_upload_behavior,
@@ -237,6 +261,7 @@ pub fn register_command_executor_config(builder: &mut GlobalsBuilder) {
                     // FIXME: We need a migration flip the default for remote_cache_enabled to
                     // remote_enabled first.
                     re_properties: re_properties.unwrap_or_default(),
+                    re_properties_by_category,
                     re_use_case: re_use_case.unwrap_or_else(RemoteExecutorUseCase::buck2_default),
                     cache_upload_behavior,
                     remote_cache_enabled: true,
@@ -12,6 +12,7 @@ use std::cell::RefMut;
 use std::fmt;
 use std::fmt::Display;
 use std::fmt::Formatter;
+use std::rc::Rc;
 
 use allocative::Allocative;
 use buck2_execute::digest_config::DigestConfig;
@@ -25,6 +26,7 @@ use starlark::environment::MethodsBuilder;
 use starlark::environment::MethodsStatic;
 use starlark::eval::Evaluator;
 use starlark::starlark_type;
+use starlark::values::none::NoneType;
 use starlark::values::structs::StructRef;
 use starlark::values::type_repr::StarlarkTypeRepr;
 use starlark::values::AllocValue;
@@ -37,7 +39,12 @@ use starlark::values::Value;
 use starlark::values::ValueLike;
 use starlark::values::ValueTyped;
 
+use crate::analysis::attrs_read::AttrsReadRecorder;
 use crate::analysis::registry::AnalysisRegistry;
+use crate::analysis::warnings::AnalysisWarning;
+use crate::interpreter::rule_defs::package_values::PackageValues;
+use crate::interpreter::rule_defs::package_values::PackageValuesLookup;
+use crate::interpreter::rule_defs::recorded_attrs::RecordedAttrs;
 
 /// Functions to allow users to interact with the Actions registry.
 ///
@@ -58,6 +65,11 @@ pub struct AnalysisActions<'v> {
     pub state: RefCell<Option<AnalysisRegistry<'v>>>,
     /// Copies from the ctx, so we can capture them for `dynamic`.
     pub attributes: Value<'v>,
+    /// The attributes read by the analysis, shared with `ctx.attrs`. Capturing the attributes
+    /// for `dynamic` reads all of them.
+    #[trace(unsafe_ignore)]
+    #[allocative(skip)]
+    pub attrs_read: Rc<AttrsReadRecorder>,
     /// Digest configuration to use when interpreting digests passed in analysis.
     pub digest_config: DigestConfig,
 }
@@ -113,6 +125,12 @@ impl<'v> UnpackValue<'v> for RefAnalysisAction<'v> {
     }
 }
 
+#[derive(Debug, thiserror::Error)]
+enum AnalysisContextError {
+    #[error("`ctx.package_values()` is only available in the analysis of a target")]
+    NoPackageValues,
+}
+
 #[derive(
     ProvidesStaticType,
     Debug,
@@ -122,10 +140,16 @@ impl<'v> UnpackValue<'v> for RefAnalysisAction<'v> {
     StarlarkDocs
 )]
 pub struct AnalysisContext<'v> {
-    attrs: Value<'v>, // A struct
+    attrs: Value<'v>, // A `RecordedAttrs` wrapping a struct
     actions: ValueTyped<'v, AnalysisActions<'v>>,
     /// Only `None` when running a `dynamic_output` action from Bxl.
     label: Option<ValueTyped<'v, Label>>,
+    /// Only `None` for anon targets and `dynamic_output`, which do not belong to a package.
+    #[trace(unsafe_ignore)]
+    package_values: Option<PackageValuesLookup>,
+    /// Warnings emitted with `ctx.emit_warning`.
+    #[trace(unsafe_ignore)]
+    warnings: RefCell<Vec<AnalysisWarning>>,
 }
 
 impl<'v> Display for AnalysisContext<'v> {
@@ -153,14 +177,26 @@ impl<'v> AnalysisContext<'v> {
         // Check the types match what the user expects.
         assert!(StructRef::from_value(attrs).is_some());
 
+        let attrs_read = Rc::new(AttrsReadRecorder::default());
         Self {
-            attrs,
+            attrs: heap.alloc_complex(RecordedAttrs::new(attrs, attrs_read.clone())),
             actions: heap.alloc_typed(AnalysisActions {
                 state: RefCell::new(Some(registry)),
                 attributes: attrs,
+                attrs_read,
                 digest_config,
             }),
             label,
+            package_values: None,
+            warnings: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Make `ctx.package_values()` return the values found by this lookup.
+    pub(crate) fn with_package_values(self, package_values: PackageValuesLookup) -> Self {
+        Self {
+            package_values: Some(package_values),
+            ..self
         }
     }
 
@@ -194,6 +230,15 @@ impl<'v> AnalysisContext<'v> {
             .take()
             .expect("nothing to have stolen state yet")
     }
+
+    pub(crate) fn take_warnings(&self) -> Vec<AnalysisWarning> {
+        self.warnings.take()
+    }
+
+    /// The attributes read so far by the analysis.
+    pub(crate) fn attrs_read(&self) -> Rc<AttrsReadRecorder> {
+        self.actions.attrs_read.clone()
+    }
 }
 
 impl<'v> StarlarkValue<'v> for AnalysisContext<'v> {
@@ -260,6 +305,51 @@ fn register_context(builder: &mut MethodsBuilder) {
     fn label<'v>(this: RefAnalysisContext) -> anyhow::Result<Value<'v>> {
         Ok(this.0.label.map_or(Value::new_none(), |v| v.to_value()))
     }
+
+    /// Returns the values written with `write_package_value` by the `PACKAGE` files applying to
+    /// the package of the target, with typed getters. Once this is called, the analysis of the
+    /// target is run again when these values change.
+    ///
+    /// ```python
+    /// def _impl(ctx):
+    ///     if ctx.package_values().get_bool("cxx.strict", False):
+    ///         ...
+    /// ```
+    fn package_values<'v>(
+        this: RefAnalysisContext,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<ValueTyped<'v, PackageValues<'v>>> {
+        this.0
+            .package_values
+            .as_ref()
+            .ok_or(AnalysisContextError::NoPackageValues)?
+            .alloc(eval)
+    }
+
+    /// Emit a warning about the target being analysed, e.g. when it uses a deprecated attribute.
+    /// The warnings of the targets being built are displayed on the console and recorded in the
+    /// build report, on every build and not only when the target is analysed.
+    ///
+    /// `tags` categorize the warning: warnings with any tag listed in the comma separated
+    /// `analysis.suppress_warnings` buckconfig are not reported. Warnings emitted from
+    /// `dynamic_output` functions are ignored.
+    ///
+    /// ```python
+    /// def _impl(ctx):
+    ///     if ctx.attrs.legacy_flags:
+    ///         ctx.emit_warning("`legacy_flags` is deprecated, use `flags`", tags = ["deprecated"])
+    /// ```
+    fn emit_warning(
+        this: RefAnalysisContext,
+        #[starlark(require = pos)] message: &str,
+        #[starlark(require = named, default = Vec::new())] tags: Vec<String>,
+    ) -> anyhow::Result<NoneType> {
+        this.0.warnings.borrow_mut().push(AnalysisWarning {
+            message: message.to_owned(),
+            tags,
+        });
+        Ok(NoneType)
+    }
 }
 
 pub static REGISTER_CONTEXT_ACTIONS: LateBinding<fn(&mut MethodsBuilder)> =
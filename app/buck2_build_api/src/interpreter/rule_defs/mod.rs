@@ -27,7 +27,9 @@ pub mod cmd_args;
 pub mod command_executor_config;
 pub mod context;
 pub mod label_relative_path;
+pub mod package_values;
 pub mod provider;
+pub(crate) mod recorded_attrs;
 pub mod transition;
 pub mod transitive_set;
 
@@ -0,0 +1,259 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+
+use allocative::Allocative;
+use buck2_core::package::PackageLabel;
+use buck2_interpreter_for_build::interpreter::calculation::InterpreterCalculation;
+use derive_more::Display;
+use dice::DiceComputations;
+use dupe::Dupe;
+use once_cell::sync::OnceCell;
+use starlark::any::ProvidesStaticType;
+use starlark::environment::Methods;
+use starlark::environment::MethodsBuilder;
+use starlark::environment::MethodsStatic;
+use starlark::eval::Evaluator;
+use starlark::starlark_type;
+use starlark::values::AllocValue;
+use starlark::values::Heap;
+use starlark::values::NoSerialize;
+use starlark::values::OwnedFrozenValue;
+use starlark::values::StarlarkValue;
+use starlark::values::Trace;
+use starlark::values::Value;
+use starlark::values::ValueTyped;
+use starlark_map::small_map::SmallMap;
+
+#[derive(Debug, thiserror::Error)]
+enum PackageValuesError {
+    #[error("package value `{key}` has type `{actual}`, expected `{expected}`")]
+    WrongType {
+        key: String,
+        actual: String,
+        expected: &'static str,
+    },
+}
+
+/// Looks up the package values of a target the first time they are read, so that its analysis
+/// only depends on the `PACKAGE` files applying to the target if it uses their values.
+#[derive(Allocative)]
+pub(crate) struct PackageValuesLookup {
+    dice: DiceComputations,
+    package: PackageLabel,
+    #[allocative(skip)]
+    values: OnceCell<SmallMap<String, OwnedFrozenValue>>,
+}
+
+impl Debug for PackageValuesLookup {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PackageValuesLookup")
+            .field("package", &self.package)
+            .finish()
+    }
+}
+
+impl PackageValuesLookup {
+    pub(crate) fn new(dice: DiceComputations, package: PackageLabel) -> Self {
+        Self {
+            dice,
+            package,
+            values: OnceCell::new(),
+        }
+    }
+
+    /// Analysis runs synchronously, so block on the computation like the BXL context does.
+    fn values(&self) -> anyhow::Result<&SmallMap<String, OwnedFrozenValue>> {
+        self.values.get_or_try_init(|| {
+            tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current()
+                    .block_on(self.dice.get_package_values(self.package.dupe()))
+            })
+        })
+    }
+
+    pub(crate) fn alloc<'v>(
+        &self,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<ValueTyped<'v, PackageValues<'v>>> {
+        // IMPORTANT: Anything given back to the user must be kept alive
+        let values = self
+            .values()?
+            .iter()
+            .map(|(key, value)| (key.clone(), value.owned_value(eval.frozen_heap())))
+            .collect();
+        Ok(eval.heap().alloc_typed(PackageValues::new(values)))
+    }
+}
+
+/// The values written with `write_package_value` by the `PACKAGE` files applying to the package
+/// of the target being analysed.
+///
+/// Accessed via `ctx.package_values()`. The typed getters fail if the value has another type.
+///
+/// ```python
+/// def _impl(ctx):
+///     flags = ctx.package_values().get_list("cxx.extra_flags", [])
+///     ...
+/// ```
+#[derive(
+    ProvidesStaticType,
+    Debug,
+    Display,
+    Trace,
+    NoSerialize,
+    Allocative,
+    StarlarkDocs
+)]
+#[display(fmt = "<package_values>")]
+pub struct PackageValues<'v> {
+    values: SmallMap<String, Value<'v>>,
+}
+
+impl<'v> PackageValues<'v> {
+    pub(crate) fn new(values: SmallMap<String, Value<'v>>) -> Self {
+        Self { values }
+    }
+
+    fn get(&self, key: &str, default: Option<Value<'v>>) -> Value<'v> {
+        match self.values.get(key) {
+            Some(value) => *value,
+            None => default.unwrap_or_else(Value::new_none),
+        }
+    }
+
+    fn get_typed(
+        &self,
+        key: &str,
+        expected: &'static str,
+        default: Option<Value<'v>>,
+    ) -> anyhow::Result<Value<'v>> {
+        match self.values.get(key) {
+            Some(value) if value.get_type() != expected => Err(PackageValuesError::WrongType {
+                key: key.to_owned(),
+                actual: value.get_type().to_owned(),
+                expected,
+            }
+            .into()),
+            _ => Ok(self.get(key, default)),
+        }
+    }
+}
+
+impl<'v> StarlarkValue<'v> for PackageValues<'v> {
+    starlark_type!("package_values");
+
+    fn get_methods() -> Option<&'static Methods> {
+        static RES: MethodsStatic = MethodsStatic::new();
+        RES.methods(register_package_values)
+    }
+}
+
+impl<'v> AllocValue<'v> for PackageValues<'v> {
+    fn alloc_value(self, heap: &'v Heap) -> Value<'v> {
+        heap.alloc_complex_no_freeze(self)
+    }
+}
+
+#[starlark_module]
+fn register_package_values(builder: &mut MethodsBuilder) {
+    /// The keys of all the package values set, parent `PACKAGE` files first.
+    fn keys<'v>(this: &PackageValues<'v>) -> anyhow::Result<Vec<String>> {
+        Ok(this.values.keys().cloned().collect())
+    }
+
+    /// The value of `key`, or `default` if it is not set.
+    fn get<'v>(
+        this: &PackageValues<'v>,
+        #[starlark(require = pos)] key: &str,
+        #[starlark(require = pos)] default: Option<Value<'v>>,
+    ) -> anyhow::Result<Value<'v>> {
+        Ok(this.get(key, default))
+    }
+
+    /// The string value of `key`, or `default` if it is not set.
+    #[starlark(return_type = "[None, str.type]")]
+    fn get_str<'v>(
+        this: &PackageValues<'v>,
+        #[starlark(require = pos)] key: &str,
+        #[starlark(require = pos)] default: Option<Value<'v>>,
+    ) -> anyhow::Result<Value<'v>> {
+        this.get_typed(key, "string", default)
+    }
+
+    /// The boolean value of `key`, or `default` if it is not set.
+    #[starlark(return_type = "[None, bool.type]")]
+    fn get_bool<'v>(
+        this: &PackageValues<'v>,
+        #[starlark(require = pos)] key: &str,
+        #[starlark(require = pos)] default: Option<Value<'v>>,
+    ) -> anyhow::Result<Value<'v>> {
+        this.get_typed(key, "bool", default)
+    }
+
+    /// The integer value of `key`, or `default` if it is not set.
+    #[starlark(return_type = "[None, int.type]")]
+    fn get_int<'v>(
+        this: &PackageValues<'v>,
+        #[starlark(require = pos)] key: &str,
+        #[starlark(require = pos)] default: Option<Value<'v>>,
+    ) -> anyhow::Result<Value<'v>> {
+        this.get_typed(key, "int", default)
+    }
+
+    /// The list value of `key`, or `default` if it is not set.
+    fn get_list<'v>(
+        this: &PackageValues<'v>,
+        #[starlark(require = pos)] key: &str,
+        #[starlark(require = pos)] default: Option<Value<'v>>,
+    ) -> anyhow::Result<Value<'v>> {
+        this.get_typed(key, "list", default)
+    }
+
+    /// The dict value of `key`, or `default` if it is not set.
+    fn get_dict<'v>(
+        this: &PackageValues<'v>,
+        #[starlark(require = pos)] key: &str,
+        #[starlark(require = pos)] default: Option<Value<'v>>,
+    ) -> anyhow::Result<Value<'v>> {
+        this.get_typed(key, "dict", default)
+    }
+}
+
+pub mod tester {
+    use anyhow::Context;
+    use starlark::environment::GlobalsBuilder;
+    use starlark::values::dict::DictRef;
+    use starlark::values::Value;
+
+    use crate::interpreter::rule_defs::package_values::PackageValues;
+
+    #[starlark_module]
+    pub fn package_values_creator(builder: &mut GlobalsBuilder) {
+        /// Creates the `package_values` object `ctx.package_values()` would return for these
+        /// values.
+        fn create_package_values<'v>(values: Value<'v>) -> anyhow::Result<PackageValues<'v>> {
+            let values = DictRef::from_value(values).context("expected a dict")?;
+            Ok(PackageValues::new(
+                values
+                    .iter()
+                    .map(|(key, value)| {
+                        Ok((
+                            key.unpack_str().context("expected string keys")?.to_owned(),
+                            value,
+                        ))
+                    })
+                    .collect::<anyhow::Result<_>>()?,
+            ))
+        }
+    }
+}
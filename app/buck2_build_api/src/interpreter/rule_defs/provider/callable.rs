@@ -9,16 +9,20 @@
 
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::fmt;
 use std::fmt::Display;
 use std::sync::Arc;
+use std::sync::Mutex;
 
 use allocative::Allocative;
 use buck2_core::cells::cell_path::CellPath;
 use buck2_core::provider::id::ProviderId;
+use buck2_events::dispatch::console_message;
 use buck2_interpreter_for_build::provider::callable::ProviderCallableLike;
 use dupe::Dupe;
 use starlark::any::ProvidesStaticType;
+use starlark::collections::Hashed;
 use starlark::docs::DocItem;
 use starlark::docs::DocString;
 use starlark::environment::GlobalsBuilder;
@@ -40,6 +44,7 @@ use starlark::values::StarlarkValue;
 use starlark::values::Trace;
 use starlark::values::Value;
 use starlark::values::ValueLike;
+use starlark_map::small_map::SmallMap;
 use starlark_map::small_set::SmallSet;
 
 use crate::interpreter::rule_defs::provider::registration::ProviderRegistration;
@@ -60,13 +65,19 @@ enum ProviderCallableError {
 fn create_callable_function_signature(
     function_name: &str,
     fields: &SmallSet<String>,
+    aliases: &SmallMap<String, String>,
 ) -> ParametersSpec<FrozenValue> {
-    let mut signature = ParametersSpec::with_capacity(function_name.to_owned(), fields.len());
+    let mut signature =
+        ParametersSpec::with_capacity(function_name.to_owned(), fields.len() + aliases.len());
     // TODO(nmj): Should double check we don't actually need positional args in-repo
     signature.no_more_positional_args();
     for field in fields {
         signature.defaulted(field, FrozenValue::new_none());
     }
+    // Aliases come after the fields, see `user_provider_creator`.
+    for alias in aliases.keys() {
+        signature.defaulted(alias, FrozenValue::new_none());
+    }
 
     signature.finish()
 }
@@ -75,6 +86,58 @@ fn create_callable_function_signature(
 pub(crate) struct UserProviderCallableData {
     pub(crate) provider_id: Arc<ProviderId>,
     pub(crate) fields: SmallSet<String>,
+    /// Old field names which still resolve to a field, to migrate users of a renamed field.
+    pub(crate) aliases: ProviderFieldAliases,
+}
+
+impl UserProviderCallableData {
+    /// Index of the field with this name, following aliases.
+    pub(crate) fn field_index(&self, name: Hashed<&str>) -> Option<usize> {
+        if let Some(index) = self.fields.get_index_of_hashed(name) {
+            return Some(index);
+        }
+        let field = self.aliases.resolve(&self.provider_id, name.key())?;
+        self.fields.get_index_of(field)
+    }
+}
+
+/// Aliases declared with `provider(aliases = ...)`.
+#[derive(Debug, Clone, Default, Allocative)]
+pub struct ProviderFieldAliases {
+    /// Map from the alias to the field it resolves to.
+    pub(crate) aliases: SmallMap<String, String>,
+    /// Whether using an alias prints a warning.
+    pub(crate) warn: bool,
+    /// Aliases which were already warned about, so each one is only reported once.
+    #[allocative(skip)]
+    warned: Arc<Mutex<HashSet<String>>>,
+}
+
+impl ProviderFieldAliases {
+    pub fn new(aliases: SmallMap<String, String>, warn: bool) -> Self {
+        Self {
+            aliases,
+            warn,
+            warned: Default::default(),
+        }
+    }
+
+    /// Whether a warning should be printed for this use of the alias: only the first one is.
+    fn should_warn(&self, alias: &str) -> bool {
+        self.warn && self.warned.lock().unwrap().insert(alias.to_owned())
+    }
+
+    /// The field this alias resolves to, warning about the first use of the alias if requested.
+    pub(crate) fn resolve(&self, provider_id: &ProviderId, alias: &str) -> Option<&str> {
+        let field = self.aliases.get(alias)?;
+        if self.should_warn(alias) {
+            console_message(format!(
+                "Field `{}` of provider `{}` is deprecated, use `{}` instead",
+                alias, provider_id.name, field
+            ));
+        }
+        Some(field)
+    }
 }
 
 #[derive(Debug, Trace, Allocative)]
@@ -122,6 +185,8 @@ pub struct UserProviderCallable {
     field_docs: Vec<Option<DocString>>,
     /// The names of the fields used in `callable`
     fields: SmallSet<String>,
+    /// Old names of fields
+    aliases: ProviderFieldAliases,
     /// The actual callable that creates instances of `UserProvider`
     callable: RefCell<UserProviderCallableImpl>,
 }
@@ -150,6 +215,7 @@ impl UserProviderCallable {
         docs: Option<DocString>,
         field_docs: Vec<Option<DocString>>,
         fields: SmallSet<String>,
+        aliases: ProviderFieldAliases,
     ) -> Self {
         assert_eq!(
             field_docs.len(),
@@ -164,6 +230,7 @@ impl UserProviderCallable {
             docs,
             field_docs,
             fields,
+            aliases,
             callable: RefCell::new(UserProviderCallableImpl::Unbound),
         }
     }
@@ -228,11 +295,16 @@ impl<'v> StarlarkValue<'v> for UserProviderCallable {
             });
             *id = Some(new_id.dupe());
             *self.callable.borrow_mut() = UserProviderCallableImpl::Bound(
-                create_callable_function_signature(&new_id.name, &self.fields),
+                create_callable_function_signature(
+                    &new_id.name,
+                    &self.fields,
+                    &self.aliases.aliases,
+                ),
                 eval.frozen_heap()
                     .alloc_any_display_from_debug(UserProviderCallableData {
                         provider_id: new_id,
                         fields: self.fields.clone(),
+                        aliases: self.aliases.clone(),
                     }),
             );
         }
@@ -380,3 +452,26 @@ fn provider_callable_methods(builder: &mut MethodsBuilder) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use starlark_map::small_map::SmallMap;
+
+    use crate::interpreter::rule_defs::provider::callable::ProviderFieldAliases;
+
+    #[test]
+    fn test_warn_once_per_alias() {
+        let mut aliases = SmallMap::new();
+        aliases.insert("old_a".to_owned(), "a".to_owned());
+        aliases.insert("old_b".to_owned(), "b".to_owned());
+
+        let warning = ProviderFieldAliases::new(aliases.clone(), true);
+        assert!(warning.should_warn("old_a"));
+        assert!(!warning.should_warn("old_a"));
+        assert!(warning.should_warn("old_b"));
+        assert!(!warning.clone().should_warn("old_b"));
+
+        let silent = ProviderFieldAliases::new(aliases, false);
+        assert!(!silent.should_warn("old_a"));
+    }
+}
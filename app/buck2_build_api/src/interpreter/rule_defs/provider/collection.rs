@@ -99,6 +99,17 @@ enum ProviderCollectionError {
     AtNotFound(String, Vec<String>),
 }
 
+/// Whether `error` is the one returned when looking up a sub-target named `name` which the rule
+/// does not define, as opposed to any other error (e.g. of the analysis itself).
+pub fn is_missing_sub_target_error(error: &anyhow::Error, name: &ProviderName) -> bool {
+    error.chain().any(|e| {
+        matches!(
+            e.downcast_ref::<ProviderCollectionError>(),
+            Some(ProviderCollectionError::RequestedInvalidSubTarget(missing, ..)) if missing == name
+        )
+    })
+}
+
 /// Holds a collection of `UserProvider`s. These can be accessed in Starlark by indexing on
 /// a `ProviderCallable` object.
 ///
@@ -534,3 +545,50 @@ pub mod tester {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Context;
+    use buck2_core::configuration::data::ConfigurationData;
+    use buck2_core::provider::label::ConfiguredProvidersLabel;
+    use buck2_core::provider::label::ProviderName;
+    use buck2_core::provider::label::ProvidersName;
+    use buck2_core::target::label::ConfiguredTargetLabel;
+
+    use crate::interpreter::rule_defs::provider::collection::is_missing_sub_target_error;
+    use crate::interpreter::rule_defs::provider::collection::ProviderCollectionError;
+
+    #[test]
+    fn test_is_missing_sub_target_error() {
+        let label = ConfiguredProvidersLabel::new(
+            ConfiguredTargetLabel::testing_parse("cell//pkg:foo", ConfigurationData::testing_new()),
+            ProvidersName::Default,
+        );
+        let foo = ProviderName::new_unchecked("foo".to_owned());
+        let bar = ProviderName::new_unchecked("bar".to_owned());
+
+        let missing = || {
+            anyhow::Error::from(ProviderCollectionError::RequestedInvalidSubTarget(
+                foo.clone(),
+                label.clone(),
+                vec!["baz".to_owned()],
+            ))
+        };
+        assert!(is_missing_sub_target_error(&missing(), &foo));
+        assert!(!is_missing_sub_target_error(&missing(), &bar));
+        assert!(is_missing_sub_target_error(
+            &missing().context("Error looking up providers"),
+            &foo
+        ));
+
+        let other = anyhow::Error::from(ProviderCollectionError::UnknownFlavors {
+            target: "cell//pkg:foo".to_owned(),
+            flavor: "foo".to_owned(),
+        });
+        assert!(!is_missing_sub_target_error(&other, &foo));
+        assert!(!is_missing_sub_target_error(
+            &anyhow::anyhow!("requested sub target named `foo` is not available"),
+            &foo
+        ));
+    }
+}
@@ -293,4 +293,47 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn provider_field_aliases() -> anyhow::Result<()> {
+        let mut tester = provider_tester();
+        tester.run_starlark_test(indoc!(
+            r#"
+            FooInfo = provider(fields=["bar", "baz"], aliases={"old_bar": "bar"})
+
+            def test():
+                foo_1 = FooInfo(bar="bar_1")
+                assert_eq("bar_1", foo_1.old_bar)
+                foo_2 = FooInfo(old_bar="bar_2")
+                assert_eq("bar_2", foo_2.bar)
+                assert_eq("bar_2", foo_2.old_bar)
+                assert_eq("FooInfo(bar=\"bar_2\", baz=None)", repr(foo_2))
+            "#
+        ))?;
+
+        let mut tester = provider_tester();
+        tester.run_starlark_test_expecting_error(
+            indoc!(
+                r#"
+            FooInfo = provider(fields=["bar"], aliases={"old_bar": "bar"})
+
+            def test():
+                FooInfo(bar="bar", old_bar="old_bar")
+            "#
+            ),
+            "Cannot set both field `bar` and its alias `old_bar`",
+        );
+
+        let mut tester = provider_tester();
+        tester.run_starlark_test_expecting_error(
+            indoc!(
+                r#"
+            FooInfo = provider(fields=["bar"], aliases={"old_bar": "baz"})
+            "#
+            ),
+            "which is not a field of the provider",
+        );
+
+        Ok(())
+    }
 }
@@ -40,6 +40,12 @@ use crate::interpreter::rule_defs::provider::callable::UserProviderCallableData;
 use crate::interpreter::rule_defs::provider::provider_methods;
 use crate::interpreter::rule_defs::provider::ProviderLike;
 
+#[derive(Debug, thiserror::Error)]
+enum UserProviderError {
+    #[error("Cannot set both field `{field}` and its alias `{alias}`")]
+    AliasAndFieldSet { alias: String, field: String },
+}
+
 /// The result of calling the output of `provider()`. This is just a simple data structure of
 /// either immediately available values or, later, `FutureValue` types that are resolved
 /// asynchronously
@@ -96,7 +102,7 @@ where
     }
 
     fn get_attr_hashed(&self, attribute: Hashed<&str>, _heap: &'v Heap) -> Option<Value<'v>> {
-        let index = self.callable.fields.get_index_of_hashed(attribute)?;
+        let index = self.callable.field_index(attribute)?;
         Some(self.attributes[index].to_value())
     }
 
@@ -161,7 +167,7 @@ impl<'v, V: ValueLike<'v>> ProviderLike<'v> for UserProviderGen<'v, V> {
     }
 
     fn get_field(&self, name: &str) -> Option<Value<'v>> {
-        let index = self.callable.fields.get_index_of(name)?;
+        let index = self.callable.field_index(Hashed::new(name))?;
         Some(self.attributes[index].to_value())
     }
 
@@ -177,11 +183,34 @@ pub(crate) fn user_provider_creator<'v>(
     mut param_parser: ParametersParser<'v, '_>,
 ) -> anyhow::Result<Value<'v>> {
     let heap = eval.heap();
-    let values = callable
+    let mut values = callable
         .fields
         .iter()
         .map(|field| param_parser.next(field))
         .collect::<anyhow::Result<Vec<Value>>>()?;
+    // Aliases are parsed after the fields, in the order they were declared.
+    for alias in callable.aliases.aliases.keys() {
+        let value: Value = param_parser.next(alias)?;
+        if value.is_none() {
+            continue;
+        }
+        let field = callable
+            .aliases
+            .resolve(&callable.provider_id, alias)
+            .expect("alias to be declared");
+        let index = callable
+            .fields
+            .get_index_of(field)
+            .expect("alias to resolve to a field");
+        if !values[index].is_none() {
+            return Err(UserProviderError::AliasAndFieldSet {
+                alias: alias.clone(),
+                field: field.to_owned(),
+            }
+            .into());
+        }
+        values[index] = value;
+    }
     Ok(heap.alloc(UserProvider {
         callable,
         attributes: values,
@@ -0,0 +1,192 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::fmt;
+use std::fmt::Display;
+use std::rc::Rc;
+
+use allocative::Allocative;
+use serde::Serialize;
+use serde::Serializer;
+use starlark::any::ProvidesStaticType;
+use starlark::starlark_type;
+use starlark::values::Freeze;
+use starlark::values::Freezer;
+use starlark::values::FrozenValue;
+use starlark::values::Heap;
+use starlark::values::StarlarkValue;
+use starlark::values::Trace;
+use starlark::values::Value;
+
+use crate::analysis::attrs_read::AttrsReadRecorder;
+
+/// `ctx.attrs`: the attributes struct of the target, recording which attributes are read by the
+/// analysis. Anything which may observe all the attributes (`dir`, `repr`, comparisons, storing
+/// the struct in a provider...) records that all of them were read.
+#[derive(Debug, Trace, ProvidesStaticType, Allocative)]
+pub(crate) struct RecordedAttrs<'v> {
+    attrs: Value<'v>,
+    #[trace(unsafe_ignore)]
+    #[allocative(skip)]
+    attrs_read: Rc<AttrsReadRecorder>,
+}
+
+impl<'v> RecordedAttrs<'v> {
+    pub(crate) fn new(attrs: Value<'v>, attrs_read: Rc<AttrsReadRecorder>) -> Self {
+        Self { attrs, attrs_read }
+    }
+}
+
+impl<'v> Display for RecordedAttrs<'v> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.attrs_read.record_all();
+        Display::fmt(&self.attrs, f)
+    }
+}
+
+impl<'v> Serialize for RecordedAttrs<'v> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.attrs_read.record_all();
+        self.attrs.serialize(serializer)
+    }
+}
+
+impl<'v> StarlarkValue<'v> for RecordedAttrs<'v> {
+    starlark_type!("struct");
+
+    fn get_attr(&self, attribute: &str, heap: &'v Heap) -> Option<Value<'v>> {
+        self.attrs_read.record(attribute);
+        self.attrs.get_attr(attribute, heap).ok().flatten()
+    }
+
+    fn dir_attr(&self) -> Vec<String> {
+        self.attrs_read.record_all();
+        self.attrs.dir_attr()
+    }
+
+    fn equals(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        self.attrs_read.record_all();
+        match other.downcast_ref::<RecordedAttrs>() {
+            Some(other) => self.attrs.equals(other.attrs),
+            None => self.attrs.equals(other),
+        }
+    }
+}
+
+impl<'v> Freeze for RecordedAttrs<'v> {
+    type Frozen = FrozenRecordedAttrs;
+
+    fn freeze(self, freezer: &Freezer) -> anyhow::Result<FrozenRecordedAttrs> {
+        // The attributes escaped the analysis, so anything may read them later.
+        self.attrs_read.record_all();
+        Ok(FrozenRecordedAttrs {
+            attrs: self.attrs.freeze(freezer)?,
+        })
+    }
+}
+
+/// `ctx.attrs` once frozen. Nothing is recorded any more, since frozen values outlive the
+/// analysis.
+#[derive(Debug, ProvidesStaticType, Allocative)]
+pub(crate) struct FrozenRecordedAttrs {
+    attrs: FrozenValue,
+}
+
+impl Display for FrozenRecordedAttrs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.attrs, f)
+    }
+}
+
+impl Serialize for FrozenRecordedAttrs {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.attrs.to_value().serialize(serializer)
+    }
+}
+
+impl<'v> StarlarkValue<'v> for FrozenRecordedAttrs {
+    starlark_type!("struct");
+
+    fn get_attr(&self, attribute: &str, heap: &'v Heap) -> Option<Value<'v>> {
+        self.attrs
+            .to_value()
+            .get_attr(attribute, heap)
+            .ok()
+            .flatten()
+    }
+
+    fn dir_attr(&self) -> Vec<String> {
+        self.attrs.to_value().dir_attr()
+    }
+
+    fn equals(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        match other.downcast_ref::<FrozenRecordedAttrs>() {
+            Some(other) => self.attrs.to_value().equals(other.attrs.to_value()),
+            None => self.attrs.to_value().equals(other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::rc::Rc;
+
+    use starlark::values::structs::AllocStruct;
+    use starlark::values::Heap;
+    use starlark::values::StarlarkValue;
+    use starlark::values::Value;
+
+    use crate::analysis::attrs_read::AttrsRead;
+    use crate::analysis::attrs_read::AttrsReadRecorder;
+    use crate::interpreter::rule_defs::recorded_attrs::RecordedAttrs;
+
+    fn recorded_attrs<'v>(heap: &'v Heap) -> (RecordedAttrs<'v>, Rc<AttrsReadRecorder>) {
+        let attrs: Value<'v> = heap.alloc(AllocStruct([("srcs", 1), ("deps", 2)]));
+        let attrs_read = Rc::new(AttrsReadRecorder::default());
+        (RecordedAttrs::new(attrs, attrs_read.clone()), attrs_read)
+    }
+
+    #[test]
+    fn test_get_attr() {
+        let heap = Heap::new();
+        let (attrs, attrs_read) = recorded_attrs(&heap);
+
+        assert_eq!(
+            Some(1),
+            attrs.get_attr("srcs", &heap).and_then(|v| v.unpack_int())
+        );
+        assert_eq!(
+            Some(1),
+            attrs.get_attr("srcs", &heap).and_then(|v| v.unpack_int())
+        );
+        assert_eq!(
+            AttrsRead::Some(BTreeSet::from(["srcs".to_owned()])),
+            attrs_read.take()
+        );
+    }
+
+    #[test]
+    fn test_observing_all_attrs() {
+        let heap = Heap::new();
+
+        let (attrs, attrs_read) = recorded_attrs(&heap);
+        assert_eq!(vec!["srcs", "deps"], attrs.dir_attr());
+        assert_eq!(AttrsRead::All, attrs_read.take());
+
+        let (attrs, attrs_read) = recorded_attrs(&heap);
+        assert_eq!("struct(srcs=1, deps=2)", attrs.to_string());
+        assert_eq!(AttrsRead::All, attrs_read.take());
+
+        let (attrs, attrs_read) = recorded_attrs(&heap);
+        let other = heap.alloc(AllocStruct([("srcs", 1), ("deps", 2)]));
+        assert!(attrs.equals(other).unwrap());
+        assert_eq!(AttrsRead::All, attrs_read.take());
+    }
+}
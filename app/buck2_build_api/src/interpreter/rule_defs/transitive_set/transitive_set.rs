@@ -124,6 +124,18 @@ impl<V> TransitiveSetGen<V> {
     pub fn key(&self) -> &TransitiveSetKey {
         &self.key
     }
+
+    /// The pre-computed projections of the value of this node, if it has one.
+    pub fn node_projections(&self) -> Option<&[V]> {
+        self.node.as_ref().map(|node| &*node.projections)
+    }
+}
+
+impl<V: Copy> TransitiveSetGen<V> {
+    /// The definition of this set, as returned by `transitive_set()`.
+    pub fn definition(&self) -> V {
+        self.definition
+    }
 }
 
 impl<'v> NodeGen<Value<'v>> {
@@ -50,6 +50,7 @@ use buck2_node::configuration::toolchain_constraints::ToolchainConstraints;
 use buck2_node::nodes::configured::ConfiguredTargetNode;
 use buck2_node::nodes::unconfigured::TargetNode;
 use buck2_node::visibility::VisibilityError;
+use buck2_query::query::compatibility::CompatibilityAttribute;
 use buck2_query::query::compatibility::IncompatiblePlatformReason;
 use buck2_query::query::compatibility::IncompatiblePlatformReasonCause;
 use buck2_query::query::compatibility::MaybeCompatible;
@@ -76,6 +77,14 @@ enum NodeCalculationError {
         LEGACY_TARGET_COMPATIBLE_WITH_ATTRIBUTE_FIELD
     )]
     BothTargetCompatibleWith(String),
+    #[error(
+        "`{0}` resolved to execution platform `{1}`, but with the `exec:` keys of its `select()`s \
+        matched against that platform, its execution platform constraints (`{}`, exec deps or \
+        toolchain deps) differ from those it was resolved with. `exec:` selects must not affect \
+        the resolution of the execution platform.",
+        EXEC_COMPATIBLE_WITH_ATTRIBUTE_FIELD
+    )]
+    ExecSelectChangesExecutionPlatform(TargetLabel, String),
 }
 
 #[async_trait]
@@ -93,8 +102,8 @@ pub trait NodeCalculation {
 }
 
 enum CompatibilityConstraints {
-    Any(ConfiguredAttr),
-    All(ConfiguredAttr),
+    Any((ConfiguredAttr, Option<String>)),
+    All((ConfiguredAttr, Option<String>)),
 }
 
 async fn compute_platform_cfgs(
@@ -152,7 +161,7 @@ pub(crate) async fn find_execution_platform_by_configuration(
     }
 }
 
-#[derive(Default)]
+#[derive(Default, PartialEq)]
 pub struct ExecutionPlatformConstraints {
     exec_deps: IndexSet<TargetLabel>,
     toolchain_deps: IndexSet<ConfiguredTargetLabel>,
@@ -189,11 +198,14 @@ impl ExecutionPlatformConstraints {
         }
     }
 
+    /// `exec_resolved_configuration` is used to match the `exec:` keys of `select()`s. It is `None`
+    /// while resolving the execution platform, in which case these selects use their default.
     async fn new(
         ctx: &DiceComputations,
         node: &TargetNode,
         resolved_configuration: &ResolvedConfiguration,
         resolved_transitions: &OrderedMap<Arc<TransitionId>, Arc<TransitionApplied>>,
+        exec_resolved_configuration: Option<&ResolvedConfiguration>,
     ) -> SharedResult<Self> {
         let mut me = Self::default();
 
@@ -207,7 +219,8 @@ impl ExecutionPlatformConstraints {
             // if something here changes.
             resolved_transitions,
             &platform_cfgs,
-        );
+        )
+        .with_exec_resolved_cfg(exec_resolved_configuration);
 
         for a in node.attrs(AttrInspectOptions::All) {
             let configured_attr = a.configure(&cfg_ctx).with_context(|| {
@@ -322,6 +335,7 @@ async fn execution_platforms_for_toolchain(
                 &node,
                 resolved_configuration,
                 &OrderedMap::new(),
+                None,
             )
             .await?;
             constraints.many(ctx, &self.0).await
@@ -388,17 +402,57 @@ async fn resolve_execution_platform(
         ));
     };
 
-    let constraints =
-        ExecutionPlatformConstraints::new(ctx, node, resolved_configuration, resolved_transitions)
+    let constraints = ExecutionPlatformConstraints::new(
+        ctx,
+        node,
+        resolved_configuration,
+        resolved_transitions,
+        None,
+    )
+    .await?;
+    let resolution = constraints.one(ctx, node).await?;
+
+    // `exec:` selects use their default while resolving the execution platform, since it is not
+    // known yet. Check that matching them against the resolved platform does not change the
+    // constraints it was resolved with: the platform would not satisfy them otherwise.
+    if node.get_exec_configuration_deps().next().is_some() {
+        if let Ok(platform) = resolution.platform() {
+            let exec_resolved_configuration = ctx
+                .get_resolved_configuration(
+                    platform.cfg(),
+                    node.label().pkg().cell_name(),
+                    node.get_exec_configuration_deps(),
+                )
+                .await?;
+            let resolved_constraints = ExecutionPlatformConstraints::new(
+                ctx,
+                node,
+                resolved_configuration,
+                resolved_transitions,
+                Some(&exec_resolved_configuration),
+            )
             .await?;
-    constraints.one(ctx, node).await
+            if resolved_constraints != constraints {
+                return Err(SharedError::new(
+                    NodeCalculationError::ExecSelectChangesExecutionPlatform(
+                        node.label().dupe(),
+                        platform.id(),
+                    ),
+                ));
+            }
+        }
+    }
+
+    Ok(resolution)
 }
 
+/// The constraints listed in a compatibility attribute, if any, along with the key of the
+/// `select()` the attribute was resolved with, if it is a `select()`.
 fn unpack_target_compatible_with_attr(
     target_node: &TargetNode,
     resolved_cfg: &ResolvedConfiguration,
     attr_name: &str,
-) -> anyhow::Result<Option<ConfiguredAttr>> {
+) -> anyhow::Result<Option<(ConfiguredAttr, Option<String>)>> {
     let attr = target_node.attr_or_none(attr_name, AttrInspectOptions::All);
     let attr = match attr {
         Some(attr) => attr,
@@ -448,14 +502,16 @@ fn unpack_target_compatible_with_attr(
         }
     }
 
+    let ctx = AttrConfigurationContextToResolveCompatibleWith { resolved_cfg };
+    let select_key = attr.value.selected_key(&ctx)?;
     let attr = attr
-        .configure(&AttrConfigurationContextToResolveCompatibleWith { resolved_cfg })
+        .configure(&ctx)
         .with_context(|| format!("Error configuring attribute `{}`", attr_name))?;
 
     match attr.value.unpack_list() {
         Some(values) => {
             if !values.is_empty() {
-                Ok(Some(attr.value))
+                Ok(Some((attr.value, select_key)))
             } else {
                 Ok(None)
             }
@@ -515,50 +571,77 @@ fn check_compatible(
         Ok((left, right))
     };
 
-    // We only record the first incompatibility, for either ANY or ALL.
-    // TODO(cjhopman): Should we report _all_ the things that are incompatible?
-    let incompatible_target = match compatibility_constraints {
-        CompatibilityConstraints::Any(attr) => {
+    // We record every unsatisfied constraint, for either ANY or ALL.
+    let (attr, select_key, unsatisfied) = match compatibility_constraints {
+        CompatibilityConstraints::Any((attr, select_key)) => {
             let (compatible, incompatible) = check_compatibility(attr).with_context(|| {
                 format!(
                     "attribute `{}`",
                     LEGACY_TARGET_COMPATIBLE_WITH_ATTRIBUTE_FIELD
                 )
             })?;
-            let incompatible = incompatible.into_iter().next();
-            match (compatible.is_empty(), incompatible.into_iter().next()) {
-                (false, _) | (true, None) => {
-                    return Ok(MaybeCompatible::Compatible(()));
-                }
-                (true, Some(v)) => v,
+            if !compatible.is_empty() || incompatible.is_empty() {
+                return Ok(MaybeCompatible::Compatible(()));
             }
+            (
+                CompatibilityAttribute::CompatibleWith,
+                select_key,
+                incompatible,
+            )
         }
-        CompatibilityConstraints::All(attr) => {
+        CompatibilityConstraints::All((attr, select_key)) => {
             let (_compatible, incompatible) = check_compatibility(attr).with_context(|| {
                 format!("attribute `{}`", TARGET_COMPATIBLE_WITH_ATTRIBUTE_FIELD)
             })?;
-            match incompatible.into_iter().next() {
-                Some(label) => label,
-                None => {
-                    return Ok(MaybeCompatible::Compatible(()));
-                }
+            if incompatible.is_empty() {
+                return Ok(MaybeCompatible::Compatible(()));
             }
+            (
+                CompatibilityAttribute::TargetCompatibleWith,
+                select_key,
+                incompatible,
+            )
         }
     };
     Ok(MaybeCompatible::Incompatible(Arc::new(
         IncompatiblePlatformReason {
             target: target_label.dupe(),
-            cause: IncompatiblePlatformReasonCause::UnsatisfiedConfig(incompatible_target),
+            cause: IncompatiblePlatformReasonCause::UnsatisfiedConfig {
+                attr,
+                select_key,
+                unsatisfied,
+            },
         },
     )))
 }
 
-/// Compute configured target node ignoring transition for this node.
-async fn compute_configured_target_node_no_transition(
+/// Everything needed to configure the attributes of a target, which does not depend on the
+/// nodes of its deps.
+struct TargetAttrsConfiguration {
+    resolved_configuration: ResolvedConfiguration,
+    resolved_transitions: OrderedMap<Arc<TransitionId>, Arc<TransitionApplied>>,
+    execution_platform_resolution: ExecutionPlatformResolution,
+    exec_resolved_configuration: Option<ResolvedConfiguration>,
+    platform_cfgs: OrderedMap<TargetLabel, ConfigurationData>,
+}
+
+impl TargetAttrsConfiguration {
+    fn attr_cfg_ctx(&self) -> AttrConfigurationContextImpl<'_> {
+        AttrConfigurationContextImpl::new(
+            &self.resolved_configuration,
+            self.execution_platform_resolution.cfg(),
+            &self.resolved_transitions,
+            &self.platform_cfgs,
+        )
+        .with_exec_resolved_cfg(self.exec_resolved_configuration.as_ref())
+    }
+}
+
+async fn compute_target_attrs_configuration(
     target_label: &ConfiguredTargetLabel,
-    target_node: TargetNode,
+    target_node: &TargetNode,
     ctx: &DiceComputations,
-) -> anyhow::Result<MaybeCompatible<ConfiguredTargetNode>> {
+) -> anyhow::Result<MaybeCompatible<TargetAttrsConfiguration>> {
     let target_cfg = target_label.cfg();
     let target_cell = target_node.label().pkg().cell_name();
     let resolved_configuration = ctx
@@ -571,31 +654,14 @@ async fn compute_configured_target_node_no_transition(
 
     // Must check for compatibility before evaluating non-compatibility attributes.
     if let MaybeCompatible::Incompatible(reason) =
-        check_compatible(target_label, &target_node, &resolved_configuration)?
+        check_compatible(target_label, target_node, &resolved_configuration)?
     {
         return Ok(MaybeCompatible::Incompatible(reason));
     }
 
-    struct Traversal<'a> {
-        deps: &'a mut SmallSet<ConfiguredProvidersLabel>,
-        exec_deps: &'a mut SmallSet<ConfiguredProvidersLabel>,
-    }
-
-    impl ConfiguredAttrTraversal for Traversal<'_> {
-        fn dep(&mut self, dep: &ConfiguredProvidersLabel) -> anyhow::Result<()> {
-            self.deps.insert(dep.clone());
-            Ok(())
-        }
-
-        fn exec_dep(&mut self, dep: &ConfiguredProvidersLabel) -> anyhow::Result<()> {
-            self.exec_deps.insert(dep.clone());
-            Ok(())
-        }
-    }
-
     let mut resolved_transitions = OrderedMap::new();
     for (_dep, tr) in target_node.transition_deps() {
-        let resolved_cfg = ctx.apply_transition(&target_node, target_cfg, tr).await?;
+        let resolved_cfg = ctx.apply_transition(target_node, target_cfg, tr).await?;
         resolved_transitions.insert(tr.dupe(), resolved_cfg);
     }
 
@@ -623,32 +689,84 @@ async fn compute_configured_target_node_no_transition(
     } else {
         resolve_execution_platform(
             ctx,
-            &target_node,
+            target_node,
             &resolved_configuration,
             &resolved_transitions,
         )
         .await?
     };
 
+    // `exec:` select keys are matched against the configuration of the execution platform. If
+    // there is none, these selects fall back to their default.
+    let has_exec_selects = target_node.get_exec_configuration_deps().next().is_some();
+    let exec_resolved_configuration = if !has_exec_selects {
+        None
+    } else if let Ok(platform) = execution_platform_resolution.platform() {
+        Some(
+            ctx.get_resolved_configuration(
+                platform.cfg(),
+                target_cell,
+                target_node.get_exec_configuration_deps(),
+            )
+            .await?,
+        )
+    } else {
+        None
+    };
+
+    let platform_cfgs = compute_platform_cfgs(ctx, target_node).await?;
+
+    Ok(MaybeCompatible::Compatible(TargetAttrsConfiguration {
+        resolved_configuration,
+        resolved_transitions,
+        execution_platform_resolution,
+        exec_resolved_configuration,
+        platform_cfgs,
+    }))
+}
+
+/// Compute configured target node ignoring transition for this node.
+async fn compute_configured_target_node_no_transition(
+    target_label: &ConfiguredTargetLabel,
+    target_node: TargetNode,
+    ctx: &DiceComputations,
+) -> anyhow::Result<MaybeCompatible<ConfiguredTargetNode>> {
+    let configuration =
+        match compute_target_attrs_configuration(target_label, &target_node, ctx).await? {
+            MaybeCompatible::Compatible(configuration) => configuration,
+            MaybeCompatible::Incompatible(reason) => {
+                return Ok(MaybeCompatible::Incompatible(reason));
+            }
+        };
+
+    struct Traversal<'a> {
+        deps: &'a mut SmallSet<ConfiguredProvidersLabel>,
+        exec_deps: &'a mut SmallSet<ConfiguredProvidersLabel>,
+    }
+
+    impl ConfiguredAttrTraversal for Traversal<'_> {
+        fn dep(&mut self, dep: &ConfiguredProvidersLabel) -> anyhow::Result<()> {
+            self.deps.insert(dep.clone());
+            Ok(())
+        }
+
+        fn exec_dep(&mut self, dep: &ConfiguredProvidersLabel) -> anyhow::Result<()> {
+            self.exec_deps.insert(dep.clone());
+            Ok(())
+        }
+    }
+
     let mut deps = SmallSet::new();
     let mut exec_deps = SmallSet::new();
 
-    let platform_cfgs = compute_platform_cfgs(ctx, &target_node).await?;
-
     // We need to collect deps and to ensure that all attrs can be successfully
     // configured so that we don't need to support propagate configuration errors on attr access.
+    let attr_cfg_ctx = configuration.attr_cfg_ctx();
     for a in target_node.attrs(AttrInspectOptions::All) {
         let mut traversal = Traversal {
             deps: &mut deps,
             exec_deps: &mut exec_deps,
         };
-        let attr_cfg_ctx = AttrConfigurationContextImpl::new(
-            &resolved_configuration,
-            execution_platform_resolution.cfg(),
-            &resolved_transitions,
-            &platform_cfgs,
-        );
-
         let configured_attr = a.configure(&attr_cfg_ctx)?;
         configured_attr.traverse(target_node.label().pkg(), &mut traversal)?;
     }
@@ -718,12 +836,13 @@ async fn compute_configured_target_node_no_transition(
     Ok(MaybeCompatible::Compatible(ConfiguredTargetNode::new(
         target_label.dupe(),
         target_node.dupe(),
-        resolved_configuration,
-        resolved_transitions,
-        execution_platform_resolution,
+        configuration.resolved_configuration,
+        configuration.resolved_transitions,
+        configuration.execution_platform_resolution,
+        configuration.exec_resolved_configuration,
         deps,
         exec_deps,
-        platform_cfgs,
+        configuration.platform_cfgs,
     )))
 }
 
@@ -755,6 +874,59 @@ async fn compute_configured_target_node_with_transition(
     })
 }
 
+/// The deps of a configured target, along with the name of the attribute referencing each of
+/// them. Unlike [`NodeCalculation::get_configured_target_node`], this doesn't compute the nodes
+/// of the deps, so it works when the deps form a cycle. The transition of the rule of the target,
+/// if any, is applied first, as when computing its node.
+pub async fn get_configured_target_attr_deps(
+    ctx: &DiceComputations,
+    target_label: &ConfiguredTargetLabel,
+) -> anyhow::Result<MaybeCompatible<Vec<(String, ConfiguredTargetLabel)>>> {
+    let target_node = ctx.get_target_node(target_label.unconfigured()).await?;
+    let target_label = match &target_node.0.rule.cfg {
+        Some(transition_id) => {
+            let cfg = ctx
+                .apply_transition(&target_node, target_label.cfg(), transition_id)
+                .await?;
+            target_label.unconfigured().configure(cfg.single()?.dupe())
+        }
+        None => target_label.dupe(),
+    };
+    let configuration =
+        match compute_target_attrs_configuration(&target_label, &target_node, ctx).await? {
+            MaybeCompatible::Compatible(configuration) => configuration,
+            MaybeCompatible::Incompatible(reason) => {
+                return Ok(MaybeCompatible::Incompatible(reason));
+            }
+        };
+
+    struct Traversal<'a> {
+        attr: &'a str,
+        deps: &'a mut Vec<(String, ConfiguredTargetLabel)>,
+    }
+
+    impl ConfiguredAttrTraversal for Traversal<'_> {
+        fn dep(&mut self, dep: &ConfiguredProvidersLabel) -> anyhow::Result<()> {
+            self.deps.push((self.attr.to_owned(), dep.target().dupe()));
+            Ok(())
+        }
+    }
+
+    let mut deps = Vec::new();
+    let attr_cfg_ctx = configuration.attr_cfg_ctx();
+    for a in target_node.attrs(AttrInspectOptions::All) {
+        let configured_attr = a.configure(&attr_cfg_ctx)?;
+        configured_attr.traverse(
+            target_node.label().pkg(),
+            &mut Traversal {
+                attr: configured_attr.name,
+                deps: &mut deps,
+            },
+        )?;
+    }
+    Ok(MaybeCompatible::Compatible(deps))
+}
+
 async fn compute_configured_target_node(
     key: &ConfiguredTargetNodeKey,
     ctx: &DiceComputations,
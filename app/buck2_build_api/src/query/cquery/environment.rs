@@ -12,6 +12,7 @@ use std::sync::Arc;
 use anyhow::Context;
 use async_trait::async_trait;
 use buck2_core::cells::cell_path::CellPath;
+use buck2_core::configuration::data::ConfigurationData;
 use buck2_core::target::label::ConfiguredTargetLabel;
 use buck2_core::target::label::TargetLabel;
 use buck2_events::dispatch::console_message;
@@ -29,8 +30,10 @@ use buck2_query::query::traversal::async_depth_limited_traversal;
 use buck2_query::query::traversal::AsyncNodeLookup;
 use buck2_query::query::traversal::AsyncTraversalDelegate;
 use dupe::Dupe;
+use starlark_map::small_set::SmallSet;
 use tracing::warn;
 
+use crate::query::cquery::functions::CqueryFunctionsModule;
 use crate::query::uquery::environment::allbuildfiles;
 use crate::query::uquery::environment::rbuildfiles;
 use crate::query::uquery::environment::QueryLiterals;
@@ -106,7 +109,10 @@ impl<'c> CqueryEnvironment<'c> {
     pub fn describe() -> QueryEnvironmentDescription {
         QueryEnvironmentDescription {
             name: "Cquery Environment".to_owned(),
-            mods: vec![DefaultQueryFunctionsModule::<Self>::describe()],
+            mods: vec![
+                DefaultQueryFunctionsModule::<Self>::describe(),
+                CqueryFunctionsModule::describe(),
+            ],
         }
     }
 
@@ -126,6 +132,42 @@ impl<'c> CqueryEnvironment<'c> {
             .await
     }
 
+    /// Configuration rules are not configured with the target platform: their nodes are
+    /// looked up in the unbound configuration.
+    async fn get_configuration_nodes<'a>(
+        &self,
+        labels: impl Iterator<Item = &'a TargetLabel>,
+    ) -> anyhow::Result<TargetSet<ConfiguredTargetNode>> {
+        let labels: SmallSet<_> = labels.collect();
+        let nodes = futures::future::try_join_all(
+            labels
+                .iter()
+                .map(|label| self.get_node(&label.configure(ConfigurationData::unbound()))),
+        )
+        .await?;
+        Ok(nodes.into_iter().collect())
+    }
+
+    pub(crate) async fn config_deps(
+        &self,
+        targets: &TargetSet<ConfiguredTargetNode>,
+    ) -> anyhow::Result<TargetSet<ConfiguredTargetNode>> {
+        self.get_configuration_nodes(targets.iter().flat_map(|node| node.configuration_deps()))
+            .await
+    }
+
+    pub(crate) async fn select_keys(
+        &self,
+        targets: &TargetSet<ConfiguredTargetNode>,
+    ) -> anyhow::Result<TargetSet<ConfiguredTargetNode>> {
+        self.get_configuration_nodes(
+            targets
+                .iter()
+                .flat_map(|node| node.selected_configuration_settings()),
+        )
+        .await
+    }
+
     /// Deprecated `owner` function implementation.
     /// See [this post](https://fburl.com/0xv7u4bz) for details.
     async fn owner_deprecated(&self, path: &CellPath) -> anyhow::Result<Vec<ConfiguredTargetNode>> {
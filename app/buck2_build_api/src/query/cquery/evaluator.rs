@@ -18,7 +18,6 @@ use buck2_events::dispatch::console_message;
 use buck2_node::configured_universe::CqueryUniverse;
 use buck2_node::nodes::configured::ConfiguredTargetNode;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
-use buck2_query::query::syntax::simple::functions::DefaultQueryFunctionsModule;
 use dice::DiceComputations;
 use dupe::Dupe;
 use futures::stream::FuturesUnordered;
@@ -28,6 +27,7 @@ use gazebo::prelude::*;
 use crate::query::analysis::evaluator::eval_query;
 use crate::query::cquery::environment::CqueryEnvironment;
 use crate::query::cquery::environment::CqueryOwnerBehavior;
+use crate::query::cquery::functions::CqueryFunctions;
 use crate::query::dice::get_dice_query_delegate;
 use crate::query::dice::DiceQueryDelegate;
 use crate::query::uquery::environment::PreresolvedQueryLiterals;
@@ -36,7 +36,7 @@ use crate::query::uquery::environment::UqueryDelegate;
 
 pub struct CqueryEvaluator<'c> {
     dice_query_delegate: Arc<DiceQueryDelegate<'c>>,
-    functions: DefaultQueryFunctionsModule<CqueryEnvironment<'c>>,
+    functions: CqueryFunctions<'c>,
     owner_behavior: CqueryOwnerBehavior,
 }
 
@@ -112,7 +112,7 @@ pub async fn get_cquery_evaluator<'a, 'c: 'a>(
 ) -> anyhow::Result<CqueryEvaluator<'c>> {
     let dice_query_delegate =
         Arc::new(get_dice_query_delegate(ctx, working_dir, global_target_platform).await?);
-    let functions = DefaultQueryFunctionsModule::new();
+    let functions = CqueryFunctions::new();
     Ok(CqueryEvaluator {
         dice_query_delegate,
         functions,
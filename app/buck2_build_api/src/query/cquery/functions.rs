@@ -0,0 +1,126 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::fmt;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+use buck2_node::nodes::configured::ConfiguredTargetNode;
+use buck2_query::query::syntax::simple::eval::error::QueryError;
+use buck2_query::query::syntax::simple::eval::set::TargetSet;
+use buck2_query::query::syntax::simple::eval::values::QueryValue;
+use buck2_query::query::syntax::simple::functions::helpers::QueryBinaryOp;
+use buck2_query::query::syntax::simple::functions::helpers::QueryFunction;
+use buck2_query::query::syntax::simple::functions::DefaultQueryFunctionsModule;
+use buck2_query::query::syntax::simple::functions::QueryFunctions;
+use buck2_query::query_module;
+use buck2_query_parser::BinaryOp;
+
+use crate::query::cquery::environment::CqueryEnvironment;
+
+#[derive(Debug)]
+pub(crate) struct CqueryFunctionsModule<'c>(PhantomData<&'c ()>);
+
+/// Cquery functions
+#[query_module(CqueryEnvironment<'c>)]
+impl<'c> CqueryFunctionsModule<'c> {
+    /// Computes the configuration deps of the given targets.
+    ///
+    /// The `config_deps(targets)` function returns the configuration targets the given targets
+    /// depend on: the conditions of their `select()`s (`config_setting`, `constraint_value`...),
+    /// the constraints of their `target_compatible_with`, and the platforms of
+    /// `configured_alias()`. Configuration targets are returned in the unbound configuration.
+    async fn config_deps(
+        &self,
+        env: &CqueryEnvironment<'c>,
+        targets: TargetSet<ConfiguredTargetNode>,
+    ) -> Result<QueryValue<ConfiguredTargetNode>, QueryError> {
+        Ok(env.config_deps(&targets).await?.into())
+    }
+
+    /// Computes the select keys which are selected in the configuration of the given targets.
+    ///
+    /// The `select_keys(targets)` function returns the conditions of the `select()`s of the given
+    /// targets which were selected in their configuration. When several conditions of a
+    /// `select()` match, only the most specific one is selected. Together with `config_deps`,
+    /// this shows which configurability of a target is actually used:
+    /// `config_deps(//foo:bar) - select_keys(//foo:bar)` is the set of conditions that were never
+    /// selected (and the constraints of `target_compatible_with`).
+    async fn select_keys(
+        &self,
+        env: &CqueryEnvironment<'c>,
+        targets: TargetSet<ConfiguredTargetNode>,
+    ) -> Result<QueryValue<ConfiguredTargetNode>, QueryError> {
+        Ok(env.select_keys(&targets).await?.into())
+    }
+}
+
+impl<'c> CqueryFunctionsModule<'c> {
+    pub(crate) fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+/// The functions available in cquery: the common query functions and the cquery specific ones.
+pub struct CqueryFunctions<'c> {
+    defaults: DefaultQueryFunctionsModule<CqueryEnvironment<'c>>,
+    extra_functions: CqueryFunctionsModule<'c>,
+}
+
+impl<'c> CqueryFunctions<'c> {
+    pub(crate) fn new() -> Self {
+        Self {
+            defaults: DefaultQueryFunctionsModule::new(),
+            extra_functions: CqueryFunctionsModule::new(),
+        }
+    }
+}
+
+impl Debug for CqueryFunctions<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CqueryFunctions").finish_non_exhaustive()
+    }
+}
+
+impl<'c> QueryFunctions for CqueryFunctions<'c> {
+    type Env = CqueryEnvironment<'c>;
+
+    fn get(&self, name: &str) -> Option<&dyn QueryFunction<CqueryEnvironment<'c>>> {
+        if let Some(v) = self.extra_functions.get(name) {
+            Some(v)
+        } else {
+            self.defaults.get(name)
+        }
+    }
+
+    fn get_op(&self, op: BinaryOp) -> Option<&dyn QueryBinaryOp<CqueryEnvironment<'c>>> {
+        if let Some(v) = self.extra_functions.get_op(op) {
+            Some(v)
+        } else {
+            self.defaults.get_op(op)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_query::query::syntax::simple::functions::QueryFunctions;
+
+    use crate::query::cquery::functions::CqueryFunctions;
+
+    #[test]
+    fn test_functions() {
+        let functions = CqueryFunctions::new();
+        assert!(functions.get("config_deps").is_some());
+        assert!(functions.get("select_keys").is_some());
+        // The common query functions are still available.
+        assert!(functions.get("deps").is_some());
+        assert!(functions.get("no_such_function").is_none());
+    }
+}
@@ -15,6 +15,9 @@ use async_trait::async_trait;
 use buck2_common::dice::cells::HasCellResolver;
 use buck2_common::dice::data::HasIoProvider;
 use buck2_common::dice::file_ops::HasFileOps;
+use buck2_common::file_ops::FileOps;
+use buck2_common::file_ops::RawPathMetadata;
+use buck2_common::file_ops::RawSymlink;
 use buck2_common::package_boundary::HasPackageBoundaryExceptions;
 use buck2_common::package_boundary::PackageBoundaryExceptions;
 use buck2_common::package_listing::dice::HasPackageListingResolver;
@@ -42,6 +45,7 @@ use buck2_core::provider::label::ProvidersName;
 use buck2_core::soft_error;
 use buck2_core::target::label::ConfiguredTargetLabel;
 use buck2_core::target::label::TargetLabel;
+use buck2_interpreter::path::PackageFilePath;
 use buck2_interpreter_for_build::interpreter::calculation::InterpreterCalculation;
 use buck2_node::nodes::configured::ConfiguredTargetNode;
 use buck2_node::nodes::eval_result::EvaluationResult;
@@ -124,23 +128,62 @@ impl LiteralParser {
     }
 
     fn parse_file_literal(&self, literal: &str) -> anyhow::Result<CellPath> {
-        match maybe_split_cell_alias_and_relative_path(literal)? {
+        let project_path = match maybe_split_cell_alias_and_relative_path(literal)? {
             Some((alias, path)) => {
                 let cell_name = self.cell_alias_resolver.resolve(alias.as_str())?;
 
                 let cell_relative_path = CellRelativePath::new(path);
 
-                Ok(CellPath::new(cell_name, cell_relative_path.to_buf()))
+                self.cell_resolver
+                    .resolve_path(CellPath::new(cell_name, cell_relative_path.to_buf()).as_ref())?
             }
             None => {
                 let path = Path::new(literal);
                 // Note if the path is absolute, this `join` is a no-op.
                 let path_abs = self.working_dir_abs.as_abs_path().join(path);
-                let project_path = self.project_root.relativize_any(path_abs)?;
-                self.cell_resolver.get_cell_path(&project_path)
+                self.project_root.relativize_any(path_abs)?
             }
+        };
+        self.cell_resolver.get_cell_path(&project_path)
+    }
+}
+
+/// Symlinks followed when resolving the directory of a file literal, to stop on symlink cycles.
+const MAX_SYMLINKED_DIRS: usize = 40;
+
+/// The same file can be referred to through symlinked directories, or through a cell other than
+/// the one containing it (e.g. `root//sub/a.txt` when `sub` is a cell). File literals are resolved
+/// to a single path so that file sets do not contain the same file twice, and `owner()` finds the
+/// owners of the file. The file itself is not resolved, since it may be a symlink owned by a
+/// target.
+///
+/// The symlinks are read through DICE, so that the resolution is invalidated when they change.
+async fn resolve_symlinked_dirs(
+    file_ops: &dyn FileOps,
+    path: CellPath,
+) -> anyhow::Result<CellPath> {
+    let (mut dir, file_name) = match (path.parent(), path.path().file_name()) {
+        (Some(dir), Some(file_name)) => (dir.to_owned(), file_name),
+        _ => return Ok(path),
+    };
+    for _ in 0..MAX_SYMLINKED_DIRS {
+        if dir.path().is_empty() {
+            return Ok(dir.join(file_name));
+        }
+        match file_ops.read_path_metadata_if_exists(dir.as_ref()).await? {
+            Some(RawPathMetadata::Symlink {
+                to: RawSymlink::Relative(target),
+                ..
+            }) => dir = (*target).clone(),
+            // Directories symlinked to outside of the project are kept as they are.
+            Some(RawPathMetadata::Symlink {
+                to: RawSymlink::External(_),
+                ..
+            }) => return Ok(path),
+            Some(_) | None => return Ok(dir.join(file_name)),
         }
     }
+    Ok(path)
 }
 
 /// A Uquery delegate that resolves TargetNodes with the provided
@@ -214,6 +257,22 @@ impl<'c> UqueryDelegate for DiceQueryDelegate<'c> {
         Ok(module.imports().cloned().collect())
     }
 
+    async fn eval_package_files(
+        &self,
+        package: PackageLabel,
+    ) -> anyhow::Result<Vec<(CellPath, Vec<ImportPath>)>> {
+        let mut package_files = Vec::new();
+        let mut path = Some(PackageFilePath::for_dir(package.as_cell_path()));
+        while let Some(package_file) = path {
+            if let Some(imports) = self.ctx.get_package_file_imports(&package_file).await? {
+                package_files.push((package_file.path().clone(), imports));
+            }
+            path = package_file.parent_package_file();
+        }
+        package_files.reverse();
+        Ok(package_files)
+    }
+
     // get the list of potential buildfile names for each cell
     fn get_buildfile_names_by_cell(&self) -> anyhow::Result<HashMap<CellName, &[FileNameBuf]>> {
         let resolver = &self.cell_resolver;
@@ -259,6 +318,7 @@ impl<'c> UqueryDelegate for DiceQueryDelegate<'c> {
 
     async fn eval_file_literal(&self, literal: &str) -> anyhow::Result<FileSet> {
         let cell_path = self.literal_parser.parse_file_literal(literal)?;
+        let cell_path = resolve_symlinked_dirs(&self.ctx.file_ops(), cell_path).await?;
         Ok(FileSet::new(indexset![FileNode(cell_path)]))
     }
 }
@@ -365,3 +425,206 @@ pub async fn get_dice_query_delegate<'a, 'c: 'a>(
         target_alias_resolver,
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    use allocative::Allocative;
+    use async_trait::async_trait;
+    use buck2_common::external_symlink::ExternalSymlink;
+    use buck2_common::file_ops::FileOps;
+    use buck2_common::file_ops::RawPathMetadata;
+    use buck2_common::file_ops::RawSymlink;
+    use buck2_common::file_ops::ReadDirOutput;
+    use buck2_common::legacy_configs::LegacyBuckConfig;
+    use buck2_core::cells::alias::NonEmptyCellAlias;
+    use buck2_core::cells::cell_path::CellPath;
+    use buck2_core::cells::cell_path::CellPathRef;
+    use buck2_core::cells::cell_root_path::CellRootPathBuf;
+    use buck2_core::cells::name::CellName;
+    use buck2_core::cells::CellResolver;
+    use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+    use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+    use buck2_core::fs::project::ProjectRoot;
+    use dupe::Dupe;
+    use gazebo::cmp::PartialEqAny;
+
+    use crate::query::dice::resolve_symlinked_dirs;
+    use crate::query::dice::LiteralParser;
+
+    fn literal_parser() -> LiteralParser {
+        let root = CellName::testing_new("root");
+        let sub = CellName::testing_new("sub");
+        let cell_resolver = CellResolver::testing_with_names_and_paths_with_alias(&[
+            (
+                root,
+                CellRootPathBuf::testing_new(""),
+                HashMap::from([(NonEmptyCellAlias::testing_new("sub"), sub)]),
+            ),
+            (sub, CellRootPathBuf::testing_new("sub"), HashMap::new()),
+        ]);
+        let cell_alias_resolver = cell_resolver
+            .get(root)
+            .unwrap()
+            .cell_alias_resolver()
+            .dupe();
+        let project_root = ProjectRoot::new_unchecked(
+            AbsNormPathBuf::try_from(std::env::current_dir().unwrap()).unwrap(),
+        );
+        LiteralParser {
+            working_dir: CellPath::testing_new("root//"),
+            working_dir_abs: project_root.root().to_buf(),
+            project_root,
+            cell_resolver,
+            cell_alias_resolver,
+            target_alias_resolver: LegacyBuckConfig::empty().target_alias_resolver(),
+        }
+    }
+
+    #[test]
+    fn test_parse_file_literal_resolves_to_owning_cell() -> anyhow::Result<()> {
+        let parser = literal_parser();
+        let expected = CellPath::testing_new("sub//a.txt");
+        let absolute = parser
+            .project_root
+            .root()
+            .join(ForwardRelativePath::new("sub/a.txt")?)
+            .to_string();
+
+        for literal in ["sub//a.txt", "root//sub/a.txt", "sub/a.txt", &absolute] {
+            assert_eq!(expected, parser.parse_file_literal(literal)?, "{}", literal);
+        }
+        assert_eq!(
+            CellPath::testing_new("root//a.txt"),
+            parser.parse_file_literal("a.txt")?
+        );
+        Ok(())
+    }
+
+    #[derive(Allocative)]
+    struct SymlinkFileOps {
+        #[allocative(skip)]
+        entries: HashMap<CellPath, RawPathMetadata>,
+    }
+
+    impl SymlinkFileOps {
+        fn new(symlinks: &[(&str, &str)]) -> Self {
+            let entries = symlinks
+                .iter()
+                .map(|(at, to)| {
+                    let at = CellPath::testing_new(at);
+                    let to = RawSymlink::Relative(Arc::new(CellPath::testing_new(to)));
+                    (
+                        at.clone(),
+                        RawPathMetadata::Symlink {
+                            at: Arc::new(at),
+                            to,
+                        },
+                    )
+                })
+                .collect();
+            Self { entries }
+        }
+    }
+
+    #[async_trait]
+    impl FileOps for SymlinkFileOps {
+        async fn read_file_if_exists(
+            &self,
+            _path: CellPathRef<'async_trait>,
+        ) -> anyhow::Result<Option<String>> {
+            Ok(None)
+        }
+
+        async fn read_dir(&self, path: CellPathRef<'async_trait>) -> anyhow::Result<ReadDirOutput> {
+            Err(anyhow::anyhow!("unexpected read_dir of `{}`", path))
+        }
+
+        async fn is_ignored(&self, _path: CellPathRef<'async_trait>) -> anyhow::Result<bool> {
+            Ok(false)
+        }
+
+        async fn read_path_metadata_if_exists(
+            &self,
+            path: CellPathRef<'async_trait>,
+        ) -> anyhow::Result<Option<RawPathMetadata>> {
+            Ok(Some(
+                self.entries
+                    .get(&path.to_owned())
+                    .cloned()
+                    .unwrap_or(RawPathMetadata::Directory),
+            ))
+        }
+
+        fn eq_token(&self) -> PartialEqAny {
+            PartialEqAny::always_false()
+        }
+    }
+
+    async fn resolve(file_ops: &SymlinkFileOps, path: &str) -> anyhow::Result<String> {
+        Ok(
+            resolve_symlinked_dirs(file_ops, CellPath::testing_new(path))
+                .await?
+                .to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_resolve_symlinked_dirs() -> anyhow::Result<()> {
+        let file_ops = SymlinkFileOps::new(&[
+            ("root//link", "root//dir"),
+            ("root//link_to_link", "root//link"),
+            ("root//to_sub", "sub//dir"),
+            ("root//dir/file_link", "root//dir/a.txt"),
+        ]);
+
+        assert_eq!(
+            "root//dir/a.txt",
+            resolve(&file_ops, "root//dir/a.txt").await?
+        );
+        assert_eq!(
+            "root//dir/a.txt",
+            resolve(&file_ops, "root//link/a.txt").await?
+        );
+        assert_eq!(
+            "root//dir/a.txt",
+            resolve(&file_ops, "root//link_to_link/a.txt").await?
+        );
+        assert_eq!(
+            "sub//dir/a.txt",
+            resolve(&file_ops, "root//to_sub/a.txt").await?
+        );
+        // The file itself is not resolved, it may be a symlink owned by a target.
+        assert_eq!(
+            "root//dir/file_link",
+            resolve(&file_ops, "root//link/file_link").await?
+        );
+        assert_eq!("root//a.txt", resolve(&file_ops, "root//a.txt").await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resolve_symlinked_dirs_stops_on_cycles_and_external() -> anyhow::Result<()> {
+        let mut file_ops = SymlinkFileOps::new(&[("root//a", "root//b"), ("root//b", "root//a")]);
+        file_ops.entries.insert(
+            CellPath::testing_new("root//external"),
+            RawPathMetadata::Symlink {
+                at: Arc::new(CellPath::testing_new("root//external")),
+                to: RawSymlink::External(Arc::new(ExternalSymlink::new(
+                    PathBuf::from(if cfg!(windows) { "C:\\ext" } else { "/ext" }),
+                    None,
+                )?)),
+            },
+        );
+
+        assert_eq!("root//a/x.txt", resolve(&file_ops, "root//a/x.txt").await?);
+        assert_eq!(
+            "root//external/x.txt",
+            resolve(&file_ops, "root//external/x.txt").await?
+        );
+        Ok(())
+    }
+}
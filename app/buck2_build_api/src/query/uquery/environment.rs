@@ -24,6 +24,7 @@ use buck2_core::fs::paths::file_name::FileNameBuf;
 use buck2_core::package::PackageLabel;
 use buck2_core::pattern::pattern_type::TargetPatternExtra;
 use buck2_core::target::label::TargetLabel;
+use buck2_interpreter::path::PackageFilePath;
 use buck2_node::nodes::eval_result::EvaluationResult;
 use buck2_node::nodes::unconfigured::TargetNode;
 use buck2_query::query::compatibility::MaybeCompatible;
@@ -86,6 +87,13 @@ pub trait UqueryDelegate: Send + Sync {
     /// Get the imports from a LoadedModule corresponding to some path.
     async fn eval_module_imports(&self, path: &ImportPath) -> anyhow::Result<Vec<ImportPath>>;
 
+    /// Get the `PACKAGE` files which apply to a package, from the cell root down to the package
+    /// directory, with the modules each of them loads.
+    async fn eval_package_files(
+        &self,
+        package: PackageLabel,
+    ) -> anyhow::Result<Vec<(CellPath, Vec<ImportPath>)>>;
+
     fn get_buildfile_names_by_cell(&self) -> anyhow::Result<HashMap<CellName, &[FileNameBuf]>>;
 
     /// Resolves a target pattern.
@@ -303,14 +311,23 @@ pub(crate) async fn allbuildfiles<'c, T: QueryTarget>(
 
     let mut top_level_imports = Vec::<ImportPath>::new();
 
+    // Many targets share a package, which only needs to be looked at once.
+    let mut packages = IndexSet::<PackageLabel>::new();
     for target in universe.iter() {
         paths.insert(FileNode(target.dupe().buildfile_path().path()));
+        packages.insert(target.buildfile_path().package().dupe());
+    }
 
-        let eval_result = delegate
-            .eval_build_file(target.buildfile_path().package())
-            .await?; // TODO: no longer use eval_build_file, just parse imports directly (will solve async issue too)
+    for package in packages {
+        let eval_result = delegate.eval_build_file(package.dupe()).await?; // TODO: no longer use eval_build_file, just parse imports directly (will solve async issue too)
 
         top_level_imports.extend(eval_result.imports().iter().cloned());
+
+        // The evaluation of the build file also depends on the `PACKAGE` files above it.
+        for (package_file, imports) in delegate.eval_package_files(package).await? {
+            paths.insert(FileNode(package_file));
+            top_level_imports.extend(imports);
+        }
     }
 
     let loads = get_transitive_loads(top_level_imports, delegate).await?;
@@ -330,11 +347,12 @@ pub(crate) async fn rbuildfiles<'c>(
 ) -> anyhow::Result<FileSet> {
     let universe_paths: Vec<ArcCellPath> =
         universe.iter().map(|file| Arc::new(file.clone())).collect();
-    // step 1: split the build files and bzl files
+    // step 1: split the build files, `PACKAGE` files and bzl files
     let (buildfiles, bzlfiles) = split_universe_files(&universe_paths, delegate)?;
 
-    // step 2: get all top level imports accordingly
-    let top_level_import_by_build_file =
+    // step 2: get all top level imports accordingly, and the `PACKAGE` files each build file
+    // depends on
+    let (top_level_import_by_build_file, package_files_by_build_file) =
         top_level_imports_by_build_file(&buildfiles, &bzlfiles, delegate).await?;
 
     // step 3: get the first order imports for every loaded file, to lookup during traversal
@@ -448,7 +466,14 @@ pub(crate) async fn rbuildfiles<'c>(
     // finally, since we didn't check the top level imports of buildfiles,
     // we do this right now
     for file in buildfiles {
-        if argset.iter().contains(&*file) {
+        let package_files = package_files_by_build_file
+            .get(&file)
+            .expect("should have stored a universe file's package files");
+        if argset.iter().contains(&*file)
+            || package_files
+                .iter()
+                .any(|package_file| argset.iter().contains(package_file))
+        {
             output_files.insert(FileNode((*file).clone()));
         } else {
             let imports = top_level_import_by_build_file
@@ -483,14 +508,19 @@ fn split_universe_files<'c>(
                 anyhow::anyhow!(RBuildFilesError::CellMissingBuildFileNames(file.cell()))
             })?;
 
-        if let Some(name) = file.path().file_name() {
-            if buildfile_names_for_file
-                .iter()
-                .map(<FileNameBuf as AsRef<FileName>>::as_ref)
-                .contains(&name)
-            {
-                buildfiles.push(file.dupe());
+        let is_buildfile = match file.path().file_name() {
+            Some(name) => {
+                name == PackageFilePath::PACKAGE_FILE_NAME
+                    || buildfile_names_for_file
+                        .iter()
+                        .map(<FileNameBuf as AsRef<FileName>>::as_ref)
+                        .contains(&name)
             }
+            None => false,
+        };
+        if is_buildfile {
+            // `PACKAGE` files are evaluated like build files, so handle them the same way.
+            buildfiles.push(file.dupe());
         } else {
             // TODO: right now we assume non-buildfiles are bzl's - we might want to handle error cases later.
             bzlfiles.push(file.dupe());
@@ -499,12 +529,18 @@ fn split_universe_files<'c>(
     Ok((buildfiles, bzlfiles))
 }
 
+/// For each universe file, the modules it loads, and for build files and `PACKAGE` files, the
+/// `PACKAGE` files their evaluation depends on.
 async fn top_level_imports_by_build_file<'c>(
     buildfiles: &[ArcCellPath],
     bzlfiles: &[ArcCellPath],
     delegate: &'c dyn UqueryDelegate,
-) -> anyhow::Result<HashMap<ArcCellPath, Vec<ImportPath>>> {
+) -> anyhow::Result<(
+    HashMap<ArcCellPath, Vec<ImportPath>>,
+    HashMap<ArcCellPath, Vec<CellPath>>,
+)> {
     let mut top_level_import_by_build_file = HashMap::<ArcCellPath, Vec<ImportPath>>::new();
+    let mut package_files_by_build_file = HashMap::<ArcCellPath, Vec<CellPath>>::new();
 
     for file in bzlfiles {
         let imports = vec![ImportPath::new(
@@ -517,27 +553,38 @@ async fn top_level_imports_by_build_file<'c>(
     let mut buildfile_futs: FuturesUnordered<_> = buildfiles
         .iter()
         .map(|file| async move {
-            if let Some(parent) = file.parent() {
-                (
-                    file.dupe(),
-                    delegate
-                        .eval_build_file(PackageLabel::from_cell_path(parent))
-                        .await,
-                )
-            } else {
-                (
-                    file.dupe(),
-                    Err(RBuildFilesError::ParentDoesNotExist(file.dupe()).into()),
-                )
-            }
+            let result = async {
+                let parent = file
+                    .parent()
+                    .ok_or_else(|| RBuildFilesError::ParentDoesNotExist(file.dupe()))?;
+                let package = PackageLabel::from_cell_path(parent);
+                let package_files = delegate.eval_package_files(package.dupe());
+                if file.path().file_name() == Some(PackageFilePath::PACKAGE_FILE_NAME) {
+                    // The `PACKAGE` file itself is the last of the package files.
+                    anyhow::Ok((Vec::new(), package_files.await?))
+                } else {
+                    let (eval_result, package_files) =
+                        futures::future::try_join(delegate.eval_build_file(package), package_files)
+                            .await?;
+                    anyhow::Ok((eval_result.imports().to_owned(), package_files))
+                }
+            };
+            (file.dupe(), result.await)
         })
         .collect();
 
-    while let Some((file, eval_result)) = tokio::task::unconstrained(buildfile_futs.next()).await {
-        top_level_import_by_build_file.insert(file.dupe(), eval_result?.imports().to_owned());
+    while let Some((file, result)) = tokio::task::unconstrained(buildfile_futs.next()).await {
+        let (mut imports, package_files) = result?;
+        let mut package_file_paths = Vec::with_capacity(package_files.len());
+        for (package_file, package_file_imports) in package_files {
+            imports.extend(package_file_imports);
+            package_file_paths.push(package_file);
+        }
+        top_level_import_by_build_file.insert(file.dupe(), imports);
+        package_files_by_build_file.insert(file.dupe(), package_file_paths);
     }
 
-    Ok(top_level_import_by_build_file)
+    Ok((top_level_import_by_build_file, package_files_by_build_file))
 }
 
 // TODO: no need to get all the first order imports prior to the traversal - we can do this
@@ -641,3 +688,328 @@ pub(crate) async fn get_transitive_loads<'c>(
 
     Ok(traversal_delegate.imports)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use buck2_common::pattern::resolve::ResolvedPattern;
+    use buck2_core::build_file_path::BuildFilePath;
+    use buck2_core::bzl::ImportPath;
+    use buck2_core::cells::cell_path::CellPath;
+    use buck2_core::cells::name::CellName;
+    use buck2_core::fs::paths::file_name::FileNameBuf;
+    use buck2_core::package::PackageLabel;
+    use buck2_core::pattern::pattern_type::TargetPatternExtra;
+    use buck2_core::provider::label::ProvidersLabel;
+    use buck2_core::provider::label::ProvidersName;
+    use buck2_core::target::label::TargetLabel;
+    use buck2_node::attrs::attr::Attribute;
+    use buck2_node::attrs::attr_type::list::ListLiteral;
+    use buck2_node::attrs::attr_type::string::StringLiteral;
+    use buck2_node::attrs::attr_type::AttrType;
+    use buck2_node::attrs::coerced_attr::CoercedAttr;
+    use buck2_node::attrs::coerced_attr::CoercedSelector;
+    use buck2_node::nodes::eval_result::EvaluationResult;
+    use buck2_node::nodes::targets_map::TargetsMap;
+    use buck2_node::nodes::unconfigured::testing::TargetNodeExt;
+    use buck2_node::nodes::unconfigured::TargetNode;
+    use buck2_node::provider_id_set::ProviderIdSet;
+    use buck2_node::rule_type::RuleType;
+    use buck2_node::rule_type::StarlarkRuleType;
+    use buck2_query::query::syntax::simple::eval::file_set::FileNode;
+    use buck2_query::query::syntax::simple::eval::file_set::FileSet;
+    use buck2_query::query::syntax::simple::eval::set::SelectBranches;
+    use buck2_query::query::syntax::simple::eval::set::TargetSet;
+    use buck2_query::query::syntax::simple::functions::DefaultQueryFunctions;
+    use buck2_util::arc_str::ArcSlice;
+
+    use crate::query::uquery::environment::PreresolvedQueryLiterals;
+    use crate::query::uquery::environment::UqueryDelegate;
+    use crate::query::uquery::environment::UqueryEnvironment;
+
+    /// A delegate over an in-memory repo:
+    /// ```text
+    /// root//pkg/BUCK:       loads `root//defs:rules.bzl`, defines `a -> b -> c`
+    /// root//other/BUCK:     loads nothing, defines `d`
+    /// root//defs/rules.bzl: loads `root//defs:utils.bzl`
+    /// ```
+    struct TestDelegate {
+        packages: HashMap<PackageLabel, Arc<EvaluationResult>>,
+        module_imports: HashMap<ImportPath, Vec<ImportPath>>,
+        buildfile_names: Vec<FileNameBuf>,
+    }
+
+    #[async_trait]
+    impl UqueryDelegate for TestDelegate {
+        async fn eval_build_file(
+            &self,
+            package: PackageLabel,
+        ) -> anyhow::Result<Arc<EvaluationResult>> {
+            self.packages
+                .get(&package)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("unknown package `{}`", package))
+        }
+
+        async fn eval_module_imports(&self, path: &ImportPath) -> anyhow::Result<Vec<ImportPath>> {
+            Ok(self.module_imports.get(path).cloned().unwrap_or_default())
+        }
+
+        async fn eval_package_files(
+            &self,
+            _package: PackageLabel,
+        ) -> anyhow::Result<Vec<(CellPath, Vec<ImportPath>)>> {
+            Ok(Vec::new())
+        }
+
+        fn get_buildfile_names_by_cell(&self) -> anyhow::Result<HashMap<CellName, &[FileNameBuf]>> {
+            Ok(HashMap::from([(
+                CellName::testing_new("root"),
+                self.buildfile_names.as_slice(),
+            )]))
+        }
+
+        async fn resolve_target_patterns(
+            &self,
+            _pattern: &[&str],
+        ) -> anyhow::Result<ResolvedPattern<TargetPatternExtra>> {
+            unimplemented!()
+        }
+
+        async fn eval_file_literal(&self, _literal: &str) -> anyhow::Result<FileSet> {
+            unimplemented!()
+        }
+
+        async fn get_enclosing_packages(
+            &self,
+            _path: &CellPath,
+        ) -> anyhow::Result<Vec<PackageLabel>> {
+            unimplemented!()
+        }
+    }
+
+    fn string(s: &str) -> CoercedAttr {
+        CoercedAttr::String(StringLiteral(s.into()))
+    }
+
+    fn node(label: &str, deps: &[&str], flavor: CoercedAttr) -> TargetNode {
+        let rule_type = RuleType::Starlark(Arc::new(StarlarkRuleType {
+            import_path: ImportPath::testing_new("root//defs:rules.bzl"),
+            name: "some_rule".to_owned(),
+        }));
+        TargetNode::testing_new(
+            TargetLabel::testing_parse(label),
+            rule_type,
+            vec![
+                (
+                    "deps",
+                    Attribute::new(
+                        None,
+                        "",
+                        AttrType::list(AttrType::dep(ProviderIdSet::EMPTY)),
+                    ),
+                    CoercedAttr::List(ListLiteral(ArcSlice::from_iter(deps.iter().map(|d| {
+                        CoercedAttr::Dep(ProvidersLabel::new(
+                            TargetLabel::testing_parse(d),
+                            ProvidersName::Default,
+                        ))
+                    })))),
+                ),
+                (
+                    "flavor",
+                    Attribute::new(None, "", AttrType::string()),
+                    flavor,
+                ),
+            ],
+        )
+    }
+
+    fn select(linux: &str, default: &str) -> anyhow::Result<CoercedAttr> {
+        Ok(CoercedAttr::Selector(Box::new(CoercedSelector::new(
+            ArcSlice::from_iter([(TargetLabel::testing_parse("root//:linux"), string(linux))]),
+            Some(string(default)),
+        )?)))
+    }
+
+    fn package(
+        path: &str,
+        imports: Vec<ImportPath>,
+        nodes: Vec<TargetNode>,
+    ) -> Arc<EvaluationResult> {
+        Arc::new(EvaluationResult::new(
+            Arc::new(BuildFilePath::new(
+                PackageLabel::testing_parse(path),
+                FileNameBuf::unchecked_new("BUCK"),
+            )),
+            imports,
+            TargetsMap::from_iter(nodes),
+        ))
+    }
+
+    fn delegate() -> anyhow::Result<TestDelegate> {
+        let rules = ImportPath::testing_new("root//defs:rules.bzl");
+        let utils = ImportPath::testing_new("root//defs:utils.bzl");
+        Ok(TestDelegate {
+            packages: HashMap::from([
+                (
+                    PackageLabel::testing_parse("root//pkg"),
+                    package(
+                        "root//pkg",
+                        vec![rules.clone()],
+                        vec![
+                            node("root//pkg:a", &["root//pkg:b"], select("libfoo", "libbar")?),
+                            node("root//pkg:b", &["root//pkg:c"], select("libfoo", "bar")?),
+                            node("root//pkg:c", &[], string("baz")),
+                        ],
+                    ),
+                ),
+                (
+                    PackageLabel::testing_parse("root//other"),
+                    package(
+                        "root//other",
+                        Vec::new(),
+                        vec![node("root//other:d", &[], string("libqux"))],
+                    ),
+                ),
+            ]),
+            module_imports: HashMap::from([(rules, vec![utils])]),
+            buildfile_names: vec![FileNameBuf::unchecked_new("BUCK")],
+        })
+    }
+
+    fn environment() -> anyhow::Result<UqueryEnvironment<'static>> {
+        Ok(UqueryEnvironment::new(
+            Arc::new(delegate()?),
+            Arc::new(PreresolvedQueryLiterals::new(HashMap::new())),
+        ))
+    }
+
+    async fn targets(
+        env: &UqueryEnvironment<'_>,
+        labels: &[&str],
+    ) -> anyhow::Result<TargetSet<TargetNode>> {
+        let mut targets = TargetSet::new();
+        for label in labels {
+            targets.insert(env.get_node(&TargetLabel::testing_parse(label)).await?);
+        }
+        Ok(targets)
+    }
+
+    fn names(targets: &TargetSet<TargetNode>) -> Vec<String> {
+        let mut names: Vec<String> = targets.iter_names().map(|t| t.to_string()).collect();
+        names.sort();
+        names
+    }
+
+    fn files(files: &FileSet) -> Vec<String> {
+        let mut files: Vec<String> = files.iter().map(|f| f.to_string()).collect();
+        files.sort();
+        files
+    }
+
+    // The functions below are the ones `ctx.uquery()` exposes to BXL, called the way it calls
+    // them.
+
+    #[tokio::test]
+    async fn test_nattrfilter() -> anyhow::Result<()> {
+        let env = environment()?;
+        let functions = DefaultQueryFunctions::<UqueryEnvironment>::new();
+        let universe = targets(&env, &["root//pkg:a", "root//pkg:c", "root//other:d"]).await?;
+
+        assert_eq!(
+            vec!["root//other:d", "root//pkg:a"],
+            names(&functions.nattrfilter("flavor", "baz", &universe)?)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_attrregexfilter_branches() -> anyhow::Result<()> {
+        let env = environment()?;
+        let functions = DefaultQueryFunctions::<UqueryEnvironment>::new();
+        let universe = targets(&env, &["root//pkg:a", "root//pkg:b", "root//pkg:c"]).await?;
+
+        // BXL passes `branches` as a string, parsed the same way.
+        let any: SelectBranches = "any".parse()?;
+        let all: SelectBranches = "all".parse()?;
+        assert!("some".parse::<SelectBranches>().is_err());
+
+        assert_eq!(
+            vec!["root//pkg:a", "root//pkg:b"],
+            names(&functions.attrregexfilter("flavor", "^lib", &universe, any)?)
+        );
+        assert_eq!(
+            vec!["root//pkg:a"],
+            names(&functions.attrregexfilter("flavor", "^lib", &universe, all)?)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rdeps_depth() -> anyhow::Result<()> {
+        let env = environment()?;
+        let functions = DefaultQueryFunctions::<UqueryEnvironment>::new();
+        let universe = targets(&env, &["root//pkg:a", "root//other:d"]).await?;
+        let from = targets(&env, &["root//pkg:c"]).await?;
+
+        assert_eq!(
+            vec!["root//pkg:a", "root//pkg:b", "root//pkg:c"],
+            names(&functions.rdeps(&env, &universe, &from, None).await?)
+        );
+        assert_eq!(
+            vec!["root//pkg:b", "root//pkg:c"],
+            names(&functions.rdeps(&env, &universe, &from, Some(1)).await?)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_allbuildfiles() -> anyhow::Result<()> {
+        let env = environment()?;
+        let functions = DefaultQueryFunctions::<UqueryEnvironment>::new();
+        let universe = targets(&env, &["root//pkg:a", "root//pkg:b"]).await?;
+
+        assert_eq!(
+            vec![
+                "root//defs/rules.bzl",
+                "root//defs/utils.bzl",
+                "root//pkg/BUCK"
+            ],
+            files(&functions.allbuildfiles(&env, &universe).await?)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rbuildfiles() -> anyhow::Result<()> {
+        let env = environment()?;
+        let functions = DefaultQueryFunctions::<UqueryEnvironment>::new();
+        let universe = FileSet::new(
+            ["root//pkg/BUCK", "root//other/BUCK"]
+                .iter()
+                .map(|p| FileNode(CellPath::testing_new(p)))
+                .collect(),
+        );
+        let argset = FileSet::new(
+            [FileNode(CellPath::testing_new("root//defs/utils.bzl"))]
+                .into_iter()
+                .collect(),
+        );
+
+        let result = files(&functions.rbuildfiles(&env, &universe, &argset).await?);
+        assert!(
+            result.contains(&"root//pkg/BUCK".to_owned()),
+            "{:?}",
+            result
+        );
+        assert!(
+            !result.contains(&"root//other/BUCK".to_owned()),
+            "{:?}",
+            result
+        );
+        Ok(())
+    }
+}
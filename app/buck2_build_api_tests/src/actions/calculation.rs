@@ -186,6 +186,7 @@ async fn make_default_dice_state(
             Ok(CommandExecutorResponse {
                 executor,
                 platform: Default::default(),
+                platform_by_category: Default::default(),
             })
         }
     }
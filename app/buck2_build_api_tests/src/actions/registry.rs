@@ -44,20 +44,20 @@ fn declaring_artifacts() -> anyhow::Result<()> {
     let mut actions = ActionsRegistry::new(base.dupe(), ExecutionPlatformResolution::unspecified());
     let out1 = ForwardRelativePathBuf::unchecked_new("bar.out".into());
     let buckout1 = BuckOutPath::new(base.dupe().into_dyn(), out1.clone());
-    let declared1 = actions.declare_artifact(None, out1.clone(), OutputType::File, None)?;
+    let declared1 = actions.declare_artifact(None, None, out1.clone(), OutputType::File, None)?;
     declared1
         .get_path()
         .with_full_path(|p| assert_eq!(p, buckout1.path()));
 
     let out2 = ForwardRelativePathBuf::unchecked_new("bar2.out".into());
     let buckout2 = BuckOutPath::new(base.into_dyn(), out2.clone());
-    let declared2 = actions.declare_artifact(None, out2, OutputType::File, None)?;
+    let declared2 = actions.declare_artifact(None, None, out2, OutputType::File, None)?;
     declared2
         .get_path()
         .with_full_path(|p| assert_eq!(p, buckout2.path()));
 
     if actions
-        .declare_artifact(None, out1, OutputType::File, None)
+        .declare_artifact(None, None, out1, OutputType::File, None)
         .is_ok()
     {
         panic!("should error due to duplicate artifact")
@@ -143,7 +143,7 @@ fn register_actions() -> anyhow::Result<()> {
     let mut deferreds = DeferredRegistry::new(BaseKey::Base(base.dupe()));
     let mut actions = ActionsRegistry::new(base.dupe(), ExecutionPlatformResolution::unspecified());
     let out = ForwardRelativePathBuf::unchecked_new("bar.out".into());
-    let declared = actions.declare_artifact(None, out, OutputType::File, None)?;
+    let declared = actions.declare_artifact(None, None, out, OutputType::File, None)?;
 
     let inputs = indexset![ArtifactGroup::Artifact(
         BuildArtifact::testing_new(
@@ -195,7 +195,7 @@ fn finalizing_actions() -> anyhow::Result<()> {
         ),
     );
     let out = ForwardRelativePathBuf::unchecked_new("bar.out".into());
-    let declared = actions.declare_artifact(None, out, OutputType::File, None)?;
+    let declared = actions.declare_artifact(None, None, out, OutputType::File, None)?;
 
     let inputs = indexset![ArtifactGroup::Artifact(
         BuildArtifact::testing_new(
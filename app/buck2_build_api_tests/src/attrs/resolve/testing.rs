@@ -222,6 +222,13 @@ pub(crate) fn resolution_ctx_with_providers<'v>(
                 .ok_or_else(|| anyhow::anyhow!("missing dep"))
         }
 
+        fn all_deps(&self) -> Vec<(ConfiguredProvidersLabel, FrozenProviderCollectionValue)> {
+            self.deps
+                .iter()
+                .map(|(label, providers)| (label.clone(), providers.dupe()))
+                .collect()
+        }
+
         fn resolve_unkeyed_placeholder(
             &self,
             name: &str,
@@ -540,6 +540,46 @@ fn test_dep_requires_providers() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_dep_requires_providers_suggestions() -> anyhow::Result<()> {
+    let env = Module::new();
+    let (resolution_ctx, provider_ids) = resolution_ctx_with_providers(&env);
+
+    let heap = Heap::new();
+    let foo = heap.alloc("//sub/dir:foo");
+
+    let attr = AttrType::dep(provider_ids);
+    let coerced = attr.coerce(AttrIsConfigurable::Yes, &coercion_ctx(), foo)?;
+    let configured = coerced.configure(&attr, &configuration_ctx())?;
+
+    let err = configured
+        .resolve_single(PackageLabel::testing(), &resolution_ctx)
+        .expect_err("Should have failed")
+        .to_string();
+    // Every missing provider is reported, not just the first one.
+    assert!(err.contains("`FooInfo`"), "Got error {}", err);
+    assert!(err.contains("`BarInfo`"), "Got error {}", err);
+    assert!(
+        err.contains("Found these providers: DefaultInfo"),
+        "Got error {}",
+        err
+    );
+    // Only the sub-target that has both providers is suggested.
+    assert!(err.contains("Did you mean"), "Got error {}", err);
+    assert!(
+        err.contains("root//sub/dir:foo[foo_and_bar]"),
+        "Got error {}",
+        err
+    );
+    assert!(
+        !err.contains("root//sub/dir:foo[foo_only]"),
+        "Got error {}",
+        err
+    );
+
+    Ok(())
+}
+
 #[test]
 fn test_source_missing() {
     let heap = Heap::new();
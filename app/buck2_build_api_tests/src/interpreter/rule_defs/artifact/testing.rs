@@ -116,6 +116,7 @@ pub(crate) fn artifactory(builder: &mut GlobalsBuilder) {
             ExecutionPlatformResolution::unspecified(),
         );
         let artifact = registry.declare_artifact(
+            None,
             None,
             ForwardRelativePathBuf::try_from(path.to_owned()).unwrap(),
             OutputType::File,
@@ -142,6 +143,7 @@ pub(crate) fn artifactory(builder: &mut GlobalsBuilder) {
             ExecutionPlatformResolution::unspecified(),
         );
         let artifact = registry.declare_artifact(
+            None,
             None,
             ForwardRelativePathBuf::try_from(path.to_owned()).unwrap(),
             OutputType::File,
@@ -9,4 +9,5 @@
 
 pub(crate) mod artifact;
 mod cmd_args;
+mod package_values;
 mod provider;
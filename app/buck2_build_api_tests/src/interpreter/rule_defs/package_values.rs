@@ -0,0 +1,60 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use buck2_build_api::interpreter::rule_defs::package_values::tester::package_values_creator;
+use buck2_common::result::SharedResult;
+use buck2_interpreter_for_build::interpreter::testing::Tester;
+use indoc::indoc;
+
+fn tester() -> Tester {
+    let mut tester = Tester::new().unwrap();
+    tester.additional_globals(package_values_creator);
+    tester
+}
+
+#[test]
+fn package_values_getters() -> SharedResult<()> {
+    let content = indoc!(
+        r#"
+        def test():
+            values = create_package_values({
+                "cxx.strict": True,
+                "cxx.level": 2,
+                "cxx.name": "foo",
+                "cxx.flags": ["-O2"],
+                "cxx.env": {"A": "b"},
+            })
+            assert_eq(["cxx.strict", "cxx.level", "cxx.name", "cxx.flags", "cxx.env"], values.keys())
+            assert_eq(True, values.get_bool("cxx.strict", False))
+            assert_eq(2, values.get_int("cxx.level", 0))
+            assert_eq("foo", values.get_str("cxx.name"))
+            assert_eq(["-O2"], values.get_list("cxx.flags", []))
+            assert_eq({"A": "b"}, values.get_dict("cxx.env", {}))
+            assert_eq("foo", values.get("cxx.name"))
+            assert_eq(None, values.get("cxx.missing"))
+            assert_eq(False, values.get_bool("cxx.missing", False))
+            assert_eq(None, values.get_str("cxx.missing"))
+        "#
+    );
+    tester().run_starlark_bzl_test(content)
+}
+
+#[test]
+fn package_values_getters_check_types() {
+    let content = indoc!(
+        r#"
+        def test():
+            create_package_values({"cxx.strict": "yes"}).get_bool("cxx.strict", False)
+        "#
+    );
+    tester().run_starlark_bzl_test_expecting_error(
+        content,
+        "package value `cxx.strict` has type `string`, expected `bool`",
+    );
+}
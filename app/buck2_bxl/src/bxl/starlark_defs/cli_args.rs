@@ -19,8 +19,13 @@ use anyhow::Context as _;
 use buck2_build_api::bxl::types::CliArgValue;
 use buck2_build_api::calculation::load_patterns;
 use buck2_build_api::calculation::MissingTargetBehavior;
+use buck2_common::dice::file_ops::HasFileOps;
+use buck2_common::file_ops::FileOps;
 use buck2_common::result::SharedResult;
+use buck2_core::cells::cell_path::CellPath;
+use buck2_core::cells::paths::CellRelativePath;
 use buck2_core::pattern::lex_target_pattern;
+use buck2_core::pattern::maybe_split_cell_alias_and_relative_path;
 use buck2_core::pattern::pattern_type::ProvidersPatternExtra;
 use buck2_core::pattern::pattern_type::TargetPatternExtra;
 use buck2_core::pattern::ParsedPattern;
@@ -37,6 +42,8 @@ use starlark::environment::GlobalsBuilder;
 use starlark::starlark_module;
 use starlark::starlark_simple_value;
 use starlark::starlark_type;
+use starlark::values::dict::AllocDict;
+use starlark::values::dict::DictRef;
 use starlark::values::float::StarlarkFloat;
 use starlark::values::list::AllocList;
 use starlark::values::list::ListRef;
@@ -58,8 +65,9 @@ use crate::bxl::eval::CliResolutionCtx;
 pub(crate) struct CliArgs {
     /// The default value. If None, the value is not optional and must be provided by the user
     pub(crate) default: Option<Arc<CliArgValue>>,
-    /// Documentation for what the attribute actually means
-    doc: String,
+    /// Documentation for what the attribute actually means, followed by its type and default,
+    /// as shown in `--help`
+    help: String,
     /// The coercer to take this parameter's value from Starlark value -> an
     /// internal representation
     coercer: CliArgType,
@@ -104,16 +112,29 @@ impl CliArgs {
             },
         };
 
+        let mut help = doc.to_owned();
+        if !help.is_empty() {
+            help.push(' ');
+        }
+        help.push_str(&format!("[type: {}", coercer.help_type()));
+        match &default {
+            Some(default) if **default != CliArgValue::None => {
+                help.push_str(&format!(", default: {}", default))
+            }
+            _ => {}
+        }
+        help.push(']');
+
         Ok(Self {
             default,
-            doc: doc.to_owned(),
+            help,
             coercer,
             short,
         })
     }
 
     pub fn to_clap<'a>(&'a self, arg: clap::Arg<'a>) -> clap::Arg<'a> {
-        let mut arg = self.coercer.to_clap(arg.help(self.doc.as_str()));
+        let mut arg = self.coercer.to_clap(arg.help(self.help.as_str()));
         if let Some(short) = self.short {
             arg = arg.short(short);
         }
@@ -149,6 +170,9 @@ impl CliArgValueExt for CliArgValue {
             CliArgValue::Float(f) => heap.alloc(f.parse::<f64>().expect("already verified")),
             CliArgValue::String(s) => heap.alloc(s),
             CliArgValue::List(l) => heap.alloc(AllocList(l.iter().map(|v| v.as_starlark(heap)))),
+            CliArgValue::KeyValue(kv) => {
+                heap.alloc(AllocDict(kv.iter().map(|(k, v)| (k.as_str(), v.as_str()))))
+            }
             CliArgValue::None => Value::new_none(),
             CliArgValue::TargetLabel(t) => heap.alloc(StarlarkTargetLabel::new(t.dupe())),
             CliArgValue::ProvidersLabel(p) => heap.alloc(StarlarkProvidersLabel::new(p.clone())),
@@ -169,6 +193,8 @@ pub(crate) enum CliArgType {
     TargetLabel,
     TargetExpr,
     SubTarget,
+    KeyValue,
+    Path { must_exist: bool },
 }
 
 impl Display for CliArgType {
@@ -188,6 +214,9 @@ impl Display for CliArgType {
             CliArgType::Option(t) => {
                 write!(f, "{}({})", self.variant_name(), t)
             }
+            CliArgType::Path { must_exist } => {
+                write!(f, "{}(must_exist={})", self.variant_name(), must_exist)
+            }
             x => write!(f, "{}", x.variant_name()),
         }
     }
@@ -233,6 +262,46 @@ impl CliArgType {
     fn enumeration(vs: HashSet<String>) -> Self {
         CliArgType::Enumeration(Arc::new(vs))
     }
+
+    fn key_value() -> Self {
+        CliArgType::KeyValue
+    }
+
+    fn path(must_exist: bool) -> Self {
+        CliArgType::Path { must_exist }
+    }
+
+    /// The type as shown in `--help`.
+    fn help_type(&self) -> String {
+        match self {
+            CliArgType::Bool => "bool".to_owned(),
+            CliArgType::Int => "int".to_owned(),
+            CliArgType::Float => "float".to_owned(),
+            CliArgType::String => "string".to_owned(),
+            CliArgType::Enumeration(_) => "enum".to_owned(),
+            CliArgType::List(inner) => format!("list of {}, can be repeated", inner.help_type()),
+            CliArgType::Option(inner) => format!("optional {}", inner.help_type()),
+            CliArgType::TargetLabel => "target label".to_owned(),
+            CliArgType::TargetExpr => "target pattern".to_owned(),
+            CliArgType::SubTarget => "target label with optional subtarget".to_owned(),
+            CliArgType::KeyValue => "KEY=VALUE, can be repeated".to_owned(),
+            CliArgType::Path { must_exist: true } => "path to an existing file".to_owned(),
+            CliArgType::Path { must_exist: false } => "path".to_owned(),
+        }
+    }
+
+    /// Name of the argument value as shown in `--help`.
+    fn value_name(&self) -> Option<&'static str> {
+        match self {
+            CliArgType::Int => Some("INT"),
+            CliArgType::Float => Some("FLOAT"),
+            CliArgType::TargetLabel | CliArgType::SubTarget => Some("TARGET"),
+            CliArgType::TargetExpr => Some("PATTERN"),
+            CliArgType::KeyValue => Some("KEY=VALUE"),
+            CliArgType::Path { .. } => Some("PATH"),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Error)]
@@ -247,6 +316,34 @@ pub(crate) enum CliArgError {
     NoDefaultsAllowed(CliArgType),
     #[error("Duplicate short args are not allowed: `{0}` was already used")]
     DuplicateShort(char),
+    #[error("Expected `KEY=VALUE`, got `{0}`")]
+    NotAKeyValue(String),
+    #[error("Key `{0}` was given more than once")]
+    DuplicateKey(String),
+    #[error("Path `{0}` does not exist")]
+    PathDoesNotExist(CellPath),
+}
+
+fn parse_key_value(x: &str) -> anyhow::Result<(&str, &str)> {
+    match x.split_once('=') {
+        Some((k, v)) if !k.is_empty() => Ok((k, v)),
+        _ => Err(CliArgError::NotAKeyValue(x.to_owned()).into()),
+    }
+}
+
+fn parse_key_values<'a>(
+    values: impl IntoIterator<Item = &'a str>,
+) -> anyhow::Result<Vec<(String, String)>> {
+    let mut res: Vec<(String, String)> = Vec::new();
+    for x in values {
+        let (k, v) = parse_key_value(x)?;
+        if res.iter().any(|(existing, _)| existing == k) {
+            return Err(CliArgError::DuplicateKey(k.to_owned()).into());
+        }
+        res.push((k.to_owned(), v.to_owned()));
+    }
+    res.sort();
+    Ok(res)
 }
 
 impl CliArgType {
@@ -327,11 +424,37 @@ impl CliArgType {
                     CliArgType::TargetExpr
                 )));
             }
+            CliArgType::KeyValue => {
+                let dict = DictRef::from_value(value).ok_or_else(|| {
+                    CliArgError::DefaultValueTypeError(self.dupe(), value.get_type().to_owned())
+                })?;
+                let mut result = Vec::with_capacity(dict.len());
+                for (k, v) in dict.iter() {
+                    match (k.unpack_str(), v.unpack_str()) {
+                        (Some(k), Some(v)) => result.push((k.to_owned(), v.to_owned())),
+                        _ => {
+                            return Err(CliArgError::DefaultValueTypeError(
+                                self.dupe(),
+                                value.get_type().to_owned(),
+                            )
+                            .into());
+                        }
+                    }
+                }
+                result.sort();
+                CliArgValue::KeyValue(result)
+            }
+            CliArgType::Path { .. } => {
+                return Err(anyhow::anyhow!(CliArgError::NoDefaultsAllowed(self.dupe())));
+            }
         })
     }
 
     #[allow(deprecated)] // TODO(nga): fix.
-    pub fn to_clap<'a>(&'a self, clap: clap::Arg<'a>) -> clap::Arg<'a> {
+    pub fn to_clap<'a>(&'a self, mut clap: clap::Arg<'a>) -> clap::Arg<'a> {
+        if let Some(value_name) = self.value_name() {
+            clap = clap.value_name(value_name);
+        }
         match self {
             CliArgType::Bool => clap.takes_value(true).validator(|x| x.parse::<bool>()),
             CliArgType::Int => clap.takes_value(true).validator(|x| x.parse::<i32>()),
@@ -363,6 +486,11 @@ impl CliArgType {
                     })
             }),
             CliArgType::TargetExpr => clap.takes_value(true),
+            CliArgType::KeyValue => clap
+                .takes_value(true)
+                .multiple(true)
+                .validator(|x| parse_key_value(x).map(|_| ())),
+            CliArgType::Path { .. } => clap.takes_value(true),
         }
     }
 
@@ -445,6 +573,23 @@ impl CliArgType {
                     };
                     r.map(Some)
                 })?,
+                CliArgType::KeyValue => match clap.values_of() {
+                    None => None,
+                    Some(values) => Some(CliArgValue::KeyValue(parse_key_values(values)?)),
+                },
+                CliArgType::Path { must_exist } => match clap.value_of() {
+                    None => None,
+                    Some(x) => {
+                        let path = resolve_path_arg(x, ctx)?;
+                        if *must_exist
+                            && !<dyn FileOps>::try_exists(&ctx.dice.file_ops(), path.as_ref())
+                                .await?
+                        {
+                            return Err(CliArgError::PathDoesNotExist(path).into());
+                        }
+                        Some(CliArgValue::String(path.to_string()))
+                    }
+                },
                 CliArgType::TargetExpr => {
                     let x = clap.value_of().unwrap_or("");
                     let pattern = ParsedPattern::<TargetPatternExtra>::parse_relaxed(
@@ -468,6 +613,29 @@ impl CliArgType {
     }
 }
 
+/// Resolve a path given on the command line, either as a cell path (`cell//path`) or relative to
+/// the working directory.
+fn resolve_path_arg(x: &str, ctx: &CliResolutionCtx<'_>) -> anyhow::Result<CellPath> {
+    match maybe_split_cell_alias_and_relative_path(x)? {
+        Some((alias, path)) => {
+            let cell_name = ctx
+                .cell_resolver
+                .get(ctx.relative_dir.cell_name())?
+                .cell_alias_resolver()
+                .resolve(alias.as_str())?;
+            Ok(CellPath::new(
+                cell_name,
+                CellRelativePath::new(path).to_buf(),
+            ))
+        }
+        None => ctx
+            .relative_dir
+            .as_cell_path()
+            .to_owned()
+            .join_normalized(x),
+    }
+}
+
 #[starlark_module]
 pub(crate) fn cli_args_module(registry: &mut GlobalsBuilder) {
     fn string<'v>(
@@ -559,6 +727,27 @@ pub(crate) fn cli_args_module(registry: &mut GlobalsBuilder) {
     ) -> anyhow::Result<CliArgs> {
         CliArgs::new(None, doc, CliArgType::target_expr(), short)
     }
+
+    /// A map of strings given as `--arg KEY=VALUE`, which can be repeated. The value in bxl is
+    /// a dict.
+    fn key_value<'v>(
+        default: Option<Value<'v>>,
+        #[starlark(default = "")] doc: &str,
+        #[starlark(require = named)] short: Option<Value<'v>>,
+    ) -> anyhow::Result<CliArgs> {
+        CliArgs::new(default, doc, CliArgType::key_value(), short)
+    }
+
+    /// A path, either as a cell path (`cell//path`) or relative to the working directory. The
+    /// value in bxl is the cell path as a string. With `must_exist`, the path is checked to
+    /// exist when parsing the command line.
+    fn path<'v>(
+        #[starlark(default = "")] doc: &str,
+        #[starlark(require = named, default = true)] must_exist: bool,
+        #[starlark(require = named)] short: Option<Value<'v>>,
+    ) -> anyhow::Result<CliArgs> {
+        CliArgs::new(None, doc, CliArgType::path(must_exist), short)
+    }
 }
 
 pub fn register_cli_args_module(registry: &mut GlobalsBuilder) {
@@ -574,9 +763,11 @@ mod tests {
     use buck2_core::target::label::TargetLabel;
     use buck2_interpreter::types::label::StarlarkProvidersLabel;
     use buck2_interpreter::types::target_label::StarlarkTargetLabel;
+    use starlark::values::dict::AllocDict;
     use starlark::values::Heap;
     use starlark::values::Value;
 
+    use crate::bxl::starlark_defs::cli_args::parse_key_values;
     use crate::bxl::starlark_defs::cli_args::CliArgType;
     use crate::bxl::starlark_defs::cli_args::CliArgValue;
 
@@ -595,6 +786,31 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn print_cli_arg_key_value() {
+        let cli_arg = CliArgValue::KeyValue(vec![
+            ("a".to_owned(), "1".to_owned()),
+            ("b".to_owned(), "x=y".to_owned()),
+        ]);
+        assert_eq!(format!("{}", cli_arg), "a=1,b=x=y");
+    }
+
+    #[test]
+    fn key_values() -> anyhow::Result<()> {
+        assert_eq!(
+            parse_key_values(["b=2", "a=1=1", "c="])?,
+            vec![
+                ("a".to_owned(), "1=1".to_owned()),
+                ("b".to_owned(), "2".to_owned()),
+                ("c".to_owned(), "".to_owned()),
+            ]
+        );
+        assert!(parse_key_values(["a=1", "a=2"]).is_err());
+        assert!(parse_key_values(["a"]).is_err());
+        assert!(parse_key_values(["=1"]).is_err());
+        Ok(())
+    }
+
     #[test]
     fn coerce_starlark() -> anyhow::Result<()> {
         let heap = Heap::new();
@@ -653,6 +869,15 @@ mod tests {
             ])
         );
 
+        assert_eq!(
+            CliArgType::key_value()
+                .coerce_value(heap.alloc(AllocDict([("b", "2"), ("a", "1")])))?,
+            CliArgValue::KeyValue(vec![
+                ("a".to_owned(), "1".to_owned()),
+                ("b".to_owned(), "2".to_owned()),
+            ])
+        );
+
         assert_eq!(
             CliArgType::target_label().coerce_value(heap.alloc(StarlarkTargetLabel::new(
                 TargetLabel::testing_parse("root//foo:bar")
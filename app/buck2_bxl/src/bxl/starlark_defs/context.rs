@@ -160,6 +160,7 @@ impl<'v> BxlContext<'v> {
             state: heap.alloc_typed(AnalysisActions {
                 state: RefCell::new(None),
                 attributes: heap.alloc(AllocStruct::EMPTY),
+                attrs_read: Default::default(),
                 digest_config,
             }),
             output_stream: heap.alloc_typed(OutputStream::new(
@@ -17,6 +17,7 @@ use buck2_build_api::query::cquery::evaluator::get_cquery_evaluator;
 use buck2_common::dice::cells::HasCellResolver;
 use buck2_core::target::label::TargetLabel;
 use buck2_node::nodes::configured::ConfiguredTargetNode;
+use buck2_query::query::syntax::simple::eval::set::SelectBranches;
 use buck2_query::query::syntax::simple::functions::helpers::CapturedExpr;
 use buck2_query::query::syntax::simple::functions::DefaultQueryFunctions;
 use buck2_query::query::syntax::simple::functions::DefaultQueryFunctionsModule;
@@ -272,6 +273,45 @@ fn register_cquery(builder: &mut MethodsBuilder) {
         })
     }
 
+    /// The nattrfilter query for rule attribute filtering, keeping the targets whose attribute
+    /// does not match.
+    ///
+    /// Sample usage:
+    /// ```text
+    /// def _impl_nattrfilter(ctx):
+    ///     result = ctx.cquery().nattrfilter("name", "foo", "root//...")
+    ///     ctx.output.print(result)
+    /// ```
+    fn nattrfilter<'v>(
+        this: &StarlarkCQueryCtx<'v>,
+        attr: &str,
+        value: &str,
+        targets: Value<'v>,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<StarlarkTargetSet<ConfiguredTargetNode>> {
+        this.ctx.async_ctx.via(|| async {
+            this.functions
+                .nattrfilter(
+                    attr,
+                    value,
+                    &filter_incompatible(
+                        TargetExpr::<'v, ConfiguredTargetNode>::unpack(
+                            targets,
+                            &this.target_platform,
+                            this.ctx,
+                            eval,
+                        )
+                        .await?
+                        .get(this.ctx.async_ctx.0)
+                        .await?
+                        .into_iter(),
+                        this.ctx,
+                    )?,
+                )
+                .map(StarlarkTargetSet::from)
+        })
+    }
+
     /// The kind query for filtering targets by rule type.
     ///
     /// Sample usage:
@@ -308,7 +348,8 @@ fn register_cquery(builder: &mut MethodsBuilder) {
         })
     }
 
-    /// The attrregexfilter query for rule attribute filtering with regex.
+    /// The attrregexfilter query for rule attribute filtering with regex. A `select()` matches if
+    /// any of its branches match, or if all of them do with `branches = "all"`.
     ///
     /// Sample usage:
     /// ```text
@@ -321,9 +362,14 @@ fn register_cquery(builder: &mut MethodsBuilder) {
         attribute: &str,
         value: &str,
         targets: Value<'v>,
+        #[starlark(default = NoneOr::None)] branches: NoneOr<&str>,
         eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<StarlarkTargetSet<ConfiguredTargetNode>> {
         this.ctx.async_ctx.via(|| async {
+            let branches = match branches.into_option() {
+                Some(branches) => branches.parse()?,
+                None => SelectBranches::Any,
+            };
             this.functions
                 .attrregexfilter(
                     attribute,
@@ -341,6 +387,7 @@ fn register_cquery(builder: &mut MethodsBuilder) {
                         .into_iter(),
                         this.ctx,
                     )?,
+                    branches,
                 )
                 .map(StarlarkTargetSet::from)
         })
@@ -566,7 +613,7 @@ fn register_cquery(builder: &mut MethodsBuilder) {
         this: &StarlarkCQueryCtx<'v>,
         universe: Value<'v>,
         from: Value<'v>,
-        depth: Option<i32>,
+        #[starlark(default = NoneOr::None)] depth: NoneOr<i32>,
         eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<StarlarkTargetSet<ConfiguredTargetNode>> {
         this.ctx
@@ -601,7 +648,7 @@ fn register_cquery(builder: &mut MethodsBuilder) {
                             .into_iter(),
                             this.ctx,
                         )?,
-                        depth,
+                        depth.into_option(),
                     )
                     .await
             })
@@ -713,4 +760,69 @@ fn register_cquery(builder: &mut MethodsBuilder) {
             })
             .map(StarlarkFileSet::from)
     }
+
+    /// The allbuildfiles query for finding the build files defining the targets, and the `.bzl`
+    /// and `PACKAGE` files their evaluation depends on.
+    ///
+    /// Sample usage:
+    /// ```text
+    /// def _allbuildfiles_impl(ctx):
+    ///     result = ctx.cquery().allbuildfiles("root//bin/...")
+    ///     ctx.output.print(result)
+    /// ```
+    fn allbuildfiles<'v>(
+        this: &StarlarkCQueryCtx<'v>,
+        universe: Value<'v>,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<StarlarkFileSet> {
+        this.ctx
+            .async_ctx
+            .via(|| async {
+                let universe = &filter_incompatible(
+                    TargetExpr::<'v, ConfiguredTargetNode>::unpack(
+                        universe,
+                        &this.target_platform,
+                        this.ctx,
+                        eval,
+                    )
+                    .await?
+                    .get(this.ctx.async_ctx.0)
+                    .await?
+                    .into_iter(),
+                    this.ctx,
+                )?;
+
+                this.functions.allbuildfiles(&this.env, universe).await
+            })
+            .map(StarlarkFileSet::from)
+    }
+
+    /// The rbuildfiles query for finding the files in `universe` whose evaluation depends on any
+    /// of the files in `argset`.
+    ///
+    /// Sample usage:
+    /// ```text
+    /// def _rbuildfiles_impl(ctx):
+    ///     universe = ctx.cquery().allbuildfiles("root//...")
+    ///     result = ctx.cquery().rbuildfiles(universe, "foo/defs.bzl")
+    ///     ctx.output.print(result)
+    /// ```
+    fn rbuildfiles<'v>(
+        this: &StarlarkCQueryCtx<'v>,
+        universe: FileSetExpr,
+        argset: FileSetExpr,
+    ) -> anyhow::Result<StarlarkFileSet> {
+        this.ctx
+            .async_ctx
+            .via(|| async {
+                this.functions
+                    .rbuildfiles(
+                        &this.env,
+                        universe.get(&this.env).await?.as_ref(),
+                        argset.get(&this.env).await?.as_ref(),
+                    )
+                    .await
+            })
+            .map(StarlarkFileSet::from)
+    }
 }
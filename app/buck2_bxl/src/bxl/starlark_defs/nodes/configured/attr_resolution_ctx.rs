@@ -10,6 +10,7 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use buck2_build_api::analysis::all_deps;
 use buck2_build_api::analysis::calculation::get_dep_analysis;
 use buck2_build_api::analysis::calculation::resolve_queries;
 use buck2_build_api::analysis::get_dep;
@@ -79,6 +80,13 @@ impl<'v> AttrResolutionContext<'v> for LazyAttrResolutionContext<'v> {
         }
     }
 
+    fn all_deps(&self) -> Vec<(ConfiguredProvidersLabel, FrozenProviderCollectionValue)> {
+        match self.dep_analysis_results() {
+            Ok(deps) => all_deps(deps),
+            Err(_) => Vec::new(),
+        }
+    }
+
     fn resolve_unkeyed_placeholder(
         &self,
         name: &str,
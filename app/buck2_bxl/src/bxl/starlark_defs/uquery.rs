@@ -15,6 +15,7 @@ use buck2_build_api::query::uquery::environment::UqueryEnvironment;
 use buck2_build_api::query::uquery::evaluator::get_uquery_evaluator;
 use buck2_common::dice::cells::HasCellResolver;
 use buck2_node::nodes::unconfigured::TargetNode;
+use buck2_query::query::syntax::simple::eval::set::SelectBranches;
 use buck2_query::query::syntax::simple::functions::helpers::CapturedExpr;
 use buck2_query::query::syntax::simple::functions::DefaultQueryFunctions;
 use buck2_query::query::syntax::simple::functions::DefaultQueryFunctionsModule;
@@ -206,6 +207,36 @@ fn register_uquery(builder: &mut MethodsBuilder) {
         })
     }
 
+    /// The nattrfilter query for rule attribute filtering, keeping the targets whose attribute
+    /// does not match.
+    ///
+    /// Sample usage:
+    /// ```text
+    /// def _impl_nattrfilter(ctx):
+    ///     result = ctx.uquery().nattrfilter("name", "foo", "root//...")
+    ///     ctx.output.print(result)
+    /// ```
+    fn nattrfilter<'v>(
+        this: &StarlarkUQueryCtx<'v>,
+        attr: &str,
+        value: &str,
+        targets: Value<'v>,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<StarlarkTargetSet<TargetNode>> {
+        this.ctx.async_ctx.via(|| async {
+            this.functions
+                .nattrfilter(
+                    attr,
+                    value,
+                    &*TargetExpr::<'v, TargetNode>::unpack(targets, this.ctx, eval)
+                        .await?
+                        .get(&this.env)
+                        .await?,
+                )
+                .map(StarlarkTargetSet::from)
+        })
+    }
+
     /// The inputs query for finding input files.
     ///
     /// Sample usage:
@@ -312,7 +343,7 @@ fn register_uquery(builder: &mut MethodsBuilder) {
         this: &StarlarkUQueryCtx<'v>,
         universe: Value<'v>,
         from: Value<'v>,
-        depth: Option<i32>,
+        #[starlark(default = NoneOr::None)] depth: NoneOr<i32>,
         eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<StarlarkTargetSet<TargetNode>> {
         this.ctx
@@ -329,7 +360,7 @@ fn register_uquery(builder: &mut MethodsBuilder) {
                             .await?
                             .get(&this.env)
                             .await?,
-                        depth,
+                        depth.into_option(),
                     )
                     .await
             })
@@ -420,6 +451,62 @@ fn register_uquery(builder: &mut MethodsBuilder) {
             .map(StarlarkFileSet::from)
     }
 
+    /// The allbuildfiles query for finding the build files defining the targets, and the `.bzl`
+    /// and `PACKAGE` files their evaluation depends on.
+    ///
+    /// Sample usage:
+    /// ```text
+    /// def _allbuildfiles_impl(ctx):
+    ///     result = ctx.uquery().allbuildfiles("root//bin/...")
+    ///     ctx.output.print(result)
+    /// ```
+    fn allbuildfiles<'v>(
+        this: &StarlarkUQueryCtx<'v>,
+        universe: Value<'v>,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<StarlarkFileSet> {
+        this.ctx
+            .async_ctx
+            .via(|| async {
+                let universe = &*TargetExpr::<'v, TargetNode>::unpack(universe, this.ctx, eval)
+                    .await?
+                    .get(&this.env)
+                    .await?;
+
+                this.functions.allbuildfiles(&this.env, universe).await
+            })
+            .map(StarlarkFileSet::from)
+    }
+
+    /// The rbuildfiles query for finding the files in `universe` whose evaluation depends on any
+    /// of the files in `argset`.
+    ///
+    /// Sample usage:
+    /// ```text
+    /// def _rbuildfiles_impl(ctx):
+    ///     universe = ctx.uquery().allbuildfiles("root//...")
+    ///     result = ctx.uquery().rbuildfiles(universe, "foo/defs.bzl")
+    ///     ctx.output.print(result)
+    /// ```
+    fn rbuildfiles<'v>(
+        this: &StarlarkUQueryCtx<'v>,
+        universe: FileSetExpr,
+        argset: FileSetExpr,
+    ) -> anyhow::Result<StarlarkFileSet> {
+        this.ctx
+            .async_ctx
+            .via(|| async {
+                this.functions
+                    .rbuildfiles(
+                        &this.env,
+                        universe.get(&this.env).await?.as_ref(),
+                        argset.get(&this.env).await?.as_ref(),
+                    )
+                    .await
+            })
+            .map(StarlarkFileSet::from)
+    }
+
     /// The owner query for finding targets that own specified files.
     ///
     /// Sample usage:
@@ -442,7 +529,8 @@ fn register_uquery(builder: &mut MethodsBuilder) {
             .map(StarlarkTargetSet::from)
     }
 
-    /// The attrregexfilter query for rule attribute filtering with regex.
+    /// The attrregexfilter query for rule attribute filtering with regex. A `select()` matches if
+    /// any of its branches match, or if all of them do with `branches = "all"`.
     ///
     /// Sample usage:
     /// ```text
@@ -455,9 +543,14 @@ fn register_uquery(builder: &mut MethodsBuilder) {
         attribute: &str,
         value: &str,
         targets: Value<'v>,
+        #[starlark(default = NoneOr::None)] branches: NoneOr<&str>,
         eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<StarlarkTargetSet<TargetNode>> {
         this.ctx.async_ctx.via(|| async {
+            let branches = match branches.into_option() {
+                Some(branches) => branches.parse()?,
+                None => SelectBranches::Any,
+            };
             this.functions
                 .attrregexfilter(
                     attribute,
@@ -466,6 +559,7 @@ fn register_uquery(builder: &mut MethodsBuilder) {
                         .await?
                         .get(&this.env)
                         .await?,
+                    branches,
                 )
                 .map(StarlarkTargetSet::from)
         })
@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use std::env;
 use std::fs::File;
 use std::io;
 use std::io::BufRead;
@@ -30,8 +31,8 @@ use thiserror::Error;
 
 #[derive(Error, Debug)]
 enum ArgExpansionError {
-    #[error("Missing flag file path after --flagfile argument")]
-    MissingFlagFilePath,
+    #[error("Missing flag file path after {0} argument")]
+    MissingFlagFilePath(String),
     #[error("Unable to read flag file at `{path}`")]
     MissingFlagFileOnDisk { source: anyhow::Error, path: String },
     #[error("Unable to read line in flag file `{path}`")]
@@ -46,8 +47,23 @@ enum ArgExpansionError {
     PythonExecutionFailed { source: io::Error, cmd: Command },
     #[error("Unable to read line from stdin")]
     StdinReadError { source: anyhow::Error },
+    #[error("Flag file `{path}` includes itself: {}", .chain.join(" -> "))]
+    FlagFileCycle { path: String, chain: Vec<String> },
+    #[error("Unterminated `${{` in flag file `{path}`")]
+    UnterminatedEnvVar { path: String },
+    #[error(
+        "Environment variable `{var}` used in flag file `{path}` is not allowed, add it to `{}` to allow it",
+        ARGFILE_ENV_ALLOWLIST
+    )]
+    EnvVarNotAllowed { var: String, path: String },
+    #[error("Environment variable `{var}` used in flag file `{path}` is not set")]
+    EnvVarNotSet { var: String, path: String },
 }
 
+/// Comma-separated list of the environment variables which can be interpolated in flag files
+/// with `${NAME}`.
+const ARGFILE_ENV_ALLOWLIST: &str = "BUCK2_ARGFILE_ENV_ALLOWLIST";
+
 /// Argument resolver that can expand cell paths, e.g., `cell//path/to/file`
 struct ArgCellPathResolver<'a> {
     // Deliberately use `OnceCell` rather than `Lazy` because `Lazy` forces
@@ -123,6 +139,8 @@ impl<'a> ArgCellPathResolver<'a> {
 pub struct ArgExpansionContext<'a> {
     arg_resolver: ArgCellPathResolver<'a>,
     trace: Vec<AbsNormPathBuf>,
+    /// Flag files currently being expanded, to detect cycles.
+    stack: Vec<AbsNormPathBuf>,
 }
 
 impl<'a> ArgExpansionContext<'a> {
@@ -130,6 +148,7 @@ impl<'a> ArgExpansionContext<'a> {
         Self {
             arg_resolver: ArgCellPathResolver::new(cwd),
             trace: Vec::new(),
+            stack: Vec::new(),
         }
     }
 
@@ -173,11 +192,28 @@ enum ArgFile {
     Stdin,
 }
 
+impl ArgFile {
+    fn path(&self) -> Option<&AbsNormPath> {
+        match self {
+            ArgFile::PythonExecutable(path, _) | ArgFile::Path(path) => Some(path),
+            ArgFile::Stdin => None,
+        }
+    }
+}
+
 // Expands any argfiles passed as command line parameters. There are
-// two ways to do: `@argfile` or `--flagfile PATH`.
+// two ways to do: `@argfile` or `--flagfile PATH` (or its alias
+// `--command-file PATH`).
+//
+// Argfiles contain one argument per line, and can include other
+// argfiles. Empty lines and lines starting with `#` are skipped, and
+// `${NAME}` is replaced with the value of the environment variable
+// `NAME` if it is listed in `BUCK2_ARGFILE_ENV_ALLOWLIST` (`$${`
+// produces a literal `${`).
 //
 // Caveats:
-//  - `--` and `--flagfile` cannot be values of other options
+//  - `--`, `--flagfile` and `--command-file` cannot be values of
+//    other options
 //  - `--flagfile=X` is _not_ supported, you need to pass
 //    `--flagfile X` instead.
 //  - `--flagfil` is _not_ supported.
@@ -203,12 +239,15 @@ pub fn expand_argfiles_with_context(
                 expanded_args.extend(arg_iterator);
                 break;
             }
-            "--flagfile" => {
+            "--flagfile" | "--command-file" => {
                 let flagfile = match arg_iterator.next() {
                     Some(val) => val,
-                    None => return Err(anyhow::anyhow!(ArgExpansionError::MissingFlagFilePath)),
+                    None => {
+                        return Err(anyhow::anyhow!(ArgExpansionError::MissingFlagFilePath(
+                            next_arg
+                        )));
+                    }
                 };
-                // TODO: We want to detect cyclic inclusion
                 let expanded_flagfile_args = resolve_and_expand_argfile(&flagfile, context)?;
                 expanded_args.extend(expanded_flagfile_args);
             }
@@ -219,7 +258,6 @@ pub fn expand_argfiles_with_context(
                         ArgExpansionError::MissingFlagFilePathInArgfile
                     ));
                 }
-                // TODO: We want to detect cyclic inclusion
                 let expanded_flagfile_args = resolve_and_expand_argfile(flagfile, context)?;
                 expanded_args.extend(expanded_flagfile_args);
             }
@@ -238,13 +276,82 @@ fn resolve_and_expand_argfile(
 ) -> anyhow::Result<Vec<String>> {
     let flagfile = resolve_flagfile(path, context)
         .with_context(|| format!("Error resolving flagfile `{}`", path))?;
+    let flagfile_path = flagfile.path().map(|p| p.to_buf());
+    if let Some(flagfile_path) = &flagfile_path {
+        if context.stack.contains(flagfile_path) {
+            let chain = context
+                .stack
+                .iter()
+                .chain(std::iter::once(flagfile_path))
+                .map(|p| p.to_string())
+                .collect();
+            return Err(ArgExpansionError::FlagFileCycle {
+                path: flagfile_path.to_string(),
+                chain,
+            }
+            .into());
+        }
+        context.stack.push(flagfile_path.clone());
+    }
     let flagfile_lines = expand_argfile_contents(&flagfile)?;
-    expand_argfiles_with_context(flagfile_lines, context)
+    let expanded = expand_argfiles_with_context(flagfile_lines, context);
+    if flagfile_path.is_some() {
+        context.stack.pop();
+    }
+    expanded
+}
+
+/// Replace `${NAME}` in a flag file line with the value of the environment variable `NAME`.
+fn interpolate_env_vars(
+    line: &str,
+    path: &str,
+    allowlist: &[&str],
+    lookup: impl Fn(&str) -> Option<String>,
+) -> anyhow::Result<String> {
+    let mut result = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            // `$${` is an escaped `${`.
+            result.push_str(&rest[..start]);
+            result.push('{');
+            rest = &rest[start + 2..];
+            continue;
+        }
+        result.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| ArgExpansionError::UnterminatedEnvVar {
+                path: path.to_owned(),
+            })?;
+        let var = &rest[start + 2..start + end];
+        if !allowlist.contains(&var) {
+            return Err(ArgExpansionError::EnvVarNotAllowed {
+                var: var.to_owned(),
+                path: path.to_owned(),
+            }
+            .into());
+        }
+        let value = lookup(var).ok_or_else(|| ArgExpansionError::EnvVarNotSet {
+            var: var.to_owned(),
+            path: path.to_owned(),
+        })?;
+        result.push_str(&value);
+        rest = &rest[start + end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
 }
 
 fn expand_argfile_contents(flagfile: &ArgFile) -> anyhow::Result<Vec<String>> {
     match flagfile {
         ArgFile::Path(path) => {
+            let allowlist = env::var(ARGFILE_ENV_ALLOWLIST).unwrap_or_default();
+            let allowlist: Vec<&str> = allowlist
+                .split(',')
+                .map(|v| v.trim())
+                .filter(|v| !v.is_empty())
+                .collect();
             let mut lines = Vec::new();
             let file =
                 File::open(path).map_err(|source| ArgExpansionError::MissingFlagFileOnDisk {
@@ -257,10 +364,15 @@ fn expand_argfile_contents(flagfile: &ArgFile) -> anyhow::Result<Vec<String>> {
                     source: source.into(),
                     path: path.to_string_lossy().into_owned(),
                 })?;
-                if line.is_empty() {
+                if line.is_empty() || line.trim_start().starts_with('#') {
                     continue;
                 }
-                lines.push(line);
+                lines.push(interpolate_env_vars(
+                    &line,
+                    &path.to_string_lossy(),
+                    &allowlist,
+                    |var| env::var(var).ok(),
+                )?);
             }
             Ok(lines)
         }
@@ -366,6 +478,7 @@ mod tests {
     use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 
     use crate::args::expand_argfile_contents;
+    use crate::args::interpolate_env_vars;
     use crate::args::ArgFile;
 
     #[test]
@@ -373,11 +486,30 @@ mod tests {
         let tempdir = tempfile::tempdir().unwrap();
         let mode_file = tempdir.path().join("mode-file");
         // Test skips empty lines.
-        fs_util::write(&mode_file, "a\n\nb\n").unwrap();
+        fs_util::write(&mode_file, "a\n\n# comment\nb\n").unwrap();
         let lines = expand_argfile_contents(&ArgFile::Path(
             AbsNormPathBuf::from(mode_file.to_string_lossy().into_owned()).unwrap(),
         ))
         .unwrap();
         assert_eq!(vec!["a".to_owned(), "b".to_owned()], lines);
     }
+
+    #[test]
+    fn test_interpolate_env_vars() {
+        let lookup = |var: &str| match var {
+            "FOO" => Some("foo".to_owned()),
+            _ => None,
+        };
+        let interpolate = |line| interpolate_env_vars(line, "argfile", &["FOO", "BAR"], lookup);
+
+        assert_eq!(
+            "--config=a.b=foo",
+            interpolate("--config=a.b=${FOO}").unwrap()
+        );
+        assert_eq!("foo/foo", interpolate("${FOO}/${FOO}").unwrap());
+        assert_eq!("${FOO} $x", interpolate("$${FOO} $x").unwrap());
+        assert!(interpolate("${BAR}").is_err());
+        assert!(interpolate("${BAZ}").is_err());
+        assert!(interpolate("${FOO").is_err());
+    }
 }
@@ -97,7 +97,9 @@ pub struct BuildCommand {
 
     #[clap(
         long = "materializations",
-        help = "Materialize (or skip) the final artifacts, bypassing buckconfig.",
+        help = "Materialize (or skip) the final artifacts, bypassing buckconfig. \
+                `none` skips materialization, `defaults` only materializes the default outputs \
+                of the requested targets, and `all` materializes everything built for them.",
         ignore_case = true,
         arg_enum
     )]
@@ -186,6 +188,7 @@ impl BuildCommand {
 #[clap(rename_all = "snake_case")]
 pub enum FinalArtifactMaterializations {
     All,
+    Defaults,
     None,
 }
 
@@ -198,6 +201,9 @@ impl MaterializationsToProto for Option<FinalArtifactMaterializations> {
             Some(FinalArtifactMaterializations::All) => {
                 buck2_cli_proto::build_request::Materializations::Materialize
             }
+            Some(FinalArtifactMaterializations::Defaults) => {
+                buck2_cli_proto::build_request::Materializations::MaterializeDefaultOutputs
+            }
             Some(FinalArtifactMaterializations::None) => {
                 buck2_cli_proto::build_request::Materializations::Skip
             }
@@ -315,6 +321,8 @@ impl StreamingCommand for BuildCommand {
                     },
                     self.show_json_output || self.show_full_json_output,
                     show_default_other_outputs,
+                    // The same target is built once per platform, so tell the outputs apart.
+                    self.common_opts.config_opts.target_platforms.len() > 1,
                 )?;
             }
 
@@ -345,6 +353,7 @@ pub(crate) fn print_outputs(
     root_path: Option<String>,
     as_json: bool,
     show_all_outputs: bool,
+    show_configurations: bool,
 ) -> anyhow::Result<()> {
     #[derive(Serialize)]
     #[serde(untagged)]
@@ -404,6 +413,12 @@ pub(crate) fn print_outputs(
     };
 
     for build_target in targets {
+        let target = if show_configurations {
+            format!("{} ({})", build_target.target, build_target.configuration)
+        } else {
+            build_target.target
+        };
+
         // just print the default info for build command
         let outputs = build_target.outputs.into_iter().filter(|output| {
             output
@@ -412,17 +427,17 @@ pub(crate) fn print_outputs(
                 .map_or(true, |p| show_all_outputs || (p.default_info && !p.other))
         });
 
-        // only print the unconfigured target for now until we migrate everything to support
-        // also printing configurations
+        // only print the unconfigured target unless several target platforms were requested,
+        // until we migrate everything to support also printing configurations
         if outputs.clone().count() > 1 && !show_all_outputs {
             // We only print the default outputs when we don't `show_all_outputs`,
             // which shouldn't have more than one output.
             // (although we currently don't yet restrict this, but we should).
-            process_output(&build_target.target, None)?;
+            process_output(&target, None)?;
             continue;
         }
         for output in outputs {
-            process_output(&build_target.target, Some(output.path))?;
+            process_output(&target, Some(output.path))?;
         }
     }
 
@@ -0,0 +1,160 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::path::Path;
+
+use anyhow::Context as _;
+use async_trait::async_trait;
+use buck2_audit::compilation_database::merge_entries;
+use buck2_audit::compilation_database::AuditCompilationDatabaseCommand;
+use buck2_audit::compilation_database::CompilationDatabaseEntry;
+use buck2_audit::AuditCommand;
+use buck2_cli_proto::GenericRequest;
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::common::CommonBuildConfigurationOptions;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_client_ctx::common::CommonConsoleOptions;
+use buck2_client_ctx::common::CommonDaemonCommandOptions;
+use buck2_client_ctx::daemon::client::BuckdClientConnector;
+use buck2_client_ctx::events_ctx::PartialResultCtx;
+use buck2_client_ctx::events_ctx::PartialResultHandler;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::streaming::BuckSubcommand;
+use buck2_client_ctx::streaming::StreamingCommand;
+use buck2_core::fs::fs_util;
+
+#[derive(Debug, clap::Subcommand)]
+#[clap(about = "Generate compilation databases (compile_commands.json) for C/C++/ObjC targets")]
+pub enum CdbCommand {
+    /// Build the compilation databases of the given targets and write them merged to a single
+    /// `compile_commands.json`.
+    ///
+    /// Targets without a `compilation-database` sub-target are ignored. Headers of the targets
+    /// get an entry copied from a source of the same target, so editors can parse them too.
+    Gen(CdbGenCommand),
+}
+
+impl CdbCommand {
+    pub fn exec(self, matches: &clap::ArgMatches, ctx: ClientCommandContext<'_>) -> ExitResult {
+        let matches = matches.subcommand().expect("subcommand not found").1;
+        match self {
+            Self::Gen(cmd) => cmd.exec(matches, ctx),
+        }
+    }
+}
+
+#[derive(Debug, clap::Parser)]
+pub struct CdbGenCommand {
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    #[clap(
+        name = "TARGET_PATTERNS",
+        help = "Patterns of the targets to include",
+        required = true
+    )]
+    patterns: Vec<String>,
+
+    /// Where to write the compilation database, relative to the current directory.
+    #[clap(long, short = 'o', default_value = "compile_commands.json")]
+    output: String,
+
+    /// Keep the entries of the existing output for files not covered by these targets, so that
+    /// the database can be updated one target at a time.
+    #[clap(long)]
+    update: bool,
+}
+
+/// Collects the stdout of the daemon, which is the JSON compilation database.
+#[derive(Default)]
+struct CaptureStdout {
+    buf: Vec<u8>,
+}
+
+#[async_trait]
+impl PartialResultHandler for CaptureStdout {
+    type PartialResult = buck2_cli_proto::StdoutBytes;
+
+    async fn handle_partial_result(
+        &mut self,
+        _ctx: PartialResultCtx<'_>,
+        partial_res: Self::PartialResult,
+    ) -> anyhow::Result<()> {
+        self.buf.extend(partial_res.data);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl StreamingCommand for CdbGenCommand {
+    const COMMAND_NAME: &'static str = "cdb";
+
+    async fn exec_impl(
+        self,
+        buckd: &mut BuckdClientConnector,
+        matches: &clap::ArgMatches,
+        ctx: &mut ClientCommandContext<'_>,
+    ) -> ExitResult {
+        let context = ctx.client_context(
+            &self.common_opts.config_opts,
+            matches,
+            self.sanitized_argv(),
+        )?;
+
+        // This is implemented by the `audit compilation-database` command on the daemon.
+        let serialized_opts = serde_json::to_string(&AuditCommand::CompilationDatabase(
+            AuditCompilationDatabaseCommand {
+                common_opts: Default::default(),
+                patterns: self.patterns.clone(),
+            },
+        ))?;
+
+        let mut capture = CaptureStdout::default();
+        buckd
+            .with_flushing()
+            .audit(
+                GenericRequest {
+                    context: Some(context),
+                    serialized_opts,
+                },
+                ctx.stdin()
+                    .console_interaction_stream(&self.common_opts.console_opts),
+                &mut capture,
+            )
+            .await??;
+        let entries: Vec<CompilationDatabaseEntry> = serde_json::from_slice(&capture.buf)
+            .context("Invalid compilation database returned by the daemon")?;
+
+        let output = ctx.working_dir.resolve(Path::new(&self.output));
+        let entries = match fs_util::read_to_string_opt(&output)? {
+            Some(existing) if self.update => {
+                let existing: Vec<CompilationDatabaseEntry> = serde_json::from_str(&existing)
+                    .with_context(|| format!("Invalid compilation database `{}`", self.output))?;
+                merge_entries(existing.into_iter().chain(entries))
+            }
+            _ => entries,
+        };
+        fs_util::write(&output, serde_json::to_string_pretty(&entries)? + "\n")?;
+        buck2_client_ctx::eprintln!("Wrote {} entries to {}", entries.len(), self.output)?;
+
+        ExitResult::success()
+    }
+
+    fn console_opts(&self) -> &CommonConsoleOptions {
+        &self.common_opts.console_opts
+    }
+
+    fn event_log_opts(&self) -> &CommonDaemonCommandOptions {
+        &self.common_opts.event_log_opts
+    }
+
+    fn common_opts(&self) -> &CommonBuildConfigurationOptions {
+        &self.common_opts.config_opts
+    }
+}
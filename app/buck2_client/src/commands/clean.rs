@@ -15,6 +15,7 @@ use anyhow::Context;
 use buck2_client_ctx::client_ctx::ClientCommandContext;
 use buck2_client_ctx::common::CommonCommandOptions;
 use buck2_client_ctx::common::CommonDaemonCommandOptions;
+use buck2_client_ctx::daemon::client::active_commands::check_no_active_commands;
 use buck2_client_ctx::daemon::client::connect::BuckdConnectOptions;
 use buck2_client_ctx::daemon::client::BuckdLifecycleLock;
 use buck2_client_ctx::exit_result::ExitResult;
@@ -36,7 +37,8 @@ use crate::commands::clean_stale::CleanStaleCommand;
 
 /// Delete generated files and caches.
 ///
-/// The command also kills the buck2 daemon.
+/// The command also kills the buck2 daemon. If the daemon is running other commands, they are
+/// listed and nothing is deleted unless `--force` is passed.
 #[derive(Debug, clap::Parser)]
 pub struct CleanCommand {
     #[clap(flatten)]
@@ -62,6 +64,12 @@ the specified duration, without killing the daemon",
 
     #[clap(long = "tracked-only", requires = "stale")]
     tracked_only: bool,
+
+    #[clap(
+        long,
+        help = "Kill the daemon and delete the files even if the daemon is running other commands"
+    )]
+    force: bool,
 }
 
 impl CleanCommand {
@@ -106,6 +114,10 @@ impl CleanCommand {
                 .connect_buckd(BuckdConnectOptions::existing_only_no_console())
                 .await
             {
+                if !self.force {
+                    let status = buckd.with_flushing().status(false, true).await?;
+                    check_no_active_commands("clean", &status.active_commands)?;
+                }
                 buckd
                     .with_flushing()
                     .kill("`buck2 clean` was invoked")
@@ -117,7 +129,7 @@ impl CleanCommand {
     }
 }
 
-async fn clean(
+pub(crate) async fn clean(
     buck_out_dir: AbsNormPathBuf,
     daemon_dir: DaemonDir,
     console: &FinalConsole,
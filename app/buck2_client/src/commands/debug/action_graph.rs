@@ -0,0 +1,131 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use async_trait::async_trait;
+use buck2_cli_proto::AqueryRequest;
+use buck2_cli_proto::QueryOutputFormat;
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::common::CommonBuildConfigurationOptions;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_client_ctx::common::CommonConsoleOptions;
+use buck2_client_ctx::common::CommonDaemonCommandOptions;
+use buck2_client_ctx::daemon::client::BuckdClientConnector;
+use buck2_client_ctx::daemon::client::StdoutPartialResultHandler;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::streaming::StreamingCommand;
+
+/// Prints the configured action graph of targets as JSON.
+///
+/// Each action is printed with its owner, kind, category, identifier, input and output
+/// paths, the keys of the actions it depends on, and its attributes (including the executor
+/// configuration). Actions are sorted by key, so the output is stable across invocations.
+#[derive(Debug, clap::Parser)]
+pub struct ActionGraphCommand {
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    /// Targets whose actions to print
+    #[clap(value_name = "TARGET", required = true)]
+    targets: Vec<String>,
+
+    /// Only follow action dependencies up to this depth (unlimited by default)
+    #[clap(long)]
+    depth: Option<u32>,
+
+    /// Only print actions of these categories
+    #[clap(long, value_name = "CATEGORY")]
+    category: Vec<String>,
+}
+
+impl ActionGraphCommand {
+    fn query(&self) -> String {
+        let targets = self
+            .targets
+            .iter()
+            .map(|t| format!("\"{}\"", t))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut query = match self.depth {
+            Some(depth) => format!("deps(set({}), {})", targets, depth),
+            None => format!("deps(set({}))", targets),
+        };
+        if !self.category.is_empty() {
+            // Categories are matched literally, not as regexes.
+            let categories = self
+                .category
+                .iter()
+                .map(|c| regex::escape(c))
+                .collect::<Vec<_>>()
+                .join("|");
+            query = format!(
+                "attrregexfilter(category, \"^({})$\", {})",
+                categories, query
+            );
+        }
+        query
+    }
+}
+
+#[async_trait]
+impl StreamingCommand for ActionGraphCommand {
+    const COMMAND_NAME: &'static str = "action-graph";
+
+    async fn exec_impl(
+        self,
+        buckd: &mut BuckdClientConnector,
+        matches: &clap::ArgMatches,
+        ctx: &mut ClientCommandContext<'_>,
+    ) -> ExitResult {
+        let query = self.query();
+        let context = ctx.client_context(
+            &self.common_opts.config_opts,
+            matches,
+            self.sanitized_argv(),
+        )?;
+
+        let response = buckd
+            .with_flushing()
+            .aquery(
+                AqueryRequest {
+                    query,
+                    query_args: Vec::new(),
+                    context: Some(context),
+                    output_attributes: Vec::new(),
+                    unstable_output_format: QueryOutputFormat::Default as i32,
+                    action_graph: true,
+                },
+                ctx.stdin()
+                    .console_interaction_stream(&self.common_opts.console_opts),
+                &mut StdoutPartialResultHandler,
+            )
+            .await??;
+
+        for message in &response.error_messages {
+            buck2_client_ctx::eprintln!("{}", message)?;
+        }
+
+        if !response.error_messages.is_empty() {
+            ExitResult::failure()
+        } else {
+            ExitResult::success()
+        }
+    }
+
+    fn console_opts(&self) -> &CommonConsoleOptions {
+        &self.common_opts.console_opts
+    }
+
+    fn event_log_opts(&self) -> &CommonDaemonCommandOptions {
+        &self.common_opts.event_log_opts
+    }
+
+    fn common_opts(&self) -> &CommonBuildConfigurationOptions {
+        &self.common_opts.config_opts
+    }
+}
@@ -0,0 +1,112 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use async_trait::async_trait;
+use buck2_cli_proto::dice_equality_check_request::DiceKeyKind;
+use buck2_cli_proto::DiceEqualityCheckRequest;
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::common::CommonBuildConfigurationOptions;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_client_ctx::common::CommonConsoleOptions;
+use buck2_client_ctx::common::CommonDaemonCommandOptions;
+use buck2_client_ctx::daemon::client::BuckdClientConnector;
+use buck2_client_ctx::daemon::client::StdoutPartialResultHandler;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::streaming::StreamingCommand;
+use dupe::Dupe;
+
+#[derive(Debug, Clone, Copy, Dupe, clap::ArgEnum)]
+#[clap(rename_all = "kebab-case")]
+enum KeyKindArg {
+    /// The configured target node of a target (argument: the target).
+    ConfiguredTargetNode,
+    /// The result of evaluating a build file (argument: the package, e.g. `//foo:`).
+    InterpreterResults,
+    /// The execution platforms (no argument).
+    ExecutionPlatforms,
+}
+
+impl KeyKindArg {
+    fn to_proto(self) -> DiceKeyKind {
+        match self {
+            KeyKindArg::ConfiguredTargetNode => DiceKeyKind::ConfiguredTargetNode,
+            KeyKindArg::InterpreterResults => DiceKeyKind::InterpreterResults,
+            KeyKindArg::ExecutionPlatforms => DiceKeyKind::ExecutionPlatforms,
+        }
+    }
+}
+
+/// Recomputes a DICE key and compares the result with its cached value.
+///
+/// Reports whether the two values are the same allocation, whether the key considers them equal,
+/// and whether their debug representations are equal. When the values differ, prints a diff of
+/// the fields which changed. Use this to find out why a key invalidates its dependents even when
+/// nothing it depends on has changed.
+#[derive(Debug, clap::Parser)]
+pub struct DiceEqualityCheckCommand {
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    /// The kind of key to check
+    #[clap(arg_enum, value_name = "KIND")]
+    kind: KeyKindArg,
+
+    /// The target or package identifying the key
+    #[clap(value_name = "KEY")]
+    key: Option<String>,
+}
+
+#[async_trait]
+impl StreamingCommand for DiceEqualityCheckCommand {
+    const COMMAND_NAME: &'static str = "dice-equality-check";
+
+    fn existing_only() -> bool {
+        true
+    }
+
+    async fn exec_impl(
+        self,
+        buckd: &mut BuckdClientConnector,
+        matches: &clap::ArgMatches,
+        ctx: &mut ClientCommandContext<'_>,
+    ) -> ExitResult {
+        let context = ctx.client_context(
+            &self.common_opts.config_opts,
+            matches,
+            self.sanitized_argv(),
+        )?;
+        buckd
+            .with_flushing()
+            .dice_equality_check(
+                DiceEqualityCheckRequest {
+                    context: Some(context),
+                    kind: self.kind.to_proto().into(),
+                    argument: self.key.unwrap_or_default(),
+                },
+                ctx.stdin()
+                    .console_interaction_stream(&self.common_opts.console_opts),
+                &mut StdoutPartialResultHandler,
+            )
+            .await??;
+
+        ExitResult::success()
+    }
+
+    fn console_opts(&self) -> &CommonConsoleOptions {
+        &self.common_opts.console_opts
+    }
+
+    fn event_log_opts(&self) -> &CommonDaemonCommandOptions {
+        &self.common_opts.event_log_opts
+    }
+
+    fn common_opts(&self) -> &CommonBuildConfigurationOptions {
+        &self.common_opts.config_opts
+    }
+}
@@ -29,6 +29,11 @@ pub struct FileStatusCommand {
     /// Paths to validate
     #[clap(value_name = "PATH", required = true)]
     paths: Vec<PathArg>,
+
+    /// Invalidate the daemon's view of any paths that disagree with the filesystem,
+    /// so they are re-read on the next command.
+    #[clap(long)]
+    fix: bool,
 }
 
 #[async_trait]
@@ -58,6 +63,7 @@ impl StreamingCommand for FileStatusCommand {
                     paths: self
                         .paths
                         .try_map(|x| x.resolve(&ctx.working_dir).into_string())?,
+                    fix: self.fix,
                 },
                 ctx.stdin()
                     .console_interaction_stream(&self.common_opts.console_opts),
@@ -0,0 +1,220 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::fmt;
+use std::fmt::Display;
+
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::stream_value::StreamValue;
+use buck2_client_ctx::subscribers::event_log::file_names::retrieve_all_logs;
+use buck2_client_ctx::tokio_runtime_setup::client_tokio_runtime;
+use buck2_event_observer::display;
+use buck2_event_observer::display::TargetDisplayOptions;
+use tokio_stream::StreamExt;
+
+/// Find the actions which produced an output with the given digest, or at the given path.
+///
+/// This searches the event logs of recent commands (the same ones `buck2 log` can show), newest
+/// first, so it can only find outputs of actions that ran (or were cache hits) in those commands.
+#[derive(Debug, clap::Parser)]
+pub struct FindArtifactCommand {
+    /// The digest to look for, either as `hash` or `hash:size`. Only the digests of the outputs
+    /// themselves are known, so files inside directory outputs must be looked up with `--path`.
+    #[clap(value_name = "DIGEST", required_unless_present = "path")]
+    digest: Option<String>,
+
+    /// Look for the output at this path (relative to the project root) instead, or for the
+    /// directory output containing it.
+    #[clap(long, value_name = "PATH", conflicts_with = "digest")]
+    path: Option<String>,
+
+    /// Stop after the first match.
+    #[clap(long)]
+    first: bool,
+}
+
+/// What to look for in the outputs of actions.
+#[derive(Debug)]
+enum Query {
+    /// A digest as `hash` (matching any size) or `hash:size`.
+    Digest(String),
+    /// A path, which matches the output at that path or the directory output containing it.
+    Path(String),
+}
+
+impl Query {
+    fn new(digest: Option<String>, path: Option<String>) -> anyhow::Result<Self> {
+        match (digest, path) {
+            (Some(digest), None) => Ok(Self::Digest(digest.to_lowercase())),
+            (None, Some(path)) => Ok(Self::Path(
+                path.trim_start_matches("./")
+                    .trim_end_matches('/')
+                    .to_owned(),
+            )),
+            _ => Err(anyhow::anyhow!(
+                "Expected exactly one of a digest or `--path`"
+            )),
+        }
+    }
+
+    fn matches(&self, output: &buck2_data::ActionOutput) -> bool {
+        match self {
+            Self::Digest(wanted) => {
+                if wanted.contains(':') {
+                    output.digest == *wanted
+                } else {
+                    output.digest.split(':').next() == Some(wanted.as_str())
+                }
+            }
+            Self::Path(wanted) => match wanted.strip_prefix(output.path.as_str()) {
+                Some(rest) => rest.is_empty() || rest.starts_with('/'),
+                None => false,
+            },
+        }
+    }
+}
+
+impl Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Digest(digest) => write!(f, "digest `{}`", digest),
+            Self::Path(path) => write!(f, "path `{}`", path),
+        }
+    }
+}
+
+impl FindArtifactCommand {
+    pub fn exec(self, _matches: &clap::ArgMatches, ctx: ClientCommandContext<'_>) -> ExitResult {
+        let Self {
+            digest,
+            path,
+            first,
+        } = self;
+
+        let query = Query::new(digest, path)?;
+
+        let rt = client_tokio_runtime()?;
+
+        let found = rt.block_on(async {
+            let mut logs = retrieve_all_logs(&ctx)?;
+            logs.reverse(); // newest first
+
+            let mut found = 0;
+            for log_path in logs {
+                let (invocation, mut events) = match log_path.unpack_stream().await {
+                    Ok(v) => v,
+                    Err(e) => {
+                        tracing::debug!("Skipping unreadable event log: {:#}", e);
+                        continue;
+                    }
+                };
+
+                // Logs of commands that were interrupted may be truncated, so stop reading at the
+                // first error rather than failing the whole search.
+                while let Ok(Some(event)) = events.try_next().await {
+                    let event = match event {
+                        StreamValue::Event(event) => event,
+                        _ => continue,
+                    };
+                    let action = match event.data {
+                        Some(buck2_data::buck_event::Data::SpanEnd(buck2_data::SpanEndEvent {
+                            data: Some(buck2_data::span_end_event::Data::ActionExecution(action)),
+                            ..
+                        })) => action,
+                        _ => continue,
+                    };
+                    for output in &action.outputs {
+                        if !query.matches(output) {
+                            continue;
+                        }
+                        let action_str = display::display_action_identity(
+                            action.key.as_ref(),
+                            action.name.as_ref(),
+                            TargetDisplayOptions::for_log(),
+                        )?;
+                        buck2_client_ctx::println!(
+                            "{}\t{}\t{}\t{}\t{}",
+                            output.digest,
+                            action_str,
+                            output.path,
+                            invocation.trace_id,
+                            invocation.display_command_line()
+                        )?;
+                        found += 1;
+                        if first {
+                            return anyhow::Ok(found);
+                        }
+                    }
+                }
+            }
+
+            anyhow::Ok(found)
+        })?;
+
+        if found == 0 {
+            buck2_client_ctx::eprintln!("No output with {} in recent event logs", query)?;
+            ExitResult::failure()
+        } else {
+            ExitResult::success()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::commands::debug::find_artifact::Query;
+
+    fn output(digest: &str, path: &str) -> buck2_data::ActionOutput {
+        buck2_data::ActionOutput {
+            tiny_digest: String::new(),
+            digest: digest.to_owned(),
+            path: path.to_owned(),
+        }
+    }
+
+    fn digest(digest: &str) -> Query {
+        Query::new(Some(digest.to_owned()), None).unwrap()
+    }
+
+    fn path(path: &str) -> Query {
+        Query::new(None, Some(path.to_owned())).unwrap()
+    }
+
+    #[test]
+    fn test_query_digest() {
+        let out = output("abc123:10", "buck-out/gen/pkg/out");
+        assert!(digest("abc123").matches(&out));
+        assert!(digest("ABC123").matches(&out));
+        assert!(digest("abc123:10").matches(&out));
+        assert!(!digest("abc123:11").matches(&out));
+        assert!(!digest("abc").matches(&out));
+    }
+
+    #[test]
+    fn test_query_path() {
+        let file = output("abc:1", "buck-out/gen/pkg/out.txt");
+        assert!(path("buck-out/gen/pkg/out.txt").matches(&file));
+        assert!(path("./buck-out/gen/pkg/out.txt").matches(&file));
+        assert!(!path("buck-out/gen/pkg/out").matches(&file));
+        assert!(!path("buck-out/gen/pkg").matches(&file));
+
+        let dir = output("def:2", "buck-out/gen/pkg/dir");
+        assert!(path("buck-out/gen/pkg/dir").matches(&dir));
+        assert!(path("buck-out/gen/pkg/dir/").matches(&dir));
+        assert!(path("buck-out/gen/pkg/dir/sub/file").matches(&dir));
+        assert!(!path("buck-out/gen/pkg/dir2/file").matches(&dir));
+    }
+
+    #[test]
+    fn test_query_needs_digest_or_path() {
+        assert!(Query::new(None, None).is_err());
+        assert!(Query::new(Some("abc".to_owned()), Some("out".to_owned())).is_err());
+    }
+}
@@ -0,0 +1,109 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use async_trait::async_trait;
+use buck2_audit::deferred_materializer::DeferredMaterializerCommand;
+use buck2_audit::deferred_materializer::DeferredMaterializerSubcommand;
+use buck2_audit::AuditCommand;
+use buck2_cli_proto::GenericRequest;
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::common::CommonBuildConfigurationOptions;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_client_ctx::common::CommonConsoleOptions;
+use buck2_client_ctx::common::CommonDaemonCommandOptions;
+use buck2_client_ctx::daemon::client::BuckdClientConnector;
+use buck2_client_ctx::daemon::client::StdoutPartialResultHandler;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::streaming::StreamingCommand;
+
+#[derive(Debug, clap::Parser)]
+pub struct MaterializerCommand {
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    #[clap(subcommand)]
+    subcommand: MaterializerSubcommand,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum MaterializerSubcommand {
+    /// Check that the deferred materializer state matches what is on disk in `buck-out`.
+    ///
+    /// Prints the artifacts which don't match, and fails if any of them are left unrepaired.
+    Verify {
+        /// Drop mismatching artifacts from the materializer state and delete them from disk,
+        /// so they are materialized again the next time they are needed. Files and symlinks in
+        /// use by the running daemon are materialized again right away.
+        #[clap(long)]
+        repair: bool,
+    },
+}
+
+#[async_trait]
+impl StreamingCommand for MaterializerCommand {
+    const COMMAND_NAME: &'static str = "materializer";
+
+    fn existing_only() -> bool {
+        true
+    }
+
+    async fn exec_impl(
+        self,
+        buckd: &mut BuckdClientConnector,
+        matches: &clap::ArgMatches,
+        ctx: &mut ClientCommandContext<'_>,
+    ) -> ExitResult {
+        let context = ctx.client_context(
+            &self.common_opts.config_opts,
+            matches,
+            self.sanitized_argv(),
+        )?;
+
+        let subcommand = match self.subcommand {
+            MaterializerSubcommand::Verify { repair } => {
+                DeferredMaterializerSubcommand::Verify { repair }
+            }
+        };
+
+        // This is implemented by the `audit deferred-materializer` command on the daemon.
+        let serialized_opts = serde_json::to_string(&AuditCommand::DeferredMaterializer(
+            DeferredMaterializerCommand {
+                common_opts: Default::default(),
+                subcommand,
+            },
+        ))?;
+
+        buckd
+            .with_flushing()
+            .audit(
+                GenericRequest {
+                    context: Some(context),
+                    serialized_opts,
+                },
+                ctx.stdin()
+                    .console_interaction_stream(&self.common_opts.console_opts),
+                &mut StdoutPartialResultHandler,
+            )
+            .await??;
+
+        ExitResult::success()
+    }
+
+    fn console_opts(&self) -> &CommonConsoleOptions {
+        &self.common_opts.console_opts
+    }
+
+    fn event_log_opts(&self) -> &CommonDaemonCommandOptions {
+        &self.common_opts.event_log_opts
+    }
+
+    fn common_opts(&self) -> &CommonBuildConfigurationOptions {
+        &self.common_opts.config_opts
+    }
+}
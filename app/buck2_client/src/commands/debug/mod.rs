@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use action_graph::ActionGraphCommand;
 use allocator_stats::AllocatorStatsCommand;
 use buck2_client_ctx::client_ctx::ClientCommandContext;
 use buck2_client_ctx::exit_result::ExitResult;
@@ -14,11 +15,14 @@ use buck2_client_ctx::streaming::BuckSubcommand;
 use chrome_trace::ChromeTraceCommand;
 use crash::CrashCommand;
 use dice_dump::DiceDumpCommand;
+use dice_equality_check::DiceEqualityCheckCommand;
 use file_status::FileStatusCommand;
+use find_artifact::FindArtifactCommand;
 use flush_dep_files::FlushDepFilesCommand;
 use heap_dump::HeapDumpCommand;
 use internal_version::InternalVersionCommand;
 use materialize::MaterializeCommand;
+use materializer::MaterializerCommand;
 use replay::ReplayCommand;
 
 use crate::commands::debug::allocative::AllocativeCommand;
@@ -33,19 +37,23 @@ use crate::commands::debug::upload_re_logs::UploadReLogsCommand;
 use crate::commands::log::debug_last_log::DebugLastLogCommand;
 use crate::commands::log::debug_what_ran::DebugWhatRanCommand;
 
+mod action_graph;
 mod allocative;
 mod allocator_stats;
 mod chrome_trace;
 mod crash;
 mod daemon_dir;
 mod dice_dump;
+mod dice_equality_check;
 mod exe;
 mod file_status;
+mod find_artifact;
 mod flush_dep_files;
 mod heap_dump;
 mod internal_version;
 mod log_perf;
 mod materialize;
+mod materializer;
 mod persist_event_logs;
 pub mod replay;
 mod segfault;
@@ -68,6 +76,8 @@ pub enum DebugCommand {
     AllocatorStats(AllocatorStatsCommand),
     /// Dump the DICE graph to a file and saves it to disk.
     DiceDump(DiceDumpCommand),
+    /// Recomputes a DICE key and compares the result with the cached value.
+    DiceEqualityCheck(DiceEqualityCheckCommand),
     /// Replay a previous command by reading off from an event log.
     ///
     /// This does not interact (or even launch) a daemon.
@@ -81,10 +91,16 @@ pub enum DebugCommand {
     FlushDepFiles(FlushDepFilesCommand),
     /// Forces materialization of a path, even on the deferred materializer
     Materialize(MaterializeCommand),
+    /// Inspect and repair the state of the deferred materializer.
+    Materializer(MaterializerCommand),
     // Upload RE logs given an RE session ID
     UploadReLogs(UploadReLogsCommand),
     /// Validates that Buck2 and disk agree on the state of files.
     FileStatus(FileStatusCommand),
+    /// Finds the actions that produced an output with a given digest in recent builds.
+    FindArtifact(FindArtifactCommand),
+    /// Prints the configured action graph of targets as JSON.
+    ActionGraph(ActionGraphCommand),
     /// Shows the commands that buck ran
     #[clap(alias = "whatran", setting(clap::AppSettings::Hidden))]
     WhatRan(DebugWhatRanCommand),
@@ -110,6 +126,7 @@ impl DebugCommand {
         let matches = matches.subcommand().expect("subcommand not found").1;
         match self {
             DebugCommand::DiceDump(cmd) => cmd.exec(matches, ctx),
+            DebugCommand::DiceEqualityCheck(cmd) => cmd.exec(matches, ctx),
             DebugCommand::Crash(cmd) => cmd.exec(matches, ctx),
             DebugCommand::HeapDump(cmd) => cmd.exec(matches, ctx),
             DebugCommand::AllocatorStats(cmd) => cmd.exec(matches, ctx),
@@ -121,12 +138,15 @@ impl DebugCommand {
             DebugCommand::WhatRan(cmd) => cmd.exec(matches, ctx),
             DebugCommand::LastLog(cmd) => cmd.exec(matches, ctx),
             DebugCommand::Materialize(cmd) => cmd.exec(matches, ctx),
+            DebugCommand::Materializer(cmd) => cmd.exec(matches, ctx),
             DebugCommand::UploadReLogs(cmd) => cmd.exec(matches, ctx),
             DebugCommand::DaemonDir(cmd) => cmd.exec(matches, ctx),
             DebugCommand::Exe(cmd) => cmd.exec(matches, ctx),
             DebugCommand::Allocative(cmd) => cmd.exec(matches, ctx),
             DebugCommand::SetLogFilter(cmd) => cmd.exec(matches, ctx),
             DebugCommand::FileStatus(cmd) => cmd.exec(matches, ctx),
+            DebugCommand::FindArtifact(cmd) => cmd.exec(matches, ctx),
+            DebugCommand::ActionGraph(cmd) => cmd.exec(matches, ctx),
             DebugCommand::LogPerf(cmd) => cmd.exec(matches, ctx),
             DebugCommand::TraceIo(cmd) => cmd.exec(matches, ctx),
             DebugCommand::PersistEventLogs(cmd) => cmd.exec(matches, ctx),
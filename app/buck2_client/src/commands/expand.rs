@@ -0,0 +1,126 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use async_trait::async_trait;
+use buck2_cli_proto::targets_request;
+use buck2_cli_proto::targets_request::OutputFormat;
+use buck2_cli_proto::TargetsRequest;
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::common::CommonBuildConfigurationOptions;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_client_ctx::common::CommonConsoleOptions;
+use buck2_client_ctx::common::CommonDaemonCommandOptions;
+use buck2_client_ctx::daemon::client::BuckdClientConnector;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::streaming::StreamingCommand;
+use gazebo::prelude::*;
+
+use crate::commands::targets::targets;
+
+/// Print the targets declared by build files after macro expansion.
+///
+/// Each target is printed as the rule call that would declare it directly, without any macros,
+/// but before configuration (so `select()` is preserved). Use `//foo:` to expand a whole build
+/// file, or `//foo:bar` to expand a single target. Pass `--stack` to print the call stack of
+/// the macros which declared each target as comments above it.
+#[derive(Debug, clap::Parser)]
+#[clap(name = "expand")]
+pub struct ExpandCommand {
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    /// Print targets as JSON rather than Starlark
+    #[clap(long)]
+    json: bool,
+
+    /// Also print attributes which were not set explicitly, and use the default declared by
+    /// the rule.
+    #[clap(long)]
+    include_defaults: bool,
+
+    /// Patterns to expand
+    #[clap(name = "TARGET_PATTERNS", required = true)]
+    patterns: Vec<String>,
+}
+
+#[async_trait]
+impl StreamingCommand for ExpandCommand {
+    const COMMAND_NAME: &'static str = "expand";
+
+    async fn exec_impl(
+        self,
+        buckd: &mut BuckdClientConnector,
+        matches: &clap::ArgMatches,
+        ctx: &mut ClientCommandContext<'_>,
+    ) -> ExitResult {
+        let context = Some(ctx.client_context(
+            &self.common_opts.config_opts,
+            matches,
+            self.sanitized_argv(),
+        )?);
+
+        let output_format = if self.json {
+            OutputFormat::Json
+        } else {
+            OutputFormat::Starlark
+        };
+
+        let target_request = TargetsRequest {
+            context,
+            target_patterns: self.patterns.map(|pat| buck2_data::TargetPattern {
+                value: pat.to_owned(),
+            }),
+            output_format: output_format as i32,
+            targets: Some(targets_request::Targets::Other(targets_request::Other {
+                include_default_attributes: self.include_defaults,
+                cached: true,
+                ..Default::default()
+            })),
+            output: None,
+            concurrency: None,
+        };
+
+        targets(
+            ctx.stdin(),
+            buckd,
+            target_request,
+            &self.common_opts.console_opts,
+        )
+        .await
+    }
+
+    fn console_opts(&self) -> &CommonConsoleOptions {
+        &self.common_opts.console_opts
+    }
+
+    fn event_log_opts(&self) -> &CommonDaemonCommandOptions {
+        &self.common_opts.event_log_opts
+    }
+
+    fn common_opts(&self) -> &CommonBuildConfigurationOptions {
+        &self.common_opts.config_opts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use crate::commands::expand::ExpandCommand;
+
+    #[test]
+    fn test_stack() {
+        let cmd = ExpandCommand::try_parse_from(["expand", "--stack", "//foo:"]).unwrap();
+        assert!(cmd.common_opts.config_opts.target_call_stacks);
+        assert_eq!(cmd.patterns, vec!["//foo:".to_owned()]);
+
+        let cmd = ExpandCommand::try_parse_from(["expand", "//foo:"]).unwrap();
+        assert!(!cmd.common_opts.config_opts.target_call_stacks);
+    }
+}
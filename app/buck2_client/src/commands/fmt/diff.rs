@@ -0,0 +1,216 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Unified diffs between the contents of a file and its formatted contents.
+
+use std::fmt::Write;
+
+/// Lines of context around each change.
+const CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Shortest edit script from `old` to `new` (Myers' algorithm), as one edit per line.
+fn edits(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    let n = old.len() as isize;
+    let m = new.len() as isize;
+    let max = n + m;
+    let offset = max + 1;
+    let mut v = vec![0isize; 2 * max as usize + 3];
+    let mut trace = Vec::new();
+
+    'search: for d in 0..=max {
+        trace.push(v.clone());
+        let mut k = -d;
+        while k <= d {
+            let i = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[i - 1] < v[i + 1]) {
+                v[i + 1]
+            } else {
+                v[i - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[i] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+            k += 2;
+        }
+    }
+
+    let mut result = Vec::new();
+    let mut x = n;
+    let mut y = m;
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let at = |k: isize| v[(k + offset) as usize];
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            result.push(Edit::Equal);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            result.push(if x == prev_x {
+                Edit::Insert
+            } else {
+                Edit::Delete
+            });
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    result.reverse();
+    result
+}
+
+fn push_line(out: &mut String, prefix: char, line: &str) {
+    out.push(prefix);
+    out.push_str(line);
+    if !line.ends_with('\n') {
+        out.push_str("\n\\ No newline at end of file\n");
+    }
+}
+
+/// Unified diff from `old` to `new`, or an empty string if they are the same.
+pub(crate) fn unified_diff(path: &str, old: &str, new: &str) -> String {
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+    let edits = edits(&old_lines, &new_lines);
+
+    let changes: Vec<usize> = edits
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| **e != Edit::Equal)
+        .map(|(i, _)| i)
+        .collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    // Group changes which are close enough to share their context into hunks.
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &change in &changes {
+        let start = change.saturating_sub(CONTEXT);
+        let end = (change + CONTEXT + 1).min(edits.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut out = String::new();
+    writeln!(out, "--- a/{}", path).unwrap();
+    writeln!(out, "+++ b/{}", path).unwrap();
+    // Position of each edit in the old and new lines.
+    let mut positions = Vec::with_capacity(edits.len() + 1);
+    let (mut old_pos, mut new_pos) = (0, 0);
+    for edit in &edits {
+        positions.push((old_pos, new_pos));
+        match edit {
+            Edit::Equal => {
+                old_pos += 1;
+                new_pos += 1;
+            }
+            Edit::Delete => old_pos += 1,
+            Edit::Insert => new_pos += 1,
+        }
+    }
+    positions.push((old_pos, new_pos));
+
+    for (start, end) in hunks {
+        let (old_start, new_start) = positions[start];
+        let (old_end, new_end) = positions[end];
+        let old_count = old_end - old_start;
+        let new_count = new_end - new_start;
+        writeln!(
+            out,
+            "@@ -{},{} +{},{} @@",
+            if old_count == 0 {
+                old_start
+            } else {
+                old_start + 1
+            },
+            old_count,
+            if new_count == 0 {
+                new_start
+            } else {
+                new_start + 1
+            },
+            new_count
+        )
+        .unwrap();
+        for (edit, &(o, n)) in edits[start..end].iter().zip(&positions[start..end]) {
+            match edit {
+                Edit::Equal => push_line(&mut out, ' ', old_lines[o]),
+                Edit::Delete => push_line(&mut out, '-', old_lines[o]),
+                Edit::Insert => push_line(&mut out, '+', new_lines[n]),
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::commands::fmt::diff::unified_diff;
+
+    #[test]
+    fn test_unified_diff() {
+        assert_eq!("", unified_diff("BUCK", "a\nb\n", "a\nb\n"));
+        assert_eq!(
+            indoc!(
+                "
+                --- a/BUCK
+                +++ b/BUCK
+                @@ -1,6 +1,6 @@
+                 1
+                 2
+                -x
+                +y
+                 4
+                 5
+                 6
+                @@ -8,3 +8,4 @@
+                 8
+                 9
+                 10
+                +11
+                "
+            ),
+            unified_diff(
+                "BUCK",
+                "1\n2\nx\n4\n5\n6\n7\n8\n9\n10\n",
+                "1\n2\ny\n4\n5\n6\n7\n8\n9\n10\n11\n"
+            )
+        );
+        assert_eq!(
+            "--- a/BUCK\n+++ b/BUCK\n@@ -1,1 +1,1 @@\n-a\n\\ No newline at end of file\n+a\n",
+            unified_diff("BUCK", "a", "a\n")
+        );
+    }
+}
@@ -0,0 +1,951 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Formatter for build files and `.bzl` files.
+//!
+//! Files are parsed with the Starlark parser, so syntax errors are reported as when evaluating
+//! them, and the formatted file must parse to the same syntax tree as the original one, so
+//! formatting never changes what a file does.
+//!
+//! The layout itself works on tokens, because the syntax tree doesn't keep comments: comments stay
+//! where they were written and only whitespace, quotes, trailing commas and the order of `load()`
+//! symbols change. It follows buildifier conventions where it can:
+//!
+//! * blocks are indented by `indent_width` spaces, and lines inside brackets by one more level
+//!   than the line which opened the bracket;
+//! * operators, `=` and commas are surrounded by single spaces, with no spaces inside brackets;
+//! * a comment following code on the same line is separated from it by two spaces;
+//! * strings use double quotes when that doesn't require escaping;
+//! * calls, lists and dicts whose closing bracket is on its own line get a trailing comma;
+//! * `load()` symbols are sorted;
+//! * at most one blank line is kept between lines, and the file ends with a single newline.
+
+use std::borrow::Cow;
+use std::str::FromStr;
+
+use buck2_common::legacy_configs::LegacyBuckConfig;
+use starlark::syntax::AstModule;
+use starlark::syntax::Dialect;
+
+#[derive(Debug, thiserror::Error)]
+enum FormatError {
+    #[error("Formatting would change the syntax tree of the file, this is a bug in `buck2 fmt`")]
+    ChangedSyntax,
+    #[error("Unterminated string starting on line {0}")]
+    UnterminatedString(usize),
+    #[error("Unexpected character `{1}` on line {0}")]
+    UnexpectedChar(usize, char),
+    #[error("Unbalanced `{1}` on line {0}")]
+    UnbalancedBracket(usize, String),
+    #[error("Unindent on line {0} does not match any outer indentation level")]
+    InconsistentDedent(usize),
+    #[error("Invalid `fmt.quote_style` `{0}`, expected `double` or `preserve`")]
+    InvalidQuoteStyle(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum QuoteStyle {
+    /// Use double quotes unless that would require escaping.
+    Double,
+    /// Keep the quotes the string was written with.
+    Preserve,
+}
+
+impl FromStr for QuoteStyle {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "double" => Ok(QuoteStyle::Double),
+            "preserve" => Ok(QuoteStyle::Preserve),
+            _ => Err(FormatError::InvalidQuoteStyle(s.to_owned()).into()),
+        }
+    }
+}
+
+/// Formatting options, configured per cell in the `[fmt]` section of the buckconfig.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FormatOptions {
+    pub(crate) indent_width: usize,
+    pub(crate) quote_style: QuoteStyle,
+    pub(crate) sort_loads: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            indent_width: 4,
+            quote_style: QuoteStyle::Double,
+            sort_loads: true,
+        }
+    }
+}
+
+impl FormatOptions {
+    pub(crate) fn from_config(config: &LegacyBuckConfig) -> anyhow::Result<Self> {
+        let default = FormatOptions::default();
+        Ok(FormatOptions {
+            indent_width: config
+                .parse("fmt", "indent_width")?
+                .unwrap_or(default.indent_width),
+            quote_style: config
+                .parse("fmt", "quote_style")?
+                .unwrap_or(default.quote_style),
+            sort_loads: config
+                .parse("fmt", "sort_loads")?
+                .unwrap_or(default.sort_loads),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+    /// Identifier, keyword or number.
+    Word,
+    Str,
+    /// Operator or punctuation.
+    Op,
+    Comment,
+    Newline,
+    /// Backslash at the end of a line.
+    Continuation,
+}
+
+#[derive(Debug, Clone)]
+struct Token<'a> {
+    kind: TokenKind,
+    text: Cow<'a, str>,
+    line: usize,
+    /// Column the token starts at. Only meaningful for the first token on a line.
+    col: usize,
+}
+
+impl<'a> Token<'a> {
+    fn new(kind: TokenKind, text: &'static str) -> Self {
+        Token {
+            kind,
+            text: Cow::Borrowed(text),
+            line: 0,
+            col: 0,
+        }
+    }
+
+    fn is_op(&self, op: &str) -> bool {
+        self.kind == TokenKind::Op && self.text == op
+    }
+
+    fn is_word(&self, word: &str) -> bool {
+        self.kind == TokenKind::Word && self.text == word
+    }
+
+    fn is_opener(&self) -> bool {
+        self.kind == TokenKind::Op && matches!(self.text.as_ref(), "(" | "[" | "{")
+    }
+
+    fn is_closer(&self) -> bool {
+        self.kind == TokenKind::Op && matches!(self.text.as_ref(), ")" | "]" | "}")
+    }
+
+    fn is_keyword(&self) -> bool {
+        self.kind == TokenKind::Word && KEYWORDS.contains(&self.text.as_ref())
+    }
+
+    /// Whether the token ends an operand, so that a following `(` or `[` is a call or an index.
+    fn is_operand(&self) -> bool {
+        match self.kind {
+            TokenKind::Word => !self.is_keyword(),
+            TokenKind::Str => true,
+            _ => self.is_closer(),
+        }
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "and", "as", "assert", "break", "class", "continue", "def", "del", "elif", "else", "except",
+    "finally", "for", "from", "global", "if", "import", "in", "is", "lambda", "nonlocal", "not",
+    "or", "pass", "raise", "return", "try", "while", "with", "yield",
+];
+
+const OPS: &[&str] = &[
+    "**=", "//=", "<<=", ">>=", "==", "!=", "<=", ">=", "//", "**", "->", "+=", "-=", "*=", "/=",
+    "%=", "&=", "|=", "^=", "<<", ">>", "+", "-", "*", "/", "%", "&", "|", "^", "~", "<", ">", "(",
+    ")", "[", "]", "{", "}", ",", ":", ";", ".", "=",
+];
+
+/// Find the end of the string literal whose opening quote is at `quote`.
+fn string_end(src: &str, quote: usize) -> Option<usize> {
+    let bytes = src.as_bytes();
+    let q = bytes[quote];
+    let triple = bytes.len() >= quote + 3 && bytes[quote + 1] == q && bytes[quote + 2] == q;
+    let mut i = quote + if triple { 3 } else { 1 };
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'\n' if !triple => return None,
+            c if c == q => {
+                if !triple {
+                    return Some(i + 1);
+                }
+                if bytes.len() >= i + 3 && bytes[i + 1] == q && bytes[i + 2] == q {
+                    return Some(i + 3);
+                }
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+fn tokenize(src: &str) -> anyhow::Result<Vec<Token<'_>>> {
+    let bytes = src.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let mut line = 1;
+    let mut col = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        let start = i;
+        let kind = match c {
+            b' ' | b'\r' | b'\x0c' => {
+                i += 1;
+                col += 1;
+                continue;
+            }
+            b'\t' => {
+                i += 1;
+                col = (col / 8 + 1) * 8;
+                continue;
+            }
+            b'\n' => {
+                i += 1;
+                TokenKind::Newline
+            }
+            b'\\'
+                if bytes.get(i + 1) == Some(&b'\n')
+                    || (bytes.get(i + 1) == Some(&b'\r') && bytes.get(i + 2) == Some(&b'\n')) =>
+            {
+                i += if bytes[i + 1] == b'\r' { 3 } else { 2 };
+                TokenKind::Continuation
+            }
+            b'#' => {
+                i = src[i..].find('\n').map_or(bytes.len(), |n| i + n);
+                TokenKind::Comment
+            }
+            b'"' | b'\'' => {
+                i = string_end(src, i).ok_or(FormatError::UnterminatedString(line))?;
+                TokenKind::Str
+            }
+            c if c.is_ascii_alphanumeric()
+                || c == b'_'
+                || (c == b'.' && bytes.get(i + 1).map_or(false, u8::is_ascii_digit)) =>
+            {
+                let number = !(c.is_ascii_alphabetic() || c == b'_');
+                i += 1;
+                while i < bytes.len() {
+                    let c = bytes[i];
+                    if c.is_ascii_alphanumeric() || c == b'_' || (number && c == b'.') {
+                        i += 1;
+                    } else if number
+                        && (c == b'+' || c == b'-')
+                        && matches!(bytes[i - 1], b'e' | b'E')
+                        && !src[start..i].starts_with("0x")
+                    {
+                        i += 1;
+                    } else {
+                        break;
+                    }
+                }
+                let word = &src[start..i];
+                if matches!(bytes.get(i), Some(b'"' | b'\''))
+                    && matches!(word.to_ascii_lowercase().as_str(), "r" | "b" | "rb" | "br")
+                {
+                    i = string_end(src, i).ok_or(FormatError::UnterminatedString(line))?;
+                    TokenKind::Str
+                } else {
+                    TokenKind::Word
+                }
+            }
+            _ => match OPS.iter().find(|op| src[i..].starts_with(*op)) {
+                Some(op) => {
+                    i += op.len();
+                    TokenKind::Op
+                }
+                None => {
+                    let c = src[i..].chars().next().unwrap();
+                    return Err(FormatError::UnexpectedChar(line, c).into());
+                }
+            },
+        };
+        let text = match kind {
+            TokenKind::Comment => src[start..i].trim_end(),
+            _ => &src[start..i],
+        };
+        tokens.push(Token {
+            kind,
+            text: Cow::Borrowed(text),
+            line,
+            col,
+        });
+        let newlines = src[start..i].matches('\n').count();
+        if newlines > 0 {
+            line += newlines;
+            col = 0;
+        } else {
+            col += i - start;
+        }
+    }
+    Ok(tokens)
+}
+
+/// Sort the symbols of every `load()` statement which doesn't contain comments.
+fn sort_loads(tokens: Vec<Token<'_>>) -> Vec<Token<'_>> {
+    let mut out = Vec::with_capacity(tokens.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        let statement_start = out
+            .last()
+            .map_or(true, |t: &Token| t.kind == TokenKind::Newline);
+        if statement_start
+            && tokens[i].is_word("load")
+            && tokens.get(i + 1).map_or(false, |t| t.is_op("("))
+        {
+            if let Some((sorted, end)) = sorted_load(&tokens, i) {
+                out.extend(sorted);
+                i = end;
+                continue;
+            }
+        }
+        out.push(tokens[i].clone());
+        i += 1;
+    }
+    out
+}
+
+/// The tokens of the `load()` starting at `start` with its symbols sorted, and the index
+/// of the token after it. `None` if the statement is not a simple `load()`.
+fn sorted_load<'a>(tokens: &[Token<'a>], start: usize) -> Option<(Vec<Token<'a>>, usize)> {
+    let mut items: Vec<Vec<Token<'a>>> = vec![Vec::new()];
+    let mut multiline = false;
+    let mut i = start + 2;
+    loop {
+        let token = tokens.get(i)?;
+        i += 1;
+        match token.kind {
+            TokenKind::Newline => multiline = true,
+            TokenKind::Comment | TokenKind::Continuation => return None,
+            _ if token.is_op(")") => break,
+            _ if token.is_op(",") => items.push(Vec::new()),
+            _ => items.last_mut().unwrap().push(token.clone()),
+        }
+    }
+    if items.last().map_or(false, Vec::is_empty) {
+        items.pop();
+    }
+
+    let symbols = items.split_off(1);
+    let module = items.pop()?;
+    if module.len() != 1 || module[0].kind != TokenKind::Str || symbols.is_empty() {
+        return None;
+    }
+    let mut keyed = Vec::with_capacity(symbols.len());
+    for symbol in symbols {
+        // Sort by the name the symbol is bound to in this file.
+        let key = match symbol.as_slice() {
+            [s] if s.kind == TokenKind::Str => {
+                s.text.trim_matches(|c| c == '"' || c == '\'').to_owned()
+            }
+            [name, eq, s]
+                if name.kind == TokenKind::Word && eq.is_op("=") && s.kind == TokenKind::Str =>
+            {
+                name.text.to_string()
+            }
+            _ => return None,
+        };
+        keyed.push((key, symbol));
+    }
+    keyed.sort_by(|a, b| a.0.cmp(&b.0));
+    keyed.dedup_by(|a, b| {
+        a.0 == b.0 && a.1.len() == b.1.len() && a.1.iter().zip(&b.1).all(|(x, y)| x.text == y.text)
+    });
+
+    let mut out = vec![tokens[start].clone(), tokens[start + 1].clone()];
+    let newline = Token::new(TokenKind::Newline, "\n");
+    let comma = Token::new(TokenKind::Op, ",");
+    if multiline {
+        out.push(newline.clone());
+    }
+    let count = keyed.len() + 1;
+    for (index, item) in std::iter::once(module)
+        .chain(keyed.into_iter().map(|(_, s)| s))
+        .enumerate()
+    {
+        out.extend(item);
+        if multiline {
+            out.push(comma.clone());
+            out.push(newline.clone());
+        } else if index + 1 < count {
+            out.push(comma.clone());
+        }
+    }
+    out.push(Token::new(TokenKind::Op, ")"));
+    Some((out, i))
+}
+
+fn matching_closer(opener: &str) -> &'static str {
+    match opener {
+        "(" => ")",
+        "[" => "]",
+        _ => "}",
+    }
+}
+
+/// Add trailing commas to calls, lists and dicts whose closing bracket is on its own line.
+fn add_trailing_commas(tokens: Vec<Token<'_>>) -> anyhow::Result<Vec<Token<'_>>> {
+    struct Open {
+        closer: &'static str,
+        /// Whether a trailing comma can be added without changing the meaning: not a
+        /// parenthesized expression, an index or a comprehension.
+        eligible: bool,
+        index: usize,
+    }
+
+    let mut out: Vec<Token> = Vec::with_capacity(tokens.len());
+    let mut open: Vec<Open> = Vec::new();
+    for token in tokens {
+        if token.is_opener() {
+            let after_operand = out.last().map_or(false, Token::is_operand);
+            open.push(Open {
+                closer: matching_closer(&token.text),
+                eligible: match token.text.as_ref() {
+                    "(" => after_operand,
+                    "[" => !after_operand,
+                    _ => true,
+                },
+                index: out.len(),
+            });
+        } else if token.is_word("for") {
+            if let Some(top) = open.last_mut() {
+                top.eligible = false;
+            }
+        } else if token.is_closer() {
+            let top = match open.pop() {
+                Some(top) if top.closer == token.text => top,
+                _ => {
+                    return Err(
+                        FormatError::UnbalancedBracket(token.line, token.text.to_string()).into(),
+                    );
+                }
+            };
+            let own_line = out.last().map_or(false, |t| t.kind == TokenKind::Newline);
+            if top.eligible && own_line {
+                let last = out
+                    .iter()
+                    .rposition(|t| !matches!(t.kind, TokenKind::Newline | TokenKind::Comment));
+                if let Some(last) = last {
+                    if last != top.index && !out[last].is_op(",") {
+                        out.insert(last + 1, Token::new(TokenKind::Op, ","));
+                    }
+                }
+            }
+        }
+        out.push(token);
+    }
+    if let Some(top) = open.last() {
+        let opener = &out[top.index];
+        return Err(FormatError::UnbalancedBracket(opener.line, opener.text.to_string()).into());
+    }
+    Ok(out)
+}
+
+fn normalize_quotes(text: &str, style: QuoteStyle) -> Cow<'_, str> {
+    if style == QuoteStyle::Preserve {
+        return Cow::Borrowed(text);
+    }
+    let quote = match text.find(|c| c == '"' || c == '\'') {
+        Some(quote) => quote,
+        None => return Cow::Borrowed(text),
+    };
+    let (prefix, literal) = text.split_at(quote);
+    if !literal.starts_with('\'') {
+        return Cow::Borrowed(text);
+    }
+    let quotes = if literal.starts_with("'''") { 3 } else { 1 };
+    let body = &literal[quotes..literal.len() - quotes];
+    if body.contains('"') || (body.contains('\\') && body.contains('\'')) {
+        return Cow::Borrowed(text);
+    }
+    let quotes = &"\"\"\""[..quotes];
+    Cow::Owned(format!("{}{}{}{}", prefix, quotes, body, quotes))
+}
+
+/// Whether `token` is a unary operator, given the last code token before it.
+fn is_unary(token: &Token, last_code: Option<&Token>) -> bool {
+    token.kind == TokenKind::Op
+        && matches!(token.text.as_ref(), "-" | "+" | "~" | "*" | "**")
+        && last_code.map_or(true, |t| {
+            (t.kind == TokenKind::Op && !t.is_closer()) || t.is_keyword()
+        })
+}
+
+fn needs_space(prev: &Token, prev_unary: bool, token: &Token, bracket: Option<&str>) -> bool {
+    if prev_unary {
+        return false;
+    }
+    if prev.is_opener() || prev.is_op(".") {
+        return false;
+    }
+    if token.kind == TokenKind::Op {
+        match token.text.as_ref() {
+            "," | ")" | "]" | "}" | ":" | ";" | "." => return false,
+            "(" | "[" => return !prev.is_operand(),
+            _ => {}
+        }
+    }
+    // Slices are written without spaces.
+    !(prev.is_op(":") && bracket == Some("["))
+}
+
+fn emit(tokens: &[Token], opts: &FormatOptions) -> anyhow::Result<String> {
+    struct Bracket<'t> {
+        opener: &'t str,
+        /// Indentation level of the line the bracket was opened on.
+        level: usize,
+        /// Indentation level of the lines inside the bracket.
+        inner_level: usize,
+    }
+
+    let mut out = String::new();
+    let mut line = String::new();
+    let mut line_has_tokens = false;
+    let mut blank_lines = 0;
+    // Original columns of the enclosing blocks.
+    let mut blocks = vec![0];
+    // Level of the current logical line, and of the current physical line.
+    let mut level = 0;
+    let mut line_level = 0;
+    let mut block_pending = false;
+    let mut continuation = false;
+    let mut brackets: Vec<Bracket> = Vec::new();
+    let mut prev: Option<&Token> = None;
+    let mut prev_unary = false;
+    let mut last_code: Option<&Token> = None;
+    let mut first_code: Option<&Token> = None;
+
+    for token in tokens {
+        match token.kind {
+            TokenKind::Newline => {
+                if line_has_tokens {
+                    out.push_str(line.trim_end());
+                    out.push('\n');
+                } else if !out.is_empty() {
+                    blank_lines += 1;
+                }
+                line.clear();
+                line_has_tokens = false;
+                prev = None;
+                if brackets.is_empty() && !continuation {
+                    if let Some(last) = last_code {
+                        block_pending = last.is_op(":");
+                    }
+                    last_code = None;
+                    first_code = None;
+                }
+                continuation = false;
+                continue;
+            }
+            TokenKind::Continuation => {
+                line.push_str(" \\");
+                out.push_str(&line);
+                out.push('\n');
+                line.clear();
+                line_has_tokens = false;
+                prev = None;
+                continuation = true;
+                continue;
+            }
+            _ => {}
+        }
+
+        if !line_has_tokens {
+            if blank_lines > 0 && !out.is_empty() {
+                out.push('\n');
+            }
+            blank_lines = 0;
+            line_level = if let Some(bracket) = brackets.last() {
+                if token.is_closer() {
+                    bracket.level
+                } else {
+                    bracket.inner_level
+                }
+            } else if continuation {
+                level + 1
+            } else if token.kind == TokenKind::Comment {
+                // Comments don't change the indentation: a comment after a line opening a block
+                // belongs to the block, others are aligned with the block they are in.
+                if block_pending {
+                    blocks.len()
+                } else {
+                    blocks.iter().filter(|c| **c <= token.col).count() - 1
+                }
+            } else {
+                let top = *blocks.last().unwrap();
+                if token.col > top {
+                    blocks.push(token.col);
+                } else if token.col < top {
+                    while token.col < *blocks.last().unwrap() {
+                        blocks.pop();
+                    }
+                    if token.col != *blocks.last().unwrap() {
+                        return Err(FormatError::InconsistentDedent(token.line).into());
+                    }
+                }
+                block_pending = false;
+                level = blocks.len() - 1;
+                level
+            };
+            line.push_str(&" ".repeat(line_level * opts.indent_width));
+            line_has_tokens = true;
+        } else if token.kind == TokenKind::Comment {
+            line.push_str("  ");
+        } else if let Some(prev) = prev {
+            if needs_space(prev, prev_unary, token, brackets.last().map(|b| b.opener)) {
+                line.push(' ');
+            }
+        }
+
+        match token.kind {
+            TokenKind::Str => line.push_str(&normalize_quotes(&token.text, opts.quote_style)),
+            _ => line.push_str(&token.text),
+        }
+
+        if token.is_opener() {
+            // Parameters of a `def` get a double indent, so they stand out from its body.
+            let def_params = brackets.is_empty() && first_code.map_or(false, |t| t.is_word("def"));
+            brackets.push(Bracket {
+                opener: &token.text,
+                level: line_level,
+                inner_level: line_level + if def_params { 2 } else { 1 },
+            });
+        } else if token.is_closer() {
+            brackets.pop();
+        }
+        if token.kind != TokenKind::Comment {
+            prev_unary = is_unary(token, last_code);
+            last_code = Some(token);
+            first_code.get_or_insert(token);
+        }
+        prev = Some(token);
+    }
+    if line_has_tokens {
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+
+    let len = out.trim_end_matches('\n').len();
+    out.truncate(len);
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Format the contents of a build file or a `.bzl` file. `path` is only used in error messages.
+pub(crate) fn format_starlark(
+    path: &str,
+    src: &str,
+    opts: &FormatOptions,
+) -> anyhow::Result<String> {
+    // Build files are a subset of `.bzl` files, so they can all be parsed with the same dialect.
+    let ast = AstModule::parse(path, src.to_owned(), &Dialect::Extended)?;
+
+    let mut tokens = tokenize(src)?;
+    if opts.sort_loads {
+        tokens = sort_loads(tokens);
+    }
+    let tokens = add_trailing_commas(tokens)?;
+    let formatted = emit(&tokens, opts)?;
+
+    let formatted_ast = AstModule::parse(path, formatted.clone(), &Dialect::Extended)
+        .map_err(|_| FormatError::ChangedSyntax)?;
+    if !ast.same_syntax(&formatted_ast) {
+        return Err(FormatError::ChangedSyntax.into());
+    }
+    Ok(formatted)
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::commands::fmt::format::format_starlark;
+    use crate::commands::fmt::format::FormatOptions;
+    use crate::commands::fmt::format::QuoteStyle;
+
+    fn format(src: &str) -> String {
+        let formatted = format_starlark("test.bzl", src, &FormatOptions::default()).unwrap();
+        assert_eq!(
+            formatted,
+            format_starlark("test.bzl", &formatted, &FormatOptions::default()).unwrap(),
+            "formatting is not idempotent"
+        );
+        formatted
+    }
+
+    #[test]
+    fn test_format_build_file() {
+        assert_eq!(
+            indoc!(
+                r#"
+                load("//rules:defs.bzl", "a_rule", "b_rule")
+
+                # Some library.
+                cc_library(
+                    name = "foo",
+                    srcs = glob(["*.c"]) + [
+                        "extra.c",  # generated
+                    ],
+                    deps = [":bar"],
+                    visibility = ["PUBLIC"],
+                )
+                "#
+            ),
+            format(indoc!(
+                r#"
+
+
+                load('//rules:defs.bzl', 'b_rule', 'a_rule')
+
+
+
+                # Some library.
+                cc_library(
+                  name='foo',
+                  srcs=glob(['*.c'])+[
+                     'extra.c' # generated
+                  ],
+                  deps = [ ':bar' ],
+                  visibility=["PUBLIC"]
+                )
+                "#
+            ))
+        );
+    }
+
+    #[test]
+    fn test_format_bzl_file() {
+        assert_eq!(
+            indoc!(
+                r#"
+                def _impl(ctx, *args, **kwargs):
+                    if not ctx.attrs.srcs:
+                        # Nothing to do.
+                        return []
+                    x = -1
+                    out = ctx.actions.declare_output(ctx.label.name + ".txt")
+                    return [DefaultInfo(default_output = out), x[1:], {"a": -x}]
+
+                my_rule = rule(impl = _impl, attrs = {})
+                "#
+            ),
+            format(indoc!(
+                r#"
+                def _impl(ctx,*args,**kwargs):
+                  if not ctx.attrs.srcs:
+                  # Nothing to do.
+                    return []
+                  x=- 1
+                  out=ctx.actions.declare_output(ctx.label.name+'.txt')
+                  return [DefaultInfo(default_output=out),x[1 : ],{'a' : -x}]
+
+                my_rule=rule(impl=_impl,attrs={})
+                "#
+            ))
+        );
+    }
+
+    #[test]
+    fn test_def_params() {
+        assert_eq!(
+            indoc!(
+                r#"
+                def _helper(
+                        ctx,
+                        name = None):
+                    pass
+                "#
+            ),
+            format(indoc!(
+                r#"
+                def _helper(
+                  ctx,
+                  name=None):
+                  pass
+                "#
+            ))
+        );
+    }
+
+    #[test]
+    fn test_no_trailing_comma_where_it_changes_meaning() {
+        let src = indoc!(
+            r#"
+            x = (
+                1 +
+                2
+            )
+            y = [
+                a
+                for a in b
+            ]
+            z = foo[
+                1
+            ]
+            "#
+        );
+        assert_eq!(src, format(src));
+    }
+
+    #[test]
+    fn test_strings_preserved() {
+        let src = indoc!(
+            r#"
+            a = 'it"s'
+            b = """
+              keep   this
+            """
+            c = r'\d'
+            "#
+        );
+        assert_eq!(
+            indoc!(
+                r#"
+                a = 'it"s'
+                b = """
+                  keep   this
+                """
+                c = r"\d"
+                "#
+            ),
+            format(src)
+        );
+        let preserve = FormatOptions {
+            quote_style: QuoteStyle::Preserve,
+            ..FormatOptions::default()
+        };
+        assert_eq!(src, format_starlark("test.bzl", src, &preserve).unwrap());
+    }
+
+    #[test]
+    fn test_multiline_load() {
+        assert_eq!(
+            indoc!(
+                r#"
+                load(
+                    ":defs.bzl",
+                    "a",
+                    c = "b",
+                )
+                "#
+            ),
+            format(indoc!(
+                r#"
+                load(
+                    ":defs.bzl",
+                    c = "b",
+                    "a"
+                )
+                "#
+            ))
+        );
+    }
+
+    #[test]
+    fn test_indent_width() {
+        let opts = FormatOptions {
+            indent_width: 2,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            "def f():\n  return [\n    1,\n  ]\n",
+            format_starlark(
+                "test.bzl",
+                "def f():\n    return [\n        1\n    ]\n",
+                &opts
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        // `format` checks that formatting is idempotent, and `format_starlark` that the formatted
+        // file has the same syntax tree as the original one.
+        let sources = [
+            "x = lambda a, b=1: a+b\n",
+            "y = {k: v for k, v in d.items() if v}\nz = [a[1:2], a[::2], a[-1], not b, a if b else c]\n",
+            "t = (1,)\nu = ()\nv = -1 ** 2\nw = a.b.c(d)[e]\nx += 1\ny //= 2\nz = a ** -b\n",
+            "x = 1 + \\\n    2\n",
+            "s = '''multi\nline''' + r'\\raw' + \"q'\"\n",
+            "load(':a.bzl', 'z', 'y')\nload(':b.bzl', b = 'x')\n",
+            "foo(\n  # leading\n  a = [  # trailing\n    1,\n    2],\n  b = {'k': (1,\n    2)},\n)\n",
+            indoc!(
+                r#"
+                def f(a, *, b, **kw) -> str:
+                  for x in a:
+                    if x == 1:
+                      continue
+                    elif x in b or x not in kw:
+                      break
+                    else:
+                      pass
+                  return "%s" % a
+                "#
+            ),
+        ];
+        for src in sources {
+            format(src);
+        }
+    }
+
+    #[test]
+    fn test_formatted_file_unchanged() {
+        let src = indoc!(
+            r#"
+            load("//rules:defs.bzl", "a_rule")
+
+            def _impl(ctx):
+                # Comments are kept.
+                return [DefaultInfo()]  # Trailing.
+
+            a_rule(
+                name = "foo",
+                srcs = [
+                    "a.c",
+                    "b.c",
+                ],
+                flags = select({
+                    "//config:linux": ["-DLINUX"],
+                    "DEFAULT": [],
+                }),
+            )
+            "#
+        );
+        assert_eq!(src, format(src));
+    }
+
+    #[test]
+    fn test_errors() {
+        let opts = FormatOptions::default();
+        assert!(format_starlark("test.bzl", "x = 'abc\n", &opts).is_err());
+        assert!(format_starlark("test.bzl", "x = [1, 2\n", &opts).is_err());
+        assert!(format_starlark("test.bzl", "x = (1]\n", &opts).is_err());
+        assert!(format_starlark("test.bzl", "if x:\n    y = 1\n  z = 2\n", &opts).is_err());
+    }
+}
@@ -0,0 +1,216 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::path::Path;
+
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::path_arg::PathArg;
+use buck2_common::legacy_configs::cells::BuckConfigBasedCells;
+use buck2_core::cells::name::CellName;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_path::AbsPathBuf;
+use buck2_core::fs::project::ProjectRoot;
+use serde::Serialize;
+use walkdir::WalkDir;
+
+use crate::commands::fmt::diff::unified_diff;
+use crate::commands::fmt::format::format_starlark;
+use crate::commands::fmt::format::FormatOptions;
+
+mod diff;
+mod format;
+
+/// Format build files, `PACKAGE` files and `.bzl` files.
+///
+/// Files are formatted following buildifier conventions where possible. Formatting can be
+/// configured per cell in the `[fmt]` section of the cell's buckconfig:
+/// `indent_width` (default 4), `quote_style` (`double` or `preserve`, default `double`) and
+/// `sort_loads` (default true).
+#[derive(Debug, clap::Parser)]
+#[clap(name = "fmt")]
+pub struct FmtCommand {
+    /// Print a diff of the changes formatting would make, and fail if there are any.
+    /// This is the default.
+    #[clap(long, conflicts_with = "write")]
+    check: bool,
+
+    /// Format the files in place.
+    #[clap(long)]
+    write: bool,
+
+    /// Print the report of unformatted files as JSON.
+    #[clap(long)]
+    json: bool,
+
+    /// Files or directories to format. Directories are searched recursively for build files,
+    /// `PACKAGE` files and `.bzl` files. Defaults to the current directory.
+    #[clap(value_name = "PATH")]
+    paths: Vec<PathArg>,
+}
+
+#[derive(Serialize)]
+struct FmtReport {
+    /// Files which are not formatted, with the diff formatting them would apply.
+    unformatted: Vec<UnformattedFile>,
+    /// Files which could not be formatted, e.g. because of syntax errors.
+    errors: Vec<FmtFileError>,
+}
+
+#[derive(Serialize)]
+struct UnformattedFile {
+    path: String,
+    diff: String,
+}
+
+#[derive(Serialize)]
+struct FmtFileError {
+    path: String,
+    error: String,
+}
+
+impl FmtCommand {
+    pub fn exec(self, _matches: &clap::ArgMatches, ctx: ClientCommandContext<'_>) -> ExitResult {
+        let write = self.write;
+        let json = self.json;
+        let report = self.format_files(&ctx)?;
+
+        if json {
+            buck2_client_ctx::println!("{}", serde_json::to_string_pretty(&report)?)?;
+        } else {
+            for file in &report.unformatted {
+                if write {
+                    buck2_client_ctx::eprintln!("Formatted {}", file.path)?;
+                } else {
+                    buck2_client_ctx::print!("{}", file.diff)?;
+                }
+            }
+            for error in &report.errors {
+                buck2_client_ctx::eprintln!("Error formatting {}: {}", error.path, error.error)?;
+            }
+            if !write && !report.unformatted.is_empty() {
+                buck2_client_ctx::eprintln!(
+                    "{} file(s) need formatting, run `buck2 fmt --write` to format them",
+                    report.unformatted.len()
+                )?;
+            }
+        }
+
+        if !report.errors.is_empty() || (!write && !report.unformatted.is_empty()) {
+            ExitResult::status(1)
+        } else {
+            ExitResult::success()
+        }
+    }
+
+    fn format_files(self, ctx: &ClientCommandContext<'_>) -> anyhow::Result<FmtReport> {
+        let project_root = ctx.paths()?.project_root();
+        let cells = BuckConfigBasedCells::parse(project_root)?;
+
+        let paths = if self.paths.is_empty() {
+            vec![ctx.working_dir.path().as_abs_path().to_owned()]
+        } else {
+            self.paths
+                .iter()
+                .map(|p| p.resolve(&ctx.working_dir))
+                .collect()
+        };
+
+        let mut options: HashMap<CellName, FormatOptions> = HashMap::new();
+        let mut report = FmtReport {
+            unformatted: Vec::new(),
+            errors: Vec::new(),
+        };
+        for file in collect_files(&paths, &cells, project_root)? {
+            let path = project_root.relativize_any(&file)?;
+            let cell = cells.cell_resolver.find(&path)?;
+            let options = match options.entry(cell) {
+                Entry::Occupied(e) => *e.get(),
+                Entry::Vacant(e) => *e.insert(FormatOptions::from_config(
+                    cells.configs_by_name.get(cell)?,
+                )?),
+            };
+
+            let path = path.to_string();
+            let src = fs_util::read_to_string(&file)?;
+            let formatted = match format_starlark(&path, &src, &options) {
+                Ok(formatted) => formatted,
+                Err(e) => {
+                    report.errors.push(FmtFileError {
+                        path,
+                        error: format!("{:#}", e),
+                    });
+                    continue;
+                }
+            };
+            if formatted == src {
+                continue;
+            }
+            if self.write {
+                fs_util::write(&file, &formatted)?;
+            }
+            report.unformatted.push(UnformattedFile {
+                diff: unified_diff(&path, &src, &formatted),
+                path,
+            });
+        }
+        Ok(report)
+    }
+}
+
+/// Find the files to format: files passed explicitly, and build files, `PACKAGE` files and `.bzl`
+/// files in the directories passed.
+fn collect_files(
+    paths: &[AbsPathBuf],
+    cells: &BuckConfigBasedCells,
+    project_root: &ProjectRoot,
+) -> anyhow::Result<Vec<AbsPathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if !path.is_dir() {
+            files.push(path.clone());
+            continue;
+        }
+        let entries = WalkDir::new(path)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|e| e.depth() == 0 || !is_ignored_dir(e.path()));
+        for entry in entries {
+            let entry = entry?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let file = AbsPathBuf::try_from(entry.into_path())?;
+            let name = match file.file_name().and_then(|n| n.to_str()) {
+                Some(name) => name,
+                None => continue,
+            };
+            let cell = cells
+                .cell_resolver
+                .find(&project_root.relativize_any(&file)?)?;
+            let buildfiles = cells.cell_resolver.get(cell)?.buildfiles();
+            if name.ends_with(".bzl")
+                || name == "PACKAGE"
+                || buildfiles.iter().any(|b| b.as_str() == name)
+            {
+                files.push(file);
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn is_ignored_dir(path: &Path) -> bool {
+    match path.file_name().and_then(|n| n.to_str()) {
+        Some(name) => name.starts_with('.') || name == "buck-out",
+        None => false,
+    }
+}
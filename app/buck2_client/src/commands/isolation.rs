@@ -0,0 +1,223 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::BTreeSet;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use anyhow::Context;
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::common::CommonConsoleOptions;
+use buck2_client_ctx::common::CommonDaemonCommandOptions;
+use buck2_client_ctx::daemon::client::active_commands::check_no_active_commands;
+use buck2_client_ctx::daemon::client::connect::BuckdConnectOptions;
+use buck2_client_ctx::daemon::client::BuckdLifecycleLock;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::startup_deadline::StartupDeadline;
+use buck2_client_ctx::subscribers::recorder::try_get_invocation_recorder;
+use buck2_common::invocation_paths::InvocationPaths;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::file_name::FileNameBuf;
+use buck2_event_observer::humanized::HumanizedBytes;
+use chrono::DateTime;
+use chrono::Local;
+use walkdir::WalkDir;
+
+use crate::commands::clean::clean;
+
+/// Manage the isolation dirs of this project.
+///
+/// Every isolation dir has its own daemon, daemon state in `~/.buck/buckd` and outputs
+/// in `buck-out/<isolation dir>`.
+#[derive(Debug, clap::Subcommand)]
+pub enum IsolationCommand {
+    /// List isolation dirs with their daemon status, disk usage and last use time.
+    List(IsolationListCommand),
+
+    /// Stop the daemon and delete the state of the given isolation dirs.
+    ///
+    /// If a daemon is running other commands, they are listed and nothing is deleted unless
+    /// `--force` is passed.
+    Clean(IsolationCleanCommand),
+}
+
+impl IsolationCommand {
+    pub fn exec(self, matches: &clap::ArgMatches, ctx: ClientCommandContext<'_>) -> ExitResult {
+        match self {
+            Self::List(cmd) => cmd.exec(matches, ctx),
+            Self::Clean(cmd) => cmd.exec(matches, ctx),
+        }
+    }
+}
+
+#[derive(Debug, clap::Parser)]
+pub struct IsolationListCommand {}
+
+#[derive(Debug, clap::Parser)]
+pub struct IsolationCleanCommand {
+    #[clap(flatten)]
+    console_opts: CommonConsoleOptions,
+
+    #[clap(
+        long = "dry-run",
+        help = "Performs a dry-run and prints the paths that would be removed."
+    )]
+    dry_run: bool,
+
+    #[clap(
+        long,
+        help = "Kill the daemons and delete the files even if the daemons are running other commands"
+    )]
+    force: bool,
+
+    /// Isolation dirs to clean.
+    #[clap(value_name = "ISOLATION_DIR", required = true, parse(try_from_str = parse_isolation_dir))]
+    isolation_dirs: Vec<FileNameBuf>,
+}
+
+fn parse_isolation_dir(s: &str) -> anyhow::Result<FileNameBuf> {
+    FileNameBuf::try_from(s.to_owned()).context("isolation dir must be a directory name")
+}
+
+/// Names of isolation dirs which have either daemon state or outputs on disk.
+fn list_isolation_dirs(paths: &InvocationPaths) -> anyhow::Result<BTreeSet<FileNameBuf>> {
+    let daemon_dir = paths.daemon_dir()?;
+    let daemon_root = daemon_dir
+        .path
+        .parent()
+        .context("daemon dir has no parent")?;
+
+    let buck_out_root = paths.buck_out_root_path();
+
+    let mut isolation_dirs = BTreeSet::new();
+    for root in [daemon_root, &*buck_out_root] {
+        let Some(entries) = fs_util::read_dir_if_exists(root)? else {
+            continue;
+        };
+        for entry in entries {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+            if let Some(name) = entry.file_name().to_str() {
+                if let Ok(name) = FileNameBuf::try_from(name.to_owned()) {
+                    isolation_dirs.insert(name);
+                }
+            }
+        }
+    }
+    Ok(isolation_dirs)
+}
+
+fn disk_usage(path: &AbsNormPath) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.metadata().ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// Most recent modification of the daemon dir or the log dir, which are both touched by every
+/// command that talks to the daemon.
+fn last_used(paths: &InvocationPaths) -> anyhow::Result<Option<SystemTime>> {
+    let mut last_used = None;
+    for path in [paths.daemon_dir()?.path, paths.log_dir()] {
+        if let Ok(modified) = path.metadata().and_then(|m| m.modified()) {
+            last_used = last_used.max(Some(modified));
+        }
+    }
+    Ok(last_used)
+}
+
+impl IsolationListCommand {
+    pub fn exec(self, _matches: &clap::ArgMatches, ctx: ClientCommandContext<'_>) -> ExitResult {
+        ctx.with_runtime(async move |ctx| {
+            let paths = ctx.paths()?;
+            for isolation in list_isolation_dirs(paths)? {
+                let current = isolation.as_str() == paths.isolation.as_str();
+                let paths = paths.with_isolation(isolation);
+                let running = BuckdConnectOptions::existing_only_no_console()
+                    .connect(&paths)
+                    .await
+                    .is_ok();
+                let last_used = match last_used(&paths)? {
+                    Some(t) => DateTime::<Local>::from(t)
+                        .format("%Y-%m-%d %H:%M:%S")
+                        .to_string(),
+                    None => "-".to_owned(),
+                };
+                buck2_client_ctx::println!(
+                    "{}{}\tdaemon: {}\tdisk usage: {}\tlast used: {}",
+                    paths.isolation,
+                    if current { " (current)" } else { "" },
+                    if running { "running" } else { "stopped" },
+                    HumanizedBytes::new(disk_usage(&paths.buck_out_path())),
+                    last_used,
+                )?;
+            }
+            anyhow::Ok(())
+        })?;
+        ExitResult::success()
+    }
+}
+
+impl IsolationCleanCommand {
+    pub fn exec(self, _matches: &clap::ArgMatches, ctx: ClientCommandContext<'_>) -> ExitResult {
+        let _log_on_drop = try_get_invocation_recorder(
+            &ctx,
+            CommonDaemonCommandOptions::default_ref(),
+            "isolation-clean",
+            std::env::args().collect(),
+            None,
+            false,
+        )?;
+
+        ctx.with_runtime(async move |ctx| {
+            let console = &self.console_opts.final_console();
+            for isolation in self.isolation_dirs {
+                let paths = ctx.paths()?.with_isolation(isolation);
+                let buck_out_dir = paths.buck_out_path();
+                let daemon_dir = paths.daemon_dir()?;
+
+                if self.dry_run {
+                    clean(buck_out_dir, daemon_dir, console, None).await?;
+                    continue;
+                }
+
+                // Same as `buck2 clean`: stop the daemon and hold the lifecycle lock so that no
+                // new daemon starts in this isolation dir while we delete its state.
+                let lifecycle_lock = BuckdLifecycleLock::lock_with_timeout(
+                    daemon_dir.clone(),
+                    StartupDeadline::duration_from_now(Duration::from_secs(10))?,
+                )
+                .await
+                .with_context(|| "Error locking buckd lifecycle.lock")?;
+                if let Ok(mut buckd) = BuckdConnectOptions::existing_only_no_console()
+                    .connect(&paths)
+                    .await
+                {
+                    if !self.force {
+                        let status = buckd.with_flushing().status(false, true).await?;
+                        check_no_active_commands("isolation clean", &status.active_commands)?;
+                    }
+                    buckd
+                        .with_flushing()
+                        .kill("`buck2 isolation clean` was invoked")
+                        .await?;
+                }
+                clean(buck_out_dir, daemon_dir, console, Some(&lifecycle_lock)).await?;
+            }
+            anyhow::Ok(())
+        })?;
+        ExitResult::success()
+    }
+}
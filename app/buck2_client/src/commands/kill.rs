@@ -9,6 +9,7 @@
 
 use buck2_client_ctx::client_ctx::ClientCommandContext;
 use buck2_client_ctx::common::CommonDaemonCommandOptions;
+use buck2_client_ctx::daemon::client::active_commands::check_no_active_commands;
 use buck2_client_ctx::daemon::client::connect::BuckdConnectOptions;
 use buck2_client_ctx::subscribers::recorder::try_get_invocation_recorder;
 
@@ -19,8 +20,15 @@ use buck2_client_ctx::subscribers::recorder::try_get_invocation_recorder;
 /// `buck2 killall` kills all the buck2 processes on the machine.
 ///
 /// `buck2 clean` kills the buck2 daemon and also deletes the buck2 state files.
+///
+/// If the daemon is running other commands, they are listed and the daemon is not killed
+/// unless `--force` is passed.
 #[derive(Debug, clap::Parser)]
-pub struct KillCommand {}
+pub struct KillCommand {
+    /// Kill the daemon even if it is running other commands.
+    #[clap(long)]
+    pub force: bool,
+}
 
 impl KillCommand {
     pub fn exec(
@@ -46,6 +54,10 @@ impl KillCommand {
                     buck2_client_ctx::eprintln!("no buckd server running")?;
                 }
                 Ok(mut client) => {
+                    if !self.force {
+                        let status = client.with_flushing().status(false, true).await?;
+                        check_no_active_commands("kill", &status.active_commands)?;
+                    }
                     buck2_client_ctx::eprintln!("killing buckd server")?;
                     client
                         .with_flushing()
@@ -16,6 +16,7 @@ use buck2_client_ctx::stream_value::StreamValue;
 use buck2_client_ctx::tokio_runtime_setup::client_tokio_runtime;
 use buck2_event_observer::display;
 use buck2_event_observer::display::TargetDisplayOptions;
+use indexmap::IndexMap;
 use tokio_stream::StreamExt;
 
 use crate::commands::log::options::EventLogOptions;
@@ -28,16 +29,38 @@ use crate::commands::log::options::EventLogOptions;
 /// (runtime of this node), user duration (duration the user can improve) and potential improvement
 /// before this node stops being on the critical path.
 ///
+/// With `--summary`, it instead lists each kind of node (load, analysis, action, etc.) with the
+/// number of nodes of that kind on the critical path and their total and user durations.
+///
+/// With `--what-if-cached`, it instead estimates how long the critical path would have been if
+/// the matching actions had been cache hits. The log only contains the critical path, so this is
+/// an estimate: it accounts for the other paths through the build graph using the potential
+/// improvement of each action, but not for how those paths interact.
+///
 /// All durations are in microseconds.
 #[derive(Debug, clap::Parser)]
 pub struct CriticalPathCommand {
     #[clap(flatten)]
     event_log: EventLogOptions,
+
+    /// Show the time spent in each kind of node instead of listing the nodes.
+    #[clap(long, conflicts_with = "what-if-cached")]
+    summary: bool,
+
+    /// Estimate the critical path assuming the actions matching this pattern were cache hits.
+    /// An action matches if its target label, category or identifier contains the pattern. Can
+    /// be repeated.
+    #[clap(long, value_name = "PATTERN")]
+    what_if_cached: Vec<String>,
 }
 
 impl CriticalPathCommand {
     pub fn exec(self, _matches: &clap::ArgMatches, ctx: ClientCommandContext<'_>) -> ExitResult {
-        let Self { event_log } = self;
+        let Self {
+            event_log,
+            summary,
+            what_if_cached,
+        } = self;
 
         let rt = client_tokio_runtime()?;
 
@@ -58,7 +81,16 @@ impl CriticalPathCommand {
                                 Some(buck2_data::instant_event::Data::BuildGraphInfo(
                                     build_graph,
                                 )) => {
-                                    log_critical_path(&build_graph)?;
+                                    if summary {
+                                        log_critical_path_summary(&build_graph)?;
+                                    } else if !what_if_cached.is_empty() {
+                                        log_critical_path_what_if_cached(
+                                            &build_graph,
+                                            &what_if_cached,
+                                        )?;
+                                    } else {
+                                        log_critical_path(&build_graph)?;
+                                    }
                                 }
                                 _ => {}
                             }
@@ -76,10 +108,18 @@ impl CriticalPathCommand {
     }
 }
 
-fn log_critical_path(critical_path: &buck2_data::BuildGraphExecutionInfo) -> anyhow::Result<()> {
-    let target_display_options = TargetDisplayOptions::for_log();
+/// Displayable description of a critical path entry.
+struct EntryDescription<'a> {
+    kind: &'static str,
+    name: String,
+    category: &'a str,
+    identifier: &'a str,
+}
+
+impl<'a> EntryDescription<'a> {
+    fn new(entry: &'a buck2_data::CriticalPathEntry2) -> anyhow::Result<Option<Self>> {
+        let target_display_options = TargetDisplayOptions::for_log();
 
-    for entry in &critical_path.critical_path2 {
         use buck2_data::critical_path_entry2::Entry;
 
         let kind;
@@ -97,7 +137,7 @@ fn log_critical_path(critical_path: &buck2_data::BuildGraphExecutionInfo) -> any
                     Some(Target::StandardTarget(t)) => {
                         display::display_configured_target_label(t, target_display_options)?
                     }
-                    None => continue,
+                    None => return Ok(None),
                 };
             }
             Some(Entry::ActionExecution(action_execution)) => {
@@ -111,7 +151,7 @@ fn log_critical_path(critical_path: &buck2_data::BuildGraphExecutionInfo) -> any
                     }
                     Some(Owner::BxlKey(t)) => display::display_bxl_key(t)?,
                     Some(Owner::AnonTarget(t)) => display::display_anon_target(t)?,
-                    None => continue,
+                    None => return Ok(None),
                 };
 
                 match &action_execution.name {
@@ -133,7 +173,7 @@ fn log_critical_path(critical_path: &buck2_data::BuildGraphExecutionInfo) -> any
                     }
                     Some(Owner::BxlKey(t)) => display::display_bxl_key(t)?,
                     Some(Owner::AnonTarget(t)) => display::display_anon_target(t)?,
-                    None => continue,
+                    None => return Ok(None),
                 };
 
                 identifier = &materialization.path;
@@ -146,32 +186,57 @@ fn log_critical_path(critical_path: &buck2_data::BuildGraphExecutionInfo) -> any
                 kind = "load";
                 name = load.package.clone();
             }
-            None => continue,
+            None => return Ok(None),
         }
 
-        struct OptionalDuration {
-            inner: Option<Duration>,
-        }
+        Ok(Some(Self {
+            kind,
+            name,
+            category,
+            identifier,
+        }))
+    }
+}
 
-        impl OptionalDuration {
-            fn new<T, E>(d: Option<T>) -> Result<Self, E>
-            where
-                T: TryInto<Duration, Error = E>,
-            {
-                Ok(Self {
-                    inner: d.map(|d| d.try_into()).transpose()?,
-                })
-            }
-        }
+struct OptionalDuration {
+    inner: Option<Duration>,
+}
 
-        impl fmt::Display for OptionalDuration {
-            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-                if let Some(inner) = self.inner {
-                    write!(f, "{}", inner.as_micros())?;
-                }
-                Ok(())
-            }
+impl OptionalDuration {
+    fn new<T, E>(d: Option<T>) -> Result<Self, E>
+    where
+        T: TryInto<Duration, Error = E>,
+    {
+        Ok(Self {
+            inner: d.map(|d| d.try_into()).transpose()?,
+        })
+    }
+}
+
+impl fmt::Display for OptionalDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(inner) = self.inner {
+            write!(f, "{}", inner.as_micros())?;
         }
+        Ok(())
+    }
+}
+
+fn duration_or_zero(d: &Option<prost_types::Duration>) -> anyhow::Result<Duration> {
+    Ok(OptionalDuration::new(d.clone())?.inner.unwrap_or_default())
+}
+
+fn log_critical_path(critical_path: &buck2_data::BuildGraphExecutionInfo) -> anyhow::Result<()> {
+    for entry in &critical_path.critical_path2 {
+        let EntryDescription {
+            kind,
+            name,
+            category,
+            identifier,
+        } = match EntryDescription::new(entry)? {
+            Some(description) => description,
+            None => continue,
+        };
 
         buck2_client_ctx::println!(
             "{}\t{}\t{}\t{}\t{}\t{}\t{}",
@@ -187,3 +252,165 @@ fn log_critical_path(critical_path: &buck2_data::BuildGraphExecutionInfo) -> any
 
     Ok(())
 }
+
+#[derive(Default)]
+struct PhaseSummary {
+    count: u64,
+    total_duration: Duration,
+    user_duration: Duration,
+}
+
+fn summarize_critical_path(
+    critical_path: &buck2_data::BuildGraphExecutionInfo,
+) -> anyhow::Result<IndexMap<&'static str, PhaseSummary>> {
+    let mut phases: IndexMap<&'static str, PhaseSummary> = IndexMap::new();
+    for entry in &critical_path.critical_path2 {
+        let description = match EntryDescription::new(entry)? {
+            Some(description) => description,
+            None => continue,
+        };
+        let phase = phases.entry(description.kind).or_default();
+        phase.count += 1;
+        phase.total_duration += duration_or_zero(&entry.total_duration)?;
+        phase.user_duration += duration_or_zero(&entry.user_duration)?;
+    }
+    Ok(phases)
+}
+
+fn log_critical_path_summary(
+    critical_path: &buck2_data::BuildGraphExecutionInfo,
+) -> anyhow::Result<()> {
+    for (kind, phase) in summarize_critical_path(critical_path)? {
+        buck2_client_ctx::println!(
+            "{}\t{}\t{}\t{}",
+            kind,
+            phase.count,
+            phase.total_duration.as_micros(),
+            phase.user_duration.as_micros(),
+        )?;
+    }
+    Ok(())
+}
+
+/// A critical path entry, reduced to what is needed to estimate the critical path when some of
+/// the entries take no time.
+struct WhatIfEntry {
+    /// Whether the entry is assumed to take no time.
+    cached: bool,
+    duration: Duration,
+    /// How much the entry can be shortened before another path becomes critical.
+    potential_improvement: Option<Duration>,
+}
+
+/// Estimate the length of the critical path if the cached entries took no time.
+///
+/// Removing the cached entries shortens this path by their cumulative duration, but each of them
+/// can only be shortened by its potential improvement before another path through the graph
+/// becomes critical.
+fn estimate_critical_path(entries: &[WhatIfEntry]) -> Duration {
+    let original: Duration = entries.iter().map(|e| e.duration).sum();
+    let improvement: Duration = entries
+        .iter()
+        .filter(|e| e.cached)
+        .map(|e| {
+            e.potential_improvement
+                .map_or(e.duration, |p| p.min(e.duration))
+        })
+        .sum();
+    original - improvement
+}
+
+fn log_critical_path_what_if_cached(
+    critical_path: &buck2_data::BuildGraphExecutionInfo,
+    patterns: &[String],
+) -> anyhow::Result<()> {
+    let mut entries = Vec::new();
+    for entry in &critical_path.critical_path2 {
+        let description = match EntryDescription::new(entry)? {
+            Some(description) => description,
+            None => continue,
+        };
+        let cached = description.kind == "action"
+            && patterns.iter().any(|p| {
+                description.name.contains(p.as_str())
+                    || description.category.contains(p.as_str())
+                    || description.identifier.contains(p.as_str())
+            });
+        if cached {
+            buck2_client_ctx::eprintln!(
+                "Assuming cache hit: {} {} {}",
+                description.name,
+                description.category,
+                description.identifier
+            )?;
+        }
+        entries.push(WhatIfEntry {
+            cached,
+            duration: duration_or_zero(&entry.duration)?,
+            potential_improvement: OptionalDuration::new(
+                entry.potential_improvement_duration.clone(),
+            )?
+            .inner,
+        });
+    }
+
+    let original: Duration = entries.iter().map(|e| e.duration).sum();
+    buck2_client_ctx::println!("original\t{}", original.as_micros())?;
+    buck2_client_ctx::println!(
+        "estimated\t{}",
+        estimate_critical_path(&entries).as_micros()
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::commands::log::critical_path::estimate_critical_path;
+    use crate::commands::log::critical_path::WhatIfEntry;
+
+    fn entry(cached: bool, duration: u64, potential_improvement: Option<u64>) -> WhatIfEntry {
+        WhatIfEntry {
+            cached,
+            duration: Duration::from_secs(duration),
+            potential_improvement: potential_improvement.map(Duration::from_secs),
+        }
+    }
+
+    #[test]
+    fn test_estimate_critical_path() {
+        // Nothing cached.
+        assert_eq!(
+            Duration::from_secs(10),
+            estimate_critical_path(&[entry(false, 4, Some(1)), entry(false, 6, Some(2))])
+        );
+        // Cached entries without a known potential improvement are removed entirely.
+        assert_eq!(
+            Duration::from_secs(4),
+            estimate_critical_path(&[entry(false, 4, None), entry(true, 6, None)])
+        );
+        // Another path becomes critical once the cached entry is shortened by 2.
+        assert_eq!(
+            Duration::from_secs(8),
+            estimate_critical_path(&[entry(false, 4, None), entry(true, 6, Some(2))])
+        );
+        // The durations of all the cached entries are removed.
+        assert_eq!(
+            Duration::from_secs(4),
+            estimate_critical_path(&[
+                entry(false, 4, None),
+                entry(true, 3, None),
+                entry(true, 3, None)
+            ])
+        );
+        assert_eq!(
+            Duration::from_secs(7),
+            estimate_critical_path(&[
+                entry(true, 3, Some(1)),
+                entry(false, 4, None),
+                entry(true, 3, Some(2))
+            ])
+        );
+    }
+}
@@ -0,0 +1,354 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::stream_value::StreamValue;
+use buck2_client_ctx::subscribers::event_log::file_names::find_log_by_trace_id;
+use buck2_client_ctx::subscribers::event_log::read::EventLogPathBuf;
+use buck2_client_ctx::tokio_runtime_setup::client_tokio_runtime;
+use buck2_common::convert::ProstDurationExt;
+use buck2_event_observer::display;
+use buck2_event_observer::display::TargetDisplayOptions;
+use buck2_event_observer::last_command_execution_kind::get_last_command_execution_kind;
+use buck2_event_observer::last_command_execution_kind::LastCommandExecutionKind;
+use buck2_wrapper_common::invocation_id::TraceId;
+use tokio_stream::StreamExt;
+
+use crate::commands::log::LogCommandOutputFormat;
+
+/// Compare two invocations, to find out why the second one did more work than the first one.
+///
+/// Actions and analyses are matched by name across the two event logs. This lists, in order:
+///
+/// * `file_changed`: the file changes the second invocation picked up, which invalidated the
+///   work below.
+///
+/// * `executed`: the actions whose command ran (locally or remotely) in the second invocation,
+///   but were cache hits, did not run a command, or did not run at all in the first one.
+///
+/// * `slower`: the actions which ran in both invocations and took at least `--min-slowdown-ms`
+///   longer in the second one.
+///
+/// * `analysis`: the targets analyzed by the second invocation. Analysis results are reused
+///   across invocations by the daemon, so these were recomputed.
+///
+/// Each line lists the kind of difference, the action or target, and what it was in the first
+/// and second invocations. Durations are in microseconds.
+#[derive(Debug, clap::Parser)]
+pub struct DiffCommand {
+    /// The first invocation: a path to its event log, or its trace id.
+    #[clap(value_name = "INVOCATION1")]
+    first: String,
+
+    /// The second invocation: a path to its event log, or its trace id.
+    #[clap(value_name = "INVOCATION2")]
+    second: String,
+
+    /// Only report actions which got slower by at least this many milliseconds.
+    #[clap(long, default_value = "100", value_name = "MILLISECONDS")]
+    min_slowdown_ms: u64,
+
+    #[clap(
+        long = "format",
+        help = "Which output format to use for this command",
+        default_value = "tabulated",
+        ignore_case = true,
+        arg_enum
+    )]
+    output: LogCommandOutputFormat,
+}
+
+/// An action, as it ran in an invocation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ActionRecord {
+    /// `local` or `remote` if a command ran, `cached` for a cache hit, `none` if no command was
+    /// needed (e.g. writing a file).
+    execution: &'static str,
+    wall_time: Duration,
+}
+
+impl ActionRecord {
+    fn executed(&self) -> bool {
+        self.execution == "local" || self.execution == "remote"
+    }
+}
+
+/// What an invocation did, keyed by action and target names so that it can be compared to
+/// another invocation.
+#[derive(Debug, Default)]
+struct InvocationRecord {
+    actions: BTreeMap<String, ActionRecord>,
+    analyses: BTreeMap<String, Duration>,
+    file_changes: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, serde::Serialize)]
+struct DiffRecord {
+    kind: &'static str,
+    name: String,
+    first: String,
+    second: String,
+}
+
+impl InvocationRecord {
+    async fn read(log_path: &EventLogPathBuf) -> anyhow::Result<Self> {
+        let target_display_options = TargetDisplayOptions::for_log();
+        let (_invocation, mut events) = log_path.unpack_stream().await?;
+        let mut record = Self::default();
+        while let Some(event) = events.try_next().await? {
+            let end = match event {
+                StreamValue::Event(event) => match event.data {
+                    Some(buck2_data::buck_event::Data::SpanEnd(end)) => end,
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            let duration = end
+                .duration
+                .as_ref()
+                .map(|d| d.try_into_duration())
+                .transpose()?
+                .unwrap_or_default();
+            match &end.data {
+                Some(buck2_data::span_end_event::Data::ActionExecution(action)) => {
+                    let name = display::display_action_identity(
+                        action.key.as_ref(),
+                        action.name.as_ref(),
+                        target_display_options,
+                    )?;
+                    let execution = match get_last_command_execution_kind(action) {
+                        LastCommandExecutionKind::Local => "local",
+                        LastCommandExecutionKind::Remote => "remote",
+                        LastCommandExecutionKind::Cached => "cached",
+                        LastCommandExecutionKind::NoCommand => "none",
+                    };
+                    let wall_time = action
+                        .wall_time
+                        .as_ref()
+                        .map(|d| d.try_into_duration())
+                        .transpose()?
+                        .unwrap_or(duration);
+                    record.actions.insert(
+                        name,
+                        ActionRecord {
+                            execution,
+                            wall_time,
+                        },
+                    );
+                }
+                Some(buck2_data::span_end_event::Data::Analysis(analysis)) => {
+                    use buck2_data::analysis_end::Target;
+
+                    let name = match &analysis.target {
+                        Some(Target::StandardTarget(t)) => {
+                            display::display_configured_target_label(t, target_display_options)?
+                        }
+                        Some(Target::AnonTarget(t)) => display::display_anon_target(t)?,
+                        None => continue,
+                    };
+                    record.analyses.insert(name, duration);
+                }
+                Some(buck2_data::span_end_event::Data::FileWatcher(file_watcher)) => {
+                    if let Some(stats) = &file_watcher.stats {
+                        record
+                            .file_changes
+                            .extend(stats.events.iter().map(|e| e.path.clone()));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(record)
+    }
+
+    /// The differences from `first` to `self`.
+    fn diff(&self, first: &InvocationRecord, min_slowdown: Duration) -> Vec<DiffRecord> {
+        let micros = |d: Duration| d.as_micros().to_string();
+        let mut records = Vec::new();
+
+        let mut file_changes = self.file_changes.clone();
+        file_changes.sort();
+        file_changes.dedup();
+        records.extend(file_changes.into_iter().map(|path| DiffRecord {
+            kind: "file_changed",
+            name: path,
+            first: String::new(),
+            second: String::new(),
+        }));
+
+        for (name, action) in &self.actions {
+            if !action.executed() {
+                continue;
+            }
+            match first.actions.get(name) {
+                Some(before) if before.executed() => {
+                    if action.wall_time > before.wall_time
+                        && action.wall_time - before.wall_time >= min_slowdown
+                    {
+                        records.push(DiffRecord {
+                            kind: "slower",
+                            name: name.clone(),
+                            first: micros(before.wall_time),
+                            second: micros(action.wall_time),
+                        });
+                    }
+                }
+                before => records.push(DiffRecord {
+                    kind: "executed",
+                    name: name.clone(),
+                    first: before.map_or("missing", |b| b.execution).to_owned(),
+                    second: action.execution.to_owned(),
+                }),
+            }
+        }
+
+        for (name, duration) in &self.analyses {
+            records.push(DiffRecord {
+                kind: "analysis",
+                name: name.clone(),
+                first: first
+                    .analyses
+                    .get(name)
+                    .map_or_else(|| "missing".to_owned(), |d| micros(*d)),
+                second: micros(*duration),
+            });
+        }
+
+        records
+    }
+}
+
+/// Find the event log of an invocation given by path or trace id.
+fn resolve_invocation(
+    ctx: &ClientCommandContext<'_>,
+    invocation: &str,
+) -> anyhow::Result<EventLogPathBuf> {
+    if let Ok(trace_id) = TraceId::from_str(invocation) {
+        if let Some(log_path) = find_log_by_trace_id(&ctx.paths()?.log_dir(), &trace_id)? {
+            return Ok(log_path);
+        }
+    }
+    EventLogPathBuf::infer(ctx.working_dir.resolve(Path::new(invocation)))
+}
+
+fn print_record(format: &LogCommandOutputFormat, record: &DiffRecord) -> anyhow::Result<()> {
+    match format {
+        LogCommandOutputFormat::Tabulated => buck2_client_ctx::println!(
+            "{}\t{}\t{}\t{}",
+            record.kind,
+            record.name,
+            record.first,
+            record.second
+        ),
+        LogCommandOutputFormat::Csv => buck2_client_ctx::stdio::print_with_writer(|w| {
+            let mut writer = csv::WriterBuilder::new().has_headers(false).from_writer(w);
+            writer.serialize(record)
+        }),
+        LogCommandOutputFormat::Json => {
+            buck2_client_ctx::stdio::print_with_writer(|w| serde_json::to_writer(w, record))?;
+            buck2_client_ctx::println!("")
+        }
+    }
+}
+
+impl DiffCommand {
+    pub fn exec(self, _matches: &clap::ArgMatches, ctx: ClientCommandContext<'_>) -> ExitResult {
+        let Self {
+            first,
+            second,
+            min_slowdown_ms,
+            output,
+        } = self;
+
+        let rt = client_tokio_runtime()?;
+
+        rt.block_on(async move {
+            let first = InvocationRecord::read(&resolve_invocation(&ctx, &first)?).await?;
+            let second = InvocationRecord::read(&resolve_invocation(&ctx, &second)?).await?;
+            for record in second.diff(&first, Duration::from_millis(min_slowdown_ms)) {
+                print_record(&output, &record)?;
+            }
+            anyhow::Ok(())
+        })?;
+
+        ExitResult::success()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::commands::log::diff::ActionRecord;
+    use crate::commands::log::diff::DiffRecord;
+    use crate::commands::log::diff::InvocationRecord;
+
+    fn action(execution: &'static str, wall_time: u64) -> ActionRecord {
+        ActionRecord {
+            execution,
+            wall_time: Duration::from_millis(wall_time),
+        }
+    }
+
+    fn record(kind: &'static str, name: &str, first: &str, second: &str) -> DiffRecord {
+        DiffRecord {
+            kind,
+            name: name.to_owned(),
+            first: first.to_owned(),
+            second: second.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut first = InvocationRecord::default();
+        first.actions.insert("a".to_owned(), action("cached", 10));
+        first.actions.insert("b".to_owned(), action("remote", 100));
+        first.actions.insert("c".to_owned(), action("local", 100));
+        first
+            .analyses
+            .insert("//:t".to_owned(), Duration::from_millis(2));
+
+        let mut second = InvocationRecord::default();
+        second.actions.insert("a".to_owned(), action("local", 50));
+        second.actions.insert("b".to_owned(), action("remote", 500));
+        // Not slower by enough.
+        second.actions.insert("c".to_owned(), action("local", 150));
+        second.actions.insert("d".to_owned(), action("remote", 10));
+        // Cache hits and actions without a command are not reported.
+        second.actions.insert("e".to_owned(), action("cached", 10));
+        second.actions.insert("f".to_owned(), action("none", 10));
+        second
+            .analyses
+            .insert("//:t".to_owned(), Duration::from_millis(3));
+        second
+            .analyses
+            .insert("//:u".to_owned(), Duration::from_millis(1));
+        second.file_changes = vec!["b.txt".to_owned(), "a.txt".to_owned(), "b.txt".to_owned()];
+
+        assert_eq!(
+            second.diff(&first, Duration::from_millis(100)),
+            vec![
+                record("file_changed", "a.txt", "", ""),
+                record("file_changed", "b.txt", "", ""),
+                record("executed", "a", "cached", "local"),
+                record("slower", "b", "100000", "500000"),
+                record("executed", "d", "missing", "remote"),
+                record("analysis", "//:t", "2000", "3000"),
+                record("analysis", "//:u", "missing", "1000"),
+            ]
+        );
+    }
+}
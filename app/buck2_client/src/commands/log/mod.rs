@@ -10,6 +10,7 @@
 mod critical_path;
 pub(crate) mod debug_last_log;
 pub(crate) mod debug_what_ran;
+mod diff;
 pub(crate) mod options;
 pub(crate) mod path_log;
 mod show_log;
@@ -70,8 +71,12 @@ pub enum LogCommand {
     /// Shows how many bytes/digests were uploaded by a command.
     WhatUploaded(what_uploaded::WhatUploadedCommand),
 
-    /// Shows how many bytes/digests were uploaded by a command.
+    /// Shows the critical path of a build, or estimates it assuming some actions were cache hits.
     CriticalPath(critical_path::CriticalPathCommand),
+
+    /// Compares two invocations: what executed again, what got slower, and which file changes
+    /// invalidated work.
+    Diff(diff::DiffCommand),
 }
 
 impl LogCommand {
@@ -86,6 +91,7 @@ impl LogCommand {
             Self::WhatMaterialized(cmd) => cmd.exec(matches, ctx),
             Self::WhatUploaded(cmd) => cmd.exec(matches, ctx),
             Self::CriticalPath(cmd) => cmd.exec(matches, ctx),
+            Self::Diff(cmd) => cmd.exec(matches, ctx),
         }
     }
 }
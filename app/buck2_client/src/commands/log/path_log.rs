@@ -7,8 +7,11 @@
  * of this source tree.
  */
 
+use buck2_cli_proto::UnstableRecentEventsRequest;
 use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::daemon::client::connect::BuckdConnectOptions;
 use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::stdio;
 use buck2_client_ctx::subscribers::event_log::file_names::retrieve_all_logs;
 use buck2_client_ctx::tokio_runtime_setup::client_tokio_runtime;
 
@@ -24,6 +27,12 @@ pub struct PathLogCommand {
     /// List all the logs.
     #[clap(long, group = "event_log")]
     all: bool,
+
+    /// Print the events retained in memory by the running daemon, as JSON lines, instead of the
+    /// path to a log. This works even if the event log was never written or has since been
+    /// deleted. The daemon only retains events if `buck2.retained_event_log_size` is set.
+    #[clap(long, group = "event_log")]
+    from_memory: bool,
 }
 
 impl PathLogCommand {
@@ -31,11 +40,16 @@ impl PathLogCommand {
         let Self {
             event_log_options,
             all,
+            from_memory,
         } = self;
 
         let rt = client_tokio_runtime()?;
 
         rt.block_on(async move {
+            if from_memory {
+                return show_retained_events(&ctx).await;
+            }
+
             let paths = if all {
                 retrieve_all_logs(&ctx)?
             } else {
@@ -50,3 +64,23 @@ impl PathLogCommand {
         ExitResult::success()
     }
 }
+
+async fn show_retained_events(ctx: &ClientCommandContext<'_>) -> anyhow::Result<()> {
+    let mut client = ctx
+        .connect_buckd(BuckdConnectOptions::existing_only_no_console())
+        .await?;
+    let events = client
+        .with_flushing()
+        .unstable_recent_events(UnstableRecentEventsRequest {})
+        .await?
+        .events;
+
+    let mut buf = Vec::new();
+    for event in events {
+        buf.clear();
+        serde_json::to_writer(&mut buf, &event)?;
+        stdio::print_bytes(&buf)?;
+        stdio::print_bytes(b"\n")?;
+    }
+    Ok(())
+}
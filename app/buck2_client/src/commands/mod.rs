@@ -9,12 +9,16 @@
 
 pub mod build;
 pub mod bxl;
+pub mod cdb;
 pub mod clean;
 pub mod clean_stale;
 pub mod ctargets;
 pub mod debug;
+pub mod expand;
+pub mod fmt;
 pub mod init;
 pub mod install;
+pub mod isolation;
 pub mod kill;
 pub mod killall;
 pub mod log;
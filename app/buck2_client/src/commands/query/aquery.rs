@@ -78,6 +78,7 @@ impl StreamingCommand for AqueryCommand {
                     context: Some(context),
                     output_attributes,
                     unstable_output_format,
+                    action_graph: false,
                 },
                 ctx.stdin()
                     .console_interaction_stream(&self.common_opts.console_opts),
@@ -25,6 +25,8 @@ enum QueryOutputFormatArg {
     Dot,
     Json,
     DotCompact,
+    #[clap(alias = "streaming-parquet")]
+    StreamingParquet,
 }
 
 /// Args common to all the query commands
@@ -53,9 +55,10 @@ pub(crate) struct CommonQueryOptions {
         long_help = "Output format (default: list). \n
            dot -  dot graph format. \n
            dot_compact - compact alternative to dot format. \n
-           json - JSON format.
+           json - JSON format. \n
+           streaming_parquet - Parquet, a row per target and a column per attribute. \n
          ",
-        value_name = "dot|dot_compact|json",
+        value_name = "dot|dot_compact|json|streaming_parquet",
         arg_enum
     )]
     output_format: Option<QueryOutputFormatArg>,
@@ -87,6 +90,7 @@ impl CommonQueryOptions {
             Some(QueryOutputFormatArg::Json) => QueryOutputFormat::Json,
             Some(QueryOutputFormatArg::Dot) => QueryOutputFormat::Dot,
             Some(QueryOutputFormatArg::DotCompact) => QueryOutputFormat::DotCompact,
+            Some(QueryOutputFormatArg::StreamingParquet) => QueryOutputFormat::StreamingParquet,
             None => {
                 if self.json {
                     QueryOutputFormat::Json
@@ -72,6 +72,12 @@ pub struct CqueryCommand {
     )]
     show_providers: bool,
 
+    /// Annotate each dependency edge with its kind: `target`, `exec` or `toolchain`.
+    /// In dot outputs, edges are labelled with their kind. In JSON output, each target has a
+    /// `buck.deps` entry mapping each kind to the deps of that kind.
+    #[clap(long)]
+    output_dep_kinds: bool,
+
     #[allow(rustdoc::bare_urls)]
     /// Enable deprecated `owner()` function behavior.
     ///
@@ -127,6 +133,7 @@ impl StreamingCommand for CqueryCommand {
                     output_attributes,
                     target_universe: self.target_universe,
                     show_providers: self.show_providers,
+                    output_dep_kinds: self.output_dep_kinds,
                     unstable_output_format,
                     correct_owner,
                 },
@@ -56,20 +56,17 @@ pub async fn upload_materializer_state(
         CommandOutcome::Failure(..) => return Err(anyhow::anyhow!("Command failed")),
     }
 
-    let manifold_bucket = manifold::Bucket::RageDumps;
     let manifold_filename = format!("{}_materializer_state", manifold_id);
-    manifold::Upload::new(manifold_bucket, &manifold_filename)
-        .with_default_ttl()
+    let upload =
+        manifold::Upload::new(manifold::Bucket::RageDumps, &manifold_filename).with_default_ttl();
+    let location = upload.location()?;
+    upload
         .from_async_read(&mut Cursor::new(&capture.buf))?
         .spawn()
         .await
-        .context("Error uploading to Manifold")?;
+        .context("Error uploading materializer state")?;
 
-    Ok(format!(
-        "{}/flat/{}",
-        manifold_bucket.info().name,
-        manifold_filename
-    ))
+    Ok(location)
 }
 
 /// Receive StdoutBytes, just capture them.
@@ -240,7 +240,7 @@ impl RageCommand {
             let system_info_command =
                 RageSection::get("System info".to_owned(), timeout, system_info::get);
             let daemon_stderr_command =
-                RageSection::get("Daemon stderr Manifold path".to_owned(), timeout, || {
+                RageSection::get("Daemon stderr upload location".to_owned(), timeout, || {
                     upload_daemon_stderr(stderr_path, &manifold_id)
                 });
             let hg_snapshot_id_command = RageSection::get(
@@ -249,11 +249,11 @@ impl RageCommand {
                 source_control::get_info,
             );
             let dice_dump_command =
-                RageSection::get("Dice dump Manifold path".to_owned(), timeout, || async {
+                RageSection::get("Dice dump upload location".to_owned(), timeout, || async {
                     rage_dumps::upload_dice_dump(buckd.clone()?, dice_dump_dir, &manifold_id).await
                 });
             let materializer_state = RageSection::get(
-                "Materializer state Manifold path".to_owned(),
+                "Materializer state upload location".to_owned(),
                 timeout,
                 || materializer::upload_materializer_state(&buckd, &client_ctx, &manifold_id),
             );
@@ -268,7 +268,7 @@ impl RageCommand {
             };
 
             let event_log_command = {
-                let title = "Event log upload Manifold path".to_owned();
+                let title = "Event log upload location".to_owned();
                 match selected_invocation.as_ref() {
                     None => RageSection::get_skipped(title),
                     Some(path) => {
@@ -404,23 +404,18 @@ async fn upload_daemon_stderr(
         .context(RageError::OpenFileError(path.display().to_string()))?
         .into();
     let filename = format!("{}.stderr", manifold_id);
-    manifold::Upload::new(manifold::Bucket::RageDumps, &filename)
-        .with_default_ttl()
-        .from_stdio(upload_log_file)?
-        .spawn()
-        .await?;
-    Ok(format!("buck2_rage_dumps/flat/{}", filename))
+    let upload = manifold::Upload::new(manifold::Bucket::RageDumps, &filename).with_default_ttl();
+    let location = upload.location()?;
+    upload.from_stdio(upload_log_file)?.spawn().await?;
+    Ok(location)
 }
 
 async fn upload_event_logs(path: &EventLogPathBuf, manifold_id: &str) -> anyhow::Result<String> {
     let filename = format!("{}-event_log{}", manifold_id, path.extension());
-    let bucket = manifold::Bucket::RageDumps;
-    manifold::Upload::new(bucket, &filename)
-        .with_default_ttl()
-        .from_file(path.path())?
-        .spawn()
-        .await?;
-    Ok(format!("{}/flat/{}", bucket.info().name, filename))
+    let upload = manifold::Upload::new(manifold::Bucket::RageDumps, &filename).with_default_ttl();
+    let location = upload.location()?;
+    upload.from_file(path.path())?.spawn().await?;
+    Ok(location)
 }
 
 async fn dispatch_invoked_event(
@@ -33,7 +33,8 @@ pub async fn upload_dice_dump(
         .upload(buckd, manifold_filename)
         .await?;
 
-    Ok(format!("buck2_rage_dumps/flat/{}", manifold_filename))
+    let upload = manifold::Upload::new(manifold::Bucket::RageDumps, manifold_filename);
+    Ok(upload.location()?)
 }
 
 struct DiceDump {
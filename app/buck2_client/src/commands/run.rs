@@ -10,6 +10,8 @@
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::Write;
+use std::process::Command;
+use std::process::ExitStatus;
 
 use anyhow::Context;
 use async_trait::async_trait;
@@ -28,8 +30,13 @@ use buck2_client_ctx::daemon::client::BuckdClientConnector;
 use buck2_client_ctx::daemon::client::NoPartialResultHandler;
 use buck2_client_ctx::exit_result::ExitResult;
 use buck2_client_ctx::streaming::StreamingCommand;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::file_name::FileName;
+use buck2_util::process::background_command;
 use buck2_wrapper_common::BUCK2_WRAPPER_ENV_VAR;
 use buck2_wrapper_common::BUCK_WRAPPER_UUID_ENV_VAR;
+use dupe::Dupe;
 use serde::Serialize;
 use thiserror::Error;
 
@@ -39,6 +46,9 @@ use crate::commands::build::print_build_result;
 ///
 /// The Build ID for the underlying build execution is made available to the target in
 /// the `BUCK_RUN_BUILD_ID` environment variable.
+///
+/// Test targets are run with the command, environment and working directory `buck2 test`
+/// would use to run them locally, and any `TARGET_ARGS` are passed on to the test binary.
 #[derive(Debug, clap::Parser)]
 #[clap(
     name = "run",
@@ -70,9 +80,23 @@ pub struct RunCommand {
 
     /// Instead of running the command, print out the command
     /// formatted for shell interpolation, use as: $(buck2 run --emit-shell ...)
+    /// For a test, the command changes to the working directory of the test first, so evaluate
+    /// it instead: eval "$(buck2 run --emit-shell ...)"
     #[clap(long, group = "exec_options")]
     emit_shell: bool,
 
+    /// Run the target under the profiler of the host: `perf` (or `heaptrack` for memory) on
+    /// Linux, Instruments on macOS and ETW (using `wpr`) on Windows. The profile is written to
+    /// `buck-out/v2/profiles`, under a name derived from the target, and its path is printed
+    /// before running the target.
+    #[clap(
+        long,
+        arg_enum,
+        value_name = "MODE",
+        conflicts_with_all = &["emit-shell", "command-args-file"]
+    )]
+    profile: Option<RunProfileMode>,
+
     #[clap(name = "TARGET", help = "Target to build and run")]
     target: String,
 
@@ -145,9 +169,22 @@ impl StreamingCommand for RunCommand {
         if response.build_targets.is_empty() || response.build_targets[0].run_args.is_empty() {
             return ExitResult::err(RunCommandError::NonBinaryRule(self.target).into());
         }
-        let mut run_args = response.build_targets[0].run_args.clone();
+        let build_target = &response.build_targets[0];
+        let mut run_args = build_target.run_args.clone();
         run_args.extend(self.extra_run_args);
 
+        let mut run_env: Vec<(String, String)> = build_target
+            .run_env
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        run_env.sort();
+        let chdir = match self.chdir {
+            Some(chdir) => Some(chdir),
+            None if !build_target.run_cwd.is_empty() => Some(build_target.run_cwd.clone()),
+            None => None,
+        };
+
         // Special case for recursive invocations of buck; `BUCK2_WRAPPER` is set by wrapper scripts that execute
         // Buck2. We're not a wrapper script, so we unset it to prevent `run` from inheriting it.
         std::env::remove_var(BUCK2_WRAPPER_ENV_VAR);
@@ -161,7 +198,7 @@ impl StreamingCommand for RunCommand {
             let command = CommandArgsFile {
                 path: run_args[0].clone(),
                 argv: run_args,
-                envp: std::env::vars().collect(),
+                envp: std::env::vars().chain(run_env).collect(),
                 is_fix_script: false,
                 print_command: false,
             };
@@ -176,19 +213,46 @@ impl StreamingCommand for RunCommand {
 
         if self.emit_shell {
             if cfg!(unix) {
-                buck2_client_ctx::println!("{}", shlex::join(run_args.iter().map(|a| a.as_str())))?;
+                let env_args: Vec<String> = run_env
+                    .iter()
+                    .map(|(k, v)| format!("{}={}", k, v))
+                    .collect();
+                let env_prefix = (!env_args.is_empty()).then_some("env");
+                let command = shlex::join(
+                    env_prefix
+                        .into_iter()
+                        .chain(env_args.iter().map(|a| a.as_str()))
+                        .chain(run_args.iter().map(|a| a.as_str())),
+                );
+                match &chdir {
+                    Some(chdir) => {
+                        buck2_client_ctx::println!("cd {} && {}", shlex::quote(chdir), command)?
+                    }
+                    None => buck2_client_ctx::println!("{}", command)?,
+                }
                 return ExitResult::success();
             } else {
                 return ExitResult::err(RunCommandError::EmitShellNotSupportedOnWindows.into());
             }
         }
 
-        ExitResult::exec(
-            run_args[0].clone(),
-            run_args,
-            self.chdir,
-            vec![("BUCK_RUN_BUILD_ID".to_owned(), ctx.trace_id.to_string())],
-        )
+        run_env.push(("BUCK_RUN_BUILD_ID".to_owned(), ctx.trace_id.to_string()));
+
+        if let Some(mode) = self.profile {
+            let profiler = Profiler::for_host(mode)?;
+            let profiles_dir = ctx.paths()?.profiles_dir();
+            fs_util::create_dir_all(&profiles_dir)?;
+            let file_name = profiler.file_name(&build_target.target, mode);
+            let output = profiles_dir.join(FileName::new(&file_name)?);
+            buck2_client_ctx::eprintln!(
+                "Writing profile to `{}`{}",
+                output,
+                profiler.output_note()
+            )?;
+            return profiler.run(mode, &output, run_args, chdir, run_env);
+        }
+
+        ExitResult::exec(run_args[0].clone(), run_args, chdir, run_env)
     }
 
     fn console_opts(&self) -> &CommonConsoleOptions {
@@ -224,8 +288,208 @@ struct CommandArgsFile {
 
 #[derive(Error, Debug)]
 pub enum RunCommandError {
-    #[error("Target `{0}` is not a binary rule (only binary rules can be `run`)")]
+    #[error("Target `{0}` is not a binary rule or a test (only those can be `run`)")]
     NonBinaryRule(String),
     #[error("`--emit-shell` is not supported on Windows")]
     EmitShellNotSupportedOnWindows,
+    #[error("`--profile` is only supported on Linux, macOS and Windows")]
+    ProfileNotSupportedOnHost,
+    #[error("`{0}` failed with {1}")]
+    ProfilerFailed(String, ExitStatus),
+}
+
+/// What `buck2 run --profile` records.
+#[derive(Debug, Clone, Copy, Dupe, clap::ArgEnum)]
+#[clap(rename_all = "snake_case")]
+enum RunProfileMode {
+    /// Where the CPU time is spent.
+    Cpu,
+    /// Where the memory is allocated.
+    Memory,
+}
+
+impl RunProfileMode {
+    fn name(self) -> &'static str {
+        match self {
+            RunProfileMode::Cpu => "cpu",
+            RunProfileMode::Memory => "memory",
+        }
+    }
+}
+
+/// The profiler used by `buck2 run --profile`, which depends on the host OS (and on the mode on
+/// Linux, as `perf` can't record allocations).
+#[derive(Debug, Clone, Copy, Dupe)]
+enum Profiler {
+    Perf,
+    Heaptrack,
+    Instruments,
+    Wpr,
+}
+
+impl Profiler {
+    fn for_host(mode: RunProfileMode) -> anyhow::Result<Self> {
+        if cfg!(target_os = "linux") {
+            match mode {
+                RunProfileMode::Cpu => Ok(Profiler::Perf),
+                RunProfileMode::Memory => Ok(Profiler::Heaptrack),
+            }
+        } else if cfg!(target_os = "macos") {
+            Ok(Profiler::Instruments)
+        } else if cfg!(windows) {
+            Ok(Profiler::Wpr)
+        } else {
+            Err(RunCommandError::ProfileNotSupportedOnHost.into())
+        }
+    }
+
+    /// The name of the profile of `target`, which is the same across runs so that a new profile
+    /// replaces the previous one.
+    fn file_name(self, target: &str, mode: RunProfileMode) -> String {
+        // Drop the configuration of the target, if any.
+        let target = target.split_once(' ').map_or(target, |(target, _)| target);
+        // Other characters are escaped rather than replaced, so that distinct targets never share
+        // a profile.
+        let mut escaped = String::new();
+        for b in target.bytes() {
+            if b.is_ascii_alphanumeric() || b == b'-' || b == b'.' {
+                escaped.push(b as char);
+            } else {
+                escaped.push_str(&format!("_{:02x}", b));
+            }
+        }
+        let target = escaped;
+        let extension = match self {
+            Profiler::Perf => "perf.data",
+            Profiler::Heaptrack => "heaptrack",
+            Profiler::Instruments => "trace",
+            Profiler::Wpr => "etl",
+        };
+        format!("{}.{}.{}", target, mode.name(), extension)
+    }
+
+    /// How the path of the profile differs from the one passed to the profiler, if it does.
+    fn output_note(self) -> &'static str {
+        match self {
+            Profiler::Heaptrack => " (heaptrack appends the extension of its compression)",
+            Profiler::Perf | Profiler::Instruments | Profiler::Wpr => "",
+        }
+    }
+
+    fn run(
+        self,
+        mode: RunProfileMode,
+        output: &AbsNormPath,
+        run_args: Vec<String>,
+        chdir: Option<String>,
+        run_env: Vec<(String, String)>,
+    ) -> ExitResult {
+        let output = output.to_string();
+        let profiler_args = match (self, mode) {
+            // Only used to profile CPU time.
+            (Profiler::Perf, _) => vec!["perf", "record", "-g", "-o", output.as_str(), "--"],
+            // Records every allocation with its backtrace.
+            (Profiler::Heaptrack, _) => vec!["heaptrack", "-o", output.as_str()],
+            (Profiler::Instruments, mode) => {
+                let template = match mode {
+                    RunProfileMode::Cpu => "Time Profiler",
+                    RunProfileMode::Memory => "Allocations",
+                };
+                vec![
+                    "xcrun",
+                    "xctrace",
+                    "record",
+                    "--template",
+                    template,
+                    "--output",
+                    output.as_str(),
+                    "--launch",
+                    "--",
+                ]
+            }
+            (Profiler::Wpr, mode) => return run_under_wpr(mode, &output, run_args, chdir, run_env),
+        };
+        let argv: Vec<String> = profiler_args
+            .into_iter()
+            .map(str::to_owned)
+            .chain(run_args)
+            .collect();
+        ExitResult::exec(argv[0].clone(), argv, chdir, run_env)
+    }
+}
+
+/// `wpr` records ETW events system wide rather than launching a process, so start recording,
+/// run the target and stop recording to `output`.
+fn run_under_wpr(
+    mode: RunProfileMode,
+    output: &str,
+    run_args: Vec<String>,
+    chdir: Option<String>,
+    run_env: Vec<(String, String)>,
+) -> ExitResult {
+    let profile = match mode {
+        RunProfileMode::Cpu => "CPU",
+        RunProfileMode::Memory => "VirtualAllocation",
+    };
+    run_wpr(&["-start", profile, "-filemode"])?;
+
+    let mut command = Command::new(&run_args[0]);
+    command.args(&run_args[1..]).envs(run_env);
+    if let Some(chdir) = chdir {
+        command.current_dir(chdir);
+    }
+    let status = command
+        .status()
+        .with_context(|| format!("Failed to execute target process, running {:?}", run_args));
+
+    // Stop recording even if the target could not be run.
+    run_wpr(&["-stop", output])?;
+
+    ExitResult::status_extended(status?.code().unwrap_or(1))
+}
+
+fn run_wpr(args: &[&str]) -> anyhow::Result<()> {
+    let status = background_command("wpr")
+        .args(args)
+        .status()
+        .context("Failed to run `wpr`, is the Windows Performance Toolkit installed?")?;
+    if !status.success() {
+        let command = format!("wpr {}", args.join(" "));
+        return Err(RunCommandError::ProfilerFailed(command, status).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::commands::run::Profiler;
+    use crate::commands::run::RunProfileMode;
+
+    #[test]
+    fn test_profile_file_name() {
+        assert_eq!(
+            "root_2f_2ffoo_3abar.cpu.perf.data",
+            Profiler::Perf.file_name(
+                "root//foo:bar (cfg//:linux-x86_64#0123)",
+                RunProfileMode::Cpu
+            )
+        );
+        assert_eq!(
+            "root_2f_2ffoo_3abar.memory.heaptrack",
+            Profiler::Heaptrack.file_name("root//foo:bar", RunProfileMode::Memory)
+        );
+        assert_eq!(
+            "root_2f_2ffoo_3abar-baz.memory.etl",
+            Profiler::Wpr.file_name("root//foo:bar-baz", RunProfileMode::Memory)
+        );
+        // Targets which only differ in characters which are not kept have distinct profiles.
+        assert_ne!(
+            Profiler::Perf.file_name("root//foo:bar_baz", RunProfileMode::Cpu),
+            Profiler::Perf.file_name("root//foo:bar/baz", RunProfileMode::Cpu)
+        );
+        assert_ne!(
+            Profiler::Perf.file_name("root//foo:bar_3abaz", RunProfileMode::Cpu),
+            Profiler::Perf.file_name("root//foo:bar:baz", RunProfileMode::Cpu)
+        );
+    }
 }
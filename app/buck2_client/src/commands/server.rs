@@ -31,7 +31,7 @@ impl StreamingCommand for ServerCommand {
         _matches: &ArgMatches,
         _ctx: &mut ClientCommandContext<'_>,
     ) -> ExitResult {
-        let status = buckd.with_flushing().status(false).await?;
+        let status = buckd.with_flushing().status(false, false).await?;
         buck2_client_ctx::println!("buckd.endpoint={}", status.process_info.unwrap().endpoint)?;
         ExitResult::success()
     }
@@ -27,6 +27,13 @@ enum StatusError {
 pub struct StatusCommand {
     #[clap(long, help = "Whether to include a state snapshot in the output.")]
     snapshot: bool,
+
+    #[clap(
+        long,
+        help = "Include everything: a state snapshot, the commands running on the daemon with \
+                their clients, and the state of the RE session."
+    )]
+    all: bool,
 }
 
 impl StatusCommand {
@@ -54,7 +61,10 @@ impl StatusCommand {
                     Ok(())
                 }
                 Ok(mut client) => {
-                    let status = client.with_flushing().status(self.snapshot).await?;
+                    let status = client
+                        .with_flushing()
+                        .status(self.snapshot || self.all, self.all)
+                        .await?;
                     let timestamp = match status.start_time {
                         None => "unknown".to_owned(),
                         Some(timestamp) => Self::timestamp_to_string(
@@ -69,13 +79,18 @@ impl StatusCommand {
                             Self::duration_to_string(uptime)
                         }
                     };
-                    let json_status = serde_json::json!({
+                    let mut json_status = serde_json::json!({
                         "start_time": timestamp,
                         "uptime": uptime,
                         "process_info": serde_json::to_value(status.process_info)?,
                         "daemon_constraints": serde_json::to_value(status.daemon_constraints)?,
                         "snapshot": serde_json::to_value(status.snapshot)?,
                     });
+                    if self.all {
+                        json_status["active_commands"] =
+                            serde_json::to_value(status.active_commands)?;
+                        json_status["re_session_id"] = serde_json::to_value(status.re_session_id)?;
+                    }
                     buck2_client_ctx::println!("{}", serde_json::to_string_pretty(&json_status)?)?;
                     Ok(())
                 }
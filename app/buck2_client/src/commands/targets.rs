@@ -10,6 +10,7 @@
 use async_trait::async_trait;
 use buck2_cli_proto::targets_request;
 use buck2_cli_proto::targets_request::OutputFormat;
+use buck2_cli_proto::targets_show_outputs_response::TargetPaths;
 use buck2_cli_proto::TargetsRequest;
 use buck2_client_ctx::client_ctx::ClientCommandContext;
 use buck2_client_ctx::common::CommonBuildConfigurationOptions;
@@ -33,6 +34,8 @@ enum TargetsError {
     /// Clap should report it, but if we missed something, this is a fallback.
     #[error("Flags are mutually exclusive")]
     IncompatibleArguments,
+    #[error("`--dot` can only be used with `--imports` or `--recursive-imports`")]
+    DotRequiresImports,
 }
 
 // Use non-camel case so the possible values match buck1's
@@ -83,6 +86,12 @@ pub struct TargetsCommand {
     #[clap(long)]
     stats: bool,
 
+    /// Print the imports as a graph in DOT format, with an edge from each file to each file
+    /// it loads directly. Requires `--imports` or `--recursive-imports`, which give the same
+    /// graph, as it already contains the imports of every loaded file.
+    #[clap(long, conflicts_with_all = &["json", "json-lines", "stats"])]
+    dot: bool,
+
     /// Print the fully-qualified build target for the specified aliases
     #[clap(long, alias = "resolvealias")]
     resolve_alias: bool,
@@ -127,6 +136,31 @@ pub struct TargetsCommand {
     #[clap(long, action = clap::ArgAction::Set, default_value = "true", conflicts_with = "streaming")]
     target_hash_recursive: bool,
 
+    /// Also hash the build file defining each target and the `.bzl` files it transitively loads,
+    /// so that changes to macros or rules are reflected in target hashes. Those files are hashed
+    /// according to --target-hash-file-mode, and have no effect when it is `NONE`.
+    #[clap(long, conflicts_with = "streaming")]
+    target_hash_build_file_inputs: bool,
+
+    /// Print a snapshot of the targets: their labels and a hash of their attributes, in a
+    /// compact format to compare against later with `--diff`.
+    #[clap(
+        long,
+        conflicts_with_all = &["streaming", "json-lines", "stats", "dot", "show-target-hash", "diff"]
+    )]
+    snapshot: bool,
+
+    /// Compare the targets with a snapshot written by `--snapshot`, and print those which were
+    /// added (`+`), removed (`-`), or whose attributes changed (`~`), or a JSON object with
+    /// `--json`. Packages which failed to evaluate in either snapshot are listed (`!`) instead of
+    /// their targets. The snapshot should have been taken with the same patterns.
+    #[clap(
+        long,
+        value_name = "SNAPSHOT",
+        conflicts_with_all = &["streaming", "json-lines", "stats", "dot", "show-target-hash"]
+    )]
+    diff: Option<PathArg>,
+
     #[clap(flatten)]
     attributes: CommonAttributeArgs,
 
@@ -135,7 +169,10 @@ pub struct TargetsCommand {
     #[clap(long)]
     include_defaults: bool,
 
-    /// Print the path to the output for each of the rules relative to the cell
+    /// Print the path to the output for each of the rules relative to the cell. The paths are
+    /// those the outputs would have if built for the target platform, only analysis is done.
+    /// With `--json`, print a list of objects with the `target`, the `configured_target` and its
+    /// `outputs`.
     #[clap(long)]
     show_output: bool,
 
@@ -163,6 +200,12 @@ pub struct TargetsCommand {
     #[clap(long, requires = "streaming")]
     imports: bool,
 
+    /// Show the transitive imports of each package. Like `--imports`, but each package's output
+    /// lists every `.bzl` file loaded directly or indirectly by its build file, and no separate
+    /// output is produced per `.bzl` file.
+    #[clap(long, requires = "streaming", conflicts_with = "imports")]
+    recursive_imports: bool,
+
     /// File to put the output in, rather than sending to stdout.
     ///
     /// File will be created if it does not exist, and overwritten if it does.
@@ -180,7 +223,15 @@ pub struct TargetsCommand {
 
 impl TargetsCommand {
     fn output_format(&self) -> anyhow::Result<OutputFormat> {
-        if self.json {
+        if self.dot {
+            if self.json || self.json_lines || self.stats {
+                return Err(TargetsError::IncompatibleArguments.into());
+            }
+            if !self.imports && !self.recursive_imports {
+                return Err(TargetsError::DotRequiresImports.into());
+            }
+            Ok(OutputFormat::Dot)
+        } else if self.json {
             if self.json_lines || self.stats {
                 return Err(TargetsError::IncompatibleArguments.into());
             }
@@ -255,6 +306,10 @@ impl StreamingCommand for TargetsCommand {
             .target_hash_modified_paths
             .into_try_map(|path| path.resolve(&ctx.working_dir).into_string())?;
 
+        let diff_snapshot = self
+            .diff
+            .try_map(|path| path.resolve(&ctx.working_dir).into_string())?;
+
         let target_request = TargetsRequest {
             context,
             target_patterns: self.patterns.map(|pat| buck2_data::TargetPattern {
@@ -282,10 +337,14 @@ impl StreamingCommand for TargetsCommand {
                     target_hash_graph_type,
                     include_default_attributes: self.include_defaults,
                     target_hash_recursive: self.target_hash_recursive,
+                    target_hash_build_file_inputs: self.target_hash_build_file_inputs,
                     keep_going: self.keep_going,
                     streaming: self.streaming,
                     cached: !self.no_cache,
                     imports: self.imports,
+                    recursive_imports: self.recursive_imports,
+                    snapshot: self.snapshot,
+                    diff_snapshot,
                 })
             }),
             output: self
@@ -302,6 +361,7 @@ impl StreamingCommand for TargetsCommand {
                 buckd,
                 target_request,
                 None,
+                self.json,
                 &self.common_opts.console_opts,
             )
             .await
@@ -312,6 +372,7 @@ impl StreamingCommand for TargetsCommand {
                 buckd,
                 target_request,
                 Some(project_root.root()),
+                self.json,
                 &self.common_opts.console_opts,
             )
             .await
@@ -344,6 +405,7 @@ async fn targets_show_outputs(
     buckd: &mut BuckdClientConnector,
     target_request: TargetsRequest,
     root_path: Option<&AbsNormPath>,
+    json: bool,
     console_opts: &CommonConsoleOptions,
 ) -> ExitResult {
     let response = buckd
@@ -354,29 +416,60 @@ async fn targets_show_outputs(
             &mut NoPartialResultHandler,
         )
         .await??;
-    for target_paths in response.targets_paths {
-        for path in target_paths.paths {
+    let rendered = render_targets_outputs(response.targets_paths, root_path, json)?;
+    if !rendered.is_empty() {
+        buck2_client_ctx::print!("{}", rendered)?;
+    }
+    ExitResult::success()
+}
+
+#[derive(serde::Serialize)]
+struct TargetOutputs {
+    target: String,
+    configured_target: String,
+    outputs: Vec<String>,
+}
+
+/// Render the output paths of the targets, relative to `root_path` if set, as a list of objects
+/// with `json`, or as `target path` lines otherwise.
+fn render_targets_outputs(
+    targets_paths: Vec<TargetPaths>,
+    root_path: Option<&AbsNormPath>,
+    json: bool,
+) -> anyhow::Result<String> {
+    let mut rendered = String::new();
+    let mut targets_outputs = Vec::new();
+    for target_paths in targets_paths {
+        let outputs = target_paths.paths.into_map(|path| {
             let path = if cfg!(windows) {
                 path.replace('/', "\\")
             } else {
                 path
             };
             match root_path {
-                Some(root) => {
-                    buck2_client_ctx::println!(
-                        "{} {}",
-                        target_paths.target,
-                        root.as_path().join(path).display()
-                    )
-                }
-                None => buck2_client_ctx::println!("{} {}", target_paths.target, path),
-            }?;
+                Some(root) => root.as_path().join(path).display().to_string(),
+                None => path,
+            }
+        });
+        if json {
+            targets_outputs.push(TargetOutputs {
+                target: target_paths.target,
+                configured_target: target_paths.configured_target,
+                outputs,
+            });
+        } else {
+            for output in outputs {
+                rendered.push_str(&format!("{} {}\n", target_paths.target, output));
+            }
         }
     }
-    ExitResult::success()
+    if json {
+        rendered = format!("{}\n", serde_json::to_string_pretty(&targets_outputs)?);
+    }
+    Ok(rendered)
 }
 
-async fn targets(
+pub(crate) async fn targets(
     stdin: &mut Stdin,
     buckd: &mut BuckdClientConnector,
     target_request: TargetsRequest,
@@ -395,3 +488,54 @@ async fn targets(
     }
     ExitResult::success()
 }
+
+#[cfg(test)]
+mod tests {
+    use buck2_cli_proto::targets_show_outputs_response::TargetPaths;
+
+    use crate::commands::targets::render_targets_outputs;
+
+    fn targets_paths() -> Vec<TargetPaths> {
+        vec![TargetPaths {
+            target: "root//foo:bar".to_owned(),
+            paths: vec![
+                "buck-out/v2/gen/root/abc/foo/__bar__/a".to_owned(),
+                "buck-out/v2/gen/root/abc/foo/__bar__/b".to_owned(),
+            ],
+            configured_target: "root//foo:bar (cfg//:linux#abc)".to_owned(),
+        }]
+    }
+
+    #[test]
+    fn test_render_targets_outputs() -> anyhow::Result<()> {
+        if cfg!(windows) {
+            return Ok(());
+        }
+        assert_eq!(
+            render_targets_outputs(targets_paths(), None, false)?,
+            "root//foo:bar buck-out/v2/gen/root/abc/foo/__bar__/a\n\
+            root//foo:bar buck-out/v2/gen/root/abc/foo/__bar__/b\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_targets_outputs_json() -> anyhow::Result<()> {
+        if cfg!(windows) {
+            return Ok(());
+        }
+        let rendered = render_targets_outputs(targets_paths(), None, true)?;
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&rendered)?,
+            serde_json::json!([{
+                "target": "root//foo:bar",
+                "configured_target": "root//foo:bar (cfg//:linux#abc)",
+                "outputs": [
+                    "buck-out/v2/gen/root/abc/foo/__bar__/a",
+                    "buck-out/v2/gen/root/abc/foo/__bar__/b",
+                ],
+            }])
+        );
+        Ok(())
+    }
+}
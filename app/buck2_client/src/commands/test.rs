@@ -30,6 +30,8 @@ use buck2_client_ctx::subscribers::superconsole::test::span_from_build_failure_c
 use buck2_client_ctx::subscribers::superconsole::test::TestCounterColumn;
 use buck2_core::fs::fs_util;
 use buck2_core::fs::working_dir::WorkingDir;
+use buck2_event_observer::display::display_configured_target_label;
+use buck2_event_observer::display::TargetDisplayOptions;
 use gazebo::prelude::*;
 use superconsole::Line;
 use superconsole::Span;
@@ -65,6 +67,27 @@ fn print_error_counter(
     }
     Ok(())
 }
+/// Render the output of `buck2 test --list`.
+fn listed_tests_to_json(listed_tests: &[buck2_data::TestSuite]) -> anyhow::Result<String> {
+    let suites = listed_tests
+        .iter()
+        .map(|suite| {
+            let target = suite
+                .target_label
+                .as_ref()
+                .context("Missing `target_label`")?;
+            Ok(serde_json::json!({
+                "target": display_configured_target_label(target, TargetDisplayOptions::for_console(false))?,
+                "suite": suite.suite_name,
+                "test_cases": suite.test_names,
+            }))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let mut out = serde_json::to_string_pretty(&suites)?;
+    out.push('\n');
+    Ok(out)
+}
+
 #[derive(Debug, clap::Parser)]
 #[clap(name = "test", about = "Build and test the specified targets")]
 pub struct TestCommand {
@@ -123,6 +146,34 @@ If include patterns are present, regardless of whether exclude patterns are pres
     #[clap(long, group = "re_options", alias = "unstable-force-tests-on-re")]
     unstable_allow_all_tests_on_re: bool,
 
+    /// Build the tests and list the test cases they contain as JSON on stdout, without running
+    /// them. Test cases are only listed if the test runner supports discovering them; otherwise
+    /// each test suite is listed with no test cases.
+    #[clap(long)]
+    list: bool,
+
+    /// Collect code coverage. Tests get a directory to write coverage data to in
+    /// `BUCK2_COVERAGE_DIR`, and `LLVM_PROFILE_FILE` pointing into it. The files are merged with
+    /// `test.coverage_merge_command` if set, and listed in a coverage report under buck-out.
+    #[clap(long, conflicts_with = "list")]
+    coverage: bool,
+
+    /// Don't test the targets listed in the `tests` attribute of the targets being tested.
+    #[clap(long)]
+    ignore_tests_attribute: bool,
+
+    /// Also test the targets listed in the `tests` attribute of targets which depend on the
+    /// targets being tested, up to this many levels of reverse dependencies (1 means direct
+    /// dependents only). The tests found this way are listed before any test runs.
+    #[clap(long, value_name = "DEPTH")]
+    rdeps_tests_depth: Option<u32>,
+
+    /// Patterns of targets in which to look for the reverse dependencies of
+    /// `--rdeps-tests-depth`. Defaults to all the targets in and below the packages of the
+    /// targets being tested.
+    #[clap(long, value_name = "PATTERN", requires = "rdeps-tests-depth")]
+    rdeps_tests_universe: Vec<String>,
+
     #[clap(name = "TARGET_PATTERNS", help = "Patterns to test")]
     patterns: Vec<String>,
 
@@ -194,7 +245,14 @@ impl StreamingCommand for TestCommand {
                             || self.unstable_allow_all_tests_on_re,
                         force_use_project_relative_paths: self.unstable_allow_all_tests_on_re,
                         force_run_from_project_root: self.unstable_allow_all_tests_on_re,
+                        list_only: self.list,
+                        coverage: self.coverage,
                     }),
+                    ignore_tests_attribute: self.ignore_tests_attribute,
+                    rdeps_tests_depth: self.rdeps_tests_depth,
+                    rdeps_tests_universe: self
+                        .rdeps_tests_universe
+                        .map(|pat| buck2_data::TargetPattern { value: pat.clone() }),
                 },
                 ctx.stdin()
                     .console_interaction_stream(&self.common_opts.console_opts),
@@ -222,6 +280,17 @@ impl StreamingCommand for TestCommand {
             console.print_error(&format!("{} BUILDS FAILED", response.error_messages.len()))?;
         }
 
+        if self.list {
+            print_error_counter(&console, listing_failed, "LISTINGS FAILED", "⚠")?;
+            let listing = listed_tests_to_json(&response.listed_tests)?;
+            let exit_result = if response.error_messages.is_empty() && listing_failed.count == 0 {
+                ExitResult::success()
+            } else {
+                ExitResult::failure()
+            };
+            return exit_result.with_stdout(listing.into_bytes());
+        }
+
         // TODO(nmj): Might make sense for us to expose the event ctx, and use its
         //            handle_stdout method, instead of raw buck2_client::println!s here.
         // TODO: also remove the duplicate information when the above is done.
@@ -253,6 +322,12 @@ impl StreamingCommand for TestCommand {
         if passed.count + failed.count + fatals.count + skipped.count == 0 {
             console.print_warning("NO TESTS RAN")?;
         }
+        if let Some(report) = &response.artifacts_report {
+            console.print_stderr(&format!("Test report: {}", report))?;
+        }
+        if let Some(report) = &response.coverage_report {
+            console.print_stderr(&format!("Coverage report: {}", report))?;
+        }
 
         match self.test_executor_stderr {
             Some(OutputDestinationArg::Path(path)) => {
@@ -294,3 +369,59 @@ impl StreamingCommand for TestCommand {
         &self.common_opts.config_opts
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suite(name: &str, suite_name: &str, test_names: &[&str]) -> buck2_data::TestSuite {
+        buck2_data::TestSuite {
+            suite_name: suite_name.to_owned(),
+            test_names: test_names.iter().map(|t| (*t).to_owned()).collect(),
+            target_label: Some(buck2_data::ConfiguredTargetLabel {
+                label: Some(buck2_data::TargetLabel {
+                    package: "root//pkg".to_owned(),
+                    name: name.to_owned(),
+                }),
+                configuration: Some(buck2_data::Configuration {
+                    full_name: "cfg#0123".to_owned(),
+                }),
+                execution_configuration: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_listed_tests_to_json() -> anyhow::Result<()> {
+        let listed = [
+            suite("a", "suite_a", &["first", "second"]),
+            // Runners which can't discover test cases list no cases.
+            suite("b", "suite_b", &[]),
+        ];
+        let json = listed_tests_to_json(&listed)?;
+        assert!(json.ends_with('\n'));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json)?,
+            serde_json::json!([
+                {
+                    "target": "root//pkg:a",
+                    "suite": "suite_a",
+                    "test_cases": ["first", "second"],
+                },
+                {
+                    "target": "root//pkg:b",
+                    "suite": "suite_b",
+                    "test_cases": [],
+                },
+            ])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_listed_tests_to_json_missing_target() {
+        let mut listed = suite("a", "suite_a", &[]);
+        listed.target_label = None;
+        assert!(listed_tests_to_json(&[listed]).is_err());
+    }
+}
@@ -26,6 +26,7 @@ use crate::common::CommonBuildConfigurationOptions;
 use crate::common::HostArchOverride;
 use crate::common::HostPlatformOverride;
 use crate::daemon::client::connect::BuckdConnectOptions;
+use crate::daemon::client::restart_policy::DaemonRestartPolicy;
 use crate::daemon::client::BuckdClientConnector;
 use crate::restarter::Restarter;
 use crate::stdin::Stdin;
@@ -36,6 +37,8 @@ pub struct ClientCommandContext<'a> {
     pub paths: SharedResult<InvocationPaths>,
     pub working_dir: WorkingDir,
     pub verbosity: Verbosity,
+    /// Whether to restart a daemon which does not match the client.
+    pub daemon_restart_policy: DaemonRestartPolicy,
     /// When set, this function is called to launch in process daemon.
     /// The function returns `Ok` when daemon successfully started
     /// and ready to accept connections.
@@ -93,7 +96,17 @@ impl<'a> ClientCommandContext<'a> {
         // TODO(cjhopman): Support non unicode paths?
         Ok(ClientContext {
             config_overrides: config_opts.config_overrides(arg_matches)?,
-            target_platform: config_opts.target_platforms.clone().unwrap_or_default(),
+            target_platform: config_opts
+                .target_platforms
+                .first()
+                .cloned()
+                .unwrap_or_default(),
+            extra_target_platforms: config_opts
+                .target_platforms
+                .iter()
+                .skip(1)
+                .cloned()
+                .collect(),
             host_platform: match config_opts.host_platform_override() {
                 HostPlatformOverride::Default => GrpcHostPlatformOverride::DefaultPlatform,
                 HostPlatformOverride::Linux => GrpcHostPlatformOverride::Linux,
@@ -119,6 +132,7 @@ impl<'a> ClientCommandContext<'a> {
                 .map(|path| path.to_string())
                 .collect(),
             target_call_stacks: config_opts.target_call_stacks,
+            aggregate_attr_errors: config_opts.aggregate_attr_errors,
             ..self.empty_client_context()?
         })
     }
@@ -143,12 +157,14 @@ impl<'a> ClientCommandContext<'a> {
                 .to_owned(),
             config_overrides: Default::default(),
             target_platform: Default::default(),
+            extra_target_platforms: Vec::new(),
             host_platform: Default::default(),
             host_arch: Default::default(),
             host_xcode_version: Default::default(),
             oncall: Default::default(),
             disable_starlark_types: false,
             target_call_stacks: false,
+            aggregate_attr_errors: false,
             trace_id: format!("{}", self.trace_id),
             reuse_current_config: false,
             daemon_uuid,
@@ -156,6 +172,7 @@ impl<'a> ClientCommandContext<'a> {
             argfiles: Vec::new(),
             buck2_hard_error: BUCK2_HARD_ERROR_ENV_VAR.get()?.cloned().unwrap_or_default(),
             exit_when_different_state: false,
+            client_pid: std::process::id() as i64,
         })
     }
 
@@ -166,11 +166,12 @@ pub struct CommonBuildConfigurationOptions {
 
     #[clap(
         long = "target-platforms",
-        help = "Configuration target (one) to use to configure targets",
+        help = "Configuration target to use to configure targets. \
+            `build` accepts this flag several times to build the targets for each of the platforms",
         number_of_values = 1,
         value_name = "PLATFORM"
     )]
-    pub target_platforms: Option<String>,
+    pub target_platforms: Vec<String>,
 
     #[clap(long, ignore_case = true, value_name = "HOST", arg_enum)]
     fake_host: Option<HostPlatformOverride>,
@@ -202,6 +203,16 @@ pub struct CommonBuildConfigurationOptions {
     #[clap(long = "stack")]
     pub target_call_stacks: bool,
 
+    /// Report all attribute errors at once.
+    ///
+    /// By default, evaluation of a build file stops at the first invalid
+    /// attribute. With this flag, all attributes of a target which fail to coerce
+    /// are reported together, and evaluation of the package continues past targets
+    /// with invalid, missing or unknown attributes, so that errors of all the
+    /// targets in the package are reported in one error.
+    #[clap(long)]
+    pub aggregate_attr_errors: bool,
+
     #[clap(long)]
     pub reuse_current_config: bool,
 
@@ -304,13 +315,14 @@ impl CommonBuildConfigurationOptions {
         static DEFAULT: CommonBuildConfigurationOptions = CommonBuildConfigurationOptions {
             config_values: vec![],
             config_files: vec![],
-            target_platforms: None,
+            target_platforms: Vec::new(),
             fake_host: None,
             fake_arch: None,
             fake_xcode_version: None,
             oncall: None,
             disable_starlark_types: false,
             target_call_stacks: false,
+            aggregate_attr_errors: false,
             reuse_current_config: false,
             exit_when_different_state: false,
         };
@@ -339,11 +351,15 @@ pub struct CommonBuildOptions {
     #[clap(short = 'j', long = "num-threads", value_name = "THREADS")]
     pub num_threads: Option<u32>,
 
-    /// Enable only local execution. Will reject actions that cannot execute locally.
+    /// Enable only local execution. Actions run locally even if their execution platform is
+    /// configured for remote or hybrid execution. The remote cache is still used unless
+    /// `--no-remote-cache` is passed.
     #[clap(long, group = "build_strategy")]
     local_only: bool,
 
-    /// Enable only remote execution. Will reject actions that cannot execute remotely.
+    /// Enable only remote execution. Actions run remotely even if their execution platform is
+    /// configured for hybrid execution. Fails if the execution platform only allows local
+    /// execution, and rejects actions that cannot execute remotely (`local_only = True`).
     #[clap(long, group = "build_strategy")]
     remote_only: bool,
 
@@ -385,6 +401,14 @@ pub struct CommonBuildOptions {
     #[clap(long)]
     upload_all_actions: bool,
 
+    /// Upload the results and outputs of all locally executed actions to the remote cache, even
+    /// if the executor config or the action itself does not allow cache uploads. Intended for
+    /// seeding shared caches from a trusted machine. Actions that set
+    /// `allow_forced_cache_upload = False` are still not uploaded. Actions whose executor config
+    /// has no remote cache fail.
+    #[clap(long, conflicts_with = "no-remote-cache")]
+    upload_all_outputs: bool,
+
     /// If Buck hits an error, do as little work as possible before exiting.
     #[clap(long, group = "fail-when")]
     fail_fast: bool,
@@ -430,6 +454,7 @@ impl CommonBuildOptions {
             unstable_build_report_filename,
             eager_dep_files: self.eager_dep_files,
             upload_all_actions: self.upload_all_actions,
+            upload_all_outputs: self.upload_all_outputs,
             skip_cache_read: self.no_remote_cache,
             skip_cache_write: self.no_remote_cache && !self.write_to_cache_anyway,
             fail_fast: self.fail_fast,
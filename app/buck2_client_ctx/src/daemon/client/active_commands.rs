@@ -0,0 +1,101 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use buck2_cli_proto::ActiveCommandStatus;
+use buck2_common::convert::ProstDurationExt;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+enum ActiveCommandsError {
+    #[error(
+        "The buck2 daemon is running {count} other command(s), which `buck2 {command}` would \
+        interrupt:\n{commands}\nWait for them to finish, or pass `--force` to interrupt them"
+    )]
+    Busy {
+        command: &'static str,
+        count: usize,
+        commands: String,
+    },
+}
+
+/// Fail if the daemon is running commands, which `command` would interrupt.
+pub fn check_no_active_commands(
+    command: &'static str,
+    active_commands: &[ActiveCommandStatus],
+) -> anyhow::Result<()> {
+    if active_commands.is_empty() {
+        return Ok(());
+    }
+    Err(ActiveCommandsError::Busy {
+        command,
+        count: active_commands.len(),
+        commands: describe_commands(active_commands),
+    }
+    .into())
+}
+
+/// One line per command: the PID of its client, how long it has been running and its arguments.
+pub(crate) fn describe_commands(active_commands: &[ActiveCommandStatus]) -> String {
+    active_commands
+        .iter()
+        .map(|c| {
+            let pid = match c.client_pid {
+                0 => "unknown".to_owned(),
+                pid => pid.to_string(),
+            };
+            let elapsed = match c.elapsed.as_ref().map(|d| d.try_into_duration()) {
+                Some(Ok(elapsed)) => format!("{}s", elapsed.as_secs()),
+                _ => "unknown".to_owned(),
+            };
+            format!(
+                "  pid {}, running for {}: {}",
+                pid,
+                elapsed,
+                c.argv.join(" ")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use buck2_cli_proto::ActiveCommandStatus;
+
+    use crate::daemon::client::active_commands::check_no_active_commands;
+    use crate::daemon::client::active_commands::describe_commands;
+
+    #[test]
+    fn test_check_no_active_commands() -> anyhow::Result<()> {
+        let busy = vec![
+            ActiveCommandStatus {
+                argv: vec!["buck2".to_owned(), "build".to_owned(), "//:foo".to_owned()],
+                client_pid: 1234,
+                elapsed: Some(Duration::from_millis(62500).try_into()?),
+                ..Default::default()
+            },
+            ActiveCommandStatus {
+                argv: vec!["buck2".to_owned(), "test".to_owned()],
+                ..Default::default()
+            },
+        ];
+        assert_eq!(
+            describe_commands(&busy),
+            "  pid 1234, running for 62s: buck2 build //:foo\n  \
+            pid unknown, running for unknown: buck2 test"
+        );
+
+        assert!(check_no_active_commands("kill", &[]).is_ok());
+        let err = check_no_active_commands("kill", &busy).unwrap_err();
+        assert!(format!("{:#}", err).contains("`buck2 kill` would interrupt"));
+        Ok(())
+    }
+}
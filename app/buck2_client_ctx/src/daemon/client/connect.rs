@@ -15,6 +15,7 @@ use std::time::Duration;
 
 use anyhow::Context;
 use buck2_cli_proto::daemon_api_client::DaemonApiClient;
+use buck2_cli_proto::ActiveCommandStatus;
 use buck2_cli_proto::DaemonProcessInfo;
 use buck2_common::buckd_connection::ConnectionType;
 use buck2_common::buckd_connection::BUCK_AUTH_TOKEN_HEADER;
@@ -38,6 +39,9 @@ use tonic::Status;
 
 use crate::command_outcome::CommandOutcome;
 use crate::daemon::client::kill;
+use crate::daemon::client::restart_policy::check_restart_allowed;
+use crate::daemon::client::restart_policy::is_interactive;
+use crate::daemon::client::restart_policy::DaemonRestartPolicy;
 use crate::daemon::client::BuckdClient;
 use crate::daemon::client::BuckdClientConnector;
 use crate::daemon::client::BuckdLifecycleLock;
@@ -72,6 +76,7 @@ pub struct DaemonConstraintsRequest {
     desired_trace_io_state: DesiredTraceIoState,
     pub reject_daemon: Option<String>,
     pub reject_materializer_state: Option<String>,
+    pub restart_policy: DaemonRestartPolicy,
 }
 
 impl DaemonConstraintsRequest {
@@ -82,21 +87,27 @@ impl DaemonConstraintsRequest {
             desired_trace_io_state,
             reject_daemon: None,
             reject_materializer_state: None,
+            restart_policy: DaemonRestartPolicy::Always,
         })
     }
 
     fn satisfied(&self, daemon: &buck2_cli_proto::DaemonConstraints) -> bool {
+        self.mismatch(daemon).is_none()
+    }
+
+    /// Why the daemon does not satisfy the constraints, if it does not.
+    fn mismatch(&self, daemon: &buck2_cli_proto::DaemonConstraints) -> Option<&'static str> {
         if self.version != daemon.version {
-            return false;
+            return Some("it runs a different version of buck2");
         }
 
         if self.user_version != daemon.user_version {
-            return false;
+            return Some("it was started by a different user version");
         }
 
         if let Some(r) = &self.reject_daemon {
             if *r == daemon.daemon_id {
-                return false;
+                return Some("its in-memory state is corrupted");
             }
         }
 
@@ -105,12 +116,13 @@ impl DaemonConstraintsRequest {
 
         let extra = match &daemon.extra {
             Some(e) => e,
-            None => return true,
+            None => return None,
         };
 
         match (self.desired_trace_io_state, extra.trace_io_enabled) {
-            (DesiredTraceIoState::Enabled, false) => return false,
-            (DesiredTraceIoState::Disabled, true) => return false,
+            (DesiredTraceIoState::Enabled, false) | (DesiredTraceIoState::Disabled, true) => {
+                return Some("I/O tracing needs to be toggled");
+            }
             _ => {}
         }
 
@@ -120,11 +132,11 @@ impl DaemonConstraintsRequest {
                 .as_ref()
                 .map_or(false, |i| i == r)
             {
-                return false;
+                return Some("its materializer state is corrupted");
             }
         }
 
-        true
+        None
     }
 }
 
@@ -188,6 +200,14 @@ impl BuckdConnectConstraints {
             Self::ExistingOnly => None,
         }
     }
+
+    fn restart_policy(&self) -> DaemonRestartPolicy {
+        match self {
+            Self::Constraints(c) => c.restart_policy,
+            // We never restart the daemon in that case.
+            Self::ExistingOnly => DaemonRestartPolicy::Always,
+        }
+    }
 }
 
 static BUCKD_STARTUP_TIMEOUT: EnvHelper<u64> = EnvHelper::new("BUCKD_STARTUP_TIMEOUT");
@@ -453,9 +473,11 @@ impl BuckdChannel {
             mut client,
         } = self;
 
-        let constraints = get_constraints(&mut client)
+        let constraints = get_status(&mut client, false)
             .await
-            .context("Error obtaining daemon constraints")?;
+            .context("Error obtaining daemon constraints")?
+            .daemon_constraints
+            .unwrap_or_default();
 
         Ok(BootstrapBuckdClient {
             info,
@@ -512,6 +534,36 @@ impl BootstrapBuckdClient {
         }
     }
 
+    /// Why this daemon does not satisfy the `constraints`, and the commands it is running.
+    async fn restart_status(
+        &mut self,
+        constraints: &BuckdConnectConstraints,
+    ) -> anyhow::Result<(&'static str, Vec<ActiveCommandStatus>)> {
+        let reason = match constraints {
+            BuckdConnectConstraints::Constraints(c) => c.mismatch(&self.constraints),
+            BuckdConnectConstraints::ExistingOnly => None,
+        };
+        let reason = reason.unwrap_or("it does not match this client");
+        let status = get_status(&mut self.client, true)
+            .await
+            .context("Error obtaining the commands running on the daemon")?;
+        Ok((reason, status.active_commands))
+    }
+
+    /// Check that the restart policy allows killing this daemon, which does not satisfy the
+    /// `constraints`, without asking the user.
+    async fn check_restart_allowed(
+        &mut self,
+        constraints: &BuckdConnectConstraints,
+    ) -> anyhow::Result<()> {
+        let policy = constraints.restart_policy();
+        if policy == DaemonRestartPolicy::Always {
+            return Ok(());
+        }
+        let (reason, active_commands) = self.restart_status(constraints).await?;
+        check_restart_allowed(policy, reason, &active_commands, false).await
+    }
+
     async fn kill_for_constraints_mismatch(&mut self) -> anyhow::Result<()> {
         kill::kill(
             &mut self.client,
@@ -556,6 +608,10 @@ async fn establish_connection(
     paths: &InvocationPaths,
     constraints: BuckdConnectConstraints,
 ) -> anyhow::Result<BootstrapBuckdClient> {
+    // Waiting for the user is not subject to the deadline. Whether the daemon may be restarted
+    // is checked again under the lifecycle lock, before it is killed.
+    let restart_consent = ask_restart_consent(paths, &constraints).await?;
+
     // There are many places where `establish_connection_inner` may hang.
     // If it does, better print something to the user instead of hanging quietly forever.
     let timeout = buckd_startup_timeout()? * 9;
@@ -563,14 +619,45 @@ async fn establish_connection(
     deadline
         .down(
             "establishing connection to Buck daemon or start a daemon",
-            |timeout| establish_connection_inner(paths, constraints, timeout),
+            |timeout| establish_connection_inner(paths, constraints, restart_consent, timeout),
         )
         .await
 }
 
+/// If the running daemon does not satisfy the `constraints` and is running commands, ask the
+/// user whether to restart it anyway, if the restart policy says so.
+///
+/// Returns the id of the daemon the user agreed to restart.
+async fn ask_restart_consent(
+    paths: &InvocationPaths,
+    constraints: &BuckdConnectConstraints,
+) -> anyhow::Result<Option<String>> {
+    if constraints.restart_policy() != DaemonRestartPolicy::Prompt || !is_interactive() {
+        return Ok(None);
+    }
+    let connect = try_connect_existing_impl(&paths.daemon_dir()?);
+    let channel = match timeout(buckd_startup_timeout()?, connect).await {
+        Ok(Ok(channel)) => channel,
+        // There is no daemon to restart, or we will fail to connect to it later.
+        _ => return Ok(None),
+    };
+    let mut client = channel.upgrade().await?;
+    if constraints.satisfied(&client.constraints).is_match() {
+        return Ok(None);
+    }
+    let (reason, active_commands) = client.restart_status(constraints).await?;
+    if active_commands.is_empty() {
+        // Nothing to ask. If it is still idle by the time we hold the lock, it is restarted.
+        return Ok(None);
+    }
+    check_restart_allowed(DaemonRestartPolicy::Prompt, reason, &active_commands, true).await?;
+    Ok(Some(client.constraints.daemon_id))
+}
+
 async fn establish_connection_inner(
     paths: &InvocationPaths,
     constraints: BuckdConnectConstraints,
+    restart_consent: Option<String>,
     deadline: StartupDeadline,
 ) -> anyhow::Result<BootstrapBuckdClient> {
     if let Some(client) = deadline
@@ -598,6 +685,15 @@ async fn establish_connection_inner(
         if constraints.satisfied(&client.constraints).is_match() {
             return Ok(client);
         }
+        // Checked while holding the lock, so that no other client replaces the daemon in between.
+        if restart_consent.as_deref() != Some(client.constraints.daemon_id.as_str()) {
+            deadline
+                .run(
+                    "checking whether the Buck daemon may be restarted",
+                    client.check_restart_allowed(&constraints),
+                )
+                .await?;
+        }
         deadline
             .run(
                 "sending kill command to the Buck daemon",
@@ -704,27 +800,27 @@ async fn try_connect_existing_impl(daemon_dir: &DaemonDir) -> anyhow::Result<Buc
     })
 }
 
-async fn get_constraints(
+async fn get_status(
     client: &mut DaemonApiClient<InterceptedService<Channel, BuckAddAuthTokenInterceptor>>,
-) -> anyhow::Result<buck2_cli_proto::DaemonConstraints> {
+    include_active_commands: bool,
+) -> anyhow::Result<buck2_cli_proto::StatusResponse> {
     // NOTE: No tailers in bootstrap client, we capture logs if we fail to connect, but
     // otherwise we leave them alone.
     let status = EventsCtx::new(vec![Box::new(StdoutStderrForwarder)])
         .unpack_oneshot(&mut None, || {
             client.status(tonic::Request::new(buck2_cli_proto::StatusRequest {
                 snapshot: false,
+                include_active_commands,
             }))
         })
         .await?;
 
-    let status: buck2_cli_proto::StatusResponse = match status {
+    match status {
         CommandOutcome::Success(r) => Ok(r),
         CommandOutcome::Failure(_) => {
             Err(anyhow::anyhow!("Unexpected failure message in status()"))
         }
-    }?;
-
-    Ok(status.daemon_constraints.unwrap_or_default())
+    }
 }
 
 #[derive(Debug, Error)]
@@ -783,6 +879,7 @@ mod tests {
             desired_trace_io_state,
             reject_daemon: None,
             reject_materializer_state: None,
+            restart_policy: DaemonRestartPolicy::Always,
         })
     }
 
@@ -831,6 +928,7 @@ mod tests {
             desired_trace_io_state: DesiredTraceIoState::Existing,
             reject_daemon: None,
             reject_materializer_state: None,
+            restart_policy: DaemonRestartPolicy::Always,
         };
 
         let daemon = buck2_cli_proto::DaemonConstraints {
@@ -845,6 +943,10 @@ mod tests {
         assert!(req.satisfied(&daemon));
         req.reject_daemon = Some("ddd".to_owned());
         assert!(!req.satisfied(&daemon));
+        assert_eq!(
+            req.mismatch(&daemon),
+            Some("its in-memory state is corrupted")
+        );
     }
 
     #[test]
@@ -855,6 +957,7 @@ mod tests {
             desired_trace_io_state: DesiredTraceIoState::Existing,
             reject_daemon: None,
             reject_materializer_state: None,
+            restart_policy: DaemonRestartPolicy::Always,
         };
 
         let daemon = buck2_cli_proto::DaemonConstraints {
@@ -41,8 +41,10 @@ use crate::stream_value::StreamValue;
 use crate::subscribers::observer::ErrorCause;
 use crate::subscribers::observer::ErrorObserver;
 
+pub mod active_commands;
 pub mod connect;
 pub mod kill;
+pub mod restart_policy;
 
 use crate::startup_deadline::StartupDeadline;
 
@@ -50,6 +52,8 @@ use crate::startup_deadline::StartupDeadline;
 enum LifecycleError {
     #[error("Missing `{}` file in `{}` directory", BuckdLifecycleLock::BUCKD_LIFECYCLE, _0.display())]
     MissingLifecycle(AbsNormPathBuf),
+    #[error("`{}` in `{}` is locked by another buck2 process starting or killing the daemon", BuckdLifecycleLock::BUCKD_LIFECYCLE, _0.display())]
+    Locked(AbsNormPathBuf),
 }
 
 /// We need to make sure that all calls to the daemon in buckd flush the tailers after completion.
@@ -116,6 +120,37 @@ impl BuckdLifecycleLock {
         })
     }
 
+    /// Block until the lock is acquired.
+    pub fn lock(daemon_dir: DaemonDir) -> anyhow::Result<BuckdLifecycleLock> {
+        create_dir_all(&daemon_dir.path)?;
+        let lifecycle_path = daemon_dir.path.as_path().join(Self::BUCKD_LIFECYCLE);
+        let file = File::create(lifecycle_path)?;
+        file.lock_exclusive()
+            .context("Error locking buckd lifecycle")?;
+
+        Ok(BuckdLifecycleLock {
+            lock_file: file,
+            daemon_dir,
+        })
+    }
+
+    /// Acquire the lock without waiting, failing if it is already held.
+    ///
+    /// Used by a daemon running in the foreground, which has no client holding the lock for it.
+    pub fn try_lock(daemon_dir: DaemonDir) -> anyhow::Result<BuckdLifecycleLock> {
+        create_dir_all(&daemon_dir.path)?;
+        let lifecycle_path = daemon_dir.path.as_path().join(Self::BUCKD_LIFECYCLE);
+        let file = File::create(lifecycle_path)?;
+        if file.try_lock_exclusive().is_err() {
+            return Err(LifecycleError::Locked(daemon_dir.path.clone()).into());
+        }
+
+        Ok(BuckdLifecycleLock {
+            lock_file: file,
+            daemon_dir,
+        })
+    }
+
     /// Remove everything except `buckd.lifecycle` file which is the lock file.
     pub fn clean_daemon_dir(&self) -> anyhow::Result<()> {
         let mut seen_lifecycle = false;
@@ -147,8 +182,11 @@ impl Drop for BuckdLifecycleLock {
 /// some of the complexity/verbosity of making calls with that. For example, the user
 /// doesn't need to deal with tonic::Response/Request and this may provide functions
 /// that take more primitive types than the protobuf structure itself.
+pub(crate) type AuthenticatedDaemonApiClient =
+    DaemonApiClient<InterceptedService<Channel, BuckAddAuthTokenInterceptor>>;
+
 pub struct BuckdClient {
-    client: DaemonApiClient<InterceptedService<Channel, BuckAddAuthTokenInterceptor>>,
+    client: AuthenticatedDaemonApiClient,
     constraints: buck2_cli_proto::DaemonConstraints,
     info: DaemonProcessInfo,
     daemon_dir: DaemonDir,
@@ -240,6 +278,7 @@ impl BuckdClient {
                 stream,
                 self.tailers.take(),
                 console_interaction,
+                client.clone(),
             )
             .await
     }
@@ -248,12 +287,19 @@ impl BuckdClient {
         kill::kill(&mut self.client, &self.info, reason).await
     }
 
-    pub async fn status(&mut self, snapshot: bool) -> anyhow::Result<StatusResponse> {
+    pub async fn status(
+        &mut self,
+        snapshot: bool,
+        include_active_commands: bool,
+    ) -> anyhow::Result<StatusResponse> {
         let outcome = self
             .events_ctx
             // Safe to unwrap tailers here because they are instantiated prior to a command being called.
             .unpack_oneshot(&mut self.tailers, || {
-                self.client.status(Request::new(StatusRequest { snapshot }))
+                self.client.status(Request::new(StatusRequest {
+                    snapshot,
+                    include_active_commands,
+                }))
             })
             .await;
         // TODO(nmj): We have a number of things that wish to use status() and return an anyhow::Result,
@@ -518,6 +564,12 @@ impl<'a> FlushingBuckdClient<'a> {
         GenericResponse,
         NoPartialResult
     );
+    stream_method!(
+        dice_equality_check,
+        DiceEqualityCheckRequest,
+        GenericResponse,
+        buck2_cli_proto::StdoutBytes
+    );
     stream_method!(
         unstable_docs,
         UnstableDocsRequest,
@@ -561,6 +613,11 @@ impl<'a> FlushingBuckdClient<'a> {
         UnstableAllocatorStatsRequest,
         UnstableAllocatorStatsResponse
     );
+    debug_method!(
+        unstable_recent_events,
+        UnstableRecentEventsRequest,
+        UnstableRecentEventsResponse
+    );
     debug_method!(
         unstable_dice_dump,
         UnstableDiceDumpRequest,
@@ -0,0 +1,160 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use anyhow::Context;
+use buck2_cli_proto::ActiveCommandStatus;
+use dupe::Dupe;
+use thiserror::Error;
+
+use crate::daemon::client::active_commands::describe_commands;
+
+/// What to do when the running daemon does not match what this client expects (e.g. it runs a
+/// different version of buck2), and has to be restarted to run the command.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq, clap::ArgEnum)]
+#[clap(rename_all = "kebab-case")]
+pub enum DaemonRestartPolicy {
+    /// Restart the daemon, interrupting the commands it is running.
+    Always,
+    /// Restart the daemon if it is not running any command, fail otherwise.
+    IfIdle,
+    /// Restart the daemon if it is not running any command. Otherwise, ask for confirmation
+    /// when running in a terminal, and fail when not.
+    Prompt,
+    /// Never restart the daemon, fail instead.
+    Never,
+}
+
+#[derive(Debug, Error)]
+enum DaemonRestartError {
+    #[error(
+        "The buck2 daemon needs to be restarted ({reason}), but `--daemon-restart=never` \
+        was passed. Run `buck2 kill` to restart it"
+    )]
+    NotAllowed { reason: &'static str },
+    #[error(
+        "The buck2 daemon needs to be restarted ({reason}), but it is running {count} other \
+        command(s):\n{commands}\nWait for them to finish, or pass `--daemon-restart=always` to \
+        interrupt them"
+    )]
+    Busy {
+        reason: &'static str,
+        count: usize,
+        commands: String,
+    },
+    #[error("Not restarting the buck2 daemon")]
+    Declined,
+}
+
+/// Decide whether the daemon may be restarted, given why it needs to be and the commands it
+/// is running. The user is only asked for confirmation if `may_prompt` is set.
+pub(crate) async fn check_restart_allowed(
+    policy: DaemonRestartPolicy,
+    reason: &'static str,
+    active_commands: &[ActiveCommandStatus],
+    may_prompt: bool,
+) -> anyhow::Result<()> {
+    let busy = || DaemonRestartError::Busy {
+        reason,
+        count: active_commands.len(),
+        commands: describe_commands(active_commands),
+    };
+    match policy {
+        DaemonRestartPolicy::Always => Ok(()),
+        DaemonRestartPolicy::Never => Err(DaemonRestartError::NotAllowed { reason }.into()),
+        DaemonRestartPolicy::IfIdle | DaemonRestartPolicy::Prompt if active_commands.is_empty() => {
+            Ok(())
+        }
+        DaemonRestartPolicy::IfIdle => Err(busy().into()),
+        DaemonRestartPolicy::Prompt => {
+            if !may_prompt || !is_interactive() {
+                return Err(busy().into());
+            }
+            crate::eprintln!("{}", busy())?;
+            if confirm("Restart it anyway? [y/N] ").await? {
+                Ok(())
+            } else {
+                Err(DaemonRestartError::Declined.into())
+            }
+        }
+    }
+}
+
+/// Whether we can ask the user something: stdin and stderr are both terminals.
+pub(crate) fn is_interactive() -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::io::AsRawFd;
+
+        nix::unistd::isatty(std::io::stdin().as_raw_fd()).unwrap_or(false)
+            && nix::unistd::isatty(std::io::stderr().as_raw_fd()).unwrap_or(false)
+    }
+
+    #[cfg(not(unix))]
+    {
+        false
+    }
+}
+
+async fn confirm(question: &str) -> anyhow::Result<bool> {
+    crate::eprint!("{}", question)?;
+    let answer = tokio::task::spawn_blocking(|| {
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer).map(|_| answer)
+    })
+    .await?
+    .context("Error reading answer")?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_cli_proto::ActiveCommandStatus;
+
+    use crate::daemon::client::restart_policy::check_restart_allowed;
+    use crate::daemon::client::restart_policy::DaemonRestartPolicy;
+
+    #[tokio::test]
+    async fn test_check_restart_allowed() {
+        let busy = vec![ActiveCommandStatus {
+            argv: vec!["buck2".to_owned(), "build".to_owned(), "//:foo".to_owned()],
+            ..Default::default()
+        }];
+
+        for policy in [
+            DaemonRestartPolicy::Always,
+            DaemonRestartPolicy::IfIdle,
+            DaemonRestartPolicy::Prompt,
+        ] {
+            assert!(
+                check_restart_allowed(policy, "version", &[], false)
+                    .await
+                    .is_ok()
+            );
+        }
+        assert!(
+            check_restart_allowed(DaemonRestartPolicy::Always, "version", &busy, false)
+                .await
+                .is_ok()
+        );
+        assert!(
+            check_restart_allowed(DaemonRestartPolicy::Never, "version", &[], false)
+                .await
+                .is_err()
+        );
+        let err = check_restart_allowed(DaemonRestartPolicy::IfIdle, "version", &busy, false)
+            .await
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("buck2 build //:foo"));
+        // Without prompting, a busy daemon is not restarted.
+        let err = check_restart_allowed(DaemonRestartPolicy::Prompt, "version", &busy, false)
+            .await
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("buck2 build //:foo"));
+    }
+}
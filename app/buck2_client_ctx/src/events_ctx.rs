@@ -15,6 +15,7 @@ use anyhow::Context;
 use async_trait::async_trait;
 use buck2_cli_proto::command_result;
 use buck2_cli_proto::CommandResult;
+use buck2_cli_proto::KillLocalActionRequest;
 use buck2_common::daemon_dir::DaemonDir;
 use buck2_events::BuckEvent;
 use futures::stream::FuturesUnordered;
@@ -31,6 +32,7 @@ use crate::command_outcome::CommandOutcome;
 use crate::console_interaction_stream::ConsoleInteraction;
 use crate::console_interaction_stream::ConsoleInteractionStream;
 use crate::console_interaction_stream::NoopConsoleInteraction;
+use crate::daemon::client::AuthenticatedDaemonApiClient;
 use crate::file_tailer::FileTailer;
 use crate::file_tailer::StdoutOrStderr;
 use crate::stream_value::StreamValue;
@@ -182,6 +184,7 @@ impl EventsCtx {
         stream: S,
         tailers: Option<FileTailers>,
         mut console_interaction: Option<ConsoleInteractionStream<'_>>,
+        mut daemon: AuthenticatedDaemonApiClient,
     ) -> anyhow::Result<CommandResult>
     where
         S: Stream<Item = anyhow::Result<StreamValue>> + Unpin,
@@ -227,7 +230,7 @@ impl EventsCtx {
                         self.dispatch_tailer_event(event).await?;
                     }
                     c = console_interaction.char() => {
-                        self.handle_console_interaction(c?).await?;
+                        self.handle_console_interaction(c?, &mut daemon).await?;
                     }
                     tick = self.ticker.tick() => {
                         self.tick(&tick).await?;
@@ -276,6 +279,7 @@ impl EventsCtx {
         stream: S,
         tailers: Option<FileTailers>,
         console_interaction: Option<ConsoleInteractionStream<'_>>,
+        daemon: AuthenticatedDaemonApiClient,
     ) -> anyhow::Result<CommandOutcome<Res>>
     where
         S: Stream<Item = anyhow::Result<StreamValue>> + Unpin,
@@ -283,7 +287,13 @@ impl EventsCtx {
         Handler: PartialResultHandler,
     {
         let command_result = self
-            .unpack_stream_inner(partial_result_handler, stream, tailers, console_interaction)
+            .unpack_stream_inner(
+                partial_result_handler,
+                stream,
+                tailers,
+                console_interaction,
+                daemon,
+            )
             .await;
 
         match command_result {
@@ -405,9 +415,31 @@ impl EventsCtx {
             .await
     }
 
-    async fn handle_console_interaction(&mut self, c: char) -> anyhow::Result<()> {
+    async fn handle_console_interaction(
+        &mut self,
+        c: char,
+        daemon: &mut AuthenticatedDaemonApiClient,
+    ) -> anyhow::Result<()> {
         self.handle_subscribers(|subscriber| subscriber.handle_console_interaction(c))
-            .await
+            .await?;
+
+        let to_kill: Vec<String> = self
+            .subscribers
+            .iter_mut()
+            .flat_map(|subscriber| subscriber.take_local_actions_to_kill())
+            .collect();
+        for action_digest in to_kill {
+            // Not fatal: the daemon may be too old to support this, or the action may be done.
+            if let Err(e) = daemon
+                .kill_local_action(tonic::Request::new(KillLocalActionRequest {
+                    action_digest,
+                }))
+                .await
+            {
+                tracing::warn!("Failed to kill local action: {}", e);
+            }
+        }
+        Ok(())
     }
 
     async fn handle_events(
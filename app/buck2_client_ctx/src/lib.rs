@@ -41,4 +41,5 @@ pub mod streaming;
 pub mod subscribers;
 pub mod ticker;
 pub mod tokio_runtime_setup;
+pub mod upload_backend;
 pub mod version;
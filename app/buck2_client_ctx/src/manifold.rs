@@ -22,24 +22,24 @@ use tokio::process::Child;
 use tokio::process::Command;
 
 use crate::find_certs::find_tls_cert;
+use crate::upload_backend::UploadBackend;
+use crate::upload_backend::UploadConfig;
 
 #[derive(Debug, thiserror::Error)]
 pub enum UploadError {
-    #[error(
-        "No result code from uploading path `{0}` to Manifold, probably due to signal interrupt"
-    )]
+    #[error("No result code from uploading path `{0}`, probably due to signal interrupt")]
     NoResultCodeError(String),
     #[error("Failed to find suitable Manifold upload command")]
     CommandNotFound,
-    #[error(
-        "Failed to upload path `{path}` to Manifold with exit code `{code}`, stderr: `{stderr}`"
-    )]
+    #[error("No upload backend is configured, set `log_upload.backend` in the root buckconfig")]
+    NoBackend,
+    #[error("Failed to upload path `{path}` with exit code `{code}`, stderr: `{stderr}`")]
     FileUploadExitCode {
         path: String,
         code: i32,
         stderr: String,
     },
-    #[error("Failed to upload stream to Manifold with exit code `{code}`, stderr: `{stderr}`")]
+    #[error("Failed to upload stream with exit code `{code}`, stderr: `{stderr}`")]
     StreamUploadExitCode { code: i32, stderr: String },
     #[error("File not found")]
     FileNotFound,
@@ -89,6 +89,8 @@ pub struct Upload<'a> {
     filename: &'a str,
     timeout_s: Option<u64>,
     ttl_s: Option<u64>,
+    /// The backend to upload to, instead of the configured one.
+    backend: Option<&'a dyn UploadBackend>,
 }
 
 impl<'a> Upload<'a> {
@@ -98,6 +100,7 @@ impl<'a> Upload<'a> {
             filename,
             timeout_s: None,
             ttl_s: None,
+            backend: None,
         }
     }
     pub fn with_timeout(mut self, timeout_s: u64) -> Self {
@@ -108,6 +111,10 @@ impl<'a> Upload<'a> {
         self.ttl_s = Some(164 * 86_400); // 164 days, equals scuba buck2_builds retention
         self
     }
+    pub fn with_backend(mut self, backend: &'a dyn UploadBackend) -> Self {
+        self.backend = Some(backend);
+        self
+    }
     pub fn from_file(self, filepath: &'a AbsPath) -> Result<FileUploader<'a>, UploadError> {
         Ok(FileUploader {
             upload: self,
@@ -130,11 +137,44 @@ impl<'a> Upload<'a> {
         })
     }
 
+    /// Where the file will be found once uploaded.
+    pub fn location(&self) -> Result<String, UploadError> {
+        Ok(self.backend()?.location(self.bucket, self.filename))
+    }
+
     pub(super) fn upload_command(&self) -> Result<Command, UploadError> {
-        let bucket = self.bucket.info();
+        Ok(self
+            .backend()?
+            .upload_command(self.bucket, self.filename, self.ttl_s)?
+            .into())
+    }
+
+    fn backend(&self) -> Result<&'a dyn UploadBackend, UploadError> {
+        match self.backend {
+            Some(backend) => Ok(backend),
+            None => backend(),
+        }
+    }
+}
+
+fn backend() -> Result<&'static dyn UploadBackend, UploadError> {
+    UploadConfig::get()?.backend().ok_or(UploadError::NoBackend)
+}
+
+/// Uploads to Manifold, with the Manifold CLI if it is available and `curl` otherwise.
+pub struct ManifoldBackend;
+
+impl UploadBackend for ManifoldBackend {
+    fn upload_command(
+        &self,
+        bucket: Bucket,
+        name: &str,
+        ttl_s: Option<u64>,
+    ) -> Result<std::process::Command, UploadError> {
+        let bucket = bucket.info();
         // we use manifold CLI as it works cross-platform
         let manifold_cli_path = get_cli_path();
-        let bucket_path = &format!("flat/{}", self.filename);
+        let bucket_path = &format!("flat/{}", name);
 
         match manifold_cli_path {
             None => {
@@ -142,18 +182,26 @@ impl<'a> Upload<'a> {
                     Ok(None) // We do not have `curl` on Windows.
                 } else {
                     let cert = find_tls_cert()?;
-                    curl_write_command(bucket, bucket_path, self.ttl_s, &cert)
+                    curl_write_std_command(bucket, bucket_path, ttl_s, &cert)
                 }
             }
             Some(cli_path) => Ok(Some(cli_upload_command(
                 cli_path,
                 &format!("{}/{}", bucket.name, bucket_path),
                 bucket.key,
-                self.ttl_s,
+                ttl_s,
             ))),
         }?
         .ok_or(UploadError::CommandNotFound)
     }
+
+    fn location(&self, bucket: Bucket, name: &str) -> String {
+        format!("{}/flat/{}", bucket.info().name, name)
+    }
+
+    fn supports_append(&self) -> bool {
+        true
+    }
 }
 
 pub struct StdinUploader<'a> {
@@ -283,6 +331,15 @@ pub fn curl_write_command(
     ttl_s: Option<u64>,
     cert: &OsString,
 ) -> anyhow::Result<Option<Command>> {
+    Ok(curl_write_std_command(bucket, manifold_bucket_path, ttl_s, cert)?.map(Command::from))
+}
+
+fn curl_write_std_command(
+    bucket: BucketInfo,
+    manifold_bucket_path: &str,
+    ttl_s: Option<u64>,
+    cert: &OsString,
+) -> anyhow::Result<Option<std::process::Command>> {
     let manifold_url = match log_upload_url() {
         None => return Ok(None),
         Some(x) => x,
@@ -299,7 +356,7 @@ pub fn curl_write_command(
         cert.to_string_lossy(),
     );
 
-    let mut upload = buck2_util::process::async_background_command("curl");
+    let mut upload = buck2_util::process::background_command("curl");
     upload.args([
         "--silent",
         "--show-error",
@@ -375,8 +432,8 @@ fn cli_upload_command(
     manifold_bucket_path: &String,
     bucket_key: &str,
     ttl_s: Option<u64>,
-) -> Command {
-    let mut upload = buck2_util::process::async_background_command(cli_path);
+) -> std::process::Command {
+    let mut upload = buck2_util::process::background_command(cli_path);
 
     tracing::debug!(
         "Uploading event log to {} using manifold CLI with command {:?}",
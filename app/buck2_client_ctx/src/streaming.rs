@@ -166,6 +166,7 @@ impl<T: StreamingCommand> BuckSubcommand for T {
                     BuckdConnectConstraints::ExistingOnly
                 } else {
                     let mut req = DaemonConstraintsRequest::new(T::trace_io(&self))?;
+                    req.restart_policy = ctx.daemon_restart_policy;
                     ctx.restarter.apply_to_constraints(&mut req);
                     BuckdConnectConstraints::Constraints(req)
                 };
@@ -12,6 +12,7 @@ use buck2_wrapper_common::invocation_id::TraceId;
 use crate::manifold;
 use crate::subscribers::event_log::read::EventLogPathBuf;
 use crate::subscribers::should_upload_log;
+use crate::upload_backend::UploadConfig;
 
 #[derive(thiserror::Error, Debug)]
 
@@ -31,13 +32,44 @@ impl From<manifold::UploadError> for LogUploadError {
     }
 }
 
+/// How the event log of a command is uploaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EventLogUpload {
+    None,
+    /// Streamed while it is written, by the `persist-event-logs` subprocess.
+    Streamed,
+    /// Uploaded once the command is done.
+    AfterCommand,
+}
+
+impl EventLogUpload {
+    pub(crate) fn get() -> anyhow::Result<Self> {
+        Ok(Self::new(should_upload_log()?, UploadConfig::get()?))
+    }
+
+    /// Outside of internal builds, event logs are only uploaded if the upload backend is
+    /// configured to archive them. They can only be streamed to backends which support appending
+    /// to uploaded files.
+    fn new(upload_log: bool, config: &UploadConfig) -> Self {
+        let backend = match config.backend() {
+            Some(backend) => backend,
+            None => return EventLogUpload::None,
+        };
+        if !upload_log && !config.archive_event_logs() {
+            EventLogUpload::None
+        } else if upload_log && backend.supports_append() {
+            EventLogUpload::Streamed
+        } else {
+            EventLogUpload::AfterCommand
+        }
+    }
+}
+
 pub(crate) async fn log_upload(
     path: &EventLogPathBuf,
     trace_id: &TraceId,
 ) -> Result<(), LogUploadError> {
-    buck2_core::facebook_only();
-
-    if !should_upload_log().map_err(LogUploadError::Other)? {
+    if EventLogUpload::get().map_err(LogUploadError::Other)? == EventLogUpload::None {
         return Ok(());
     }
 
@@ -55,3 +87,52 @@ pub(crate) async fn log_upload(
     };
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use buck2_common::legacy_configs::testing::legacy_buck_config_from_entries;
+
+    use crate::subscribers::event_log::upload::EventLogUpload;
+    use crate::upload_backend::UploadConfig;
+
+    fn event_log_upload(
+        upload_log: bool,
+        entries: &[(&str, &str, &str)],
+    ) -> anyhow::Result<EventLogUpload> {
+        let config =
+            UploadConfig::from_config(&legacy_buck_config_from_entries(entries.iter().copied())?)?;
+        Ok(EventLogUpload::new(upload_log, &config))
+    }
+
+    #[test]
+    fn test_event_log_upload() -> anyhow::Result<()> {
+        let http = [
+            ("log_upload", "backend", "http"),
+            ("log_upload", "url", "https://logs.example.com"),
+        ];
+        let archived_http = [
+            ("log_upload", "backend", "http"),
+            ("log_upload", "url", "https://logs.example.com"),
+            ("log_upload", "event_logs", "true"),
+        ];
+        let manifold = [("log_upload", "backend", "manifold")];
+        let archived_manifold = [
+            ("log_upload", "backend", "manifold"),
+            ("log_upload", "event_logs", "true"),
+        ];
+
+        assert_eq!(EventLogUpload::None, event_log_upload(false, &http)?);
+        assert_eq!(
+            EventLogUpload::AfterCommand,
+            event_log_upload(false, &archived_http)?
+        );
+        assert_eq!(EventLogUpload::AfterCommand, event_log_upload(true, &http)?);
+        assert_eq!(EventLogUpload::Streamed, event_log_upload(true, &manifold)?);
+        // Only internal builds can stream to Manifold.
+        assert_eq!(
+            EventLogUpload::AfterCommand,
+            event_log_upload(false, &archived_manifold)?
+        );
+        Ok(())
+    }
+}
@@ -42,6 +42,7 @@ use crate::subscribers::event_log::file_names::get_logfile_name;
 use crate::subscribers::event_log::file_names::remove_old_logs;
 use crate::subscribers::event_log::read::EventLogPathBuf;
 use crate::subscribers::event_log::upload::log_upload;
+use crate::subscribers::event_log::upload::EventLogUpload;
 use crate::subscribers::event_log::upload::LogUploadError;
 use crate::subscribers::event_log::utils::Compression;
 use crate::subscribers::event_log::utils::Encoding;
@@ -49,7 +50,6 @@ use crate::subscribers::event_log::utils::EventLogErrors;
 use crate::subscribers::event_log::utils::Invocation;
 use crate::subscribers::event_log::utils::LogMode;
 use crate::subscribers::event_log::utils::NoInference;
-use crate::subscribers::should_upload_log;
 
 type EventLogWriter = Box<dyn AsyncWrite + Send + Sync + Unpin + 'static>;
 
@@ -254,15 +254,21 @@ impl WriteEventLog {
             encoding,
         };
         let (needs_upload, writer) = match self.use_streaming_upload {
-            true => (
-                false, // Upload is handled in subprocess
-                start_persist_subprocess(
-                    path,
-                    event.trace_id()?.clone(),
-                    self.log_size_counter_bytes.clone(),
+            true => {
+                // The subprocess streams the upload if the backend supports it, otherwise the log
+                // is uploaded once the command is done.
+                let upload = EventLogUpload::get()?;
+                (
+                    upload == EventLogUpload::AfterCommand,
+                    start_persist_subprocess(
+                        path,
+                        event.trace_id()?.clone(),
+                        self.log_size_counter_bytes.clone(),
+                        upload == EventLogUpload::Streamed,
+                    )
+                    .await?,
                 )
-                .await?,
-            ),
+            }
             false => (
                 true,
                 open_event_log_for_writing(
@@ -373,6 +379,7 @@ async fn start_persist_subprocess(
     path: EventLogPathBuf,
     trace_id: TraceId,
     bytes_written: Option<Arc<AtomicU64>>,
+    upload: bool,
 ) -> anyhow::Result<NamedEventLogWriter> {
     let current_exe = std::env::current_exe().context("No current_exe")?;
     let mut command = buck2_util::process::async_background_command(current_exe);
@@ -381,7 +388,7 @@ async fn start_persist_subprocess(
         .args(["debug", "persist-event-logs"])
         .args(["--manifold-name", manifold_name])
         .args(["--local-path".as_ref(), path.path.as_os_str()]);
-    if !should_upload_log()? {
+    if !upload {
         command.arg("--no-upload");
     };
     let child = command.stdin(Stdio::piped()).spawn().with_context(|| {
@@ -1001,6 +1001,7 @@ mod imp {
         a.events.extend(b.events);
         a.incomplete_events_reason = a.incomplete_events_reason.or(b.incomplete_events_reason);
         a.watchman_version = a.watchman_version.or(b.watchman_version);
+        a.watchman_unavailable = a.watchman_unavailable.or(b.watchman_unavailable);
         Some(a)
     }
 }
@@ -125,7 +125,9 @@ fn eprint_command_details(
                 remote_command.action_digest
             )?;
         }
-        Some(Command::OmittedLocalCommand(..)) | None => {
+        Some(Command::OmittedLocalCommand(..))
+        | Some(Command::LocalActionCacheCommand(..))
+        | None => {
             // Nothing to show in this case.
         }
     };
@@ -336,6 +338,35 @@ where
         Ok(())
     }
 
+    async fn handle_analysis_warning(
+        &mut self,
+        warning: &buck2_data::AnalysisWarning,
+        _event: &BuckEvent,
+    ) -> anyhow::Result<()> {
+        echo!(
+            "{}",
+            display::display_analysis_warning(warning, TargetDisplayOptions::for_log())?
+        )?;
+        self.notify_printed();
+        Ok(())
+    }
+
+    async fn handle_action_input_budget_exceeded(
+        &mut self,
+        warning: &buck2_data::ActionInputBudgetExceeded,
+        _event: &BuckEvent,
+    ) -> anyhow::Result<()> {
+        echo!(
+            "{}",
+            display::display_action_input_budget_exceeded(
+                warning,
+                TargetDisplayOptions::for_log(),
+            )?
+        )?;
+        self.notify_printed();
+        Ok(())
+    }
+
     async fn handle_file_watcher_end(
         &mut self,
         file_watcher: &buck2_data::FileWatcherEnd,
@@ -441,6 +472,13 @@ where
                     self.observer().action_stats().total_executed_actions()
                 )?;
             }
+            if self.observer().action_stats().cache_uploads > 0 {
+                echo!(
+                    "Cache uploads: {} ({})",
+                    self.observer().action_stats().cache_uploads,
+                    HumanizedBytes::new(self.observer().action_stats().cache_upload_bytes)
+                )?;
+            }
         }
 
         if let Some(re) = &self.observer().re_state().render_header(DrawMode::Final) {
@@ -51,6 +51,11 @@ pub trait EventSubscriber: Send {
     async fn handle_console_interaction(&mut self, _c: char) -> anyhow::Result<()> {
         Ok(())
     }
+    /// Digests of the local actions the user asked to kill through console interactions since
+    /// the last call. The daemon is asked to kill them.
+    fn take_local_actions_to_kill(&mut self) -> Vec<String> {
+        Vec::new()
+    }
     async fn handle_events(&mut self, _event: &[Arc<BuckEvent>]) -> anyhow::Result<()> {
         Ok(())
     }
@@ -38,6 +38,9 @@ pub trait UnpackingEventSubscriber: Send {
     async fn handle_console_interaction(&mut self, _c: char) -> anyhow::Result<()> {
         Ok(())
     }
+    fn take_local_actions_to_kill(&mut self) -> Vec<String> {
+        Vec::new()
+    }
     async fn handle_event(&mut self, event: &Arc<BuckEvent>) -> anyhow::Result<()> {
         self.handle_inner_event(event).await
     }
@@ -130,6 +133,13 @@ pub trait UnpackingEventSubscriber: Send {
             buck2_data::instant_event::Data::DebugAdapterSnapshot(snapshot) => {
                 self.handle_debug_adapter_snapshot(snapshot).await
             }
+            buck2_data::instant_event::Data::AnalysisWarning(warning) => {
+                self.handle_analysis_warning(warning, event).await
+            }
+            buck2_data::instant_event::Data::ActionInputBudgetExceeded(warning) => {
+                self.handle_action_input_budget_exceeded(warning, event)
+                    .await
+            }
             _ => Ok(()),
         }
     }
@@ -202,6 +212,22 @@ pub trait UnpackingEventSubscriber: Send {
         Ok(())
     }
 
+    async fn handle_analysis_warning(
+        &mut self,
+        _warning: &buck2_data::AnalysisWarning,
+        _event: &BuckEvent,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn handle_action_input_budget_exceeded(
+        &mut self,
+        _warning: &buck2_data::ActionInputBudgetExceeded,
+        _event: &BuckEvent,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
     /// Give the subscriber a chance to react to errors as we start trying to clean up.
     /// They may return another error, which will be incorporated into the end result.
     async fn handle_error(&mut self, _error: &anyhow::Error) -> anyhow::Result<()>;
@@ -228,6 +254,10 @@ impl<U: UnpackingEventSubscriber> EventSubscriber for UnpackingEventSubscriberAs
         self.0.handle_console_interaction(c).await
     }
 
+    fn take_local_actions_to_kill(&mut self) -> Vec<String> {
+        self.0.take_local_actions_to_kill()
+    }
+
     async fn handle_events(&mut self, events: &[Arc<BuckEvent>]) -> anyhow::Result<()> {
         for event in events {
             self.0.handle_event(event).await?;
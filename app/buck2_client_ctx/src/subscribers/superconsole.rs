@@ -11,6 +11,7 @@ use std::borrow::Cow;
 use std::fmt::Debug;
 use std::io::Write;
 use std::iter;
+use std::mem;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -55,6 +56,7 @@ use crate::subscribers::superconsole::dice::DiceComponent;
 use crate::subscribers::superconsole::io::IoHeader;
 use crate::subscribers::superconsole::re::ReHeader;
 use crate::subscribers::superconsole::test::TestHeader;
+use crate::subscribers::superconsole::timed_list::killable_local_actions;
 use crate::subscribers::superconsole::timed_list::Cutoffs;
 use crate::subscribers::superconsole::timed_list::TimedList;
 
@@ -81,6 +83,14 @@ pub struct StatefulSuperConsole {
     state: SuperConsoleState,
     super_console: Option<SuperConsole>,
     verbosity: Verbosity,
+    /// When paused, we stop redrawing the console. Emitted lines are buffered by the console and
+    /// flushed on the next render, so nothing is lost while paused.
+    paused: bool,
+    /// Filter currently being typed by the user, if any. This is only applied to `config.filter`
+    /// when the user presses enter.
+    filter_input: Option<String>,
+    /// Digests of the local actions the user asked to kill, not yet sent to the daemon.
+    local_actions_to_kill: Vec<String>,
 }
 
 #[derive(Copy, Clone, Dupe, Debug)]
@@ -124,6 +134,10 @@ pub struct SuperConsoleConfig {
     /// Two lines for root events with single child event.
     pub two_lines: bool,
     pub max_lines: usize,
+    /// Show the full command line of running local actions.
+    pub expand_commands: bool,
+    /// Only show root events whose description contains this string.
+    pub filter: Option<String>,
 }
 
 impl Default for SuperConsoleConfig {
@@ -137,6 +151,8 @@ impl Default for SuperConsoleConfig {
             display_platform: false,
             two_lines: false,
             max_lines: 10,
+            expand_commands: false,
+            filter: None,
         }
     }
 }
@@ -298,6 +314,9 @@ impl StatefulSuperConsole {
             )?,
             super_console: Some(super_console),
             verbosity,
+            paused: false,
+            filter_input: None,
+            local_actions_to_kill: Vec::new(),
         })
     }
 
@@ -383,6 +402,55 @@ impl StatefulSuperConsole {
         self.handle_stderr(&format!("{what}: {on_off}, press `{key}` to revert"))
             .await
     }
+
+    /// Handle a key press while the user is typing a filter. Enter applies the filter (an empty
+    /// filter clears it), escape discards the input.
+    async fn handle_filter_input(&mut self, c: char) -> anyhow::Result<()> {
+        let input = match &mut self.filter_input {
+            Some(input) => input,
+            None => return Ok(()),
+        };
+
+        match c {
+            '\n' | '\r' => {
+                let filter = self.filter_input.take().unwrap_or_default();
+                let msg = if filter.is_empty() {
+                    "Filter cleared".to_owned()
+                } else {
+                    format!("Filtering by `{filter}`, press `/` then enter to clear")
+                };
+                self.state.config.filter = Some(filter).filter(|f| !f.is_empty());
+                self.handle_stderr(&msg).await?;
+            }
+            '\x1b' => {
+                self.filter_input = None;
+                self.handle_stderr("Filter input cancelled").await?;
+            }
+            '\x7f' | '\x08' => {
+                input.pop();
+            }
+            c if !c.is_control() => input.push(c),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Kill the running local action shown given the current filter, if there is exactly one,
+    /// so that the user picks the action to kill by filtering.
+    async fn kill_local_action(&mut self) -> anyhow::Result<()> {
+        let mut actions = killable_local_actions(&self.state)?;
+        let msg = match actions.len() {
+            0 => "No running local action to kill".to_owned(),
+            1 => {
+                let (display, action_digest) = actions.pop().unwrap();
+                self.local_actions_to_kill.push(action_digest);
+                format!("Killing {display}")
+            }
+            n => format!("{n} running local actions are shown, use `/` to filter the one to kill"),
+        };
+        self.handle_stderr(&msg).await
+    }
 }
 
 // TODO(brasselsprouts): after deprecating filetailers, simplify these code paths
@@ -459,6 +527,64 @@ impl UnpackingEventSubscriber for StatefulSuperConsole {
         }
     }
 
+    async fn handle_analysis_warning(
+        &mut self,
+        warning: &buck2_data::AnalysisWarning,
+        event: &BuckEvent,
+    ) -> anyhow::Result<()> {
+        match &mut self.super_console {
+            Some(super_console) => {
+                let display_platform = self.state.config.display_platform;
+                let warning = display::display_analysis_warning(
+                    warning,
+                    TargetDisplayOptions::for_console(display_platform),
+                )?;
+                super_console.emit(Lines(vec![Line::from_iter([Span::new_colored_lossy(
+                    &warning,
+                    Color::DarkYellow,
+                )])]));
+                Ok(())
+            }
+            None => {
+                self.state
+                    .simple_console
+                    .handle_analysis_warning(warning, event)
+                    .await
+            }
+        }
+    }
+
+    async fn handle_action_input_budget_exceeded(
+        &mut self,
+        warning: &buck2_data::ActionInputBudgetExceeded,
+        event: &BuckEvent,
+    ) -> anyhow::Result<()> {
+        match &mut self.super_console {
+            Some(super_console) => {
+                let display_platform = self.state.config.display_platform;
+                let warning = display::display_action_input_budget_exceeded(
+                    warning,
+                    TargetDisplayOptions::for_console(display_platform),
+                )?;
+                super_console.emit(Lines(
+                    warning
+                        .lines()
+                        .map(|line| {
+                            Line::from_iter([Span::new_colored_lossy(line, Color::DarkYellow)])
+                        })
+                        .collect(),
+                ));
+                Ok(())
+            }
+            None => {
+                self.state
+                    .simple_console
+                    .handle_action_input_budget_exceeded(warning, event)
+                    .await
+            }
+        }
+    }
+
     async fn handle_file_watcher_end(
         &mut self,
         file_watcher: &buck2_data::FileWatcherEnd,
@@ -494,6 +620,10 @@ impl UnpackingEventSubscriber for StatefulSuperConsole {
     }
 
     async fn handle_console_interaction(&mut self, c: char) -> anyhow::Result<()> {
+        if self.filter_input.is_some() {
+            return self.handle_filter_input(c).await;
+        }
+
         if c == 'd' {
             self.toggle("DICE component", 'd', |s| &mut s.state.config.enable_dice)
                 .await?;
@@ -521,6 +651,19 @@ impl UnpackingEventSubscriber for StatefulSuperConsole {
         } else if c == 'c' {
             self.toggle("Commands", 'c', |s| &mut s.state.config.enable_commands)
                 .await?;
+        } else if c == 'x' {
+            self.toggle("Expanded command lines", 'x', |s| {
+                &mut s.state.config.expand_commands
+            })
+            .await?;
+        } else if c == ' ' {
+            self.toggle("Paused", ' ', |s| &mut s.paused).await?;
+        } else if c == '/' {
+            self.filter_input = Some(String::new());
+            self.handle_stderr("Type a filter (e.g. a target pattern), then press enter")
+                .await?;
+        } else if c == 'k' {
+            self.kill_local_action().await?;
         } else if c == '+' {
             self.state.config.max_lines = self.state.config.max_lines.saturating_add(1);
         } else if c == '-' {
@@ -534,6 +677,11 @@ impl UnpackingEventSubscriber for StatefulSuperConsole {
                 `r` = toggle detailed RE\n\
                 `i` = toggle I/O counters\n\
                 `p` = display target configurations\n\
+                `c` = toggle commands\n\
+                `x` = toggle full command lines of running local actions\n\
+                `/` = filter running actions (e.g. by target pattern)\n\
+                `k` = kill the running local action, once filtered down to one\n\
+                `space` = pause/resume rendering\n\
                 `+` = show more lines\n\
                 `-` = show fewer lines\n\
                 `h` = show this help",
@@ -544,6 +692,10 @@ impl UnpackingEventSubscriber for StatefulSuperConsole {
         Ok(())
     }
 
+    fn take_local_actions_to_kill(&mut self) -> Vec<String> {
+        mem::take(&mut self.local_actions_to_kill)
+    }
+
     async fn handle_command_result(
         &mut self,
         result: &buck2_cli_proto::CommandResult,
@@ -569,6 +721,7 @@ impl UnpackingEventSubscriber for StatefulSuperConsole {
     async fn tick(&mut self, tick: &Tick) -> anyhow::Result<()> {
         self.state.simple_console.detect_hangs().await?;
         match &mut self.super_console {
+            Some(_) if self.paused => Ok(()),
             Some(super_console) => {
                 self.state.current_tick = tick.dupe();
                 super_console.render(&BuckRootComponent {
@@ -780,7 +933,9 @@ fn lines_for_command_details(
                 )]));
             }
         }
-        Some(Command::OmittedLocalCommand(..)) | None => {
+        Some(Command::OmittedLocalCommand(..))
+        | Some(Command::LocalActionCacheCommand(..))
+        | None => {
             // Nothing to show in this case.
         }
     };
@@ -17,7 +17,9 @@ use buck2_event_observer::fmt_duration;
 use buck2_event_observer::humanized::HumanizedCount;
 use buck2_event_observer::pending_estimate::pending_estimate;
 use buck2_event_observer::span_tracker::BuckEventSpanHandle;
+use buck2_event_observer::span_tracker::BuckEventSpanInfo;
 use buck2_event_observer::span_tracker::BuckEventSpanTracker;
+use buck2_event_observer::what_ran::local_command_to_string;
 use superconsole::components::bordering::BorderedSpec;
 use superconsole::components::Bordered;
 use superconsole::components::DrawVertical;
@@ -115,7 +117,7 @@ impl<'c> TimedListBody<'c> {
         )
     }
 
-    fn draw_root(&self, root: &BuckEventSpanHandle) -> anyhow::Result<Vec<TimedRow>> {
+    fn draw_root(&self, root: &BuckEventSpanHandle) -> anyhow::Result<Vec<Row>> {
         let time_speed = self.state.time_speed;
         let config = &self.state.config;
         let two_lines = config.two_lines;
@@ -124,35 +126,128 @@ impl<'c> TimedListBody<'c> {
 
         let mut it = root.children();
 
-        match it.next() {
-            Some(first) if !two_lines => Ok(vec![self.draw_root_first_child(
-                root,
-                first,
-                it.len(),
-                display_platform,
-            )?]),
+        let mut rows: Vec<Row> = match it.next() {
+            Some(first) if !two_lines => vec![
+                self.draw_root_first_child(root, first, it.len(), display_platform)?
+                    .into(),
+            ],
             first => {
                 let mut rows = Vec::new();
-                rows.push(TimedRow::span(
-                    0,
-                    info,
-                    time_speed.speed(),
-                    self.cutoffs,
-                    display_platform,
-                )?);
+                rows.push(
+                    TimedRow::span(0, info, time_speed.speed(), self.cutoffs, display_platform)?
+                        .into(),
+                );
 
                 for child in first.into_iter().chain(it) {
-                    rows.push(TimedRow::span(
-                        2,
-                        child.info(),
-                        time_speed.speed(),
-                        self.cutoffs,
-                        display_platform,
-                    )?);
+                    rows.push(
+                        TimedRow::span(
+                            2,
+                            child.info(),
+                            time_speed.speed(),
+                            self.cutoffs,
+                            display_platform,
+                        )?
+                        .into(),
+                    );
+                }
+                rows
+            }
+        };
+
+        if config.expand_commands {
+            for child in root.children() {
+                if let Some(command) = running_local_command(child.info()) {
+                    rows.push(
+                        Line::from_iter([
+                            Span::padding(4),
+                            Span::new_styled_lossy(local_command_to_string(command).dark_grey()),
+                        ])
+                        .into(),
+                    );
                 }
-                Ok(rows)
             }
         }
+
+        Ok(rows)
+    }
+}
+
+/// Whether a root should be displayed given the filter currently entered by the user (if any).
+fn root_matches_filter(
+    state: &SuperConsoleState,
+    root: &BuckEventSpanHandle,
+) -> anyhow::Result<bool> {
+    let filter = match &state.config.filter {
+        Some(filter) => filter,
+        None => return Ok(true),
+    };
+    let display = display::display_event(
+        &root.info().event,
+        TargetDisplayOptions::for_console(state.config.display_platform),
+    )?;
+    Ok(filter_matches(filter, &display))
+}
+
+/// Whether the description of an event matches a filter typed by the user. Recursive target
+/// patterns like `//foo/...` match targets in the package and in the packages below it, and
+/// `//foo:...` matches targets in the package. Anything else matches as a substring.
+fn filter_matches(filter: &str, display: &str) -> bool {
+    let (prefix, separators): (&str, &[char]) = if let Some(prefix) = filter.strip_suffix("/...") {
+        (prefix, &[':', '/'])
+    } else if let Some(prefix) = filter.strip_suffix(":...") {
+        (prefix, &[':'])
+    } else {
+        return display.contains(filter);
+    };
+    display.match_indices(prefix).any(|(i, _)| {
+        display[i + prefix.len()..]
+            .chars()
+            .next()
+            .map_or(false, |c| separators.contains(&c))
+    })
+}
+
+/// The running local actions the user can kill, i.e. the ones shown given the current filter, as
+/// their description and action digest.
+pub(crate) fn killable_local_actions(
+    state: &SuperConsoleState,
+) -> anyhow::Result<Vec<(String, String)>> {
+    let mut actions = Vec::new();
+    for root in state.simple_console.observer().spans().iter_roots() {
+        if !root_matches_filter(state, &root)? {
+            continue;
+        }
+        for child in root.children() {
+            if let Some(command) = running_local_command(child.info()) {
+                let display = display::display_event(
+                    &root.info().event,
+                    TargetDisplayOptions::for_console(state.config.display_platform),
+                )?;
+                actions.push((display, command.action_digest.clone()));
+            }
+        }
+    }
+    Ok(actions)
+}
+
+/// The command line of a local command that is currently executing, if this span is one.
+fn running_local_command(info: &BuckEventSpanInfo) -> Option<&buck2_data::LocalCommand> {
+    use buck2_data::buck_event::Data;
+    use buck2_data::executor_stage_start::Stage;
+    use buck2_data::local_stage;
+    use buck2_data::span_start_event::Data as StartData;
+
+    match info.event.data() {
+        Data::SpanStart(buck2_data::SpanStartEvent {
+            data:
+                Some(StartData::ExecutorStage(buck2_data::ExecutorStageStart {
+                    stage:
+                        Some(Stage::Local(buck2_data::LocalStage {
+                            stage: Some(local_stage::Stage::Execute(execute)),
+                        })),
+                })),
+        }) => execute.command.as_ref(),
+        _ => None,
     }
 }
 
@@ -170,10 +265,16 @@ impl<'c> Component for TimedListBody<'c> {
         let mut builder = Table::new();
 
         let mut first_not_rendered = None;
+        let mut filtered_out = 0;
 
         let pending = pending_estimate(spans.roots(), observer.extra().dice_state());
 
         for root in &mut roots {
+            if !root_matches_filter(self.state, &root)? {
+                filtered_out += 1;
+                continue;
+            }
+
             let rows = self.draw_root(&root)?;
 
             if builder.len() + rows.len() >= max_lines {
@@ -181,12 +282,22 @@ impl<'c> Component for TimedListBody<'c> {
                 break;
             }
 
-            builder.rows.extend(rows.into_iter().map(Row::from));
+            builder.rows.extend(rows);
         }
 
         // Add remaining unshown tasks, if any.
         let more = roots.len() as u64 + first_not_rendered.map_or(0, |_| 1) + pending;
 
+        if let Some(filter) = &config.filter {
+            builder.rows.push(
+                std::iter::once(Span::new_styled(
+                    format!("(filtered by `{}`, {} hidden)", filter, filtered_out).italic(),
+                )?)
+                .collect::<Line>()
+                .into(),
+            );
+        }
+
         if more > 0 {
             let remaining = format!("... and {} more", more);
             builder.rows.push(
@@ -456,6 +567,8 @@ mod tests {
             remote_actions: 0,
             cached_actions: 1,
             fallback_actions: 0,
+            cache_uploads: 0,
+            cache_upload_bytes: 0,
         };
 
         let timed_list_state = SuperConsoleConfig {
@@ -495,6 +608,213 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_filter() -> anyhow::Result<()> {
+        let tick = Tick::now();
+
+        let label = Arc::new(BuckEvent::new(
+            UNIX_EPOCH,
+            TraceId::new(),
+            Some(SpanId::new()),
+            None,
+            buck2_data::buck_event::Data::SpanStart(SpanStartEvent {
+                data: Some(buck2_data::span_start_event::Data::Fake(FakeStart {
+                    caramba: "test".to_owned(),
+                })),
+            }),
+        ));
+
+        let module = Arc::new(BuckEvent::new(
+            UNIX_EPOCH,
+            TraceId::new(),
+            Some(SpanId::new()),
+            None,
+            buck2_data::buck_event::Data::SpanStart(SpanStartEvent {
+                data: Some(buck2_data::span_start_event::Data::Fake(FakeStart {
+                    caramba: "foo".to_owned(),
+                })),
+            }),
+        ));
+
+        let mut state = BuckEventSpanTracker::new();
+        state.start_at(&label, fake_time(&tick, 3)).unwrap();
+        state.start_at(&module, fake_time(&tick, 1)).unwrap();
+
+        let timed_list_state = SuperConsoleConfig {
+            max_lines: 5,
+            filter: Some("foo".to_owned()),
+            ..Default::default()
+        };
+
+        let output = TimedList::new(
+            &CUTOFFS,
+            "test",
+            &super_console_state_for_test(
+                state,
+                ActionStats::default(),
+                tick,
+                fake_time_speed(),
+                timed_list_state,
+            ),
+        )
+        .draw(
+            Dimensions {
+                width: 40,
+                height: 10,
+            },
+            DrawMode::Normal,
+        )?;
+        let expected = Lines(vec![
+            vec!["test", "Jobs: Running: 2. Progress: 0/2. Tim"].try_into()?,
+            Line::sanitized(&"-".repeat(40)),
+            Line::from_iter([
+                Span::new_unstyled("foo -- speak of the devil".to_owned())?,
+                Span::padding(11),
+                Span::new_unstyled("1.0s".to_owned())?,
+            ]),
+            Line::from_iter([Span::new_styled(
+                "(filtered by `foo`, 1 hidden)".to_owned().italic(),
+            )?]),
+        ]);
+
+        pretty_assertions::assert_eq!(output, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_matches() {
+        let display = "root//foo/bar:baz -- action (cxx_compile baz.cpp)";
+        assert!(filter_matches("cxx_compile", display));
+        assert!(filter_matches("//foo/...", display));
+        assert!(filter_matches("//foo/bar/...", display));
+        assert!(filter_matches("//foo/bar:...", display));
+        assert!(filter_matches("//...", display));
+        assert!(!filter_matches("//foo:...", display));
+        assert!(!filter_matches("//fo/...", display));
+        assert!(!filter_matches("//foo/bar/baz/...", display));
+        assert!(!filter_matches("link", display));
+    }
+
+    /// An action of a target in `package`, which is running a local command.
+    fn local_action(package: &str, action_digest: &str) -> [Arc<BuckEvent>; 2] {
+        let parent = SpanId::new();
+        let action = BuckEvent::new(
+            UNIX_EPOCH,
+            TraceId::new(),
+            Some(parent),
+            None,
+            SpanStartEvent {
+                data: Some(
+                    buck2_data::ActionExecutionStart {
+                        key: Some(buck2_data::ActionKey {
+                            id: Default::default(),
+                            owner: Some(buck2_data::action_key::Owner::TargetLabel(
+                                buck2_data::ConfiguredTargetLabel {
+                                    label: Some(buck2_data::TargetLabel {
+                                        package: package.into(),
+                                        name: "target".into(),
+                                    }),
+                                    configuration: Some(buck2_data::Configuration {
+                                        full_name: "conf".into(),
+                                    }),
+                                    execution_configuration: None,
+                                },
+                            )),
+                            key: "".to_owned(),
+                        }),
+                        name: Some(buck2_data::ActionName {
+                            category: "category".into(),
+                            identifier: "identifier".into(),
+                        }),
+                        kind: buck2_data::ActionKind::NotSet as i32,
+                    }
+                    .into(),
+                ),
+            }
+            .into(),
+        );
+        let execute = BuckEvent::new(
+            UNIX_EPOCH,
+            TraceId::new(),
+            Some(SpanId::new()),
+            Some(parent),
+            SpanStartEvent {
+                data: Some(
+                    buck2_data::ExecutorStageStart {
+                        stage: Some(
+                            buck2_data::LocalStage {
+                                stage: Some(
+                                    buck2_data::LocalExecute {
+                                        command: Some(buck2_data::LocalCommand {
+                                            action_digest: action_digest.to_owned(),
+                                            argv: vec!["true".to_owned()],
+                                            env: Vec::new(),
+                                        }),
+                                    }
+                                    .into(),
+                                ),
+                            }
+                            .into(),
+                        ),
+                    }
+                    .into(),
+                ),
+            }
+            .into(),
+        );
+        [Arc::new(action), Arc::new(execute)]
+    }
+
+    #[test]
+    fn test_killable_local_actions() -> anyhow::Result<()> {
+        let tick = Tick::now();
+
+        let mut state = BuckEventSpanTracker::new();
+        for event in local_action("root//foo", "digest_foo:10")
+            .iter()
+            .chain(&local_action("root//foobar", "digest_foobar:10"))
+        {
+            state.start_at(event, fake_time(&tick, 1)).unwrap();
+        }
+
+        let mut super_console_state = super_console_state_for_test(
+            state,
+            ActionStats::default(),
+            tick,
+            fake_time_speed(),
+            SuperConsoleConfig::default(),
+        );
+        let digests = |state: &SuperConsoleState| -> anyhow::Result<Vec<String>> {
+            let mut digests: Vec<String> = killable_local_actions(state)?
+                .into_iter()
+                .map(|(_, digest)| digest)
+                .collect();
+            digests.sort();
+            Ok(digests)
+        };
+
+        assert_eq!(
+            digests(&super_console_state)?,
+            vec!["digest_foo:10", "digest_foobar:10"]
+        );
+
+        super_console_state.config.filter = Some("//foo/...".to_owned());
+        let actions = killable_local_actions(&super_console_state)?;
+        assert_eq!(
+            actions,
+            vec![(
+                "root//foo:target -- action (category identifier)".to_owned(),
+                "digest_foo:10".to_owned()
+            )]
+        );
+
+        super_console_state.config.filter = Some("//baz/...".to_owned());
+        assert_eq!(digests(&super_console_state)?, Vec::<String>::new());
+
+        Ok(())
+    }
+
     #[test]
     fn test_remaining() -> anyhow::Result<()> {
         let tick = Tick::now();
@@ -549,6 +869,8 @@ mod tests {
             remote_actions: 0,
             cached_actions: 1,
             fallback_actions: 0,
+            cache_uploads: 0,
+            cache_upload_bytes: 0,
         };
 
         let timed_list_state = SuperConsoleConfig {
@@ -802,6 +1124,8 @@ mod tests {
             remote_actions: 0,
             cached_actions: 1,
             fallback_actions: 0,
+            cache_uploads: 0,
+            cache_upload_bytes: 0,
         };
 
         let timed_list_state = SuperConsoleConfig {
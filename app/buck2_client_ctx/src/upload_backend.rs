@@ -0,0 +1,413 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Where `buck2 rage` reports, crash reports and archived event logs are uploaded to.
+//!
+//! The backend is configured in the `[log_upload]` section of the root cell's buckconfig:
+//!
+//! - `backend`: one of `manifold`, `http`, `s3` or `gcs`. Internal builds use `manifold` by
+//!   default, open source builds don't upload anything unless a backend is set.
+//! - `url` (`http`): files are uploaded with `curl` as `PUT <url>/<bucket>/<name>`.
+//! - `headers` (`http`): comma-separated headers sent with each upload, e.g. for authentication.
+//! - `bucket`, `prefix` (`s3` and `gcs`): files are uploaded to
+//!   `<bucket>/<prefix>/<buck2 bucket>/<name>` with the `aws` or `gsutil` CLI, which must be
+//!   installed and authenticated.
+//! - `event_logs`: also upload the event log of every command (`false` by default).
+//!
+//! Except for Manifold, the TTL requested for an upload is not enforced: use the lifecycle rules
+//! of the storage to expire old files.
+
+use std::process::Command;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use buck2_common::invocation_roots::find_invocation_roots;
+use buck2_common::legacy_configs::cells::BuckConfigBasedCells;
+use buck2_common::legacy_configs::LegacyBuckConfig;
+use buck2_util::process::background_command;
+use once_cell::sync::OnceCell;
+
+use crate::manifold::Bucket;
+use crate::manifold::ManifoldBackend;
+use crate::manifold::UploadError;
+
+const SECTION: &str = "log_upload";
+
+#[derive(Debug, thiserror::Error)]
+enum UploadConfigError {
+    #[error("Unknown `log_upload.backend` `{0}`, expected `manifold`, `http`, `s3` or `gcs`")]
+    UnknownBackend(String),
+    #[error("`log_upload.{0}` must be set to upload to `{1}`")]
+    MissingKey(&'static str, &'static str),
+}
+
+/// A place files can be uploaded to.
+pub trait UploadBackend: Send + Sync + 'static {
+    /// A command which uploads its stdin as `name` in `bucket`, to be deleted after `ttl_s`
+    /// seconds if the backend supports it.
+    fn upload_command(
+        &self,
+        bucket: Bucket,
+        name: &str,
+        ttl_s: Option<u64>,
+    ) -> Result<Command, UploadError>;
+
+    /// Where the file uploaded as `name` in `bucket` can be found, to show to users.
+    fn location(&self, bucket: Bucket, name: &str) -> String;
+
+    /// Whether uploaded files can be appended to, which event logs are streamed with.
+    fn supports_append(&self) -> bool {
+        false
+    }
+}
+
+/// The path of an object in an S3 or GCS bucket.
+fn object_path(prefix: &str, bucket: Bucket, name: &str) -> String {
+    [prefix.trim_matches('/'), bucket.info().name, name]
+        .iter()
+        .filter(|part| !part.is_empty())
+        .copied()
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Uploads with `curl` to any HTTP server accepting `PUT` requests.
+struct HttpBackend {
+    url: String,
+    headers: Vec<String>,
+}
+
+impl UploadBackend for HttpBackend {
+    fn upload_command(
+        &self,
+        bucket: Bucket,
+        name: &str,
+        _ttl_s: Option<u64>,
+    ) -> Result<Command, UploadError> {
+        let mut upload = background_command("curl");
+        upload.args([
+            "--silent",
+            "--show-error",
+            "--retry",
+            "2",
+            "--fail",
+            "-X",
+            "PUT",
+        ]);
+        for header in &self.headers {
+            upload.args(["-H", header]);
+        }
+        upload.args(["--data-binary", "@-", &self.location(bucket, name)]);
+        Ok(upload)
+    }
+
+    fn location(&self, bucket: Bucket, name: &str) -> String {
+        format!(
+            "{}/{}",
+            self.url.trim_end_matches('/'),
+            object_path("", bucket, name)
+        )
+    }
+}
+
+/// Uploads to Amazon S3 with the `aws` CLI.
+struct S3Backend {
+    bucket: String,
+    prefix: String,
+}
+
+impl UploadBackend for S3Backend {
+    fn upload_command(
+        &self,
+        bucket: Bucket,
+        name: &str,
+        _ttl_s: Option<u64>,
+    ) -> Result<Command, UploadError> {
+        let mut upload = background_command("aws");
+        upload.args([
+            "s3",
+            "cp",
+            "--only-show-errors",
+            "-",
+            &self.location(bucket, name),
+        ]);
+        Ok(upload)
+    }
+
+    fn location(&self, bucket: Bucket, name: &str) -> String {
+        format!(
+            "s3://{}/{}",
+            self.bucket,
+            object_path(&self.prefix, bucket, name)
+        )
+    }
+}
+
+/// Uploads to Google Cloud Storage with the `gsutil` CLI.
+struct GcsBackend {
+    bucket: String,
+    prefix: String,
+}
+
+impl UploadBackend for GcsBackend {
+    fn upload_command(
+        &self,
+        bucket: Bucket,
+        name: &str,
+        _ttl_s: Option<u64>,
+    ) -> Result<Command, UploadError> {
+        let mut upload = background_command("gsutil");
+        upload.args(["-q", "cp", "-", &self.location(bucket, name)]);
+        Ok(upload)
+    }
+
+    fn location(&self, bucket: Bucket, name: &str) -> String {
+        format!(
+            "gs://{}/{}",
+            self.bucket,
+            object_path(&self.prefix, bucket, name)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BackendKind {
+    Manifold,
+    Http,
+    S3,
+    Gcs,
+}
+
+impl FromStr for BackendKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "manifold" => Ok(BackendKind::Manifold),
+            "http" => Ok(BackendKind::Http),
+            "s3" => Ok(BackendKind::S3),
+            "gcs" => Ok(BackendKind::Gcs),
+            _ => Err(UploadConfigError::UnknownBackend(s.to_owned()).into()),
+        }
+    }
+}
+
+/// How uploads are configured for the project buck2 is invoked in.
+pub struct UploadConfig {
+    backend: Option<Arc<dyn UploadBackend>>,
+    archive_event_logs: bool,
+}
+
+static UPLOAD_CONFIG: OnceCell<UploadConfig> = OnceCell::new();
+
+impl UploadConfig {
+    /// The upload configuration of the project containing the current directory, read once per
+    /// process. Outside of a project, the defaults are used.
+    pub fn get() -> anyhow::Result<&'static UploadConfig> {
+        UPLOAD_CONFIG.get_or_try_init(|| match find_invocation_roots(&std::env::current_dir()?) {
+            Ok(roots) => {
+                let cells = BuckConfigBasedCells::parse(&roots.project_root)?;
+                Self::from_config(cells.configs_by_name.get(cells.cell_resolver.root_cell())?)
+            }
+            Err(_) => Ok(Self::default_for_build()),
+        })
+    }
+
+    fn default_for_build() -> Self {
+        let backend: Option<Arc<dyn UploadBackend>> = if buck2_core::is_open_source() {
+            None
+        } else {
+            Some(Arc::new(ManifoldBackend))
+        };
+        UploadConfig {
+            backend,
+            archive_event_logs: false,
+        }
+    }
+
+    pub(crate) fn from_config(config: &LegacyBuckConfig) -> anyhow::Result<Self> {
+        let required = |key: &'static str, backend: &'static str| -> anyhow::Result<String> {
+            Ok(config
+                .get(SECTION, key)
+                .ok_or(UploadConfigError::MissingKey(key, backend))?
+                .to_owned())
+        };
+        let prefix = config.get(SECTION, "prefix").unwrap_or_default().to_owned();
+
+        let backend: Option<Arc<dyn UploadBackend>> =
+            match config.parse::<BackendKind>(SECTION, "backend")? {
+                None => Self::default_for_build().backend,
+                Some(BackendKind::Manifold) => Some(Arc::new(ManifoldBackend)),
+                Some(BackendKind::Http) => Some(Arc::new(HttpBackend {
+                    url: required("url", "http")?,
+                    headers: config
+                        .get(SECTION, "headers")
+                        .map(|headers| headers.split(',').map(|h| h.trim().to_owned()).collect())
+                        .unwrap_or_default(),
+                })),
+                Some(BackendKind::S3) => Some(Arc::new(S3Backend {
+                    bucket: required("bucket", "s3")?,
+                    prefix,
+                })),
+                Some(BackendKind::Gcs) => Some(Arc::new(GcsBackend {
+                    bucket: required("bucket", "gcs")?,
+                    prefix,
+                })),
+            };
+        Ok(UploadConfig {
+            backend,
+            archive_event_logs: config.parse(SECTION, "event_logs")?.unwrap_or(false),
+        })
+    }
+
+    /// Where to upload to, if anywhere.
+    pub fn backend(&self) -> Option<&dyn UploadBackend> {
+        self.backend.as_deref()
+    }
+
+    /// Whether the event log of every command should be uploaded.
+    pub fn archive_event_logs(&self) -> bool {
+        self.backend.is_some() && self.archive_event_logs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::process::Command;
+
+    use buck2_common::legacy_configs::testing::legacy_buck_config_from_entries;
+    use buck2_core::fs::paths::abs_path::AbsPath;
+
+    use crate::manifold::Bucket;
+    use crate::manifold::Upload;
+    use crate::manifold::UploadError;
+    use crate::upload_backend::UploadBackend;
+    use crate::upload_backend::UploadConfig;
+
+    /// Uploads by copying to a local directory.
+    struct DirBackend {
+        dir: PathBuf,
+    }
+
+    impl UploadBackend for DirBackend {
+        fn upload_command(
+            &self,
+            bucket: Bucket,
+            name: &str,
+            _ttl_s: Option<u64>,
+        ) -> Result<Command, UploadError> {
+            let mut upload = Command::new("sh");
+            upload.args(["-c", "cat > \"$0\"", &self.location(bucket, name)]);
+            Ok(upload)
+        }
+
+        fn location(&self, bucket: Bucket, name: &str) -> String {
+            self.dir
+                .join(format!("{}-{}", bucket.info().name, name))
+                .display()
+                .to_string()
+        }
+    }
+
+    fn location(entries: &[(&str, &str, &str)]) -> anyhow::Result<Option<String>> {
+        let config =
+            UploadConfig::from_config(&legacy_buck_config_from_entries(entries.iter().copied())?)?;
+        Ok(config
+            .backend()
+            .map(|backend| backend.location(Bucket::RageDumps, "id.stderr")))
+    }
+
+    #[test]
+    fn test_locations() -> anyhow::Result<()> {
+        assert_eq!(
+            location(&[
+                ("log_upload", "backend", "http"),
+                ("log_upload", "url", "https://logs.example.com/upload/"),
+            ])?,
+            Some("https://logs.example.com/upload/buck2_rage_dumps/id.stderr".to_owned())
+        );
+        assert_eq!(
+            location(&[
+                ("log_upload", "backend", "s3"),
+                ("log_upload", "bucket", "my-bucket"),
+                ("log_upload", "prefix", "/buck2/"),
+            ])?,
+            Some("s3://my-bucket/buck2/buck2_rage_dumps/id.stderr".to_owned())
+        );
+        assert_eq!(
+            location(&[
+                ("log_upload", "backend", "gcs"),
+                ("log_upload", "bucket", "my-bucket"),
+            ])?,
+            Some("gs://my-bucket/buck2_rage_dumps/id.stderr".to_owned())
+        );
+        assert_eq!(
+            location(&[("log_upload", "backend", "manifold")])?,
+            Some("buck2_rage_dumps/flat/id.stderr".to_owned())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert!(location(&[("log_upload", "backend", "ftp")]).is_err());
+        assert!(location(&[("log_upload", "backend", "s3")]).is_err());
+    }
+
+    #[test]
+    fn test_archive_event_logs() -> anyhow::Result<()> {
+        let config = UploadConfig::from_config(&legacy_buck_config_from_entries([
+            ("log_upload", "backend", "http"),
+            ("log_upload", "url", "https://logs.example.com"),
+            ("log_upload", "event_logs", "true"),
+        ])?)?;
+        assert!(config.archive_event_logs());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_upload_to_backend() -> anyhow::Result<()> {
+        let temp = tempfile::tempdir()?;
+        let backend = DirBackend {
+            dir: temp.path().to_owned(),
+        };
+
+        let file = temp.path().join("report.txt");
+        std::fs::write(&file, "report")?;
+        let upload = Upload::new(Bucket::RageDumps, "report").with_backend(&backend);
+        let location = upload.location()?;
+        upload.from_file(AbsPath::new(&file)?)?.spawn().await?;
+        assert_eq!("report", std::fs::read_to_string(location)?);
+
+        let mut stream: &[u8] = b"event log";
+        let upload = Upload::new(Bucket::EventLogs, "trace.pb.zst").with_backend(&backend);
+        let location = upload.location()?;
+        upload.from_async_read(&mut stream)?.spawn().await?;
+        assert_eq!("event log", std::fs::read_to_string(location)?);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_upload_missing_file() -> anyhow::Result<()> {
+        let temp = tempfile::tempdir()?;
+        let backend = DirBackend {
+            dir: temp.path().to_owned(),
+        };
+        let missing = temp.path().join("missing.txt");
+        let upload = Upload::new(Bucket::RageDumps, "missing").with_backend(&backend);
+        assert!(matches!(
+            upload.from_file(AbsPath::new(&missing)?)?.spawn().await,
+            Err(UploadError::FileNotFound)
+        ));
+        Ok(())
+    }
+}
@@ -25,6 +25,7 @@ use buck2_core::fs::paths::file_name::FileNameBuf;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
 use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use buck2_events::dispatch::console_message;
+use dashmap::DashMap;
 use derivative::Derivative;
 use derive_more::Display;
 use dice::DiceComputations;
@@ -46,6 +47,7 @@ use crate::file_ops::SimpleDirEntry;
 use crate::ignores::all_cells::AllCellIgnores;
 use crate::ignores::all_cells::HasAllCellIgnores;
 use crate::io::IoProvider;
+use crate::legacy_configs::external_cells::materialize_external_cell;
 use crate::result::SharedResult;
 use crate::result::ToSharedResultExt;
 use crate::result::ToUnsharedResultExt;
@@ -104,6 +106,10 @@ async fn get_default_file_ops(dice: &DiceComputations) -> SharedResult<Arc<dyn F
         io: Arc<dyn IoProvider>,
         cells: CellResolver,
         ignores: Arc<AllCellIgnores>,
+        /// External cells are fetched on the first access to one of their files.
+        #[derivative(PartialEq = "ignore")]
+        #[allocative(skip)]
+        external_cells_fetched: Arc<DashMap<CellName, Arc<tokio::sync::OnceCell<()>>>>,
     }
 
     impl DiceFileOpsDelegate {
@@ -123,6 +129,35 @@ async fn get_default_file_ops(dice: &DiceComputations) -> SharedResult<Arc<dyn F
         fn io_provider(&self) -> &dyn IoProvider {
             self.io.as_ref()
         }
+
+        async fn fetch_if_external_cell(&self, cell: CellName) -> anyhow::Result<()> {
+            let instance = self.cells.get(cell)?;
+            let origin = match instance.external() {
+                Some(origin) => origin.clone(),
+                None => return Ok(()),
+            };
+            let fetched = self
+                .external_cells_fetched
+                .entry(cell)
+                .or_default()
+                .value()
+                .clone();
+            fetched
+                .get_or_try_init(|| async {
+                    let path = self
+                        .io
+                        .project_root()
+                        .resolve(instance.path().as_project_relative_path());
+                    tokio::task::spawn_blocking(move || {
+                        materialize_external_cell(&origin, cell, &path)
+                    })
+                    .await
+                    .context("Fetching external cell panicked")?
+                    .with_context(|| format!("Error fetching external cell `{}`", cell))
+                })
+                .await?;
+            Ok(())
+        }
     }
 
     #[async_trait]
@@ -132,6 +167,7 @@ async fn get_default_file_ops(dice: &DiceComputations) -> SharedResult<Arc<dyn F
             path: CellPathRef<'async_trait>,
         ) -> anyhow::Result<Option<String>> {
             // TODO(cjhopman): error on ignored paths, maybe.
+            self.fetch_if_external_cell(path.cell()).await?;
             let project_path = self.resolve(path)?;
             self.io_provider().read_file_if_exists(project_path).await
         }
@@ -143,6 +179,7 @@ async fn get_default_file_ops(dice: &DiceComputations) -> SharedResult<Arc<dyn F
                 .into_result()
                 .with_context(|| format!("Error checking whether dir `{}` is ignored", path))?;
 
+            self.fetch_if_external_cell(path.cell()).await?;
             let project_path = self.resolve(path)?;
             let mut entries = self
                 .io_provider()
@@ -210,6 +247,7 @@ async fn get_default_file_ops(dice: &DiceComputations) -> SharedResult<Arc<dyn F
             &self,
             path: CellPathRef<'async_trait>,
         ) -> anyhow::Result<Option<RawPathMetadata>> {
+            self.fetch_if_external_cell(path.cell()).await?;
             let project_path = self.resolve(path)?;
 
             let res = self
@@ -250,6 +288,7 @@ async fn get_default_file_ops(dice: &DiceComputations) -> SharedResult<Arc<dyn F
                 io,
                 cells,
                 ignores,
+                external_cells_fetched: Arc::new(DashMap::new()),
             })))
         }
 
@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use std::collections::BTreeMap;
 use std::fmt::Display;
 use std::fmt::Formatter;
 use std::hash::Hash;
@@ -15,6 +16,7 @@ use std::str::FromStr;
 use std::sync::Arc;
 
 use allocative::Allocative;
+use anyhow::Context;
 use buck2_core::collections::sorted_map::SortedMap;
 use derive_more::Display;
 use dupe::Dupe;
@@ -22,6 +24,8 @@ use internment_tweaks::Intern;
 use internment_tweaks::StaticInterner;
 use once_cell::sync::Lazy;
 
+use crate::legacy_configs::LegacyBuckConfig;
+
 #[derive(Debug, Eq, PartialEq, Copy, Clone, Dupe, Display, Allocative)]
 pub struct RemoteExecutorUseCase(Intern<String>);
 
@@ -85,6 +89,9 @@ pub enum Executor {
     RemoteEnabled {
         executor: RemoteEnabledExecutor,
         re_properties: SortedMap<String, String>,
+        /// Properties to use instead of `re_properties` for actions of some categories (e.g.
+        /// `cxx_link`). They are merged on top of `re_properties`.
+        re_properties_by_category: RePropertiesByCategory,
         re_use_case: RemoteExecutorUseCase,
         cache_upload_behavior: CacheUploadBehavior,
         remote_cache_enabled: bool,
@@ -100,6 +107,7 @@ impl Display for Executor {
             Self::RemoteEnabled {
                 executor,
                 re_properties: _,
+                re_properties_by_category: _,
                 re_use_case: _,
                 cache_upload_behavior,
                 remote_cache_enabled,
@@ -118,6 +126,101 @@ impl Display for Executor {
     }
 }
 
+#[derive(Debug, thiserror::Error)]
+enum RePropertiesError {
+    #[error("Expected `key=value`, got `{0}`")]
+    NotKeyValue(String),
+}
+
+/// RE properties to set for actions of some categories, keyed by category. They are merged on top
+/// of the properties of the executor, which allows e.g. routing links to workers with more memory.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Hash, Allocative)]
+pub struct RePropertiesByCategory(pub SortedMap<String, SortedMap<String, String>>);
+
+impl RePropertiesByCategory {
+    /// Section of the buckconfig overriding RE properties per category, with one key per category:
+    ///
+    /// ```ini
+    /// [re_properties_by_category]
+    /// cxx_link = pool=big-memory, container-image=linker
+    /// ```
+    pub const SECTION: &'static str = "re_properties_by_category";
+
+    pub fn from_config(config: &LegacyBuckConfig) -> anyhow::Result<Self> {
+        let mut categories = Vec::new();
+        if let Some(section) = config.get_section(Self::SECTION) {
+            for (category, value) in section.iter() {
+                let properties = parse_re_properties(value.as_str())
+                    .with_context(|| format!("Invalid `{}.{}`", Self::SECTION, category))?;
+                categories.push((category.to_owned(), properties));
+            }
+        }
+        Ok(Self(categories.into_iter().collect()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Merge `overrides` on top of these properties: categories present in both get the union of
+    /// their properties, with the values from `overrides` taking precedence.
+    pub fn merge(&self, overrides: &RePropertiesByCategory) -> RePropertiesByCategory {
+        let mut categories: BTreeMap<&String, BTreeMap<&String, &String>> = BTreeMap::new();
+        for (category, properties) in self.0.iter().chain(overrides.0.iter()) {
+            categories
+                .entry(category)
+                .or_default()
+                .extend(properties.iter());
+        }
+        Self(
+            categories
+                .into_iter()
+                .map(|(category, properties)| {
+                    (
+                        category.clone(),
+                        properties
+                            .into_iter()
+                            .map(|(k, v)| (k.clone(), v.clone()))
+                            .collect(),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    /// The properties to use for actions of `category`: `default` with this category's properties
+    /// merged on top.
+    pub fn properties_for(
+        &self,
+        category: &str,
+        default: &SortedMap<String, String>,
+    ) -> Option<SortedMap<String, String>> {
+        let properties = self.0.get(category)?;
+        let mut merged: BTreeMap<&String, &String> = default.iter().collect();
+        merged.extend(properties.iter());
+        Some(
+            merged
+                .into_iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+        )
+    }
+}
+
+/// Parse RE properties written as `key=value, key=value`.
+fn parse_re_properties(s: &str) -> anyhow::Result<SortedMap<String, String>> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| -> anyhow::Result<_> {
+            let (key, value) = item
+                .split_once('=')
+                .ok_or_else(|| RePropertiesError::NotKeyValue(item.to_owned()))?;
+            Ok((key.trim().to_owned(), value.trim().to_owned()))
+        })
+        .collect()
+}
+
 #[derive(Debug, Eq, PartialEq, Clone, Copy, Dupe, Hash, Allocative)]
 pub enum PathSeparatorKind {
     Unix,
@@ -222,3 +325,69 @@ impl CommandExecutorConfig {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::collections::sorted_map::SortedMap;
+
+    use crate::executor_config::parse_re_properties;
+    use crate::executor_config::RePropertiesByCategory;
+
+    fn properties(items: &[(&str, &str)]) -> SortedMap<String, String> {
+        items
+            .iter()
+            .map(|(k, v)| ((*k).to_owned(), (*v).to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_re_properties() -> anyhow::Result<()> {
+        assert_eq!(
+            properties(&[("container-image", "linker"), ("pool", "big-memory")]),
+            parse_re_properties("pool=big-memory, container-image = linker,")?
+        );
+        assert!(parse_re_properties("pool").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_re_properties_by_category() {
+        let rule = RePropertiesByCategory(
+            [
+                ("cxx_link", properties(&[("pool", "large")])),
+                ("test", properties(&[("os", "linux")])),
+            ]
+            .into_iter()
+            .map(|(k, v)| (k.to_owned(), v))
+            .collect(),
+        );
+        let config = RePropertiesByCategory(
+            [(
+                "cxx_link".to_owned(),
+                properties(&[("pool", "big-memory"), ("image", "linker")]),
+            )]
+            .into_iter()
+            .collect(),
+        );
+        let merged = rule.merge(&config);
+        let default = properties(&[("platform", "linux"), ("pool", "default")]);
+
+        assert_eq!(
+            Some(properties(&[
+                ("image", "linker"),
+                ("platform", "linux"),
+                ("pool", "big-memory")
+            ])),
+            merged.properties_for("cxx_link", &default)
+        );
+        assert_eq!(
+            Some(properties(&[
+                ("os", "linux"),
+                ("platform", "linux"),
+                ("pool", "default")
+            ])),
+            merged.properties_for("test", &default)
+        );
+        assert_eq!(None, merged.properties_for("cxx_compile", &default));
+    }
+}
@@ -14,7 +14,7 @@ use globset::GlobSetBuilder;
 use once_cell::sync::Lazy;
 use regex::Regex;
 
-#[derive(Debug, Allocative)]
+#[derive(Clone, Debug, Allocative)]
 pub struct IgnoreSet {
     #[allocative(skip)]
     globset: globset::GlobSet,
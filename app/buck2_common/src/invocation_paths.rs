@@ -127,6 +127,14 @@ impl InvocationPaths {
         Ok(DaemonDir { path: ret })
     }
 
+    /// Paths for the same project with a different isolation dir.
+    pub fn with_isolation(&self, isolation: FileNameBuf) -> InvocationPaths {
+        InvocationPaths {
+            roots: self.roots.clone(),
+            isolation,
+        }
+    }
+
     pub fn cell_root(&self) -> &AbsNormPath {
         &self.roots.cell_root
     }
@@ -160,10 +168,30 @@ impl InvocationPaths {
             .join(ForwardRelativePath::unchecked_new("dice_dump"))
     }
 
+    /// Directory containing the profiles written by `buck2 run --profile`.
+    pub fn profiles_dir(&self) -> AbsNormPathBuf {
+        self.buck_out_path()
+            .join(ForwardRelativePath::unchecked_new("profiles"))
+    }
+
+    /// Crash reports written by the client when it crashes on a signal.
+    pub fn crashes_dir(&self) -> AbsNormPathBuf {
+        self.buck_out_path()
+            .join(ForwardRelativePath::unchecked_new("crashes"))
+    }
+
     pub fn buck_out_dir_prefix() -> &'static ProjectRelativePath {
         ProjectRelativePath::unchecked_new("buck-out")
     }
 
+    /// `buck-out` directory containing the outputs of every isolation dir.
+    pub fn buck_out_root_path(&self) -> AbsNormPathBuf {
+        self.roots
+            .project_root
+            .root()
+            .join(Self::buck_out_dir_prefix())
+    }
+
     pub fn buck_out_dir(&self) -> ProjectRelativePathBuf {
         Self::buck_out_dir_prefix().join(&self.isolation)
     }
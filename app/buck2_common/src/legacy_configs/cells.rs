@@ -27,6 +27,7 @@ use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use gazebo::prelude::*;
 use once_cell::unsync::OnceCell;
 
+use crate::legacy_configs::external_cells::parse_external_cells;
 use crate::legacy_configs::path::BuckConfigFile;
 use crate::legacy_configs::path::DEFAULT_BUCK_CONFIG_FILES;
 use crate::legacy_configs::push_all_files_from_a_directory;
@@ -46,6 +47,8 @@ enum CellsError {
         like `root = .` which defines the root cell name"
     )]
     MissingRootCellName,
+    #[error("External cell `{0}` is not declared in the `[repositories]` section")]
+    UnknownExternalCell(NonEmptyCellAlias),
 }
 
 /// Used for creating a CellResolver in a buckv1-compatible way based on values
@@ -169,6 +172,7 @@ impl BuckConfigBasedCells {
         )?)];
         let mut cells_aggregator = CellsAggregator::new();
         let mut root_aliases = HashMap::new();
+        let mut external_cells = HashMap::new();
 
         // By definition, cell resolution should be happening against the cell mapping defined
         // by the .buckconfig of the project root.
@@ -200,6 +204,14 @@ impl BuckConfigBasedCells {
                 continue;
             }
 
+            // External cells are only fetched when they are first used, so their configs
+            // can't be read.
+            if let Some(origin) = external_cells.remove(&path) {
+                cells_aggregator.set_external(path.clone(), origin);
+                buckconfigs.insert(path, LegacyBuckConfig::empty());
+                continue;
+            }
+
             let mut buckconfig_paths: Vec<MainConfigFile> = Vec::new();
 
             for buckconfig in DEFAULT_BUCK_CONFIG_FILES {
@@ -330,6 +342,15 @@ impl BuckConfigBasedCells {
                 if is_root && !seen_dot {
                     return Err(CellsError::MissingRootCellName.into());
                 }
+
+                if is_root {
+                    for (alias, origin) in parse_external_cells(&config)? {
+                        let alias_path = root_aliases
+                            .get(&alias)
+                            .ok_or_else(|| CellsError::UnknownExternalCell(alias.clone()))?;
+                        external_cells.insert(alias_path.clone(), origin);
+                    }
+                }
             } else if is_root {
                 return Err(CellsError::MissingRootCellName.into());
             }
@@ -407,6 +428,7 @@ impl BuckConfigBasedCells {
 #[cfg(test)]
 mod tests {
 
+    use buck2_core::cells::external::ExternalCellOrigin;
     use buck2_core::cells::name::CellName;
     use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
     use buck2_core::fs::project::ProjectRoot;
@@ -516,6 +538,63 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_external_cells() -> anyhow::Result<()> {
+        let mut file_ops = TestConfigParserFileOps::new(&[
+            (
+                "/.buckconfig",
+                indoc!(
+                    r#"
+                            [repositories]
+                                root = .
+                                prelude = prelude/
+                            [external_cells]
+                                prelude = git
+                            [external_cell_prelude]
+                                git_origin = https://example.com/prelude.git
+                                commit_hash = abc
+                        "#
+                ),
+            ),
+            (
+                "/prelude/.buckconfig",
+                indoc!(
+                    r#"
+                            [buildfile]
+                                name = TARGETS
+                        "#
+                ),
+            ),
+        ])?;
+
+        let project_fs = create_project_filesystem();
+        let cells = BuckConfigBasedCells::parse_with_file_ops(
+            &project_fs,
+            &mut file_ops,
+            &[],
+            ProjectRelativePath::empty(),
+        )?;
+
+        let resolver = &cells.cell_resolver;
+        let root_instance = resolver.get(CellName::testing_new("root"))?;
+        let prelude_instance = resolver.get(CellName::testing_new("prelude"))?;
+        assert_eq!(None, root_instance.external());
+        assert_eq!(
+            Some(&ExternalCellOrigin::Git {
+                origin: "https://example.com/prelude.git".to_owned(),
+                commit: "abc".to_owned(),
+            }),
+            prelude_instance.external()
+        );
+        // The config of the cell isn't read, since the cell isn't fetched yet.
+        assert_eq!(
+            vec!["BUCK.v2", "BUCK"],
+            prelude_instance.buildfiles().map(|n| n.as_str())
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_multi_cell_with_config_file() -> anyhow::Result<()> {
         let mut file_ops = TestConfigParserFileOps::new(&[
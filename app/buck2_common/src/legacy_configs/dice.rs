@@ -58,6 +58,19 @@ impl<'a> LegacyBuckConfigOnDice<'a> {
                 property: property.to_owned(),
             })?)
     }
+
+    /// The resolved values of a whole section, for consumers which read arbitrary keys (like
+    /// `[alias]`). Only depends on that section.
+    pub fn get_section(
+        &self,
+        section: &str,
+    ) -> anyhow::Result<Option<Arc<SortedMap<String, String>>>> {
+        Ok(self
+            .config
+            .projection(&LegacyBuckConfigSectionProjectionKey {
+                section: section.to_owned(),
+            })?)
+    }
 }
 
 #[derive(Debug)]
@@ -257,6 +270,38 @@ impl ProjectionKey for LegacyBuckConfigPropertyProjectionKey {
     }
 }
 
+#[derive(Debug, Display, Hash, Eq, PartialEq, Clone, Allocative)]
+#[display(fmt = "[{}]", section)]
+struct LegacyBuckConfigSectionProjectionKey {
+    section: String,
+}
+
+impl ProjectionKey for LegacyBuckConfigSectionProjectionKey {
+    type DeriveFromKey = LegacyBuckConfigForCellKey;
+    type Value = Option<Arc<SortedMap<String, String>>>;
+
+    fn compute(
+        &self,
+        config: &SharedResult<LegacyBuckConfig>,
+        _ctx: &DiceProjectionComputations,
+    ) -> Option<Arc<SortedMap<String, String>>> {
+        // Safe for the same reason as in `LegacyBuckConfigPropertyProjectionKey`.
+        let config = config.as_ref().unwrap();
+        config.get_section(&self.section).map(|section| {
+            Arc::new(
+                section
+                    .iter()
+                    .map(|(key, value)| (key.to_owned(), value.as_str().to_owned()))
+                    .collect(),
+            )
+        })
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        x == y
+    }
+}
+
 #[derive(Debug, Display, Hash, PartialEq, Eq, Clone, Dupe, Allocative)]
 #[display(fmt = "{:?}", self)]
 struct LegacyBuckConfigCellNamesKey;
@@ -0,0 +1,450 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Cells whose contents come from an immutable external source rather than from the repo.
+//!
+//! External cells are declared in the root `.buckconfig`, in addition to their entry in
+//! `[repositories]`:
+//!
+//! ```ini
+//! [repositories]
+//!   prelude = prelude
+//!
+//! [external_cells]
+//!   prelude = git
+//!
+//! [external_cell_prelude]
+//!   git_origin = https://github.com/facebook/buck2-prelude.git
+//!   commit_hash = 0123456789abcdef0123456789abcdef01234567
+//! ```
+//!
+//! or, for a `.tar.gz` archive:
+//!
+//! ```ini
+//! [external_cells]
+//!   toolchains = archive
+//!
+//! [external_cell_toolchains]
+//!   url = https://example.com/toolchains-1.0.tar.gz
+//!   sha256 = <hex digest of the archive>
+//!   strip_prefix = toolchains-1.0
+//! ```
+//!
+//! The contents are fetched into the cell path the first time a file of the cell is accessed,
+//! and are re-fetched when the origin changes. The buckconfig files of an external cell are
+//! not read, since the cell is not fetched yet when the cells are parsed.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::process::Command;
+
+use anyhow::Context;
+use buck2_core::cells::alias::NonEmptyCellAlias;
+use buck2_core::cells::external::ExternalCellOrigin;
+use buck2_core::cells::name::CellName;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use fs4::FileExt;
+use sha2::Digest;
+use sha2::Sha256;
+
+use crate::http::HttpClient;
+use crate::http::SecureHttpClient;
+use crate::legacy_configs::LegacyBuckConfig;
+
+/// File written in the root of a materialized external cell, containing its origin.
+const EXTERNAL_CELL_MARKER: &str = ".buck2_external_cell";
+
+#[derive(Debug, thiserror::Error)]
+enum ExternalCellError {
+    #[error("Unknown origin `{1}` for external cell `{0}`, expected `git` or `archive`")]
+    UnknownOrigin(NonEmptyCellAlias, String),
+    #[error("Missing `{1}` in section `[external_cell_{0}]`")]
+    MissingKey(NonEmptyCellAlias, &'static str),
+    #[error(
+        "Directory `{0}` of external cell `{1}` is not empty and was not fetched by buck2, \
+        remove it to fetch the cell"
+    )]
+    DirectoryNotEmpty(AbsNormPathBuf, CellName),
+    #[error("Digest mismatch for `{url}`: expected sha256 `{expected}`, got `{actual}`")]
+    DigestMismatch {
+        url: String,
+        expected: String,
+        actual: String,
+    },
+    #[error("`git {0}` failed with {1}: {2}")]
+    GitFailed(String, std::process::ExitStatus, String),
+    #[error("Archive does not contain `{0}`")]
+    MissingStripPrefix(String),
+}
+
+/// Parse the `[external_cells]` section of the root buckconfig.
+pub(crate) fn parse_external_cells(
+    config: &LegacyBuckConfig,
+) -> anyhow::Result<HashMap<NonEmptyCellAlias, ExternalCellOrigin>> {
+    let mut origins = HashMap::new();
+    if let Some(section) = config.get_section("external_cells") {
+        for (alias, kind) in section.iter() {
+            let alias = NonEmptyCellAlias::new(alias.to_owned())?;
+            let origin = parse_external_cell(config, &alias, kind.as_str())?;
+            origins.insert(alias, origin);
+        }
+    }
+    Ok(origins)
+}
+
+fn parse_external_cell(
+    config: &LegacyBuckConfig,
+    alias: &NonEmptyCellAlias,
+    kind: &str,
+) -> anyhow::Result<ExternalCellOrigin> {
+    let section = format!("external_cell_{}", alias);
+    let get = |key: &'static str| -> anyhow::Result<String> {
+        config
+            .get(&section, key)
+            .map(str::to_owned)
+            .ok_or_else(|| ExternalCellError::MissingKey(alias.clone(), key).into())
+    };
+    match kind {
+        "git" => Ok(ExternalCellOrigin::Git {
+            origin: get("git_origin")?,
+            commit: get("commit_hash")?,
+        }),
+        "archive" => Ok(ExternalCellOrigin::Archive {
+            url: get("url")?,
+            sha256: get("sha256")?.to_ascii_lowercase(),
+            strip_prefix: config
+                .get(&section, "strip_prefix")
+                .map(|s| s.trim_matches('/').to_owned()),
+        }),
+        kind => Err(ExternalCellError::UnknownOrigin(alias.clone(), kind.to_owned()).into()),
+    }
+}
+
+/// Fetch the contents of the cell into `path`, unless they were already fetched from
+/// this origin. This blocks, so it must be called from a blocking thread.
+pub(crate) fn materialize_external_cell(
+    origin: &ExternalCellOrigin,
+    cell: CellName,
+    path: &AbsNormPath,
+) -> anyhow::Result<()> {
+    let marker = path.join(ForwardRelativePath::unchecked_new(EXTERNAL_CELL_MARKER));
+    let expected = origin.to_string();
+    if fs_util::read_to_string_opt(&marker)?.as_deref() == Some(expected.as_str()) {
+        return Ok(());
+    }
+
+    // Daemons with different isolation dirs run on the same project, and so share the cell
+    // path, so hold a lock while fetching, and re-check the marker once we have it.
+    let lock_path = AbsNormPathBuf::try_from(format!("{}{}.lock", path, EXTERNAL_CELL_MARKER))?;
+    if let Some(parent) = lock_path.parent() {
+        fs_util::create_dir_all(parent)?;
+    }
+    let lock = File::create(lock_path.as_path())
+        .with_context(|| format!("Creating lock file `{}`", lock_path))?;
+    lock.lock_exclusive()
+        .with_context(|| format!("Locking `{}`", lock_path))?;
+    let res = materialize_locked(origin, cell, path, &marker, expected);
+    lock.unlock()
+        .with_context(|| format!("Unlocking `{}`", lock_path))?;
+    res
+}
+
+fn materialize_locked(
+    origin: &ExternalCellOrigin,
+    cell: CellName,
+    path: &AbsNormPath,
+    marker: &AbsNormPath,
+    expected: String,
+) -> anyhow::Result<()> {
+    match fs_util::read_to_string_opt(marker)? {
+        Some(existing) if existing == expected => return Ok(()),
+        Some(_) => {}
+        None => {
+            if let Some(mut entries) = fs_util::read_dir_if_exists(path)? {
+                if entries.next().is_some() {
+                    return Err(ExternalCellError::DirectoryNotEmpty(path.to_buf(), cell).into());
+                }
+            }
+        }
+    }
+
+    tracing::info!("Fetching external cell `{}` from {}", cell, expected);
+
+    // Fetch into a staging directory first, so that an interrupted fetch never leaves
+    // a partially populated cell behind.
+    let staging = AbsNormPathBuf::try_from(format!("{}{}.tmp", path, EXTERNAL_CELL_MARKER))?;
+    fs_util::remove_all(&staging)?;
+    fs_util::create_dir_all(&staging)?;
+
+    match origin {
+        ExternalCellOrigin::Git { origin, commit } => {
+            fetch_git(&staging, origin, commit)?;
+        }
+        ExternalCellOrigin::Archive {
+            url,
+            sha256,
+            strip_prefix,
+        } => {
+            let bytes = download(url)?;
+            let actual = hex::encode(Sha256::digest(&bytes));
+            if &actual != sha256 {
+                return Err(ExternalCellError::DigestMismatch {
+                    url: url.clone(),
+                    expected: sha256.clone(),
+                    actual,
+                }
+                .into());
+            }
+            unpack_tar_gz(&bytes[..], &staging, strip_prefix.as_deref())?;
+        }
+    }
+
+    fs_util::write(
+        staging.join(ForwardRelativePath::unchecked_new(EXTERNAL_CELL_MARKER)),
+        expected,
+    )?;
+    fs_util::remove_all(path)?;
+    fs_util::rename(&staging, path)?;
+    Ok(())
+}
+
+fn git(dir: &AbsNormPath, args: &[&str]) -> anyhow::Result<()> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir.as_path())
+        .args(args)
+        .output()
+        .context("Failed to run `git`")?;
+    if !output.status.success() {
+        return Err(ExternalCellError::GitFailed(
+            args.join(" "),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        )
+        .into());
+    }
+    Ok(())
+}
+
+fn fetch_git(dir: &AbsNormPath, origin: &str, commit: &str) -> anyhow::Result<()> {
+    git(dir, &["init", "--quiet"])?;
+    git(dir, &["fetch", "--quiet", "--depth", "1", origin, commit])?;
+    git(dir, &["checkout", "--quiet", "FETCH_HEAD"])?;
+    // The cell is immutable, so there is no need to keep the history around.
+    fs_util::remove_dir_all(dir.as_path().join(".git"))?;
+    Ok(())
+}
+
+/// Download a file. Fetching is synchronous, possibly on a blocking thread of the runtime,
+/// so the request is made on a separate thread with its own runtime.
+fn download(url: &str) -> anyhow::Result<Vec<u8>> {
+    let url = url.to_owned();
+    std::thread::spawn(move || {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?
+            .block_on(async {
+                let response = SecureHttpClient::new()?.get(&url).await?;
+                let bytes = hyper::body::to_bytes(response.into_body())
+                    .await
+                    .with_context(|| format!("Reading response from `{}`", url))?;
+                Ok(bytes.to_vec())
+            })
+    })
+    .join()
+    .map_err(|_| anyhow::anyhow!("Download thread panicked"))?
+}
+
+fn unpack_tar_gz(
+    archive: impl Read,
+    dest: &AbsNormPath,
+    strip_prefix: Option<&str>,
+) -> anyhow::Result<()> {
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(archive));
+    let prefix = match strip_prefix {
+        None => return unpack_entries_in(&mut archive, dest),
+        Some(prefix) => ForwardRelativePath::new(prefix)?,
+    };
+
+    // Unpack the whole archive next to `dest`, and move the prefix into place.
+    let unpacked = AbsNormPathBuf::try_from(format!("{}.archive", dest))?;
+    fs_util::remove_all(&unpacked)?;
+    fs_util::create_dir_all(&unpacked)?;
+    unpack_entries_in(&mut archive, &unpacked)?;
+    let unpacked_prefix = unpacked.join(prefix);
+    if !fs_util::symlink_metadata_if_exists(&unpacked_prefix)?.map_or(false, |m| m.is_dir()) {
+        return Err(ExternalCellError::MissingStripPrefix(prefix.to_string()).into());
+    }
+    fs_util::remove_all(dest)?;
+    fs_util::rename(&unpacked_prefix, dest)?;
+    fs_util::remove_all(&unpacked)?;
+    Ok(())
+}
+
+/// Unpack with `unpack_in`, which refuses entries that would be written outside of `dest`,
+/// including through symlinks created by earlier entries.
+fn unpack_entries_in(
+    archive: &mut tar::Archive<impl Read>,
+    dest: &AbsNormPath,
+) -> anyhow::Result<()> {
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let entry_path = entry.path()?.into_owned();
+        let unpacked = entry
+            .unpack_in(dest.as_path())
+            .with_context(|| format!("Unpacking `{}`", entry_path.display()))?;
+        if !unpacked {
+            return Err(anyhow::anyhow!(
+                "Archive entry `{}` is not a normalized relative path",
+                entry_path.display()
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::cells::alias::NonEmptyCellAlias;
+    use buck2_core::cells::external::ExternalCellOrigin;
+    use buck2_core::fs::fs_util;
+    use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+
+    use crate::legacy_configs::external_cells::parse_external_cells;
+    use crate::legacy_configs::external_cells::unpack_tar_gz;
+    use crate::legacy_configs::testing::legacy_buck_config_from_entries;
+
+    /// A `.tar.gz` archive of the given files, and of symlinks for entries whose contents start
+    /// with `->`.
+    fn tar_gz(entries: &[(&str, &str)]) -> anyhow::Result<Vec<u8>> {
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::default(),
+        ));
+        for (path, contents) in entries {
+            let mut header = tar::Header::new_gnu();
+            match contents.strip_prefix("->") {
+                Some(target) => {
+                    header.set_entry_type(tar::EntryType::Symlink);
+                    header.set_size(0);
+                    header.set_mode(0o777);
+                    builder.append_link(&mut header, path, target)?;
+                }
+                None => {
+                    header.set_size(contents.len() as u64);
+                    header.set_mode(0o644);
+                    builder.append_data(&mut header, path, contents.as_bytes())?;
+                }
+            }
+        }
+        Ok(builder.into_inner()?.finish()?)
+    }
+
+    #[test]
+    fn test_parse_external_cells() -> anyhow::Result<()> {
+        let config = legacy_buck_config_from_entries([
+            ("external_cells", "prelude", "git"),
+            (
+                "external_cell_prelude",
+                "git_origin",
+                "https://example.com/p.git",
+            ),
+            ("external_cell_prelude", "commit_hash", "abc"),
+            ("external_cells", "tools", "archive"),
+            ("external_cell_tools", "url", "https://example.com/t.tar.gz"),
+            ("external_cell_tools", "sha256", "ABCD"),
+            ("external_cell_tools", "strip_prefix", "tools-1.0/"),
+        ])?;
+        let origins = parse_external_cells(&config)?;
+        assert_eq!(
+            origins[&NonEmptyCellAlias::new("prelude".to_owned())?],
+            ExternalCellOrigin::Git {
+                origin: "https://example.com/p.git".to_owned(),
+                commit: "abc".to_owned(),
+            }
+        );
+        assert_eq!(
+            origins[&NonEmptyCellAlias::new("tools".to_owned())?],
+            ExternalCellOrigin::Archive {
+                url: "https://example.com/t.tar.gz".to_owned(),
+                sha256: "abcd".to_owned(),
+                strip_prefix: Some("tools-1.0".to_owned()),
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_external_cells_missing_key() -> anyhow::Result<()> {
+        let config = legacy_buck_config_from_entries([
+            ("external_cells", "prelude", "git"),
+            (
+                "external_cell_prelude",
+                "git_origin",
+                "https://example.com/p.git",
+            ),
+        ])?;
+        assert!(
+            parse_external_cells(&config)
+                .unwrap_err()
+                .to_string()
+                .contains("commit_hash")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_unpack_tar_gz_strip_prefix() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let dest = AbsNormPath::new(dir.path())?.join_normalized("cell")?;
+        fs_util::create_dir_all(&dest)?;
+        let archive = tar_gz(&[
+            ("tools-1.0/BUCK", "build"),
+            ("tools-1.0/defs.bzl", "defs"),
+            ("README", "readme"),
+        ])?;
+        unpack_tar_gz(&archive[..], &dest, Some("tools-1.0"))?;
+        assert_eq!(
+            fs_util::read_to_string(dest.join_normalized("BUCK")?)?,
+            "build"
+        );
+        assert_eq!(
+            fs_util::read_to_string(dest.join_normalized("defs.bzl")?)?,
+            "defs"
+        );
+        assert!(!fs_util::try_exists(dest.join_normalized("README")?)?);
+
+        let missing = AbsNormPath::new(dir.path())?.join_normalized("missing")?;
+        fs_util::create_dir_all(&missing)?;
+        assert!(unpack_tar_gz(&archive[..], &missing, Some("other")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unpack_tar_gz_rejects_escaping_symlink() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let root = AbsNormPath::new(dir.path())?;
+        let outside = root.join_normalized("outside")?;
+        fs_util::create_dir_all(&outside)?;
+        let dest = root.join_normalized("cell")?;
+        fs_util::create_dir_all(&dest)?;
+
+        let archive = tar_gz(&[
+            ("escape", &format!("->{}", outside)),
+            ("escape/file", "contents"),
+        ])?;
+        assert!(unpack_tar_gz(&archive[..], &dest, None).is_err());
+        assert!(!fs_util::try_exists(outside.join_normalized("file")?)?);
+        Ok(())
+    }
+}
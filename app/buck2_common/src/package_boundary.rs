@@ -8,6 +8,7 @@
  */
 
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::sync::Arc;
 
 use allocative::Allocative;
@@ -29,11 +30,28 @@ use more_futures::cancellation::CancellationContext;
 use ref_cast::RefCast;
 
 use crate::legacy_configs::dice::HasLegacyConfigs;
-use crate::legacy_configs::LegacyBuckConfigs;
+use crate::legacy_configs::view::LegacyBuckConfigsView;
 use crate::result::SharedResult;
 
 #[derive(PartialEq, Allocative)]
-pub struct PackageBoundaryExceptions(HashMap<CellName, CellPackageBoundaryExceptions>);
+pub struct PackageBoundaryExceptions {
+    exceptions: HashMap<CellName, CellPackageBoundaryExceptions>,
+    /// Cells setting `project.enforce_package_boundaries`.
+    enforced_cells: HashSet<CellName>,
+}
+
+/// How package boundary violations (sources of a package which belong to another package) are
+/// reported when evaluating a package.
+#[derive(Clone, Copy, Dupe, Debug, PartialEq, Eq, Allocative)]
+pub enum PackageBoundaryMode {
+    /// Violations are soft errors.
+    Default,
+    /// The package is covered by `project.package_boundary_exceptions`, violations are allowed.
+    Exception,
+    /// The cell of the package sets `project.enforce_package_boundaries`, violations are errors
+    /// (even in packages covered by `project.package_boundary_exceptions`).
+    Enforced,
+}
 
 #[derive(PartialEq, Allocative)]
 struct CellPackageBoundaryExceptions {
@@ -97,27 +115,35 @@ impl CellPackageBoundaryExceptions {
 }
 
 impl PackageBoundaryExceptions {
-    fn new(configs: &LegacyBuckConfigs) -> anyhow::Result<Self> {
-        Ok(Self(
-            configs
-                .iter()
-                .filter_map(|(name, cell_configs)| {
-                    cell_configs
-                        .get("project", "package_boundary_exceptions")
-                        .map(|v| {
-                            let e = CellPackageBoundaryExceptions::new(v).with_context(
-                                || format!("Error parsing `project.package_boundary_exceptions` key from cell `{}`", name)
-                            )?;
-                            Ok((name, e))
-                        })
-                })
-                .collect::<anyhow::Result<_>>()?,
-        ))
+    fn new(configs: &dyn LegacyBuckConfigsView) -> anyhow::Result<Self> {
+        let mut exceptions = HashMap::new();
+        let mut enforced_cells = HashSet::new();
+        for (name, cell_configs) in configs.iter() {
+            if cell_configs
+                .parse::<bool>("project", "enforce_package_boundaries")?
+                .unwrap_or(false)
+            {
+                enforced_cells.insert(name);
+            }
+            if let Some(v) = cell_configs.get("project", "package_boundary_exceptions")? {
+                let e = CellPackageBoundaryExceptions::new(&v).with_context(|| {
+                    format!(
+                        "Error parsing `project.package_boundary_exceptions` key from cell `{}`",
+                        name
+                    )
+                })?;
+                exceptions.insert(name, e);
+            }
+        }
+        Ok(Self {
+            exceptions,
+            enforced_cells,
+        })
     }
 
     /// Returns the package boundary exception path that covers this path, if it exists
     pub fn get_package_boundary_exception_path(&self, path: &CellPath) -> Option<CellPath> {
-        if let Some(exceptions) = self.0.get(&path.cell()) {
+        if let Some(exceptions) = self.exceptions.get(&path.cell()) {
             exceptions
                 .get_package_boundary_exception_path(path.path())
                 .map(|p| CellPath::new(path.cell(), p))
@@ -132,6 +158,17 @@ impl PackageBoundaryExceptions {
             _ => true,
         }
     }
+
+    /// How package boundary violations are reported in the package at `path`.
+    pub fn mode(&self, path: &CellPath) -> PackageBoundaryMode {
+        if self.enforced_cells.contains(&path.cell()) {
+            PackageBoundaryMode::Enforced
+        } else if self.contains(path) {
+            PackageBoundaryMode::Exception
+        } else {
+            PackageBoundaryMode::Default
+        }
+    }
 }
 
 #[async_trait]
@@ -139,10 +176,10 @@ pub trait HasPackageBoundaryExceptions {
     async fn get_package_boundary_exceptions(&self)
     -> SharedResult<Arc<PackageBoundaryExceptions>>;
 
-    async fn get_package_boundary_exception(
+    async fn get_package_boundary_mode(
         &self,
         path: CellPathRef<'async_trait>,
-    ) -> SharedResult<bool>;
+    ) -> SharedResult<PackageBoundaryMode>;
 }
 
 #[async_trait]
@@ -163,8 +200,11 @@ impl HasPackageBoundaryExceptions for DiceComputations {
                 ctx: &DiceComputations,
                 _cancellations: &CancellationContext,
             ) -> Self::Value {
+                // Only depend on `project.package_boundary_exceptions` and
+                // `project.enforce_package_boundaries`, not on the whole buckconfigs, so that
+                // unrelated buckconfig changes do not recompute this.
                 Ok(Arc::new(PackageBoundaryExceptions::new(
-                    &ctx.get_legacy_configs().await?,
+                    &ctx.get_legacy_configs_on_dice().await?,
                 )?))
             }
 
@@ -183,27 +223,24 @@ impl HasPackageBoundaryExceptions for DiceComputations {
         self.compute(&PackageBoundaryExceptionsKey).await?
     }
 
-    async fn get_package_boundary_exception(
+    async fn get_package_boundary_mode(
         &self,
         path: CellPathRef<'async_trait>,
-    ) -> SharedResult<bool> {
+    ) -> SharedResult<PackageBoundaryMode> {
         #[derive(Hash, Eq, PartialEq, Clone, Display, Debug, RefCast, Allocative)]
         #[repr(transparent)]
-        struct PackageBoundaryExceptionKey(CellPath);
+        struct PackageBoundaryModeKey(CellPath);
 
         #[async_trait]
-        impl Key for PackageBoundaryExceptionKey {
-            type Value = SharedResult<bool>;
+        impl Key for PackageBoundaryModeKey {
+            type Value = SharedResult<PackageBoundaryMode>;
 
             async fn compute(
                 &self,
                 ctx: &DiceComputations,
                 _cancellations: &CancellationContext,
             ) -> Self::Value {
-                Ok(ctx
-                    .get_package_boundary_exceptions()
-                    .await?
-                    .contains(&self.0))
+                Ok(ctx.get_package_boundary_exceptions().await?.mode(&self.0))
             }
 
             fn validity(x: &Self::Value) -> bool {
@@ -218,7 +255,7 @@ impl HasPackageBoundaryExceptions for DiceComputations {
             }
         }
 
-        self.compute(&PackageBoundaryExceptionKey(path.to_owned()))
+        self.compute(&PackageBoundaryModeKey(path.to_owned()))
             .await?
     }
 }
@@ -228,6 +265,8 @@ mod tests {
     use buck2_core::cells::paths::CellRelativePath;
 
     use super::*;
+    use crate::legacy_configs::testing::legacy_buck_config_from_entries;
+    use crate::legacy_configs::LegacyBuckConfigs;
 
     fn get_package_boundary_exception_path(
         exceptions: &CellPackageBoundaryExceptions,
@@ -315,4 +354,54 @@ mod tests {
             package_boundary_allowlist_path(""),
         );
     }
+
+    #[test]
+    fn test_package_boundary_exceptions_from_configs() -> anyhow::Result<()> {
+        let configs = LegacyBuckConfigs::new(HashMap::from_iter([
+            (
+                CellName::testing_new("root"),
+                legacy_buck_config_from_entries([(
+                    "project",
+                    "package_boundary_exceptions",
+                    "foo/bar",
+                )])?,
+            ),
+            (
+                CellName::testing_new("other"),
+                legacy_buck_config_from_entries([("project", "ide", "intellij")])?,
+            ),
+            (
+                CellName::testing_new("strict"),
+                legacy_buck_config_from_entries([
+                    ("project", "package_boundary_exceptions", "foo/bar"),
+                    ("project", "enforce_package_boundaries", "true"),
+                ])?,
+            ),
+        ]));
+        let exceptions = PackageBoundaryExceptions::new(&configs)?;
+
+        let path = |cell: &str, path: &str| {
+            CellPath::new(
+                CellName::testing_new(cell),
+                CellRelativePathBuf::unchecked_new(path.to_owned()),
+            )
+        };
+        assert!(exceptions.contains(&path("root", "foo/bar/baz")));
+        assert!(!exceptions.contains(&path("root", "foo/baz")));
+        assert!(!exceptions.contains(&path("other", "foo/bar/baz")));
+
+        assert_eq!(
+            exceptions.mode(&path("root", "foo/bar/baz")),
+            PackageBoundaryMode::Exception
+        );
+        assert_eq!(
+            exceptions.mode(&path("root", "foo/baz")),
+            PackageBoundaryMode::Default
+        );
+        assert_eq!(
+            exceptions.mode(&path("strict", "foo/bar/baz")),
+            PackageBoundaryMode::Enforced
+        );
+        Ok(())
+    }
 }
@@ -96,6 +96,18 @@ impl PackageListing {
             .filter(move |x: &&PackageRelativePath| x.starts_with(dir))
     }
 
+    /// The subpackage which `path` belongs to, if it is not a path of this package.
+    pub fn subpackage_containing<'a>(
+        &'a self,
+        path: &PackageRelativePath,
+    ) -> Option<&'a PackageRelativePath> {
+        self.listing
+            .subpackages
+            .iter()
+            .map(|x| x.as_ref())
+            .find(|x: &&PackageRelativePath| path.starts_with(x))
+    }
+
     pub fn buildfile(&self) -> &FileName {
         &self.listing.buildfile
     }
@@ -113,6 +125,9 @@ pub mod testing {
         fn testing_empty() -> Self;
         fn testing_files(files: &[&str]) -> Self;
         fn testing_new(files: &[&str], buildfile: &str) -> Self;
+        /// Listing with `files` and the directories containing them, where `subpackages` are
+        /// directories containing a build file of another package.
+        fn testing_with_subpackages(files: &[&str], subpackages: &[&str]) -> Self;
     }
 
     impl PackageListingExt for PackageListing {
@@ -138,5 +153,27 @@ pub mod testing {
                 FileNameBuf::unchecked_new(buildfile),
             )
         }
+
+        #[allow(clippy::from_iter_instead_of_collect)]
+        fn testing_with_subpackages(files: &[&str], subpackages: &[&str]) -> Self {
+            let path = |p: &str| {
+                PackageRelativePathBuf::try_from(p.to_owned())
+                    .unwrap()
+                    .to_arc()
+            };
+            let directories = files
+                .iter()
+                .chain(subpackages)
+                .copied()
+                .flat_map(|f| f.match_indices('/').map(move |(i, _)| &f[..i]))
+                .chain(subpackages.iter().copied())
+                .map(path);
+            PackageListing::new(
+                SortedSet::from_iter(files.iter().map(|f| path(f))),
+                SortedSet::from_iter(directories),
+                SortedVec::from_iter(subpackages.iter().map(|p| path(p))),
+                FileNameBuf::unchecked_new("BUCK"),
+            )
+        }
     }
 }
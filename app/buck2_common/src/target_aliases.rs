@@ -7,9 +7,12 @@
  * of this source tree.
  */
 
+use std::sync::Arc;
+
 use allocative::Allocative;
 use async_trait::async_trait;
 use buck2_core::cells::name::CellName;
+use buck2_core::collections::sorted_map::SortedMap;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
 use buck2_core::package::PackageLabel;
 use buck2_core::target_aliases::TargetAliasResolver;
@@ -38,24 +41,10 @@ enum AliasResolutionError {
     AliasCycle(Vec<String>, String),
 }
 
-#[derive(Dupe, Clone, Allocative)]
+#[derive(Dupe, Clone, PartialEq, Allocative)]
 pub struct BuckConfigTargetAliasResolver {
-    config: LegacyBuckConfig,
-}
-
-impl PartialEq for BuckConfigTargetAliasResolver {
-    fn eq(&self, other: &BuckConfigTargetAliasResolver) -> bool {
-        // `TargetAliasResolver` only uses `alias` section of buckconfig,
-        // comparing only this section is enough.
-        // Please update this code if `TargetAliasResolver` uses other buckconfigs.
-        let self_aliases = self.config.get_section("alias");
-        let other_aliases = other.config.get_section("alias");
-        match (self_aliases, other_aliases) {
-            (Some(self_aliases), Some(other_aliases)) => self_aliases.compare(other_aliases),
-            (None, None) => true,
-            (None, Some(_)) | (Some(_), None) => false,
-        }
-    }
+    /// The `[alias]` section of the buckconfig, the only one this uses.
+    aliases: Option<Arc<SortedMap<String, String>>>,
 }
 
 impl TargetAliasResolver for BuckConfigTargetAliasResolver {
@@ -75,7 +64,18 @@ impl TargetAliasResolver for BuckConfigTargetAliasResolver {
 
 impl BuckConfigTargetAliasResolver {
     pub fn new(config: LegacyBuckConfig) -> Self {
-        Self { config }
+        Self::from_aliases(config.get_section("alias").map(|section| {
+            Arc::new(
+                section
+                    .iter()
+                    .map(|(key, value)| (key.to_owned(), value.as_str().to_owned()))
+                    .collect(),
+            )
+        }))
+    }
+
+    fn from_aliases(aliases: Option<Arc<SortedMap<String, String>>>) -> Self {
+        Self { aliases }
     }
 
     /// Resolves an alias in the `[alias]` section. Aliases can refer to other aliases. Any
@@ -87,7 +87,7 @@ impl BuckConfigTargetAliasResolver {
 
         let mut alias = alias;
 
-        let section = self.config.get_section("alias");
+        let section = &self.aliases;
         let mut stack = IndexSet::<&str>::new();
         loop {
             if stack.contains(alias) {
@@ -154,8 +154,12 @@ impl Key for TargetAliasResolverKey {
         ctx: &DiceComputations,
         _cancellations: &CancellationContext,
     ) -> SharedResult<BuckConfigTargetAliasResolver> {
-        let legacy_configs = ctx.get_legacy_config_for_cell(self.cell_name).await?;
-        Ok(legacy_configs.target_alias_resolver())
+        // Only depend on the `[alias]` section, so that changes to other buckconfigs do not
+        // recompute this or what depends on it.
+        let config = ctx.get_legacy_config_on_dice(self.cell_name).await?;
+        Ok(BuckConfigTargetAliasResolver::from_aliases(
+            config.get_section("alias")?,
+        ))
     }
 
     fn equality(x: &Self::Value, y: &Self::Value) -> bool {
@@ -191,12 +195,23 @@ impl HasTargetAliasResolver for DiceComputations {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
     use assert_matches::assert_matches;
+    use buck2_core::cells::name::CellName;
+    use buck2_core::target_aliases::TargetAliasResolver;
+    use dice::DetectCycles;
+    use dice::Dice;
     use indoc::indoc;
 
     use crate::legacy_configs;
+    use crate::legacy_configs::dice::SetLegacyConfigs;
+    use crate::legacy_configs::testing::legacy_buck_config_from_entries;
+    use crate::legacy_configs::LegacyBuckConfigs;
     use crate::target_aliases::AliasResolutionError;
     use crate::target_aliases::BuckConfigTargetAliasResolver;
+    use crate::target_aliases::HasTargetAliasResolver;
 
     #[test]
     fn test_aliases() -> anyhow::Result<()> {
@@ -264,4 +279,45 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_resolver_only_depends_on_aliases() -> anyhow::Result<()> {
+        let cell = CellName::testing_new("root");
+        let configs = |target: &str, mode: &str| -> anyhow::Result<LegacyBuckConfigs> {
+            Ok(LegacyBuckConfigs::new(HashMap::from_iter([(
+                cell,
+                legacy_buck_config_from_entries([
+                    ("alias", "foo", target),
+                    ("build", "mode", mode),
+                ])?,
+            )])))
+        };
+        let dice = Dice::builder().build(DetectCycles::Enabled);
+
+        let mut updater = dice.updater();
+        updater.set_legacy_configs(configs("//:foo", "dev")?)?;
+        let ctx = updater.commit().await;
+        let before = ctx.target_alias_resolver_for_cell(cell).await?;
+        drop(ctx);
+
+        // Changing another key does not recompute the resolver: we get the same value back.
+        let mut updater = dice.updater();
+        updater.set_legacy_configs(configs("//:foo", "opt")?)?;
+        let ctx = updater.commit().await;
+        let after = ctx.target_alias_resolver_for_cell(cell).await?;
+        drop(ctx);
+        assert!(Arc::ptr_eq(
+            before.aliases.as_ref().unwrap(),
+            after.aliases.as_ref().unwrap()
+        ));
+        assert_eq!(Some("//:foo"), after.get("foo")?);
+
+        let mut updater = dice.updater();
+        updater.set_legacy_configs(configs("//:bar", "opt")?)?;
+        let ctx = updater.commit().await;
+        let changed = ctx.target_alias_resolver_for_cell(cell).await?;
+        assert_eq!(Some("//:bar"), changed.get("foo")?);
+
+        Ok(())
+    }
 }
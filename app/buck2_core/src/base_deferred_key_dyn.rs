@@ -17,6 +17,7 @@ use allocative::Allocative;
 use dupe::Dupe;
 use gazebo::cmp::PartialEqAny;
 
+use crate::fs::buck_out_path::BuckOutPathScheme;
 use crate::fs::paths::forward_rel_path::ForwardRelativePath;
 use crate::fs::paths::forward_rel_path::ForwardRelativePathBuf;
 use crate::fs::project_rel_path::ProjectRelativePath;
@@ -29,6 +30,7 @@ pub trait BaseDeferredKeyDynImpl: Debug + Display + Allocative + Send + Sync + '
     fn make_hashed_path(
         &self,
         base: &ProjectRelativePath,
+        scheme: &BuckOutPathScheme,
         prefix: &ForwardRelativePath,
         action_key: Option<&str>,
         path: &ForwardRelativePath,
@@ -76,6 +78,7 @@ impl BaseDeferredKeyDyn {
     pub fn make_hashed_path(
         &self,
         base: &ProjectRelativePath,
+        scheme: &BuckOutPathScheme,
         prefix: &ForwardRelativePath,
         action_key: Option<&str>,
         path: &ForwardRelativePath,
@@ -83,6 +86,16 @@ impl BaseDeferredKeyDyn {
         match self {
             BaseDeferredKeyDyn::TargetLabel(target) => {
                 let cell_relative_path = target.pkg().cell_relative_path().as_str();
+                let cfg_hash = target.cfg().output_hash().as_str();
+                let exec_cfg_hash = target.exec_cfg().as_ref().map(|x| x.output_hash().as_str());
+                let short_cfg_hash = scheme.short_config_hash(cfg_hash, exec_cfg_hash);
+                let short_package_path = scheme.short_package_path(cell_relative_path);
+                let (cfg_hash, exec_cfg_hash) = match &short_cfg_hash {
+                    Some(hash) => (hash.as_str(), None),
+                    None => (cfg_hash, exec_cfg_hash),
+                };
+                let cell_relative_path =
+                    short_package_path.as_deref().unwrap_or(cell_relative_path);
 
                 // It is performance critical that we use slices and allocate via `join` instead of
                 // repeated calls to `join` on the path object because `join` allocates on each call,
@@ -94,12 +107,9 @@ impl BaseDeferredKeyDyn {
                     "/",
                     target.pkg().cell_name().as_str(),
                     "/",
-                    target.cfg().output_hash().as_str(),
-                    if target.exec_cfg().is_some() { "-" } else { "" },
-                    target
-                        .exec_cfg()
-                        .as_ref()
-                        .map_or("", |x| x.output_hash().as_str()),
+                    cfg_hash,
+                    if exec_cfg_hash.is_some() { "-" } else { "" },
+                    exec_cfg_hash.unwrap_or_default(),
                     "/",
                     cell_relative_path,
                     if cell_relative_path.is_empty() {
@@ -123,7 +133,9 @@ impl BaseDeferredKeyDyn {
 
                 ProjectRelativePathBuf::unchecked_new(parts.concat())
             }
-            BaseDeferredKeyDyn::Dyn(d) => d.make_hashed_path(base, prefix, action_key, path),
+            BaseDeferredKeyDyn::Dyn(d) => {
+                d.make_hashed_path(base, scheme, prefix, action_key, path)
+            }
         }
     }
 
@@ -0,0 +1,46 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::fmt;
+
+use allocative::Allocative;
+
+/// Where the contents of an external cell come from. External cells are not part of the repo,
+/// and are fetched into the cell path the first time a file of the cell is accessed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Allocative)]
+pub enum ExternalCellOrigin {
+    /// A commit of a git repository.
+    Git { origin: String, commit: String },
+    /// A `.tar.gz` archive with the given sha256 digest, optionally only using a
+    /// subdirectory of it.
+    Archive {
+        url: String,
+        sha256: String,
+        strip_prefix: Option<String>,
+    },
+}
+
+impl fmt::Display for ExternalCellOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExternalCellOrigin::Git { origin, commit } => write!(f, "git {} {}", origin, commit),
+            ExternalCellOrigin::Archive {
+                url,
+                sha256,
+                strip_prefix,
+            } => {
+                write!(f, "archive {} sha256:{}", url, sha256)?;
+                if let Some(strip_prefix) = strip_prefix {
+                    write!(f, " strip_prefix:{}", strip_prefix)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
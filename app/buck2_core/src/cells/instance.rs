@@ -15,6 +15,7 @@ use dupe::Dupe;
 
 use crate::cells::cell_root_path::CellRootPath;
 use crate::cells::cell_root_path::CellRootPathBuf;
+use crate::cells::external::ExternalCellOrigin;
 use crate::cells::name::CellName;
 use crate::cells::nested::NestedCells;
 use crate::cells::CellAliasResolver;
@@ -46,6 +47,8 @@ struct CellData {
     /// the aliases of this specific cell
     aliases: CellAliasResolver,
     nested_cells: NestedCells,
+    /// where the contents of the cell come from, if it is not part of the repo
+    external: Option<ExternalCellOrigin>,
 }
 
 impl CellInstance {
@@ -55,6 +58,7 @@ impl CellInstance {
         buildfiles: Vec<FileNameBuf>,
         aliases: CellAliasResolver,
         nested_cells: NestedCells,
+        external: Option<ExternalCellOrigin>,
     ) -> anyhow::Result<CellInstance> {
         if name != aliases.current {
             return Err(CellInstanceError::InconsistentCellName(
@@ -69,6 +73,7 @@ impl CellInstance {
             buildfiles,
             aliases,
             nested_cells,
+            external,
         })))
     }
 
@@ -99,4 +104,10 @@ impl CellInstance {
     pub fn nested_cells(&self) -> &NestedCells {
         &self.0.nested_cells
     }
+
+    /// Where the contents of the cell come from, if it is an external cell.
+    #[inline]
+    pub fn external(&self) -> Option<&ExternalCellOrigin> {
+        self.0.external.as_ref()
+    }
 }
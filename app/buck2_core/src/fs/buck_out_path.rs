@@ -163,19 +163,112 @@ impl BuckOutTestPath {
     }
 }
 
+/// The prefix of the directory which replaces a package path that is too long.
+const SHORT_PACKAGE_PREFIX: &str = "__pkg_";
+const SHORT_PACKAGE_HASH_LEN: usize = 16;
+/// Shorter configuration hashes make it likely for distinct configurations to share an output
+/// directory, and overwrite each other's outputs.
+const MIN_CONFIG_HASH_LEN: usize = 12;
+
+#[derive(Debug, thiserror::Error)]
+enum BuckOutPathSchemeError {
+    #[error(
+        "Output path config hash length must be between {} and {}, got {0}",
+        MIN_CONFIG_HASH_LEN,
+        blake3::OUT_LEN * 2
+    )]
+    ConfigHashLength(usize),
+}
+
+/// How the configuration and package parts of output paths are laid out.
+///
+/// By default output paths contain the full configuration hashes and package path, which can make
+/// them exceed the Windows `MAX_PATH` limit (260 characters). Both parts can be shortened to
+/// stable hashes instead, at the cost of paths which are harder to read; `buck2 audit output`
+/// maps a shortened path back to the action which produced it.
+#[derive(Clone, Copy, Dupe, Debug, Default, Eq, PartialEq, Hash, Allocative)]
+pub struct BuckOutPathScheme {
+    /// Replace the configuration part (`<cfg hash>[-<exec cfg hash>]`) by a hash of it with this
+    /// many hex digits. Validated by `BuckOutPathScheme::new`.
+    pub config_hash_len: Option<usize>,
+    /// Replace package paths longer than this by `__pkg_<hash>__`.
+    pub max_package_path_len: Option<usize>,
+}
+
+impl BuckOutPathScheme {
+    pub fn new(
+        config_hash_len: Option<usize>,
+        max_package_path_len: Option<usize>,
+    ) -> anyhow::Result<Self> {
+        if let Some(len) = config_hash_len {
+            if !(MIN_CONFIG_HASH_LEN..=blake3::OUT_LEN * 2).contains(&len) {
+                return Err(BuckOutPathSchemeError::ConfigHashLength(len).into());
+            }
+        }
+        Ok(Self {
+            config_hash_len,
+            max_package_path_len,
+        })
+    }
+
+    /// The hash replacing the configuration part of a path, if configuration hashes are shortened.
+    pub fn short_config_hash(&self, cfg_hash: &str, exec_cfg_hash: Option<&str>) -> Option<String> {
+        let len = self.config_hash_len?;
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(cfg_hash.as_bytes());
+        if let Some(exec_cfg_hash) = exec_cfg_hash {
+            hasher.update(b"-");
+            hasher.update(exec_cfg_hash.as_bytes());
+        }
+        let hash = hasher.finalize().to_hex();
+        Some(hash[..len.min(hash.len())].to_owned())
+    }
+
+    /// The directory replacing a cell relative package path, if the path is too long.
+    pub fn short_package_path(&self, package_path: &str) -> Option<String> {
+        if package_path.len() <= self.max_package_path_len? {
+            return None;
+        }
+        let hash = blake3::hash(package_path.as_bytes()).to_hex();
+        Some(format!(
+            "{}{}__",
+            SHORT_PACKAGE_PREFIX,
+            &hash[..SHORT_PACKAGE_HASH_LEN]
+        ))
+    }
+
+    /// Whether a path component is a directory which replaced a package path that was too long.
+    pub fn is_short_package_path(&self, component: &str) -> bool {
+        self.max_package_path_len.is_some()
+            && component.starts_with(SHORT_PACKAGE_PREFIX)
+            && component.ends_with("__")
+    }
+}
+
 #[derive(Clone, Allocative)]
-pub struct BuckOutPathResolver(ProjectRelativePathBuf);
+pub struct BuckOutPathResolver {
+    buck_out: ProjectRelativePathBuf,
+    scheme: BuckOutPathScheme,
+}
 
 impl BuckOutPathResolver {
     /// creates a 'BuckOutPathResolver' that will resolve outputs to the provided buck-out root.
     /// If not set, buck_out defaults to "buck-out/v2"
     pub fn new(buck_out: ProjectRelativePathBuf) -> Self {
-        BuckOutPathResolver(buck_out)
+        Self::with_scheme(buck_out, BuckOutPathScheme::default())
+    }
+
+    pub fn with_scheme(buck_out: ProjectRelativePathBuf, scheme: BuckOutPathScheme) -> Self {
+        BuckOutPathResolver { buck_out, scheme }
     }
 
     /// Returns the buck-out root.
     pub fn root(&self) -> &ProjectRelativePath {
-        &self.0
+        &self.buck_out
+    }
+
+    pub fn scheme(&self) -> &BuckOutPathScheme {
+        &self.scheme
     }
 
     /// Resolves a 'BuckOutPath' into a 'ProjectRelativePath' based on the base
@@ -207,16 +300,45 @@ impl BuckOutPathResolver {
         )
     }
 
+    /// Resolves the directory where an action keeps incremental state between runs. It has the
+    /// same layout as the scratch directory, but is not cleaned before the action runs.
+    pub fn resolve_incremental_state(&self, path: &BuckOutScratchPath) -> ProjectRelativePathBuf {
+        self.prefixed_path_for_owner(
+            ForwardRelativePath::unchecked_new("incremental"),
+            &path.owner,
+            None,
+            &path.path,
+        )
+    }
+
     /// Resolve a test path
     pub fn resolve_test(&self, path: &BuckOutTestPath) -> ProjectRelativePathBuf {
         ProjectRelativePathBuf::from(ForwardRelativePathBuf::concat([
-            self.0.as_forward_relative_path(),
+            self.buck_out.as_forward_relative_path(),
             ForwardRelativePath::new("test").unwrap(),
             &path.base,
             &path.path,
         ]))
     }
 
+    /// Directory holding the artifacts attached to test results by a test session.
+    pub fn resolve_test_artifacts(&self, session: &ForwardRelativePath) -> ProjectRelativePathBuf {
+        ProjectRelativePathBuf::from(ForwardRelativePathBuf::concat([
+            self.buck_out.as_forward_relative_path(),
+            ForwardRelativePath::new("test-artifacts").unwrap(),
+            session,
+        ]))
+    }
+
+    /// Directory holding the code coverage collected by a test session.
+    pub fn resolve_coverage(&self, session: &ForwardRelativePath) -> ProjectRelativePathBuf {
+        ProjectRelativePathBuf::from(ForwardRelativePathBuf::concat([
+            self.buck_out.as_forward_relative_path(),
+            ForwardRelativePath::new("coverage").unwrap(),
+            session,
+        ]))
+    }
+
     fn prefixed_path_for_owner(
         &self,
         prefix: &ForwardRelativePath,
@@ -224,7 +346,18 @@ impl BuckOutPathResolver {
         action_key: Option<&str>,
         path: &ForwardRelativePath,
     ) -> ProjectRelativePathBuf {
-        owner.make_hashed_path(&self.0, prefix, action_key, path)
+        owner.make_hashed_path(&self.buck_out, &self.scheme, prefix, action_key, path)
+    }
+
+    /// The directory holding the outputs of `owner` under `prefix` (e.g. `gen`).
+    pub fn resolve_owner_dir(
+        &self,
+        prefix: &ForwardRelativePath,
+        owner: &BaseDeferredKeyDyn,
+    ) -> ProjectRelativePathBuf {
+        let path = self.prefixed_path_for_owner(prefix, owner, None, ForwardRelativePath::empty());
+        // The empty path relative to the owner leaves a trailing separator.
+        ProjectRelativePathBuf::unchecked_new(path.as_str().trim_end_matches('/').to_owned())
     }
 
     /// This function returns the exact location of the symlink of a given target.
@@ -233,7 +366,7 @@ impl BuckOutPathResolver {
     pub fn unhashed_gen(&self, path: &BuckOutPath) -> Option<ProjectRelativePathBuf> {
         Some(ProjectRelativePathBuf::from(
             ForwardRelativePathBuf::concat([
-                self.0.as_ref(),
+                self.buck_out.as_ref(),
                 ForwardRelativePath::unchecked_new("gen"),
                 &path.0.owner.make_unhashed_path()?,
                 path.path(),
@@ -261,6 +394,7 @@ mod tests {
     use crate::configuration::data::ConfigurationData;
     use crate::fs::buck_out_path::BuckOutPath;
     use crate::fs::buck_out_path::BuckOutPathResolver;
+    use crate::fs::buck_out_path::BuckOutPathScheme;
     use crate::fs::buck_out_path::BuckOutScratchPath;
     use crate::fs::paths::forward_rel_path::ForwardRelativePathBuf;
     use crate::fs::project_rel_path::ProjectRelativePathBuf;
@@ -386,6 +520,63 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn buck_output_path_resolves_with_short_scheme() -> anyhow::Result<()> {
+        let scheme = BuckOutPathScheme::new(Some(12), Some(10))?;
+        let path_resolver = BuckOutPathResolver::with_scheme(
+            ProjectRelativePathBuf::unchecked_new("buck-out".into()),
+            scheme,
+        );
+
+        let resolve = |package: &str| {
+            let pkg = PackageLabel::new(
+                CellName::testing_new("foo"),
+                CellRelativePath::unchecked_new(package),
+            );
+            let target = TargetLabel::new(pkg, TargetNameRef::unchecked_new("target-name"));
+            path_resolver.resolve_gen(&BuckOutPath::new(
+                BaseDeferredKeyDyn::TargetLabel(target.configure(ConfigurationData::testing_new())),
+                ForwardRelativePathBuf::unchecked_new("quux".to_owned()),
+            ))
+        };
+
+        let re = Regex::new("^buck-out/gen/foo/[0-9a-f]{12}/short/__target-name__/quux$")?;
+        let resolved = resolve("short");
+        assert!(re.is_match(resolved.as_str()), "{}", resolved);
+
+        let re = Regex::new(
+            "^buck-out/gen/foo/[0-9a-f]{12}/__pkg_[0-9a-f]{16}__/__target-name__/quux$",
+        )?;
+        let resolved = resolve("a/very/long/package");
+        assert!(re.is_match(resolved.as_str()), "{}", resolved);
+
+        let short = scheme.short_package_path("a/very/long/package").unwrap();
+        assert!(scheme.is_short_package_path(&short));
+        assert!(!BuckOutPathScheme::default().is_short_package_path(&short));
+        assert_eq!(
+            resolved.as_str(),
+            resolve("a/very/long/package").as_str(),
+            "shortened paths are stable"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_short_config_hash_length() {
+        assert!(BuckOutPathScheme::new(Some(8), None).is_err());
+        assert!(BuckOutPathScheme::new(Some(65), None).is_err());
+        let scheme = BuckOutPathScheme::new(Some(12), None).unwrap();
+        assert_eq!(scheme.short_config_hash("abc", None).unwrap().len(), 12);
+        assert_ne!(
+            scheme.short_config_hash("abc", None),
+            scheme.short_config_hash("abc", Some("def"))
+        );
+        assert_eq!(
+            BuckOutPathScheme::default().short_config_hash("abc", None),
+            None
+        );
+    }
+
     #[test]
     fn test_scratch_path_is_sensible() {
         let pkg = PackageLabel::new(
@@ -982,7 +982,7 @@ where
     Ok(Some(res))
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub enum PackageSpec<T: PatternType> {
     /// Given targets in a package.
     Targets(Vec<(TargetName, T)>),
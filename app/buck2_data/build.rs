@@ -99,6 +99,10 @@ fn main() -> io::Result<()> {
             "buck.data.AnalysisEnd.target",
             "#[derive(::derive_more::From, ::gazebo::variants::VariantName)]",
         )
+        .type_attribute(
+            "buck.data.AnalysisWarning.target",
+            "#[derive(::derive_more::From, ::gazebo::variants::VariantName)]",
+        )
         .type_attribute("buck.data.TargetLabel", "#[derive(Eq, Hash)]")
         .type_attribute("buck.data.Configuration", "#[derive(Eq, Hash)]")
         .type_attribute("buck.data.ConfiguredTargetLabel", "#[derive(Eq, Hash)]")
@@ -11,6 +11,7 @@ use std::fmt;
 
 use dupe::Dupe;
 
+use crate::humanized::HumanizedBytes;
 use crate::last_command_execution_kind::get_last_command_execution_kind;
 use crate::last_command_execution_kind::LastCommandExecutionKind;
 
@@ -23,12 +24,17 @@ use crate::last_command_execution_kind::LastCommandExecutionKind;
 /// that had its command run more than once (hence, using fallback to run).
 ///
 /// These stats only track executions/commands.
+///
+/// `cache_uploads` and `cache_upload_bytes` count the action results (and the size of their
+/// outputs) that were successfully uploaded to the remote cache, independently of the above.
 #[derive(Default, Clone, Dupe)]
 pub struct ActionStats {
     pub local_actions: u64,
     pub remote_actions: u64,
     pub cached_actions: u64,
     pub fallback_actions: u64,
+    pub cache_uploads: u64,
+    pub cache_upload_bytes: u64,
 }
 
 impl ActionStats {
@@ -78,6 +84,13 @@ impl ActionStats {
         }
     }
 
+    pub fn update_cache_upload(&mut self, cache_upload: &buck2_data::CacheUploadEnd) {
+        if cache_upload.success {
+            self.cache_uploads += 1;
+            self.cache_upload_bytes += cache_upload.output_bytes.unwrap_or_default();
+        }
+    }
+
     pub fn log_stats(&self) -> bool {
         self.total_executed_and_cached_actions() > 0
     }
@@ -101,6 +114,14 @@ impl fmt::Display for ActionStats {
             )
             .as_str();
         }
+        if self.cache_uploads > 0 {
+            action_stats_message += format!(
+                ". Cache uploads: {} ({})",
+                self.cache_uploads,
+                HumanizedBytes::new(self.cache_upload_bytes)
+            )
+            .as_str();
+        }
         write!(f, "{}", action_stats_message)
     }
 }
@@ -39,6 +39,7 @@ use superconsole::Span;
 use thiserror::Error;
 
 use crate::fmt_duration;
+use crate::humanized::HumanizedBytes;
 use crate::verbosity::Verbosity;
 
 #[derive(Copy, Clone, Dupe)]
@@ -110,6 +111,25 @@ pub fn display_analysis_target(
     }
 }
 
+/// Display a warning emitted by a rule implementation, e.g.
+/// `Warning for root//foo:bar (cfg): `x` is deprecated [deprecated]`.
+pub fn display_analysis_warning(
+    warning: &buck2_data::AnalysisWarning,
+    opts: TargetDisplayOptions,
+) -> anyhow::Result<String> {
+    use buck2_data::analysis_warning::Target;
+    let target = match &warning.target {
+        Some(Target::StandardTarget(ctl)) => display_configured_target_label(ctl, opts)?,
+        Some(Target::AnonTarget(anon)) => display_anon_target(anon)?,
+        None => return Err(ParseEventError::MissingConfiguredTargetLabel.into()),
+    };
+    let mut res = format!("Warning for {}: {}", target, warning.message);
+    if !warning.tags.is_empty() {
+        write!(res, " [{}]", warning.tags.join(", "))?;
+    }
+    Ok(res)
+}
+
 pub fn display_bxl_key(ctl: &BxlFunctionKey) -> anyhow::Result<String> {
     if let BxlFunctionKey {
         label: Some(BxlFunctionLabel { bxl_path, name }),
@@ -179,6 +199,37 @@ pub fn display_action_identity(
     Ok(format!("{}{}", key_string, action_string))
 }
 
+/// Display a warning for an action whose inputs exceed the `action_input_budget` buckconfig,
+/// followed by the transitive sets contributing the most to it, one per line.
+pub fn display_action_input_budget_exceeded(
+    warning: &buck2_data::ActionInputBudgetExceeded,
+    opts: TargetDisplayOptions,
+) -> anyhow::Result<String> {
+    let action = display_action_identity(warning.key.as_ref(), warning.name.as_ref(), opts)?;
+    let exceeded = warning
+        .exceeded
+        .iter()
+        .map(|limit| format!("{} is {} > {}", limit.name, limit.value, limit.limit));
+    let mut res = format!(
+        "Warning for {}: inputs exceed the budget ({})",
+        action,
+        exceeded.collect::<Vec<_>>().join(", ")
+    );
+    if !warning.largest_contributors.is_empty() {
+        res.push_str(". Largest transitive sets:");
+        for contributor in &warning.largest_contributors {
+            write!(
+                res,
+                "\n  {}: {} inputs, {}",
+                contributor.name,
+                contributor.input_count,
+                HumanizedBytes::new(contributor.input_bytes)
+            )?;
+        }
+    }
+    Ok(res)
+}
+
 /// Formats event payloads for display.
 pub fn display_event(event: &BuckEvent, opts: TargetDisplayOptions) -> anyhow::Result<String> {
     let res: anyhow::Result<_> = try {
@@ -386,6 +437,18 @@ pub fn display_file_watcher_end(file_watcher_end: &buck2_data::FileWatcherEnd) -
             }
             res.push(msg);
         }
+
+        if let Some(unavailable) = &stats.watchman_unavailable {
+            if unavailable.cleared_dice {
+                res.push(format!(
+                    "Watchman is unavailable, watching files natively until it is back \
+                    (cleared graph state): {}",
+                    unavailable.error
+                ));
+            } else {
+                res.push("Watchman is still unavailable, watching files natively".to_owned());
+            }
+        }
     }
 
     res
@@ -577,7 +640,9 @@ fn failure_reason_for_command_execution(
 
     let locality = match command.command {
         Some(Command::RemoteCommand(..)) => "Remote ",
-        Some(Command::LocalCommand(..)) | Some(Command::OmittedLocalCommand(..)) => "Local ",
+        Some(Command::LocalCommand(..))
+        | Some(Command::OmittedLocalCommand(..))
+        | Some(Command::LocalActionCacheCommand(..)) => "Local ",
         None => "",
     };
 
@@ -74,6 +74,9 @@ where
                         ActionExecution(action_execution_end) => {
                             self.action_stats.update(action_execution_end);
                         }
+                        CacheUpload(cache_upload_end) => {
+                            self.action_stats.update_cache_upload(cache_upload_end);
+                        }
                         _ => {}
                     }
                 }
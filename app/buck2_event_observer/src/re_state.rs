@@ -114,6 +114,15 @@ impl ReState {
         Ok(Some(Line::unstyled(&line)?))
     }
 
+    /// RPCs waiting on the RPC scheduler, shown only when some are.
+    fn render_queue(&self, name: &str, queued: u32, running: u32) -> anyhow::Result<Option<Line>> {
+        if queued == 0 {
+            return Ok(None);
+        }
+        let line = format!("{name:<20}: {queued:>5} queued, {running:>5} running");
+        Ok(Some(Line::unstyled(&line)?))
+    }
+
     fn render_detailed(&self) -> anyhow::Result<Vec<Line>> {
         let mut r = Vec::new();
         if let Some((_, last)) = &self.two_snapshots.last {
@@ -159,6 +168,21 @@ impl ReState {
                 last.re_get_digest_expirations_finished_successfully,
                 last.re_get_digest_expirations_finished_with_error,
             )?);
+            r.extend(self.render_queue(
+                "execution_rpcs",
+                last.re_execution_rpcs_queued,
+                last.re_execution_rpcs_running,
+            )?);
+            r.extend(self.render_queue(
+                "cache_query_rpcs",
+                last.re_cache_query_rpcs_queued,
+                last.re_cache_query_rpcs_running,
+            )?);
+            r.extend(self.render_queue(
+                "prefetch_rpcs",
+                last.re_prefetch_rpcs_queued,
+                last.re_prefetch_rpcs_running,
+            )?);
         }
         Ok(r)
     }
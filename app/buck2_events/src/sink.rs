@@ -11,5 +11,6 @@
 //! sink during normal operation.
 pub(crate) mod channel;
 pub(crate) mod null;
+pub mod ring_buffer;
 pub mod scribe;
 pub mod tee;
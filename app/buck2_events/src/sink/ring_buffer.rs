@@ -0,0 +1,131 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use dupe::Dupe;
+
+use crate::BuckEvent;
+use crate::ControlEvent;
+use crate::EventSink;
+use crate::EventSinkStats;
+
+/// An EventSink that retains the most recent `capacity` events it receives, discarding older ones.
+/// The daemon keeps one of these across commands so that recent events can be recovered after the
+/// client that requested them has gone away (or failed to write its event log).
+#[derive(Clone, Dupe)]
+pub struct RingBufferSink(Arc<RingBufferSinkData>);
+
+struct RingBufferSinkData {
+    capacity: usize,
+    events: Mutex<VecDeque<BuckEvent>>,
+}
+
+impl RingBufferSink {
+    pub fn new(capacity: usize) -> RingBufferSink {
+        RingBufferSink(Arc::new(RingBufferSinkData {
+            capacity,
+            events: Mutex::new(VecDeque::with_capacity(capacity)),
+        }))
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.0.capacity
+    }
+
+    /// The retained events, oldest first.
+    pub fn events(&self) -> Vec<BuckEvent> {
+        self.0.events.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+impl EventSink for RingBufferSink {
+    fn send(&self, event: BuckEvent) {
+        if self.0.capacity == 0 {
+            return;
+        }
+        let mut events = self.0.events.lock().unwrap();
+        if events.len() == self.0.capacity {
+            events.pop_front();
+        }
+        events.push_back(event);
+    }
+
+    fn send_control(&self, _: ControlEvent) {}
+
+    fn stats(&self) -> Option<EventSinkStats> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::SystemTime;
+
+    use buck2_data::buck_event::Data::SpanStart;
+    use buck2_data::span_start_event::Data::Command;
+    use buck2_data::CommandStart;
+    use buck2_data::SpanStartEvent;
+
+    use super::RingBufferSink;
+    use crate::BuckEvent;
+    use crate::EventSink;
+    use crate::TraceId;
+
+    fn event(n: u64) -> BuckEvent {
+        BuckEvent::new(
+            SystemTime::now(),
+            TraceId::new(),
+            None,
+            None,
+            SpanStartEvent {
+                data: Some(
+                    CommandStart {
+                        data: None,
+                        metadata: HashMap::from([("n".to_owned(), n.to_string())]),
+                    }
+                    .into(),
+                ),
+            }
+            .into(),
+        )
+    }
+
+    fn retained(sink: &RingBufferSink) -> Vec<String> {
+        sink.events()
+            .iter()
+            .map(|e| match e.data() {
+                SpanStart(SpanStartEvent {
+                    data: Some(Command(c)),
+                }) => c.metadata["n"].clone(),
+                _ => panic!("unexpected event"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_ring_buffer_retains_most_recent() {
+        let sink = RingBufferSink::new(2);
+        sink.send(event(1));
+        sink.send(event(2));
+        assert_eq!(retained(&sink), vec!["1", "2"]);
+        sink.send(event(3));
+        assert_eq!(retained(&sink), vec!["2", "3"]);
+    }
+
+    #[test]
+    fn test_ring_buffer_disabled() {
+        let sink = RingBufferSink::new(0);
+        sink.send(event(1));
+        assert!(sink.events().is_empty());
+    }
+}
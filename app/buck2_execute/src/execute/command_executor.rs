@@ -14,6 +14,7 @@ use std::time::Duration;
 use buck2_common::executor_config::CommandGenerationOptions;
 use buck2_common::executor_config::OutputPathsBehavior;
 use buck2_common::file_ops::TrackedFileDigest;
+use buck2_core::collections::sorted_map::SortedMap;
 use buck2_core::directory::FingerprintedDirectory;
 use buck2_core::fs::artifact_path_resolver::ArtifactFs;
 use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
@@ -69,6 +70,8 @@ struct CommandExecutorData {
     artifact_fs: ArtifactFs,
     options: CommandGenerationOptions,
     re_platform: RE::Platform,
+    /// Platforms to use instead of `re_platform` for actions of some categories.
+    re_platform_by_category: SortedMap<String, RE::Platform>,
 }
 
 impl CommandExecutor {
@@ -77,12 +80,14 @@ impl CommandExecutor {
         artifact_fs: ArtifactFs,
         options: CommandGenerationOptions,
         re_platform: RE::Platform,
+        re_platform_by_category: SortedMap<String, RE::Platform>,
     ) -> Self {
         Self(Arc::new(CommandExecutorData {
             inner,
             artifact_fs,
             options,
             re_platform,
+            re_platform_by_category,
         }))
     }
 
@@ -107,7 +112,9 @@ impl CommandExecutor {
         digest_config: DigestConfig,
         cancellations: &CancellationContext,
     ) -> CommandExecutionResult {
-        let (manager, prepared_action) = self.prepare(manager, request, digest_config).await?;
+        let (manager, prepared_action) = self
+            .prepare(manager, action, request, digest_config)
+            .await?;
         self.0
             .inner
             .exec_cmd(
@@ -129,9 +136,21 @@ impl CommandExecutor {
             .is_local_execution_possible(executor_preference)
     }
 
+    /// The RE platform for `action`: the platform of its category if there is one.
+    fn re_platform(&self, action: &dyn CommandExecutionTarget) -> &RE::Platform {
+        if self.0.re_platform_by_category.is_empty() {
+            return &self.0.re_platform;
+        }
+        self.0
+            .re_platform_by_category
+            .get(action.as_proto_action_name().category.as_str())
+            .unwrap_or(&self.0.re_platform)
+    }
+
     async fn prepare(
         &self,
         manager: CommandExecutionManager,
+        target: &dyn CommandExecutionTarget,
         request: &CommandExecutionRequest,
         digest_config: DigestConfig,
     ) -> ControlFlow<CommandExecutionResult, (CommandExecutionManager, PreparedAction)> {
@@ -152,7 +171,7 @@ impl CommandExecutor {
                 input_digest,
                 action_metadata_blobs,
                 request.timeout(),
-                self.0.re_platform.clone(),
+                self.re_platform(target).clone(),
                 false,
                 digest_config,
                 self.0.options.output_paths_behavior,
@@ -12,6 +12,7 @@
 use std::sync::Arc;
 
 use buck2_common::executor_config::CommandExecutorConfig;
+use buck2_core::collections::sorted_map::SortedMap;
 use buck2_core::fs::artifact_path_resolver::ArtifactFs;
 use dice::DiceComputations;
 use dice::DiceData;
@@ -25,6 +26,8 @@ use crate::re::manager::ManagedRemoteExecutionClient;
 pub struct CommandExecutorResponse {
     pub executor: Arc<dyn PreparedCommandExecutor>,
     pub platform: RE::Platform,
+    /// Platforms to use instead of `platform` for actions of some categories.
+    pub platform_by_category: SortedMap<String, RE::Platform>,
 }
 
 pub trait SetCommandExecutor {
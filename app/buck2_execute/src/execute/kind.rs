@@ -25,10 +25,21 @@ pub enum CommandExecutionKind {
     },
     /// This action was executed via a remote executor.
     #[display(fmt = "remote")]
-    Remote { digest: ActionDigest },
+    Remote {
+        digest: ActionDigest,
+        /// The RE worker which executed the action, if reported.
+        worker: Option<String>,
+    },
     /// This action was served by the action cache and not executed.
     #[display(fmt = "action_cache")]
-    ActionCache { digest: ActionDigest },
+    ActionCache {
+        digest: ActionDigest,
+        /// The RE worker which originally executed the action, if reported.
+        worker: Option<String>,
+    },
+    /// This action was served by the local disk cache and not executed.
+    #[display(fmt = "local_action_cache")]
+    LocalActionCache { digest: ActionDigest },
 }
 
 impl CommandExecutionKind {
@@ -37,6 +48,24 @@ impl CommandExecutionKind {
             Self::Local { .. } => buck2_data::ActionExecutionKind::Local,
             Self::Remote { .. } => buck2_data::ActionExecutionKind::Remote,
             Self::ActionCache { .. } => buck2_data::ActionExecutionKind::ActionCache,
+            Self::LocalActionCache { .. } => buck2_data::ActionExecutionKind::LocalActionCache,
+        }
+    }
+
+    pub fn digest(&self) -> &ActionDigest {
+        match self {
+            Self::Local { digest, .. }
+            | Self::Remote { digest, .. }
+            | Self::ActionCache { digest, .. }
+            | Self::LocalActionCache { digest } => digest,
+        }
+    }
+
+    /// The RE worker which executed the action, if it ran remotely.
+    pub fn worker(&self) -> Option<&str> {
+        match self {
+            Self::Remote { worker, .. } | Self::ActionCache { worker, .. } => worker.as_deref(),
+            Self::Local { .. } | Self::LocalActionCache { .. } => None,
         }
     }
 }
@@ -8,6 +8,7 @@
  */
 
 use std::fmt::Display;
+use std::str::FromStr;
 use std::time::Duration;
 
 use allocative::Allocative;
@@ -29,6 +30,7 @@ use derive_more::Display;
 use dupe::Dupe;
 use gazebo::variants::UnpackVariants;
 use host_sharing::host_sharing::HostSharingRequirements;
+use host_sharing::MemoryRequirement;
 use indexmap::IndexSet;
 use sorted_vector_map::SortedVectorMap;
 use thiserror::Error;
@@ -79,6 +81,46 @@ pub enum ExecutorPreference {
     RemotePreferred,
 }
 
+#[derive(Debug, Error)]
+#[error("Invalid nondeterminism `{0}`, expected `no_verify` or `prefer_cached`")]
+pub struct InvalidNondeterminism(String);
+
+/// Whether the outputs of a command may differ between runs (e.g. archives embedding timestamps),
+/// and how the cache should treat the command if so.
+#[derive(Copy, Clone, Dupe, Display, Debug, PartialEq, Eq, Hash, Allocative)]
+pub enum Nondeterminism {
+    /// The outputs only depend on the inputs.
+    #[display(fmt = "deterministic")]
+    Deterministic,
+    /// The outputs may differ between runs, so they are never checked against a rebuild: results
+    /// of a rebuild which skips the cache (`--no-remote-cache`) are never written to the cache,
+    /// since that would replace outputs which other cached actions were built against.
+    #[display(fmt = "no_verify")]
+    NoVerify,
+    /// The outputs may differ between runs, so a cached result is always preferred to running the
+    /// command: the cache is queried for this command even with `--no-remote-cache`.
+    #[display(fmt = "prefer_cached")]
+    PreferCached,
+}
+
+impl Nondeterminism {
+    pub fn is_deterministic(self) -> bool {
+        self == Nondeterminism::Deterministic
+    }
+}
+
+impl FromStr for Nondeterminism {
+    type Err = InvalidNondeterminism;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "no_verify" => Ok(Nondeterminism::NoVerify),
+            "prefer_cached" => Ok(Nondeterminism::PreferCached),
+            _ => Err(InvalidNondeterminism(s.to_owned())),
+        }
+    }
+}
+
 impl ExecutorPreference {
     pub fn and(self, other: Self) -> anyhow::Result<Self> {
         let requires_remote = self.requires_remote() || other.requires_remote();
@@ -233,11 +275,19 @@ pub struct CommandExecutionRequest {
     args: Vec<String>,
     paths: CommandExecutionPaths,
     env: SortedVectorMap<String, String>,
+    /// Environment variables which are not part of the action digest, so they can change between
+    /// runs without affecting caching (e.g. the build ID). Only the local executor supports them:
+    /// the remote executor rejects requests which have any.
+    cache_exempt_env: SortedVectorMap<String, String>,
     timeout: Option<Duration>,
     executor_preference: ExecutorPreference,
     // Run with a custom $TMPDIR, or just the standard system one
     custom_tmpdir: Option<BuckOutScratchPath>,
+    /// Directory preserved between runs of the command on this host, which it may write to.
+    incremental_state_dir: Option<ProjectRelativePathBuf>,
     host_sharing_requirements: HostSharingRequirements,
+    /// Memory to reserve from the local host while this command runs.
+    memory_requirement: Option<MemoryRequirement>,
     /// Working directory, relative to the project root.
     working_directory: Option<ProjectRelativePathBuf>,
     /// Whether we should always prefetch stderr when executing. When it's needed, this lets us
@@ -250,11 +300,20 @@ pub struct CommandExecutionRequest {
     local_environment_inheritance: Option<EnvironmentInheritance>,
     /// Whether this command should be uploaded to cache when successful.
     allow_cache_upload: bool,
+    /// Whether this command may be uploaded to cache when `--upload-all-outputs` is passed, even
+    /// if `allow_cache_upload` is not set.
+    allow_forced_cache_upload: bool,
     /// Whether this command should override the fallback-only behavior on an hybrid executor and
     /// thus always run as if the executor was full-hybrid, assuming it is capable.
     force_full_hybrid_if_capable: bool,
     /// Whether to disable capturing performance counters for this execution.
     disable_miniperf: bool,
+    /// Whether to run this command unsandboxed when local actions are sandboxed.
+    disable_sandbox: bool,
+    /// Whether to run this command in a pseudo-terminal when it runs locally.
+    tty: bool,
+    /// Whether the outputs may differ between runs, which changes how the cache is used.
+    nondeterminism: Nondeterminism,
     required_local_resources: SortedSet<LocalResourceState>,
 }
 
@@ -268,17 +327,24 @@ impl CommandExecutionRequest {
             args,
             paths,
             env,
+            cache_exempt_env: SortedVectorMap::new(),
             timeout: None,
             executor_preference: ExecutorPreference::Default,
             custom_tmpdir: None,
+            incremental_state_dir: None,
             host_sharing_requirements: HostSharingRequirements::default(),
+            memory_requirement: None,
             working_directory: None,
             prefetch_lossy_stderr: false,
             outputs_cleanup: true,
             local_environment_inheritance: None,
             allow_cache_upload: false,
+            allow_forced_cache_upload: true,
             force_full_hybrid_if_capable: false,
             disable_miniperf: false,
+            disable_sandbox: false,
+            tty: false,
+            nondeterminism: Nondeterminism::Deterministic,
             required_local_resources: SortedSet::new(),
         }
     }
@@ -306,6 +372,15 @@ impl CommandExecutionRequest {
         self
     }
 
+    pub fn incremental_state_dir(&self) -> Option<&ProjectRelativePath> {
+        self.incremental_state_dir.as_deref()
+    }
+
+    pub fn with_incremental_state_dir(mut self, dir: ProjectRelativePathBuf) -> Self {
+        self.incremental_state_dir = Some(dir);
+        self
+    }
+
     pub fn with_host_sharing_requirements(
         mut self,
         host_sharing_requirements: HostSharingRequirements,
@@ -314,6 +389,11 @@ impl CommandExecutionRequest {
         self
     }
 
+    pub fn with_memory_requirement(mut self, memory_requirement: MemoryRequirement) -> Self {
+        self.memory_requirement = Some(memory_requirement);
+        self
+    }
+
     pub fn with_working_directory(mut self, working_directory: ProjectRelativePathBuf) -> Self {
         self.working_directory = Some(working_directory);
         self
@@ -353,6 +433,18 @@ impl CommandExecutionRequest {
         &self.env
     }
 
+    pub fn with_cache_exempt_env(
+        mut self,
+        cache_exempt_env: SortedVectorMap<String, String>,
+    ) -> Self {
+        self.cache_exempt_env = cache_exempt_env;
+        self
+    }
+
+    pub fn cache_exempt_env(&self) -> &SortedVectorMap<String, String> {
+        &self.cache_exempt_env
+    }
+
     pub fn timeout(&self) -> Option<Duration> {
         self.timeout
     }
@@ -365,6 +457,10 @@ impl CommandExecutionRequest {
         &self.host_sharing_requirements
     }
 
+    pub fn memory_requirement(&self) -> Option<&MemoryRequirement> {
+        self.memory_requirement.as_ref()
+    }
+
     pub fn working_directory(&self) -> Option<&ProjectRelativePath> {
         self.working_directory.as_deref()
     }
@@ -390,6 +486,15 @@ impl CommandExecutionRequest {
         self.allow_cache_upload
     }
 
+    pub fn with_allow_forced_cache_upload(mut self, allow_forced_cache_upload: bool) -> Self {
+        self.allow_forced_cache_upload = allow_forced_cache_upload;
+        self
+    }
+
+    pub fn allow_forced_cache_upload(&self) -> bool {
+        self.allow_forced_cache_upload
+    }
+
     pub fn with_force_full_hybrid_if_capable(mut self, force_full_hybrid_if_capable: bool) -> Self {
         self.force_full_hybrid_if_capable = force_full_hybrid_if_capable;
         self
@@ -408,6 +513,33 @@ impl CommandExecutionRequest {
         self.disable_miniperf
     }
 
+    pub fn with_disable_sandbox(mut self, disable_sandbox: bool) -> Self {
+        self.disable_sandbox = disable_sandbox;
+        self
+    }
+
+    pub fn disable_sandbox(&self) -> bool {
+        self.disable_sandbox
+    }
+
+    pub fn with_tty(mut self, tty: bool) -> Self {
+        self.tty = tty;
+        self
+    }
+
+    pub fn tty(&self) -> bool {
+        self.tty
+    }
+
+    pub fn with_nondeterminism(mut self, nondeterminism: Nondeterminism) -> Self {
+        self.nondeterminism = nondeterminism;
+        self
+    }
+
+    pub fn nondeterminism(&self) -> Nondeterminism {
+        self.nondeterminism
+    }
+
     pub fn with_required_local_resources(
         mut self,
         required_local_resources: Vec<LocalResourceState>,
@@ -7,10 +7,69 @@
  * of this source tree.
  */
 
+use std::str::FromStr;
+use std::sync::Arc;
+
 use dupe::Dupe;
 
 /// Command-level config that can tweak how the executors work.
 #[derive(Clone, Dupe, Default)]
 pub struct ExecutorGlobalKnobs {
     pub enable_miniperf: bool,
+    /// Whether to run local actions in a sandbox only exposing their inputs and outputs.
+    pub sandbox_local_actions: bool,
+    /// The executor plugin to run local actions with instead of spawning them, if any.
+    pub executor_plugin: Option<ExecutorPluginAddress>,
+}
+
+#[derive(Debug, thiserror::Error)]
+enum ExecutorPluginAddressError {
+    #[error(
+        "Invalid executor plugin address `{0}`, expected `unix:<socket path>` or `tcp:<port>`"
+    )]
+    Invalid(String),
+}
+
+/// Where an executor plugin (a gRPC server implementing `buck.executor_plugin.ExecutorPlugin`)
+/// listens, as set in the `build.executor_plugin` buckconfig.
+#[derive(Clone, Dupe, Debug, PartialEq, Eq, Hash, derive_more::Display)]
+pub enum ExecutorPluginAddress {
+    /// A unix domain socket.
+    #[display(fmt = "unix:{}", _0)]
+    Unix(Arc<str>),
+    /// A port on localhost.
+    #[display(fmt = "tcp:{}", _0)]
+    Tcp(u16),
+}
+
+impl FromStr for ExecutorPluginAddress {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let invalid = || ExecutorPluginAddressError::Invalid(s.to_owned());
+        match s.split_once(':') {
+            Some(("unix", path)) if !path.is_empty() => Ok(Self::Unix(path.into())),
+            Some(("tcp", port)) => Ok(Self::Tcp(port.parse().map_err(|_| invalid())?)),
+            _ => Err(invalid().into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::knobs::ExecutorPluginAddress;
+
+    #[test]
+    fn test_parse_executor_plugin_address() -> anyhow::Result<()> {
+        assert_eq!(
+            ExecutorPluginAddress::Unix("/tmp/plugin.sock".into()),
+            "unix:/tmp/plugin.sock".parse()?
+        );
+        assert_eq!(ExecutorPluginAddress::Tcp(5000), "tcp:5000".parse()?);
+        assert_eq!("tcp:5000", ExecutorPluginAddress::Tcp(5000).to_string());
+        assert!("unix:".parse::<ExecutorPluginAddress>().is_err());
+        assert!("tcp:localhost".parse::<ExecutorPluginAddress>().is_err());
+        assert!("/tmp/plugin.sock".parse::<ExecutorPluginAddress>().is_err());
+        Ok(())
+    }
 }
@@ -7,7 +7,11 @@
  * of this source tree.
  */
 
+use std::collections::HashMap;
+use std::io::Read;
 use std::io::Write;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -15,8 +19,11 @@ use allocative::Allocative;
 use anyhow::Context as _;
 use buck2_common::cas_digest::CasDigestConfig;
 use buck2_common::cas_digest::DigestAlgorithmKind;
+use buck2_common::cas_digest::Digester;
 use buck2_common::file_ops::FileDigest;
+use buck2_common::file_ops::FileDigestKind;
 use buck2_common::file_ops::TrackedFileDigest;
+use buck2_core::env_helper::EnvHelper;
 use buck2_core::fs::fs_util;
 use buck2_core::fs::project::ProjectRoot;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
@@ -27,6 +34,9 @@ use dupe::Dupe;
 use futures::future::Future;
 use futures::stream::Stream;
 use futures::StreamExt;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use reqwest::header::RANGE;
 use reqwest::Client;
 use reqwest::RequestBuilder;
 use reqwest::Response;
@@ -36,31 +46,50 @@ use sha1::Sha1;
 use sha2::Sha256;
 use smallvec::SmallVec;
 use thiserror::Error;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::Semaphore;
 
 use crate::digest_config::DigestConfig;
 
+/// Limit on the number of concurrent downloads from a single host. Unlimited if unset.
+static HTTP_MAX_CONCURRENT_PER_HOST: EnvHelper<usize> =
+    EnvHelper::new("BUCK2_HTTP_MAX_CONCURRENT_PER_HOST");
+
+/// Checksums a downloaded file must match. At least one is set.
 #[derive(Debug, Clone, Dupe, Allocative)]
-pub enum Checksum {
-    Sha1(Arc<str>),
-    Sha256(Arc<str>),
-    Both { sha1: Arc<str>, sha256: Arc<str> },
+pub struct Checksum {
+    sha1: Option<Arc<str>>,
+    sha256: Option<Arc<str>>,
+    blake3: Option<Arc<str>>,
 }
 
 impl Checksum {
-    pub fn sha1(&self) -> Option<&str> {
-        match self {
-            Self::Sha1(sha1) => Some(sha1),
-            Self::Sha256(..) => None,
-            Self::Both { sha1, .. } => Some(sha1),
+    /// Returns `None` if no checksum is given.
+    pub fn new(
+        sha1: Option<Arc<str>>,
+        sha256: Option<Arc<str>>,
+        blake3: Option<Arc<str>>,
+    ) -> Option<Checksum> {
+        if sha1.is_none() && sha256.is_none() && blake3.is_none() {
+            return None;
         }
+        Some(Checksum {
+            sha1,
+            sha256,
+            blake3,
+        })
+    }
+
+    pub fn sha1(&self) -> Option<&str> {
+        self.sha1.as_deref()
     }
 
     pub fn sha256(&self) -> Option<&str> {
-        match self {
-            Self::Sha1(..) => None,
-            Self::Sha256(sha256) => Some(sha256),
-            Self::Both { sha256, .. } => Some(sha256),
-        }
+        self.sha256.as_deref()
+    }
+
+    pub fn blake3(&self) -> Option<&str> {
+        self.blake3.as_deref()
     }
 }
 
@@ -103,6 +132,19 @@ pub enum HttpError {
 }
 
 impl HttpError {
+    /// Errors caused by the URL itself (e.g. a 404) are user errors, server and network
+    /// errors are infra errors.
+    fn category(&self) -> buck2_data::ErrorCategory {
+        match self {
+            Self::HttpErrorStatus { status, .. }
+                if status.is_client_error() && *status != StatusCode::TOO_MANY_REQUESTS =>
+            {
+                buck2_data::ErrorCategory::User
+            }
+            _ => buck2_data::ErrorCategory::Infra,
+        }
+    }
+
     /// Decide whether to retry this HTTP error. If we got a response but the server errored or
     /// told us to come back later, we retry. If we didn't get a response, then we retry only if we
     /// succeeded in connecting (so as to ensure we don't waste time retrying when the domain
@@ -150,6 +192,39 @@ enum HttpDownloadError {
 
     #[error(transparent)]
     IoError(anyhow::Error),
+
+    #[error("Failed to download from all URLs:{}", format_url_errors(.0))]
+    AllUrlsFailed(Vec<(String, HttpDownloadError)>),
+}
+
+fn format_url_errors(errors: &[(String, HttpDownloadError)]) -> String {
+    let mut s = String::new();
+    for (url, error) in errors {
+        s.push_str(&format!("\n  {}: {:#}", url, error));
+    }
+    s
+}
+
+impl HttpDownloadError {
+    /// A checksum mismatch means the checksum or the URL given by the user is wrong. Downloads
+    /// from several URLs are only user errors if all of them are.
+    fn category(&self) -> buck2_data::ErrorCategory {
+        match self {
+            Self::HttpError(e) => e.category(),
+            Self::InvalidChecksum(..) => buck2_data::ErrorCategory::User,
+            Self::IoError(..) => buck2_data::ErrorCategory::Infra,
+            Self::AllUrlsFailed(errors) => {
+                if errors
+                    .iter()
+                    .all(|(_, e)| e.category() == buck2_data::ErrorCategory::User)
+                {
+                    buck2_data::ErrorCategory::User
+                } else {
+                    buck2_data::ErrorCategory::Infra
+                }
+            }
+        }
+    }
 }
 
 trait AsHttpError {
@@ -168,7 +243,7 @@ impl AsHttpError for HttpDownloadError {
     fn as_http_error(&self) -> Option<&HttpError> {
         match self {
             Self::HttpError(e) => Some(e),
-            Self::InvalidChecksum(..) | Self::IoError(..) => None,
+            Self::InvalidChecksum(..) | Self::IoError(..) | Self::AllUrlsFailed(..) => None,
         }
     }
 }
@@ -215,20 +290,56 @@ async fn http_dispatch(req: RequestBuilder, url: &str) -> Result<Response, HttpE
     Ok(response)
 }
 
+/// Wait until a download from the host of this URL is allowed to start, if the number of
+/// concurrent downloads per host is limited.
+async fn acquire_host_permit(url: &str) -> anyhow::Result<Option<OwnedSemaphorePermit>> {
+    static HOST_SEMAPHORES: Lazy<Mutex<HashMap<String, Arc<Semaphore>>>> =
+        Lazy::new(|| Mutex::new(HashMap::new()));
+
+    let limit = match HTTP_MAX_CONCURRENT_PER_HOST.get_copied()? {
+        Some(limit) => limit,
+        None => return Ok(None),
+    };
+    let host = match reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| host.to_owned()))
+    {
+        Some(host) => host,
+        None => return Ok(None),
+    };
+
+    let semaphore = HOST_SEMAPHORES
+        .lock()
+        .entry(host)
+        .or_insert_with(|| Arc::new(Semaphore::new(limit.max(1))))
+        .dupe();
+    Ok(Some(semaphore.acquire_owned().await?))
+}
+
 pub async fn http_head(client: &Client, url: &str) -> anyhow::Result<Response> {
+    let _permit = acquire_host_permit(url).await?;
     Ok(http_retry(|| async {
         let response = http_dispatch(client.head(url), url).await?;
         Result::<_, HttpHeadError>::Ok(response)
     })
-    .await?)
+    .await
+    .map_err(|e| {
+        let category = match &e {
+            HttpHeadError::HttpError(e) => e.category(),
+        };
+        anyhow::Error::new(e).context(category)
+    })?)
 }
 
+/// Download a file, trying `url` then each of the `mirrors` until one succeeds. Transfer errors
+/// are retried, resuming the download where it stopped if the server supports it.
 pub async fn http_download(
     client: &Client,
     fs: &ProjectRoot,
     digest_config: DigestConfig,
     path: &ProjectRelativePath,
     url: &str,
+    mirrors: &[Arc<str>],
     checksum: &Checksum,
     executable: bool,
 ) -> anyhow::Result<TrackedFileDigest> {
@@ -237,29 +348,97 @@ pub async fn http_download(
         fs_util::create_dir_all(fs.resolve(dir))?;
     }
 
-    Ok(http_retry(|| async {
-        let file = std::fs::OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open(path.to_string())
-            .with_context(|| format!("open({})", abs_path))
-            .map_err(HttpDownloadError::IoError)?;
+    let mut errors = Vec::new();
+    for url in std::iter::once(url).chain(mirrors.iter().map(|m| &**m)) {
+        let _permit = acquire_host_permit(url).await?;
+        match http_download_from(client, fs, digest_config, path, url, checksum, executable).await {
+            Ok(digest) => return Ok(digest),
+            Err(e) => {
+                if !mirrors.is_empty() {
+                    tracing::warn!("Download from `{}` failed: {:#}", url, e);
+                }
+                errors.push((url.to_owned(), e));
+            }
+        }
+    }
 
-        let response = http_dispatch(client.get(url), url).await?;
+    let error = if errors.len() == 1 {
+        errors.pop().unwrap().1
+    } else {
+        HttpDownloadError::AllUrlsFailed(errors)
+    };
+    let category = error.category();
+    Err(anyhow::Error::new(error).context(category))
+}
+
+async fn http_download_from(
+    client: &Client,
+    fs: &ProjectRoot,
+    digest_config: DigestConfig,
+    path: &ProjectRelativePath,
+    url: &str,
+    checksum: &Checksum,
+    executable: bool,
+) -> Result<TrackedFileDigest, HttpDownloadError> {
+    let abs_path = fs.resolve(path);
+
+    // Bytes of the file written to disk by a previous attempt, which can be resumed.
+    let written = AtomicU64::new(0);
+
+    http_retry(|| async {
+        // Only resume from what actually made it to disk.
+        let resume_from = match written.load(Ordering::Relaxed) {
+            0 => 0,
+            written => fs_util::symlink_metadata_if_exists(&abs_path)
+                .map_err(HttpDownloadError::IoError)?
+                .map_or(0, |m| m.len().min(written)),
+        };
+
+        let mut req = client.get(url);
+        if resume_from > 0 {
+            req = req.header(RANGE, format!("bytes={}-", resume_from));
+        }
+        let response = http_dispatch(req, url).await?;
+
+        // Servers which don't support ranges send the whole file.
+        let resumed = resume_from > 0 && response.status() == StatusCode::PARTIAL_CONTENT;
+
+        let mut hasher = ChecksumHasher::new(digest_config.cas_digest_config(), checksum);
+        let file = if resumed {
+            let mut file = std::fs::OpenOptions::new()
+                .read(true)
+                .append(true)
+                .open(&abs_path)
+                .with_context(|| format!("open({})", abs_path))
+                .map_err(HttpDownloadError::IoError)?;
+            file.set_len(resume_from)
+                .with_context(|| format!("truncate({})", abs_path))
+                .map_err(HttpDownloadError::IoError)?;
+            hash_existing(&mut file, &mut hasher)
+                .with_context(|| format!("read({})", abs_path))
+                .map_err(HttpDownloadError::IoError)?;
+            file
+        } else {
+            std::fs::OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(&abs_path)
+                .with_context(|| format!("open({})", abs_path))
+                .map_err(HttpDownloadError::IoError)?
+        };
 
         let stream = response.bytes_stream();
         let buf_writer = std::io::BufWriter::new(file);
 
-        let digest = copy_and_hash(
-            url,
-            &abs_path,
-            stream,
-            buf_writer,
-            digest_config.cas_digest_config(),
-            checksum,
-        )
-        .await?;
+        let res = copy_and_hash(url, &abs_path, stream, buf_writer, hasher).await;
+        if let Err(HttpDownloadError::HttpError(HttpError::HttpTransferError {
+            received, ..
+        })) = &res
+        {
+            written.store(*received, Ordering::Relaxed);
+        }
+        let digest = res?;
 
         if executable {
             fs.set_executable(path)
@@ -271,93 +450,145 @@ pub async fn http_download(
             digest_config.cas_digest_config(),
         ))
     })
-    .await?)
+    .await
 }
 
-/// Copy a stream into a writer while producing its digest and checksumming it.
-async fn copy_and_hash(
-    url: &str,
-    abs_path: &(impl std::fmt::Display + ?Sized),
-    mut stream: impl Stream<Item = Result<Bytes, reqwest::Error>> + Unpin,
-    mut writer: impl Write,
-    digest_config: CasDigestConfig,
-    checksum: &Checksum,
-) -> Result<FileDigest, HttpDownloadError> {
-    let mut digester = FileDigest::digester(digest_config);
+/// Feed the part of a file downloaded by a previous attempt to the hasher.
+fn hash_existing(file: &mut std::fs::File, hasher: &mut ChecksumHasher) -> anyhow::Result<()> {
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            return Ok(());
+        }
+        hasher.update(&buf[..n]);
+    }
+}
 
-    // For each checksum entry we have, we're going to add a validator. We might have to create
-    // a new hasher, or reuse the `FileDigest::digester` if it matches.
+enum Validator {
+    PrimaryDigest,
+    ExtraDigest(Box<dyn DynDigest + Send>),
+    Blake3(Box<blake3::Hasher>),
+}
 
-    enum Validator {
-        PrimaryDigest,
-        ExtraDigest(Box<dyn DynDigest + Send>),
-    }
+/// Produces the digest of a file and checks it against the expected checksums.
+struct ChecksumHasher<'a> {
+    digester: Digester<FileDigestKind>,
+    validators: SmallVec<[(Validator, &'a str, &'static str); 3]>,
+}
 
-    let mut validators = SmallVec::<[_; 2]>::new();
+impl<'a> ChecksumHasher<'a> {
+    fn new(digest_config: CasDigestConfig, checksum: &'a Checksum) -> Self {
+        let digester = FileDigest::digester(digest_config);
 
-    if let Some(sha1) = checksum.sha1() {
-        let validator = if digester.algorithm() == DigestAlgorithmKind::Sha1 {
-            Validator::PrimaryDigest
-        } else {
-            Validator::ExtraDigest(Box::new(Sha1::new()) as _)
-        };
+        // For each checksum entry we have, we're going to add a validator. We might have to create
+        // a new hasher, or reuse the `FileDigest::digester` if it matches.
+        let mut validators = SmallVec::new();
+
+        if let Some(sha1) = checksum.sha1() {
+            let validator = if digester.algorithm() == DigestAlgorithmKind::Sha1 {
+                Validator::PrimaryDigest
+            } else {
+                Validator::ExtraDigest(Box::new(Sha1::new()) as _)
+            };
+
+            validators.push((validator, sha1, "sha1"));
+        }
+
+        if let Some(sha256) = checksum.sha256() {
+            let validator = if digester.algorithm() == DigestAlgorithmKind::Sha256 {
+                Validator::PrimaryDigest
+            } else {
+                Validator::ExtraDigest(Box::new(Sha256::new()) as _)
+            };
 
-        validators.push((validator, sha1, "sha1"));
+            validators.push((validator, sha256, "sha256"));
+        }
+
+        if let Some(blake3) = checksum.blake3() {
+            let validator = if digester.algorithm() == DigestAlgorithmKind::Blake3 {
+                Validator::PrimaryDigest
+            } else {
+                Validator::Blake3(Box::new(blake3::Hasher::new()))
+            };
+
+            validators.push((validator, blake3, "blake3"));
+        }
+
+        Self {
+            digester,
+            validators,
+        }
     }
 
-    if let Some(sha256) = checksum.sha256() {
-        let validator = if digester.algorithm() == DigestAlgorithmKind::Sha256 {
-            Validator::PrimaryDigest
-        } else {
-            Validator::ExtraDigest(Box::new(Sha256::new()) as _)
-        };
+    fn update(&mut self, data: &[u8]) {
+        self.digester.update(data);
+        for (validator, _expected, _kind) in self.validators.iter_mut() {
+            match validator {
+                Validator::PrimaryDigest => {}
+                Validator::ExtraDigest(hasher) => hasher.update(data),
+                Validator::Blake3(hasher) => {
+                    hasher.update(data);
+                }
+            }
+        }
+    }
 
-        validators.push((validator, sha256, "sha256"));
+    fn bytes_read(&self) -> u64 {
+        self.digester.bytes_read()
     }
 
+    fn finalize(self, url: &str) -> Result<FileDigest, HttpDownloadError> {
+        let digest = self.digester.finalize();
+
+        for (validator, expected, kind) in self.validators {
+            let obtained = match validator {
+                Validator::PrimaryDigest => digest.raw_digest().to_string(),
+                Validator::ExtraDigest(hasher) => hex::encode(hasher.finalize()),
+                Validator::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+            };
+
+            if expected != obtained {
+                return Err(HttpDownloadError::InvalidChecksum(
+                    kind,
+                    expected.to_owned(),
+                    obtained,
+                    url.to_owned(),
+                ));
+            }
+        }
+
+        Ok(digest)
+    }
+}
+
+/// Copy a stream into a writer while producing its digest and checksumming it.
+async fn copy_and_hash(
+    url: &str,
+    abs_path: &(impl std::fmt::Display + ?Sized),
+    mut stream: impl Stream<Item = Result<Bytes, reqwest::Error>> + Unpin,
+    mut writer: impl Write,
+    mut hasher: ChecksumHasher<'_>,
+) -> Result<FileDigest, HttpDownloadError> {
     while let Some(chunk) = stream.next().await {
         let chunk = chunk.map_err(|source| HttpError::HttpTransferError {
-            received: digester.bytes_read(),
+            received: hasher.bytes_read(),
             url: url.to_owned(),
             source,
         })?;
         writer
-            .write(&chunk)
+            .write_all(&chunk)
             .with_context(|| format!("write({})", abs_path))
             .map_err(HttpDownloadError::IoError)?;
 
-        digester.update(&chunk);
-        for (validator, _expected, _kind) in validators.iter_mut() {
-            if let Validator::ExtraDigest(hasher) = validator {
-                hasher.update(&chunk);
-            }
-        }
+        hasher.update(&chunk);
     }
     writer
         .flush()
         .with_context(|| format!("flush({})", abs_path))
         .map_err(HttpDownloadError::IoError)?;
 
-    let digest = digester.finalize();
-
-    // Validate
-    for (validator, expected, kind) in validators {
-        let obtained = match validator {
-            Validator::PrimaryDigest => digest.raw_digest().to_string(),
-            Validator::ExtraDigest(hasher) => hex::encode(hasher.finalize()),
-        };
-
-        if expected != obtained {
-            return Err(HttpDownloadError::InvalidChecksum(
-                kind,
-                expected.to_owned(),
-                obtained,
-                url.to_owned(),
-            ));
-        }
-    }
-
-    Ok(digest)
+    hasher.finalize(url)
 }
 
 async fn http_retry<Exec, F, T, E>(exec: Exec) -> Result<T, E>
@@ -413,24 +644,31 @@ mod test {
             "test",
             stream::iter(vec![Ok(Bytes::from("foo")), Ok(Bytes::from("bar"))]),
             &mut out,
-            digest_config,
-            checksum,
+            ChecksumHasher::new(digest_config, checksum),
         )
         .await?;
 
         Ok((digest, out))
     }
 
+    fn checksum(sha1: Option<&str>, sha256: Option<&str>, blake3: Option<&str>) -> Checksum {
+        Checksum::new(
+            sha1.map(Arc::from),
+            sha256.map(Arc::from),
+            blake3.map(Arc::from),
+        )
+        .unwrap()
+    }
+
     #[tokio::test]
     async fn test_copy_and_hash_ok() -> anyhow::Result<()> {
         let (digest, bytes) = do_test(
             testing::blake3(),
-            &Checksum::Both {
-                sha1: Arc::from("8843d7f92416211de9ebb963ff4ce28125932878"),
-                sha256: Arc::from(
-                    "c3ab8ff13720e8ad9047dd39466b3c8974e592c2fa383d4a3960714caef0c4f2",
-                ),
-            },
+            &checksum(
+                Some("8843d7f92416211de9ebb963ff4ce28125932878"),
+                Some("c3ab8ff13720e8ad9047dd39466b3c8974e592c2fa383d4a3960714caef0c4f2"),
+                Some("aa51dcd43d5c6c5203ee16906fd6b35db298b9b2e1de3fce81811d4806b76b7d"),
+            ),
         )
         .await?;
 
@@ -444,15 +682,35 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_copy_and_hash_blake3_secondary() -> anyhow::Result<()> {
+        do_test(
+            testing::sha1(),
+            &checksum(
+                None,
+                None,
+                Some("aa51dcd43d5c6c5203ee16906fd6b35db298b9b2e1de3fce81811d4806b76b7d"),
+            ),
+        )
+        .await?;
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_copy_and_hash_invalid_primary_hash() -> anyhow::Result<()> {
         assert_matches!(
-            do_test(testing::sha1(), &Checksum::Sha1(Arc::from("oops")),).await,
+            do_test(testing::sha1(), &checksum(Some("oops"), None, None)).await,
             Err(HttpDownloadError::InvalidChecksum(..))
         );
 
         assert_matches!(
-            do_test(testing::sha256(), &Checksum::Sha256(Arc::from("oops")),).await,
+            do_test(testing::sha256(), &checksum(None, Some("oops"), None)).await,
+            Err(HttpDownloadError::InvalidChecksum(..))
+        );
+
+        assert_matches!(
+            do_test(testing::blake3(), &checksum(None, None, Some("oops"))).await,
             Err(HttpDownloadError::InvalidChecksum(..))
         );
 
@@ -462,15 +720,58 @@ mod test {
     #[tokio::test]
     async fn test_copy_and_hash_invalid_secondary_hash() -> anyhow::Result<()> {
         assert_matches!(
-            do_test(testing::blake3(), &Checksum::Sha1(Arc::from("oops")),).await,
+            do_test(testing::blake3(), &checksum(Some("oops"), None, None)).await,
             Err(HttpDownloadError::InvalidChecksum(..))
         );
 
         assert_matches!(
-            do_test(testing::blake3(), &Checksum::Sha256(Arc::from("oops")),).await,
+            do_test(testing::blake3(), &checksum(None, Some("oops"), None)).await,
+            Err(HttpDownloadError::InvalidChecksum(..))
+        );
+
+        assert_matches!(
+            do_test(testing::sha1(), &checksum(None, None, Some("oops"))).await,
             Err(HttpDownloadError::InvalidChecksum(..))
         );
 
         Ok(())
     }
+
+    #[test]
+    fn test_checksum_required() {
+        assert!(Checksum::new(None, None, None).is_none());
+    }
+
+    #[test]
+    fn test_error_category() {
+        assert_eq!(
+            HttpDownloadError::InvalidChecksum(
+                "sha1",
+                "a".to_owned(),
+                "b".to_owned(),
+                "url".to_owned()
+            )
+            .category(),
+            buck2_data::ErrorCategory::User
+        );
+        assert_eq!(
+            HttpDownloadError::AllUrlsFailed(vec![
+                (
+                    "a".to_owned(),
+                    HttpDownloadError::IoError(anyhow::anyhow!("oops"))
+                ),
+                (
+                    "b".to_owned(),
+                    HttpDownloadError::InvalidChecksum(
+                        "sha1",
+                        "a".to_owned(),
+                        "b".to_owned(),
+                        "url".to_owned()
+                    )
+                ),
+            ])
+            .category(),
+            buck2_data::ErrorCategory::Infra
+        );
+    }
 }
@@ -520,6 +520,9 @@ pub struct HttpDownloadInfo {
     /// URL to download the file from.
     pub url: Arc<str>,
 
+    /// URLs to try, in order, if downloading from `url` fails.
+    pub mirrors: Arc<[Arc<str>]>,
+
     /// Size, whether the file is executable. Also contains a digest, which is a bit of a shame
     /// since it's duplicative of checksum.
     pub metadata: FileMetadata,
@@ -633,6 +636,22 @@ impl MaterializationMethod {
 /// `DeferredMaterializerEntry` lives in a crate that depends on this one.
 pub trait DeferredMaterializerEntry: Send + Sync + std::fmt::Display {}
 
+/// An entry of the deferred materializer state which doesn't match what is on disk.
+#[derive(Debug)]
+pub struct MaterializerInconsistency {
+    pub path: ProjectRelativePathBuf,
+    pub error: anyhow::Error,
+    /// The artifact was declared by the running daemon. Dropping it from the state is not enough
+    /// to have it materialized again, since DICE still assumes it exists.
+    pub active: bool,
+    /// The entry was dropped from the state and whatever was on disk at its path was deleted,
+    /// so that it is materialized again the next time it is declared.
+    pub repaired: bool,
+    /// The artifact was in use by the running daemon and was materialized again as part of the
+    /// repair.
+    pub rematerialized: bool,
+}
+
 /// Obtain notifications for entries as they are materialized, and request eager materialization of
 /// those paths.
 #[async_trait]
@@ -662,8 +681,22 @@ pub trait DeferredMaterializerExtensions: Send + Sync {
     /// all discrepancies.
     fn fsck(&self) -> anyhow::Result<BoxStream<'static, (ProjectRelativePathBuf, anyhow::Error)>>;
 
+    /// Compare the materialized entries with what is on disk (hashing files and directories), and
+    /// report the ones that don't match. With `repair`, these entries are dropped, and the ones in
+    /// use by the running daemon are materialized again when possible.
+    fn verify(&self, repair: bool)
+    -> anyhow::Result<BoxStream<'static, MaterializerInconsistency>>;
+
     async fn refresh_ttls(&self, min_ttl: i64) -> anyhow::Result<()>;
 
+    /// The artifacts the materializer has at these paths, as declared by the builds which
+    /// produced them, or `None` for paths which are not artifacts. Only a fingerprint of
+    /// materialized directories is kept in memory, so their files are read from disk.
+    async fn get_artifact_entries(
+        &self,
+        paths: Vec<ProjectRelativePathBuf>,
+    ) -> anyhow::Result<Vec<Option<ActionDirectoryEntry<ActionSharedDirectory>>>>;
+
     async fn get_ttl_refresh_log(&self) -> anyhow::Result<String>;
 
     async fn clean_stale_artifacts(
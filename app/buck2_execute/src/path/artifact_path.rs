@@ -31,6 +31,9 @@ pub struct ArtifactPath<'a> {
     /// The number of components at the prefix of that path that are internal details to the rule,
     /// not returned by `.short_path`. Omitted from Eq and Hash comparisons.
     pub hidden_components_count: usize,
+    /// A logical prefix prepended to `.short_path` once hidden components are stripped.
+    /// Omitted from Eq and Hash comparisons.
+    pub short_path_prefix: Option<&'a ForwardRelativePath>,
 }
 
 impl<'a> ArtifactPath<'a> {
@@ -70,7 +73,21 @@ impl<'a> ArtifactPath<'a> {
             None => ForwardRelativePath::empty(),
         };
 
-        f(path)
+        match self.short_path_prefix {
+            Some(prefix) => f(&prefix.join(path)),
+            None => f(path),
+        }
+    }
+
+    /// The number of components of the path `.short_path` is computed from,
+    /// before hidden components are stripped and the short path prefix is added.
+    pub fn components_count(&self) -> usize {
+        let base_short_path = match self.base_path.as_ref() {
+            Either::Left(buck_out) => buck_out.path(),
+            Either::Right(buck) => buck.path().as_ref(),
+        };
+
+        base_short_path.iter().count() + self.projected_path.map_or(0, |p| p.iter().count())
     }
 
     pub fn with_full_path<F, T>(&self, f: F) -> T
@@ -100,6 +117,7 @@ impl<'a> ArtifactPath<'a> {
             base_path,
             projected_path,
             hidden_components_count: _,
+            short_path_prefix: _,
         } = self;
 
         let base_path = match base_path {
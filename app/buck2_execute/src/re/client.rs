@@ -71,6 +71,9 @@ use crate::execute::manager::CommandExecutionManager;
 use crate::materialize::materializer::Materializer;
 use crate::re::action_identity::ReActionIdentity;
 use crate::re::metadata::RemoteExecutionMetadataExt;
+use crate::re::scheduler::RpcClass;
+use crate::re::scheduler::RpcClassStats;
+use crate::re::scheduler::RpcScheduler;
 use crate::re::uploader::UploadStats;
 use crate::re::uploader::Uploader;
 
@@ -102,6 +105,16 @@ pub struct RemoteExecutionClientStats {
     pub materializes: RemoteExecutionClientOpStats,
     pub write_action_results: RemoteExecutionClientOpStats,
     pub get_digest_expirations: RemoteExecutionClientOpStats,
+    pub execution_rpcs: RpcClassStats,
+    pub cache_query_rpcs: RpcClassStats,
+    pub prefetch_rpcs: RpcClassStats,
+}
+
+/// Whether the expirations returned by `get_digest_expirations` are the TTLs the backend reports.
+/// The open source client can't get TTLs through the Remote Execution API, so it only knows
+/// which digests exist, and returns the same short TTL for all of them.
+pub fn reports_digest_ttls() -> bool {
+    !buck2_core::is_open_source()
 }
 
 #[derive(Clone, Dupe, Allocative)]
@@ -158,6 +171,7 @@ struct RemoteExecutionClientData {
     materializes: OpStats,
     write_action_results: OpStats,
     get_digest_expirations: OpStats,
+    scheduler: RpcScheduler,
 }
 
 impl RemoteExecutionClient {
@@ -168,6 +182,7 @@ impl RemoteExecutionClient {
         logs_dir_path: Option<&str>,
         buck_out_path: &str,
     ) -> anyhow::Result<Self> {
+        let scheduler = RpcScheduler::new(static_metadata.rpc_limits());
         let client = RemoteExecutionClientImpl::new(
             fb,
             skip_remote_cache,
@@ -193,6 +208,7 @@ impl RemoteExecutionClient {
                 materializes: OpStats::default(),
                 write_action_results: OpStats::default(),
                 get_digest_expirations: OpStats::default(),
+                scheduler,
             }),
         })
     }
@@ -249,6 +265,7 @@ impl RemoteExecutionClient {
         action_digest: ActionDigest,
         use_case: RemoteExecutorUseCase,
     ) -> anyhow::Result<Option<ActionResultResponse>> {
+        let _permit = self.data.scheduler.acquire(RpcClass::CacheQuery).await;
         self.data
             .action_cache
             .op(self.data.client.action_cache(action_digest, use_case))
@@ -264,6 +281,7 @@ impl RemoteExecutionClient {
         use_case: RemoteExecutorUseCase,
         digest_config: DigestConfig,
     ) -> anyhow::Result<UploadStats> {
+        let _permit = self.data.scheduler.acquire(RpcClass::Execution).await;
         self.data
             .uploads
             .op(self
@@ -288,6 +306,7 @@ impl RemoteExecutionClient {
         inlined_blobs_with_digest: Vec<InlinedBlobWithDigest>,
         use_case: RemoteExecutorUseCase,
     ) -> anyhow::Result<()> {
+        let _permit = self.data.scheduler.acquire(RpcClass::Execution).await;
         self.data
             .uploads
             .op(self
@@ -298,6 +317,31 @@ impl RemoteExecutionClient {
                     directories,
                     inlined_blobs_with_digest,
                     use_case,
+                    true,
+                )
+                .map_err(|e| self.decorate_error(e)))
+            .await
+    }
+
+    /// Upload files even if the CAS already has them. On backends which refresh the TTL of a
+    /// blob when it is uploaded again, this extends it.
+    pub async fn reupload_files(
+        &self,
+        files_with_digest: Vec<NamedDigest>,
+        use_case: RemoteExecutorUseCase,
+    ) -> anyhow::Result<()> {
+        let _permit = self.data.scheduler.acquire(RpcClass::Execution).await;
+        self.data
+            .uploads
+            .op(self
+                .data
+                .client
+                .upload_files_and_directories(
+                    files_with_digest,
+                    Vec::new(),
+                    Vec::new(),
+                    use_case,
+                    false,
                 )
                 .map_err(|e| self.decorate_error(e)))
             .await
@@ -328,6 +372,7 @@ impl RemoteExecutionClient {
                     skip_cache_read,
                     skip_cache_write,
                     re_max_queue_time,
+                    &self.data.scheduler,
                 )
                 .map_err(|e| self.decorate_error(e)))
             .await
@@ -338,6 +383,7 @@ impl RemoteExecutionClient {
         files: Vec<NamedDigestWithPermissions>,
         use_case: RemoteExecutorUseCase,
     ) -> anyhow::Result<()> {
+        let _permit = self.data.scheduler.acquire(RpcClass::Prefetch).await;
         self.data
             .materializes
             .op(self.data.client.materialize_files(files, use_case))
@@ -349,6 +395,7 @@ impl RemoteExecutionClient {
         digests: Vec<TDigest>,
         use_case: RemoteExecutorUseCase,
     ) -> anyhow::Result<Vec<T>> {
+        let _permit = self.data.scheduler.acquire(RpcClass::CacheQuery).await;
         self.data
             .downloads
             .op(self
@@ -364,6 +411,7 @@ impl RemoteExecutionClient {
         digest: &TDigest,
         use_case: RemoteExecutorUseCase,
     ) -> anyhow::Result<Vec<u8>> {
+        let _permit = self.data.scheduler.acquire(RpcClass::CacheQuery).await;
         self.data
             .downloads
             .op(self
@@ -379,6 +427,7 @@ impl RemoteExecutionClient {
         blob: Vec<u8>,
         use_case: RemoteExecutorUseCase,
     ) -> anyhow::Result<TDigest> {
+        let _permit = self.data.scheduler.acquire(RpcClass::Execution).await;
         self.data
             .uploads
             .op(self
@@ -394,6 +443,7 @@ impl RemoteExecutionClient {
         digests: Vec<TDigest>,
         use_case: RemoteExecutorUseCase,
     ) -> anyhow::Result<Vec<(TDigest, DateTime<Utc>)>> {
+        let _permit = self.data.scheduler.acquire(RpcClass::CacheQuery).await;
         self.data
             .get_digest_expirations
             .op(self
@@ -410,6 +460,7 @@ impl RemoteExecutionClient {
         result: TActionResult2,
         use_case: RemoteExecutorUseCase,
     ) -> anyhow::Result<()> {
+        let _permit = self.data.scheduler.acquire(RpcClass::Execution).await;
         self.data
             .write_action_results
             .op(self
@@ -460,6 +511,9 @@ impl RemoteExecutionClient {
             get_digest_expirations: RemoteExecutionClientOpStats::from(
                 &self.data.get_digest_expirations,
             ),
+            execution_rpcs: self.data.scheduler.stats(RpcClass::Execution),
+            cache_query_rpcs: self.data.scheduler.stats(RpcClass::CacheQuery),
+            prefetch_rpcs: self.data.scheduler.stats(RpcClass::Prefetch),
         })
     }
 }
@@ -707,6 +761,7 @@ impl RemoteExecutionClientImpl {
         directories: Vec<remote_execution::Path>,
         inlined_blobs_with_digest: Vec<InlinedBlobWithDigest>,
         use_case: RemoteExecutorUseCase,
+        upload_only_missing: bool,
     ) -> anyhow::Result<()> {
         self.client()
             .get_cas_client()
@@ -716,7 +771,7 @@ impl RemoteExecutionClientImpl {
                     files_with_digest: Some(files_with_digest),
                     inlined_blobs_with_digest: Some(inlined_blobs_with_digest),
                     directories: Some(directories),
-                    upload_only_missing: true,
+                    upload_only_missing,
                     ..Default::default()
                 },
             )
@@ -732,6 +787,7 @@ impl RemoteExecutionClientImpl {
         manager: &mut CommandExecutionManager,
         re_max_queue_time: Option<Duration>,
         platform: &remote_execution::Platform,
+        scheduler: &RpcScheduler,
     ) -> anyhow::Result<ExecuteResponseOrCancelled> {
         use buck2_data::re_stage;
         use buck2_data::ReExecute;
@@ -852,6 +908,9 @@ impl RemoteExecutionClientImpl {
 
         // Obtain a stream of events from RE. If this fails then that is case #1 above so we
         // bail.
+        // Only starting the execution counts against the RPC limits, not waiting for it to
+        // finish, which can take minutes.
+        let permit = scheduler.acquire(RpcClass::Execution).await;
         let mut receiver = self
             .client()
             .get_execution_client()
@@ -861,6 +920,7 @@ impl RemoteExecutionClientImpl {
             .await
             .map_err(anyhow::Error::from)
             .context("Failed to start remote execution")?;
+        drop(permit);
 
         // Now we wait until the ExecuteResponse shows up, and produce events accordingly. If
         // this doesn't give us an ExecuteResponse then this is case #1 again so we also fail.
@@ -909,6 +969,7 @@ impl RemoteExecutionClientImpl {
         skip_cache_read: bool,
         skip_cache_write: bool,
         re_max_queue_time: Option<Duration>,
+        scheduler: &RpcScheduler,
     ) -> anyhow::Result<ExecuteResponseOrCancelled> {
         let metadata = RemoteExecutionMetadata {
             action_history_info: Some(ActionHistoryInfo {
@@ -947,6 +1008,7 @@ impl RemoteExecutionClientImpl {
             manager,
             re_max_queue_time,
             platform,
+            scheduler,
         )
         .await
         .with_context(|| format!("RE: execution with digest {}", &action_digest))
@@ -239,6 +239,12 @@ impl ReConnectionManager {
             .and_then(|lazy_client| lazy_client.with_client(|client| client.get_network_stats()))
             .transpose()
     }
+
+    /// The ID of the current RE session, without connecting to RE if there is no session.
+    pub fn get_existing_session_id(&self) -> Option<String> {
+        let conn = self.data.read().unwrap().upgrade()?;
+        conn.with_client(|client| client.get_session_id().to_owned())
+    }
 }
 
 #[async_trait]
@@ -385,6 +391,18 @@ impl ManagedRemoteExecutionClient {
             .await
     }
 
+    pub async fn reupload_files(
+        &self,
+        files_with_digest: Vec<NamedDigest>,
+        use_case: RemoteExecutorUseCase,
+    ) -> anyhow::Result<()> {
+        self.lock()?
+            .get()
+            .await?
+            .reupload_files(files_with_digest, use_case)
+            .await
+    }
+
     pub async fn materialize_files(
         &self,
         files: Vec<NamedDigestWithPermissions>,
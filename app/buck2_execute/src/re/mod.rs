@@ -13,5 +13,6 @@ pub mod manager;
 pub mod metadata;
 pub mod re_get_session_id;
 pub mod remote_action_result;
+pub mod scheduler;
 pub mod streams;
 pub mod uploader;
@@ -58,7 +58,10 @@ impl RemoteActionResult for ExecuteResponse {
     }
 
     fn execution_kind(&self, digest: ActionDigest) -> CommandExecutionKind {
-        CommandExecutionKind::Remote { digest }
+        CommandExecutionKind::Remote {
+            digest,
+            worker: worker(&self.action_result.execution_metadata),
+        }
     }
 
     fn timing(&self) -> CommandExecutionMetadata {
@@ -89,7 +92,10 @@ impl RemoteActionResult for ActionResultResponse {
     }
 
     fn execution_kind(&self, digest: ActionDigest) -> CommandExecutionKind {
-        CommandExecutionKind::ActionCache { digest }
+        CommandExecutionKind::ActionCache {
+            digest,
+            worker: worker(&self.action_result.execution_metadata),
+        }
     }
 
     fn timing(&self) -> CommandExecutionMetadata {
@@ -112,6 +118,14 @@ impl RemoteActionResult for ActionResultResponse {
     }
 }
 
+fn worker(meta: &TExecutedActionMetadata) -> Option<String> {
+    if meta.worker.is_empty() {
+        None
+    } else {
+        Some(meta.worker.clone())
+    }
+}
+
 fn timing_from_re_metadata(meta: &TExecutedActionMetadata) -> CommandExecutionMetadata {
     let execution_time = meta
         .execution_completed_timestamp
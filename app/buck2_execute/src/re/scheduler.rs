@@ -0,0 +1,342 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Scheduling of the RPCs to RE, so that cache queries or materializations can't flood the network
+//! and starve action execution. See [`RemoteExecutionRpcLimits`] for the configuration.
+
+use std::collections::VecDeque;
+
+use allocative::Allocative;
+use buck2_re_configuration::RemoteExecutionRpcLimits;
+use dupe::Dupe;
+use parking_lot::Mutex;
+use tokio::sync::oneshot;
+
+/// The class of an RPC to RE. Classes are listed in decreasing priority.
+#[derive(Clone, Copy, Dupe, Debug, PartialEq, Eq, Allocative)]
+pub enum RpcClass {
+    Execution,
+    CacheQuery,
+    Prefetch,
+}
+
+impl RpcClass {
+    const ALL: [RpcClass; 3] = [
+        RpcClass::Execution,
+        RpcClass::CacheQuery,
+        RpcClass::Prefetch,
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RpcClassStats {
+    /// RPCs waiting for a slot.
+    pub queued: u32,
+    /// RPCs holding a slot.
+    pub running: u32,
+}
+
+/// How many times the waiting RPCs of a class can be passed over for RPCs of higher priority
+/// classes before the class gets the next slot, so that no class can be starved.
+const MAX_PASSED_OVER: usize = 4;
+
+struct Waiter {
+    id: u64,
+    tx: oneshot::Sender<()>,
+}
+
+#[derive(Default)]
+struct SchedulerState {
+    running: [usize; 3],
+    total_running: usize,
+    /// Waiting RPCs of each class, in order of arrival.
+    queued: [VecDeque<Waiter>; 3],
+    /// How many RPCs of higher priority classes were started while each class had RPCs waiting
+    /// which could otherwise have run, since one of them last started.
+    passed_over: [usize; 3],
+    next_id: u64,
+}
+
+#[derive(Allocative)]
+pub struct RpcScheduler {
+    total: usize,
+    limits: [usize; 3],
+    #[allocative(skip)]
+    state: Mutex<SchedulerState>,
+}
+
+impl RpcScheduler {
+    pub fn new(limits: &RemoteExecutionRpcLimits) -> Self {
+        let limit = |l: Option<usize>| l.unwrap_or(usize::MAX);
+        Self {
+            total: limit(limits.total),
+            limits: [
+                limit(limits.execution),
+                limit(limits.cache_query),
+                limit(limits.prefetch),
+            ],
+            state: Mutex::new(SchedulerState::default()),
+        }
+    }
+
+    fn has_capacity(&self, state: &SchedulerState, class: RpcClass) -> bool {
+        state.total_running < self.total
+            && state.running[class.index()] < self.limits[class.index()]
+    }
+
+    fn start(state: &mut SchedulerState, class: RpcClass) {
+        state.running[class.index()] += 1;
+        state.total_running += 1;
+    }
+
+    fn can_start_queued(&self, state: &SchedulerState, class: RpcClass) -> bool {
+        !state.queued[class.index()].is_empty() && self.has_capacity(state, class)
+    }
+
+    /// The class of the next waiting RPC to start: by order of priority, except for classes
+    /// which were passed over too many times.
+    fn next_class(&self, state: &SchedulerState) -> Option<RpcClass> {
+        let mut can_start = RpcClass::ALL
+            .into_iter()
+            .filter(|class| self.can_start_queued(state, *class));
+        let first = can_start.next()?;
+        Some(
+            can_start
+                .find(|class| state.passed_over[class.index()] >= MAX_PASSED_OVER)
+                .unwrap_or(first),
+        )
+    }
+
+    /// Start the waiting RPCs which can run.
+    fn dispatch(&self, state: &mut SchedulerState) {
+        while let Some(class) = self.next_class(state) {
+            for lower in &RpcClass::ALL[class.index() + 1..] {
+                if self.can_start_queued(state, *lower) {
+                    state.passed_over[lower.index()] += 1;
+                }
+            }
+            state.passed_over[class.index()] = 0;
+            let waiter = state.queued[class.index()]
+                .pop_front()
+                .expect("Only classes with waiting RPCs are started");
+            // If the waiter is gone, its `QueuedRpc` will release the slot.
+            Self::start(state, class);
+            let _ignored = waiter.tx.send(());
+        }
+    }
+
+    fn release(&self, class: RpcClass) {
+        let mut state = self.state.lock();
+        state.running[class.index()] -= 1;
+        state.total_running -= 1;
+        self.dispatch(&mut state);
+    }
+
+    /// Wait for a slot to run an RPC of the given class, which is held until the permit is
+    /// dropped.
+    pub async fn acquire(&self, class: RpcClass) -> RpcPermit<'_> {
+        let (id, rx) = {
+            let mut state = self.state.lock();
+            // RPCs of the class which are already waiting go first. Those of other classes are
+            // only waiting if they couldn't run.
+            if state.queued[class.index()].is_empty() && self.has_capacity(&state, class) {
+                Self::start(&mut state, class);
+                return RpcPermit {
+                    scheduler: self,
+                    class,
+                };
+            }
+            let (tx, rx) = oneshot::channel();
+            let id = state.next_id;
+            state.next_id += 1;
+            state.queued[class.index()].push_back(Waiter { id, tx });
+            (id, rx)
+        };
+
+        let mut queued = QueuedRpc {
+            scheduler: self,
+            class,
+            id,
+            started: false,
+        };
+        // The sender is only dropped after sending, or by `QueuedRpc`.
+        let _ignored = rx.await;
+        queued.started = true;
+        RpcPermit {
+            scheduler: self,
+            class,
+        }
+    }
+
+    pub fn stats(&self, class: RpcClass) -> RpcClassStats {
+        let state = self.state.lock();
+        RpcClassStats {
+            queued: state.queued[class.index()].len() as u32,
+            running: state.running[class.index()] as u32,
+        }
+    }
+}
+
+/// An RPC waiting for a slot, which gives up its place (or its slot, if it was just given one)
+/// if it is cancelled.
+struct QueuedRpc<'a> {
+    scheduler: &'a RpcScheduler,
+    class: RpcClass,
+    id: u64,
+    started: bool,
+}
+
+impl Drop for QueuedRpc<'_> {
+    fn drop(&mut self) {
+        if self.started {
+            return;
+        }
+        let mut state = self.scheduler.state.lock();
+        let queue = &mut state.queued[self.class.index()];
+        match queue.iter().position(|w| w.id == self.id) {
+            Some(position) => {
+                queue.remove(position);
+            }
+            None => {
+                drop(state);
+                self.scheduler.release(self.class);
+            }
+        }
+    }
+}
+
+/// A slot to run an RPC, released on drop.
+pub struct RpcPermit<'a> {
+    scheduler: &'a RpcScheduler,
+    class: RpcClass,
+}
+
+impl Drop for RpcPermit<'_> {
+    fn drop(&mut self) {
+        self.scheduler.release(self.class);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::task::Poll;
+
+    use assert_matches::assert_matches;
+    use buck2_re_configuration::RemoteExecutionRpcLimits;
+
+    use crate::re::scheduler::RpcClass;
+    use crate::re::scheduler::RpcClassStats;
+    use crate::re::scheduler::RpcScheduler;
+    use crate::re::scheduler::MAX_PASSED_OVER;
+
+    #[tokio::test]
+    async fn test_priority() {
+        let scheduler = RpcScheduler::new(&RemoteExecutionRpcLimits {
+            total: Some(1),
+            ..Default::default()
+        });
+        let running = scheduler.acquire(RpcClass::Prefetch).await;
+
+        let prefetch = scheduler.acquire(RpcClass::Prefetch);
+        let cache_query = scheduler.acquire(RpcClass::CacheQuery);
+        let execution = scheduler.acquire(RpcClass::Execution);
+        futures::pin_mut!(prefetch, cache_query, execution);
+        assert_matches!(futures::poll!(prefetch.as_mut()), Poll::Pending);
+        assert_matches!(futures::poll!(cache_query.as_mut()), Poll::Pending);
+        assert_matches!(futures::poll!(execution.as_mut()), Poll::Pending);
+        assert_eq!(
+            scheduler.stats(RpcClass::Prefetch),
+            RpcClassStats {
+                queued: 1,
+                running: 1
+            }
+        );
+
+        // Slots go to the highest priority first, whatever the order of arrival.
+        drop(running);
+        let permit = assert_matches!(futures::poll!(execution.as_mut()), Poll::Ready(p) => p);
+        assert_matches!(futures::poll!(cache_query.as_mut()), Poll::Pending);
+        drop(permit);
+        let permit = assert_matches!(futures::poll!(cache_query.as_mut()), Poll::Ready(p) => p);
+        assert_matches!(futures::poll!(prefetch.as_mut()), Poll::Pending);
+        drop(permit);
+        assert_matches!(futures::poll!(prefetch.as_mut()), Poll::Ready(..));
+    }
+
+    #[tokio::test]
+    async fn test_class_limit() {
+        let scheduler = RpcScheduler::new(&RemoteExecutionRpcLimits {
+            cache_query: Some(1),
+            ..Default::default()
+        });
+        let _query = scheduler.acquire(RpcClass::CacheQuery).await;
+        let query = scheduler.acquire(RpcClass::CacheQuery);
+        futures::pin_mut!(query);
+        assert_matches!(futures::poll!(query.as_mut()), Poll::Pending);
+        // Other classes are not limited by it.
+        let _execution = scheduler.acquire(RpcClass::Execution).await;
+    }
+
+    #[tokio::test]
+    async fn test_cancelled() {
+        let scheduler = RpcScheduler::new(&RemoteExecutionRpcLimits {
+            total: Some(1),
+            ..Default::default()
+        });
+        let running = scheduler.acquire(RpcClass::Execution).await;
+        {
+            let cancelled = scheduler.acquire(RpcClass::Execution);
+            futures::pin_mut!(cancelled);
+            assert_matches!(futures::poll!(cancelled.as_mut()), Poll::Pending);
+            // Given the slot, but cancelled before using it.
+            drop(running);
+        }
+        assert_eq!(
+            scheduler.stats(RpcClass::Execution),
+            RpcClassStats::default()
+        );
+        let _running = scheduler.acquire(RpcClass::Execution).await;
+    }
+
+    #[tokio::test]
+    async fn test_no_starvation() {
+        let scheduler = RpcScheduler::new(&RemoteExecutionRpcLimits {
+            total: Some(1),
+            ..Default::default()
+        });
+        let mut running = scheduler.acquire(RpcClass::Execution).await;
+        let prefetch = scheduler.acquire(RpcClass::Prefetch);
+        futures::pin_mut!(prefetch);
+        assert_matches!(futures::poll!(prefetch.as_mut()), Poll::Pending);
+
+        // Executions keep coming, and go first for a while.
+        for _ in 0..MAX_PASSED_OVER {
+            let execution = scheduler.acquire(RpcClass::Execution);
+            futures::pin_mut!(execution);
+            assert_matches!(futures::poll!(execution.as_mut()), Poll::Pending);
+            drop(running);
+            running = assert_matches!(futures::poll!(execution.as_mut()), Poll::Ready(p) => p);
+            assert_matches!(futures::poll!(prefetch.as_mut()), Poll::Pending);
+        }
+
+        // But the prefetch gets the next slot, even though an execution is waiting.
+        let execution = scheduler.acquire(RpcClass::Execution);
+        futures::pin_mut!(execution);
+        assert_matches!(futures::poll!(execution.as_mut()), Poll::Pending);
+        drop(running);
+        let permit = assert_matches!(futures::poll!(prefetch.as_mut()), Poll::Ready(p) => p);
+        assert_matches!(futures::poll!(execution.as_mut()), Poll::Pending);
+        drop(permit);
+        assert_matches!(futures::poll!(execution.as_mut()), Poll::Ready(..));
+    }
+}
@@ -34,6 +34,7 @@ use buck2_execute::execute::prepared::PreparedCommand;
 use buck2_execute::execute::prepared::PreparedCommandExecutor;
 use buck2_execute::execute::request::CommandExecutionRequest;
 use buck2_execute::execute::request::ExecutorPreference;
+use buck2_execute::execute::request::Nondeterminism;
 use buck2_execute::execute::result::CommandExecutionResult;
 use buck2_execute::execute::result::CommandExecutionStatus;
 use buck2_execute::execute::target::CommandExecutionTarget;
@@ -71,8 +72,14 @@ pub struct CachingExecutor {
     pub re_client: ManagedRemoteExecutionClient,
     pub re_use_case: RemoteExecutorUseCase,
     pub upload_all_actions: bool,
+    /// Upload results of local actions regardless of `cache_upload_behavior` and of whether the
+    /// action allows cache uploads, unless the action opted out of forced uploads.
+    pub upload_all_outputs: bool,
     pub knobs: ExecutorGlobalKnobs,
     pub cache_upload_behavior: CacheUploadBehavior,
+    /// The cache is disabled for this command (`--no-remote-cache`): it is only queried for
+    /// commands which prefer cached results, and nothing is uploaded.
+    pub skip_cache_read: bool,
 }
 
 impl CachingExecutor {
@@ -157,7 +164,8 @@ impl CachingExecutor {
     /// Upload an action result to the RE action cache, assuming conditions for the upload are met:
     /// the action must have been successful and must have run locally (not much point in caching
     /// something that ran on RE and is already cached), and cache uploads must be enabled, both
-    /// for this executor and this particular action.
+    /// for this executor and this particular action. `upload_all_outputs` overrides the latter
+    /// two conditions (and the upload size limit) for actions that allow forced uploads.
     async fn maybe_perform_cache_upload(
         &self,
         request: &CommandExecutionRequest,
@@ -166,14 +174,20 @@ impl CachingExecutor {
         result: &CommandExecutionResult,
         digest_config: DigestConfig,
     ) -> anyhow::Result<Option<CacheUploadOutcome>> {
-        let max_bytes = match self.cache_upload_behavior {
-            CacheUploadBehavior::Enabled { max_bytes } => max_bytes,
-            CacheUploadBehavior::Disabled => return Ok(None),
-        };
+        let max_bytes = if self.upload_all_outputs && request.allow_forced_cache_upload() {
+            None
+        } else {
+            let max_bytes = match self.cache_upload_behavior {
+                CacheUploadBehavior::Enabled { max_bytes } => max_bytes,
+                CacheUploadBehavior::Disabled => return Ok(None),
+            };
 
-        if !request.allow_cache_upload() {
-            return Ok(None);
-        }
+            if !request.allow_cache_upload() {
+                return Ok(None);
+            }
+
+            max_bytes
+        };
 
         let output_bytes = result.calc_output_size_bytes();
 
@@ -428,6 +442,23 @@ impl PreparedCommandExecutor for CachingExecutor {
             Err(e) => return manager.error("cache_upload", e),
         };
 
+        if self.skip_cache_read {
+            if command.request.nondeterminism() != Nondeterminism::PreferCached {
+                return self.inner.exec_cmd(command, manager, cancellations).await;
+            }
+            let manager = self
+                .try_action_cache_fetch(
+                    manager,
+                    command.request,
+                    &command.prepared_action.action,
+                    &command.prepared_action.blobs,
+                    command.digest_config,
+                    cancellations,
+                )
+                .await?;
+            return self.inner.exec_cmd(command, manager, cancellations).await;
+        }
+
         let manager = self
             .try_action_cache_fetch(
                 manager,
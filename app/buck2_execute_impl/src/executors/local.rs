@@ -9,6 +9,7 @@
 
 use std::borrow::Cow;
 use std::ffi::OsStr;
+use std::io::Read;
 use std::ops::ControlFlow;
 use std::path::Path;
 use std::process::Command;
@@ -25,6 +26,7 @@ use buck2_common::liveliness_observer::LivelinessObserverExt;
 use buck2_common::local_resource_state::LocalResourceHolder;
 use buck2_core::fs::artifact_path_resolver::ArtifactFs;
 use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
 use buck2_core::tag_error;
@@ -71,12 +73,22 @@ use futures::future::FutureExt;
 use futures::stream::StreamExt;
 use gazebo::prelude::*;
 use host_sharing::HostSharingBroker;
+use host_sharing::HostSharingRequirements;
 use indexmap::IndexMap;
 use more_futures::cancellable_future::CancellationObserver;
 use more_futures::cancellation::CancellationContext;
 use thiserror::Error;
 use tracing::info;
 
+use crate::executors::plugin::check_output_digests;
+use crate::executors::plugin::execute_via_plugin;
+use crate::executors::plugin::plugin_request;
+use crate::executors::pty::pty_wrap;
+use crate::executors::pty::strip_control_sequences;
+use crate::executors::sandbox::violations_report;
+use crate::executors::sandbox::LocalSandbox;
+use crate::running_local_actions::RunningLocalActions;
+
 #[derive(Debug, Error)]
 enum LocalExecutionError {
     #[error("Args list was empty")]
@@ -84,6 +96,19 @@ enum LocalExecutionError {
 
     #[error("Trying to execute a remote-only action on a local executor")]
     RemoteOnlyAction,
+
+    #[error(
+        "The action requested a pseudo-terminal (`tty = True`), which is not supported on this \
+        platform"
+    )]
+    TtyUnsupported,
+
+    #[error(
+        "Output `{output}` contains the value of `{var}`, which is not part of the cache key of \
+        the action, so the output must not be cached. Use `allow_cache_upload = False` and \
+        `allow_forced_cache_upload = False` on this action, or don't embed `{var}` in its outputs"
+    )]
+    CacheExemptEnvInOutput { var: String, output: String },
 }
 
 #[derive(Clone)]
@@ -95,8 +120,8 @@ pub struct LocalExecutor {
     root: AbsNormPathBuf,
     #[cfg_attr(not(unix), allow(unused))]
     forkserver: Option<ForkserverClient>,
-    #[allow(unused)]
     knobs: ExecutorGlobalKnobs,
+    running_actions: Arc<RunningLocalActions>,
 }
 
 impl LocalExecutor {
@@ -108,6 +133,7 @@ impl LocalExecutor {
         root: AbsNormPathBuf,
         forkserver: Option<ForkserverClient>,
         knobs: ExecutorGlobalKnobs,
+        running_actions: Arc<RunningLocalActions>,
     ) -> Self {
         Self {
             artifact_fs,
@@ -117,6 +143,7 @@ impl LocalExecutor {
             root,
             forkserver,
             knobs,
+            running_actions,
         }
     }
 
@@ -289,6 +316,34 @@ impl LocalExecutor {
             args.join(" "),
         );
 
+        // Plugins run the command as they see fit, including in their own sandbox or terminal.
+        let plugin = self.knobs.executor_plugin.as_ref();
+
+        let sandbox =
+            if self.knobs.sandbox_local_actions && !request.disable_sandbox() && plugin.is_none() {
+                match LocalSandbox::new(request, &self.artifact_fs) {
+                    Ok(sandbox) => Some(sandbox),
+                    Err(e) => return manager.error("sandbox_failed", e),
+                }
+            } else {
+                None
+            };
+        let sandboxed_args = sandbox
+            .as_ref()
+            .and_then(|sandbox| sandbox.wrap(&self.root, request.working_directory(), args));
+        let sandboxed_args: &[String] = sandboxed_args.as_deref().unwrap_or(args);
+        let tty_args = if request.tty() && plugin.is_none() {
+            match pty_wrap(sandboxed_args) {
+                Some(tty_args) => Some(tty_args),
+                None => {
+                    return manager.error("tty_unsupported", LocalExecutionError::TtyUnsupported);
+                }
+            }
+        } else {
+            None
+        };
+        let exec_args: &[String] = tty_args.as_deref().unwrap_or(sandboxed_args);
+
         let scratch_dir_abs;
 
         let tmpdirs = if let Some(scratch_dir) = scratch_dir {
@@ -342,6 +397,12 @@ impl LocalExecutor {
                         .iter()
                         .map(|(k, v)| (k.as_str(), StrOrOsStr::from(v.as_str()))),
                 )
+                .chain(
+                    request
+                        .cache_exempt_env()
+                        .iter()
+                        .map(|(k, v)| (k.as_str(), StrOrOsStr::from(v.as_str()))),
+                )
                 .chain(local_resource_env_vars.iter().cloned())
                 .chain(std::iter::once((
                     "BUCK2_DAEMON_UUID",
@@ -353,7 +414,12 @@ impl LocalExecutor {
                 )))
         };
 
-        let liveliness_observer = manager.liveliness_observer.dupe().and(cancellation);
+        let (kill_observer, running_action) = self.running_actions.register(action_digest);
+        let liveliness_observer = manager
+            .liveliness_observer
+            .dupe()
+            .and(cancellation)
+            .and(kill_observer);
 
         let (mut timing, res) = executor_stage_async(
             {
@@ -378,19 +444,33 @@ impl LocalExecutor {
                 let execution_start = Instant::now();
                 let start_time = SystemTime::now();
 
-                let env = iter_env().map(|(k, v)| (k, v.into_os_str()));
-                let r = self
-                    .exec(
-                        &args[0],
-                        &args[1..],
-                        env,
-                        request.working_directory(),
-                        request.timeout(),
-                        request.local_environment_inheritance(),
-                        liveliness_observer,
-                        request.disable_miniperf(),
-                    )
-                    .await;
+                let r = match plugin {
+                    Some(plugin) => {
+                        let env = iter_env().map(|(k, v)| (k, v.into_os_str()));
+                        match plugin_request(action_digest, &self.root, request, env) {
+                            Ok(plugin_request) => {
+                                execute_via_plugin(plugin, plugin_request, liveliness_observer)
+                                    .await
+                            }
+                            Err(e) => Err(e),
+                        }
+                    }
+                    None => {
+                        let env = iter_env().map(|(k, v)| (k, v.into_os_str()));
+                        self.exec(
+                            &exec_args[0],
+                            &exec_args[1..],
+                            env,
+                            request.working_directory(),
+                            request.timeout(),
+                            request.local_environment_inheritance(),
+                            liveliness_observer,
+                            request.disable_miniperf(),
+                        )
+                        .await
+                        .map(|(status, stdout, stderr)| (status, stdout, stderr, Vec::new()))
+                    }
+                };
 
                 let execution_time = execution_start.elapsed();
 
@@ -413,11 +493,30 @@ impl LocalExecutor {
             env: request.env().clone(),
         };
 
-        let (status, stdout, stderr) = match res {
+        let (status, mut stdout, mut stderr, output_digests) = match res {
             Ok(res) => res,
             Err(e) => return manager.error("exec_failed", e), // TODO (torozco): Can this take CommandExecutionKind? Should this be a failure?
         };
 
+        if tty_args.is_some() {
+            stdout = strip_control_sequences(&stdout);
+            stderr = strip_control_sequences(&stderr);
+        }
+
+        if let Some(sandbox) = &sandbox {
+            report_sandbox_violations(
+                sandbox,
+                &self.root,
+                request.working_directory(),
+                &status,
+                &mut stderr,
+            );
+        }
+
+        if matches!(status, GatherOutputStatus::Cancelled) && running_action.killed() {
+            stderr.extend(b"Killed by the user\n");
+        }
+
         let std_streams = CommandStdStreams::Local { stdout, stderr };
 
         match status {
@@ -425,17 +524,38 @@ impl LocalExecutor {
                 exit_code,
                 execution_stats,
             } => {
-                let outputs = match self
-                    .calculate_and_declare_output_values(request, digest_config)
-                    .await
+                let outputs = match calculate_and_declare_output_values(
+                    &self.artifact_fs,
+                    self.materializer.as_ref(),
+                    &self.root,
+                    request,
+                    digest_config,
+                )
+                .await
                 {
                     Ok(output_values) => output_values,
                     Err(e) => return manager.error("calculate_output_values_failed", e),
                 };
 
+                if let Some(plugin) = plugin {
+                    if let Err(e) =
+                        check_output_digests(plugin, &self.artifact_fs, &outputs, &output_digests)
+                    {
+                        return manager.error("plugin_output_digest_mismatch", e);
+                    }
+                }
+
                 timing.execution_stats = execution_stats;
 
                 if exit_code == 0 {
+                    let manager = check_outputs_do_not_embed_cache_exempt_env(
+                        manager,
+                        &self.artifact_fs,
+                        self.blocking_executor.as_ref(),
+                        request,
+                    )
+                    .await?;
+
                     manager.success(execution_kind, outputs, std_streams, timing)
                 } else {
                     let manager = check_inputs(
@@ -472,8 +592,20 @@ impl LocalExecutor {
                     Default::default(),
                     CommandStdStreams::Local {
                         stdout: Default::default(),
-                        stderr: format!("Spawning executable `{}` failed: {}", args[0], reason)
-                            .into_bytes(),
+                        stderr: if tty_args.is_some() {
+                            format!(
+                                "Spawning executable `{}` in a pty with `{}` failed: {}",
+                                args[0], exec_args[0], reason
+                            )
+                        } else if exec_args[0] != args[0] {
+                            format!(
+                                "Spawning sandboxed executable `{}` with `{}` failed: {}",
+                                args[0], exec_args[0], reason
+                            )
+                        } else {
+                            format!("Spawning executable `{}` failed: {}", args[0], reason)
+                        }
+                        .into_bytes(),
                     },
                     None,
                     timing,
@@ -482,59 +614,17 @@ impl LocalExecutor {
             GatherOutputStatus::TimedOut(duration) => {
                 manager.timeout(execution_kind, duration, std_streams, timing)
             }
+            // Killed by the user, which unlike other cancellations is a failure of the action.
+            GatherOutputStatus::Cancelled if running_action.killed() => manager.failure(
+                execution_kind,
+                Default::default(),
+                std_streams,
+                None,
+                timing,
+            ),
             GatherOutputStatus::Cancelled => manager.cancel_claim(),
         }
     }
-
-    async fn calculate_and_declare_output_values(
-        &self,
-        request: &CommandExecutionRequest,
-        digest_config: DigestConfig,
-    ) -> anyhow::Result<IndexMap<CommandExecutionOutput, ArtifactValue>> {
-        let mut builder = inputs_directory(request.inputs(), &self.artifact_fs)?;
-
-        // Read outputs from disk and add them to the builder
-        let mut entries = Vec::new();
-        for output in request.outputs() {
-            let path = output.resolve(&self.artifact_fs).into_path();
-            let abspath = self.root.join(&path);
-            let entry = build_entry_from_disk(
-                abspath,
-                FileDigestConfig::build(digest_config.cas_digest_config()),
-            )
-            .with_context(|| format!("collecting output {:?}", path))?;
-            if let Some(entry) = entry {
-                insert_entry(&mut builder, &path, entry)?;
-                entries.push((output.cloned(), path));
-            }
-        }
-
-        let mut to_declare = vec![];
-        let mut mapped_outputs = IndexMap::with_capacity(entries.len());
-
-        for (output, path) in entries {
-            let value = extract_artifact_value(&builder, &path, digest_config)?;
-            if let Some(value) = value {
-                match output {
-                    CommandExecutionOutput::BuildArtifact { .. } => {
-                        to_declare.push((path, value.dupe()));
-                    }
-                    CommandExecutionOutput::TestPath { .. } => {
-                        // Don't declare those as we don't currently have any form of GC so this
-                        // would take up space for nothing, and most importantly, we will never
-                        // need them to be in materializer state for e.g. matching as nothing
-                        // should depend on them.
-                    }
-                }
-
-                mapped_outputs.insert(output, value);
-            }
-        }
-
-        self.materializer.declare_existing(to_declare).await?;
-
-        Ok(mapped_outputs)
-    }
 }
 
 #[async_trait]
@@ -578,12 +668,31 @@ impl PreparedCommandExecutor for LocalExecutor {
         )
         .await;
 
+        let queued = {
+            let broker = &self.host_sharing_broker;
+            let permits = match request.host_sharing_requirements() {
+                HostSharingRequirements::ExclusiveAccess => broker.num_machine_permits(),
+                HostSharingRequirements::OnePerToken(.., class)
+                | HostSharingRequirements::Shared(class) => broker.requested_permits(class),
+            };
+            buck2_data::LocalQueued {
+                permits: permits as u64,
+                memory_megabytes: request
+                    .memory_requirement()
+                    .and_then(|m| broker.requested_memory(m))
+                    .map(|m| m as u64),
+                queue_depth: broker.num_waiting() as u64,
+            }
+        };
+
         let _permit = executor_stage_async(
             buck2_data::LocalStage {
-                stage: Some(buck2_data::LocalQueued {}.into()),
+                stage: Some(queued.into()),
             },
-            self.host_sharing_broker
-                .acquire(request.host_sharing_requirements()),
+            self.host_sharing_broker.acquire(
+                request.host_sharing_requirements(),
+                request.memory_requirement(),
+            ),
         )
         .await;
 
@@ -634,6 +743,59 @@ impl<'a> StrOrOsStr<'a> {
     }
 }
 
+/// Hash the outputs of a command which are on disk, and declare them to the materializer.
+pub async fn calculate_and_declare_output_values(
+    artifact_fs: &ArtifactFs,
+    materializer: &dyn Materializer,
+    root: &AbsNormPath,
+    request: &CommandExecutionRequest,
+    digest_config: DigestConfig,
+) -> anyhow::Result<IndexMap<CommandExecutionOutput, ArtifactValue>> {
+    let mut builder = inputs_directory(request.inputs(), artifact_fs)?;
+
+    // Read outputs from disk and add them to the builder
+    let mut entries = Vec::new();
+    for output in request.outputs() {
+        let path = output.resolve(artifact_fs).into_path();
+        let abspath = root.join(&path);
+        let entry = build_entry_from_disk(
+            abspath,
+            FileDigestConfig::build(digest_config.cas_digest_config()),
+        )
+        .with_context(|| format!("collecting output {:?}", path))?;
+        if let Some(entry) = entry {
+            insert_entry(&mut builder, &path, entry)?;
+            entries.push((output.cloned(), path));
+        }
+    }
+
+    let mut to_declare = vec![];
+    let mut mapped_outputs = IndexMap::with_capacity(entries.len());
+
+    for (output, path) in entries {
+        let value = extract_artifact_value(&builder, &path, digest_config)?;
+        if let Some(value) = value {
+            match output {
+                CommandExecutionOutput::BuildArtifact { .. } => {
+                    to_declare.push((path, value.dupe()));
+                }
+                CommandExecutionOutput::TestPath { .. } => {
+                    // Don't declare those as we don't currently have any form of GC so this
+                    // would take up space for nothing, and most importantly, we will never
+                    // need them to be in materializer state for e.g. matching as nothing
+                    // should depend on them.
+                }
+            }
+
+            mapped_outputs.insert(output, value);
+        }
+    }
+
+    materializer.declare_existing(to_declare).await?;
+
+    Ok(mapped_outputs)
+}
+
 /// Materialize all inputs artifact for CommandExecutionRequest so the command can be executed locally.
 pub async fn materialize_inputs(
     artifact_fs: &ArtifactFs,
@@ -731,6 +893,96 @@ async fn check_inputs(
     }
 }
 
+/// Fail if an output of `request` contains the value of one of its cache-exempt environment
+/// variables, when the outputs may be uploaded to the cache: those variables are not part of the
+/// action digest, so the cached outputs would be reused by builds where they have other values.
+async fn check_outputs_do_not_embed_cache_exempt_env(
+    manager: CommandExecutionManagerWithClaim,
+    artifact_fs: &ArtifactFs,
+    blocking_executor: &dyn BlockingExecutor,
+    request: &CommandExecutionRequest,
+) -> ControlFlow<CommandExecutionResult, CommandExecutionManagerWithClaim> {
+    if request.cache_exempt_env().is_empty()
+        || !(request.allow_cache_upload() || request.allow_forced_cache_upload())
+    {
+        return ControlFlow::Continue(manager);
+    }
+
+    let res = blocking_executor
+        .execute_io_inline(|| {
+            for output in request.outputs() {
+                let path = output.resolve(artifact_fs).into_path();
+                let abs_path = artifact_fs.fs().resolve(&path);
+                for (var, value) in request.cache_exempt_env() {
+                    if !value.is_empty() && file_tree_contains(&abs_path, value.as_bytes())? {
+                        return Err(LocalExecutionError::CacheExemptEnvInOutput {
+                            var: var.clone(),
+                            output: path.to_string(),
+                        }
+                        .into());
+                    }
+                }
+            }
+
+            Ok(())
+        })
+        .await;
+
+    match res {
+        Ok(()) => ControlFlow::Continue(manager),
+        Err(err) => ControlFlow::Break(manager.error("local_check_outputs", err)),
+    }
+}
+
+/// Whether the file at `path`, or any file under it if it is a directory, contains `needle`.
+/// Symlinks are not followed.
+fn file_tree_contains(path: &AbsNormPath, needle: &[u8]) -> anyhow::Result<bool> {
+    let metadata = match fs_util::symlink_metadata_if_exists(path)? {
+        Some(metadata) => metadata,
+        None => return Ok(false),
+    };
+    if metadata.is_dir() {
+        for entry in fs_util::read_dir(path)? {
+            if file_tree_contains(&AbsNormPathBuf::new(entry?.path())?, needle)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    } else if metadata.is_file() {
+        reader_contains(fs_util::open_file(path)?, needle, READ_CHUNK_LEN)
+    } else {
+        Ok(false)
+    }
+}
+
+/// How much of a file `file_tree_contains` reads at once.
+const READ_CHUNK_LEN: usize = 64 * 1024;
+
+/// Whether `reader` yields `needle`, reading `chunk_len` bytes at a time so that large outputs
+/// are not loaded into memory.
+fn reader_contains(mut reader: impl Read, needle: &[u8], chunk_len: usize) -> anyhow::Result<bool> {
+    if needle.is_empty() {
+        return Ok(true);
+    }
+    let mut chunk = vec![0; chunk_len];
+    // The end of the data read so far, which may be the start of a match.
+    let mut window = Vec::with_capacity(chunk_len + needle.len());
+    loop {
+        let read = match reader.read(&mut chunk) {
+            Ok(0) => return Ok(false),
+            Ok(read) => read,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        window.extend_from_slice(&chunk[..read]);
+        if window.windows(needle.len()).any(|w| w == needle) {
+            return Ok(true);
+        }
+        let keep = window.len().min(needle.len() - 1);
+        window.drain(..window.len() - keep);
+    }
+}
+
 /// Materialize build outputs from the previous run of the same command.
 /// Useful when executing incremental actions first remotely and then locally.
 /// In that case output from remote execution which is incremental state should be materialized prior local execution.
@@ -954,6 +1206,27 @@ mod unix {
     }
 }
 
+/// Append the likely undeclared inputs of a sandboxed action which failed to its stderr.
+fn report_sandbox_violations(
+    sandbox: &LocalSandbox,
+    root: &AbsNormPath,
+    working_directory: Option<&ProjectRelativePath>,
+    status: &GatherOutputStatus,
+    stderr: &mut Vec<u8>,
+) {
+    match status {
+        GatherOutputStatus::Finished { exit_code, .. } if *exit_code != 0 => {
+            let violations = sandbox.violations(root, working_directory, stderr, |path| {
+                fs_util::symlink_metadata(&root.join(path)).is_ok()
+            });
+            if !violations.is_empty() {
+                stderr.extend(violations_report(&violations).into_bytes());
+            }
+        }
+        _ => {}
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
@@ -1087,6 +1360,7 @@ mod tests {
             temp.path().root().to_buf(),
             None,
             ExecutorGlobalKnobs::default(),
+            Arc::new(RunningLocalActions::new()),
         );
 
         Ok((executor, temp.path().root().to_buf(), temp))
@@ -1150,4 +1424,78 @@ mod tests {
 
         Ok(())
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_report_sandbox_violations() -> anyhow::Result<()> {
+        use std::collections::BTreeSet;
+
+        use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+
+        let temp = ProjectRootTemp::new()?;
+        let root = temp.path().root();
+        let path = |p| root.join(ForwardRelativePath::unchecked_new(p));
+        fs_util::create_dir_all(path("src"))?;
+        fs_util::write(path("src/main.c"), "")?;
+        fs_util::write(path("src/util.h"), "")?;
+
+        let sandbox = LocalSandbox::testing_new(
+            BTreeSet::from([ProjectRelativePath::unchecked_new("src/main.c").to_owned()]),
+            BTreeSet::new(),
+        );
+        let src = Some(ProjectRelativePath::unchecked_new("src"));
+        let output = b"main.c:1:10: fatal error: 'util.h' file not found\n";
+        let report = |exit_code| {
+            let mut stderr = output.to_vec();
+            report_sandbox_violations(
+                &sandbox,
+                root,
+                src,
+                &GatherOutputStatus::Finished {
+                    exit_code,
+                    execution_stats: None,
+                },
+                &mut stderr,
+            );
+            String::from_utf8(stderr).unwrap()
+        };
+
+        let failed = report(1);
+        assert!(failed.starts_with(str::from_utf8(output)?));
+        assert!(failed.contains("not declared as inputs"), "{}", failed);
+        assert!(failed.ends_with("\n  src/util.h\n"), "{}", failed);
+        assert_eq!(report(0), str::from_utf8(output)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_tree_contains() -> anyhow::Result<()> {
+        use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+
+        let temp = ProjectRootTemp::new()?;
+        let root = temp.path().root();
+        let path = |p| root.join(ForwardRelativePath::unchecked_new(p));
+        fs_util::create_dir_all(path("out/sub"))?;
+        fs_util::write(path("out/a"), "nothing here")?;
+        fs_util::write(path("out/sub/b"), "built by 1234-abcd")?;
+
+        assert!(file_tree_contains(&path("out"), b"1234-abcd")?);
+        assert!(file_tree_contains(&path("out/sub/b"), b"1234-abcd")?);
+        assert!(!file_tree_contains(&path("out/a"), b"1234-abcd")?);
+        assert!(!file_tree_contains(&path("missing"), b"1234-abcd")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reader_contains_across_chunks() -> anyhow::Result<()> {
+        let data = b"built by 1234-abcd".as_slice();
+        for chunk_len in 1..=data.len() {
+            assert!(reader_contains(data, b"1234-abcd", chunk_len)?);
+            assert!(!reader_contains(data, b"1234-abce", chunk_len)?);
+        }
+        assert!(!reader_contains(b"".as_slice(), b"1234-abcd", 4)?);
+        Ok(())
+    }
 }
@@ -0,0 +1,208 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use std::time::Instant;
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+use buck2_common::file_ops::FileDigestConfig;
+use buck2_core::fs::artifact_path_resolver::ArtifactFs;
+use buck2_execute::execute::blocking::BlockingExecutor;
+use buck2_execute::execute::kind::CommandExecutionKind;
+use buck2_execute::execute::manager::CommandExecutionManager;
+use buck2_execute::execute::manager::CommandExecutionManagerExt;
+use buck2_execute::execute::output::CommandStdStreams;
+use buck2_execute::execute::prepared::PreparedCommand;
+use buck2_execute::execute::prepared::PreparedCommandExecutor;
+use buck2_execute::execute::request::CommandExecutionOutputRef;
+use buck2_execute::execute::request::CommandExecutionRequest;
+use buck2_execute::execute::request::ExecutorPreference;
+use buck2_execute::execute::result::CommandExecutionMetadata;
+use buck2_execute::execute::result::CommandExecutionResult;
+use buck2_execute::execute::result::CommandExecutionStatus;
+use buck2_execute::materialize::materializer::Materializer;
+use dupe::Dupe;
+use more_futures::cancellation::CancellationContext;
+
+use crate::executors::local::calculate_and_declare_output_values;
+use crate::executors::local::create_output_dirs;
+use crate::local_disk_cache::LocalDiskCache;
+
+/// A PreparedCommandExecutor that checks the local disk cache before executing actions using the
+/// underlying executor, and stores the results of the actions that ran locally in it.
+pub struct LocalDiskCachingExecutor {
+    pub inner: Arc<dyn PreparedCommandExecutor>,
+    pub cache: Arc<LocalDiskCache>,
+    pub artifact_fs: ArtifactFs,
+    pub materializer: Arc<dyn Materializer>,
+    pub blocking_executor: Arc<dyn BlockingExecutor>,
+    pub skip_cache_read: bool,
+    pub skip_cache_write: bool,
+}
+
+impl LocalDiskCachingExecutor {
+    async fn try_local_cache_fetch(
+        &self,
+        command: &PreparedCommand<'_, '_>,
+        manager: CommandExecutionManager,
+        cancellations: &CancellationContext,
+    ) -> ControlFlow<CommandExecutionResult, CommandExecutionManager> {
+        let digest = &command.prepared_action.action;
+        let cached = match self
+            .blocking_executor
+            .execute_io_inline(|| self.cache.lookup(digest))
+            .await
+        {
+            Ok(Some(cached)) => cached,
+            Ok(None) => return ControlFlow::Continue(manager),
+            Err(e) => {
+                tracing::warn!("Error querying the local cache for `{}`: {:#}", digest, e);
+                return ControlFlow::Continue(manager);
+            }
+        };
+
+        let start_time = SystemTime::now();
+        let start = Instant::now();
+
+        // Nothing else runs the command until we return the manager, so the outputs are restored
+        // before claiming it: if that fails, the action still runs as if it were a cache miss.
+        let outputs = async {
+            create_output_dirs(
+                &self.artifact_fs,
+                command.request,
+                self.materializer.dupe(),
+                self.blocking_executor.dupe(),
+                cancellations,
+            )
+            .await?;
+            self.blocking_executor
+                .execute_io_inline(|| self.cache.restore(&cached, self.artifact_fs.fs()))
+                .await?;
+            calculate_and_declare_output_values(
+                &self.artifact_fs,
+                self.materializer.as_ref(),
+                self.artifact_fs.fs().root(),
+                command.request,
+                command.digest_config,
+            )
+            .await
+        }
+        .await;
+
+        let outputs = match outputs {
+            Ok(outputs) => outputs,
+            Err(e) => {
+                tracing::warn!("Error restoring `{}` from the local cache: {:#}", digest, e);
+                return ControlFlow::Continue(manager);
+            }
+        };
+
+        let manager = manager.claim().await;
+
+        let (stdout, stderr) = cached.into_std_streams();
+        ControlFlow::Break(manager.success(
+            CommandExecutionKind::LocalActionCache {
+                digest: digest.dupe(),
+            },
+            outputs,
+            CommandStdStreams::Local { stdout, stderr },
+            CommandExecutionMetadata {
+                wall_time: start.elapsed(),
+                start_time,
+                ..Default::default()
+            },
+        ))
+    }
+
+    /// Store the result of an action in the local cache, if it ran locally and succeeded.
+    async fn maybe_store(
+        &self,
+        command: &PreparedCommand<'_, '_>,
+        result: &CommandExecutionResult,
+    ) {
+        match &result.report.status {
+            CommandExecutionStatus::Success {
+                execution_kind: CommandExecutionKind::Local { .. },
+            } => {}
+            _ => return,
+        }
+        let (stdout, stderr) = match &result.report.std_streams {
+            CommandStdStreams::Local { stdout, stderr } => (stdout, stderr),
+            _ => return,
+        };
+
+        let outputs: Vec<_> = result
+            .resolve_outputs(&self.artifact_fs)
+            .map(|(output, _)| output.path)
+            .collect();
+        let digest = &command.prepared_action.action;
+        let digest_config = FileDigestConfig::build(command.digest_config.cas_digest_config());
+
+        if let Err(e) = self
+            .blocking_executor
+            .execute_io_inline(|| {
+                self.cache.store(
+                    digest,
+                    self.artifact_fs.fs(),
+                    &outputs,
+                    stdout,
+                    stderr,
+                    digest_config,
+                )
+            })
+            .await
+        {
+            tracing::warn!("Error storing `{}` in the local cache: {:#}", digest, e);
+        }
+    }
+}
+
+/// Only actions whose outputs are fully determined by their inputs can be cached: not tests, and
+/// not actions which reuse their outputs from a previous run.
+fn is_cacheable(request: &CommandExecutionRequest) -> bool {
+    request.outputs_cleanup
+        && request
+            .outputs()
+            .all(|output| matches!(output, CommandExecutionOutputRef::BuildArtifact { .. }))
+}
+
+#[async_trait]
+impl PreparedCommandExecutor for LocalDiskCachingExecutor {
+    async fn exec_cmd(
+        &self,
+        command: &PreparedCommand<'_, '_>,
+        manager: CommandExecutionManager,
+        cancellations: &CancellationContext,
+    ) -> CommandExecutionResult {
+        if !is_cacheable(command.request) {
+            return self.inner.exec_cmd(command, manager, cancellations).await;
+        }
+
+        let manager = if self.skip_cache_read {
+            manager
+        } else {
+            self.try_local_cache_fetch(command, manager, cancellations)
+                .await?
+        };
+
+        let res = self.inner.exec_cmd(command, manager, cancellations).await;
+
+        if !self.skip_cache_write {
+            self.maybe_store(command, &res).await;
+        }
+
+        res
+    }
+
+    fn is_local_execution_possible(&self, executor_preference: ExecutorPreference) -> bool {
+        self.inner.is_local_execution_possible(executor_preference)
+    }
+}
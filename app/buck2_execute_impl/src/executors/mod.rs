@@ -10,4 +10,8 @@
 pub mod caching;
 pub mod hybrid;
 pub mod local;
+pub mod local_disk_cache;
+pub(crate) mod plugin;
+pub(crate) mod pty;
 pub mod re;
+pub(crate) mod sandbox;
@@ -0,0 +1,582 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Running local actions with an executor plugin, enabled with `build.executor_plugin`.
+//!
+//! An executor plugin is a gRPC server implementing the `buck.executor_plugin.ExecutorPlugin`
+//! service (see `executor_plugin.proto`), listening on a unix domain socket or a port on
+//! localhost. It receives the actions the local executor would spawn, after their inputs were
+//! materialized, and reports how the command exited once it wrote the outputs in the project,
+//! along with their digests, which are checked against the outputs buck2 hashes. This lets organizations run actions in their own sandbox or on their own build farm without
+//! patching buck2: everything else (caching, output hashing, hybrid execution) is unchanged.
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::net::Ipv4Addr;
+use std::path::Path;
+
+use anyhow::Context as _;
+use buck2_common::client_utils::get_channel_tcp;
+use buck2_common::client_utils::get_channel_uds;
+use buck2_common::convert::ProstDurationExt;
+use buck2_common::liveliness_observer::LivelinessObserver;
+use buck2_core::directory::DirectoryEntry;
+use buck2_core::directory::DirectoryIterator;
+use buck2_core::directory::FingerprintedDirectory;
+use buck2_core::fs::artifact_path_resolver::ArtifactFs;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_execute::artifact_value::ArtifactValue;
+use buck2_execute::directory::ActionDirectoryMember;
+use buck2_execute::execute::action_digest::ActionDigest;
+use buck2_execute::execute::request::CommandExecutionOutput;
+use buck2_execute::execute::request::CommandExecutionRequest;
+use buck2_execute::execute::request::OutputType;
+use buck2_execute::knobs::ExecutorPluginAddress;
+use buck2_executor_plugin_proto::execute_response::Status;
+use buck2_executor_plugin_proto::executor_plugin_client::ExecutorPluginClient;
+use buck2_executor_plugin_proto::EnvironmentVariable;
+use buck2_executor_plugin_proto::ExecuteRequest;
+use buck2_executor_plugin_proto::ExecuteResponse;
+use buck2_executor_plugin_proto::InputFile;
+use buck2_executor_plugin_proto::InputSymlink;
+use buck2_executor_plugin_proto::Output;
+use buck2_executor_plugin_proto::OutputDigest;
+use buck2_forkserver::run::GatherOutputStatus;
+use dupe::Dupe;
+use futures::future;
+use futures::future::Either;
+use gazebo::prelude::*;
+use indexmap::IndexMap;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use tonic::transport::Channel;
+
+/// Connections to the plugins, shared by all the commands of the daemon.
+static CLIENTS: Lazy<Mutex<HashMap<ExecutorPluginAddress, ExecutorPluginClient<Channel>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, thiserror::Error)]
+enum ExecutorPluginError {
+    #[error("Executor plugin `{0}` returned a response without a status")]
+    MissingStatus(ExecutorPluginAddress),
+    #[error("Executor plugin `{0}` reported a digest for `{1}`, which the action did not produce")]
+    UnknownOutput(ExecutorPluginAddress, String),
+    #[error(
+        "Executor plugin `{address}` reported digest `{reported}` for output `{path}`, but its digest is `{actual}`"
+    )]
+    OutputDigestMismatch {
+        address: ExecutorPluginAddress,
+        path: String,
+        reported: String,
+        actual: String,
+    },
+}
+
+/// The result of an action run by a plugin: how it exited, its stdout and stderr, and the
+/// digests the plugin reported for its outputs.
+pub(crate) type PluginResult = (GatherOutputStatus, Vec<u8>, Vec<u8>, Vec<OutputDigest>);
+
+async fn client(address: &ExecutorPluginAddress) -> anyhow::Result<ExecutorPluginClient<Channel>> {
+    if let Some(client) = CLIENTS.lock().get(address) {
+        return Ok(client.clone());
+    }
+    let channel = match address {
+        ExecutorPluginAddress::Unix(path) => get_channel_uds(Path::new(&**path), false).await?,
+        ExecutorPluginAddress::Tcp(port) => get_channel_tcp(Ipv4Addr::LOCALHOST, *port).await?,
+    };
+    let client = ExecutorPluginClient::new(channel);
+    CLIENTS.lock().insert(address.dupe(), client.clone());
+    Ok(client)
+}
+
+/// The bytes of an environment variable value, which are passed as is on Unix.
+fn env_value(value: &OsStr) -> Vec<u8> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        value.as_bytes().to_vec()
+    }
+    #[cfg(not(unix))]
+    {
+        value.to_string_lossy().into_owned().into_bytes()
+    }
+}
+
+/// The request to send to a plugin to run `request`. `env` is the full environment of the
+/// command, as it would be set for local execution.
+pub(crate) fn plugin_request<'a>(
+    action_digest: &ActionDigest,
+    root: &AbsNormPath,
+    request: &CommandExecutionRequest,
+    env: impl IntoIterator<Item = (&'a str, &'a OsStr)>,
+) -> anyhow::Result<ExecuteRequest> {
+    let mut input_files = Vec::new();
+    let mut input_symlinks = Vec::new();
+    let mut walk = request
+        .paths()
+        .input_directory()
+        .fingerprinted_unordered_walk();
+    while let Some((path, entry)) = walk.next() {
+        let path = path.get().to_string();
+        match entry {
+            DirectoryEntry::Leaf(ActionDirectoryMember::File(file)) => {
+                input_files.push(InputFile {
+                    path,
+                    digest: file.digest.to_string(),
+                    is_executable: file.is_executable,
+                })
+            }
+            DirectoryEntry::Leaf(ActionDirectoryMember::Symlink(symlink)) => {
+                input_symlinks.push(InputSymlink {
+                    path,
+                    target: symlink.to_string(),
+                })
+            }
+            DirectoryEntry::Leaf(ActionDirectoryMember::ExternalSymlink(symlink)) => input_symlinks
+                .push(InputSymlink {
+                    path,
+                    target: symlink.to_string(),
+                }),
+            DirectoryEntry::Dir(_) => {}
+        }
+    }
+
+    let outputs = request
+        .paths()
+        .output_paths()
+        .iter()
+        .map(|(path, output_type)| Output {
+            path: path.to_string(),
+            r#type: match output_type {
+                OutputType::FileOrDirectory => {
+                    buck2_executor_plugin_proto::OutputType::FileOrDirectory
+                }
+                OutputType::File => buck2_executor_plugin_proto::OutputType::File,
+                OutputType::Directory => buck2_executor_plugin_proto::OutputType::Directory,
+            } as i32,
+        })
+        .collect();
+
+    Ok(ExecuteRequest {
+        action_digest: action_digest.to_string(),
+        project_root: root.to_string(),
+        argv: request.args().to_vec(),
+        env: env
+            .into_iter()
+            .map(|(key, value)| EnvironmentVariable {
+                key: key.to_owned(),
+                value: env_value(value),
+            })
+            .collect(),
+        working_directory: request
+            .working_directory()
+            .map_or_else(String::new, |d| d.to_string()),
+        timeout: request.timeout().try_map(|d| d.try_into())?,
+        input_files,
+        input_symlinks,
+        outputs,
+        tty: request.tty(),
+    })
+}
+
+/// Run an action with the plugin at `address`. The result is reported like the result of a
+/// local command.
+pub(crate) async fn execute_via_plugin(
+    address: &ExecutorPluginAddress,
+    request: ExecuteRequest,
+    liveliness_observer: impl LivelinessObserver + 'static,
+) -> anyhow::Result<PluginResult> {
+    let mut client = client(address)
+        .await
+        .with_context(|| format!("Error connecting to executor plugin `{}`", address))?;
+
+    let execute = client.execute(request);
+    let alive = liveliness_observer.while_alive();
+    futures::pin_mut!(execute, alive);
+    let response = match future::select(execute, alive).await {
+        Either::Left((response, _)) => response,
+        // Dropping the request cancels it on the plugin side.
+        Either::Right(((), _)) => {
+            return Ok((GatherOutputStatus::Cancelled, vec![], vec![], vec![]));
+        }
+    };
+
+    match response {
+        Ok(response) => status_from_response(address, response.into_inner()),
+        Err(status) => {
+            // The connection may be broken, connect again for the next action.
+            CLIENTS.lock().remove(address);
+            Err(anyhow::Error::new(status))
+                .with_context(|| format!("Error executing action with plugin `{}`", address))
+        }
+    }
+}
+
+fn status_from_response(
+    address: &ExecutorPluginAddress,
+    response: ExecuteResponse,
+) -> anyhow::Result<PluginResult> {
+    let status = match response.status {
+        Some(Status::Finished(finished)) => GatherOutputStatus::Finished {
+            exit_code: finished.exit_code,
+            execution_stats: None,
+        },
+        Some(Status::TimedOut(timed_out)) => GatherOutputStatus::TimedOut(
+            timed_out
+                .duration
+                .map(|d| d.try_into_duration())
+                .transpose()?
+                .unwrap_or_default(),
+        ),
+        Some(Status::SpawnFailed(spawn_failed)) => {
+            GatherOutputStatus::SpawnFailed(spawn_failed.reason)
+        }
+        None => return Err(ExecutorPluginError::MissingStatus(address.dupe()).into()),
+    };
+    Ok((
+        status,
+        response.stdout,
+        response.stderr,
+        response.output_digests,
+    ))
+}
+
+/// Check the digests the plugin at `address` reported against the outputs of the action, as
+/// hashed by buck2.
+pub(crate) fn check_output_digests(
+    address: &ExecutorPluginAddress,
+    artifact_fs: &ArtifactFs,
+    outputs: &IndexMap<CommandExecutionOutput, ArtifactValue>,
+    reported: &[OutputDigest],
+) -> anyhow::Result<()> {
+    if reported.is_empty() {
+        return Ok(());
+    }
+    let actual: HashMap<String, Option<String>> = outputs
+        .iter()
+        .map(|(output, value)| {
+            (
+                output.as_ref().resolve(artifact_fs).into_path().to_string(),
+                value.digest().map(|d| d.to_string()),
+            )
+        })
+        .collect();
+    for OutputDigest { path, digest } in reported {
+        match actual.get(path) {
+            None => {
+                return Err(
+                    ExecutorPluginError::UnknownOutput(address.dupe(), path.clone()).into(),
+                );
+            }
+            Some(actual) if actual.as_ref() != Some(digest) => {
+                return Err(ExecutorPluginError::OutputDigestMismatch {
+                    address: address.dupe(),
+                    path: path.clone(),
+                    reported: digest.clone(),
+                    actual: actual.clone().unwrap_or_else(|| "none".to_owned()),
+                }
+                .into());
+            }
+            Some(_) => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsStr;
+    use std::net::Ipv4Addr;
+    use std::time::Duration;
+
+    use assert_matches::assert_matches;
+    use buck2_common::cas_digest::TrackedFileDigest;
+    use buck2_common::file_ops::FileMetadata;
+    use buck2_common::liveliness_observer::NoopLivelinessObserver;
+    use buck2_core::base_deferred_key_dyn::BaseDeferredKeyDyn;
+    use buck2_core::buck_path::resolver::BuckPathResolver;
+    use buck2_core::cells::cell_root_path::CellRootPathBuf;
+    use buck2_core::cells::name::CellName;
+    use buck2_core::cells::CellResolver;
+    use buck2_core::configuration::data::ConfigurationData;
+    use buck2_core::fs::artifact_path_resolver::ArtifactFs;
+    use buck2_core::fs::buck_out_path::BuckOutPath;
+    use buck2_core::fs::buck_out_path::BuckOutPathResolver;
+    use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+    use buck2_core::fs::project::ProjectRootTemp;
+    use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+    use buck2_core::target::label::TargetLabel;
+    use buck2_execute::artifact_value::ArtifactValue;
+    use buck2_execute::digest_config::DigestConfig;
+    use buck2_execute::execute::action_digest::ActionDigest;
+    use buck2_execute::execute::request::ActionMetadataBlob;
+    use buck2_execute::execute::request::CommandExecutionInput;
+    use buck2_execute::execute::request::CommandExecutionOutput;
+    use buck2_execute::execute::request::CommandExecutionPaths;
+    use buck2_execute::execute::request::CommandExecutionRequest;
+    use buck2_execute::execute::request::OutputType;
+    use buck2_execute::knobs::ExecutorPluginAddress;
+    use buck2_executor_plugin_proto::executor_plugin_server::ExecutorPlugin;
+    use buck2_executor_plugin_proto::executor_plugin_server::ExecutorPluginServer;
+    use buck2_executor_plugin_proto::ExecuteRequest;
+    use buck2_executor_plugin_proto::ExecuteResponse;
+    use buck2_executor_plugin_proto::Finished;
+    use buck2_executor_plugin_proto::OutputDigest;
+    use buck2_executor_plugin_proto::SpawnFailed;
+    use buck2_executor_plugin_proto::TimedOut;
+    use buck2_forkserver::run::GatherOutputStatus;
+    use dupe::Dupe;
+    use indexmap::indexmap;
+    use indexmap::indexset;
+    use tokio_stream::wrappers::TcpListenerStream;
+
+    use crate::executors::plugin::check_output_digests;
+    use crate::executors::plugin::execute_via_plugin;
+    use crate::executors::plugin::plugin_request;
+    use crate::executors::plugin::status_from_response;
+
+    #[test]
+    fn test_status_from_response() -> anyhow::Result<()> {
+        let address = ExecutorPluginAddress::Tcp(5000);
+        let response = |status: Option<_>| ExecuteResponse {
+            status,
+            stdout: b"out".to_vec(),
+            stderr: b"err".to_vec(),
+            output_digests: vec![OutputDigest {
+                path: "buck-out/v2/out".to_owned(),
+                digest: "0000:4".to_owned(),
+            }],
+        };
+
+        let (status, stdout, stderr, output_digests) =
+            status_from_response(&address, response(Some(Finished { exit_code: 2 }.into())))?;
+        assert_matches!(status, GatherOutputStatus::Finished { exit_code: 2, .. });
+        assert_eq!((stdout, stderr), (b"out".to_vec(), b"err".to_vec()));
+        assert_eq!(output_digests.len(), 1);
+
+        let timed_out = TimedOut {
+            duration: Some(Duration::from_secs(3).try_into()?),
+        };
+        let (status, ..) = status_from_response(&address, response(Some(timed_out.into())))?;
+        assert_matches!(status, GatherOutputStatus::TimedOut(d) if d == Duration::from_secs(3));
+
+        let spawn_failed = SpawnFailed {
+            reason: "not found".to_owned(),
+        };
+        let (status, ..) = status_from_response(&address, response(Some(spawn_failed.into())))?;
+        assert_matches!(status, GatherOutputStatus::SpawnFailed(reason) if reason == "not found");
+
+        assert!(status_from_response(&address, response(None)).is_err());
+        Ok(())
+    }
+
+    /// A request with an action metadata file as input and a single file output.
+    fn request(temp: &ProjectRootTemp) -> anyhow::Result<(ArtifactFs, CommandExecutionRequest)> {
+        let artifact_fs = ArtifactFs::new(
+            BuckPathResolver::new(CellResolver::testing_with_name_and_path(
+                CellName::testing_new("cell"),
+                CellRootPathBuf::new(ProjectRelativePathBuf::unchecked_new("cell_path".into())),
+            )),
+            BuckOutPathResolver::new(ProjectRelativePathBuf::unchecked_new("buck-out/v2".into())),
+            temp.path().dupe(),
+        );
+        let owner = BaseDeferredKeyDyn::TargetLabel(
+            TargetLabel::testing_parse("cell//pkg:target")
+                .configure(ConfigurationData::testing_new()),
+        );
+        let digest_config = DigestConfig::testing_default();
+        let metadata = ActionMetadataBlob {
+            data: b"metadata".to_vec(),
+            digest: TrackedFileDigest::from_content(b"metadata", digest_config.cas_digest_config()),
+            path: BuckOutPath::new(
+                owner.dupe(),
+                ForwardRelativePathBuf::unchecked_new("metadata.json".into()),
+            ),
+        };
+        let output = CommandExecutionOutput::BuildArtifact {
+            path: BuckOutPath::new(owner, ForwardRelativePathBuf::unchecked_new("out".into())),
+            output_type: OutputType::File,
+        };
+        let request = CommandExecutionRequest::new(
+            vec!["cat".to_owned(), "metadata.json".to_owned()],
+            CommandExecutionPaths::new(
+                vec![CommandExecutionInput::ActionMetadata(metadata)],
+                indexset![output],
+                &artifact_fs,
+                digest_config,
+            )?,
+            Default::default(),
+        )
+        .with_timeout(Duration::from_secs(10));
+        Ok((artifact_fs, request))
+    }
+
+    #[test]
+    fn test_plugin_request() -> anyhow::Result<()> {
+        let temp = ProjectRootTemp::new()?;
+        let (_artifact_fs, request) = request(&temp)?;
+        let digest = ActionDigest::new_sha1([1; 20], 10);
+
+        #[cfg(unix)]
+        let value: &OsStr = {
+            use std::os::unix::ffi::OsStrExt;
+            OsStr::from_bytes(b"not\xffutf8")
+        };
+        #[cfg(not(unix))]
+        let value = OsStr::new("value");
+        let plugin_request =
+            plugin_request(&digest, temp.path().root(), &request, [("KEY", value)])?;
+
+        assert_eq!(plugin_request.action_digest, digest.to_string());
+        assert_eq!(plugin_request.project_root, temp.path().root().to_string());
+        assert_eq!(plugin_request.argv, vec!["cat", "metadata.json"]);
+        assert_eq!(plugin_request.env.len(), 1);
+        assert_eq!(plugin_request.env[0].key, "KEY");
+        #[cfg(unix)]
+        assert_eq!(plugin_request.env[0].value, b"not\xffutf8".to_vec());
+        assert_eq!(
+            plugin_request.timeout,
+            Some(Duration::from_secs(10).try_into()?)
+        );
+
+        assert_eq!(plugin_request.input_files.len(), 1);
+        let input = &plugin_request.input_files[0];
+        assert!(input.path.ends_with("/metadata.json"), "{}", input.path);
+        assert!(input.path.starts_with("buck-out/v2/gen/"), "{}", input.path);
+        assert!(!input.is_executable);
+        assert!(plugin_request.input_symlinks.is_empty());
+
+        assert_eq!(plugin_request.outputs.len(), 1);
+        let output = &plugin_request.outputs[0];
+        assert!(output.path.ends_with("/out"), "{}", output.path);
+        assert_eq!(
+            output.r#type,
+            buck2_executor_plugin_proto::OutputType::File as i32
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_output_digests() -> anyhow::Result<()> {
+        let temp = ProjectRootTemp::new()?;
+        let (artifact_fs, request) = request(&temp)?;
+        let address = ExecutorPluginAddress::Tcp(5000);
+        let output = request.outputs().next().unwrap();
+        let path = output.resolve(&artifact_fs).into_path().to_string();
+        let digest = TrackedFileDigest::from_content(
+            b"out",
+            DigestConfig::testing_default().cas_digest_config(),
+        );
+        let outputs = indexmap! {
+            output.cloned() => ArtifactValue::file(FileMetadata {
+                digest: digest.dupe(),
+                is_executable: false,
+            }),
+        };
+        let reported = |path: &str, digest: &str| {
+            vec![OutputDigest {
+                path: path.to_owned(),
+                digest: digest.to_owned(),
+            }]
+        };
+
+        check_output_digests(&address, &artifact_fs, &outputs, &[])?;
+        check_output_digests(
+            &address,
+            &artifact_fs,
+            &outputs,
+            &reported(&path, &digest.to_string()),
+        )?;
+        assert!(
+            check_output_digests(
+                &address,
+                &artifact_fs,
+                &outputs,
+                &reported(&path, "da39a3ee5e6b4b0d3255bfef95601890afd80709:0"),
+            )
+            .is_err()
+        );
+        assert!(
+            check_output_digests(
+                &address,
+                &artifact_fs,
+                &outputs,
+                &reported("buck-out/v2/other", &digest.to_string()),
+            )
+            .is_err()
+        );
+        Ok(())
+    }
+
+    /// Answers every request with the command line it received on stdout, and the digest of an
+    /// empty file for each output.
+    struct EchoPlugin;
+
+    #[tonic::async_trait]
+    impl ExecutorPlugin for EchoPlugin {
+        async fn execute(
+            &self,
+            request: tonic::Request<ExecuteRequest>,
+        ) -> Result<tonic::Response<ExecuteResponse>, tonic::Status> {
+            let request = request.into_inner();
+            Ok(tonic::Response::new(ExecuteResponse {
+                status: Some(Finished { exit_code: 0 }.into()),
+                stdout: request.argv.join(" ").into_bytes(),
+                stderr: request
+                    .env
+                    .iter()
+                    .flat_map(|var| var.value.iter().copied())
+                    .collect(),
+                output_digests: request
+                    .outputs
+                    .into_iter()
+                    .map(|output| OutputDigest {
+                        path: output.path,
+                        digest: "da39a3ee5e6b4b0d3255bfef95601890afd80709:0".to_owned(),
+                    })
+                    .collect(),
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_execute_via_plugin() -> anyhow::Result<()> {
+        let listener = tokio::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+        let address = ExecutorPluginAddress::Tcp(listener.local_addr()?.port());
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(ExecutorPluginServer::new(EchoPlugin))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        let temp = ProjectRootTemp::new()?;
+        let (_artifact_fs, request) = request(&temp)?;
+        let plugin_request = plugin_request(
+            &ActionDigest::new_sha1([1; 20], 10),
+            temp.path().root(),
+            &request,
+            [("KEY", OsStr::new("value"))],
+        )?;
+        let output = plugin_request.outputs[0].path.clone();
+
+        let (status, stdout, stderr, output_digests) =
+            execute_via_plugin(&address, plugin_request, NoopLivelinessObserver::create()).await?;
+        assert_matches!(status, GatherOutputStatus::Finished { exit_code: 0, .. });
+        assert_eq!(stdout, b"cat metadata.json");
+        assert_eq!(stderr, b"value");
+        assert_eq!(
+            output_digests,
+            vec![OutputDigest {
+                path: output,
+                digest: "da39a3ee5e6b4b0d3255bfef95601890afd80709:0".to_owned(),
+            }]
+        );
+        Ok(())
+    }
+}
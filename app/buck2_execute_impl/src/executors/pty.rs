@@ -0,0 +1,154 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Running local actions in a pseudo-terminal, for `ctx.actions.run(..., tty = True)`.
+//!
+//! Some tools only produce the output we want (or behave at all) when writing to a terminal. We
+//! run those under `script`, which allocates a pty for the command and copies what it writes to
+//! it to our stdout. Since a terminal has a single output, the stdout and stderr of the command
+//! are both captured as stdout.
+//!
+//! What tools write to a terminal is full of escape sequences (colors, cursor movements, progress
+//! bars redrawn with carriage returns), so we strip them from the captured output: it is recorded
+//! and cached as the text a user would end up seeing, which does not depend on timing.
+//!
+//! Platforms without `script` (Windows) fail tty actions rather than silently running them
+//! without a terminal.
+
+/// The command line running `args` in a pseudo-terminal, or `None` if that is not supported on
+/// this platform.
+pub(crate) fn pty_wrap(args: &[String]) -> Option<Vec<String>> {
+    if cfg!(target_os = "linux") {
+        Some(script_linux_args(args))
+    } else if cfg!(target_os = "macos") {
+        Some(script_bsd_args(args))
+    } else {
+        None
+    }
+}
+
+/// util-linux `script` takes the command as a shell string. `-e` makes it exit with the status
+/// of the command.
+fn script_linux_args(args: &[String]) -> Vec<String> {
+    let command = std::iter::once("exec".to_owned())
+        .chain(args.iter().map(|arg| shell_quote(arg)))
+        .collect::<Vec<_>>()
+        .join(" ");
+    vec![
+        "script".to_owned(),
+        "-qefc".to_owned(),
+        command,
+        "/dev/null".to_owned(),
+    ]
+}
+
+/// BSD `script` takes the command and its arguments as is.
+fn script_bsd_args(args: &[String]) -> Vec<String> {
+    let mut wrapped = vec!["script".to_owned(), "-q".to_owned(), "/dev/null".to_owned()];
+    wrapped.extend(args.iter().cloned());
+    wrapped
+}
+
+fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+/// The text a terminal would show for `output`: escape sequences and control characters are
+/// removed, and a carriage return not followed by a newline discards the line written so far.
+pub(crate) fn strip_control_sequences(output: &[u8]) -> Vec<u8> {
+    const ESC: u8 = 0x1b;
+    const BEL: u8 = 0x07;
+
+    let mut stripped = Vec::with_capacity(output.len());
+    // Where the current line starts in `stripped`.
+    let mut line_start = 0;
+    let mut i = 0;
+    while i < output.len() {
+        match output[i] {
+            ESC => match output.get(i + 1) {
+                // CSI: parameters and intermediate bytes, then a final byte in `@..=~`.
+                Some(b'[') => {
+                    i += 2;
+                    while i < output.len() && !(0x40..=0x7e).contains(&output[i]) {
+                        i += 1;
+                    }
+                }
+                // OSC (e.g. window titles): terminated by BEL or `ESC \`.
+                Some(b']') => {
+                    i += 2;
+                    while i < output.len() {
+                        if output[i] == BEL {
+                            break;
+                        }
+                        if output[i] == ESC && output.get(i + 1) == Some(&b'\\') {
+                            i += 1;
+                            break;
+                        }
+                        i += 1;
+                    }
+                }
+                // Two bytes sequences, e.g. `ESC =`, or `ESC (` followed by the charset.
+                Some(b'(' | b')') => i += 2,
+                Some(_) => i += 1,
+                None => {}
+            },
+            b'\r' if output.get(i + 1) == Some(&b'\n') => {}
+            b'\r' => stripped.truncate(line_start),
+            b'\n' => {
+                stripped.push(b'\n');
+                line_start = stripped.len();
+            }
+            b'\t' => stripped.push(b'\t'),
+            c if c < 0x20 || c == 0x7f => {}
+            c => stripped.push(c),
+        }
+        i += 1;
+    }
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::executors::pty::script_bsd_args;
+    use crate::executors::pty::script_linux_args;
+    use crate::executors::pty::strip_control_sequences;
+
+    fn strip(output: &str) -> String {
+        String::from_utf8(strip_control_sequences(output.as_bytes())).unwrap()
+    }
+
+    #[test]
+    fn test_strip_control_sequences() {
+        assert_eq!(strip("plain\ntext\n"), "plain\ntext\n");
+        assert_eq!(strip("line\r\n"), "line\n");
+        assert_eq!(
+            strip("\x1b[1;31merror\x1b[0m: \x1b[Kfoo\x1b(B\n"),
+            "error: foo\n"
+        );
+        assert_eq!(strip("\x1b]0;title\x07a\x1b]2;t\x1b\\b\n"), "ab\n");
+        assert_eq!(
+            strip("start\n10%\r50%\r100%\r\ndone\x07\n"),
+            "start\n100%\ndone\n"
+        );
+        assert_eq!(strip("unterminated\x1b["), "unterminated");
+    }
+
+    #[test]
+    fn test_script_args() {
+        let args = ["echo".to_owned(), "it's".to_owned()];
+        assert_eq!(
+            script_linux_args(&args),
+            vec!["script", "-qefc", "exec 'echo' 'it'\\''s'", "/dev/null"]
+        );
+        assert_eq!(
+            script_bsd_args(&args),
+            vec!["script", "-q", "/dev/null", "echo", "it's"]
+        );
+    }
+}
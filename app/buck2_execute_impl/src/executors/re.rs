@@ -21,7 +21,6 @@ use buck2_events::dispatch::span_async;
 use buck2_execute::digest_config::DigestConfig;
 use buck2_execute::execute::action_digest::ActionDigest;
 use buck2_execute::execute::blobs::ActionBlobs;
-use buck2_execute::execute::kind::CommandExecutionKind;
 use buck2_execute::execute::manager::CommandExecutionManager;
 use buck2_execute::execute::manager::CommandExecutionManagerExt;
 use buck2_execute::execute::output::CommandStdStreams;
@@ -31,6 +30,7 @@ use buck2_execute::execute::prepared::PreparedCommandExecutor;
 use buck2_execute::execute::request::CommandExecutionPaths;
 use buck2_execute::execute::request::CommandExecutionRequest;
 use buck2_execute::execute::request::ExecutorPreference;
+use buck2_execute::execute::request::Nondeterminism;
 use buck2_execute::execute::result::CommandExecutionResult;
 use buck2_execute::execute::target::CommandExecutionTarget;
 use buck2_execute::knobs::ExecutorGlobalKnobs;
@@ -54,8 +54,16 @@ use crate::re::download::DownloadResult;
 
 #[derive(Debug, Error)]
 pub enum RemoteExecutorError {
-    #[error("Trying to execute a `local_only = True` action on remote executor")]
+    #[error(
+        "Trying to execute a `local_only = True` action on remote executor (remote execution is \
+        required by `--remote-only` or by the executor config of the execution platform)"
+    )]
     LocalOnlyAction,
+    #[error(
+        "Trying to execute an action with environment variables excluded from its cache key (e.g. \
+        `build_id_env_var`) on remote executor, which would make them part of the action digest"
+    )]
+    CacheExemptEnv,
 }
 
 pub struct ReExecutor {
@@ -137,6 +145,11 @@ impl ReExecutor {
         let identity =
             ReActionIdentity::new(action, self.re_action_key.as_deref(), request.paths());
 
+        // A rebuild which skipped the cache must not replace the cached result of a command whose
+        // outputs differ between runs.
+        let skip_cache_write = self.skip_cache_write
+            || (self.skip_cache_read && request.nondeterminism() == Nondeterminism::NoVerify);
+
         let execute_response = self
             .re_client
             .execute(
@@ -146,7 +159,7 @@ impl ReExecutor {
                 &identity,
                 &mut manager,
                 self.skip_cache_read,
-                self.skip_cache_write,
+                skip_cache_write,
                 self.re_max_queue_time_ms.map(Duration::from_millis),
             )
             .await;
@@ -167,9 +180,7 @@ impl ReExecutor {
                 // NOTE: We don't get stdout / stderr from RE when this happens, so the best we can
                 // do here is just pass on the error.
                 manager.failure(
-                    CommandExecutionKind::Remote {
-                        digest: action_digest.dupe(),
-                    },
+                    response.execution_kind(action_digest.dupe()),
                     IndexMap::new(),
                     CommandStdStreams::Local {
                         stdout: Vec::new(),
@@ -181,9 +192,7 @@ impl ReExecutor {
                 )
             } else if is_timeout_error(&response.error) && request.timeout().is_some() {
                 manager.timeout(
-                    CommandExecutionKind::Remote {
-                        digest: action_digest.dupe(),
-                    },
+                    response.execution_kind(action_digest.dupe()),
                     // Checked above: we fallthrough to the error path if we didn't set a timeout
                     // and yet received one.
                     request.timeout().unwrap(),
@@ -229,9 +238,7 @@ impl ReExecutor {
 
         if action_result.exit_code != 0 {
             return ControlFlow::Break(manager.failure(
-                CommandExecutionKind::Remote {
-                    digest: action_digest.dupe(),
-                },
+                response.execution_kind(action_digest.dupe()),
                 // TODO: we want to expose RE outputs even when actions fail,
                 //   this will allow tpx to correctly retrieve the output of
                 //   failing tests running on RE. See D34344489 for context.
@@ -276,6 +283,12 @@ impl PreparedCommandExecutor for ReExecutor {
             )?;
         }
 
+        if !command.request.cache_exempt_env().is_empty() {
+            return ControlFlow::Break(
+                manager.error("remote_prepare", RemoteExecutorError::CacheExemptEnv),
+            )?;
+        }
+
         // TODO(bobyf, torozco): remote execution probably needs to explicitly handle cancellations
         let manager = self
             .upload(manager, blobs, request.paths(), *digest_config)
@@ -0,0 +1,399 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Sandboxing of local actions, enabled with `build.sandbox_local_actions = true`.
+//!
+//! A sandboxed action only sees the parts of the project (sources and `buck-out`) it declared as
+//! inputs, and can only write to its output directories, its scratch directory and its incremental
+//! state directory, if any. Anything outside of the project (toolchains installed on the host,
+//! `/tmp`, ...) is left as is. This catches actions reading undeclared inputs, which would
+//! otherwise poison caches.
+//!
+//! On Linux, the command runs in a mount namespace set up by `bwrap` (bubblewrap), which must be
+//! installed and allowed to create unprivileged user namespaces. On macOS, it runs under
+//! `sandbox-exec`. Other platforms run actions unsandboxed.
+//!
+//! Actions can opt out with `ctx.actions.run(..., no_sandbox = True)`.
+//!
+//! When a sandboxed action fails, the paths mentioned in its stderr which exist in the project but
+//! were hidden from it are reported as its likely undeclared inputs. This is a heuristic: `bwrap`
+//! hides paths rather than denying accesses to them, so there is nothing to log, and
+//! `sandbox-exec` only logs its denials to the system log. Undeclared inputs which the action
+//! does not print are not reported, and the action may have failed for another reason.
+
+use std::collections::BTreeSet;
+
+use buck2_core::directory::DirectoryEntry;
+use buck2_core::directory::DirectoryIterator;
+use buck2_core::directory::FingerprintedDirectory;
+use buck2_core::fs::artifact_path_resolver::ArtifactFs;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_execute::execute::request::CommandExecutionInput;
+use buck2_execute::execute::request::CommandExecutionRequest;
+
+/// How many undeclared paths to list when a sandboxed action fails.
+const MAX_REPORTED_VIOLATIONS: usize = 20;
+
+/// The parts of the project a sandboxed action can access.
+pub(crate) struct LocalSandbox {
+    /// Paths the action can read.
+    inputs: BTreeSet<ProjectRelativePathBuf>,
+    /// Paths the action can read and write.
+    writable: BTreeSet<ProjectRelativePathBuf>,
+}
+
+impl LocalSandbox {
+    pub(crate) fn new(
+        request: &CommandExecutionRequest,
+        artifact_fs: &ArtifactFs,
+    ) -> anyhow::Result<Self> {
+        let mut inputs = BTreeSet::new();
+        for input in request.inputs() {
+            match input {
+                CommandExecutionInput::Artifact(group) => {
+                    for (artifact, value) in group.iter() {
+                        inputs.insert(artifact.resolve_path(artifact_fs)?);
+                        // Symlinked artifacts also need what they point to.
+                        if let Some(deps) = value.deps() {
+                            let mut walk = deps.fingerprinted_unordered_walk();
+                            while let Some((path, entry)) = walk.next() {
+                                if let DirectoryEntry::Leaf(..) = entry {
+                                    inputs.insert(ProjectRelativePathBuf::from(path.get()));
+                                }
+                            }
+                        }
+                    }
+                }
+                CommandExecutionInput::ActionMetadata(metadata) => {
+                    inputs.insert(
+                        artifact_fs
+                            .buck_out_path_resolver()
+                            .resolve_gen(&metadata.path),
+                    );
+                }
+            }
+        }
+
+        let mut writable = BTreeSet::new();
+        for output in request.outputs() {
+            let output = output.resolve(artifact_fs);
+            writable.insert(match output.path_to_create() {
+                Some(dir) => dir.to_owned(),
+                None => output.path,
+            });
+        }
+        if let Some(tmpdir) = request.custom_tmpdir() {
+            writable.insert(artifact_fs.buck_out_path_resolver().resolve_scratch(tmpdir));
+        }
+        if let Some(state) = request.incremental_state_dir() {
+            writable.insert(state.to_owned());
+        }
+
+        Ok(Self { inputs, writable })
+    }
+
+    #[cfg(test)]
+    pub(crate) fn testing_new(
+        inputs: BTreeSet<ProjectRelativePathBuf>,
+        writable: BTreeSet<ProjectRelativePathBuf>,
+    ) -> Self {
+        Self { inputs, writable }
+    }
+
+    /// Whether a sandboxed action can see `path`. The parents of accessible paths are visible
+    /// too, since the action needs to traverse them.
+    fn allows(&self, path: &ProjectRelativePath) -> bool {
+        self.inputs
+            .iter()
+            .chain(self.writable.iter())
+            .any(|allowed| path.starts_with(allowed) || allowed.starts_with(path))
+    }
+
+    /// The command line running `args` in the sandbox, or `None` if sandboxing is not supported
+    /// on this platform.
+    pub(crate) fn wrap(
+        &self,
+        root: &AbsNormPath,
+        working_directory: Option<&ProjectRelativePath>,
+        args: &[String],
+    ) -> Option<Vec<String>> {
+        if cfg!(target_os = "linux") {
+            Some(self.bwrap_args(root, working_directory, args))
+        } else if cfg!(target_os = "macos") {
+            Some(self.sandbox_exec_args(root, args))
+        } else {
+            None
+        }
+    }
+
+    fn bwrap_args(
+        &self,
+        root: &AbsNormPath,
+        working_directory: Option<&ProjectRelativePath>,
+        args: &[String],
+    ) -> Vec<String> {
+        let abs = |path: &ProjectRelativePath| root.join(path).to_string();
+        let cwd = match working_directory {
+            Some(dir) => abs(dir),
+            None => root.to_string(),
+        };
+
+        let mut wrapped = vec![
+            "bwrap".to_owned(),
+            "--die-with-parent".to_owned(),
+            "--dev-bind".to_owned(),
+            "/".to_owned(),
+            "/".to_owned(),
+            // Hide the project, then bring back what the action may access.
+            "--tmpfs".to_owned(),
+            root.to_string(),
+            "--dir".to_owned(),
+            cwd.clone(),
+        ];
+        for path in &self.writable {
+            wrapped.extend(["--bind".to_owned(), abs(path), abs(path)]);
+        }
+        // After the writable paths, so that inputs nested in output directories are read-only.
+        for path in &self.inputs {
+            wrapped.extend(["--ro-bind-try".to_owned(), abs(path), abs(path)]);
+        }
+        wrapped.extend(["--chdir".to_owned(), cwd, "--".to_owned()]);
+        wrapped.extend(args.iter().cloned());
+        wrapped
+    }
+
+    fn sandbox_exec_args(&self, root: &AbsNormPath, args: &[String]) -> Vec<String> {
+        let quote = |path: &str| format!("\"{}\"", path.replace('\\', "\\\\").replace('"', "\\\""));
+        let abs = |path: &ProjectRelativePath| quote(&root.join(path).to_string());
+
+        // Later rules take precedence over earlier ones.
+        let mut profile = format!(
+            "(version 1)(allow default)(deny file-read* file-write* (subpath {root}))\
+             (allow file-read-metadata (subpath {root}))",
+            root = quote(&root.to_string())
+        );
+        for path in &self.inputs {
+            profile.push_str(&format!("(allow file-read* (subpath {}))", abs(path)));
+        }
+        for path in &self.writable {
+            profile.push_str(&format!(
+                "(allow file-read* file-write* (subpath {}))",
+                abs(path)
+            ));
+        }
+
+        let mut wrapped = vec!["sandbox-exec".to_owned(), "-p".to_owned(), profile];
+        wrapped.extend(args.iter().cloned());
+        wrapped
+    }
+
+    /// The paths mentioned in the output of a failed sandboxed action which exist in the project
+    /// but were hidden from it: the likely undeclared inputs of the action. These are guessed from
+    /// the tokens of `stderr` which look like paths, see the module documentation.
+    pub(crate) fn violations(
+        &self,
+        root: &AbsNormPath,
+        working_directory: Option<&ProjectRelativePath>,
+        stderr: &[u8],
+        exists: impl Fn(&ProjectRelativePath) -> bool,
+    ) -> Vec<ProjectRelativePathBuf> {
+        let stderr = String::from_utf8_lossy(stderr);
+        let root_prefix = format!("{}/", root);
+
+        let mut violations = BTreeSet::new();
+        for token in stderr.split(|c: char| c.is_whitespace() || "'\"`:;,()[]<>".contains(c)) {
+            let path = match token.strip_prefix(&root_prefix) {
+                Some(path) => ProjectRelativePath::new(path).ok().map(|p| p.to_owned()),
+                // Only consider relative tokens which look like paths rather than words.
+                None if !token.contains(['/', '.']) || token.starts_with(['/', '-']) => None,
+                None => match working_directory {
+                    Some(dir) => dir.join_normalized(token).ok(),
+                    None => ProjectRelativePath::new(token).ok().map(|p| p.to_owned()),
+                },
+            };
+            if let Some(path) = path {
+                if !path.is_empty() && !self.allows(&path) && exists(&path) {
+                    violations.insert(path);
+                }
+            }
+            if violations.len() == MAX_REPORTED_VIOLATIONS {
+                break;
+            }
+        }
+        violations.into_iter().collect()
+    }
+}
+
+/// Describe the sandbox violations of a failed action, to be appended to its stderr.
+pub(crate) fn violations_report(violations: &[ProjectRelativePathBuf]) -> String {
+    let mut report = "\nThe action ran in a sandbox and failed. These paths exist in the project \
+        but were not declared as inputs, which may be why it failed (use `no_sandbox = True` to \
+        opt out of sandboxing):\n"
+        .to_owned();
+    for path in violations {
+        report.push_str(&format!("  {}\n", path));
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use buck2_core::buck_path::resolver::BuckPathResolver;
+    use buck2_core::cells::cell_root_path::CellRootPathBuf;
+    use buck2_core::cells::name::CellName;
+    use buck2_core::cells::CellResolver;
+    use buck2_core::fs::artifact_path_resolver::ArtifactFs;
+    use buck2_core::fs::buck_out_path::BuckOutPathResolver;
+    use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+    use buck2_core::fs::project::ProjectRootTemp;
+    use buck2_core::fs::project_rel_path::ProjectRelativePath;
+    use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+    use buck2_execute::digest_config::DigestConfig;
+    use buck2_execute::execute::request::CommandExecutionPaths;
+    use buck2_execute::execute::request::CommandExecutionRequest;
+    use dupe::Dupe;
+    use indexmap::IndexSet;
+
+    use crate::executors::sandbox::LocalSandbox;
+
+    fn sandbox() -> LocalSandbox {
+        let paths = |paths: &[&str]| -> BTreeSet<ProjectRelativePathBuf> {
+            paths
+                .iter()
+                .map(|p| ProjectRelativePath::unchecked_new(p).to_owned())
+                .collect()
+        };
+        LocalSandbox {
+            inputs: paths(&["src/main.c", "buck-out/v2/gen/root/lib/lib.h"]),
+            writable: paths(&["buck-out/v2/gen/root/app/__objects__"]),
+        }
+    }
+
+    #[cfg(unix)]
+    fn root() -> AbsNormPathBuf {
+        AbsNormPathBuf::from("/repo".to_owned()).unwrap()
+    }
+
+    #[test]
+    fn test_incremental_state_is_writable() -> anyhow::Result<()> {
+        let temp = ProjectRootTemp::new()?;
+        let artifact_fs = ArtifactFs::new(
+            BuckPathResolver::new(CellResolver::testing_with_name_and_path(
+                CellName::testing_new("cell"),
+                CellRootPathBuf::new(ProjectRelativePathBuf::unchecked_new("cell_path".into())),
+            )),
+            BuckOutPathResolver::new(ProjectRelativePathBuf::unchecked_new("buck-out/v2".into())),
+            temp.path().dupe(),
+        );
+        let state = ProjectRelativePathBuf::unchecked_new("buck-out/v2/incremental/state".into());
+        let request = CommandExecutionRequest::new(
+            Vec::new(),
+            CommandExecutionPaths::new(
+                Vec::new(),
+                IndexSet::new(),
+                &artifact_fs,
+                DigestConfig::testing_default(),
+            )?,
+            Default::default(),
+        )
+        .with_incremental_state_dir(state.clone());
+
+        let sandbox = LocalSandbox::new(&request, &artifact_fs)?;
+        assert_eq!(sandbox.writable, BTreeSet::from([state]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_allows() {
+        let sandbox = sandbox();
+        assert!(sandbox.allows(ProjectRelativePath::unchecked_new("src/main.c")));
+        assert!(sandbox.allows(ProjectRelativePath::unchecked_new("src")));
+        assert!(sandbox.allows(ProjectRelativePath::unchecked_new(
+            "buck-out/v2/gen/root/app/__objects__/main.o"
+        )));
+        assert!(!sandbox.allows(ProjectRelativePath::unchecked_new("src/util.h")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_violations() {
+        let sandbox = sandbox();
+        let stderr = b"src/main.c:1:10: fatal error: 'src/util.h' file not found\n\
+            cat: /repo/third-party/data.txt: No such file or directory\n\
+            error: missing src/gone.h\n";
+        let violations = sandbox.violations(&root(), None, stderr, |path| {
+            ["src/main.c", "src/util.h", "third-party/data.txt"].contains(&path.as_str())
+        });
+        assert_eq!(
+            violations,
+            vec![
+                ProjectRelativePath::unchecked_new("src/util.h").to_owned(),
+                ProjectRelativePath::unchecked_new("third-party/data.txt").to_owned(),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sandbox_exec_args() {
+        let args = sandbox().sandbox_exec_args(&root(), &["cc".to_owned(), "main.c".to_owned()]);
+        assert_eq!(args.len(), 5);
+        assert_eq!(args[0], "sandbox-exec");
+        assert_eq!(args[1], "-p");
+        assert_eq!(
+            args[2],
+            "(version 1)(allow default)(deny file-read* file-write* (subpath \"/repo\"))\
+             (allow file-read-metadata (subpath \"/repo\"))\
+             (allow file-read* (subpath \"/repo/buck-out/v2/gen/root/lib/lib.h\"))\
+             (allow file-read* (subpath \"/repo/src/main.c\"))\
+             (allow file-read* file-write* \
+             (subpath \"/repo/buck-out/v2/gen/root/app/__objects__\"))"
+        );
+        assert_eq!(args[3..], ["cc", "main.c"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sandbox_exec_args_quotes_paths() {
+        let sandbox = LocalSandbox {
+            inputs: BTreeSet::from([ProjectRelativePath::unchecked_new("a\"b").to_owned()]),
+            writable: BTreeSet::new(),
+        };
+        let args = sandbox.sandbox_exec_args(&root(), &[]);
+        assert!(
+            args[2].ends_with("(allow file-read* (subpath \"/repo/a\\\"b\"))"),
+            "{}",
+            args[2]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_bwrap_args() {
+        let args = sandbox().bwrap_args(
+            &root(),
+            Some(ProjectRelativePath::unchecked_new("src")),
+            &["cc".to_owned(), "main.c".to_owned()],
+        );
+        assert_eq!(
+            args.join(" "),
+            "bwrap --die-with-parent --dev-bind / / --tmpfs /repo --dir /repo/src \
+             --bind /repo/buck-out/v2/gen/root/app/__objects__ \
+             /repo/buck-out/v2/gen/root/app/__objects__ \
+             --ro-bind-try /repo/buck-out/v2/gen/root/lib/lib.h \
+             /repo/buck-out/v2/gen/root/lib/lib.h \
+             --ro-bind-try /repo/src/main.c /repo/src/main.c \
+             --chdir /repo/src -- cc main.c"
+        );
+    }
+}
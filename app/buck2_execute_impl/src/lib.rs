@@ -12,6 +12,8 @@
 #![feature(try_trait_v2)]
 
 pub mod executors;
+pub mod local_disk_cache;
 pub mod low_pass_filter;
 pub mod materializers;
 pub mod re;
+pub mod running_local_actions;
@@ -0,0 +1,654 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! A content-addressed cache of the results of local actions, which lives outside of `buck-out`
+//! so that it is shared by all the checkouts and isolation dirs on a machine.
+//!
+//! The cache directory contains:
+//! - `cas/`: the contents of output files, named after their digest.
+//! - `ac/`: one entry per action digest, listing the outputs of the action and the blobs they
+//!   are made of, as well as the std streams of the action.
+//! - `tmp/`: files being written, which are renamed into place once complete.
+//!
+//! Once the cache grows past its size limit, the least recently used entries are evicted, along
+//! with the blobs that no remaining entry references. Several daemons may use the same cache
+//! concurrently: an entry whose blobs were evicted by another daemon is a cache miss.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use anyhow::Context as _;
+use buck2_common::file_ops::FileDigest;
+use buck2_common::file_ops::FileDigestConfig;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::file_name::FileName;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::fs::project::ProjectRoot;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_execute::execute::action_digest::ActionDigest;
+use faccess::PathExt;
+use parking_lot::Mutex;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Garbage collection shrinks the cache to this percentage of its size limit, so that it doesn't
+/// need to run again on the next store.
+const GC_TARGET_PERCENT: u64 = 75;
+
+/// Temporary files older than this were left behind by an interrupted write.
+const STALE_TMP_FILE_AGE: Duration = Duration::from_secs(3600);
+
+/// Counters of how the cache was used by this daemon.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LocalDiskCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub stores: u64,
+    pub evictions: u64,
+}
+
+/// A file, directory or symlink in the outputs of an action. Paths are relative to the project
+/// root, and directories are listed before their contents.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum CachedEntry {
+    File {
+        path: String,
+        blob: String,
+        executable: bool,
+    },
+    Dir {
+        path: String,
+    },
+    Symlink {
+        path: String,
+        target: String,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    entries: Vec<CachedEntry>,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+}
+
+impl CacheEntry {
+    fn blobs(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().filter_map(|entry| match entry {
+            CachedEntry::File { blob, .. } => Some(blob.as_str()),
+            _ => None,
+        })
+    }
+}
+
+/// An action result found in the cache, which can be restored with `LocalDiskCache::restore`.
+pub struct CachedAction {
+    name: String,
+    entry: CacheEntry,
+    /// The serialized entry, rewritten when restoring it to mark it as recently used.
+    bytes: Vec<u8>,
+}
+
+impl CachedAction {
+    /// The stdout and stderr of the action.
+    pub fn into_std_streams(self) -> (Vec<u8>, Vec<u8>) {
+        (self.entry.stdout, self.entry.stderr)
+    }
+}
+
+pub struct LocalDiskCache {
+    ac_dir: AbsNormPathBuf,
+    cas_dir: AbsNormPathBuf,
+    tmp_dir: AbsNormPathBuf,
+    max_bytes: u64,
+    /// Approximate size of the cache, computed on the first store.
+    size: Mutex<Option<u64>>,
+    /// Held while collecting garbage, so that concurrent stores don't all collect at once.
+    gc_lock: Mutex<()>,
+    next_tmp_file: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    stores: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl LocalDiskCache {
+    pub fn new(root: &AbsNormPath, max_bytes: u64) -> anyhow::Result<Self> {
+        let ac_dir = root.join(ForwardRelativePath::unchecked_new("ac"));
+        let cas_dir = root.join(ForwardRelativePath::unchecked_new("cas"));
+        let tmp_dir = root.join(ForwardRelativePath::unchecked_new("tmp"));
+        for dir in [&ac_dir, &cas_dir, &tmp_dir] {
+            fs_util::create_dir_all(dir)
+                .with_context(|| format!("Error creating local cache directory `{}`", dir))?;
+        }
+
+        Ok(Self {
+            ac_dir,
+            cas_dir,
+            tmp_dir,
+            max_bytes,
+            size: Mutex::new(None),
+            gc_lock: Mutex::new(()),
+            next_tmp_file: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            stores: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        })
+    }
+
+    pub fn stats(&self) -> LocalDiskCacheStats {
+        LocalDiskCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            stores: self.stores.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+        }
+    }
+
+    fn entry_name(digest: &ActionDigest) -> String {
+        format!("{}_{}", digest.raw_digest(), digest.size())
+    }
+
+    fn entry_path(&self, name: &str) -> AbsNormPathBuf {
+        self.ac_dir.join(ForwardRelativePath::unchecked_new(name))
+    }
+
+    fn blob_path(&self, blob: &str) -> AbsNormPathBuf {
+        self.cas_dir.join(ForwardRelativePath::unchecked_new(blob))
+    }
+
+    /// Write a file atomically, so that other daemons never observe partial contents.
+    fn write_atomically(
+        &self,
+        path: &AbsNormPathBuf,
+        write: impl FnOnce(&AbsNormPathBuf) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        let tmp = self
+            .tmp_dir
+            .join(ForwardRelativePath::unchecked_new(&format!(
+                "{}-{}",
+                std::process::id(),
+                self.next_tmp_file.fetch_add(1, Ordering::Relaxed)
+            )));
+        let res = write(&tmp).and_then(|()| fs_util::rename(&tmp, path));
+        if res.is_err() {
+            let _ignored = fs_util::remove_file(&tmp);
+        }
+        res
+    }
+
+    /// Find the result of an action. All the blobs of a result which is returned exist.
+    pub fn lookup(&self, digest: &ActionDigest) -> anyhow::Result<Option<CachedAction>> {
+        let name = Self::entry_name(digest);
+        let path = self.entry_path(&name);
+
+        if !path.exists() {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return Ok(None);
+        }
+        let bytes = match fs_util::read(&path) {
+            Ok(bytes) => bytes,
+            // The entry was evicted by another daemon since we checked it exists.
+            Err(_) if !path.exists() => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                return Ok(None);
+            }
+            Err(e) => return Err(e),
+        };
+        let entry: CacheEntry = serde_json::from_slice(&bytes)
+            .with_context(|| format!("Error parsing local cache entry `{}`", path))?;
+
+        for blob in entry.blobs() {
+            if !self.blob_path(blob).exists() {
+                // The blob was evicted, so this entry can't be used anymore.
+                remove_cache_file(&path)?;
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                return Ok(None);
+            }
+        }
+
+        Ok(Some(CachedAction { name, entry, bytes }))
+    }
+
+    /// Write the outputs of a cached action to disk. The output paths must have been cleaned up.
+    /// A failure to restore (e.g. because another daemon evicted blobs of the entry after it was
+    /// looked up) counts as a miss, and may leave some of the outputs on disk.
+    pub fn restore(&self, cached: &CachedAction, fs: &ProjectRoot) -> anyhow::Result<()> {
+        if let Err(e) = self.restore_outputs(cached, fs) {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return Err(e);
+        }
+
+        // Rewrite the entry to mark it as recently used.
+        let path = self.entry_path(&cached.name);
+        self.write_atomically(&path, |tmp| fs_util::write(tmp, &cached.bytes))?;
+
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn restore_outputs(&self, cached: &CachedAction, fs: &ProjectRoot) -> anyhow::Result<()> {
+        for entry in &cached.entry.entries {
+            match entry {
+                CachedEntry::Dir { path } => {
+                    fs_util::create_dir_all(fs.resolve(ProjectRelativePath::new(path)?))?;
+                }
+                CachedEntry::File {
+                    path,
+                    blob,
+                    executable,
+                } => {
+                    let dest = fs.resolve(ProjectRelativePath::new(path)?);
+                    if let Some(parent) = dest.parent() {
+                        fs_util::create_dir_all(parent)?;
+                    }
+                    fs_util::copy(self.blob_path(blob), &dest)?;
+                    if *executable {
+                        fs_util::set_executable(&dest)?;
+                    }
+                }
+                CachedEntry::Symlink { path, target } => {
+                    let dest = fs.resolve(ProjectRelativePath::new(path)?);
+                    if let Some(parent) = dest.parent() {
+                        fs_util::create_dir_all(parent)?;
+                    }
+                    fs_util::symlink(target, &dest)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Store the outputs of an action, which must be on disk. Actions with outputs that can't be
+    /// shared between checkouts (i.e. absolute symlinks) are not stored.
+    pub fn store(
+        &self,
+        digest: &ActionDigest,
+        fs: &ProjectRoot,
+        outputs: &[ProjectRelativePathBuf],
+        stdout: &[u8],
+        stderr: &[u8],
+        digest_config: FileDigestConfig,
+    ) -> anyhow::Result<()> {
+        let mut entries = Vec::new();
+        let mut files = Vec::new();
+        for output in outputs {
+            if !collect_entries(fs, output, digest_config, &mut entries, &mut files)? {
+                return Ok(());
+            }
+        }
+
+        let mut added_bytes = 0;
+        for (blob, file) in files {
+            let blob_path = self.blob_path(&blob);
+            if blob_path.exists() {
+                continue;
+            }
+            self.write_atomically(&blob_path, |tmp| {
+                added_bytes += fs_util::copy(&file, tmp)?;
+                // Blobs are shared by files with the same contents, so the executable bit is
+                // stored in the entries.
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    fs_util::set_permissions(tmp, std::fs::Permissions::from_mode(0o644))?;
+                }
+                Ok(())
+            })?;
+        }
+
+        let bytes = serde_json::to_vec(&CacheEntry {
+            entries,
+            stdout: stdout.to_vec(),
+            stderr: stderr.to_vec(),
+        })?;
+        added_bytes += bytes.len() as u64;
+        let path = self.entry_path(&Self::entry_name(digest));
+        self.write_atomically(&path, |tmp| fs_util::write(tmp, &bytes))?;
+        self.stores.fetch_add(1, Ordering::Relaxed);
+
+        let new_size = {
+            let mut size = self.size.lock();
+            let new_size = match *size {
+                Some(size) => size + added_bytes,
+                None => self.disk_size()?,
+            };
+            *size = Some(new_size);
+            new_size
+        };
+
+        // Stores keep going while the garbage is collected, and if another store is already
+        // collecting there is nothing left to do.
+        if new_size > self.max_bytes {
+            if let Some(_gc) = self.gc_lock.try_lock() {
+                let collected = self.gc()?;
+                *self.size.lock() = Some(collected);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn disk_size(&self) -> anyhow::Result<u64> {
+        let mut size = 0;
+        for dir in [&self.ac_dir, &self.cas_dir] {
+            for file in fs_util::read_dir(dir)? {
+                size += file?.metadata()?.len();
+            }
+        }
+        Ok(size)
+    }
+
+    /// Evict the least recently used entries until the cache fits in its target size, and delete
+    /// the blobs which are not used anymore. Returns the size of the cache after collection.
+    fn gc(&self) -> anyhow::Result<u64> {
+        let target = self.max_bytes / 100 * GC_TARGET_PERCENT;
+
+        let mut blob_sizes = HashMap::new();
+        for file in fs_util::read_dir(&self.cas_dir)? {
+            let file = file?;
+            if let Some(name) = file.file_name().to_str() {
+                blob_sizes.insert(name.to_owned(), file.metadata()?.len());
+            }
+        }
+
+        let mut entries = Vec::new();
+        for file in fs_util::read_dir(&self.ac_dir)? {
+            let file = file?;
+            let metadata = file.metadata()?;
+            entries.push((metadata.modified()?, metadata.len(), file.path()));
+        }
+        // Most recently used first.
+        entries.sort_by(|a, b| b.0.cmp(&a.0));
+
+        let mut size = 0;
+        let mut live_blobs = HashSet::new();
+        let mut full = false;
+        let mut evictions = 0;
+        for (_, entry_size, path) in entries {
+            let entry = fs_util::read(&path)
+                .and_then(|bytes| Ok(serde_json::from_slice::<CacheEntry>(&bytes)?));
+            let entry = match entry {
+                Ok(entry) if !full => entry,
+                _ => {
+                    remove_cache_file(&path)?;
+                    evictions += 1;
+                    continue;
+                }
+            };
+
+            let new_blobs: HashSet<&str> = entry
+                .blobs()
+                .filter(|blob| !live_blobs.contains(*blob))
+                .collect();
+            let entry_size = entry_size
+                + new_blobs
+                    .iter()
+                    .map(|blob| blob_sizes.get(*blob).copied().unwrap_or_default())
+                    .sum::<u64>();
+
+            if size + entry_size > target {
+                // Everything older than this entry is evicted too.
+                full = true;
+                remove_cache_file(&path)?;
+                evictions += 1;
+                continue;
+            }
+            size += entry_size;
+            live_blobs.extend(new_blobs.into_iter().map(|blob| blob.to_owned()));
+        }
+
+        for blob in blob_sizes.keys() {
+            if !live_blobs.contains(blob) {
+                remove_cache_file(self.blob_path(blob))?;
+            }
+        }
+
+        let now = SystemTime::now();
+        for file in fs_util::read_dir(&self.tmp_dir)? {
+            let file = file?;
+            let modified = file.metadata()?.modified()?;
+            if now.duration_since(modified).unwrap_or_default() > STALE_TMP_FILE_AGE {
+                remove_cache_file(file.path())?;
+            }
+        }
+
+        self.evictions.fetch_add(evictions, Ordering::Relaxed);
+        Ok(size)
+    }
+}
+
+/// Remove a file of the cache, which may have been removed already by another daemon.
+fn remove_cache_file<P: AsRef<Path>>(path: P) -> anyhow::Result<()> {
+    match std::fs::remove_file(path.as_ref()) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        res => res.with_context(|| {
+            format!(
+                "Error removing local cache file `{}`",
+                path.as_ref().display()
+            )
+        }),
+    }
+}
+
+/// List the entries of an output on disk, and the files whose contents need storing. Returns
+/// false if the output can't be cached.
+fn collect_entries(
+    fs: &ProjectRoot,
+    path: &ProjectRelativePath,
+    digest_config: FileDigestConfig,
+    entries: &mut Vec<CachedEntry>,
+    files: &mut Vec<(String, AbsNormPathBuf)>,
+) -> anyhow::Result<bool> {
+    let abs_path = fs.resolve(path);
+    let metadata = match fs_util::symlink_metadata_if_exists(&abs_path)? {
+        Some(metadata) => metadata,
+        None => return Ok(true),
+    };
+
+    if metadata.file_type().is_symlink() {
+        let target = fs_util::read_link(&abs_path)?;
+        if target.is_absolute() {
+            return Ok(false);
+        }
+        let target = target
+            .to_str()
+            .with_context(|| format!("Symlink target of `{}` is not UTF-8", path))?;
+        entries.push(CachedEntry::Symlink {
+            path: path.to_string(),
+            target: target.to_owned(),
+        });
+    } else if metadata.is_file() {
+        let digest = FileDigest::from_file(&abs_path, digest_config)?;
+        let blob = format!("{}_{}", digest.raw_digest(), digest.size());
+        entries.push(CachedEntry::File {
+            path: path.to_string(),
+            blob: blob.clone(),
+            executable: abs_path.executable(),
+        });
+        files.push((blob, abs_path));
+    } else if metadata.is_dir() {
+        entries.push(CachedEntry::Dir {
+            path: path.to_string(),
+        });
+        let mut children = Vec::new();
+        for child in fs_util::read_dir(&abs_path)? {
+            let name = child?.file_name();
+            let name = name
+                .to_str()
+                .with_context(|| format!("File name in `{}` is not UTF-8", path))?;
+            children.push(path.join(FileName::new(name)?));
+        }
+        children.sort();
+        for child in children {
+            if !collect_entries(fs, &child, digest_config, entries, files)? {
+                return Ok(false);
+            }
+        }
+    } else {
+        return Ok(false);
+    }
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use buck2_common::cas_digest::CasDigestConfig;
+    use buck2_common::file_ops::FileDigestConfig;
+    use buck2_core::fs::fs_util;
+    use buck2_core::fs::project::ProjectRootTemp;
+    use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+    use buck2_execute::execute::action_digest::ActionDigest;
+
+    use crate::local_disk_cache::LocalDiskCache;
+
+    fn digest_config() -> FileDigestConfig {
+        FileDigestConfig::build(CasDigestConfig::testing_default())
+    }
+
+    fn output(path: &str) -> ProjectRelativePathBuf {
+        ProjectRelativePathBuf::unchecked_new(path.to_owned())
+    }
+
+    #[test]
+    fn test_store_and_restore() -> anyhow::Result<()> {
+        let cache_dir = ProjectRootTemp::new()?;
+        let cache = LocalDiskCache::new(cache_dir.path().root(), 1 << 30)?;
+
+        let checkout = ProjectRootTemp::new()?;
+        checkout.write_file("out/file", "file");
+        checkout.write_file("out/dir/a", "a");
+        checkout.write_file("out/dir/b", "a");
+
+        let digest = ActionDigest::new_sha1([1; 20], 10);
+        assert!(cache.lookup(&digest)?.is_none());
+
+        let outputs = [output("out/file"), output("out/dir")];
+        cache.store(
+            &digest,
+            checkout.path(),
+            &outputs,
+            b"stdout",
+            b"",
+            digest_config(),
+        )?;
+
+        // The cache is shared with another checkout.
+        let other = ProjectRootTemp::new()?;
+        let cached = cache.lookup(&digest)?.unwrap();
+        cache.restore(&cached, other.path())?;
+        assert_eq!(
+            "file",
+            fs_util::read_to_string(other.path().resolve("out/file"))?
+        );
+        assert_eq!(
+            "a",
+            fs_util::read_to_string(other.path().resolve("out/dir/a"))?
+        );
+        assert_eq!(
+            "a",
+            fs_util::read_to_string(other.path().resolve("out/dir/b"))?
+        );
+        assert_eq!((b"stdout".to_vec(), Vec::new()), cached.into_std_streams());
+
+        let stats = cache.stats();
+        assert_eq!((1, 1, 1), (stats.hits, stats.misses, stats.stores));
+        Ok(())
+    }
+
+    #[test]
+    fn test_gc_evicts_least_recently_used() -> anyhow::Result<()> {
+        let cache_dir = ProjectRootTemp::new()?;
+        // Only fits the outputs of one action.
+        let cache = LocalDiskCache::new(cache_dir.path().root(), 3000)?;
+
+        let checkout = ProjectRootTemp::new()?;
+        let first = ActionDigest::new_sha1([1; 20], 10);
+        let second = ActionDigest::new_sha1([2; 20], 10);
+
+        checkout.write_file("out", &"1".repeat(1500));
+        cache.store(
+            &first,
+            checkout.path(),
+            &[output("out")],
+            b"",
+            b"",
+            digest_config(),
+        )?;
+        // Entries are ordered by modification time, make sure they don't have the same one.
+        std::thread::sleep(Duration::from_millis(100));
+        checkout.write_file("out", &"2".repeat(1500));
+        cache.store(
+            &second,
+            checkout.path(),
+            &[output("out")],
+            b"",
+            b"",
+            digest_config(),
+        )?;
+
+        assert!(cache.lookup(&first)?.is_none());
+        let cached = cache.lookup(&second)?.unwrap();
+        fs_util::remove_file(checkout.path().resolve("out"))?;
+        cache.restore(&cached, checkout.path())?;
+        assert_eq!(
+            "2".repeat(1500),
+            fs_util::read_to_string(checkout.path().resolve("out"))?
+        );
+        assert_eq!(1, cache.stats().evictions);
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_after_eviction_is_a_miss() -> anyhow::Result<()> {
+        let cache_dir = ProjectRootTemp::new()?;
+        let cache = LocalDiskCache::new(cache_dir.path().root(), 1 << 30)?;
+
+        let checkout = ProjectRootTemp::new()?;
+        checkout.write_file("out", "out");
+        let digest = ActionDigest::new_sha1([1; 20], 10);
+        cache.store(
+            &digest,
+            checkout.path(),
+            &[output("out")],
+            b"",
+            b"",
+            digest_config(),
+        )?;
+
+        let cached = cache.lookup(&digest)?.unwrap();
+        // Another daemon evicts the blobs after the lookup.
+        fs_util::remove_dir_all(cache_dir.path().resolve("cas"))?;
+        fs_util::create_dir_all(cache_dir.path().resolve("cas"))?;
+
+        let other = ProjectRootTemp::new()?;
+        assert!(cache.restore(&cached, other.path()).is_err());
+        // The next lookup finds the blob missing and drops the entry.
+        assert!(cache.lookup(&digest)?.is_none());
+        assert!(cache.lookup(&digest)?.is_none());
+
+        let stats = cache.stats();
+        assert_eq!((0, 3, 1), (stats.hits, stats.misses, stats.stores));
+        Ok(())
+    }
+}
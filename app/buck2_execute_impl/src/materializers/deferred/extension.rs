@@ -15,14 +15,27 @@ use std::sync::Arc;
 
 use anyhow::Context as _;
 use async_trait::async_trait;
+use buck2_common::executor_config::RemoteExecutorUseCase;
+use buck2_common::file_ops::FileDigestConfig;
 use buck2_core::directory::DirectoryEntry;
 use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use buck2_events::dispatch::get_dispatcher;
+use buck2_events::dispatch::EventDispatcher;
+use buck2_execute::artifact_value::ArtifactValue;
+use buck2_execute::digest_config::DigestConfig;
+use buck2_execute::directory::ActionDirectoryEntry;
 use buck2_execute::directory::ActionDirectoryMember;
+use buck2_execute::directory::ActionSharedDirectory;
+use buck2_execute::directory::INTERNER;
+use buck2_execute::entry::build_entry_from_disk;
+use buck2_execute::materialize::materializer::CasDownloadInfo;
 use buck2_execute::materialize::materializer::DeferredMaterializerEntry;
 use buck2_execute::materialize::materializer::DeferredMaterializerExtensions;
 use buck2_execute::materialize::materializer::DeferredMaterializerSubscription;
+use buck2_execute::materialize::materializer::MaterializationError;
+use buck2_execute::materialize::materializer::MaterializerInconsistency;
 use chrono::DateTime;
 use chrono::Duration;
 use chrono::TimeZone;
@@ -31,6 +44,7 @@ use derivative::Derivative;
 use dupe::Dupe;
 use futures::stream::BoxStream;
 use futures::stream::StreamExt;
+use thiserror::Error;
 use tokio::sync::mpsc;
 use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::oneshot;
@@ -43,6 +57,8 @@ use crate::materializers::deferred::io_handler::create_ttl_refresh;
 use crate::materializers::deferred::subscriptions::MaterializerSubscriptionOperation;
 use crate::materializers::deferred::ArtifactMaterializationMethod;
 use crate::materializers::deferred::ArtifactMaterializationStage;
+use crate::materializers::deferred::ArtifactMetadata;
+use crate::materializers::deferred::ArtifactTree;
 use crate::materializers::deferred::DefaultIoHandler;
 use crate::materializers::deferred::DeferredMaterializer;
 use crate::materializers::deferred::DeferredMaterializerCommandProcessor;
@@ -169,6 +185,183 @@ impl ExtensionCommand<DefaultIoHandler> for Fsck {
     }
 }
 
+#[derive(Debug, Error)]
+enum VerifyError {
+    #[error("Path does not exist on disk")]
+    Missing,
+    #[error("Expected `{expected}`, found `{actual}` on disk")]
+    Mismatch { expected: String, actual: String },
+}
+
+/// Check that what is on disk at `path` matches a materialized artifact. Files are hashed and
+/// directories are fingerprinted recursively, so this checks their contents as well.
+fn verify_materialized(
+    path: AbsNormPathBuf,
+    metadata: &ArtifactMetadata,
+    digest_config: DigestConfig,
+) -> anyhow::Result<()> {
+    let disk_entry = build_entry_from_disk(
+        path,
+        FileDigestConfig::build(digest_config.cas_digest_config()),
+    )?
+    .ok_or(VerifyError::Missing)?
+    .map_dir(|dir| {
+        dir.fingerprint(digest_config.as_directory_serializer())
+            .shared(&*INTERNER)
+    });
+
+    if metadata.matches_entry(&disk_entry) {
+        return Ok(());
+    }
+
+    Err(VerifyError::Mismatch {
+        expected: metadata.0.to_string(),
+        actual: ArtifactMetadata::new(&disk_entry).0.to_string(),
+    }
+    .into())
+}
+
+/// A materialized artifact which doesn't match what is on disk.
+struct Mismatch {
+    path: ProjectRelativePathBuf,
+    metadata: ArtifactMetadata,
+    deps: Option<ActionSharedDirectory>,
+    active: bool,
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+struct Verify {
+    repair: bool,
+    #[derivative(Debug = "ignore")]
+    dispatcher: EventDispatcher,
+    /// This is for debug commands so we use an unbounded channel to avoid locking up the
+    /// materializer command thread.
+    #[derivative(Debug = "ignore")]
+    sender: UnboundedSender<MaterializerInconsistency>,
+}
+
+impl Verify {
+    /// Drop a mismatching artifact from the state and delete it from disk. Files and symlinks
+    /// used by the running daemon are declared again and materialized right away, fetching files
+    /// from the CAS, since nothing would declare them again otherwise. Only the fingerprint of
+    /// materialized directories is kept, so those can't be materialized again here.
+    fn repair(
+        &self,
+        processor: &mut DeferredMaterializerCommandProcessor<DefaultIoHandler>,
+        mismatch: &Mismatch,
+    ) -> anyhow::Result<Option<BoxStream<'static, Result<(), MaterializationError>>>> {
+        // Drop the entry from the state first, so there is never a moment where the
+        // state claims an artifact which we are deleting.
+        processor.tree.invalidate_paths_and_collect_futures(
+            vec![mismatch.path.clone()],
+            processor.sqlite_db.as_mut(),
+        )?;
+
+        let leaf = match &mismatch.metadata.0 {
+            DirectoryEntry::Leaf(leaf) if mismatch.active => leaf.dupe(),
+            _ => {
+                fs_util::remove_all(processor.io.fs.resolve(&mismatch.path))?;
+                return Ok(None);
+            }
+        };
+
+        // Declaring cleans up the path before materializing the artifact.
+        processor.declare(
+            &mismatch.path,
+            ArtifactValue::new(DirectoryEntry::Leaf(leaf), mismatch.deps.dupe()),
+            Box::new(ArtifactMaterializationMethod::CasDownload {
+                info: Arc::new(CasDownloadInfo::new_declared(
+                    RemoteExecutorUseCase::buck2_default(),
+                )),
+            }),
+        );
+        Ok(Some(processor.materialize_many_artifacts(
+            vec![mismatch.path.clone()],
+            self.dispatcher.dupe(),
+        )))
+    }
+}
+
+impl ExtensionCommand<DefaultIoHandler> for Verify {
+    fn execute(
+        self: Box<Self>,
+        processor: &mut DeferredMaterializerCommandProcessor<DefaultIoHandler>,
+    ) {
+        // Like `Fsck`, this blocks the command thread so nothing gets materialized or deleted
+        // while we check.
+        let mut mismatches = Vec::new();
+        for (path, data) in processor.tree.iter_with_paths() {
+            let (metadata, active) = match &data.stage {
+                ArtifactMaterializationStage::Declared { .. } => continue,
+                ArtifactMaterializationStage::Materialized {
+                    metadata, active, ..
+                } => (metadata, *active),
+            };
+
+            let path = ProjectRelativePathBuf::from(path);
+            if let Err(error) = verify_materialized(
+                processor.io.fs.resolve(&path),
+                metadata,
+                processor.digest_config,
+            ) {
+                let mismatch = Mismatch {
+                    path,
+                    metadata: metadata.dupe(),
+                    deps: data.deps.dupe(),
+                    active,
+                };
+                mismatches.push((mismatch, error));
+            }
+        }
+
+        for (mismatch, error) in mismatches {
+            let mut inconsistency = MaterializerInconsistency {
+                path: mismatch.path.clone(),
+                error,
+                active: mismatch.active,
+                repaired: false,
+                rematerialized: false,
+            };
+
+            if self.repair {
+                match self.repair(processor, &mismatch) {
+                    Ok(None) => inconsistency.repaired = true,
+                    Ok(Some(mut materialization)) => {
+                        // Report the artifact once it is materialized again. The stream returned
+                        // to the client ends when all the senders are dropped.
+                        let sender = self.sender.clone();
+                        processor.rt.spawn(async move {
+                            match materialization.next().await.transpose() {
+                                Ok(_) => {
+                                    inconsistency.repaired = true;
+                                    inconsistency.rematerialized = true;
+                                }
+                                Err(e) => {
+                                    inconsistency.error = inconsistency
+                                        .error
+                                        .context(format!("Failed to materialize again: {:#}", e))
+                                }
+                            }
+                            let _ignored = sender.send(inconsistency);
+                        });
+                        continue;
+                    }
+                    Err(e) => {
+                        inconsistency.error = inconsistency
+                            .error
+                            .context(format!("Failed to repair: {:#}", e))
+                    }
+                }
+            }
+
+            if self.sender.send(inconsistency).is_err() {
+                break;
+            }
+        }
+    }
+}
+
 #[derive(Derivative)]
 #[derivative(Debug)]
 struct RefreshTtls {
@@ -192,6 +385,61 @@ impl ExtensionCommand<DefaultIoHandler> for RefreshTtls {
     }
 }
 
+/// What the materializer has in memory for an artifact.
+#[derive(Debug)]
+pub(super) enum ArtifactEntry {
+    Entry(ActionDirectoryEntry<ActionSharedDirectory>),
+    /// A directory which was materialized. Only its fingerprint is kept.
+    MaterializedDir,
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+struct GetArtifactEntries {
+    paths: Vec<ProjectRelativePathBuf>,
+    sender: Sender<Vec<Option<ArtifactEntry>>>,
+}
+
+/// The artifacts at exactly these paths, `None` for paths which are not artifacts (or are inside
+/// one).
+pub(super) fn artifact_entries(
+    tree: &ArtifactTree,
+    paths: &[ProjectRelativePathBuf],
+) -> Vec<Option<ArtifactEntry>> {
+    paths
+        .iter()
+        .map(|path| {
+            let mut path_iter = path.iter();
+            let data = tree.prefix_get(&mut path_iter)?;
+            if path_iter.next().is_some() {
+                return None;
+            }
+            Some(match &data.stage {
+                ArtifactMaterializationStage::Declared { entry, .. } => {
+                    ArtifactEntry::Entry(entry.dupe())
+                }
+                ArtifactMaterializationStage::Materialized { metadata, .. } => match &metadata.0 {
+                    DirectoryEntry::Dir(_) => ArtifactEntry::MaterializedDir,
+                    DirectoryEntry::Leaf(leaf) => {
+                        ArtifactEntry::Entry(DirectoryEntry::Leaf(leaf.dupe()))
+                    }
+                },
+            })
+        })
+        .collect()
+}
+
+impl ExtensionCommand<DefaultIoHandler> for GetArtifactEntries {
+    fn execute(
+        self: Box<Self>,
+        processor: &mut DeferredMaterializerCommandProcessor<DefaultIoHandler>,
+    ) {
+        let _ignored = self
+            .sender
+            .send(artifact_entries(&processor.tree, &self.paths));
+    }
+}
+
 #[derive(Derivative)]
 #[derivative(Debug)]
 struct GetTtlRefreshLog {
@@ -303,6 +551,20 @@ impl DeferredMaterializerExtensions for DeferredMaterializer {
         Ok(UnboundedReceiverStream::new(receiver).boxed())
     }
 
+    fn verify(
+        &self,
+        repair: bool,
+    ) -> anyhow::Result<BoxStream<'static, MaterializerInconsistency>> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.command_sender
+            .send(MaterializerCommand::Extension(Box::new(Verify {
+                repair,
+                dispatcher: get_dispatcher(),
+                sender,
+            }) as _))?;
+        Ok(UnboundedReceiverStream::new(receiver).boxed())
+    }
+
     async fn refresh_ttls(&self, min_ttl: i64) -> anyhow::Result<()> {
         let (sender, receiver) = oneshot::channel();
         self.command_sender
@@ -319,6 +581,47 @@ impl DeferredMaterializerExtensions for DeferredMaterializer {
         Ok(())
     }
 
+    async fn get_artifact_entries(
+        &self,
+        paths: Vec<ProjectRelativePathBuf>,
+    ) -> anyhow::Result<Vec<Option<ActionDirectoryEntry<ActionSharedDirectory>>>> {
+        let (sender, receiver) = oneshot::channel();
+        self.command_sender.send(MaterializerCommand::Extension(
+            Box::new(GetArtifactEntries {
+                paths: paths.clone(),
+                sender,
+            }) as _,
+        ))?;
+        let entries = receiver.await.context("No response from materializer")?;
+
+        let mut res = Vec::with_capacity(entries.len());
+        for (path, entry) in paths.into_iter().zip(entries) {
+            res.push(match entry {
+                None => None,
+                Some(ArtifactEntry::Entry(entry)) => Some(entry),
+                Some(ArtifactEntry::MaterializedDir) => {
+                    let abs_path = self.fs.resolve(&path);
+                    let digest_config = self.digest_config;
+                    self.io_executor
+                        .execute_io_inline(|| {
+                            build_entry_from_disk(
+                                abs_path,
+                                FileDigestConfig::build(digest_config.cas_digest_config()),
+                            )
+                        })
+                        .await?
+                        .map(|entry| {
+                            entry.map_dir(|dir| {
+                                dir.fingerprint(digest_config.as_directory_serializer())
+                                    .shared(&*INTERNER)
+                            })
+                        })
+                }
+            });
+        }
+        Ok(res)
+    }
+
     async fn get_ttl_refresh_log(&self) -> anyhow::Result<String> {
         let (sender, receiver) = oneshot::channel();
         self.command_sender
@@ -372,3 +675,115 @@ impl DeferredMaterializerExtensions for DeferredMaterializer {
         Ok(Box::new(receiver.await.context("No response from materializer")?) as _)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use buck2_core::fs::project::ProjectRootTemp;
+    use buck2_core::fs::project_rel_path::ProjectRelativePath;
+
+    use super::*;
+
+    fn metadata_from_disk(
+        fs: &ProjectRootTemp,
+        path: &ProjectRelativePath,
+    ) -> anyhow::Result<ArtifactMetadata> {
+        let digest_config = DigestConfig::testing_default();
+        let entry = build_entry_from_disk(
+            fs.path().resolve(path),
+            FileDigestConfig::build(digest_config.cas_digest_config()),
+        )?
+        .context("Path does not exist")?
+        .map_dir(|dir| {
+            dir.fingerprint(digest_config.as_directory_serializer())
+                .shared(&*INTERNER)
+        });
+        Ok(ArtifactMetadata::new(&entry))
+    }
+
+    fn verify(fs: &ProjectRootTemp, path: &ProjectRelativePath, metadata: &ArtifactMetadata) {
+        verify_materialized(
+            fs.path().resolve(path),
+            metadata,
+            DigestConfig::testing_default(),
+        )
+        .unwrap()
+    }
+
+    fn verify_error(
+        fs: &ProjectRootTemp,
+        path: &ProjectRelativePath,
+        metadata: &ArtifactMetadata,
+    ) -> VerifyError {
+        verify_materialized(
+            fs.path().resolve(path),
+            metadata,
+            DigestConfig::testing_default(),
+        )
+        .unwrap_err()
+        .downcast::<VerifyError>()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_verify_file() -> anyhow::Result<()> {
+        let fs = ProjectRootTemp::new()?;
+        let path = ProjectRelativePath::new("out/file")?;
+        fs.write_file("out/file", "abc");
+        let metadata = metadata_from_disk(&fs, path)?;
+        verify(&fs, path, &metadata);
+
+        // Same size, different contents.
+        fs.write_file("out/file", "abd");
+        assert_matches!(
+            verify_error(&fs, path, &metadata),
+            VerifyError::Mismatch { .. }
+        );
+
+        fs_util::remove_file(fs.path().resolve(path))?;
+        assert_matches!(verify_error(&fs, path, &metadata), VerifyError::Missing);
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_dir_recursively() -> anyhow::Result<()> {
+        let fs = ProjectRootTemp::new()?;
+        let path = ProjectRelativePath::new("out/dir")?;
+        fs.write_file("out/dir/a", "a");
+        fs.write_file("out/dir/b/c", "c");
+        let metadata = metadata_from_disk(&fs, path)?;
+        verify(&fs, path, &metadata);
+
+        fs.write_file("out/dir/b/c", "d");
+        assert_matches!(
+            verify_error(&fs, path, &metadata),
+            VerifyError::Mismatch { .. }
+        );
+
+        fs.write_file("out/dir/b/c", "c");
+        verify(&fs, path, &metadata);
+
+        fs.write_file("out/dir/b/extra", "");
+        assert_matches!(
+            verify_error(&fs, path, &metadata),
+            VerifyError::Mismatch { .. }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_wrong_type() -> anyhow::Result<()> {
+        let fs = ProjectRootTemp::new()?;
+        let path = ProjectRelativePath::new("out/x")?;
+        fs.write_file("out/x", "x");
+        let metadata = metadata_from_disk(&fs, path)?;
+
+        fs_util::remove_file(fs.path().resolve(path))?;
+        fs.write_file("out/x/y", "x");
+        assert_matches!(
+            verify_error(&fs, path, &metadata),
+            VerifyError::Mismatch { .. }
+        );
+        Ok(())
+    }
+}
@@ -53,6 +53,8 @@ use remote_execution::TCode;
 use remote_execution::TDigest;
 use tracing::instrument;
 
+use crate::materializers::deferred::relink::relinks;
+use crate::materializers::deferred::relink::RelinkTreeStructure;
 use crate::materializers::deferred::ArtifactMaterializationMethod;
 use crate::materializers::deferred::ArtifactMaterializationStage;
 use crate::materializers::deferred::ArtifactTree;
@@ -128,14 +130,19 @@ impl DefaultIoHandler {
         cancellations: &CancellationContext,
     ) -> Result<(), MaterializeEntryError> {
         // Materialize the dir structure, and symlinks
+        let tree_structure: Box<dyn IoRequest> = if relinks(&method, &entry) {
+            Box::new(RelinkTreeStructure {
+                path: path.clone(),
+                entry: entry.dupe(),
+            })
+        } else {
+            Box::new(MaterializeTreeStructure {
+                path: path.clone(),
+                entry: entry.dupe(),
+            })
+        };
         self.io_executor
-            .execute_io(
-                Box::new(MaterializeTreeStructure {
-                    path: path.clone(),
-                    entry: entry.dupe(),
-                }),
-                cancellations,
-            )
+            .execute_io(tree_structure, cancellations)
             .await?;
 
         // Materialize files
@@ -198,6 +205,7 @@ impl DefaultIoHandler {
                         self.digest_config,
                         &path,
                         &info.url,
+                        &info.mirrors,
                         &info.checksum,
                         info.metadata.is_executable,
                     )
@@ -11,12 +11,14 @@ mod clean_stale;
 mod extension;
 mod file_tree;
 mod io_handler;
+mod relink;
 mod subscriptions;
 
 #[cfg(test)]
 mod tests;
 
 use std::borrow::Cow;
+use std::collections::HashSet;
 use std::collections::VecDeque;
 use std::pin::Pin;
 use std::sync::atomic::AtomicUsize;
@@ -37,6 +39,7 @@ use buck2_common::result::ToUnsharedResultExt;
 use buck2_core::directory::unordered_entry_walk;
 use buck2_core::directory::DirectoryEntry;
 use buck2_core::env_helper::EnvHelper;
+use buck2_core::fs::fs_util;
 use buck2_core::fs::paths::RelativePathBuf;
 use buck2_core::fs::project::ProjectRoot;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
@@ -875,8 +878,8 @@ impl DeferredMaterializer {
         re_client_manager: Arc<ReConnectionManager>,
         io_executor: Arc<dyn BlockingExecutor>,
         configs: DeferredMaterializerConfigs,
-        sqlite_db: Option<MaterializerStateSqliteDb>,
-        sqlite_state: Option<MaterializerState>,
+        mut sqlite_db: Option<MaterializerStateSqliteDb>,
+        mut sqlite_state: Option<MaterializerState>,
     ) -> anyhow::Result<Self> {
         let (high_priority_sender, high_priority_receiver) = mpsc::unbounded_channel();
         let (low_priority_sender, low_priority_receiver) = mpsc::unbounded_channel();
@@ -895,9 +898,24 @@ impl DeferredMaterializer {
             counters,
         };
 
+        let num_interrupted_materializations = match sqlite_db.as_mut() {
+            Some(sqlite_db) => {
+                match clean_interrupted_materializations(&fs, sqlite_db, &mut sqlite_state) {
+                    Ok(n) => n,
+                    Err(e) => {
+                        soft_error!("materializer_interrupted_cleanup_error", e, quiet: true)
+                            .unwrap();
+                        0
+                    }
+                }
+            }
+            None => 0,
+        };
+
         let num_entries_from_sqlite = sqlite_state.as_ref().map_or(0, |s| s.len()) as u64;
         let materializer_state_info = buck2_data::MaterializerStateInfo {
             num_entries_from_sqlite,
+            num_interrupted_materializations,
         };
 
         let mut tree = ArtifactTree::new();
@@ -977,6 +995,36 @@ impl DeferredMaterializer {
     }
 }
 
+/// Materializations which were interrupted (e.g. because the daemon was killed) may have left
+/// partial outputs in buck-out. Delete them and forget about them: as they are not in the
+/// materializer state, they are materialized again next time they are needed.
+fn clean_interrupted_materializations(
+    fs: &ProjectRoot,
+    sqlite_db: &mut MaterializerStateSqliteDb,
+    sqlite_state: &mut Option<MaterializerState>,
+) -> anyhow::Result<u64> {
+    let paths = sqlite_db.materializations_in_progress_table().read_all()?;
+    if paths.is_empty() {
+        return Ok(0);
+    }
+
+    for path in &paths {
+        tracing::info!(path = %path, "deleting output of interrupted materialization");
+        fs_util::remove_all(fs.resolve(path))?;
+    }
+
+    // Those should not be in the state, but if they are, they are not materialized anymore.
+    if let Some(sqlite_state) = sqlite_state {
+        let paths = paths.iter().collect::<HashSet<_>>();
+        sqlite_state.retain(|(path, _)| !paths.contains(path));
+    }
+
+    let count = paths.len() as u64;
+    sqlite_db.materializer_state_table().delete(paths)?;
+    sqlite_db.materializations_in_progress_table().clear()?;
+    Ok(count)
+}
+
 /// Simple ring buffer for tracking recent commands, to be shown on materializer error
 #[derive(Clone)]
 struct LogBuffer {
@@ -1390,6 +1438,9 @@ impl<T: IoHandler> DeferredMaterializerCommandProcessor<T> {
                 );
                 ProcessingFuture::Materializing(materialize.shared())
             }
+            _ if relink::relinks(&method, value.entry()) => {
+                ProcessingFuture::Cleaning(wait_for_existing_futs(existing_futs, &self.rt))
+            }
             _ => ProcessingFuture::Cleaning(clean_path(
                 &self.io,
                 path.to_owned(),
@@ -1548,6 +1599,19 @@ impl<T: IoHandler> DeferredMaterializerCommandProcessor<T> {
 
         let version = self.version_tracker.next();
 
+        if entry_and_method.is_some() {
+            if let Some(sqlite_db) = self.sqlite_db.as_mut() {
+                if let Err(e) = sqlite_db.materializations_in_progress_table().insert(path) {
+                    soft_error!(
+                        "materializer_materialize_error",
+                        e.context(self.log_buffer.clone()),
+                        quiet: true
+                    )
+                    .unwrap();
+                }
+            }
+        }
+
         tracing::debug!(
             has_entry_and_method = entry_and_method.is_some(),
             method = ?entry_and_method.as_ref().map(|(_, m)| m),
@@ -1775,10 +1839,15 @@ fn on_materialization(
     error_name: &'static str,
 ) {
     if let Some(sqlite_db) = sqlite_db {
-        if let Err(e) = sqlite_db
-            .materializer_state_table()
-            .insert(path, metadata, timestamp)
-        {
+        let res: anyhow::Result<()> = try {
+            sqlite_db
+                .materializer_state_table()
+                .insert(path, metadata, timestamp)?;
+            sqlite_db
+                .materializations_in_progress_table()
+                .delete(path)?;
+        };
+        if let Err(e) = res {
             soft_error!(error_name, e.context(log_buffer.clone()), quiet: true).unwrap();
         }
     }
@@ -2082,6 +2151,22 @@ fn clean_path<T: IoHandler>(
     .shared()
 }
 
+/// Like `clean_path`, but leaves what is on disk at the path in place, once the existing futures
+/// are done with it. Used for the artifacts which are relinked when materialized.
+fn wait_for_existing_futs(existing_futs: ExistingFutures, rt: &Handle) -> CleaningFuture {
+    if existing_futs.is_empty() {
+        return futures::future::ready(Ok(())).boxed().shared();
+    }
+
+    rt.spawn(async move { join_all_existing_futs(existing_futs.into_result()?).await })
+        .map(|r| match r {
+            Ok(r) => r,
+            Err(e) => Err(e.into()), // Turn the JoinError into a SharedError.
+        })
+        .boxed()
+        .shared()
+}
+
 /// A wrapper type around the Result it contains. Used to expose some extra methods.
 struct ExistingFutures(anyhow::Result<Vec<(ProjectRelativePathBuf, ProcessingFuture)>>);
 
@@ -0,0 +1,460 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Incremental materialization of directories of symlinks, such as the outputs of
+//! `ctx.actions.symlinked_dir`.
+//!
+//! Such a directory is not cleaned when it is declared. When it is materialized, the links found
+//! on disk are diffed against the ones in its value, and only the links which were added, removed
+//! or retargeted are touched. On Windows, symlinks are created with their resolved targets, so
+//! these directories are cleaned and materialized like any other.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::path::PathBuf;
+
+use buck2_core::directory::unordered_entry_walk;
+use buck2_core::directory::DirectoryEntry;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::file_name::FileName;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+use buck2_core::fs::project::ProjectRoot;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_execute::directory::ActionDirectoryEntry;
+use buck2_execute::directory::ActionDirectoryMember;
+use buck2_execute::directory::ActionSharedDirectory;
+use buck2_execute::execute::blocking::IoRequest;
+
+use crate::materializers::deferred::ArtifactMaterializationMethod;
+use crate::materializers::io::materialize_dirs_and_syms;
+
+/// Whether an artifact declared with `method` is relinked over what is on disk when it is
+/// materialized, rather than cleaned when it is declared. Copies without sources are only made
+/// of directories and symlinks.
+pub(super) fn relinks(
+    method: &ArtifactMaterializationMethod,
+    entry: &ActionDirectoryEntry<ActionSharedDirectory>,
+) -> bool {
+    cfg!(unix)
+        && matches!(entry, DirectoryEntry::Dir(_))
+        && matches!(method, ArtifactMaterializationMethod::LocalCopy(_, srcs) if srcs.is_empty())
+}
+
+pub(super) struct RelinkTreeStructure {
+    pub(super) path: ProjectRelativePathBuf,
+    pub(super) entry: ActionDirectoryEntry<ActionSharedDirectory>,
+}
+
+impl IoRequest for RelinkTreeStructure {
+    fn execute(self: Box<Self>, project_fs: &ProjectRoot) -> anyhow::Result<()> {
+        let root = project_fs.resolve(&self.path);
+        match links(&self.entry) {
+            Some(links) => relink(&root, &links),
+            None => {
+                fs_util::remove_all(&root)?;
+                materialize_dirs_and_syms(self.entry.as_ref(), &root)
+            }
+        }
+    }
+}
+
+/// The symlinks of `entry`, by path relative to it, or `None` if it has files.
+fn links(
+    entry: &ActionDirectoryEntry<ActionSharedDirectory>,
+) -> Option<BTreeMap<ForwardRelativePathBuf, PathBuf>> {
+    let mut links = BTreeMap::new();
+    let mut walk = unordered_entry_walk(entry.as_ref());
+    while let Some((path, entry)) = walk.next() {
+        let target = match entry {
+            DirectoryEntry::Dir(_) => continue,
+            DirectoryEntry::Leaf(ActionDirectoryMember::File(_)) => return None,
+            DirectoryEntry::Leaf(ActionDirectoryMember::Symlink(s)) => {
+                PathBuf::from(s.target().as_str())
+            }
+            DirectoryEntry::Leaf(ActionDirectoryMember::ExternalSymlink(s)) => {
+                s.target().to_owned()
+            }
+        };
+        links.insert(path.get(), target);
+    }
+    Some(links)
+}
+
+/// What is found at a leaf of a directory of symlinks on disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ManifestEntry {
+    Symlink(PathBuf),
+    /// A file or an empty directory, which relinking never creates.
+    Other,
+}
+
+/// The leaves of a directory of symlinks on disk, by path relative to the directory: its
+/// symlinks, and anything else which is not a non-empty directory.
+type Manifest = BTreeMap<ForwardRelativePathBuf, ManifestEntry>;
+
+/// The changes laying out a directory of symlinks as the new links, given its previous manifest.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct ManifestDiff<'a> {
+    /// Leaves to delete: the links which are gone or retargeted, and anything not a link.
+    remove: Vec<&'a ForwardRelativePath>,
+    /// Links to create once the removals are done.
+    add: Vec<(&'a ForwardRelativePath, &'a Path)>,
+}
+
+impl<'a> ManifestDiff<'a> {
+    fn new(previous: &'a Manifest, links: &'a BTreeMap<ForwardRelativePathBuf, PathBuf>) -> Self {
+        fn is_link_to(entry: Option<&ManifestEntry>, target: Option<&PathBuf>) -> bool {
+            matches!((entry, target), (Some(ManifestEntry::Symlink(a)), Some(b)) if a == b)
+        }
+
+        let remove = previous
+            .iter()
+            .filter(|(path, entry)| !is_link_to(Some(*entry), links.get(*path)))
+            .map(|(path, _)| &**path)
+            .collect();
+        let add = links
+            .iter()
+            .filter(|(path, target)| !is_link_to(previous.get(*path), Some(*target)))
+            .map(|(path, target)| (&**path, &**target))
+            .collect();
+        Self { remove, add }
+    }
+
+    fn apply(&self, root: &AbsNormPath) -> anyhow::Result<()> {
+        for path in &self.remove {
+            fs_util::remove_all(root.join(path))?;
+            // A link replacing a directory needs it gone, and the output must not keep
+            // directories which are not in its value.
+            let mut dir = path.parent();
+            while let Some(parent) = dir {
+                if parent.is_empty() {
+                    break;
+                }
+                let abs_parent = root.join(parent);
+                match fs_util::read_dir_if_exists(&abs_parent)? {
+                    Some(mut entries) if entries.next().is_none() => {}
+                    _ => break,
+                }
+                fs_util::remove_dir(&abs_parent)?;
+                dir = parent.parent();
+            }
+        }
+        for (path, target) in &self.add {
+            let link = root.join(path);
+            if let Some(parent) = link.parent() {
+                fs_util::create_dir_all(parent)?;
+            }
+            fs_util::symlink(target, &link)?;
+        }
+        Ok(())
+    }
+}
+
+/// Collects the leaves under `dir`, which is at `path` in the directory of symlinks. Returns
+/// `false` if a name is not UTF-8, in which case the layout on disk is discarded.
+fn read_manifest_dir(
+    dir: &AbsNormPath,
+    path: &ForwardRelativePath,
+    manifest: &mut Manifest,
+) -> anyhow::Result<bool> {
+    let mut is_empty = true;
+    for entry in fs_util::read_dir(dir)? {
+        let entry = entry?;
+        is_empty = false;
+        let file_name = entry.file_name();
+        let file_name = match file_name.to_str().and_then(|n| FileName::new(n).ok()) {
+            Some(file_name) => file_name,
+            None => return Ok(false),
+        };
+        let child = path.join(file_name);
+        let file_type = entry.file_type()?;
+        if file_type.is_symlink() {
+            let target = fs_util::read_link(entry.path())?;
+            manifest.insert(child, ManifestEntry::Symlink(target));
+        } else if file_type.is_dir() {
+            if !read_manifest_dir(&entry.path(), &child, manifest)? {
+                return Ok(false);
+            }
+        } else {
+            manifest.insert(child, ManifestEntry::Other);
+        }
+    }
+    if is_empty && !path.is_empty() {
+        manifest.insert(path.to_buf(), ManifestEntry::Other);
+    }
+    Ok(true)
+}
+
+/// The manifest of the directory of symlinks at `root`, or `None` if there is no directory to
+/// reuse.
+fn read_manifest(root: &AbsNormPath) -> anyhow::Result<Option<Manifest>> {
+    match fs_util::symlink_metadata_if_exists(root)? {
+        Some(metadata) if metadata.is_dir() => {}
+        _ => return Ok(None),
+    }
+    let mut manifest = Manifest::new();
+    if read_manifest_dir(root, ForwardRelativePath::empty(), &mut manifest)? {
+        Ok(Some(manifest))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Lays out the directory of symlinks at `root` as `links`, touching only the links which
+/// changed.
+fn relink(
+    root: &AbsNormPath,
+    links: &BTreeMap<ForwardRelativePathBuf, PathBuf>,
+) -> anyhow::Result<()> {
+    let previous = match read_manifest(root)? {
+        Some(previous) => previous,
+        None => {
+            fs_util::remove_all(root)?;
+            fs_util::create_dir_all(root)?;
+            Manifest::new()
+        }
+    };
+    let diff = ManifestDiff::new(&previous, links);
+    tracing::debug!(
+        "Relinking `{}`: {} removed, {} added, {} kept",
+        root,
+        diff.remove.len(),
+        diff.add.len(),
+        links.len() - diff.add.len(),
+    );
+    diff.apply(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::fs::project::ProjectRootTemp;
+
+    use super::*;
+
+    fn links(links: &[(&str, &str)]) -> BTreeMap<ForwardRelativePathBuf, PathBuf> {
+        links
+            .iter()
+            .map(|(path, target)| {
+                (
+                    ForwardRelativePathBuf::unchecked_new((*path).to_owned()),
+                    PathBuf::from(target),
+                )
+            })
+            .collect()
+    }
+
+    /// A manifest from links and other leaves, given as `None` targets.
+    fn manifest(entries: &[(&str, Option<&str>)]) -> Manifest {
+        entries
+            .iter()
+            .map(|(path, target)| {
+                let entry = match target {
+                    Some(target) => ManifestEntry::Symlink(PathBuf::from(target)),
+                    None => ManifestEntry::Other,
+                };
+                (
+                    ForwardRelativePathBuf::unchecked_new((*path).to_owned()),
+                    entry,
+                )
+            })
+            .collect()
+    }
+
+    fn diff(
+        previous: &[(&str, Option<&str>)],
+        new: &[(&str, &str)],
+    ) -> (Vec<String>, Vec<(String, String)>) {
+        let previous = manifest(previous);
+        let new = links(new);
+        let diff = ManifestDiff::new(&previous, &new);
+        (
+            diff.remove.iter().map(|p| p.to_string()).collect(),
+            diff.add
+                .iter()
+                .map(|(p, t)| (p.to_string(), t.display().to_string()))
+                .collect(),
+        )
+    }
+
+    fn strings<const N: usize>(xs: [&str; N]) -> Vec<String> {
+        xs.iter().map(|x| (*x).to_owned()).collect()
+    }
+
+    fn pairs<const N: usize>(xs: [(&str, &str); N]) -> Vec<(String, String)> {
+        xs.iter()
+            .map(|(a, b)| ((*a).to_owned(), (*b).to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn test_manifest_diff_from_nothing() {
+        assert_eq!(
+            (strings([]), pairs([("a", "../x"), ("b/c", "../../y")])),
+            diff(&[], &[("a", "../x"), ("b/c", "../../y")])
+        );
+    }
+
+    #[test]
+    fn test_manifest_diff_unchanged() {
+        assert_eq!(
+            (strings([]), pairs([])),
+            diff(&[("a", Some("../x"))], &[("a", "../x")])
+        );
+    }
+
+    #[test]
+    fn test_manifest_diff_only_touches_changes() {
+        assert_eq!(
+            (strings(["b", "c"]), pairs([("b", "../z"), ("d", "../w")])),
+            diff(
+                &[
+                    ("a", Some("../x")),
+                    ("b", Some("../y")),
+                    ("c", Some("../c"))
+                ],
+                &[("a", "../x"), ("b", "../z"), ("d", "../w")],
+            )
+        );
+    }
+
+    #[test]
+    fn test_manifest_diff_rename() {
+        assert_eq!(
+            (strings(["a"]), pairs([("b", "../x")])),
+            diff(&[("a", Some("../x"))], &[("b", "../x")])
+        );
+    }
+
+    #[test]
+    fn test_manifest_diff_link_to_directory() {
+        // `a` goes from a link to a directory of links: the link must be removed first.
+        assert_eq!(
+            (strings(["a"]), pairs([("a/b", "../../x")])),
+            diff(&[("a", Some("../x"))], &[("a/b", "../../x")])
+        );
+    }
+
+    #[test]
+    fn test_manifest_diff_directory_to_link() {
+        assert_eq!(
+            (strings(["a/b", "a/c"]), pairs([("a", "../x")])),
+            diff(
+                &[("a/b", Some("../../x")), ("a/c", Some("../../y"))],
+                &[("a", "../x")],
+            )
+        );
+    }
+
+    #[test]
+    fn test_manifest_diff_removes_other_leaves() {
+        // Files and empty directories are never created by the action, so they are always
+        // removed, even where a link is expected.
+        assert_eq!(
+            (strings(["a", "b", "c/d"]), pairs([("a", "../x")])),
+            diff(
+                &[
+                    ("a", None),
+                    ("b", None),
+                    ("c/d", None),
+                    ("c/e", Some("../../y"))
+                ],
+                &[("a", "../x"), ("c/e", "../../y")],
+            )
+        );
+    }
+
+    /// A directory of symlinks on disk, with a file and an empty directory.
+    #[cfg(unix)]
+    fn create_tree(root: &AbsNormPath) -> anyhow::Result<()> {
+        fs_util::create_dir_all(root.join(ForwardRelativePath::new("b/e")?))?;
+        fs_util::symlink("../x", root.join(ForwardRelativePath::new("a")?))?;
+        fs_util::symlink("../../y", root.join(ForwardRelativePath::new("b/c")?))?;
+        fs_util::write(root.join(ForwardRelativePath::new("d")?), "d")?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_read_manifest() -> anyhow::Result<()> {
+        let fs = ProjectRootTemp::new()?;
+        let root = fs.path().root().join(ForwardRelativePath::new("out")?);
+        create_tree(&root)?;
+
+        assert_eq!(
+            Some(manifest(&[
+                ("a", Some("../x")),
+                ("b/c", Some("../../y")),
+                ("b/e", None),
+                ("d", None),
+            ])),
+            read_manifest(&root)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_manifest_not_a_dir() -> anyhow::Result<()> {
+        let fs = ProjectRootTemp::new()?;
+        let root = fs.path().root().join(ForwardRelativePath::new("out")?);
+        assert_eq!(None, read_manifest(&root)?);
+
+        fs_util::write(&root, "out")?;
+        assert_eq!(None, read_manifest(&root)?);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_relink() -> anyhow::Result<()> {
+        use std::os::unix::fs::MetadataExt;
+
+        let fs = ProjectRootTemp::new()?;
+        let root = fs.path().root().join(ForwardRelativePath::new("out")?);
+        create_tree(&root)?;
+        let inode = |path: &str| -> anyhow::Result<u64> {
+            Ok(fs_util::symlink_metadata(root.join(ForwardRelativePath::new(path)?))?.ino())
+        };
+        let kept = inode("a")?;
+
+        relink(
+            &root,
+            &links(&[("a", "../x"), ("b", "../z"), ("f/g", "../../w")]),
+        )?;
+
+        assert_eq!(
+            Some(manifest(&[
+                ("a", Some("../x")),
+                ("b", Some("../z")),
+                ("f/g", Some("../../w")),
+            ])),
+            read_manifest(&root)?
+        );
+        // The unchanged link was not recreated.
+        assert_eq!(kept, inode("a")?);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_relink_over_file() -> anyhow::Result<()> {
+        let fs = ProjectRootTemp::new()?;
+        let root = fs.path().root().join(ForwardRelativePath::new("out")?);
+        fs_util::write(&root, "out")?;
+
+        relink(&root, &links(&[("a", "../x")]))?;
+
+        assert_eq!(
+            Some(manifest(&[("a", Some("../x"))])),
+            read_manifest(&root)?
+        );
+        Ok(())
+    }
+}
@@ -96,6 +96,8 @@ mod state_machine {
     use tokio::time::sleep;
     use tokio::time::Duration as TokioDuration;
 
+    use super::extension::artifact_entries;
+    use super::extension::ArtifactEntry;
     use super::*;
 
     #[derive(Debug, Eq, PartialEq)]
@@ -313,6 +315,69 @@ mod state_machine {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_artifact_entries() -> anyhow::Result<()> {
+        let digest_config = DigestConfig::testing_default();
+
+        let (mut dm, _) = make_processor(digest_config, Default::default());
+
+        let file_path = make_path("foo/file");
+        let file = ArtifactValue::file(digest_config.empty_file());
+        dm.declare(
+            &file_path,
+            file.dupe(),
+            Box::new(ArtifactMaterializationMethod::Test),
+        );
+
+        let dir_path = make_path("foo/dir");
+        let mut builder = ActionDirectoryBuilder::empty();
+        insert_file(&mut builder, &make_path("a/b"), digest_config.empty_file())?;
+        let dir = ArtifactValue::dir(
+            builder
+                .fingerprint(digest_config.as_directory_serializer())
+                .shared(&*INTERNER),
+        );
+        dm.declare(
+            &dir_path,
+            dir.dupe(),
+            Box::new(ArtifactMaterializationMethod::Test),
+        );
+
+        let paths = [
+            file_path.clone(),
+            dir_path.clone(),
+            make_path("foo"),
+            make_path("foo/dir/a"),
+            make_path("bar"),
+        ];
+
+        // Declared artifacts have their entry in memory.
+        let entries = artifact_entries(&dm.tree, &paths);
+        assert_matches!(&entries[0], Some(ArtifactEntry::Entry(e)) if e == file.entry());
+        assert_matches!(&entries[1], Some(ArtifactEntry::Entry(e)) if e == dir.entry());
+        assert_matches!(&entries[2..], [None, None, None]);
+
+        // Once materialized, only the entries of files are kept.
+        for path in [&file_path, &dir_path] {
+            let res = dm
+                .materialize_artifact(path, EventDispatcher::null())
+                .context("Expected a future")?
+                .await;
+            dm.materialization_finished(
+                path.clone(),
+                Utc::now(),
+                dm.version_tracker.current(),
+                res,
+            );
+        }
+        let entries = artifact_entries(&dm.tree, &paths);
+        assert_matches!(&entries[0], Some(ArtifactEntry::Entry(e)) if e == file.entry());
+        assert_matches!(&entries[1], Some(ArtifactEntry::MaterializedDir));
+        assert_matches!(&entries[2..], [None, None, None]);
+
+        Ok(())
+    }
+
     fn make_artifact_value_with_symlink_dep(
         target_path: &ProjectRelativePathBuf,
         target_from_symlink: &RelativePathBuf,
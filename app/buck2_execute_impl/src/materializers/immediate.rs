@@ -210,6 +210,7 @@ impl Materializer for ImmediateMaterializer {
             self.digest_config,
             &path,
             &info.url,
+            &info.mirrors,
             &info.checksum,
             info.metadata.is_executable,
         )
@@ -52,9 +52,10 @@ pub struct MaterializerStateIdentity(String);
 /// materializer state sqlite db schema! If you forget to bump this version,
 /// then you can fix forward by bumping the `buck2.sqlite_materializer_state_version`
 /// buckconfig in the project root's .buckconfig.
-pub const DB_SCHEMA_VERSION: u64 = 6;
+pub const DB_SCHEMA_VERSION: u64 = 7;
 
 const STATE_TABLE_NAME: &str = "materializer_state";
+const IN_PROGRESS_TABLE_NAME: &str = "materializations_in_progress";
 const IDENTITY_KEY: &str = "timestamp_on_initialization";
 
 pub type MaterializerState = Vec<(ProjectRelativePathBuf, (ArtifactMetadata, DateTime<Utc>))>;
@@ -474,6 +475,86 @@ impl MaterializerStateSqliteTable {
     }
 }
 
+/// Paths whose materialization started but did not finish. If the daemon dies while
+/// materializing, these may hold partial outputs, which must be deleted on startup.
+pub(crate) struct MaterializationsInProgressSqliteTable {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl MaterializationsInProgressSqliteTable {
+    pub fn new(connection: Arc<Mutex<Connection>>) -> Self {
+        Self { connection }
+    }
+
+    pub(crate) fn create_table(&self) -> anyhow::Result<()> {
+        let sql = format!(
+            "CREATE TABLE {} (path TEXT NOT NULL PRIMARY KEY)",
+            IN_PROGRESS_TABLE_NAME,
+        );
+        tracing::trace!(sql = %*sql, "creating table");
+        self.connection
+            .lock()
+            .execute(&sql, [])
+            .with_context(|| format!("creating sqlite table {}", IN_PROGRESS_TABLE_NAME))?;
+        Ok(())
+    }
+
+    pub(crate) fn insert(&self, path: &ProjectRelativePath) -> anyhow::Result<()> {
+        static SQL: Lazy<String> = Lazy::new(|| {
+            format!(
+                "INSERT OR IGNORE INTO {} (path) VALUES (?1)",
+                IN_PROGRESS_TABLE_NAME
+            )
+        });
+        tracing::trace!(sql = %*SQL, path = %path, "inserting into table");
+        self.connection
+            .lock()
+            .execute(&SQL, rusqlite::params![path.as_str()])
+            .with_context(|| {
+                format!(
+                    "inserting `{}` into sqlite table {}",
+                    path, IN_PROGRESS_TABLE_NAME
+                )
+            })?;
+        Ok(())
+    }
+
+    pub(crate) fn delete(&self, path: &ProjectRelativePath) -> anyhow::Result<()> {
+        static SQL: Lazy<String> =
+            Lazy::new(|| format!("DELETE FROM {} WHERE path = (?1)", IN_PROGRESS_TABLE_NAME));
+        tracing::trace!(sql = %*SQL, path = %path, "deleting from table");
+        self.connection
+            .lock()
+            .execute(&SQL, rusqlite::params![path.as_str()])
+            .with_context(|| format!("deleting from sqlite table {}", IN_PROGRESS_TABLE_NAME))?;
+        Ok(())
+    }
+
+    pub(crate) fn read_all(&self) -> anyhow::Result<Vec<ProjectRelativePathBuf>> {
+        static SQL: Lazy<String> =
+            Lazy::new(|| format!("SELECT path FROM {}", IN_PROGRESS_TABLE_NAME));
+        tracing::trace!(sql = %*SQL, "reading all from table");
+        let connection = self.connection.lock();
+        let mut stmt = connection.prepare(&SQL)?;
+        let result = stmt
+            .query_map([], |row| row.get(0))?
+            .map(|path| path.map(ProjectRelativePathBuf::unchecked_new))
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| format!("reading from sqlite table {}", IN_PROGRESS_TABLE_NAME))?;
+        Ok(result)
+    }
+
+    pub(crate) fn clear(&self) -> anyhow::Result<()> {
+        static SQL: Lazy<String> = Lazy::new(|| format!("DELETE FROM {}", IN_PROGRESS_TABLE_NAME));
+        tracing::trace!(sql = %*SQL, "clearing table");
+        self.connection
+            .lock()
+            .execute(&SQL, [])
+            .with_context(|| format!("clearing sqlite table {}", IN_PROGRESS_TABLE_NAME))?;
+        Ok(())
+    }
+}
+
 #[derive(Error, Debug, PartialEq, Eq)]
 enum MaterializerStateSqliteDbError {
     #[error("Path {} does not exist", .0)]
@@ -633,6 +714,12 @@ impl MaterializerStateSqliteDb {
         &self.tables.materializer_state_table
     }
 
+    pub(crate) fn materializations_in_progress_table(
+        &mut self,
+    ) -> &MaterializationsInProgressSqliteTable {
+        &self.tables.materializations_in_progress_table
+    }
+
     pub fn identity(&self) -> &MaterializerStateIdentity {
         &self.identity
     }
@@ -641,6 +728,8 @@ impl MaterializerStateSqliteDb {
 struct MaterializerStateTables {
     /// Table storing actual materializer state
     materializer_state_table: MaterializerStateSqliteTable,
+    /// Table storing paths which are being materialized
+    materializations_in_progress_table: MaterializationsInProgressSqliteTable,
     /// Table for holding any metadata used to check version match. When loading
     /// from an existing db, we check if the versions from this table match the
     /// versions this buck2 binary expects. If the versions don't match, we throw
@@ -684,12 +773,15 @@ impl MaterializerStateTables {
 
         let connection = Arc::new(Mutex::new(connection));
         let materializer_state_table = MaterializerStateSqliteTable::new(connection.dupe());
+        let materializations_in_progress_table =
+            MaterializationsInProgressSqliteTable::new(connection.dupe());
         let versions_table = KeyValueSqliteTable::new("versions".to_owned(), connection.dupe());
         let created_by_table = KeyValueSqliteTable::new("created_by".to_owned(), connection.dupe());
         let last_read_by_table = KeyValueSqliteTable::new("last_read_by".to_owned(), connection);
 
         Ok(Self {
             materializer_state_table,
+            materializations_in_progress_table,
             versions_table,
             created_by_table,
             last_read_by_table,
@@ -698,6 +790,7 @@ impl MaterializerStateTables {
 
     fn create_all_tables(&self) -> anyhow::Result<()> {
         self.materializer_state_table.create_table()?;
+        self.materializations_in_progress_table.create_table()?;
         self.versions_table.create_table()?;
         self.created_by_table.create_table()?;
         self.last_read_by_table.create_table()?;
@@ -1086,4 +1179,27 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_materializations_in_progress_table() -> anyhow::Result<()> {
+        let conn = Connection::open_in_memory()?;
+
+        let table = MaterializationsInProgressSqliteTable::new(Arc::new(Mutex::new(conn)));
+        table.create_table()?;
+
+        let foo = ProjectRelativePath::unchecked_new("foo");
+        let bar = ProjectRelativePath::unchecked_new("bar");
+        table.insert(foo)?;
+        table.insert(bar)?;
+        // Restarting a materialization is fine.
+        table.insert(foo)?;
+        table.delete(bar)?;
+
+        assert_eq!(vec![foo.to_buf()], table.read_all()?);
+
+        table.clear()?;
+        assert_eq!(Vec::<ProjectRelativePathBuf>::new(), table.read_all()?);
+
+        Ok(())
+    }
 }
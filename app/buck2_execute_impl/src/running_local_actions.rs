@@ -0,0 +1,136 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! The local actions currently executing in this daemon, so that the user can kill one of them
+//! (e.g. from the interactive console) without interrupting the command which is running it.
+
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use buck2_common::liveliness_observer::LivelinessGuard;
+use buck2_common::liveliness_observer::LivelinessObserver;
+use buck2_execute::execute::action_digest::ActionDigest;
+use dupe::Dupe;
+use parking_lot::Mutex;
+
+struct RunningLocalAction {
+    action_digest: String,
+    /// Dropped to make the liveliness observer of the action report it is no longer alive.
+    guard: Option<LivelinessGuard>,
+    killed: Arc<AtomicBool>,
+}
+
+#[derive(Default)]
+pub struct RunningLocalActions {
+    next_id: AtomicU64,
+    actions: Mutex<HashMap<u64, RunningLocalAction>>,
+}
+
+/// Unregisters the action when dropped.
+pub struct RunningLocalActionGuard<'a> {
+    actions: &'a RunningLocalActions,
+    id: u64,
+    killed: Arc<AtomicBool>,
+}
+
+impl RunningLocalActionGuard<'_> {
+    /// Whether the action was killed with `RunningLocalActions::kill`, as opposed to cancelled
+    /// for any other reason.
+    pub fn killed(&self) -> bool {
+        self.killed.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for RunningLocalActionGuard<'_> {
+    fn drop(&mut self) {
+        self.actions.actions.lock().remove(&self.id);
+    }
+}
+
+impl RunningLocalActions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register an action which is about to execute. The returned observer stops being alive if
+    /// the action is killed, which should make the executor kill its process.
+    pub fn register(
+        &self,
+        action_digest: &ActionDigest,
+    ) -> (Arc<dyn LivelinessObserver>, RunningLocalActionGuard<'_>) {
+        let (observer, guard) = LivelinessGuard::create();
+        let killed = Arc::new(AtomicBool::new(false));
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.actions.lock().insert(
+            id,
+            RunningLocalAction {
+                action_digest: action_digest.to_string(),
+                guard: Some(guard),
+                killed: killed.dupe(),
+            },
+        );
+        (
+            observer,
+            RunningLocalActionGuard {
+                actions: self,
+                id,
+                killed,
+            },
+        )
+    }
+
+    /// Kill the running actions with this digest. Returns how many were killed, which is more
+    /// than one if concurrent commands are running the same action.
+    pub fn kill(&self, action_digest: &str) -> usize {
+        let mut killed = 0;
+        for action in self.actions.lock().values_mut() {
+            if action.action_digest == action_digest {
+                if let Some(guard) = action.guard.take() {
+                    action.killed.store(true, Ordering::Relaxed);
+                    drop(guard);
+                    killed += 1;
+                }
+            }
+        }
+        killed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_execute::execute::action_digest::ActionDigest;
+
+    use crate::running_local_actions::RunningLocalActions;
+
+    #[tokio::test]
+    async fn test_kill() {
+        let actions = RunningLocalActions::new();
+        let digest = ActionDigest::new_sha1([1; 20], 10);
+        let other = ActionDigest::new_sha1([2; 20], 10);
+
+        let (observer, guard) = actions.register(&digest);
+        let (other_observer, other_guard) = actions.register(&other);
+
+        assert_eq!(actions.kill(&digest.to_string()), 1);
+        assert!(!observer.is_alive().await);
+        assert!(guard.killed());
+        assert!(other_observer.is_alive().await);
+        assert!(!other_guard.killed());
+
+        // Killing twice doesn't count the action again.
+        assert_eq!(actions.kill(&digest.to_string()), 0);
+
+        drop(guard);
+        drop(other_guard);
+        assert_eq!(actions.kill(&other.to_string()), 0);
+    }
+}
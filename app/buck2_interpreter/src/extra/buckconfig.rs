@@ -9,6 +9,7 @@
 
 use std::cell::RefCell;
 use std::fmt;
+use std::sync::Arc;
 
 use buck2_common::legacy_configs::view::LegacyBuckConfigView;
 use hashbrown::raw::RawTable;
@@ -111,4 +112,10 @@ impl<'a> LegacyBuckConfigForStarlark<'a> {
         // `StringValue` caches the hashes.
         self.get_impl(section.get_hashed_str(), key.get_hashed_str())
     }
+
+    /// Find the buckconfig entry, for callers which are not `read_config`
+    /// and do not need the value as a Starlark string.
+    pub fn get_str(&self, section: &str, key: &str) -> anyhow::Result<Option<Arc<str>>> {
+        self.buckconfig.get(section, key)
+    }
 }
@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use std::fmt;
 use std::sync::Arc;
 
 use allocative::Allocative;
@@ -57,6 +58,47 @@ impl ModuleDeps {
     }
 }
 
+/// Digest of everything a module evaluation depends on: its source, the digests of the modules
+/// it loads, and the buckconfig values read while evaluating it.
+///
+/// Unlike the module itself, this is stable across re-evaluations of unchanged files, so DICE
+/// compares it to cut off re-evaluations which didn't change anything.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Allocative, Debug)]
+pub struct ModuleDigest([u8; 32]);
+
+impl fmt::Display for ModuleDigest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct ModuleDigestBuilder(blake3::Hasher);
+
+impl ModuleDigestBuilder {
+    pub fn new(content: &str) -> Self {
+        let mut builder = ModuleDigestBuilder(blake3::Hasher::new());
+        builder.add_str(content);
+        builder
+    }
+
+    /// Add a string, prefixed with its length so adjacent strings can't be confused.
+    pub fn add_str(&mut self, s: &str) {
+        self.0.update(&(s.len() as u64).to_le_bytes());
+        self.0.update(s.as_bytes());
+    }
+
+    pub fn add_digest(&mut self, digest: ModuleDigest) {
+        self.0.update(&digest.0);
+    }
+
+    pub fn finish(&self) -> ModuleDigest {
+        ModuleDigest(*self.0.finalize().as_bytes())
+    }
+}
+
 #[derive(Clone, Dupe, Allocative, Debug)]
 pub struct LoadedModule(Arc<LoadedModuleData>);
 
@@ -68,6 +110,7 @@ struct LoadedModuleData {
     loaded_modules: LoadedModules,
     #[derivative(Debug = "ignore")]
     env: FrozenModule,
+    digest: ModuleDigest,
 }
 
 impl LoadedModule {
@@ -75,11 +118,13 @@ impl LoadedModule {
         path: OwnedStarlarkModulePath,
         loaded_modules: LoadedModules,
         env: FrozenModule,
+        digest: ModuleDigest,
     ) -> Self {
         Self(Arc::new(LoadedModuleData {
             path,
             loaded_modules,
             env,
+            digest,
         }))
     }
 
@@ -98,6 +143,10 @@ impl LoadedModule {
     pub fn env(&self) -> &FrozenModule {
         &self.0.env
     }
+
+    pub fn digest(&self) -> ModuleDigest {
+        self.0.digest
+    }
 }
 
 pub struct InterpreterFileLoader {
@@ -195,6 +244,7 @@ mod tests {
                 import_path.clone(),
                 LoadedModules::default(),
                 env(import_path.borrow()),
+                ModuleDigestBuilder::new(path).finish(),
             );
             loaded_modules.map.insert(import_path, module);
         };
@@ -0,0 +1,118 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use starlark::environment::GlobalsBuilder;
+
+/// Largest input (in bytes) accepted by the native hashing and encoding functions.
+///
+/// These functions run during loading, so an unbounded input would let a single macro
+/// stall evaluation of every package that calls it.
+pub const MAX_INPUT_BYTES: usize = 4 * 1024 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+enum EncodingError {
+    #[error("Input to `{function}` is {len} bytes, which exceeds the limit of {limit} bytes")]
+    InputTooLarge {
+        function: &'static str,
+        len: usize,
+        limit: usize,
+    },
+    #[error("Input to `base64_decode` is not valid base64: {0}")]
+    InvalidBase64(base64::DecodeError),
+    #[error("Result of `base64_decode` is not valid UTF-8")]
+    NotUtf8,
+}
+
+/// Fails if `val` is larger than [`MAX_INPUT_BYTES`].
+pub(crate) fn check_input_size(function: &'static str, val: &str) -> anyhow::Result<()> {
+    if val.len() > MAX_INPUT_BYTES {
+        return Err(EncodingError::InputTooLarge {
+            function,
+            len: val.len(),
+            limit: MAX_INPUT_BYTES,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Hashing and encoding functions that we include in all contexts.
+///
+/// All of these are deterministic: the result depends only on the UTF-8 bytes of the input,
+/// never on the host platform or the order of evaluation. Inputs larger than 4 MiB are rejected.
+#[starlark_module]
+pub fn register_encoding(builder: &mut GlobalsBuilder) {
+    /// Computes a blake3 digest for a string. Returns the lowercase hex representation of the
+    /// 32-byte digest.
+    ///
+    /// ```python
+    /// blake3("") == "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+    /// ```
+    fn blake3(val: &str) -> anyhow::Result<String> {
+        check_input_size("blake3", val)?;
+        Ok(::blake3::hash(val.as_bytes()).to_hex().to_string())
+    }
+
+    /// Encodes the UTF-8 bytes of a string using standard base64 with padding.
+    ///
+    /// ```python
+    /// base64_encode("buck2") == "YnVjazI="
+    /// ```
+    fn base64_encode(val: &str) -> anyhow::Result<String> {
+        check_input_size("base64_encode", val)?;
+        Ok(base64::encode(val.as_bytes()))
+    }
+
+    /// Decodes a standard base64 string with padding. Fails if the input is not valid base64
+    /// or the decoded bytes are not valid UTF-8.
+    ///
+    /// ```python
+    /// base64_decode("YnVjazI=") == "buck2"
+    /// ```
+    fn base64_decode(val: &str) -> anyhow::Result<String> {
+        check_input_size("base64_decode", val)?;
+        let bytes = base64::decode(val).map_err(EncodingError::InvalidBase64)?;
+        Ok(String::from_utf8(bytes).map_err(|_| EncodingError::NotUtf8)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starlark::assert::Assert;
+
+    use crate::functions::encoding::register_encoding;
+    use crate::functions::sha256::register_sha256;
+
+    #[test]
+    fn test_encoding() {
+        let mut a = Assert::new();
+        a.globals_add(register_encoding);
+        a.pass(
+            r#"
+assert_eq(blake3(""), "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262")
+assert_eq(base64_encode("buck2"), "YnVjazI=")
+assert_eq(base64_decode("YnVjazI="), "buck2")
+assert_eq(base64_decode(base64_encode("ünïcödé")), "ünïcödé")
+            "#,
+        );
+        a.fail("base64_decode('not base64!')", "not valid base64");
+        a.fail("base64_decode('/w==')", "not valid UTF-8");
+    }
+
+    #[test]
+    fn test_input_limit() {
+        let mut a = Assert::new();
+        a.globals_add(register_encoding);
+        a.globals_add(register_sha256);
+        a.pass("blake3('x' * 4194304)");
+        a.fail("blake3('x' * 4194305)", "exceeds the limit");
+        a.fail("base64_encode('x' * 4194305)", "exceeds the limit");
+        a.fail("sha256('x' * 4194305)", "exceeds the limit");
+    }
+}
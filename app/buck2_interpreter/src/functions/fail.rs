@@ -0,0 +1,132 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::BTreeMap;
+
+use buck2_common::result::recursive_shared_downcast_ref;
+use starlark::environment::GlobalsBuilder;
+use starlark::errors::Diagnostic;
+use starlark::values::dict::DictRef;
+use starlark::values::none::NoneType;
+use starlark::values::Value;
+
+/// Error produced by `fail()`, carrying an optional machine-readable code and metadata
+/// so that failures can be classified without parsing the message.
+#[derive(Debug, thiserror::Error)]
+#[error("fail:{message}")]
+pub struct StarlarkFailError {
+    /// The message, including the leading space, as in the standard `fail()`.
+    message: String,
+    code: Option<String>,
+    metadata: BTreeMap<String, String>,
+}
+
+impl StarlarkFailError {
+    pub fn message(&self) -> &str {
+        self.message.trim_start()
+    }
+
+    pub fn code(&self) -> Option<&str> {
+        self.code.as_deref()
+    }
+
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+}
+
+/// Find the error raised by `fail()` which caused this error, if any.
+pub fn find_starlark_fail(err: &anyhow::Error) -> Option<&StarlarkFailError> {
+    if let Some(fail) = recursive_shared_downcast_ref::<StarlarkFailError>(err) {
+        return Some(fail);
+    }
+    // Errors from native functions are wrapped in a `Diagnostic` with the call stack,
+    // which doesn't expose them as its source.
+    let diagnostic = recursive_shared_downcast_ref::<Diagnostic>(err)?;
+    find_starlark_fail(&diagnostic.message)
+}
+
+#[derive(Debug, thiserror::Error)]
+enum FailError {
+    #[error("`fail()` metadata keys must be strings, got `{0}`")]
+    MetadataKeyNotString(String),
+}
+
+fn push_str_or_repr(s: &mut String, x: Value) {
+    match x.unpack_str() {
+        Some(x) => s.push_str(x),
+        None => x.collect_repr(s),
+    }
+}
+
+/// Contains functions that we include in all contexts.
+#[starlark_module]
+pub fn register_fail(builder: &mut GlobalsBuilder) {
+    /// fail: fail the execution
+    ///
+    /// Like the standard `fail()`, but also accepts an optional error `code` and a dict of
+    /// `metadata`, which are reported in the build report and the event log:
+    ///
+    /// ```python
+    /// fail("srcs must not be empty", code = "MY_RULE_BAD_SRCS", metadata = {"srcs": "0"})
+    /// ```
+    fn fail<'v>(
+        #[starlark(args)] args: Vec<Value<'v>>,
+        #[starlark(require = named)] code: Option<String>,
+        #[starlark(require = named)] metadata: Option<DictRef<'v>>,
+    ) -> anyhow::Result<NoneType> {
+        let mut message = String::new();
+        for x in args {
+            message.push(' ');
+            push_str_or_repr(&mut message, x);
+        }
+
+        let mut metadata_map = BTreeMap::new();
+        if let Some(metadata) = metadata {
+            for (k, v) in metadata.iter() {
+                let k = match k.unpack_str() {
+                    Some(k) => k.to_owned(),
+                    None => return Err(FailError::MetadataKeyNotString(k.to_repr()).into()),
+                };
+                let mut value = String::new();
+                push_str_or_repr(&mut value, v);
+                metadata_map.insert(k, value);
+            }
+        }
+
+        Err(StarlarkFailError {
+            message,
+            code,
+            metadata: metadata_map,
+        }
+        .into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starlark::assert::Assert;
+
+    use crate::functions::fail::register_fail;
+
+    #[test]
+    fn test_fail() {
+        let mut a = Assert::new();
+        a.globals_add(register_fail);
+        a.fail("fail('oops', 1, False)", "fail: oops 1 False");
+        a.fail(
+            "fail('bad srcs', code = 'MY_RULE_BAD_SRCS', metadata = {'count': 0})",
+            "fail: bad srcs",
+        );
+        a.fail(
+            "fail('bad', metadata = {1: 'x'})",
+            "metadata keys must be strings",
+        );
+    }
+}
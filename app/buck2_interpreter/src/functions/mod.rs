@@ -8,4 +8,7 @@
  */
 
 pub mod dedupe;
+pub mod encoding;
+pub mod fail;
+pub mod regex;
 pub mod sha256;
@@ -0,0 +1,222 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use regex::Captures;
+use regex::Regex;
+use regex::RegexBuilder;
+use starlark::environment::GlobalsBuilder;
+use starlark::values::list::AllocList;
+use starlark::values::tuple::AllocTuple;
+use starlark::values::Heap;
+use starlark::values::Value;
+
+use crate::functions::encoding::check_input_size;
+
+/// Longest pattern (in bytes) accepted by the `regex` functions.
+const MAX_PATTERN_BYTES: usize = 4 * 1024;
+
+/// Limit on the size of a compiled pattern, so that patterns like `a{1000}{1000}` fail instead
+/// of using a lot of memory.
+const MAX_COMPILED_BYTES: usize = 1024 * 1024;
+
+/// Number of compiled patterns kept by the daemon. Macros use a handful of constant patterns, so
+/// when this is reached the cache is cleared rather than tracking which patterns are in use.
+const MAX_CACHED_PATTERNS: usize = 1024;
+
+/// Compiled patterns, shared by all the evaluations of the daemon.
+static PATTERN_CACHE: Lazy<Mutex<HashMap<String, Regex>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, thiserror::Error)]
+enum RegexError {
+    #[error(
+        "Pattern passed to `regex.{function}` is {len} bytes, which exceeds the limit of {limit} bytes"
+    )]
+    PatternTooLarge {
+        function: &'static str,
+        len: usize,
+        limit: usize,
+    },
+    #[error("Invalid pattern passed to `regex.{0}`: {1}")]
+    InvalidPattern(&'static str, regex::Error),
+}
+
+/// Compile `pattern`, or get it from the cache if it was already compiled by this daemon.
+fn compile(function: &'static str, pattern: &str) -> anyhow::Result<Regex> {
+    if pattern.len() > MAX_PATTERN_BYTES {
+        return Err(RegexError::PatternTooLarge {
+            function,
+            len: pattern.len(),
+            limit: MAX_PATTERN_BYTES,
+        }
+        .into());
+    }
+    if let Some(regex) = PATTERN_CACHE.lock().get(pattern) {
+        return Ok(regex.clone());
+    }
+    // Compile without holding the lock, other threads may need the cache meanwhile.
+    let regex = RegexBuilder::new(pattern)
+        .size_limit(MAX_COMPILED_BYTES)
+        .build()
+        .map_err(|e| RegexError::InvalidPattern(function, e))?;
+    let mut cache = PATTERN_CACHE.lock();
+    if cache.len() >= MAX_CACHED_PATTERNS {
+        cache.clear();
+    }
+    cache.insert(pattern.to_owned(), regex.clone());
+    Ok(regex)
+}
+
+/// A list of the groups of a match: the whole match first, then each capture group, `None` for
+/// the groups which did not participate.
+fn alloc_groups<'v>(captures: &Captures, heap: &'v Heap) -> Value<'v> {
+    heap.alloc(AllocList(captures.iter().map(|group| match group {
+        Some(m) => heap.alloc(m.as_str()),
+        None => Value::new_none(),
+    })))
+}
+
+#[starlark_module]
+fn regex_members(builder: &mut GlobalsBuilder) {
+    /// Matches a regular expression at the start of a string. Returns `None` if it does not
+    /// match, otherwise a list of the whole match followed by each capture group (`None` for
+    /// the groups which did not participate).
+    ///
+    /// Patterns use the syntax of the Rust `regex` crate, and are compiled once per daemon.
+    /// Patterns larger than 4 KiB and strings larger than 4 MiB are rejected.
+    ///
+    /// ```python
+    /// regex.match("([a-z]+)-([0-9]+)", "foo-12 bar") == ["foo-12", "foo", "12"]
+    /// regex.match("[0-9]+", "foo-12") == None
+    /// ```
+    fn r#match<'v>(
+        #[starlark(require = pos)] pattern: &str,
+        #[starlark(require = pos)] string: &str,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Value<'v>> {
+        check_input_size("regex.match", string)?;
+        let regex = compile("match", pattern)?;
+        // Matches are leftmost, so if any match starts at the beginning this one does.
+        Ok(match regex.captures(string) {
+            Some(captures) if captures.get(0).map_or(false, |m| m.start() == 0) => {
+                alloc_groups(&captures, heap)
+            }
+            _ => Value::new_none(),
+        })
+    }
+
+    /// Like `regex.match`, but the match can start anywhere in the string.
+    ///
+    /// ```python
+    /// regex.search("[0-9]+", "foo-12") == ["12"]
+    /// ```
+    fn search<'v>(
+        #[starlark(require = pos)] pattern: &str,
+        #[starlark(require = pos)] string: &str,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Value<'v>> {
+        check_input_size("regex.search", string)?;
+        let regex = compile("search", pattern)?;
+        Ok(match regex.captures(string) {
+            Some(captures) => alloc_groups(&captures, heap),
+            None => Value::new_none(),
+        })
+    }
+
+    /// Returns all the non-overlapping matches of a regular expression in a string. As in
+    /// Python, the elements are the whole matches if the pattern has no capture groups, the
+    /// first group if it has one, and a tuple of the groups otherwise.
+    ///
+    /// ```python
+    /// regex.findall("[0-9]+", "a1 b22") == ["1", "22"]
+    /// regex.findall("([a-z])([0-9]+)", "a1 b22") == [("a", "1"), ("b", "22")]
+    /// ```
+    fn findall<'v>(
+        #[starlark(require = pos)] pattern: &str,
+        #[starlark(require = pos)] string: &str,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Value<'v>> {
+        check_input_size("regex.findall", string)?;
+        let regex = compile("findall", pattern)?;
+        let group = |m: Option<regex::Match>| heap.alloc(m.map_or("", |m| m.as_str()));
+        let groups = regex.captures_len() - 1;
+        let matches = regex.captures_iter(string).map(|captures| match groups {
+            0 => group(captures.get(0)),
+            1 => group(captures.get(1)),
+            _ => heap.alloc(AllocTuple(captures.iter().skip(1).map(group))),
+        });
+        Ok(heap.alloc(AllocList(matches)))
+    }
+
+    /// Replaces the matches of a regular expression in a string, all of them or the first
+    /// `count` ones. The replacement can refer to capture groups with `$1` or `${name}`, use
+    /// `$$` for a literal `$`.
+    ///
+    /// ```python
+    /// regex.replace("([a-z]+)-([0-9]+)", "foo-1 bar-2", "$2-$1") == "1-foo 2-bar"
+    /// regex.replace("[0-9]", "a1b2", "_", count = 1) == "a_b2"
+    /// ```
+    fn replace(
+        #[starlark(require = pos)] pattern: &str,
+        #[starlark(require = pos)] string: &str,
+        #[starlark(require = pos)] replacement: &str,
+        #[starlark(require = named, default = 0)] count: usize,
+    ) -> anyhow::Result<String> {
+        check_input_size("regex.replace", string)?;
+        check_input_size("regex.replace", replacement)?;
+        let regex = compile("replace", pattern)?;
+        Ok(regex.replacen(string, count, replacement).into_owned())
+    }
+}
+
+/// Regular expression functions, under the `regex` namespace, that we include in all contexts.
+pub fn register_regex(builder: &mut GlobalsBuilder) {
+    builder.struct_("regex", regex_members);
+}
+
+#[cfg(test)]
+mod tests {
+    use starlark::assert::Assert;
+
+    use crate::functions::regex::register_regex;
+
+    #[test]
+    fn test_regex() {
+        let mut a = Assert::new();
+        a.globals_add(register_regex);
+        a.pass(
+            r#"
+assert_eq(regex.match("([a-z]+)-([0-9]+)", "foo-12 bar"), ["foo-12", "foo", "12"])
+assert_eq(regex.match("[0-9]+", "foo-12"), None)
+assert_eq(regex.match("a(x)?b", "ab"), ["ab", None])
+assert_eq(regex.search("[0-9]+", "foo-12"), ["12"])
+assert_eq(regex.search("[0-9]+", "foo"), None)
+assert_eq(regex.findall("[0-9]+", "a1 b22"), ["1", "22"])
+assert_eq(regex.findall("[a-z]([0-9]+)", "a1 b22"), ["1", "22"])
+assert_eq(regex.findall("([a-z])([0-9]+)", "a1 b22"), [("a", "1"), ("b", "22")])
+assert_eq(regex.replace("([a-z]+)-([0-9]+)", "foo-1 bar-2", "$2-$1"), "1-foo 2-bar")
+assert_eq(regex.replace("[0-9]", "a1b2", "_", count = 1), "a_b2")
+"#,
+        );
+        a.fail("regex.match('(', 'x')", "Invalid pattern");
+    }
+
+    #[test]
+    fn test_limits() {
+        let mut a = Assert::new();
+        a.globals_add(register_regex);
+        a.fail("regex.search('x' * 4097, 'x')", "exceeds the limit");
+        a.fail("regex.search('x', 'x' * 4194305)", "exceeds the limit");
+        a.fail("regex.search('(a{1000}){1000}', 'a')", "Invalid pattern");
+    }
+}
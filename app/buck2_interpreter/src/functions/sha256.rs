@@ -11,15 +11,19 @@ use sha2::Digest;
 use sha2::Sha256;
 use starlark::environment::GlobalsBuilder;
 
+use crate::functions::encoding::check_input_size;
+
 /// Contains functions that we include in all contexts.
 #[starlark_module]
 pub fn register_sha256(builder: &mut GlobalsBuilder) {
     /// Computes a sha256 digest for a string. Returns the hex representation of the digest.
+    /// Inputs larger than 4 MiB are rejected.
     ///
     /// ```python
     /// sha256("Buck2 is the best build system") == "bb99a3f19ecba6c4d2c7cd321b63b669684c713881baae21a6b1d759b3ec6ac9"
     /// ```
     fn sha256(val: &str) -> anyhow::Result<String> {
+        check_input_size("sha256", val)?;
         let hash = Sha256::digest(val.as_bytes());
         Ok(hex::encode(hash))
     }
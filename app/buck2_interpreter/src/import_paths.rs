@@ -13,7 +13,7 @@ use allocative::Allocative;
 use async_trait::async_trait;
 use buck2_common::dice::cells::HasCellResolver;
 use buck2_common::legacy_configs::dice::HasLegacyConfigs;
-use buck2_common::legacy_configs::LegacyBuckConfig;
+use buck2_common::legacy_configs::view::LegacyBuckConfigView;
 use buck2_common::result::SharedResult;
 use buck2_common::result::ToUnsharedResultExt;
 use buck2_core::bzl::ImportPath;
@@ -27,16 +27,20 @@ use dupe::Dupe;
 use more_futures::cancellation::CancellationContext;
 
 use crate::package_imports::PackageImplicitImports;
+use crate::parse_import::parse_import;
 
 #[derive(PartialEq, Allocative)]
 pub struct ImplicitImportPaths {
     pub root_import: Option<ImportPath>,
     pub package_imports: PackageImplicitImports,
+    /// Files whose public symbols are added to the prelude for the build files of the cell, in
+    /// order: symbols of a later extension shadow the ones of earlier extensions.
+    pub prelude_extensions: Vec<ImportPath>,
 }
 
 impl ImplicitImportPaths {
     pub fn parse(
-        config: &LegacyBuckConfig,
+        config: &dyn LegacyBuckConfigView,
         cell_name: BuildFileCell,
         cell_alias_resolver: &CellAliasResolver,
     ) -> anyhow::Result<ImplicitImportPaths> {
@@ -44,9 +48,9 @@ impl ImplicitImportPaths {
         // normal imports. e.g. it uses `cell//path/to/file.bzl` instead of
         // `cell//path/to:file.bzl`.
         let root_import = config
-            .get("buildfile", "includes")
+            .get("buildfile", "includes")?
             .map(|i| {
-                let (cell_alias, path): (&str, &str) = i.split_once("//").unwrap_or(("", i));
+                let (cell_alias, path): (&str, &str) = i.split_once("//").unwrap_or(("", &*i));
                 let path = CellRelativePathBuf::try_from(path.to_owned())?;
                 let path = CellPath::new(cell_alias_resolver.resolve(cell_alias)?, path.to_buf());
 
@@ -58,11 +62,31 @@ impl ImplicitImportPaths {
         let package_imports = PackageImplicitImports::new(
             cell_name,
             cell_alias_resolver.dupe(),
-            config.get("buildfile", "package_includes"),
+            config.get("buildfile", "package_includes")?.as_deref(),
         )?;
+        let prelude_extensions = match config.get("buildfile", "prelude_extensions")? {
+            Some(extensions) => {
+                let root_path = CellPath::new(
+                    cell_name.name(),
+                    CellRelativePathBuf::unchecked_new("".to_owned()),
+                );
+                extensions
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|extension| !extension.is_empty())
+                    .map(|extension| {
+                        let path = parse_import(cell_alias_resolver, &root_path, extension)?;
+                        // Like package imports, only used by build files of this cell.
+                        ImportPath::new(path, cell_name)
+                    })
+                    .collect::<anyhow::Result<_>>()?
+            }
+            None => Vec::new(),
+        };
         Ok(ImplicitImportPaths {
             root_import,
             package_imports,
+            prelude_extensions,
         })
     }
 
@@ -100,9 +124,9 @@ impl HasImportPaths for DiceComputations {
                 ctx: &DiceComputations,
                 _cancellation: &CancellationContext,
             ) -> Self::Value {
-                let config = ctx
-                    .get_legacy_config_for_cell(self.cell_name.name())
-                    .await?;
+                // Only depend on the properties read, so that unrelated buckconfig changes do not
+                // recompute the interpreters of the cell.
+                let config = ctx.get_legacy_config_on_dice(self.cell_name.name()).await?;
                 let cell_resolver = ctx.get_cell_resolver().await?;
                 let cell_alias_resolver = cell_resolver
                     .get(self.cell_name.name())?
@@ -0,0 +1,123 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Benchmark attr interning on a synthetic graph of 1M unconfigured targets.
+//!
+//! Besides timing, prints the memory taken by the coerced attrs of the whole graph
+//! with and without interning, as measured by `allocative`.
+
+use allocative::Allocative;
+use allocative::FlameGraphBuilder;
+use buck2_interpreter_for_build::attrs::coerce::testing::coercion_ctx;
+use buck2_node::attrs::attr_type::list::ListLiteral;
+use buck2_node::attrs::attr_type::string::StringLiteral;
+use buck2_node::attrs::coerced_attr::CoercedAttr;
+use buck2_node::attrs::coercion_context::AttrCoercionContext;
+use buck2_util::arc_str::ArcSlice;
+use buck2_util::arc_str::ArcStr;
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::Criterion;
+
+const PACKAGES: usize = 10_000;
+const TARGETS_PER_PACKAGE: usize = 100;
+
+/// Flags shared by many targets, as is typical for attrs set by macros.
+const COMMON_FLAGS: &[&[&str]] = &[
+    &["-Wall", "-Werror"],
+    &["-Wall", "-Werror", "-O2"],
+    &["-fno-exceptions", "-fno-rtti"],
+];
+
+/// Attrs of one synthetic target: a unique name, a label from a small pool and a list of flags
+/// shared with other targets.
+fn target_attrs(
+    package: usize,
+    target: usize,
+    intern_str: &dyn Fn(&str) -> ArcStr,
+    intern_list: &dyn Fn(Vec<CoercedAttr>) -> ArcSlice<CoercedAttr>,
+) -> [CoercedAttr; 3] {
+    let name = CoercedAttr::String(StringLiteral(intern_str(&format!("target{}", target))));
+    let label = CoercedAttr::String(StringLiteral(intern_str(&format!(
+        "//third-party/lib{}:lib",
+        (package * 7 + target) % 1000
+    ))));
+    let flags = COMMON_FLAGS[(package + target) % COMMON_FLAGS.len()]
+        .iter()
+        .map(|f| CoercedAttr::String(StringLiteral(intern_str(f))))
+        .collect();
+    let flags = CoercedAttr::List(ListLiteral(intern_list(flags)));
+    [name, label, flags]
+}
+
+/// Coerce attrs of a package the way a build file evaluation does.
+fn package_attrs_interned(package: usize) -> Vec<[CoercedAttr; 3]> {
+    // New context for each package, like each build file gets in buck2.
+    let ctx = coercion_ctx();
+    (0..TARGETS_PER_PACKAGE)
+        .map(|target| {
+            target_attrs(package, target, &|s| ctx.intern_str(s), &|l| {
+                ctx.intern_list(l)
+            })
+        })
+        .collect()
+}
+
+fn package_attrs_not_interned(package: usize) -> Vec<[CoercedAttr; 3]> {
+    (0..TARGETS_PER_PACKAGE)
+        .map(|target| {
+            target_attrs(package, target, &|s| ArcStr::from(s), &|l| {
+                ArcSlice::from_iter(l)
+            })
+        })
+        .collect()
+}
+
+fn allocated_bytes(graph: &dyn Allocative) -> u64 {
+    let mut builder = FlameGraphBuilder::default();
+    builder.visit_root(graph);
+    builder
+        .finish()
+        .flamegraph()
+        .lines()
+        .filter_map(|line| line.rsplit(' ').next()?.parse::<u64>().ok())
+        .sum()
+}
+
+fn report_memory() {
+    let interned: Vec<_> = (0..PACKAGES).map(package_attrs_interned).collect();
+    let not_interned: Vec<_> = (0..PACKAGES).map(package_attrs_not_interned).collect();
+    eprintln!(
+        "attrs of {} targets: {} bytes interned, {} bytes not interned",
+        PACKAGES * TARGETS_PER_PACKAGE,
+        allocated_bytes(&interned),
+        allocated_bytes(&not_interned),
+    );
+}
+
+fn bench(c: &mut Criterion) {
+    report_memory();
+
+    let mut package = 0;
+    c.bench_function("intern_package_attrs", |b| {
+        b.iter(|| {
+            package = (package + 1) % PACKAGES;
+            package_attrs_interned(package)
+        })
+    });
+    c.bench_function("coerce_package_attrs_without_interning", |b| {
+        b.iter(|| {
+            package = (package + 1) % PACKAGES;
+            package_attrs_not_interned(package)
+        })
+    });
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
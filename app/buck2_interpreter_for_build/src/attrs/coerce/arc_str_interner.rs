@@ -13,6 +13,7 @@ use buck2_util::arc_str::ArcStr;
 use dupe::Dupe;
 use hashbrown::raw::RawTable;
 
+use crate::attrs::coerce::global_interner::GLOBAL_STR_INTERNER;
 use crate::attrs::coerce::str_hash::str_hash;
 
 pub(crate) struct ArcStrInterner {
@@ -38,7 +39,7 @@ impl ArcStrInterner {
             return v.dupe();
         }
 
-        let value = ArcStr::from(s);
+        let value = GLOBAL_STR_INTERNER.intern(hash, s);
         cache.insert(hash, (hash, value.dupe()), |(h, _v)| *h);
         value
     }
@@ -15,6 +15,7 @@ use buck2_interpreter::selector::StarlarkSelectorGen;
 use buck2_node::attrs::attr_type::AttrType;
 use buck2_node::attrs::coerced_attr::CoercedAttr;
 use buck2_node::attrs::coerced_attr::CoercedSelector;
+use buck2_node::attrs::coerced_attr::EXEC_SELECT_KEY_PREFIX;
 use buck2_node::attrs::coercion_context::AttrCoercionContext;
 use buck2_node::attrs::configurable::AttrIsConfigurable;
 use starlark::values::dict::DictRef;
@@ -35,6 +36,11 @@ enum SelectError {
     SelectCannotBeUsedForNonConfigurableAttr,
     #[error("duplicate `\"DEFAULT\"` key in `select()` (internal error)")]
     DuplicateDefaultKey,
+    #[error(
+        "select() cannot mix `exec:` keys with other keys: `exec:` keys are matched against the \
+        execution platform, other keys against the target configuration"
+    )]
+    MixedExecKeys,
 }
 
 pub trait CoercedAttrExr: Sized {
@@ -79,6 +85,9 @@ impl CoercedAttrExr for CoercedAttr {
                             Vec::with_capacity(dict.len().saturating_sub(has_default as usize));
 
                         let mut default = None;
+                        // Whether the keys have the `exec:` prefix, checked to be the same for all
+                        // the keys.
+                        let mut exec = None;
                         for (k, v) in dict.iter() {
                             let k = k.unpack_str().ok_or_else(|| {
                                 anyhow::anyhow!(SelectError::KeyNotString(k.to_repr()))
@@ -93,6 +102,13 @@ impl CoercedAttrExr for CoercedAttr {
                                 }
                                 default = Some(v);
                             } else {
+                                let (is_exec, k) = match k.strip_prefix(EXEC_SELECT_KEY_PREFIX) {
+                                    Some(k) => (true, k),
+                                    None => (false, k),
+                                };
+                                if *exec.get_or_insert(is_exec) != is_exec {
+                                    return Err(SelectError::MixedExecKeys.into());
+                                }
                                 let target = ctx.coerce_target(k)?;
                                 entries.push((target, v));
                             }
@@ -100,10 +116,13 @@ impl CoercedAttrExr for CoercedAttr {
 
                         assert_eq!(entries.capacity(), entries.len());
 
-                        Ok(CoercedAttr::Selector(Box::new(CoercedSelector::new(
-                            ctx.intern_select(entries),
-                            default,
-                        )?)))
+                        let entries = ctx.intern_select(entries);
+                        let selector = if exec == Some(true) {
+                            CoercedSelector::new_exec(entries, default)?
+                        } else {
+                            CoercedSelector::new(entries, default)?
+                        };
+                        Ok(CoercedAttr::Selector(Box::new(selector)))
                     } else {
                         Err(anyhow::anyhow!(SelectError::ValueNotDict(v.to_repr())))
                     }
@@ -144,6 +163,7 @@ mod tests {
     use std::sync::Arc;
 
     use buck2_core::collections::ordered_map::OrderedMap;
+    use buck2_core::collections::unordered_map::UnorderedMap;
     use buck2_core::configuration::config_setting::ConfigSettingData;
     use buck2_core::configuration::constraints::ConstraintKey;
     use buck2_core::configuration::constraints::ConstraintValue;
@@ -155,10 +175,15 @@ mod tests {
     use buck2_core::target::label::TargetLabel;
     use buck2_node::attrs::attr_type::bool::BoolLiteral;
     use buck2_node::attrs::attr_type::string::StringLiteral;
+    use buck2_node::attrs::attr_type::AttrType;
     use buck2_node::attrs::coerced_attr::CoercedAttr;
     use buck2_node::attrs::coerced_attr::CoercedSelector;
     use buck2_node::attrs::configuration_context::AttrConfigurationContext;
+    use buck2_node::attrs::configured_attr::ConfiguredAttr;
     use buck2_node::attrs::fmt_context::AttrFmtContext;
+    use buck2_node::configuration::resolved::ConfigurationNode;
+    use buck2_node::configuration::resolved::ConfigurationSettingKey;
+    use buck2_node::configuration::resolved::ResolvedConfiguration;
     use buck2_util::arc_str::ArcSlice;
     use buck2_util::arc_str::ArcStr;
     use dupe::Dupe;
@@ -386,4 +411,107 @@ mod tests {
             .to_string()
         );
     }
+
+    #[test]
+    fn test_exec_select() {
+        struct ExecSelectTestConfigurationContext {
+            exec_resolved_cfg: Option<ResolvedConfiguration>,
+        }
+
+        impl AttrConfigurationContext for ExecSelectTestConfigurationContext {
+            fn matches<'a>(&'a self, _label: &TargetLabel) -> Option<&'a ConfigSettingData> {
+                panic!("`exec:` keys must not be matched against the target configuration")
+            }
+
+            fn cfg(&self) -> ConfigurationNoExec {
+                ConfigurationNoExec::new(ConfigurationData::testing_new())
+            }
+
+            fn exec_cfg(&self) -> ConfigurationNoExec {
+                panic!("not used in test")
+            }
+
+            fn exec_resolved_cfg(&self) -> Option<&ResolvedConfiguration> {
+                self.exec_resolved_cfg.as_ref()
+            }
+
+            fn toolchain_cfg(&self) -> ConfigurationWithExec {
+                panic!("not used in test")
+            }
+
+            fn platform_cfg(&self, _label: &TargetLabel) -> anyhow::Result<ConfigurationData> {
+                panic!("not used in test")
+            }
+
+            fn resolved_transitions(
+                &self,
+            ) -> &OrderedMap<Arc<TransitionId>, Arc<TransitionApplied>> {
+                panic!("not used in test")
+            }
+        }
+
+        let linux = TargetLabel::testing_parse("prelude//os:linux");
+        let macos = TargetLabel::testing_parse("prelude//os:macos");
+        let setting = |label: &TargetLabel, matches| {
+            (
+                ConfigurationSettingKey(label.dupe()),
+                ConfigurationNode::new(
+                    ConfigurationData::testing_new(),
+                    label.dupe(),
+                    ConfigSettingData {
+                        constraints: BTreeMap::new(),
+                        buckconfigs: BTreeMap::new(),
+                    },
+                    matches,
+                ),
+            )
+        };
+        let exec_resolved_cfg = ResolvedConfiguration::new(
+            ConfigurationNoExec::new(ConfigurationData::testing_new()),
+            UnorderedMap::from_iter([setting(&linux, true), setting(&macos, false)]),
+        );
+
+        let selector = |default| {
+            CoercedAttr::Selector(Box::new(
+                CoercedSelector::new_exec(
+                    ArcSlice::new([
+                        (macos.dupe(), CoercedAttr::Int(1)),
+                        (linux.dupe(), CoercedAttr::Int(2)),
+                    ]),
+                    default,
+                )
+                .unwrap(),
+            ))
+        };
+        let configure = |attr: &CoercedAttr, exec_resolved_cfg: Option<&ResolvedConfiguration>| {
+            let ctx = ExecSelectTestConfigurationContext {
+                exec_resolved_cfg: exec_resolved_cfg.cloned(),
+            };
+            attr.configure(&AttrType::int(), &ctx)
+        };
+
+        assert_eq!(
+            ConfiguredAttr::Int(2),
+            configure(&selector(None), Some(&exec_resolved_cfg)).unwrap()
+        );
+        // The execution platform is not resolved yet.
+        assert_eq!(
+            ConfiguredAttr::Int(0),
+            configure(&selector(Some(CoercedAttr::Int(0))), None).unwrap()
+        );
+        assert!(
+            configure(&selector(None), None)
+                .unwrap_err()
+                .to_string()
+                .contains("exec:prelude//os:linux")
+        );
+
+        assert_eq!(
+            r#"{"__type":"selector","entries":{"exec:prelude//os:linux":2,"exec:prelude//os:macos":1}}"#,
+            selector(None)
+                .to_json(&AttrFmtContext::NO_CONTEXT)
+                .unwrap()
+                .to_string()
+        );
+    }
 }
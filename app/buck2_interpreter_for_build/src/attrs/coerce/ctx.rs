@@ -11,6 +11,7 @@ use std::cell::RefCell;
 use std::fmt;
 use std::fmt::Debug;
 
+use buck2_common::package_boundary::PackageBoundaryMode;
 use buck2_common::package_listing::listing::PackageListing;
 use buck2_core::cells::name::CellName;
 use buck2_core::cells::CellResolver;
@@ -42,6 +43,9 @@ use tracing::info;
 
 use super::interner::AttrCoercionInterner;
 use crate::attrs::coerce::arc_str_interner::ArcStrInterner;
+use crate::attrs::coerce::global_interner::GLOBAL_DICT_INTERNER;
+use crate::attrs::coerce::global_interner::GLOBAL_LIST_INTERNER;
+use crate::attrs::coerce::global_interner::GLOBAL_SELECT_INTERNER;
 use crate::attrs::coerce::query_functions::QUERY_FUNCTIONS;
 use crate::attrs::coerce::str_hash::str_hash;
 
@@ -59,6 +63,11 @@ enum BuildAttrCoercionContextError {
         "Directory `{1}` of package `{0}` may not cover any subpackages, but includes subpackage `{2}`."
     )]
     SourceDirectoryIncludesSubPackage(PackageLabel, String, PackageRelativePathBuf),
+    #[error(
+        "Source file `{1}` of package `{0}` belongs to subpackage `{2}` \
+        (package boundaries are enforced in this cell)."
+    )]
+    SourceFileInSubPackage(PackageLabel, String, PackageRelativePathBuf),
 }
 
 /// An incomplete attr coercion context. Will be replaced with a real one later.
@@ -71,8 +80,8 @@ pub struct BuildAttrCoercionContext {
     /// evaluated. The latter case occurs when default values for attributes
     /// are coerced when a UDR is declared.
     enclosing_package: Option<(PackageLabel, PackageListing)>,
-    /// Does this package (if present) have a package boundary exception on it.
-    package_boundary_exception: bool,
+    /// How package boundary violations of this package (if present) are reported.
+    package_boundary: PackageBoundaryMode,
     /// Allocator for `label_cache`.
     alloc: Bump,
     /// Label coercion cache. We use `RawTable` where because `HashMap` API
@@ -103,39 +112,43 @@ impl BuildAttrCoercionContext {
         cell_resolver: CellResolver,
         cell_name: CellName,
         enclosing_package: Option<(PackageLabel, PackageListing)>,
-        package_boundary_exception: bool,
+        package_boundary: PackageBoundaryMode,
     ) -> Self {
         Self {
             cell_resolver,
             cell_name,
             enclosing_package,
-            package_boundary_exception,
+            package_boundary,
             alloc: Bump::new(),
             label_cache: RefCell::new(RawTable::new()),
             str_interner: ArcStrInterner::new(),
-            list_interner: AttrCoercionInterner::new(),
-            dict_interner: AttrCoercionInterner::new(),
-            select_interner: AttrCoercionInterner::new(),
+            list_interner: AttrCoercionInterner::with_global(&GLOBAL_LIST_INTERNER),
+            dict_interner: AttrCoercionInterner::with_global(&GLOBAL_DICT_INTERNER),
+            select_interner: AttrCoercionInterner::with_global(&GLOBAL_SELECT_INTERNER),
         }
     }
 
     pub fn new_no_package(cell_resolver: CellResolver, cell_name: CellName) -> Self {
-        Self::new(cell_resolver, cell_name, None, false)
+        Self::new(cell_resolver, cell_name, None, PackageBoundaryMode::Default)
     }
 
     pub fn new_with_package(
         cell_resolver: CellResolver,
         enclosing_package: (PackageLabel, PackageListing),
-        package_boundary_exception: bool,
+        package_boundary: PackageBoundaryMode,
     ) -> Self {
         Self::new(
             cell_resolver,
             enclosing_package.0.cell_name(),
             Some(enclosing_package),
-            package_boundary_exception,
+            package_boundary,
         )
     }
 
+    pub(crate) fn package_boundary(&self) -> PackageBoundaryMode {
+        self.package_boundary
+    }
+
     pub fn parse_pattern<P: PatternType>(&self, value: &str) -> anyhow::Result<ParsedPattern<P>> {
         ParsedPattern::parsed_opt_absolute(
             value,
@@ -228,10 +241,14 @@ impl AttrCoercionContext for BuildAttrCoercionContext {
                     value.to_owned(),
                     subpackage.to_owned(),
                 );
-                if self.package_boundary_exception {
-                    info!("{} (could be due to a package boundary violation)", e);
-                } else {
-                    soft_error!("source_directory_includes_subpackage", e.into())?;
+                match self.package_boundary {
+                    PackageBoundaryMode::Exception => {
+                        info!("{} (could be due to a package boundary violation)", e);
+                    }
+                    PackageBoundaryMode::Default => {
+                        soft_error!("source_directory_includes_subpackage", e.into())?;
+                    }
+                    PackageBoundaryMode::Enforced => return Err(e.into()),
                 }
             }
             let files = listing.files_within(&path).duped().collect();
@@ -240,9 +257,19 @@ impl AttrCoercionContext for BuildAttrCoercionContext {
                 files,
             })))
         } else {
+            if self.package_boundary == PackageBoundaryMode::Enforced {
+                if let Some(subpackage) = listing.subpackage_containing(path) {
+                    return Err(BuildAttrCoercionContextError::SourceFileInSubPackage(
+                        package.dupe(),
+                        value.to_owned(),
+                        subpackage.to_owned(),
+                    )
+                    .into());
+                }
+            }
             let e =
                 BuildAttrCoercionContextError::SourceFileMissing(package.dupe(), value.to_owned());
-            if self.package_boundary_exception {
+            if self.package_boundary == PackageBoundaryMode::Exception {
                 info!("{} (could be due to a package boundary violation)", e);
             } else {
                 soft_error!("source_file_missing", e.into())?;
@@ -0,0 +1,182 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::sync::Arc;
+
+use allocative::Allocative;
+use buck2_core::target::label::TargetLabel;
+use buck2_node::attrs::coerced_attr::CoercedAttr;
+use buck2_util::arc_str::ArcSlice;
+use buck2_util::arc_str::ArcStr;
+use dupe::Dupe;
+use hashbrown::raw::RawTable;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+
+use crate::attrs::coerce::interner::Equiv;
+
+/// Process-wide interner for strings, shared by all build files.
+#[allocative::root]
+pub(crate) static GLOBAL_STR_INTERNER: Lazy<GlobalAttrInterner<ArcStr>> =
+    Lazy::new(GlobalAttrInterner::new);
+
+#[allocative::root]
+pub(crate) static GLOBAL_LIST_INTERNER: Lazy<GlobalAttrInterner<ArcSlice<CoercedAttr>>> =
+    Lazy::new(GlobalAttrInterner::new);
+
+#[allocative::root]
+pub(crate) static GLOBAL_DICT_INTERNER: Lazy<
+    GlobalAttrInterner<ArcSlice<(CoercedAttr, CoercedAttr)>>,
+> = Lazy::new(GlobalAttrInterner::new);
+
+#[allocative::root]
+pub(crate) static GLOBAL_SELECT_INTERNER: Lazy<
+    GlobalAttrInterner<ArcSlice<(TargetLabel, CoercedAttr)>>,
+> = Lazy::new(GlobalAttrInterner::new);
+
+/// Values which can tell whether the interner holds the only reference to them.
+pub(crate) trait InternedRefCount {
+    fn is_unique(&self) -> bool;
+}
+
+impl InternedRefCount for ArcStr {
+    fn is_unique(&self) -> bool {
+        ArcStr::is_unique(self)
+    }
+}
+
+impl<T> InternedRefCount for ArcSlice<T> {
+    fn is_unique(&self) -> bool {
+        ArcSlice::is_unique(self)
+    }
+}
+
+impl<T> InternedRefCount for Arc<T> {
+    fn is_unique(&self) -> bool {
+        Arc::strong_count(self) == 1
+    }
+}
+
+const SHARDS: usize = 64;
+
+/// Do not bother collecting shards smaller than this.
+const MIN_GC_LEN: usize = 1024;
+
+/// Second level of attr interning, shared between build files.
+///
+/// [`AttrCoercionInterner`](crate::attrs::coerce::interner::AttrCoercionInterner) only
+/// deduplicates values within a single build file, but in large repos the same strings and
+/// label lists are repeated across many packages, and unconfigured target nodes for all of them
+/// are kept in memory. Per-file interners fall back to this one on a miss.
+///
+/// Values are kept alive by the interner itself, so entries referenced only by the interner
+/// are dropped whenever a shard doubles in size since the last collection.
+#[derive(Allocative)]
+pub(crate) struct GlobalAttrInterner<T: 'static> {
+    shards: Vec<Mutex<GlobalAttrInternerShard<T>>>,
+}
+
+#[derive(Allocative)]
+struct GlobalAttrInternerShard<T: 'static> {
+    table: RawTable<(u64, T)>,
+    /// Length at which entries no longer referenced outside the interner are dropped.
+    gc_len: usize,
+}
+
+impl<T: Dupe + InternedRefCount> GlobalAttrInterner<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            shards: (0..SHARDS)
+                .map(|_| {
+                    Mutex::new(GlobalAttrInternerShard {
+                        table: RawTable::new(),
+                        gc_len: MIN_GC_LEN,
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    /// Intern a value. `hash` must be computed the same way for all values in this interner.
+    pub(crate) fn intern<S: Equiv<T> + Into<T>>(&self, hash: u64, internable: S) -> T {
+        // Low bits select the bucket within a shard, and high bits are the control byte,
+        // so pick the shard from the middle bits.
+        let mut shard = self.shards[(hash >> 32) as usize % SHARDS].lock();
+
+        if let Some((_h, v)) = shard.table.get(hash, |(_h, v)| internable.equivalent(v)) {
+            return v.dupe();
+        }
+
+        if shard.table.len() >= shard.gc_len {
+            shard.gc();
+        }
+
+        let value: T = internable.into();
+        shard.table.insert(hash, (hash, value.dupe()), |(h, _v)| *h);
+        value
+    }
+
+    /// Number of interned values, including ones not yet collected.
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.shards.iter().map(|s| s.lock().table.len()).sum()
+    }
+}
+
+impl<T: InternedRefCount> GlobalAttrInternerShard<T> {
+    fn gc(&mut self) {
+        // Values can only be cloned from the table while holding the lock,
+        // so a value referenced only by the table can't be resurrected concurrently.
+        unsafe {
+            for bucket in self.table.iter() {
+                if bucket.as_ref().1.is_unique() {
+                    self.table.erase(bucket);
+                }
+            }
+        }
+        self.gc_len = (self.table.len() * 2).max(MIN_GC_LEN);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_util::arc_str::ArcStr;
+
+    use crate::attrs::coerce::global_interner::GlobalAttrInterner;
+    use crate::attrs::coerce::str_hash::str_hash;
+
+    #[test]
+    fn test_global_interner_shares_values() {
+        let interner: GlobalAttrInterner<ArcStr> = GlobalAttrInterner::new();
+        let foo0 = interner.intern(str_hash("foo"), "foo");
+        let foo1 = interner.intern(str_hash("foo"), "foo");
+        assert!(std::ptr::eq(foo0.as_ptr(), foo1.as_ptr()));
+    }
+
+    #[test]
+    fn test_global_interner_drops_unreferenced_values() {
+        let interner: GlobalAttrInterner<ArcStr> = GlobalAttrInterner::new();
+        let kept: Vec<ArcStr> = (0..1000)
+            .map(|i| {
+                let s = format!("kept{}", i);
+                interner.intern(str_hash(&s), s.as_str())
+            })
+            .collect();
+        for i in 0..200000 {
+            let s = format!("dropped{}", i);
+            interner.intern(str_hash(&s), s.as_str());
+        }
+        // Every shard has been collected at least once, and the values we kept survived.
+        assert!(interner.len() < 200000);
+        for (i, s) in kept.iter().enumerate() {
+            let again = interner.intern(str_hash(s), format!("kept{}", i).as_str());
+            assert!(std::ptr::eq(s.as_ptr(), again.as_ptr()));
+        }
+    }
+}
@@ -20,22 +20,37 @@ use dupe::Dupe;
 use hashbrown::raw::RawTable;
 use twox_hash::XxHash64;
 
+use crate::attrs::coerce::global_interner::GlobalAttrInterner;
+use crate::attrs::coerce::global_interner::InternedRefCount;
+
 /// An interner specific to our AttrCoercionContext used for interning different kinds of attributes.
 /// Things specific about this interner:
 /// - Requires interned values to be Dupe, so that you can intern both Arc<...> and specific Arc types like ArcStr.
 /// - Interner is not static, so it's not required to take up memory for the entire duration of the program.
-pub(crate) struct AttrCoercionInterner<T: Dupe + Hash + Eq, H = XxHash64> {
+///   It can be backed by a [`GlobalAttrInterner`] to also share values between build files.
+pub(crate) struct AttrCoercionInterner<T: Dupe + Hash + Eq + 'static, H = XxHash64> {
     /// We use `RawTable` where because `HashMap` API
     /// requires either computing hash twice (for get, then for insert) or
     /// allocating a key to perform a query using `entry` API.
     cache: RefCell<RawTable<(u64, T)>>,
+    global: Option<&'static GlobalAttrInterner<T>>,
     _marker: marker::PhantomData<H>,
 }
 
-impl<T: Dupe + Hash + Eq, H: Hasher + Default> AttrCoercionInterner<T, H> {
+impl<T: Dupe + Hash + Eq + InternedRefCount, H: Hasher + Default> AttrCoercionInterner<T, H> {
     pub(crate) fn new() -> Self {
         Self {
             cache: RefCell::new(RawTable::new()),
+            global: None,
+            _marker: marker::PhantomData,
+        }
+    }
+
+    /// Interner which looks up values missing in this interner in the global one.
+    pub(crate) fn with_global(global: &'static GlobalAttrInterner<T>) -> Self {
+        Self {
+            cache: RefCell::new(RawTable::new()),
+            global: Some(global),
             _marker: marker::PhantomData,
         }
     }
@@ -53,7 +68,10 @@ impl<T: Dupe + Hash + Eq, H: Hasher + Default> AttrCoercionInterner<T, H> {
             return v.dupe();
         }
 
-        let value: T = internable.into();
+        let value: T = match self.global {
+            Some(global) => global.intern(hash, internable),
+            None => internable.into(),
+        };
         cache.insert(hash, (hash, value.dupe()), |(h, _v)| *h);
         value
     }
@@ -17,6 +17,7 @@ pub mod attr_type;
 pub mod coerced_attr;
 pub mod ctx;
 pub mod error;
+mod global_interner;
 mod interner;
 pub mod query_functions;
 pub(crate) mod str_hash;
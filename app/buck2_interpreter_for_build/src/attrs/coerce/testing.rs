@@ -10,6 +10,7 @@
 use std::collections::HashMap;
 
 use buck2_common::legacy_configs::LegacyBuckConfig;
+use buck2_common::package_boundary::PackageBoundaryMode;
 use buck2_common::package_listing::listing::testing::PackageListingExt;
 use buck2_common::package_listing::listing::PackageListing;
 use buck2_core::bzl::ImportPath;
@@ -59,7 +60,11 @@ pub fn coercion_ctx_listing(package_listing: PackageListing) -> impl AttrCoercio
         ),
     ]);
 
-    BuildAttrCoercionContext::new_with_package(cell_resolver, (package, package_listing), false)
+    BuildAttrCoercionContext::new_with_package(
+        cell_resolver,
+        (package, package_listing),
+        PackageBoundaryMode::Default,
+    )
 }
 
 fn cell_resolver() -> CellResolver {
@@ -8,6 +8,9 @@
  */
 
 use buck2_interpreter::functions::dedupe::dedupe;
+use buck2_interpreter::functions::encoding::register_encoding;
+use buck2_interpreter::functions::fail::register_fail;
+use buck2_interpreter::functions::regex::register_regex;
 use buck2_interpreter::functions::sha256::register_sha256;
 use buck2_interpreter::globspec::GlobSpec;
 use buck2_interpreter::selector::register_select;
@@ -59,7 +62,12 @@ pub fn native_module(builder: &mut GlobalsBuilder) {
     ) -> anyhow::Result<Value<'v>> {
         let extra = ModuleInternals::from_context(eval, "glob")?;
         let spec = GlobSpec::new(&include, &exclude)?;
-        let res = extra.resolve_glob(&spec).map(|path| path.as_str());
+        extra.check_glob_package_boundary(&include)?;
+        let res: Vec<&str> = extra
+            .resolve_glob(&spec)
+            .map(|path| path.as_str())
+            .collect();
+        extra.record_glob_results(res.len(), eval)?;
         Ok(eval.heap().alloc(AllocList(res)))
     }
 
@@ -117,6 +125,9 @@ pub fn register_base_natives(registry: &mut GlobalsBuilder) {
     native_module(registry);
     register_select(registry);
     register_sha256(registry);
+    register_encoding(registry);
+    register_regex(registry);
+    register_fail(registry);
 }
 
 /// Configure globals for all three possible environments: `BUCK`, `bzl` and `bxl`.
@@ -20,6 +20,7 @@ use buck2_core::cells::build_file_cell::BuildFileCell;
 use buck2_core::package::PackageLabel;
 use buck2_interpreter::dice::starlark_profiler::GetStarlarkProfilerInstrumentation;
 use buck2_interpreter::file_loader::LoadedModule;
+use buck2_interpreter::path::PackageFilePath;
 use buck2_interpreter::path::StarlarkModulePath;
 use buck2_interpreter::starlark_profiler::StarlarkProfilerOrInstrumentation;
 use buck2_node::nodes::eval_result::EvaluationResult;
@@ -27,8 +28,12 @@ use dice::DiceComputations;
 use dice::Key;
 use dupe::Dupe;
 use more_futures::cancellation::CancellationContext;
+use starlark::values::OwnedFrozenValue;
+use starlark_map::small_map::SmallMap;
 
+use crate::interpreter::calculation::keys::BuildFileEvalKey;
 use crate::interpreter::calculation::keys::InterpreterResultsKey;
+use crate::interpreter::dice_calculation_delegate::BuildFileEvalDigest;
 use crate::interpreter::dice_calculation_delegate::HasCalculationDelegate;
 
 #[async_trait]
@@ -54,6 +59,20 @@ pub trait InterpreterCalculation {
         &self,
         path: &ImportPath,
     ) -> anyhow::Result<LoadedModule>;
+
+    /// Returns the modules loaded by a `PACKAGE` file, or `None` if there is no such file.
+    async fn get_package_file_imports(
+        &self,
+        path: &PackageFilePath,
+    ) -> anyhow::Result<Option<Vec<ImportPath>>>;
+
+    /// Returns the values written with `write_package_value` by the `PACKAGE` files applying to
+    /// a package (the `PACKAGE` files of the package and of its parents). This only depends on
+    /// these `PACKAGE` files, not on the build file of the package.
+    async fn get_package_values(
+        &self,
+        package: PackageLabel,
+    ) -> anyhow::Result<SmallMap<String, OwnedFrozenValue>>;
 }
 
 #[async_trait]
@@ -87,6 +106,44 @@ impl InterpreterCalculation for DiceComputations {
         &self,
         package: PackageLabel,
     ) -> anyhow::Result<Arc<EvaluationResult>> {
+        #[async_trait]
+        impl Key for BuildFileEvalKey {
+            type Value = SharedResult<(Arc<EvaluationResult>, Arc<BuildFileEvalDigest>)>;
+            async fn compute(
+                &self,
+                ctx: &DiceComputations,
+                _cancellation: &CancellationContext,
+            ) -> Self::Value {
+                let starlark_profiler_instrumentation =
+                    ctx.get_starlark_profiler_instrumentation().await?;
+                let (result, digest) = ctx
+                    .get_interpreter_calculator(
+                        self.0.cell_name(),
+                        BuildFileCell::new(self.0.cell_name()),
+                    )
+                    .await?
+                    .eval_build_file_with_digest(
+                        self.0.dupe(),
+                        &mut StarlarkProfilerOrInstrumentation::maybe_instrumentation(
+                            starlark_profiler_instrumentation,
+                        ),
+                    )
+                    .await?;
+                Ok((Arc::new(result), Arc::new(digest)))
+            }
+
+            fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+                match (x, y) {
+                    (Ok((_, x)), Ok((_, y))) => x == y,
+                    _ => false,
+                }
+            }
+
+            fn validity(x: &Self::Value) -> bool {
+                x.is_ok()
+            }
+        }
+
         #[async_trait]
         impl Key for InterpreterResultsKey {
             type Value = SharedResult<Arc<EvaluationResult>>;
@@ -95,9 +152,7 @@ impl InterpreterCalculation for DiceComputations {
                 ctx: &DiceComputations,
                 _cancellation: &CancellationContext,
             ) -> Self::Value {
-                ctx.get_interpreter_results_uncached(self.0.dupe())
-                    .await
-                    .shared_error()
+                Ok(ctx.compute(&BuildFileEvalKey(self.0.dupe())).await??.0)
             }
 
             fn equality(_: &Self::Value, _: &Self::Value) -> bool {
@@ -133,9 +188,37 @@ impl InterpreterCalculation for DiceComputations {
         let module_path = StarlarkModulePath::LoadFile(path);
         self.get_loaded_module(module_path).await
     }
+
+    async fn get_package_file_imports(
+        &self,
+        path: &PackageFilePath,
+    ) -> anyhow::Result<Option<Vec<ImportPath>>> {
+        Ok(self
+            .get_interpreter_calculator(path.cell(), path.build_file_cell())
+            .await?
+            .prepare_package_file_eval(path)
+            .await?
+            .map(|(_ast, deps)| {
+                let loaded_modules = deps.get_loaded_modules();
+                loaded_modules.imports().cloned().collect()
+            }))
+    }
+
+    async fn get_package_values(
+        &self,
+        package: PackageLabel,
+    ) -> anyhow::Result<SmallMap<String, OwnedFrozenValue>> {
+        self.get_interpreter_calculator(
+            package.cell_name(),
+            BuildFileCell::new(package.cell_name()),
+        )
+        .await?
+        .eval_package_values(package)
+        .await
+    }
 }
 
-mod keys {
+pub mod keys {
     use allocative::Allocative;
     use buck2_core::package::PackageLabel;
     use derive_more::Display;
@@ -144,6 +227,12 @@ mod keys {
     // Key for 'InterpreterCalculation::get_interpreter_results'
     #[derive(Clone, Dupe, Display, Debug, Eq, Hash, PartialEq, Allocative)]
     pub struct InterpreterResultsKey(pub PackageLabel);
+
+    /// The evaluation of a build file along with the digest of its inputs, so that an
+    /// evaluation with the same inputs as the previous one doesn't invalidate the result.
+    #[derive(Clone, Dupe, Display, Debug, Eq, Hash, PartialEq, Allocative)]
+    #[display(fmt = "BuildFileEval({})", _0)]
+    pub struct BuildFileEvalKey(pub PackageLabel);
 }
 
 pub mod testing {
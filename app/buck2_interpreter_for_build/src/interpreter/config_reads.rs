@@ -0,0 +1,152 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Recording of the buckconfig values read while evaluating a file. The values a module reads
+//! are part of its digest, so that DICE can cut off a re-evaluation with an identical result.
+
+use std::sync::Arc;
+
+use buck2_common::legacy_configs::view::LegacyBuckConfigView;
+use buck2_interpreter::file_loader::ModuleDigestBuilder;
+use dupe::Dupe;
+use parking_lot::Mutex;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct ConfigRead {
+    section: String,
+    property: String,
+    value: Option<Arc<str>>,
+}
+
+/// Buckconfig properties read during an evaluation, and the values they had.
+#[derive(Debug)]
+pub(crate) struct ConfigReads(Vec<ConfigRead>);
+
+impl ConfigReads {
+    pub(crate) fn add_to_digest(&self, digest: &mut ModuleDigestBuilder) {
+        for read in &self.0 {
+            digest.add_str(&read.section);
+            digest.add_str(&read.property);
+            match &read.value {
+                Some(value) => {
+                    digest.add_str("=");
+                    digest.add_str(value);
+                }
+                None => digest.add_str("unset"),
+            }
+        }
+    }
+}
+
+/// Buckconfig view which records the properties read through it.
+#[derive(Debug)]
+pub(crate) struct RecordingBuckConfigView<'a> {
+    inner: &'a dyn LegacyBuckConfigView,
+    reads: Mutex<Vec<ConfigRead>>,
+}
+
+impl<'a> RecordingBuckConfigView<'a> {
+    pub(crate) fn new(inner: &'a dyn LegacyBuckConfigView) -> Self {
+        Self {
+            inner,
+            reads: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub(crate) fn into_reads(self) -> ConfigReads {
+        let mut reads = self.reads.into_inner();
+        reads.sort();
+        reads.dedup();
+        ConfigReads(reads)
+    }
+}
+
+impl<'a> LegacyBuckConfigView for RecordingBuckConfigView<'a> {
+    fn get(&self, section: &str, key: &str) -> anyhow::Result<Option<Arc<str>>> {
+        let value = self.inner.get(section, key)?;
+        self.reads.lock().push(ConfigRead {
+            section: section.to_owned(),
+            property: key.to_owned(),
+            value: value.dupe(),
+        });
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_common::legacy_configs::testing::parse;
+    use buck2_common::legacy_configs::view::LegacyBuckConfigView;
+    use buck2_interpreter::file_loader::ModuleDigest;
+    use buck2_interpreter::file_loader::ModuleDigestBuilder;
+    use indoc::indoc;
+
+    use crate::interpreter::config_reads::RecordingBuckConfigView;
+
+    fn digest_of_reads(config: &dyn LegacyBuckConfigView) -> anyhow::Result<ModuleDigest> {
+        let recording = RecordingBuckConfigView::new(config);
+        recording.get("foo", "bar")?;
+        recording.get("foo", "missing")?;
+        let mut digest = ModuleDigestBuilder::new("");
+        recording.into_reads().add_to_digest(&mut digest);
+        Ok(digest.finish())
+    }
+
+    #[test]
+    fn test_config_reads() -> anyhow::Result<()> {
+        let config = parse(
+            &[(
+                "/config",
+                indoc!(
+                    r#"
+                    [foo]
+                      bar = 1
+                    "#
+                ),
+            )],
+            "/config",
+        )?;
+        let changed = parse(
+            &[(
+                "/config",
+                indoc!(
+                    r#"
+                    [foo]
+                      bar = 2
+                    "#
+                ),
+            )],
+            "/config",
+        )?;
+        let unrelated = parse(
+            &[(
+                "/config",
+                indoc!(
+                    r#"
+                    [foo]
+                      bar = 1
+                      baz = 3
+                    "#
+                ),
+            )],
+            "/config",
+        )?;
+
+        let recording = RecordingBuckConfigView::new(&config);
+        recording.get("foo", "bar")?;
+        recording.get("foo", "missing")?;
+        recording.get("foo", "bar")?;
+        assert_eq!(2, recording.into_reads().0.len());
+
+        // Only the values of the properties read are part of the digest.
+        assert_eq!(digest_of_reads(&config)?, digest_of_reads(&unrelated)?);
+        assert_ne!(digest_of_reads(&config)?, digest_of_reads(&changed)?);
+        Ok(())
+    }
+}
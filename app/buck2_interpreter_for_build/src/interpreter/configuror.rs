@@ -12,6 +12,7 @@ use std::ptr;
 use std::sync::Arc;
 
 use allocative::Allocative;
+use buck2_common::package_boundary::PackageBoundaryMode;
 use buck2_common::package_listing::listing::PackageListing;
 use buck2_core::build_file_path::BuildFilePath;
 use buck2_core::bzl::ImportPath;
@@ -88,6 +89,8 @@ pub struct BuildInterpreterConfiguror {
     prelude_import: Option<ImportPath>,
     host_info: HostInfo,
     record_target_call_stack: bool,
+    /// Collect attribute coercion errors instead of failing on the first one.
+    aggregate_attr_errors: bool,
     configure_build_file_globals: ConfigureGlobalsFn,
     configure_package_file_globals: ConfigureGlobalsFn,
     configure_extension_file_globals: ConfigureGlobalsFn,
@@ -103,6 +106,7 @@ impl BuildInterpreterConfiguror {
         host_architecture: InterpreterHostArchitecture,
         host_xcode_version: Option<XcodeVersionInfo>,
         record_target_call_stack: bool,
+        aggregate_attr_errors: bool,
         configure_build_file_globals: fn(&mut GlobalsBuilder),
         configure_package_file_globals: fn(&mut GlobalsBuilder),
         configure_extension_file_globals: fn(&mut GlobalsBuilder),
@@ -113,6 +117,7 @@ impl BuildInterpreterConfiguror {
             prelude_import,
             host_info: HostInfo::new(host_platform, host_architecture, host_xcode_version),
             record_target_call_stack,
+            aggregate_attr_errors,
             configure_build_file_globals: ConfigureGlobalsFn(configure_build_file_globals),
             configure_package_file_globals: ConfigureGlobalsFn(configure_package_file_globals),
             configure_extension_file_globals: ConfigureGlobalsFn(configure_extension_file_globals),
@@ -176,7 +181,7 @@ impl BuildInterpreterConfiguror {
         buildfile_path: BuildFilePath,
         package_listing: PackageListing,
         super_package: SuperPackage,
-        package_boundary_exception: bool,
+        package_boundary: PackageBoundaryMode,
         loaded_modules: &LoadedModules,
         implicit_import: Option<&Arc<ImplicitImport>>,
     ) -> anyhow::Result<ModuleInternals> {
@@ -200,7 +205,7 @@ impl BuildInterpreterConfiguror {
         let attr_coercer = BuildAttrCoercionContext::new_with_package(
             cell_info.cell_resolver().dupe(),
             (buildfile_path.package().dupe(), package_listing.dupe()),
-            package_boundary_exception,
+            package_boundary,
         );
 
         let imports = loaded_modules.imports().cloned().collect();
@@ -212,6 +217,7 @@ impl BuildInterpreterConfiguror {
             package_implicits,
             cell_info.default_visibility_to_public(),
             record_target_call_stack,
+            self.aggregate_attr_errors,
             package_listing,
             super_package,
         ))
@@ -22,6 +22,7 @@ use buck2_common::file_ops::FileOps;
 use buck2_common::legacy_configs::dice::HasLegacyConfigs;
 use buck2_common::legacy_configs::dice::LegacyBuckConfigOnDice;
 use buck2_common::package_boundary::HasPackageBoundaryExceptions;
+use buck2_common::package_boundary::PackageBoundaryMode;
 use buck2_common::package_listing::dice::HasPackageListingResolver;
 use buck2_common::package_listing::listing::PackageListing;
 use buck2_common::result::SharedResult;
@@ -38,6 +39,8 @@ use buck2_interpreter::dice::starlark_profiler::GetStarlarkProfilerInstrumentati
 use buck2_interpreter::dice::starlark_provider::with_starlark_eval_provider;
 use buck2_interpreter::file_loader::LoadedModule;
 use buck2_interpreter::file_loader::ModuleDeps;
+use buck2_interpreter::file_loader::ModuleDigest;
+use buck2_interpreter::file_loader::ModuleDigestBuilder;
 use buck2_interpreter::import_paths::HasImportPaths;
 use buck2_interpreter::path::OwnedStarlarkModulePath;
 use buck2_interpreter::path::PackageFilePath;
@@ -55,12 +58,17 @@ use futures::future;
 use more_futures::cancellation::CancellationContext;
 use starlark::codemap::FileSpan;
 use starlark::syntax::AstModule;
+use starlark::values::OwnedFrozenValue;
+use starlark_map::small_map::SmallMap;
 
+use crate::interpreter::config_reads::RecordingBuckConfigView;
 use crate::interpreter::cycles::LoadCycleDescriptor;
 use crate::interpreter::dice_calculation_delegate::keys::EvalImportKey;
 use crate::interpreter::global_interpreter_state::HasGlobalInterpreterState;
+use crate::interpreter::interpreter_for_cell::check_prelude_extension;
 use crate::interpreter::interpreter_for_cell::InterpreterForCell;
 use crate::interpreter::interpreter_for_cell::ParseResult;
+use crate::interpreter::load_digests::HasLoadDigests;
 use crate::load_signals::HasLoadSignals;
 use crate::super_package::data::SuperPackage;
 
@@ -183,11 +191,14 @@ impl<'c> DiceCalculationDelegate<'c> {
                     .await?)
             }
 
-            fn equality(_: &Self::Value, _: &Self::Value) -> bool {
-                // While it is technically possible to compare the modules
-                // at least for simple modules (like modules defining only string constants),
-                // practically it is too hard to make it work correctly for every case.
-                false
+            fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+                // Comparing the modules themselves is too hard to get right in every case, but
+                // modules evaluated from the same inputs are the same, so a module re-evaluated
+                // after a spurious invalidation doesn't invalidate the files loading it.
+                match (x, y) {
+                    (Ok(x), Ok(y)) => x.digest() == y.digest(),
+                    _ => false,
+                }
             }
 
             fn validity(x: &Self::Value) -> bool {
@@ -209,12 +220,10 @@ impl<'c> DiceCalculationDelegate<'c> {
             .await
     }
 
-    async fn parse_file(&self, starlark_path: StarlarkPath<'_>) -> anyhow::Result<ParseResult> {
-        let content =
-            <dyn FileOps>::read_file(&self.fs, starlark_path.path().as_ref().as_ref()).await?;
-        self.configs.parse(starlark_path, content)
-    }
-
+    /// Evaluate the modules loaded by a file. Each module is its own DICE key, computed in its own
+    /// task, so independent `load` chains are evaluated in parallel. The results are kept in the
+    /// order of the `load` statements, which makes the digest and the error reported (the one of
+    /// the first failing `load`, not of the first to fail) independent of scheduling.
     async fn eval_deps(
         &self,
         modules: &[(Option<FileSpan>, OwnedStarlarkModulePath)],
@@ -239,10 +248,33 @@ impl<'c> DiceCalculationDelegate<'c> {
         &'a self,
         starlark_file: StarlarkPath<'_>,
     ) -> anyhow::Result<(AstModule, ModuleDeps)> {
-        let ParseResult(ast, imports) = self.parse_file(starlark_file).await?;
+        let (ast, deps, _digest) = self.prepare_eval_with_digest(starlark_file).await?;
+        Ok((ast, deps))
+    }
+
+    /// Like `prepare_eval`, but also start the digest of the file: its content, its path and
+    /// the interpreter evaluating it, followed by the digests of the modules it loads.
+    async fn prepare_eval_with_digest(
+        &self,
+        starlark_file: StarlarkPath<'_>,
+    ) -> anyhow::Result<(AstModule, ModuleDeps, ModuleDigestBuilder)> {
+        let content =
+            <dyn FileOps>::read_file(&self.fs, starlark_file.path().as_ref().as_ref()).await?;
+        if let StarlarkPath::LoadFile(path) = starlark_file {
+            if let Some(load_digests) = self.ctx.per_transaction_data().get_load_digests() {
+                load_digests.record_load(path, &content);
+            }
+        }
+        let mut digest = ModuleDigestBuilder::new(&content);
+        digest.add_str(&starlark_file.to_string());
+        digest.add_str(&self.configs.id().to_string());
+        let ParseResult(ast, imports) = self.configs.parse(starlark_file, content)?;
         let fut = self.eval_deps(&imports);
         let deps = LoadCycleDescriptor::guard_this(self.ctx, fut).await???;
-        Ok((ast, deps))
+        for dep in &deps.0 {
+            digest.add_digest(dep.digest());
+        }
+        Ok((ast, deps, digest))
     }
 
     pub fn prepare_eval_with_content<'a>(
@@ -254,6 +286,28 @@ impl<'c> DiceCalculationDelegate<'c> {
         Ok(ast)
     }
 
+    /// Like `prepare_eval_with_content`, but also evaluate the modules loaded by the file. Returns
+    /// the errors evaluating them, along with the `load` they come from, rather than failing: the
+    /// file itself parsed fine.
+    pub async fn prepare_eval_with_content_and_load_errors(
+        &self,
+        starlark_file: StarlarkPath<'_>,
+        content: String,
+    ) -> anyhow::Result<(AstModule, Vec<(Option<FileSpan>, anyhow::Error)>)> {
+        let ParseResult(ast, imports) = self.configs.parse(starlark_file, content)?;
+        let load_errors = future::join_all(imports.iter().map(|(span, import)| async move {
+            self.eval_module(import.borrow())
+                .await
+                .err()
+                .map(|e| (span.dupe(), e))
+        }))
+        .await
+        .into_iter()
+        .flatten()
+        .collect();
+        Ok((ast, load_errors))
+    }
+
     pub async fn resolve_load(
         &self,
         starlark_file: StarlarkPath<'_>,
@@ -267,10 +321,14 @@ impl<'c> DiceCalculationDelegate<'c> {
         starlark_file: StarlarkModulePath<'_>,
         starlark_profiler_instrumentation: Option<StarlarkProfilerInstrumentation>,
     ) -> anyhow::Result<LoadedModule> {
-        let (ast, deps) = self.prepare_eval(starlark_file.into()).await?;
+        // Fetch the configs while the loads are evaluated rather than after.
+        let ((ast, deps, mut digest), buckconfig, root_buckconfig) = future::try_join3(
+            self.prepare_eval_with_digest(starlark_file.into()),
+            self.get_legacy_buck_config_for_starlark(),
+            self.ctx.get_legacy_root_config_on_dice(),
+        )
+        .await?;
         let loaded_modules = deps.get_loaded_modules();
-        let buckconfig = self.get_legacy_buck_config_for_starlark().await?;
-        let root_buckconfig = self.ctx.get_legacy_root_config_on_dice().await?;
 
         with_starlark_eval_provider(
             self.ctx,
@@ -279,6 +337,8 @@ impl<'c> DiceCalculationDelegate<'c> {
             ),
             format!("load:{}", &starlark_file),
             move |provider| {
+                let buckconfig = RecordingBuckConfigView::new(&buckconfig);
+                let root_buckconfig = RecordingBuckConfigView::new(&root_buckconfig);
                 let evaluation = self
                     .configs
                     .eval_module(
@@ -293,10 +353,16 @@ impl<'c> DiceCalculationDelegate<'c> {
                         DiceCalculationDelegateError::EvalModuleError(starlark_file.to_string())
                     })?;
 
+                // Module values may depend on buckconfig values, so they are part of the digest.
+                buckconfig.into_reads().add_to_digest(&mut digest);
+                digest.add_str("root");
+                root_buckconfig.into_reads().add_to_digest(&mut digest);
+
                 Ok(LoadedModule::new(
                     OwnedStarlarkModulePath::new(starlark_file),
                     loaded_modules,
                     evaluation,
+                    digest.finish(),
                 ))
             },
         )
@@ -442,6 +508,18 @@ impl<'c> DiceCalculationDelegate<'c> {
         }
     }
 
+    /// Package values applying to a package, see `InterpreterCalculation::get_package_values`.
+    pub async fn eval_package_values(
+        &self,
+        package: PackageLabel,
+    ) -> anyhow::Result<SmallMap<String, OwnedFrozenValue>> {
+        let listing = self.ctx.resolve_package_listing(package.dupe()).await?;
+        let super_package = self
+            .eval_package_file_for_build_file(package, &listing)
+            .await?;
+        Ok(super_package.package_values().clone())
+    }
+
     async fn resolve_package_listing(
         &self,
         package: PackageLabel,
@@ -465,27 +543,132 @@ impl<'c> DiceCalculationDelegate<'c> {
         .await
     }
 
-    pub async fn eval_build_file(
+    /// Check that the prelude extensions of the cell don't redefine the symbols of the prelude.
+    /// This only depends on the prelude and the extensions, so it is computed once per cell
+    /// rather than for every build file.
+    async fn check_prelude_extensions(&self) -> anyhow::Result<()> {
+        #[derive(Clone, Dupe, Display, Debug, Eq, Hash, PartialEq, Allocative)]
+        #[display(fmt = "{}", _0)]
+        struct CheckPreludeExtensionsKey(BuildFileCell);
+
+        #[async_trait]
+        impl Key for CheckPreludeExtensionsKey {
+            type Value = SharedResult<()>;
+            async fn compute(
+                &self,
+                ctx: &DiceComputations,
+                _cancellation: &CancellationContext,
+            ) -> Self::Value {
+                Ok(ctx
+                    .get_interpreter_calculator(self.0.name(), self.0)
+                    .await?
+                    .check_prelude_extensions_uncached()
+                    .await?)
+            }
+
+            fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+                matches!((x, y), (Ok(()), Ok(())))
+            }
+        }
+
+        self.ctx
+            .compute(&CheckPreludeExtensionsKey(self.build_file_cell))
+            .await?
+            .unshared_error()
+    }
+
+    async fn check_prelude_extensions_uncached(&self) -> anyhow::Result<()> {
+        let (prelude_import, extension_imports) = match self.configs.build_file_prelude() {
+            Some((_, [])) | None => return Ok(()),
+            Some(prelude) => prelude,
+        };
+        let (prelude, extensions) = future::try_join(
+            self.eval_module(StarlarkModulePath::LoadFile(prelude_import)),
+            future::try_join_all(
+                extension_imports
+                    .iter()
+                    .map(|import| self.eval_module(StarlarkModulePath::LoadFile(import))),
+            ),
+        )
+        .await?;
+        for (extension_import, extension) in extension_imports.iter().zip(&extensions) {
+            check_prelude_extension(
+                prelude_import,
+                prelude.env(),
+                extension_import,
+                extension.env(),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Gather everything evaluation of a build file depends on.
+    async fn prepare_build_file_eval(
         &self,
         package: PackageLabel,
-        profiler_instrumentation: &mut StarlarkProfilerOrInstrumentation<'_>,
-    ) -> anyhow::Result<EvaluationResult> {
-        let now = Instant::now();
+    ) -> anyhow::Result<BuildFileEvalInputs<'c>> {
         let listing = self.resolve_package_listing(package.dupe()).await?;
 
         let build_file_path = BuildFilePath::new(package.dupe(), listing.buildfile().to_owned());
-        let ast_deps = self.prepare_eval(StarlarkPath::BuildFile(&build_file_path));
+        let ast_deps = self.prepare_eval_with_digest(StarlarkPath::BuildFile(&build_file_path));
 
         let super_package = self.eval_package_file_for_build_file(package.dupe(), &listing);
 
-        let ((ast, deps), super_package) = future::try_join(ast_deps, super_package).await?;
+        let package_boundary = async {
+            anyhow::Ok(
+                self.ctx
+                    .get_package_boundary_mode(package.as_cell_path())
+                    .await?,
+            )
+        };
 
-        let package_boundary_exception = self
-            .ctx
-            .get_package_boundary_exception(package.as_cell_path())
-            .await?;
-        let buckconfig = self.get_legacy_buck_config_for_starlark().await?;
-        let root_buckconfig = self.ctx.get_legacy_root_config_on_dice().await?;
+        let (
+            ((ast, deps, digest), super_package, package_boundary, buckconfig, root_buckconfig),
+            (),
+        ) = future::try_join(
+            future::try_join5(
+                ast_deps,
+                super_package,
+                package_boundary,
+                self.get_legacy_buck_config_for_starlark(),
+                self.ctx.get_legacy_root_config_on_dice(),
+            ),
+            self.check_prelude_extensions(),
+        )
+        .await?;
+
+        Ok(BuildFileEvalInputs {
+            build_file_path,
+            ast,
+            deps,
+            digest,
+            listing,
+            super_package,
+            package_boundary,
+            buckconfig,
+            root_buckconfig,
+        })
+    }
+
+    /// Evaluate a build file, returning the digest of everything the evaluation depended on
+    /// along with the result.
+    pub(crate) async fn eval_build_file_with_digest(
+        &self,
+        package: PackageLabel,
+        profiler_instrumentation: &mut StarlarkProfilerOrInstrumentation<'_>,
+    ) -> anyhow::Result<(EvaluationResult, BuildFileEvalDigest)> {
+        let now = Instant::now();
+        let BuildFileEvalInputs {
+            build_file_path,
+            ast,
+            deps,
+            mut digest,
+            listing,
+            super_package,
+            package_boundary,
+            buckconfig,
+            root_buckconfig,
+        } = self.prepare_build_file_eval(package.dupe()).await?;
         let module_id = build_file_path.to_string();
         let cell_str = build_file_path.cell().as_str().to_owned();
         let start_event = buck2_data::LoadBuildFileStart {
@@ -498,15 +681,17 @@ impl<'c> DiceCalculationDelegate<'c> {
             format!("load_buildfile:{}", &package),
             move |provider| {
                 span(start_event, move || {
+                    let buckconfig = RecordingBuckConfigView::new(&buckconfig);
+                    let root_buckconfig = RecordingBuckConfigView::new(&root_buckconfig);
                     let result = self
                         .configs
                         .eval_build_file(
                             &build_file_path,
                             &buckconfig,
                             &root_buckconfig,
-                            listing,
-                            super_package,
-                            package_boundary_exception,
+                            listing.dupe(),
+                            super_package.dupe(),
+                            package_boundary,
                             ast,
                             deps.get_loaded_modules(),
                             provider,
@@ -523,7 +708,20 @@ impl<'c> DiceCalculationDelegate<'c> {
                     }
 
                     (
-                        result,
+                        result.map(|res| {
+                            buckconfig.into_reads().add_to_digest(&mut digest);
+                            digest.add_str("root");
+                            root_buckconfig.into_reads().add_to_digest(&mut digest);
+                            (
+                                res,
+                                BuildFileEvalDigest {
+                                    digest: digest.finish(),
+                                    listing,
+                                    super_package,
+                                    package_boundary,
+                                },
+                            )
+                        }),
                         buck2_data::LoadBuildFileEnd {
                             module_id,
                             cell: cell_str,
@@ -536,6 +734,17 @@ impl<'c> DiceCalculationDelegate<'c> {
         .await
     }
 
+    pub async fn eval_build_file(
+        &self,
+        package: PackageLabel,
+        profiler_instrumentation: &mut StarlarkProfilerOrInstrumentation<'_>,
+    ) -> anyhow::Result<EvaluationResult> {
+        let (result, _digest) = self
+            .eval_build_file_with_digest(package, profiler_instrumentation)
+            .await?;
+        Ok(result)
+    }
+
     /// Eval build file, return one requested target node.
     pub async fn testing_eval_single_target(&self, target_label: &str) -> TargetNode {
         let target_label = TargetLabel::testing_parse(target_label);
@@ -551,6 +760,33 @@ impl<'c> DiceCalculationDelegate<'c> {
     }
 }
 
+/// Inputs of a build file evaluation, requested through DICE.
+struct BuildFileEvalInputs<'c> {
+    build_file_path: BuildFilePath,
+    ast: AstModule,
+    deps: ModuleDeps,
+    /// Digest of the build file and all the modules it transitively loads.
+    digest: ModuleDigestBuilder,
+    listing: PackageListing,
+    super_package: SuperPackage,
+    package_boundary: PackageBoundaryMode,
+    buckconfig: LegacyBuckConfigOnDice<'c>,
+    root_buckconfig: LegacyBuckConfigOnDice<'c>,
+}
+
+/// Everything the evaluation of a build file depended on. Evaluations with equal digests have
+/// equal results, which lets DICE cut off the re-evaluation of a build file after a spurious
+/// invalidation (e.g. a file touched without changes) before it invalidates the targets.
+#[derive(Debug, PartialEq, Allocative)]
+pub struct BuildFileEvalDigest {
+    /// Digest of the build file, of the modules it transitively loads, and of the buckconfig
+    /// values read while evaluating it.
+    digest: ModuleDigest,
+    listing: PackageListing,
+    super_package: SuperPackage,
+    package_boundary: PackageBoundaryMode,
+}
+
 mod keys {
     use allocative::Allocative;
     use buck2_interpreter::path::OwnedStarlarkModulePath;
@@ -0,0 +1,276 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Limits on the resources a single build file evaluation may use, so that a pathological (e.g.
+//! generated) `BUCK` file fails with an actionable error instead of exhausting daemon memory.
+//!
+//! Statements and heap usage are checked by a hook the evaluator runs before each statement,
+//! which aborts the evaluation as soon as a limit is exceeded. The hook also attributes the cost
+//! to the stack frames it is spent in, so the error can point at the costliest ones.
+
+use std::cell::Cell;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Display;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use buck2_common::legacy_configs::view::LegacyBuckConfigView;
+use buck2_core::build_file_path::BuildFilePath;
+use dupe::Dupe;
+use starlark::codemap::FileSpan;
+use starlark::codemap::FileSpanRef;
+use starlark::eval::BeforeStmtFuncDyn;
+use starlark::eval::CallStack;
+use starlark::eval::Evaluator;
+
+const LIMITS_SECTION: &str = "buck2";
+const MAX_HEAP_BYTES_KEY: &str = "build_file_max_heap_bytes";
+const MAX_STEPS_KEY: &str = "build_file_max_steps";
+const MAX_GLOB_RESULTS_KEY: &str = "build_file_max_glob_results";
+
+/// Number of frames listed in the errors.
+const COSTLIEST_FRAMES: usize = 5;
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum BuildFileEvalLimitError {
+    #[error(
+        "Evaluating `{path}` allocated {allocated} bytes on the Starlark heap, more than the limit of {max} bytes (set by `buck2.build_file_max_heap_bytes`)\n{frames}"
+    )]
+    HeapExceeded {
+        path: BuildFilePath,
+        allocated: u64,
+        max: u64,
+        frames: CostliestFrames,
+    },
+    #[error(
+        "Evaluating `{path}` executed more than {max} Starlark statements (set by `buck2.build_file_max_steps`)\n{frames}"
+    )]
+    StepsExceeded {
+        path: BuildFilePath,
+        max: u64,
+        frames: CostliestFrames,
+    },
+    #[error(
+        "Evaluating `{path}` returned {results} files from `glob`, more than the limit of {max} (set by `buck2.build_file_max_glob_results`)\n{call_stack}"
+    )]
+    GlobResultsExceeded {
+        path: BuildFilePath,
+        results: u64,
+        max: u64,
+        call_stack: CallStack,
+    },
+}
+
+/// Limits on the evaluation of a single build file, configured per cell. `None` means unlimited.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct BuildFileEvalLimits {
+    max_heap_bytes: Option<u64>,
+    max_steps: Option<u64>,
+    max_glob_results: Option<u64>,
+}
+
+impl BuildFileEvalLimits {
+    pub(crate) fn from_config(buckconfig: &dyn LegacyBuckConfigView) -> anyhow::Result<Self> {
+        Ok(Self {
+            max_heap_bytes: buckconfig.parse(LIMITS_SECTION, MAX_HEAP_BYTES_KEY)?,
+            max_steps: buckconfig.parse(LIMITS_SECTION, MAX_STEPS_KEY)?,
+            max_glob_results: buckconfig.parse(LIMITS_SECTION, MAX_GLOB_RESULTS_KEY)?,
+        })
+    }
+}
+
+/// The statements executed and the heap allocated while a stack frame was on top of the stack.
+#[derive(Debug, Clone)]
+struct FrameCost {
+    /// The function and where it was called from, or the build file for top-level statements.
+    name: String,
+    steps: u64,
+    heap_bytes: u64,
+}
+
+/// The frames which used most of the resource whose limit was exceeded, costliest first.
+#[derive(Debug)]
+pub(crate) struct CostliestFrames {
+    frames: Vec<FrameCost>,
+}
+
+impl Display for CostliestFrames {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Costliest stack frames:")?;
+        for frame in &self.frames {
+            write!(
+                f,
+                "\n  {}: {} statements, {} heap bytes",
+                frame.name, frame.steps, frame.heap_bytes
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct Usage {
+    steps: u64,
+    /// Heap size at the previous statement.
+    allocated: u64,
+    /// Frame of the previous statement, which the heap allocated since is attributed to.
+    frame: Option<FileSpan>,
+    /// Cost per frame, keyed by where the function was called from. Calls of the same function
+    /// from different places are different frames, and top-level statements have no key.
+    frames: HashMap<Option<FileSpan>, FrameCost>,
+}
+
+impl Usage {
+    fn costliest_frames(&self, cost: impl Fn(&FrameCost) -> u64) -> CostliestFrames {
+        let mut frames: Vec<FrameCost> = self.frames.values().cloned().collect();
+        frames.sort_by_key(|f| Reverse(cost(f)));
+        frames.truncate(COSTLIEST_FRAMES);
+        CostliestFrames { frames }
+    }
+}
+
+/// Invoked by the evaluator before each statement. Returning an error aborts the evaluation.
+struct LimitsHook {
+    limits: BuildFileEvalLimits,
+    path: BuildFilePath,
+    usage: Arc<Mutex<Usage>>,
+}
+
+impl<'a> BeforeStmtFuncDyn<'a> for LimitsHook {
+    fn call<'v>(&mut self, _span: FileSpanRef, eval: &mut Evaluator<'v, 'a>) -> anyhow::Result<()> {
+        let mut usage = self.usage.lock().unwrap();
+        let usage = &mut *usage;
+
+        // Allocations since the previous statement were made by the frame which executed it.
+        let allocated = eval.heap().allocated_bytes() as u64;
+        if let Some(previous) = usage.frames.get_mut(&usage.frame) {
+            previous.heap_bytes += allocated.saturating_sub(usage.allocated);
+        }
+        usage.allocated = allocated;
+
+        let frame = eval.call_stack_top_location();
+        usage
+            .frames
+            .entry(frame.clone())
+            .or_insert_with(|| FrameCost {
+                name: match eval.call_stack_top_frame() {
+                    Some(top) if frame.is_some() => top.to_string(),
+                    _ => self.path.to_string(),
+                },
+                steps: 0,
+                heap_bytes: 0,
+            })
+            .steps += 1;
+        usage.frame = frame;
+        usage.steps += 1;
+
+        if let Some(max) = self.limits.max_steps {
+            if usage.steps > max {
+                return Err(BuildFileEvalLimitError::StepsExceeded {
+                    path: self.path.clone(),
+                    max,
+                    frames: usage.costliest_frames(|f| f.steps),
+                }
+                .into());
+            }
+        }
+        if let Some(max) = self.limits.max_heap_bytes {
+            if allocated > max {
+                return Err(BuildFileEvalLimitError::HeapExceeded {
+                    path: self.path.clone(),
+                    allocated,
+                    max,
+                    frames: usage.costliest_frames(|f| f.heap_bytes),
+                }
+                .into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Usage of the limited resources by the build file being evaluated.
+#[derive(Debug, Default)]
+pub(crate) struct BuildFileEvalLimitsState {
+    limits: BuildFileEvalLimits,
+    usage: Arc<Mutex<Usage>>,
+    glob_results: Cell<u64>,
+}
+
+impl BuildFileEvalLimitsState {
+    pub(crate) fn new(limits: BuildFileEvalLimits) -> Self {
+        Self {
+            limits,
+            usage: Default::default(),
+            glob_results: Cell::new(0),
+        }
+    }
+
+    /// Start checking the statements executed and the heap allocated by the evaluator, if there
+    /// is a limit on them. Statement hooks slow evaluation down, so they are only installed when
+    /// needed.
+    pub(crate) fn instrument<'a>(&self, path: &BuildFilePath, eval: &mut Evaluator<'_, 'a>) {
+        if self.limits.max_steps.is_some() || self.limits.max_heap_bytes.is_some() {
+            let hook: Box<dyn BeforeStmtFuncDyn<'a>> = Box::new(LimitsHook {
+                limits: self.limits,
+                path: path.clone(),
+                usage: self.usage.dupe(),
+            });
+            eval.before_stmt_for_dap(hook.into());
+        }
+    }
+
+    pub(crate) fn add_glob_results(
+        &self,
+        path: &BuildFilePath,
+        results: usize,
+        eval: &Evaluator,
+    ) -> anyhow::Result<()> {
+        let results = self.glob_results.get() + results as u64;
+        self.glob_results.set(results);
+        if let Some(max) = self.limits.max_glob_results {
+            if results > max {
+                return Err(BuildFileEvalLimitError::GlobResultsExceeded {
+                    path: path.clone(),
+                    results,
+                    max,
+                    call_stack: eval.call_stack(),
+                }
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the heap once more when the evaluation finishes, since the last statement may have
+    /// allocated past the limit.
+    pub(crate) fn check(&self, path: &BuildFilePath, eval: &Evaluator) -> anyhow::Result<()> {
+        if let Some(max) = self.limits.max_heap_bytes {
+            let allocated = eval.heap().allocated_bytes() as u64;
+            if allocated > max {
+                let mut usage = self.usage.lock().unwrap();
+                let usage = &mut *usage;
+                if let Some(last) = usage.frames.get_mut(&usage.frame) {
+                    last.heap_bytes += allocated.saturating_sub(usage.allocated);
+                }
+                usage.allocated = allocated;
+                return Err(BuildFileEvalLimitError::HeapExceeded {
+                    path: path.clone(),
+                    allocated,
+                    max,
+                    frames: usage.costliest_frames(|f| f.heap_bytes),
+                }
+                .into());
+            }
+        }
+        Ok(())
+    }
+}
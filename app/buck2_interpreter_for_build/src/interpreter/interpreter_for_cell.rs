@@ -12,11 +12,14 @@
 //! build files.
 
 use std::cell::RefCell;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 use allocative::Allocative;
 use anyhow::Context;
 use buck2_common::legacy_configs::view::LegacyBuckConfigView;
+use buck2_common::package_boundary::PackageBoundaryMode;
 use buck2_common::package_listing::listing::PackageListing;
 use buck2_core::build_file_path::BuildFilePath;
 use buck2_core::bzl::ImportPath;
@@ -55,12 +58,25 @@ use thiserror::Error;
 use super::print_handler::EventDispatcherPrintHandler;
 use crate::interpreter::build_context::BuildContext;
 use crate::interpreter::build_context::PerFileTypeContext;
+use crate::interpreter::eval_limits::BuildFileEvalLimits;
 use crate::interpreter::global_interpreter_state::GlobalInterpreterState;
 use crate::interpreter::module_internals::ModuleInternals;
 use crate::super_package::data::SuperPackage;
 use crate::super_package::eval_ctx::PackageFileEvalCtx;
 use crate::super_package::package_value::PackageValues;
 
+#[derive(Debug, Error)]
+enum PreludeExtensionError {
+    #[error(
+        "Prelude extension `{extension}` defines `{symbol}`, which is already defined by the prelude `{prelude}`"
+    )]
+    ConflictsWithPrelude {
+        extension: ImportPath,
+        symbol: String,
+        prelude: ImportPath,
+    },
+}
+
 #[derive(Debug, Error)]
 enum StarlarkParseError {
     #[error("Error parsing: `{0}`")]
@@ -128,6 +144,9 @@ pub(crate) struct InterpreterForCell {
     /// Implicit imports. These are only used for build files (e.g. `BUCK`),
     /// not for `bzl` or other files, because we only have implicit imports for build files.
     implicit_import_paths: Arc<ImplicitImportPaths>,
+    /// Distinct for every interpreter created. Modules evaluated by different interpreters may
+    /// differ even if their inputs are the same, so this is part of the module digests.
+    id: u64,
 }
 
 struct InterpreterLoadResolver {
@@ -222,6 +241,41 @@ impl LoadResolver for InterpreterLoadResolver {
     }
 }
 
+/// The symbols a module adds to the modules importing it with `import_public_symbols`.
+pub fn public_symbols(module: &FrozenModule) -> impl Iterator<Item = &str> {
+    module
+        .names()
+        .map(|name| name.as_str())
+        .filter(|name| !name.starts_with('_'))
+}
+
+/// Check that a prelude extension does not redefine the symbols of the prelude, including the
+/// `native` ones.
+pub fn check_prelude_extension(
+    prelude_import: &ImportPath,
+    prelude: &FrozenModule,
+    extension_import: &ImportPath,
+    extension: &FrozenModule,
+) -> anyhow::Result<()> {
+    let native_symbols = match prelude.get_option("native")? {
+        Some(native) => native.value().dir_attr(),
+        None => Vec::new(),
+    };
+    for symbol in public_symbols(extension) {
+        let redefined = public_symbols(prelude).any(|s| s == symbol)
+            || native_symbols.iter().any(|s| s == symbol);
+        if redefined {
+            return Err(PreludeExtensionError::ConflictsWithPrelude {
+                extension: extension_import.clone(),
+                symbol: symbol.to_owned(),
+                prelude: prelude_import.clone(),
+            }
+            .into());
+        }
+    }
+    Ok(())
+}
+
 fn is_prelude_path(import_path: &CellPath, prelude_import: &ImportPath) -> bool {
     import_path.starts_with(prelude_import.path_parent())
 }
@@ -256,15 +310,29 @@ impl InterpreterForCell {
         global_state: Arc<GlobalInterpreterState>,
         implicit_import_paths: Arc<ImplicitImportPaths>,
     ) -> anyhow::Result<Self> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
         Ok(Self {
             global_state,
             cell_names,
             verbose_gc: Self::verbose_gc()?,
             ignore_attrs_for_profiling: Self::is_ignore_attrs_for_profiling()?,
             implicit_import_paths,
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
         })
     }
 
+    pub(crate) fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The prelude imported by the build files of the cell, along with the extensions of it.
+    pub(crate) fn build_file_prelude(&self) -> Option<(&ImportPath, &[ImportPath])> {
+        self.global_state
+            .configuror
+            .prelude_import()
+            .map(|prelude| (prelude, &*self.implicit_import_paths.prelude_extensions))
+    }
+
     fn create_env(
         &self,
         starlark_path: StarlarkPath<'_>,
@@ -311,7 +379,7 @@ impl InterpreterForCell {
         build_file: &BuildFilePath,
         package_listing: &PackageListing,
         super_package: SuperPackage,
-        package_boundary_exception: bool,
+        package_boundary: PackageBoundaryMode,
         loaded_modules: &LoadedModules,
     ) -> anyhow::Result<(Module, ModuleInternals)> {
         let internals = self.global_state.configuror.new_extra_context(
@@ -319,12 +387,28 @@ impl InterpreterForCell {
             build_file.clone(),
             package_listing.dupe(),
             super_package,
-            package_boundary_exception,
+            package_boundary,
             loaded_modules,
             self.package_import(build_file),
         )?;
         let env = self.create_env(StarlarkPath::BuildFile(build_file), loaded_modules)?;
 
+        // The extensions are checked against the prelude once per cell, in
+        // `DiceCalculationDelegate::check_prelude_extensions`.
+        for extension_import in &self.implicit_import_paths.prelude_extensions {
+            let extension_env = loaded_modules
+                .map
+                .get(&StarlarkModulePath::LoadFile(extension_import))
+                .with_context(|| {
+                    format!(
+                        "Should've had an env for the prelude extension `{}` (internal error)",
+                        extension_import,
+                    )
+                })?
+                .env();
+            env.import_public_symbols(extension_env);
+        }
+
         if let Some(root_import) = self.root_import() {
             let root_env = loaded_modules
                 .map
@@ -421,6 +505,9 @@ impl InterpreterForCell {
                 implicit_imports.push(OwnedStarlarkModulePath::LoadFile(i.clone()));
             }
             if let StarlarkPath::BuildFile(build_file) = import {
+                for i in &self.implicit_import_paths.prelude_extensions {
+                    implicit_imports.push(OwnedStarlarkModulePath::LoadFile(i.clone()));
+                }
                 if let Some(i) = self.package_import(build_file) {
                     implicit_imports.push(OwnedStarlarkModulePath::LoadFile(i.import().clone()));
                 }
@@ -477,8 +564,18 @@ impl InterpreterForCell {
             if self.verbose_gc {
                 eval.verbose_gc();
             }
+            let build_internals = match &extra.additional {
+                PerFileTypeContext::Build(_, internals) => Some(internals),
+                _ => None,
+            };
+            if let Some(internals) = build_internals {
+                internals.instrument_eval_limits(&mut eval);
+            }
             match eval.eval_module(ast, globals) {
                 Ok(_) => {
+                    if let Some(internals) = build_internals {
+                        internals.check_eval_limits(&eval)?;
+                    }
                     eval_provider
                         .evaluation_complete(&mut eval)
                         .context("Profiler finalization failed")?;
@@ -540,6 +637,7 @@ impl InterpreterForCell {
             PackageFileEvalCtx {
                 parent,
                 visibility: RefCell::new(None),
+                package_value_schemas: RefCell::new(SmallMap::new()),
             },
         );
 
@@ -611,18 +709,19 @@ impl InterpreterForCell {
         root_buckconfig: &dyn LegacyBuckConfigView,
         listing: PackageListing,
         super_package: SuperPackage,
-        package_boundary_exception: bool,
+        package_boundary: PackageBoundaryMode,
         ast: AstModule,
         loaded_modules: LoadedModules,
         eval_provider: &mut dyn StarlarkEvaluatorProvider,
     ) -> anyhow::Result<EvaluationResult> {
-        let (env, internals) = self.create_build_env(
+        let (env, mut internals) = self.create_build_env(
             build_file,
             &listing,
             super_package,
-            package_boundary_exception,
+            package_boundary,
             &loaded_modules,
         )?;
+        internals.set_eval_limits(BuildFileEvalLimits::from_config(buckconfig)?);
         let internals = self
             .eval(
                 &env,
@@ -635,6 +734,73 @@ impl InterpreterForCell {
             )?
             .into_build()?;
 
+        internals.check_attr_errors()?;
         Ok(EvaluationResult::from(internals))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::bzl::ImportPath;
+    use starlark::environment::FrozenModule;
+    use starlark::environment::Globals;
+    use starlark::environment::Module;
+    use starlark::eval::Evaluator;
+    use starlark::syntax::AstModule;
+    use starlark::syntax::Dialect;
+
+    use crate::interpreter::interpreter_for_cell::check_prelude_extension;
+    use crate::interpreter::interpreter_for_cell::public_symbols;
+
+    fn eval(content: &str) -> FrozenModule {
+        let module = Module::new();
+        let ast = AstModule::parse("test.bzl", content.to_owned(), &Dialect::Extended).unwrap();
+        Evaluator::new(&module)
+            .eval_module(ast, &Globals::extended())
+            .unwrap();
+        module.freeze().unwrap()
+    }
+
+    fn check(prelude: &str, extension: &str) -> anyhow::Result<()> {
+        check_prelude_extension(
+            &ImportPath::testing_new("prelude//:prelude.bzl"),
+            &eval(prelude),
+            &ImportPath::testing_new("root//:extension.bzl"),
+            &eval(extension),
+        )
+    }
+
+    #[test]
+    fn test_public_symbols() {
+        let module = eval("foo = 1\n_bar = 2\nbaz = 3");
+        let mut symbols: Vec<_> = public_symbols(&module).collect();
+        symbols.sort_unstable();
+        assert_eq!(symbols, vec!["baz", "foo"]);
+    }
+
+    #[test]
+    fn test_check_prelude_extension() {
+        let prelude = "native = struct(cxx_library = 1)\nfoo = 2\n_private = 3";
+        assert!(check(prelude, "bar = 1").is_ok());
+        // Private symbols are not imported, so they can't conflict.
+        assert!(check(prelude, "_private = 1\n_foo = 2").is_ok());
+
+        let err = check(prelude, "foo = 1").unwrap_err();
+        assert!(
+            err.to_string().contains(
+                "Prelude extension `root//extension.bzl` defines `foo`, which is already \
+                defined by the prelude `prelude//prelude.bzl`"
+            ),
+            "{}",
+            err
+        );
+        let err = check(prelude, "cxx_library = 1").unwrap_err();
+        assert!(err.to_string().contains("defines `cxx_library`"), "{}", err);
+    }
+
+    #[test]
+    fn test_check_prelude_extension_without_native() {
+        assert!(check("foo = 1", "bar = 1").is_ok());
+        assert!(check("foo = 1", "foo = 1").is_err());
+    }
+}
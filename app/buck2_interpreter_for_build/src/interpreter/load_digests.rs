@@ -0,0 +1,194 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Content digests of the `.bzl` files loaded by a daemon, for `buck2 audit includes
+//! --digests`.
+//!
+//! DICE only keeps the current version of a module, so to tell why a package was evaluated again
+//! the daemon remembers, for each file, the digest of its content when it was last loaded and
+//! when it was last loaded by an earlier command. Loads by the same command (e.g. after DICE
+//! evicted the module) do not replace the previous digest.
+
+use std::collections::HashMap;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use buck2_core::bzl::ImportPath;
+use buck2_interpreter::file_loader::ModuleDigest;
+use buck2_interpreter::file_loader::ModuleDigestBuilder;
+use dice::UserComputationData;
+use dupe::Dupe;
+use parking_lot::Mutex;
+
+/// Whether a file changed since it was loaded by an earlier command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, derive_more::Display)]
+pub enum LoadChange {
+    /// No earlier command of this daemon loaded the file.
+    #[display(fmt = "new")]
+    New,
+    #[display(fmt = "changed")]
+    Changed,
+    /// The file was loaded again, e.g. because DICE invalidated it, with the same content.
+    #[display(fmt = "unchanged")]
+    Unchanged,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoadDigest {
+    /// Digest of the content of the file when it was last loaded.
+    pub digest: ModuleDigest,
+    pub change: LoadChange,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LoadDigestEntry {
+    /// The command which last loaded the file.
+    command: u64,
+    current: ModuleDigest,
+    /// The digest of the file when it was last loaded by a command before `command`.
+    previous: Option<ModuleDigest>,
+}
+
+/// The digests of the files loaded by the commands of a daemon, owned by the daemon state.
+#[derive(Default)]
+pub struct LoadDigests {
+    entries: Mutex<HashMap<ImportPath, LoadDigestEntry>>,
+    next_command: AtomicU64,
+}
+
+impl LoadDigests {
+    /// Where a new command records the files it loads.
+    pub fn for_command(self: &Arc<Self>) -> CommandLoadDigests {
+        CommandLoadDigests {
+            digests: self.dupe(),
+            command: self.next_command.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    fn record(&self, command: u64, path: &ImportPath, digest: ModuleDigest) {
+        let mut entries = self.entries.lock();
+        match entries.get_mut(path) {
+            Some(entry) => {
+                if entry.command != command {
+                    entry.previous = Some(entry.current);
+                    entry.command = command;
+                }
+                entry.current = digest;
+            }
+            None => {
+                entries.insert(
+                    path.clone(),
+                    LoadDigestEntry {
+                        command,
+                        current: digest,
+                        previous: None,
+                    },
+                );
+            }
+        }
+    }
+
+    /// The digest of a `.bzl` file when it was last loaded, if it was loaded by this daemon.
+    pub fn get(&self, path: &ImportPath) -> Option<LoadDigest> {
+        let entry = *self.entries.lock().get(path)?;
+        Some(LoadDigest {
+            digest: entry.current,
+            change: match entry.previous {
+                None => LoadChange::New,
+                Some(previous) if previous != entry.current => LoadChange::Changed,
+                Some(_) => LoadChange::Unchanged,
+            },
+        })
+    }
+}
+
+/// The digests of the files loaded by the daemon, as seen by one of its commands.
+#[derive(Clone, Dupe)]
+pub struct CommandLoadDigests {
+    digests: Arc<LoadDigests>,
+    command: u64,
+}
+
+impl CommandLoadDigests {
+    /// Record the content of a `.bzl` file being loaded.
+    pub(crate) fn record_load(&self, path: &ImportPath, content: &str) {
+        self.digests.record(
+            self.command,
+            path,
+            ModuleDigestBuilder::new(content).finish(),
+        );
+    }
+
+    /// The digest of a `.bzl` file when it was last loaded, if it was loaded by this daemon.
+    pub fn get(&self, path: &ImportPath) -> Option<LoadDigest> {
+        self.digests.get(path)
+    }
+}
+
+pub trait SetLoadDigests {
+    fn set_load_digests(&mut self, digests: CommandLoadDigests);
+}
+
+impl SetLoadDigests for UserComputationData {
+    fn set_load_digests(&mut self, digests: CommandLoadDigests) {
+        self.data.set(digests);
+    }
+}
+
+pub trait HasLoadDigests {
+    fn get_load_digests(&self) -> Option<&CommandLoadDigests>;
+}
+
+impl HasLoadDigests for UserComputationData {
+    fn get_load_digests(&self) -> Option<&CommandLoadDigests> {
+        self.data.get::<CommandLoadDigests>().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use buck2_core::bzl::ImportPath;
+
+    use crate::interpreter::load_digests::CommandLoadDigests;
+    use crate::interpreter::load_digests::LoadChange;
+    use crate::interpreter::load_digests::LoadDigests;
+
+    #[test]
+    fn test_record() {
+        let digests = Arc::new(LoadDigests::default());
+        let path = ImportPath::testing_new("root//foo:defs.bzl");
+        let change = |command: &CommandLoadDigests| command.get(&path).unwrap().change;
+
+        let first = digests.for_command();
+        assert!(first.get(&path).is_none());
+        first.record_load(&path, "a = 1");
+        assert_eq!(change(&first), LoadChange::New);
+        // Loading the file again in the same command does not make it unchanged.
+        first.record_load(&path, "a = 1");
+        assert_eq!(change(&first), LoadChange::New);
+
+        let second = digests.for_command();
+        second.record_load(&path, "a = 1");
+        assert_eq!(change(&second), LoadChange::Unchanged);
+
+        // A change is still reported if the command loads the file several times.
+        let third = digests.for_command();
+        third.record_load(&path, "a = 2");
+        assert_eq!(change(&third), LoadChange::Changed);
+        third.record_load(&path, "a = 2");
+        assert_eq!(change(&third), LoadChange::Changed);
+        assert_eq!(
+            digests.get(&path).unwrap().digest,
+            third.get(&path).unwrap().digest
+        );
+    }
+}
@@ -10,14 +10,17 @@
 pub mod build_context;
 pub mod build_defs;
 pub mod calculation;
+mod config_reads;
 pub mod configuror;
 pub mod context;
 pub mod cycles;
 pub mod dice_calculation_delegate;
+mod eval_limits;
 pub mod functions;
 pub mod global_interpreter_state;
 pub mod interpreter_for_cell;
 pub mod interpreter_setup;
+pub mod load_digests;
 pub mod module_internals;
 pub mod natives;
 pub mod print_handler;
@@ -14,22 +14,29 @@ use std::fmt::Debug;
 use std::mem;
 use std::sync::Arc;
 
+use buck2_common::package_boundary::PackageBoundaryMode;
 use buck2_common::package_listing::listing::PackageListing;
 use buck2_core::build_file_path::BuildFilePath;
 use buck2_core::bzl::ImportPath;
 use buck2_core::package::package_relative_path::PackageRelativePath;
+use buck2_core::package::PackageLabel;
 use buck2_core::target::name::TargetNameRef;
 use buck2_interpreter::globspec::GlobSpec;
 use buck2_interpreter::package_imports::ImplicitImport;
 use buck2_node::nodes::eval_result::EvaluationResult;
+use buck2_node::nodes::eval_result::GlobPackageBoundaryViolation;
 use buck2_node::nodes::targets_map::TargetsMap;
 use buck2_node::nodes::unconfigured::TargetNode;
 use buck2_node::package::Package;
 use dupe::Dupe;
 use starlark::environment::FrozenModule;
+use starlark::eval::Evaluator;
 use starlark::values::OwnedFrozenValue;
 
 use crate::attrs::coerce::ctx::BuildAttrCoercionContext;
+use crate::interpreter::eval_limits::BuildFileEvalLimits;
+use crate::interpreter::eval_limits::BuildFileEvalLimitsState;
+use crate::nodes::attr_spec::AttrErrors;
 use crate::super_package::data::SuperPackage;
 
 impl From<ModuleInternals> for EvaluationResult {
@@ -39,6 +46,7 @@ impl From<ModuleInternals> for EvaluationResult {
             state,
             imports,
             buildfile_path,
+            glob_package_boundary_violations,
             ..
         } = internals;
         let recorder = match state.into_inner() {
@@ -46,6 +54,7 @@ impl From<ModuleInternals> for EvaluationResult {
             State::Targets(RecordingTargets { recorder, .. }) => recorder,
         };
         EvaluationResult::new(buildfile_path, imports, recorder.take())
+            .with_glob_package_boundary_violations(glob_package_boundary_violations.into_inner())
     }
 }
 
@@ -81,9 +90,16 @@ pub struct ModuleInternals {
     package_implicits: Option<PackageImplicits>,
     default_visibility_to_public: bool,
     record_target_call_stacks: bool,
+    /// Collect errors of invalid target declarations and report them after evaluation.
+    aggregate_attr_errors: bool,
+    /// Errors of targets which were not recorded because of invalid attributes.
+    attr_errors: RefCell<Vec<anyhow::Error>>,
     /// The files owned by this directory. Is `None` for .bzl files.
     package_listing: PackageListing,
+    /// `glob` patterns which can only match files of a subpackage.
+    glob_package_boundary_violations: RefCell<Vec<GlobPackageBoundaryViolation>>,
     pub(crate) super_package: SuperPackage,
+    eval_limits: BuildFileEvalLimitsState,
 }
 
 #[derive(Debug)]
@@ -113,6 +129,25 @@ enum OncallErrors {
     DuplicateOncall,
 }
 
+#[derive(Debug, thiserror::Error)]
+enum GlobPackageBoundaryError {
+    #[error(
+        "Glob pattern `{1}` of package `{0}` can only match files of subpackage `{2}`, which are \
+        never returned by `glob` (package boundaries are enforced in this cell)"
+    )]
+    IncludesSubPackage(String, String, String),
+}
+
+/// The directory of a glob pattern before its first wildcard, e.g. `foo/bar` for `foo/bar/**/*.h`
+/// or `foo` for `foo/b*/x.h`.
+fn glob_literal_dir(pattern: &str) -> &str {
+    let literal = match pattern.find(|c: char| "*?[".contains(c)) {
+        Some(i) => &pattern[..i],
+        None => pattern,
+    };
+    literal.rsplit_once('/').map_or("", |(dir, _)| dir)
+}
+
 impl ModuleInternals {
     pub(crate) fn new(
         attr_coercion_context: BuildAttrCoercionContext,
@@ -121,6 +156,7 @@ impl ModuleInternals {
         package_implicits: Option<PackageImplicits>,
         default_visibility_to_public: bool,
         record_target_call_stacks: bool,
+        aggregate_attr_errors: bool,
         package_listing: PackageListing,
         super_package: SuperPackage,
     ) -> Self {
@@ -132,11 +168,29 @@ impl ModuleInternals {
             package_implicits,
             default_visibility_to_public,
             record_target_call_stacks,
+            aggregate_attr_errors,
+            attr_errors: RefCell::new(Vec::new()),
             package_listing,
+            glob_package_boundary_violations: RefCell::new(Vec::new()),
             super_package,
+            eval_limits: BuildFileEvalLimitsState::default(),
         }
     }
 
+    pub(crate) fn set_eval_limits(&mut self, limits: BuildFileEvalLimits) {
+        self.eval_limits = BuildFileEvalLimitsState::new(limits);
+    }
+
+    /// Install the hook enforcing the limits on the evaluation of this build file.
+    pub(crate) fn instrument_eval_limits(&self, eval: &mut Evaluator<'_, '_>) {
+        self.eval_limits.instrument(&self.buildfile_path, eval)
+    }
+
+    /// Fails if the evaluation of this build file went over one of its limits.
+    pub(crate) fn check_eval_limits(&self, eval: &Evaluator) -> anyhow::Result<()> {
+        self.eval_limits.check(&self.buildfile_path, eval)
+    }
+
     pub(crate) fn attr_coercion_context(&self) -> &BuildAttrCoercionContext {
         &self.attr_coercion_context
     }
@@ -172,6 +226,7 @@ impl ModuleInternals {
                                 buildfile_path: self.buildfile_path.dupe(),
                                 oncall,
                                 default_visibility_to_public: self.default_visibility_to_public,
+                                within_view: self.super_package.within_view().dupe(),
                             }),
                             recorder: TargetsRecorder::new(),
                         });
@@ -208,12 +263,75 @@ impl ModuleInternals {
         self.record_target_call_stacks
     }
 
+    pub fn aggregate_attr_errors(&self) -> bool {
+        self.aggregate_attr_errors
+    }
+
+    /// Remember attribute errors of a target to report them once the whole package is evaluated.
+    pub(crate) fn record_attr_errors(&self, errors: anyhow::Error) {
+        self.attr_errors.borrow_mut().push(errors);
+    }
+
+    /// Fails if any target of the package had invalid attributes.
+    pub(crate) fn check_attr_errors(&self) -> anyhow::Result<()> {
+        let errors = mem::take(&mut *self.attr_errors.borrow_mut());
+        if errors.is_empty() {
+            return Ok(());
+        }
+        Err(AttrErrors::Package {
+            package: self.buildfile_path.package().to_string(),
+            errors,
+        }
+        .into())
+    }
+
     pub(crate) fn resolve_glob<'a>(
         &'a self,
         spec: &'a GlobSpec,
     ) -> impl Iterator<Item = &'a PackageRelativePath> {
         spec.resolve_glob(self.package_listing.files())
     }
+
+    /// Records the `glob` include patterns which can only match files of a subpackage, which
+    /// `glob` silently skips. These are errors when package boundaries are enforced.
+    pub(crate) fn check_glob_package_boundary(&self, include: &[String]) -> anyhow::Result<()> {
+        for pattern in include {
+            let dir = match PackageRelativePath::new(glob_literal_dir(pattern)) {
+                Ok(dir) => dir,
+                Err(_) => continue,
+            };
+            if let Some(subpackage) = self.package_listing.subpackage_containing(dir) {
+                if self.attr_coercion_context.package_boundary() == PackageBoundaryMode::Enforced {
+                    return Err(GlobPackageBoundaryError::IncludesSubPackage(
+                        self.buildfile_path.package().to_string(),
+                        pattern.clone(),
+                        subpackage.to_string(),
+                    )
+                    .into());
+                }
+                let package = self.buildfile_path.package();
+                self.glob_package_boundary_violations.borrow_mut().push(
+                    GlobPackageBoundaryViolation {
+                        pattern: pattern.clone(),
+                        subpackage: PackageLabel::from_cell_path(
+                            package.as_cell_path().join(subpackage).as_ref(),
+                        ),
+                    },
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Count the files returned by a `glob` call towards the limit for this build file.
+    pub(crate) fn record_glob_results(
+        &self,
+        results: usize,
+        eval: &Evaluator,
+    ) -> anyhow::Result<()> {
+        self.eval_limits
+            .add_glob_results(&self.buildfile_path, results, eval)
+    }
 }
 
 // Records the targets declared when evaluating a build file.
@@ -242,3 +360,17 @@ impl TargetsRecorder {
         self.targets
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::module_internals::glob_literal_dir;
+
+    #[test]
+    fn test_glob_literal_dir() {
+        assert_eq!(glob_literal_dir("foo/bar/**/*.h"), "foo/bar");
+        assert_eq!(glob_literal_dir("foo/b*/x.h"), "foo");
+        assert_eq!(glob_literal_dir("foo/bar/x.h"), "foo/bar");
+        assert_eq!(glob_literal_dir("**/*.h"), "");
+        assert_eq!(glob_literal_dir("x.h"), "");
+    }
+}
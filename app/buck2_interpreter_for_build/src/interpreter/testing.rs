@@ -12,6 +12,7 @@ use std::sync::Arc;
 use buck2_common::legacy_configs::testing::TestConfigParserFileOps;
 use buck2_common::legacy_configs::LegacyBuckConfig;
 use buck2_common::legacy_configs::LegacyBuckConfigs;
+use buck2_common::package_boundary::PackageBoundaryMode;
 use buck2_common::package_listing::listing::testing::PackageListingExt;
 use buck2_common::package_listing::listing::PackageListing;
 use buck2_common::result::SharedResult;
@@ -29,6 +30,7 @@ use buck2_interpreter::extra::InterpreterHostPlatform;
 use buck2_interpreter::factory::StarlarkPassthroughProvider;
 use buck2_interpreter::file_loader::LoadedModule;
 use buck2_interpreter::file_loader::LoadedModules;
+use buck2_interpreter::file_loader::ModuleDigestBuilder;
 use buck2_interpreter::import_paths::ImplicitImportPaths;
 use buck2_interpreter::path::OwnedStarlarkModulePath;
 use buck2_interpreter::path::StarlarkModulePath;
@@ -58,6 +60,8 @@ pub struct Tester {
     loaded_modules: LoadedModules,
     additional_globals: Vec<AdditionalGlobalsFn>,
     prelude_path: Option<ImportPath>,
+    aggregate_attr_errors: bool,
+    package_boundary: PackageBoundaryMode,
 }
 
 /// These functions will be available in the starlark environment for all code running through a Tester.
@@ -169,6 +173,8 @@ impl Tester {
             loaded_modules: LoadedModules::default(),
             additional_globals: Vec::new(),
             prelude_path: None,
+            aggregate_attr_errors: false,
+            package_boundary: PackageBoundaryMode::Default,
         })
     }
 
@@ -184,6 +190,14 @@ impl Tester {
         self.prelude_path = Some(prelude_import);
     }
 
+    pub fn set_aggregate_attr_errors(&mut self, aggregate_attr_errors: bool) {
+        self.aggregate_attr_errors = aggregate_attr_errors;
+    }
+
+    pub fn set_package_boundary_mode(&mut self, package_boundary: PackageBoundaryMode) {
+        self.package_boundary = package_boundary;
+    }
+
     fn interpreter(&self) -> anyhow::Result<Arc<InterpreterForCell>> {
         let import_paths = ImplicitImportPaths::parse(
             self.configs
@@ -204,6 +218,7 @@ impl Tester {
                     InterpreterHostArchitecture::X86_64,
                     None,
                     false,
+                    self.aggregate_attr_errors,
                     |_| {},
                     |_| {},
                     |_| {},
@@ -268,6 +283,7 @@ impl Tester {
             OwnedStarlarkModulePath::LoadFile(path.clone()),
             loaded_modules,
             env,
+            ModuleDigestBuilder::new(content).finish(),
         ))
     }
 
@@ -311,7 +327,7 @@ impl Tester {
             root_buckconfig,
             package_listing,
             SuperPackage::default(),
-            false,
+            self.package_boundary,
             ast,
             loaded_modules,
             &mut provider,
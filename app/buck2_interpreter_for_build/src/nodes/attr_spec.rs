@@ -9,7 +9,6 @@
 
 use std::collections::HashMap;
 
-use anyhow::Context;
 use buck2_core::target::name::TargetName;
 use buck2_node::attrs::attr::CoercedValue;
 use buck2_node::attrs::attr_type::string::StringLiteral;
@@ -30,6 +29,39 @@ use starlark::values::Value;
 use crate::attrs::AttributeCoerceExt;
 use crate::interpreter::module_internals::ModuleInternals;
 
+/// Attribute errors collected when `--aggregate-attr-errors` is set.
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum AttrErrors {
+    #[error(
+        "Found {} invalid attributes of `{}`:\n{}",
+        .errors.len(),
+        .target,
+        display_list(.errors)
+    )]
+    Target {
+        target: String,
+        errors: Vec<anyhow::Error>,
+    },
+    #[error(
+        "Found {} invalid targets in package `{}`:\n{}",
+        .errors.len(),
+        .package,
+        display_list(.errors)
+    )]
+    Package {
+        package: String,
+        errors: Vec<anyhow::Error>,
+    },
+}
+
+fn display_list(errors: &[anyhow::Error]) -> String {
+    errors
+        .iter()
+        .map(|e| format!("  - {:#}", e).replace('\n', "\n    "))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 pub trait AttributeSpecExt {
     fn parse_params<'v>(
         &self,
@@ -55,6 +87,8 @@ impl AttributeSpecExt for AttributeSpec {
         internals: &ModuleInternals,
     ) -> anyhow::Result<(TargetName, AttrValues)> {
         let mut attr_values = AttrValues::with_capacity(arg_count);
+        // Only populated when attribute errors are aggregated.
+        let mut errors = Vec::new();
 
         let mut indices = self.attr_specs();
         let name = match indices.next() {
@@ -83,21 +117,27 @@ impl AttributeSpecExt for AttributeSpec {
 
             let is_visibility = attr_name == VISIBILITY_ATTRIBUTE_FIELD;
             if let Some(v) = user_value {
-                let mut coerced = attribute
-                    .coerce(
-                        attr_name,
-                        configurable,
-                        internals.attr_coercion_context(),
-                        v,
-                    )
-                    .with_context(|| {
-                        format!(
+                let coerced = attribute.coerce(
+                    attr_name,
+                    configurable,
+                    internals.attr_coercion_context(),
+                    v,
+                );
+                let mut coerced = match coerced {
+                    Ok(coerced) => coerced,
+                    Err(e) if internals.aggregate_attr_errors() => {
+                        errors.push(e);
+                        continue;
+                    }
+                    Err(e) => {
+                        return Err(e.context(format!(
                             "Error coercing attribute `{}` of `{}:{}`",
                             attr_name,
                             internals.buildfile_path().package(),
                             name,
-                        )
-                    })?;
+                        )));
+                    }
+                };
 
                 if is_visibility {
                     if internals.package().default_visibility_to_public {
@@ -144,6 +184,14 @@ impl AttributeSpecExt for AttributeSpec {
             }
         }
 
+        if !errors.is_empty() {
+            return Err(AttrErrors::Target {
+                target: format!("{}:{}", internals.buildfile_path().package(), name),
+                errors,
+            }
+            .into());
+        }
+
         attr_values.shrink_to_fit();
         Ok((name, attr_values))
     }
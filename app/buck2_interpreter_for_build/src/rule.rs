@@ -53,6 +53,7 @@ use crate::attrs::attribute_as_starlark_value::AttributeAsStarlarkValue;
 use crate::interpreter::build_context::BuildContext;
 use crate::interpreter::build_context::PerFileTypeContext;
 use crate::interpreter::module_internals::ModuleInternals;
+use crate::nodes::attr_spec::AttrErrors;
 use crate::nodes::attr_spec::AttributeSpecExt;
 use crate::nodes::unconfigured::TargetNodeExt;
 use crate::transition::transition_id_from_value;
@@ -231,16 +232,16 @@ impl<'v> StarlarkValue<'v> for FrozenRuleCallable {
         args: &Arguments<'v, '_>,
         eval: &mut Evaluator<'v, '_>,
     ) -> anyhow::Result<Value<'v>> {
-        let record_target_call_stack =
-            ModuleInternals::from_context(eval, self.rule.rule_type.name())?
-                .record_target_call_stacks();
+        let internals = ModuleInternals::from_context(eval, self.rule.rule_type.name())?;
+        let record_target_call_stack = internals.record_target_call_stacks();
+        let aggregate_attr_errors = internals.aggregate_attr_errors();
         let call_stack = if record_target_call_stack {
             Some(eval.call_stack())
         } else {
             None
         };
         let arg_count = args.len()?;
-        self.signature.parser(args, eval, |param_parser, eval| {
+        let res = self.signature.parser(args, eval, |param_parser, eval| {
             // The body of the callable returned by `rule()`.
             // Records the target in this package's `TargetMap`.
             let internals = ModuleInternals::from_context(eval, self.rule.rule_type.name())?;
@@ -253,9 +254,26 @@ impl<'v> StarlarkValue<'v> for FrozenRuleCallable {
                 self.ignore_attrs_for_profiling,
                 call_stack,
             )?;
-            internals.record(target_node)?;
-            Ok(Value::new_none())
-        })
+            internals.record(target_node)
+        });
+        match res {
+            Ok(()) => {}
+            Err(e) if aggregate_attr_errors => {
+                // Keep evaluating the package to report errors of all the targets at once.
+                let e = if e.is::<AttrErrors>() {
+                    e
+                } else {
+                    e.context(format!(
+                        "Error declaring a target of rule `{}`",
+                        self.rule.rule_type.name()
+                    ))
+                };
+                ModuleInternals::from_context(eval, self.rule.rule_type.name())?
+                    .record_attr_errors(e);
+            }
+            Err(e) => return Err(e),
+        }
+        Ok(Value::new_none())
     }
 
     fn documentation(&self) -> Option<DocItem> {
@@ -16,9 +16,12 @@ use dupe::Dupe;
 use starlark::values::OwnedFrozenValue;
 use starlark_map::small_map::SmallMap;
 
+use crate::super_package::package_value::PackageValueSchema;
+
 #[derive(Default, Debug, Allocative)]
 pub(crate) struct SuperPackageData {
     package_values: SmallMap<String, OwnedFrozenValue>,
+    package_value_schemas: SmallMap<String, PackageValueSchema>,
     visibility: VisibilitySpecification,
     within_view: WithinViewSpecification,
 }
@@ -31,11 +34,13 @@ pub(crate) struct SuperPackage(Arc<SuperPackageData>);
 impl SuperPackage {
     pub(crate) fn new(
         package_values: SmallMap<String, OwnedFrozenValue>,
+        package_value_schemas: SmallMap<String, PackageValueSchema>,
         visibility: VisibilitySpecification,
         within_view: WithinViewSpecification,
     ) -> SuperPackage {
         SuperPackage(Arc::new(SuperPackageData {
             package_values,
+            package_value_schemas,
             visibility,
             within_view,
         }))
@@ -45,6 +50,10 @@ impl SuperPackage {
         &self.0.package_values
     }
 
+    pub(crate) fn package_value_schemas(&self) -> &SmallMap<String, PackageValueSchema> {
+        &self.0.package_value_schemas
+    }
+
     pub(crate) fn visibility(&self) -> &VisibilitySpecification {
         &self.0.visibility
     }
@@ -58,19 +67,23 @@ impl PartialEq for SuperPackage {
     fn eq(&self, other: &Self) -> bool {
         let SuperPackageData {
             package_values: this_values,
+            package_value_schemas: this_schemas,
             visibility: this_visibility,
             within_view: this_within_view,
         } = &*self.0;
         let SuperPackageData {
             package_values: other_values,
+            package_value_schemas: other_schemas,
             visibility: other_visibility,
             within_view: other_within_view,
         } = &*other.0;
-        (this_visibility, this_within_view) == (other_visibility, other_within_view) && {
-            // If either package values are not empty, we cannot compare them
-            // because we cannot reliably compare arbitrary Starlark values.
-            // So if either package values are not empty, we consider super package not equal.
-            this_values.is_empty() && other_values.is_empty()
-        }
+        (this_schemas, this_visibility, this_within_view)
+            == (other_schemas, other_visibility, other_within_view)
+            && {
+                // If either package values are not empty, we cannot compare them
+                // because we cannot reliably compare arbitrary Starlark values.
+                // So if either package values are not empty, we consider super package not equal.
+                this_values.is_empty() && other_values.is_empty()
+            }
     }
 }
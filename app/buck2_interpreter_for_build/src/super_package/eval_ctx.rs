@@ -15,6 +15,7 @@ use starlark::values::OwnedFrozenValue;
 use starlark_map::small_map::SmallMap;
 
 use crate::super_package::data::SuperPackage;
+use crate::super_package::package_value::PackageValueSchema;
 
 #[derive(Debug, Default)]
 pub(crate) struct PackageFileVisibilityFields {
@@ -29,6 +30,8 @@ pub(crate) struct PackageFileEvalCtx {
     /// When evaluating root `PACKAGE` file, parent is still defined.
     pub(crate) parent: SuperPackage,
     pub(crate) visibility: RefCell<Option<PackageFileVisibilityFields>>,
+    /// Schemas declared in this file with `package_value_schema()`.
+    pub(crate) package_value_schemas: RefCell<SmallMap<String, PackageValueSchema>>,
 }
 
 impl PackageFileEvalCtx {
@@ -39,6 +42,9 @@ impl PackageFileEvalCtx {
         let mut merged_package_values = self.parent.package_values().clone();
         merged_package_values.extend(package_values);
 
+        let mut merged_package_value_schemas = self.parent.package_value_schemas().clone();
+        merged_package_value_schemas.extend(self.package_value_schemas.into_inner());
+
         let PackageFileVisibilityFields {
             visibility,
             within_view,
@@ -54,6 +60,11 @@ impl PackageFileEvalCtx {
             (visibility, within_view)
         };
 
-        SuperPackage::new(merged_package_values, visibility, within_view)
+        SuperPackage::new(
+            merged_package_values,
+            merged_package_value_schemas,
+            visibility,
+            within_view,
+        )
     }
 }
@@ -16,6 +16,7 @@ use starlark::environment::GlobalsBuilder;
 use starlark::eval::Evaluator;
 use starlark::starlark_module;
 use starlark::starlark_type;
+use starlark::values::dict::DictRef;
 use starlark::values::none::NoneType;
 use starlark::values::NoSerialize;
 use starlark::values::StarlarkValue;
@@ -26,6 +27,7 @@ use starlark_map::small_map::SmallMap;
 
 use crate::interpreter::build_context::BuildContext;
 use crate::interpreter::module_internals::ModuleInternals;
+use crate::super_package::eval_ctx::PackageFileEvalCtx;
 
 #[derive(Debug, thiserror::Error)]
 enum PackageValueError {
@@ -35,6 +37,27 @@ enum PackageValueError {
     KeySetInParentFile(String),
     #[error("key must contain exactly one dot: `{0}`")]
     KeyMustContainExactlyOneDot(String),
+    #[error("schema for key already declared in this or parent `PACKAGE` file: `{0}`")]
+    SchemaAlreadyDeclared(String),
+}
+
+/// A package value does not conform to the schema declared for its key.
+#[derive(Debug, thiserror::Error)]
+pub enum PackageValueSchemaError {
+    #[error("value for key `{key}` has type `{actual}`, but schema allows only: {expected}")]
+    WrongType {
+        key: String,
+        actual: String,
+        expected: String,
+    },
+    #[error("value for key `{key}` must be a dict with keys {expected}, but it is a `{actual}`")]
+    NotADict {
+        key: String,
+        actual: String,
+        expected: String,
+    },
+    #[error("value for key `{key}` is missing required key `{missing}`")]
+    MissingRequiredKey { key: String, missing: String },
 }
 
 fn validate_key(key: &str) -> anyhow::Result<()> {
@@ -44,6 +67,67 @@ fn validate_key(key: &str) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Constraints on a package value, declared with `package_value_schema()`.
+#[derive(Debug, Clone, PartialEq, Eq, Allocative)]
+pub(crate) struct PackageValueSchema {
+    /// Allowed types, as returned by `type()`. Empty means any type is allowed.
+    types: Vec<String>,
+    /// Keys which must be present. Non-empty implies the value must be a dict.
+    required_keys: Vec<String>,
+}
+
+impl PackageValueSchema {
+    fn validate(&self, key: &str, value: Value) -> Result<(), PackageValueSchemaError> {
+        let actual = value.get_type();
+        if !self.types.is_empty() && !self.types.iter().any(|t| t == actual) {
+            return Err(PackageValueSchemaError::WrongType {
+                key: key.to_owned(),
+                actual: actual.to_owned(),
+                expected: format_list(&self.types),
+            });
+        }
+        if !self.required_keys.is_empty() {
+            let dict = match DictRef::from_value(value) {
+                Some(dict) => dict,
+                None => {
+                    return Err(PackageValueSchemaError::NotADict {
+                        key: key.to_owned(),
+                        actual: actual.to_owned(),
+                        expected: format_list(&self.required_keys),
+                    });
+                }
+            };
+            for required in &self.required_keys {
+                if dict.get_str(required).is_none() {
+                    return Err(PackageValueSchemaError::MissingRequiredKey {
+                        key: key.to_owned(),
+                        missing: required.clone(),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn format_list(items: &[String]) -> String {
+    items
+        .iter()
+        .map(|i| format!("`{}`", i))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Check the value against the schema declared for the key in this or a parent `PACKAGE` file.
+fn check_schema(package_ctx: &PackageFileEvalCtx, key: &str, value: Value) -> anyhow::Result<()> {
+    if let Some(schema) = package_ctx.package_value_schemas.borrow().get(key) {
+        schema.validate(key, value)?;
+    } else if let Some(schema) = package_ctx.parent.package_value_schemas().get(key) {
+        schema.validate(key, value)?;
+    }
+    Ok(())
+}
+
 #[derive(
     Default,
     Debug,
@@ -97,10 +181,63 @@ pub(crate) fn register_write_package_value(globals: &mut GlobalsBuilder) {
             }
         }
 
+        check_schema(package_ctx, key, value)?;
+
         package_values.insert(key.to_owned(), value);
 
         Ok(NoneType)
     }
+
+    /// Declare the schema of the value for the key, in this and all nested `PACKAGE` files.
+    ///
+    /// `types` lists the allowed value types (as returned by `type()`), any type if empty.
+    /// `required_keys` lists the keys the value must contain, which requires the value to be
+    /// a dict. Values already set for the key are checked immediately, and
+    /// `write_package_value` fails for values which do not conform.
+    fn package_value_schema<'v>(
+        #[starlark(require = pos)] key: &str,
+        #[starlark(require = named, default = Vec::new())] types: Vec<String>,
+        #[starlark(require = named, default = Vec::new())] required_keys: Vec<String>,
+        eval: &mut Evaluator<'v, '_>,
+    ) -> anyhow::Result<NoneType> {
+        validate_key(key)?;
+
+        let package_ctx = BuildContext::from_context(eval)?
+            .additional
+            .require_package_file("package_value_schema")?;
+
+        if package_ctx.package_value_schemas.borrow().contains_key(key)
+            || package_ctx.parent.package_value_schemas().contains_key(key)
+        {
+            return Err(PackageValueError::SchemaAlreadyDeclared(key.to_owned()).into());
+        }
+
+        let schema = PackageValueSchema {
+            types,
+            required_keys,
+        };
+
+        let extra_value = eval
+            .module()
+            .extra_value()
+            .context("Module extra value was not set (internal error)")?;
+        let package_values = extra_value
+            .downcast_ref::<PackageValues>()
+            .context("Module extra value was not a `PackageValues` (internal error)")?;
+
+        if let Some(value) = package_values.values.borrow().get(key) {
+            schema.validate(key, *value)?;
+        } else if let Some(value) = package_ctx.parent.package_values().get(key) {
+            schema.validate(key, value.value())?;
+        }
+
+        package_ctx
+            .package_value_schemas
+            .borrow_mut()
+            .insert(key.to_owned(), schema);
+
+        Ok(NoneType)
+    }
 }
 
 #[starlark_module]
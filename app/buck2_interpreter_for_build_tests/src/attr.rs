@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use buck2_common::package_boundary::PackageBoundaryMode;
 use buck2_common::package_listing::listing::testing::PackageListingExt;
 use buck2_common::package_listing::listing::PackageListing;
 use buck2_common::result::SharedResult;
@@ -118,8 +119,11 @@ fn attr_coercer_coerces() -> anyhow::Result<()> {
         CellRelativePath::unchecked_new("foo"),
     );
     let enclosing_package = (package.dupe(), PackageListing::testing_empty());
-    let coercer_ctx =
-        BuildAttrCoercionContext::new_with_package(cell_resolver, enclosing_package, false);
+    let coercer_ctx = BuildAttrCoercionContext::new_with_package(
+        cell_resolver,
+        enclosing_package,
+        PackageBoundaryMode::Default,
+    );
     let label_coercer = AttrType::dep(ProviderIdSet::EMPTY);
     let string_coercer = AttrType::string();
     let enum_coercer = AttrType::enumeration(vec![
@@ -290,7 +294,7 @@ fn coercing_src_to_path_works() -> anyhow::Result<()> {
             package.dupe(),
             PackageListing::testing_files(&["baz/quz.cpp"]),
         ),
-        false,
+        PackageBoundaryMode::Default,
     );
     let no_package_ctx =
         BuildAttrCoercionContext::new_no_package(cell_resolver, CellName::testing_new("root"));
@@ -320,3 +324,41 @@ fn coercing_src_to_path_works() -> anyhow::Result<()> {
     );
     Ok(())
 }
+
+#[test]
+fn coercing_src_across_enforced_package_boundary_fails() -> anyhow::Result<()> {
+    let cell_resolver = cells(None).unwrap().1;
+    let package = PackageLabel::new(
+        CellName::testing_new("root"),
+        CellRelativePath::unchecked_new("foo"),
+    );
+    let ctx = BuildAttrCoercionContext::new_with_package(
+        cell_resolver,
+        (
+            package,
+            PackageListing::testing_with_subpackages(&["src/a.cpp", "src/b.cpp"], &["src/sub"]),
+        ),
+        PackageBoundaryMode::Enforced,
+    );
+
+    // Sources of this package are still fine.
+    ctx.coerce_path("src/a.cpp", false)?;
+
+    let err = ctx.coerce_path("src/sub/x.h", false).unwrap_err();
+    assert!(
+        err.to_string().contains(
+            "Source file `src/sub/x.h` of package `root//foo` belongs to subpackage `src/sub`"
+        ),
+        "{:#}",
+        err
+    );
+
+    let err = ctx.coerce_path("src", true).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("Directory `src` of package `root//foo` may not cover any subpackages"),
+        "{:#}",
+        err
+    );
+    Ok(())
+}
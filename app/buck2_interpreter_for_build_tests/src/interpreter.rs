@@ -10,6 +10,7 @@
 use buck2_build_api::interpreter::rule_defs::register_rule_defs;
 use buck2_common::legacy_configs::cells::BuckConfigBasedCells;
 use buck2_common::legacy_configs::testing::TestConfigParserFileOps;
+use buck2_common::package_boundary::PackageBoundaryMode;
 use buck2_common::package_listing::listing::testing::PackageListingExt;
 use buck2_common::package_listing::listing::PackageListing;
 use buck2_core::build_file_path::BuildFilePath;
@@ -18,9 +19,11 @@ use buck2_core::cells::name::CellName;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::fs::project::ProjectRoot;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_core::package::PackageLabel;
 use buck2_interpreter::path::StarlarkPath;
 use buck2_interpreter_for_build::interpreter::testing::CellsData;
 use buck2_interpreter_for_build::interpreter::testing::Tester;
+use buck2_node::nodes::eval_result::GlobPackageBoundaryViolation;
 use dupe::Dupe;
 use gazebo::prelude::SliceExt;
 use indoc::indoc;
@@ -314,3 +317,119 @@ fn test_root_import() {
         .collect::<Vec<_>>();
     assert_eq!(vec!["hello"], target_names);
 }
+
+#[test]
+fn test_glob_package_boundary() -> anyhow::Result<()> {
+    let content = indoc!(
+        r#"
+        a = glob(["src/*.java"])
+        b = glob(["src/sub/**/*.java"])
+        "#
+    );
+    let eval = |mode| {
+        let mut tester = Tester::new()?;
+        tester.set_package_boundary_mode(mode);
+        tester.eval_build_file(
+            &BuildFilePath::testing_new("root//some/package:BUCK"),
+            content,
+            PackageListing::testing_with_subpackages(&["src/a.java"], &["src/sub"]),
+        )
+    };
+
+    let eval_result = eval(PackageBoundaryMode::Default)?;
+    assert_eq!(
+        vec![GlobPackageBoundaryViolation {
+            pattern: "src/sub/**/*.java".to_owned(),
+            subpackage: PackageLabel::testing_parse("root//some/package/src/sub"),
+        }],
+        eval_result.glob_package_boundary_violations()
+    );
+
+    let err = eval(PackageBoundaryMode::Enforced).unwrap_err();
+    assert!(
+        format!("{:#}", err).contains(
+            "Glob pattern `src/sub/**/*.java` of package `root//some/package` can only match files of subpackage `src/sub`"
+        ),
+        "{:#}",
+        err
+    );
+    Ok(())
+}
+
+#[test]
+fn test_build_file_eval_limits() {
+    let eval_with_config = |config: &str, content: &str| {
+        let tester = Tester::with_cells(
+            buck2_interpreter_for_build::interpreter::testing::cells(Some(config)).unwrap(),
+        )
+        .unwrap();
+        tester.eval_build_file(
+            &BuildFilePath::testing_new("root//some/package:BUCK"),
+            content,
+            PackageListing::testing_files(&["file1.java", "file2.java"]),
+        )
+    };
+
+    let globs = indoc!(
+        r#"
+        a = glob(["file1.java"])
+        b = glob(["*.java"])
+        "#
+    );
+    assert!(eval_with_config("[buck2]\nbuild_file_max_glob_results = 3", globs).is_ok());
+    let err = eval_with_config("[buck2]\nbuild_file_max_glob_results = 2", globs).unwrap_err();
+    assert!(
+        format!("{:#}", err).contains("buck2.build_file_max_glob_results"),
+        "{:#}",
+        err
+    );
+
+    let loop_ = indoc!(
+        r#"
+        def f():
+            x = 0
+            for i in range(1000):
+                x += i
+            return x
+        f()
+        "#
+    );
+    let err = eval_with_config("[buck2]\nbuild_file_max_steps = 100", loop_).unwrap_err();
+    let message = format!("{:#}", err);
+    assert!(
+        message.contains("buck2.build_file_max_steps"),
+        "{}",
+        message
+    );
+    assert!(message.contains("Costliest stack frames"), "{}", message);
+    assert!(message.contains("\n  f (called from "), "{}", message);
+
+    // The loop never calls into buck2, so it must be aborted by the evaluator itself.
+    let infinite = indoc!(
+        r#"
+        def f():
+            for i in range(1000000000):
+                pass
+        f()
+        "#
+    );
+    let err = eval_with_config("[buck2]\nbuild_file_max_steps = 1000", infinite).unwrap_err();
+    assert!(
+        format!("{:#}", err).contains("buck2.build_file_max_steps"),
+        "{:#}",
+        err
+    );
+
+    let allocate = indoc!(
+        r#"
+        x = [str(i) * 100 for i in range(1000)]
+        "#
+    );
+    assert!(eval_with_config("", allocate).is_ok());
+    let err = eval_with_config("[buck2]\nbuild_file_max_heap_bytes = 10000", allocate).unwrap_err();
+    assert!(
+        format!("{:#}", err).contains("buck2.build_file_max_heap_bytes"),
+        "{:#}",
+        err
+    );
+}
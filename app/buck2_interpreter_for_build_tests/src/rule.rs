@@ -247,6 +247,53 @@ fn udr_rejects_invalid_parameters() {
     );
 }
 
+#[test]
+fn udr_aggregates_attr_errors() {
+    let content = indoc!(
+        r#"
+        def impl(ctx):
+            pass
+
+        foo_binary = rule(
+            impl=impl,
+            attrs={"first": attrs.string(), "second": attrs.list(attrs.string(), default=[])},
+        )
+
+        def test():
+            foo_binary(name="t1", first=1, second="not a list")
+            foo_binary(name="t2", first="ok")
+            foo_binary(name="t3", first=[])
+            foo_binary(name="t4")
+            foo_binary(name="t5", first="ok", third="unknown")
+            foo_binary(name="t2", first="duplicate")
+        "#
+    );
+
+    let mut tester = rule_tester();
+    tester.set_aggregate_attr_errors(true);
+    let err = format!("{:#}", tester.run_starlark_test(content).unwrap_err());
+    for expected in [
+        "Found 5 invalid targets in package `root//some/package`",
+        "Found 2 invalid attributes of `root//some/package:t1`",
+        "Error coercing attribute `first`",
+        "Error coercing attribute `second`",
+        "Found 1 invalid attributes of `root//some/package:t3`",
+        "Error declaring a target of rule `foo_binary`",
+        "Missing parameter `first`",
+        "extra named parameter(s)",
+        "Attempted to register target root//some/package:t2 twice",
+    ] {
+        assert!(
+            err.contains(expected),
+            "`{}` not found in:\n{}",
+            expected,
+            err
+        );
+    }
+    assert!(!err.contains(":t2`"), "{}", err);
+    assert!(!err.contains(":t5`"), "{}", err);
+}
+
 #[test]
 fn option_allows_none() -> anyhow::Result<()> {
     let mut tester = rule_tester();
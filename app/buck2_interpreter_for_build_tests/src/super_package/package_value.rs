@@ -13,6 +13,7 @@ use buck2_core::cells::build_file_cell::BuildFileCell;
 use buck2_core::fs::project::ProjectRootTemp;
 use buck2_core::package::PackageLabel;
 use buck2_interpreter::starlark_profiler::StarlarkProfilerOrInstrumentation;
+use buck2_interpreter_for_build::interpreter::calculation::InterpreterCalculation;
 use buck2_interpreter_for_build::interpreter::dice_calculation_delegate::HasCalculationDelegate;
 use buck2_node::attrs::display::AttrDisplayWithContextExt;
 use buck2_node::attrs::inspect_options::AttrInspectOptions;
@@ -204,3 +205,85 @@ async fn test_overwrite_package_value_with_flag() {
             .to_string()
     );
 }
+
+#[tokio::test]
+async fn test_package_value_schema_violation_in_nested_package_file() {
+    let fs = ProjectRootTemp::new().unwrap();
+
+    fs.write_file(
+        "PACKAGE",
+        "package_value_schema('aaa.bbb', types = ['dict'], required_keys = ['x'])",
+    );
+    fs.write_file("foo/PACKAGE", "write_package_value('aaa.bbb', {'y': 1})");
+    fs.write_file("foo/BUCK", "");
+
+    let ctx = calculation(&fs).await;
+    let interpreter = ctx
+        .get_interpreter_calculator(root_cell(), BuildFileCell::new(root_cell()))
+        .await
+        .unwrap();
+    let err = interpreter
+        .eval_build_file(
+            PackageLabel::testing_parse("root//foo"),
+            &mut StarlarkProfilerOrInstrumentation::disabled(),
+        )
+        .await;
+    assert!(
+        format!("{:?}", err).contains("value for key `aaa.bbb` is missing required key `x`"),
+        "err = {:?}",
+        err
+    );
+}
+
+#[tokio::test]
+async fn test_package_value_schema_checks_existing_value() {
+    let fs = ProjectRootTemp::new().unwrap();
+
+    fs.write_file("PACKAGE", "write_package_value('aaa.bbb', 'ccc')");
+    fs.write_file(
+        "foo/PACKAGE",
+        "package_value_schema('aaa.bbb', types = ['list'])",
+    );
+    fs.write_file("foo/BUCK", "");
+
+    let ctx = calculation(&fs).await;
+    let interpreter = ctx
+        .get_interpreter_calculator(root_cell(), BuildFileCell::new(root_cell()))
+        .await
+        .unwrap();
+    let err = interpreter
+        .eval_build_file(
+            PackageLabel::testing_parse("root//foo"),
+            &mut StarlarkProfilerOrInstrumentation::disabled(),
+        )
+        .await;
+    assert!(
+        format!("{:?}", err)
+            .contains("value for key `aaa.bbb` has type `string`, but schema allows only: `list`"),
+        "err = {:?}",
+        err
+    );
+}
+
+#[tokio::test]
+async fn test_get_package_values() {
+    let fs = ProjectRootTemp::new().unwrap();
+
+    fs.write_file("PACKAGE", "write_package_value('aaa.bbb', 'ccc')");
+    fs.write_file("foo/PACKAGE", "write_package_value('ddd.eee', [1, 2])");
+    fs.write_file("foo/BUCK", "");
+    fs.write_file("foo/bar/BUCK", "");
+
+    let ctx = calculation(&fs).await;
+    for package in ["root//foo", "root//foo/bar"] {
+        let values = ctx
+            .get_package_values(PackageLabel::testing_parse(package))
+            .await
+            .unwrap();
+        let values: Vec<_> = values
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value.value()))
+            .collect();
+        assert_eq!(vec!["aaa.bbb=\"ccc\"", "ddd.eee=[1, 2]"], values);
+    }
+}
@@ -83,6 +83,7 @@ pub(crate) async fn calculation(fs: &ProjectRootTemp) -> DiceTransaction {
             InterpreterHostArchitecture::X86_64,
             None,
             false,
+            false,
             register_read_package_value,
             register_package_natives,
             register_rule_defs,
@@ -175,6 +176,156 @@ async fn test_eval_import_with_load() {
     );
 }
 
+#[tokio::test]
+async fn test_eval_import_with_independent_loads() {
+    let fs = ProjectRootTemp::new().unwrap();
+
+    // Two independent chains sharing a module, and a module loading nothing.
+    fs.write_file("imports/shared.bzl", "shared = 'shared'");
+    fs.write_file(
+        "imports/a.bzl",
+        indoc!(
+            r#"
+                load("//imports:shared.bzl", "shared")
+                a = "a-" + shared
+            "#
+        ),
+    );
+    fs.write_file(
+        "imports/b.bzl",
+        indoc!(
+            r#"
+                load("//imports:shared.bzl", "shared")
+                b = "b-" + shared
+            "#
+        ),
+    );
+    fs.write_file("imports/c.bzl", "c = 'c'");
+    fs.write_file(
+        "pkg/two.bzl",
+        indoc!(
+            r#"
+                load("//imports:c.bzl", "c")
+                load("//imports:b.bzl", "b")
+                load("//imports:a.bzl", "a")
+                message = " ".join([a, b, c])
+            "#
+        ),
+    );
+
+    let ctx = calculation(&fs).await;
+    let calculation = ctx
+        .get_interpreter_calculator(root_cell(), BuildFileCell::new(root_cell()))
+        .await
+        .unwrap();
+
+    let env = calculation
+        .eval_module(StarlarkModulePath::LoadFile(&ImportPath::testing_new(
+            "root//pkg:two.bzl",
+        )))
+        .await
+        .unwrap();
+    assert_eq!(
+        "a-shared b-shared c",
+        env.env().get("message").unwrap().unpack_str().unwrap()
+    );
+    // Loaded modules are kept in the order of the `load` statements.
+    assert_eq!(
+        vec![
+            "root//imports/c.bzl",
+            "root//imports/b.bzl",
+            "root//imports/a.bzl"
+        ],
+        env.imports().map(|i| i.to_string()).collect::<Vec<_>>()
+    );
+}
+
+#[tokio::test]
+async fn test_eval_import_reports_first_failing_load() {
+    let fs = ProjectRootTemp::new().unwrap();
+
+    fs.write_file("imports/ok.bzl", "ok = 1");
+    fs.write_file("imports/first.bzl", "fail('first failure')");
+    fs.write_file("imports/second.bzl", "fail('second failure')");
+    fs.write_file(
+        "pkg/two.bzl",
+        indoc!(
+            r#"
+                load("//imports:ok.bzl", "ok")
+                load("//imports:first.bzl", "x")
+                load("//imports:second.bzl", "y")
+            "#
+        ),
+    );
+
+    let ctx = calculation(&fs).await;
+    let calculation = ctx
+        .get_interpreter_calculator(root_cell(), BuildFileCell::new(root_cell()))
+        .await
+        .unwrap();
+
+    let err = calculation
+        .eval_module(StarlarkModulePath::LoadFile(&ImportPath::testing_new(
+            "root//pkg:two.bzl",
+        )))
+        .await
+        .err()
+        .unwrap();
+    let err = format!("{:#}", err);
+    assert!(err.contains("first failure"), "{}", err);
+    assert!(!err.contains("second failure"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_eval_import_load_cycle() {
+    let fs = ProjectRootTemp::new().unwrap();
+
+    fs.write_file("imports/independent.bzl", "independent = 1");
+    fs.write_file(
+        "imports/a.bzl",
+        indoc!(
+            r#"
+                load("//imports:b.bzl", "b")
+                a = 1
+            "#
+        ),
+    );
+    fs.write_file(
+        "imports/b.bzl",
+        indoc!(
+            r#"
+                load("//imports:a.bzl", "a")
+                b = 1
+            "#
+        ),
+    );
+    fs.write_file(
+        "pkg/two.bzl",
+        indoc!(
+            r#"
+                load("//imports:independent.bzl", "independent")
+                load("//imports:a.bzl", "a")
+            "#
+        ),
+    );
+
+    let ctx = calculation(&fs).await;
+    let calculation = ctx
+        .get_interpreter_calculator(root_cell(), BuildFileCell::new(root_cell()))
+        .await
+        .unwrap();
+
+    let err = calculation
+        .eval_module(StarlarkModulePath::LoadFile(&ImportPath::testing_new(
+            "root//pkg:two.bzl",
+        )))
+        .await
+        .err()
+        .unwrap();
+    let err = format!("{:#}", err);
+    assert!(err.to_lowercase().contains("cycle"), "{}", err);
+}
+
 // TODO: this test require imports extractions
 #[tokio::test]
 async fn test_eval_build_file() {
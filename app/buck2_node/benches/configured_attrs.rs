@@ -0,0 +1,161 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Benchmark resolving a few attrs of every node of a large configured graph, as
+//! `cquery --output-attribute` does: configuring all the attrs and filtering them, looking up
+//! each requested attr, and configuring only the requested attrs in one pass.
+
+use std::sync::Arc;
+
+use buck2_core::bzl::ImportPath;
+use buck2_core::collections::ordered_map::OrderedMap;
+use buck2_core::collections::unordered_map::UnorderedMap;
+use buck2_core::configuration::data::ConfigurationData;
+use buck2_core::configuration::pair::ConfigurationNoExec;
+use buck2_core::target::label::ConfiguredTargetLabel;
+use buck2_node::attrs::attr::Attribute;
+use buck2_node::attrs::attr_type::list::ListLiteral;
+use buck2_node::attrs::attr_type::string::StringLiteral;
+use buck2_node::attrs::attr_type::AttrType;
+use buck2_node::attrs::coerced_attr::CoercedAttr;
+use buck2_node::configuration::execution::ExecutionPlatformResolution;
+use buck2_node::configuration::resolved::ResolvedConfiguration;
+use buck2_node::nodes::configured::ConfiguredTargetNode;
+use buck2_node::nodes::unconfigured::testing::TargetNodeExt;
+use buck2_node::nodes::unconfigured::TargetNode;
+use buck2_node::rule_type::RuleType;
+use buck2_node::rule_type::StarlarkRuleType;
+use buck2_query::query::environment::QueryTarget;
+use buck2_query::query::environment::QueryTargets;
+use buck2_util::arc_str::ArcSlice;
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::Criterion;
+use dupe::Dupe;
+
+const TARGETS: usize = 100_000;
+const ATTRS_PER_TARGET: usize = 50;
+
+/// The attrs requested by the benchmarked query, out of the `ATTRS_PER_TARGET` of each node.
+const REQUESTED_ATTRS: &[&str] = &["attr1", "attr17", "attr42"];
+
+fn attr_names() -> Vec<String> {
+    (0..ATTRS_PER_TARGET)
+        .map(|i| format!("attr{}", i))
+        .collect()
+}
+
+fn configured_node(
+    target: usize,
+    rule_type: &RuleType,
+    attr_names: &[String],
+) -> ConfiguredTargetNode {
+    let label = ConfiguredTargetLabel::testing_parse(
+        &format!("cell//pkg{}:target{}", target / 100, target),
+        ConfigurationData::testing_new(),
+    );
+    let attrs = attr_names
+        .iter()
+        .map(|name| {
+            let value = CoercedAttr::List(ListLiteral(ArcSlice::new([
+                CoercedAttr::String(StringLiteral(name.as_str().into())),
+                CoercedAttr::String(StringLiteral("value".into())),
+            ])));
+            (
+                name.as_str(),
+                Attribute::new(None, "", AttrType::list(AttrType::string())),
+                value,
+            )
+        })
+        .collect();
+    ConfiguredTargetNode::new(
+        label.dupe(),
+        TargetNode::testing_new(label.unconfigured().dupe(), rule_type.dupe(), attrs),
+        ResolvedConfiguration::new(
+            ConfigurationNoExec::new(label.cfg().dupe()),
+            UnorderedMap::new(),
+        ),
+        OrderedMap::new(),
+        ExecutionPlatformResolution::new(None, Vec::new()),
+        None,
+        Vec::new(),
+        Vec::new(),
+        OrderedMap::new(),
+    )
+}
+
+fn graph() -> Vec<ConfiguredTargetNode> {
+    let rule_type = RuleType::Starlark(Arc::new(StarlarkRuleType {
+        import_path: ImportPath::testing_new("cell//pkg:rules.bzl"),
+        name: "some_rule".to_owned(),
+    }));
+    let attr_names = attr_names();
+    (0..TARGETS)
+        .map(|target| configured_node(target, &rule_type, &attr_names))
+        .collect()
+}
+
+fn is_requested(attr_name: &str) -> bool {
+    REQUESTED_ATTRS.contains(&attr_name)
+}
+
+fn bench(c: &mut Criterion) {
+    let graph = graph();
+
+    c.bench_function("configure_all_attrs_and_filter", |b| {
+        b.iter(|| {
+            let mut count = 0;
+            for node in &graph {
+                QueryTargets::for_all_attrs::<(), _, _>(node, |attr_name, _attr| {
+                    if is_requested(attr_name) {
+                        count += 1;
+                    }
+                    Ok(())
+                })
+                .unwrap();
+            }
+            count
+        })
+    });
+    c.bench_function("configure_each_requested_attr", |b| {
+        b.iter(|| {
+            let mut count = 0;
+            for node in &graph {
+                for attr_name in REQUESTED_ATTRS {
+                    node.map_attr(attr_name, |attr| {
+                        if attr.is_some() {
+                            count += 1;
+                        }
+                    });
+                }
+            }
+            count
+        })
+    });
+    c.bench_function("configure_requested_attrs_batched", |b| {
+        b.iter(|| {
+            let mut count = 0;
+            for node in &graph {
+                QueryTargets::for_all_attrs_matching::<(), _, _>(
+                    node,
+                    &is_requested,
+                    |_attr_name, _attr| {
+                        count += 1;
+                        Ok(())
+                    },
+                )
+                .unwrap();
+            }
+            count
+        })
+    });
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
@@ -7,12 +7,15 @@
  * of this source tree.
  */
 
+use std::collections::BTreeMap;
 use std::fmt;
 use std::fmt::Formatter;
 use std::ops::Deref;
 
 use allocative::Allocative;
 use buck2_util::arc_str::ArcSlice;
+use serde::Serialize;
+use serde::Serializer;
 use serde_json::Value;
 
 use crate::attrs::attr_type::any_matches::AnyMatches;
@@ -21,6 +24,8 @@ use crate::attrs::display::AttrDisplayWithContext;
 use crate::attrs::display::AttrDisplayWithContextExt;
 use crate::attrs::fmt_context::AttrFmtContext;
 use crate::attrs::json::ToJsonWithContext;
+use crate::attrs::serialize::AttrSerializeWithContext;
+use crate::attrs::serialize::AttrSerializeWithContextExt;
 
 #[derive(Debug, Hash, Eq, PartialEq, Allocative)]
 pub struct DictAttrType {
@@ -101,3 +106,26 @@ impl<C: Eq + ToJsonWithContext> ToJsonWithContext for DictLiteral<C> {
         Ok(res.into())
     }
 }
+
+impl<C: Eq + ToJsonWithContext + AttrSerializeWithContext> AttrSerializeWithContext
+    for DictLiteral<C>
+{
+    fn serialize_with_ctx<S>(&self, ctx: &AttrFmtContext, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // Keys are written in the same (sorted) order as `to_json` would produce.
+        let mut entries = BTreeMap::new();
+        for (k, v) in self.iter() {
+            let key = k
+                .to_json(ctx)
+                .map_err(|e| serde::ser::Error::custom(format!("{}", e)))?;
+            let key = match key {
+                Value::String(key) => key,
+                key => key.to_string(),
+            };
+            entries.insert(key, v.as_serialize(ctx));
+        }
+        entries.serialize(s)
+    }
+}
@@ -14,6 +14,8 @@ use std::ops::Deref;
 use allocative::Allocative;
 use buck2_util::arc_str::ArcSlice;
 use gazebo::prelude::SliceExt;
+use serde::ser::SerializeSeq;
+use serde::Serializer;
 use serde_json::to_value;
 use serde_json::Value;
 
@@ -22,6 +24,8 @@ use crate::attrs::attr_type::AttrType;
 use crate::attrs::display::AttrDisplayWithContext;
 use crate::attrs::fmt_context::AttrFmtContext;
 use crate::attrs::json::ToJsonWithContext;
+use crate::attrs::serialize::AttrSerializeWithContext;
+use crate::attrs::serialize::AttrSerializeWithContextExt;
 
 #[derive(Debug, Hash, Eq, PartialEq, Allocative)]
 pub struct ListAttrType {
@@ -85,3 +89,16 @@ impl<C: Eq + ToJsonWithContext> ToJsonWithContext for ListLiteral<C> {
         Ok(to_value(self.try_map(|c| c.to_json(ctx))?)?)
     }
 }
+
+impl<C: Eq + AttrSerializeWithContext> AttrSerializeWithContext for ListLiteral<C> {
+    fn serialize_with_ctx<S>(&self, ctx: &AttrFmtContext, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = s.serialize_seq(Some(self.len()))?;
+        for c in self.iter() {
+            seq.serialize_element(&c.as_serialize(ctx))?;
+        }
+        seq.end()
+    }
+}
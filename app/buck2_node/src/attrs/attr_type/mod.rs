@@ -268,7 +268,7 @@ impl AttrType {
         Self(Arc::new(AttrTypeInner::Label(LabelAttrType)))
     }
 
-    pub(crate) fn visibility() -> Self {
+    pub fn visibility() -> Self {
         Self(Arc::new(AttrTypeInner::Visibility(VisibilityAttrType)))
     }
 
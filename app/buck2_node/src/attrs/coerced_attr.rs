@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use std::collections::BTreeMap;
 use std::collections::HashSet;
 use std::fmt::Display;
 use std::hash::Hash;
@@ -25,6 +26,7 @@ use dupe::Dupe;
 use dupe::IterDupedExt;
 use gazebo::prelude::SliceExt;
 use itertools::Itertools;
+use serde::ser::SerializeMap;
 use serde::Serialize;
 use serde::Serializer;
 use serde_json::to_value;
@@ -55,6 +57,7 @@ use crate::attrs::display::AttrDisplayWithContextExt;
 use crate::attrs::fmt_context::AttrFmtContext;
 use crate::attrs::json::ToJsonWithContext;
 use crate::attrs::serialize::AttrSerializeWithContext;
+use crate::attrs::serialize::AttrSerializeWithContextExt;
 use crate::attrs::traversal::CoercedAttrTraversal;
 use crate::visibility::VisibilitySpecification;
 
@@ -74,6 +77,12 @@ enum SelectError {
     ConcatEmpty,
     #[error("duplicate key `{0}` in `select()`")]
     DuplicateKey(String),
+    #[error(
+        "`select()` on `exec:` keys cannot be resolved while resolving the execution platform, \
+        and no default was set:\n{}",
+        .0.iter().map(| s | format ! ("  exec:{}", s)).join("\n"),
+    )]
+    ExecMissingDefault(Vec<TargetLabel>),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -84,13 +93,31 @@ enum CoercedAttrError {
 
 enum CoercedSelectorKeyRef<'a> {
     Target(&'a TargetLabel),
+    ExecTarget(&'a TargetLabel),
     Default,
 }
 
+impl Display for CoercedSelectorKeyRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CoercedSelectorKeyRef::Target(k) => write!(f, "{}", k),
+            CoercedSelectorKeyRef::ExecTarget(k) => write!(f, "{}{}", EXEC_SELECT_KEY_PREFIX, k),
+            CoercedSelectorKeyRef::Default => write!(f, "DEFAULT"),
+        }
+    }
+}
+
+/// Prefix of `select()` keys matched against the configuration of the execution platform, e.g.
+/// `"exec:prelude//os:linux"`, rather than against the target configuration.
+pub const EXEC_SELECT_KEY_PREFIX: &str = "exec:";
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Allocative)]
 pub struct CoercedSelector {
     pub(crate) entries: ArcSlice<(TargetLabel, CoercedAttr)>,
     pub(crate) default: Option<CoercedAttr>,
+    /// Keys are matched against the execution platform configuration (they are written with the
+    /// `exec:` prefix).
+    pub(crate) exec: bool,
 }
 
 impl CoercedSelector {
@@ -99,7 +126,26 @@ impl CoercedSelector {
         default: Option<CoercedAttr>,
     ) -> anyhow::Result<CoercedSelector> {
         Self::check_all_keys_unique(&entries)?;
-        Ok(CoercedSelector { entries, default })
+        Ok(CoercedSelector {
+            entries,
+            default,
+            exec: false,
+        })
+    }
+
+    /// A `select()` whose keys are matched against the execution platform configuration.
+    pub fn new_exec(
+        entries: ArcSlice<(TargetLabel, CoercedAttr)>,
+        default: Option<CoercedAttr>,
+    ) -> anyhow::Result<CoercedSelector> {
+        Ok(CoercedSelector {
+            exec: true,
+            ..Self::new(entries, default)?
+        })
+    }
+
+    pub fn is_exec(&self) -> bool {
+        self.exec
     }
 
     fn check_all_keys_unique(entries: &[(TargetLabel, CoercedAttr)]) -> anyhow::Result<()> {
@@ -139,7 +185,14 @@ impl CoercedSelector {
     fn all_entries(&self) -> impl Iterator<Item = (CoercedSelectorKeyRef, &CoercedAttr)> {
         self.entries
             .iter()
-            .map(|(k, v)| (CoercedSelectorKeyRef::Target(k), v))
+            .map(|(k, v)| {
+                let key = if self.exec {
+                    CoercedSelectorKeyRef::ExecTarget(k)
+                } else {
+                    CoercedSelectorKeyRef::Target(k)
+                };
+                (key, v)
+            })
             .chain(
                 self.default
                     .iter()
@@ -223,14 +276,7 @@ impl AttrDisplayWithContext for CoercedAttr {
                     if i > 0 {
                         write!(f, ",")?;
                     }
-                    match key {
-                        CoercedSelectorKeyRef::Target(k) => {
-                            write!(f, "\"{}\"={}", k, value.as_display(ctx))?;
-                        }
-                        CoercedSelectorKeyRef::Default => {
-                            write!(f, "\"DEFAULT\"={}", value.as_display(ctx))?;
-                        }
-                    }
+                    write!(f, "\"{}\"={}", key, value.as_display(ctx))?;
                 }
                 write!(f, ")")?;
                 Ok(())
@@ -270,14 +316,71 @@ impl AttrSerializeWithContext for CoercedAttr {
     where
         S: Serializer,
     {
-        // TODO this is inefficient. We should impl Serialize and derive value from this instead.
-        self.to_json(ctx)
-            .map_err(|e| serde::ser::Error::custom(format!("{}", e)))?
-            .serialize(s)
+        // Produces the same output as `to_json`, without building an intermediate value, which
+        // matters when dumping all attributes of many targets (e.g. `query -A`).
+        match self {
+            CoercedAttr::Selector(sel) => {
+                let mut entries = BTreeMap::new();
+                for (key, value) in sel.all_entries() {
+                    let key = key.to_string();
+                    entries.insert(key, value.as_serialize(ctx));
+                }
+                let mut map = s.serialize_map(Some(2))?;
+                map.serialize_entry("__type", "selector")?;
+                map.serialize_entry("entries", &entries)?;
+                map.end()
+            }
+            CoercedAttr::Concat(items) => {
+                let items: Vec<_> = items.iter().map(|item| item.as_serialize(ctx)).collect();
+                let mut map = s.serialize_map(Some(2))?;
+                map.serialize_entry("__type", "concat")?;
+                map.serialize_entry("items", &items)?;
+                map.end()
+            }
+            CoercedAttr::Bool(v) => v.serialize(s),
+            CoercedAttr::Int(v) => v.serialize(s),
+            CoercedAttr::String(v) | CoercedAttr::EnumVariant(v) => v.serialize(s),
+            CoercedAttr::List(list) => list.serialize_with_ctx(ctx, s),
+            CoercedAttr::Tuple(list) => list.serialize_with_ctx(ctx, s),
+            CoercedAttr::Dict(dict) => dict.serialize_with_ctx(ctx, s),
+            CoercedAttr::None => s.serialize_none(),
+            CoercedAttr::OneOf(box l, _) => l.serialize_with_ctx(ctx, s),
+            CoercedAttr::Visibility(_) | CoercedAttr::ExplicitConfiguredDep(_) => self
+                .to_json(ctx)
+                .map_err(|e| serde::ser::Error::custom(format!("{}", e)))?
+                .serialize(s),
+            CoercedAttr::SplitTransitionDep(e) => s.collect_str(e),
+            CoercedAttr::ConfiguredDep(e) => s.collect_str(e),
+            CoercedAttr::ConfigurationDep(e) => s.collect_str(e),
+            CoercedAttr::Dep(e) => s.collect_str(e),
+            CoercedAttr::SourceLabel(e) => s.collect_str(e),
+            CoercedAttr::Label(e) => s.collect_str(e),
+            CoercedAttr::Arg(e) => s.collect_str(e),
+            CoercedAttr::Query(e) => s.serialize_str(e.query()),
+            CoercedAttr::SourceFile(e) => s.collect_str(&source_file_display(ctx, e)),
+        }
     }
 }
 
 impl CoercedAttr {
+    /// Renders the attr the way it would be written in a build file. This is like the
+    /// `AttrDisplayWithContext` impl, except that `select()` takes a dict, as it does in Starlark.
+    pub fn to_starlark_string(&self, ctx: &AttrFmtContext) -> String {
+        match self {
+            CoercedAttr::Selector(s) => {
+                let entries = s.all_entries().map(|(key, value)| {
+                    let key = key.to_string();
+                    format!("\"{}\": {}", key, value.to_starlark_string(ctx))
+                });
+                format!("select({{{}}})", entries.format(", "))
+            }
+            CoercedAttr::Concat(items) => {
+                items.iter().map(|a| a.to_starlark_string(ctx)).join(" + ")
+            }
+            _ => self.as_display(ctx).to_string(),
+        }
+    }
+
     /// Converts the coerced attr to a serde_json Value. This is generally just used for debugging or introspective
     /// things, a lot of the types will be dropped without special handling. For example, an artifact will just end
     /// up as the stringified version of its coerced value (i.e. while `//a:b` might represent some list of targets,
@@ -287,14 +390,7 @@ impl CoercedAttr {
             CoercedAttr::Selector(s) => {
                 let mut map = serde_json::Map::new();
                 for (key, value) in s.all_entries() {
-                    match key {
-                        CoercedSelectorKeyRef::Target(k) => {
-                            map.insert(k.to_string(), value.to_json(ctx)?);
-                        }
-                        CoercedSelectorKeyRef::Default => {
-                            map.insert("DEFAULT".to_owned(), value.to_json(ctx)?);
-                        }
-                    }
+                    map.insert(key.to_string(), value.to_json(ctx)?);
                 }
                 let select = serde_json::Value::Object(map);
 
@@ -361,9 +457,20 @@ impl CoercedAttr {
         traversal: &mut dyn CoercedAttrTraversal<'a>,
     ) -> anyhow::Result<()> {
         match CoercedAttrWithType::pack(self, t)? {
-            CoercedAttrWithType::Selector(CoercedSelector { entries, default }, t) => {
+            CoercedAttrWithType::Selector(
+                CoercedSelector {
+                    entries,
+                    default,
+                    exec,
+                },
+                t,
+            ) => {
                 for (condition, value) in entries.iter() {
-                    traversal.configuration_dep(condition)?;
+                    if *exec {
+                        traversal.exec_configuration_dep(condition)?;
+                    } else {
+                        traversal.configuration_dep(condition)?;
+                    }
                     value.traverse(t, pkg.dupe(), traversal)?;
                 }
                 if let Some(v) = default {
@@ -464,9 +571,16 @@ impl CoercedAttr {
         ctx: &dyn AttrConfigurationContext,
         select_entries: &'a [(TargetLabel, CoercedAttr)],
     ) -> anyhow::Result<Option<&'a CoercedAttr>> {
+        Ok(Self::select_the_most_specific_by(|k| ctx.matches(k), select_entries)?.map(|(_k, v)| v))
+    }
+
+    fn select_the_most_specific_by<'a, 'c>(
+        matches: impl Fn(&TargetLabel) -> Option<&'c ConfigSettingData>,
+        select_entries: &'a [(TargetLabel, CoercedAttr)],
+    ) -> anyhow::Result<Option<(&'a TargetLabel, &'a CoercedAttr)>> {
         let mut matching: Option<(&TargetLabel, &ConfigSettingData, &CoercedAttr)> = None;
         for (k, v) in select_entries {
-            matching = match (ctx.matches(k), matching) {
+            matching = match (matches(k), matching) {
                 (None, matching) => matching,
                 (Some(conf), None) => Some((k, conf, v)),
                 (Some(conf), Some((prev_k, prev_conf, prev_v))) => {
@@ -484,27 +598,105 @@ impl CoercedAttr {
                 }
             }
         }
-        Ok(matching.map(|(_k, _conf, v)| v))
+        Ok(matching.map(|(k, _conf, v)| (k, v)))
     }
 
     fn select<'a>(
         ctx: &dyn AttrConfigurationContext,
         select: &'a CoercedSelector,
     ) -> anyhow::Result<&'a CoercedAttr> {
-        let CoercedSelector { entries, default } = select;
-        if let Some(v) = Self::select_the_most_specific(ctx, entries)? {
-            Ok(v)
+        Ok(Self::select_entry(ctx, select)?.1)
+    }
+
+    /// The entry of the `select()` matching the configuration, along with its key.
+    fn select_entry<'a>(
+        ctx: &dyn AttrConfigurationContext,
+        select: &'a CoercedSelector,
+    ) -> anyhow::Result<(CoercedSelectorKeyRef<'a>, &'a CoercedAttr)> {
+        let CoercedSelector {
+            entries,
+            default,
+            exec,
+        } = select;
+        let default = || {
+            default
+                .as_ref()
+                .map(|default| (CoercedSelectorKeyRef::Default, default))
+        };
+        let (selected, cfg) = if *exec {
+            match ctx.exec_resolved_cfg() {
+                Some(exec_cfg) => (
+                    Self::select_the_most_specific_by(|k| exec_cfg.matches(k), entries)?
+                        .map(|(k, v)| (CoercedSelectorKeyRef::ExecTarget(k), v)),
+                    exec_cfg.cfg().cfg().dupe(),
+                ),
+                // We are configuring the attributes to resolve the execution platform.
+                None => {
+                    return default().ok_or_else(|| {
+                        SelectError::ExecMissingDefault(
+                            entries.iter().map(|(k, _)| k).duped().collect(),
+                        )
+                        .into()
+                    });
+                }
+            }
         } else {
-            default.as_ref().ok_or_else(|| {
-                SelectError::MissingDefault(
-                    ctx.cfg().cfg().dupe(),
-                    entries.iter().map(|(k, _)| k).duped().collect(),
-                )
-                .into()
+            (
+                Self::select_the_most_specific_by(|k| ctx.matches(k), entries)?
+                    .map(|(k, v)| (CoercedSelectorKeyRef::Target(k), v)),
+                ctx.cfg().cfg().dupe(),
+            )
+        };
+        if let Some(entry) = selected {
+            Ok(entry)
+        } else {
+            default().ok_or_else(|| {
+                SelectError::MissingDefault(cfg, entries.iter().map(|(k, _)| k).duped().collect())
+                    .into()
             })
         }
     }
 
+    /// The key of the `select()` this attribute resolves with in the provided context (`DEFAULT`
+    /// for its default), if the attribute is a `select()`.
+    pub fn selected_key(
+        &self,
+        ctx: &dyn AttrConfigurationContext,
+    ) -> anyhow::Result<Option<String>> {
+        match self {
+            CoercedAttr::Selector(select) => {
+                Ok(Some(Self::select_entry(ctx, select)?.0.to_string()))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Collect the keys of the `select()`s of this attribute (including the ones nested in the
+    /// selected branches) which are selected in the provided context. When several keys match,
+    /// only the most specific one, which is selected, is collected. `exec:` keys are not collected.
+    pub fn selected_keys<'a>(
+        &'a self,
+        ctx: &dyn AttrConfigurationContext,
+        keys: &mut Vec<&'a TargetLabel>,
+    ) -> anyhow::Result<()> {
+        match self {
+            CoercedAttr::Selector(select) => {
+                let (key, value) = Self::select_entry(ctx, select)?;
+                if let CoercedSelectorKeyRef::Target(key) = key {
+                    keys.push(key);
+                }
+                value.selected_keys(ctx, keys)
+            }
+            CoercedAttr::Concat(items) => {
+                for item in items.iter() {
+                    item.selected_keys(ctx, keys)?;
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Returns the "configured" representation of the attribute in the provided context.
     /// This handles the resolution of the select() conditions and delegates to
     /// the actual attr type for handling any appropriate configuration-time
@@ -643,16 +835,68 @@ impl CoercedAttr {
             CoercedAttr::SourceFile(e) => filter(&e.path().to_string()),
         }
     }
+
+    /// Like `any_matches`, but a selector only matches if every one of its branches matches,
+    /// including selectors nested in containers. Containers match if any contained item matches.
+    pub fn all_branches_match(
+        &self,
+        filter: &dyn Fn(&str) -> anyhow::Result<bool>,
+    ) -> anyhow::Result<bool> {
+        match self {
+            CoercedAttr::Selector(s) => {
+                for value in s.all_values() {
+                    if !value.all_branches_match(filter)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            CoercedAttr::Concat(items) => {
+                for item in &**items {
+                    if item.all_branches_match(filter)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+            CoercedAttr::List(ListLiteral(items)) | CoercedAttr::Tuple(TupleLiteral(items)) => {
+                for item in items.iter() {
+                    if item.all_branches_match(filter)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+            CoercedAttr::Dict(DictLiteral(entries)) => {
+                for (k, v) in entries.iter() {
+                    if k.all_branches_match(filter)? || v.all_branches_match(filter)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+            CoercedAttr::OneOf(l, _) => l.all_branches_match(filter),
+            _ => self.any_matches(filter),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
     use buck2_core::target::label::TargetLabel;
+    use buck2_util::arc_str::ArcSlice;
+    use buck2_util::arc_str::ArcStr;
     use dupe::Dupe;
 
+    use crate::attrs::attr_type::bool::BoolLiteral;
+    use crate::attrs::attr_type::dict::DictLiteral;
+    use crate::attrs::attr_type::list::ListLiteral;
+    use crate::attrs::attr_type::string::StringLiteral;
     use crate::attrs::coerced_attr::CoercedAttr;
     use crate::attrs::coerced_attr::CoercedSelector;
+    use crate::attrs::fmt_context::AttrFmtContext;
+    use crate::attrs::serialize::AttrSerializeWithContextExt;
 
     #[test]
     fn test_check_all_keys_unique_small() {
@@ -690,4 +934,73 @@ mod tests {
         long[10].0 = long[0].0.dupe();
         assert!(CoercedSelector::check_all_keys_unique(&long).is_err());
     }
+
+    #[test]
+    fn test_all_branches_match() -> anyhow::Result<()> {
+        let string = |s: &str| CoercedAttr::String(StringLiteral(ArcStr::from(s)));
+        let select = |a: CoercedAttr, b: CoercedAttr| -> anyhow::Result<CoercedAttr> {
+            Ok(CoercedAttr::Selector(Box::new(CoercedSelector::new(
+                ArcSlice::from_iter([(TargetLabel::testing_parse("foo//:linux"), a)]),
+                Some(b),
+            )?)))
+        };
+        let filter = |s: &str| Ok(s.starts_with("lib"));
+
+        let both = select(
+            CoercedAttr::List(ListLiteral(ArcSlice::from_iter([string("libfoo")]))),
+            string("libbar"),
+        )?;
+        assert!(both.any_matches(&filter)?);
+        assert!(both.all_branches_match(&filter)?);
+
+        let one = select(string("libfoo"), string("bar"))?;
+        assert!(one.any_matches(&filter)?);
+        assert!(!one.all_branches_match(&filter)?);
+
+        // Selectors nested in containers must match on every branch too.
+        let list = CoercedAttr::List(ListLiteral(ArcSlice::from_iter([
+            select(string("libfoo"), string("bar"))?,
+            string("baz"),
+        ])));
+        assert!(list.any_matches(&filter)?);
+        assert!(!list.all_branches_match(&filter)?);
+        let dict = CoercedAttr::Dict(DictLiteral(ArcSlice::from_iter([(
+            string("key"),
+            select(string("libfoo"), string("libbar"))?,
+        )])));
+        assert!(dict.all_branches_match(&filter)?);
+
+        let concat = CoercedAttr::Concat(Box::new([one, string("libbaz")]));
+        assert!(concat.all_branches_match(&filter)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_matches_to_json() -> anyhow::Result<()> {
+        let string = |s: &str| CoercedAttr::String(StringLiteral(ArcStr::from(s)));
+        let dict = CoercedAttr::Dict(DictLiteral(ArcSlice::from_iter([
+            (string("z"), CoercedAttr::Int(1)),
+            (string("a"), CoercedAttr::None),
+        ])));
+        let list = CoercedAttr::List(ListLiteral(ArcSlice::from_iter([
+            string("x"),
+            CoercedAttr::Bool(BoolLiteral(false)),
+        ])));
+        let select = CoercedAttr::Selector(Box::new(CoercedSelector::new(
+            ArcSlice::from_iter([
+                (TargetLabel::testing_parse("config//:b"), dict),
+                (TargetLabel::testing_parse("config//:a"), list),
+            ]),
+            Some(string("default")),
+        )?));
+        let attr = CoercedAttr::Concat(Box::new([select, string("suffix")]));
+
+        let ctx = AttrFmtContext::NO_CONTEXT;
+        assert_eq!(
+            attr.to_json(&ctx)?.to_string(),
+            serde_json::to_string(&attr.as_serialize(&ctx))?
+        );
+        Ok(())
+    }
 }
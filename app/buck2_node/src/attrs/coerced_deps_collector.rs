@@ -37,6 +37,10 @@ pub struct CoercedDeps {
     /// Contains the configuration deps. These are deps that appear as conditions in selects.
     pub configuration_deps: Box<[TargetLabel]>,
 
+    /// Contains the configuration deps which appear as `exec:` conditions in selects. These are
+    /// also included in `configuration_deps`.
+    pub exec_configuration_deps: Box<[TargetLabel]>,
+
     /// Contains platform targets of configured_alias()
     pub platform_deps: Box<[TargetLabel]>,
 }
@@ -49,6 +53,7 @@ impl From<CoercedDepsCollector> for CoercedDeps {
             exec_deps,
             toolchain_deps,
             configuration_deps,
+            exec_configuration_deps,
             platform_deps,
         } = collector;
         CoercedDeps {
@@ -57,6 +62,7 @@ impl From<CoercedDepsCollector> for CoercedDeps {
             exec_deps: exec_deps.into_iter().collect(),
             toolchain_deps: toolchain_deps.into_iter().collect(),
             configuration_deps: configuration_deps.into_iter().collect(),
+            exec_configuration_deps: exec_configuration_deps.into_iter().collect(),
             platform_deps: platform_deps.into_iter().collect(),
         }
     }
@@ -81,6 +87,10 @@ pub struct CoercedDepsCollector {
     /// Contains the configuration deps. These are deps that appear as conditions in selects.
     pub configuration_deps: OrderedSet<TargetLabel>,
 
+    /// Contains the configuration deps which appear as `exec:` conditions in selects. These are
+    /// also included in `configuration_deps`.
+    pub exec_configuration_deps: OrderedSet<TargetLabel>,
+
     /// Contains platform targets of configured_alias()
     pub platform_deps: OrderedSet<TargetLabel>,
 }
@@ -93,6 +103,7 @@ impl CoercedDepsCollector {
             toolchain_deps: OrderedSet::new(),
             transition_deps: OrderedSet::new(),
             configuration_deps: OrderedSet::new(),
+            exec_configuration_deps: OrderedSet::new(),
             platform_deps: OrderedSet::new(),
         }
     }
@@ -137,6 +148,12 @@ impl<'a> CoercedAttrTraversal<'a> for CoercedDepsCollector {
         Ok(())
     }
 
+    fn exec_configuration_dep(&mut self, dep: &'a TargetLabel) -> anyhow::Result<()> {
+        self.configuration_deps.insert(dep.dupe());
+        self.exec_configuration_deps.insert(dep.dupe());
+        Ok(())
+    }
+
     fn platform_dep(&mut self, dep: &'a TargetLabel) -> anyhow::Result<()> {
         self.platform_deps.insert(dep.dupe());
         Ok(())
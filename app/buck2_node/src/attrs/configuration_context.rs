@@ -42,6 +42,12 @@ pub trait AttrConfigurationContext {
 
     fn exec_cfg(&self) -> ConfigurationNoExec;
 
+    /// The configuration of the execution platform resolved against the `exec:` keys of
+    /// `select()`s. `None` while resolving the execution platform.
+    fn exec_resolved_cfg(&self) -> Option<&ResolvedConfiguration> {
+        None
+    }
+
     /// Must be equal to `(cfg, Some(exec_cfg))`.
     fn toolchain_cfg(&self) -> ConfigurationWithExec;
 
@@ -99,6 +105,7 @@ pub trait AttrConfigurationContext {
 pub struct AttrConfigurationContextImpl<'b> {
     resolved_cfg: &'b ResolvedConfiguration,
     exec_cfg: ConfigurationNoExec,
+    exec_resolved_cfg: Option<&'b ResolvedConfiguration>,
     /// Must be equal to `(cfg, Some(exec_cfg))`.
     toolchain_cfg: ConfigurationWithExec,
     resolved_transitions: &'b OrderedMap<Arc<TransitionId>, Arc<TransitionApplied>>,
@@ -116,10 +123,20 @@ impl<'b> AttrConfigurationContextImpl<'b> {
             resolved_cfg,
             toolchain_cfg: resolved_cfg.cfg().make_toolchain(&exec_cfg),
             exec_cfg,
+            exec_resolved_cfg: None,
             resolved_transitions,
             platform_cfgs,
         }
     }
+
+    /// Match the `exec:` keys of `select()`s against `exec_resolved_cfg`.
+    pub fn with_exec_resolved_cfg(
+        mut self,
+        exec_resolved_cfg: Option<&'b ResolvedConfiguration>,
+    ) -> AttrConfigurationContextImpl<'b> {
+        self.exec_resolved_cfg = exec_resolved_cfg;
+        self
+    }
 }
 
 impl<'b> AttrConfigurationContext for AttrConfigurationContextImpl<'b> {
@@ -136,6 +153,10 @@ impl<'b> AttrConfigurationContext for AttrConfigurationContextImpl<'b> {
         self.exec_cfg.dupe()
     }
 
+    fn exec_resolved_cfg(&self) -> Option<&ResolvedConfiguration> {
+        self.exec_resolved_cfg
+    }
+
     fn toolchain_cfg(&self) -> ConfigurationWithExec {
         self.toolchain_cfg.dupe()
     }
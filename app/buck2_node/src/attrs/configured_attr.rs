@@ -112,10 +112,30 @@ impl AttrSerializeWithContext for ConfiguredAttr {
     where
         S: Serializer,
     {
-        // TODO this is inefficient. We should impl Serialize and derive value from this instead.
-        self.to_json(ctx)
-            .map_err(|e| serde::ser::Error::custom(format!("{}", e)))?
-            .serialize(s)
+        // Produces the same output as `to_json`, without building an intermediate value.
+        match self {
+            ConfiguredAttr::Bool(v) => v.serialize(s),
+            ConfiguredAttr::Int(v) => v.serialize(s),
+            ConfiguredAttr::String(v) | ConfiguredAttr::EnumVariant(v) => v.serialize(s),
+            ConfiguredAttr::List(list) => list.serialize_with_ctx(ctx, s),
+            ConfiguredAttr::Tuple(list) => list.serialize_with_ctx(ctx, s),
+            ConfiguredAttr::Dict(dict) => dict.serialize_with_ctx(ctx, s),
+            ConfiguredAttr::None => s.serialize_none(),
+            ConfiguredAttr::OneOf(box l, _) => l.serialize_with_ctx(ctx, s),
+            ConfiguredAttr::Visibility(_)
+            | ConfiguredAttr::ExplicitConfiguredDep(_)
+            | ConfiguredAttr::SplitTransitionDep(_) => self
+                .to_json(ctx)
+                .map_err(|e| serde::ser::Error::custom(format!("{}", e)))?
+                .serialize(s),
+            ConfiguredAttr::ConfigurationDep(e) => s.collect_str(e),
+            ConfiguredAttr::Dep(e) => s.collect_str(e),
+            ConfiguredAttr::SourceLabel(e) => s.collect_str(e),
+            ConfiguredAttr::Label(e) => s.collect_str(e),
+            ConfiguredAttr::Arg(e) => s.collect_str(e),
+            ConfiguredAttr::Query(e) => s.serialize_str(e.query()),
+            ConfiguredAttr::SourceFile(e) => s.collect_str(&source_file_display(ctx, e)),
+        }
     }
 }
 
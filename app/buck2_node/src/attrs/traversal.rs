@@ -29,6 +29,10 @@ pub trait CoercedAttrTraversal<'a> {
         tr: &Arc<TransitionId>,
     ) -> anyhow::Result<()>;
     fn configuration_dep(&mut self, dep: &'a TargetLabel) -> anyhow::Result<()>;
+    /// A condition of a `select()` matched against the execution platform configuration.
+    fn exec_configuration_dep(&mut self, dep: &'a TargetLabel) -> anyhow::Result<()> {
+        self.configuration_dep(dep)
+    }
     fn platform_dep(&mut self, dep: &'a TargetLabel) -> anyhow::Result<()>;
     fn input(&mut self, input: BuckPathRef) -> anyhow::Result<()>;
     fn label(&mut self, _label: &'a ProvidersLabel) -> anyhow::Result<()> {
@@ -15,6 +15,7 @@ use buck2_core::configuration::data::ConfigurationData;
 use buck2_core::configuration::pair::ConfigurationNoExec;
 use buck2_core::target::label::ConfiguredTargetLabel;
 use buck2_core::target::label::TargetLabel;
+use buck2_query::query::compatibility::CompatibilityAttribute;
 use buck2_query::query::compatibility::IncompatiblePlatformReason;
 use buck2_query::query::compatibility::IncompatiblePlatformReasonCause;
 use dupe::Dupe;
@@ -119,7 +120,11 @@ impl ExecutionPlatformIncompatibleReason {
         match self {
             Self::ConstraintNotSatisfied(unsatisfied_config) => IncompatiblePlatformReason {
                 target,
-                cause: IncompatiblePlatformReasonCause::UnsatisfiedConfig(unsatisfied_config),
+                cause: IncompatiblePlatformReasonCause::UnsatisfiedConfig {
+                    attr: CompatibilityAttribute::ExecCompatibleWith,
+                    select_key: None,
+                    unsatisfied: vec![unsatisfied_config],
+                },
             },
             Self::ExecutionDependencyIncompatible(previous) => IncompatiblePlatformReason {
                 target,
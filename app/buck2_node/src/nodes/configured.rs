@@ -45,6 +45,7 @@ use crate::attrs::attr_type::query::ResolvedQueryLiterals;
 use crate::attrs::attr_type::string::StringLiteral;
 use crate::attrs::attr_type::AttrType;
 use crate::attrs::coerced_attr::CoercedAttr;
+use crate::attrs::coerced_attr::EXEC_SELECT_KEY_PREFIX;
 use crate::attrs::coerced_attr_full::CoercedAttrFull;
 use crate::attrs::configuration_context::AttrConfigurationContextImpl;
 use crate::attrs::configured_attr::ConfiguredAttr;
@@ -57,6 +58,7 @@ use crate::configuration::execution::ExecutionPlatformResolution;
 use crate::configuration::resolved::ResolvedConfiguration;
 use crate::nodes::attributes::DEPS;
 use crate::nodes::attributes::EXECUTION_PLATFORM;
+use crate::nodes::attributes::EXEC_SELECT_MATCHES;
 use crate::nodes::attributes::ONCALL;
 use crate::nodes::attributes::PACKAGE;
 use crate::nodes::attributes::TARGET_CONFIGURATION;
@@ -171,6 +173,9 @@ struct ConfiguredTargetNodeData {
     resolved_configuration: ResolvedConfiguration,
     resolved_transition_configurations: OrderedMap<Arc<TransitionId>, Arc<TransitionApplied>>,
     execution_platform_resolution: ExecutionPlatformResolution,
+    /// The execution platform configuration, resolved for the `exec:` keys of `select()`s. `None`
+    /// if there are none.
+    exec_resolved_configuration: Option<ResolvedConfiguration>,
     // Deps includes regular deps and transitioned deps,
     // but excludes exec deps or configuration deps.
     // TODO(cjhopman): Should this be a diff against the node's deps?
@@ -214,6 +219,7 @@ impl ConfiguredTargetNode {
             ),
             OrderedMap::new(),
             execution_platform_resolution,
+            None,
             Vec::new(),
             Vec::new(),
             OrderedMap::new(),
@@ -226,6 +232,7 @@ impl ConfiguredTargetNode {
         resolved_configuration: ResolvedConfiguration,
         resolved_tr_configurations: OrderedMap<Arc<TransitionId>, Arc<TransitionApplied>>,
         execution_platform_resolution: ExecutionPlatformResolution,
+        exec_resolved_configuration: Option<ResolvedConfiguration>,
         deps: Vec<ConfiguredTargetNode>,
         exec_deps: Vec<ConfiguredTargetNode>,
         platform_cfgs: OrderedMap<TargetLabel, ConfigurationData>,
@@ -236,6 +243,7 @@ impl ConfiguredTargetNode {
             resolved_configuration,
             resolved_transition_configurations: resolved_tr_configurations,
             execution_platform_resolution,
+            exec_resolved_configuration,
             deps: ConfiguredTargetNodeDeps(deps.into_boxed_slice()),
             exec_deps: ConfiguredTargetNodeDeps(exec_deps.into_boxed_slice()),
             platform_cfgs,
@@ -291,6 +299,7 @@ impl ConfiguredTargetNode {
                 execution_platform_resolution: transitioned_node
                     .execution_platform_resolution()
                     .dupe(),
+                exec_resolved_configuration: None,
                 deps: ConfiguredTargetNodeDeps(Box::new([transitioned_node])),
                 exec_deps: ConfiguredTargetNodeDeps(Box::new([])),
                 platform_cfgs: OrderedMap::new(),
@@ -411,6 +420,49 @@ impl ConfiguredTargetNode {
         self.0.exec_deps.iter()
     }
 
+    /// Returns the configuration deps of this node: the conditions of its `select()`s, its
+    /// compatibility constraints and the platforms of `configured_alias()`.
+    pub fn configuration_deps(&self) -> impl Iterator<Item = &TargetLabel> {
+        match &self.0.target_node {
+            TargetNodeOrForward::TargetNode(target_node) => Either::Left(
+                target_node
+                    .get_configuration_deps()
+                    .chain(target_node.platform_deps()),
+            ),
+            TargetNodeOrForward::Forward(..) => Either::Right(iter::empty()),
+        }
+    }
+
+    /// Returns the keys of the `select()`s of this node which are selected in its configuration.
+    /// Keys which match but are refined by a more specific matching key are not selected.
+    pub fn selected_configuration_settings(&self) -> Vec<&TargetLabel> {
+        let mut keys = Vec::new();
+        if let TargetNodeOrForward::TargetNode(target_node) = &self.0.target_node {
+            let ctx = self.attr_configuration_context();
+            for attr in target_node.attrs(AttrInspectOptions::All) {
+                attr.value
+                    .selected_keys(&ctx, &mut keys)
+                    .expect("checked attr configuration in constructor");
+            }
+        }
+        keys
+    }
+
+    /// Returns the `exec:` conditions of the `select()`s of this node which match its execution
+    /// platform.
+    pub fn matching_exec_configuration_settings(&self) -> impl Iterator<Item = &TargetLabel> {
+        match (&self.0.target_node, &self.0.exec_resolved_configuration) {
+            (TargetNodeOrForward::TargetNode(target_node), Some(exec_resolved_configuration)) => {
+                Either::Left(
+                    target_node
+                        .get_exec_configuration_deps()
+                        .filter(|label| exec_resolved_configuration.matches(label).is_some()),
+                )
+            }
+            _ => Either::Right(iter::empty()),
+        }
+    }
+
     /// Return the `tests` declared for this target.
     pub fn tests(&self) -> impl Iterator<Item = ConfiguredProvidersLabel> {
         #[derive(Default)]
@@ -441,6 +493,11 @@ impl ConfiguredTargetNode {
         &self.0.label
     }
 
+    /// Whether both nodes are the same allocation, rather than merely equal.
+    pub fn ptr_eq(&self, other: &ConfiguredTargetNode) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
     pub fn rule_type(&self) -> &RuleType {
         self.0.target_node.rule_type()
     }
@@ -472,7 +529,7 @@ impl ConfiguredTargetNode {
         let package_attr = ConfiguredAttr::String(StringLiteral(ArcStr::from(
             self.buildfile_path().to_string(),
         )));
-        vec![
+        let mut attrs = vec![
             (TYPE, typ_attr),
             (DEPS, deps_attr),
             (PACKAGE, package_attr),
@@ -496,8 +553,23 @@ impl ConfiguredTargetNode {
                         .map_or_else(|_| ArcStr::from("<NONE>"), |v| ArcStr::from(v.id())),
                 )),
             ),
-        ]
-        .into_iter()
+        ];
+        if self.0.exec_resolved_configuration.is_some() {
+            attrs.push((
+                EXEC_SELECT_MATCHES,
+                ConfiguredAttr::List(
+                    self.matching_exec_configuration_settings()
+                        .map(|label| {
+                            ConfiguredAttr::String(StringLiteral(ArcStr::from(format!(
+                                "{}{}",
+                                EXEC_SELECT_KEY_PREFIX, label
+                            ))))
+                        })
+                        .collect(),
+                ),
+            ));
+        }
+        attrs.into_iter()
     }
 
     pub fn oncall(&self) -> Option<&str> {
@@ -511,16 +583,34 @@ impl ConfiguredTargetNode {
             &self.0.resolved_transition_configurations,
             &self.0.platform_cfgs,
         )
+        .with_exec_resolved_cfg(self.0.exec_resolved_configuration.as_ref())
     }
 
     pub fn attrs<'a>(
         &'a self,
         opts: AttrInspectOptions,
     ) -> impl Iterator<Item = ConfiguredAttrFull<'a>> + 'a {
-        self.0.target_node.attrs(opts).map(move |a| {
-            a.configure(&self.attr_configuration_context())
-                .expect("checked attr configuration in constructor")
-        })
+        self.attrs_matching(opts, |_| true)
+    }
+
+    /// Configure the attributes whose name matches `filter`, in a single pass sharing the
+    /// configuration context. Attributes which do not match are not configured, so this is
+    /// cheaper than `attrs` when only a few attributes are needed, and cheaper than `get` for
+    /// each of them.
+    pub fn attrs_matching<'a>(
+        &'a self,
+        opts: AttrInspectOptions,
+        filter: impl Fn(&str) -> bool + 'a,
+    ) -> impl Iterator<Item = ConfiguredAttrFull<'a>> + 'a {
+        let ctx = self.attr_configuration_context();
+        self.0
+            .target_node
+            .attrs(opts)
+            .filter(move |a| filter(a.name))
+            .map(move |a| {
+                a.configure(&ctx)
+                    .expect("checked attr configuration in constructor")
+            })
     }
 
     pub fn get<'a>(
@@ -601,3 +691,120 @@ impl Hash for ConfiguredTargetNodeDeps {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+
+    use buck2_core::bzl::ImportPath;
+    use buck2_core::collections::ordered_map::OrderedMap;
+    use buck2_core::configuration::config_setting::ConfigSettingData;
+    use buck2_core::configuration::constraints::ConstraintKey;
+    use buck2_core::configuration::constraints::ConstraintValue;
+    use buck2_core::configuration::data::ConfigurationData;
+    use buck2_core::configuration::pair::ConfigurationNoExec;
+    use buck2_core::target::label::TargetLabel;
+    use buck2_util::arc_str::ArcSlice;
+    use dupe::Dupe;
+
+    use crate::attrs::attr::Attribute;
+    use crate::attrs::attr_type::string::StringLiteral;
+    use crate::attrs::attr_type::AttrType;
+    use crate::attrs::coerced_attr::CoercedAttr;
+    use crate::attrs::coerced_attr::CoercedSelector;
+    use crate::configuration::execution::ExecutionPlatformResolution;
+    use crate::configuration::resolved::ConfigurationNode;
+    use crate::configuration::resolved::ConfigurationSettingKey;
+    use crate::configuration::resolved::ResolvedConfiguration;
+    use crate::nodes::configured::ConfiguredTargetNode;
+    use crate::nodes::unconfigured::testing::TargetNodeExt;
+    use crate::nodes::unconfigured::TargetNode;
+    use crate::rule_type::RuleType;
+    use crate::rule_type::StarlarkRuleType;
+
+    #[test]
+    fn test_configuration_deps_and_selected_settings() -> anyhow::Result<()> {
+        let cfg = ConfigurationData::testing_new();
+        let label = TargetLabel::testing_parse("cell//pkg:foo");
+        let linux = TargetLabel::testing_parse("config//:linux");
+        let linux_arm64 = TargetLabel::testing_parse("config//:linux-arm64");
+        let macos = TargetLabel::testing_parse("config//:macos");
+
+        let string = |s: &str| CoercedAttr::String(StringLiteral(s.into()));
+        // Both `linux` and `linux-arm64` match, but only the more specific `linux-arm64` is
+        // selected.
+        let select = CoercedSelector::new(
+            ArcSlice::new([
+                (linux.dupe(), string("linux.c")),
+                (linux_arm64.dupe(), string("linux-arm64.c")),
+                (macos.dupe(), string("macos.c")),
+            ]),
+            Some(string("other.c")),
+        )?;
+        let target_node = TargetNode::testing_new(
+            label.dupe(),
+            RuleType::Starlark(Arc::new(StarlarkRuleType {
+                import_path: ImportPath::testing_new("cell//pkg:rules.bzl"),
+                name: "library".to_owned(),
+            })),
+            vec![(
+                "src",
+                Attribute::new(None, "", AttrType::string()),
+                CoercedAttr::Selector(Box::new(select)),
+            )],
+        );
+
+        let constraint = |key: &str, value: &str| {
+            (
+                ConstraintKey(TargetLabel::testing_parse(key)),
+                ConstraintValue(TargetLabel::testing_parse(value)),
+            )
+        };
+        let os_linux = constraint("config//:os", "config//:linux");
+        let cpu_arm64 = constraint("config//:cpu", "config//:arm64");
+        let os_macos = constraint("config//:os", "config//:macos");
+        let setting =
+            |label: &TargetLabel, constraints: &[(ConstraintKey, ConstraintValue)], matches| {
+                (
+                    ConfigurationSettingKey(label.dupe()),
+                    ConfigurationNode::new(
+                        cfg.dupe(),
+                        label.dupe(),
+                        ConfigSettingData {
+                            constraints: constraints.iter().cloned().collect(),
+                            buckconfigs: BTreeMap::new(),
+                        },
+                        matches,
+                    ),
+                )
+            };
+        let node = ConfiguredTargetNode::new(
+            label.configure(cfg.dupe()),
+            target_node,
+            ResolvedConfiguration::new(
+                ConfigurationNoExec::new(cfg.dupe()),
+                [
+                    setting(&linux, &[os_linux.clone()], true),
+                    setting(&linux_arm64, &[os_linux, cpu_arm64], true),
+                    setting(&macos, &[os_macos], false),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            OrderedMap::new(),
+            ExecutionPlatformResolution::new(None, Vec::new()),
+            None,
+            Vec::new(),
+            Vec::new(),
+            OrderedMap::new(),
+        );
+
+        assert_eq!(
+            vec![&linux, &linux_arm64, &macos],
+            node.configuration_deps().collect::<Vec<_>>()
+        );
+        assert_eq!(vec![&linux_arm64], node.selected_configuration_settings());
+        Ok(())
+    }
+}
@@ -135,6 +135,17 @@ impl QueryTarget for ConfiguredGraphNodeRef {
         Ok(())
     }
 
+    fn attrs_for_each_matching<E, F: FnMut(&str, &Self::Attr<'_>) -> Result<(), E>>(
+        &self,
+        filter: &dyn Fn(&str) -> bool,
+        mut func: F,
+    ) -> Result<(), E> {
+        for a in self.0.attrs_matching(AttrInspectOptions::All, filter) {
+            func(a.name, &a.value)?;
+        }
+        Ok(())
+    }
+
     fn map_attr<R, F: FnMut(Option<&Self::Attr<'_>>) -> R>(&self, key: &str, mut func: F) -> R {
         func(
             self.0
@@ -112,6 +112,14 @@ impl MissingTargets {
     }
 }
 
+/// A `glob` include pattern which can only match files of a subpackage (and so never matches
+/// anything).
+#[derive(Debug, Clone, PartialEq, Eq, Allocative)]
+pub struct GlobPackageBoundaryViolation {
+    pub pattern: String,
+    pub subpackage: PackageLabel,
+}
+
 /// An EvaluationResult contains the list of targets resulting from evaluating a build file.
 #[derive(Debug, Allocative)]
 pub struct EvaluationResult {
@@ -121,6 +129,7 @@ pub struct EvaluationResult {
     buildfile_path: Arc<BuildFilePath>,
     imports: Vec<ImportPath>,
     targets: TargetsMap,
+    glob_package_boundary_violations: Vec<GlobPackageBoundaryViolation>,
 }
 
 impl EvaluationResult {
@@ -133,9 +142,18 @@ impl EvaluationResult {
             buildfile_path,
             imports,
             targets,
+            glob_package_boundary_violations: Vec::new(),
         }
     }
 
+    pub fn with_glob_package_boundary_violations(
+        mut self,
+        violations: Vec<GlobPackageBoundaryViolation>,
+    ) -> Self {
+        self.glob_package_boundary_violations = violations;
+        self
+    }
+
     pub fn buildfile_path(&self) -> &Arc<BuildFilePath> {
         &self.buildfile_path
     }
@@ -152,6 +170,11 @@ impl EvaluationResult {
         &self.imports
     }
 
+    /// `glob` patterns of this build file which cross a package boundary.
+    pub fn glob_package_boundary_violations(&self) -> &[GlobPackageBoundaryViolation] {
+        &self.glob_package_boundary_violations
+    }
+
     pub fn get_target<'a>(&'a self, name: &TargetNameRef) -> Option<&'a TargetNode> {
         self.targets.get(name)
     }
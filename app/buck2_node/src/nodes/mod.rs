@@ -41,6 +41,10 @@ pub mod attributes {
     /// The resolved execution platform for this node.
     pub static EXECUTION_PLATFORM: &str = "buck.execution_platform";
 
+    /// The `exec:` select keys which match the execution platform of this node. Only set on
+    /// nodes with such selects.
+    pub static EXEC_SELECT_MATCHES: &str = "buck.exec_select_matches";
+
     /// The resolved target configuration for this node.
     pub static TARGET_CONFIGURATION: &str = "buck.target_configuration";
 
@@ -42,6 +42,7 @@ use crate::package::Package;
 use crate::rule::Rule;
 use crate::rule_type::RuleType;
 use crate::visibility::VisibilitySpecification;
+use crate::visibility::WithinViewSpecification;
 
 #[derive(Debug, thiserror::Error)]
 enum TargetNodeError {
@@ -239,6 +240,19 @@ impl TargetNode {
         Ok(self.visibility()?.is_visible_to(target))
     }
 
+    /// The `within_view` of the package of this target, from its `PACKAGE` files.
+    pub fn within_view(&self) -> &WithinViewSpecification {
+        &self.0.package.within_view
+    }
+
+    /// Whether this target may depend on `dep` according to the `within_view` of its package.
+    pub fn is_within_view(&self, dep: &TargetLabel) -> bool {
+        if self.label().pkg() == dep.pkg() {
+            return true;
+        }
+        self.within_view().matches(dep)
+    }
+
     pub fn attrs(&self, opts: AttrInspectOptions) -> impl Iterator<Item = CoercedAttrFull> {
         self.0.rule.attributes.attrs(&self.0.attributes, opts)
     }
@@ -283,6 +297,11 @@ impl TargetNode {
         self.deps_cache().configuration_deps.iter()
     }
 
+    /// The conditions of `select()`s matched against the execution platform configuration.
+    pub fn get_exec_configuration_deps(&self) -> impl Iterator<Item = &TargetLabel> {
+        self.deps_cache().exec_configuration_deps.iter()
+    }
+
     pub fn tests(&self) -> impl Iterator<Item = &ProvidersLabel> {
         #[derive(Default)]
         struct TestCollector<'a> {
@@ -436,10 +455,12 @@ pub mod testing {
     use crate::attrs::coerced_deps_collector::CoercedDepsCollector;
     use crate::attrs::fmt_context::AttrFmtContext;
     use crate::attrs::inspect_options::AttrInspectOptions;
+    use crate::attrs::internal::internal_attrs;
     use crate::attrs::spec::AttributeSpec;
     use crate::attrs::values::AttrValues;
     use crate::nodes::targets_map::TargetsMap;
     use crate::rule_type::RuleType;
+    use crate::visibility::WithinViewSpecification;
 
     pub trait TargetNodeExt {
         fn testing_new(
@@ -447,6 +468,22 @@ pub mod testing {
             rule_type: RuleType,
             attrs: Vec<(&str, Attribute, CoercedAttr)>,
         ) -> Self;
+
+        /// Like `testing_new`, with a call stack, as if it was recorded with `--stack`.
+        fn testing_new_with_call_stack(
+            label: TargetLabel,
+            rule_type: RuleType,
+            attrs: Vec<(&str, Attribute, CoercedAttr)>,
+            call_stack: &str,
+        ) -> Self;
+
+        /// Like `testing_new`, in a package with the given `within_view`.
+        fn testing_new_with_within_view(
+            label: TargetLabel,
+            rule_type: RuleType,
+            attrs: Vec<(&str, Attribute, CoercedAttr)>,
+            within_view: WithinViewSpecification,
+        ) -> Self;
     }
 
     impl TargetNodeExt for TargetNode {
@@ -455,52 +492,96 @@ pub mod testing {
             rule_type: RuleType,
             attrs: Vec<(&str, Attribute, CoercedAttr)>,
         ) -> TargetNode {
-            let attr_spec = AttributeSpec::testing_new(
-                attrs
-                    .iter()
-                    .map(|(name, attr, _)| ((*name).to_owned(), attr.clone()))
-                    .collect(),
-            );
-
-            let mut attributes = AttrValues::with_capacity(attrs.len() + 1);
-
-            attributes.push_sorted(
-                AttributeSpec::name_attr_id(),
-                CoercedAttr::String(StringLiteral(label.name().as_str().into())),
-            );
-
-            let mut deps_cache = CoercedDepsCollector::new();
-
-            for (name, _attr, val) in attrs.into_iter() {
-                let idx = attr_spec.attribute_id_by_name(name).unwrap();
-                let attr = attr_spec.attribute(name).unwrap();
-                val.traverse(attr.coercer(), label.pkg(), &mut deps_cache)
-                    .unwrap();
-                attributes.push_sorted(idx, val);
-            }
-
-            let buildfile_path = Arc::new(BuildFilePath::new(
-                label.pkg().dupe(),
-                FileNameBuf::unchecked_new("BUCK"),
-            ));
-            TargetNode::new(
-                Arc::new(Rule {
-                    attributes: attr_spec,
-                    rule_type,
-                    rule_kind: RuleKind::Normal,
-                    cfg: None,
-                }),
-                Arc::new(Package {
-                    buildfile_path,
-                    oncall: None,
-                    default_visibility_to_public: false,
-                }),
+            testing_node(
                 label,
-                attributes,
-                CoercedDeps::from(deps_cache),
+                rule_type,
+                attrs,
                 None,
+                WithinViewSpecification::Public,
+            )
+        }
+
+        fn testing_new_with_call_stack(
+            label: TargetLabel,
+            rule_type: RuleType,
+            attrs: Vec<(&str, Attribute, CoercedAttr)>,
+            call_stack: &str,
+        ) -> TargetNode {
+            testing_node(
+                label,
+                rule_type,
+                attrs,
+                Some(StarlarkCallStack::new(call_stack.to_owned())),
+                WithinViewSpecification::Public,
             )
         }
+
+        fn testing_new_with_within_view(
+            label: TargetLabel,
+            rule_type: RuleType,
+            attrs: Vec<(&str, Attribute, CoercedAttr)>,
+            within_view: WithinViewSpecification,
+        ) -> TargetNode {
+            testing_node(label, rule_type, attrs, None, within_view)
+        }
+    }
+
+    fn testing_node(
+        label: TargetLabel,
+        rule_type: RuleType,
+        attrs: Vec<(&str, Attribute, CoercedAttr)>,
+        call_stack: Option<StarlarkCallStack>,
+        within_view: WithinViewSpecification,
+    ) -> TargetNode {
+        // Internal attributes (e.g. `visibility`) can be given values (before the other ones, as
+        // they come first in the spec), but are always defined by the spec.
+        let attr_spec = AttributeSpec::testing_new(
+            attrs
+                .iter()
+                .filter(|(name, _, _)| !internal_attrs().contains_key(*name))
+                .map(|(name, attr, _)| ((*name).to_owned(), attr.clone()))
+                .collect(),
+        );
+
+        let mut attributes = AttrValues::with_capacity(attrs.len() + 1);
+
+        attributes.push_sorted(
+            AttributeSpec::name_attr_id(),
+            CoercedAttr::String(StringLiteral(label.name().as_str().into())),
+        );
+
+        let mut deps_cache = CoercedDepsCollector::new();
+
+        for (name, _attr, val) in attrs.into_iter() {
+            let idx = attr_spec.attribute_id_by_name(name).unwrap();
+            let attr = attr_spec.attribute(name).unwrap();
+            val.traverse(attr.coercer(), label.pkg(), &mut deps_cache)
+                .unwrap();
+            attributes.push_sorted(idx, val);
+        }
+
+        let buildfile_path = Arc::new(BuildFilePath::new(
+            label.pkg().dupe(),
+            FileNameBuf::unchecked_new("BUCK"),
+        ));
+        TargetNode::new(
+            Arc::new(Rule {
+                attributes: attr_spec,
+                rule_type,
+                rule_kind: RuleKind::Normal,
+                cfg: None,
+            }),
+            Arc::new(Package {
+                buildfile_path,
+                oncall: None,
+                default_visibility_to_public: false,
+                within_view,
+            }),
+            label,
+            attributes,
+            CoercedDeps::from(deps_cache),
+            call_stack,
+        )
     }
 
     /// Take a TargetsMap and convert it to a nice json representation. Adds in a __type__ attr
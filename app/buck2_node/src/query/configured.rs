@@ -13,6 +13,7 @@ use buck2_core::build_file_path::BuildFilePath;
 use buck2_core::cells::cell_path::CellPath;
 use buck2_core::target::label::ConfiguredTargetLabel;
 use buck2_query::query::environment::LabeledNode;
+use buck2_query::query::environment::QueryDepKind;
 use buck2_query::query::environment::QueryTarget;
 use dupe::Dupe;
 use serde::Serializer;
@@ -24,6 +25,7 @@ use crate::attrs::fmt_context::AttrFmtContext;
 use crate::attrs::inspect_options::AttrInspectOptions;
 use crate::attrs::serialize::AttrSerializeWithContext;
 use crate::nodes::configured::ConfiguredTargetNode;
+use crate::nodes::unconfigured::RuleKind;
 
 impl LabeledNode for ConfiguredTargetNode {
     type NodeRef = ConfiguredTargetLabel;
@@ -57,6 +59,22 @@ impl QueryTarget for ConfiguredTargetNode {
         Box::new(ConfiguredTargetNode::target_deps(self).map(|v| v.label()))
     }
 
+    fn deps_with_kinds<'a>(
+        &'a self,
+    ) -> Box<dyn Iterator<Item = (&'a Self::NodeRef, QueryDepKind)> + Send + 'a> {
+        let target_deps = ConfiguredTargetNode::target_deps(self).map(|v| {
+            let kind = if v.rule_kind() == RuleKind::Toolchain {
+                QueryDepKind::Toolchain
+            } else {
+                QueryDepKind::Target
+            };
+            (v.label(), kind)
+        });
+        let exec_deps =
+            ConfiguredTargetNode::exec_deps(self).map(|v| (v.label(), QueryDepKind::Exec));
+        Box::new(target_deps.chain(exec_deps))
+    }
+
     fn tests<'a>(&'a self) -> Option<Box<dyn Iterator<Item = Self::NodeRef> + Send + 'a>> {
         Some(Box::new(self.tests().map(|t| t.target().dupe())))
     }
@@ -88,6 +106,17 @@ impl QueryTarget for ConfiguredTargetNode {
         Ok(())
     }
 
+    fn attrs_for_each_matching<E, F: FnMut(&str, &Self::Attr<'_>) -> Result<(), E>>(
+        &self,
+        filter: &dyn Fn(&str) -> bool,
+        mut func: F,
+    ) -> Result<(), E> {
+        for a in self.attrs_matching(AttrInspectOptions::All, filter) {
+            func(a.name, &a.value)?;
+        }
+        Ok(())
+    }
+
     fn map_attr<R, F: FnMut(Option<&Self::Attr<'_>>) -> R>(&self, key: &str, mut func: F) -> R {
         func(
             self.get(key, AttrInspectOptions::All)
@@ -67,6 +67,13 @@ impl QueryTarget for TargetNode {
         attr.any_matches(filter)
     }
 
+    fn attr_all_branches_match(
+        attr: &Self::Attr<'_>,
+        filter: &dyn Fn(&str) -> anyhow::Result<bool>,
+    ) -> anyhow::Result<bool> {
+        attr.all_branches_match(filter)
+    }
+
     fn special_attrs_for_each<E, F: FnMut(&str, &Self::Attr<'_>) -> Result<(), E>>(
         &self,
         mut func: F,
@@ -27,6 +27,10 @@ pub enum VisibilityError {
         "`{0}` is not visible to `{1}` (run `buck2 uquery --output-attribute visibility {0}` to check the visibility)"
     )]
     NotVisibleTo(TargetLabel, TargetLabel),
+    #[error(
+        "`{1}` depends on `{0}`, which is not in the `within_view` of its package (set in `PACKAGE` files)"
+    )]
+    NotWithinView(TargetLabel, TargetLabel),
 }
 
 #[derive(Debug, Eq, PartialEq, Hash, Clone, Allocative, derive_more::Display)]
@@ -145,6 +149,15 @@ impl Display for VisibilitySpecification {
 }
 
 impl WithinViewSpecification {
+    pub fn matches(&self, target: &TargetLabel) -> bool {
+        match self {
+            WithinViewSpecification::Public => true,
+            WithinViewSpecification::VisibleTo(patterns) => {
+                patterns.iter().any(|pattern| pattern.0.matches(target))
+            }
+        }
+    }
+
     pub fn extend_with(&self, other: &WithinViewSpecification) -> WithinViewSpecification {
         match (self, other) {
             (WithinViewSpecification::Public, _) | (_, WithinViewSpecification::Public) => {
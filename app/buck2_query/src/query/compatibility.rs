@@ -18,6 +18,7 @@ use allocative::Allocative;
 use buck2_core::target::label::ConfiguredTargetLabel;
 use buck2_core::target::label::TargetLabel;
 use dupe::Dupe;
+use itertools::Itertools;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -51,6 +52,13 @@ impl<T> MaybeCompatible<T> {
         matches!(self, Self::Compatible(..))
     }
 
+    pub fn as_compatible_mut(&mut self) -> Option<&mut T> {
+        match self {
+            MaybeCompatible::Incompatible(_) => None,
+            MaybeCompatible::Compatible(t) => Some(t),
+        }
+    }
+
     pub fn map<U>(self, func: impl FnOnce(T) -> U) -> MaybeCompatible<U> {
         match self {
             MaybeCompatible::Incompatible(e) => MaybeCompatible::Incompatible(e),
@@ -69,10 +77,37 @@ impl<T> MaybeCompatible<T> {
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Hash, Clone, Dupe, Allocative)]
+/// The attribute of a target listing the constraints it requires.
+#[derive(
+    Debug,
+    Eq,
+    PartialEq,
+    Hash,
+    Clone,
+    Copy,
+    Dupe,
+    Allocative,
+    derive_more::Display
+)]
+pub enum CompatibilityAttribute {
+    #[display(fmt = "target_compatible_with")]
+    TargetCompatibleWith,
+    #[display(fmt = "compatible_with")]
+    CompatibleWith,
+    #[display(fmt = "exec_compatible_with")]
+    ExecCompatibleWith,
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Allocative)]
 pub enum IncompatiblePlatformReasonCause {
-    /// Target is incompatible because of unsatisfied config setting.
-    UnsatisfiedConfig(TargetLabel),
+    /// Target is incompatible because of unsatisfied config settings listed in `attr`.
+    UnsatisfiedConfig {
+        attr: CompatibilityAttribute,
+        /// The key of the `select()` the attribute was resolved with (`DEFAULT` for its
+        /// default), if the attribute is a `select()`.
+        select_key: Option<String>,
+        unsatisfied: Vec<TargetLabel>,
+    },
     /// Target is incompatible because dependency is incompatible.
     Dependency(Arc<IncompatiblePlatformReason>),
 }
@@ -88,6 +123,27 @@ impl IncompatiblePlatformReason {
         CompatibilityErrors::TargetIncompatible(self.clone()).into()
     }
 
+    /// The constraints which are not satisfied, the attribute listing them and the `select()`
+    /// key it was resolved with: those of this target, or of the incompatible dependency it
+    /// inherited its incompatibility from. The dependencies leading to that one are appended to
+    /// `chain`.
+    pub fn unsatisfied_constraints<'a>(
+        &'a self,
+        chain: &mut Vec<&'a ConfiguredTargetLabel>,
+    ) -> (CompatibilityAttribute, Option<&'a str>, &'a [TargetLabel]) {
+        match &self.cause {
+            IncompatiblePlatformReasonCause::UnsatisfiedConfig {
+                attr,
+                select_key,
+                unsatisfied,
+            } => (*attr, select_key.as_deref(), unsatisfied),
+            IncompatiblePlatformReasonCause::Dependency(previous) => {
+                chain.push(&previous.target);
+                previous.unsatisfied_constraints(chain)
+            }
+        }
+    }
+
     pub fn skipping_message(&self, target: &ConfiguredTargetLabel) -> String {
         format!("Skipping target incompatible node `{}`", target)
     }
@@ -125,12 +181,12 @@ impl IncompatiblePlatformReason {
 impl Display for IncompatiblePlatformReason {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match &self.cause {
-            IncompatiblePlatformReasonCause::UnsatisfiedConfig(unsatisfied_config) => write!(
+            IncompatiblePlatformReasonCause::UnsatisfiedConfig { unsatisfied, .. } => write!(
                 f,
                 "{} is incompatible with {} ({} unsatisfied), check the target's compatibility attributes",
                 self.target.unconfigured(),
                 self.target.cfg(),
-                unsatisfied_config,
+                unsatisfied.iter().join(", "),
             ),
             IncompatiblePlatformReasonCause::Dependency(previous) => {
                 if f.alternate() {
@@ -145,10 +201,60 @@ impl Display for IncompatiblePlatformReason {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use buck2_core::configuration::data::ConfigurationData;
     use buck2_core::target::label::TargetLabel;
+    use dupe::Dupe;
 
+    use crate::query::compatibility::CompatibilityAttribute;
     use crate::query::compatibility::IncompatiblePlatformReason;
+    use crate::query::compatibility::IncompatiblePlatformReasonCause;
+
+    #[test]
+    fn test_unsatisfied_constraints() {
+        let configured = |label: &str| {
+            TargetLabel::testing_parse(label).configure(ConfigurationData::testing_new())
+        };
+        let constraints = vec![
+            TargetLabel::testing_parse("config//os:linux"),
+            TargetLabel::testing_parse("config//cpu:arm64"),
+        ];
+        let dep = Arc::new(IncompatiblePlatformReason {
+            target: configured("root//foo:dep"),
+            cause: IncompatiblePlatformReasonCause::UnsatisfiedConfig {
+                attr: CompatibilityAttribute::TargetCompatibleWith,
+                select_key: Some("config//os:macos".to_owned()),
+                unsatisfied: constraints.clone(),
+            },
+        });
+        let middle = Arc::new(IncompatiblePlatformReason {
+            target: configured("root//foo:middle"),
+            cause: IncompatiblePlatformReasonCause::Dependency(dep.dupe()),
+        });
+        let target = IncompatiblePlatformReason {
+            target: configured("root//foo:bar"),
+            cause: IncompatiblePlatformReasonCause::Dependency(middle.dupe()),
+        };
+
+        let mut chain = Vec::new();
+        assert_eq!(
+            target.unsatisfied_constraints(&mut chain),
+            (
+                CompatibilityAttribute::TargetCompatibleWith,
+                Some("config//os:macos"),
+                constraints.as_slice()
+            )
+        );
+        assert_eq!(chain, vec![&middle.target, &dep.target]);
+
+        let mut chain = Vec::new();
+        assert_eq!(
+            dep.unsatisfied_constraints(&mut chain).2,
+            constraints.as_slice()
+        );
+        assert!(chain.is_empty());
+    }
 
     #[test]
     fn test_skipping_message_for_multiple() {
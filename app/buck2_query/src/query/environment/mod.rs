@@ -80,6 +80,46 @@ impl QueryTargets {
         target.attrs_for_each(&mut func)?;
         Ok(())
     }
+
+    /// Like `for_all_attrs`, but only processes the attrs whose name matches `filter`. Attrs which
+    /// don't match may not be resolved at all, which is much cheaper when only a few attrs of many
+    /// nodes are needed (e.g. `cquery --output-attribute`).
+    pub fn for_all_attrs_matching<
+        E,
+        T: QueryTarget,
+        F: FnMut(&str, &T::Attr<'_>) -> Result<(), E>,
+    >(
+        target: &T,
+        filter: &dyn Fn(&str) -> bool,
+        mut func: F,
+    ) -> Result<(), E> {
+        target.special_attrs_for_each(|name, attr| {
+            if filter(name) {
+                func(name, attr)?;
+            }
+            Ok(())
+        })?;
+        target.attrs_for_each_matching(filter, &mut func)?;
+        Ok(())
+    }
+}
+
+/// The kind of a dependency edge, for outputs which annotate edges (`cquery --output-dep-kinds`).
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq)]
+pub enum QueryDepKind {
+    Target,
+    Exec,
+    Toolchain,
+}
+
+impl QueryDepKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            QueryDepKind::Target => "target",
+            QueryDepKind::Exec => "exec",
+            QueryDepKind::Toolchain => "toolchain",
+        }
+    }
 }
 
 pub trait QueryTarget: LabeledNode + Dupe + Send + Sync + 'static {
@@ -102,6 +142,17 @@ pub trait QueryTarget: LabeledNode + Dupe + Send + Sync + 'static {
     // TODO(cjhopman): Use existential traits to remove the Box<> once they are stabilized.
     fn target_deps<'a>(&'a self) -> Box<dyn Iterator<Item = &'a Self::NodeRef> + Send + 'a>;
 
+    /// Same deps as `deps`, with the kind of edge leading to each of them.
+    fn deps_with_kinds<'a>(
+        &'a self,
+    ) -> Box<dyn Iterator<Item = (&'a Self::NodeRef, QueryDepKind)> + Send + 'a> {
+        Box::new(
+            self.target_deps()
+                .map(|dep| (dep, QueryDepKind::Target))
+                .chain(self.exec_deps().map(|dep| (dep, QueryDepKind::Exec))),
+        )
+    }
+
     fn tests<'a>(&'a self) -> Option<Box<dyn Iterator<Item = Self::NodeRef> + Send + 'a>> {
         None
     }
@@ -119,6 +170,15 @@ pub trait QueryTarget: LabeledNode + Dupe + Send + Sync + 'static {
         filter: &dyn Fn(&str) -> anyhow::Result<bool>,
     ) -> anyhow::Result<bool>;
 
+    /// Like `attr_any_matches`, but a `select()` only matches if all of its branches match.
+    /// Attributes without selects (e.g. configured ones) behave as in `attr_any_matches`.
+    fn attr_all_branches_match(
+        attr: &Self::Attr<'_>,
+        filter: &dyn Fn(&str) -> anyhow::Result<bool>,
+    ) -> anyhow::Result<bool> {
+        Self::attr_any_matches(attr, filter)
+    }
+
     fn special_attrs_for_each<E, F: FnMut(&str, &Self::Attr<'_>) -> Result<(), E>>(
         &self,
         func: F,
@@ -129,6 +189,22 @@ pub trait QueryTarget: LabeledNode + Dupe + Send + Sync + 'static {
         func: F,
     ) -> Result<(), E>;
 
+    /// Like `attrs_for_each`, but only for the attrs whose name matches `filter`. Targets whose
+    /// attrs are resolved lazily should override this to resolve the matching attrs in one pass,
+    /// without resolving the others.
+    fn attrs_for_each_matching<E, F: FnMut(&str, &Self::Attr<'_>) -> Result<(), E>>(
+        &self,
+        filter: &dyn Fn(&str) -> bool,
+        mut func: F,
+    ) -> Result<(), E> {
+        self.attrs_for_each(|name, attr| {
+            if filter(name) {
+                func(name, attr)?;
+            }
+            Ok(())
+        })
+    }
+
     fn map_attr<R, F: FnMut(Option<&Self::Attr<'_>>) -> R>(&self, key: &str, func: F) -> R;
 
     fn call_stack(&self) -> Option<String>;
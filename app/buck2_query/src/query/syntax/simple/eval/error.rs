@@ -54,6 +54,8 @@ pub enum QueryError {
         "Operation + requires either two set types, or one set and one string, got `{0}` and `{1}`"
     )]
     UnionIncompatibleTypes(&'static str, &'static str),
+    #[error("Invalid select branch mode `{0}`, expected `any` or `all`")]
+    InvalidSelectBranches(String),
     /// Used to propagate up an inner error. The inner span will mark where the inner error was (which itself may be the
     /// propagation of another error). This error will end up in a Spanned that indicates where this error (the propagation) occurs.
     /// Since QueryError has an impl for `From<Spanned<QueryError>>`, just propagating inner eval errors via `?` will hit this case (and
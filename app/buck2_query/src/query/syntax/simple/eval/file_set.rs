@@ -70,6 +70,26 @@ impl FileSet {
         Self { files }
     }
 
+    pub fn intersect(&self, right: &FileSet) -> FileSet {
+        let files = self
+            .files
+            .iter()
+            .filter(|file| right.files.contains(*file))
+            .cloned()
+            .collect();
+        Self { files }
+    }
+
+    pub fn difference(&self, right: &FileSet) -> FileSet {
+        let files = self
+            .files
+            .iter()
+            .filter(|file| !right.files.contains(*file))
+            .cloned()
+            .collect();
+        Self { files }
+    }
+
     pub fn owner<T: QueryTarget>(
         &self,
         _env: &dyn QueryEnvironment<Target = T>,
@@ -9,10 +9,12 @@
 
 use std::fmt;
 use std::fmt::Display;
+use std::str::FromStr;
 
 use allocative::Allocative;
 use buck2_query::query::environment::LabeledNode;
 use display_container::fmt_container;
+use dupe::Dupe;
 use dupe::IterDupedExt;
 use fancy_regex::Regex;
 use indexmap::IndexSet;
@@ -168,6 +170,27 @@ impl<T: QueryTarget> FromIterator<T> for TargetSet<T> {
     }
 }
 
+/// How attribute filters treat the branches of a `select()`.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq)]
+pub enum SelectBranches {
+    /// The attribute matches if any branch matches.
+    Any,
+    /// The attribute matches only if every branch matches.
+    All,
+}
+
+impl FromStr for SelectBranches {
+    type Err = QueryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "any" => Ok(SelectBranches::Any),
+            "all" => Ok(SelectBranches::All),
+            _ => Err(QueryError::InvalidSelectBranches(s.to_owned())),
+        }
+    }
+}
+
 /// This contains additional TargetSet functions implemented via the core
 /// functions on TargetSet itself.
 pub trait TargetSetExt {
@@ -204,10 +227,32 @@ pub trait TargetSetExt {
         })
     }
 
-    fn attrregexfilter(&self, attribute: &str, value: &str) -> anyhow::Result<TargetSet<Self::T>> {
+    /// Like `attrfilter`, but a `select()` only matches if all of its branches match.
+    fn attrfilter_all_branches(
+        &self,
+        attribute: &str,
+        filter: &dyn Fn(&str) -> anyhow::Result<bool>,
+    ) -> anyhow::Result<TargetSet<Self::T>> {
+        self.filter(move |node| {
+            node.map_attr(attribute, |val| match val {
+                None => Ok(false),
+                Some(v) => Self::T::attr_all_branches_match(v, &filter),
+            })
+        })
+    }
+
+    fn attrregexfilter(
+        &self,
+        attribute: &str,
+        value: &str,
+        branches: SelectBranches,
+    ) -> anyhow::Result<TargetSet<Self::T>> {
         let regex = Regex::new(value)?;
         let filter = move |s: &'_ str| -> anyhow::Result<bool> { Ok(regex.is_match(s)?) };
-        self.attrfilter(attribute, &filter)
+        match branches {
+            SelectBranches::Any => self.attrfilter(attribute, &filter),
+            SelectBranches::All => self.attrfilter_all_branches(attribute, &filter),
+        }
     }
 
     fn filter_name(&self, regex: &str) -> anyhow::Result<TargetSet<Self::T>> {
@@ -20,6 +20,7 @@ use buck2_query::query::environment::LabeledNode;
 use buck2_query_parser::parse_expr;
 use derive_more::Display;
 use dupe::Dupe;
+use indexmap::indexset;
 use serde::Serialize;
 use serde::Serializer;
 
@@ -29,8 +30,10 @@ use crate::query::environment::QueryEnvironment;
 use crate::query::environment::QueryTarget;
 use crate::query::syntax::simple::eval::error::QueryError;
 use crate::query::syntax::simple::eval::evaluator::QueryEvaluator;
+use crate::query::syntax::simple::eval::file_set::FileNode;
 use crate::query::syntax::simple::eval::file_set::FileSet;
 use crate::query::syntax::simple::eval::set::TargetSet;
+use crate::query::syntax::simple::eval::values::QueryValue;
 use crate::query::syntax::simple::functions::DefaultQueryFunctionsModule;
 use crate::query::traversal::AsyncTraversalDelegate;
 
@@ -141,8 +144,10 @@ impl QueryEnvironment for Env {
         unimplemented!()
     }
 
-    async fn eval_file_literal(&self, _literal: &str) -> anyhow::Result<FileSet> {
-        unimplemented!()
+    async fn eval_file_literal(&self, literal: &str) -> anyhow::Result<FileSet> {
+        Ok(FileSet::new(indexset![FileNode(CellPath::testing_new(
+            &format!("root//{}", literal)
+        ))]))
     }
 
     async fn dfs_postorder(
@@ -187,3 +192,30 @@ pub async fn test_missing_arg() -> anyhow::Result<()> {
     }
     Ok(())
 }
+
+#[tokio::test]
+pub async fn test_file_set_operations() -> anyhow::Result<()> {
+    let files = |paths: &[&str]| {
+        QueryValue::FileSet(FileSet::new(
+            paths
+                .iter()
+                .map(|p| FileNode(CellPath::testing_new(&format!("root//{}", p))))
+                .collect(),
+        ))
+    };
+    for (input, expected) in [
+        ("fileset(a b) + fileset(b c)", files(&["a", "b", "c"])),
+        ("fileset(a b c) ^ fileset(b c d)", files(&["b", "c"])),
+        ("fileset(a b c) - fileset(b)", files(&["a", "c"])),
+        ("fileset(a b c) - c", files(&["a", "b"])),
+        ("a intersect fileset(a b)", files(&["a"])),
+    ] {
+        let parsed = parse_expr(input)?;
+        let value = QueryEvaluator::new(&Env, &DefaultQueryFunctionsModule::new())
+            .eval(&parsed)
+            .await
+            .map_err(|e| QueryError::convert_error(e, input))?;
+        assert_eq!(expected, value.value, "query: {}", input);
+    }
+    Ok(())
+}
@@ -17,6 +17,7 @@ use buck2_query_parser::Expr;
 use buck2_query_parser::SpannedExpr;
 use enum_iterator::IntoEnumIterator;
 use gazebo::variants::VariantName;
+use indexmap::IndexSet;
 
 use crate::query::environment::QueryEnvironment;
 use crate::query::syntax::simple::eval::error::QueryError;
@@ -248,6 +249,26 @@ impl<'a, Env: QueryEnvironment> QueryFunctionArg<'a, Env> for TargetSet<Env::Tar
 impl<'a, Env: QueryEnvironment> QueryFunctionArg<'a, Env> for FileSet {
     const ARG_TYPE: QueryArgType = QueryArgType::FileSet;
 
+    /// Where a file set is expected, `set(...)` is a set of file literals, like `fileset(...)`.
+    async fn eval(
+        evaluator: &QueryEvaluator<'a, Env>,
+        expr: &'a Spanned<Expr<'a>>,
+    ) -> Result<Self, QueryError> {
+        match &expr.value {
+            Expr::Set(args) => {
+                let mut files = FileSet::new(IndexSet::new());
+                for arg in args {
+                    files.insert_all(&evaluator.env().eval_file_literal(arg.fragment()).await?);
+                }
+                Ok(files)
+            }
+            _ => {
+                let result = evaluator.eval(expr).await?;
+                Self::accept(evaluator.env(), result.value).await
+            }
+        }
+    }
+
     async fn accept(env: &Env, val: QueryValue<Env::Target>) -> Result<Self, QueryError> {
         match val {
             QueryValue::String(v) => Ok(env.eval_file_literal(&v).await?),
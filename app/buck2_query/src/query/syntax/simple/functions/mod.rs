@@ -24,6 +24,7 @@ use crate::query::environment::QueryEnvironment;
 use crate::query::syntax::simple::eval::error::QueryError;
 use crate::query::syntax::simple::eval::evaluator::QueryEvaluator;
 use crate::query::syntax::simple::eval::file_set::FileSet;
+use crate::query::syntax::simple::eval::set::SelectBranches;
 use crate::query::syntax::simple::eval::set::TargetSet;
 use crate::query::syntax::simple::eval::set::TargetSetExt;
 use crate::query::syntax::simple::eval::values::QueryResult;
@@ -140,6 +141,9 @@ impl<F: QueryFunctions> QueryFunctionsVisitLiterals for F {
                     Expr::Integer(..) => {
                         // ignored
                     }
+                    Expr::Set(..) if !is_target_expr => {
+                        // A set of file literals, e.g. `owner(set(a.txt b.txt))`.
+                    }
                     _ => visit_literals_recurse(this, visitor, value)?,
                 }
                 Ok(())
@@ -188,6 +192,20 @@ async fn accept_target_set<Env: QueryEnvironment>(
     }
 }
 
+async fn accept_file_set<Env: QueryEnvironment>(
+    env: &Env,
+    val: QueryValue<Env::Target>,
+) -> Result<FileSet, QueryError> {
+    match val {
+        QueryValue::FileSet(x) => Ok(x),
+        QueryValue::String(literal) => Ok(env.eval_file_literal(&literal).await?),
+        _ => Err(QueryError::InvalidType {
+            expected: "file_set",
+            actual: val.variant_name(),
+        }),
+    }
+}
+
 /// Common query functions
 #[query_module(Env)]
 impl<Env: QueryEnvironment> DefaultQueryFunctionsModule<Env> {
@@ -251,22 +269,46 @@ impl<Env: QueryEnvironment> DefaultQueryFunctionsModule<Env> {
             .into())
     }
 
+    /// The `attrregexfilter(attribute, pattern, argument[, branches])` filter evaluates the specified argument and filters the
+    /// resulting build targets to those whose attribute matches the given regular expression.
+    ///
+    /// The attribute is searched recursively, so lists, dicts and the branches of a `select()` are looked into.
+    /// By default a `select()` matches if any of its branches match; pass `all` as the optional fourth argument
+    /// to require that every branch matches. For example, the command:
+    ///
+    /// `buck2 uquery "attrregexfilter(deps, '//third-party/.*', '//foo/...', all)"`
+    ///
+    /// returns the targets in `//foo` whose `deps` reference third-party code in every configuration.
     async fn attrregexfilter(
         &self,
         attr: String,
         value: String,
         targets: TargetSet<Env::Target>,
+        branches: Option<String>,
     ) -> QueryFuncResult<Env> {
+        let branches = match branches {
+            Some(branches) => branches.parse()?,
+            None => SelectBranches::Any,
+        };
         Ok(self
             .implementation
-            .attrregexfilter(&attr, &value, &targets)?
+            .attrregexfilter(&attr, &value, &targets, branches)?
             .into())
     }
 
+    /// The `buildfile(targets)` function evaluates to the build files that define the given targets.
     async fn buildfile(&self, targets: TargetSet<Env::Target>) -> QueryFuncResult<Env> {
         Ok(self.implementation.buildfile(&targets).into())
     }
 
+    /// The `rbuildfiles(universe, argset)` function evaluates to the files in `universe` whose
+    /// evaluation depends on any of the files in `argset`.
+    ///
+    /// `universe` can contain build files, `PACKAGE` files and `.bzl` files. A build file depends on
+    /// the `.bzl` files it loads transitively, and on the `PACKAGE` files in its directory and all
+    /// its parent directories, together with the `.bzl` files those load. This is useful to find the
+    /// build files affected by a change to a macro, e.g.
+    /// `buck2 uquery "rbuildfiles(allbuildfiles(//...), 'foo/defs.bzl')"`.
     async fn rbuildfiles(
         &self,
         env: &Env,
@@ -280,6 +322,9 @@ impl<Env: QueryEnvironment> DefaultQueryFunctionsModule<Env> {
             .into())
     }
 
+    /// The `allbuildfiles(universe)` function evaluates to the build files defining the targets in
+    /// `universe`, and all the files their evaluation depends on: the `.bzl` files they load
+    /// transitively and the `PACKAGE` files which apply to them.
     async fn allbuildfiles(
         &self,
         env: &Env,
@@ -458,8 +503,9 @@ impl<Env: QueryEnvironment> DefaultQueryFunctions<Env> {
         attr: &str,
         value: &str,
         targets: &TargetSet<Env::Target>,
+        branches: SelectBranches,
     ) -> anyhow::Result<TargetSet<Env::Target>> {
-        targets.attrregexfilter(attr, value)
+        targets.attrregexfilter(attr, value, branches)
     }
 
     pub fn buildfile(&self, targets: &TargetSet<Env::Target>) -> FileSet {
@@ -583,9 +629,22 @@ impl<Env: QueryEnvironment> DefaultQueryFunctions<Env> {
         left: QueryValue<Env::Target>,
         right: QueryValue<Env::Target>,
     ) -> Result<QueryValue<Env::Target>, QueryError> {
-        let left = accept_target_set(env, left).await?;
-        let right = accept_target_set(env, right).await?;
-        Ok(QueryValue::TargetSet(left.intersect(&right)?))
+        // If either side is a file set, a string on the other side is a file literal.
+        match (left, right) {
+            (QueryValue::FileSet(l), r) => {
+                let r = accept_file_set(env, r).await?;
+                Ok(QueryValue::FileSet(l.intersect(&r)))
+            }
+            (l, QueryValue::FileSet(r)) => {
+                let l = accept_file_set(env, l).await?;
+                Ok(QueryValue::FileSet(l.intersect(&r)))
+            }
+            (l, r) => {
+                let left = accept_target_set(env, l).await?;
+                let right = accept_target_set(env, r).await?;
+                Ok(QueryValue::TargetSet(left.intersect(&right)?))
+            }
+        }
     }
 
     pub async fn except(
@@ -594,9 +653,22 @@ impl<Env: QueryEnvironment> DefaultQueryFunctions<Env> {
         left: QueryValue<Env::Target>,
         right: QueryValue<Env::Target>,
     ) -> Result<QueryValue<Env::Target>, QueryError> {
-        let left = accept_target_set(env, left).await?;
-        let right = accept_target_set(env, right).await?;
-        Ok(QueryValue::TargetSet(left.difference(&right)?))
+        // If either side is a file set, a string on the other side is a file literal.
+        match (left, right) {
+            (QueryValue::FileSet(l), r) => {
+                let r = accept_file_set(env, r).await?;
+                Ok(QueryValue::FileSet(l.difference(&r)))
+            }
+            (l, QueryValue::FileSet(r)) => {
+                let l = accept_file_set(env, l).await?;
+                Ok(QueryValue::FileSet(l.difference(&r)))
+            }
+            (l, r) => {
+                let left = accept_target_set(env, l).await?;
+                let right = accept_target_set(env, r).await?;
+                Ok(QueryValue::TargetSet(left.difference(&right)?))
+            }
+        }
     }
 
     pub async fn union(
@@ -19,6 +19,39 @@ static BUCK2_RE_CLIENT_CFG_SECTION: &str = "buck2_re_client";
 pub trait RemoteExecutionStaticMetadataImpl: Sized {
     fn from_legacy_config(legacy_config: &LegacyBuckConfig) -> anyhow::Result<Self>;
     fn cas_semaphore_size(&self) -> usize;
+    fn rpc_limits(&self) -> &RemoteExecutionRpcLimits;
+}
+
+/// Limits on the number of concurrent RPCs to RE, `None` for no limit.
+///
+/// When the `total` limit is reached, RPCs start in order of priority as slots free up: action
+/// execution, then cache queries, then prefetches (materializations), except that a class passed
+/// over a few times in a row gets the next slot, so that none is starved. The limits of each class
+/// apply on top of that, to keep a class from taking all the slots. Remote executions only hold a
+/// slot while they are being started, not while waiting for them to finish.
+#[derive(Clone, Debug, Default, Allocative)]
+pub struct RemoteExecutionRpcLimits {
+    pub total: Option<usize>,
+    /// Uploads, executions, and writes of action results.
+    pub execution: Option<usize>,
+    /// Action cache lookups, downloads of the results found, and queries of digest TTLs.
+    pub cache_query: Option<usize>,
+    /// Materializations of outputs.
+    pub prefetch: Option<usize>,
+}
+
+impl RemoteExecutionRpcLimits {
+    pub fn from_legacy_config(legacy_config: &LegacyBuckConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            total: legacy_config.parse(BUCK2_RE_CLIENT_CFG_SECTION, "rpc_concurrency")?,
+            execution: legacy_config
+                .parse(BUCK2_RE_CLIENT_CFG_SECTION, "execution_rpc_concurrency")?,
+            cache_query: legacy_config
+                .parse(BUCK2_RE_CLIENT_CFG_SECTION, "cache_query_rpc_concurrency")?,
+            prefetch: legacy_config
+                .parse(BUCK2_RE_CLIENT_CFG_SECTION, "prefetch_rpc_concurrency")?,
+        })
+    }
 }
 
 #[allow(unused)]
@@ -49,6 +82,8 @@ mod fbcode {
         pub force_enable_deduplicate_find_missing: Option<bool>,
 
         pub features_config_path: Option<String>,
+
+        pub rpc_limits: RemoteExecutionRpcLimits,
     }
 
     impl RemoteExecutionStaticMetadataImpl for RemoteExecutionStaticMetadata {
@@ -97,12 +132,17 @@ mod fbcode {
                 )?,
                 features_config_path: legacy_config
                     .parse(BUCK2_RE_CLIENT_CFG_SECTION, "features_config_path")?,
+                rpc_limits: RemoteExecutionRpcLimits::from_legacy_config(legacy_config)?,
             })
         }
 
         fn cas_semaphore_size(&self) -> usize {
             self.cas_connection_count as usize * 30
         }
+
+        fn rpc_limits(&self) -> &RemoteExecutionRpcLimits {
+            &self.rpc_limits
+        }
     }
 }
 
@@ -125,6 +165,10 @@ mod not_fbcode {
             // FIXME: make this configurable?
             1024
         }
+
+        fn rpc_limits(&self) -> &RemoteExecutionRpcLimits {
+            &self.0.rpc_limits
+        }
     }
 }
 
@@ -162,6 +206,8 @@ pub struct Buck2OssReConfiguration {
     pub capabilities: Option<bool>,
     /// The instance name to use in requests.
     pub instance_name: Option<String>,
+    /// Limits on the concurrent RPCs to RE.
+    pub rpc_limits: RemoteExecutionRpcLimits,
 }
 
 #[derive(Clone, Debug, Default, Allocative)]
@@ -215,6 +261,7 @@ impl Buck2OssReConfiguration {
                 .unwrap_or_default(), // Empty list is as good None.
             capabilities: legacy_config.parse(BUCK2_RE_CLIENT_CFG_SECTION, "capabilities")?,
             instance_name: legacy_config.parse(BUCK2_RE_CLIENT_CFG_SECTION, "instance_name")?,
+            rpc_limits: RemoteExecutionRpcLimits::from_legacy_config(legacy_config)?,
         })
     }
 }
@@ -10,6 +10,7 @@
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::sync::Arc;
+use std::time::Instant;
 
 use buck2_cli_proto::ClientContext;
 use buck2_event_observer::dice_state::DiceState;
@@ -100,9 +101,16 @@ impl Drop for ActiveCommandDropGuard {
 
 /// A handle to the stats for this command. We use this to broadcast state about this command.
 pub struct ActiveCommandState {
-    #[allow(unused)]
     pub argv: Vec<String>,
 
+    /// Working directory of the client which started this command.
+    pub working_dir: String,
+
+    /// PID of the client which started this command, 0 if unknown.
+    pub client_pid: i64,
+
+    pub start_instant: Instant,
+
     spans: Mutex<SpansSnapshot>,
 }
 
@@ -111,9 +119,12 @@ impl ActiveCommandState {
         *self.spans.lock()
     }
 
-    fn new(argv: Vec<String>) -> Self {
+    fn new(argv: Vec<String>, working_dir: String, client_pid: i64) -> Self {
         Self {
             argv,
+            working_dir,
+            client_pid,
+            start_instant: Instant::now(),
             spans: Mutex::new(SpansSnapshot::default()),
         }
     }
@@ -225,7 +236,11 @@ impl ActiveCommand {
     pub fn new(event_dispatcher: &EventDispatcher, client_ctx: &ClientContext) -> Self {
         let (sender, receiver) = oneshot::channel();
 
-        let state = Arc::new(ActiveCommandState::new(client_ctx.sanitized_argv.clone()));
+        let state = Arc::new(ActiveCommandState::new(
+            client_ctx.sanitized_argv.clone(),
+            client_ctx.working_dir.clone(),
+            client_ctx.client_pid,
+        ));
 
         let trace_id = event_dispatcher.trace_id().dupe();
         let result = {
@@ -283,8 +298,11 @@ mod tests {
 
     #[test]
     fn test_active_command_state() {
-        let mut writer =
-            ActiveCommandStateWriter::new(Arc::new(ActiveCommandState::new(Vec::new())));
+        let mut writer = ActiveCommandStateWriter::new(Arc::new(ActiveCommandState::new(
+            Vec::new(),
+            String::new(),
+            0,
+        )));
 
         let root = SpanId::new();
         let child = SpanId::new();
@@ -19,6 +19,8 @@ use anyhow::Context;
 use async_trait::async_trait;
 use buck2_build_api::actions::build_listener::BuildSignalSender;
 use buck2_build_api::actions::build_listener::SetBuildSignals;
+use buck2_build_api::actions::impls::run_action_knobs::ActionInputBudget;
+use buck2_build_api::actions::impls::run_action_knobs::ActionResourceDefaults;
 use buck2_build_api::actions::impls::run_action_knobs::HasRunActionKnobs;
 use buck2_build_api::actions::impls::run_action_knobs::RunActionKnobs;
 use buck2_build_api::build::HasCreateUnhashedSymlinkLock;
@@ -41,6 +43,7 @@ use buck2_common::dice::cycles::CycleDetectorAdapter;
 use buck2_common::dice::cycles::PairDiceCycleDetector;
 use buck2_common::dice::data::HasIoProvider;
 use buck2_common::executor_config::CommandExecutorConfig;
+use buck2_common::executor_config::RePropertiesByCategory;
 use buck2_common::io::trace::TracingIoProvider;
 use buck2_common::io::IoProvider;
 use buck2_common::legacy_configs::dice::HasLegacyConfigs;
@@ -52,6 +55,7 @@ use buck2_common::result::ToSharedResultExt;
 use buck2_core::async_once_cell::AsyncOnceCell;
 use buck2_core::cells::CellResolver;
 use buck2_core::facebook_only;
+use buck2_core::fs::buck_out_path::BuckOutPathScheme;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::fs::project::ProjectRoot;
@@ -77,7 +81,9 @@ use buck2_execute::re::client::RemoteExecutionClient;
 use buck2_execute::re::manager::ReConnectionHandle;
 use buck2_execute::re::manager::ReConnectionManager;
 use buck2_execute::re::manager::ReConnectionObserver;
+use buck2_execute_impl::local_disk_cache::LocalDiskCache;
 use buck2_execute_impl::low_pass_filter::LowPassFilter;
+use buck2_execute_impl::running_local_actions::RunningLocalActions;
 use buck2_forkserver::client::ForkserverClient;
 use buck2_interpreter::dice::starlark_debug::SetStarlarkDebugger;
 use buck2_interpreter::dice::starlark_profiler::StarlarkProfilerConfiguration;
@@ -88,6 +94,9 @@ use buck2_interpreter_for_build::interpreter::configuror::BuildInterpreterConfig
 use buck2_interpreter_for_build::interpreter::configuror::CONFIGURE_BXL_FILE_GLOBALS;
 use buck2_interpreter_for_build::interpreter::cycles::LoadCycleDescriptor;
 use buck2_interpreter_for_build::interpreter::interpreter_setup::setup_interpreter;
+use buck2_interpreter_for_build::interpreter::load_digests::CommandLoadDigests;
+use buck2_interpreter_for_build::interpreter::load_digests::LoadDigests;
+use buck2_interpreter_for_build::interpreter::load_digests::SetLoadDigests;
 use buck2_server_ctx::concurrency::ConcurrencyHandler;
 use buck2_server_ctx::concurrency::DiceDataProvider;
 use buck2_server_ctx::concurrency::DiceUpdater;
@@ -105,6 +114,7 @@ use dupe::Dupe;
 use gazebo::prelude::SliceExt;
 use host_sharing::HostSharingBroker;
 use host_sharing::HostSharingStrategy;
+use host_sharing::MemoryRequirement;
 use more_futures::cancellation::CancellationContext;
 use tokio::sync::Mutex;
 use tracing::warn;
@@ -164,6 +174,12 @@ pub struct BaseServerCommandContext {
     pub daemon_start_time: Instant,
     /// Mutex for creating symlinks
     pub create_unhashed_outputs_lock: Arc<Mutex<()>>,
+    /// Cache of local action results shared by all the checkouts on this machine, if enabled.
+    pub local_disk_cache: Option<Arc<LocalDiskCache>>,
+    /// Local actions executing in the daemon, which the user can kill.
+    pub running_local_actions: Arc<RunningLocalActions>,
+    /// Digests of the `.bzl` files loaded by the commands of the daemon.
+    pub load_digests: Arc<LoadDigests>,
 }
 
 /// ServerCommandContext provides access to the global daemon state and information about the calling client for
@@ -201,6 +217,7 @@ pub struct ServerCommandContext<'a> {
     debugger_handle: Option<BuckStarlarkDebuggerHandle>,
 
     record_target_call_stacks: bool,
+    aggregate_attr_errors: bool,
     disable_starlark_types: bool,
 
     pub buck_out_dir: ProjectRelativePathBuf,
@@ -322,6 +339,7 @@ impl<'a> ServerCommandContext<'a> {
             build_options: build_options.cloned(),
             cell_configs_loader,
             record_target_call_stacks: client_context.target_call_stacks,
+            aggregate_attr_errors: client_context.aggregate_attr_errors,
             disable_starlark_types: client_context.disable_starlark_types,
             heartbeat_guard_handle: Some(heartbeat_guard_handle),
             daemon_uuid_from_client: client_context.daemon_uuid.clone(),
@@ -383,8 +401,19 @@ impl<'a> ServerCommandContext<'a> {
             .as_ref()
             .map_or(false, |opts| opts.upload_all_actions);
 
+        let upload_all_outputs = self
+            .build_options
+            .as_ref()
+            .map_or(false, |opts| opts.upload_all_outputs);
+
         let create_unhashed_symlink_lock = self.base_context.create_unhashed_outputs_lock.dupe();
 
+        let local_disk_cache = self.base_context.local_disk_cache.dupe();
+
+        let running_local_actions = self.base_context.running_local_actions.dupe();
+
+        let load_digests = self.base_context.load_digests.for_command();
+
         DiceCommandDataProvider {
             cell_configs_loader: self.cell_configs_loader.dupe(),
             events: self.events().dupe(),
@@ -398,8 +427,12 @@ impl<'a> ServerCommandContext<'a> {
             build_signals,
             forkserver,
             upload_all_actions,
+            upload_all_outputs,
             skip_cache_read,
             skip_cache_write,
+            local_disk_cache,
+            running_local_actions,
+            load_digests,
             create_unhashed_symlink_lock,
             starlark_debugger: self.debugger_handle.dupe(),
             keep_going: self
@@ -429,6 +462,7 @@ impl<'a> ServerCommandContext<'a> {
                 .dupe(),
             disable_starlark_types: self.disable_starlark_types,
             record_target_call_stacks: self.record_target_call_stacks,
+            aggregate_attr_errors: self.aggregate_attr_errors,
         })
     }
 
@@ -497,9 +531,13 @@ struct DiceCommandDataProvider {
     build_signals: BuildSignalSender,
     forkserver: Option<ForkserverClient>,
     upload_all_actions: bool,
+    upload_all_outputs: bool,
     run_action_knobs: RunActionKnobs,
     skip_cache_read: bool,
     skip_cache_write: bool,
+    local_disk_cache: Option<Arc<LocalDiskCache>>,
+    running_local_actions: Arc<RunningLocalActions>,
+    load_digests: CommandLoadDigests,
     create_unhashed_symlink_lock: Arc<Mutex<()>>,
     starlark_debugger: Option<BuckStarlarkDebuggerHandle>,
     keep_going: bool,
@@ -535,11 +573,28 @@ impl DiceDataProvider for DiceCommandDataProvider {
             .unwrap_or_else(RolloutPercentage::always)
             .roll();
 
-        let executor_global_knobs = ExecutorGlobalKnobs { enable_miniperf };
+        let sandbox_local_actions = root_config
+            .parse("build", "sandbox_local_actions")?
+            .unwrap_or(false);
+
+        let executor_plugin = root_config.parse("build", "executor_plugin")?;
+
+        let executor_global_knobs = ExecutorGlobalKnobs {
+            enable_miniperf,
+            sandbox_local_actions,
+            executor_plugin,
+        };
 
-        let host_sharing_broker =
+        let mut host_sharing_broker =
             HostSharingBroker::new(HostSharingStrategy::SmallerTasksFirst, concurrency);
 
+        if let Some(local_memory) =
+            root_config.parse::<MemoryRequirement>("build", "local_memory")?
+        {
+            host_sharing_broker = host_sharing_broker
+                .with_memory_budget(HostSharingStrategy::SmallerTasksFirst, local_memory);
+        }
+
         // We use the job count for the low pass filter too. The low pass filter prevents sending
         // RE-eligile tasks to local if their concurrency is higher than our threshold. While it
         // doesn't *have* to be the same as the concurrency we give the actual executor, it's a
@@ -564,6 +619,9 @@ impl DiceDataProvider for DiceCommandDataProvider {
         run_action_knobs.use_network_action_output_cache |= root_config
             .parse::<bool>("buck2", "use_network_action_output_cache")?
             .unwrap_or(false);
+        run_action_knobs.action_resource_defaults =
+            Arc::new(ActionResourceDefaults::from_config(root_config)?);
+        run_action_knobs.action_input_budget = ActionInputBudget::from_config(root_config)?;
 
         let mut data = UserComputationData {
             data,
@@ -583,9 +641,13 @@ impl DiceDataProvider for DiceCommandDataProvider {
             self.execution_strategy,
             executor_global_knobs,
             self.upload_all_actions,
+            self.upload_all_outputs,
             self.forkserver.dupe(),
             self.skip_cache_read,
             self.skip_cache_write,
+            self.local_disk_cache.dupe(),
+            Arc::new(RePropertiesByCategory::from_config(root_config)?),
+            self.running_local_actions.dupe(),
             ctx.global_data()
                 .get_io_provider()
                 .project_root()
@@ -596,6 +658,7 @@ impl DiceDataProvider for DiceCommandDataProvider {
         data.set_build_signals(self.build_signals.dupe());
         data.set_run_action_knobs(run_action_knobs);
         data.set_create_unhashed_symlink_lock(self.create_unhashed_symlink_lock.dupe());
+        data.set_load_digests(self.load_digests.dupe());
         data.set_starlark_debugger_handle(self.starlark_debugger.clone().map(|v| Box::new(v) as _));
         data.set_keep_going(self.keep_going);
         data.spawner = Arc::new(BuckSpawner::default());
@@ -627,6 +690,7 @@ struct DiceCommandUpdater {
     starlark_profiler_instrumentation_override: StarlarkProfilerConfiguration,
     disable_starlark_types: bool,
     record_target_call_stacks: bool,
+    aggregate_attr_errors: bool,
 }
 
 #[async_trait]
@@ -641,12 +705,21 @@ impl DiceUpdater for DiceCommandUpdater {
 
         let cell_alias_resolver = cell_resolver.root_cell_instance().cell_alias_resolver();
 
+        let root_config = legacy_configs
+            .get(cell_resolver.root_cell())
+            .context("No config for root cell")?;
+        let buck_out_path_scheme = BuckOutPathScheme::new(
+            root_config.parse("buck2", "output_path_config_hash_length")?,
+            root_config.parse("buck2", "output_path_max_package_length")?,
+        )?;
+
         let configuror = BuildInterpreterConfiguror::new(
             Some(prelude_path(cell_alias_resolver)?),
             self.interpreter_platform,
             self.interpreter_architecture,
             self.interpreter_xcode_version.clone(),
             self.record_target_call_stacks,
+            self.aggregate_attr_errors,
             configure_build_file_globals,
             configure_package_file_globals,
             configure_extension_file_globals,
@@ -656,7 +729,7 @@ impl DiceUpdater for DiceCommandUpdater {
 
         let mut ctx = self.file_watcher.sync(ctx).await?;
 
-        ctx.set_buck_out_path(Some(self.buck_out_dir.clone()))?;
+        ctx.set_buck_out_path_with_scheme(Some(self.buck_out_dir.clone()), buck_out_path_scheme)?;
 
         setup_interpreter(
             &mut ctx,
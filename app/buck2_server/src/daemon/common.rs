@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use std::borrow::Cow;
 use std::sync::Arc;
 
 use anyhow::Context as _;
@@ -18,6 +19,7 @@ use buck2_common::executor_config::CommandGenerationOptions;
 use buck2_common::executor_config::Executor;
 use buck2_common::executor_config::HybridExecutionLevel;
 use buck2_common::executor_config::PathSeparatorKind;
+use buck2_common::executor_config::RePropertiesByCategory;
 use buck2_common::executor_config::RemoteEnabledExecutor;
 use buck2_common::executor_config::RemoteExecutorOptions;
 use buck2_common::executor_config::RemoteExecutorUseCase;
@@ -36,8 +38,11 @@ use buck2_execute::re::manager::ReConnectionHandle;
 use buck2_execute_impl::executors::caching::CachingExecutor;
 use buck2_execute_impl::executors::hybrid::HybridExecutor;
 use buck2_execute_impl::executors::local::LocalExecutor;
+use buck2_execute_impl::executors::local_disk_cache::LocalDiskCachingExecutor;
 use buck2_execute_impl::executors::re::ReExecutor;
+use buck2_execute_impl::local_disk_cache::LocalDiskCache;
 use buck2_execute_impl::low_pass_filter::LowPassFilter;
+use buck2_execute_impl::running_local_actions::RunningLocalActions;
 use buck2_forkserver::client::ForkserverClient;
 use dupe::Dupe;
 use host_sharing::HostSharingBroker;
@@ -69,9 +74,16 @@ pub struct CommandExecutorFactory {
     pub strategy: ExecutionStrategy,
     pub executor_global_knobs: ExecutorGlobalKnobs,
     pub upload_all_actions: bool,
+    pub upload_all_outputs: bool,
     pub forkserver: Option<ForkserverClient>,
     pub skip_cache_read: bool,
     pub skip_cache_write: bool,
+    pub local_disk_cache: Option<Arc<LocalDiskCache>>,
+    /// RE properties per action category from the buckconfig, which take precedence over the
+    /// ones of the executor config.
+    pub re_properties_by_category: Arc<RePropertiesByCategory>,
+    /// Local actions executing in the daemon, which the user can kill.
+    pub running_local_actions: Arc<RunningLocalActions>,
     project_root: ProjectRoot,
 }
 
@@ -85,9 +97,13 @@ impl CommandExecutorFactory {
         strategy: ExecutionStrategy,
         executor_global_knobs: ExecutorGlobalKnobs,
         upload_all_actions: bool,
+        upload_all_outputs: bool,
         forkserver: Option<ForkserverClient>,
         skip_cache_read: bool,
         skip_cache_write: bool,
+        local_disk_cache: Option<Arc<LocalDiskCache>>,
+        re_properties_by_category: Arc<RePropertiesByCategory>,
+        running_local_actions: Arc<RunningLocalActions>,
         project_root: ProjectRoot,
     ) -> Self {
         Self {
@@ -99,12 +115,49 @@ impl CommandExecutorFactory {
             strategy,
             executor_global_knobs,
             upload_all_actions,
+            upload_all_outputs,
             forkserver,
             skip_cache_read,
             skip_cache_write,
+            local_disk_cache,
+            re_properties_by_category,
+            running_local_actions,
             project_root,
         }
     }
+
+    /// Check the local disk cache before the remote cache and before executing anything, if it is
+    /// enabled.
+    fn with_local_disk_cache(
+        &self,
+        executor: Arc<dyn PreparedCommandExecutor>,
+        artifact_fs: &ArtifactFs,
+    ) -> Arc<dyn PreparedCommandExecutor> {
+        match &self.local_disk_cache {
+            Some(cache) => Arc::new(LocalDiskCachingExecutor {
+                inner: executor,
+                cache: cache.dupe(),
+                artifact_fs: artifact_fs.clone(),
+                materializer: self.materializer.dupe(),
+                blocking_executor: self.blocking_executor.dupe(),
+                skip_cache_read: self.skip_cache_read,
+                skip_cache_write: self.skip_cache_write,
+            }),
+            None => executor,
+        }
+    }
+
+    /// `--upload-all-outputs` is implemented by the `CachingExecutor`, so reject it for
+    /// executors which don't have one rather than silently not uploading anything.
+    fn check_no_forced_cache_upload(&self, reason: &str) -> anyhow::Result<()> {
+        if self.upload_all_outputs {
+            return Err(anyhow::anyhow!(
+                "`--upload-all-outputs` cannot be used: {}",
+                reason
+            ));
+        }
+        Ok(())
+    }
 }
 
 impl HasCommandExecutor for CommandExecutorFactory {
@@ -122,6 +175,7 @@ impl HasCommandExecutor for CommandExecutorFactory {
                 self.project_root.root().to_owned(),
                 self.forkserver.dupe(),
                 self.executor_global_knobs.dupe(),
+                self.running_local_actions.dupe(),
             )
         };
 
@@ -137,10 +191,12 @@ impl HasCommandExecutor for CommandExecutorFactory {
                     self.strategy,
                 ));
             }
+            self.check_no_forced_cache_upload("remote caching is disabled in Cargo builds")?;
 
             return Ok(CommandExecutorResponse {
-                executor: Arc::new(local_executor_new()),
+                executor: self.with_local_disk_cache(Arc::new(local_executor_new()), artifact_fs),
                 platform: Default::default(),
+                platform_by_category: Default::default(),
             });
         }
 
@@ -169,23 +225,32 @@ impl HasCommandExecutor for CommandExecutorFactory {
 
         let response = match &executor_config.executor {
             Executor::Local => {
+                self.check_no_forced_cache_upload(
+                    "the executor config of this action has no remote cache",
+                )?;
                 if self.strategy.ban_local() {
                     None
                 } else {
                     Some(CommandExecutorResponse {
                         executor: Arc::new(local_executor_new()),
                         platform: Default::default(),
+                        platform_by_category: Default::default(),
                     })
                 }
             }
             Executor::RemoteEnabled {
                 executor,
                 re_properties,
+                re_properties_by_category,
                 re_use_case,
                 cache_upload_behavior,
                 remote_cache_enabled,
             } => {
-                let inner_executor: Option<Arc<dyn PreparedCommandExecutor>> = match &executor {
+                // `--local-only` and `--remote-only` take precedence over the executor config. The
+                // remote cache is still used as configured.
+                let executor = forced_executor(self.strategy, executor)?;
+
+                let inner_executor: Option<Arc<dyn PreparedCommandExecutor>> = match &*executor {
                     RemoteEnabledExecutor::Local if !self.strategy.ban_local() => {
                         Some(Arc::new(local_executor_new()))
                     }
@@ -216,11 +281,14 @@ impl HasCommandExecutor for CommandExecutorFactory {
                 static DISABLE_CACHING: EnvHelper<bool> =
                     EnvHelper::new("BUCK2_TEST_DISABLE_CACHING");
 
-                let disable_caching = DISABLE_CACHING
-                    .get_copied()?
-                    .unwrap_or(self.skip_cache_read);
+                // `--no-remote-cache` is handled by the `CachingExecutor` itself, since some
+                // actions still query the cache when it is passed.
+                let disable_caching = DISABLE_CACHING.get_copied()?.unwrap_or_default();
 
                 let executor = if disable_caching || !remote_cache_enabled {
+                    self.check_no_forced_cache_upload(
+                        "the remote cache is disabled for the executor config of this action",
+                    )?;
                     inner_executor
                 } else {
                     inner_executor.map(|inner_executor| {
@@ -231,23 +299,32 @@ impl HasCommandExecutor for CommandExecutorFactory {
                             re_client: self.re_connection.get_client(),
                             re_use_case: *re_use_case,
                             upload_all_actions: self.upload_all_actions,
+                            upload_all_outputs: self.upload_all_outputs,
                             knobs: self.executor_global_knobs.dupe(),
                             cache_upload_behavior: *cache_upload_behavior,
+                            skip_cache_read: self.skip_cache_read,
                         }) as _
                     })
                 };
 
-                let platform = RE::Platform {
-                    properties: re_properties
-                        .iter()
-                        .map(|(k, v)| RE::Property {
-                            name: k.clone(),
-                            value: v.clone(),
-                        })
-                        .collect(),
-                };
+                let platform = re_platform(re_properties);
+                let re_properties_by_category =
+                    re_properties_by_category.merge(&self.re_properties_by_category);
+                let platform_by_category = re_properties_by_category
+                    .0
+                    .keys()
+                    .filter_map(|category| {
+                        let properties =
+                            re_properties_by_category.properties_for(category, re_properties)?;
+                        Some((category.clone(), re_platform(&properties)))
+                    })
+                    .collect();
 
-                executor.map(|executor| CommandExecutorResponse { executor, platform })
+                executor.map(|executor| CommandExecutorResponse {
+                    executor,
+                    platform,
+                    platform_by_category,
+                })
             }
         };
 
@@ -256,14 +333,66 @@ impl HasCommandExecutor for CommandExecutorFactory {
 "The desired execution strategy (`{:?}`) is incompatible with the executor config that was selected: {:?}",
 self.strategy, executor_config))?;
 
-        Ok(response)
+        Ok(CommandExecutorResponse {
+            executor: self.with_local_disk_cache(response.executor, artifact_fs),
+            platform: response.platform,
+            platform_by_category: response.platform_by_category,
+        })
+    }
+}
+
+fn re_platform(properties: &SortedMap<String, String>) -> RE::Platform {
+    RE::Platform {
+        properties: properties
+            .iter()
+            .map(|(k, v)| RE::Property {
+                name: k.clone(),
+                value: v.clone(),
+            })
+            .collect(),
     }
 }
 
+/// Where `--local-only` and `--remote-only` force all actions to execute, whatever the executor
+/// config of their execution platform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ForcedExecutor {
+    Local,
+    Remote,
+}
+
+#[derive(Debug, thiserror::Error)]
+enum ForcedExecutorError {
+    #[error(
+        "`--remote-only` was passed, but the executor config of the execution platform only \
+        allows local execution, so there are no remote execution options to use"
+    )]
+    RemoteOnlyOnLocalPlatform,
+}
+
+/// The executor to use given the executor config of the execution platform, once `--local-only`
+/// or `--remote-only` are applied.
+fn forced_executor(
+    strategy: ExecutionStrategy,
+    executor: &RemoteEnabledExecutor,
+) -> anyhow::Result<Cow<'_, RemoteEnabledExecutor>> {
+    Ok(match (strategy.forced_executor(), executor) {
+        (Some(ForcedExecutor::Local), _) => Cow::Owned(RemoteEnabledExecutor::Local),
+        (Some(ForcedExecutor::Remote), RemoteEnabledExecutor::Local) => {
+            return Err(ForcedExecutorError::RemoteOnlyOnLocalPlatform.into());
+        }
+        (Some(ForcedExecutor::Remote), RemoteEnabledExecutor::Hybrid { remote, .. }) => {
+            Cow::Owned(RemoteEnabledExecutor::Remote(remote.clone()))
+        }
+        _ => Cow::Borrowed(executor),
+    })
+}
+
 trait ExecutionStrategyExt {
     fn ban_local(&self) -> bool;
     fn ban_remote(&self) -> bool;
     fn ban_hybrid(&self) -> bool;
+    fn forced_executor(&self) -> Option<ForcedExecutor>;
     fn hybrid_preference(&self) -> ExecutorPreference;
 }
 
@@ -289,6 +418,14 @@ impl ExecutionStrategyExt for ExecutionStrategy {
         }
     }
 
+    fn forced_executor(&self) -> Option<ForcedExecutor> {
+        match self {
+            Self::LocalOnly => Some(ForcedExecutor::Local),
+            Self::RemoteOnly => Some(ForcedExecutor::Remote),
+            _ => None,
+        }
+    }
+
     fn hybrid_preference(&self) -> ExecutorPreference {
         match self {
             Self::HybridPreferLocal => ExecutorPreference::LocalPreferred,
@@ -311,6 +448,7 @@ pub fn get_default_executor_config(host_platform: HostPlatformOverride) -> Comma
                 level: HybridExecutionLevel::Limited,
             },
             re_properties: get_default_re_properties(host_platform),
+            re_properties_by_category: RePropertiesByCategory::default(),
             re_use_case: RemoteExecutorUseCase::buck2_default(),
             cache_upload_behavior: CacheUploadBehavior::Disabled,
             remote_cache_enabled: true,
@@ -348,3 +486,81 @@ fn get_default_re_properties(host_platform: HostPlatformOverride) -> SortedMap<S
         .map(|(k, v)| ((*k).to_owned(), (*v).to_owned()))
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use buck2_cli_proto::common_build_options::ExecutionStrategy;
+    use buck2_common::executor_config::HybridExecutionLevel;
+    use buck2_common::executor_config::RemoteEnabledExecutor;
+    use buck2_common::executor_config::RemoteExecutorOptions;
+
+    use crate::daemon::common::forced_executor;
+
+    fn remote_options() -> RemoteExecutorOptions {
+        RemoteExecutorOptions {
+            re_max_queue_time_ms: Some(1000),
+            ..Default::default()
+        }
+    }
+
+    fn hybrid() -> RemoteEnabledExecutor {
+        RemoteEnabledExecutor::Hybrid {
+            remote: remote_options(),
+            level: HybridExecutionLevel::Limited,
+        }
+    }
+
+    #[test]
+    fn test_forced_executor_default() -> anyhow::Result<()> {
+        for executor in [
+            RemoteEnabledExecutor::Local,
+            RemoteEnabledExecutor::Remote(remote_options()),
+            hybrid(),
+        ] {
+            assert_eq!(
+                executor,
+                *forced_executor(ExecutionStrategy::Default, &executor)?
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_forced_executor_local_only() -> anyhow::Result<()> {
+        for executor in [
+            RemoteEnabledExecutor::Local,
+            RemoteEnabledExecutor::Remote(remote_options()),
+            hybrid(),
+        ] {
+            assert_eq!(
+                RemoteEnabledExecutor::Local,
+                *forced_executor(ExecutionStrategy::LocalOnly, &executor)?
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_forced_executor_remote_only() -> anyhow::Result<()> {
+        assert_eq!(
+            RemoteEnabledExecutor::Remote(remote_options()),
+            *forced_executor(
+                ExecutionStrategy::RemoteOnly,
+                &RemoteEnabledExecutor::Remote(remote_options())
+            )?
+        );
+        // The remote options of the hybrid executor are kept.
+        assert_eq!(
+            RemoteEnabledExecutor::Remote(remote_options()),
+            *forced_executor(ExecutionStrategy::RemoteOnly, &hybrid())?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_forced_executor_remote_only_on_local_platform() {
+        let err = forced_executor(ExecutionStrategy::RemoteOnly, &RemoteEnabledExecutor::Local)
+            .unwrap_err();
+        assert!(err.to_string().contains("`--remote-only`"), "{:#}", err);
+    }
+}
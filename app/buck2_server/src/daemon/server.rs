@@ -79,6 +79,7 @@ use tonic::Response;
 use tonic::Status;
 use tracing::debug_span;
 
+use crate::active_commands::active_commands;
 use crate::active_commands::ActiveCommand;
 use crate::active_commands::ActiveCommandStateWriter;
 use crate::clean_stale::clean_stale_command;
@@ -86,6 +87,7 @@ use crate::ctx::ServerCommandContext;
 use crate::daemon::multi_event_stream::MultiEventStream;
 use crate::daemon::server_allocative::spawn_allocative;
 use crate::daemon::state::DaemonState;
+use crate::dice_equality_check::dice_equality_check_command;
 use crate::file_status::file_status_command;
 use crate::lsp::run_lsp_server_command;
 use crate::materialize::materialize_command;
@@ -852,6 +854,7 @@ impl DaemonApi for BuckdServer {
                         data.start_time,
                         data.dice_manager.unsafe_dice().dupe(),
                         data.materializer.dupe(),
+                        data.local_disk_cache.dupe(),
                         data.scribe_sink.dupe() as _,
                     )
                     .create_snapshot(),
@@ -873,6 +876,33 @@ impl DaemonApi for BuckdServer {
             let mut daemon_constraints = self.0.base_daemon_constraints.clone();
             daemon_constraints.extra = extra_constraints;
 
+            let (active_commands, re_session_id) = if req.include_active_commands {
+                let active_commands = active_commands()
+                    .iter()
+                    .map(|(trace_id, handle)| {
+                        let state = handle.state();
+                        let spans = state.spans();
+                        anyhow::Ok(ActiveCommandStatus {
+                            trace_id: trace_id.to_string(),
+                            argv: state.argv.clone(),
+                            working_dir: state.working_dir.clone(),
+                            client_pid: state.client_pid,
+                            elapsed: Some(state.start_instant.elapsed().try_into()?),
+                            open_spans: spans.open,
+                            closed_spans: spans.closed,
+                            pending_spans: spans.pending,
+                        })
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                let re_session_id = daemon_state
+                    .data()
+                    .ok()
+                    .and_then(|data| data.re_client_manager.get_existing_session_id());
+                (active_commands, re_session_id)
+            } else {
+                (Vec::new(), None)
+            };
+
             let uptime = self.0.start_instant.elapsed();
             let base = StatusResponse {
                 process_info: Some(self.0.process_info.clone()),
@@ -880,6 +910,8 @@ impl DaemonApi for BuckdServer {
                 uptime: Some(uptime.try_into()?),
                 snapshot,
                 daemon_constraints: Some(daemon_constraints),
+                active_commands,
+                re_session_id,
                 ..Default::default()
             };
             Ok(base)
@@ -914,6 +946,21 @@ impl DaemonApi for BuckdServer {
         .await
     }
 
+    type DiceEqualityCheckStream = ResponseStream;
+    async fn dice_equality_check(
+        &self,
+        req: Request<DiceEqualityCheckRequest>,
+    ) -> Result<Response<ResponseStream>, Status> {
+        self.run_streaming(
+            req,
+            DefaultCommandOptions,
+            |context, partial_result_dispatcher, req| {
+                dice_equality_check_command(context, partial_result_dispatcher, req).boxed()
+            },
+        )
+        .await
+    }
+
     type BuildStream = ResponseStream;
     async fn build(&self, req: Request<BuildRequest>) -> Result<Response<ResponseStream>, Status> {
         let callbacks = self.0.callbacks;
@@ -1146,6 +1193,30 @@ impl DaemonApi for BuckdServer {
         }
     }
 
+    async fn unstable_recent_events(
+        &self,
+        _req: Request<UnstableRecentEventsRequest>,
+    ) -> Result<Response<UnstableRecentEventsResponse>, Status> {
+        self.check_if_accepting_requests()?;
+
+        let res: anyhow::Result<_> = try {
+            let data = self.0.daemon_state.data()?;
+            let retained_events = data.retained_events.as_ref().context(
+                "The daemon is not retaining events (`buck2.retained_event_log_size` is not set)",
+            )?;
+            UnstableRecentEventsResponse {
+                events: retained_events
+                    .events()
+                    .into_iter()
+                    .map(|e| *Box::<buck2_data::BuckEvent>::from(e))
+                    .collect(),
+            }
+        };
+
+        res.map(Response::new)
+            .map_err(|e| Status::failed_precondition(format!("{:#}", e)))
+    }
+
     async fn unstable_dice_dump(
         &self,
         req: Request<UnstableDiceDumpRequest>,
@@ -1388,6 +1459,23 @@ impl DaemonApi for BuckdServer {
         Ok(Response::new(SetLogFilterResponse {}))
     }
 
+    async fn kill_local_action(
+        &self,
+        req: Request<KillLocalActionRequest>,
+    ) -> Result<Response<KillLocalActionResponse>, Status> {
+        let req = req.into_inner();
+
+        // Nothing can be running if the daemon state was never initialized.
+        let killed = match self.0.daemon_state.data() {
+            Ok(data) => data.running_local_actions.kill(&req.action_digest),
+            Err(_) => 0,
+        };
+
+        Ok(Response::new(KillLocalActionResponse {
+            killed: killed as u64,
+        }))
+    }
+
     type TraceIoStream = ResponseStream;
     async fn trace_io(
         &self,
@@ -28,11 +28,13 @@ use buck2_common::result::ToSharedResultExt;
 use buck2_core::cells::name::CellName;
 use buck2_core::env_helper::EnvHelper;
 use buck2_core::facebook_only;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::fs::project::ProjectRoot;
 use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use buck2_core::rollout_percentage::RolloutPercentage;
 use buck2_core::tag_result;
 use buck2_events::dispatch::EventDispatcher;
+use buck2_events::sink::ring_buffer::RingBufferSink;
 use buck2_events::sink::scribe;
 use buck2_events::sink::tee::TeeSink;
 use buck2_events::EventSink;
@@ -43,6 +45,7 @@ use buck2_execute::execute::blocking::BuckBlockingExecutor;
 use buck2_execute::materialize::materializer::MaterializationMethod;
 use buck2_execute::materialize::materializer::Materializer;
 use buck2_execute::re::manager::ReConnectionManager;
+use buck2_execute_impl::local_disk_cache::LocalDiskCache;
 use buck2_execute_impl::materializers::deferred::DeferredMaterializer;
 use buck2_execute_impl::materializers::deferred::DeferredMaterializerConfigs;
 use buck2_execute_impl::materializers::deferred::TtlRefreshConfiguration;
@@ -50,7 +53,9 @@ use buck2_execute_impl::materializers::immediate::ImmediateMaterializer;
 use buck2_execute_impl::materializers::sqlite::MaterializerState;
 use buck2_execute_impl::materializers::sqlite::MaterializerStateIdentity;
 use buck2_execute_impl::materializers::sqlite::MaterializerStateSqliteDb;
+use buck2_execute_impl::running_local_actions::RunningLocalActions;
 use buck2_forkserver::client::ForkserverClient;
+use buck2_interpreter_for_build::interpreter::load_digests::LoadDigests;
 use buck2_re_configuration::RemoteExecutionStaticMetadata;
 use buck2_re_configuration::RemoteExecutionStaticMetadataImpl;
 use buck2_server_ctx::concurrency::ConcurrencyHandler;
@@ -75,6 +80,9 @@ use crate::daemon::panic::DaemonStatePanicDiceDump;
 use crate::daemon::server::BuckdServerInitPreferences;
 use crate::file_watcher::FileWatcher;
 
+/// Default size limit of the local disk cache, when it is enabled.
+const DEFAULT_LOCAL_CACHE_MAX_BYTES: u64 = 10 * 1024 * 1024 * 1024;
+
 /// For a buckd process there is a single DaemonState created at startup and never destroyed.
 #[derive(Allocative)]
 pub struct DaemonState {
@@ -123,6 +131,12 @@ pub struct DaemonStateData {
     #[allocative(skip)]
     pub scribe_sink: Option<Arc<dyn EventSink>>,
 
+    /// The most recent events emitted by any command, retained so they can be recovered with
+    /// `buck2 log last --from-memory` if the client's event log is lost. `None` unless enabled
+    /// with `buck2.retained_event_log_size`, since it takes a lock for every event.
+    #[allocative(skip)]
+    pub retained_events: Option<RingBufferSink>,
+
     /// Whether or not to hash all commands
     pub hash_all_commands: bool,
 
@@ -152,6 +166,19 @@ pub struct DaemonStateData {
     /// Whether to enable the restarter. This controls whether the client will attempt to restart
     /// the daemon when we hit an error.
     pub enable_restarter: bool,
+
+    /// Cache of local action results which lives outside of buck-out, configured with
+    /// `buck2.local_cache_dir`.
+    #[allocative(skip)]
+    pub local_disk_cache: Option<Arc<LocalDiskCache>>,
+
+    /// Local actions executing on behalf of any command, so that the user can kill one of them.
+    #[allocative(skip)]
+    pub running_local_actions: Arc<RunningLocalActions>,
+
+    /// Digests of the `.bzl` files loaded by the commands of this daemon.
+    #[allocative(skip)]
+    pub load_digests: Arc<LoadDigests>,
 }
 
 impl DaemonStateData {
@@ -418,6 +445,14 @@ impl DaemonState {
         )
         .context("failed to init scribe sink")?;
 
+        let retained_events = match root_config
+            .parse::<usize>("buck2", "retained_event_log_size")?
+            .unwrap_or_default()
+        {
+            0 => None,
+            size => Some(RingBufferSink::new(size)),
+        };
+
         let critical_path_backend = root_config
             .parse("buck2", "critical_path_backend2")?
             .unwrap_or(CriticalPathBackendName::Default);
@@ -427,6 +462,18 @@ impl DaemonState {
             .unwrap_or_else(RolloutPercentage::never)
             .roll();
 
+        let local_disk_cache = match root_config.get("buck2", "local_cache_dir") {
+            Some(dir) => {
+                let dir = AbsNormPathBuf::try_from(dir.to_owned())
+                    .context("`buck2.local_cache_dir` must be an absolute path")?;
+                let max_bytes = root_config
+                    .parse("buck2", "local_cache_max_bytes")?
+                    .unwrap_or(DEFAULT_LOCAL_CACHE_MAX_BYTES);
+                Some(Arc::new(LocalDiskCache::new(&dir, max_bytes)?))
+            }
+            None => None,
+        };
+
         // Kick off an initial sync eagerly. This gets Watchamn to start watching the path we care
         // about (potentially kicking off an initial crawl).
 
@@ -446,6 +493,7 @@ impl DaemonState {
             materializer,
             forkserver,
             scribe_sink,
+            retained_events,
             hash_all_commands,
             use_network_action_output_cache,
             disk_state_options,
@@ -454,6 +502,9 @@ impl DaemonState {
             critical_path_backend,
             materializer_state_identity,
             enable_restarter,
+            local_disk_cache,
+            running_local_actions: Arc::new(RunningLocalActions::new()),
+            load_digests: Arc::new(LoadDigests::default()),
         }))
     }
 
@@ -551,7 +602,8 @@ impl DaemonState {
     }
 
     /// Prepares an event stream for a request by bootstrapping an event source and EventDispatcher pair. The given
-    /// EventDispatcher will log to the returned EventSource and (optionally) to Scribe if enabled via buckconfig.
+    /// EventDispatcher will log to the returned EventSource, to the daemon's retained event log, and (optionally) to
+    /// Scribe if enabled via buckconfig.
     pub async fn prepare_events(
         &self,
         trace_id: TraceId,
@@ -560,12 +612,14 @@ impl DaemonState {
         facebook_only();
         let (events, sink) = buck2_events::create_source_sink_pair();
         let data = self.data()?;
-        let dispatcher = if let Some(scribe_sink) = data.scribe_sink.dupe() {
-            EventDispatcher::new(trace_id, TeeSink::new(scribe_sink, sink))
-        } else {
-            EventDispatcher::new(trace_id, sink)
-        };
-        Ok((events, dispatcher))
+        let mut sink: Arc<dyn EventSink> = Arc::new(sink);
+        if let Some(retained_events) = data.retained_events.dupe() {
+            sink = Arc::new(TeeSink::new(retained_events, sink));
+        }
+        if let Some(scribe_sink) = data.scribe_sink.dupe() {
+            sink = Arc::new(TeeSink::new(scribe_sink, sink));
+        }
+        Ok((events, EventDispatcher::new(trace_id, sink)))
     }
 
     /// Prepares a ServerCommandContext for processing a complex command (that accesses the dice computation graph, for example).
@@ -639,6 +693,9 @@ impl DaemonState {
             _drop_guard: drop_guard,
             daemon_start_time: data.start_time,
             create_unhashed_outputs_lock: data.create_unhashed_outputs_lock.dupe(),
+            local_disk_cache: data.local_disk_cache.dupe(),
+            running_local_actions: data.running_local_actions.dupe(),
+            load_digests: data.load_digests.dupe(),
         })
     }
 
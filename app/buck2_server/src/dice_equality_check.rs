@@ -0,0 +1,500 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Recompute a DICE key outside of the cache and compare the result with the cached value.
+//!
+//! DICE only avoids invalidating the dependents of a recomputed key if the new value is equal
+//! (per `Key::equality`) to the old one. Values which are constructed non-deterministically, or
+//! keys whose equality is stricter than it needs to be, cause spurious invalidation, which shows
+//! up as unexpectedly slow incremental builds.
+
+use std::fmt::Debug;
+use std::io::Write;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use buck2_build_api::calculation::Calculation;
+use buck2_build_api::configuration::calculation::ExecutionPlatformsKey;
+use buck2_build_api::nodes::calculation::ConfiguredTargetNodeKey;
+use buck2_cli_proto::dice_equality_check_request::DiceKeyKind;
+use buck2_cli_proto::HasClientContext;
+use buck2_core::package::PackageLabel;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_core::pattern::ParsedPattern;
+use buck2_interpreter_for_build::interpreter::calculation::keys::BuildFileEvalKey;
+use buck2_query::query::compatibility::MaybeCompatible;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::pattern::target_platform_from_client_context;
+use buck2_server_ctx::pattern::PatternParser;
+use buck2_server_ctx::template::run_server_command;
+use buck2_server_ctx::template::ServerCommandTemplate;
+use dice::DiceComputations;
+use dice::DiceTransaction;
+use dice::Key;
+use more_futures::cancellation::CancellationContext;
+
+use crate::ctx::ServerCommandContext;
+
+/// Above this many pairs of lines, we don't look for a minimal diff of the differing lines of the
+/// two values, and print them all as changed instead.
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+#[derive(Debug, thiserror::Error)]
+enum DiceEqualityCheckError {
+    #[error("Unknown kind of DICE key: {0}")]
+    UnknownKind(i32),
+    #[error("Checking `{0}` keys requires a target or package argument")]
+    MissingArgument(&'static str),
+    #[error("Expected a package (e.g. `//foo:`) or a target, got `{0}`")]
+    NotAPackage(String),
+}
+
+pub(crate) async fn dice_equality_check_command(
+    ctx: &ServerCommandContext<'_>,
+    partial_result_dispatcher: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+    req: buck2_cli_proto::DiceEqualityCheckRequest,
+) -> anyhow::Result<buck2_cli_proto::GenericResponse> {
+    run_server_command(
+        DiceEqualityCheckServerCommand { req },
+        ctx,
+        partial_result_dispatcher,
+    )
+    .await
+}
+
+struct DiceEqualityCheckServerCommand {
+    req: buck2_cli_proto::DiceEqualityCheckRequest,
+}
+
+impl DiceEqualityCheckServerCommand {
+    fn argument(&self, kind: &'static str) -> anyhow::Result<&str> {
+        if self.req.argument.is_empty() {
+            return Err(DiceEqualityCheckError::MissingArgument(kind).into());
+        }
+        Ok(&self.req.argument)
+    }
+
+    async fn package(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        ctx: &DiceTransaction,
+    ) -> anyhow::Result<PackageLabel> {
+        let argument = self.argument("interpreter-results")?;
+        let parser = PatternParser::new(ctx, server_ctx.working_dir()).await?;
+        match parser.parse_pattern::<TargetPatternExtra>(argument)? {
+            ParsedPattern::Package(package) | ParsedPattern::Target(package, _, _) => Ok(package),
+            ParsedPattern::Recursive(_) => {
+                Err(DiceEqualityCheckError::NotAPackage(argument.to_owned()).into())
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl ServerCommandTemplate for DiceEqualityCheckServerCommand {
+    type StartEvent = buck2_data::DiceEqualityCheckCommandStart;
+    type EndEvent = buck2_data::DiceEqualityCheckCommandEnd;
+    type Response = buck2_cli_proto::GenericResponse;
+    type PartialResult = buck2_cli_proto::StdoutBytes;
+
+    async fn command(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        mut partial_result_dispatcher: PartialResultDispatcher<Self::PartialResult>,
+        ctx: DiceTransaction,
+    ) -> anyhow::Result<Self::Response> {
+        let kind = DiceKeyKind::from_i32(self.req.kind)
+            .ok_or(DiceEqualityCheckError::UnknownKind(self.req.kind))?;
+        let mut stdout = partial_result_dispatcher.as_writer();
+
+        match kind {
+            DiceKeyKind::ConfiguredTargetNode => {
+                let argument = self.argument("configured-target-node")?;
+                let label = PatternParser::new(&ctx, server_ctx.working_dir())
+                    .await?
+                    .parse_pattern::<TargetPatternExtra>(argument)?
+                    .as_target_label(argument)?;
+                let target_platform = target_platform_from_client_context(
+                    self.req.client_context()?,
+                    server_ctx,
+                    &ctx,
+                )
+                .await?;
+                let label = ctx
+                    .get_configured_target(&label, target_platform.as_ref())
+                    .await?;
+                check_key(
+                    &ctx,
+                    ConfiguredTargetNodeKey(label),
+                    |x, y| match (x, y) {
+                        (
+                            Ok(MaybeCompatible::Compatible(x)),
+                            Ok(MaybeCompatible::Compatible(y)),
+                        ) => x.ptr_eq(y),
+                        _ => false,
+                    },
+                    &mut stdout,
+                )
+                .await?;
+            }
+            DiceKeyKind::InterpreterResults => {
+                // `InterpreterResultsKey` only reads the cached evaluation, so check the key which
+                // evaluates the build file, whose equality compares the digests of its inputs.
+                let package = self.package(server_ctx, &ctx).await?;
+                check_key(
+                    &ctx,
+                    BuildFileEvalKey(package),
+                    |x, y| match (x, y) {
+                        (Ok((x, _)), Ok((y, _))) => Arc::ptr_eq(x, y),
+                        _ => false,
+                    },
+                    &mut stdout,
+                )
+                .await?;
+            }
+            DiceKeyKind::ExecutionPlatforms => {
+                check_key(
+                    &ctx,
+                    ExecutionPlatformsKey,
+                    |x, y| match (x, y) {
+                        (Ok(Some(x)), Ok(Some(y))) => Arc::ptr_eq(x, y),
+                        _ => false,
+                    },
+                    &mut stdout,
+                )
+                .await?;
+            }
+        }
+
+        Ok(buck2_cli_proto::GenericResponse {})
+    }
+
+    fn is_success(&self, _response: &Self::Response) -> bool {
+        // No response if we failed.
+        true
+    }
+}
+
+/// Compare the cached value of `key` with a value computed afresh from the same dependencies.
+async fn check_key<K>(
+    ctx: &DiceComputations,
+    key: K,
+    ptr_eq: impl Fn(&K::Value, &K::Value) -> bool,
+    mut stdout: impl Write,
+) -> anyhow::Result<()>
+where
+    K: Key,
+    K::Value: Debug,
+{
+    let cached = ctx.compute(&key).await?;
+    let recomputed = key
+        .compute(ctx, CancellationContext::never_cancelled())
+        .await;
+
+    let cached_debug = format!("{:#?}", cached);
+    let recomputed_debug = format!("{:#?}", recomputed);
+    let key_equal = K::equality(&cached, &recomputed);
+    let debug_equal = cached_debug == recomputed_debug;
+
+    writeln!(stdout, "Key: {} ({})", key, K::key_type_name())?;
+    writeln!(stdout, "Pointer equal: {}", ptr_eq(&cached, &recomputed))?;
+    writeln!(stdout, "Value equal (`Key::equality`): {}", key_equal)?;
+    writeln!(stdout, "Debug representation equal: {}", debug_equal)?;
+
+    if key_equal {
+        writeln!(
+            stdout,
+            "OK: recomputing this key produces an equal value, so its dependents are not invalidated."
+        )?;
+    } else if debug_equal {
+        writeln!(
+            stdout,
+            "SPURIOUS INVALIDATION: the values look identical, but `Key::equality` considers them different, so recomputing this key invalidates all its dependents. The key's equality is likely based on identity, or not implemented."
+        )?;
+    } else {
+        writeln!(
+            stdout,
+            "NON-DETERMINISTIC: recomputing this key from the same dependencies produced a different value. The value is likely constructed non-deterministically (e.g. from hash map iteration order or the current time). Differences (`-` cached, `+` recomputed):"
+        )?;
+        write_diff(&mut stdout, &cached_debug, &recomputed_debug)?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
+enum DiffLine<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Diff two multi-line strings line by line.
+fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<DiffLine<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    let prefix = old.iter().zip(&new).take_while(|(x, y)| x == y).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    let mut diff: Vec<DiffLine> = old[..prefix].iter().map(|l| DiffLine::Same(l)).collect();
+
+    if old_mid.len().saturating_mul(new_mid.len()) > MAX_DIFF_CELLS {
+        diff.extend(old_mid.iter().map(|l| DiffLine::Removed(l)));
+        diff.extend(new_mid.iter().map(|l| DiffLine::Added(l)));
+    } else {
+        // `lcs[i][j]` is the length of the longest common subsequence of `old_mid[i..]` and
+        // `new_mid[j..]`.
+        let mut lcs = vec![vec![0u32; new_mid.len() + 1]; old_mid.len() + 1];
+        for i in (0..old_mid.len()).rev() {
+            for j in (0..new_mid.len()).rev() {
+                lcs[i][j] = if old_mid[i] == new_mid[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < old_mid.len() || j < new_mid.len() {
+            if i < old_mid.len() && j < new_mid.len() && old_mid[i] == new_mid[j] {
+                diff.push(DiffLine::Same(old_mid[i]));
+                i += 1;
+                j += 1;
+            } else if j == new_mid.len() || (i < old_mid.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+                diff.push(DiffLine::Removed(old_mid[i]));
+                i += 1;
+            } else {
+                diff.push(DiffLine::Added(new_mid[j]));
+                j += 1;
+            }
+        }
+    }
+
+    diff.extend(old[old.len() - suffix..].iter().map(|l| DiffLine::Same(l)));
+    diff
+}
+
+/// Tracks which field of a `{:#?}` representation a line belongs to, based on the lines before it.
+#[derive(Default)]
+struct FieldPath<'a> {
+    /// The indentation of the line opening each enclosing field, and the field's name.
+    scopes: Vec<(usize, &'a str)>,
+}
+
+impl<'a> FieldPath<'a> {
+    fn push_line(&mut self, line: &'a str) {
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+        while self.scopes.last().map_or(false, |(i, _)| *i >= indent) {
+            self.scopes.pop();
+        }
+        if let Some(opening) = trimmed
+            .strip_suffix('{')
+            .or_else(|| trimmed.strip_suffix('['))
+            .or_else(|| trimmed.strip_suffix('('))
+        {
+            let name = match opening.split_once(": ") {
+                Some((field, _)) => field,
+                None => opening.trim_end(),
+            };
+            self.scopes.push((indent, name));
+        }
+    }
+
+    fn display(&self) -> String {
+        self.scopes
+            .iter()
+            .map(|(_, name)| *name)
+            .collect::<Vec<_>>()
+            .join(" > ")
+    }
+}
+
+/// Print the lines which differ between two `{:#?}` representations, each group of changed lines
+/// preceded by the path to the field which contains them.
+fn write_diff(mut w: impl Write, old: &str, new: &str) -> anyhow::Result<()> {
+    let mut path = FieldPath::default();
+    let mut in_hunk = false;
+    for line in diff_lines(old, new) {
+        let (sign, line) = match line {
+            DiffLine::Same(line) => {
+                path.push_line(line);
+                in_hunk = false;
+                continue;
+            }
+            DiffLine::Removed(line) => ('-', line),
+            DiffLine::Added(line) => ('+', line),
+        };
+        if !in_hunk {
+            writeln!(w, "@@ {} @@", path.display())?;
+            in_hunk = true;
+        }
+        writeln!(w, "{}{}", sign, line)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::Hash;
+    use std::hash::Hasher;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+
+    use allocative::Allocative;
+    use async_trait::async_trait;
+    use derive_more::Display;
+    use dice::DetectCycles;
+    use dice::Dice;
+    use dice::DiceComputations;
+    use dice::Key;
+    use dupe::Dupe;
+    use more_futures::cancellation::CancellationContext;
+
+    use crate::dice_equality_check::check_key;
+    use crate::dice_equality_check::diff_lines;
+    use crate::dice_equality_check::write_diff;
+    use crate::dice_equality_check::DiffLine;
+
+    #[derive(Debug)]
+    struct Inner {
+        a: u32,
+        b: Vec<u32>,
+    }
+
+    #[derive(Debug)]
+    struct Outer {
+        name: &'static str,
+        inner: Inner,
+    }
+
+    /// Counts its computations. The value is the count if `DETERMINISTIC` is false.
+    #[derive(Clone, Dupe, Debug, Display, Allocative)]
+    #[display(fmt = "Counter")]
+    struct Counter<const DETERMINISTIC: bool, const EQUAL: bool>(Arc<AtomicUsize>);
+
+    impl<const DETERMINISTIC: bool, const EQUAL: bool> PartialEq for Counter<DETERMINISTIC, EQUAL> {
+        fn eq(&self, _other: &Self) -> bool {
+            true
+        }
+    }
+
+    impl<const DETERMINISTIC: bool, const EQUAL: bool> Eq for Counter<DETERMINISTIC, EQUAL> {}
+
+    impl<const DETERMINISTIC: bool, const EQUAL: bool> Hash for Counter<DETERMINISTIC, EQUAL> {
+        fn hash<H: Hasher>(&self, _state: &mut H) {}
+    }
+
+    #[async_trait]
+    impl<const DETERMINISTIC: bool, const EQUAL: bool> Key for Counter<DETERMINISTIC, EQUAL> {
+        type Value = usize;
+
+        async fn compute(
+            &self,
+            _ctx: &DiceComputations,
+            _cancellations: &CancellationContext,
+        ) -> Self::Value {
+            let count = self.0.fetch_add(1, Ordering::SeqCst);
+            if DETERMINISTIC { 0 } else { count }
+        }
+
+        fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+            EQUAL && x == y
+        }
+    }
+
+    async fn check<const DETERMINISTIC: bool, const EQUAL: bool>() -> String {
+        let dice = Dice::builder().build(DetectCycles::Enabled);
+        let ctx = dice.updater().commit().await;
+        let counter = Arc::new(AtomicUsize::new(0));
+        let mut out = Vec::new();
+        check_key(
+            &ctx,
+            Counter::<DETERMINISTIC, EQUAL>(counter.dupe()),
+            |_, _| false,
+            &mut out,
+        )
+        .await
+        .unwrap();
+        // The key was recomputed outside of the cache.
+        assert_eq!(2, counter.load(Ordering::SeqCst));
+        String::from_utf8(out).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_check_key() {
+        let out = check::<true, true>().await;
+        assert!(
+            out.contains("Value equal (`Key::equality`): true"),
+            "{}",
+            out
+        );
+        assert!(out.contains("\nOK: "), "{}", out);
+
+        let out = check::<true, false>().await;
+        assert!(out.contains("Debug representation equal: true"), "{}", out);
+        assert!(out.contains("\nSPURIOUS INVALIDATION: "), "{}", out);
+
+        let out = check::<false, true>().await;
+        assert!(out.contains("Debug representation equal: false"), "{}", out);
+        assert!(out.contains("\nNON-DETERMINISTIC: "), "{}", out);
+        assert!(out.contains("\n-0\n+1\n"), "{}", out);
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        assert_eq!(
+            diff_lines("a\nb\nc\nd", "a\nc\nx\nd"),
+            vec![
+                DiffLine::Same("a"),
+                DiffLine::Removed("b"),
+                DiffLine::Same("c"),
+                DiffLine::Added("x"),
+                DiffLine::Same("d"),
+            ]
+        );
+        assert_eq!(
+            diff_lines("a\nb", "a\nb"),
+            vec![DiffLine::Same("a"), DiffLine::Same("b")]
+        );
+    }
+
+    #[test]
+    fn test_write_diff() {
+        let old = Outer {
+            name: "x",
+            inner: Inner {
+                a: 1,
+                b: vec![1, 2],
+            },
+        };
+        let new = Outer {
+            name: "x",
+            inner: Inner {
+                a: 2,
+                b: vec![1, 2],
+            },
+        };
+        let mut out = Vec::new();
+        write_diff(&mut out, &format!("{:#?}", old), &format!("{:#?}", new)).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "@@ Outer > inner @@\n-        a: 1,\n+        a: 2,\n"
+        );
+    }
+}
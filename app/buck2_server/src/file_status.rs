@@ -15,6 +15,7 @@ use anyhow::Context;
 use async_recursion::async_recursion;
 use async_trait::async_trait;
 use buck2_common::dice::cells::HasCellResolver;
+use buck2_common::dice::file_ops::FileChangeTracker;
 use buck2_common::dice::file_ops::HasFileOps;
 use buck2_common::file_ops::FileDigest;
 use buck2_common::file_ops::FileDigestConfig;
@@ -22,6 +23,7 @@ use buck2_common::file_ops::FileOps;
 use buck2_common::file_ops::RawPathMetadata;
 use buck2_common::file_ops::RawSymlink;
 use buck2_common::file_ops::SimpleDirEntry;
+use buck2_core::cells::cell_path::CellPath;
 use buck2_core::cells::CellResolver;
 use buck2_core::fs::fs_util;
 use buck2_core::fs::paths::abs_path::AbsPath;
@@ -63,6 +65,8 @@ struct FileStatusResult<'a> {
     checked: usize,
     /// Number of ones that were bad
     bad: usize,
+    /// The dice state to invalidate for the bad ones
+    stale: FileChangeTracker,
     /// Handle for writing output
     stderr: StderrOutputGuard<'a>,
 }
@@ -72,9 +76,13 @@ impl FileStatusResult<'_> {
         self.checked += 1;
     }
 
-    fn mismatch(&mut self, err: Mismatch) -> anyhow::Result<()> {
+    fn mismatch(&mut self, cell_path: &CellPath, err: Mismatch) -> anyhow::Result<()> {
         writeln!(self.stderr, "MISMATCH: {}", err)?;
         self.bad += 1;
+        // We don't know which of the file or directory state is wrong (e.g. if the type changed),
+        // so invalidate everything that could be derived from this path.
+        self.stale.file_added_or_removed(cell_path.clone());
+        self.stale.dir_added_or_removed(cell_path.clone());
         Ok(())
     }
 }
@@ -92,30 +100,43 @@ impl ServerCommandTemplate for FileStatusServerCommand {
         _partial_result_dispatcher: PartialResultDispatcher<Self::PartialResult>,
         ctx: DiceTransaction,
     ) -> anyhow::Result<Self::Response> {
-        let file_ops = ctx.file_ops();
         let cell_resolver = ctx.get_cell_resolver().await?;
         let project_root = server_ctx.project_root();
         let digest_config = ctx.global_data().get_digest_config();
         let mut result = FileStatusResult {
             checked: 0,
             bad: 0,
+            stale: FileChangeTracker::new(),
             stderr: server_ctx.stderr()?,
         };
 
-        for path in &self.req.paths {
-            let path = project_root.relativize_any(AbsPath::new(Path::new(path))?)?;
-            writeln!(result.stderr, "Check file status: {}", path)?;
-            check_file_status(
-                &file_ops,
-                &cell_resolver,
-                project_root,
-                digest_config,
-                &path,
-                &mut result,
-            )
-            .await?;
+        {
+            let file_ops = ctx.file_ops();
+            for path in &self.req.paths {
+                let path = project_root.relativize_any(AbsPath::new(Path::new(path))?)?;
+                writeln!(result.stderr, "Check file status: {}", path)?;
+                check_file_status(
+                    &file_ops,
+                    &cell_resolver,
+                    project_root,
+                    digest_config,
+                    &path,
+                    &mut result,
+                )
+                .await?;
+            }
         }
-        if result.bad != 0 {
+        if result.bad != 0 && self.req.fix {
+            let mut updater = ctx.into_updater();
+            result.stale.write_to_dice(&mut updater)?;
+            updater.commit().await;
+            writeln!(
+                result.stderr,
+                "Invalidated {} mismatched entries ({} entries checked)",
+                result.bad, result.checked
+            )?;
+            Ok(buck2_cli_proto::GenericResponse {})
+        } else if result.bad != 0 {
             Err(anyhow::anyhow!("Failed with {} mismatches", result.bad))
         } else {
             writeln!(
@@ -180,7 +201,7 @@ async fn check_file_status(
         (Some(fs_metadata), Some(dice_metadata)) => match dice_metadata {
             RawPathMetadata::Symlink { at: _, to: dice_to } => {
                 if !fs_metadata.is_symlink() {
-                    result.mismatch(Mismatch::FileType(path.to_owned(), "symlink"))?;
+                    result.mismatch(&cell_path, Mismatch::FileType(path.to_owned(), "symlink"))?;
                 } else {
                     // Canonicalize isn't quite right here, but it's close enough
                     // given we encourage to have very few symlinks.
@@ -195,45 +216,55 @@ async fn check_file_status(
                             RawSymlink::External(x) => x.to_path_buf(),
                         };
                         if fs_to.as_path() != dice_to.as_path() {
-                            result.mismatch(Mismatch::SymlinkTarget(
-                                path.to_owned(),
-                                fs_to.into_path_buf(),
-                                dice_to,
-                            ))?;
+                            result.mismatch(
+                                &cell_path,
+                                Mismatch::SymlinkTarget(
+                                    path.to_owned(),
+                                    fs_to.into_path_buf(),
+                                    dice_to,
+                                ),
+                            )?;
                         }
                     }
                 }
             }
             RawPathMetadata::File(dice_metadata) => {
                 if !fs_metadata.is_file() {
-                    result.mismatch(Mismatch::FileType(path.to_owned(), "file"))?;
+                    result.mismatch(&cell_path, Mismatch::FileType(path.to_owned(), "file"))?;
                 } else {
                     // We don't check is_executable as there are multiple definitions of this,
                     // and it usually isn't too important.
                     if fs_metadata.len() != dice_metadata.digest.size() {
-                        result.mismatch(Mismatch::FileSize(
-                            path.to_owned(),
-                            fs_metadata.len(),
-                            dice_metadata.digest.size(),
-                        ))?;
+                        result.mismatch(
+                            &cell_path,
+                            Mismatch::FileSize(
+                                path.to_owned(),
+                                fs_metadata.len(),
+                                dice_metadata.digest.size(),
+                            ),
+                        )?;
                     } else {
                         let fs_digest = FileDigest::from_file_disk(
                             &abs_path,
                             FileDigestConfig::source(digest_config.cas_digest_config()),
                         )?;
                         if &fs_digest != dice_metadata.digest.data() {
-                            result.mismatch(Mismatch::FileDigest(
-                                path.to_owned(),
-                                fs_digest,
-                                dice_metadata.digest.data().to_owned(),
-                            ))?;
+                            result.mismatch(
+                                &cell_path,
+                                Mismatch::FileDigest(
+                                    path.to_owned(),
+                                    fs_digest,
+                                    dice_metadata.digest.data().to_owned(),
+                                ),
+                            )?;
                         }
                     }
                 }
             }
             RawPathMetadata::Directory => {
                 if !fs_metadata.is_dir() {
-                    result.mismatch(Mismatch::FileType(path.to_owned(), "directory"))?;
+                    result
+                        .mismatch(&cell_path, Mismatch::FileType(path.to_owned(), "directory"))?;
                 } else {
                     let mut fs_list: Vec<String> = Vec::new();
                     for entry in fs_util::read_dir(&abs_path)? {
@@ -248,11 +279,10 @@ async fn check_file_status(
                     fs_list.sort();
                     dice_list.sort();
                     if fs_list != dice_list {
-                        result.mismatch(Mismatch::DirContents(
-                            path.to_owned(),
-                            fs_list,
-                            dice_list,
-                        ))?;
+                        result.mismatch(
+                            &cell_path,
+                            Mismatch::DirContents(path.to_owned(), fs_list, dice_list),
+                        )?;
                     } else {
                         for file in &*dice_read_dir.included {
                             let mut path = path.to_owned();
@@ -271,11 +301,14 @@ async fn check_file_status(
                 }
             }
         },
-        _ => result.mismatch(Mismatch::Existence(
-            path.to_owned(),
-            fs_metadata.is_some(),
-            dice_metadata.is_some(),
-        ))?,
+        _ => result.mismatch(
+            &cell_path,
+            Mismatch::Existence(
+                path.to_owned(),
+                fs_metadata.is_some(),
+                dice_metadata.is_some(),
+            ),
+        )?,
     }
     Ok(())
 }
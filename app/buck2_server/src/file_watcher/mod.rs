@@ -50,7 +50,7 @@ impl dyn FileWatcher {
 
         match root_config.get("buck2", "file_watcher").unwrap_or(default) {
             "watchman" => Ok(Arc::new(
-                WatchmanFileWatcher::new(project_root.root(), root_config, cells, ignore_specs)
+                WatchmanFileWatcher::new(project_root, root_config, cells, ignore_specs)
                     .context("Creating watchman file watcher")?,
             )),
             "notify" => Ok(Arc::new(
@@ -203,7 +203,7 @@ impl NotifyFileWatcher {
         Ok(Self { watcher, data })
     }
 
-    fn sync2(
+    pub(crate) fn sync2(
         &self,
         mut dice: DiceTransactionUpdater,
     ) -> anyhow::Result<(buck2_data::FileWatcherStats, DiceTransactionUpdater)> {
@@ -13,6 +13,7 @@ use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
+use std::time::Instant;
 
 use anyhow::Context as _;
 use async_trait::async_trait;
@@ -183,8 +184,23 @@ pub trait SyncableQueryProcessor: Send + Sync {
         mergebase: &Option<String>,
         watchman_version: Option<String>,
     ) -> anyhow::Result<(Self::Output, Self::Payload)>;
+
+    /// Indicates that watchman could not be queried, even after reconnecting. The processor can
+    /// fail the sync with `error`, or track changes some other way. If it does the latter, watchman
+    /// is not queried again before the retry interval passes, and this is called for every sync in
+    /// the meantime. Once watchman is back, the next query will be a fresh instance, since the
+    /// clock is reset when reconnecting.
+    async fn on_watchman_unavailable(
+        &mut self,
+        dice: Self::Payload,
+        error: anyhow::Error,
+    ) -> anyhow::Result<(Self::Output, Self::Payload)>;
 }
 
+/// How long to wait before querying watchman again once it was found unavailable, so that syncs
+/// don't each pay for a failed reconnect.
+const WATCHMAN_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
 /// commands to be sent to the SyncableQueryHandler.
 enum SyncableQueryCommand<T, P> {
     Sync(P, oneshot::Sender<anyhow::Result<(T, P)>>),
@@ -232,6 +248,10 @@ struct SyncableQueryHandler<T, P> {
     last_mergebase: Option<String>,
     mergebase_with: Option<String>,
     control_rx: UnboundedReceiver<SyncableQueryCommand<T, P>>,
+    retry_interval: Duration,
+    /// When watchman was last found unavailable and the processor handled it: the time until
+    /// which watchman is not queried, and the error it failed with.
+    unavailable: Option<(Instant, String)>,
 }
 
 impl<T, P> SyncableQueryHandler<T, P>
@@ -272,10 +292,28 @@ where
         payload: P,
         client: &mut Option<WatchmanClient>,
     ) -> anyhow::Result<(T, P)> {
+        if let Some((retry_at, error)) = &self.unavailable {
+            if Instant::now() < *retry_at {
+                let error = anyhow::anyhow!("{}", error);
+                return self.processor.on_watchman_unavailable(payload, error).await;
+            }
+        }
+
         let sync_res = match self.sync_query(client).await {
-            Ok(res) => Ok(res),
-            Err(e) => self.reconnect_and_sync_query(client).await.context(e),
-        }?;
+            Ok(res) => res,
+            Err(e) => match self.reconnect_and_sync_query(client).await.context(e) {
+                Ok(res) => res,
+                Err(e) => {
+                    let error = format!("{:#}", e);
+                    let res = self.processor.on_watchman_unavailable(payload, e).await;
+                    if res.is_ok() {
+                        self.unavailable = Some((Instant::now() + self.retry_interval, error));
+                    }
+                    return res;
+                }
+            },
+        };
+        self.unavailable = None;
 
         let (res, new_mergebase, clock) = match sync_res {
             WatchmanSyncResult::Events {
@@ -453,6 +491,24 @@ where
         expr: Expr,
         processor: Box<dyn SyncableQueryProcessor<Output = T, Payload = P>>,
         mergebase_with: Option<String>,
+    ) -> anyhow::Result<SyncableQuery<T, P>> {
+        Self::new_with_retry_interval(
+            connector,
+            path,
+            expr,
+            processor,
+            mergebase_with,
+            WATCHMAN_RETRY_INTERVAL,
+        )
+    }
+
+    pub(crate) fn new_with_retry_interval(
+        connector: Connector,
+        path: impl AsRef<Path>,
+        expr: Expr,
+        processor: Box<dyn SyncableQueryProcessor<Output = T, Payload = P>>,
+        mergebase_with: Option<String>,
+        retry_interval: Duration,
     ) -> anyhow::Result<SyncableQuery<T, P>> {
         let path = path.as_ref();
         let path = CanonicalPath::canonicalize(path)
@@ -486,6 +542,8 @@ where
                 mergebase_with,
                 processor,
                 control_rx,
+                retry_interval,
+                unavailable: None,
             };
             handler.run_loop().await
         });
@@ -18,17 +18,21 @@ use buck2_common::ignores::ignore_set::IgnoreSet;
 use buck2_common::legacy_configs::LegacyBuckConfig;
 use buck2_core::cells::name::CellName;
 use buck2_core::cells::CellResolver;
-use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::project::ProjectRoot;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
 use buck2_core::rollout_percentage::RolloutPercentage;
 use buck2_events::dispatch::span_async;
 use dice::DiceTransactionUpdater;
+use dupe::Dupe;
+use futures::future::BoxFuture;
+use futures::FutureExt;
 use tracing::info;
 use tracing::warn;
 use watchman_client::expr::Expr;
 use watchman_client::prelude::Connector;
 use watchman_client::prelude::FileType;
 
+use crate::file_watcher::notify::NotifyFileWatcher;
 use crate::file_watcher::stats::FileWatcherStats;
 use crate::file_watcher::watchman::core::SyncableQuery;
 use crate::file_watcher::watchman::core::SyncableQueryProcessor;
@@ -42,6 +46,58 @@ struct WatchmanQueryProcessor {
     ignore_specs: HashMap<CellName, IgnoreSet>,
     retain_dep_files_on_watchman_fresh_instance: bool,
     last_mergebase: Option<String>,
+    project_root: ProjectRoot,
+    /// Whether to watch files with `notify` when watchman is unavailable, rather than failing.
+    fallback_to_notify: bool,
+    /// Tracks the changes while watchman is unavailable.
+    fallback: Option<NotifyFallback>,
+}
+
+/// The `notify` watcher used while watchman is unavailable.
+enum NotifyFallback<W = NotifyFileWatcher> {
+    /// Watching the whole repo can take a while on large repos, so it is set up in the background.
+    Starting(BoxFuture<'static, anyhow::Result<W>>),
+    Watching(W),
+}
+
+/// How a sync while watchman is unavailable invalidates DICE, depending on the state of the
+/// fallback.
+#[derive(Debug, PartialEq, Eq)]
+enum FallbackSync {
+    /// The fallback was just started: the changes since the last sync are unknown, so everything
+    /// is invalidated, and dep files are flushed.
+    Started,
+    /// The fallback is still being set up. Everything was already invalidated when it was
+    /// started, so it is not invalidated again on every sync.
+    StillStarting,
+    /// The fallback just started watching: the changes made while it was being set up are
+    /// unknown, so everything is invalidated once more.
+    StartedWatching,
+    /// The fallback is watching, so only what changed is invalidated.
+    Watching,
+}
+
+impl<W> NotifyFallback<W> {
+    /// Move the fallback on to its next state on a sync while watchman is unavailable, setting it
+    /// up with `start` if there is none yet.
+    fn advance(
+        fallback: Option<Self>,
+        start: impl FnOnce() -> BoxFuture<'static, anyhow::Result<W>>,
+    ) -> anyhow::Result<(Self, FallbackSync)> {
+        Ok(match fallback {
+            None => (NotifyFallback::Starting(start()), FallbackSync::Started),
+            Some(NotifyFallback::Starting(mut setup)) => match (&mut setup).now_or_never() {
+                None => (NotifyFallback::Starting(setup), FallbackSync::StillStarting),
+                Some(watcher) => (
+                    NotifyFallback::Watching(watcher?),
+                    FallbackSync::StartedWatching,
+                ),
+            },
+            Some(NotifyFallback::Watching(watcher)) => {
+                (NotifyFallback::Watching(watcher), FallbackSync::Watching)
+            }
+        })
+    }
 }
 
 /// Used in process_one_change
@@ -189,6 +245,15 @@ impl WatchmanQueryProcessor {
 
         Ok(())
     }
+
+    /// Stop using the fallback watcher, if watchman was unavailable.
+    fn recover(&mut self) -> bool {
+        let recovered = self.fallback.take().is_some();
+        if recovered {
+            info!("Watchman is available again, no longer watching files with notify");
+        }
+        recovered
+    }
 }
 
 fn find_first_valid_parent(mut path: &Path) -> Option<&ProjectRelativePath> {
@@ -214,6 +279,13 @@ impl SyncableQueryProcessor for WatchmanQueryProcessor {
         mergebase: &Option<String>,
         watchman_version: Option<String>,
     ) -> anyhow::Result<(Self::Output, DiceTransactionUpdater)> {
+        // Changes may have been missed while watchman was unavailable if it was not restarted,
+        // and DICE is not in sync with what it has seen anyway.
+        if self.recover() {
+            return self
+                .on_fresh_instance(dice, mergebase, watchman_version)
+                .await;
+        }
         self.last_mergebase = mergebase.clone();
         self.process_events_impl(dice, events, mergebase, watchman_version)
             .await
@@ -225,6 +297,8 @@ impl SyncableQueryProcessor for WatchmanQueryProcessor {
         mergebase: &Option<String>,
         watchman_version: Option<String>,
     ) -> anyhow::Result<(Self::Output, DiceTransactionUpdater)> {
+        self.recover();
+
         let has_new_mergebase = self.last_mergebase.as_ref() != mergebase.as_ref();
 
         let clear_dep_files =
@@ -268,6 +342,104 @@ impl SyncableQueryProcessor for WatchmanQueryProcessor {
             ctx,
         ))
     }
+
+    async fn on_watchman_unavailable(
+        &mut self,
+        dice: DiceTransactionUpdater,
+        error: anyhow::Error,
+    ) -> anyhow::Result<(Self::Output, DiceTransactionUpdater)> {
+        if !self.fallback_to_notify {
+            return Err(error);
+        }
+
+        let error = format!("{:#}", error);
+        let (fallback, sync) = NotifyFallback::advance(self.fallback.take(), || {
+            let project_root = self.project_root.dupe();
+            let cells = self.cells.dupe();
+            let ignore_specs = self.ignore_specs.clone();
+            tokio::task::spawn_blocking(move || {
+                NotifyFileWatcher::new(&project_root, cells, ignore_specs)
+            })
+            .map(|res| {
+                res.context("Watching files with notify panicked")
+                    .and_then(|watcher| watcher)
+            })
+            .boxed()
+        })
+        .with_context(|| {
+            format!(
+                "Watchman is unavailable ({}), and watching files with notify failed",
+                error
+            )
+        })?;
+
+        let cleared_dep_files = match sync {
+            FallbackSync::Started => {
+                warn!(
+                    "Watchman is unavailable, watching files with notify: {}",
+                    error
+                );
+                // The mergebase may have changed while watchman was unavailable, so the dep files
+                // may be irrelevant: err on the side of dropping them, as on a fresh instance
+                // with a new mergebase.
+                buck2_build_api::actions::impls::dep_files::flush_dep_files();
+                true
+            }
+            FallbackSync::StillStarting => {
+                self.fallback = Some(fallback);
+                return Ok((
+                    buck2_data::FileWatcherStats {
+                        incomplete_events_reason: Some("Watchman unavailable".to_owned()),
+                        watchman_unavailable: Some(buck2_data::WatchmanUnavailable {
+                            error,
+                            cleared_dice: false,
+                        }),
+                        ..Default::default()
+                    },
+                    dice,
+                ));
+            }
+            FallbackSync::StartedWatching => {
+                info!("Watching files with notify while watchman is unavailable");
+                false
+            }
+            FallbackSync::Watching => {
+                let res = match &fallback {
+                    NotifyFallback::Watching(watcher) => watcher.sync2(dice),
+                    NotifyFallback::Starting(_) => unreachable!("the fallback is watching"),
+                };
+                self.fallback = Some(fallback);
+                let (mut stats, dice) = res?;
+                stats.watchman_unavailable = Some(buck2_data::WatchmanUnavailable {
+                    error,
+                    cleared_dice: false,
+                });
+                return Ok((stats, dice));
+            }
+        };
+        self.fallback = Some(fallback);
+
+        // The changes since the last sync are unknown when the fallback starts, and so are the
+        // ones made while it was being set up once it is watching, so invalidate everything, as
+        // on a fresh instance.
+        Ok((
+            buck2_data::FileWatcherStats {
+                fresh_instance: true,
+                incomplete_events_reason: Some("Watchman unavailable".to_owned()),
+                watchman_unavailable: Some(buck2_data::WatchmanUnavailable {
+                    error,
+                    cleared_dice: true,
+                }),
+                fresh_instance_data: Some(buck2_data::FreshInstance {
+                    new_mergebase: false,
+                    cleared_dice: true,
+                    cleared_dep_files,
+                }),
+                ..Default::default()
+            },
+            dice.unstable_take(),
+        ))
+    }
 }
 
 #[derive(Allocative)]
@@ -282,7 +454,7 @@ pub(crate) struct WatchmanFileWatcher {
 /// ensure that any recent changes are flushed and visible to the computation.
 impl WatchmanFileWatcher {
     pub(crate) fn new(
-        project_root: &AbsNormPath,
+        project_root: &ProjectRoot,
         root_config: &LegacyBuckConfig,
         cells: CellResolver,
         ignore_specs: HashMap<CellName, IgnoreSet>,
@@ -296,9 +468,13 @@ impl WatchmanFileWatcher {
             .unwrap_or_else(RolloutPercentage::always)
             .roll();
 
+        let fallback_to_notify = root_config
+            .parse::<bool>("buck2", "watchman_fallback_to_notify")?
+            .unwrap_or(false);
+
         let query = SyncableQuery::new(
             Connector::new(),
-            project_root,
+            project_root.root(),
             Expr::Any(vec![
                 Expr::FileType(FileType::Regular),
                 Expr::FileType(FileType::Directory),
@@ -309,6 +485,9 @@ impl WatchmanFileWatcher {
                 ignore_specs,
                 retain_dep_files_on_watchman_fresh_instance,
                 last_mergebase: None,
+                project_root: project_root.dupe(),
+                fallback_to_notify,
+                fallback: None,
             }),
             watchman_merge_base,
         )?;
@@ -335,3 +514,62 @@ impl FileWatcher for WatchmanFileWatcher {
         .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use futures::future;
+    use futures::FutureExt;
+
+    use crate::file_watcher::watchman::interface::FallbackSync;
+    use crate::file_watcher::watchman::interface::NotifyFallback;
+
+    fn advance(
+        fallback: Option<NotifyFallback<u32>>,
+    ) -> anyhow::Result<(NotifyFallback<u32>, FallbackSync)> {
+        NotifyFallback::advance(fallback, || future::pending().boxed())
+    }
+
+    #[test]
+    fn test_fallback_starts() -> anyhow::Result<()> {
+        let (fallback, sync) = advance(None)?;
+        assert_eq!(sync, FallbackSync::Started);
+        assert_matches!(fallback, NotifyFallback::Starting(_));
+        Ok(())
+    }
+
+    #[test]
+    fn test_fallback_still_starting() -> anyhow::Result<()> {
+        let (mut fallback, sync) = advance(None)?;
+        assert_eq!(sync, FallbackSync::Started);
+        // DICE is only cleared once while the fallback is being set up.
+        for _ in 0..3 {
+            let sync;
+            (fallback, sync) = advance(Some(fallback))?;
+            assert_eq!(sync, FallbackSync::StillStarting);
+            assert_matches!(fallback, NotifyFallback::Starting(_));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_fallback_started_watching() -> anyhow::Result<()> {
+        let (fallback, sync) =
+            advance(Some(NotifyFallback::Starting(future::ready(Ok(1)).boxed())))?;
+        assert_eq!(sync, FallbackSync::StartedWatching);
+        assert_matches!(fallback, NotifyFallback::Watching(1));
+
+        let (fallback, sync) = advance(Some(fallback))?;
+        assert_eq!(sync, FallbackSync::Watching);
+        assert_matches!(fallback, NotifyFallback::Watching(1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_fallback_setup_failed() {
+        let res = advance(Some(NotifyFallback::Starting(
+            future::ready(Err(anyhow::anyhow!("too many files"))).boxed(),
+        )));
+        assert!(res.is_err());
+    }
+}
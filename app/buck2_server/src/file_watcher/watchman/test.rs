@@ -28,12 +28,16 @@ use crate::file_watcher::watchman::core::SyncableQuery;
 use crate::file_watcher::watchman::core::SyncableQueryProcessor;
 use crate::file_watcher::watchman::core::WatchmanEvent;
 
-struct TestQueryProcessor;
+struct TestQueryProcessor {
+    /// Whether to keep going when watchman is unavailable, rather than failing.
+    fallback: bool,
+}
 
 #[derive(PartialEq, Eq, Debug)]
 enum Out {
     FreshInstance,
     Files(Vec<String>),
+    Unavailable,
 }
 
 #[async_trait]
@@ -62,6 +66,18 @@ impl SyncableQueryProcessor for TestQueryProcessor {
     ) -> anyhow::Result<(Self::Output, Self::Payload)> {
         Ok((Out::FreshInstance, payload))
     }
+
+    async fn on_watchman_unavailable(
+        &mut self,
+        payload: Self::Payload,
+        error: anyhow::Error,
+    ) -> anyhow::Result<(Self::Output, Self::Payload)> {
+        if self.fallback {
+            Ok((Out::Unavailable, payload))
+        } else {
+            Err(error)
+        }
+    }
 }
 
 async fn wait_for_watchman(watchman_sock: &Path) -> anyhow::Result<()> {
@@ -193,7 +209,7 @@ async fn test_syncable_query() -> anyhow::Result<()> {
         connector,
         &root,
         Expr::Any(vec![Expr::FileType(FileType::Regular)]),
-        Box::new(TestQueryProcessor),
+        Box::new(TestQueryProcessor { fallback: false }),
         None,
     )?;
 
@@ -223,3 +239,59 @@ async fn test_syncable_query() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_syncable_query_fallback() -> anyhow::Result<()> {
+    // This test doesn't work unless Watchman is working, so let's
+    // over-approximate that as fbcode_build for now.
+    if !cfg!(fbcode_build) {
+        return Ok(());
+    }
+
+    let tempdir = tempfile::tempdir()?;
+
+    let root = tempdir.path().join("root");
+    let watchman_dir = tempdir.path().join("watchman");
+    fs::create_dir(&watchman_dir)?;
+    fs::create_dir(&root)?;
+
+    let mut watchman_instance = spawn_watchman(&watchman_dir).await?;
+
+    let connector = Connector::default().unix_domain_socket(&watchman_instance.sock);
+
+    let retry_interval = Duration::from_secs(2);
+    let watchman_query = SyncableQuery::new_with_retry_interval(
+        connector,
+        &root,
+        Expr::Any(vec![Expr::FileType(FileType::Regular)]),
+        Box::new(TestQueryProcessor { fallback: true }),
+        None,
+        retry_interval,
+    )?;
+
+    assert_eq!(watchman_query.sync(()).await?.0, Out::FreshInstance);
+    assert_eq!(watchman_query.sync(()).await?.0, Out::Files(vec![]));
+
+    // Kill Watchman, the processor is told about it instead of the sync failing.
+    watchman_instance.shutdown().await?;
+    assert_eq!(watchman_query.sync(()).await?.0, Out::Unavailable);
+
+    // Restart Watchman: it is not queried again until the retry interval has passed.
+    let mut watchman_instance = spawn_watchman(&watchman_dir).await?;
+    assert_eq!(watchman_query.sync(()).await?.0, Out::Unavailable);
+
+    // Then we resync with a fresh instance, and get events again.
+    tokio::time::sleep(retry_interval).await;
+    assert_eq!(watchman_query.sync(()).await?.0, Out::FreshInstance);
+    let test = root.join("test");
+    File::create(&test)?;
+    assert_eq!(
+        watchman_query.sync(()).await?.0,
+        Out::Files(vec!["test".into()])
+    );
+
+    // Clean up
+    watchman_instance.shutdown().await?;
+
+    Ok(())
+}
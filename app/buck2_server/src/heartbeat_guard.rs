@@ -34,6 +34,7 @@ impl HeartbeatGuard {
             ctx.daemon_start_time,
             ctx.dice_manager.unsafe_dice().dupe(),
             ctx.materializer.dupe(),
+            ctx.local_disk_cache.dupe(),
             Some(ctx.events.sink().dupe()),
         );
 
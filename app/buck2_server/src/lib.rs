@@ -16,6 +16,7 @@ mod clean_stale;
 mod configs;
 mod ctx;
 pub mod daemon;
+mod dice_equality_check;
 mod dice_tracker;
 mod file_status;
 mod file_watcher;
@@ -62,6 +62,7 @@ use starlark::docs::DocItem;
 use starlark::docs::Identifier;
 use starlark::docs::Location;
 use starlark::errors::EvalMessage;
+use starlark::errors::EvalSeverity;
 use starlark::lsp::server::server_with_connection;
 use starlark::lsp::server::LspContext;
 use starlark::lsp::server::LspEvalResult;
@@ -487,9 +488,29 @@ impl<'a> BuckLspContext<'a> {
 
                 let module_path = import_path.borrow();
                 let path = module_path.starlark_path();
-                let ast = calculator.prepare_eval_with_content(path, content)?;
+                let (ast, load_errors) = calculator
+                    .prepare_eval_with_content_and_load_errors(path, content)
+                    .await?;
+                // Report the loaded modules which fail to evaluate on their `load`, so that
+                // saving a broken `.bzl` file shows up in the files loading it.
+                let diagnostics = load_errors
+                    .into_iter()
+                    .map(|(span, e)| match span {
+                        Some(span) => EvalMessage {
+                            path: span.filename().to_owned(),
+                            span: Some(span.resolve_span()),
+                            severity: EvalSeverity::Error,
+                            name: "load-error".to_owned(),
+                            description: format!("{:#}", e),
+                            full_error_with_span: None,
+                            original: Some(span.source_span().to_owned()),
+                        }
+                        .into(),
+                        None => EvalMessage::from_anyhow(uri.path(), &e).into(),
+                    })
+                    .collect();
                 Ok(LspEvalResult {
-                    diagnostics: vec![],
+                    diagnostics,
                     ast: Some(ast),
                 })
             })
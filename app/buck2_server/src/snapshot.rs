@@ -17,6 +17,7 @@ use buck2_events::EventSink;
 use buck2_execute::execute::blocking::BlockingExecutor;
 use buck2_execute::materialize::materializer::Materializer;
 use buck2_execute::re::manager::ReConnectionManager;
+use buck2_execute_impl::local_disk_cache::LocalDiskCache;
 use buck2_util::process_stats::process_stats;
 use dice::Dice;
 use dupe::Dupe;
@@ -32,6 +33,7 @@ pub struct SnapshotCollector {
     daemon_start_time: Instant,
     dice: Arc<Dice>,
     materializer: Arc<dyn Materializer>,
+    local_disk_cache: Option<Arc<LocalDiskCache>>,
     event_sink: Option<Arc<dyn EventSink>>,
     net_io_collector: SystemNetworkIoCollector,
 }
@@ -43,6 +45,7 @@ impl SnapshotCollector {
         daemon_start_time: Instant,
         dice: Arc<Dice>,
         materializer: Arc<dyn Materializer>,
+        local_disk_cache: Option<Arc<LocalDiskCache>>,
         event_sink: Option<Arc<dyn EventSink>>,
     ) -> SnapshotCollector {
         SnapshotCollector {
@@ -51,6 +54,7 @@ impl SnapshotCollector {
             daemon_start_time,
             dice,
             materializer,
+            local_disk_cache,
             event_sink,
             net_io_collector: SystemNetworkIoCollector::new(),
         }
@@ -73,6 +77,7 @@ impl SnapshotCollector {
         self.add_io_metrics(&mut snapshot);
         self.add_dice_metrics(&mut snapshot);
         self.add_materializer_metrics(&mut snapshot);
+        self.add_local_disk_cache_metrics(&mut snapshot);
         self.add_sink_metrics(&mut snapshot);
         self.add_net_io_metrics(&mut snapshot);
         snapshot
@@ -151,6 +156,12 @@ impl SnapshotCollector {
                 stats.get_digest_expirations.finished_successfully;
             snapshot.re_get_digest_expirations_finished_with_error =
                 stats.get_digest_expirations.finished_with_error;
+            snapshot.re_execution_rpcs_queued = stats.execution_rpcs.queued;
+            snapshot.re_execution_rpcs_running = stats.execution_rpcs.running;
+            snapshot.re_cache_query_rpcs_queued = stats.cache_query_rpcs.queued;
+            snapshot.re_cache_query_rpcs_running = stats.cache_query_rpcs.running;
+            snapshot.re_prefetch_rpcs_queued = stats.prefetch_rpcs.queued;
+            snapshot.re_prefetch_rpcs_running = stats.prefetch_rpcs.running;
 
             Ok(())
         }
@@ -174,6 +185,16 @@ impl SnapshotCollector {
         }
     }
 
+    fn add_local_disk_cache_metrics(&self, snapshot: &mut buck2_data::Snapshot) {
+        if let Some(cache) = &self.local_disk_cache {
+            let stats = cache.stats();
+            snapshot.local_cache_hits = Some(stats.hits);
+            snapshot.local_cache_misses = Some(stats.misses);
+            snapshot.local_cache_stores = Some(stats.stores);
+            snapshot.local_cache_evictions = Some(stats.evictions);
+        }
+    }
+
     fn add_sink_metrics(&self, snapshot: &mut buck2_data::Snapshot) {
         if let Some(metrics) = self.event_sink.as_ref().and_then(|sink| sink.stats()) {
             snapshot.sink_successes = Some(metrics.successes);
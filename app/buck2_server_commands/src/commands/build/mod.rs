@@ -8,11 +8,13 @@
  */
 
 use std::collections::BTreeMap;
+use std::collections::HashSet;
 use std::io::BufWriter;
 use std::sync::Arc;
 
 use anyhow::Context as _;
 use async_trait::async_trait;
+use buck2_build_api::analysis::warnings::report_analysis_warnings;
 use buck2_build_api::build;
 use buck2_build_api::build::BuildEvent;
 use buck2_build_api::build::BuildTargetResult;
@@ -26,6 +28,7 @@ use buck2_build_api::query::dice::get_dice_query_delegate;
 use buck2_cli_proto::build_request::build_providers::Action as BuildProviderAction;
 use buck2_cli_proto::build_request::BuildProviders;
 use buck2_cli_proto::build_request::Materializations;
+use buck2_cli_proto::CommonBuildOptions;
 use buck2_cli_proto::HasClientContext;
 use buck2_common::dice::cells::HasCellResolver;
 use buck2_common::dice::file_ops::HasFileOps;
@@ -33,6 +36,8 @@ use buck2_common::legacy_configs::dice::HasLegacyConfigs;
 use buck2_common::pattern::resolve::resolve_target_patterns;
 use buck2_common::pattern::resolve::ResolvedPattern;
 use buck2_core::fs::fs_util;
+use buck2_core::fs::project::ProjectRoot;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
 use buck2_core::pattern::pattern_type::ConfiguredProvidersPatternExtra;
 use buck2_core::pattern::pattern_type::ProvidersPatternExtra;
 use buck2_core::pattern::PackageSpec;
@@ -47,11 +52,12 @@ use buck2_interpreter_for_build::interpreter::calculation::InterpreterCalculatio
 use buck2_node::configured_universe::CqueryUniverse;
 use buck2_node::nodes::eval_result::EvaluationResult;
 use buck2_node::nodes::unconfigured::TargetNode;
+use buck2_query::query::compatibility::MaybeCompatible;
 use buck2_server_ctx::ctx::ServerCommandContextTrait;
 use buck2_server_ctx::partial_result_dispatcher::NoPartialResult;
 use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
 use buck2_server_ctx::pattern::parse_patterns_from_cli_args;
-use buck2_server_ctx::pattern::target_platform_from_client_context;
+use buck2_server_ctx::pattern::target_platforms_from_client_context;
 use buck2_server_ctx::template::run_server_command;
 use buck2_server_ctx::template::ServerCommandTemplate;
 use dice::DiceComputations;
@@ -64,6 +70,7 @@ use futures::stream::Stream;
 use futures::stream::StreamExt;
 use itertools::Itertools;
 
+use crate::commands::build::provenance::write_provenance;
 use crate::commands::build::results::build_report::BuildReportCollector;
 use crate::commands::build::results::providers::ProvidersPrinter;
 use crate::commands::build::results::result_report::ResultReporter;
@@ -72,6 +79,7 @@ use crate::commands::build::results::BuildOwner;
 use crate::commands::build::results::BuildResultCollector;
 use crate::commands::build::unhashed_outputs::create_unhashed_outputs;
 
+mod provenance;
 mod results;
 mod unhashed_outputs;
 
@@ -114,9 +122,17 @@ impl ServerCommandTemplate for BuildServerCommand {
     }
 }
 
+#[derive(Debug, thiserror::Error)]
+enum BuildError {
+    #[error(
+        "Cannot build with several `--target-platforms` when `--target-universe` is specified"
+    )]
+    TargetUniverseWithMultiplePlatforms,
+}
+
 enum TargetResolutionConfig {
-    /// Resolve using target platform.
-    Default(Option<TargetLabel>),
+    /// Resolve using target platforms, building every target once per platform.
+    Default(Vec<Option<TargetLabel>>),
     /// Resolve in the universe.
     Universe(CqueryUniverse),
 }
@@ -138,12 +154,17 @@ async fn build(
     let cell_resolver = ctx.get_cell_resolver().await?;
 
     let client_ctx = request.client_context()?;
-    let global_target_platform =
-        target_platform_from_client_context(client_ctx, server_ctx, &ctx).await?;
+    let global_target_platforms =
+        target_platforms_from_client_context(client_ctx, server_ctx, &ctx).await?;
+    let multiple_target_platforms = global_target_platforms.len() > 1;
 
     let should_create_unhashed_links = ctx
         .parse_legacy_config_property(cell_resolver.root_cell(), "buck2", "create_unhashed_links")
         .await?;
+    let should_write_provenance = ctx
+        .parse_legacy_config_property(cell_resolver.root_cell(), "provenance", "enabled")
+        .await?
+        .unwrap_or(false);
 
     let parsed_patterns: Vec<ParsedPattern<ConfiguredProvidersPatternExtra>> =
         parse_patterns_from_cli_args(&ctx, &request.target_patterns, cwd).await?;
@@ -157,8 +178,12 @@ async fn build(
         resolve_target_patterns(&cell_resolver, &parsed_patterns, &ctx.file_ops()).await?;
 
     let target_resolution_config: TargetResolutionConfig = if request.target_universe.is_empty() {
-        TargetResolutionConfig::Default(global_target_platform)
+        TargetResolutionConfig::Default(global_target_platforms)
     } else {
+        if multiple_target_platforms {
+            return Err(BuildError::TargetUniverseWithMultiplePlatforms.into());
+        }
+        let global_target_platform = global_target_platforms.into_iter().next().flatten();
         let query_delegate = get_dice_query_delegate(&ctx, cwd, global_target_platform).await?;
         TargetResolutionConfig::Universe(
             universe_from_literals(&query_delegate, &request.target_universe).await?,
@@ -177,6 +202,11 @@ async fn build(
         },
     );
 
+    let final_artifact_materializations =
+        Materializations::from_i32(request.final_artifact_materializations)
+            .with_context(|| "Invalid final_artifact_materializations")
+            .unwrap();
+
     let mut build_report_collector = if build_opts.unstable_print_build_report {
         Some(BuildReportCollector::new(
             server_ctx.events().trace_id(),
@@ -196,6 +226,8 @@ async fn build(
             )
             .await?
             .unwrap_or(false),
+            final_artifact_materializations,
+            multiple_target_platforms,
         ))
     } else {
         None
@@ -220,15 +252,10 @@ async fn build(
     .flatten()
     .collect::<Vec<&mut dyn BuildResultCollector>>();
 
-    let final_artifact_materializations =
-        Materializations::from_i32(request.final_artifact_materializations)
-            .with_context(|| "Invalid final_artifact_materializations")
-            .unwrap();
     let materialization_context =
         ConvertMaterializationContext::from(final_artifact_materializations);
 
-    let mut provider_artifacts = Vec::new();
-    for (k, v) in build_targets(
+    let results = match build_targets(
         &ctx,
         resolved_pattern,
         target_resolution_config,
@@ -236,14 +263,63 @@ async fn build(
         &materialization_context,
         build_opts.fail_fast,
     )
-    .await?
+    .await
     {
+        Ok(results) => results,
+        Err(e) => {
+            // Analysis errors fail the whole build, but we still want them (and the error code
+            // and metadata they may carry from `fail()`) in the build report.
+            if let Some(mut build_report_collector) = build_report_collector {
+                build_report_collector.collect_error(&e);
+                write_build_report(build_report_collector, build_opts, fs, cwd)?;
+            }
+            return Err(e);
+        }
+    };
+
+    let mut provider_artifacts = Vec::new();
+    let mut provenance_outputs = Vec::new();
+    let mut targets_with_reported_warnings = HashSet::new();
+    for (k, v) in results {
+        let v = match v {
+            MaybeCompatible::Compatible(v) => v,
+            MaybeCompatible::Incompatible(reason) => {
+                result_collectors.collect_skipped(&BuildOwner::Target(&k), &reason);
+                continue;
+            }
+        };
         result_collectors.collect_result(&BuildOwner::Target(&k), &v);
+        // The sub-targets of a target share its analysis, so its warnings are reported once.
+        if targets_with_reported_warnings.insert(k.target().dupe()) {
+            report_analysis_warnings(k.target(), &v.warnings);
+        }
         let mut outputs = v.outputs.into_iter().filter_map(|output| match output {
             Ok(output) => Some(output),
             _ => None,
         });
-        provider_artifacts.extend(&mut outputs);
+        for output in &mut outputs {
+            if should_write_provenance {
+                provenance_outputs.push((k.clone(), output.clone()));
+            }
+            provider_artifacts.push(output);
+        }
+    }
+
+    if should_write_provenance {
+        let sign_command = ctx
+            .get_legacy_config_property(cell_resolver.root_cell(), "provenance", "sign_command")
+            .await?;
+        let provenance = write_provenance(
+            &ctx,
+            &artifact_fs,
+            fs,
+            sign_command.as_deref(),
+            provenance_outputs,
+        )
+        .await?;
+        if let Some(build_report_collector) = build_report_collector.as_mut() {
+            build_report_collector.collect_provenance(provenance);
+        }
     }
 
     if should_create_unhashed_links.unwrap_or(false) {
@@ -263,22 +339,12 @@ async fn build(
         .await?;
     }
 
-    let mut serialized_build_report = None;
-    if let Some(build_report_collector) = build_report_collector {
-        let report = build_report_collector.into_report();
-        if !build_opts.unstable_build_report_filename.is_empty() {
-            let file = fs_util::create_file(
-                fs.resolve(cwd)
-                    .as_path()
-                    .join(&build_opts.unstable_build_report_filename),
-            )
-            .context("Error writing build report")?;
-            let mut file = BufWriter::new(file);
-            serde_json::to_writer_pretty(&mut file, &report)?
-        } else {
-            serialized_build_report = Some(serde_json::to_string(&report)?);
-        };
-    }
+    let serialized_build_report = match build_report_collector {
+        Some(build_report_collector) => {
+            write_build_report(build_report_collector, build_opts, fs, cwd)?
+        }
+        None => None,
+    };
 
     // TODO(nmj): The BuildResult / BuildResponse will eventually return all of the
     //            data back to the CLI client, and all build report generation will happen there.
@@ -307,6 +373,29 @@ async fn build(
     })
 }
 
+/// Write the build report to the requested file, or return it serialized if no file was given.
+fn write_build_report(
+    build_report_collector: BuildReportCollector<'_>,
+    build_opts: &CommonBuildOptions,
+    fs: &ProjectRoot,
+    cwd: &ProjectRelativePath,
+) -> anyhow::Result<Option<String>> {
+    let report = build_report_collector.into_report();
+    if !build_opts.unstable_build_report_filename.is_empty() {
+        let file = fs_util::create_file(
+            fs.resolve(cwd)
+                .as_path()
+                .join(&build_opts.unstable_build_report_filename),
+        )
+        .context("Error writing build report")?;
+        let mut file = BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut file, &report)?;
+        Ok(None)
+    } else {
+        Ok(Some(serde_json::to_string(&report)?))
+    }
+}
+
 async fn build_targets(
     ctx: &DiceComputations,
     spec: ResolvedPattern<ConfiguredProvidersPatternExtra>,
@@ -314,16 +403,16 @@ async fn build_targets(
     build_providers: Arc<BuildProviders>,
     materialization_context: &MaterializationContext,
     fail_fast: bool,
-) -> anyhow::Result<BTreeMap<ConfiguredProvidersLabel, BuildTargetResult>> {
+) -> anyhow::Result<BTreeMap<ConfiguredProvidersLabel, MaybeCompatible<BuildTargetResult>>> {
     let stream = match target_resolution_config {
-        TargetResolutionConfig::Default(global_target_platform) => {
+        TargetResolutionConfig::Default(global_target_platforms) => {
             let spec = spec.convert_pattern().context(
                 "Cannot build with explicit configurations when universe is not specified",
             )?;
-            build_targets_with_global_target_platform(
+            build_targets_with_global_target_platforms(
                 ctx,
                 spec,
-                global_target_platform,
+                global_target_platforms,
                 build_providers,
                 materialization_context,
             )
@@ -339,14 +428,7 @@ async fn build_targets(
         .right_stream(),
     };
 
-    // We omit skipped targets here.
-    let res = BuildTargetResult::collect_stream(stream, fail_fast)
-        .await?
-        .into_iter()
-        .filter_map(|(k, v)| Some((k, v?)))
-        .collect();
-
-    Ok(res)
+    BuildTargetResult::collect_stream(stream, fail_fast).await
 }
 
 fn build_targets_in_universe<'a>(
@@ -388,10 +470,12 @@ fn build_targets_in_universe<'a>(
         .flatten_unordered(None)
 }
 
-fn build_targets_with_global_target_platform<'a>(
+/// Build the targets matching `spec` once for each of the platforms. Each platform produces
+/// distinct configured labels, so the results for the platforms are kept apart downstream.
+fn build_targets_with_global_target_platforms<'a>(
     ctx: &'a DiceComputations,
     spec: ResolvedPattern<ProvidersPatternExtra>,
-    global_target_platform: Option<TargetLabel>,
+    global_target_platforms: Vec<Option<TargetLabel>>,
     build_providers: Arc<BuildProviders>,
     materialization_context: &'a MaterializationContext,
 ) -> impl Stream<Item = anyhow::Result<BuildEvent>> + Unpin + 'a {
@@ -399,16 +483,22 @@ fn build_targets_with_global_target_platform<'a>(
         .into_iter()
         .map(|(package, spec)| {
             let build_providers = build_providers.dupe();
-            let global_target_platform = global_target_platform.dupe();
+            let global_target_platforms = global_target_platforms.clone();
             async move {
                 let res = ctx.get_interpreter_results(package.dupe()).await?;
-                anyhow::Ok(build_targets_for_spec(
-                    ctx,
-                    spec,
-                    global_target_platform,
-                    res,
-                    build_providers,
-                    materialization_context,
+                anyhow::Ok(futures::stream::select_all(
+                    global_target_platforms
+                        .into_iter()
+                        .map(|global_target_platform| {
+                            build_targets_for_spec(
+                                ctx,
+                                spec.clone(),
+                                global_target_platform,
+                                res.dupe(),
+                                build_providers.dupe(),
+                                materialization_context,
+                            )
+                        }),
                 ))
             }
         })
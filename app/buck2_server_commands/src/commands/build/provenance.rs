@@ -0,0 +1,272 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Provenance files for the outputs of a build, enabled with `provenance.enabled = true`.
+//!
+//! For each output built by an action, we write a JSON file in `buck-out/<isolation
+//! dir>/provenance` describing how it was produced: which buck2 built it, for which target and
+//! configuration, with which action (by digest) on which kind of executor (and which RE worker),
+//! and from which inputs. These are meant to be fed to supply-chain attestation tooling, so they can be signed
+//! with `provenance.sign_command`, which is passed the path of the file and whose output is
+//! saved next to it with a `.sig` extension.
+
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use buck2_build_api::actions::artifact::artifact_type::BaseArtifactKind;
+use buck2_build_api::actions::calculation::ActionCalculation;
+use buck2_build_api::actions::execute::action_executor::ActionOutputsProvenance;
+use buck2_build_api::artifact_groups::calculation::ArtifactGroupCalculation;
+use buck2_build_api::build::ProviderArtifacts;
+use buck2_build_api::calculation::Calculation;
+use buck2_core::fs::artifact_path_resolver::ArtifactFs;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::project::ProjectRoot;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_core::provider::label::ConfiguredProvidersLabel;
+use buck2_execute::artifact::artifact_dyn::ArtifactDyn;
+use buck2_execute::artifact_value::ArtifactValue;
+use buck2_util::process::async_background_command;
+use dice::DiceComputations;
+use serde::Serialize;
+
+#[derive(Debug, thiserror::Error)]
+enum ProvenanceError {
+    #[error("`provenance.sign_command` is empty")]
+    EmptySignCommand,
+    #[error("Signing `{path}` failed: {stderr}")]
+    SignFailed { path: String, stderr: String },
+}
+
+#[derive(Debug, Serialize)]
+struct Provenance {
+    builder: Builder,
+    /// the requested target (with its providers) which the output belongs to
+    target: String,
+    configuration: String,
+    output: Subject,
+    /// the action which produced the output, if it ran a command
+    #[serde(skip_serializing_if = "Option::is_none")]
+    action: Option<ActionProvenance>,
+    inputs: Vec<Subject>,
+}
+
+#[derive(Debug, Serialize)]
+struct Builder {
+    id: &'static str,
+    /// the source control revision of buck2, if it was built for release
+    #[serde(skip_serializing_if = "Option::is_none")]
+    revision: Option<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+struct Subject {
+    path: ProjectRelativePathBuf,
+    /// the digest of the file, absent for directories and symlinks
+    #[serde(skip_serializing_if = "Option::is_none")]
+    digest: Option<String>,
+}
+
+impl Subject {
+    fn new(path: ProjectRelativePathBuf, value: &ArtifactValue) -> Self {
+        Self {
+            path,
+            digest: value.digest().map(|d| d.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ActionProvenance {
+    digest: String,
+    /// `local`, `remote`, `action_cache` or `local_action_cache`.
+    executor: String,
+    /// the RE worker which executed the action (originally, for `action_cache`), when reported
+    #[serde(skip_serializing_if = "Option::is_none")]
+    worker: Option<String>,
+}
+
+impl ActionProvenance {
+    fn new(provenance: &ActionOutputsProvenance) -> Self {
+        Self {
+            digest: provenance.action_digest.to_string(),
+            executor: provenance
+                .execution_kind
+                .as_str_name()
+                .trim_start_matches("ACTION_EXECUTION_KIND_")
+                .to_lowercase(),
+            worker: provenance.worker.clone(),
+        }
+    }
+}
+
+/// Write the provenance of the outputs built by actions, returning the path of the provenance
+/// file of each output.
+pub(crate) async fn write_provenance(
+    ctx: &DiceComputations,
+    artifact_fs: &ArtifactFs,
+    fs: &ProjectRoot,
+    sign_command: Option<&str>,
+    outputs: Vec<(ConfiguredProvidersLabel, ProviderArtifacts)>,
+) -> anyhow::Result<BTreeMap<ProjectRelativePathBuf, ProjectRelativePathBuf>> {
+    let buck_out = artifact_fs.buck_out_path_resolver().root();
+
+    let mut written = BTreeMap::new();
+    for (label, provider_artifacts) in outputs {
+        for (artifact, value) in provider_artifacts.values.iter() {
+            let build = match artifact.as_parts() {
+                (BaseArtifactKind::Build(build), _) => build,
+                _ => continue,
+            };
+            let path = artifact.resolve_path(artifact_fs)?;
+            // The same output may be requested through several targets.
+            if written.contains_key(&path) {
+                continue;
+            }
+
+            let action_outputs = ctx.build_artifact(build).await?;
+            let action = ActionCalculation::get_action(ctx, build.key()).await?;
+            let mut inputs = Vec::new();
+            for input in action.inputs()?.iter() {
+                let values = ctx.ensure_artifact_group(input).await?;
+                for (input, value) in values.iter() {
+                    inputs.push(Subject::new(input.resolve_path(artifact_fs)?, value));
+                }
+            }
+
+            let provenance = Provenance {
+                builder: Builder {
+                    id: "buck2",
+                    revision: buck2_build_info::revision(),
+                },
+                target: label.unconfigured().to_string(),
+                configuration: label.cfg().to_string(),
+                output: Subject::new(path.clone(), value),
+                action: action_outputs.provenance().map(ActionProvenance::new),
+                inputs,
+            };
+
+            let provenance_path = provenance_file_path(buck_out, &path);
+            let abs_path = fs.resolve(&provenance_path);
+            fs_util::create_dir_all(abs_path.parent().context("Provenance file has no parent")?)?;
+            fs_util::write(&abs_path, serde_json::to_vec_pretty(&provenance)?)
+                .context("Error writing provenance file")?;
+            if let Some(sign_command) = sign_command {
+                sign(sign_command, &abs_path.to_string()).await?;
+            }
+            written.insert(path, provenance_path);
+        }
+    }
+    Ok(written)
+}
+
+/// The provenance of `buck-out/v2/gen/foo/bar` is in `buck-out/v2/provenance/gen/foo/bar.json`.
+fn provenance_file_path(
+    buck_out: &ProjectRelativePath,
+    output: &ProjectRelativePath,
+) -> ProjectRelativePathBuf {
+    let output = output
+        .strip_prefix_opt(buck_out)
+        .map_or_else(|| output.as_str(), |p| p.as_str());
+    ProjectRelativePathBuf::unchecked_new(format!("{}/provenance/{}.json", buck_out, output))
+}
+
+/// Run the signing command on the file at `path`, and save its output to `<path>.sig`.
+async fn sign(sign_command: &str, path: &str) -> anyhow::Result<()> {
+    let mut args = sign_command.split_whitespace();
+    let program = args.next().ok_or(ProvenanceError::EmptySignCommand)?;
+    let output = async_background_command(program)
+        .args(args)
+        .arg(path)
+        .output()
+        .await
+        .with_context(|| format!("Error running `{}`", program))?;
+    if !output.status.success() {
+        return Err(ProvenanceError::SignFailed {
+            path: path.to_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        }
+        .into());
+    }
+    fs_util::write(format!("{}.sig", path), output.stdout).context("Error writing signature")
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_build_api::actions::execute::action_executor::ActionOutputsProvenance;
+    use buck2_core::fs::project_rel_path::ProjectRelativePath;
+    use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+    use buck2_execute::execute::action_digest::ActionDigest;
+    use serde_json::json;
+
+    use crate::commands::build::provenance::provenance_file_path;
+    use crate::commands::build::provenance::ActionProvenance;
+    use crate::commands::build::provenance::Builder;
+    use crate::commands::build::provenance::Provenance;
+    use crate::commands::build::provenance::Subject;
+
+    #[test]
+    fn test_provenance_json() -> anyhow::Result<()> {
+        let action_digest = ActionDigest::new_sha1([1; 20], 10);
+        let provenance = Provenance {
+            builder: Builder {
+                id: "buck2",
+                revision: None,
+            },
+            target: "root//foo:bar".to_owned(),
+            configuration: "cfg#abc".to_owned(),
+            output: Subject {
+                path: ProjectRelativePathBuf::unchecked_new("buck-out/v2/gen/out".to_owned()),
+                digest: Some("da39a3ee5e6b4b0d3255bfef95601890afd80709:0".to_owned()),
+            },
+            action: Some(ActionProvenance::new(&ActionOutputsProvenance {
+                action_digest: action_digest.clone(),
+                execution_kind: buck2_data::ActionExecutionKind::ActionCache,
+                worker: Some("worker-1".to_owned()),
+            })),
+            inputs: vec![Subject {
+                path: ProjectRelativePathBuf::unchecked_new("foo/src".to_owned()),
+                digest: None,
+            }],
+        };
+        assert_eq!(
+            serde_json::to_value(&provenance)?,
+            json!({
+                "builder": {"id": "buck2"},
+                "target": "root//foo:bar",
+                "configuration": "cfg#abc",
+                "output": {
+                    "path": "buck-out/v2/gen/out",
+                    "digest": "da39a3ee5e6b4b0d3255bfef95601890afd80709:0",
+                },
+                "action": {
+                    "digest": action_digest.to_string(),
+                    "executor": "action_cache",
+                    "worker": "worker-1",
+                },
+                "inputs": [{"path": "foo/src"}],
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_provenance_file_path() {
+        let buck_out = ProjectRelativePath::unchecked_new("buck-out/v2");
+        assert_eq!(
+            provenance_file_path(
+                buck_out,
+                ProjectRelativePath::unchecked_new("buck-out/v2/gen/root/abc/foo/bar.o"),
+            )
+            .as_str(),
+            "buck-out/v2/provenance/gen/root/abc/foo/bar.o.json"
+        );
+    }
+}
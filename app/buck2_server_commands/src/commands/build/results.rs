@@ -12,6 +12,7 @@
 use buck2_build_api::build::BuildTargetResult;
 use buck2_build_api::bxl::types::BxlFunctionLabel;
 use buck2_core::provider::label::ConfiguredProvidersLabel;
+use buck2_query::query::compatibility::IncompatiblePlatformReason;
 
 pub(crate) enum BuildOwner<'a> {
     Target(&'a ConfiguredProvidersLabel),
@@ -21,6 +22,10 @@ pub(crate) enum BuildOwner<'a> {
 /// Collects the results of the build and processes it
 pub(crate) trait BuildResultCollector: Send {
     fn collect_result(&mut self, label: &BuildOwner, result: &BuildTargetResult);
+
+    /// A target matched by a pattern was not built because it is incompatible with its
+    /// configuration.
+    fn collect_skipped(&mut self, _label: &BuildOwner, _reason: &IncompatiblePlatformReason) {}
 }
 
 impl BuildResultCollector for Vec<&mut dyn BuildResultCollector> {
@@ -29,6 +34,12 @@ impl BuildResultCollector for Vec<&mut dyn BuildResultCollector> {
             collector.collect_result(label, result);
         }
     }
+
+    fn collect_skipped(&mut self, label: &BuildOwner, reason: &IncompatiblePlatformReason) {
+        for collector in self {
+            collector.collect_skipped(label, reason);
+        }
+    }
 }
 
 pub mod result_report {
@@ -167,10 +178,25 @@ pub mod result_report {
                     }
                 };
 
+                let (run_args, run_env, run_cwd) = match &result.run_args {
+                    Some(run_args) => (
+                        run_args.args.clone(),
+                        run_args.env.iter().cloned().collect(),
+                        run_args
+                            .cwd
+                            .as_ref()
+                            .map(|cwd| cwd.to_string())
+                            .unwrap_or_default(),
+                    ),
+                    None => Default::default(),
+                };
+
                 r.push(BuildTarget {
                     target,
                     configuration,
-                    run_args: result.run_args.clone().unwrap_or_default(),
+                    run_args,
+                    run_env,
+                    run_cwd,
                     outputs: artifacts,
                 })
             };
@@ -179,19 +205,24 @@ pub mod result_report {
 }
 
 pub mod build_report {
+    use std::collections::BTreeMap;
     use std::collections::HashMap;
 
     use buck2_build_api::build::BuildProviderType;
     use buck2_build_api::bxl::types::BxlFunctionLabel;
+    use buck2_cli_proto::build_request::Materializations;
     use buck2_core::configuration::data::ConfigurationData;
     use buck2_core::fs::artifact_path_resolver::ArtifactFs;
     use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
     use buck2_core::fs::project::ProjectRoot;
     use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+    use buck2_core::provider::label::ConfiguredProvidersLabel;
     use buck2_core::provider::label::NonDefaultProvidersName;
     use buck2_core::provider::label::ProvidersName;
     use buck2_core::target::label::TargetLabel;
     use buck2_execute::artifact::artifact_dyn::ArtifactDyn;
+    use buck2_interpreter::functions::fail::find_starlark_fail;
+    use buck2_query::query::compatibility::IncompatiblePlatformReason;
     use buck2_wrapper_common::invocation_id::TraceId;
     use derivative::Derivative;
     use dupe::Dupe;
@@ -210,6 +241,8 @@ pub mod build_report {
         FAIL,
         #[allow(dead_code)] // Part of the spec, but not yet used
         CANCELED,
+        /// The target was matched by a pattern but is incompatible with its configuration.
+        SKIPPED,
     }
 
     impl Default for BuildOutcome {
@@ -226,6 +259,46 @@ pub mod build_report {
         failures: HashMap<EntryLabel, ProjectRelativePathBuf>,
         project_root: AbsNormPathBuf,
         truncated: bool,
+        /// which outputs of the requested targets were materialized
+        materializations: MaterializedOutputs,
+        /// errors which could not be attributed to a single target, e.g. analysis failures
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        errors: Vec<BuildReportError>,
+        /// the outcome of the build for each target platform, when several were requested
+        #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+        platforms: BTreeMap<String, PlatformBuildReport>,
+        /// the provenance file written for each output, when enabled
+        #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+        provenance: BTreeMap<ProjectRelativePathBuf, ProjectRelativePathBuf>,
+    }
+
+    #[derive(Debug, Serialize)]
+    pub(crate) struct PlatformBuildReport {
+        /// whether every target built for this platform was successful
+        success: bool,
+        /// the outcome for each target (with its providers) built for this platform
+        targets: BTreeMap<String, BuildOutcome>,
+    }
+
+    #[derive(Debug, Serialize)]
+    #[serde(rename_all = "lowercase")]
+    enum MaterializedOutputs {
+        /// decided by the materializer configuration
+        Default,
+        None,
+        Defaults,
+        All,
+    }
+
+    impl From<Materializations> for MaterializedOutputs {
+        fn from(materializations: Materializations) -> Self {
+            match materializations {
+                Materializations::Default => Self::Default,
+                Materializations::Skip => Self::None,
+                Materializations::MaterializeDefaultOutputs => Self::Defaults,
+                Materializations::Materialize => Self::All,
+            }
+        }
     }
 
     #[derive(Default, Debug, Serialize)]
@@ -239,6 +312,109 @@ pub mod build_report {
         /// the hidden, implicitly built outputs of the subtarget. There are multiple outputs
         /// per subtarget
         other_outputs: HashMap<String, Vec<ProjectRelativePathBuf>>,
+        /// the errors which caused this target to fail
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        errors: Vec<BuildReportError>,
+        /// the warnings emitted by the analysis of this target with `ctx.emit_warning`
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        warnings: Vec<BuildReportWarning>,
+        /// why this target was skipped, if it is incompatible with its configuration
+        #[serde(skip_serializing_if = "Option::is_none")]
+        incompatibility: Option<BuildReportIncompatibility>,
+        /// whether a result was recorded for this target, in any configuration
+        #[serde(skip)]
+        built: bool,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize)]
+    pub(crate) struct BuildReportIncompatibility {
+        /// the constraints required by the target, or by the dependency it inherited its
+        /// incompatibility from, which the configuration does not satisfy
+        unsatisfied_constraints: Vec<String>,
+        /// the attribute listing these constraints, e.g. `target_compatible_with`
+        attribute: String,
+        /// the key of the `select()` in that attribute which was resolved to these constraints,
+        /// if the attribute is a `select()`
+        #[serde(skip_serializing_if = "Option::is_none")]
+        select_key: Option<String>,
+        /// the dependencies from the target to the incompatible one, if the target is only
+        /// incompatible because of a dependency
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        dependency_chain: Vec<String>,
+        message: String,
+    }
+
+    impl BuildReportIncompatibility {
+        fn new(reason: &IncompatiblePlatformReason) -> Self {
+            let mut chain = Vec::new();
+            let (attribute, select_key, unsatisfied) = reason.unsatisfied_constraints(&mut chain);
+            Self {
+                unsatisfied_constraints: unsatisfied.iter().map(|c| c.to_string()).collect(),
+                attribute: attribute.to_string(),
+                select_key: select_key.map(str::to_owned),
+                dependency_chain: chain.iter().map(|t| t.to_string()).collect(),
+                message: reason.to_string(),
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize)]
+    pub(crate) struct BuildReportWarning {
+        message: String,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        tags: Vec<String>,
+    }
+
+    impl BuildReportEntry {
+        /// Record warnings, skipping those already recorded: the same analysis is reported for
+        /// each of the target's requested sub-targets.
+        fn add_warnings(&mut self, warnings: &[BuildReportWarning]) {
+            for warning in warnings {
+                if !self.warnings.contains(warning) {
+                    self.warnings.push(warning.clone());
+                }
+            }
+        }
+
+        /// Record that the target was skipped. Whether the entry is skipped is only decided by
+        /// `finish`, once all the configurations of the target have been collected.
+        fn add_skipped(&mut self, incompatibility: &BuildReportIncompatibility) {
+            if self.incompatibility.is_none() {
+                self.incompatibility = Some(incompatibility.clone());
+            }
+        }
+
+        /// Decide the outcome of the entry: it is skipped only if it was not built in any of
+        /// the configurations it covers.
+        fn finish(&mut self) {
+            if self.built {
+                self.incompatibility = None;
+            } else if self.incompatibility.is_some() {
+                self.success = BuildOutcome::SKIPPED;
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Serialize)]
+    pub(crate) struct BuildReportError {
+        message: String,
+        /// the `code` passed to `fail()`, if the error was raised by it
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error_code: Option<String>,
+        /// the `metadata` passed to `fail()`, if the error was raised by it
+        #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+        metadata: BTreeMap<String, String>,
+    }
+
+    impl BuildReportError {
+        fn new(e: &anyhow::Error) -> Self {
+            let fail = find_starlark_fail(e);
+            Self {
+                message: format!("{:#}", e),
+                error_code: fail.and_then(|f| f.code().map(str::to_owned)),
+                metadata: fail.map(|f| f.metadata().clone()).unwrap_or_default(),
+            }
+        }
     }
 
     #[derive(Debug, Serialize)]
@@ -269,6 +445,11 @@ pub mod build_report {
         project_root: &'a ProjectRoot,
         include_unconfigured_section: bool,
         include_other_outputs: bool,
+        materializations: Materializations,
+        errors: Vec<BuildReportError>,
+        /// `None` unless several target platforms were requested.
+        platforms: Option<BTreeMap<String, PlatformBuildReport>>,
+        provenance: BTreeMap<ProjectRelativePathBuf, ProjectRelativePathBuf>,
     }
 
     impl<'a> BuildReportCollector<'a> {
@@ -278,6 +459,8 @@ pub mod build_report {
             project_root: &'a ProjectRoot,
             include_unconfigured_section: bool,
             include_other_outputs: bool,
+            materializations: Materializations,
+            group_by_platform: bool,
         ) -> Self {
             Self {
                 trace_id,
@@ -287,10 +470,42 @@ pub mod build_report {
                 project_root,
                 include_unconfigured_section,
                 include_other_outputs,
+                materializations,
+                errors: Vec::new(),
+                platforms: if group_by_platform {
+                    Some(BTreeMap::new())
+                } else {
+                    None
+                },
+                provenance: BTreeMap::new(),
             }
         }
 
-        pub(crate) fn into_report(self) -> BuildReport {
+        /// Record an error which failed the build before results for individual targets
+        /// were available.
+        pub(crate) fn collect_error(&mut self, e: &anyhow::Error) {
+            self.errors.push(BuildReportError::new(e));
+            self.overall_success = false;
+        }
+
+        /// Record the provenance files written for the outputs, keyed by output.
+        pub(crate) fn collect_provenance(
+            &mut self,
+            provenance: BTreeMap<ProjectRelativePathBuf, ProjectRelativePathBuf>,
+        ) {
+            self.provenance.extend(provenance);
+        }
+
+        pub(crate) fn into_report(mut self) -> BuildReport {
+            for entry in self.build_report_results.values_mut() {
+                if let Some(report) = &mut entry.compatible {
+                    report.finish();
+                }
+                entry
+                    .configured
+                    .values_mut()
+                    .for_each(BuildReportEntry::finish);
+            }
             BuildReport {
                 trace_id: self.trace_id.dupe(),
                 success: self.overall_success,
@@ -300,16 +515,20 @@ pub mod build_report {
                 // In buck1 we may truncate build report for a large number of targets.
                 // Setting this to false since we don't currently truncate buck2's build report.
                 truncated: false,
+                materializations: self.materializations.into(),
+                errors: self.errors,
+                platforms: self.platforms.unwrap_or_default(),
+                provenance: self.provenance,
             }
         }
     }
 
     impl<'a> BuildResultCollector for BuildReportCollector<'a> {
         fn collect_result(&mut self, label: &BuildOwner, result: &BuildTargetResult) {
-            let (default_outs, other_outs, success) = {
+            let (default_outs, other_outs, errors) = {
                 let mut default_outs = SmallSet::new();
                 let mut other_outs = SmallSet::new();
-                let mut success = true;
+                let mut errors = Vec::new();
 
                 result.outputs.iter().for_each(|res| {
                     match res {
@@ -347,13 +566,65 @@ pub mod build_report {
                                 }
                             }
                         }
-                        Err(..) => success = false,
+                        Err(e) => errors.push(BuildReportError::new(e.inner())),
                     }
                 });
 
-                (default_outs, other_outs, success)
+                (default_outs, other_outs, errors)
             };
 
+            let warnings = result
+                .warnings
+                .iter()
+                .map(|w| BuildReportWarning {
+                    message: w.message.clone(),
+                    tags: w.tags.clone(),
+                })
+                .collect();
+
+            self.record_result(label, default_outs, other_outs, errors, warnings);
+        }
+
+        fn collect_skipped(&mut self, label: &BuildOwner, reason: &IncompatiblePlatformReason) {
+            let t = match label {
+                BuildOwner::Target(t) => t,
+                BuildOwner::_Bxl(_) => return,
+            };
+            let incompatibility = BuildReportIncompatibility::new(reason);
+            let report_results = self
+                .build_report_results
+                .entry(EntryLabel::Target(t.unconfigured().target().dupe()))
+                .or_insert_with(|| ConfiguredBuildReportEntry {
+                    compatible: if self.include_unconfigured_section {
+                        Some(BuildReportEntry::default())
+                    } else {
+                        None
+                    },
+                    configured: HashMap::new(),
+                });
+            if let Some(report) = &mut report_results.compatible {
+                report.add_skipped(&incompatibility);
+            }
+            report_results
+                .configured
+                .entry(t.cfg().dupe())
+                .or_insert_with(BuildReportEntry::default)
+                .add_skipped(&incompatibility);
+
+            self.report_platform_outcome(t, BuildOutcome::SKIPPED);
+        }
+    }
+
+    impl<'a> BuildReportCollector<'a> {
+        /// Record the outputs, errors and warnings of a target built in one configuration.
+        fn record_result(
+            &mut self,
+            label: &BuildOwner,
+            default_outs: SmallSet<ProjectRelativePathBuf>,
+            other_outs: SmallSet<ProjectRelativePathBuf>,
+            errors: Vec<BuildReportError>,
+            warnings: Vec<BuildReportWarning>,
+        ) {
             let report_results = self
                 .build_report_results
                 .entry(match label {
@@ -370,6 +641,9 @@ pub mod build_report {
                 });
 
             let unconfigured_report = &mut report_results.compatible;
+            if let Some(report) = unconfigured_report {
+                report.built = true;
+            }
             let configured_report = report_results
                 .configured
                 .entry(match label {
@@ -377,6 +651,7 @@ pub mod build_report {
                     BuildOwner::_Bxl(_) => ConfigurationData::unspecified(),
                 })
                 .or_insert_with(BuildReportEntry::default);
+            configured_report.built = true;
             if !default_outs.is_empty() {
                 if let Some(report) = unconfigured_report {
                     report.outputs.insert(
@@ -404,13 +679,60 @@ pub mod build_report {
                 );
             }
 
-            if !success {
+            if let Some(report) = unconfigured_report {
+                report.add_warnings(&warnings);
+            }
+            configured_report.add_warnings(&warnings);
+
+            let errors_seen = !errors.is_empty();
+            if errors_seen {
                 if let Some(report) = unconfigured_report {
                     report.success = BuildOutcome::FAIL;
+                    report.errors.extend(errors.iter().cloned());
                 }
                 configured_report.success = BuildOutcome::FAIL;
+                configured_report.errors.extend(errors);
                 self.overall_success = false;
             }
+
+            if let BuildOwner::Target(t) = label {
+                let outcome = if errors_seen {
+                    BuildOutcome::FAIL
+                } else {
+                    BuildOutcome::SUCCESS
+                };
+                self.report_platform_outcome(t, outcome);
+            }
+        }
+
+        fn report_platform_outcome(
+            &mut self,
+            label: &ConfiguredProvidersLabel,
+            outcome: BuildOutcome,
+        ) {
+            let platforms = match &mut self.platforms {
+                Some(platforms) => platforms,
+                None => return,
+            };
+            // Targets can be transitioned away from the requested platform, in which case
+            // they are grouped under the platform they were actually configured for.
+            let platform = match label.cfg().label() {
+                Ok(platform) => platform.to_owned(),
+                Err(_) => label.cfg().to_string(),
+            };
+            let platform_report =
+                platforms
+                    .entry(platform)
+                    .or_insert_with(|| PlatformBuildReport {
+                        success: true,
+                        targets: BTreeMap::new(),
+                    });
+            if matches!(outcome, BuildOutcome::FAIL) {
+                platform_report.success = false;
+            }
+            platform_report
+                .targets
+                .insert(label.unconfigured().to_string(), outcome);
         }
     }
 
@@ -428,6 +750,131 @@ pub mod build_report {
             BuildOwner::_Bxl(_) => "DEFAULT".to_owned(),
         }
     }
+
+    #[cfg(test)]
+    mod tests {
+        use buck2_cli_proto::build_request::Materializations;
+        use buck2_core::buck_path::resolver::BuckPathResolver;
+        use buck2_core::cells::cell_root_path::CellRootPathBuf;
+        use buck2_core::cells::name::CellName;
+        use buck2_core::cells::CellResolver;
+        use buck2_core::configuration::data::ConfigurationData;
+        use buck2_core::configuration::data::ConfigurationDataData;
+        use buck2_core::fs::artifact_path_resolver::ArtifactFs;
+        use buck2_core::fs::buck_out_path::BuckOutPathResolver;
+        use buck2_core::fs::project::ProjectRootTemp;
+        use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+        use buck2_core::provider::label::ConfiguredProvidersLabel;
+        use buck2_core::provider::label::ProvidersName;
+        use buck2_core::target::label::TargetLabel;
+        use buck2_query::query::compatibility::CompatibilityAttribute;
+        use buck2_query::query::compatibility::IncompatiblePlatformReason;
+        use buck2_query::query::compatibility::IncompatiblePlatformReasonCause;
+        use buck2_wrapper_common::invocation_id::TraceId;
+        use dupe::Dupe;
+        use starlark_map::small_set::SmallSet;
+
+        use crate::commands::build::results::build_report::BuildOutcome;
+        use crate::commands::build::results::build_report::BuildReportCollector;
+        use crate::commands::build::results::build_report::EntryLabel;
+        use crate::commands::build::results::BuildOwner;
+        use crate::commands::build::results::BuildResultCollector;
+
+        fn configured(label: &str, platform: &str) -> ConfiguredProvidersLabel {
+            let cfg = ConfigurationData::from_platform(
+                platform.to_owned(),
+                ConfigurationDataData::empty(),
+            )
+            .unwrap();
+            ConfiguredProvidersLabel::new(
+                TargetLabel::testing_parse(label).configure(cfg),
+                ProvidersName::Default,
+            )
+        }
+
+        fn incompatible(label: &ConfiguredProvidersLabel) -> IncompatiblePlatformReason {
+            IncompatiblePlatformReason {
+                target: label.target().dupe(),
+                cause: IncompatiblePlatformReasonCause::UnsatisfiedConfig {
+                    attr: CompatibilityAttribute::TargetCompatibleWith,
+                    select_key: Some("config//os:macos".to_owned()),
+                    unsatisfied: vec![TargetLabel::testing_parse("config//os:linux")],
+                },
+            }
+        }
+
+        #[test]
+        fn test_skipped_configurations() {
+            let trace_id = TraceId::new();
+            let project_root = ProjectRootTemp::new().unwrap();
+            let artifact_fs = ArtifactFs::new(
+                BuckPathResolver::new(CellResolver::testing_with_name_and_path(
+                    CellName::testing_new("root"),
+                    CellRootPathBuf::new(ProjectRelativePathBuf::unchecked_new("".into())),
+                )),
+                BuckOutPathResolver::new(ProjectRelativePathBuf::unchecked_new("buck-out".into())),
+                project_root.path().dupe(),
+            );
+            let mut collector = BuildReportCollector::new(
+                &trace_id,
+                &artifact_fs,
+                project_root.path(),
+                true,
+                false,
+                Materializations::Default,
+                false,
+            );
+
+            // Skipped on macOS, which is collected first, but built on Linux.
+            let macos = configured("root//foo:bar", "cfg//:macos");
+            let linux = configured("root//foo:bar", "cfg//:linux");
+            collector.collect_skipped(&BuildOwner::Target(&macos), &incompatible(&macos));
+            let mut outputs = SmallSet::new();
+            outputs.insert(ProjectRelativePathBuf::unchecked_new("buck-out/bar".into()));
+            collector.record_result(
+                &BuildOwner::Target(&linux),
+                outputs,
+                SmallSet::new(),
+                Vec::new(),
+                Vec::new(),
+            );
+
+            // Skipped everywhere.
+            let baz = configured("root//foo:baz", "cfg//:macos");
+            collector.collect_skipped(&BuildOwner::Target(&baz), &incompatible(&baz));
+
+            let report = collector.into_report();
+
+            let bar =
+                &report.results[&EntryLabel::Target(TargetLabel::testing_parse("root//foo:bar"))];
+            let unconfigured = bar.compatible.as_ref().unwrap();
+            assert!(matches!(unconfigured.success, BuildOutcome::SUCCESS));
+            assert_eq!(None, unconfigured.incompatibility);
+            assert!(matches!(
+                bar.configured[linux.cfg()].success,
+                BuildOutcome::SUCCESS
+            ));
+            let skipped_on_macos = &bar.configured[macos.cfg()];
+            assert!(matches!(skipped_on_macos.success, BuildOutcome::SKIPPED));
+            let incompatibility = skipped_on_macos.incompatibility.as_ref().unwrap();
+            assert_eq!(
+                Some("config//os:macos"),
+                incompatibility.select_key.as_deref()
+            );
+            assert_eq!(
+                vec!["config//os:linux".to_owned()],
+                incompatibility.unsatisfied_constraints
+            );
+            assert_eq!("target_compatible_with", incompatibility.attribute);
+
+            let baz =
+                &report.results[&EntryLabel::Target(TargetLabel::testing_parse("root//foo:baz"))];
+            let unconfigured = baz.compatible.as_ref().unwrap();
+            assert!(matches!(unconfigured.success, BuildOutcome::SKIPPED));
+            assert!(unconfigured.incompatibility.is_some());
+            assert!(report.success);
+        }
+    }
 }
 
 pub mod providers {
@@ -7,12 +7,21 @@
  * of this source tree.
  */
 
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::io::Write;
 
 use anyhow::Context;
 use async_trait::async_trait;
+use buck2_build_api::artifact_groups::ArtifactGroup;
+use buck2_build_api::calculation::Calculation;
+use buck2_build_api::query::aquery::environment::ActionQueryNode;
 use buck2_build_api::query::aquery::evaluator::get_aquery_evaluator;
 use buck2_common::dice::cells::HasCellResolver;
+use buck2_core::fs::artifact_path_resolver::ArtifactFs;
+use buck2_execute::artifact::artifact_dyn::ArtifactDyn;
+use buck2_query::query::environment::QueryTarget;
+use buck2_query::query::syntax::simple::eval::set::TargetSet;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
 use buck2_server_ctx::ctx::ServerCommandContextTrait;
 use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
@@ -36,6 +45,12 @@ struct AqueryServerCommand {
     req: buck2_cli_proto::AqueryRequest,
 }
 
+#[derive(Debug, thiserror::Error)]
+enum AqueryError {
+    #[error("Action graph output does not support queries with multiple results (using `%s`)")]
+    ActionGraphMultipleResults,
+}
+
 #[async_trait]
 impl ServerCommandTemplate for AqueryServerCommand {
     type StartEvent = buck2_data::AqueryCommandStart;
@@ -95,6 +110,20 @@ async fn aquery(
 
     let query_result = evaluator.eval_query(query, query_args).await?;
 
+    if request.action_graph {
+        let targets = match query_result {
+            QueryEvaluationResult::Single(targets) => targets,
+            QueryEvaluationResult::Multiple(..) => {
+                return Err(AqueryError::ActionGraphMultipleResults.into());
+            }
+        };
+        let artifact_fs = ctx.get_artifact_fs().await?;
+        print_action_graph(&mut stdout, &targets, &artifact_fs)?;
+        return Ok(buck2_cli_proto::AqueryResponse {
+            error_messages: Vec::new(),
+        });
+    }
+
     let result = match query_result {
         QueryEvaluationResult::Single(targets) => {
             output_configuration
@@ -113,3 +142,71 @@ async fn aquery(
     };
     Ok(buck2_cli_proto::AqueryResponse { error_messages })
 }
+
+#[derive(serde::Serialize)]
+struct ActionGraphNode {
+    key: String,
+    owner: String,
+    kind: String,
+    category: String,
+    identifier: Option<String>,
+    /// Paths of input artifacts, and transitive set projections (whose artifacts are the
+    /// outputs of the actions in `deps`).
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    /// Keys of the actions producing the inputs.
+    deps: BTreeSet<String>,
+    /// Action specific attributes, including the executor configuration.
+    attributes: BTreeMap<String, String>,
+}
+
+/// Print actions in a stable JSON format: sorted by key, with inputs and outputs as paths
+/// relative to the project root.
+fn print_action_graph(
+    mut stdout: impl Write,
+    targets: &TargetSet<ActionQueryNode>,
+    artifact_fs: &ArtifactFs,
+) -> anyhow::Result<()> {
+    let mut actions = BTreeMap::new();
+    for node in targets.iter() {
+        let action = node.action();
+        let inputs = action
+            .inputs()?
+            .iter()
+            .map(|input| match input {
+                ArtifactGroup::Artifact(artifact) => {
+                    Ok(artifact.resolve_path(artifact_fs)?.to_string())
+                }
+                ArtifactGroup::TransitiveSetProjection(tset) => Ok(tset.to_string()),
+            })
+            .collect::<anyhow::Result<_>>()?;
+        let outputs = action
+            .outputs()?
+            .iter()
+            .map(|output| artifact_fs.resolve_build(output.get_path()).to_string())
+            .collect();
+        let key = action.key().to_string();
+        actions.insert(
+            key.clone(),
+            ActionGraphNode {
+                key,
+                owner: action.owner().to_string(),
+                kind: node.rule_type().into_owned(),
+                category: action.category().as_str().to_owned(),
+                identifier: action.identifier().map(str::to_owned),
+                inputs,
+                outputs,
+                deps: node.deps().map(|dep| dep.to_string()).collect(),
+                attributes: node.attrs().into_iter().collect(),
+            },
+        );
+    }
+
+    let actions: Vec<_> = actions.into_values().collect();
+    writeln!(
+        stdout,
+        "{}",
+        serde_json::to_string_pretty(&serde_json::json!({ "actions": actions }))?
+    )?;
+    Ok(())
+}
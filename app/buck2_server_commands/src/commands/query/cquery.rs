@@ -95,7 +95,8 @@ async fn cquery(
         &cell_resolver,
         &request.output_attributes,
         request.unstable_output_format,
-    )?;
+    )?
+    .with_dep_kinds(request.output_dep_kinds);
 
     let CqueryRequest {
         query,
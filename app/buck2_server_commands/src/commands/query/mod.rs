@@ -11,6 +11,7 @@ use thiserror::Error;
 
 pub mod aquery;
 pub mod cquery;
+mod parquet;
 pub mod printer;
 pub mod uquery;
 
@@ -20,4 +21,6 @@ enum QueryCommandError {
         "query result was a set of files and one or more --output-attribute was requested, but files have not attributes"
     )]
     FileSetHasNoAttributes,
+    #[error("query result was a set of files, but parquet output is only supported for targets")]
+    FileSetHasNoParquetOutput,
 }
@@ -0,0 +1,262 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! `--output-format streaming_parquet`: the targets of a query as a Parquet file, for data
+//! pipelines which would otherwise spend most of their time parsing the JSON output.
+//!
+//! Each row is a target: its label (`buck.target`), its rule type (`buck.type`), and one column
+//! for each attribute matching `--output-attribute`. Attribute values are JSON, as printed by
+//! `--output-attribute` in JSON output, and null when the target has no such attribute. Targets
+//! are written in row groups of `ROWS_PER_ROW_GROUP` as they are converted, so that the whole
+//! result is never buffered.
+
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::Arc;
+
+use arrow::array::ArrayRef;
+use arrow::array::StringBuilder;
+use arrow::datatypes::DataType;
+use arrow::datatypes::Field;
+use arrow::datatypes::Schema;
+use arrow::record_batch::RecordBatch;
+use buck2_query::query::environment::LabeledNode;
+use buck2_query::query::environment::QueryTarget;
+use buck2_query::query::environment::QueryTargets;
+use buck2_query::query::syntax::simple::eval::set::TargetSet;
+use itertools::Itertools;
+use parquet::arrow::ArrowWriter;
+use parquet::file::properties::WriterProperties;
+use regex::RegexSet;
+
+const TARGET_COLUMN: &str = "buck.target";
+const RULE_TYPE_COLUMN: &str = "buck.type";
+
+const ROWS_PER_ROW_GROUP: usize = 10_000;
+
+/// Names of the requested attributes of any of the targets, which is the attribute columns.
+/// Only the names are looked at: no attribute is resolved.
+fn attr_columns<T: QueryTarget>(
+    targets: &TargetSet<T>,
+    attributes: &Option<RegexSet>,
+) -> Vec<String> {
+    let attributes = match attributes {
+        Some(attributes) => attributes,
+        None => return Vec::new(),
+    };
+    let names = RefCell::new(BTreeSet::new());
+    for target in targets.iter() {
+        let filter = |name: &str| {
+            // `buck.type` is already a column.
+            if name != RULE_TYPE_COLUMN
+                && !names.borrow().contains(name)
+                && attributes.is_match(name)
+            {
+                names.borrow_mut().insert(name.to_owned());
+            }
+            false
+        };
+        QueryTargets::for_all_attrs_matching::<(), _, _>(target, &filter, |_, _| Ok(()))
+            .expect("no attribute matches");
+    }
+    names.into_inner().into_iter().collect()
+}
+
+fn attr_to_json<T: QueryTarget>(target: &T, attr: &T::Attr<'_>) -> anyhow::Result<String> {
+    let mut json = Vec::new();
+    target.attr_serialize(attr, &mut serde_json::Serializer::new(&mut json))?;
+    Ok(String::from_utf8(json)?)
+}
+
+fn record_batch<'t, T: QueryTarget>(
+    schema: &Arc<Schema>,
+    attr_columns: &HashMap<&str, usize>,
+    targets: impl Iterator<Item = &'t T>,
+) -> anyhow::Result<RecordBatch> {
+    let mut target_column = StringBuilder::new();
+    let mut rule_type_column = StringBuilder::new();
+    let mut attr_builders: Vec<_> = (0..attr_columns.len())
+        .map(|_| StringBuilder::new())
+        .collect();
+
+    for target in targets {
+        target_column.append_value(target.node_ref().to_string());
+        rule_type_column.append_value(target.rule_type());
+
+        let mut values = vec![None; attr_columns.len()];
+        if !attr_columns.is_empty() {
+            QueryTargets::for_all_attrs_matching::<anyhow::Error, _, _>(
+                target,
+                &|name: &str| attr_columns.contains_key(name),
+                |name, attr| {
+                    values[attr_columns[name]] = Some(attr_to_json(target, attr)?);
+                    Ok(())
+                },
+            )?;
+        }
+        for (builder, value) in attr_builders.iter_mut().zip(values) {
+            builder.append_option(value);
+        }
+    }
+
+    let columns = [target_column, rule_type_column]
+        .into_iter()
+        .chain(attr_builders)
+        .map(|mut builder| Arc::new(builder.finish()) as ArrayRef)
+        .collect();
+    Ok(RecordBatch::try_new(schema.clone(), columns)?)
+}
+
+/// Writes the targets and their attributes matching `attributes` as a Parquet file.
+pub(crate) fn write_parquet<T: QueryTarget, W: Write + Send>(
+    output: W,
+    targets: &TargetSet<T>,
+    attributes: &Option<RegexSet>,
+) -> anyhow::Result<()> {
+    let attr_columns = attr_columns(targets, attributes);
+    let schema = Arc::new(Schema::new(
+        [
+            Field::new(TARGET_COLUMN, DataType::Utf8, false),
+            Field::new(RULE_TYPE_COLUMN, DataType::Utf8, false),
+        ]
+        .into_iter()
+        .chain(
+            attr_columns
+                .iter()
+                .map(|name| Field::new(name, DataType::Utf8, true)),
+        )
+        .collect(),
+    ));
+    let attr_columns: HashMap<&str, usize> = attr_columns
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.as_str(), i))
+        .collect();
+
+    let properties = WriterProperties::builder()
+        .set_max_row_group_size(ROWS_PER_ROW_GROUP)
+        .build();
+    let mut writer = ArrowWriter::try_new(output, schema.clone(), Some(properties))?;
+    for chunk in &targets.iter().chunks(ROWS_PER_ROW_GROUP) {
+        writer.write(&record_batch(&schema, &attr_columns, chunk)?)?;
+    }
+    writer.close()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow::array::Array;
+    use arrow::array::StringArray;
+    use arrow::record_batch::RecordBatch;
+    use buck2_core::bzl::ImportPath;
+    use buck2_core::target::label::TargetLabel;
+    use buck2_node::attrs::attr::Attribute;
+    use buck2_node::attrs::attr_type::string::StringLiteral;
+    use buck2_node::attrs::attr_type::AttrType;
+    use buck2_node::attrs::coerced_attr::CoercedAttr;
+    use buck2_node::nodes::unconfigured::testing::TargetNodeExt;
+    use buck2_node::nodes::unconfigured::TargetNode;
+    use buck2_node::rule_type::RuleType;
+    use buck2_node::rule_type::StarlarkRuleType;
+    use buck2_query::query::syntax::simple::eval::set::TargetSet;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use regex::RegexSet;
+
+    use crate::commands::query::parquet::write_parquet;
+
+    fn node(label: &str, src: Option<&str>) -> TargetNode {
+        TargetNode::testing_new(
+            TargetLabel::testing_parse(label),
+            RuleType::Starlark(Arc::new(StarlarkRuleType {
+                import_path: ImportPath::testing_new("root//rules:defs.bzl"),
+                name: "my_rule".to_owned(),
+            })),
+            src.into_iter()
+                .map(|src| {
+                    (
+                        "src",
+                        Attribute::new(None, "", AttrType::string()),
+                        CoercedAttr::String(StringLiteral(src.into())),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    fn write_and_read(
+        targets: &TargetSet<TargetNode>,
+        attributes: &Option<RegexSet>,
+    ) -> anyhow::Result<RecordBatch> {
+        let mut file = tempfile::tempfile()?;
+        write_parquet(&mut file, targets, attributes)?;
+        let mut batches = ParquetRecordBatchReaderBuilder::try_new(file)?
+            .build()?
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(1, batches.len());
+        Ok(batches.pop().unwrap())
+    }
+
+    fn column(batch: &RecordBatch, i: usize) -> Vec<Option<&str>> {
+        batch
+            .column(i)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap()
+            .iter()
+            .collect()
+    }
+
+    fn column_names(batch: &RecordBatch) -> Vec<String> {
+        batch
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| f.name().clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_write_parquet() -> anyhow::Result<()> {
+        let targets: TargetSet<_> = [
+            node("root//foo:bar", Some("bar.c")),
+            node("root//foo:baz", None),
+        ]
+        .into_iter()
+        .collect();
+        // `buck.type` is always a column, so it does not get a second one.
+        let batch = write_and_read(&targets, &Some(RegexSet::new(["^src$", "^buck.type$"])?))?;
+
+        assert_eq!(
+            vec!["buck.target", "buck.type", "src"],
+            column_names(&batch)
+        );
+        assert_eq!(
+            vec![Some("root//foo:bar"), Some("root//foo:baz")],
+            column(&batch, 0)
+        );
+        assert_eq!(vec![Some("my_rule"), Some("my_rule")], column(&batch, 1));
+        assert_eq!(vec![Some("\"bar.c\""), None], column(&batch, 2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_parquet_without_attributes() -> anyhow::Result<()> {
+        let targets: TargetSet<_> = [node("root//foo:bar", Some("bar.c"))].into_iter().collect();
+        let batch = write_and_read(&targets, &None)?;
+
+        assert_eq!(vec!["buck.target", "buck.type"], column_names(&batch));
+        assert_eq!(vec![Some("root//foo:bar")], column(&batch, 0));
+        Ok(())
+    }
+}
@@ -9,6 +9,7 @@
 
 #![allow(clippy::drop_non_drop)] // FIXME?
 
+use std::collections::BTreeMap;
 use std::fmt::Display;
 use std::fmt::Formatter;
 
@@ -35,6 +36,7 @@ use serde::ser::SerializeSeq;
 use serde::Serialize;
 use serde::Serializer;
 
+use crate::commands::query::parquet::write_parquet;
 use crate::commands::query::QueryCommandError;
 use crate::dot::targets::DotTargetGraph;
 use crate::dot::Dot;
@@ -57,6 +59,8 @@ pub struct QueryResultPrinter<'a> {
     resolver: &'a CellResolver,
     attributes: Option<RegexSet>,
     output_format: QueryOutputFormat,
+    /// Annotate dependency edges with their kind (dot and json outputs).
+    dep_kinds: bool,
 }
 
 struct TargetSetJsonPrinter<'a, T: QueryTarget> {
@@ -69,12 +73,20 @@ impl<'a, T: QueryTarget> TargetSetJsonPrinter<'a, T> {
         target_call_stacks: bool,
         print_providers: ShouldPrintProviders<'a, T>,
         attributes: &'a Option<RegexSet>,
+        dep_kinds: bool,
         targets: &'a TargetSet<T>,
     ) -> anyhow::Result<TargetSetJsonPrinter<'a, T>> {
         Ok(TargetSetJsonPrinter {
-            value: printable_targets(targets, print_providers, attributes, target_call_stacks)
-                .await?,
+            value: printable_targets(
+                targets,
+                print_providers,
+                attributes,
+                dep_kinds,
+                target_call_stacks,
+            )
+            .await?,
             is_complex: attributes.is_some()
+                || dep_kinds
                 || target_call_stacks
                 || print_providers.unpack_yes().is_some(),
         })
@@ -84,6 +96,7 @@ impl<'a, T: QueryTarget> TargetSetJsonPrinter<'a, T> {
 struct PrintableQueryTarget<'a, T: QueryTarget> {
     value: &'a T,
     attributes: &'a Option<RegexSet>,
+    dep_kinds: bool,
     providers: Option<FrozenProviderCollectionValue>,
     target_call_stacks: bool,
 }
@@ -133,34 +146,44 @@ impl<'a, T: QueryTarget> Serialize for PrintableQueryTarget<'a, T> {
     {
         let mut map = serializer.serialize_map(None)?;
 
-        QueryTargets::for_all_attrs(self.value, |attr_name, attr_value| {
-            if let Some(attr_regex) = self.attributes {
-                if attr_regex.is_match(attr_name) {
-                    struct AttrValueSerialize<'a, 'b, T: QueryTarget> {
-                        target: &'a T,
-                        attr: &'a T::Attr<'b>,
-                    }
+        if let Some(attr_regex) = self.attributes {
+            struct AttrValueSerialize<'a, 'b, T: QueryTarget> {
+                target: &'a T,
+                attr: &'a T::Attr<'b>,
+            }
 
-                    impl<'a, 'b, T: QueryTarget> Serialize for AttrValueSerialize<'a, 'b, T> {
-                        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-                        where
-                            S: Serializer,
-                        {
-                            self.target.attr_serialize(self.attr, serializer)
-                        }
-                    }
+            impl<'a, 'b, T: QueryTarget> Serialize for AttrValueSerialize<'a, 'b, T> {
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where
+                    S: Serializer,
+                {
+                    self.target.attr_serialize(self.attr, serializer)
+                }
+            }
 
+            // Only the requested attributes are resolved, the others are skipped.
+            QueryTargets::for_all_attrs_matching(
+                self.value,
+                &|attr_name: &str| attr_regex.is_match(attr_name),
+                |attr_name, attr_value| {
                     map.serialize_entry(
                         attr_name,
                         &AttrValueSerialize {
                             target: self.value,
                             attr: attr_value,
                         },
-                    )?;
-                }
+                    )
+                },
+            )?;
+        }
+
+        if self.dep_kinds {
+            let mut deps: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+            for (dep, kind) in self.value.deps_with_kinds() {
+                deps.entry(kind.as_str()).or_default().push(dep.to_string());
             }
-            Ok(())
-        })?;
+            map.serialize_entry("buck.deps", &deps)?;
+        }
 
         if self.target_call_stacks {
             map.serialize_entry("buck.target_call_stack", &self.value.call_stack())?;
@@ -247,10 +270,18 @@ impl<'a> QueryResultPrinter<'a> {
             resolver,
             attributes,
             output_format,
+            dep_kinds: false,
         })
     }
 
-    pub async fn print_multi_output<'b, T: QueryTarget, W: std::io::Write>(
+    /// Annotate each dependency edge with its kind: as edge labels in dot outputs, and as a
+    /// `buck.deps` entry mapping each kind to the deps of that kind in json output.
+    pub fn with_dep_kinds(mut self, dep_kinds: bool) -> Self {
+        self.dep_kinds = dep_kinds;
+        self
+    }
+
+    pub async fn print_multi_output<'b, T: QueryTarget, W: std::io::Write + Send>(
         &self,
         mut output: W,
         multi_result: MultiQueryResult<T>,
@@ -277,6 +308,7 @@ impl<'a> QueryResultPrinter<'a> {
                                     target_call_stacks,
                                     print_providers,
                                     &self.attributes,
+                                    self.dep_kinds,
                                     &targets,
                                 )
                                 .await?,
@@ -316,7 +348,7 @@ impl<'a> QueryResultPrinter<'a> {
         }
     }
 
-    pub async fn print_single_output<'b, T: QueryTarget, W: std::io::Write>(
+    pub async fn print_single_output<'b, T: QueryTarget, W: std::io::Write + Send>(
         &self,
         mut output: W,
         result: QueryEvaluationValue<T>,
@@ -326,9 +358,14 @@ impl<'a> QueryResultPrinter<'a> {
         match result {
             QueryEvaluationValue::TargetSet(targets) => match self.output_format {
                 QueryOutputFormat::Default => {
-                    for target in
-                        printable_targets(&targets, print_providers, &self.attributes, call_stack)
-                            .await?
+                    for target in printable_targets(
+                        &targets,
+                        print_providers,
+                        &self.attributes,
+                        false,
+                        call_stack,
+                    )
+                    .await?
                     {
                         writeln!(&mut output, "{}", target)?;
                     }
@@ -339,6 +376,7 @@ impl<'a> QueryResultPrinter<'a> {
                         call_stack,
                         print_providers,
                         &self.attributes,
+                        self.dep_kinds,
                         &targets,
                     )
                     .await?
@@ -352,6 +390,7 @@ impl<'a> QueryResultPrinter<'a> {
                         &DotTargetGraph {
                             targets,
                             attributes: self.attributes.clone(),
+                            dep_kinds: self.dep_kinds,
                         },
                         &mut output,
                     )?;
@@ -361,10 +400,14 @@ impl<'a> QueryResultPrinter<'a> {
                         &DotTargetGraph {
                             targets,
                             attributes: self.attributes.clone(),
+                            dep_kinds: self.dep_kinds,
                         },
                         &mut output,
                     )?;
                 }
+                QueryOutputFormat::StreamingParquet => {
+                    write_parquet(&mut output, &targets, &self.attributes)?;
+                }
             },
             QueryEvaluationValue::FileSet(files) => {
                 if self.attributes.is_some() {
@@ -397,6 +440,9 @@ impl<'a> QueryResultPrinter<'a> {
                     QueryOutputFormat::DotCompact => {
                         unimplemented!("dot_compact output for files not implemented yet")
                     }
+                    QueryOutputFormat::StreamingParquet => {
+                        return Err(QueryCommandError::FileSetHasNoParquetOutput.into());
+                    }
                 }
             }
         }
@@ -409,6 +455,7 @@ async fn printable_targets<'a, T: QueryTarget>(
     targets: &'a TargetSet<T>,
     print_providers: ShouldPrintProviders<'a, T>,
     attributes: &'a Option<RegexSet>,
+    dep_kinds: bool,
     target_call_stacks: bool,
 ) -> anyhow::Result<Vec<PrintableQueryTarget<'a, T>>> {
     futures::future::join_all(targets.iter().map(|t| {
@@ -417,6 +464,7 @@ async fn printable_targets<'a, T: QueryTarget>(
             Ok(PrintableQueryTarget {
                 value: t,
                 attributes,
+                dep_kinds,
                 target_call_stacks,
                 providers: match print_providers {
                     ShouldPrintProviders::No => None,
@@ -431,3 +479,93 @@ async fn printable_targets<'a, T: QueryTarget>(
     .into_iter()
     .collect::<anyhow::Result<_>>()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use buck2_core::bzl::ImportPath;
+    use buck2_core::provider::label::ProvidersLabel;
+    use buck2_core::provider::label::ProvidersName;
+    use buck2_core::target::label::TargetLabel;
+    use buck2_node::attrs::attr::Attribute;
+    use buck2_node::attrs::attr_type::list::ListLiteral;
+    use buck2_node::attrs::attr_type::AttrType;
+    use buck2_node::attrs::coerced_attr::CoercedAttr;
+    use buck2_node::nodes::unconfigured::testing::TargetNodeExt;
+    use buck2_node::nodes::unconfigured::TargetNode;
+    use buck2_node::provider_id_set::ProviderIdSet;
+    use buck2_node::rule_type::RuleType;
+    use buck2_node::rule_type::StarlarkRuleType;
+
+    use crate::commands::query::printer::PrintableQueryTarget;
+
+    fn deps(deps: &[&str]) -> CoercedAttr {
+        CoercedAttr::List(ListLiteral(
+            deps.iter()
+                .map(|dep| {
+                    CoercedAttr::Dep(ProvidersLabel::new(
+                        TargetLabel::testing_parse(dep),
+                        ProvidersName::Default,
+                    ))
+                })
+                .collect(),
+        ))
+    }
+
+    fn node() -> TargetNode {
+        TargetNode::testing_new(
+            TargetLabel::testing_parse("root//:a"),
+            RuleType::Starlark(Arc::new(StarlarkRuleType {
+                import_path: ImportPath::testing_new("root//rules:defs.bzl"),
+                name: "my_rule".to_owned(),
+            })),
+            vec![
+                (
+                    "deps",
+                    Attribute::new(
+                        None,
+                        "",
+                        AttrType::list(AttrType::dep(ProviderIdSet::EMPTY)),
+                    ),
+                    deps(&["root//:b", "root//:c"]),
+                ),
+                (
+                    "exec_deps",
+                    Attribute::new(
+                        None,
+                        "",
+                        AttrType::list(AttrType::exec_dep(ProviderIdSet::EMPTY)),
+                    ),
+                    deps(&["root//:d"]),
+                ),
+            ],
+        )
+    }
+
+    fn to_json(node: &TargetNode, dep_kinds: bool) -> anyhow::Result<serde_json::Value> {
+        Ok(serde_json::to_value(PrintableQueryTarget {
+            value: node,
+            attributes: &None,
+            dep_kinds,
+            providers: None,
+            target_call_stacks: false,
+        })?)
+    }
+
+    #[test]
+    fn test_json_dep_kinds() -> anyhow::Result<()> {
+        let node = node();
+        assert_eq!(
+            serde_json::json!({
+                "buck.deps": {
+                    "exec": ["root//:d"],
+                    "target": ["root//:b", "root//:c"],
+                },
+            }),
+            to_json(&node, true)?
+        );
+        assert_eq!(serde_json::json!({}), to_json(&node, false)?);
+        Ok(())
+    }
+}
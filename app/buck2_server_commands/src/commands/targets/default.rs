@@ -46,6 +46,7 @@ pub(crate) struct TargetHashOptions {
     fast_hash: bool,
     graph_type: TargetHashGraphType,
     recursive: bool,
+    build_file_inputs: bool,
 }
 
 impl TargetHashOptions {
@@ -78,6 +79,7 @@ impl TargetHashOptions {
             graph_type: TargetHashGraphType::from_i32(request.target_hash_graph_type)
                 .expect("buck cli should send valid target hash graph type"),
             recursive: request.target_hash_recursive,
+            build_file_inputs: request.target_hash_build_file_inputs,
         })
     }
 }
@@ -103,6 +105,7 @@ pub(crate) async fn targets_batch(
                 hash_options.file_mode,
                 hash_options.fast_hash,
                 hash_options.recursive,
+                hash_options.build_file_inputs,
             )
             .await?,
         ),
@@ -115,6 +118,7 @@ pub(crate) async fn targets_batch(
                 hash_options.file_mode,
                 hash_options.fast_hash,
                 hash_options.recursive,
+                hash_options.build_file_inputs,
             )
             .await?,
         ),
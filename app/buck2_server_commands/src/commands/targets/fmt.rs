@@ -19,8 +19,9 @@ use buck2_cli_proto::TargetsRequest;
 use buck2_core::bzl::ImportPath;
 use buck2_core::cells::cell_path::CellPath;
 use buck2_core::package::PackageLabel;
-use buck2_node::attrs::hacks::value_to_json;
+use buck2_node::attrs::fmt_context::AttrFmtContext;
 use buck2_node::attrs::inspect_options::AttrInspectOptions;
+use buck2_node::attrs::serialize::AttrSerializeWithContextExt;
 use buck2_node::nodes::attributes::DEPS;
 use buck2_node::nodes::attributes::INPUTS;
 use buck2_node::nodes::attributes::PACKAGE;
@@ -32,6 +33,7 @@ use buck2_util::indent::indent;
 use gazebo::prelude::SliceExt;
 use regex::RegexSet;
 
+use crate::dot::escape_id;
 use crate::json::QuotedJson;
 use crate::target_hash::BuckTargetHash;
 
@@ -71,6 +73,12 @@ pub(crate) trait TargetFormatter: Send + Sync {
         stderr: &mut String,
     ) {
     }
+    /// Whether `imports` can be given everything a file transitively loads, for
+    /// `--recursive-imports`. Formats which print an edge per import want the direct loads of
+    /// every file instead, as flattening would add edges between files which don't load each other.
+    fn transitive_imports(&self) -> bool {
+        true
+    }
 }
 
 pub(crate) struct JsonWriter {
@@ -201,11 +209,12 @@ impl TargetFormatter for JsonFormat {
             QuotedJson::quote_display(target_info.node.label().pkg())
         });
 
+        let ctx = AttrFmtContext {
+            package: Some(target_info.node.label().pkg()),
+        };
         for a in target_info.node.attrs(self.attr_inspect_opts) {
             print_attr(self, buffer, &mut first, a.name, || {
-                QuotedJson::from_serde_json_value(
-                    value_to_json(a.value, target_info.node.label().pkg()).unwrap(),
-                )
+                QuotedJson::from_serialize(&a.value.as_serialize(&ctx))
             });
         }
 
@@ -336,6 +345,103 @@ impl TargetFormatter for TargetNameFormat {
     }
 }
 
+/// Prints the imports as a DOT graph, with an edge from each file to each file it imports.
+/// Targets are not printed.
+struct DotFormat;
+
+impl TargetFormatter for DotFormat {
+    fn begin(&self, buffer: &mut String) {
+        buffer.push_str("digraph imports {\n");
+    }
+
+    fn end(&self, _stats: &Stats, buffer: &mut String) {
+        buffer.push_str("}\n");
+    }
+
+    fn imports(
+        &self,
+        source: &CellPath,
+        imports: &[ImportPath],
+        _package: Option<PackageLabel>,
+        buffer: &mut String,
+    ) {
+        // Always emit the node, so files without imports still show up.
+        let source = escape_id(&source.to_string());
+        writeln!(buffer, "  {};", source).unwrap();
+        for import in imports {
+            writeln!(
+                buffer,
+                "  {} -> {};",
+                source,
+                escape_id(&import.path().to_string())
+            )
+            .unwrap();
+        }
+    }
+
+    fn package_error(
+        &self,
+        package: PackageLabel,
+        error: &anyhow::Error,
+        _stdout: &mut String,
+        stderr: &mut String,
+    ) {
+        writeln!(stderr, "Error parsing {}\n{:?}", package, error).unwrap();
+    }
+
+    fn transitive_imports(&self) -> bool {
+        false
+    }
+}
+
+/// Prints each target as the rule call that would produce it, i.e. after macros have been
+/// expanded, but before configuration.
+struct StarlarkFormat {
+    attr_inspect_opts: AttrInspectOptions,
+    target_call_stacks: bool,
+}
+
+impl TargetFormatter for StarlarkFormat {
+    fn separator(&self, buffer: &mut String) {
+        buffer.push('\n');
+    }
+
+    fn target(&self, target_info: TargetInfo<'_>, buffer: &mut String) {
+        let node = target_info.node;
+        if self.target_call_stacks {
+            if let Some(call_stack) = node.call_stack() {
+                for line in call_stack.lines() {
+                    writeln!(buffer, "# {}", line).unwrap();
+                }
+            }
+        }
+        let ctx = AttrFmtContext {
+            package: Some(node.label().pkg()),
+        };
+        writeln!(buffer, "{}(", node.rule_type().name()).unwrap();
+        for a in node.attrs(self.attr_inspect_opts) {
+            writeln!(
+                buffer,
+                "    {} = {},",
+                a.name,
+                a.value.to_starlark_string(&ctx)
+            )
+            .unwrap();
+        }
+        buffer.push_str(")\n");
+    }
+
+    fn package_error(
+        &self,
+        package: PackageLabel,
+        error: &anyhow::Error,
+        _stdout: &mut String,
+        stderr: &mut String,
+    ) {
+        writeln!(stderr, "Error parsing {}\n{:?}", package, error).unwrap();
+    }
+}
+
 pub(crate) fn print_target_call_stack_after_target(out: &mut String, call_stack: Option<&str>) {
     if let Some(call_stack) = call_stack {
         write!(out, "{}", indent("  ", call_stack)).unwrap();
@@ -364,6 +470,15 @@ pub(crate) fn create_formatter(
     match output_format {
         OutputFormat::Unknown => Err(FormatterError::OutputFormatNotSet.into()),
         OutputFormat::Stats => Ok(Arc::new(StatsFormat)),
+        OutputFormat::Dot => Ok(Arc::new(DotFormat)),
+        OutputFormat::Starlark => Ok(Arc::new(StarlarkFormat {
+            attr_inspect_opts: if other.include_default_attributes {
+                AttrInspectOptions::All
+            } else {
+                AttrInspectOptions::DefinedOnly
+            },
+            target_call_stacks,
+        })),
         OutputFormat::Text => Ok(Arc::new(TargetNameFormat {
             target_call_stacks,
             target_hash_graph_type: TargetHashGraphType::from_i32(other.target_hash_graph_type)
@@ -387,3 +502,116 @@ pub(crate) fn create_formatter(
         })),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use buck2_core::bzl::ImportPath;
+    use buck2_core::cells::cell_path::CellPath;
+    use buck2_core::target::label::TargetLabel;
+    use buck2_node::attrs::attr::Attribute;
+    use buck2_node::attrs::attr_type::string::StringLiteral;
+    use buck2_node::attrs::attr_type::AttrType;
+    use buck2_node::attrs::coerced_attr::CoercedAttr;
+    use buck2_node::attrs::inspect_options::AttrInspectOptions;
+    use buck2_node::nodes::unconfigured::testing::TargetNodeExt;
+    use buck2_node::nodes::unconfigured::TargetNode;
+    use buck2_node::rule_type::RuleType;
+    use buck2_node::rule_type::StarlarkRuleType;
+
+    use crate::commands::targets::fmt::DotFormat;
+    use crate::commands::targets::fmt::StarlarkFormat;
+    use crate::commands::targets::fmt::Stats;
+    use crate::commands::targets::fmt::TargetFormatter;
+    use crate::commands::targets::fmt::TargetInfo;
+
+    #[test]
+    fn test_starlark_format_call_stacks() {
+        let node = TargetNode::testing_new_with_call_stack(
+            TargetLabel::testing_parse("root//foo:bar"),
+            RuleType::Starlark(Arc::new(StarlarkRuleType {
+                import_path: ImportPath::testing_new("root//rules:defs.bzl"),
+                name: "my_rule".to_owned(),
+            })),
+            vec![(
+                "src",
+                Attribute::new(None, "", AttrType::string()),
+                CoercedAttr::String(StringLiteral("bar.c".into())),
+            )],
+            "File <builtin>, in <module>\n  * foo/BUCK:1, in <module>\n    my_macro()",
+        );
+        let target_info = || TargetInfo {
+            node: &node,
+            target_hash: None,
+        };
+
+        let expected = r#"my_rule(
+    name = "bar",
+    src = "bar.c",
+)
+"#;
+
+        let mut buffer = String::new();
+        StarlarkFormat {
+            attr_inspect_opts: AttrInspectOptions::DefinedOnly,
+            target_call_stacks: false,
+        }
+        .target(target_info(), &mut buffer);
+        assert_eq!(buffer, expected);
+
+        let mut buffer = String::new();
+        StarlarkFormat {
+            attr_inspect_opts: AttrInspectOptions::DefinedOnly,
+            target_call_stacks: true,
+        }
+        .target(target_info(), &mut buffer);
+        assert_eq!(
+            buffer,
+            format!(
+                "# File <builtin>, in <module>\n#   * foo/BUCK:1, in <module>\n#     my_macro()\n{}",
+                expected
+            )
+        );
+    }
+
+    #[test]
+    fn test_dot_format_edges_per_file() {
+        let formatter = DotFormat;
+        assert!(!formatter.transitive_imports());
+
+        let mut buffer = String::new();
+        formatter.begin(&mut buffer);
+        formatter.imports(
+            &CellPath::testing_new("root//foo/BUCK"),
+            &[ImportPath::testing_new("root//foo:a.bzl")],
+            None,
+            &mut buffer,
+        );
+        formatter.imports(
+            ImportPath::testing_new("root//foo:a.bzl").path(),
+            &[ImportPath::testing_new("root//bar:b.bzl")],
+            None,
+            &mut buffer,
+        );
+        formatter.imports(
+            ImportPath::testing_new("root//bar:b.bzl").path(),
+            &[],
+            None,
+            &mut buffer,
+        );
+        formatter.end(&Stats::default(), &mut buffer);
+
+        assert_eq!(
+            buffer,
+            r#"digraph imports {
+  "root//foo/BUCK";
+  "root//foo/BUCK" -> "root//foo/a.bzl";
+  "root//foo/a.bzl";
+  "root//foo/a.bzl" -> "root//bar/b.bzl";
+  "root//bar/b.bzl";
+}
+"#
+        );
+    }
+}
@@ -10,7 +10,8 @@
 mod default;
 pub(crate) mod fmt;
 mod resolve_alias;
-mod streaming;
+mod snapshot;
+pub(crate) mod streaming;
 
 use std::fs::File;
 use std::io::BufWriter;
@@ -19,6 +20,7 @@ use std::io::Write;
 use anyhow::Context as _;
 use async_trait::async_trait;
 use buck2_cli_proto::targets_request;
+use buck2_cli_proto::targets_request::OutputFormat;
 use buck2_cli_proto::targets_request::TargetHashGraphType;
 use buck2_cli_proto::HasClientContext;
 use buck2_cli_proto::TargetsRequest;
@@ -37,6 +39,7 @@ use crate::commands::targets::default::targets_batch;
 use crate::commands::targets::default::TargetHashOptions;
 use crate::commands::targets::fmt::create_formatter;
 use crate::commands::targets::resolve_alias::targets_resolve_aliases;
+use crate::commands::targets::snapshot::targets_snapshot;
 use crate::commands::targets::streaming::targets_streaming;
 
 #[derive(Debug, thiserror::Error)]
@@ -172,7 +175,17 @@ async fn targets(
             targets_resolve_aliases(dice, request, parsed_target_patterns).await?
         }
         Some(targets_request::Targets::Other(other)) => {
-            if other.streaming {
+            if other.snapshot || other.diff_snapshot.is_some() {
+                targets_snapshot(
+                    server_ctx,
+                    dice,
+                    parsed_target_patterns,
+                    other.keep_going,
+                    other.diff_snapshot.as_deref(),
+                    request.output_format == OutputFormat::Json as i32,
+                )
+                .await?
+            } else if other.streaming {
                 let formatter = create_formatter(request, other)?;
                 let hashing = match TargetHashGraphType::from_i32(other.target_hash_graph_type)
                     .expect("buck cli should send valid target hash graph type")
@@ -191,6 +204,7 @@ async fn targets(
                     other.keep_going,
                     other.cached,
                     other.imports,
+                    other.recursive_imports,
                     hashing,
                     request.concurrency.as_ref().map(|x| x.concurrency as usize),
                 )
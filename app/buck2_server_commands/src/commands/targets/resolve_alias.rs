@@ -15,6 +15,10 @@ enum ResolveAliasError {
     OutputFormatNotSet,
     #[error("`--stat` format is not supported by `--resolve-alias`")]
     StatFormatNotSupported,
+    #[error("`--dot` format is not supported by `--resolve-alias`")]
+    DotFormatNotSupported,
+    #[error("Starlark format is not supported by `--resolve-alias`")]
+    StarlarkFormatNotSupported,
 }
 
 use std::collections::HashMap;
@@ -163,6 +167,10 @@ pub(crate) async fn targets_resolve_aliases(
             &json_writer as &dyn ResolveAliasFormatter
         }
         OutputFormat::Stats => return Err(ResolveAliasError::StatFormatNotSupported.into()),
+        OutputFormat::Dot => return Err(ResolveAliasError::DotFormatNotSupported.into()),
+        OutputFormat::Starlark => {
+            return Err(ResolveAliasError::StarlarkFormatNotSupported.into());
+        }
     };
 
     let mut needs_separator = false;
@@ -0,0 +1,315 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Server-side implementation of `buck2 targets --snapshot` and `buck2 targets --diff`.
+//!
+//! A snapshot lists the targets matching the patterns along with a hash of their attributes (the
+//! unconfigured, non-recursive target hash), one per line. Diffing the current targets against a
+//! snapshot taken on an older revision reports which targets were added, removed, or changed,
+//! which is enough for CI to summarize the impact of a change at review time.
+//!
+//! Packages which failed to evaluate (with `--keep-going`) are recorded as `! <package>` lines.
+//! Their targets are unknown, so the diff reports these packages as errors rather than claiming
+//! their targets were added or removed.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+use std::io::Write;
+
+use anyhow::Context;
+use buck2_build_api::calculation::load_patterns;
+use buck2_build_api::calculation::MissingTargetBehavior;
+use buck2_cli_proto::TargetsResponse;
+use buck2_core::fs::fs_util;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_core::pattern::ParsedPattern;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use dice::DiceTransaction;
+use serde::Serialize;
+
+use crate::commands::targets::mk_error;
+use crate::target_hash::TargetHashes;
+
+/// First line of a snapshot, so that we fail on files which are not snapshots, or come from an
+/// incompatible version.
+const SNAPSHOT_HEADER: &str = "# buck2 targets snapshot v1";
+
+#[derive(Debug, thiserror::Error)]
+enum TargetsSnapshotError {
+    #[error(
+        "`{0}` is not a targets snapshot (expected it to start with `{}`)",
+        SNAPSHOT_HEADER
+    )]
+    NotASnapshot(String),
+    #[error("Invalid line {line} in targets snapshot `{path}`: `{content}`")]
+    InvalidLine {
+        path: String,
+        line: usize,
+        content: String,
+    },
+}
+
+/// Labels of targets, and the hashes of their attributes.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct TargetsSnapshot {
+    targets: BTreeMap<String, String>,
+    /// Packages which failed to evaluate.
+    errors: BTreeSet<String>,
+}
+
+/// Targets which differ between two snapshots, sorted by label.
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+struct TargetsDiff {
+    added: Vec<String>,
+    removed: Vec<String>,
+    changed: Vec<String>,
+    /// Packages which failed to evaluate in either snapshot, whose targets are not compared.
+    errors: Vec<String>,
+}
+
+/// The package of a target label, e.g. `root//foo` for `root//foo:bar`.
+fn package_of(label: &str) -> &str {
+    label.rsplit_once(':').map_or(label, |(package, _)| package)
+}
+
+impl TargetsSnapshot {
+    fn parse(path: &str, content: &str) -> anyhow::Result<Self> {
+        let mut lines = content.lines();
+        if lines.next() != Some(SNAPSHOT_HEADER) {
+            return Err(TargetsSnapshotError::NotASnapshot(path.to_owned()).into());
+        }
+        let mut snapshot = TargetsSnapshot::default();
+        for (i, line) in lines.enumerate() {
+            if let Some(package) = line.strip_prefix("! ") {
+                snapshot.errors.insert(package.to_owned());
+                continue;
+            }
+            let (hash, label) =
+                line.split_once(' ')
+                    .ok_or_else(|| TargetsSnapshotError::InvalidLine {
+                        path: path.to_owned(),
+                        // Lines are numbered from 1, and we skipped the header.
+                        line: i + 2,
+                        content: line.to_owned(),
+                    })?;
+            snapshot.targets.insert(label.to_owned(), hash.to_owned());
+        }
+        Ok(snapshot)
+    }
+
+    fn write(&self, buffer: &mut String) {
+        buffer.push_str(SNAPSHOT_HEADER);
+        buffer.push('\n');
+        for package in &self.errors {
+            writeln!(buffer, "! {}", package).unwrap();
+        }
+        for (label, hash) in &self.targets {
+            writeln!(buffer, "{} {}", hash, label).unwrap();
+        }
+    }
+
+    /// The changes from `old` to `self`.
+    fn diff(&self, old: &TargetsSnapshot) -> TargetsDiff {
+        let errors: BTreeSet<&str> = self
+            .errors
+            .iter()
+            .chain(&old.errors)
+            .map(|p| p.as_str())
+            .collect();
+        let compared = |label: &&String| !errors.contains(package_of(label));
+
+        let mut diff = TargetsDiff::default();
+        for (label, hash) in self.targets.iter().filter(|(label, _)| compared(label)) {
+            match old.targets.get(label) {
+                None => diff.added.push(label.clone()),
+                Some(old_hash) if old_hash != hash => diff.changed.push(label.clone()),
+                Some(_) => {}
+            }
+        }
+        diff.removed = old
+            .targets
+            .keys()
+            .filter(compared)
+            .filter(|label| !self.targets.contains_key(*label))
+            .cloned()
+            .collect();
+        diff.errors = errors.into_iter().map(|p| p.to_owned()).collect();
+        diff
+    }
+}
+
+impl TargetsDiff {
+    fn write(&self, buffer: &mut String, json: bool) -> anyhow::Result<()> {
+        if json {
+            buffer.push_str(&serde_json::to_string_pretty(self)?);
+            buffer.push('\n');
+        } else {
+            let sections = [
+                ("+", &self.added),
+                ("-", &self.removed),
+                ("~", &self.changed),
+                ("!", &self.errors),
+            ];
+            for (prefix, labels) in sections {
+                for label in labels {
+                    writeln!(buffer, "{} {}", prefix, label)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Print a snapshot of the targets matching the patterns or, if `diff` is the path of a snapshot,
+/// how they differ from it.
+pub(crate) async fn targets_snapshot(
+    server_ctx: &dyn ServerCommandContextTrait,
+    dice: DiceTransaction,
+    parsed_patterns: Vec<ParsedPattern<TargetPatternExtra>>,
+    keep_going: bool,
+    diff: Option<&str>,
+    json: bool,
+) -> anyhow::Result<TargetsResponse> {
+    let results = load_patterns(&dice, parsed_patterns, MissingTargetBehavior::Fail).await?;
+
+    let mut snapshot = TargetsSnapshot::default();
+    let mut errors = 0;
+    for (package, result) in results.iter() {
+        match result {
+            Ok(res) => {
+                for (_, node) in res.iter() {
+                    let hash = TargetHashes::compute_immediate_one(node, false);
+                    snapshot
+                        .targets
+                        .insert(node.label().to_string(), hash.to_string());
+                }
+            }
+            Err(e) => {
+                errors += 1;
+                writeln!(
+                    server_ctx.stderr()?,
+                    "Error parsing {}: {:#}",
+                    package,
+                    e.inner()
+                )?;
+                if !keep_going {
+                    return Err(mk_error(errors));
+                }
+                snapshot.errors.insert(package.to_string());
+            }
+        }
+    }
+
+    let mut buffer = String::new();
+    match diff {
+        Some(path) => {
+            let content = fs_util::read_to_string(path)
+                .with_context(|| format!("Error reading targets snapshot `{}`", path))?;
+            let old = TargetsSnapshot::parse(path, &content)?;
+            snapshot.diff(&old).write(&mut buffer, json)?;
+        }
+        None => snapshot.write(&mut buffer),
+    }
+    Ok(TargetsResponse {
+        error_count: errors,
+        serialized_targets_output: buffer,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::commands::targets::snapshot::TargetsDiff;
+    use crate::commands::targets::snapshot::TargetsSnapshot;
+
+    fn snapshot(targets: &[(&str, &str)]) -> TargetsSnapshot {
+        snapshot_with_errors(targets, &[])
+    }
+
+    fn snapshot_with_errors(targets: &[(&str, &str)], errors: &[&str]) -> TargetsSnapshot {
+        TargetsSnapshot {
+            targets: targets
+                .iter()
+                .map(|(label, hash)| ((*label).to_owned(), (*hash).to_owned()))
+                .collect(),
+            errors: errors.iter().map(|p| (*p).to_owned()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_write_and_parse() -> anyhow::Result<()> {
+        let snapshot = snapshot(&[("root//b:b", "0002"), ("root//a:a", "0001")]);
+        let mut buffer = String::new();
+        snapshot.write(&mut buffer);
+        assert_eq!(
+            buffer,
+            "# buck2 targets snapshot v1\n0001 root//a:a\n0002 root//b:b\n"
+        );
+        assert_eq!(TargetsSnapshot::parse("snapshot", &buffer)?, snapshot);
+
+        let snapshot = snapshot_with_errors(&[("root//a:a", "0001")], &["root//c"]);
+        let mut buffer = String::new();
+        snapshot.write(&mut buffer);
+        assert_eq!(
+            buffer,
+            "# buck2 targets snapshot v1\n! root//c\n0001 root//a:a\n"
+        );
+        assert_eq!(TargetsSnapshot::parse("snapshot", &buffer)?, snapshot);
+
+        assert!(TargetsSnapshot::parse("snapshot", "root//a:a\n").is_err());
+        assert!(TargetsSnapshot::parse("snapshot", "# buck2 targets snapshot v1\nbad\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff() -> anyhow::Result<()> {
+        let old = snapshot(&[("root//a:a", "1"), ("root//b:b", "2"), ("root//c:c", "3")]);
+        let new = snapshot(&[("root//a:a", "1"), ("root//b:b", "4"), ("root//d:d", "5")]);
+        let diff = new.diff(&old);
+        assert_eq!(
+            diff,
+            TargetsDiff {
+                added: vec!["root//d:d".to_owned()],
+                removed: vec!["root//c:c".to_owned()],
+                changed: vec!["root//b:b".to_owned()],
+                errors: Vec::new(),
+            }
+        );
+
+        let mut buffer = String::new();
+        diff.write(&mut buffer, false)?;
+        assert_eq!(buffer, "+ root//d:d\n- root//c:c\n~ root//b:b\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_with_failed_packages() -> anyhow::Result<()> {
+        let old = snapshot_with_errors(
+            &[("root//a:a", "1"), ("root//b:b", "2"), ("root//b:c", "3")],
+            &["root//d"],
+        );
+        // `root//b` fails to evaluate now, and `root//d` evaluates again.
+        let new = snapshot_with_errors(&[("root//a:a", "4"), ("root//d:d", "5")], &["root//b"]);
+        let diff = new.diff(&old);
+        assert_eq!(
+            diff,
+            TargetsDiff {
+                added: Vec::new(),
+                removed: Vec::new(),
+                changed: vec!["root//a:a".to_owned()],
+                errors: vec!["root//b".to_owned(), "root//d".to_owned()],
+            }
+        );
+
+        let mut buffer = String::new();
+        diff.write(&mut buffer, false)?;
+        assert_eq!(buffer, "~ root//a:a\n! root//b\n! root//d\n");
+        Ok(())
+    }
+}
@@ -62,6 +62,7 @@ pub(crate) async fn targets_streaming(
     keep_going: bool,
     cached: bool,
     imports: bool,
+    recursive_imports: bool,
     fast_hash: Option<bool>, // None = no hashing
     threads: Option<usize>,
 ) -> anyhow::Result<TargetsResponse> {
@@ -72,6 +73,13 @@ pub(crate) async fn targets_streaming(
         stdout: String,         // Print to stdout
     }
 
+    // With `--recursive-imports` each file lists everything it transitively loads,
+    // so there is no need to print the imported files separately afterwards.
+    // Formats which can't show that print the direct loads of every imported file instead,
+    // which is the same as `--imports`.
+    let imports = imports || recursive_imports;
+    let recursive_imports = recursive_imports && formatter.transitive_imports();
+
     let imported = Arc::new(Mutex::new(SmallSet::new()));
     let threads = Arc::new(Semaphore::new(threads.unwrap_or(Semaphore::MAX_PERMITS)));
 
@@ -109,17 +117,19 @@ pub(crate) async fn targets_streaming(
                             }
                             res.stats.success += 1;
                             if imports {
-                                let eval_imports = eval_result.imports();
+                                let eval_imports = if recursive_imports {
+                                    transitive_imports(&dice, eval_result.imports().to_vec())
+                                        .await?
+                                } else {
+                                    eval_result.imports().to_vec()
+                                };
                                 formatter.imports(
                                     &eval_result.buildfile_path().path(),
-                                    eval_imports,
+                                    &eval_imports,
                                     Some(package.dupe()),
                                     &mut res.stdout,
                                 );
-                                imported
-                                    .lock()
-                                    .unwrap()
-                                    .extend(eval_imports.iter().cloned());
+                                imported.lock().unwrap().extend(eval_imports);
                             }
                             for (i, node) in targets.iter().enumerate() {
                                 res.stats.targets += 1;
@@ -183,6 +193,11 @@ pub(crate) async fn targets_streaming(
                 // and there aren't many, so we just do it on the main thread.
                 // We ignore errors as these will bubble up as BUCK file errors already.
                 if let Ok(Some(imports)) = package_imports(&dice, &x).await {
+                    let imports = if recursive_imports {
+                        transitive_imports(&dice, imports).await?
+                    } else {
+                        imports
+                    };
                     if needs_separator {
                         formatter.separator(&mut buffer);
                     }
@@ -198,7 +213,11 @@ pub(crate) async fn targets_streaming(
     }
 
     // Recursively chase down all imported paths
-    let mut todo = mem::take(&mut *imported.lock().unwrap());
+    let mut todo = if recursive_imports {
+        SmallSet::new()
+    } else {
+        mem::take(&mut *imported.lock().unwrap())
+    };
     let mut seen_imported = HashSet::new();
     while let Some(path) = todo.pop() {
         if seen_imported.insert(path.path().clone()) {
@@ -311,6 +330,23 @@ async fn load_targets(
     }
 }
 
+/// Expand the given imports to everything they transitively load, in breadth-first order.
+pub(crate) async fn transitive_imports(
+    dice: &DiceComputations,
+    imports: Vec<ImportPath>,
+) -> anyhow::Result<Vec<ImportPath>> {
+    let mut seen: SmallSet<ImportPath> = imports.into_iter().collect();
+    let mut i = 0;
+    while let Some(path) = seen.get_index(i).cloned() {
+        // These modules were already loaded to evaluate the importing file, so this is a DICE lookup.
+        let loaded = dice.get_loaded_module_from_import_path(&path).await?;
+        let next = loaded.imports().cloned().collect::<Vec<_>>();
+        seen.extend(next);
+        i += 1;
+    }
+    Ok(seen.into_iter().collect())
+}
+
 /// Return `None` if the PACKAGE file doesn't exist
 async fn package_imports(
     dice: &DiceComputations,
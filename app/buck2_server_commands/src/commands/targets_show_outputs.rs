@@ -29,9 +29,12 @@ use buck2_core::pattern::ParsedPattern;
 use buck2_core::provider::label::ConfiguredProvidersLabel;
 use buck2_core::provider::label::ProvidersLabel;
 use buck2_core::target::label::TargetLabel;
+use buck2_events::dispatch::console_message;
 use buck2_execute::artifact::artifact_dyn::ArtifactDyn;
 use buck2_interpreter_for_build::interpreter::calculation::InterpreterCalculation;
 use buck2_node::nodes::eval_result::EvaluationResult;
+use buck2_query::query::compatibility::IncompatiblePlatformReason;
+use buck2_query::query::compatibility::MaybeCompatible;
 use buck2_server_ctx::ctx::ServerCommandContextTrait;
 use buck2_server_ctx::partial_result_dispatcher::NoPartialResult;
 use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
@@ -126,6 +129,7 @@ async fn targets_show_outputs(
         }
         targets_paths.push(TargetPaths {
             target: targets_artifacts.providers_label.unconfigured().to_string(),
+            configured_target: targets_artifacts.providers_label.to_string(),
             paths,
         })
     }
@@ -177,8 +181,21 @@ async fn retrieve_artifacts_for_targets(
     futures::pin_mut!(futs);
 
     let mut results = Vec::new();
-    while let Some(mut targets_artifacts) = futs.try_next().await? {
-        results.append(&mut targets_artifacts);
+    let mut incompatible_targets = Vec::new();
+    while let Some(targets_artifacts) = futs.try_next().await? {
+        for targets_artifacts in targets_artifacts {
+            match targets_artifacts {
+                MaybeCompatible::Compatible(targets_artifacts) => results.push(targets_artifacts),
+                MaybeCompatible::Incompatible(reason) => {
+                    incompatible_targets.push(reason.target.dupe())
+                }
+            }
+        }
+    }
+    if !incompatible_targets.is_empty() {
+        console_message(IncompatiblePlatformReason::skipping_message_for_multiple(
+            incompatible_targets.iter(),
+        ));
     }
 
     Ok(results)
@@ -190,9 +207,12 @@ async fn retrieve_artifacts_for_spec(
     spec: PackageSpec<ProvidersPatternExtra>,
     global_target_platform: Option<TargetLabel>,
     res: Arc<EvaluationResult>,
-) -> anyhow::Result<Vec<TargetsArtifacts>> {
+) -> anyhow::Result<Vec<MaybeCompatible<TargetsArtifacts>>> {
     let available_targets = res.targets();
 
+    // Like when building, the targets of `...` or `:` patterns which are incompatible with the
+    // target platform are skipped, while requesting an incompatible target is an error.
+    let skip_incompatible = matches!(spec, PackageSpec::All);
     let todo_targets: Vec<(ProvidersLabel, Option<TargetLabel>)> = match spec {
         PackageSpec::All => available_targets
             .keys()
@@ -232,7 +252,12 @@ async fn retrieve_artifacts_for_spec(
 
     let mut outputs = Vec::new();
     while let Some(targets_artifacts) = futs.next().await {
-        outputs.push(targets_artifacts?);
+        match targets_artifacts? {
+            MaybeCompatible::Incompatible(reason) if !skip_incompatible => {
+                return Err(reason.to_err());
+            }
+            targets_artifacts => outputs.push(targets_artifacts),
+        }
     }
 
     Ok(outputs)
@@ -242,15 +267,16 @@ async fn retrieve_artifacts_for_provider_label(
     ctx: &DiceComputations,
     providers_label: ProvidersLabel,
     target_platform: Option<TargetLabel>,
-) -> anyhow::Result<TargetsArtifacts> {
+) -> anyhow::Result<MaybeCompatible<TargetsArtifacts>> {
     let providers_label = ctx
         .get_configured_target(&providers_label, target_platform.as_ref())
         .await?;
 
-    let providers = ctx
-        .get_providers(&providers_label)
-        .await?
-        .require_compatible()?;
+    // Only analysis is needed to know the outputs, nothing is built.
+    let providers = match ctx.get_providers(&providers_label).await? {
+        MaybeCompatible::Compatible(providers) => providers,
+        MaybeCompatible::Incompatible(reason) => return Ok(MaybeCompatible::Incompatible(reason)),
+    };
 
     let collection = providers.provider_collection();
 
@@ -262,8 +288,8 @@ async fn retrieve_artifacts_for_provider_label(
             Ok(())
         })?;
 
-    Ok(TargetsArtifacts {
+    Ok(MaybeCompatible::Compatible(TargetsArtifacts {
         providers_label,
         artifacts,
-    })
+    }))
 }
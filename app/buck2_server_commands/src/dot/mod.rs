@@ -70,6 +70,17 @@ pub trait DotNode {
 pub struct DotEdge<'a> {
     from: &'a str,
     to: &'a str,
+    /// Rendered as the label of the edge.
+    label: Option<&'a str>,
+}
+
+impl<'a> DotEdge<'a> {
+    fn attrs(&self) -> String {
+        match self.label {
+            Some(label) => format!(" [label={}]", escape_id(label)),
+            None => String::new(),
+        }
+    }
 }
 
 pub trait DotDigraph<'a> {
@@ -99,7 +110,7 @@ pub trait DotDigraph<'a> {
 ///  - an HTML string (<...>).
 ///
 /// We support (approximately) the first two forms and then anything else gets quoted and escaped as the third form.
-fn escape_id(value: &str) -> String {
+pub(crate) fn escape_id(value: &str) -> String {
     static RE_STRING: Lazy<Regex> = Lazy::new(|| Regex::new("^[a-zA-Z_][a-zA-Z_0-9]*$").unwrap());
     static RE_NUMBER: Lazy<Regex> =
         Lazy::new(|| Regex::new("^-?(.[0-9]+ | [0-9]+.[0-9]*)$").unwrap());
@@ -120,7 +131,13 @@ impl Dot {
             let attrs = node.attrs()?;
             writeln!(w, "  {} [{}];", escape_id(&node.id()), attrs)?;
             graph.for_each_edge(node, |edge| {
-                writeln!(w, "  {} -> {};", escape_id(edge.from), escape_id(edge.to))?;
+                writeln!(
+                    w,
+                    "  {} -> {}{};",
+                    escape_id(edge.from),
+                    escape_id(edge.to),
+                    edge.attrs()
+                )?;
                 Ok(())
             })?;
             Ok(())
@@ -163,9 +180,10 @@ impl DotCompact {
             graph.for_each_edge(node, |edge| {
                 writeln!(
                     w,
-                    "  {} -> {};",
+                    "  {} -> {}{};",
                     name_to_number(&escape_id(edge.from)),
-                    name_to_number(&escape_id(edge.to))
+                    name_to_number(&escape_id(edge.to)),
+                    edge.attrs()
                 )?;
                 Ok(())
             })?;
@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use buck2_query::query::environment::QueryDepKind;
 use buck2_query::query::environment::QueryTarget;
 use buck2_query::query::environment::QueryTargets;
 use buck2_query::query::syntax::simple::eval::set::TargetSet;
@@ -24,6 +25,8 @@ pub struct DotTargetGraphNode<'a, T: QueryTarget>(&'a T, &'a DotTargetGraph<T>);
 pub struct DotTargetGraph<T: QueryTarget> {
     pub targets: TargetSet<T>,
     pub attributes: Option<RegexSet>,
+    /// Label each edge with the kind of the dependency.
+    pub dep_kinds: bool,
 }
 
 impl<'a, T: QueryTarget> DotDigraph<'a> for DotTargetGraph<T> {
@@ -48,12 +51,23 @@ impl<'a, T: QueryTarget> DotDigraph<'a> for DotTargetGraph<T> {
         node: &Self::Node,
         mut f: F,
     ) -> anyhow::Result<()> {
-        for dep in node.0.deps() {
+        let deps: Box<dyn Iterator<Item = (&T::NodeRef, Option<QueryDepKind>)> + '_> =
+            if self.dep_kinds {
+                Box::new(
+                    node.0
+                        .deps_with_kinds()
+                        .map(|(dep, kind)| (dep, Some(kind))),
+                )
+            } else {
+                Box::new(node.0.deps().map(|dep| (dep, None)))
+            };
+        for (dep, kind) in deps {
             // Only include edges to other nodes within the subgraph.
             if self.targets.contains(dep) {
                 f(&DotEdge {
                     from: &node.0.node_ref().to_string(),
                     to: &dep.to_string(),
+                    label: kind.map(QueryDepKind::as_str),
                 })?;
             }
         }
@@ -66,15 +80,14 @@ impl<'a, T: QueryTarget> DotNode for DotTargetGraphNode<'a, T> {
         let extra = match &self.1.attributes {
             Some(attr_regex) => {
                 let mut extra = SmallMap::new();
-                QueryTargets::for_all_attrs::<anyhow::Error, _, _>(
+                QueryTargets::for_all_attrs_matching::<anyhow::Error, _, _>(
                     self.0,
+                    &|attr_name: &str| attr_regex.is_match(attr_name),
                     |attr_name, attr_value| {
-                        if attr_regex.is_match(attr_name) {
-                            extra.insert(
-                                format!("buck_{}", attr_name),
-                                self.0.attr_to_string_alternate(attr_value),
-                            );
-                        }
+                        extra.insert(
+                            format!("buck_{}", attr_name),
+                            self.0.attr_to_string_alternate(attr_value),
+                        );
                         Ok(())
                     },
                 )?;
@@ -94,3 +107,128 @@ impl<'a, T: QueryTarget> DotNode for DotTargetGraphNode<'a, T> {
         self.0.node_ref().to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use buck2_core::bzl::ImportPath;
+    use buck2_core::provider::label::ProvidersLabel;
+    use buck2_core::provider::label::ProvidersName;
+    use buck2_core::target::label::TargetLabel;
+    use buck2_node::attrs::attr::Attribute;
+    use buck2_node::attrs::attr_type::list::ListLiteral;
+    use buck2_node::attrs::attr_type::AttrType;
+    use buck2_node::attrs::coerced_attr::CoercedAttr;
+    use buck2_node::nodes::unconfigured::testing::TargetNodeExt;
+    use buck2_node::nodes::unconfigured::TargetNode;
+    use buck2_node::provider_id_set::ProviderIdSet;
+    use buck2_node::rule_type::RuleType;
+    use buck2_node::rule_type::StarlarkRuleType;
+    use buck2_query::query::syntax::simple::eval::set::TargetSet;
+
+    use crate::dot::targets::DotTargetGraph;
+    use crate::dot::Dot;
+    use crate::dot::DotCompact;
+
+    fn deps_attr(
+        name: &'static str,
+        attr_type: AttrType,
+        deps: &[&str],
+    ) -> (&'static str, Attribute, CoercedAttr) {
+        (
+            name,
+            Attribute::new(None, "", AttrType::list(attr_type)),
+            CoercedAttr::List(ListLiteral(
+                deps.iter()
+                    .map(|dep| {
+                        CoercedAttr::Dep(ProvidersLabel::new(
+                            TargetLabel::testing_parse(dep),
+                            ProvidersName::Default,
+                        ))
+                    })
+                    .collect(),
+            )),
+        )
+    }
+
+    fn node(label: &str, deps: &[&str], exec_deps: &[&str]) -> TargetNode {
+        TargetNode::testing_new(
+            TargetLabel::testing_parse(label),
+            RuleType::Starlark(Arc::new(StarlarkRuleType {
+                import_path: ImportPath::testing_new("root//rules:defs.bzl"),
+                name: "my_rule".to_owned(),
+            })),
+            vec![
+                deps_attr("deps", AttrType::dep(ProviderIdSet::EMPTY), deps),
+                deps_attr(
+                    "exec_deps",
+                    AttrType::exec_dep(ProviderIdSet::EMPTY),
+                    exec_deps,
+                ),
+            ],
+        )
+    }
+
+    fn graph(dep_kinds: bool) -> DotTargetGraph<TargetNode> {
+        DotTargetGraph {
+            targets: [
+                node("root//:a", &["root//:b", "root//:outside"], &["root//:c"]),
+                node("root//:b", &[], &[]),
+                node("root//:c", &[], &[]),
+            ]
+            .into_iter()
+            .collect(),
+            attributes: None,
+            dep_kinds,
+        }
+    }
+
+    /// The edges of the rendered graph.
+    fn edges(rendered: Vec<u8>) -> anyhow::Result<Vec<String>> {
+        Ok(String::from_utf8(rendered)?
+            .lines()
+            .filter(|line| line.contains("->"))
+            .map(|line| line.trim().to_owned())
+            .collect())
+    }
+
+    #[test]
+    fn test_dot_dep_kinds() -> anyhow::Result<()> {
+        let mut out = Vec::new();
+        Dot::render(&graph(true), &mut out)?;
+        assert_eq!(
+            vec![
+                "\"root//:a\" -> \"root//:b\" [label=target];",
+                "\"root//:a\" -> \"root//:c\" [label=exec];",
+            ],
+            edges(out)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_dot_without_dep_kinds() -> anyhow::Result<()> {
+        let mut out = Vec::new();
+        Dot::render(&graph(false), &mut out)?;
+        assert_eq!(
+            vec![
+                "\"root//:a\" -> \"root//:b\";",
+                "\"root//:a\" -> \"root//:c\";",
+            ],
+            edges(out)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_dot_compact_dep_kinds() -> anyhow::Result<()> {
+        let mut out = Vec::new();
+        DotCompact::render(&graph(true), &mut out)?;
+        assert_eq!(
+            vec!["1 -> 2 [label=target];", "1 -> 3 [label=exec];"],
+            edges(out)?
+        );
+        Ok(())
+    }
+}
@@ -9,6 +9,8 @@
 
 use std::fmt::Display;
 
+use serde::Serialize;
+
 /// Serialized JSON.
 /// It can be output as is to a JSON file.
 pub(crate) struct QuotedJson(String);
@@ -39,8 +41,8 @@ impl QuotedJson {
         QuotedJson(s)
     }
 
-    pub(crate) fn from_serde_json_value(v: serde_json::Value) -> QuotedJson {
-        QuotedJson(serde_json::ser::to_string(&v).unwrap())
+    pub(crate) fn from_serialize(v: &impl Serialize) -> QuotedJson {
+        QuotedJson(serde_json::ser::to_string(v).unwrap())
     }
 }
 
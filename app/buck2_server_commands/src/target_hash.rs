@@ -11,7 +11,9 @@ use std::collections::HashMap;
 use std::collections::HashSet;
 use std::hash::Hash;
 use std::hash::Hasher;
+use std::iter;
 use std::sync::Arc;
+use std::sync::Mutex;
 
 use async_recursion::async_recursion;
 use async_trait::async_trait;
@@ -26,6 +28,7 @@ use buck2_core::cells::cell_path::CellPath;
 use buck2_core::cells::cell_path::CellPathRef;
 use buck2_core::package::PackageLabel;
 use buck2_core::target::label::TargetLabel;
+use buck2_interpreter_for_build::interpreter::calculation::InterpreterCalculation;
 use buck2_node::nodes::configured::ConfiguredTargetNode;
 use buck2_node::nodes::unconfigured::TargetNode;
 use buck2_query::query::environment::ConfiguredOrUnconfiguredTargetLabel;
@@ -49,6 +52,8 @@ use os_str_bytes::OsStrBytes;
 use siphasher::sip128::Hasher128;
 use siphasher::sip128::SipHasher24;
 
+use crate::commands::targets::streaming::transitive_imports;
+
 #[derive(Clone, Dupe, derive_more::Display)]
 #[display(fmt = "{:032x}", _0)]
 pub struct BuckTargetHash(pub u128);
@@ -180,6 +185,73 @@ impl FileHasher for PathsAndContentsHasher {
     }
 }
 
+#[async_trait]
+trait BuildFileInputs: Send + Sync {
+    /// The build file of a package, followed by the `.bzl` files it transitively loads.
+    async fn build_file_inputs(&self, package: PackageLabel) -> anyhow::Result<Vec<CellPath>>;
+}
+
+struct DiceBuildFileInputs {
+    dice: DiceTransaction,
+}
+
+#[async_trait]
+impl BuildFileInputs for DiceBuildFileInputs {
+    async fn build_file_inputs(&self, package: PackageLabel) -> anyhow::Result<Vec<CellPath>> {
+        let eval_result = self.dice.get_interpreter_results(package).await?;
+        let imports = transitive_imports(&self.dice, eval_result.imports().to_vec()).await?;
+        Ok(iter::once(eval_result.buildfile_path().path())
+            .chain(imports.iter().map(|import| import.path().clone()))
+            .collect())
+    }
+}
+
+/// Hashes the build file of a package and the `.bzl` files it transitively loads, so that changes
+/// to macros or rule implementations are reflected in the hashes of the package's targets.
+/// Each package is hashed once, and different packages are hashed in parallel.
+struct BuildFileHasher {
+    inputs: Arc<dyn BuildFileInputs>,
+    file_hasher: Arc<dyn FileHasher>,
+    use_fast_hash: bool,
+    packages:
+        Mutex<HashMap<PackageLabel, Shared<BoxFuture<'static, SharedResult<BuckTargetHash>>>>>,
+}
+
+impl BuildFileHasher {
+    fn hash_package(
+        self: &Arc<Self>,
+        package: PackageLabel,
+    ) -> Shared<BoxFuture<'static, SharedResult<BuckTargetHash>>> {
+        let mut packages = self.packages.lock().unwrap();
+        packages
+            .entry(package.dupe())
+            .or_insert_with(|| {
+                let this = self.dupe();
+                async move {
+                    let hash: anyhow::Result<BuckTargetHash> = try {
+                        let paths = this.inputs.build_file_inputs(package).await?;
+                        let file_hashes = join_all(paths.into_iter().map(|path| {
+                            let file_hasher = this.file_hasher.dupe();
+                            async move {
+                                let file_hash = file_hasher.hash_path(&path).await;
+                                (path, file_hash)
+                            }
+                        }))
+                        .await;
+
+                        let mut hasher = TargetHashes::new_hasher(this.use_fast_hash);
+                        TargetHashes::hash_files(file_hashes, &mut *hasher)?;
+                        hasher.finish_u128()
+                    };
+                    hash.shared_error()
+                }
+                .boxed()
+                .shared()
+            })
+            .clone()
+    }
+}
+
 /// Types of node that can be target hashed (just configured and unconfigured).
 /// This trait is purposely defined here instead of in buck2_node crate
 /// so that we can only access the public fields of these nodes.
@@ -254,6 +326,7 @@ impl TargetHashes {
         lookup: L,
         targets: TargetSet<T>,
         file_hasher: Option<Arc<dyn FileHasher>>,
+        build_file_hasher: Option<Arc<BuildFileHasher>>,
         use_fast_hash: bool,
     ) -> anyhow::Result<Self>
     where
@@ -262,6 +335,7 @@ impl TargetHashes {
         struct Delegate<T: QueryTarget> {
             hashes: HashMap<T::NodeRef, Shared<BoxFuture<'static, SharedResult<BuckTargetHash>>>>,
             file_hasher: Option<Arc<dyn FileHasher>>,
+            build_file_hasher: Option<Arc<BuildFileHasher>>,
             use_fast_hash: bool,
             dice: DiceTransaction,
         }
@@ -283,6 +357,7 @@ impl TargetHashes {
                     .collect::<Result<Vec<_>, TargetHashError>>()?;
 
                 let file_hasher = self.file_hasher.dupe();
+                let build_file_hasher = self.build_file_hasher.dupe();
                 let dice = self.dice.dupe();
 
                 let use_fast_hash = self.use_fast_hash;
@@ -296,6 +371,12 @@ impl TargetHashes {
                                 let mut hasher = TargetHashes::new_hasher(use_fast_hash);
                                 TargetHashes::hash_node(&target, &mut *hasher);
 
+                                if let Some(build_file_hasher) = build_file_hasher {
+                                    let package = target.node_ref().unconfigured_label().pkg();
+                                    let hash = build_file_hasher.hash_package(package).await?;
+                                    hasher.write_u128(hash.0);
+                                }
+
                                 let mut input_futs = Vec::new();
                                 if let Some(file_hasher) = file_hasher {
                                     target.inputs_for_each(|cell_path| {
@@ -343,6 +424,7 @@ impl TargetHashes {
         let mut delegate = Delegate::<T> {
             hashes: HashMap::new(),
             file_hasher,
+            build_file_hasher,
             use_fast_hash,
             dice,
         };
@@ -378,6 +460,7 @@ impl TargetHashes {
     async fn compute_immediate_target_hashes<T: TargetHashingTargetNode>(
         targets: TargetSet<T>,
         file_hasher: Option<Arc<dyn FileHasher>>,
+        build_file_hasher: Option<Arc<BuildFileHasher>>,
         use_fast_hash: bool,
     ) -> anyhow::Result<Self>
     where
@@ -387,11 +470,17 @@ impl TargetHashes {
             .into_iter()
             .map(|target| {
                 let file_hasher = file_hasher.dupe();
+                let build_file_hasher = build_file_hasher.dupe();
                 async move {
                     let hash_result: anyhow::Result<BuckTargetHash> = try {
                         let mut hasher = TargetHashes::new_hasher(use_fast_hash);
                         TargetHashes::hash_node(&target, &mut *hasher);
 
+                        if let Some(build_file_hasher) = build_file_hasher {
+                            let package = target.node_ref().unconfigured_label().pkg();
+                            hasher.write_u128(build_file_hasher.hash_package(package).await?.0);
+                        }
+
                         if let Some(file_hasher) = file_hasher {
                             let mut input_futs = Vec::new();
                             target.inputs_for_each(|cell_path| {
@@ -438,17 +527,40 @@ impl TargetHashes {
         file_hash_mode: TargetHashesFileMode,
         use_fast_hash: bool,
         target_hash_recursive: bool,
+        build_file_inputs: bool,
     ) -> anyhow::Result<Self>
     where
         T::NodeRef: ConfiguredOrUnconfiguredTargetLabel,
     {
         let targets = T::get_target_nodes(&dice, targets, global_target_platform).await?;
         let file_hasher = Self::new_file_hasher(dice.dupe(), file_hash_mode);
+        let build_file_hasher = match &file_hasher {
+            Some(file_hasher) if build_file_inputs => Some(Arc::new(BuildFileHasher {
+                inputs: Arc::new(DiceBuildFileInputs { dice: dice.dupe() }),
+                file_hasher: file_hasher.dupe(),
+                use_fast_hash,
+                packages: Mutex::new(HashMap::new()),
+            })),
+            _ => None,
+        };
         if target_hash_recursive {
-            Self::compute_recursive_target_hashes(dice, lookup, targets, file_hasher, use_fast_hash)
-                .await
+            Self::compute_recursive_target_hashes(
+                dice,
+                lookup,
+                targets,
+                file_hasher,
+                build_file_hasher,
+                use_fast_hash,
+            )
+            .await
         } else {
-            Self::compute_immediate_target_hashes(targets, file_hasher, use_fast_hash).await
+            Self::compute_immediate_target_hashes(
+                targets,
+                file_hasher,
+                build_file_hasher,
+                use_fast_hash,
+            )
+            .await
         }
     }
 
@@ -507,7 +619,159 @@ impl TargetHashes {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+    use buck2_core::cells::cell_path::CellPath;
+    use buck2_core::package::PackageLabel;
+    use dupe::Dupe;
+
     use crate::target_hash::BuckTargetHash;
+    use crate::target_hash::BuildFileHasher;
+    use crate::target_hash::BuildFileInputs;
+    use crate::target_hash::FileHasher;
+
+    /// Build files and loads by package, counting the lookups.
+    struct TestBuildFileInputs {
+        inputs: HashMap<PackageLabel, Vec<CellPath>>,
+        lookups: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl BuildFileInputs for TestBuildFileInputs {
+        async fn build_file_inputs(&self, package: PackageLabel) -> anyhow::Result<Vec<CellPath>> {
+            self.lookups.fetch_add(1, Ordering::SeqCst);
+            self.inputs
+                .get(&package)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("No build file for `{}`", package))
+        }
+    }
+
+    /// File contents by path. Missing files fail to hash.
+    struct TestFileHasher(HashMap<CellPath, Vec<u8>>);
+
+    #[async_trait]
+    impl FileHasher for TestFileHasher {
+        async fn hash_path(&self, path: &CellPath) -> anyhow::Result<Vec<u8>> {
+            self.0
+                .get(path)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("No file at `{}`", path))
+        }
+    }
+
+    fn build_file_hasher(
+        contents: &[(&str, &str)],
+    ) -> (Arc<BuildFileHasher>, Arc<TestBuildFileInputs>) {
+        let inputs = Arc::new(TestBuildFileInputs {
+            inputs: HashMap::from([
+                (
+                    PackageLabel::testing_parse("root//foo"),
+                    vec![
+                        CellPath::testing_new("root//foo/BUCK"),
+                        CellPath::testing_new("root//defs.bzl"),
+                    ],
+                ),
+                (
+                    PackageLabel::testing_parse("root//bar"),
+                    vec![
+                        CellPath::testing_new("root//bar/BUCK"),
+                        CellPath::testing_new("root//defs.bzl"),
+                    ],
+                ),
+            ]),
+            lookups: AtomicUsize::new(0),
+        });
+        let file_hasher = TestFileHasher(
+            contents
+                .iter()
+                .map(|(path, contents)| (CellPath::testing_new(path), contents.as_bytes().to_vec()))
+                .collect(),
+        );
+        let hasher = Arc::new(BuildFileHasher {
+            inputs: inputs.dupe(),
+            file_hasher: Arc::new(file_hasher),
+            use_fast_hash: true,
+            packages: Mutex::new(HashMap::new()),
+        });
+        (hasher, inputs)
+    }
+
+    async fn hash_package(hasher: &Arc<BuildFileHasher>, package: &str) -> anyhow::Result<u128> {
+        Ok(hasher
+            .hash_package(PackageLabel::testing_parse(package))
+            .await?
+            .0)
+    }
+
+    const CONTENTS: &[(&str, &str)] = &[
+        ("root//foo/BUCK", "foo()"),
+        ("root//bar/BUCK", "bar()"),
+        ("root//defs.bzl", "def foo(): pass"),
+    ];
+
+    #[tokio::test]
+    async fn test_build_file_hasher_hashes_each_package_once() -> anyhow::Result<()> {
+        let (hasher, inputs) = build_file_hasher(CONTENTS);
+        let (a, b) = futures::join!(
+            hash_package(&hasher, "root//foo"),
+            hash_package(&hasher, "root//foo")
+        );
+        let a = a?;
+        assert_eq!(a, b?);
+        assert_eq!(a, hash_package(&hasher, "root//foo").await?);
+        assert_eq!(1, inputs.lookups.load(Ordering::SeqCst));
+
+        assert_ne!(a, hash_package(&hasher, "root//bar").await?);
+        assert_eq!(2, inputs.lookups.load(Ordering::SeqCst));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_build_file_hasher_changes_with_loads() -> anyhow::Result<()> {
+        let (hasher, _) = build_file_hasher(CONTENTS);
+        let (changed, _) = build_file_hasher(&[
+            ("root//foo/BUCK", "foo()"),
+            ("root//bar/BUCK", "bar()"),
+            ("root//defs.bzl", "def foo(): fail()"),
+        ]);
+        // A change to a load changes the hashes of all the packages loading it.
+        for package in ["root//foo", "root//bar"] {
+            assert_ne!(
+                hash_package(&hasher, package).await?,
+                hash_package(&changed, package).await?
+            );
+        }
+
+        let (changed, _) = build_file_hasher(&[
+            ("root//foo/BUCK", "foo(name = 'x')"),
+            ("root//bar/BUCK", "bar()"),
+            ("root//defs.bzl", "def foo(): pass"),
+        ]);
+        assert_ne!(
+            hash_package(&hasher, "root//foo").await?,
+            hash_package(&changed, "root//foo").await?
+        );
+        assert_eq!(
+            hash_package(&hasher, "root//bar").await?,
+            hash_package(&changed, "root//bar").await?
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_build_file_hasher_errors() {
+        let (hasher, _) = build_file_hasher(&[("root//foo/BUCK", "foo()")]);
+        let err = hash_package(&hasher, "root//foo").await.unwrap_err();
+        assert!(err.to_string().contains("root//defs.bzl"), "{:#}", err);
+        let err = hash_package(&hasher, "root//baz").await.unwrap_err();
+        assert!(err.to_string().contains("root//baz"), "{:#}", err);
+    }
 
     #[test]
     fn test_hash_display() {
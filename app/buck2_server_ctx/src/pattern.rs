@@ -70,12 +70,24 @@ pub async fn parse_patterns_from_cli_args<T: PatternType>(
     target_patterns.try_map(|value| parser.parse_pattern(&value.value))
 }
 
+#[derive(Debug, thiserror::Error)]
+enum TargetPlatformError {
+    #[error("This command accepts only one `--target-platforms`, got {0}")]
+    MultipleTargetPlatforms(usize),
+}
+
 /// Extract target configuration (platform) label from [`ClientContext`].
 pub async fn target_platform_from_client_context(
     client_ctx: &ClientContext,
     server_ctx: &dyn ServerCommandContextTrait,
     dice_ctx: &DiceTransaction,
 ) -> anyhow::Result<Option<TargetLabel>> {
+    if !client_ctx.extra_target_platforms.is_empty() {
+        return Err(TargetPlatformError::MultipleTargetPlatforms(
+            client_ctx.extra_target_platforms.len() + 1,
+        )
+        .into());
+    }
     target_platform_from_client_context_impl(
         client_ctx,
         &dice_ctx.get_cell_resolver().await?,
@@ -84,6 +96,44 @@ pub async fn target_platform_from_client_context(
     .await
 }
 
+/// Extract all the target configuration (platform) labels from [`ClientContext`], for commands
+/// which configure targets for each of several platforms. Returns a single `None` when no
+/// platform was specified.
+pub async fn target_platforms_from_client_context(
+    client_ctx: &ClientContext,
+    server_ctx: &dyn ServerCommandContextTrait,
+    dice_ctx: &DiceTransaction,
+) -> anyhow::Result<Vec<Option<TargetLabel>>> {
+    let cell_resolver = dice_ctx.get_cell_resolver().await?;
+    let cwd = cell_resolver.get_cell_path(server_ctx.working_dir())?;
+
+    let mut platforms = vec![
+        target_platform_from_client_context_impl(
+            client_ctx,
+            &cell_resolver,
+            server_ctx.working_dir(),
+        )
+        .await?,
+    ];
+    for target_platform in &client_ctx.extra_target_platforms {
+        platforms.push(Some(parse_target_platform(
+            target_platform,
+            &cwd,
+            &cell_resolver,
+        )?));
+    }
+    Ok(platforms)
+}
+
+fn parse_target_platform(
+    target_platform: &str,
+    cwd: &CellPath,
+    cell_resolver: &CellResolver,
+) -> anyhow::Result<TargetLabel> {
+    ParsedPattern::parse_precise(target_platform, cwd.cell(), cell_resolver)?
+        .as_target_label(target_platform)
+}
+
 async fn target_platform_from_client_context_impl(
     client_context: &ClientContext,
     cell_resolver: &CellResolver,
@@ -93,10 +143,11 @@ async fn target_platform_from_client_context_impl(
 
     let target_platform = &client_context.target_platform;
     if !target_platform.is_empty() {
-        Ok(Some(
-            ParsedPattern::parse_precise(target_platform, cwd.cell(), cell_resolver)?
-                .as_target_label(target_platform)?,
-        ))
+        Ok(Some(parse_target_platform(
+            target_platform,
+            &cwd,
+            cell_resolver,
+        )?))
     } else {
         Ok(None)
     }
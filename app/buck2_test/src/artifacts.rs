@@ -0,0 +1,498 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Storage for the artifacts (screenshots, logs, profiles...) that test runners attach to
+//! individual test results.
+//!
+//! Artifacts are copied to `buck-out/<isolation dir>/test-artifacts/<session>/<target>/<test>/`
+//! and every test result is listed with its artifacts in a `report.json` in the session directory.
+//!
+//! Sessions are named after their start time and trace id, so that concurrent test commands get
+//! their own directory. A session is marked in progress until its report is written, and sessions
+//! in progress are not pruned.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::Context as _;
+use buck2_common::legacy_configs::dice::HasLegacyConfigs;
+use buck2_core::cells::name::CellName;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::fs::project::ProjectRoot;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_core::target::label::ConfiguredTargetLabel;
+use buck2_test_api::data::TestArtifact;
+use buck2_test_api::data::TestResult;
+use dice::DiceComputations;
+use dupe::Dupe;
+use serde::Serialize;
+
+/// Path components derived from target labels and test names are truncated to this length.
+const MAX_COMPONENT_LEN: usize = 96;
+
+/// File present in the directory of a session until its report is written.
+const IN_PROGRESS_MARKER: &str = ".in-progress";
+
+/// Sessions marked in progress for longer than this are assumed to have been left over by a daemon
+/// which was killed, and can be pruned.
+const STALE_SESSION: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Limits applied to the artifacts attached to test results, configured in the `[test]` section.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq)]
+pub struct TestArtifactLimits {
+    /// Artifacts larger than this are dropped (`test.artifacts_max_bytes`).
+    pub max_artifact_bytes: u64,
+    /// Once a session has stored this many bytes, further artifacts are dropped
+    /// (`test.artifacts_max_session_bytes`).
+    pub max_session_bytes: u64,
+    /// Number of test sessions whose artifacts are kept, including the current one
+    /// (`test.artifacts_retained_sessions`).
+    pub retained_sessions: usize,
+}
+
+impl Default for TestArtifactLimits {
+    fn default() -> Self {
+        Self {
+            max_artifact_bytes: 64 << 20,
+            max_session_bytes: 1 << 30,
+            retained_sessions: 5,
+        }
+    }
+}
+
+impl TestArtifactLimits {
+    pub async fn from_config(ctx: &DiceComputations, cell: CellName) -> anyhow::Result<Self> {
+        let default = Self::default();
+        Ok(Self {
+            max_artifact_bytes: ctx
+                .parse_legacy_config_property(cell, "test", "artifacts_max_bytes")
+                .await?
+                .unwrap_or(default.max_artifact_bytes),
+            max_session_bytes: ctx
+                .parse_legacy_config_property(cell, "test", "artifacts_max_session_bytes")
+                .await?
+                .unwrap_or(default.max_session_bytes),
+            retained_sessions: ctx
+                .parse_legacy_config_property(cell, "test", "artifacts_retained_sessions")
+                .await?
+                .unwrap_or(default.retained_sessions),
+        })
+    }
+}
+
+/// An artifact copied into the store.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct StoredTestArtifact {
+    pub name: String,
+    /// Project relative path of the stored copy.
+    pub path: String,
+    pub size: u64,
+}
+
+impl StoredTestArtifact {
+    pub fn to_proto(&self) -> buck2_data::TestArtifact {
+        buck2_data::TestArtifact {
+            name: self.name.clone(),
+            path: self.path.clone(),
+            size: self.size,
+        }
+    }
+}
+
+/// An artifact that was not stored, and why.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+struct DroppedTestArtifact {
+    name: String,
+    reason: String,
+}
+
+#[derive(Debug, Serialize)]
+struct TestArtifactsReportEntry {
+    target: String,
+    name: String,
+    status: String,
+    artifacts: Vec<StoredTestArtifact>,
+    dropped: Vec<DroppedTestArtifact>,
+}
+
+#[derive(Debug, Serialize)]
+struct TestArtifactsReport<'a> {
+    project_root: String,
+    results: &'a [TestArtifactsReportEntry],
+}
+
+/// The artifacts stored for a single test session.
+pub struct TestArtifactStore {
+    fs: ProjectRoot,
+    /// `buck-out/<isolation dir>/test-artifacts/<session>`.
+    dir: ProjectRelativePathBuf,
+    limits: TestArtifactLimits,
+    used_bytes: AtomicU64,
+    report: Mutex<Vec<TestArtifactsReportEntry>>,
+}
+
+impl TestArtifactStore {
+    pub fn new(fs: ProjectRoot, dir: ProjectRelativePathBuf, limits: TestArtifactLimits) -> Self {
+        Self {
+            fs,
+            dir,
+            limits,
+            used_bytes: AtomicU64::new(0),
+            report: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Create the directory of this session, marked in progress, and delete the artifacts of
+    /// older sessions.
+    pub fn start_session(&self) -> anyhow::Result<()> {
+        let dir = self.fs.resolve(&self.dir);
+        fs_util::create_dir_all(&dir)?;
+        fs_util::write(dir.join(IN_PROGRESS_MARKER), "")?;
+        self.prune_old_sessions()
+    }
+
+    /// Delete the artifacts of older sessions, keeping `retained_sessions` sessions including
+    /// this one. Sessions in progress are never deleted, unless they are stale. Session
+    /// directories are prefixed with their start time, so they sort chronologically.
+    fn prune_old_sessions(&self) -> anyhow::Result<()> {
+        let (Some(parent), Some(current)) = (self.dir.parent(), self.dir.file_name()) else {
+            return Ok(());
+        };
+        let parent = self.fs.resolve(parent);
+        let Some(entries) = fs_util::read_dir_if_exists(&parent)? else {
+            return Ok(());
+        };
+
+        let mut sessions = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            if entry.file_name().to_str() == Some(current.as_str()) {
+                continue;
+            }
+            let path = entry.path();
+            let in_progress =
+                match fs_util::symlink_metadata_if_exists(path.join(IN_PROGRESS_MARKER))? {
+                    Some(marker) => {
+                        marker.modified()?.elapsed().unwrap_or_default() < STALE_SESSION
+                    }
+                    None => false,
+                };
+            if !in_progress {
+                sessions.push(path);
+            }
+        }
+        sessions.sort();
+
+        let keep = self.limits.retained_sessions.saturating_sub(1);
+        let remove = sessions.len().saturating_sub(keep);
+        for session in &sessions[..remove] {
+            fs_util::remove_all(session)?;
+        }
+        Ok(())
+    }
+
+    /// Copy the artifacts attached to `result` into the store and add the result to the report.
+    /// On return, `result.artifacts` only contains the stored artifacts and points to the stored
+    /// copies. Artifacts that are missing or exceed the limits are dropped and listed as such in
+    /// the report.
+    pub fn store(
+        &self,
+        target: &ConfiguredTargetLabel,
+        result: &mut TestResult,
+    ) -> anyhow::Result<Vec<StoredTestArtifact>> {
+        let target_str = target.to_string();
+        let case_dir = self
+            .dir
+            .join(ForwardRelativePath::new(&path_component(&target_str))?)
+            .join(ForwardRelativePath::new(&path_component(&result.name))?);
+
+        let mut stored = Vec::new();
+        let mut dropped = Vec::new();
+        let mut stored_artifacts = Vec::new();
+        for artifact in std::mem::take(&mut result.artifacts) {
+            match self.store_artifact(&case_dir, &artifact)? {
+                Ok((path, size)) => {
+                    stored.push(StoredTestArtifact {
+                        name: artifact.name.as_str().to_owned(),
+                        path: path.as_str().to_owned(),
+                        size,
+                    });
+                    stored_artifacts.push(TestArtifact {
+                        name: artifact.name,
+                        path: self.fs.resolve(&path),
+                    });
+                }
+                Err(reason) => {
+                    tracing::warn!(
+                        "Dropping artifact `{}` of test `{}` for `{}`: {}",
+                        artifact.name,
+                        result.name,
+                        target_str,
+                        reason
+                    );
+                    dropped.push(DroppedTestArtifact {
+                        name: artifact.name.as_str().to_owned(),
+                        reason,
+                    });
+                }
+            }
+        }
+        result.artifacts = stored_artifacts;
+
+        self.report.lock().unwrap().push(TestArtifactsReportEntry {
+            target: target_str,
+            name: result.name.clone(),
+            status: format!("{:?}", result.status),
+            artifacts: stored.clone(),
+            dropped,
+        });
+
+        Ok(stored)
+    }
+
+    /// The outer error is an IO failure; the inner one is the reason the artifact was dropped.
+    fn store_artifact(
+        &self,
+        case_dir: &ProjectRelativePath,
+        artifact: &TestArtifact,
+    ) -> anyhow::Result<Result<(ProjectRelativePathBuf, u64), String>> {
+        let size = match fs_util::symlink_metadata(&artifact.path) {
+            Ok(m) if m.is_file() => m.len(),
+            Ok(_) => return Ok(Err("not a regular file".to_owned())),
+            Err(e) => return Ok(Err(format!("{:#}", e))),
+        };
+        if size > self.limits.max_artifact_bytes {
+            return Ok(Err(format!(
+                "size {} exceeds the per-artifact limit of {} bytes",
+                size, self.limits.max_artifact_bytes
+            )));
+        }
+        let reserved = self
+            .used_bytes
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                Some(used + size).filter(|total| *total <= self.limits.max_session_bytes)
+            });
+        if reserved.is_err() {
+            return Ok(Err(format!(
+                "the session limit of {} bytes has been reached",
+                self.limits.max_session_bytes
+            )));
+        }
+
+        let path = case_dir.join(&artifact.name);
+        let abs_path = self.fs.resolve(&path);
+        if let Some(parent) = abs_path.parent() {
+            fs_util::create_dir_all(parent)?;
+        }
+        fs_util::copy(&artifact.path, &abs_path)
+            .with_context(|| format!("Error storing test artifact `{}`", artifact.name))?;
+        Ok(Ok((path, size)))
+    }
+
+    /// Write `report.json` listing every test result with its artifacts, and mark the session
+    /// finished. Returns the path of the report, or `None` if no test reported a result, in which
+    /// case the session directory is deleted.
+    pub fn write_report(&self) -> anyhow::Result<Option<ProjectRelativePathBuf>> {
+        let results = self.report.lock().unwrap();
+        let dir = self.fs.resolve(&self.dir);
+        if results.is_empty() {
+            fs_util::remove_all(&dir)?;
+            return Ok(None);
+        }
+        let report = TestArtifactsReport {
+            project_root: self.fs.root().to_string(),
+            results: &results,
+        };
+        let path = self.dir.join(ForwardRelativePath::new("report.json")?);
+        fs_util::create_dir_all(&dir)?;
+        fs_util::write(self.fs.resolve(&path), serde_json::to_vec_pretty(&report)?)
+            .context("Error writing test report")?;
+        fs_util::remove_all(dir.join(IN_PROGRESS_MARKER))?;
+        Ok(Some(path))
+    }
+}
+
+/// Turn an arbitrary string into a single, reasonably short path component.
+fn path_component(s: &str) -> String {
+    let mut res: String = s
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if res.len() > MAX_COMPONENT_LEN || res.chars().all(|c| c == '.') {
+        let mut hasher = DefaultHasher::new();
+        s.hash(&mut hasher);
+        res.truncate(MAX_COMPONENT_LEN);
+        res = format!("{}-{:016x}", res, hasher.finish());
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::configuration::data::ConfigurationData;
+    use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+    use buck2_core::fs::project::ProjectRootTemp;
+    use buck2_test_api::data::ConfiguredTargetHandle;
+    use buck2_test_api::data::TestStatus;
+
+    use super::*;
+
+    fn result(artifacts: Vec<TestArtifact>) -> TestResult {
+        TestResult {
+            target: ConfiguredTargetHandle::from(0),
+            name: "suite - test/case".to_owned(),
+            status: TestStatus::FAIL,
+            msg: None,
+            duration: None,
+            details: String::new(),
+            artifacts,
+        }
+    }
+
+    fn artifact(fs: &ProjectRootTemp, name: &str, content: &str) -> TestArtifact {
+        let source = format!("runner/{}", name);
+        fs.write_file(&source, content);
+        TestArtifact {
+            name: ForwardRelativePathBuf::unchecked_new(name.to_owned()),
+            path: fs
+                .path()
+                .resolve(ProjectRelativePath::unchecked_new(&source)),
+        }
+    }
+
+    #[test]
+    fn test_path_component() {
+        assert_eq!("cell__pkg_foo", path_component("cell//pkg:foo"));
+        assert_ne!(".", path_component("."));
+        assert_ne!("..", path_component(".."));
+        let long = path_component(&"x".repeat(500));
+        assert!(long.len() < 128);
+        assert_ne!(long, path_component(&"x".repeat(501)));
+    }
+
+    #[test]
+    fn test_store_and_limits() -> anyhow::Result<()> {
+        let fs = ProjectRootTemp::new()?;
+        let store = TestArtifactStore::new(
+            fs.path().dupe(),
+            ProjectRelativePathBuf::unchecked_new("buck-out/v2/test-artifacts/s1".to_owned()),
+            TestArtifactLimits {
+                max_artifact_bytes: 4,
+                max_session_bytes: 6,
+                retained_sessions: 2,
+            },
+        );
+        let target =
+            ConfiguredTargetLabel::testing_parse("cell//pkg:foo", ConfigurationData::testing_new());
+
+        let mut r = result(vec![
+            artifact(&fs, "a.log", "1234"),
+            artifact(&fs, "big.log", "12345"),
+            artifact(&fs, "b.log", "123"),
+        ]);
+        let stored = store.store(&target, &mut r)?;
+
+        // `big.log` is over the per-artifact limit and `b.log` over the session limit.
+        assert_eq!(1, stored.len());
+        assert_eq!(1, r.artifacts.len());
+        assert_eq!("a.log", stored[0].name);
+        assert_eq!(4, stored[0].size);
+        assert_eq!("1234", fs_util::read_to_string(&r.artifacts[0].path)?);
+        assert!(stored[0].path.starts_with("buck-out/v2/test-artifacts/s1/"));
+
+        let report = store.write_report()?.unwrap();
+        let report = fs_util::read_to_string(fs.path().resolve(&report))?;
+        assert!(report.contains("big.log"));
+        assert!(report.contains("per-artifact limit"));
+        assert!(report.contains("session limit"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_report() -> anyhow::Result<()> {
+        let fs = ProjectRootTemp::new()?;
+        let new_store = || {
+            TestArtifactStore::new(
+                fs.path().dupe(),
+                ProjectRelativePathBuf::unchecked_new("buck-out/v2/test-artifacts/s1".to_owned()),
+                TestArtifactLimits::default(),
+            )
+        };
+        let session_dir = fs.path().resolve(ProjectRelativePath::unchecked_new(
+            "buck-out/v2/test-artifacts/s1",
+        ));
+
+        // Results without artifacts are reported too.
+        let store = new_store();
+        store.start_session()?;
+        assert!(fs_util::try_exists(session_dir.join(IN_PROGRESS_MARKER))?);
+        let target =
+            ConfiguredTargetLabel::testing_parse("cell//pkg:foo", ConfigurationData::testing_new());
+        assert!(store.store(&target, &mut result(Vec::new()))?.is_empty());
+        let report = store.write_report()?.unwrap();
+        let report = fs_util::read_to_string(fs.path().resolve(&report))?;
+        assert!(report.contains("suite - test/case"));
+        assert!(!fs_util::try_exists(session_dir.join(IN_PROGRESS_MARKER))?);
+
+        // Without any result, the session directory is deleted.
+        let store = new_store();
+        store.start_session()?;
+        assert_eq!(None, store.write_report()?);
+        assert!(!fs_util::try_exists(&session_dir)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_prune_old_sessions() -> anyhow::Result<()> {
+        let fs = ProjectRootTemp::new()?;
+        for session in ["s1", "s2", "s3"] {
+            fs.write_file(&format!("buck-out/v2/test-artifacts/{}/x", session), "");
+        }
+        // A concurrent session, which is still running.
+        fs.write_file(
+            &format!("buck-out/v2/test-artifacts/s0/{}", IN_PROGRESS_MARKER),
+            "",
+        );
+        let store = TestArtifactStore::new(
+            fs.path().dupe(),
+            ProjectRelativePathBuf::unchecked_new("buck-out/v2/test-artifacts/s4".to_owned()),
+            TestArtifactLimits {
+                retained_sessions: 2,
+                ..Default::default()
+            },
+        );
+        store.start_session()?;
+
+        let root = fs.path().resolve(ProjectRelativePath::unchecked_new(
+            "buck-out/v2/test-artifacts",
+        ));
+        let mut remaining = fs_util::read_dir(&root)?
+            .map(|e| Ok(e?.file_name().to_string_lossy().into_owned()))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        remaining.sort();
+        assert_eq!(
+            vec!["s0".to_owned(), "s3".to_owned(), "s4".to_owned()],
+            remaining
+        );
+        Ok(())
+    }
+}
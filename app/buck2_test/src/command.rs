@@ -38,10 +38,12 @@ use buck2_core::cells::CellResolver;
 use buck2_core::env_helper::EnvHelper;
 use buck2_core::fs::fs_util;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
 use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use buck2_core::package::PackageLabel;
 use buck2_core::pattern::pattern_type::ConfiguredProvidersPatternExtra;
 use buck2_core::pattern::pattern_type::ProvidersPatternExtra;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
 use buck2_core::pattern::PackageSpec;
 use buck2_core::provider::label::ConfiguredProvidersLabel;
 use buck2_core::provider::label::ProvidersLabel;
@@ -80,12 +82,18 @@ use indexmap::IndexSet;
 use more_futures::cancellation::CancellationContext;
 use serde::Serialize;
 
+use crate::artifacts::TestArtifactLimits;
+use crate::artifacts::TestArtifactStore;
+use crate::coverage::TestCoverageCollector;
 use crate::downward_api::BuckTestDownwardApi;
 use crate::executor_launcher::ExecutorLaunch;
 use crate::executor_launcher::ExecutorLauncher;
 use crate::executor_launcher::OutOfProcessTestExecutor;
 use crate::orchestrator::BuckTestOrchestrator;
 use crate::orchestrator::TestResultOrExitCode;
+use crate::rdeps_tests::default_universe;
+use crate::rdeps_tests::plan_summary;
+use crate::rdeps_tests::rdeps_tests;
 use crate::session::TestSession;
 use crate::session::TestSessionOptions;
 use crate::translations::build_configured_target_handle;
@@ -281,6 +289,33 @@ async fn test(
     let resolved_pattern =
         resolve_target_patterns(&cell_resolver, &parsed_patterns, &ctx.file_ops()).await?;
 
+    let rdeps_tests = match request.rdeps_tests_depth {
+        Some(depth) => {
+            let tested = parsed_patterns
+                .iter()
+                .map(|p| p.clone().try_map(|_| Ok(TargetPatternExtra)))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let universe = if request.rdeps_tests_universe.is_empty() {
+                default_universe(&tested)
+            } else {
+                parse_patterns_from_cli_args(&ctx, &request.rdeps_tests_universe, cwd).await?
+            };
+            let tests = rdeps_tests(
+                &ctx,
+                &tested,
+                universe,
+                global_target_platform.dupe(),
+                depth,
+            )
+            .await?;
+            server_ctx
+                .events()
+                .console_message(plan_summary(&tests, depth));
+            tests.into_map(|t| t.test)
+        }
+        None => Vec::new(),
+    };
+
     let launcher: Box<dyn ExecutorLauncher> = Box::new(OutOfProcessTestExecutor {
         executable: test_executor,
         args: test_executor_args,
@@ -292,11 +327,46 @@ async fn test(
         .as_ref()
         .context("Missing `options`")?;
 
-    let session = TestSession::new(TestSessionOptions {
+    let session = Arc::new(TestSession::new(TestSessionOptions {
         allow_re: options.allow_re,
         force_use_project_relative_paths: options.force_use_project_relative_paths,
         force_run_from_project_root: options.force_run_from_project_root,
-    });
+        list_only: options.list_only,
+    }));
+
+    let artifact_fs = ctx.get_artifact_fs().await?;
+    // The session prefix has a resolution of one second, the trace id tells apart concurrent
+    // commands.
+    let artifacts_session = ForwardRelativePathBuf::new(format!(
+        "{}-{}",
+        session.prefix(),
+        server_ctx.events().trace_id()
+    ))?;
+    let artifact_store = Arc::new(TestArtifactStore::new(
+        artifact_fs.fs().dupe(),
+        artifact_fs
+            .buck_out_path_resolver()
+            .resolve_test_artifacts(&artifacts_session),
+        TestArtifactLimits::from_config(&ctx, cell_resolver.root_cell()).await?,
+    ));
+    artifact_store
+        .start_session()
+        .context("Error creating the test artifacts directory")?;
+
+    let coverage = if options.coverage {
+        Some(Arc::new(TestCoverageCollector::new(
+            artifact_fs.fs().dupe(),
+            artifact_fs
+                .buck_out_path_resolver()
+                .resolve_coverage(session.prefix()),
+        )))
+    } else {
+        None
+    };
+    let coverage_merge_command = ctx
+        .get_legacy_config_property(cell_resolver.root_cell(), "test", "coverage_merge_command")
+        .await?
+        .filter(|s| !s.is_empty());
 
     let test_outcome = test_targets(
         &ctx,
@@ -310,12 +380,30 @@ async fn test(
             request.build_filtered_targets,
         )),
         &*launcher,
-        session,
+        session.dupe(),
+        artifact_store.dupe(),
+        coverage.dupe(),
         cell_resolver,
         working_dir_cell,
+        request.ignore_tests_attribute,
+        rdeps_tests,
     )
     .await?;
 
+    let artifacts_report = artifact_store
+        .write_report()?
+        .map(|path| artifact_fs.fs().resolve(&path).to_string());
+
+    let coverage_report = match coverage {
+        Some(coverage) => {
+            let report = coverage
+                .write_report(coverage_merge_command.as_deref())
+                .await?;
+            Some(artifact_fs.fs().resolve(&report).to_string())
+        }
+        None => None,
+    };
+
     // TODO(bobyf) remap exit code for buck reserved exit code
     let exit_code = test_outcome.exit_code().context("No exit code available")?;
 
@@ -370,6 +458,9 @@ async fn test(
         test_statuses: Some(test_statuses),
         executor_stdout: test_outcome.executor_stdout,
         executor_stderr: test_outcome.executor_stderr,
+        artifacts_report,
+        listed_tests: session.take_listed_tests(),
+        coverage_report,
     })
 }
 
@@ -380,11 +471,14 @@ async fn test_targets(
     external_runner_args: Vec<String>,
     label_filtering: Arc<TestLabelFiltering>,
     launcher: &dyn ExecutorLauncher,
-    session: TestSession,
+    session: Arc<TestSession>,
+    artifact_store: Arc<TestArtifactStore>,
+    coverage: Option<Arc<TestCoverageCollector>>,
     cell_resolver: CellResolver,
     working_dir_cell: CellName,
+    ignore_tests_attribute: bool,
+    rdeps_tests: Vec<ConfiguredProvidersLabel>,
 ) -> anyhow::Result<TestOutcome> {
-    let session = Arc::new(session);
     let (liveliness_observer, _guard) = LivelinessGuard::create();
 
     let tpx_args = {
@@ -437,6 +531,8 @@ async fn test_targets(
                             liveliness_observer.dupe(),
                             test_status_sender,
                             CancellationContext::never_cancelled(), // sending the orchestrator directly to be spawned by make_server, which never calls it.
+                            artifact_store,
+                            coverage,
                         )
                         .await
                         .context("Failed to create a BuckTestOrchestrator")?,
@@ -451,11 +547,13 @@ async fn test_targets(
                         test_executor: &test_executor,
                         cell_resolver: &cell_resolver,
                         working_dir_cell,
+                        ignore_tests_attribute,
                     });
 
                     driver.push_pattern(pattern.convert_pattern().context(
                         "Test with explicit configuration pattern is not supported yet",
                     )?);
+                    driver.push_labels(rdeps_tests);
 
                     {
                         let drive = driver.drive_to_completion();
@@ -557,6 +655,7 @@ pub(crate) struct TestDriverState<'a, 'e> {
     test_executor: &'a Arc<dyn TestExecutor + 'e>,
     cell_resolver: &'a CellResolver,
     working_dir_cell: CellName,
+    ignore_tests_attribute: bool,
 }
 
 /// Maintains the state of an ongoing test execution.
@@ -590,6 +689,14 @@ impl<'a, 'e> TestDriver<'a, 'e> {
         }
     }
 
+    /// Add already configured targets for the test driver to test.
+    fn push_labels(&mut self, labels: Vec<ConfiguredProvidersLabel>) {
+        if !labels.is_empty() {
+            let fut = future::ready(anyhow::Ok(TestDriverTask::TestTargets { labels })).boxed();
+            self.work.push(fut);
+        }
+    }
+
     /// Drive the test loop until all work is complete.
     async fn drive_to_completion(&mut self) {
         while let Some(task) = self.work.next().await {
@@ -665,8 +772,8 @@ impl<'a, 'e> TestDriver<'a, 'e> {
 
                 // Look up `tests` in the the target we're testing, and if we find any tests them, add them to the
                 // test backlog.
-                for test in node.tests() {
-                    labels.push(test);
+                if !state.ignore_tests_attribute {
+                    labels.extend(node.tests());
                 }
 
                 anyhow::Ok(TestDriverTask::TestTargets { labels })
@@ -0,0 +1,287 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Code coverage collection for `buck2 test --coverage`.
+//!
+//! Every test execution gets a `buck2-coverage` output directory, passed to it as
+//! `BUCK2_COVERAGE_DIR`, and `LLVM_PROFILE_FILE` points into it so that binaries built with
+//! `-fprofile-instr-generate` write their raw profiles there. Other runtimes can write their
+//! coverage data to `BUCK2_COVERAGE_DIR` too.
+//!
+//! Once all the tests ran, the coverage files are merged by the `test.coverage_merge_command`
+//! buckconfig, if set, into `buck-out/<isolation dir>/coverage/<session>/merged`, and listed per
+//! target in a `report.json` next to it.
+
+use std::collections::BTreeMap;
+
+use anyhow::Context as _;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+use buck2_core::fs::project::ProjectRoot;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_core::target::label::ConfiguredTargetLabel;
+use buck2_test_api::data::ArgValue;
+use buck2_test_api::data::ArgValueContent;
+use buck2_test_api::data::DeclaredOutput;
+use buck2_util::process::async_background_command;
+use parking_lot::Mutex;
+use serde::Serialize;
+use sorted_vector_map::SortedVectorMap;
+
+/// Name of the output directory tests write their coverage data to.
+const COVERAGE_OUTPUT: &str = "buck2-coverage";
+
+#[derive(Debug, thiserror::Error)]
+enum TestCoverageError {
+    #[error("`test.coverage_merge_command` is empty")]
+    EmptyMergeCommand,
+    #[error("Coverage merge command exited with {status}: {stderr}")]
+    MergeFailed { status: String, stderr: String },
+}
+
+#[derive(Debug, Serialize)]
+struct TestCoverageReport<'a> {
+    project_root: String,
+    /// The merged coverage, if `test.coverage_merge_command` is set and succeeded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    merged: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    merge_error: Option<String>,
+    /// The coverage files written by the tests of each target.
+    targets: &'a BTreeMap<String, Vec<String>>,
+}
+
+/// The coverage collected by a single test session.
+pub struct TestCoverageCollector {
+    fs: ProjectRoot,
+    /// `buck-out/<isolation dir>/coverage/<session>`.
+    dir: ProjectRelativePathBuf,
+    /// Project relative paths of the coverage files, by target.
+    files: Mutex<BTreeMap<String, Vec<String>>>,
+}
+
+impl TestCoverageCollector {
+    pub fn new(fs: ProjectRoot, dir: ProjectRelativePathBuf) -> Self {
+        Self {
+            fs,
+            dir,
+            files: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// The output every test execution writes its coverage data to.
+    pub fn output() -> DeclaredOutput {
+        DeclaredOutput {
+            name: ForwardRelativePathBuf::unchecked_new(COVERAGE_OUTPUT.to_owned()),
+        }
+    }
+
+    /// Tell a test where to write its coverage data.
+    pub fn add_env(env: &mut SortedVectorMap<String, ArgValue>) {
+        let output = |format: Option<&str>| ArgValue {
+            content: ArgValueContent::DeclaredOutput(Self::output()),
+            format: format.map(str::to_owned),
+        };
+        env.insert("BUCK2_COVERAGE_DIR".to_owned(), output(None));
+        // `%p` and `%m` keep the profiles of different processes and binaries apart.
+        env.insert(
+            "LLVM_PROFILE_FILE".to_owned(),
+            output(Some("{}/%p-%m.profraw")),
+        );
+    }
+
+    /// Record the coverage files written to `dir` by a test execution for `target`.
+    pub fn record(&self, target: &ConfiguredTargetLabel, dir: &AbsNormPath) -> anyhow::Result<()> {
+        let mut abs_files = Vec::new();
+        collect_files(dir, &mut abs_files)?;
+        if abs_files.is_empty() {
+            return Ok(());
+        }
+        let mut files = Vec::with_capacity(abs_files.len());
+        for file in abs_files {
+            files.push(self.fs.relativize(&file)?.as_str().to_owned());
+        }
+        self.files
+            .lock()
+            .entry(target.to_string())
+            .or_default()
+            .extend(files);
+        Ok(())
+    }
+
+    /// Merge the coverage files with `merge_command`, if set, and write `report.json`. Returns the
+    /// path of the report.
+    pub async fn write_report(
+        &self,
+        merge_command: Option<&str>,
+    ) -> anyhow::Result<ProjectRelativePathBuf> {
+        let targets = self.files.lock().clone();
+        fs_util::create_dir_all(self.fs.resolve(&self.dir))?;
+
+        let mut merged = None;
+        let mut merge_error = None;
+        if let Some(merge_command) = merge_command {
+            let output = self.dir.join(ForwardRelativePath::new("merged")?);
+            let inputs = targets.values().flatten().cloned().collect::<Vec<_>>();
+            if !inputs.is_empty() {
+                let args = merge_command_args(merge_command, output.as_str(), &inputs);
+                match merge(&args, self.fs.root()).await {
+                    Ok(()) => merged = Some(output.to_string()),
+                    Err(e) => {
+                        tracing::warn!("Error merging coverage: {:#}", e);
+                        merge_error = Some(format!("{:#}", e));
+                    }
+                }
+            }
+        }
+
+        let report = TestCoverageReport {
+            project_root: self.fs.root().to_string(),
+            merged,
+            merge_error,
+            targets: &targets,
+        };
+        let path = self.dir.join(ForwardRelativePath::new("report.json")?);
+        fs_util::write(self.fs.resolve(&path), serde_json::to_vec_pretty(&report)?)
+            .context("Error writing coverage report")?;
+        Ok(path)
+    }
+}
+
+fn collect_files(dir: &AbsNormPath, files: &mut Vec<AbsNormPathBuf>) -> anyhow::Result<()> {
+    let Some(entries) = fs_util::read_dir_if_exists(dir)? else {
+        return Ok(());
+    };
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            collect_files(&entry.path(), files)?;
+        } else {
+            files.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
+/// The merge command line: `{output}` is replaced with the path of the merged coverage, and an
+/// argument which is exactly `{inputs}` with the coverage files, which are appended otherwise.
+/// Paths are relative to the project root, where the command runs.
+fn merge_command_args(merge_command: &str, output: &str, inputs: &[String]) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut has_inputs = false;
+    for arg in merge_command.split_whitespace() {
+        if arg == "{inputs}" {
+            args.extend(inputs.iter().cloned());
+            has_inputs = true;
+        } else {
+            args.push(arg.replace("{output}", output));
+        }
+    }
+    if !has_inputs {
+        args.extend(inputs.iter().cloned());
+    }
+    args
+}
+
+async fn merge(args: &[String], cwd: &AbsNormPath) -> anyhow::Result<()> {
+    let (program, args) = args
+        .split_first()
+        .ok_or(TestCoverageError::EmptyMergeCommand)?;
+    let output = async_background_command(program)
+        .args(args)
+        .current_dir(cwd)
+        .output()
+        .await
+        .with_context(|| format!("Error running `{}`", program))?;
+    if !output.status.success() {
+        return Err(TestCoverageError::MergeFailed {
+            status: output.status.to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::configuration::data::ConfigurationData;
+    use buck2_core::fs::project::ProjectRootTemp;
+    use buck2_core::fs::project_rel_path::ProjectRelativePath;
+    use dupe::Dupe;
+
+    use super::*;
+
+    #[test]
+    fn test_merge_command_args() {
+        let inputs = ["a.profraw".to_owned(), "b.profraw".to_owned()];
+        assert_eq!(
+            merge_command_args(
+                "llvm-profdata merge -sparse -o {output} {inputs}",
+                "merged",
+                &inputs,
+            ),
+            vec![
+                "llvm-profdata",
+                "merge",
+                "-sparse",
+                "-o",
+                "merged",
+                "a.profraw",
+                "b.profraw",
+            ]
+        );
+        assert_eq!(
+            merge_command_args("merge.sh --out={output}", "merged", &inputs),
+            vec!["merge.sh", "--out=merged", "a.profraw", "b.profraw"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_record_and_report() -> anyhow::Result<()> {
+        let fs = ProjectRootTemp::new()?;
+        fs.write_file("buck-out/v2/test/s1/x/buck2-coverage/1-abc.profraw", "");
+        fs.write_file("buck-out/v2/test/s1/x/buck2-coverage/sub/2-abc.profraw", "");
+        let collector = TestCoverageCollector::new(
+            fs.path().dupe(),
+            ProjectRelativePathBuf::unchecked_new("buck-out/v2/coverage/s1".to_owned()),
+        );
+        let target =
+            ConfiguredTargetLabel::testing_parse("cell//pkg:foo", ConfigurationData::testing_new());
+        let resolve = |path| fs.path().resolve(ProjectRelativePath::unchecked_new(path));
+
+        collector.record(&target, &resolve("buck-out/v2/test/s1/x/buck2-coverage"))?;
+        // Tests which did not write any coverage are not listed.
+        collector.record(&target, &resolve("buck-out/v2/test/s1/y"))?;
+
+        let report = collector.write_report(None).await?;
+        assert_eq!("buck-out/v2/coverage/s1/report.json", report.as_str());
+        let report: serde_json::Value =
+            serde_json::from_str(&fs_util::read_to_string(fs.path().resolve(&report))?)?;
+        let mut files = report["targets"][target.to_string()]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| f.as_str().unwrap().to_owned())
+            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                "buck-out/v2/test/s1/x/buck2-coverage/1-abc.profraw",
+                "buck-out/v2/test/s1/x/buck2-coverage/sub/2-abc.profraw",
+            ]
+        );
+        assert!(report.get("merged").is_none());
+        Ok(())
+    }
+}
@@ -11,13 +11,16 @@
 
 #![feature(async_closure)]
 
+pub mod artifacts;
 pub mod command;
+pub mod coverage;
 pub mod downward_api;
 pub mod executor_launcher;
 pub(crate) mod local_resource_api;
 pub(crate) mod local_resource_registry;
 pub(crate) mod local_resource_setup;
 pub mod orchestrator;
+pub(crate) mod rdeps_tests;
 pub mod session;
 pub(crate) mod tcp;
 pub mod translations;
@@ -13,6 +13,7 @@ use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::sync::Arc;
 use std::time::Duration;
+use std::time::SystemTime;
 
 use anyhow::Context as _;
 use async_trait::async_trait;
@@ -103,6 +104,7 @@ use buck2_test_api::data::Output;
 use buck2_test_api::data::PrepareForLocalExecutionResult;
 use buck2_test_api::data::RequiredLocalResources;
 use buck2_test_api::data::TestResult;
+use buck2_test_api::data::TestStatus;
 use buck2_test_api::protocol::TestOrchestrator;
 use dice::DiceTransaction;
 use dupe::Dupe;
@@ -118,6 +120,8 @@ use sorted_vector_map::SortedVectorMap;
 use starlark::values::FrozenRef;
 use uuid::Uuid;
 
+use crate::artifacts::TestArtifactStore;
+use crate::coverage::TestCoverageCollector;
 use crate::local_resource_api::LocalResourcesSetupResult;
 use crate::local_resource_registry::LocalResourceRegistry;
 use crate::local_resource_setup::required_local_resources_setup_contexts;
@@ -142,6 +146,9 @@ pub struct BuckTestOrchestrator<'a> {
     digest_config: DigestConfig,
     cancellations: &'a CancellationContext,
     local_resource_state_registry: LocalResourceRegistry<'a>,
+    artifact_store: Arc<TestArtifactStore>,
+    /// Set when collecting code coverage.
+    coverage: Option<Arc<TestCoverageCollector>>,
 }
 
 impl<'a> BuckTestOrchestrator<'a> {
@@ -151,6 +158,8 @@ impl<'a> BuckTestOrchestrator<'a> {
         liveliness_observer: Arc<dyn LivelinessObserver>,
         results_channel: UnboundedSender<anyhow::Result<TestResultOrExitCode>>,
         cancellations: &'a CancellationContext,
+        artifact_store: Arc<TestArtifactStore>,
+        coverage: Option<Arc<TestCoverageCollector>>,
     ) -> anyhow::Result<BuckTestOrchestrator<'a>> {
         let events = dice.per_transaction_data().get_dispatcher().dupe();
         let digest_config = dice.global_data().get_digest_config();
@@ -162,6 +171,8 @@ impl<'a> BuckTestOrchestrator<'a> {
             events,
             digest_config,
             cancellations,
+            artifact_store,
+            coverage,
         ))
     }
 
@@ -173,6 +184,8 @@ impl<'a> BuckTestOrchestrator<'a> {
         events: EventDispatcher,
         digest_config: DigestConfig,
         cancellations: &'a CancellationContext,
+        artifact_store: Arc<TestArtifactStore>,
+        coverage: Option<Arc<TestCoverageCollector>>,
     ) -> BuckTestOrchestrator<'a> {
         Self {
             dice,
@@ -183,6 +196,8 @@ impl<'a> BuckTestOrchestrator<'a> {
             digest_config,
             cancellations,
             local_resource_state_registry: LocalResourceRegistry::new(),
+            artifact_store,
+            coverage,
         }
     }
 }
@@ -200,10 +215,10 @@ impl<'a> TestOrchestrator for BuckTestOrchestrator<'a> {
         metadata: DisplayMetadata,
         test_target: ConfiguredTargetHandle,
         cmd: Vec<ArgValue>,
-        env: SortedVectorMap<String, ArgValue>,
+        mut env: SortedVectorMap<String, ArgValue>,
         timeout: Duration,
         host_sharing_requirements: HostSharingRequirements,
-        pre_create_dirs: Vec<DeclaredOutput>,
+        mut pre_create_dirs: Vec<DeclaredOutput>,
         executor_override: Option<ExecutorConfigOverride>,
         required_local_resources: RequiredLocalResources,
     ) -> anyhow::Result<ExecutionResult2> {
@@ -211,8 +226,31 @@ impl<'a> TestOrchestrator for BuckTestOrchestrator<'a> {
 
         let test_target = self.session.get(test_target)?;
 
+        if self.session.options().list_only {
+            if let DisplayMetadata::Testing { suite, testcases } = metadata {
+                // Listing only: record what the runner wanted to run, but don't run it. Runners
+                // that list test binaries themselves report the cases via
+                // `report_tests_discovered` instead.
+                self.session
+                    .record_listed_tests(&test_target, suite, testcases);
+                return Ok(ExecutionResult2 {
+                    status: ExecutionStatus::Finished { exitcode: 0 },
+                    stdout: ExecutionStream::Inline(Default::default()),
+                    stderr: ExecutionStream::Inline(Default::default()),
+                    outputs: HashMap::new(),
+                    start_time: SystemTime::now(),
+                    execution_time: Duration::ZERO,
+                });
+            }
+        }
+
         let fs = self.dice.get_artifact_fs().await?;
 
+        if self.coverage.is_some() {
+            TestCoverageCollector::add_env(&mut env);
+            pre_create_dirs.push(TestCoverageCollector::output());
+        }
+
         let test_info = self.get_test_info(&test_target).await?;
         let test_executor = self
             .get_test_executor(&test_target, &test_info, executor_override, &fs)
@@ -284,7 +322,7 @@ impl<'a> TestOrchestrator for BuckTestOrchestrator<'a> {
 
         self.liveliness_observer.require_alive().await?;
 
-        let (outputs, paths_to_materialize) = outputs
+        let (mut outputs, paths_to_materialize): (HashMap<_, _>, Vec<_>) = outputs
             .into_iter()
             .map(|test_path| {
                 let project_path = fs.buck_out_path_resolver().resolve_test(&test_path);
@@ -305,6 +343,13 @@ impl<'a> TestOrchestrator for BuckTestOrchestrator<'a> {
             .await
             .context("Error materializing test outputs")?;
 
+        if let Some(coverage) = &self.coverage {
+            // The test runner did not declare this output, so it does not get to see it.
+            if let Some(Output::LocalPath(dir)) = outputs.remove(&TestCoverageCollector::output()) {
+                coverage.record(test_target.target(), &dir)?;
+            }
+        }
+
         Ok(ExecutionResult2 {
             status,
             stdout,
@@ -316,9 +361,30 @@ impl<'a> TestOrchestrator for BuckTestOrchestrator<'a> {
     }
 
     async fn report_test_result(&self, r: TestResult) -> anyhow::Result<()> {
+        if self.session.options().list_only
+            && !matches!(
+                r.status,
+                TestStatus::LISTING_SUCCESS | TestStatus::LISTING_FAILED
+            )
+        {
+            // Nothing was actually run, so these results would be meaningless.
+            return Ok(());
+        }
+
+        let target = self.session.get(r.target)?.target().dupe();
+        let store = self.artifact_store.dupe();
+        let (r, artifacts) = tokio::task::spawn_blocking(move || {
+            let mut r = r;
+            let artifacts = store.store(&target, &mut r)?;
+            anyhow::Ok((r, artifacts))
+        })
+        .await
+        .context("Error storing test artifacts")??;
+
         let event = buck2_data::instant_event::Data::TestResult(translations::convert_test_result(
             r.clone(),
             &self.session,
+            artifacts.map(|a| a.to_proto()),
         )?);
         self.events.instant_event(event);
         self.results_channel
@@ -335,6 +401,11 @@ impl<'a> TestOrchestrator for BuckTestOrchestrator<'a> {
     ) -> anyhow::Result<()> {
         let test_target = self.session.get(test_target)?;
 
+        if self.session.options().list_only {
+            self.session
+                .record_listed_tests(&test_target, suite.clone(), names.iter().cloned());
+        }
+
         self.events.instant_event(TestDiscovery {
             data: Some(buck2_data::test_discovery::Data::Tests(TestSuite {
                 suite_name: suite,
@@ -604,10 +675,18 @@ impl<'b> BuckTestOrchestrator<'b> {
                 .context("Error accessing executor config")?,
         };
 
-        let CommandExecutorResponse { executor, platform } =
-            self.dice.get_command_executor(fs, executor_config)?;
-        let executor =
-            CommandExecutor::new(executor, fs.clone(), executor_config.options, platform);
+        let CommandExecutorResponse {
+            executor,
+            platform,
+            platform_by_category,
+        } = self.dice.get_command_executor(fs, executor_config)?;
+        let executor = CommandExecutor::new(
+            executor,
+            fs.clone(),
+            executor_config.options,
+            platform,
+            platform_by_category,
+        );
         Ok(executor)
     }
 
@@ -619,10 +698,18 @@ impl<'b> BuckTestOrchestrator<'b> {
                 output_paths_behavior: Default::default(),
             },
         };
-        let CommandExecutorResponse { executor, platform } =
-            self.dice.get_command_executor(fs, &executor_config)?;
-        let executor =
-            CommandExecutor::new(executor, fs.clone(), executor_config.options, platform);
+        let CommandExecutorResponse {
+            executor,
+            platform,
+            platform_by_category,
+        } = self.dice.get_command_executor(fs, &executor_config)?;
+        let executor = CommandExecutor::new(
+            executor,
+            fs.clone(),
+            executor_config.options,
+            platform,
+            platform_by_category,
+        );
         Ok(executor)
     }
 
@@ -1278,6 +1365,7 @@ mod tests {
     use buck2_core::configuration::data::ConfigurationData;
     use buck2_core::fs::project::ProjectRootTemp;
     use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+    use buck2_data::TestSuite;
     use buck2_events::dispatch::EventDispatcher;
     use buck2_test_api::data::TestStatus;
     use dice::testing::DiceBuilder;
@@ -1288,10 +1376,20 @@ mod tests {
     use futures::stream::TryStreamExt;
 
     use super::*;
+    use crate::session::TestSessionOptions;
 
     async fn make() -> anyhow::Result<(
         BuckTestOrchestrator<'static>,
         UnboundedReceiver<anyhow::Result<TestResultOrExitCode>>,
+    )> {
+        make_with_options(Default::default()).await
+    }
+
+    async fn make_with_options(
+        options: TestSessionOptions,
+    ) -> anyhow::Result<(
+        BuckTestOrchestrator<'static>,
+        UnboundedReceiver<anyhow::Result<TestResultOrExitCode>>,
     )> {
         let fs = ProjectRootTemp::new().unwrap();
 
@@ -1313,12 +1411,20 @@ mod tests {
         Ok((
             BuckTestOrchestrator::from_parts(
                 dice,
-                Arc::new(TestSession::new(Default::default())),
+                Arc::new(TestSession::new(options)),
                 NoopLivelinessObserver::create(),
                 sender,
                 EventDispatcher::null(),
                 DigestConfig::testing_default(),
                 CancellationContext::testing(),
+                Arc::new(TestArtifactStore::new(
+                    fs.path().dupe(),
+                    ProjectRelativePathBuf::unchecked_new(
+                        "buck_out/v2/test-artifacts/session".to_owned(),
+                    ),
+                    Default::default(),
+                )),
+                None,
             ),
             receiver,
         ))
@@ -1343,6 +1449,7 @@ mod tests {
                     name: "First - test".to_owned(),
                     duration: Some(Duration::from_micros(1)),
                     details: "1".to_owned(),
+                    artifacts: Vec::new(),
                 })
                 .await?;
 
@@ -1354,6 +1461,7 @@ mod tests {
                     name: "Second - test".to_owned(),
                     duration: Some(Duration::from_micros(2)),
                     details: "2".to_owned(),
+                    artifacts: Vec::new(),
                 })
                 .await?;
 
@@ -1375,6 +1483,7 @@ mod tests {
                     name: "First - test".to_owned(),
                     duration: Some(Duration::from_micros(1)),
                     details: "1".to_owned(),
+                    artifacts: Vec::new(),
                 }),
                 TestResultOrExitCode::TestResult(TestResult {
                     target,
@@ -1384,6 +1493,7 @@ mod tests {
                     name: "Second - test".to_owned(),
                     duration: Some(Duration::from_micros(2)),
                     details: "2".to_owned(),
+                    artifacts: Vec::new(),
                 }),
                 TestResultOrExitCode::ExitCode(0),
             ]
@@ -1392,6 +1502,104 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_orchestrator_list_only() -> anyhow::Result<()> {
+        let (orchestrator, channel) = make_with_options(TestSessionOptions {
+            list_only: true,
+            ..Default::default()
+        })
+        .await?;
+
+        let target =
+            ConfiguredTargetLabel::testing_parse("cell//pkg:foo", ConfigurationData::testing_new());
+
+        let target = ConfiguredProvidersLabel::new(target, Default::default());
+        let handle = orchestrator.session.register(target.clone());
+
+        let listing = TestResult {
+            target: handle,
+            status: TestStatus::LISTING_SUCCESS,
+            msg: None,
+            name: "suite - listing".to_owned(),
+            duration: None,
+            details: "".to_owned(),
+            artifacts: Vec::new(),
+        };
+
+        let jobs = async {
+            // Runners which list test binaries themselves report the test cases they found...
+            orchestrator
+                .report_tests_discovered(
+                    handle,
+                    "suite".to_owned(),
+                    vec!["a".to_owned(), "b".to_owned()],
+                )
+                .await?;
+
+            // ... others ask to run them, which only records them.
+            let res = orchestrator
+                .execute2(
+                    DisplayMetadata::Testing {
+                        suite: "suite".to_owned(),
+                        testcases: vec!["b".to_owned(), "c".to_owned()],
+                    },
+                    handle,
+                    Vec::new(),
+                    SortedVectorMap::new(),
+                    Duration::from_secs(1),
+                    HostSharingRequirements::default(),
+                    Vec::new(),
+                    None,
+                    RequiredLocalResources {
+                        resources: Vec::new(),
+                    },
+                )
+                .await?;
+            assert!(matches!(
+                res.status,
+                ExecutionStatus::Finished { exitcode: 0 }
+            ));
+
+            // Nothing was run, so only the results of listings are reported.
+            orchestrator
+                .report_test_result(TestResult {
+                    target: handle,
+                    status: TestStatus::PASS,
+                    msg: None,
+                    name: "suite - b".to_owned(),
+                    duration: Some(Duration::ZERO),
+                    details: "".to_owned(),
+                    artifacts: Vec::new(),
+                })
+                .await?;
+            orchestrator.report_test_result(listing.clone()).await?;
+
+            orchestrator.end_of_test_results(0).await?;
+
+            anyhow::Ok(())
+        };
+
+        let ((), results) = future::try_join(jobs, channel.try_collect::<Vec<_>>()).await?;
+
+        assert_eq!(
+            results,
+            vec![
+                TestResultOrExitCode::TestResult(listing),
+                TestResultOrExitCode::ExitCode(0),
+            ]
+        );
+        assert_eq!(
+            orchestrator.session.take_listed_tests(),
+            vec![TestSuite {
+                suite_name: "suite".to_owned(),
+                test_names: vec!["a".to_owned(), "b".to_owned(), "c".to_owned()],
+                target_label: Some(target.target().as_proto()),
+            }]
+        );
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_orchestrator_channel_drop() -> anyhow::Result<()> {
         let (orchestrator, channel) = make().await?;
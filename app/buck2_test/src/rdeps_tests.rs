@@ -0,0 +1,204 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Finding the tests declared by the reverse dependencies of the targets being tested, so that
+//! `buck2 test //lib:lib --rdeps-tests-depth=N` also runs the tests of the code using the library.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::hash::Hash;
+
+use buck2_build_api::calculation::MissingTargetBehavior;
+use buck2_build_api::configure_targets::load_compatible_patterns;
+use buck2_core::cells::cell_path::CellPath;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_core::pattern::ParsedPattern;
+use buck2_core::provider::label::ConfiguredProvidersLabel;
+use buck2_core::target::label::ConfiguredTargetLabel;
+use buck2_core::target::label::TargetLabel;
+use dice::DiceComputations;
+use dupe::Dupe;
+use gazebo::prelude::*;
+
+/// A test found in the `tests` attribute of a reverse dependency of a target being tested.
+pub(crate) struct RdepTest {
+    pub(crate) test: ConfiguredProvidersLabel,
+    /// The reverse dependency whose `tests` attribute lists the test.
+    pub(crate) declared_by: ConfiguredTargetLabel,
+}
+
+/// Patterns matching every target under the packages of the given patterns. Loading whole cells
+/// would be too expensive on large repositories, so reverse dependencies elsewhere are only found
+/// with an explicit universe.
+pub(crate) fn default_universe(
+    tested: &[ParsedPattern<TargetPatternExtra>],
+) -> Vec<ParsedPattern<TargetPatternExtra>> {
+    let mut roots: Vec<CellPath> = Vec::new();
+    for pattern in tested {
+        let root = match pattern {
+            ParsedPattern::Target(package, _, _) | ParsedPattern::Package(package) => {
+                package.to_cell_path()
+            }
+            ParsedPattern::Recursive(path) => path.clone(),
+        };
+        if roots.iter().any(|r| root.starts_with(r.as_ref())) {
+            continue;
+        }
+        roots.retain(|r| !r.starts_with(root.as_ref()));
+        roots.push(root);
+    }
+    roots.into_map(ParsedPattern::Recursive)
+}
+
+/// The nodes from which `roots` can be reached by following at least one and at most `depth`
+/// edges, in the order they are found. `edges` maps each node to the nodes it depends on.
+fn reverse_reachable<T: Clone + Eq + Hash>(
+    edges: impl IntoIterator<Item = (T, Vec<T>)>,
+    roots: impl IntoIterator<Item = T>,
+    depth: u32,
+) -> Vec<T> {
+    let mut rdeps: HashMap<T, Vec<T>> = HashMap::new();
+    for (node, deps) in edges {
+        for dep in deps {
+            rdeps.entry(dep).or_default().push(node.clone());
+        }
+    }
+
+    let mut frontier: Vec<T> = roots.into_iter().collect();
+    let mut seen: HashSet<T> = frontier.iter().cloned().collect();
+    let mut found = Vec::new();
+    for _ in 0..depth {
+        let mut next = Vec::new();
+        for node in &frontier {
+            for rdep in rdeps.get(node).into_iter().flatten() {
+                if seen.insert(rdep.clone()) {
+                    found.push(rdep.clone());
+                    next.push(rdep.clone());
+                }
+            }
+        }
+        if next.is_empty() {
+            break;
+        }
+        frontier = next;
+    }
+    found
+}
+
+/// Find the tests declared by targets in `universe` which depend (transitively, up to `depth`
+/// levels) on the targets matching `tested`.
+pub(crate) async fn rdeps_tests(
+    ctx: &DiceComputations,
+    tested: &[ParsedPattern<TargetPatternExtra>],
+    universe: Vec<ParsedPattern<TargetPatternExtra>>,
+    global_target_platform: Option<TargetLabel>,
+    depth: u32,
+) -> anyhow::Result<Vec<RdepTest>> {
+    let universe = load_compatible_patterns(
+        ctx,
+        universe,
+        global_target_platform,
+        MissingTargetBehavior::Fail,
+    )
+    .await?;
+
+    let roots = universe.iter().filter_map(|node| {
+        let label = node.label();
+        if tested.iter().any(|p| p.matches(label.unconfigured())) {
+            Some(label.dupe())
+        } else {
+            None
+        }
+    });
+    let edges = universe.iter().map(|node| {
+        (
+            node.label().dupe(),
+            node.deps().map(|dep| dep.label().dupe()).collect(),
+        )
+    });
+
+    let mut tests = Vec::new();
+    for rdep in reverse_reachable(edges, roots, depth) {
+        let node = universe
+            .get(&rdep)
+            .expect("reverse dependencies are found in the universe");
+        for test in node.tests() {
+            tests.push(RdepTest {
+                test,
+                declared_by: rdep.dupe(),
+            });
+        }
+    }
+    Ok(tests)
+}
+
+/// Describe the tests which will run in addition to the targets being tested.
+pub(crate) fn plan_summary(tests: &[RdepTest], depth: u32) -> String {
+    if tests.is_empty() {
+        return format!(
+            "Test plan: no reverse dependencies (up to depth {}) declare `tests`",
+            depth
+        );
+    }
+    let mut summary = format!(
+        "Test plan: also testing {} targets declared in `tests` of reverse dependencies (up to depth {}):",
+        tests.len(),
+        depth
+    );
+    for test in tests {
+        write!(summary, "\n  {} (from {})", test.test, test.declared_by).unwrap();
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::pattern::pattern_type::TargetPatternExtra;
+    use buck2_core::pattern::ParsedPattern;
+
+    use crate::rdeps_tests::default_universe;
+    use crate::rdeps_tests::reverse_reachable;
+
+    #[test]
+    fn test_default_universe() {
+        let universe = default_universe(&[
+            ParsedPattern::<TargetPatternExtra>::testing_parse("root//lib/foo:foo"),
+            ParsedPattern::testing_parse("root//lib:"),
+            ParsedPattern::testing_parse("root//lib/bar:bar"),
+            ParsedPattern::testing_parse("other//tools/..."),
+            ParsedPattern::testing_parse("other//tools/x:x"),
+        ]);
+        assert_eq!(
+            universe.iter().map(|p| p.to_string()).collect::<Vec<_>>(),
+            vec!["root//lib/...", "other//tools/..."]
+        );
+    }
+
+    #[test]
+    fn test_reverse_reachable() {
+        // app -> lib -> base, tool -> base, other
+        let edges = vec![
+            ("app", vec!["lib"]),
+            ("lib", vec!["base"]),
+            ("tool", vec!["base"]),
+            ("base", vec![]),
+            ("other", vec![]),
+        ];
+        let mut depth_1 = reverse_reachable(edges.clone(), ["base"], 1);
+        depth_1.sort();
+        assert_eq!(depth_1, vec!["lib", "tool"]);
+
+        let mut depth_2 = reverse_reachable(edges.clone(), ["base"], 2);
+        depth_2.sort();
+        assert_eq!(depth_2, vec!["app", "lib", "tool"]);
+
+        assert_eq!(reverse_reachable(edges, ["base"], 0), Vec::<&str>::new());
+    }
+}
@@ -14,10 +14,12 @@ use anyhow::Context as _;
 use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
 use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
 use buck2_core::provider::label::ConfiguredProvidersLabel;
+use buck2_data::TestSuite;
 use buck2_test_api::data::ConfiguredTargetHandle;
 use chrono::Local;
 use dashmap::DashMap;
 use dupe::Dupe;
+use parking_lot::Mutex;
 
 #[derive(Debug, Clone, Copy, Dupe, Default)]
 pub struct TestSessionOptions {
@@ -25,6 +27,9 @@ pub struct TestSessionOptions {
     pub allow_re: bool,
     pub force_use_project_relative_paths: bool,
     pub force_run_from_project_root: bool,
+    /// Whether tests should only be listed. Test executions requested by the runner are not
+    /// performed, and the suites and test cases the runner reports are recorded instead.
+    pub list_only: bool,
 }
 
 /// The state of a buck2 test command.
@@ -41,6 +46,8 @@ pub struct TestSession {
     /// Options overriding the behavior of tests executed in this session. This is primarily
     /// intended for unstable or debugging features.
     options: TestSessionOptions,
+    /// The test suites found so far, when `options.list_only` is set.
+    listed_tests: Mutex<Vec<TestSuite>>,
 }
 
 impl TestSession {
@@ -57,6 +64,7 @@ impl TestSession {
             labels: DashMap::new(),
             prefix,
             options,
+            listed_tests: Mutex::new(Vec::new()),
         }
     }
 
@@ -85,4 +93,40 @@ impl TestSession {
 
         Ok(res.clone())
     }
+
+    /// Record test cases found in a suite. Suites can be reported several times (e.g. once per
+    /// shard), so cases are merged into the existing entry for the same target and suite.
+    pub fn record_listed_tests(
+        &self,
+        label: &ConfiguredProvidersLabel,
+        suite_name: String,
+        test_names: impl IntoIterator<Item = String>,
+    ) {
+        let target_label = Some(label.target().as_proto());
+        let mut listed_tests = self.listed_tests.lock();
+        let suite = match listed_tests
+            .iter_mut()
+            .find(|s| s.suite_name == suite_name && s.target_label == target_label)
+        {
+            Some(suite) => suite,
+            None => {
+                listed_tests.push(TestSuite {
+                    suite_name,
+                    test_names: Vec::new(),
+                    target_label,
+                });
+                listed_tests.last_mut().unwrap()
+            }
+        };
+        for name in test_names {
+            if !suite.test_names.contains(&name) {
+                suite.test_names.push(name);
+            }
+        }
+    }
+
+    /// The test suites found by this session.
+    pub fn take_listed_tests(&self) -> Vec<TestSuite> {
+        std::mem::take(&mut *self.listed_tests.lock())
+    }
 }
@@ -44,6 +44,7 @@ pub fn build_configured_target_handle(
 pub fn convert_test_result(
     test_result: buck2_test_api::data::TestResult,
     session: &TestSession,
+    artifacts: Vec<buck2_data::TestArtifact>,
 ) -> anyhow::Result<buck2_data::TestResult> {
     let buck2_test_api::data::TestResult {
         name,
@@ -52,6 +53,7 @@ pub fn convert_test_result(
         duration,
         details,
         target: test_target,
+        artifacts: _,
     } = test_result;
 
     let test_target = session.get(test_target)?;
@@ -63,5 +65,6 @@ pub fn convert_test_result(
         duration: duration.and_then(|d| d.try_into().ok()),
         details,
         target_label: Some(test_target.target().as_proto()),
+        artifacts,
     })
 }
@@ -34,6 +34,7 @@ use crate::data::ExecutorConfigOverride;
 use crate::data::ExternalRunnerSpec;
 use crate::data::ExternalRunnerSpecValue;
 use crate::data::Output;
+use crate::data::TestArtifact;
 use crate::data::TestExecutable;
 use crate::data::TestResult;
 use crate::data::TestStatus;
@@ -247,6 +248,7 @@ impl TryFrom<buck2_test_proto::TestResult> for TestResult {
             msg,
             duration,
             details,
+            artifacts,
         } = s;
 
         let duration = duration
@@ -254,6 +256,10 @@ impl TryFrom<buck2_test_proto::TestResult> for TestResult {
             .transpose()
             .context("For `duration`")?;
 
+        let artifacts = artifacts
+            .into_try_map(|a| a.try_into())
+            .context("Invalid `artifacts`")?;
+
         Ok(Self {
             target: target
                 .context("Missing `target`")?
@@ -264,6 +270,7 @@ impl TryFrom<buck2_test_proto::TestResult> for TestResult {
             msg: msg.map(|m| m.msg),
             duration,
             details,
+            artifacts,
         })
     }
 }
@@ -281,6 +288,31 @@ impl TryInto<buck2_test_proto::TestResult> for TestResult {
             details: self.details,
             msg: self.msg.map(|msg| OptionalMsg { msg }),
             duration: self.duration.try_map(|d| d.try_into())?,
+            artifacts: self.artifacts.into_try_map(|a| a.try_into())?,
+        })
+    }
+}
+
+impl TryFrom<buck2_test_proto::TestArtifact> for TestArtifact {
+    type Error = anyhow::Error;
+
+    fn try_from(s: buck2_test_proto::TestArtifact) -> Result<Self, Self::Error> {
+        let buck2_test_proto::TestArtifact { name, local_path } = s;
+
+        Ok(Self {
+            name: ForwardRelativePathBuf::new(name).context("Invalid `name`")?,
+            path: local_path.try_into().context("Invalid `local_path`")?,
+        })
+    }
+}
+
+impl TryInto<buck2_test_proto::TestArtifact> for TestArtifact {
+    type Error = anyhow::Error;
+
+    fn try_into(self) -> Result<buck2_test_proto::TestArtifact, Self::Error> {
+        Ok(buck2_test_proto::TestArtifact {
+            name: self.name.as_str().to_owned(),
+            local_path: self.path.to_str().context("Invalid local path")?.to_owned(),
         })
     }
 }
@@ -944,6 +976,29 @@ mod tests {
         assert_roundtrips::<buck2_test_proto::ExecutionResult2, ExecutionResult2>(&result);
     }
 
+    #[test]
+    fn test_result_roundtrip() {
+        let local_path = if cfg!(not(windows)) {
+            "/some/path/screenshot.png"
+        } else {
+            "c:/some/path/screenshot.png"
+        };
+
+        let result = TestResult {
+            target: ConfiguredTargetHandle(7),
+            name: "test_foo".to_owned(),
+            status: TestStatus::FAIL,
+            msg: Some("boom".to_owned()),
+            duration: Some(Duration::from_secs(3)),
+            details: "details".to_owned(),
+            artifacts: vec![TestArtifact {
+                name: ForwardRelativePathBuf::unchecked_new("screenshots/foo.png".to_owned()),
+                path: String::from(local_path).try_into().expect("valid abs path"),
+            }],
+        };
+        assert_roundtrips::<buck2_test_proto::TestResult, TestResult>(&result);
+    }
+
     #[test]
     fn prepare_for_local_execution_result_roundtrip() {
         let cmd = vec![
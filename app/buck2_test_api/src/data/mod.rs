@@ -92,6 +92,18 @@ pub struct TestResult {
     pub duration: Option<Duration>,
     // the output of the test execution (combining stdout and stderr)
     pub details: String,
+    // files produced by this test case that Buck should keep
+    pub artifacts: Vec<TestArtifact>,
+}
+
+/// A file attached to a single test result by the test runner.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct TestArtifact {
+    /// Relative path the artifact is stored under, unique within the test case.
+    pub name: ForwardRelativePathBuf,
+    /// The file as written by the test runner. Once Buck has stored the artifact, this points to
+    /// the stored copy.
+    pub path: AbsNormPathBuf,
 }
 
 /// different possible test results
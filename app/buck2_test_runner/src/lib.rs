@@ -11,6 +11,7 @@
 
 mod config;
 mod executor;
+mod results;
 mod runner;
 mod service;
 pub mod tcp;
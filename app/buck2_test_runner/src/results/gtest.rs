@@ -0,0 +1,147 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! GoogleTest JSON reports, as written with `--gtest_output=json:<path>`.
+
+use buck2_test_api::data::TestStatus;
+use serde::Deserialize;
+
+use crate::results::first_line;
+use crate::results::parse_seconds;
+use crate::results::CaseResult;
+
+#[derive(Deserialize)]
+struct Report {
+    #[serde(default)]
+    testsuites: Vec<Suite>,
+}
+
+#[derive(Deserialize)]
+struct Suite {
+    name: String,
+    #[serde(default)]
+    testsuite: Vec<Case>,
+}
+
+#[derive(Deserialize)]
+struct Case {
+    name: String,
+    /// `RUN` or `NOTRUN` (disabled tests).
+    #[serde(default)]
+    status: Option<String>,
+    /// `COMPLETED`, `SKIPPED` or `SUPPRESSED`.
+    #[serde(default)]
+    result: Option<String>,
+    /// Seconds, suffixed with `s`.
+    #[serde(default)]
+    time: Option<String>,
+    #[serde(default)]
+    failures: Vec<Failure>,
+}
+
+#[derive(Deserialize)]
+struct Failure {
+    failure: String,
+}
+
+impl Case {
+    fn status(&self) -> TestStatus {
+        if !self.failures.is_empty() {
+            TestStatus::FAIL
+        } else if self.status.as_deref() == Some("NOTRUN")
+            || self.result.as_deref() == Some("SUPPRESSED")
+        {
+            TestStatus::OMITTED
+        } else if self.result.as_deref() == Some("SKIPPED") {
+            TestStatus::SKIP
+        } else {
+            TestStatus::PASS
+        }
+    }
+}
+
+pub(super) fn parse(contents: &str) -> anyhow::Result<Vec<CaseResult>> {
+    let report: Report = serde_json::from_str(contents)?;
+    let mut cases = Vec::new();
+    for suite in report.testsuites {
+        for case in suite.testsuite {
+            let failures = case
+                .failures
+                .iter()
+                .map(|f| f.failure.as_str())
+                .collect::<Vec<_>>();
+            cases.push(CaseResult {
+                name: format!("{}.{}", suite.name, case.name),
+                status: case.status(),
+                duration: case
+                    .time
+                    .as_deref()
+                    .and_then(|time| parse_seconds(time.trim_end_matches('s'))),
+                msg: failures.first().and_then(|failure| first_line(failure)),
+                details: failures.join("\n"),
+            });
+        }
+    }
+    Ok(cases)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let report = r#"{
+          "tests": 4,
+          "failures": 1,
+          "testsuites": [
+            {
+              "name": "MathTest",
+              "tests": 4,
+              "testsuite": [
+                {"name": "Adds", "status": "RUN", "result": "COMPLETED", "time": "0.5s",
+                 "classname": "MathTest"},
+                {"name": "Divides", "status": "RUN", "result": "COMPLETED", "time": "0s",
+                 "classname": "MathTest",
+                 "failures": [
+                   {"failure": "math_test.cpp:12\nExpected equality\n  1 / 2\n", "type": ""}
+                 ]},
+                {"name": "Skipped", "status": "RUN", "result": "SKIPPED", "time": "0s",
+                 "classname": "MathTest"},
+                {"name": "DISABLED_Slow", "status": "NOTRUN", "result": "SUPPRESSED",
+                 "time": "0s", "classname": "MathTest"}
+              ]
+            }
+          ]
+        }"#;
+        let cases = parse(report).unwrap();
+        assert_eq!(
+            vec![
+                ("MathTest.Adds", TestStatus::PASS),
+                ("MathTest.Divides", TestStatus::FAIL),
+                ("MathTest.Skipped", TestStatus::SKIP),
+                ("MathTest.DISABLED_Slow", TestStatus::OMITTED),
+            ],
+            cases
+                .iter()
+                .map(|c| (c.name.as_str(), c.status.clone()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Some(Duration::from_millis(500)), cases[0].duration);
+        assert_eq!(Some("math_test.cpp:12"), cases[1].msg.as_deref());
+        assert!(cases[1].details.contains("Expected equality"));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(parse("not json").is_err());
+    }
+}
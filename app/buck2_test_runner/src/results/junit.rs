@@ -0,0 +1,199 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! JUnit XML reports, as written by JUnit, pytest's `--junitxml` and many other frameworks.
+//!
+//! Only `<testcase>` elements are looked at, wherever they are nested, so that reports with a
+//! single `<testsuite>` and with `<testsuites>` are both supported.
+
+use buck2_test_api::data::TestStatus;
+use quick_xml::events::BytesStart;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+use crate::results::first_line;
+use crate::results::parse_seconds;
+use crate::results::CaseResult;
+
+/// The part of a test case the text being read belongs to.
+enum Section {
+    /// The body of `<failure>`, `<error>` or `<skipped>`.
+    Details,
+    Stdout,
+    Stderr,
+}
+
+fn attribute(element: &BytesStart, name: &str) -> anyhow::Result<Option<String>> {
+    for attr in element.attributes() {
+        let attr = attr?;
+        if attr.key.as_ref() == name.as_bytes() {
+            return Ok(Some(attr.unescape_value()?.into_owned()));
+        }
+    }
+    Ok(None)
+}
+
+fn new_case(testcase: &BytesStart) -> anyhow::Result<CaseResult> {
+    let name = attribute(testcase, "name")?.unwrap_or_default();
+    let name = match attribute(testcase, "classname")? {
+        Some(classname) if !classname.is_empty() => format!("{}.{}", classname, name),
+        _ => name,
+    };
+    Ok(CaseResult {
+        name,
+        status: TestStatus::PASS,
+        duration: attribute(testcase, "time")?.and_then(|time| parse_seconds(&time)),
+        msg: None,
+        details: String::new(),
+    })
+}
+
+pub(super) fn parse(contents: &str) -> anyhow::Result<Vec<CaseResult>> {
+    let mut reader = Reader::from_str(contents);
+    reader.expand_empty_elements(true);
+
+    let mut cases = Vec::new();
+    let mut case = None;
+    let mut section = None;
+    let mut stdout = String::new();
+    let mut stderr = String::new();
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) if e.local_name().as_ref() == b"testcase" => {
+                case = Some(new_case(&e)?);
+                stdout.clear();
+                stderr.clear();
+            }
+            Event::Start(e) => {
+                let case = match &mut case {
+                    Some(case) => case,
+                    None => continue,
+                };
+                section = match e.local_name().as_ref() {
+                    b"failure" | b"error" => {
+                        case.status = TestStatus::FAIL;
+                        case.msg = attribute(&e, "message")?;
+                        Some(Section::Details)
+                    }
+                    b"skipped" => {
+                        if case.status != TestStatus::FAIL {
+                            case.status = TestStatus::SKIP;
+                            case.msg = attribute(&e, "message")?;
+                        }
+                        Some(Section::Details)
+                    }
+                    b"system-out" => Some(Section::Stdout),
+                    b"system-err" => Some(Section::Stderr),
+                    _ => None,
+                };
+            }
+            e @ (Event::Text(_) | Event::CData(_)) => {
+                let (case, section) = match (&mut case, &section) {
+                    (Some(case), Some(section)) => (case, section),
+                    _ => continue,
+                };
+                let text = match e {
+                    Event::Text(text) => text.unescape()?,
+                    Event::CData(data) => String::from_utf8(data.into_inner().into_owned())?.into(),
+                    _ => unreachable!(),
+                };
+                match section {
+                    Section::Details => case.details.push_str(&text),
+                    Section::Stdout => stdout.push_str(&text),
+                    Section::Stderr => stderr.push_str(&text),
+                }
+            }
+            Event::End(e) if e.local_name().as_ref() == b"testcase" => {
+                if let Some(mut case) = case.take() {
+                    if case.msg.is_none() && case.status == TestStatus::FAIL {
+                        case.msg = first_line(&case.details);
+                    }
+                    if !stdout.is_empty() || !stderr.is_empty() {
+                        case.details.push_str(&format!(
+                            "\n---- STDOUT ----\n{}\n---- STDERR ----\n{}\n",
+                            stdout, stderr
+                        ));
+                    }
+                    cases.push(case);
+                }
+                section = None;
+            }
+            Event::End(_) => section = None,
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(cases)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let report = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites>
+  <testsuite name="com.example.MathTest" tests="4" failures="1" errors="1" skipped="1">
+    <testcase classname="com.example.MathTest" name="adds" time="0.25"/>
+    <testcase classname="com.example.MathTest" name="divides" time="0.5">
+      <failure message="expected:&lt;1&gt; but was:&lt;0&gt;" type="AssertionError">
+        at MathTest.divides(MathTest.java:12)</failure>
+      <system-out><![CDATA[dividing 1 by 2]]></system-out>
+    </testcase>
+    <testcase classname="com.example.MathTest" name="crashes">
+      <error type="NullPointerException">java.lang.NullPointerException
+        at MathTest.crashes(MathTest.java:20)</error>
+    </testcase>
+    <testcase classname="com.example.MathTest" name="later">
+      <skipped message="not yet"/>
+    </testcase>
+  </testsuite>
+</testsuites>"#;
+        let cases = parse(report).unwrap();
+        assert_eq!(
+            vec![
+                ("com.example.MathTest.adds", TestStatus::PASS),
+                ("com.example.MathTest.divides", TestStatus::FAIL),
+                ("com.example.MathTest.crashes", TestStatus::FAIL),
+                ("com.example.MathTest.later", TestStatus::SKIP),
+            ],
+            cases
+                .iter()
+                .map(|c| (c.name.as_str(), c.status.clone()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Some(Duration::from_millis(250)), cases[0].duration);
+        assert_eq!(Some("expected:<1> but was:<0>"), cases[1].msg.as_deref());
+        assert!(cases[1].details.contains("MathTest.java:12"));
+        assert!(cases[1].details.contains("dividing 1 by 2"));
+        assert_eq!(
+            Some("java.lang.NullPointerException"),
+            cases[2].msg.as_deref()
+        );
+        assert_eq!(Some("not yet"), cases[3].msg.as_deref());
+    }
+
+    #[test]
+    fn test_parse_single_suite() {
+        let report = r#"<testsuite name="tests"><testcase name="test_it"/></testsuite>"#;
+        let cases = parse(report).unwrap();
+        assert_eq!(1, cases.len());
+        assert_eq!("test_it", cases[0].name);
+        assert_eq!(TestStatus::PASS, cases[0].status);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(parse("<testsuite><testcase></testsuite>").is_err());
+    }
+}
@@ -0,0 +1,118 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Per-case results written by test binaries in standard formats.
+//!
+//! The runner asks the test binary to write its results to a declared output, through the
+//! environment variable the format's frameworks read, and reports each case it finds there in
+//! addition to (or in place of) the result of the whole test.
+
+mod gtest;
+mod junit;
+
+use std::time::Duration;
+
+use anyhow::Context;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+use buck2_test_api::data::ArgValue;
+use buck2_test_api::data::ArgValueContent;
+use buck2_test_api::data::DeclaredOutput;
+use buck2_test_api::data::ExecutionResult2;
+use buck2_test_api::data::Output;
+use buck2_test_api::data::TestStatus;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ResultFormat {
+    /// `--gtest_output=json`, written by GoogleTest binaries to `$GTEST_OUTPUT`.
+    GTestJson,
+    /// JUnit XML, written to `$XML_OUTPUT_FILE` by the frameworks following this convention.
+    JUnitXml,
+}
+
+/// The result of a single test case of a test binary.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CaseResult {
+    pub(crate) name: String,
+    pub(crate) status: TestStatus,
+    pub(crate) duration: Option<Duration>,
+    pub(crate) msg: Option<String>,
+    pub(crate) details: String,
+}
+
+impl ResultFormat {
+    /// The format to ask of tests of the `test_type` of their `ExternalRunnerTestInfo`.
+    pub(crate) fn for_test_type(test_type: &str) -> Self {
+        match test_type {
+            "gtest" => ResultFormat::GTestJson,
+            _ => ResultFormat::JUnitXml,
+        }
+    }
+
+    fn output(self) -> DeclaredOutput {
+        let name = match self {
+            ResultFormat::GTestJson => "gtest_results.json",
+            ResultFormat::JUnitXml => "junit_results.xml",
+        };
+        DeclaredOutput {
+            name: ForwardRelativePathBuf::unchecked_new(name.to_owned()),
+        }
+    }
+
+    /// The environment variable pointing the test binary at the output to write results to.
+    pub(crate) fn env(self) -> (String, ArgValue) {
+        let (name, format) = match self {
+            ResultFormat::GTestJson => ("GTEST_OUTPUT", Some("json:{}".to_owned())),
+            ResultFormat::JUnitXml => ("XML_OUTPUT_FILE", None),
+        };
+        let value = ArgValue {
+            content: ArgValueContent::DeclaredOutput(self.output()),
+            format,
+        };
+        (name.to_owned(), value)
+    }
+
+    fn parse(self, contents: &str) -> anyhow::Result<Vec<CaseResult>> {
+        match self {
+            ResultFormat::GTestJson => gtest::parse(contents),
+            ResultFormat::JUnitXml => junit::parse(contents),
+        }
+    }
+
+    /// The cases reported by the test, or none if it did not write any results.
+    pub(crate) async fn read(
+        self,
+        execution_result: &ExecutionResult2,
+    ) -> anyhow::Result<Vec<CaseResult>> {
+        let path = match execution_result.outputs.get(&self.output()) {
+            Some(Output::LocalPath(path)) => path,
+            None => return Ok(Vec::new()),
+        };
+        let contents = match tokio::fs::read_to_string(path).await {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            res => res.with_context(|| format!("Error reading test results at `{}`", path))?,
+        };
+        self.parse(&contents)
+            .with_context(|| format!("Error parsing test results at `{}`", path))
+    }
+}
+
+/// A duration in seconds, as both formats write them.
+fn parse_seconds(seconds: &str) -> Option<Duration> {
+    let seconds: f64 = seconds.trim().parse().ok()?;
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+/// The first line of a failure message, for the summary of a case.
+fn first_line(message: &str) -> Option<String> {
+    message
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(ToOwned::to_owned)
+}
@@ -29,6 +29,8 @@ use parking_lot::Mutex;
 
 use crate::config::Config;
 use crate::config::EnvValue;
+use crate::results::CaseResult;
+use crate::results::ResultFormat;
 
 pub type SpecReceiver = UnboundedReceiver<ExternalRunnerSpec>;
 
@@ -38,6 +40,9 @@ pub type SpecReceiver = UnboundedReceiver<ExternalRunnerSpec>;
 /// if no external test runner is provided. This ensures that `buck2 test` works
 /// out-of-the-box for open-source users.
 ///
+/// Tests pass if they exit successfully. Those which write their results as JUnit XML or
+/// GoogleTest JSON (see `ResultFormat`) also get a result for each of their cases.
+///
 /// **This is intended for open-source use only.**
 pub struct Buck2TestRunner {
     orchestrator_client: TestOrchestratorClient,
@@ -75,20 +80,24 @@ impl Buck2TestRunner {
                     spec.target.cell, spec.target.package, spec.target.target
                 );
                 let target_handle = spec.target.handle.to_owned();
+                let result_format = ResultFormat::for_test_type(&spec.test_type);
 
                 let execution_result = self
-                    .execute_test_from_spec(spec)
+                    .execute_test_from_spec(spec, result_format)
                     .await
                     .expect("Test execution request failed");
 
-                let test_result = get_test_result(name, target_handle, execution_result);
-                let test_status = test_result.status.clone();
+                let cases = result_format.read(&execution_result).await;
+                let test_results = get_test_results(name, target_handle, execution_result, cases);
+                let test_statuses: Vec<_> = test_results.iter().map(|r| r.status.clone()).collect();
 
-                self.report_test_result(test_result)
-                    .await
-                    .expect("Test result reporting failed");
+                for test_result in test_results {
+                    self.report_test_result(test_result)
+                        .await
+                        .expect("Test result reporting failed");
+                }
 
-                test_status
+                test_statuses
             })
             // Use an arbitrarily large buffer -- execution throttling will be handled by the Buck2
             // executor, so no need to hold back on requests here.
@@ -96,8 +105,8 @@ impl Buck2TestRunner {
             // If any individual test failed, consider the entire run to have failed.
             .fold(
                 RunVerdict::Pass,
-                async move |mut run_verdict, test_status| {
-                    if test_status != TestStatus::PASS {
+                async move |mut run_verdict, test_statuses| {
+                    if test_statuses.iter().any(is_failure) {
                         run_verdict = RunVerdict::Fail;
                     }
                     run_verdict
@@ -113,6 +122,7 @@ impl Buck2TestRunner {
     async fn execute_test_from_spec(
         &self,
         spec: ExternalRunnerSpec,
+        result_format: ResultFormat,
     ) -> anyhow::Result<ExecutionResult2> {
         let display_metadata = DisplayMetadata::Testing {
             suite: spec.target.target,
@@ -140,6 +150,11 @@ impl Buck2TestRunner {
             )
         });
 
+        // Tests setting the variable themselves write their results elsewhere.
+        let (result_env_name, result_env_value) = result_format.env();
+        let result_env = (!spec.env.contains_key(&result_env_name))
+            .then_some((result_env_name, result_env_value));
+
         let env = spec
             .env
             .into_iter()
@@ -153,6 +168,7 @@ impl Buck2TestRunner {
                 )
             })
             .chain(config_env)
+            .chain(result_env)
             .collect();
 
         let target_handle = spec.target.handle;
@@ -182,11 +198,15 @@ impl Buck2TestRunner {
     }
 }
 
-fn get_test_result(
+/// The results of a test: one for each of its cases, and one for the whole test unless its cases
+/// already account for its status. A test exiting with an error without a failing case (e.g.
+/// crashing after its cases ran) still gets a failure.
+fn get_test_results(
     name: String,
     target: ConfiguredTargetHandle,
     execution_result: ExecutionResult2,
-) -> TestResult {
+    cases: anyhow::Result<Vec<CaseResult>>,
+) -> Vec<TestResult> {
     let status = match execution_result.status {
         ExecutionStatus::Finished { exitcode } => match exitcode {
             0 => TestStatus::PASS,
@@ -194,17 +214,48 @@ fn get_test_result(
         },
         ExecutionStatus::TimedOut { .. } => TestStatus::TIMEOUT,
     };
-    TestResult {
-        target,
-        name,
-        status,
-        msg: None,
-        duration: Some(execution_result.execution_time),
-        details: format!(
-            "---- STDOUT ----\n{:?}\n---- STDERR ----\n{:?}\n",
-            execution_result.stdout, execution_result.stderr
-        ),
+
+    let (cases, msg) = match cases {
+        Ok(cases) => (cases, None),
+        Err(e) => (Vec::new(), Some(format!("{:#}", e))),
+    };
+    let mut results: Vec<TestResult> = cases
+        .into_iter()
+        .map(|case| TestResult {
+            target,
+            name: format!("{} - {}", name, case.name),
+            status: case.status,
+            msg: case.msg,
+            duration: case.duration,
+            details: case.details,
+            artifacts: Vec::new(),
+        })
+        .collect();
+
+    let any_case_failed = results.iter().any(|r| r.status == TestStatus::FAIL);
+    if results.is_empty() || msg.is_some() || (status != TestStatus::PASS && !any_case_failed) {
+        results.push(TestResult {
+            target,
+            name,
+            status,
+            msg,
+            duration: Some(execution_result.execution_time),
+            details: format!(
+                "---- STDOUT ----\n{:?}\n---- STDERR ----\n{:?}\n",
+                execution_result.stdout, execution_result.stderr
+            ),
+            artifacts: Vec::new(),
+        });
     }
+    results
+}
+
+/// Whether a result fails the run. Skipped cases do not.
+fn is_failure(status: &TestStatus) -> bool {
+    !matches!(
+        status,
+        TestStatus::PASS | TestStatus::SKIP | TestStatus::OMITTED
+    )
 }
 
 #[derive(Debug)]
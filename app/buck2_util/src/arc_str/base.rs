@@ -127,6 +127,13 @@ impl<P: ArcStrLenStrategy> ArcStrBase<P> {
         self.len() == 0
     }
 
+    /// Whether this is the only reference to the string. Always false for the empty string,
+    /// which is statically allocated.
+    #[inline]
+    pub(crate) fn is_unique(&self) -> bool {
+        !self.is_empty() && self.inner().refcount.load(atomic::Ordering::Acquire) == 1
+    }
+
     #[inline]
     pub(crate) fn as_str(&self) -> &str {
         unsafe {
@@ -64,6 +64,12 @@ impl ArcStr {
     pub fn as_str(&self) -> &str {
         self.base.as_str()
     }
+
+    /// Whether this is the only reference to the string.
+    #[inline]
+    pub fn is_unique(&self) -> bool {
+        self.base.is_unique()
+    }
 }
 
 impl Deref for ArcStr {
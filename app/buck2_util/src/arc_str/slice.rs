@@ -32,6 +32,15 @@ impl<T> ArcSlice<T> {
     pub fn new<const N: usize>(array: [T; N]) -> ArcSlice<T> {
         ArcSlice::from_iter(array)
     }
+
+    /// Whether this is the only reference to the slice. Always false for the empty slice.
+    #[inline]
+    pub fn is_unique(&self) -> bool {
+        match &self.slice {
+            Some(slice) => slice.is_unique(),
+            None => false,
+        }
+    }
 }
 
 impl<T> Clone for ArcSlice<T> {
@@ -0,0 +1,128 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Benchmark a key depending on many other keys, with its deps stored plain or compressed:
+//! computing it from scratch, and recomputing it after one of its deps changed, which walks its
+//! recorded deps. The memory used by DICE in each mode is printed before the timings.
+
+use std::sync::Arc;
+
+use allocative::Allocative;
+use async_trait::async_trait;
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::Criterion;
+use derive_more::Display;
+use dice::DetectCycles;
+use dice::Dice;
+use dice::DiceComputations;
+use dice::DiceTransactionUpdater;
+use dice::InjectedKey;
+use dice::Key;
+use dupe::Dupe;
+use more_futures::cancellation::CancellationContext;
+
+const FANOUT: u32 = 20_000;
+
+#[derive(Clone, Copy, Dupe, Debug, Display, Eq, Hash, PartialEq, Allocative)]
+#[display(fmt = "{:?}", self)]
+struct Leaf(u32);
+
+#[async_trait]
+impl InjectedKey for Leaf {
+    type Value = u64;
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        x == y
+    }
+}
+
+#[derive(Clone, Copy, Dupe, Debug, Display, Eq, Hash, PartialEq, Allocative)]
+#[display(fmt = "{:?}", self)]
+struct Fanout;
+
+#[async_trait]
+impl Key for Fanout {
+    type Value = u64;
+
+    async fn compute(
+        &self,
+        ctx: &DiceComputations,
+        _cancellations: &CancellationContext,
+    ) -> Self::Value {
+        let leaves: Vec<_> = (0..FANOUT).map(Leaf).collect();
+        futures::future::join_all(leaves.iter().map(|leaf| ctx.compute(leaf)))
+            .await
+            .into_iter()
+            .map(|v| v.unwrap())
+            .sum()
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        x == y
+    }
+}
+
+fn inject_leaves(updater: &mut DiceTransactionUpdater, value: u64) {
+    updater
+        .changed_to((0..FANOUT).map(|i| (Leaf(i), value)))
+        .unwrap();
+}
+
+fn dice(compress_deps: bool) -> Arc<Dice> {
+    let mut builder = Dice::modern();
+    if compress_deps {
+        builder.compress_deps_at_least(1_000);
+    }
+    builder.build(DetectCycles::Disabled)
+}
+
+fn bench(c: &mut Criterion) {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+
+    for (name, compress_deps) in [("plain", false), ("compressed", true)] {
+        let dice = dice(compress_deps);
+        rt.block_on(async {
+            let mut updater = dice.updater();
+            inject_leaves(&mut updater, 1);
+            updater.commit().await.compute(&Fanout).await.unwrap();
+        });
+        println!(
+            "{}: {} bytes allocated by DICE",
+            name,
+            allocative::size_of_unique_allocated_data(&*dice)
+        );
+
+        c.bench_function(&format!("compute_fanout_{}", name), |b| {
+            b.iter(|| {
+                let dice = self::dice(compress_deps);
+                rt.block_on(async {
+                    let mut updater = dice.updater();
+                    inject_leaves(&mut updater, 1);
+                    updater.commit().await.compute(&Fanout).await.unwrap()
+                })
+            })
+        });
+
+        let mut value = 1;
+        c.bench_function(&format!("recompute_fanout_{}", name), |b| {
+            b.iter(|| {
+                value += 1;
+                rt.block_on(async {
+                    let mut updater = dice.updater();
+                    updater.changed_to([(Leaf(0), value)]).unwrap();
+                    updater.commit().await.compute(&Fanout).await.unwrap()
+                })
+            })
+        });
+    }
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
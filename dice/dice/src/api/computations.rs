@@ -132,6 +132,7 @@ mod tests {
     use crate::api::cycles::DetectCycles;
     use crate::api::error::DiceErrorImpl;
     use crate::api::storage_type::StorageType;
+    use crate::api::task_spans::DiceTaskSpans;
     use crate::api::user_data::UserComputationData;
     use crate::legacy::ctx::ComputationData;
     use crate::legacy::cycles::RequestedKey;
@@ -167,7 +168,11 @@ mod tests {
 
     #[test]
     fn cycle_detection_when_no_cycles() -> anyhow::Result<()> {
-        let ctx = ComputationData::new(UserComputationData::new(), DetectCycles::Enabled);
+        let ctx = ComputationData::new(
+            UserComputationData::new(),
+            DetectCycles::Enabled,
+            DiceTaskSpans::Disabled,
+        );
         let ctx = ctx.subrequest::<K>(&K(1))?;
         let ctx = ctx.subrequest::<K>(&K(2))?;
         let ctx = ctx.subrequest::<K>(&K(3))?;
@@ -178,7 +183,11 @@ mod tests {
 
     #[test]
     fn cycle_detection_when_cycles() -> anyhow::Result<()> {
-        let ctx = ComputationData::new(UserComputationData::new(), DetectCycles::Enabled);
+        let ctx = ComputationData::new(
+            UserComputationData::new(),
+            DetectCycles::Enabled,
+            DiceTaskSpans::Disabled,
+        );
         let ctx = ctx.subrequest::<K>(&K(1))?;
         let ctx = ctx.subrequest::<K>(&K(2))?;
         let ctx = ctx.subrequest::<K>(&K(3))?;
@@ -194,6 +194,7 @@ use futures::future::Future;
 use serde::Serializer;
 
 use crate::api::cycles::DetectCycles;
+use crate::api::task_spans::DiceTaskSpans;
 use crate::api::transaction::DiceTransactionUpdater;
 use crate::api::user_data::UserComputationData;
 use crate::api::which::WhichSpawner;
@@ -283,6 +284,19 @@ impl DiceDataBuilder {
         self.0.set(val);
     }
 
+    /// Store the deps of keys with at least `num_deps` dependencies in a compressed form. This
+    /// trades some CPU when the deps are revisited for much less memory on high-fanout keys.
+    ///
+    /// Only supported by modern dice, this is a no-op for legacy dice.
+    pub fn compress_deps_at_least(&mut self, num_deps: usize) {
+        self.0.compress_deps_at_least(num_deps);
+    }
+
+    /// Set how much tracing information the tasks computing keys record.
+    pub fn task_spans(&mut self, task_spans: DiceTaskSpans) {
+        self.0.task_spans(task_spans);
+    }
+
     pub fn build(self, detect_cycles: DetectCycles) -> Arc<Dice> {
         self.build_with_which_spawner(detect_cycles, WhichSpawner::ExplicitCancel)
     }
@@ -62,6 +62,9 @@ pub trait Key: Allocative + Debug + Display + Clone + Eq + Hash + Send + Sync +
         true
     }
 
+    /// How many values of this key DICE retains. Keys whose values must never be reused at a
+    /// later version (e.g. because they read the wall clock) should return
+    /// `StorageType::Transient`.
     fn storage_type() -> StorageType {
         StorageType::LastN(1)
     }
@@ -21,6 +21,7 @@ pub mod key;
 pub mod opaque;
 pub mod projection;
 pub mod storage_type;
+pub mod task_spans;
 pub mod transaction;
 pub mod user_data;
 pub mod which;
@@ -18,4 +18,28 @@ use gazebo::variants::UnpackVariants;
 #[derive(UnpackVariants, Debug, Clone, Copy, Dupe, Allocative)]
 pub enum StorageType {
     LastN(usize),
+    /// The value is never cached across versions, e.g. because it depends on the wall clock.
+    /// It is computed at most once per version and shared by all ongoing computations at that
+    /// version, exactly like a value for which `Key::validity` returns `false`. Everything that
+    /// depends on it is transient as well, and so is recomputed at the next version.
+    ///
+    /// Values of such keys can't be set with `changed_to`, since injected values must always be
+    /// valid.
+    Transient,
+}
+
+impl StorageType {
+    /// Whether every value stored with this storage type is transient.
+    pub fn is_transient(self) -> bool {
+        matches!(self, StorageType::Transient)
+    }
+
+    /// The number of valid entries to retain per key. Transient values are never retained
+    /// past their version, so this only matters for valid values.
+    pub(crate) fn num_to_keep(self) -> usize {
+        match self {
+            StorageType::LastN(n) => n,
+            StorageType::Transient => 1,
+        }
+    }
 }
@@ -0,0 +1,48 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Tracing spans for the tasks computing keys in DICE
+
+use std::str::FromStr;
+
+use allocative::Allocative;
+use dupe::Dupe;
+use gazebo::variants::VariantName;
+use thiserror::Error;
+
+/// How much tracing information each DICE task records. Every poll of a task happens inside its
+/// span, so a subscriber (e.g. `tokio-console`) sees how long each task was busy and idle.
+#[derive(Clone, Dupe, Copy, Debug, VariantName, Allocative, PartialEq, Eq)]
+pub enum DiceTaskSpans {
+    /// Tasks only have a nameless `debug` span, which has no overhead unless debug logging
+    /// is enabled.
+    Disabled,
+    /// Tasks have an `info` span with the type of the key and the version computed.
+    Enabled,
+    /// Like `Enabled`, and the span also records the key index and the state transitions of
+    /// the task (checking deps, computing, finished).
+    Verbose,
+}
+
+#[derive(Error, Debug)]
+#[error("Invalid type of DiceTaskSpans: `{0}`")]
+pub struct InvalidType(String);
+
+impl FromStr for DiceTaskSpans {
+    type Err = InvalidType;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "DISABLED" => Ok(DiceTaskSpans::Disabled),
+            "ENABLED" => Ok(DiceTaskSpans::Enabled),
+            "VERBOSE" => Ok(DiceTaskSpans::Verbose),
+            _ => Err(InvalidType(s.to_owned())),
+        }
+    }
+}
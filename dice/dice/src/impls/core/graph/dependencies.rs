@@ -24,7 +24,7 @@ pub(crate) struct VersionedDependencies {
     /// once the deps at a particular version is written, it is final and never modified
     /// We only store the dependencies relevant to the most recent result
     recorded_at: VersionNumber,
-    deps: Arc<Vec<DiceKey>>,
+    deps: StoredDeps,
 }
 
 impl Dupe for VersionedDependencies {
@@ -32,27 +32,140 @@ impl Dupe for VersionedDependencies {
 }
 
 impl VersionedDependencies {
-    pub(crate) fn new(recorded_at: VersionNumber, deps: Arc<Vec<DiceKey>>) -> Self {
-        Self { recorded_at, deps }
+    pub(crate) fn new(
+        recorded_at: VersionNumber,
+        deps: Arc<Vec<DiceKey>>,
+        compression: DepsCompression,
+    ) -> Self {
+        Self {
+            recorded_at,
+            deps: StoredDeps::new(deps, compression),
+        }
     }
 
     pub(crate) fn deps(&self) -> Arc<Vec<DiceKey>> {
-        self.deps.dupe()
+        match &self.deps {
+            StoredDeps::Plain(deps) => deps.dupe(),
+            StoredDeps::Compressed(deps) => Arc::new(deps.iter().collect()),
+        }
     }
 
-    pub(crate) fn replace_deps(&mut self, v: VersionNumber, deps: Arc<Vec<DiceKey>>) {
+    pub(crate) fn replace_deps(
+        &mut self,
+        v: VersionNumber,
+        deps: Arc<Vec<DiceKey>>,
+        compression: DepsCompression,
+    ) {
         if self.recorded_at < v {
             // we only ever write the newest version of the dependencies of this node for simplicity
             // That way, if we are ever dirtied, we just check if the latest version of the deps
             // have changed at the dirtied version which only requires spawning one set of deps.
             // It might cause us to falsely fail to reuse some nodes, but this is less memory
             // and less work per node when in incremental cases.
-            self.deps = deps;
+            self.deps = StoredDeps::new(deps, compression);
             self.recorded_at = v;
         }
     }
 }
 
+/// When to store the deps of a node as `CompressedDeps` rather than a plain `Vec`.
+#[derive(Allocative, Clone, Copy, Dupe, Debug, Default, PartialEq, Eq)]
+pub(crate) enum DepsCompression {
+    #[default]
+    Disabled,
+    /// Compress the deps of nodes with at least this many deps.
+    AtLeast(usize),
+}
+
+impl DepsCompression {
+    fn should_compress(self, num_deps: usize) -> bool {
+        match self {
+            DepsCompression::Disabled => false,
+            DepsCompression::AtLeast(threshold) => num_deps >= threshold,
+        }
+    }
+}
+
+#[derive(Allocative, Clone)]
+enum StoredDeps {
+    Plain(Arc<Vec<DiceKey>>),
+    Compressed(Arc<CompressedDeps>),
+}
+
+impl StoredDeps {
+    fn new(deps: Arc<Vec<DiceKey>>, compression: DepsCompression) -> Self {
+        if compression.should_compress(deps.len()) {
+            StoredDeps::Compressed(Arc::new(CompressedDeps::new(&deps)))
+        } else {
+            StoredDeps::Plain(deps)
+        }
+    }
+}
+
+/// A compact encoding of a set of deps for nodes with a large fanout.
+///
+/// The key indices are sorted and stored as LEB128 encoded deltas from the previous index. Since
+/// keys are interned roughly in the order they are first requested, the deps of a single node tend
+/// to be clustered and most deltas fit in one or two bytes, compared to four bytes per `DiceKey`
+/// plus the `Vec` slack of the plain representation.
+///
+/// The order of deps is not preserved.
+#[derive(Allocative, Debug, PartialEq, Eq)]
+pub(crate) struct CompressedDeps {
+    len: usize,
+    bytes: Box<[u8]>,
+}
+
+impl CompressedDeps {
+    pub(crate) fn new(deps: &[DiceKey]) -> Self {
+        let mut indices: Vec<u32> = deps.iter().map(|k| k.index).collect();
+        indices.sort_unstable();
+
+        let mut bytes = Vec::with_capacity(indices.len() * 2);
+        let mut prev = 0;
+        for index in &indices {
+            let mut delta = index - prev;
+            prev = *index;
+            loop {
+                let byte = (delta & 0x7f) as u8;
+                delta >>= 7;
+                if delta == 0 {
+                    bytes.push(byte);
+                    break;
+                }
+                bytes.push(byte | 0x80);
+            }
+        }
+
+        Self {
+            len: indices.len(),
+            bytes: bytes.into_boxed_slice(),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = DiceKey> + '_ {
+        let mut bytes = self.bytes.iter();
+        let mut prev = 0;
+        (0..self.len).map(move |_| {
+            let mut delta = 0;
+            let mut shift = 0;
+            for byte in bytes.by_ref() {
+                delta |= ((byte & 0x7f) as u32) << shift;
+                shift += 7;
+                if byte & 0x80 == 0 {
+                    break;
+                }
+            }
+            prev += delta;
+            DiceKey { index: prev }
+        })
+    }
+}
+
 // the set of reverse dependencies of a node
 #[derive(Allocative, Clone)] // TODO(bobyf) remove need to clone
 pub(crate) struct VersionedRevDependencies {
@@ -83,3 +196,66 @@ impl VersionedRevDependencies {
         &self.rdeps
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use allocative::size_of_unique_allocated_data;
+    use dupe::Dupe;
+
+    use crate::arc::Arc;
+    use crate::impls::core::graph::dependencies::CompressedDeps;
+    use crate::impls::core::graph::dependencies::DepsCompression;
+    use crate::impls::core::graph::dependencies::VersionedDependencies;
+    use crate::impls::key::DiceKey;
+    use crate::versions::VersionNumber;
+
+    #[test]
+    fn compressed_deps_roundtrip() {
+        let deps = [5, 1, 130, 0, 1 << 20, 129, u32::MAX]
+            .map(|index| DiceKey { index })
+            .to_vec();
+        let compressed = CompressedDeps::new(&deps);
+
+        assert_eq!(compressed.len(), deps.len());
+        let mut expected = deps;
+        expected.sort_by_key(|k| k.index);
+        assert_eq!(compressed.iter().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn compression_threshold() {
+        let deps = Arc::new((0..10).map(|index| DiceKey { index }).collect::<Vec<_>>());
+
+        let plain = VersionedDependencies::new(
+            VersionNumber::new(0),
+            deps.dupe(),
+            DepsCompression::AtLeast(11),
+        );
+        assert!(Arc::ptr_eq(&plain.deps(), &deps));
+
+        let compressed = VersionedDependencies::new(
+            VersionNumber::new(0),
+            deps.dupe(),
+            DepsCompression::AtLeast(10),
+        );
+        assert!(!Arc::ptr_eq(&compressed.deps(), &deps));
+        assert_eq!(compressed.deps(), deps);
+    }
+
+    #[test]
+    fn compressed_deps_memory() {
+        // A node depending on many keys, interned close together.
+        let deps = (0..20_000)
+            .map(|i| DiceKey {
+                index: 1_000 + i * 3,
+            })
+            .collect::<Vec<_>>();
+
+        let plain = size_of_unique_allocated_data(&deps);
+        let compressed = size_of_unique_allocated_data(&CompressedDeps::new(&deps));
+
+        assert_eq!(plain, 80_000);
+        // every delta fits in a single byte.
+        assert_eq!(compressed, 20_001);
+    }
+}
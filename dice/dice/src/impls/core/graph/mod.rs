@@ -8,7 +8,7 @@
  */
 
 //! The versioned dice graph of dependencies
-mod dependencies;
+pub(crate) mod dependencies;
 pub(crate) mod history;
 #[allow(unused)]
 pub(crate) mod introspection;
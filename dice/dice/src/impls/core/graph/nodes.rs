@@ -23,6 +23,7 @@ use dupe::Dupe;
 use gazebo::variants::UnpackVariants;
 
 use crate::arc::Arc;
+use crate::impls::core::graph::dependencies::DepsCompression;
 use crate::impls::core::graph::dependencies::VersionedDependencies;
 use crate::impls::core::graph::dependencies::VersionedRevDependencies;
 use crate::impls::core::graph::history::CellHistory;
@@ -111,6 +112,7 @@ impl OccupiedGraphNode {
         latest_dep_verified: Option<VersionNumber>,
         first_dep_dirtied: Option<VersionNumber>,
         deps: Arc<Vec<DiceKey>>,
+        deps_compression: DepsCompression,
     ) -> VersionNumber {
         // Marking a node as unchanged ALWAYS requires the dependencies for which we used to deem
         // that the node is unchanged.
@@ -125,7 +127,9 @@ impl OccupiedGraphNode {
             self.metadata
                 .hist
                 .mark_verified_modern(v, latest_dep_verified, first_dep_dirtied);
-        self.metadata.deps.replace_deps(changed_since, deps);
+        self.metadata
+            .deps
+            .replace_deps(changed_since, deps, deps_compression);
 
         changed_since
     }
@@ -164,6 +168,7 @@ mod tests {
     use crate::api::computations::DiceComputations;
     use crate::api::key::Key;
     use crate::arc::Arc;
+    use crate::impls::core::graph::dependencies::DepsCompression;
     use crate::impls::core::graph::dependencies::VersionedDependencies;
     use crate::impls::core::graph::history::testing::CellHistoryExt;
     use crate::impls::core::graph::history::testing::HistoryExt;
@@ -200,7 +205,11 @@ mod tests {
         let mut entry = OccupiedGraphNode::new(
             DiceKey { index: 1335 },
             DiceValidValue::testing_new(DiceKeyValue::<K>::new(1)),
-            VersionedDependencies::new(VersionNumber::new(0), deps0.dupe()),
+            VersionedDependencies::new(
+                VersionNumber::new(0),
+                deps0.dupe(),
+                DepsCompression::Disabled,
+            ),
             CellHistory::testing_new(
                 &[VersionNumber::new(0)],
                 &[VersionNumber::new(1), VersionNumber::new(2)],
@@ -214,7 +223,13 @@ mod tests {
             .assert_verified();
         assert_eq!(entry.metadata().deps.deps(), deps0);
 
-        entry.mark_unchanged(VersionNumber::new(1), None, None, Arc::new(vec![]));
+        entry.mark_unchanged(
+            VersionNumber::new(1),
+            None,
+            None,
+            Arc::new(vec![]),
+            DepsCompression::Disabled,
+        );
         entry
             .metadata()
             .hist
@@ -233,6 +248,7 @@ mod tests {
             Some(VersionNumber::new(1)),
             None,
             deps1.dupe(),
+            DepsCompression::Disabled,
         );
 
         entry
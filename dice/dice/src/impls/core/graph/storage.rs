@@ -27,6 +27,7 @@ use sorted_vector_map::SortedVectorMap;
 
 use crate::api::storage_type::StorageType;
 use crate::arc::Arc;
+use crate::impls::core::graph::dependencies::DepsCompression;
 use crate::impls::core::graph::dependencies::VersionedDependencies;
 use crate::impls::core::graph::history::CellHistory;
 use crate::impls::core::graph::history::HistoryState;
@@ -56,12 +57,22 @@ pub(crate) struct VersionedGraph {
     /// VacantGraphEntries can only be present when no other entries are present for the key at
     /// any version.
     pub(crate) last_n: HashMap<DiceKey, SortedVectorMap<VersionNumber, VersionedGraphNode>>,
+    /// How the deps of newly computed nodes are stored.
+    deps_compression: DepsCompression,
 }
 
 impl VersionedGraph {
     pub(crate) fn new() -> Self {
         Self {
             last_n: Default::default(),
+            deps_compression: DepsCompression::Disabled,
+        }
+    }
+
+    pub(crate) fn with_deps_compression(self, deps_compression: DepsCompression) -> Self {
+        Self {
+            deps_compression,
+            ..self
         }
     }
 
@@ -172,7 +183,7 @@ impl VersionedGraph {
         deps: Arc<Vec<DiceKey>>,
         storage_type: StorageType,
     ) -> (DiceComputedValue, bool) {
-        let StorageType::LastN(num_to_keep) = storage_type;
+        let num_to_keep = storage_type.num_to_keep();
         // persistent keys, if any changes, are committed at the moment when the version
         // is increased. therefore, it must be the case that the current update for the
         // persistent key is the largest/newest version. it's also the case that they are
@@ -286,8 +297,12 @@ impl VersionedGraph {
         let since = latest_dep_verified.unwrap_or(v);
         let mut hist = CellHistory::verified(since);
         hist.propagate_from_deps_version(since, first_dep_dirtied);
-        let entry =
-            OccupiedGraphNode::new(key, value, VersionedDependencies::new(since, deps), hist);
+        let entry = OccupiedGraphNode::new(
+            key,
+            value,
+            VersionedDependencies::new(since, deps, self.deps_compression),
+            hist,
+        );
 
         let res = entry.computed_val();
 
@@ -310,11 +325,17 @@ impl VersionedGraph {
         deps: Arc<Vec<DiceKey>>,
         num_to_keep: usize,
     ) -> (DiceComputedValue, bool) {
+        let deps_compression = self.deps_compression;
         let versioned_map = self.last_n.get_mut(&key.k).unwrap();
         let (ret, map_fixup) = match versioned_map.get_mut(&key_of_e).unwrap() {
             VersionedGraphNode::Occupied(entry) if value.equality(entry.val()) => {
-                let since =
-                    entry.mark_unchanged(key.v, latest_dep_verified, first_dep_dirtied, deps);
+                let since = entry.mark_unchanged(
+                    key.v,
+                    latest_dep_verified,
+                    first_dep_dirtied,
+                    deps,
+                    deps_compression,
+                );
 
                 let ret = entry.computed_val();
 
@@ -330,7 +351,7 @@ impl VersionedGraph {
                 let new = OccupiedGraphNode::new(
                     key.k,
                     value,
-                    VersionedDependencies::new(since, deps),
+                    VersionedDependencies::new(since, deps, deps_compression),
                     hist,
                 );
 
@@ -411,7 +432,8 @@ impl VersionedGraph {
                         return true;
                     }
                 }
-                InvalidateKind::Update(value, StorageType::LastN(num_to_keep)) => {
+                InvalidateKind::Update(value, storage_type) => {
+                    let num_to_keep = storage_type.num_to_keep();
                     let rdeps = {
                         let entry = self.last_n.get(&key.k).and_then(|versioned_map| {
                             versioned_map
@@ -450,7 +472,11 @@ impl VersionedGraph {
                         let new = OccupiedGraphNode::new(
                             key.k,
                             value,
-                            VersionedDependencies::new(since, Arc::new(vec![])),
+                            VersionedDependencies::new(
+                                since,
+                                Arc::new(vec![]),
+                                DepsCompression::Disabled,
+                            ),
                             hist,
                         );
 
@@ -465,7 +491,11 @@ impl VersionedGraph {
                         let entry = VersionedGraphNode::Occupied(OccupiedGraphNode::new(
                             key.k,
                             value,
-                            VersionedDependencies::new(key.v, Arc::new(vec![])),
+                            VersionedDependencies::new(
+                                key.v,
+                                Arc::new(vec![]),
+                                DepsCompression::Disabled,
+                            ),
                             CellHistory::verified(key.v),
                         ));
 
@@ -10,6 +10,7 @@
 use crate::api::storage_type::StorageType;
 use crate::arc::Arc;
 use crate::impls::cache::SharedCache;
+use crate::impls::core::graph::dependencies::DepsCompression;
 use crate::impls::core::graph::storage::InvalidateKind;
 use crate::impls::core::graph::storage::VersionedGraph;
 use crate::impls::core::graph::types::VersionedGraphKey;
@@ -32,10 +33,10 @@ pub(super) struct CoreState {
 }
 
 impl CoreState {
-    pub(super) fn new() -> Self {
+    pub(super) fn new(deps_compression: DepsCompression) -> Self {
         Self {
             version_tracker: VersionTracker::new(),
-            graph: VersionedGraph::new(),
+            graph: VersionedGraph::new().with_deps_compression(deps_compression),
         }
     }
 
@@ -122,6 +123,7 @@ impl CoreState {
 
 #[cfg(test)]
 mod tests {
+    use crate::impls::core::graph::dependencies::DepsCompression;
     use crate::impls::core::internals::CoreState;
     use crate::impls::key::DiceKey;
     use crate::impls::transaction::ChangeType;
@@ -129,7 +131,7 @@ mod tests {
 
     #[test]
     fn update_state_gets_next_version() {
-        let mut core = CoreState::new();
+        let mut core = CoreState::new(DepsCompression::Disabled);
 
         assert_eq!(
             core.update_state([(DiceKey { index: 0 }, ChangeType::Invalidate)]),
@@ -144,7 +146,7 @@ mod tests {
 
     #[test]
     fn state_ctx_at_version() {
-        let mut core = CoreState::new();
+        let mut core = CoreState::new(DepsCompression::Disabled);
         let v = VersionNumber::new(0);
 
         let ctx = core.ctx_at_version(v);
@@ -15,6 +15,7 @@ use tokio::sync::oneshot::Sender;
 
 use crate::api::storage_type::StorageType;
 use crate::arc::Arc;
+use crate::impls::core::graph::dependencies::DepsCompression;
 use crate::impls::core::graph::types::VersionedGraphKey;
 use crate::impls::core::graph::types::VersionedGraphResult;
 use crate::impls::core::processor::StateProcessor;
@@ -101,6 +102,6 @@ impl CoreStateHandle {
 impl Dupe for CoreStateHandle {}
 
 /// Start processing state
-pub(crate) fn init_state() -> CoreStateHandle {
-    StateProcessor::spawn()
+pub(crate) fn init_state(deps_compression: DepsCompression) -> CoreStateHandle {
+    StateProcessor::spawn(deps_compression)
 }
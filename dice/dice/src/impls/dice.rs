@@ -16,7 +16,9 @@ use dupe::Dupe;
 
 use crate::api::cycles::DetectCycles;
 use crate::api::data::DiceData;
+use crate::api::task_spans::DiceTaskSpans;
 use crate::api::user_data::UserComputationData;
+use crate::impls::core::graph::dependencies::DepsCompression;
 use crate::impls::core::state::init_state;
 use crate::impls::core::state::CoreStateHandle;
 use crate::impls::core::state::StateRequest;
@@ -30,6 +32,7 @@ pub(crate) struct DiceModern {
     pub(crate) key_index: DiceKeyIndex,
     pub(crate) state_handle: CoreStateHandle,
     pub(crate) global_data: DiceData,
+    pub(crate) task_spans: DiceTaskSpans,
 }
 
 impl Debug for DiceModern {
@@ -38,30 +41,59 @@ impl Debug for DiceModern {
     }
 }
 
-pub(crate) struct DiceModernDataBuilder(DiceData);
+pub(crate) struct DiceModernDataBuilder {
+    data: DiceData,
+    deps_compression: DepsCompression,
+    task_spans: DiceTaskSpans,
+}
 
 impl DiceModernDataBuilder {
     pub(crate) fn new() -> Self {
-        Self(DiceData::new())
+        Self {
+            data: DiceData::new(),
+            deps_compression: DepsCompression::Disabled,
+            task_spans: DiceTaskSpans::Disabled,
+        }
     }
 
     pub fn set<K: Send + Sync + 'static>(&mut self, val: K) {
-        self.0.set(val);
+        self.data.set(val);
+    }
+
+    pub fn compress_deps_at_least(&mut self, num_deps: usize) {
+        self.deps_compression = DepsCompression::AtLeast(num_deps);
+    }
+
+    pub fn task_spans(&mut self, task_spans: DiceTaskSpans) {
+        self.task_spans = task_spans;
     }
 
     pub fn build(self, _detect_cycles: DetectCycles) -> Arc<DiceModern> {
-        DiceModern::new(self.0)
+        DiceModern::new_with_config(self.data, self.deps_compression, self.task_spans)
     }
 }
 
 impl DiceModern {
     pub(crate) fn new(global_data: DiceData) -> Arc<Self> {
-        let state_handle = init_state();
+        Self::new_with_config(
+            global_data,
+            DepsCompression::Disabled,
+            DiceTaskSpans::Disabled,
+        )
+    }
+
+    fn new_with_config(
+        global_data: DiceData,
+        deps_compression: DepsCompression,
+        task_spans: DiceTaskSpans,
+    ) -> Arc<Self> {
+        let state_handle = init_state(deps_compression);
 
         Arc::new(DiceModern {
             key_index: Default::default(),
             state_handle,
             global_data,
+            task_spans,
         })
     }
 
@@ -25,9 +25,11 @@ use futures::FutureExt;
 use futures::StreamExt;
 use more_futures::cancellation::future::TerminationStatus;
 use tokio::sync::oneshot;
+use tracing::Span;
 
 use crate::api::error::DiceError;
 use crate::api::error::DiceResult;
+use crate::api::task_spans::DiceTaskSpans;
 use crate::arc::Arc;
 use crate::impls::core::graph::history::CellHistory;
 use crate::impls::core::graph::types::VersionedGraphKey;
@@ -84,37 +86,70 @@ impl IncrementalEngine {
         previously_cancelled_task: Option<PreviouslyCancelledTask>,
     ) -> DiceTask {
         let eval_dupe = eval.dupe();
-        spawn_dice_task(&*eval.user_data.spawner, &eval.user_data, move |handle| {
-            async move {
-                if let Some(previous) = previously_cancelled_task {
-                    match previous.termination.await {
-                        TerminationStatus::Finished => {
-                            // old task actually finished, so just use that result
-                            handle.finished(
-                                previous
-                                    .previous
-                                    .get_finished_value()
-                                    .expect("A finished task must have been completed"),
-                            );
-
-                            return Box::new(()) as Box<dyn Any + Send + 'static>;
-                        }
-                        _ => {
-                            // continue re-evaluating
+        let span = Self::task_span(k, &eval);
+        spawn_dice_task(
+            &*eval.user_data.spawner,
+            &eval.user_data,
+            span,
+            move |handle| {
+                async move {
+                    if let Some(previous) = previously_cancelled_task {
+                        match previous.termination.await {
+                            TerminationStatus::Finished => {
+                                // old task actually finished, so just use that result
+                                handle.finished(
+                                    previous
+                                        .previous
+                                        .get_finished_value()
+                                        .expect("A finished task must have been completed"),
+                                );
+
+                                return Box::new(()) as Box<dyn Any + Send + 'static>;
+                            }
+                            _ => {
+                                // continue re-evaluating
+                            }
                         }
                     }
-                }
 
-                let engine = IncrementalEngine::new(eval_dupe.dice.state_handle.dupe());
+                    let engine = IncrementalEngine::new(eval_dupe.dice.state_handle.dupe());
 
-                engine
-                    .eval_entry_versioned(k, eval_dupe, cycles, events_dispatcher, handle)
-                    .await;
+                    engine
+                        .eval_entry_versioned(k, eval_dupe, cycles, events_dispatcher, handle)
+                        .await;
 
-                Box::new(()) as Box<dyn Any + Send + 'static>
-            }
-            .boxed()
-        })
+                    Box::new(()) as Box<dyn Any + Send + 'static>
+                }
+                .boxed()
+            },
+        )
+    }
+
+    /// The span in which the task computing `k` is polled.
+    fn task_span(k: DiceKey, eval: &AsyncEvaluator) -> Span {
+        match eval.dice.task_spans {
+            DiceTaskSpans::Disabled => debug_span!(parent: None, "spawned_dice_task"),
+            DiceTaskSpans::Enabled => info_span!(
+                parent: None,
+                "dice_task",
+                key_type = eval.dice.key_index.get(k).key_type_name(),
+                version = %eval.per_live_version_ctx.get_version(),
+            ),
+            DiceTaskSpans::Verbose => info_span!(
+                parent: None,
+                "dice_task",
+                key_type = eval.dice.key_index.get(k).key_type_name(),
+                key = k.index,
+                version = %eval.per_live_version_ctx.get_version(),
+            ),
+        }
+    }
+
+    /// Record a state transition of the task computing a key, in its span.
+    fn trace_task_state(eval: &AsyncEvaluator, state: &'static str) {
+        if eval.dice.task_spans == DiceTaskSpans::Verbose {
+            info!(state, "dice task state");
+        }
     }
 
     pub(crate) fn project_for_key(
@@ -191,6 +226,7 @@ impl IncrementalEngine {
         match state_result {
             VersionedGraphResult::Match(entry) => {
                 debug!( k = ?k ,msg = "found existing entry with matching version in cache. reusing result.",);
+                Self::trace_task_state(&eval, "reused");
                 task_handle.finished(Ok(entry))
             }
             VersionedGraphResult::Compute => {
@@ -210,6 +246,7 @@ impl IncrementalEngine {
                     eval.user_data.cycle_detector.as_deref(),
                 );
                 task_handle.checking_deps();
+                Self::trace_task_state(&eval, "checking_deps");
 
                 let deps_changed = {
                     events_dispatcher.check_deps_started(k);
@@ -240,6 +277,7 @@ impl IncrementalEngine {
                         );
 
                         // report reuse
+                        Self::trace_task_state(&eval, "unchanged_deps");
                         let (tx, rx) = tokio::sync::oneshot::channel();
                         self.state.request(StateRequest::UpdateComputed {
                             key: VersionedGraphKey::new(v, k),
@@ -270,6 +308,7 @@ impl IncrementalEngine {
         task_handle: DiceTaskHandle<'_>,
     ) {
         task_handle.computing();
+        Self::trace_task_state(&eval, "computing");
 
         event_dispatcher.started(k);
         scopeguard::defer! {
@@ -289,6 +328,7 @@ impl IncrementalEngine {
             Some(g) => g,
             None => {
                 debug!("evaluation cancelled, skipping cache updates");
+                Self::trace_task_state(&eval, "cancelled");
                 task_handle.finished(Err(DiceError::cancelled()));
                 return;
             }
@@ -321,6 +361,7 @@ impl IncrementalEngine {
         }
 
         debug!(msg = "update future completed");
+        Self::trace_task_state(&eval, "computed");
     }
 
     /// determines if the given 'Dependency' has changed between versions 'last_version' and
@@ -16,6 +16,7 @@ use more_futures::cancellation::future::TerminationObserver;
 use more_futures::spawn::spawn_cancellable;
 use more_futures::spawn::FutureAndCancellationHandle;
 use more_futures::spawner::Spawner;
+use tracing::Span;
 
 use crate::impls::task::dice::Cancellations;
 use crate::impls::task::dice::DiceTask;
@@ -30,15 +31,15 @@ mod state;
 #[cfg(test)]
 mod tests;
 
+/// Spawn a task, which is polled inside `span`.
 pub(crate) fn spawn_dice_task<S>(
     spawner: &dyn Spawner<S>,
     ctx: &S,
+    span: Span,
     f: impl for<'a> FnOnce(DiceTaskHandle<'a>) -> BoxFuture<'a, Box<dyn Any + Send>> + Send + 'static,
 ) -> DiceTask {
     let internal = DiceTaskInternal::new();
 
-    let span = debug_span!(parent: None, "spawned_dice_task",);
-
     // since the spawn is alive until cancelled via the handle, we can drop the spawn future itself
     let FutureAndCancellationHandle {
         cancellation_handle,
@@ -65,7 +65,7 @@ async fn simple_task() -> anyhow::Result<()> {
     let lock_dupe = lock.dupe();
     let locked = lock_dupe.lock().await;
 
-    let task = spawn_dice_task(&TokioSpawner, &(), |handle| {
+    let task = spawn_dice_task(&TokioSpawner, &(), debug_span!("test"), |handle| {
         async move {
             // wait for the lock too
             let _lock = lock.lock().await;
@@ -122,7 +122,7 @@ async fn simple_task() -> anyhow::Result<()> {
 
 #[tokio::test]
 async fn never_ready_results_in_terminated() -> anyhow::Result<()> {
-    let task = spawn_dice_task(&TokioSpawner, &(), |_handle| {
+    let task = spawn_dice_task(&TokioSpawner, &(), debug_span!("test"), |_handle| {
         async move {
             // never report ready
 
@@ -153,7 +153,7 @@ async fn never_ready_results_in_terminated() -> anyhow::Result<()> {
 
 #[tokio::test]
 async fn multiple_promises_all_completes() -> anyhow::Result<()> {
-    let task = spawn_dice_task(&TokioSpawner, &(), |handle| {
+    let task = spawn_dice_task(&TokioSpawner, &(), debug_span!("test"), |handle| {
         async move {
             // wait for the lock too
             handle.finished(Ok(DiceComputedValue::new(
@@ -379,7 +379,7 @@ async fn sync_complete_unfinished_spawned_task() -> anyhow::Result<()> {
 
     let g = lock.lock().await;
 
-    let task = spawn_dice_task(&TokioSpawner, &(), {
+    let task = spawn_dice_task(&TokioSpawner, &(), debug_span!("test"), {
         let lock = lock.dupe();
         |handle| {
             async move {
@@ -453,7 +453,7 @@ async fn sync_complete_unfinished_spawned_task() -> anyhow::Result<()> {
 async fn sync_complete_finished_spawned_task() -> anyhow::Result<()> {
     let sem = Arc::new(Semaphore::new(0));
 
-    let task = spawn_dice_task(&TokioSpawner, &(), {
+    let task = spawn_dice_task(&TokioSpawner, &(), debug_span!("test"), {
         let sem = sem.dupe();
         |handle| {
             async move {
@@ -529,7 +529,7 @@ async fn sync_complete_finished_spawned_task() -> anyhow::Result<()> {
 async fn dropping_all_waiters_cancels_task() {
     let barrier = Arc::new(Barrier::new(2));
 
-    let task = spawn_dice_task(&TokioSpawner, &(), {
+    let task = spawn_dice_task(&TokioSpawner, &(), debug_span!("test"), {
         let barrier = barrier.dupe();
         |handle| {
             async move {
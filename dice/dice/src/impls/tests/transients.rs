@@ -87,6 +87,85 @@ async fn invalid_results_are_not_cached() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn transient_storage_type_is_not_cached() -> anyhow::Result<()> {
+    use std::sync::atomic::AtomicUsize;
+
+    use crate::api::storage_type::StorageType;
+
+    #[derive(Clone, Dupe, Debug, Display, Derivative, Allocative)]
+    #[derivative(Hash, PartialEq, Eq)]
+    #[display(fmt = "{:?}", self)]
+    struct Clock(#[derivative(PartialEq = "ignore", Hash = "ignore")] Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl Key for Clock {
+        type Value = usize;
+
+        async fn compute(
+            &self,
+            _ctx: &DiceComputations,
+            _cancellations: &CancellationContext,
+        ) -> Self::Value {
+            self.0.fetch_add(1, Ordering::SeqCst)
+        }
+
+        fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+            x == y
+        }
+
+        fn storage_type() -> StorageType {
+            StorageType::Transient
+        }
+    }
+
+    #[derive(Clone, Dupe, Debug, Display, Derivative, Allocative)]
+    #[derivative(Hash, PartialEq, Eq)]
+    #[display(fmt = "{:?}", self)]
+    struct ReadsClock(#[derivative(PartialEq = "ignore", Hash = "ignore")] Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl Key for ReadsClock {
+        type Value = usize;
+
+        async fn compute(
+            &self,
+            ctx: &DiceComputations,
+            _cancellations: &CancellationContext,
+        ) -> Self::Value {
+            ctx.compute(&Clock(self.0.dupe())).await.unwrap() * 10
+        }
+
+        fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+            x == y
+        }
+    }
+
+    let dice = DiceModern::builder().build(DetectCycles::Enabled);
+    let clock = Arc::new(AtomicUsize::new(0));
+
+    let ctx = dice.updater().commit().await;
+    assert_eq!(0, ctx.compute(&ReadsClock(clock.dupe())).await?);
+    // Within a version, the transient value is shared.
+    assert_eq!(0, ctx.compute(&Clock(clock.dupe())).await?);
+    assert_eq!(0, ctx.compute(&ReadsClock(clock.dupe())).await?);
+    drop(ctx);
+
+    // At the next version, both the transient key and the key depending on it are recomputed,
+    // even though nothing was invalidated.
+    let ctx = dice.updater().commit().await;
+    assert_eq!(10, ctx.compute(&ReadsClock(clock.dupe())).await?);
+    assert_eq!(1, ctx.compute(&Clock(clock.dupe())).await?);
+    assert_eq!(2, clock.load(Ordering::SeqCst));
+    drop(ctx);
+
+    // Injected values must be valid, so transient keys can't be injected.
+    let mut updater = dice.updater();
+    assert!(updater.changed_to([(Clock(clock.dupe()), 5)]).is_err());
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn demo_with_transient() -> anyhow::Result<()> {
     #[derive(Clone, Dupe, Debug, Display, Derivative, Allocative)]
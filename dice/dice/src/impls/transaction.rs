@@ -76,6 +76,10 @@ impl TransactionUpdater {
         I: IntoIterator<Item = (K, K::Value)> + Send + Sync + 'static,
     {
         changed.into_iter().try_for_each(|(k, new_value)| {
+            // Injected values must be valid, so transient keys can't be injected.
+            if K::storage_type().is_transient() {
+                return Err(DiceError::invalid_change(Arc::new(k)));
+            }
             match MaybeValidDiceValue::new(
                 Arc::new(DiceKeyValue::<K>::new(new_value)),
                 DiceValidity::Valid,
@@ -171,7 +171,7 @@ where
     }
 
     fn validity(&self) -> bool {
-        K::validity(&self.value)
+        K::validity(&self.value) && !K::storage_type().is_transient()
     }
 }
 
@@ -202,7 +202,7 @@ where
     }
 
     fn validity(&self) -> bool {
-        K::validity(&self.value)
+        K::validity(&self.value) && !K::storage_type().is_transient()
     }
 }
 
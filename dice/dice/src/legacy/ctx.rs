@@ -32,6 +32,7 @@ use crate::api::error::DiceErrorImpl;
 use crate::api::error::DiceResult;
 use crate::api::key::Key;
 use crate::api::projection::ProjectionKey;
+use crate::api::task_spans::DiceTaskSpans;
 use crate::api::transaction::DiceTransaction;
 use crate::api::user_data::UserComputationData;
 use crate::api::user_data::UserCycleDetectorGuard;
@@ -69,10 +70,15 @@ pub(crate) struct ComputationData {
     /// user_data's ActivationTracker when the key evaluation finishes.
     #[allocative(skip)]
     pub(crate) evaluation_data: Mutex<Option<Box<dyn Any + Send + Sync + 'static>>>,
+    pub(crate) task_spans: DiceTaskSpans,
 }
 
 impl ComputationData {
-    pub(crate) fn new(data: UserComputationData, detect_cycles: DetectCycles) -> Self {
+    pub(crate) fn new(
+        data: UserComputationData,
+        detect_cycles: DetectCycles,
+        task_spans: DiceTaskSpans,
+    ) -> Self {
         Self {
             user_data: Arc::new(data),
             cycle_detector: match detect_cycles {
@@ -81,6 +87,7 @@ impl ComputationData {
             },
             user_cycle_detector_guard: None,
             evaluation_data: Mutex::new(None),
+            task_spans,
         }
     }
 
@@ -102,6 +109,7 @@ impl ComputationData {
                 .transpose()?,
             user_cycle_detector_guard: None,
             evaluation_data: Mutex::new(None),
+            task_spans: self.task_spans,
         })
     }
 
@@ -277,7 +285,7 @@ impl DiceComputationsImplLegacy {
         let mut changes = self.transaction_ctx.changes();
 
         changed.into_iter().try_for_each(|(k, v)| {
-            if !K::validity(&v) {
+            if !K::validity(&v) || K::storage_type().is_transient() {
                 return Err(DiceError::invalid_change(Arc::new(k)));
             }
             let dice = self.dice.dupe();
@@ -332,6 +340,7 @@ impl DiceComputationsImplLegacy {
             cycle_detector: this.extra.cycle_detector.take(),
             user_cycle_detector_guard: None,
             evaluation_data: Mutex::new(None),
+            task_spans: this.extra.task_spans,
         })
     }
 
@@ -431,6 +440,7 @@ impl DiceComputationsImplLegacy {
 #[cfg(test)]
 pub(crate) mod testing {
     use crate::api::cycles::DetectCycles;
+    use crate::api::task_spans::DiceTaskSpans;
     use crate::api::user_data::UserComputationData;
     use crate::ctx::DiceComputationsImpl;
     use crate::legacy::ctx::ComputationData;
@@ -459,7 +469,11 @@ pub(crate) mod testing {
 
     impl ComputationDataExt for ComputationData {
         fn testing_new() -> Self {
-            Self::new(UserComputationData::new(), DetectCycles::Enabled)
+            Self::new(
+                UserComputationData::new(),
+                DetectCycles::Enabled,
+                DiceTaskSpans::Disabled,
+            )
         }
     }
 }
@@ -10,6 +10,7 @@
 //! Represents the forward and backward dependencies of the computation graph
 
 use std::any::Any;
+use std::cmp;
 use std::collections::hash_map::Entry;
 use std::fmt::Debug;
 use std::fmt::Display;
@@ -34,6 +35,7 @@ use crate::legacy::incremental::transaction_ctx::TransactionCtx;
 use crate::legacy::incremental::versions::MinorVersion;
 use crate::versions::VersionNumber;
 use crate::HashMap;
+use crate::HashSet;
 
 /// The dependency information stored by the core engine
 #[async_trait]
@@ -106,39 +108,71 @@ impl Hash for dyn ComputedDependency {
     }
 }
 
+/// Maximum number of distinct sets of dependencies stored per node.
+const MAX_DEPS_VERSIONS: usize = 4;
+
 #[derive(Allocative)]
 pub(crate) struct VersionedDependencies {
     /// once the deps at a particular version is written, it is final and never modified
-    /// We only store the dependencies relevant to the most recent result
-    deps: RwLock<Option<(VersionNumber, Arc<Vec<Box<dyn Dependency>>>)>>,
+    /// We store the distinct sets of dependencies of the most recent results, newest first.
+    deps: RwLock<Vec<(VersionNumber, Arc<Vec<Box<dyn Dependency>>>)>>,
 }
 
 impl VersionedDependencies {
     pub(crate) fn new() -> Self {
         Self {
-            deps: RwLock::new(None),
+            deps: RwLock::new(Vec::new()),
         }
     }
 
+    /// The dependencies recorded at the newest version.
     pub(crate) fn deps(&self) -> Option<Arc<Vec<Box<dyn Dependency>>>> {
-        self.deps.read().as_ref().map(|d| d.1.dupe())
+        self.deps.read().first().map(|d| d.1.dupe())
+    }
+
+    /// All the stored sets of dependencies with the newest version they were recorded at,
+    /// newest first.
+    pub(crate) fn all_deps(&self) -> Vec<(VersionNumber, Arc<Vec<Box<dyn Dependency>>>)> {
+        self.deps.read().iter().map(|d| (d.0, d.1.dupe())).collect()
     }
 
     pub(crate) fn add_deps(&self, v: VersionNumber, deps: Arc<Vec<Box<dyn Dependency>>>) {
         let mut this_deps = self.deps.write();
-        if this_deps.as_ref().map_or(true, |d| v > d.0) {
-            // we only ever write the newest version of the dependencies of this node for simplicity
-            // That way, if we are ever dirtied, we just check if the latest version of the deps
-            // have changed at the dirtied version which only requires spawning one set of deps.
-            // It might cause us to falsely fail to reuse some nodes, but this is less memory
-            // and less work per node when in incremental cases.
-            *this_deps = Some((v, deps));
+        if this_deps.iter().any(|d| d.0 == v) {
+            return;
+        }
+
+        // When the deps of a node change between versions but its value doesn't (e.g. when
+        // alternating between two states of the repo), keeping only the newest deps would
+        // cause us to falsely fail to reuse the node when going back to the older state.
+        // So we keep a few distinct sets of deps, and check each of them when we are dirtied.
+        // Sets equal to the new one are dropped, so nodes whose deps don't change only ever
+        // store a single set.
+        let new_set: HashSet<&dyn Dependency> = deps.iter().map(|d| &**d).collect();
+        let existing = this_deps.iter().position(|d| {
+            d.1.len() == deps.len() && d.1.iter().all(|dep| new_set.contains(&&**dep))
+        });
+        let v = match existing {
+            Some(i) => {
+                let (existing_v, _) = this_deps.remove(i);
+                cmp::max(existing_v, v)
+            }
+            None => v,
+        };
+
+        let pos = this_deps
+            .iter()
+            .position(|d| d.0 < v)
+            .unwrap_or(this_deps.len());
+        if pos < MAX_DEPS_VERSIONS {
+            this_deps.insert(pos, (v, deps));
+            this_deps.truncate(MAX_DEPS_VERSIONS);
         }
     }
 
     pub(crate) fn debug_deps(
         &self,
-    ) -> &RwLock<Option<(VersionNumber, Arc<Vec<Box<dyn Dependency>>>)>> {
+    ) -> &RwLock<Vec<(VersionNumber, Arc<Vec<Box<dyn Dependency>>>)>> {
         &self.deps
     }
 }
@@ -46,7 +46,6 @@ use parking_lot::RwLockReadGuard;
 use parking_lot::RwLockWriteGuard;
 use sorted_vector_map::SortedVectorMap;
 
-use crate::api::storage_type::StorageType;
 use crate::impls::core::graph::history::HistoryState;
 use crate::introspection::graph::AnyKey;
 use crate::legacy::incremental::dep_trackers::BothDeps;
@@ -58,6 +57,7 @@ use crate::legacy::incremental::versions::MinorVersion;
 use crate::legacy::incremental::CellHistory;
 use crate::legacy::incremental::Dependency;
 use crate::versions::VersionNumber;
+use crate::versions::VersionRange;
 use crate::versions::VersionRanges;
 use crate::HashSet;
 
@@ -583,10 +583,31 @@ impl<K> VersionedGraphResultMismatch<K>
 where
     K: StorageProperties,
 {
-    pub(crate) fn deps_at_last_version(
+    /// The stored sets of deps of the entry, newest first, each with the versions at which the
+    /// entry is known to have been computed from exactly that set of deps.
+    ///
+    /// The newest set is valid over all the verified versions of the entry. An older set is only
+    /// known to be the one read by the computation at the version it was recorded at: at other
+    /// verified versions the computation may have read different keys (e.g. it reads `z` if `x`
+    /// is 1 and `w` otherwise), so checking it against the whole verified range would reuse the
+    /// value even though the keys which decided what to read have changed.
+    pub(crate) fn deps_at_last_versions(
         &self,
-    ) -> (&VersionRanges, Option<Arc<Vec<Box<dyn Dependency>>>>) {
-        (&self.verified_versions, self.entry.read_meta().deps.deps())
+    ) -> Vec<(VersionRanges, Arc<Vec<Box<dyn Dependency>>>)> {
+        let mut all_deps = self.entry.read_meta().deps.all_deps().into_iter();
+        let newest = match all_deps.next() {
+            Some((_, deps)) => (self.verified_versions.clone(), deps),
+            None => return Vec::new(),
+        };
+        std::iter::once(newest)
+            .chain(all_deps.map(|(v, deps)| {
+                let mut end = v;
+                end.inc();
+                let mut versions = VersionRanges::new();
+                versions.insert(VersionRange::bounded(v, end));
+                (versions, deps)
+            }))
+            .collect()
     }
 }
 
@@ -781,7 +802,7 @@ where
         key: VersionedGraphKey<K::Key>,
         entry_updater: EntryUpdater<K>,
     ) -> (GraphNode<K>, Option<GraphNode<K>>) {
-        let StorageType::LastN(num_to_keep) = self.storage_properties.storage_type();
+        let num_to_keep = self.storage_properties.storage_type().num_to_keep();
         // persistent keys, if any changes, are committed at the moment when the version
         // is increased. therefore, it must be the case that the current update for the
         // persistent key is the largest/newest version. it's also the case that they are
@@ -1419,6 +1440,7 @@ mod tests {
     use crate::legacy::incremental::dep_trackers::BothDeps;
     use crate::legacy::incremental::evaluator::testing::EvaluatorUnreachable;
     use crate::legacy::incremental::graph::dependencies::Dependency;
+    use crate::legacy::incremental::graph::dependencies::VersionedDependencies;
     use crate::legacy::incremental::graph::storage_properties::testing::StoragePropertiesLastN;
     use crate::legacy::incremental::graph::storage_properties::StorageProperties;
     use crate::legacy::incremental::graph::testing::VersionedCacheResultAssertsExt;
@@ -1931,7 +1953,7 @@ mod tests {
             .assert_verified();
         assert_eq!(
             *entry.read_meta().deps.debug_deps().read(),
-            Some((VersionNumber::new(0), deps0.dupe()))
+            vec![(VersionNumber::new(0), deps0.dupe())]
         );
 
         entry.mark_unchanged(VersionNumber::new(1), HashSet::default());
@@ -1947,7 +1969,10 @@ mod tests {
             .assert_verified();
         assert_eq!(
             *entry.read_meta().deps.debug_deps().read(),
-            Some((VersionNumber::new(1), Arc::new(Vec::new())))
+            vec![
+                (VersionNumber::new(1), Arc::new(Vec::new())),
+                (VersionNumber::new(0), deps0.dupe())
+            ]
         );
 
         let deps1 = HashSet::from_iter([
@@ -1980,7 +2005,51 @@ mod tests {
 
         assert_eq!(
             *entry.read_meta().deps.debug_deps().read(),
-            Some((VersionNumber::new(2), deps1))
+            vec![
+                (VersionNumber::new(2), deps1),
+                (VersionNumber::new(1), Arc::new(Vec::new())),
+                (VersionNumber::new(0), deps0)
+            ]
+        );
+    }
+
+    #[test]
+    fn versioned_dependencies_keeps_distinct_deps() {
+        fn deps(keys: &[usize]) -> Arc<Vec<Box<dyn Dependency>>> {
+            Arc::new(
+                keys.iter()
+                    .map(|k| DependencyExt::<EvaluatorUnreachable<_, usize>>::testing_raw(*k))
+                    .collect(),
+            )
+        }
+
+        let versioned = VersionedDependencies::new();
+        versioned.add_deps(VersionNumber::new(0), deps(&[1]));
+        versioned.add_deps(VersionNumber::new(1), deps(&[2]));
+        // same set of deps as at version 0, so only its version is updated
+        versioned.add_deps(VersionNumber::new(2), deps(&[1]));
+        assert_eq!(
+            *versioned.debug_deps().read(),
+            vec![
+                (VersionNumber::new(2), deps(&[1])),
+                (VersionNumber::new(1), deps(&[2]))
+            ]
+        );
+        assert_eq!(versioned.deps(), Some(deps(&[1])));
+
+        for v in 3..10 {
+            versioned.add_deps(VersionNumber::new(v), deps(&[v]));
+        }
+        // an old version doesn't evict newer ones
+        versioned.add_deps(VersionNumber::new(1), deps(&[100]));
+        assert_eq!(
+            versioned.all_deps(),
+            vec![
+                (VersionNumber::new(9), deps(&[9])),
+                (VersionNumber::new(8), deps(&[8])),
+                (VersionNumber::new(7), deps(&[7])),
+                (VersionNumber::new(6), deps(&[6]))
+            ]
         );
     }
 
@@ -117,7 +117,7 @@ where
             map_id: &mut M,
         ) -> Option<HashSet<KeyID>> {
             deps.debug_deps().try_read().and_then(|deps| {
-                deps.as_ref()
+                deps.first()
                     .map(|deps| deps.1.iter().map(|d| map_id(d.introspect())).collect())
             })
         }
@@ -53,6 +53,7 @@ use crate::api::events::DiceEvent;
 use crate::api::key::Key;
 use crate::api::projection::DiceProjectionComputations;
 use crate::api::projection::ProjectionKey;
+use crate::api::task_spans::DiceTaskSpans;
 use crate::api::user_data::UserComputationData;
 use crate::impls::core::graph::history::CellHistory;
 use crate::introspection::graph::EngineForIntrospection;
@@ -376,6 +377,7 @@ where
         let epoch = self.next_epoch();
 
         let user_data = extra.user_data.dupe();
+        let task_spans = extra.task_spans;
 
         struct Evaluation<K: IncrementalComputeProperties> {
             engine: Arc<IncrementalEngine<K>>,
@@ -433,11 +435,13 @@ where
             ) {
                 VersionedGraphResult::Match(entry) => {
                     debug!("found existing entry with matching version in cache. reusing result.");
+                    Self::trace_task_state(task_spans, "reused");
                     Ok(entry)
                 }
                 VersionedGraphResult::Mismatch(mismatch) => {
                     let mut extra = extra;
                     debug!("no matching entry in cache. checking for dependency changes");
+                    Self::trace_task_state(task_spans, "checking_deps");
                     extra.start_computing_key::<K>(&ev.k);
 
                     let deps_changed = {
@@ -461,12 +465,14 @@ where
                     match deps_changed {
                         DidDepsChange::Changed | DidDepsChange::NoDeps => {
                             debug!("dependencies changed. recomputing...");
+                            Self::trace_task_state(task_spans, "computing");
                             ev.engine
                                 .compute(&ev.k, eval_ctx, extra, &cancellation)
                                 .await
                         }
                         DidDepsChange::NoChange(unchanged_both_deps) => {
                             debug!("dependencies are unchanged, reusing entry");
+                            Self::trace_task_state(task_spans, "unchanged_deps");
                             extra.finished_computing_key::<K>(&ev.k, &unchanged_both_deps, true);
                             Ok(ev.engine.reuse(
                                 ev.k.clone(),
@@ -482,6 +488,7 @@ where
                     extra.start_computing_key::<K>(&ev.k);
 
                     debug!("dirtied. recomputing...");
+                    Self::trace_task_state(task_spans, "computing");
                     ev.engine
                         .compute(&ev.k, eval_ctx, extra, &cancellation)
                         .await
@@ -489,16 +496,11 @@ where
             };
 
             debug!("finished. returning result");
+            Self::trace_task_state(task_spans, "finished");
             res
         };
 
-        let span = debug_span!(
-            parent: None,
-            "spawned_dice_task",
-            key = % key,
-            version = % v,
-            epoch = % epoch,
-        );
+        let span = Self::task_span(task_spans, &key, v, epoch);
 
         // If a task is being cancelled, then we need to wait for it to finish first. This wait
         // should normally be fairly short. It goes into a non-cancellable preamble because we hold
@@ -520,6 +522,40 @@ where
         (RunningEntry { task, epoch }, handle)
     }
 
+    /// The span in which the task computing `k` is polled.
+    fn task_span(task_spans: DiceTaskSpans, k: &K::Key, v: VersionNumber, epoch: Epoch) -> Span {
+        match task_spans {
+            DiceTaskSpans::Disabled => debug_span!(
+                parent: None,
+                "spawned_dice_task",
+                key = % k,
+                version = % v,
+                epoch = % epoch,
+            ),
+            DiceTaskSpans::Enabled => info_span!(
+                parent: None,
+                "dice_task",
+                key_type = K::key_type_name(),
+                version = %v,
+            ),
+            DiceTaskSpans::Verbose => info_span!(
+                parent: None,
+                "dice_task",
+                key_type = K::key_type_name(),
+                key = %k,
+                version = %v,
+                epoch = %epoch,
+            ),
+        }
+    }
+
+    /// Record a state transition of the task computing a key, in its span.
+    fn trace_task_state(task_spans: DiceTaskSpans, state: &'static str) {
+        if task_spans == DiceTaskSpans::Verbose {
+            info!(state, "dice task state");
+        }
+    }
+
     fn spawn_task(
         future: impl Future<Output = DiceResult<GraphNode<K>>> + Send + 'static,
         preamble: impl Future<Output = ()> + Send + 'static,
@@ -936,23 +972,27 @@ impl<K: IncrementalComputeProperties> IncrementalEngine<K> {
         // So to determine if this result is reusable, we check whether any of the dependencies
         // have changed between 'last_verified_version' and the currently requested version.
 
-        match mismatch.deps_at_last_version() {
-            (versions, Some(deps)) => {
-                // TODO(bobyf) spawn everything for now, but we really should be smarter here
-                Self::compute_whether_dependencies_changed(
-                    key,
-                    transaction_ctx,
-                    extra,
-                    versions,
-                    &deps,
-                )
-                // boxed to segment this more expensive bit out of the main new_dice_task future (held
-                // by all active computations).
-                .boxed()
-                .await
+        // Check the newest deps first, falling back to older sets of deps, since the deps of
+        // this key at the current version may be the same as at some older version.
+        for (versions, deps) in mismatch.deps_at_last_versions() {
+            // TODO(bobyf) spawn everything for now, but we really should be smarter here
+            match Self::compute_whether_dependencies_changed(
+                key,
+                transaction_ctx,
+                extra,
+                &versions,
+                &deps,
+            )
+            // boxed to segment this more expensive bit out of the main new_dice_task future (held
+            // by all active computations).
+            .boxed()
+            .await
+            {
+                DidDepsChange::Changed => {}
+                unchanged => return unchanged,
             }
-            _ => DidDepsChange::Changed,
         }
+        DidDepsChange::Changed
     }
 
     #[instrument(
@@ -50,7 +50,7 @@ where
     }
 
     fn validity(&self, x: &Self::Value) -> bool {
-        K::validity(x)
+        K::validity(x) && !K::storage_type().is_transient()
     }
 
     fn key_type_name() -> &'static str {
@@ -38,6 +38,7 @@ use crate::api::data::DiceData;
 use crate::api::error::DiceResult;
 use crate::api::key::Key;
 use crate::api::projection::ProjectionKey;
+use crate::api::task_spans::DiceTaskSpans;
 use crate::api::transaction::DiceTransactionUpdater;
 use crate::api::user_data::UserComputationData;
 use crate::api::which::WhichSpawner;
@@ -74,6 +75,7 @@ pub(crate) struct DiceLegacy {
     #[allocative(skip)]
     active_versions_observer: watch::Receiver<usize>,
     which_spawner: WhichSpawner,
+    task_spans: DiceTaskSpans,
 }
 
 impl Debug for DiceLegacy {
@@ -84,15 +86,25 @@ impl Debug for DiceLegacy {
     }
 }
 
-pub(crate) struct DiceLegacyDataBuilder(DiceData);
+pub(crate) struct DiceLegacyDataBuilder {
+    data: DiceData,
+    task_spans: DiceTaskSpans,
+}
 
 impl DiceLegacyDataBuilder {
     pub(crate) fn new() -> Self {
-        Self(DiceData::new())
+        Self {
+            data: DiceData::new(),
+            task_spans: DiceTaskSpans::Disabled,
+        }
     }
 
     pub fn set<K: Send + Sync + 'static>(&mut self, val: K) {
-        self.0.set(val);
+        self.data.set(val);
+    }
+
+    pub fn task_spans(&mut self, task_spans: DiceTaskSpans) {
+        self.task_spans = task_spans;
     }
 
     pub fn build(
@@ -100,7 +112,7 @@ impl DiceLegacyDataBuilder {
         detect_cycles: DetectCycles,
         which_spawner: WhichSpawner,
     ) -> Arc<DiceLegacy> {
-        DiceLegacy::new(self.0, detect_cycles, which_spawner)
+        DiceLegacy::new_with_task_spans(self.data, detect_cycles, which_spawner, self.task_spans)
     }
 }
 
@@ -114,6 +126,15 @@ impl DiceLegacy {
         data: DiceData,
         detect_cycles: DetectCycles,
         which_spawner: WhichSpawner,
+    ) -> Arc<Self> {
+        Self::new_with_task_spans(data, detect_cycles, which_spawner, DiceTaskSpans::Disabled)
+    }
+
+    fn new_with_task_spans(
+        data: DiceData,
+        detect_cycles: DetectCycles,
+        which_spawner: WhichSpawner,
+        task_spans: DiceTaskSpans,
     ) -> Arc<Self> {
         let map = Arc::new(RwLock::new(DiceMap::new()));
         let weak_map = Arc::downgrade(&map);
@@ -139,6 +160,7 @@ impl DiceLegacy {
             })),
             detect_cycles,
             which_spawner,
+            task_spans,
             active_transaction_count: AtomicU32::new(0),
             active_versions_observer,
         })
@@ -152,7 +174,11 @@ impl DiceLegacy {
         self: &Arc<DiceLegacy>,
         extra: UserComputationData,
     ) -> DiceTransactionUpdater {
-        let ctx = self.make_ctx(ComputationData::new(extra, self.detect_cycles));
+        let ctx = self.make_ctx(ComputationData::new(
+            extra,
+            self.detect_cycles,
+            self.task_spans,
+        ));
         DiceTransactionUpdater(DiceTransactionUpdaterImpl::Legacy(ctx))
     }
 
@@ -69,7 +69,7 @@ impl<P: ProjectionKey> StorageProperties for ProjectionKeyProperties<P> {
     }
 
     fn validity(&self, x: &Self::Value) -> bool {
-        P::validity(x)
+        P::validity(x) && !P::storage_type().is_transient()
     }
 
     /// Provides a short informative name for this projection type.
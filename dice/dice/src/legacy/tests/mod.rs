@@ -237,7 +237,7 @@ fn ctx_tracks_deps_properly() -> anyhow::Result<()> {
             // TODO(bobyf) better assert the versions stored in deps
             let meta = cached.read_meta();
             let deps = meta.deps.debug_deps().read();
-            let (version, deps) = deps.as_ref().expect("No deps");
+            let (version, deps) = deps.first().expect("No deps");
 
             assert_eq!(*version, VersionNumber::new(0));
 
@@ -528,6 +528,140 @@ async fn invalid_results_are_not_cached() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn older_deps_are_not_reused_when_what_was_read_changed() -> anyhow::Result<()> {
+    /// Reads `Foo(1)` if `Foo(0)` is 1, and `Foo(2)` otherwise.
+    #[derive(Clone, Dupe, Debug, Display, Eq, Hash, PartialEq, Allocative)]
+    #[display(fmt = "{:?}", self)]
+    struct Branches;
+
+    #[async_trait]
+    impl Key for Branches {
+        type Value = i32;
+
+        async fn compute(
+            &self,
+            ctx: &DiceComputations,
+            _cancellations: &CancellationContext,
+        ) -> Self::Value {
+            let branch = if ctx.compute(&Foo(0)).await.unwrap() == 1 {
+                Foo(1)
+            } else {
+                Foo(2)
+            };
+            ctx.compute(&branch).await.unwrap()
+        }
+
+        fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+            x == y
+        }
+    }
+
+    let dice = DiceLegacy::builder().build(DetectCycles::Enabled, WhichSpawner::ExplicitCancel);
+
+    let mut updater = dice.updater();
+    updater.changed_to(vec![(Foo(0), 1), (Foo(1), 5), (Foo(2), 5)])?;
+    let ctx = updater.commit().await;
+    assert_eq!(5, ctx.compute(&Branches).await?);
+    drop(ctx);
+
+    // Reads `Foo(2)` instead, but the value doesn't change, so the node now stores both sets of
+    // deps.
+    let mut updater = dice.updater();
+    updater.changed_to(vec![(Foo(0), 2)])?;
+    let ctx = updater.commit().await;
+    assert_eq!(5, ctx.compute(&Branches).await?);
+    drop(ctx);
+
+    // The newest deps changed. The older deps `Foo(0)` and `Foo(1)` are unchanged since the
+    // previous version, but `Foo(0)` changed since the version they were recorded at.
+    let mut updater = dice.updater();
+    updater.changed_to(vec![(Foo(2), 7)])?;
+    let ctx = updater.commit().await;
+    assert_eq!(7, ctx.compute(&Branches).await?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn transient_storage_type_is_not_cached() -> anyhow::Result<()> {
+    use std::sync::atomic::AtomicUsize;
+
+    use crate::api::storage_type::StorageType;
+
+    #[derive(Clone, Dupe, Debug, Display, Derivative, Allocative)]
+    #[derivative(Hash, PartialEq, Eq)]
+    #[display(fmt = "{:?}", self)]
+    struct Clock(#[derivative(PartialEq = "ignore", Hash = "ignore")] Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl Key for Clock {
+        type Value = usize;
+
+        async fn compute(
+            &self,
+            _ctx: &DiceComputations,
+            _cancellations: &CancellationContext,
+        ) -> Self::Value {
+            self.0.fetch_add(1, Ordering::SeqCst)
+        }
+
+        fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+            x == y
+        }
+
+        fn storage_type() -> StorageType {
+            StorageType::Transient
+        }
+    }
+
+    #[derive(Clone, Dupe, Debug, Display, Derivative, Allocative)]
+    #[derivative(Hash, PartialEq, Eq)]
+    #[display(fmt = "{:?}", self)]
+    struct ReadsClock(#[derivative(PartialEq = "ignore", Hash = "ignore")] Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl Key for ReadsClock {
+        type Value = usize;
+
+        async fn compute(
+            &self,
+            ctx: &DiceComputations,
+            _cancellations: &CancellationContext,
+        ) -> Self::Value {
+            ctx.compute(&Clock(self.0.dupe())).await.unwrap() * 10
+        }
+
+        fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+            x == y
+        }
+    }
+
+    let dice = DiceLegacy::builder().build(DetectCycles::Enabled, WhichSpawner::ExplicitCancel);
+    let clock = Arc::new(AtomicUsize::new(0));
+
+    let ctx = dice.updater().commit().await;
+    assert_eq!(0, ctx.compute(&ReadsClock(clock.dupe())).await?);
+    // Within a version, the transient value is shared.
+    assert_eq!(0, ctx.compute(&Clock(clock.dupe())).await?);
+    assert_eq!(0, ctx.compute(&ReadsClock(clock.dupe())).await?);
+    drop(ctx);
+
+    // At the next version, both the transient key and the key depending on it are recomputed,
+    // even though nothing was invalidated.
+    let ctx = dice.updater().commit().await;
+    assert_eq!(10, ctx.compute(&ReadsClock(clock.dupe())).await?);
+    assert_eq!(1, ctx.compute(&Clock(clock.dupe())).await?);
+    assert_eq!(2, clock.load(Ordering::SeqCst));
+    drop(ctx);
+
+    // Injected values must be valid, so transient keys can't be injected.
+    let mut updater = dice.updater();
+    assert!(updater.changed_to([(Clock(clock.dupe()), 5)]).is_err());
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn demo_with_transient() -> anyhow::Result<()> {
     #[derive(Clone, Dupe, Debug, Display, Derivative, Allocative)]
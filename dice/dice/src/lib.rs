@@ -253,6 +253,7 @@ pub use crate::api::key::Key;
 pub use crate::api::opaque::OpaqueValue;
 pub use crate::api::projection::DiceProjectionComputations;
 pub use crate::api::projection::ProjectionKey;
+pub use crate::api::task_spans::DiceTaskSpans;
 pub use crate::api::transaction::DiceEquality;
 pub use crate::api::transaction::DiceTransaction;
 pub use crate::api::transaction::DiceTransactionUpdater;
@@ -377,6 +378,21 @@ impl DiceDataBuilderImpl {
         }
     }
 
+    pub fn compress_deps_at_least(&mut self, num_deps: usize) {
+        match self {
+            // legacy dice always stores deps uncompressed
+            DiceDataBuilderImpl::Legacy(_) => {}
+            DiceDataBuilderImpl::Modern(d) => d.compress_deps_at_least(num_deps),
+        }
+    }
+
+    pub fn task_spans(&mut self, task_spans: DiceTaskSpans) {
+        match self {
+            DiceDataBuilderImpl::Legacy(d) => d.task_spans(task_spans),
+            DiceDataBuilderImpl::Modern(d) => d.task_spans(task_spans),
+        }
+    }
+
     pub fn build(self, detect_cycles: DetectCycles, which_spawner: WhichSpawner) -> Arc<Dice> {
         Dice::new(match self {
             DiceDataBuilderImpl::Legacy(d) => {
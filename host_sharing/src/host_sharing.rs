@@ -8,6 +8,9 @@
  */
 
 use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 
 use allocative::Allocative;
 use anyhow::Context;
@@ -75,6 +78,86 @@ impl WeightPercentage {
     }
 }
 
+/// Memory that a command expects to use while it runs. This is tracked separately from the
+/// `WeightClass` so that commands which are light on CPU but heavy on memory (e.g. linkers) can't
+/// oversubscribe the host just because permits are still available.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Allocative)]
+pub struct MemoryRequirement {
+    megabytes: u64,
+}
+
+#[derive(Debug, thiserror::Error)]
+enum MemoryRequirementError {
+    #[error(
+        "Invalid memory requirement `{0}`, expected a number with an optional unit (e.g. `512M`, `8G`)"
+    )]
+    InvalidFormat(String),
+    #[error("Unknown memory unit `{unit}` in `{value}`, expected one of `K`, `M`, `G`, `T`")]
+    InvalidUnit { value: String, unit: String },
+    #[error("Memory requirement `{0}` is too large")]
+    Overflow(String),
+}
+
+impl MemoryRequirement {
+    pub fn from_megabytes(megabytes: u64) -> Self {
+        Self { megabytes }
+    }
+
+    pub fn megabytes(self) -> u64 {
+        self.megabytes
+    }
+}
+
+impl fmt::Display for MemoryRequirement {
+    fn fmt(&self, w: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(w, "{}M", self.megabytes)
+    }
+}
+
+/// Parses sizes such as `512M`, `8G` or `8GB`. Units are binary (i.e. `1G` is `1024M`) and a number
+/// without a unit is interpreted as megabytes. Sizes below a megabyte are rounded up.
+impl FromStr for MemoryRequirement {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> anyhow::Result<Self> {
+        let trimmed = value.trim();
+        let split = trimmed
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(trimmed.len());
+        let (number, unit) = trimmed.split_at(split);
+        if number.is_empty() {
+            return Err(MemoryRequirementError::InvalidFormat(value.to_owned()).into());
+        }
+        let number: u64 = number
+            .parse()
+            .map_err(|_| MemoryRequirementError::Overflow(value.to_owned()))?;
+
+        let unit = unit.trim();
+        let unit = unit.strip_suffix(|c| c == 'B' || c == 'b').unwrap_or(unit);
+        let kilobytes_per_unit: u64 = match unit {
+            "K" | "k" => 1,
+            "" | "M" | "m" => 1024,
+            "G" | "g" => 1024 * 1024,
+            "T" | "t" => 1024 * 1024 * 1024,
+            _ => {
+                return Err(MemoryRequirementError::InvalidUnit {
+                    value: value.to_owned(),
+                    unit: unit.to_owned(),
+                }
+                .into());
+            }
+        };
+
+        let kilobytes = number
+            .checked_mul(kilobytes_per_unit)
+            .ok_or_else(|| MemoryRequirementError::Overflow(value.to_owned()))?;
+
+        Ok(Self {
+            megabytes: kilobytes.div_ceil(1024),
+        })
+    }
+}
+
 /// Some commands require that we only run one instance of this binary (using an identifier)
 /// to check for other instances of the same binary.
 /// Some commands required the full host to run, others just dont care.
@@ -100,14 +183,40 @@ impl Default for HostSharingRequirements {
 /// Semaphores are held until this struct is dropped.
 pub struct HostSharingGuard {
     _run_guard: SharedSemaphoreReleaser,
+    _memory_guard: Option<SharedSemaphoreReleaser>,
     _name_guard: Option<SharedSemaphoreReleaser>,
 }
 
+/// The memory available to commands on this host, tracked in megabytes.
+struct MemoryBudget {
+    permits: SharedSemaphore,
+    megabytes: usize,
+}
+
+/// Decrements the number of waiters when dropped, so that the count stays accurate if the
+/// acquiring future gets cancelled.
+struct WaitingGuard<'a>(&'a AtomicUsize);
+
+impl<'a> WaitingGuard<'a> {
+    fn new(waiting: &'a AtomicUsize) -> Self {
+        waiting.fetch_add(1, Ordering::Relaxed);
+        Self(waiting)
+    }
+}
+
+impl Drop for WaitingGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
 /// Used to ensure that host resources are properly reserved before executing a command spec.
 pub struct HostSharingBroker {
     permits: SharedSemaphore,
     num_machine_permits: usize,
+    memory: Option<MemoryBudget>,
     named_semaphores: NamedSemaphores,
+    waiting: AtomicUsize,
 }
 
 impl HostSharingBroker {
@@ -125,42 +234,91 @@ impl HostSharingBroker {
         }
     }
 
-    pub fn new(host_sharing_strategy: HostSharingStrategy, num_machine_permits: usize) -> Self {
-        let permits = match host_sharing_strategy {
-            HostSharingStrategy::Fifo => SharedSemaphore::new(true, num_machine_permits),
-            HostSharingStrategy::SmallerTasksFirst => {
-                SharedSemaphore::new(false, num_machine_permits)
-            }
-        };
+    /// Like `requested_permits`, a command requiring more memory than the host has is capped to
+    /// the whole budget so that it can still run. Returns `None` if no memory budget is configured.
+    pub fn requested_memory(&self, memory: &MemoryRequirement) -> Option<usize> {
+        let budget = self.memory.as_ref()?;
+        let megabytes = usize::try_from(memory.megabytes()).unwrap_or(usize::MAX);
+        Some(budget.megabytes.min(megabytes))
+    }
 
+    pub fn new(host_sharing_strategy: HostSharingStrategy, num_machine_permits: usize) -> Self {
         Self {
-            permits,
+            permits: host_sharing_strategy.new_semaphore(num_machine_permits),
             num_machine_permits,
+            memory: None,
             named_semaphores: NamedSemaphores::new(),
+            waiting: AtomicUsize::new(0),
         }
     }
 
+    /// Limit the total memory that commands declaring a `MemoryRequirement` may reserve at once.
+    pub fn with_memory_budget(
+        mut self,
+        host_sharing_strategy: HostSharingStrategy,
+        budget: MemoryRequirement,
+    ) -> Self {
+        let megabytes = usize::try_from(budget.megabytes()).unwrap_or(usize::MAX);
+        self.memory = Some(MemoryBudget {
+            permits: host_sharing_strategy.new_semaphore(megabytes),
+            megabytes,
+        });
+        self
+    }
+
     pub fn num_machine_permits(&self) -> usize {
         self.num_machine_permits
     }
 
+    /// The memory budget of this host in megabytes, if one was configured.
+    pub fn memory_budget(&self) -> Option<usize> {
+        self.memory.as_ref().map(|m| m.megabytes)
+    }
+
+    /// The number of `acquire` calls currently waiting for resources.
+    pub fn num_waiting(&self) -> usize {
+        self.waiting.load(Ordering::Relaxed)
+    }
+
+    /// Reserve memory (if required and a budget is configured) before the permits. Resources are
+    /// always acquired in the same order (token, then memory, then permits), so two commands can't
+    /// each hold a resource the other is waiting for.
+    async fn acquire_memory(
+        &self,
+        memory: Option<&MemoryRequirement>,
+    ) -> Option<SharedSemaphoreReleaser> {
+        let budget = self.memory.as_ref()?;
+        let megabytes = self.requested_memory(memory?)?;
+        Some(budget.permits.acquire(megabytes).await)
+    }
+
     pub async fn acquire(
         &self,
         host_sharing_requirements: &HostSharingRequirements,
+        memory: Option<&MemoryRequirement>,
     ) -> HostSharingGuard {
+        let _waiting = WaitingGuard::new(&self.waiting);
+
         match host_sharing_requirements {
             HostSharingRequirements::Shared(weight_class) => {
+                let _memory_guard = self.acquire_memory(memory).await;
                 let permits = self.requested_permits(weight_class);
                 let _run_guard = self.permits.acquire(permits).await;
                 HostSharingGuard {
                     _run_guard,
+                    _memory_guard,
                     _name_guard: None,
                 }
             }
             HostSharingRequirements::ExclusiveAccess => {
+                let _memory_guard = match &self.memory {
+                    Some(budget) => Some(budget.permits.acquire(budget.megabytes).await),
+                    None => None,
+                };
                 let _run_guard = self.permits.acquire(self.num_machine_permits).await;
                 HostSharingGuard {
                     _run_guard,
+                    _memory_guard,
                     _name_guard: None,
                 }
             }
@@ -171,10 +329,12 @@ impl HostSharingBroker {
                 // for the previous run on this identifier to finish.
                 let run_semaphore = self.named_semaphores.get(identifier);
                 let _name_guard = Some(run_semaphore.acquire(SINGLE_RUN).await);
+                let _memory_guard = self.acquire_memory(memory).await;
                 let permits = self.requested_permits(weight_class);
                 let _run_guard = self.permits.acquire(permits).await;
                 HostSharingGuard {
                     _run_guard,
+                    _memory_guard,
                     _name_guard,
                 }
             }
@@ -183,11 +343,21 @@ impl HostSharingBroker {
 }
 
 /// Determines whether a fair or unfair semaphore is used to manage host sharing
+#[derive(Clone, Copy)]
 pub enum HostSharingStrategy {
     SmallerTasksFirst,
     Fifo,
 }
 
+impl HostSharingStrategy {
+    fn new_semaphore(self, permits: usize) -> SharedSemaphore {
+        match self {
+            HostSharingStrategy::Fifo => SharedSemaphore::new(true, permits),
+            HostSharingStrategy::SmallerTasksFirst => SharedSemaphore::new(false, permits),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -232,4 +402,77 @@ mod tests {
             10,
         );
     }
+
+    #[test]
+    fn test_parse_memory_requirement() {
+        let parse = |s: &str| s.parse::<MemoryRequirement>().unwrap().megabytes();
+
+        assert_eq!(parse("512"), 512);
+        assert_eq!(parse("512M"), 512);
+        assert_eq!(parse("8G"), 8 * 1024);
+        assert_eq!(parse("8GB"), 8 * 1024);
+        assert_eq!(parse("1t"), 1024 * 1024);
+        // This rounds up.
+        assert_eq!(parse("1K"), 1);
+
+        assert!("".parse::<MemoryRequirement>().is_err());
+        assert!("G".parse::<MemoryRequirement>().is_err());
+        assert!("8X".parse::<MemoryRequirement>().is_err());
+        assert!(
+            "99999999999999999999T"
+                .parse::<MemoryRequirement>()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_memory_capped_to_budget() {
+        let broker = HostSharingBroker::new(HostSharingStrategy::SmallerTasksFirst, 2);
+        assert_eq!(
+            broker.requested_memory(&MemoryRequirement::from_megabytes(4096)),
+            None
+        );
+
+        let broker = broker.with_memory_budget(
+            HostSharingStrategy::SmallerTasksFirst,
+            MemoryRequirement::from_megabytes(1024),
+        );
+        assert_eq!(
+            broker.requested_memory(&MemoryRequirement::from_megabytes(4096)),
+            Some(1024)
+        );
+        assert_eq!(
+            broker.requested_memory(&MemoryRequirement::from_megabytes(512)),
+            Some(512)
+        );
+    }
+
+    #[test]
+    fn test_memory_limits_concurrency() {
+        futures::executor::block_on(async {
+            let broker = HostSharingBroker::new(HostSharingStrategy::SmallerTasksFirst, 8)
+                .with_memory_budget(
+                    HostSharingStrategy::SmallerTasksFirst,
+                    MemoryRequirement::from_megabytes(1024),
+                );
+            let requirements = HostSharingRequirements::default();
+            let memory = MemoryRequirement::from_megabytes(768);
+
+            let first = broker.acquire(&requirements, Some(&memory)).await;
+            assert_eq!(broker.num_waiting(), 0);
+
+            // Plenty of permits are left, but not enough memory.
+            let mut second = Box::pin(broker.acquire(&requirements, Some(&memory)));
+            assert!(futures::poll!(&mut second).is_pending());
+            assert_eq!(broker.num_waiting(), 1);
+
+            // Commands without a memory requirement are only limited by permits.
+            let third = broker.acquire(&requirements, None).await;
+
+            drop(first);
+            let _second = second.await;
+            assert_eq!(broker.num_waiting(), 0);
+            drop(third);
+        });
+    }
 }
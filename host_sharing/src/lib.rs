@@ -16,5 +16,6 @@ pub mod host_sharing;
 pub use crate::host_sharing::HostSharingBroker;
 pub use crate::host_sharing::HostSharingRequirements;
 pub use crate::host_sharing::HostSharingStrategy;
+pub use crate::host_sharing::MemoryRequirement;
 pub use crate::host_sharing::WeightClass;
 pub use crate::host_sharing::WeightPercentage;
@@ -15,12 +15,14 @@ def http_file_shared(
         is_exploded_zip: bool.type,
         unzip_tool: [RunInfo.type, None],
         sha1: [None, str.type],
-        sha256 = [None, str.type]) -> ["provider"]:
+        sha256 = [None, str.type],
+        mirrors: [str.type] = []) -> ["provider"]:
     output = actions.declare_output(name)
     downloaded_output = actions.declare_output("exploded_zip") if is_exploded_zip else output
     actions.download_file(
         downloaded_output,
         url,
+        mirrors = mirrors,
         is_executable = is_executable,
         sha1 = sha1,
         sha256 = sha256,
@@ -46,11 +48,14 @@ def http_file_shared(
     return providers
 
 def http_file_impl(ctx: "context") -> ["provider"]:
-    expect(len(ctx.attrs.urls) == 1, "multiple `urls` not supported: {}", ctx.attrs.urls)
+    expect(len(ctx.attrs.urls) > 0, "`urls` must not be empty")
+
     return http_file_shared(
         ctx.actions,
         name = value_or(ctx.attrs.out, ctx.label.name),
         url = ctx.attrs.urls[0],
+        # Any other URLs are mirrors of the first one.
+        mirrors = ctx.attrs.urls[1:],
         sha1 = ctx.attrs.sha1,
         sha256 = ctx.attrs.sha256,
         is_executable = ctx.attrs.executable or False,
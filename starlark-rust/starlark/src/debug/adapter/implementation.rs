@@ -97,7 +97,11 @@ fn evaluate_expr<'v>(
 }
 
 impl<'a> BeforeStmtFuncDyn<'a> for DapAdapterEvalHookImpl {
-    fn call<'v>(&mut self, span_loc: FileSpanRef, eval: &mut Evaluator<'v, 'a>) {
+    fn call<'v>(
+        &mut self,
+        span_loc: FileSpanRef,
+        eval: &mut Evaluator<'v, 'a>,
+    ) -> anyhow::Result<()> {
         let stop = if self.state.disable_breakpoints.load(Ordering::SeqCst) > 0 {
             false
         } else {
@@ -145,6 +149,7 @@ impl<'a> BeforeStmtFuncDyn<'a> for DapAdapterEvalHookImpl {
                 }
             }
         }
+        Ok(())
     }
 }
 
@@ -139,7 +139,9 @@ fn step<'v, 'b, EC: EvaluationCallbacks>(
         }
     }
 
-    ec.before_instr(eval, ip, opcode);
+    if let Err(e) = ec.before_instr(eval, ip, opcode) {
+        return InstrControl::Err(e);
+    }
     opcode.dispatch(HandlerImpl { eval, frame, ip })
 }
 
@@ -40,9 +40,16 @@ pub enum BeforeStmtFunc<'a> {
 }
 
 impl<'a> BeforeStmtFunc<'a> {
-    pub(crate) fn call<'v>(&mut self, span: FileSpanRef, eval: &mut Evaluator<'v, 'a>) {
+    pub(crate) fn call<'v>(
+        &mut self,
+        span: FileSpanRef,
+        eval: &mut Evaluator<'v, 'a>,
+    ) -> anyhow::Result<()> {
         match self {
-            BeforeStmtFunc::Fn(f) => f(span, eval),
+            BeforeStmtFunc::Fn(f) => {
+                f(span, eval);
+                Ok(())
+            }
             BeforeStmtFunc::Dyn(d) => d.call(span, eval),
         }
     }
@@ -53,9 +60,10 @@ impl<'a> BeforeStmtFunc<'a> {
 #[doc(hidden)]
 pub trait BeforeStmtFuncDyn<'a> {
     /// This is used by DAP, and it is not public API.
+    /// An error aborts the evaluation, as if the statement failed.
     // TODO(cjhopman): pull DAP into the crate, and hide this function.
     #[doc(hidden)]
-    fn call<'v>(&mut self, span: FileSpanRef, eval: &mut Evaluator<'v, 'a>);
+    fn call<'v>(&mut self, span: FileSpanRef, eval: &mut Evaluator<'v, 'a>) -> anyhow::Result<()>;
 }
 
 impl<'a> BeforeStmt<'a> {
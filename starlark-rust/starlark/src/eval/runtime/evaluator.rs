@@ -798,14 +798,26 @@ impl<'v, 'a> Evaluator<'v, 'a> {
 }
 
 pub(crate) trait EvaluationCallbacks {
-    fn before_instr(&mut self, _eval: &mut Evaluator, _ip: BcPtrAddr, _opcode: BcOpcode);
+    fn before_instr(
+        &mut self,
+        _eval: &mut Evaluator,
+        _ip: BcPtrAddr,
+        _opcode: BcOpcode,
+    ) -> anyhow::Result<()>;
 }
 
 pub(crate) struct EvalCallbacksDisabled;
 
 impl EvaluationCallbacks for EvalCallbacksDisabled {
     #[inline(always)]
-    fn before_instr(&mut self, _eval: &mut Evaluator, _ip: BcPtrAddr, _opcode: BcOpcode) {}
+    fn before_instr(
+        &mut self,
+        _eval: &mut Evaluator,
+        _ip: BcPtrAddr,
+        _opcode: BcOpcode,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
 }
 
 pub(crate) struct EvalCallbacksEnabled<'a> {
@@ -816,23 +828,30 @@ pub(crate) struct EvalCallbacksEnabled<'a> {
 }
 
 impl<'a> EvalCallbacksEnabled<'a> {
-    fn before_stmt(&mut self, eval: &mut Evaluator, ip: BcPtrAddr) {
+    fn before_stmt(&mut self, eval: &mut Evaluator, ip: BcPtrAddr) -> anyhow::Result<()> {
         let offset = ip.offset_from(self.bc_start_ptr);
         if let Some(loc) = self.stmt_locs.stmt_at(offset) {
-            before_stmt(loc.span, eval);
+            before_stmt(loc.span, eval)?;
         }
+        Ok(())
     }
 }
 
 impl<'a> EvaluationCallbacks for EvalCallbacksEnabled<'a> {
     #[inline(always)]
-    fn before_instr(&mut self, eval: &mut Evaluator, ip: BcPtrAddr, opcode: BcOpcode) {
+    fn before_instr(
+        &mut self,
+        eval: &mut Evaluator,
+        ip: BcPtrAddr,
+        opcode: BcOpcode,
+    ) -> anyhow::Result<()> {
         if self.bc_profile {
             eval.eval_instrumentation.bc_profile.before_instr(opcode)
         }
         if self.before_stmt {
-            self.before_stmt(eval, ip);
+            self.before_stmt(eval, ip)?;
         }
+        Ok(())
     }
 }
 
@@ -840,18 +859,20 @@ impl<'a> EvaluationCallbacks for EvalCallbacksEnabled<'a> {
 // The purposes are GC, profiling and debugging.
 //
 // This function is called only if `before_stmt` is set before compilation start.
-pub(crate) fn before_stmt(span: FrameSpan, eval: &mut Evaluator) {
+pub(crate) fn before_stmt(span: FrameSpan, eval: &mut Evaluator) -> anyhow::Result<()> {
     assert!(
         eval.eval_instrumentation.before_stmt.enabled(),
         "this code should only be called if `before_stmt` is set"
     );
     let mut fs = mem::take(&mut eval.eval_instrumentation.before_stmt.before_stmt);
-    for f in &mut fs {
-        f.call(span.span.file_span_ref(), eval)
-    }
+    // Stop at the first error, but put the functions back first.
+    let res = fs
+        .iter_mut()
+        .try_for_each(|f| f.call(span.span.file_span_ref(), eval));
     let added = mem::replace(&mut eval.eval_instrumentation.before_stmt.before_stmt, fs);
     assert!(
         added.is_empty(),
         "`before_stmt` cannot be modified during evaluation"
     );
+    res
 }
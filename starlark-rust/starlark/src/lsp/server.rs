@@ -21,6 +21,8 @@ use std::collections::HashMap;
 use std::fmt::Debug;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::atomic::AtomicI32;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::sync::RwLock;
 
@@ -28,6 +30,7 @@ use derivative::Derivative;
 use derive_more::Display;
 use dupe::Dupe;
 use dupe::OptionDupedExt;
+use itertools::Itertools;
 use lsp_server::Connection;
 use lsp_server::Message;
 use lsp_server::Notification;
@@ -36,16 +39,25 @@ use lsp_server::RequestId;
 use lsp_server::Response;
 use lsp_server::ResponseError;
 use lsp_types::notification::DidChangeTextDocument;
+use lsp_types::notification::DidChangeWatchedFiles;
 use lsp_types::notification::DidCloseTextDocument;
 use lsp_types::notification::DidOpenTextDocument;
+use lsp_types::notification::DidSaveTextDocument;
 use lsp_types::notification::LogMessage;
+use lsp_types::notification::Progress;
 use lsp_types::notification::PublishDiagnostics;
 use lsp_types::request::GotoDefinition;
+use lsp_types::request::RegisterCapability;
+use lsp_types::request::WorkDoneProgressCreate;
 use lsp_types::DefinitionOptions;
 use lsp_types::Diagnostic;
 use lsp_types::DidChangeTextDocumentParams;
+use lsp_types::DidChangeWatchedFilesParams;
+use lsp_types::DidChangeWatchedFilesRegistrationOptions;
 use lsp_types::DidCloseTextDocumentParams;
 use lsp_types::DidOpenTextDocumentParams;
+use lsp_types::DidSaveTextDocumentParams;
+use lsp_types::FileSystemWatcher;
 use lsp_types::GotoDefinitionParams;
 use lsp_types::GotoDefinitionResponse;
 use lsp_types::InitializeParams;
@@ -53,13 +65,25 @@ use lsp_types::LocationLink;
 use lsp_types::LogMessageParams;
 use lsp_types::MessageType;
 use lsp_types::OneOf;
+use lsp_types::ProgressParams;
+use lsp_types::ProgressParamsValue;
+use lsp_types::ProgressToken;
 use lsp_types::PublishDiagnosticsParams;
 use lsp_types::Range;
+use lsp_types::Registration;
+use lsp_types::RegistrationParams;
 use lsp_types::ServerCapabilities;
 use lsp_types::TextDocumentSyncCapability;
 use lsp_types::TextDocumentSyncKind;
+use lsp_types::TextDocumentSyncOptions;
+use lsp_types::TextDocumentSyncSaveOptions;
 use lsp_types::Url;
+use lsp_types::WorkDoneProgress;
+use lsp_types::WorkDoneProgressBegin;
+use lsp_types::WorkDoneProgressCreateParams;
+use lsp_types::WorkDoneProgressEnd;
 use lsp_types::WorkDoneProgressOptions;
+use lsp_types::WorkDoneProgressReport;
 use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use serde::Deserializer;
@@ -74,6 +98,9 @@ use crate::codemap::ResolvedSpan;
 use crate::lsp::server::LoadContentsError::WrongScheme;
 use crate::syntax::AstModule;
 
+/// The files whose changes on disk the client is asked to report: those which can be loaded.
+const WATCHED_FILES_GLOB: &str = "**/*.{bzl,bxl,star,sky}";
+
 /// The request to get the file contents for a starlark: URI
 struct StarlarkFileContentsRequest {}
 
@@ -311,12 +338,32 @@ pub(crate) enum LoadContentsError {
     WrongScheme(String, LspUrl),
 }
 
+/// A file opened by the client.
+#[derive(Clone)]
+struct OpenFile {
+    version: Option<i64>,
+    text: String,
+}
+
 struct Backend<T: LspContext> {
     connection: Connection,
     context: T,
     /// The `AstModule` from the last time that a file was opened / changed and parsed successfully.
     /// Entries are evicted when the file is closed.
     last_valid_parse: RwLock<HashMap<LspUrl, Arc<LspModule>>>,
+    /// The latest contents of the files opened by the client, to diagnose them again when a file
+    /// they load is saved. Entries are evicted when the file is closed.
+    open_files: RwLock<HashMap<LspUrl, OpenFile>>,
+    /// The resolved `load()`s of the files parsed so far: the open files, and the files they load
+    /// (parsed from disk when looking for the files loading a saved file). The entries of files
+    /// which are not open are evicted when the client reports that they changed on disk.
+    load_graph: RwLock<HashMap<LspUrl, Vec<LspUrl>>>,
+    /// Whether the client accepts progress reports initiated by the server.
+    work_done_progress: bool,
+    /// Whether the client lets the server register to be notified of changes to files on disk.
+    watch_files: bool,
+    /// Used to generate the ids of the requests we send to the client, and the progress tokens.
+    next_id: AtomicI32,
 }
 
 /// The logic implementations of stuff
@@ -330,7 +377,14 @@ impl<T: LspContext> Backend<T> {
             })
         });
         ServerCapabilities {
-            text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+            text_document_sync: Some(TextDocumentSyncCapability::Options(
+                TextDocumentSyncOptions {
+                    open_close: Some(true),
+                    change: Some(TextDocumentSyncKind::FULL),
+                    save: Some(TextDocumentSyncSaveOptions::Supported(true)),
+                    ..TextDocumentSyncOptions::default()
+                },
+            )),
             definition_provider,
             ..ServerCapabilities::default()
         }
@@ -354,8 +408,20 @@ impl<T: LspContext> Backend<T> {
 
     fn validate(&self, uri: Url, version: Option<i64>, text: String) -> anyhow::Result<()> {
         let uri = uri.try_into()?;
-        let eval_result = self.context.parse_file_with_contents(&uri, text);
+        self.open_files.write().unwrap().insert(
+            uri.clone(),
+            OpenFile {
+                version,
+                text: text.clone(),
+            },
+        );
+        self.diagnose(&uri, version, text)
+    }
+
+    fn diagnose(&self, uri: &LspUrl, version: Option<i64>, text: String) -> anyhow::Result<()> {
+        let eval_result = self.context.parse_file_with_contents(uri, text);
         if let Some(ast) = eval_result.ast {
+            self.record_loads(uri, &ast);
             let module = Arc::new(LspModule::new(ast));
             let mut last_valid_parse = self.last_valid_parse.write().unwrap();
             last_valid_parse.insert(uri.clone(), module);
@@ -364,6 +430,111 @@ impl<T: LspContext> Backend<T> {
         Ok(())
     }
 
+    /// Record the files loaded by `uri` in the load graph. `load()`s which cannot be resolved
+    /// are ignored.
+    fn record_loads(&self, uri: &LspUrl, ast: &AstModule) -> Vec<LspUrl> {
+        let loads: Vec<_> = ast
+            .loads()
+            .iter()
+            .filter_map(|load| self.resolve_load_path(load.module_id, uri).ok())
+            .collect();
+        let mut load_graph = self.load_graph.write().unwrap();
+        load_graph.insert(uri.clone(), loads.clone());
+        loads
+    }
+
+    /// The files loaded by `uri`, which is parsed from disk if it was not parsed yet.
+    fn loads_of(&self, uri: &LspUrl) -> Vec<LspUrl> {
+        if let Some(loads) = self.load_graph.read().unwrap().get(uri) {
+            return loads.clone();
+        }
+        match self.context.parse_file(uri) {
+            Ok(Some(LspEvalResult { ast: Some(ast), .. })) => self.record_loads(uri, &ast),
+            // Files which cannot be read or parsed do not load anything.
+            _ => {
+                let mut load_graph = self.load_graph.write().unwrap();
+                load_graph.insert(uri.clone(), Vec::new());
+                Vec::new()
+            }
+        }
+    }
+
+    /// Whether `uri` loads one of `targets`, directly or not. `visited` memoizes the answer for
+    /// the files visited so far.
+    fn transitively_loads(
+        &self,
+        uri: &LspUrl,
+        targets: &[LspUrl],
+        visited: &mut HashMap<LspUrl, bool>,
+    ) -> bool {
+        if let Some(loads) = visited.get(uri) {
+            return *loads;
+        }
+        // Load cycles are errors, but we must not loop on them.
+        visited.insert(uri.clone(), false);
+        let loads = self
+            .loads_of(uri)
+            .iter()
+            .any(|load| targets.contains(load) || self.transitively_loads(load, targets, visited));
+        visited.insert(uri.clone(), loads);
+        loads
+    }
+
+    /// The open files whose load graph includes one of `uris`, sorted.
+    fn open_dependents(&self, uris: &[LspUrl]) -> Vec<LspUrl> {
+        let open_files: Vec<_> = self
+            .open_files
+            .read()
+            .unwrap()
+            .keys()
+            .filter(|open| !uris.contains(open))
+            .cloned()
+            .collect();
+        let mut visited = HashMap::new();
+        let mut dependents: Vec<_> = open_files
+            .into_iter()
+            .filter(|open| self.transitively_loads(open, uris, &mut visited))
+            .collect();
+        dependents.sort_by(|a, b| a.path().cmp(b.path()));
+        dependents
+    }
+
+    /// Diagnose again, once, the open files which load one of `uris`, directly or not, since
+    /// what they load changed. Other files are not diagnosed again.
+    fn refresh_dependents(&self, uris: &[LspUrl]) -> anyhow::Result<()> {
+        let dependents = self.open_dependents(uris);
+        if dependents.is_empty() {
+            return Ok(());
+        }
+        let progress = self.begin_progress(
+            "Refreshing diagnostics",
+            format!("Files loading {}", uris.iter().join(", ")),
+        );
+        for (i, dependent) in dependents.iter().enumerate() {
+            if let Some(progress) = &progress {
+                self.report_progress(
+                    progress,
+                    WorkDoneProgress::Report(WorkDoneProgressReport {
+                        cancellable: Some(false),
+                        message: Some(dependent.to_string()),
+                        percentage: Some((i * 100 / dependents.len()) as u32),
+                    }),
+                );
+            }
+            let open_file = self.open_files.read().unwrap().get(dependent).cloned();
+            if let Some(OpenFile { version, text }) = open_file {
+                self.diagnose(dependent, version, text)?;
+            }
+        }
+        if let Some(progress) = &progress {
+            self.report_progress(
+                progress,
+                WorkDoneProgress::End(WorkDoneProgressEnd { message: None }),
+            );
+        }
+        Ok(())
+    }
+
     fn did_open(&self, params: DidOpenTextDocumentParams) -> anyhow::Result<()> {
         self.validate(
             params.text_document.uri,
@@ -382,15 +553,50 @@ impl<T: LspContext> Backend<T> {
         )
     }
 
+    fn did_save(&self, params: DidSaveTextDocumentParams) -> anyhow::Result<()> {
+        // The files loading this one see the saved contents from now on.
+        self.refresh_dependents(&[params.text_document.uri.try_into()?])
+    }
+
     fn did_close(&self, params: DidCloseTextDocumentParams) -> anyhow::Result<()> {
         {
+            let uri = params.text_document.uri.clone().try_into()?;
             let mut last_valid_parse = self.last_valid_parse.write().unwrap();
-            last_valid_parse.remove(&params.text_document.uri.clone().try_into()?);
+            last_valid_parse.remove(&uri);
+            self.open_files.write().unwrap().remove(&uri);
+            // Unsaved changes are discarded, so the loads are read from disk again if needed.
+            self.load_graph.write().unwrap().remove(&uri);
         }
         self.publish_diagnostics(params.text_document.uri, Vec::new(), None);
         Ok(())
     }
 
+    fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) -> anyhow::Result<()> {
+        let mut changed = Vec::new();
+        for change in params.changes {
+            let uri = match LspUrl::try_from(change.uri) {
+                Ok(uri) => uri,
+                // Only files on disk can be loaded.
+                Err(_) => continue,
+            };
+            // The contents of open files come from the editor, which did not change: their
+            // dependents are refreshed when they are saved.
+            if self.open_files.read().unwrap().contains_key(&uri) || changed.contains(&uri) {
+                continue;
+            }
+            changed.push(uri);
+        }
+        // Evict every changed file before walking the load graph, so that the dependents of
+        // several changed files are diagnosed once, against all the changes.
+        {
+            let mut load_graph = self.load_graph.write().unwrap();
+            for uri in &changed {
+                load_graph.remove(uri);
+            }
+        }
+        self.refresh_dependents(&changed)
+    }
+
     /// Go to the definition of the symbol at the current cursor if that definition is in
     /// the same file.
     ///
@@ -602,6 +808,71 @@ impl<T: LspContext> Backend<T> {
         self.connection.sender.send(Message::Response(x)).unwrap()
     }
 
+    fn send_request(&self, x: Request) {
+        self.connection.sender.send(Message::Request(x)).unwrap()
+    }
+
+    /// Start reporting progress to the client, if it supports it. Returns the token to report
+    /// progress with.
+    fn begin_progress(&self, title: &str, message: String) -> Option<ProgressToken> {
+        if !self.work_done_progress {
+            return None;
+        }
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let token = ProgressToken::String(format!("starlark-{}", id));
+        // We do not need to wait for the response: clients accept progress reports right away.
+        self.send_request(new_request::<WorkDoneProgressCreate>(
+            RequestId::from(id),
+            WorkDoneProgressCreateParams {
+                token: token.clone(),
+            },
+        ));
+        self.report_progress(
+            &token,
+            WorkDoneProgress::Begin(WorkDoneProgressBegin {
+                title: title.to_owned(),
+                cancellable: Some(false),
+                message: Some(message),
+                percentage: Some(0),
+            }),
+        );
+        Some(token)
+    }
+
+    /// Ask the client to notify us when files change on disk, if it supports it, so that the
+    /// loads of closed files are read again.
+    fn register_file_watcher(&self) {
+        if !self.watch_files {
+            return;
+        }
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let options = DidChangeWatchedFilesRegistrationOptions {
+            watchers: vec![FileSystemWatcher {
+                glob_pattern: WATCHED_FILES_GLOB.to_owned(),
+                kind: None,
+            }],
+        };
+        self.send_request(new_request::<RegisterCapability>(
+            RequestId::from(id),
+            RegistrationParams {
+                registrations: vec![Registration {
+                    id: format!("starlark-{}", id),
+                    method:
+                        <DidChangeWatchedFiles as lsp_types::notification::Notification>::METHOD
+                            .to_owned(),
+                    register_options: Some(serde_json::to_value(options).unwrap()),
+                }],
+            },
+        ));
+    }
+
+    fn report_progress(&self, token: &ProgressToken, progress: WorkDoneProgress) {
+        self.send_notification(new_notification::<Progress>(ProgressParams {
+            token: token.clone(),
+            value: ProgressParamsValue::WorkDone(progress),
+        }))
+    }
+
     fn log_message(&self, typ: MessageType, message: &str) {
         self.send_notification(new_notification::<LogMessage>(LogMessageParams {
             typ,
@@ -617,6 +888,7 @@ impl<T: LspContext> Backend<T> {
 
     fn main_loop(&self, _params: InitializeParams) -> anyhow::Result<()> {
         self.log_message(MessageType::INFO, "Starlark server initialised");
+        self.register_file_watcher();
         for msg in &self.connection.receiver {
             match msg {
                 Message::Request(req) => {
@@ -636,8 +908,12 @@ impl<T: LspContext> Backend<T> {
                         self.did_open(params)?;
                     } else if let Some(params) = as_notification::<DidChangeTextDocument>(&x) {
                         self.did_change(params)?;
+                    } else if let Some(params) = as_notification::<DidSaveTextDocument>(&x) {
+                        self.did_save(params)?;
                     } else if let Some(params) = as_notification::<DidCloseTextDocument>(&x) {
                         self.did_close(params)?;
+                    } else if let Some(params) = as_notification::<DidChangeWatchedFiles>(&x) {
+                        self.did_change_watched_files(params)?;
                     }
                 }
                 Message::Response(_) => {
@@ -677,6 +953,19 @@ pub fn server_with_connection<T: LspContext>(
         .as_ref()
         .and_then(|opts| serde_json::from_value(opts.clone()).ok())
         .unwrap_or_default();
+    let work_done_progress = initialization_params
+        .capabilities
+        .window
+        .as_ref()
+        .and_then(|window| window.work_done_progress)
+        .unwrap_or(false);
+    let watch_files = initialization_params
+        .capabilities
+        .workspace
+        .as_ref()
+        .and_then(|workspace| workspace.did_change_watched_files.as_ref())
+        .and_then(|watched_files| watched_files.dynamic_registration)
+        .unwrap_or(false);
     let capabilities_payload = Backend::<T>::server_capabilities(server_settings);
     let server_capabilities = serde_json::to_value(capabilities_payload).unwrap();
 
@@ -689,6 +978,11 @@ pub fn server_with_connection<T: LspContext>(
         connection,
         context,
         last_valid_parse: RwLock::default(),
+        open_files: RwLock::default(),
+        load_graph: RwLock::default(),
+        work_done_progress,
+        watch_files,
+        next_id: AtomicI32::new(0),
     }
     .main_loop(initialization_params)?;
 
@@ -742,6 +1036,18 @@ where
     }
 }
 
+/// Create a new `Request` object with the correct name from the given params.
+fn new_request<T>(id: RequestId, params: T::Params) -> Request
+where
+    T: lsp_types::request::Request,
+{
+    Request {
+        id,
+        method: T::METHOD.to_owned(),
+        params: serde_json::to_value(&params).unwrap(),
+    }
+}
+
 fn new_response<T>(id: RequestId, params: anyhow::Result<T>) -> Response
 where
     T: serde::Serialize,
@@ -774,6 +1080,7 @@ mod test {
     use anyhow::Context;
     use lsp_server::Request;
     use lsp_server::RequestId;
+    use lsp_types::notification::PublishDiagnostics;
     use lsp_types::request::GotoDefinition;
     use lsp_types::GotoDefinitionParams;
     use lsp_types::GotoDefinitionResponse;
@@ -990,6 +1297,132 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn saving_a_file_refreshes_the_open_files_loading_it() -> anyhow::Result<()> {
+        let bar_uri = temp_file_uri("bar.star");
+        let foo_uri = temp_file_uri("foo.star");
+        let mid_uri = temp_file_uri("mid.star");
+        let qux_uri = temp_file_uri("qux.star");
+        let baz_uri = temp_file_uri("baz.star");
+
+        let loading = |load: &Url, symbol: &str| {
+            format!("load(\"{}\", \"{}\")\n{}()\n", load.path(), symbol, symbol)
+        };
+        let bar_contents = "def bar():\n    pass\n";
+
+        let mut server = TestServer::new()?;
+        server.set_file_contents(PathBuf::from(bar_uri.path()), bar_contents.to_owned())?;
+        // `qux.star` only loads `bar.star` through `mid.star`, which is not open.
+        server.set_file_contents(PathBuf::from(mid_uri.path()), loading(&bar_uri, "bar"))?;
+        server.open_file(bar_uri.clone(), bar_contents.to_owned())?;
+        server.open_file(qux_uri.clone(), loading(&mid_uri, "bar"))?;
+        server.open_file(baz_uri.clone(), "baz = 1\n".to_owned())?;
+        server.open_file(foo_uri.clone(), loading(&bar_uri, "bar"))?;
+
+        server.save_file(bar_uri)?;
+        let refreshed = [
+            server.get_notification::<PublishDiagnostics>()?.uri,
+            server.get_notification::<PublishDiagnostics>()?.uri,
+        ];
+        assert_eq!([foo_uri, qux_uri], refreshed);
+
+        // `baz.star` was not diagnosed again: the next diagnostics are the ones for this change.
+        server.change_file(baz_uri.clone(), "baz = 2\n".to_owned())?;
+        assert_eq!(
+            baz_uri,
+            server.get_notification::<PublishDiagnostics>()?.uri
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn changes_on_disk_invalidate_the_loads_of_closed_files() -> anyhow::Result<()> {
+        let bar_uri = temp_file_uri("bar.star");
+        let baz_uri = temp_file_uri("baz.star");
+        let mid_uri = temp_file_uri("mid.star");
+        let qux_uri = temp_file_uri("qux.star");
+
+        let mut server = TestServer::new()?;
+        server.set_file_contents(PathBuf::from(bar_uri.path()), "bar = 1\n".to_owned())?;
+        server.set_file_contents(
+            PathBuf::from(mid_uri.path()),
+            format!("load(\"{}\", \"bar\")\n", bar_uri.path()),
+        )?;
+        server.open_file(bar_uri.clone(), "bar = 1\n".to_owned())?;
+        server.open_file(
+            qux_uri.clone(),
+            format!("load(\"{}\", \"bar\")\n", mid_uri.path()),
+        )?;
+        server.open_file(baz_uri.clone(), "baz = 1\n".to_owned())?;
+
+        // Saving `bar.star` parses the closed `mid.star` from disk.
+        server.save_file(bar_uri.clone())?;
+        assert_eq!(
+            qux_uri,
+            server.get_notification::<PublishDiagnostics>()?.uri
+        );
+
+        // `mid.star` stops loading `bar.star`: `qux.star` is diagnosed again since what it loads
+        // changed, and is no longer a dependent of `bar.star`.
+        server.set_file_contents(PathBuf::from(mid_uri.path()), "bar = 2\n".to_owned())?;
+        server.change_watched_file(mid_uri)?;
+        assert_eq!(
+            qux_uri,
+            server.get_notification::<PublishDiagnostics>()?.uri
+        );
+        server.save_file(bar_uri)?;
+
+        // The next diagnostics are the ones for this change, not for `qux.star`.
+        server.change_file(baz_uri.clone(), "baz = 2\n".to_owned())?;
+        assert_eq!(
+            baz_uri,
+            server.get_notification::<PublishDiagnostics>()?.uri
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn changes_on_disk_refresh_each_dependent_once() -> anyhow::Result<()> {
+        let bar_uri = temp_file_uri("bar.star");
+        let baz_uri = temp_file_uri("baz.star");
+        let foo_uri = temp_file_uri("foo.star");
+        let one_uri = temp_file_uri("one.star");
+        let two_uri = temp_file_uri("two.star");
+        let qux_uri = temp_file_uri("qux.star");
+
+        let mut server = TestServer::new()?;
+        server.set_file_contents(PathBuf::from(one_uri.path()), "one = 1\n".to_owned())?;
+        server.set_file_contents(PathBuf::from(two_uri.path()), "two = 2\n".to_owned())?;
+        server.open_file(bar_uri.clone(), "bar = 1\n".to_owned())?;
+        server.open_file(
+            foo_uri.clone(),
+            format!("load(\"{}\", \"bar\")\n", bar_uri.path()),
+        )?;
+        server.open_file(
+            qux_uri.clone(),
+            format!(
+                "load(\"{}\", \"one\")\nload(\"{}\", \"two\")\n",
+                one_uri.path(),
+                two_uri.path()
+            ),
+        )?;
+        server.open_file(baz_uri.clone(), "baz = 1\n".to_owned())?;
+
+        // `qux.star` loads both changed files but is diagnosed once. `bar.star` is open, so its
+        // contents come from the editor and `foo.star` is not diagnosed again.
+        server.change_watched_files(vec![one_uri, bar_uri, two_uri])?;
+        assert_eq!(
+            qux_uri,
+            server.get_notification::<PublishDiagnostics>()?.uri
+        );
+        server.change_file(baz_uri.clone(), "baz = 2\n".to_owned())?;
+        assert_eq!(
+            baz_uri,
+            server.get_notification::<PublishDiagnostics>()?.uri
+        );
+        Ok(())
+    }
+
     #[test]
     fn jumps_to_definition_from_closed_loaded_file() -> anyhow::Result<()> {
         let foo_uri = temp_file_uri("foo.star");
@@ -32,7 +32,9 @@ use lsp_server::RequestId;
 use lsp_server::Response;
 use lsp_server::ResponseError;
 use lsp_types::notification::DidChangeTextDocument;
+use lsp_types::notification::DidChangeWatchedFiles;
 use lsp_types::notification::DidOpenTextDocument;
+use lsp_types::notification::DidSaveTextDocument;
 use lsp_types::notification::Exit;
 use lsp_types::notification::Initialized;
 use lsp_types::notification::Notification;
@@ -42,7 +44,11 @@ use lsp_types::request::Request;
 use lsp_types::request::Shutdown;
 use lsp_types::ClientCapabilities;
 use lsp_types::DidChangeTextDocumentParams;
+use lsp_types::DidChangeWatchedFilesParams;
 use lsp_types::DidOpenTextDocumentParams;
+use lsp_types::DidSaveTextDocumentParams;
+use lsp_types::FileChangeType;
+use lsp_types::FileEvent;
 use lsp_types::GotoCapability;
 use lsp_types::InitializeParams;
 use lsp_types::InitializeResult;
@@ -51,6 +57,7 @@ use lsp_types::Position;
 use lsp_types::Range;
 use lsp_types::TextDocumentClientCapabilities;
 use lsp_types::TextDocumentContentChangeEvent;
+use lsp_types::TextDocumentIdentifier;
 use lsp_types::TextDocumentItem;
 use lsp_types::Url;
 use lsp_types::VersionedTextDocumentIdentifier;
@@ -602,6 +609,39 @@ impl TestServer {
         Ok(())
     }
 
+    /// Send a notification saying that a file was saved. The contents on disk should be set with
+    /// `set_file_contents()` first.
+    pub fn save_file(&mut self, uri: Url) -> anyhow::Result<()> {
+        let save_params = DidSaveTextDocumentParams {
+            text_document: TextDocumentIdentifier { uri },
+            text: None,
+        };
+        let save_notification = new_notification::<DidSaveTextDocument>(save_params);
+        self.send_notification(save_notification)?;
+        Ok(())
+    }
+
+    /// Send a notification saying that a file changed on disk. The contents on disk should be set
+    /// with `set_file_contents()` first.
+    pub fn change_watched_file(&mut self, uri: Url) -> anyhow::Result<()> {
+        self.change_watched_files(vec![uri])
+    }
+
+    /// Send a single notification saying that several files changed on disk.
+    pub fn change_watched_files(&mut self, uris: Vec<Url>) -> anyhow::Result<()> {
+        let params = DidChangeWatchedFilesParams {
+            changes: uris
+                .into_iter()
+                .map(|uri| FileEvent {
+                    uri,
+                    typ: FileChangeType::CHANGED,
+                })
+                .collect(),
+        };
+        self.send_notification(new_notification::<DidChangeWatchedFiles>(params))?;
+        Ok(())
+    }
+
     /// Set the file contents that `get_load_contents()` will return. The path must be absolute.
     pub fn set_file_contents(&self, path: PathBuf, contents: String) -> anyhow::Result<()> {
         let path = get_path_from_uri(&format!("{}", path.display()));
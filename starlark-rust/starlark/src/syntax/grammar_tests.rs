@@ -316,3 +316,14 @@ fn test_bad_assignment() {
     assert::parse_fail("[!x or y!] = 1");
     assert::parse_fail("![x]! += 1");
 }
+
+#[test]
+fn test_same_syntax() {
+    let a = assert::parse_ast("load('a.bzl', 'x', y = 'z')\nf(x, y = [1, 2])\n");
+    let b = assert::parse_ast(
+        "load(\"a.bzl\", y = \"z\", x = \"x\")\n\n# c\nf(\n  x,\n  y=[1,2],\n)\n",
+    );
+    assert!(a.same_syntax(&b));
+    let c = assert::parse_ast("load('a.bzl', 'x', y = 'z')\nf(y, y = [1, 2])\n");
+    assert!(!a.same_syntax(&c));
+}
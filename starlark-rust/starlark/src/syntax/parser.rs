@@ -185,6 +185,32 @@ impl AstModule {
         loads
     }
 
+    /// Whether the two modules have the same syntax tree, i.e. only differ in formatting:
+    /// whitespace, comments, how literals are written and the order of the symbols of `load`
+    /// statements.
+    pub fn same_syntax(&self, other: &AstModule) -> bool {
+        fn render(module: &AstModule) -> Vec<String> {
+            module
+                .top_level_statements()
+                .into_iter()
+                .map(|stmt| match &stmt.node {
+                    Stmt::Load(load) => {
+                        let mut symbols: Vec<_> = load
+                            .args
+                            .iter()
+                            .map(|(name, sym)| (name.node.0.as_str(), sym.node.as_str()))
+                            .collect();
+                        symbols.sort_unstable();
+                        format!("load({:?}, {:?})", load.module.node, symbols)
+                    }
+                    stmt => stmt.to_string(),
+                })
+                .collect()
+        }
+
+        render(self) == render(other)
+    }
+
     /// Look up a [`Span`] contained in this module to a [`FileSpan`].
     pub(crate) fn file_span(&self, x: Span) -> FileSpan {
         self.codemap.file_span(x)
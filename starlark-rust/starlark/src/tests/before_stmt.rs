@@ -20,6 +20,7 @@ use std::cell::Cell;
 use crate::codemap::FileSpanRef;
 use crate::environment::Globals;
 use crate::environment::Module;
+use crate::eval::BeforeStmtFuncDyn;
 use crate::eval::Evaluator;
 use crate::syntax::AstModule;
 use crate::syntax::Dialect;
@@ -47,3 +48,40 @@ f()            # 2 + 1
     evaluator.eval_module(ast, &globals).unwrap();
     assert_eq!(7, counter.get());
 }
+
+struct FailAfter(u32);
+
+impl<'a> BeforeStmtFuncDyn<'a> for FailAfter {
+    fn call<'v>(
+        &mut self,
+        _span: FileSpanRef,
+        _eval: &mut Evaluator<'v, 'a>,
+    ) -> anyhow::Result<()> {
+        match self.0.checked_sub(1) {
+            Some(n) => {
+                self.0 = n;
+                Ok(())
+            }
+            None => Err(anyhow::anyhow!("too many statements")),
+        }
+    }
+}
+
+#[test]
+fn before_stmt_error_aborts_evaluation() {
+    let module = Module::new();
+    let globals = Globals::new();
+
+    let mut evaluator = Evaluator::new(&module);
+    evaluator.before_stmt_for_dap((Box::new(FailAfter(10)) as Box<dyn BeforeStmtFuncDyn>).into());
+
+    let program = "\
+def f():
+  for i in range(1000000):
+    pass
+f()
+";
+    let ast = AstModule::parse("a.star", program.to_owned(), &Dialect::Extended).unwrap();
+    let err = evaluator.eval_module(ast, &globals).unwrap_err();
+    assert!(err.to_string().contains("too many statements"), "{:?}", err);
+}
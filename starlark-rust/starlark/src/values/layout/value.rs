@@ -480,6 +480,12 @@ impl<'v> Value<'v> {
         self.get_ref().get_type()
     }
 
+    /// Memory used by this value on the heap, including the data it owns, as measured by
+    /// [`allocative`]. Other values it refers to are not included.
+    pub fn total_memory(self) -> usize {
+        self.get_ref().total_memory()
+    }
+
     /// `bool(x)`.
     pub fn to_bool(self) -> bool {
         // Fast path for the common case